@@ -49,6 +49,9 @@ pub struct Agent {
     bg_wire_tx: Arc<std::sync::Mutex<mpsc::UnboundedSender<(u64, Vec<u8>)>>>,
     /// Receiver end, moved into each run() call's sender task.
     wire_rx: Option<mpsc::UnboundedReceiver<(u64, Vec<u8>)>>,
+    /// What the connected client can decode, negotiated at Hello. The
+    /// collector downgrades events to it before encoding.
+    api_caps: Arc<std::sync::Mutex<nexus_api::ApiCaps>>,
     /// Handle to the persistent background collector task.
     _bg_collector: tokio::task::JoinHandle<()>,
     /// Terminal viewport dimensions (for native commands like `ls`).
@@ -94,10 +97,16 @@ impl Agent {
         let (wire_tx, wire_rx) = mpsc::unbounded_channel::<(u64, Vec<u8>)>();
         let bg_wire_tx = Arc::new(std::sync::Mutex::new(wire_tx));
         let bg_wire_tx_clone = bg_wire_tx.clone();
+        let api_caps = Arc::new(std::sync::Mutex::new(nexus_api::ApiCaps::current()));
+        let bg_api_caps = api_caps.clone();
         let bg_collector = tokio::spawn(async move {
             loop {
                 match bg_event_rx.recv().await {
                     Ok(event) => {
+                        let caps = *bg_api_caps.lock().unwrap();
+                        let Some(event) = nexus_api::downgrade_event(event, &caps) else {
+                            continue;
+                        };
                         let seq = bg_next_seq.fetch_add(1, Ordering::Relaxed);
                         let resp = Response::Event { seq, event };
                         match encode_payload(&resp) {
@@ -129,6 +138,7 @@ impl Agent {
             ring_buffer,
             bg_wire_tx,
             wire_rx: Some(wire_rx),
+            api_caps,
             _bg_collector: bg_collector,
             viewport_cols: 80,
             viewport_rows: 24,
@@ -329,9 +339,10 @@ impl Agent {
             match request {
                 Request::Hello {
                     protocol_version: _,
-                    capabilities: _,
+                    capabilities,
                     forwarded_env,
                 } => {
+                    *self.api_caps.lock().unwrap() = capabilities.api.intersect(nexus_api::ApiCaps::current());
                     if self.handle_hello(forwarded_env, &writer).await.is_err() {
                         break;
                     }
@@ -510,7 +521,8 @@ impl Agent {
                     transport,
                     force_redeploy: _,
                 } => {
-                    match relay::spawn_and_handshake(&transport, self.forwarded_env.clone()).await {
+                    let api = *self.api_caps.lock().unwrap();
+                    match relay::spawn_and_handshake(&transport, self.forwarded_env.clone(), api).await {
                         Ok((child_reader, child_writer, child, env)) => {
                             // Register relay child PID as Tokio-managed
                             if let Some(pid) = child.id() {
@@ -841,6 +853,7 @@ impl Agent {
                 nesting: true,
                 file_transfer: true,
                 pty: true,
+                api: nexus_api::ApiCaps::current(),
             },
             session_token,
         };
@@ -912,7 +925,7 @@ impl Agent {
         // as the definitive screen state (correcting any gaps from evicted
         // ring buffer frames).
         let active_blocks = self.pty_manager.active_block_ids();
-        {
+        if self.api_caps.lock().unwrap().terminal_state {
             let mut w = writer.lock().await;
             for &block_id in &active_blocks {
                 if let Some(snap) = self.pty_manager.snapshot(block_id).await {
@@ -971,36 +984,11 @@ impl Agent {
     ) {
         let kernel = self.kernel.clone();
 
+        // Panics are contained by the kernel, which finishes the block and
+        // emits a KernelPanic report, so the client never hangs.
         tokio::task::spawn_blocking(move || {
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                let handle = tokio::runtime::Handle::current();
-                handle.block_on(async {
-                    let mut kernel = kernel.lock().await;
-                    let _ = kernel.execute_with_block_id(&command, Some(block_id));
-                });
-            }));
-
-            if let Err(panic_info) = result {
-                let error_msg = if let Some(s) = panic_info.downcast_ref::<&str>() {
-                    format!("Command panicked: {}", s)
-                } else if let Some(s) = panic_info.downcast_ref::<String>() {
-                    format!("Command panicked: {}", s)
-                } else {
-                    "Command panicked (unknown error)".to_string()
-                };
-                tracing::error!("{error_msg}");
-
-                // Emit CommandFinished so the client doesn't hang forever
-                let handle = tokio::runtime::Handle::current();
-                handle.block_on(async {
-                    let k = kernel.lock().await;
-                    let _ = k.event_sender().send(ShellEvent::CommandFinished {
-                        block_id,
-                        exit_code: 1,
-                        duration_ms: 0,
-                    });
-                });
-            }
+            let mut kernel = kernel.blocking_lock();
+            let _ = kernel.execute_supervised(&command, block_id);
         });
     }
 
@@ -1055,6 +1043,7 @@ impl Agent {
                     nexus_kernel::CompletionKind::Variable => CompletionKind::Variable,
                     nexus_kernel::CompletionKind::GitBranch => CompletionKind::GitBranch,
                     nexus_kernel::CompletionKind::Flag => CompletionKind::Flag,
+                    nexus_kernel::CompletionKind::Task => CompletionKind::Task,
                 },
                 score: c.score,
             })
@@ -1228,6 +1217,7 @@ impl Agent {
 
         let os = std::env::consts::OS.to_string();
         let arch = std::env::consts::ARCH.to_string();
+        let home = std::env::var_os("HOME").map(PathBuf::from);
 
         EnvInfo {
             instance_id: instance_id.to_string(),
@@ -1236,6 +1226,7 @@ impl Agent {
             cwd,
             os,
             arch,
+            home,
         }
     }
 }
@@ -111,6 +111,7 @@ impl ActiveRelay {
 pub(crate) async fn spawn_and_handshake(
     transport: &Transport,
     forwarded_env: HashMap<String, String>,
+    api: nexus_api::ApiCaps,
 ) -> Result<(FrameReader<ChildStdout>, FrameWriter<ChildStdin>, Child, EnvInfo)> {
     let mut child = spawn_child(transport).await?;
 
@@ -134,6 +135,9 @@ pub(crate) async fn spawn_and_handshake(
             resume: false,
             nesting: true,
             file_transfer: true,
+            // Ask the child for what our own client understands; events
+            // are relayed without being re-downgraded.
+            api,
         },
         forwarded_env,
     };
@@ -29,6 +29,9 @@ pub enum BlockState {
     Success,
     /// Command exited with non-zero code (includes signal kills: 128+signal).
     Failed(i32),
+    /// Nexus crashed while the command was running; output was recovered
+    /// from the crash journal and may be partial.
+    Interrupted,
 }
 
 /// Metadata for a command block.
@@ -0,0 +1,102 @@
+//! Block id allocation.
+//!
+//! Block ids must be unique across everything that creates blocks in a
+//! process: the kernel (commands run without an id from the UI), every
+//! window's shell and agent blocks, and blocks restored from the crash
+//! journal. [`BlockIdAllocator::global`] is the one source of fresh ids;
+//! nothing else should mint a `BlockId` from a counter of its own.
+//!
+//! Ids are also stored in the history database. The store advances the
+//! allocator past the largest id it holds when it opens, so ids stay unique
+//! across restarts too.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::BlockId;
+
+static GLOBAL: BlockIdAllocator = BlockIdAllocator::starting_at(1);
+
+/// Hands out block ids that are never reused.
+#[derive(Debug)]
+pub struct BlockIdAllocator {
+    next: AtomicU64,
+}
+
+impl BlockIdAllocator {
+    /// An allocator whose first id is `first`. Only for tests; real callers
+    /// share [`global`](Self::global).
+    pub const fn starting_at(first: u64) -> Self {
+        Self { next: AtomicU64::new(first) }
+    }
+
+    /// The process-wide allocator.
+    pub fn global() -> &'static Self {
+        &GLOBAL
+    }
+
+    /// Reserve one id.
+    pub fn reserve(&self) -> BlockId {
+        BlockId(self.next.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Reserve `count` consecutive ids.
+    pub fn reserve_many(&self, count: u64) -> BlockIdRange {
+        let start = self.next.fetch_add(count, Ordering::Relaxed);
+        BlockIdRange { ids: start..start + count }
+    }
+
+    /// Make sure `id` (allocated elsewhere, e.g. loaded from disk) is never
+    /// handed out again.
+    pub fn advance_past(&self, id: BlockId) {
+        self.next.fetch_max(id.0.saturating_add(1), Ordering::Relaxed);
+    }
+
+    /// The id the next reservation will return.
+    pub fn peek(&self) -> BlockId {
+        BlockId(self.next.load(Ordering::Relaxed))
+    }
+}
+
+/// Ids from [`BlockIdAllocator::reserve_many`].
+#[derive(Debug, Clone)]
+pub struct BlockIdRange {
+    ids: Range<u64>,
+}
+
+impl Iterator for BlockIdRange {
+    type Item = BlockId;
+
+    fn next(&mut self) -> Option<BlockId> {
+        self.ids.next().map(BlockId)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl ExactSizeIterator for BlockIdRange {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reservations_do_not_overlap() {
+        let alloc = BlockIdAllocator::starting_at(1);
+        assert_eq!(alloc.reserve(), BlockId(1));
+        let range: Vec<_> = alloc.reserve_many(3).collect();
+        assert_eq!(range, [BlockId(2), BlockId(3), BlockId(4)]);
+        assert_eq!(alloc.reserve(), BlockId(5));
+    }
+
+    #[test]
+    fn advance_past_skips_known_ids_but_never_goes_back() {
+        let alloc = BlockIdAllocator::starting_at(1);
+        alloc.advance_past(BlockId(41));
+        assert_eq!(alloc.reserve(), BlockId(42));
+        alloc.advance_past(BlockId(10));
+        assert_eq!(alloc.peek(), BlockId(43));
+    }
+}
@@ -0,0 +1,93 @@
+//! Sleep-aware elapsed time.
+//!
+//! `Instant` stops while the machine is asleep (`mach_absolute_time` on
+//! macOS, `CLOCK_MONOTONIC` on Linux), so a command that ran across a lid
+//! close reports only its awake time, and tick-gap checks never see the
+//! sleep at all. `SystemTime` includes sleep but jumps when the clock is
+//! adjusted. `Stopwatch` records both and uses the wall-clock delta only to
+//! account for time spent asleep.
+
+use std::time::{Duration, Instant, SystemTime};
+
+/// Wall-clock drift below this is clock noise (NTP slew), not sleep.
+const SLEEP_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// A start point whose `elapsed()` includes time the system was asleep.
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    instant: Instant,
+    wall: SystemTime,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self { instant: Instant::now(), wall: SystemTime::now() }
+    }
+
+    /// Real time since start: awake time plus any time spent asleep.
+    pub fn elapsed(&self) -> Duration {
+        let awake = self.instant.elapsed();
+        awake + self.slept_during(awake)
+    }
+
+    /// `elapsed()` in whole milliseconds, for `duration_ms` fields.
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed().as_millis() as u64
+    }
+
+    /// Time the system spent asleep since start (zero if it never slept).
+    pub fn slept(&self) -> Duration {
+        self.slept_during(self.instant.elapsed())
+    }
+
+    fn slept_during(&self, awake: Duration) -> Duration {
+        // A wall clock set backwards can't be told apart from "no sleep".
+        let Ok(wall) = self.wall.elapsed() else {
+            return Duration::ZERO;
+        };
+        match wall.checked_sub(awake) {
+            Some(gap) if gap >= SLEEP_THRESHOLD => gap,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_sleep_matches_monotonic() {
+        let sw = Stopwatch::start();
+        assert_eq!(sw.slept(), Duration::ZERO);
+        assert!(sw.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn wall_clock_gap_counts_as_sleep() {
+        // Simulate a 10 minute sleep: wall clock started earlier than the
+        // monotonic clock would suggest.
+        let sw = Stopwatch {
+            instant: Instant::now(),
+            wall: SystemTime::now() - Duration::from_secs(600),
+        };
+        assert!(sw.slept() >= Duration::from_secs(599));
+        assert!(sw.elapsed() >= Duration::from_secs(599));
+    }
+
+    #[test]
+    fn clock_set_backwards_is_ignored() {
+        let sw = Stopwatch {
+            instant: Instant::now(),
+            wall: SystemTime::now() + Duration::from_secs(3600),
+        };
+        assert_eq!(sw.slept(), Duration::ZERO);
+        assert!(sw.elapsed() < Duration::from_secs(1));
+    }
+}
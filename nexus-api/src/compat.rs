@@ -0,0 +1,302 @@
+//! API version and capability negotiation.
+//!
+//! `ShellEvent` and `Value` grow new variants over time, and a peer built
+//! against an older `nexus-api` cannot decode them: an alternative frontend,
+//! an older UI talking to a newer remote agent, or an out-of-process kernel.
+//! Each side advertises the [`ApiCaps`] it was built with during the
+//! handshake; the sender takes the intersection and passes every event
+//! through [`downgrade_event`] before serializing it. Unsupported values
+//! degrade to something the peer understands (a domain value becomes its
+//! text rendering), and events with no useful fallback are dropped.
+//!
+//! Bump [`API_VERSION`] and add a flag whenever a variant is added.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ReplUpdate, ShellEvent, Value};
+
+/// Version of the event and value model in this build.
+pub const API_VERSION: u32 = 5;
+
+/// Optional parts of the event protocol a peer understands. The baseline
+/// every peer supports is plain values (primitives, lists, records, tables,
+/// paths, errors) and the command lifecycle and output events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiCaps {
+    /// [`API_VERSION`] of the peer.
+    pub version: u32,
+    /// `Value::Domain`: file operations, trees, diffs, network events, viewers.
+    pub domain_values: bool,
+    /// `Value::Media`.
+    pub media: bool,
+    /// `Value::Structured`.
+    pub structured_values: bool,
+    /// `Value::FileEntry`, `Process`, `GitStatus` and `GitCommit`.
+    pub rich_rows: bool,
+    /// `ShellEvent::StreamingUpdate`.
+    pub streaming: bool,
+    /// `ShellEvent::CommandError`.
+    pub command_errors: bool,
+    /// `ShellEvent::KernelPanic`.
+    pub panic_reports: bool,
+    /// `TerminalSnapshot`, `ScrollbackHistory` and `TerminalModeChanged`.
+    pub terminal_state: bool,
+    /// `ShellEvent::Trace`. Absent before version 2.
+    #[serde(default)]
+    pub traces: bool,
+    /// `ShellEvent::DebugPaused`. Absent before version 3.
+    #[serde(default)]
+    pub debugger: bool,
+    /// `ShellEvent::Repl`. Absent before version 4.
+    #[serde(default)]
+    pub repls: bool,
+    /// `ShellEvent::ResourceUsage`. Absent before version 5.
+    #[serde(default)]
+    pub resource_usage: bool,
+}
+
+impl ApiCaps {
+    /// Everything this build understands.
+    pub const fn current() -> Self {
+        Self {
+            version: API_VERSION,
+            domain_values: true,
+            media: true,
+            structured_values: true,
+            rich_rows: true,
+            streaming: true,
+            command_errors: true,
+            panic_reports: true,
+            terminal_state: true,
+            traces: true,
+            debugger: true,
+            repls: true,
+            resource_usage: true,
+        }
+    }
+
+    /// Only the baseline: text-like values and lifecycle events.
+    pub const fn baseline() -> Self {
+        Self {
+            version: 0,
+            domain_values: false,
+            media: false,
+            structured_values: false,
+            rich_rows: false,
+            streaming: false,
+            command_errors: false,
+            panic_reports: false,
+            terminal_state: false,
+            traces: false,
+            debugger: false,
+            repls: false,
+            resource_usage: false,
+        }
+    }
+
+    /// What both sides understand.
+    pub fn intersect(self, other: Self) -> Self {
+        Self {
+            version: self.version.min(other.version),
+            domain_values: self.domain_values && other.domain_values,
+            media: self.media && other.media,
+            structured_values: self.structured_values && other.structured_values,
+            rich_rows: self.rich_rows && other.rich_rows,
+            streaming: self.streaming && other.streaming,
+            command_errors: self.command_errors && other.command_errors,
+            panic_reports: self.panic_reports && other.panic_reports,
+            terminal_state: self.terminal_state && other.terminal_state,
+            traces: self.traces && other.traces,
+            debugger: self.debugger && other.debugger,
+            repls: self.repls && other.repls,
+            resource_usage: self.resource_usage && other.resource_usage,
+        }
+    }
+
+    /// Whether values and events can be sent unchanged.
+    pub fn is_current(&self) -> bool {
+        *self == Self::current()
+    }
+}
+
+impl Default for ApiCaps {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl Value {
+    /// Rewrite this value using only variants `caps` allows.
+    pub fn downgrade(self, caps: &ApiCaps) -> Value {
+        if caps.is_current() {
+            return self;
+        }
+        match self {
+            Value::List(items) => Value::List(items.into_iter().map(|v| v.downgrade(caps)).collect()),
+            Value::Record(fields) => Value::Record(fields.into_iter().map(|(k, v)| (k, v.downgrade(caps))).collect()),
+            Value::Table { columns, rows } => Value::Table {
+                columns,
+                rows: rows.into_iter().map(|row| row.into_iter().map(|v| v.downgrade(caps)).collect()).collect(),
+            },
+            Value::Structured { data, .. } if !caps.structured_values => {
+                Value::Record(data.into_iter().map(|(k, v)| (k, v.downgrade(caps))).collect())
+            }
+            Value::Structured { kind, data } => Value::Structured {
+                kind,
+                data: data.into_iter().map(|(k, v)| (k, v.downgrade(caps))).collect(),
+            },
+            Value::FileEntry(entry) if !caps.rich_rows => Value::Path(entry.path),
+            v @ (Value::Process(_) | Value::GitStatus(_) | Value::GitCommit(_)) if !caps.rich_rows => {
+                Value::String(v.to_text())
+            }
+            v @ Value::Media { .. } if !caps.media => Value::String(v.to_text()),
+            v @ Value::Domain(_) if !caps.domain_values => Value::String(v.to_text()),
+            v => v,
+        }
+    }
+}
+
+/// Rewrite an event for a peer with `caps`. Returns `None` when the event
+/// has no meaningful fallback and should not be sent.
+pub fn downgrade_event(event: ShellEvent, caps: &ApiCaps) -> Option<ShellEvent> {
+    if caps.is_current() {
+        return Some(event);
+    }
+    let event = match event {
+        ShellEvent::CommandOutput { block_id, value } => {
+            ShellEvent::CommandOutput { block_id, value: value.downgrade(caps) }
+        }
+        // Without streaming, each update stands in as the block's output.
+        ShellEvent::StreamingUpdate { block_id, update, .. } if !caps.streaming => {
+            ShellEvent::CommandOutput { block_id, value: update.downgrade(caps) }
+        }
+        ShellEvent::StreamingUpdate { block_id, seq, update, coalesce } => {
+            ShellEvent::StreamingUpdate { block_id, seq, update: update.downgrade(caps), coalesce }
+        }
+        ShellEvent::CommandError { block_id, error } if !caps.command_errors => {
+            ShellEvent::StderrChunk { block_id, data: format!("{}\n", error).into_bytes() }
+        }
+        ShellEvent::Trace { block_id, depth, line } if !caps.traces => ShellEvent::StderrChunk {
+            block_id,
+            data: format!("{} {}\n", "+".repeat(depth as usize), line).into_bytes(),
+        },
+        // The peer can't resume the script, but can see where it stopped.
+        ShellEvent::DebugPaused { block_id, step, total, command } if !caps.debugger => ShellEvent::StderrChunk {
+            block_id,
+            data: format!("debug: paused before step {}/{}: {}\n", step, total, command).into_bytes(),
+        },
+        // The session reads as a transcript; cells can't be sent.
+        ShellEvent::Repl { block_id, update } if !caps.repls => {
+            let data = match update {
+                ReplUpdate::Ready { language } => format!("{} ready\n", language),
+                ReplUpdate::CellStarted { cell, code } => format!("[{}] {}\n", cell, code.replace('\n', "\n... ")),
+                ReplUpdate::Output { text, stderr: true, .. } => {
+                    return Some(ShellEvent::StderrChunk { block_id, data: text.into_bytes() });
+                }
+                ReplUpdate::Output { text, .. } => text,
+                ReplUpdate::Value { value, .. } => format!("{}\n", value.to_text()),
+                ReplUpdate::CellFinished { error: Some(error), .. } => {
+                    return Some(ShellEvent::StderrChunk { block_id, data: format!("{}\n", error).into_bytes() });
+                }
+                ReplUpdate::CellFinished { error: None, .. } => return None,
+            };
+            ShellEvent::StdoutChunk { block_id, data: data.into_bytes(), last_echo_epoch: 0 }
+        }
+        ShellEvent::Repl { block_id, update: ReplUpdate::Value { cell, value } } => {
+            ShellEvent::Repl { block_id, update: ReplUpdate::Value { cell, value: value.downgrade(caps) } }
+        }
+        // The block still fails via CommandFinished; the report is extra.
+        ShellEvent::KernelPanic { .. } if !caps.panic_reports => return None,
+        ShellEvent::ResourceUsage { .. } if !caps.resource_usage => return None,
+        ShellEvent::TerminalSnapshot { .. }
+        | ShellEvent::ScrollbackHistory { .. }
+        | ShellEvent::TerminalModeChanged { .. }
+            if !caps.terminal_state =>
+        {
+            return None;
+        }
+        event => event,
+    };
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockId, CommandError, DomainValue, NetEventInfo, NetEventType};
+
+    fn ping() -> Value {
+        Value::Domain(Box::new(DomainValue::NetEvent(NetEventInfo {
+            event_type: NetEventType::PingResponse,
+            host: "example.com".into(),
+            ip: None,
+            rtt_ms: Some(12.5),
+            ttl: None,
+            seq: Some(1),
+            success: true,
+            message: None,
+        })))
+    }
+
+    #[test]
+    fn current_caps_pass_values_through() {
+        assert_eq!(ping().downgrade(&ApiCaps::current()), ping());
+    }
+
+    #[test]
+    fn domain_values_degrade_to_text_inside_collections() {
+        let caps = ApiCaps::baseline();
+        let Value::List(items) = Value::List(vec![ping(), Value::Int(1)]).downgrade(&caps) else {
+            panic!("expected list");
+        };
+        assert_eq!(items[0], Value::String(ping().to_text()));
+        assert_eq!(items[1], Value::Int(1));
+    }
+
+    #[test]
+    fn events_degrade_or_drop() {
+        let caps = ApiCaps::baseline();
+        let block_id = BlockId(1);
+
+        let error = ShellEvent::CommandError { block_id, error: CommandError::usage("rm", "missing operand") };
+        let Some(ShellEvent::StderrChunk { data, .. }) = downgrade_event(error, &caps) else {
+            panic!("expected stderr");
+        };
+        assert_eq!(data, b"rm: missing operand\n");
+
+        let update = ShellEvent::StreamingUpdate { block_id, seq: 1, update: ping(), coalesce: false };
+        assert!(matches!(
+            downgrade_event(update, &caps),
+            Some(ShellEvent::CommandOutput { value: Value::String(_), .. })
+        ));
+
+        let panic = ShellEvent::KernelPanic { block_id, message: "boom".into(), location: None, backtrace: String::new() };
+        assert!(downgrade_event(panic, &caps).is_none());
+
+        let trace = ShellEvent::Trace { block_id, depth: 2, line: "echo hi".into() };
+        let Some(ShellEvent::StderrChunk { data, .. }) = downgrade_event(trace, &caps) else {
+            panic!("expected stderr");
+        };
+        assert_eq!(data, b"++ echo hi\n");
+
+        let cell = ShellEvent::Repl { block_id, update: ReplUpdate::CellStarted { cell: 2, code: "x = 1\nx".into() } };
+        let Some(ShellEvent::StdoutChunk { data, .. }) = downgrade_event(cell, &caps) else {
+            panic!("expected stdout");
+        };
+        assert_eq!(data, b"[2] x = 1\n... x\n");
+        let done = ShellEvent::Repl { block_id, update: ReplUpdate::CellFinished { cell: 2, error: None } };
+        assert!(downgrade_event(done, &caps).is_none());
+
+        let usage = ShellEvent::ResourceUsage { block_id, usage: Default::default() };
+        assert!(downgrade_event(usage, &caps).is_none());
+    }
+
+    #[test]
+    fn intersect_takes_the_common_subset() {
+        let old = ApiCaps { media: true, ..ApiCaps::baseline() };
+        let caps = ApiCaps::current().intersect(old);
+        assert_eq!(caps.version, 0);
+        assert!(caps.media);
+        assert!(!caps.domain_values);
+    }
+}
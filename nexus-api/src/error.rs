@@ -0,0 +1,169 @@
+//! Structured failures of native commands.
+//!
+//! A native command that fails reports a [`CommandError`] instead of a line
+//! of stderr text. The UI renders it as an error chip and the agent sees
+//! the fields rather than having to parse `cat: foo: No such file or
+//! directory` back apart. `Display` gives the traditional one-line form for
+//! when the error is written to a file.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// What went wrong, independent of how it is worded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CommandErrorKind {
+    /// A file, directory or other named thing does not exist.
+    NotFound,
+    PermissionDenied,
+    AlreadyExists,
+    /// Expected a file, got a directory.
+    IsADirectory,
+    /// Expected a directory, got something else.
+    NotADirectory,
+    DirectoryNotEmpty,
+    /// Missing or malformed arguments.
+    Usage,
+    /// Input (piped or read) the command could not make sense of.
+    InvalidInput,
+    /// Not available on this platform or in this context.
+    Unsupported,
+    /// Cancelled by the user.
+    Interrupted,
+    /// Any other I/O failure.
+    Io,
+    Other,
+}
+
+impl CommandErrorKind {
+    /// Short label for error chips.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::NotFound => "not found",
+            Self::PermissionDenied => "permission denied",
+            Self::AlreadyExists => "already exists",
+            Self::IsADirectory => "is a directory",
+            Self::NotADirectory => "not a directory",
+            Self::DirectoryNotEmpty => "directory not empty",
+            Self::Usage => "usage",
+            Self::InvalidInput => "invalid input",
+            Self::Unsupported => "unsupported",
+            Self::Interrupted => "interrupted",
+            Self::Io => "i/o error",
+            Self::Other => "error",
+        }
+    }
+
+    /// Classify an I/O error.
+    pub fn from_io(kind: std::io::ErrorKind) -> Self {
+        use std::io::ErrorKind as K;
+        match kind {
+            K::NotFound => Self::NotFound,
+            K::PermissionDenied => Self::PermissionDenied,
+            K::AlreadyExists => Self::AlreadyExists,
+            K::IsADirectory => Self::IsADirectory,
+            K::NotADirectory => Self::NotADirectory,
+            K::DirectoryNotEmpty => Self::DirectoryNotEmpty,
+            K::InvalidInput => Self::Usage,
+            K::InvalidData | K::UnexpectedEof => Self::InvalidInput,
+            K::Unsupported => Self::Unsupported,
+            K::Interrupted => Self::Interrupted,
+            _ => Self::Io,
+        }
+    }
+}
+
+/// A failed native command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandError {
+    /// The command that failed, e.g. `cat`.
+    pub command: String,
+    pub kind: CommandErrorKind,
+    /// Human-readable description, without the command or path prefix.
+    pub message: String,
+    /// The path the failure concerns, if any.
+    pub path: Option<PathBuf>,
+    /// OS error number, when the failure came from a system call.
+    pub errno: Option<i32>,
+    /// What the user might do about it.
+    pub suggestion: Option<String>,
+}
+
+impl CommandError {
+    pub fn new(command: impl Into<String>, kind: CommandErrorKind, message: impl Into<String>) -> Self {
+        Self { command: command.into(), kind, message: message.into(), path: None, errno: None, suggestion: None }
+    }
+
+    /// Missing or malformed arguments.
+    pub fn usage(command: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(command, CommandErrorKind::Usage, message)
+    }
+
+    /// An I/O failure on `path`.
+    pub fn io(command: impl Into<String>, path: impl Into<PathBuf>, err: &std::io::Error) -> Self {
+        Self {
+            errno: err.raw_os_error(),
+            path: Some(path.into()),
+            ..Self::new(command, CommandErrorKind::from_io(err.kind()), io_message(err))
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_errno(mut self, errno: i32) -> Self {
+        self.errno = Some(errno);
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+/// The OS description of an I/O error without Rust's " (os error N)" suffix.
+fn io_message(err: &std::io::Error) -> String {
+    let text = err.to_string();
+    match text.rfind(" (os error ") {
+        Some(i) if text.ends_with(')') => text[..i].to_string(),
+        _ => text,
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.command.is_empty() {
+            write!(f, "{}: ", self.command)?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_keep_kind_errno_and_path() {
+        let err = std::io::Error::from_raw_os_error(2);
+        let ce = CommandError::io("cat", "missing.txt", &err);
+        assert_eq!(ce.kind, CommandErrorKind::NotFound);
+        assert_eq!(ce.errno, Some(2));
+        assert_eq!(ce.to_string(), "cat: missing.txt: No such file or directory");
+    }
+
+    #[test]
+    fn display_omits_missing_parts() {
+        let ce = CommandError::usage("cp", "missing destination file operand");
+        assert_eq!(ce.to_string(), "cp: missing destination file operand");
+        assert_eq!(CommandError::new("", CommandErrorKind::Other, "boom").to_string(), "boom");
+    }
+}
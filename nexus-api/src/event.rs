@@ -1,6 +1,6 @@
 //! Shell events emitted by the kernel to subscribers (UI, history, etc.)
 
-use crate::Value;
+use crate::{CommandError, ResourceUsage, Value};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -16,6 +16,9 @@ pub enum ShellEvent {
         block_id: BlockId,
         command: String,
         cwd: PathBuf,
+        /// The container the command runs in, for `nexus exec -c`.
+        #[serde(default)]
+        container: Option<String>,
     },
 
     /// A chunk of stdout data is available.
@@ -40,6 +43,45 @@ pub enum ShellEvent {
         value: Value,
     },
 
+    /// A native command failed. Sent instead of an stderr line, before the
+    /// block's `CommandFinished`.
+    CommandError {
+        block_id: BlockId,
+        error: CommandError,
+    },
+
+    /// With `set -x`: a command line, expanded, about to run. `depth` is
+    /// one more than the number of function calls it is nested in, for the
+    /// `+` prefix.
+    Trace {
+        block_id: BlockId,
+        depth: u32,
+        line: String,
+    },
+
+    /// `debug <script>` is paused before step `step` of `total` (1-based),
+    /// waiting to be told to step, continue or abort. `command` is the
+    /// step's source with variables expanded.
+    DebugPaused {
+        block_id: BlockId,
+        step: u32,
+        total: u32,
+        command: String,
+    },
+
+    /// Progress of a `repl` block's interpreter session.
+    Repl {
+        block_id: BlockId,
+        update: ReplUpdate,
+    },
+
+    /// CPU time, memory and IO used by the process a block ran. Sent just
+    /// before the block's `CommandFinished`; in-process commands send none.
+    ResourceUsage {
+        block_id: BlockId,
+        usage: ResourceUsage,
+    },
+
     /// A command has finished executing.
     CommandFinished {
         block_id: BlockId,
@@ -62,6 +104,8 @@ pub enum ShellEvent {
     /// A job state changed (started, stopped, continued, terminated).
     JobStateChanged {
         job_id: u32,
+        /// The command line the job was started with.
+        command: String,
         state: JobState,
     },
 
@@ -105,6 +149,16 @@ pub enum ShellEvent {
         modes: TerminalModes,
     },
 
+    /// A command panicked inside the kernel. The block is finished with a
+    /// failure and the kernel state rolled back; this is the crash report.
+    KernelPanic {
+        block_id: BlockId,
+        message: String,
+        /// `file:line:col` of the panic, when known.
+        location: Option<String>,
+        backtrace: String,
+    },
+
     /// Scrollback history sent on reconnect.
     /// Contains structured Cell rows from the agent's shadow parser,
     /// allowing the UI to populate the scrollback buffer with styled content
@@ -118,6 +172,23 @@ pub enum ShellEvent {
     },
 }
 
+/// What happened in a `repl` block's interpreter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplUpdate {
+    /// The interpreter started, or restarted with a fresh session, and is
+    /// waiting for cells. `language` is `python` or `node`.
+    Ready { language: String },
+    /// Cell `cell` (numbered from 1 in each session) was sent to run.
+    CellStarted { cell: u32, code: String },
+    /// Text the cell printed.
+    Output { cell: u32, text: String, stderr: bool },
+    /// The value of the cell's last expression: a table for dataframes and
+    /// lists of records, else its text.
+    Value { cell: u32, value: Value },
+    /// The cell finished; `error` is the traceback if it raised.
+    CellFinished { cell: u32, error: Option<String> },
+}
+
 /// Terminal mode flags reported by the agent's shadow parser.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TerminalModes {
@@ -139,4 +210,6 @@ pub enum JobState {
     Running,
     Stopped,
     Done(i32),
+    /// Removed from the job table by `disown`; the process keeps running.
+    Disowned,
 }
@@ -1,11 +1,21 @@
 //! Nexus API - Shared types and IPC protocol for the Nexus shell runtime.
 
 mod block;
+mod block_id;
+mod clock;
+mod compat;
+mod error;
 mod event;
 mod provider;
+mod usage;
 mod value;
 
 pub use block::*;
+pub use block_id::*;
+pub use clock::*;
+pub use compat::*;
+pub use error::*;
 pub use event::*;
 pub use provider::*;
+pub use usage::*;
 pub use value::*;
@@ -0,0 +1,65 @@
+//! Resources a finished process used, as reported by `wait4(2)`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::format_size;
+
+/// CPU time, peak memory and disk IO of a process, or of all the children
+/// a command line ran (`time`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// CPU time spent in user mode.
+    pub user_ms: u64,
+    /// CPU time spent in the kernel on the process's behalf.
+    pub sys_ms: u64,
+    /// Peak resident set size, in bytes.
+    pub max_rss: u64,
+    /// Reads that had to go to disk, in filesystem blocks.
+    pub blocks_in: u64,
+    /// Writes that went to disk, in filesystem blocks.
+    pub blocks_out: u64,
+}
+
+impl ResourceUsage {
+    /// One line for a block footer: `user 1.2s sys 0.3s maxrss 210.0M`.
+    pub fn summary(&self) -> String {
+        format!(
+            "user {} sys {} maxrss {}",
+            format_seconds(self.user_ms),
+            format_seconds(self.sys_ms),
+            format_size(self.max_rss)
+        )
+    }
+
+    /// Label and value for each figure, for the expanded footer.
+    pub fn details(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("cpu", format_seconds(self.user_ms + self.sys_ms)),
+            ("user", format_seconds(self.user_ms)),
+            ("sys", format_seconds(self.sys_ms)),
+            ("max rss", format_size(self.max_rss)),
+            ("blocks in", self.blocks_in.to_string()),
+            ("blocks out", self.blocks_out.to_string()),
+        ]
+    }
+}
+
+/// `850ms` below a second, else `1.2s`.
+fn format_seconds(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let usage = ResourceUsage { user_ms: 1234, sys_ms: 300, max_rss: 210 * 1024 * 1024, ..Default::default() };
+        assert_eq!(usage.summary(), "user 1.2s sys 300ms maxrss 210.0M");
+    }
+}
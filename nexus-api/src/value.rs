@@ -718,6 +718,10 @@ pub enum FileOpKind {
     Remove,
     Chmod,
     Chown,
+    /// `push` to an ssh host.
+    Upload,
+    /// `pull` from an ssh host.
+    Download,
 }
 
 /// Phase of a file operation.
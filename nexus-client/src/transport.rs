@@ -350,6 +350,7 @@ impl TransportHandle {
                 resume: true,
                 nesting: true,
                 file_transfer: true,
+                api: nexus_api::ApiCaps::current(),
             },
             forwarded_env,
         };
@@ -378,6 +379,10 @@ impl TransportHandle {
             }
         };
 
+        if caps.api != nexus_api::ApiCaps::current() {
+            tracing::info!(agent = ?caps.api, "agent API differs from ours; it downgrades events we cannot decode");
+        }
+
         let (request_tx, rtt_ms, last_pong_at, last_seen_seq, last_confirmed_epoch, response_rx) =
             Self::setup_bridge(reader, writer, kernel_tx);
 
@@ -2,7 +2,7 @@
 
 use super::{CommandContext, NexusCommand};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
-use nexus_api::Value;
+use nexus_api::{CommandError, CommandErrorKind, Value};
 use std::path::PathBuf;
 
 pub struct Base64Command;
@@ -35,7 +35,7 @@ impl NexusCommand for Base64Command {
                 ctx.state.cwd.join(&f)
             };
             std::fs::read(&path)
-                .map_err(|e| anyhow::anyhow!("base64: {}: {}", path.display(), e))?
+                .map_err(|e| CommandError::io("base64", &path, &e))?
         } else if let Some(stdin) = &ctx.stdin {
             match stdin {
                 Value::String(s) => s.as_bytes().to_vec(),
@@ -43,7 +43,7 @@ impl NexusCommand for Base64Command {
                 other => other.to_text().into_bytes(),
             }
         } else {
-            anyhow::bail!("base64: no input (pipe data or specify a file)");
+            return Err(CommandError::usage("base64", "no input (pipe data or specify a file)").into());
         };
 
         if decode {
@@ -52,7 +52,7 @@ impl NexusCommand for Base64Command {
             let cleaned: String = text.chars().filter(|c| !c.is_whitespace()).collect();
             let decoded = STANDARD
                 .decode(&cleaned)
-                .map_err(|e| anyhow::anyhow!("base64: invalid input: {}", e))?;
+                .map_err(|e| CommandError::new("base64", CommandErrorKind::InvalidInput, format!("invalid input: {}", e)))?;
             Ok(Value::Bytes(decoded))
         } else {
             let encoded = STANDARD.encode(&input_data);
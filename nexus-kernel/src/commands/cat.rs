@@ -1,7 +1,7 @@
 //! The `cat` command - concatenate and display files.
 
 use super::{CommandContext, NexusCommand};
-use nexus_api::{detect_mime_type, mime_from_extension, MediaMetadata, Value};
+use nexus_api::{detect_mime_type, mime_from_extension, CommandError, MediaMetadata, Value};
 use std::fs;
 use std::path::PathBuf;
 
@@ -88,7 +88,7 @@ impl NexusCommand for CatCommand {
 
             // Read raw bytes first
             let data = fs::read(&resolved)
-                .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+                .map_err(|e| CommandError::io("cat", path, &e))?;
 
             // Detect content type
             let ext = resolved
@@ -165,7 +165,7 @@ impl NexusCommand for CatCommand {
                     all_content.push_str(&content);
                 }
                 Err(e) => {
-                    return Err(anyhow::anyhow!("{}: {}", path.display(), e));
+                    return Err(CommandError::io("cat", path, &e).into());
                 }
             }
         }
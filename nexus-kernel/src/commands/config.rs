@@ -0,0 +1,176 @@
+//! config - Show the merged user and project configuration.
+//!
+//! ```text
+//! config                      key/value table
+//! config show [--origin]      ... with the file each value came from
+//! config files                configuration files in effect, lowest precedence first
+//! config reload               re-read the files for the current directory
+//! config set <key> <value>    write a setting to ~/.nexus/config.toml
+//! config unset <key>          remove it again
+//! config import [--apply]     preview (or write) aliases, env and PATH from zsh/bash/fish rc files
+//! ```
+//!
+//! `set` takes the value as TOML (`15`, `true`, `"#5fafff"`); anything that
+//! doesn't parse as TOML is stored as a string.
+
+use super::{CommandContext, NexusCommand};
+use crate::config::{self, Config};
+use crate::shell_import::{self, ShellImport};
+use std::path::PathBuf;
+use nexus_api::{CommandError, TableColumn, Value};
+
+pub struct ConfigCommand;
+
+impl NexusCommand for ConfigCommand {
+    fn name(&self) -> &'static str {
+        "config"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show user and project configuration (.nexus/config.toml)"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        match args.first().map(String::as_str) {
+            None => Ok(settings_table(&ctx.state.config, false)),
+            Some("show") => match args.get(1).map(String::as_str) {
+                None => Ok(settings_table(&ctx.state.config, false)),
+                Some("--origin") => Ok(settings_table(&ctx.state.config, true)),
+                Some(other) => Err(CommandError::usage("config", format!("unknown option '{}'", other)).into()),
+            },
+            Some("files") => Ok(files_table(&ctx.state.config)),
+            Some("reload") => {
+                ctx.state.reload_config();
+                Ok(files_table(&ctx.state.config))
+            }
+            Some("set") => {
+                let [_, key, value] = args else {
+                    return Err(CommandError::usage("config", "usage: config set <key> <value>").into());
+                };
+                let value = value.parse::<toml_edit::Value>().unwrap_or_else(|_| value.as_str().into());
+                config::set_setting(&user_file()?, key, value)?;
+                ctx.state.reload_config();
+                Ok(Value::Unit)
+            }
+            Some("unset") => {
+                let [_, key] = args else {
+                    return Err(CommandError::usage("config", "usage: config unset <key>").into());
+                };
+                config::unset_setting(&user_file()?, key)?;
+                ctx.state.reload_config();
+                Ok(Value::Unit)
+            }
+            Some("import") => {
+                let apply = match args.get(1).map(String::as_str) {
+                    None => false,
+                    Some("--apply") => true,
+                    Some(other) => {
+                        return Err(CommandError::usage("config", format!("unknown option '{}'", other)).into());
+                    }
+                };
+                import(apply, ctx)
+            }
+            Some(other) => Err(CommandError::usage(
+                "config",
+                format!("unknown subcommand '{}' (expected show, files, reload, set, unset or import)", other),
+            )
+            .into()),
+        }
+    }
+}
+
+fn import(apply: bool, ctx: &mut CommandContext) -> anyhow::Result<Value> {
+    let home = std::env::var_os("HOME").map(PathBuf::from).ok_or_else(|| anyhow::anyhow!("config: HOME is not set"))?;
+    let shell = shell_import::detect_shell(&home);
+    let found = ShellImport::scan(shell, &home);
+    if found.files.is_empty() {
+        return Ok(Value::String("No zsh, bash or fish startup files found".to_string()));
+    }
+    let file = user_file()?;
+    if apply {
+        let count = found.apply(&ctx.state.config, &file)?;
+        ctx.state.reload_config();
+        return Ok(Value::String(format!("Imported {} settings into {}", count, file.display())));
+    }
+    Ok(match found.diff(&ctx.state.config, &file)? {
+        Some(diff) => Value::diff_file(diff),
+        None => Value::String("Nothing new to import".to_string()),
+    })
+}
+
+fn user_file() -> anyhow::Result<PathBuf> {
+    config::user_config_path().ok_or_else(|| anyhow::anyhow!("config: HOME is not set"))
+}
+
+fn settings_table(config: &Config, origin: bool) -> Value {
+    let rows = config
+        .entries()
+        .into_iter()
+        .map(|(key, value, from)| {
+            let mut row = vec![Value::String(key), Value::String(value)];
+            if origin {
+                row.push(Value::String(from.to_string()));
+            }
+            row
+        })
+        .collect();
+
+    let mut columns = vec![TableColumn::new("key"), TableColumn::new("value")];
+    if origin {
+        columns.push(TableColumn::new("origin"));
+    }
+    Value::Table { columns, rows }
+}
+
+fn files_table(config: &Config) -> Value {
+    let loaded = config.sources.iter().map(|origin| {
+        let scope = match origin {
+            config::Origin::User(_) => "user",
+            config::Origin::Project(_) => "project",
+        };
+        vec![Value::String(scope.to_string()), Value::Path(origin.path().to_path_buf()), Value::String("ok".to_string())]
+    });
+    let failed = config.errors.iter().map(|(path, error)| {
+        vec![Value::String(String::new()), Value::Path(path.clone()), Value::String(format!("ignored: {}", error))]
+    });
+
+    Value::Table {
+        columns: vec![TableColumn::new("scope"), TableColumn::new("file"), TableColumn::new("status")],
+        rows: loaded.chain(failed).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_show_origin_adds_column() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".nexus")).unwrap();
+        std::fs::write(dir.path().join(".nexus/config.toml"), "[snippets]\nt = \"cargo test\"\n").unwrap();
+
+        let mut test_ctx = TestContext::new(dir.path().to_path_buf());
+        test_ctx.state.enable_config();
+        let args = ["show".to_string(), "--origin".to_string()];
+        let Value::Table { columns, rows } = ConfigCommand.execute(&args, &mut test_ctx.ctx()).unwrap() else {
+            panic!("expected table");
+        };
+        assert_eq!(columns.len(), 3);
+        let row = rows.iter().find(|r| r[0] == Value::String("snippets.t".into())).unwrap();
+        assert_eq!(row[1], Value::String("cargo test".into()));
+        assert!(row[2].to_text().starts_with("project ("));
+    }
+
+    #[test]
+    fn test_unknown_subcommand_is_usage_error() {
+        let mut test_ctx = TestContext::new_default();
+        let err = ConfigCommand.execute(&["edit".to_string()], &mut test_ctx.ctx()).unwrap_err();
+        assert!(err.to_string().contains("unknown subcommand"));
+    }
+}
@@ -0,0 +1,234 @@
+//! conformance - Compare the shell against bash on the built-in corpus.
+//!
+//! ```text
+//! conformance [category]            pass rate per category (recorded in history)
+//! conformance failures [category]   failing cases with both shells' results
+//! conformance fuzz [N] [--seed S]   run N fuzz inputs (default 200)
+//! conformance history               pass rate of previous runs
+//! ```
+
+use super::{CommandContext, NexusCommand};
+use crate::conformance::{self as harness, CaseResult, fuzz};
+use anyhow::Context;
+use nexus_api::{DisplayFormat, TableColumn, Value};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+const DEFAULT_FUZZ_ITERATIONS: usize = 200;
+
+pub struct ConformanceCommand;
+
+impl NexusCommand for ConformanceCommand {
+    fn name(&self) -> &'static str {
+        "conformance"
+    }
+
+    fn description(&self) -> &'static str {
+        "Compare shell semantics against bash"
+    }
+
+    fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        match args.first().map(String::as_str) {
+            Some("failures") => {
+                let results = run(args.get(1).map(String::as_str))?;
+                Ok(failures(&results))
+            }
+            Some("fuzz") => fuzz_report(&args[1..]),
+            Some("history") => history(),
+            category => {
+                let results = run(category)?;
+                let summaries = harness::summarize(&results);
+                if category.is_none()
+                    && let Err(e) = record_history(&summaries)
+                {
+                    tracing::warn!("conformance: failed to record history: {}", e);
+                }
+                Ok(summary_table(&summaries))
+            }
+        }
+    }
+}
+
+fn run(category: Option<&str>) -> anyhow::Result<Vec<CaseResult>> {
+    let results = harness::run_corpus(category);
+    if results.is_empty()
+        && let Some(category) = category
+    {
+        let mut known: Vec<String> = harness::corpus().into_iter().map(|c| c.category).collect();
+        known.dedup();
+        anyhow::bail!("conformance: unknown category '{}' (expected one of: {})", category, known.join(", "));
+    }
+    Ok(results)
+}
+
+fn summary_table(summaries: &[harness::CategorySummary]) -> Value {
+    let rows = summaries
+        .iter()
+        .map(|s| {
+            vec![
+                Value::String(s.category.clone()),
+                Value::Int(s.cases as i64),
+                Value::Int(s.passed as i64),
+                Value::Int(s.failed as i64),
+                Value::Int(s.skipped as i64),
+                Value::Float(s.pass_rate() * 100.0),
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("category"),
+            TableColumn::new("cases"),
+            TableColumn::new("passed"),
+            TableColumn::new("failed"),
+            TableColumn::new("skipped"),
+            TableColumn::with_format("pass_rate", DisplayFormat::Percentage),
+        ],
+        rows,
+    }
+}
+
+fn failures(results: &[CaseResult]) -> Value {
+    let rows = results
+        .iter()
+        .filter(|r| r.verdict != harness::Verdict::Pass && r.verdict != harness::Verdict::Skipped)
+        .map(|r| {
+            let (bash_exit, bash_stdout) = match &r.bash {
+                Some(b) => (Value::Int(b.exit_code as i64), Value::String(b.stdout.clone())),
+                None => (Value::Unit, Value::Unit),
+            };
+            vec![
+                Value::String(r.case.category.clone()),
+                Value::String(r.case.script.clone()),
+                Value::String(r.verdict.as_str().to_string()),
+                Value::Bool(r.case.xfail),
+                Value::Int(r.nexus.exit_code as i64),
+                bash_exit,
+                Value::String(r.nexus.stdout.clone()),
+                bash_stdout,
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("category"),
+            TableColumn::new("script"),
+            TableColumn::new("verdict"),
+            TableColumn::new("known"),
+            TableColumn::new("nexus_exit"),
+            TableColumn::new("bash_exit"),
+            TableColumn::new("nexus_stdout"),
+            TableColumn::new("bash_stdout"),
+        ],
+        rows,
+    }
+}
+
+fn fuzz_report(args: &[String]) -> anyhow::Result<Value> {
+    let mut iterations = DEFAULT_FUZZ_ITERATIONS;
+    let mut seed = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = iter.next().context("conformance: fuzz: --seed needs a value")?;
+                seed = Some(value.parse().with_context(|| format!("conformance: fuzz: invalid seed '{}'", value))?);
+            }
+            n => {
+                iterations = n.parse().with_context(|| format!("conformance: fuzz: invalid count '{}'", n))?;
+            }
+        }
+    }
+    let seed = seed.unwrap_or_else(rand::random);
+
+    let report = fuzz::run(seed, iterations);
+    let findings = |list: &[fuzz::Finding]| {
+        Value::List(
+            list.iter()
+                .map(|f| {
+                    Value::Record(vec![
+                        ("input".to_string(), Value::String(f.input.clone())),
+                        ("detail".to_string(), Value::String(f.detail.clone())),
+                    ])
+                })
+                .collect(),
+        )
+    };
+    Ok(Value::Record(vec![
+        ("seed".to_string(), Value::Int(seed as i64)),
+        ("iterations".to_string(), Value::Int(report.iterations as i64)),
+        ("panics".to_string(), findings(&report.panics)),
+        ("divergences".to_string(), findings(&report.divergences)),
+    ]))
+}
+
+/// One line of `~/.nexus/conformance.jsonl`.
+#[derive(Serialize, Deserialize)]
+struct HistoryEntry {
+    at: i64,
+    cases: usize,
+    passed: usize,
+    skipped: usize,
+}
+
+fn history_path() -> anyhow::Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME environment variable not set")?;
+    Ok(PathBuf::from(home).join(".nexus").join("conformance.jsonl"))
+}
+
+fn record_history(summaries: &[harness::CategorySummary]) -> anyhow::Result<()> {
+    let entry = HistoryEntry {
+        at: chrono::Utc::now().timestamp(),
+        cases: summaries.iter().map(|s| s.cases).sum(),
+        passed: summaries.iter().map(|s| s.passed).sum(),
+        skipped: summaries.iter().map(|s| s.skipped).sum(),
+    };
+    let path = history_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+fn history() -> anyhow::Result<Value> {
+    let path = history_path()?;
+    let entries: Vec<HistoryEntry> = match std::fs::File::open(&path) {
+        Ok(file) => std::io::BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e).with_context(|| format!("conformance: failed to read {}", path.display())),
+    };
+
+    let rows = entries
+        .iter()
+        .rev()
+        .map(|e| {
+            let compared = e.cases - e.skipped;
+            let rate = if compared == 0 { 0.0 } else { e.passed as f64 * 100.0 / compared as f64 };
+            vec![
+                Value::Int(e.at),
+                Value::Int(e.cases as i64),
+                Value::Int(e.passed as i64),
+                Value::Float(rate),
+            ]
+        })
+        .collect();
+
+    Ok(Value::Table {
+        columns: vec![
+            TableColumn::with_format("when", DisplayFormat::RelativeTime),
+            TableColumn::new("cases"),
+            TableColumn::new("passed"),
+            TableColumn::with_format("pass_rate", DisplayFormat::Percentage),
+        ],
+        rows,
+    })
+}
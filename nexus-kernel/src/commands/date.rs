@@ -11,6 +11,10 @@ impl NexusCommand for DateCommand {
         "date"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut format: Option<&str> = None;
         let mut timestamp: Option<i64> = None;
@@ -47,6 +47,10 @@ impl NexusCommand for DfCommand {
         "df"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let (opts, paths) = DfOptions::parse(args);
 
@@ -0,0 +1,253 @@
+//! diagnostics - Inspect runtime health and build a bug-report bundle.
+//!
+//! ```text
+//! diagnostics            overview: uptime, channels, spans, recent warnings
+//! diagnostics warnings   recent warnings and errors
+//! diagnostics channels   event channel depths and dropped-event counts
+//! diagnostics spans      timings of traced spans
+//! diagnostics bundle     write a text bundle to ~/.nexus and copy it
+//! diagnostics crash list        crash reports in ~/.nexus/crashes, newest first
+//! diagnostics crash show <n>    the nth report's full text
+//! diagnostics crash submit <n>  post it to [crash] upload_url
+//! ```
+
+use super::{CommandContext, NexusCommand};
+use crate::crash::{self, CrashSummary};
+use crate::diagnostics::{self, ChannelReport, SpanStats, WarningRecord};
+use anyhow::Context;
+use nexus_api::{CommandError, DisplayFormat, TableColumn, Value};
+use std::path::PathBuf;
+
+pub struct DiagnosticsCommand;
+
+impl NexusCommand for DiagnosticsCommand {
+    fn name(&self) -> &'static str {
+        "diagnostics"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show recent warnings, channel health and span timings"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        match args.first().map(String::as_str) {
+            None => Ok(Value::Record(vec![
+                ("uptime_secs".to_string(), Value::Int(diagnostics::uptime().as_secs() as i64)),
+                ("channels".to_string(), channels_table(&diagnostics::channels())),
+                ("spans".to_string(), spans_table(&diagnostics::spans())),
+                ("warnings".to_string(), warnings_table(&diagnostics::warnings())),
+            ])),
+            Some("warnings") => Ok(warnings_table(&diagnostics::warnings())),
+            Some("channels") => Ok(channels_table(&diagnostics::channels())),
+            Some("spans") => Ok(spans_table(&diagnostics::spans())),
+            Some("bundle") => bundle(),
+            Some("crash") => {
+                let dir = crash::crashes_dir().context("HOME environment variable not set")?;
+                crash_report(&args[1..], &dir, ctx.state.config.crash_upload_url())
+            }
+            Some(other) => anyhow::bail!(
+                "diagnostics: unknown subcommand '{}' (expected warnings, channels, spans, bundle or crash)",
+                other
+            ),
+        }
+    }
+}
+
+fn warnings_table(warnings: &[WarningRecord]) -> Value {
+    let rows = warnings
+        .iter()
+        .rev()
+        .map(|w| {
+            vec![
+                Value::Int(w.at_ms / 1000),
+                Value::String(w.level.to_string()),
+                Value::String(w.target.clone()),
+                w.span.clone().map(Value::String).unwrap_or(Value::Unit),
+                Value::String(w.message.clone()),
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::with_format("when", DisplayFormat::RelativeTime),
+            TableColumn::new("level"),
+            TableColumn::new("target"),
+            TableColumn::new("span"),
+            TableColumn::new("message"),
+        ],
+        rows,
+    }
+}
+
+fn channels_table(channels: &[ChannelReport]) -> Value {
+    let rows = channels
+        .iter()
+        .map(|c| {
+            vec![
+                Value::String(c.name.clone()),
+                Value::Int(c.depth as i64),
+                c.capacity.map(|cap| Value::Int(cap as i64)).unwrap_or(Value::Unit),
+                Value::Int(c.dropped as i64),
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("channel"),
+            TableColumn::new("depth"),
+            TableColumn::new("capacity"),
+            TableColumn::new("dropped"),
+        ],
+        rows,
+    }
+}
+
+fn spans_table(spans: &[SpanStats]) -> Value {
+    let ms = |d: std::time::Duration| Value::Float(d.as_secs_f64() * 1000.0);
+    let rows = spans
+        .iter()
+        .map(|s| vec![Value::String(s.name.clone()), Value::Int(s.count as i64), ms(s.mean()), ms(s.max), ms(s.total)])
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("span"),
+            TableColumn::new("count"),
+            TableColumn::new("mean_ms"),
+            TableColumn::new("max_ms"),
+            TableColumn::new("total_ms"),
+        ],
+        rows,
+    }
+}
+
+fn crash_report(args: &[String], dir: &std::path::Path, upload_url: Option<&str>) -> anyhow::Result<Value> {
+    let crashes = crash::list(dir);
+    let nth = |arg: Option<&String>| -> anyhow::Result<&CrashSummary> {
+        let n: usize = arg
+            .and_then(|a| a.parse().ok())
+            .ok_or_else(|| CommandError::usage("diagnostics", "usage: diagnostics crash show|submit <n>"))?;
+        crashes
+            .get(n.wrapping_sub(1))
+            .ok_or_else(|| anyhow::anyhow!("diagnostics: no crash report {} ({} in {})", n, crashes.len(), dir.display()))
+    };
+    match args.first().map(String::as_str) {
+        None | Some("list") => Ok(crashes_table(&crashes)),
+        Some("show") => {
+            let path = &nth(args.get(1))?.path;
+            let text = std::fs::read_to_string(path).with_context(|| format!("diagnostics: failed to read {}", path.display()))?;
+            Ok(Value::String(text))
+        }
+        Some("submit") => {
+            let path = &nth(args.get(1))?.path;
+            let url = upload_url.context("diagnostics: set [crash] upload_url in ~/.nexus/config.toml to submit reports")?;
+            crash::submit(path, url)?;
+            Ok(Value::String(format!("Submitted {}", path.display())))
+        }
+        Some(other) => Err(CommandError::usage(
+            "diagnostics",
+            format!("unknown crash subcommand '{}' (expected list, show or submit)", other),
+        )
+        .into()),
+    }
+}
+
+fn crashes_table(crashes: &[CrashSummary]) -> Value {
+    let rows = crashes
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            vec![
+                Value::Int(i as i64 + 1),
+                Value::Int(c.at_ms / 1000),
+                Value::String(c.message.clone()),
+                c.location.clone().map(Value::String).unwrap_or(Value::Unit),
+                Value::String(c.status.as_str().to_string()),
+                Value::Path(c.path.clone()),
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("n"),
+            TableColumn::with_format("when", DisplayFormat::RelativeTime),
+            TableColumn::new("message"),
+            TableColumn::new("location"),
+            TableColumn::new("status"),
+            TableColumn::new("path"),
+        ],
+        rows,
+    }
+}
+
+/// Write the bundle next to the other Nexus state and put it on the
+/// clipboard. A clipboard failure is reported but not fatal: the file is
+/// still there to attach.
+fn bundle() -> anyhow::Result<Value> {
+    let text = diagnostics::bundle();
+
+    let home = std::env::var("HOME").context("HOME environment variable not set")?;
+    let dir = PathBuf::from(home).join(".nexus").join("diagnostics");
+    std::fs::create_dir_all(&dir).with_context(|| format!("diagnostics: failed to create {}", dir.display()))?;
+    let path = dir.join(format!("bundle-{}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+    std::fs::write(&path, &text).with_context(|| format!("diagnostics: failed to write {}", path.display()))?;
+
+    let copied = match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(&text)) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("diagnostics: failed to copy bundle: {}", e);
+            false
+        }
+    };
+
+    Ok(Value::Record(vec![
+        ("path".to_string(), Value::Path(path)),
+        ("bytes".to_string(), Value::Int(text.len() as i64)),
+        ("copied".to_string(), Value::Bool(copied)),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_overview_sections() {
+        let mut test_ctx = TestContext::new_default();
+        let result = DiagnosticsCommand.execute(&[], &mut test_ctx.ctx()).unwrap();
+        let Value::Record(fields) = result else { panic!("expected record") };
+        let names: Vec<&str> = fields.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(names, ["uptime_secs", "channels", "spans", "warnings"]);
+    }
+
+    #[test]
+    fn test_unknown_subcommand() {
+        let mut test_ctx = TestContext::new_default();
+        let err = DiagnosticsCommand.execute(&["bogus".to_string()], &mut test_ctx.ctx()).unwrap_err();
+        assert!(err.to_string().contains("unknown subcommand"));
+    }
+
+    #[test]
+    fn test_crash_list_and_show() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("crash-5000.txt"), "# Nexus crash report\nmessage: boom\n").unwrap();
+
+        let Value::Table { rows, .. } = crash_report(&[], dir.path(), None).unwrap() else { panic!("expected table") };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][2], Value::String("boom".into()));
+
+        let shown = crash_report(&["show".to_string(), "1".to_string()], dir.path(), None).unwrap();
+        assert!(shown.to_text().contains("message: boom"));
+        assert!(crash_report(&["show".to_string(), "2".to_string()], dir.path(), None).is_err());
+        let err = crash_report(&["submit".to_string(), "1".to_string()], dir.path(), None).unwrap_err();
+        assert!(err.to_string().contains("upload_url"));
+    }
+}
@@ -1,7 +1,7 @@
 //! `diff` — compare files and produce structured diffs.
 
 use super::{CommandContext, NexusCommand};
-use nexus_api::{DiffFileInfo, DiffHunk, DiffLine, DiffLineKind, GitChangeType, Value};
+use nexus_api::{CommandError, DiffFileInfo, DiffHunk, DiffLine, DiffLineKind, GitChangeType, Value};
 use similar::{ChangeTag, TextDiff};
 use std::path::PathBuf;
 
@@ -16,6 +16,10 @@ impl NexusCommand for DiffCommand {
         "Compare two files"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut context_lines: usize = 3;
         let mut files = Vec::new();
@@ -38,78 +42,18 @@ impl NexusCommand for DiffCommand {
         }
 
         if files.len() != 2 {
-            anyhow::bail!("diff: requires exactly two files");
+            return Err(CommandError::usage("diff", "requires exactly two files").into());
         }
 
         let path_a = resolve_path(&files[0], &ctx.state.cwd);
         let path_b = resolve_path(&files[1], &ctx.state.cwd);
 
         let text_a = std::fs::read_to_string(&path_a)
-            .map_err(|e| anyhow::anyhow!("diff: {}: {}", path_a.display(), e))?;
+            .map_err(|e| CommandError::io("diff", &path_a, &e))?;
         let text_b = std::fs::read_to_string(&path_b)
-            .map_err(|e| anyhow::anyhow!("diff: {}: {}", path_b.display(), e))?;
-
-        let diff = TextDiff::from_lines(&text_a, &text_b);
-
-        let mut hunks = Vec::new();
-        let mut additions: usize = 0;
-        let mut deletions: usize = 0;
-
-        for group in diff.grouped_ops(context_lines) {
-            let mut lines = Vec::new();
-
-            // Compute hunk header ranges
-            let first_op = group.first().unwrap();
-            let last_op = group.last().unwrap();
-            let old_start = first_op.old_range().start + 1;
-            let old_count = last_op.old_range().end - first_op.old_range().start;
-            let new_start = first_op.new_range().start + 1;
-            let new_count = last_op.new_range().end - first_op.new_range().start;
-
-            for op in &group {
-                for change in diff.iter_changes(op) {
-                    let (kind, content) = match change.tag() {
-                        ChangeTag::Equal => (
-                            DiffLineKind::Context,
-                            change.as_str().unwrap_or("").to_string(),
-                        ),
-                        ChangeTag::Insert => {
-                            additions += 1;
-                            (
-                                DiffLineKind::Addition,
-                                change.as_str().unwrap_or("").to_string(),
-                            )
-                        }
-                        ChangeTag::Delete => {
-                            deletions += 1;
-                            (
-                                DiffLineKind::Deletion,
-                                change.as_str().unwrap_or("").to_string(),
-                            )
-                        }
-                    };
-
-                    // Strip trailing newline from content (our renderer adds them)
-                    let content = content.trim_end_matches('\n').to_string();
-
-                    lines.push(DiffLine {
-                        kind,
-                        content,
-                        old_lineno: change.old_index().map(|i| i + 1),
-                        new_lineno: change.new_index().map(|i| i + 1),
-                    });
-                }
-            }
+            .map_err(|e| CommandError::io("diff", &path_b, &e))?;
 
-            hunks.push(DiffHunk {
-                header: String::new(),
-                old_start,
-                old_count,
-                new_start,
-                new_count,
-                lines,
-            });
-        }
+        let (hunks, additions, deletions) = diff_hunks(&text_a, &text_b, context_lines);
 
         // If files are identical, return a message
         if hunks.is_empty() {
@@ -129,6 +73,64 @@ impl NexusCommand for DiffCommand {
     }
 }
 
+/// Line diff of two texts as hunks with `context_lines` of context, plus
+/// the number of added and deleted lines.
+pub(crate) fn diff_hunks(text_a: &str, text_b: &str, context_lines: usize) -> (Vec<DiffHunk>, usize, usize) {
+    let diff = TextDiff::from_lines(text_a, text_b);
+
+    let mut hunks = Vec::new();
+    let mut additions: usize = 0;
+    let mut deletions: usize = 0;
+
+    for group in diff.grouped_ops(context_lines) {
+        let mut lines = Vec::new();
+
+        // Compute hunk header ranges
+        let first_op = group.first().unwrap();
+        let last_op = group.last().unwrap();
+        let old_start = first_op.old_range().start + 1;
+        let old_count = last_op.old_range().end - first_op.old_range().start;
+        let new_start = first_op.new_range().start + 1;
+        let new_count = last_op.new_range().end - first_op.new_range().start;
+
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let kind = match change.tag() {
+                    ChangeTag::Equal => DiffLineKind::Context,
+                    ChangeTag::Insert => {
+                        additions += 1;
+                        DiffLineKind::Addition
+                    }
+                    ChangeTag::Delete => {
+                        deletions += 1;
+                        DiffLineKind::Deletion
+                    }
+                };
+
+                // Strip trailing newline from content (our renderer adds them)
+                let content = change.as_str().unwrap_or("").trim_end_matches('\n').to_string();
+
+                lines.push(DiffLine {
+                    kind,
+                    content,
+                    old_lineno: change.old_index().map(|i| i + 1),
+                    new_lineno: change.new_index().map(|i| i + 1),
+                });
+            }
+        }
+
+        hunks.push(DiffHunk {
+            header: String::new(),
+            old_start,
+            old_count,
+            new_start,
+            new_count,
+            lines,
+        });
+    }
+    (hunks, additions, deletions)
+}
+
 fn resolve_path(file: &str, cwd: &PathBuf) -> PathBuf {
     let p = PathBuf::from(file);
     if p.is_absolute() {
@@ -0,0 +1,340 @@
+//! doctor - Check the environment Nexus runs in.
+//!
+//! ```text
+//! doctor      table of checks: pass, warn or fail, with a suggested fix
+//! ```
+//!
+//! The kernel checks PATH, locale, terminfo, the Claude CLI and its MCP
+//! servers, and the persistence store; the UI adds the renderer and the
+//! permission server through [`diagnostics::register_check`].
+
+use super::{CommandContext, NexusCommand};
+use crate::diagnostics::{self, CheckResult};
+use crate::persistence::{self, Store};
+use nexus_api::{CommandError, TableColumn, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long `claude --version` and `claude mcp list` may take. The latter
+/// starts every configured server, so it gets longer.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+const MCP_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct DoctorCommand;
+
+impl NexusCommand for DoctorCommand {
+    fn name(&self) -> &'static str {
+        "doctor"
+    }
+
+    fn description(&self) -> &'static str {
+        "Check PATH, locale, terminfo, Claude CLI, MCP servers and the store"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        if let Some(arg) = args.first() {
+            return Err(CommandError::usage("doctor", format!("unexpected argument '{}'", arg)).into());
+        }
+        let env = |key: &str| ctx.state.get_env(key).filter(|v| !v.is_empty());
+
+        let mut results = vec![
+            check_path(env("PATH")),
+            check_locale(env("LC_ALL"), env("LC_CTYPE"), env("LANG")),
+            check_terminfo(env("TERM"), &terminfo_dirs(env("TERMINFO"), env("TERMINFO_DIRS"), env("HOME"))),
+        ];
+        let claude = env("PATH").and_then(|path| find_executable(path, "claude"));
+        results.push(check_claude(claude.as_deref()));
+        if let Some(claude) = &claude {
+            results.push(check_mcp(claude));
+        }
+        results.push(check_store());
+        results.extend(diagnostics::run_checks());
+        Ok(results_table(results))
+    }
+}
+
+fn results_table(results: Vec<CheckResult>) -> Value {
+    let rows = results
+        .into_iter()
+        .map(|r| {
+            vec![
+                Value::String(r.name),
+                Value::String(r.status.as_str().to_string()),
+                Value::String(r.detail),
+                r.fix.map(Value::String).unwrap_or(Value::Unit),
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("check"),
+            TableColumn::new("status"),
+            TableColumn::new("detail"),
+            TableColumn::new("fix"),
+        ],
+        rows,
+    }
+}
+
+/// Empty, relative, missing and repeated PATH entries.
+fn check_path(path: Option<&str>) -> CheckResult {
+    let Some(path) = path else {
+        return CheckResult::fail("path", "PATH is not set", "set PATH in your shell rc, or [path] in ~/.nexus/config.toml");
+    };
+
+    let mut seen = HashSet::new();
+    let mut problems = Vec::new();
+    for entry in path.split(':') {
+        if entry.is_empty() {
+            problems.push("empty entry (searches the current directory)".to_string());
+        } else if !Path::new(entry).is_absolute() {
+            problems.push(format!("relative entry {}", entry));
+        } else if !seen.insert(entry) {
+            problems.push(format!("{} listed twice", entry));
+        } else if !Path::new(entry).is_dir() {
+            problems.push(format!("{} does not exist", entry));
+        }
+    }
+
+    let count = path.split(':').count();
+    if problems.is_empty() {
+        CheckResult::pass("path", format!("{} directories", count))
+    } else {
+        CheckResult::warn("path", problems.join("; "), "remove them from PATH in your shell rc or [path] in ~/.nexus/config.toml")
+    }
+}
+
+/// The effective character locale should be UTF-8, or non-ASCII output
+/// from child programs comes out mangled.
+fn check_locale(lc_all: Option<&str>, lc_ctype: Option<&str>, lang: Option<&str>) -> CheckResult {
+    let fix = "config set env.LANG en_US.UTF-8";
+    let Some((var, locale)) = [("LC_ALL", lc_all), ("LC_CTYPE", lc_ctype), ("LANG", lang)]
+        .into_iter()
+        .find_map(|(var, value)| value.map(|v| (var, v)))
+    else {
+        return CheckResult::warn("locale", "no locale set; programs fall back to C (ASCII)", fix);
+    };
+
+    let lower = locale.to_ascii_lowercase();
+    if lower.contains("utf-8") || lower.contains("utf8") {
+        CheckResult::pass("locale", format!("{}={}", var, locale))
+    } else {
+        CheckResult::warn("locale", format!("{}={} is not UTF-8", var, locale), fix)
+    }
+}
+
+/// Where ncurses looks for compiled terminal descriptions, in order.
+fn terminfo_dirs(terminfo: Option<&str>, terminfo_dirs: Option<&str>, home: Option<&str>) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = terminfo.map(PathBuf::from).into_iter().collect();
+    dirs.extend(home.map(|h| Path::new(h).join(".terminfo")));
+    dirs.extend(terminfo_dirs.into_iter().flat_map(|d| d.split(':')).filter(|d| !d.is_empty()).map(PathBuf::from));
+    dirs.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo"].map(PathBuf::from));
+    dirs
+}
+
+fn check_terminfo(term: Option<&str>, dirs: &[PathBuf]) -> CheckResult {
+    let Some(term) = term else {
+        return CheckResult::warn("terminfo", "TERM is not set", "config set env.TERM xterm-256color");
+    };
+    let Some(first) = term.chars().next() else {
+        return CheckResult::warn("terminfo", "TERM is empty", "config set env.TERM xterm-256color");
+    };
+
+    // Linux uses the first character as the subdirectory, macOS its hex code.
+    let found = dirs.iter().find_map(|dir| {
+        [first.to_string(), format!("{:x}", first as u32)]
+            .into_iter()
+            .map(|sub| dir.join(sub).join(term))
+            .find(|entry| entry.is_file())
+    });
+    match found {
+        Some(entry) => CheckResult::pass("terminfo", format!("{} ({})", term, entry.display())),
+        None => CheckResult::fail(
+            "terminfo",
+            format!("no terminfo entry for TERM={}", term),
+            "install it, or config set env.TERM xterm-256color",
+        ),
+    }
+}
+
+fn find_executable(path: &str, name: &str) -> Option<PathBuf> {
+    path.split(':').filter(|dir| !dir.is_empty()).map(|dir| Path::new(dir).join(name)).find(|p| p.is_file())
+}
+
+fn check_claude(claude: Option<&Path>) -> CheckResult {
+    let Some(claude) = claude else {
+        return CheckResult::fail(
+            "claude",
+            "claude not found on PATH; the agent is unavailable",
+            "npm install -g @anthropic-ai/claude-code",
+        );
+    };
+    match run_with_timeout(claude, &["--version"], VERSION_TIMEOUT) {
+        Ok(out) if out.success => {
+            CheckResult::pass("claude", format!("{} ({})", out.stdout.trim(), claude.display()))
+        }
+        Ok(out) => CheckResult::fail(
+            "claude",
+            format!("`claude --version` failed: {}", out.stderr.trim()),
+            "reinstall with npm install -g @anthropic-ai/claude-code",
+        ),
+        Err(e) => CheckResult::fail("claude", e, "reinstall with npm install -g @anthropic-ai/claude-code"),
+    }
+}
+
+/// MCP servers the Claude CLI is configured with, as its own health check
+/// reports them.
+fn check_mcp(claude: &Path) -> CheckResult {
+    let out = match run_with_timeout(claude, &["mcp", "list"], MCP_TIMEOUT) {
+        Ok(out) if out.success => out,
+        Ok(out) => return CheckResult::warn("mcp", format!("`claude mcp list` failed: {}", out.stderr.trim()), "run `claude mcp list` for details"),
+        Err(e) => return CheckResult::warn("mcp", e, "run `claude mcp list` for details"),
+    };
+    let (connected, failed) = parse_mcp_list(&out.stdout);
+    match (connected, failed.as_slice()) {
+        (0, []) => CheckResult::pass("mcp", "no MCP servers configured"),
+        (n, []) => CheckResult::pass("mcp", format!("{} servers connected", n)),
+        (n, failed) => CheckResult::fail(
+            "mcp",
+            format!("{} connected, unreachable: {}", n, failed.join(", ")),
+            "check the server commands with `claude mcp get <name>`",
+        ),
+    }
+}
+
+/// Count connected servers and name the failing ones in `claude mcp list`
+/// output (`name: command - ✓ Connected` / `- ✗ Failed to connect`).
+fn parse_mcp_list(output: &str) -> (usize, Vec<String>) {
+    let mut connected = 0;
+    let mut failed = Vec::new();
+    for line in output.lines() {
+        let Some((name, _)) = line.split_once(':') else { continue };
+        if line.contains("\u{2713}") {
+            connected += 1;
+        } else if line.contains("\u{2717}") {
+            failed.push(name.trim().to_string());
+        }
+    }
+    (connected, failed)
+}
+
+fn check_store() -> CheckResult {
+    let fix = "move ~/.nexus/nexus.db aside; Nexus starts a fresh one";
+    match persistence::default_db_path() {
+        Ok(path) if !path.exists() => return CheckResult::pass("store", "not created yet"),
+        Ok(_) => {}
+        Err(e) => return CheckResult::warn("store", e.to_string(), "set HOME"),
+    }
+    let problems = Store::open_default().and_then(|store| store.integrity_check());
+    match problems {
+        Ok(problems) if problems.is_empty() => CheckResult::pass("store", "integrity ok"),
+        Ok(problems) => CheckResult::fail("store", problems.join("; "), fix),
+        Err(e) => CheckResult::fail("store", format!("{:#}", e), fix),
+    }
+}
+
+struct Output {
+    success: bool,
+    stdout: String,
+    stderr: String,
+}
+
+/// Run `program`, killing it if it hasn't exited within `timeout`. Only
+/// for commands with short output: the pipes are drained after exit.
+fn run_with_timeout(program: &Path, args: &[&str], timeout: Duration) -> Result<Output, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {}: {}", program.display(), e))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < timeout => std::thread::sleep(Duration::from_millis(20)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("`{} {}` timed out after {}s", program.display(), args.join(" "), timeout.as_secs()));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    let out = child.wait_with_output().map_err(|e| e.to_string())?;
+    Ok(Output {
+        success: out.status.success(),
+        stdout: String::from_utf8_lossy(&out.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&out.stderr).into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::CheckStatus;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_path_problems_are_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().to_str().unwrap();
+        let missing = dir.path().join("missing");
+        let path = format!("{real}::bin:{real}:{}", missing.display());
+
+        let result = check_path(Some(&path));
+        assert_eq!(result.status, CheckStatus::Warn);
+        for problem in ["empty entry", "relative entry bin", "listed twice", "missing does not exist"] {
+            assert!(result.detail.contains(problem), "missing {problem:?} in {}", result.detail);
+        }
+        assert_eq!(check_path(Some(real)).status, CheckStatus::Pass);
+        assert_eq!(check_path(None).status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_locale_precedence() {
+        assert_eq!(check_locale(None, None, Some("en_US.UTF-8")).status, CheckStatus::Pass);
+        let result = check_locale(Some("C"), None, Some("en_US.UTF-8"));
+        assert_eq!(result.status, CheckStatus::Warn);
+        assert_eq!(result.detail, "LC_ALL=C is not UTF-8");
+        assert_eq!(check_locale(None, Some("de_DE.utf8"), None).status, CheckStatus::Pass);
+        assert_eq!(check_locale(None, None, None).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_terminfo_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        // macOS layout: hex subdirectory.
+        std::fs::create_dir_all(dir.path().join("78")).unwrap();
+        std::fs::write(dir.path().join("78/xterm-256color"), "").unwrap();
+        let dirs = vec![dir.path().to_path_buf()];
+
+        assert_eq!(check_terminfo(Some("xterm-256color"), &dirs).status, CheckStatus::Pass);
+        assert_eq!(check_terminfo(Some("xterm-nexus"), &dirs).status, CheckStatus::Fail);
+        assert_eq!(check_terminfo(None, &dirs).status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_parse_mcp_list() {
+        let output = "Checking MCP server health...\n\n\
+            github: npx -y @modelcontextprotocol/server-github - \u{2713} Connected\n\
+            db: /usr/local/bin/db-mcp - \u{2717} Failed to connect\n";
+        assert_eq!(parse_mcp_list(output), (1, vec!["db".to_string()]));
+    }
+
+    #[test]
+    fn test_arguments_are_rejected() {
+        let mut test_ctx = TestContext::new_default();
+        let err = DoctorCommand.execute(&["--fix".to_string()], &mut test_ctx.ctx()).unwrap_err();
+        assert!(err.to_string().contains("unexpected argument"));
+    }
+}
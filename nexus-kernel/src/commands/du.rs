@@ -78,6 +78,10 @@ impl NexusCommand for DuCommand {
         "du"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let (opts, paths) = DuOptions::parse(args);
 
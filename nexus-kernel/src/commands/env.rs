@@ -1,6 +1,10 @@
 //! Environment commands - env, printenv.
+//!
+//! `env refresh` runs the login shell again and takes on the environment
+//! it sets up (see [`crate::login_env`]), listing what changed.
 
 use super::{CommandContext, NexusCommand};
+use crate::login_env;
 use nexus_api::Value;
 
 // ============================================================================
@@ -14,7 +18,27 @@ impl NexusCommand for EnvCommand {
         "env"
     }
 
-    fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        if args.first().map(String::as_str) == Some("refresh") {
+            let captured = login_env::capture_login_shell().map_err(|e| anyhow::anyhow!("env refresh: {:#}", e))?;
+            let changed = ctx.state.apply_login_env(captured);
+            if changed.is_empty() {
+                return Ok(Value::String("Environment unchanged".to_string()));
+            }
+            let rows = changed
+                .into_iter()
+                .map(|name| {
+                    let value = ctx.state.get_env(&name).unwrap_or_default().to_string();
+                    vec![Value::String(name), Value::String(value)]
+                })
+                .collect();
+            return Ok(Value::table(vec!["name", "value"], rows));
+        }
+
         let mut entries: Vec<(&String, &String)> = ctx.state.env.iter().collect();
 
         // Sort by key for consistent output
@@ -40,6 +64,10 @@ impl NexusCommand for PrintenvCommand {
         "printenv"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if args.is_empty() {
             // Print all variables as table (like env)
@@ -247,6 +247,10 @@ impl NexusCommand for FindCommand {
         "find"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let (opts, paths) = FindOptions::parse(args);
 
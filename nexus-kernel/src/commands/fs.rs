@@ -1,7 +1,7 @@
 //! Filesystem commands - touch, mkdir, rm, rmdir, cp, mv.
 
 use super::{CommandContext, NexusCommand};
-use nexus_api::{FileOpError, FileOpInfo, FileOpKind, FileOpPhase, ShellEvent, Value};
+use nexus_api::{CommandError, FileOpError, FileOpInfo, FileOpKind, FileOpPhase, ShellEvent, Value};
 use std::fs;
 use std::path::PathBuf;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
@@ -30,7 +30,7 @@ impl NexusCommand for TouchCommand {
         }
 
         if files.is_empty() {
-            return Err(anyhow::anyhow!("touch: missing file operand"));
+            return Err(CommandError::usage("touch", "missing file operand").into());
         }
 
         let mut created = Vec::new();
@@ -87,7 +87,7 @@ impl NexusCommand for MkdirCommand {
         }
 
         if dirs.is_empty() {
-            return Err(anyhow::anyhow!("mkdir: missing operand"));
+            return Err(CommandError::usage("mkdir", "missing operand").into());
         }
 
         let mut created = Vec::new();
@@ -147,7 +147,7 @@ impl NexusCommand for RmCommand {
 
         if targets.is_empty() {
             if !force {
-                return Err(anyhow::anyhow!("rm: missing operand"));
+                return Err(CommandError::usage("rm", "missing operand").into());
             }
             return Ok(Value::Unit);
         }
@@ -263,7 +263,7 @@ impl NexusCommand for RmdirCommand {
         }
 
         if dirs.is_empty() {
-            return Err(anyhow::anyhow!("rmdir: missing operand"));
+            return Err(CommandError::usage("rmdir", "missing operand").into());
         }
 
         for dir in dirs {
@@ -318,7 +318,7 @@ impl NexusCommand for CpCommand {
         }
 
         if paths.len() < 2 {
-            return Err(anyhow::anyhow!("cp: missing destination file operand after '{}'", paths.first().unwrap_or(&String::new())));
+            return Err(CommandError::usage("cp", format!("missing destination file operand after '{}'", paths.first().unwrap_or(&String::new()))).into());
         }
 
         let dest = paths.pop().unwrap();
@@ -556,7 +556,7 @@ impl NexusCommand for MvCommand {
         }
 
         if paths.len() < 2 {
-            return Err(anyhow::anyhow!("mv: missing destination file operand after '{}'", paths.first().unwrap_or(&String::new())));
+            return Err(CommandError::usage("mv", format!("missing destination file operand after '{}'", paths.first().unwrap_or(&String::new()))).into());
         }
 
         let dest = paths.pop().unwrap();
@@ -163,6 +163,10 @@ impl NexusCommand for GrepCommand {
         "grep"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = GrepOptions::parse(args);
 
@@ -21,6 +21,10 @@ impl NexusCommand for HashCommand {
         "Compute a cryptographic hash (md5, sha256, sha512)"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut algo = "sha256";
         let mut files = Vec::new();
@@ -81,6 +85,10 @@ impl NexusCommand for Md5sumCommand {
         "Compute MD5 hash"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let files: Vec<&str> = args.iter().map(|s| s.as_str()).filter(|s| !s.starts_with('-')).collect();
 
@@ -122,6 +130,10 @@ impl NexusCommand for Sha256sumCommand {
         "Compute SHA-256 hash"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let files: Vec<&str> = args.iter().map(|s| s.as_str()).filter(|s| !s.starts_with('-')).collect();
 
@@ -64,6 +64,10 @@ impl NexusCommand for HeadCommand {
         "head"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = HeadOptions::parse(args);
 
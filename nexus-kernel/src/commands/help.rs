@@ -93,6 +93,7 @@ const BUILTINS: &[&(&str, &str)] = &[
     &("unset", "Remove a variable"),
     &("set", "Set shell options"),
     &(":", "No-op (always succeeds)"),
+    &("shopt", "Set or show glob options (dotglob, extglob, globstar, nullglob)"),
     &("test", "Evaluate conditional expression"),
     &("[", "Evaluate conditional expression"),
     &("[[", "Evaluate conditional expression"),
@@ -112,6 +113,9 @@ const BUILTINS: &[&(&str, &str)] = &[
     &("trap", "Register a signal handler"),
     &("exec", "Replace the shell with a command"),
     &("local", "Declare a local variable"),
+    &("profile", "Time the commands and functions a line runs"),
+    &("time", "Show the time, CPU and memory a line's processes used"),
+    &("debug", "Step through a script one command at a time"),
 ];
 
 /// Command catalog, organized by category.
@@ -138,9 +142,11 @@ const CATEGORIES: &[(&str, &[(&str, &str)])] = &[
     ("Text Processing", &[
         ("grep", "Search for patterns"),
         ("sort", "Sort lines or values"),
+        ("sort-by", "Sort a table or list by fields"),
         ("uniq", "Filter duplicate adjacent lines"),
         ("wc", "Count lines, words, and bytes"),
         ("diff", "Compare two files"),
+        ("lint", "Check scripts for common shell mistakes"),
     ]),
     ("Data Iteration", &[
         ("each", "Run command for each item"),
@@ -162,6 +168,7 @@ const CATEGORIES: &[(&str, &[(&str, &str)])] = &[
         ("compact", "Remove null/empty values"),
         ("reverse", "Reverse order"),
         ("enumerate", "Add index to each item"),
+        ("select", "Keep only the named columns"),
     ]),
     ("Splitting & Joining", &[
         ("lines", "Split string into lines"),
@@ -197,7 +204,7 @@ const CATEGORIES: &[(&str, &[(&str, &str)])] = &[
         ("sha256sum", "Compute SHA-256 hash"),
     ]),
     ("Environment", &[
-        ("env", "List environment variables"),
+        ("env", "List environment variables; env refresh re-reads the login shell's"),
         ("printenv", "Print an environment variable"),
         ("export", "Set environment variable"),
         ("unset", "Remove a variable"),
@@ -209,7 +216,9 @@ const CATEGORIES: &[(&str, &[(&str, &str)])] = &[
         ("fg", "Bring job to foreground"),
         ("bg", "Resume job in background"),
         ("wait", "Wait for background jobs"),
+        ("disown", "Remove jobs from the job table"),
         ("top", "Interactive process viewer"),
+        ("repl", "Persistent python or node interpreter"),
     ]),
     ("System Info", &[
         ("whoami", "Print current username"),
@@ -238,7 +247,7 @@ const CATEGORIES: &[(&str, &[(&str, &str)])] = &[
     ]),
     ("Clipboard & Desktop", &[
         ("clip", "Copy to or paste from clipboard"),
-        ("open", "Open files or URLs in default app"),
+        ("open", "Open files, URLs or . in their app; -R to reveal"),
     ]),
     ("Command Info", &[
         ("which", "Locate a command"),
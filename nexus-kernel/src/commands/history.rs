@@ -13,6 +13,10 @@ impl NexusCommand for HistoryCommand {
         "history"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
         // Parse arguments
         let mut search_query: Option<String> = None;
@@ -0,0 +1,99 @@
+//! instructions - Show the project instructions the agent is given.
+//!
+//! ```text
+//! instructions          the nearest NEXUS.md (or CLAUDE.md) and its text
+//! instructions path     just its path
+//! instructions init     create NEXUS.md in the current directory
+//! instructions edit     open it in $VISUAL / $EDITOR (in the Nexus window)
+//! ```
+
+use super::{CommandContext, NexusCommand};
+use crate::instructions::{Instructions, TEMPLATE};
+use nexus_api::{CommandError, CommandErrorKind, Value};
+
+pub struct InstructionsCommand;
+
+impl NexusCommand for InstructionsCommand {
+    fn name(&self) -> &'static str {
+        "instructions"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show the project instructions (NEXUS.md) given to the agent"
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let cwd = &ctx.state.cwd;
+        match args.first().map(String::as_str) {
+            None | Some("show") => {
+                let found = find(cwd)?;
+                Ok(Value::Record(vec![
+                    ("path".to_string(), Value::Path(found.path.clone())),
+                    ("sent".to_string(), Value::String(sent(&found).to_string())),
+                    ("text".to_string(), Value::String(found.text)),
+                ]))
+            }
+            Some("path") => Ok(Value::Path(find(cwd)?.path)),
+            Some("init") => {
+                let path = cwd.join("NEXUS.md");
+                if path.exists() {
+                    return Err(CommandError::new("instructions", CommandErrorKind::AlreadyExists, format!("{} exists", path.display())).into());
+                }
+                std::fs::write(&path, TEMPLATE)?;
+                Ok(Value::Path(path))
+            }
+            Some("edit") => Err(CommandError::new(
+                "instructions",
+                CommandErrorKind::Unsupported,
+                "edit opens an editor from the Nexus input bar",
+            )
+            .into()),
+            Some(other) => Err(CommandError::usage(
+                "instructions",
+                format!("unknown subcommand '{}' (expected show, path, init or edit)", other),
+            )
+            .into()),
+        }
+    }
+}
+
+fn find(cwd: &std::path::Path) -> Result<Instructions, CommandError> {
+    Instructions::find(cwd).ok_or_else(|| {
+        CommandError::new(
+            "instructions",
+            CommandErrorKind::NotFound,
+            "no NEXUS.md or CLAUDE.md here or above (`instructions init` creates one)",
+        )
+    })
+}
+
+/// How the agent gets the file.
+fn sent(found: &Instructions) -> &'static str {
+    if found.read_by_agent() {
+        "read by the agent CLI"
+    } else {
+        "with each new agent conversation"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_init_then_show() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut test_ctx = TestContext::new(dir.path().to_path_buf());
+        assert!(InstructionsCommand.execute(&[], &mut test_ctx.ctx()).is_err());
+
+        let created = InstructionsCommand.execute(&["init".to_string()], &mut test_ctx.ctx()).unwrap();
+        assert_eq!(created, Value::Path(dir.path().join("NEXUS.md")));
+        assert!(InstructionsCommand.execute(&["init".to_string()], &mut test_ctx.ctx()).is_err());
+
+        let Value::Record(fields) = InstructionsCommand.execute(&[], &mut test_ctx.ctx()).unwrap() else {
+            panic!("expected record");
+        };
+        assert_eq!(fields[2].1, Value::String(TEMPLATE.to_string()));
+    }
+}
@@ -17,6 +17,10 @@ impl NexusCommand for EachCommand {
         "each"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         // If a field name is given, extract that field from each item (like pluck)
         let field = args.first().map(|s| s.as_str());
@@ -100,6 +104,10 @@ impl NexusCommand for MapCommand {
         "map"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         // map <field> - extract field from each item
         let field = args.first().map(|s| s.as_str());
@@ -128,6 +136,10 @@ impl NexusCommand for FilterCommand {
         "filter"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         // Parse filter condition: field=value, field!=value, field>value, etc.
         let condition = parse_condition(args);
@@ -147,6 +159,10 @@ impl NexusCommand for WhereCommand {
         "where"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         // where is an alias for filter
         let condition = parse_condition(args);
@@ -410,6 +426,10 @@ impl NexusCommand for ReduceCommand {
         "reduce"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         // reduce sum - sum all numeric values
         // reduce min - find minimum
@@ -530,6 +550,10 @@ impl NexusCommand for AnyCommand {
         "any"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let condition = parse_condition(args);
 
@@ -568,6 +592,10 @@ impl NexusCommand for AllCommand {
         "all"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let condition = parse_condition(args);
 
@@ -606,6 +634,10 @@ impl NexusCommand for GroupByCommand {
         "group-by"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let field = args.first().map(|s| s.as_str()).unwrap_or("");
 
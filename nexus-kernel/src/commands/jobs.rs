@@ -1,11 +1,12 @@
-//! Job control commands - jobs, fg, bg, wait.
+//! Job control commands - jobs, fg, bg, wait, disown.
 //!
-//! These commands access ctx.state.jobs to manage background processes.
+//! These commands access ctx.state.jobs to manage background processes,
+//! and report every state change as a `JobStateChanged` event.
 
 use super::{CommandContext, NexusCommand};
-use crate::process::JobState;
+use crate::process::{emit_job_state, reap_jobs, JobState};
 use crate::state::ShellState;
-use nexus_api::Value;
+use nexus_api::{ShellEvent, Value};
 use nix::sys::signal::{kill, Signal};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
@@ -21,12 +22,16 @@ impl NexusCommand for JobsCommand {
         "jobs"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let show_pids = args.iter().any(|a| a == "-l" || a == "-p");
         let show_pids_only = args.iter().any(|a| a == "-p");
 
         // Update job statuses before listing
-        update_job_statuses(ctx);
+        reap_jobs(ctx.state, ctx.events);
 
         if ctx.state.jobs.is_empty() {
             return Ok(Value::table(vec!["id", "status", "pid", "command"], vec![]));
@@ -67,6 +72,9 @@ impl NexusCommand for JobsCommand {
             vec!["id", "status", "pid", "command"]
         };
 
+        // Finished jobs are reported once, then forgotten.
+        ctx.state.jobs.retain(|j| !j.is_done());
+
         Ok(Value::table(columns, rows))
     }
 }
@@ -97,21 +105,15 @@ impl NexusCommand for FgCommand {
         if job.state == JobState::Stopped {
             kill(job.pgid, Signal::SIGCONT)?;
             job.state = JobState::Running;
+            emit_job_state(ctx.events, job);
         }
 
         eprintln!("{}", job.command);
 
-        // Wait for the job to complete
+        // Wait for the job to complete or stop again
         let pgid = job.pgid;
-        let exit_code = wait_for_job(pgid)?;
-
-        // Update job state
-        if let Some(job) = ctx.state.jobs.iter_mut().find(|j| j.pgid == pgid) {
-            job.state = JobState::Done(exit_code);
-        }
-
-        // Remove completed job
-        ctx.state.jobs.retain(|j| !matches!(j.state, JobState::Done(_)));
+        let state = wait_for_job(ctx, pgid)?;
+        let exit_code = finish_wait(ctx, pgid, state);
 
         Ok(Value::Int(exit_code as i64))
     }
@@ -143,6 +145,7 @@ impl NexusCommand for BgCommand {
         if job.state == JobState::Stopped {
             kill(job.pgid, Signal::SIGCONT)?;
             job.state = JobState::Running;
+            emit_job_state(ctx.events, job);
         }
 
         eprintln!("[{}]+ {} &", job.id, job.command);
@@ -164,19 +167,14 @@ impl NexusCommand for WaitCommand {
 
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if args.is_empty() {
-            // Wait for all background jobs
+            // Wait for all running background jobs; stopped ones would never finish
             let mut last_exit = 0;
 
-            while !ctx.state.jobs.is_empty() {
-                let pgid = ctx.state.jobs[0].pgid;
-                last_exit = wait_for_job(pgid)?;
-
-                // Update job state and remove
-                if let Some(job) = ctx.state.jobs.iter_mut().find(|j| j.pgid == pgid) {
-                    job.state = JobState::Done(last_exit);
-                }
-                ctx.state.jobs.retain(|j| !matches!(j.state, JobState::Done(_)));
+            while let Some(pgid) = ctx.state.jobs.iter().find(|j| j.is_running()).map(|j| j.pgid) {
+                let state = wait_for_job(ctx, pgid)?;
+                last_exit = finish_wait(ctx, pgid, state);
             }
+            ctx.state.jobs.retain(|j| !j.is_done());
 
             return Ok(Value::Int(last_exit as i64));
         }
@@ -186,35 +184,89 @@ impl NexusCommand for WaitCommand {
 
         for arg in args {
             // Parse as job spec or PID
-            if let Some(job_id) = arg.strip_prefix('%') {
+            let pgid = if let Some(job_id) = arg.strip_prefix('%') {
                 let job_id: u32 = job_id.parse().unwrap_or(0);
-                if let Some(job) = ctx.state.jobs.iter().find(|j| j.id == job_id) {
-                    last_exit = wait_for_job(job.pgid)?;
-                } else {
-                    anyhow::bail!("wait: {}: no such job", arg);
+                match ctx.state.jobs.iter().find(|j| j.id == job_id) {
+                    Some(job) => job.pgid,
+                    None => anyhow::bail!("wait: {}: no such job", arg),
                 }
             } else if let Ok(pid) = arg.parse::<i32>() {
-                // Wait for specific PID
-                match waitpid(Pid::from_raw(pid), None) {
-                    Ok(WaitStatus::Exited(_, code)) => last_exit = code,
-                    Ok(WaitStatus::Signaled(_, sig, _)) => last_exit = 128 + sig as i32,
-                    Ok(_) => last_exit = 0,
-                    Err(_) => {
-                        // PID not found
-                        anyhow::bail!("wait: pid {} is not a child of this shell", pid);
-                    }
+                Pid::from_raw(pid)
+            } else {
+                continue;
+            };
+
+            if ctx.state.jobs.iter().any(|j| j.pgid == pgid) {
+                let state = wait_for_job(ctx, pgid)?;
+                last_exit = finish_wait(ctx, pgid, state);
+                continue;
+            }
+            // Wait for a child that isn't a job
+            match waitpid(pgid, None) {
+                Ok(WaitStatus::Exited(_, code)) => last_exit = code,
+                Ok(WaitStatus::Signaled(_, sig, _)) => last_exit = 128 + sig as i32,
+                Ok(_) => last_exit = 0,
+                Err(_) => {
+                    // PID not found
+                    anyhow::bail!("wait: pid {} is not a child of this shell", pgid);
                 }
             }
         }
 
         // Clean up completed jobs
-        update_job_statuses(ctx);
-        ctx.state.jobs.retain(|j| !matches!(j.state, JobState::Done(_)));
+        reap_jobs(ctx.state, ctx.events);
+        ctx.state.jobs.retain(|j| !j.is_done());
 
         Ok(Value::Int(last_exit as i64))
     }
 }
 
+// ============================================================================
+// disown - Remove jobs from the job table
+// ============================================================================
+
+pub struct DisownCommand;
+
+impl NexusCommand for DisownCommand {
+    fn name(&self) -> &'static str {
+        "disown"
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let all = args.iter().any(|a| a == "-a");
+        let running_only = args.iter().any(|a| a == "-r");
+        let specs: Vec<&str> = args.iter().map(String::as_str).filter(|a| !a.starts_with('-')).collect();
+
+        let ids: Vec<u32> = if all || running_only {
+            ctx.state
+                .jobs
+                .iter()
+                .filter(|j| !running_only || j.is_running())
+                .map(|j| j.id)
+                .collect()
+        } else if specs.is_empty() {
+            vec![parse_job_spec(None, ctx.state)?]
+        } else {
+            specs
+                .iter()
+                .map(|spec| parse_job_spec(Some(spec), ctx.state))
+                .collect::<anyhow::Result<_>>()?
+        };
+
+        for id in ids {
+            let Some(pos) = ctx.state.jobs.iter().position(|j| j.id == id) else { continue };
+            let job = ctx.state.jobs.remove(pos);
+            let _ = ctx.events.send(ShellEvent::JobStateChanged {
+                job_id: job.id,
+                command: job.command,
+                state: nexus_api::JobState::Disowned,
+            });
+        }
+
+        Ok(Value::Unit)
+    }
+}
+
 // ============================================================================
 // Helper functions
 // ============================================================================
@@ -276,54 +328,43 @@ fn parse_job_spec(spec: Option<&str>, state: &ShellState) -> anyhow::Result<u32>
     }
 }
 
-/// Update the status of all jobs by checking with the OS.
-fn update_job_statuses(ctx: &mut CommandContext) {
-    for job in &mut ctx.state.jobs {
-        if job.state == JobState::Running {
-            // Non-blocking check
-            match waitpid(job.pgid, Some(WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED)) {
-                Ok(WaitStatus::Exited(_, code)) => {
-                    job.state = JobState::Done(code);
-                }
-                Ok(WaitStatus::Signaled(_, sig, _)) => {
-                    job.state = JobState::Done(128 + sig as i32);
-                }
-                Ok(WaitStatus::Stopped(_, _)) => {
-                    job.state = JobState::Stopped;
-                }
-                Ok(WaitStatus::StillAlive) | Ok(_) => {
-                    // Still running
-                }
-                Err(_) => {
-                    // Process may have already exited
-                    job.state = JobState::Done(0);
-                }
-            }
-        }
+/// Wait for a job to finish or stop and return its new state. A job that
+/// was already reaped keeps the exit code it was reaped with.
+fn wait_for_job(ctx: &CommandContext, pgid: Pid) -> anyhow::Result<JobState> {
+    if let Some(job) = ctx.state.jobs.iter().find(|j| j.pgid == pgid && j.is_done()) {
+        return Ok(job.state);
     }
-}
-
-/// Wait for a job to complete and return its exit code.
-fn wait_for_job(pgid: Pid) -> anyhow::Result<i32> {
     loop {
         match waitpid(pgid, Some(WaitPidFlag::WUNTRACED)) {
-            Ok(WaitStatus::Exited(_, code)) => return Ok(code),
-            Ok(WaitStatus::Signaled(_, sig, _)) => return Ok(128 + sig as i32),
-            Ok(WaitStatus::Stopped(_, _)) => {
-                // Job was stopped
-                return Ok(128 + 19); // SIGSTOP
-            }
-            Ok(WaitStatus::Continued(_)) => continue,
+            Ok(WaitStatus::Exited(_, code)) => return Ok(JobState::Done(code)),
+            Ok(WaitStatus::Signaled(_, sig, _)) => return Ok(JobState::Done(128 + sig as i32)),
+            Ok(WaitStatus::Stopped(_, _)) => return Ok(JobState::Stopped),
             Ok(_) => continue,
             Err(nix::errno::Errno::ECHILD) => {
                 // No child process
-                return Ok(0);
+                return Ok(JobState::Done(0));
             }
             Err(e) => return Err(e.into()),
         }
     }
 }
 
+/// Record the state a wait ended in, report it, and return the exit code
+/// the shell sees (128 + SIGTSTP for a job that stopped). Finished jobs
+/// leave the table.
+fn finish_wait(ctx: &mut CommandContext, pgid: Pid, state: JobState) -> i32 {
+    if let Some(job) = ctx.state.jobs.iter_mut().find(|j| j.pgid == pgid) {
+        job.state = state;
+        emit_job_state(ctx.events, job);
+    }
+    ctx.state.jobs.retain(|j| !j.is_done());
+    match state {
+        JobState::Done(code) => code,
+        JobState::Stopped => 128 + Signal::SIGTSTP as i32,
+        JobState::Running => 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -16,6 +16,10 @@ impl NexusCommand for FromJsonCommand {
         "from-json"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             let text = stdin_value.to_text();
@@ -46,7 +50,7 @@ fn parse_json(text: &str) -> anyhow::Result<Value> {
     Ok(json_to_value(json))
 }
 
-fn json_to_value(json: serde_json::Value) -> Value {
+pub(crate) fn json_to_value(json: serde_json::Value) -> Value {
     match json {
         serde_json::Value::Null => Value::Unit,
         serde_json::Value::Bool(b) => Value::Bool(b),
@@ -101,7 +105,7 @@ impl NexusCommand for ToJsonCommand {
     }
 }
 
-fn value_to_json(value: &Value) -> serde_json::Value {
+pub(crate) fn value_to_json(value: &Value) -> serde_json::Value {
     #[allow(unreachable_patterns)]
     match value {
         Value::Unit => serde_json::Value::Null,
@@ -311,6 +315,10 @@ impl NexusCommand for GetCommand {
         "get"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let key = args.first().map(|s| s.as_str()).unwrap_or("");
 
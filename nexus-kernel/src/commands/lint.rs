@@ -0,0 +1,92 @@
+//! lint - Check scripts for common shell mistakes.
+//!
+//! ```text
+//! lint <file>...      table of findings: file, line, column, severity, rule, message
+//! ```
+//!
+//! These are the checks the input bar runs as a line is typed; see
+//! [`crate::lint`] for the rules and `[lint]` in the config for their
+//! severities.
+
+use super::{CommandContext, NexusCommand};
+use crate::lint;
+use crate::Parser;
+use nexus_api::{CommandError, CommandErrorKind, TableColumn, Value};
+
+pub struct LintCommand;
+
+impl NexusCommand for LintCommand {
+    fn name(&self) -> &'static str {
+        "lint"
+    }
+
+    fn description(&self) -> &'static str {
+        "Check scripts for unquoted expansions, risky rm paths and other mistakes"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        if args.is_empty() {
+            return Err(CommandError::usage("lint", "usage: lint <file>...").into());
+        }
+        let mut parser = Parser::new()?;
+        let mut rows = Vec::new();
+        for file in args {
+            let path = ctx.state.cwd.join(file);
+            let source = std::fs::read_to_string(&path).map_err(|e| CommandError::io("lint", &path, &e))?;
+            let lints = lint::lint(&mut parser, &source, &ctx.state.config)
+                .map_err(|e| CommandError::new("lint", CommandErrorKind::InvalidInput, e.to_string()).with_path(&path))?;
+            for found in lints {
+                let (line, column) = line_column(&source, found.range.start);
+                rows.push(vec![
+                    Value::String(file.clone()),
+                    Value::Int(line as i64),
+                    Value::Int(column as i64),
+                    Value::String(found.severity.as_str().to_string()),
+                    Value::String(found.rule.name().to_string()),
+                    Value::String(found.message),
+                ]);
+            }
+        }
+        Ok(Value::Table {
+            columns: vec![
+                TableColumn::new("file"),
+                TableColumn::new("line"),
+                TableColumn::new("column"),
+                TableColumn::new("severity"),
+                TableColumn::new("rule"),
+                TableColumn::new("message"),
+            ],
+            rows,
+        })
+    }
+}
+
+/// 1-based line and column of byte `offset` in `source`.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_lint_reports_line_and_column() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("build.sh"), "#!/bin/sh\nset -e\nrm -rf \"$OUT/\"*\n").unwrap();
+        let mut test_ctx = TestContext::new(dir.path().to_path_buf());
+        let value = LintCommand.execute(&["build.sh".to_string()], &mut test_ctx.ctx()).unwrap();
+        let Value::Table { rows, .. } = value else {
+            panic!("expected table");
+        };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][1..5], [Value::Int(3), Value::Int(8), Value::String("error".into()), Value::String("rm-variable-path".into())]);
+    }
+}
@@ -79,6 +79,10 @@ impl NexusCommand for LsCommand {
         "ls"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = LsOptions::parse(args)?;
 
@@ -14,6 +14,10 @@ impl NexusCommand for SumCommand {
         "sum"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(sum_value(stdin_value));
@@ -74,6 +78,10 @@ impl NexusCommand for AvgCommand {
         "avg"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(avg_value(stdin_value));
@@ -135,6 +143,10 @@ impl NexusCommand for MinCommand {
         "min"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(min_value(stdin_value));
@@ -183,6 +195,10 @@ impl NexusCommand for MaxCommand {
         "max"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(max_value(stdin_value));
@@ -231,6 +247,10 @@ impl NexusCommand for CountCommand {
         "count"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(count_value(stdin_value));
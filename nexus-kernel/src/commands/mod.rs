@@ -8,9 +8,14 @@ mod basic;
 mod cat;
 mod chmod;
 mod clip;
+mod config;
+#[cfg(feature = "conformance")]
+mod conformance;
 mod date;
+mod diagnostics;
 mod df;
-mod diff;
+pub(crate) mod diff;
+mod doctor;
 mod du;
 mod env;
 mod find;
@@ -20,29 +25,37 @@ mod hash;
 mod head;
 mod help;
 mod history;
+mod instructions;
 mod iterators;
 mod jobs;
-mod json;
+pub(crate) mod json;
 mod less;
 mod link;
+mod lint;
 mod ls;
 mod man;
 mod math;
 mod open;
+mod output;
 mod path;
+mod plugin;
 mod prev;
 mod printf;
 pub(crate) mod ps;
 mod registry;
+mod repl;
 mod select;
 mod seq;
 mod shuf;
 mod signal;
 mod sort;
+mod schedule;
 mod split;
+mod storage;
 mod system;
 mod tail;
 mod top;
+mod transfer;
 mod tree;
 mod unicode_stress;
 mod tee;
@@ -51,24 +64,27 @@ mod wc;
 mod which;
 
 #[cfg(test)]
-mod test_utils;
+pub(crate) mod test_utils;
 
+pub use output::OutputMode;
 pub use registry::CommandRegistry;
 
 use crate::ShellState;
-use nexus_api::{BlockId, ShellEvent, Value};
-use tokio::sync::broadcast::Sender;
+use nexus_api::{BlockId, Value};
+use crate::replay::EventSender;
 
 /// Context passed to commands during execution.
 pub struct CommandContext<'a> {
     /// The current shell state (env, cwd, etc.)
     pub state: &'a mut ShellState,
     /// Event channel for streaming output
-    pub events: &'a Sender<ShellEvent>,
+    pub events: &'a EventSender,
     /// Block ID for this command invocation
     pub block_id: BlockId,
     /// Piped input from previous command (if any)
     pub stdin: Option<Value>,
+    /// How the result will be emitted; the evaluator applies it.
+    pub output: OutputMode,
 }
 
 // ---- Cancellation registry for long-running commands ----
@@ -121,6 +137,13 @@ pub trait NexusCommand: Send + Sync {
         ""
     }
 
+    /// Whether the command takes the global `--output MODE` flag (see
+    /// [`OutputMode`]). Commands that print text of their own (`echo`,
+    /// `printf`) leave it off, so `--output` reaches them as an argument.
+    fn structured_output(&self) -> bool {
+        false
+    }
+
     /// Execute the command with the given arguments.
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value>;
 }
@@ -1,12 +1,25 @@
 //! `open` — open files/URLs in the default application.
+//!
+//! `open FILE...` uses the application configured for the file's extension
+//! under `[open]`, or the system default; `-a APP` picks one explicitly.
+//! URLs go to the browser, `open .` opens the working directory and
+//! `open -R FILE` shows the file selected in the file manager.
 
 use super::{CommandContext, NexusCommand};
-use nexus_api::Value;
+use crate::opener;
+use nexus_api::{CommandError, CommandErrorKind, Value};
 use std::path::PathBuf;
-use std::process::Command;
 
 pub struct OpenCommand;
 
+/// What an `open` command line asks for.
+#[derive(Debug, PartialEq)]
+struct OpenArgs {
+    app: Option<String>,
+    reveal: bool,
+    targets: Vec<String>,
+}
+
 impl NexusCommand for OpenCommand {
     fn name(&self) -> &'static str {
         "open"
@@ -17,65 +30,71 @@ impl NexusCommand for OpenCommand {
     }
 
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
-        let mut targets = Vec::new();
-        let mut app: Option<&str> = None;
-        let mut iter = args.iter();
-
-        while let Some(arg) = iter.next() {
-            match arg.as_str() {
-                "-a" => {
-                    app = iter.next().map(|s| s.as_str());
+        let args = parse_args(args)?;
+
+        // Check every target before launching anything.
+        let mut resolved = Vec::new();
+        for target in &args.targets {
+            if opener::is_url(target) {
+                if args.reveal {
+                    return Err(CommandError::usage("open", format!("cannot reveal a URL: {}", target)).into());
                 }
-                s if !s.starts_with('-') => targets.push(s.to_string()),
-                _ => {}
+                resolved.push((target.clone(), None));
+                continue;
             }
+            let path = ctx.state.cwd.join(target);
+            if let Err(e) = std::fs::symlink_metadata(&path) {
+                return Err(CommandError::io("open", PathBuf::from(target), &e).into());
+            }
+            resolved.push((path.to_string_lossy().into_owned(), Some(path)));
         }
 
-        if targets.is_empty() {
-            anyhow::bail!("open: missing file operand");
-        }
-
-        for target in &targets {
-            // Resolve relative paths (but leave URLs as-is)
-            let is_url = target.contains("://");
-            let resolved = if is_url {
-                target.clone()
-            } else {
-                let p = PathBuf::from(target);
-                if p.is_absolute() {
-                    target.clone()
-                } else {
-                    ctx.state.cwd.join(target).to_string_lossy().to_string()
+        for (target, path) in resolved {
+            let result = match &path {
+                Some(path) if args.reveal => opener::reveal(path),
+                _ => {
+                    let configured = path.as_deref().and_then(|p| ctx.state.config.opener_for(p));
+                    opener::open(&target, args.app.as_deref().or(configured))
                 }
             };
-
-            let mut cmd = open_command();
-            if let Some(a) = app {
-                cmd.args(["-a", a]);
-            }
-            cmd.arg(&resolved);
-
-            cmd.spawn()
-                .map_err(|e| anyhow::anyhow!("open: failed to launch: {}", e))?;
+            result.map_err(|e| launch_error(&target, &e))?;
         }
 
         Ok(Value::Unit)
     }
 }
 
-#[cfg(target_os = "macos")]
-fn open_command() -> Command {
-    Command::new("open")
-}
-
-#[cfg(target_os = "linux")]
-fn open_command() -> Command {
-    Command::new("xdg-open")
+fn parse_args(args: &[String]) -> Result<OpenArgs, CommandError> {
+    let mut parsed = OpenArgs { app: None, reveal: false, targets: Vec::new() };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-a" => {
+                let app = iter.next().ok_or_else(|| CommandError::usage("open", "-a needs an application"))?;
+                parsed.app = Some(app.clone());
+            }
+            "-R" => parsed.reveal = true,
+            "--" => parsed.targets.extend(iter.by_ref().cloned()),
+            s if s.starts_with('-') && s.len() > 1 => {
+                return Err(CommandError::usage("open", format!("unknown option '{}'", s)));
+            }
+            s => parsed.targets.push(s.to_string()),
+        }
+    }
+    if parsed.targets.is_empty() {
+        return Err(CommandError::usage("open", "missing file operand"));
+    }
+    Ok(parsed)
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "linux")))]
-fn open_command() -> Command {
-    Command::new("open")
+fn launch_error(target: &str, err: &std::io::Error) -> CommandError {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        let error = CommandError::new("open", CommandErrorKind::Unsupported, "no application to open it with");
+        #[cfg(target_os = "linux")]
+        let error = error.with_suggestion("install xdg-utils, or pass one with -a");
+        return error.with_path(target);
+    }
+    CommandError::new("open", CommandErrorKind::Io, format!("failed to launch: {}", err)).with_path(target)
 }
 
 #[cfg(test)]
@@ -83,6 +102,10 @@ mod tests {
     use super::*;
     use crate::commands::test_utils::test_helpers::TestContext;
 
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
     #[test]
     fn test_open_missing_operand() {
         let mut test_ctx = TestContext::new_default();
@@ -90,4 +113,33 @@ mod tests {
         let result = cmd.execute(&[], &mut test_ctx.ctx());
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_parse_args() {
+        let parsed = parse_args(&args(&["-R", "-a", "Preview", "a.pdf", "--", "-b.pdf"])).unwrap();
+        assert_eq!(
+            parsed,
+            OpenArgs { app: Some("Preview".into()), reveal: true, targets: vec!["a.pdf".into(), "-b.pdf".into()] }
+        );
+        assert_eq!(parse_args(&args(&["."])).unwrap().targets, ["."]);
+        assert!(parse_args(&args(&["-a"])).is_err());
+        assert!(parse_args(&args(&["-x", "a"])).is_err());
+    }
+
+    #[test]
+    fn test_open_reports_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut test_ctx = TestContext::new(dir.path().to_path_buf());
+        let err = OpenCommand.execute(&args(&["nope.txt"]), &mut test_ctx.ctx()).unwrap_err();
+        let err = err.downcast_ref::<CommandError>().unwrap();
+        assert_eq!(err.kind, CommandErrorKind::NotFound);
+        assert_eq!(err.path.as_deref(), Some(std::path::Path::new("nope.txt")));
+    }
+
+    #[test]
+    fn test_open_cannot_reveal_url() {
+        let mut test_ctx = TestContext::new_default();
+        let err = OpenCommand.execute(&args(&["-R", "https://example.com"]), &mut test_ctx.ctx()).unwrap_err();
+        assert_eq!(err.downcast_ref::<CommandError>().unwrap().kind, CommandErrorKind::Usage);
+    }
 }
@@ -0,0 +1,143 @@
+//! Output mode for native commands: the structured `Value` for the UI, or
+//! canonical JSON text for scripts and headless use.
+//!
+//! Native commands that declare [`NexusCommand::structured_output`] accept a
+//! global `--output json` (or `--output=json`), removed before the command
+//! sees its arguments; `NEXUS_OUTPUT=json` in the environment sets it for
+//! every command whose result leaves the kernel.
+
+use super::NexusCommand;
+use crate::ShellState;
+use nexus_api::{CommandError, Value};
+
+/// Environment variable selecting the default output mode.
+pub const OUTPUT_ENV: &str = "NEXUS_OUTPUT";
+
+/// How a native command's result is emitted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// The structured value, rendered by the UI.
+    #[default]
+    Value,
+    /// Compact JSON with object keys sorted.
+    Json,
+}
+
+impl OutputMode {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "value" => Some(Self::Value),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Remove a global `--output MODE` from `args`, stopping at `--`.
+    /// An `--output` followed by something other than a mode is left for
+    /// the command itself.
+    pub fn take_flag(args: &mut Vec<String>) -> Option<Self> {
+        let mut i = 0;
+        while i < args.len() && args[i] != "--" {
+            if let Some(mode) = args[i].strip_prefix("--output=").and_then(Self::parse) {
+                args.remove(i);
+                return Some(mode);
+            }
+            if args[i] == "--output"
+                && let Some(mode) = args.get(i + 1).and_then(|s| Self::parse(s))
+            {
+                args.drain(i..i + 2);
+                return Some(mode);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// [`take_flag`](Self::take_flag) for `cmd`. Commands that don't
+    /// declare structured output keep their arguments as they are.
+    pub fn take_flag_for(cmd: &dyn NexusCommand, args: &mut Vec<String>) -> Option<Self> {
+        if cmd.structured_output() { Self::take_flag(args) } else { None }
+    }
+
+    /// The mode `NEXUS_OUTPUT` selects, or an error naming a bad value.
+    pub fn from_env(state: &ShellState) -> Result<Self, CommandError> {
+        match state.get_var(OUTPUT_ENV) {
+            None | Some("") => Ok(Self::Value),
+            Some(s) => Self::parse(s)
+                .ok_or_else(|| CommandError::usage(OUTPUT_ENV, format!("unknown output mode '{}' (value, json)", s))),
+        }
+    }
+
+    /// `value` as this mode emits it. `Unit` stays `Unit` so commands with
+    /// nothing to say print nothing.
+    pub fn render(self, value: Value) -> Value {
+        match (self, value) {
+            (Self::Json, Value::Unit) => Value::Unit,
+            (Self::Json, value) => {
+                let json = super::json::value_to_json(&value);
+                Value::String(serde_json::to_string(&json).unwrap_or_default())
+            }
+            (Self::Value, value) => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_take_flag() {
+        let mut a = args(&["-l", "--output", "json", "src"]);
+        assert_eq!(OutputMode::take_flag(&mut a), Some(OutputMode::Json));
+        assert_eq!(a, ["-l", "src"]);
+
+        let mut a = args(&["--output=value"]);
+        assert_eq!(OutputMode::take_flag(&mut a), Some(OutputMode::Value));
+        assert!(a.is_empty());
+
+        let mut a = args(&["--output", "out.txt"]);
+        assert_eq!(OutputMode::take_flag(&mut a), None);
+        assert_eq!(a, ["--output", "out.txt"]);
+
+        let mut a = args(&["--", "--output", "json"]);
+        assert_eq!(OutputMode::take_flag(&mut a), None);
+        assert_eq!(a.len(), 3);
+    }
+
+    #[test]
+    fn test_take_flag_only_for_structured_commands() {
+        use crate::commands::basic::EchoCommand;
+        use crate::commands::seq::SeqCommand;
+
+        let mut a = args(&["hi", "--output", "json"]);
+        assert_eq!(OutputMode::take_flag_for(&EchoCommand, &mut a), None);
+        assert_eq!(a, ["hi", "--output", "json"]);
+
+        let mut a = args(&["3", "--output", "json"]);
+        assert_eq!(OutputMode::take_flag_for(&SeqCommand, &mut a), Some(OutputMode::Json));
+        assert_eq!(a, ["3"]);
+    }
+
+    #[test]
+    fn test_render_json_is_canonical() {
+        let value = Value::Record(vec![("b".into(), Value::Int(2)), ("a".into(), Value::List(vec![Value::Bool(true)]))]);
+        assert_eq!(OutputMode::Json.render(value), Value::String(r#"{"a":[true],"b":2}"#.into()));
+        assert_eq!(OutputMode::Json.render(Value::Unit), Value::Unit);
+        assert_eq!(OutputMode::Value.render(Value::Int(1)), Value::Int(1));
+    }
+
+    #[test]
+    fn test_from_env() {
+        let mut state = ShellState::from_cwd(std::env::temp_dir());
+        assert_eq!(OutputMode::from_env(&state).unwrap(), OutputMode::Value);
+        state.env.insert(OUTPUT_ENV.into(), "json".into());
+        assert_eq!(OutputMode::from_env(&state).unwrap(), OutputMode::Json);
+        state.env.insert(OUTPUT_ENV.into(), "yaml".into());
+        assert!(OutputMode::from_env(&state).is_err());
+    }
+}
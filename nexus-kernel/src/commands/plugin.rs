@@ -0,0 +1,123 @@
+//! plugin - List, enable and reload wasm plugins.
+//!
+//! ```text
+//! plugin                  list discovered plugins
+//! plugin enable <name>    grant the plugin its capabilities and load it
+//! plugin disable <name>   unload the plugin and remember the choice
+//! plugin reload [name]    rescan the plugin directories and recompile modules
+//! ```
+
+use super::{CommandContext, NexusCommand};
+use crate::plugins::{PluginHost, PluginInfo, PluginStatus};
+use nexus_api::{CommandError, TableColumn, Value};
+use std::sync::Arc;
+
+pub struct PluginCommand {
+    host: Arc<PluginHost>,
+}
+
+impl PluginCommand {
+    pub fn new(host: Arc<PluginHost>) -> Self {
+        Self { host }
+    }
+}
+
+impl NexusCommand for PluginCommand {
+    fn name(&self) -> &'static str {
+        "plugin"
+    }
+
+    fn description(&self) -> &'static str {
+        "List, enable and reload wasm command plugins"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let name = args.get(1).map(String::as_str);
+        match (args.first().map(String::as_str), name) {
+            (None | Some("list"), _) => Ok(plugins_table(&self.host.list())),
+            (Some("enable"), Some(name)) => {
+                let info = self.host.enable(name)?;
+                if let PluginStatus::Failed(reason) = &info.status {
+                    anyhow::bail!("plugin: {} is enabled but failed to load: {}", name, reason);
+                }
+                Ok(plugins_table(&[info]))
+            }
+            (Some("disable"), Some(name)) => Ok(plugins_table(&[self.host.disable(name)?])),
+            (Some("enable" | "disable"), None) => Err(CommandError::usage("plugin", "missing plugin name").into()),
+            (Some("reload"), name) => {
+                self.host.reload(name)?;
+                Ok(plugins_table(&self.host.list()))
+            }
+            (Some(other), _) => Err(CommandError::usage(
+                "plugin",
+                format!("unknown subcommand '{}' (expected list, enable, disable or reload)", other),
+            )
+            .into()),
+        }
+    }
+}
+
+fn plugins_table(plugins: &[PluginInfo]) -> Value {
+    let rows = plugins
+        .iter()
+        .map(|p| {
+            let manifest = p.manifest.as_ref();
+            let status = match &p.status {
+                PluginStatus::Disabled => "disabled".to_string(),
+                PluginStatus::Loaded => "loaded".to_string(),
+                PluginStatus::Failed(reason) => format!("failed: {}", reason),
+            };
+            let commands = manifest
+                .map(|m| m.commands.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", "))
+                .unwrap_or_default();
+            vec![
+                Value::String(p.name.clone()),
+                Value::String(manifest.map(|m| m.version.clone()).unwrap_or_default()),
+                Value::String(status),
+                Value::String(commands),
+                Value::String(manifest.map(|m| m.capabilities.summary()).unwrap_or_default()),
+                Value::String(manifest.map(|m| m.description.clone()).unwrap_or_default()),
+            ]
+        })
+        .collect();
+
+    Value::Table {
+        columns: vec![
+            TableColumn::new("name"),
+            TableColumn::new("version"),
+            TableColumn::new("status"),
+            TableColumn::new("commands"),
+            TableColumn::new("capabilities"),
+            TableColumn::new("description"),
+        ],
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_list_without_plugin_dir_is_empty() {
+        let mut test_ctx = TestContext::new_default();
+        let cmd = PluginCommand::new(Arc::new(PluginHost::empty()));
+        let Value::Table { rows, .. } = cmd.execute(&[], &mut test_ctx.ctx()).unwrap() else {
+            panic!("expected table");
+        };
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn test_enable_requires_a_name() {
+        let mut test_ctx = TestContext::new_default();
+        let cmd = PluginCommand::new(Arc::new(PluginHost::empty()));
+        let err = cmd.execute(&["enable".to_string()], &mut test_ctx.ctx()).unwrap_err();
+        assert!(err.to_string().contains("missing plugin name"));
+    }
+}
@@ -8,11 +8,13 @@
 //!   _1   # most recent output (same as _)
 //!   _2   # second most recent
 //!   _3   # third most recent
+//!   _ 7  # seventh most recent (any index; `_7 | cmd` is rewritten to this)
 
 use super::{CommandContext, NexusCommand};
 use nexus_api::Value;
 
-/// The `_` command - outputs the last stored value.
+/// The `_` command - outputs the last stored value, or with an index
+/// argument (`_ 5`) the stored value at that index.
 pub struct PrevCommand;
 
 impl NexusCommand for PrevCommand {
@@ -20,11 +22,25 @@ impl NexusCommand for PrevCommand {
         "_"
     }
 
-    fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let Some(arg) = args.first() else {
+            return ctx
+                .state
+                .get_last_output()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("no previous output"));
+        };
+        let index: usize = arg
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid output index: {}", arg))?;
         ctx.state
-            .get_last_output()
+            .get_output_by_index(index)
             .cloned()
-            .ok_or_else(|| anyhow::anyhow!("no previous output"))
+            .ok_or_else(|| anyhow::anyhow!("no output at index {}", index))
     }
 }
 
@@ -36,6 +52,10 @@ impl NexusCommand for Prev1Command {
         "_1"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         ctx.state
             .get_output_by_index(1)
@@ -52,6 +72,10 @@ impl NexusCommand for Prev2Command {
         "_2"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         ctx.state
             .get_output_by_index(2)
@@ -68,6 +92,10 @@ impl NexusCommand for Prev3Command {
         "_3"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         ctx.state
             .get_output_by_index(3)
@@ -84,6 +112,10 @@ impl NexusCommand for OutputsCommand {
         "outputs"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let limit: usize = args
             .first()
@@ -189,6 +221,20 @@ mod tests {
         assert_eq!(result3, Value::Int(1));
     }
 
+    #[test]
+    fn test_prev_with_index_argument() {
+        let mut test_ctx = TestContext::new_default();
+        for i in 1..=5 {
+            test_ctx.ctx().state.store_output(nexus_api::BlockId(i), format!("cmd{}", i), Value::Int(i as i64));
+        }
+
+        let cmd = PrevCommand;
+        let result = cmd.execute(&["5".to_string()], &mut test_ctx.ctx()).unwrap();
+        assert_eq!(result, Value::Int(1));
+        assert!(cmd.execute(&["6".to_string()], &mut test_ctx.ctx()).is_err());
+        assert!(cmd.execute(&["x".to_string()], &mut test_ctx.ctx()).is_err());
+    }
+
     #[test]
     fn test_outputs_command() {
         let mut test_ctx = TestContext::new_default();
@@ -262,6 +262,10 @@ impl NexusCommand for PsCommand {
         "ps"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = PsOptions::parse(args);
         let mut processes = list_processes()?;
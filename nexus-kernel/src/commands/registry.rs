@@ -1,7 +1,9 @@
 //! Command registry for looking up in-process commands.
 
 use super::NexusCommand;
+use crate::plugins::PluginHost;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 // Import all commands
 use super::base64_cmd::Base64Command;
@@ -12,9 +14,14 @@ use super::basic::{
 use super::cat::CatCommand;
 use super::chmod::ChmodCommand;
 use super::clip::ClipCommand;
+use super::config::ConfigCommand;
+#[cfg(feature = "conformance")]
+use super::conformance::ConformanceCommand;
 use super::date::DateCommand;
+use super::diagnostics::DiagnosticsCommand;
 use super::df::DfCommand;
 use super::diff::DiffCommand;
+use super::doctor::DoctorCommand;
 use super::du::DuCommand;
 use super::env::{EnvCommand, ExportCommand, PrintenvCommand, UnsetCommand};
 use super::find::FindCommand;
@@ -25,35 +32,42 @@ use super::less::LessCommand;
 use super::head::HeadCommand;
 use super::help::HelpCommand;
 use super::history::{FcCommand, HistoryCommand};
+use super::instructions::InstructionsCommand;
 use super::iterators::{
     AllCommand, AnyCommand, EachCommand, FilterCommand, GroupByCommand, MapCommand, ReduceCommand,
     WhereCommand,
 };
-use super::jobs::{BgCommand, FgCommand, JobsCommand, WaitCommand};
+use super::jobs::{BgCommand, DisownCommand, FgCommand, JobsCommand, WaitCommand};
 use super::json::{FromJsonCommand, GetCommand, ToJsonCommand};
 use super::link::LnCommand;
+use super::lint::LintCommand;
 use super::ls::LsCommand;
 use super::man::ManCommand;
 use super::math::{AvgCommand, CountCommand, MaxCommand, MinCommand, SumCommand};
 use super::open::OpenCommand;
+use super::plugin::PluginCommand;
 use super::path::{BasenameCommand, DirnameCommand, ExtnameCommand, RealpathCommand, StemCommand};
 use super::prev::{OutputsCommand, Prev1Command, Prev2Command, Prev3Command, PrevCommand};
 use super::printf::PrintfCommand;
 use super::ps::PsCommand;
 use super::select::{
     CompactCommand, EnumerateCommand, FirstCommand, FlattenCommand, LastCommand, NthCommand,
-    ReverseCommand, SkipCommand, TakeCommand,
+    ReverseCommand, SelectCommand, SkipCommand, TakeCommand,
 };
+use super::repl::ReplCommand;
+use super::schedule::ScheduleCommand;
 use super::seq::SeqCommand;
 use super::shuf::ShufCommand;
 use super::signal::KillCommand;
-use super::sort::SortCommand;
+use super::sort::{SortByCommand, SortCommand};
 use super::system::{TtyCommand, UmaskCommand, UnameCommand};
 use super::split::{
     BytesCommand, CharsCommand, JoinCommand, LinesCommand, SplitCommand, WordsCommand,
 };
+use super::storage::StorageCommand;
 use super::tail::TailCommand;
 use super::tee::TeeCommand;
+use super::transfer::{PullCommand, PushCommand};
 use super::top::TopCommand;
 use super::tree::TreeCommand;
 use super::unicode_stress::UnicodeStressCommand;
@@ -61,16 +75,27 @@ use super::uniq::UniqCommand;
 use super::wc::WcCommand;
 use super::which::{TypeCommand, WhichCommand};
 
-/// Registry of all available in-process commands.
+/// Registry of all available in-process commands. Cheap to clone: commands
+/// and plugins are shared.
+#[derive(Clone)]
 pub struct CommandRegistry {
-    commands: HashMap<&'static str, Box<dyn NexusCommand>>,
+    commands: HashMap<&'static str, Arc<dyn NexusCommand>>,
+    /// Commands from wasm plugins, consulted after the built-ins.
+    plugins: Arc<PluginHost>,
 }
 
 impl CommandRegistry {
-    /// Create a new registry with all built-in commands registered.
+    /// Create a new registry with all built-in commands registered and no
+    /// plugins.
     pub fn new() -> Self {
+        Self::with_plugins(Arc::new(PluginHost::empty()))
+    }
+
+    /// Create a registry whose plugin commands come from `plugins`.
+    pub fn with_plugins(plugins: Arc<PluginHost>) -> Self {
         let mut registry = Self {
             commands: HashMap::new(),
+            plugins: plugins.clone(),
         };
 
         // Basic commands
@@ -92,6 +117,7 @@ impl CommandRegistry {
         // Text processing
         registry.register(GrepCommand);
         registry.register(SortCommand);
+        registry.register(SortByCommand);
         registry.register(UniqCommand);
         registry.register(WcCommand);
 
@@ -127,6 +153,7 @@ impl CommandRegistry {
         registry.register(CompactCommand);
         registry.register(ReverseCommand);
         registry.register(EnumerateCommand);
+        registry.register(SelectCommand);
 
         // Iterators (next-gen structured data commands)
         registry.register(EachCommand);
@@ -161,6 +188,7 @@ impl CommandRegistry {
         registry.register(FgCommand);
         registry.register(BgCommand);
         registry.register(WaitCommand);
+        registry.register(DisownCommand);
         registry.register(KillCommand);
 
         // File finding
@@ -175,6 +203,8 @@ impl CommandRegistry {
         registry.register(MvCommand);
         registry.register(ChmodCommand);
         registry.register(LnCommand);
+        registry.register(PushCommand); // push - upload over ssh with progress
+        registry.register(PullCommand); // pull - download over ssh with progress
 
         // I/O
         registry.register(TeeCommand);
@@ -183,6 +213,8 @@ impl CommandRegistry {
         registry.register(TtyCommand);
         registry.register(UnameCommand);
         registry.register(UmaskCommand);
+        registry.register(DiagnosticsCommand);
+        registry.register(DoctorCommand);
 
         // Disk usage
         registry.register(DuCommand);
@@ -204,6 +236,9 @@ impl CommandRegistry {
         registry.register(Prev2Command);   // _2 - second most recent
         registry.register(Prev3Command);   // _3 - third most recent
         registry.register(OutputsCommand); // outputs - list recent outputs
+        registry.register(StorageCommand); // storage - session disk usage & cleanup
+        registry.register(ScheduleCommand); // schedule - recurring commands
+        registry.register(ReplCommand); // repl - persistent python/node interpreter
 
         // Interactive viewers
         registry.register(LessCommand);
@@ -224,33 +259,52 @@ impl CommandRegistry {
         // File comparison
         registry.register(DiffCommand);
 
+        // Static checks
+        registry.register(LintCommand);
+
         // Formatted output
         registry.register(PrintfCommand);
 
         // Testing
         registry.register(UnicodeStressCommand);
+        #[cfg(feature = "conformance")]
+        registry.register(ConformanceCommand);
+
+        // Configuration & plugins
+        registry.register(ConfigCommand);
+        registry.register(InstructionsCommand);
+        registry.register(PluginCommand::new(plugins));
 
         registry
     }
 
     /// Register a command.
-    fn register<C: NexusCommand + 'static>(&mut self, cmd: C) {
-        self.commands.insert(cmd.name(), Box::new(cmd));
+    pub(crate) fn register<C: NexusCommand + 'static>(&mut self, cmd: C) {
+        self.commands.insert(cmd.name(), Arc::new(cmd));
     }
 
     /// Look up a command by name.
-    pub fn get(&self, name: &str) -> Option<&dyn NexusCommand> {
-        self.commands.get(name).map(|c| c.as_ref())
+    pub fn get(&self, name: &str) -> Option<Arc<dyn NexusCommand>> {
+        match self.commands.get(name) {
+            Some(cmd) => Some(cmd.clone()),
+            None => self.plugins.command(name).map(|cmd| cmd as Arc<dyn NexusCommand>),
+        }
     }
 
     /// Check if a command is registered.
     pub fn contains(&self, name: &str) -> bool {
-        self.commands.contains_key(name)
+        self.commands.contains_key(name) || self.plugins.command(name).is_some()
     }
 
     /// List all registered command names.
     pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
-        self.commands.keys().copied()
+        let plugins = self.plugins.command_names().into_iter().filter(|name| !self.commands.contains_key(name));
+        self.commands.keys().copied().chain(plugins)
+    }
+
+    /// The plugin host behind this registry's plugin commands.
+    pub fn plugins(&self) -> &Arc<PluginHost> {
+        &self.plugins
     }
 }
 
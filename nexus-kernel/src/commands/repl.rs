@@ -0,0 +1,66 @@
+//! repl - A persistent python or node interpreter in a block.
+//!
+//! ```text
+//! repl python    cells typed at the prompt run in one Python session
+//! repl node      the same, in Node
+//! ```
+//!
+//! The session lives until the block is killed or the interpreter exits.
+//! See [`crate::repl`] for how cells travel.
+
+use nexus_api::{CommandError, CommandErrorKind, Value};
+
+use super::{CommandContext, NexusCommand};
+use crate::repl::{self, Language};
+
+pub struct ReplCommand;
+
+impl NexusCommand for ReplCommand {
+    fn name(&self) -> &'static str {
+        "repl"
+    }
+
+    fn description(&self) -> &'static str {
+        "Start a persistent python or node interpreter"
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let name = args.first().map(String::as_str).unwrap_or("python");
+        let language = Language::parse(name)
+            .ok_or_else(|| CommandError::usage("repl", format!("unknown language '{}' (python or node)", name)))?;
+
+        let cancel = super::register_cancel(ctx.block_id);
+        let result = repl::run(language, &ctx.state.cwd, &ctx.state.env, ctx.block_id, ctx.events, &cancel);
+        super::unregister_cancel(ctx.block_id);
+
+        match result {
+            Ok(0) => Ok(Value::Unit),
+            Ok(code) => Err(CommandError::new(
+                "repl",
+                CommandErrorKind::Other,
+                format!("{} exited with status {}", language.name(), code),
+            )
+            .into()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(CommandError::new(
+                "repl",
+                CommandErrorKind::NotFound,
+                format!("{} is not installed", language.name()),
+            )
+            .into()),
+            Err(e) => Err(CommandError::new("repl", CommandErrorKind::Io, e.to_string()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    #[test]
+    fn test_repl_unknown_language() {
+        let mut test_ctx = TestContext::new_default();
+        let err = ReplCommand.execute(&["ruby".to_string()], &mut test_ctx.ctx()).unwrap_err();
+        assert!(err.to_string().contains("unknown language 'ruby'"));
+    }
+}
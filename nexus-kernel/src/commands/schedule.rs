@@ -0,0 +1,182 @@
+//! schedule - Run commands on a cron schedule while Nexus is open.
+//!
+//! ```text
+//! schedule "0 9 * * 1-5" 'git fetch --all'   run a command here on a schedule
+//! schedule list                              the scheduled commands
+//! schedule runs <id>                         the latest runs of one
+//! schedule remove <id>                       stop running it
+//! ```
+
+use super::{CommandContext, NexusCommand};
+use crate::persistence::{ScheduledCommand, Store};
+use crate::scheduler::CronExpr;
+use chrono::{Local, Utc};
+use nexus_api::{CommandError, CommandErrorKind, DisplayFormat, TableColumn, Value};
+use std::path::Path;
+
+/// Runs shown by `schedule runs`.
+const RUNS_SHOWN: usize = 20;
+
+pub struct ScheduleCommand;
+
+impl NexusCommand for ScheduleCommand {
+    fn name(&self) -> &'static str {
+        "schedule"
+    }
+
+    fn description(&self) -> &'static str {
+        "Run commands on a cron schedule while Nexus is open"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let store = Store::open_default()?;
+        run(&store, args, &ctx.state.cwd)
+    }
+}
+
+fn run(store: &Store, args: &[String], cwd: &Path) -> anyhow::Result<Value> {
+    match args.first().map(String::as_str) {
+        None | Some("list") => list(store),
+        Some("runs") => runs(store, id_arg(args)?),
+        Some("remove") => {
+            let id = id_arg(args)?;
+            if !store.remove_schedule(id)? {
+                return Err(not_found(id).into());
+            }
+            Ok(Value::String(format!("Removed schedule #{}", id)))
+        }
+        Some(expr) => {
+            let cron: CronExpr = expr.parse().map_err(|e| CommandError::usage("schedule", e))?;
+            let command = args[1..].join(" ");
+            if command.trim().is_empty() {
+                return Err(CommandError::usage("schedule", "missing command (e.g. `schedule \"0 9 * * 1-5\" 'git fetch --all'`)").into());
+            }
+            let cwd = cwd.display().to_string();
+            let id = store.add_schedule(expr, &command, &cwd)?;
+            Ok(Value::Record(vec![
+                ("id".to_string(), Value::Int(id)),
+                ("schedule".to_string(), Value::String(expr.to_string())),
+                ("command".to_string(), Value::String(command)),
+                ("cwd".to_string(), Value::Path(cwd.into())),
+                ("next".to_string(), next_run(&cron)),
+            ]))
+        }
+    }
+}
+
+fn list(store: &Store) -> anyhow::Result<Value> {
+    let rows = store
+        .schedules()?
+        .into_iter()
+        .map(|schedule| {
+            let last = store.schedule_runs(schedule.id, 1)?.into_iter().next();
+            let next = schedule.expr.parse().map_or(Value::Unit, |cron| next_run(&cron));
+            Ok(vec![
+                Value::Int(schedule.id),
+                Value::String(schedule.expr.clone()),
+                Value::String(schedule.command.clone()),
+                Value::String(schedule.cwd.clone()),
+                next,
+                last.as_ref().map_or(Value::Unit, |run| Value::Int(run.started_at.timestamp())),
+                last.and_then(|run| run.exit_code).map_or(Value::Unit, |code| Value::Int(code as i64)),
+            ])
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Value::Table {
+        columns: vec![
+            TableColumn::new("id"),
+            TableColumn::new("schedule"),
+            TableColumn::new("command"),
+            TableColumn::new("cwd"),
+            TableColumn::with_format("next", DisplayFormat::DateTime),
+            TableColumn::with_format("last_run", DisplayFormat::RelativeTime),
+            TableColumn::new("exit"),
+        ],
+        rows,
+    })
+}
+
+fn runs(store: &Store, id: i64) -> anyhow::Result<Value> {
+    if !store.schedules()?.iter().any(|schedule: &ScheduledCommand| schedule.id == id) {
+        return Err(not_found(id).into());
+    }
+    let rows = store
+        .schedule_runs(id, RUNS_SHOWN)?
+        .into_iter()
+        .map(|run| {
+            vec![
+                Value::Int(run.started_at.timestamp()),
+                run.exit_code.map_or(Value::Unit, |code| Value::Int(code as i64)),
+                run.duration_ms.map_or(Value::Unit, |ms| Value::Int((ms / 1000) as i64)),
+            ]
+        })
+        .collect();
+
+    Ok(Value::Table {
+        columns: vec![
+            TableColumn::with_format("started", DisplayFormat::RelativeTime),
+            TableColumn::new("exit"),
+            TableColumn::with_format("duration", DisplayFormat::Duration),
+        ],
+        rows,
+    })
+}
+
+/// When `cron` next runs, as a Unix timestamp.
+fn next_run(cron: &CronExpr) -> Value {
+    cron.next_after(Local::now().naive_local())
+        .and_then(|next| next.and_local_timezone(Local).earliest())
+        .map_or(Value::Unit, |next| Value::Int(next.with_timezone(&Utc).timestamp()))
+}
+
+fn id_arg(args: &[String]) -> Result<i64, CommandError> {
+    let sub = &args[0];
+    let id = args.get(1).ok_or_else(|| CommandError::usage("schedule", format!("{}: missing schedule id", sub)))?;
+    id.trim_start_matches('#')
+        .parse()
+        .map_err(|_| CommandError::usage("schedule", format!("{}: invalid schedule id '{}'", sub, id)))
+}
+
+fn not_found(id: i64) -> CommandError {
+    CommandError::new("schedule", CommandErrorKind::NotFound, format!("no schedule #{}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_list_remove() {
+        let store = Store::open_in_memory().unwrap();
+        let cwd = Path::new("/repo");
+        assert!(run(&store, &args(&["0 9 * *", "git fetch"]), cwd).is_err());
+        assert!(run(&store, &args(&["0 9 * * 1-5"]), cwd).is_err());
+
+        let Value::Record(fields) = run(&store, &args(&["0 9 * * 1-5", "git", "fetch", "--all"]), cwd).unwrap() else {
+            panic!("expected record");
+        };
+        assert_eq!(fields[2].1, Value::String("git fetch --all".to_string()));
+        assert!(matches!(fields[4].1, Value::Int(_)));
+
+        let Value::Table { rows, .. } = run(&store, &[], cwd).unwrap() else {
+            panic!("expected table");
+        };
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][3], Value::String("/repo".to_string()));
+        assert_eq!(rows[0][6], Value::Unit);
+
+        assert!(run(&store, &args(&["runs", "#1"]), cwd).is_ok());
+        assert!(run(&store, &args(&["remove", "1"]), cwd).is_ok());
+        assert!(run(&store, &args(&["remove", "1"]), cwd).is_err());
+        assert!(run(&store, &args(&["runs", "1"]), cwd).is_err());
+    }
+}
@@ -1,4 +1,5 @@
-//! Selection commands - first, last, nth, skip, take, flatten, compact, reverse.
+//! Selection commands - first, last, nth, skip, take, flatten, compact, reverse,
+//! enumerate, select.
 
 use super::{CommandContext, NexusCommand};
 use nexus_api::Value;
@@ -14,6 +15,10 @@ impl NexusCommand for FirstCommand {
         "first"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let count: usize = args
             .first()
@@ -65,6 +70,10 @@ impl NexusCommand for LastCommand {
         "last"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let count: usize = args
             .first()
@@ -123,6 +132,10 @@ impl NexusCommand for NthCommand {
         "nth"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let index: usize = args
             .first()
@@ -172,6 +185,10 @@ impl NexusCommand for SkipCommand {
         "skip"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let count: usize = args
             .first()
@@ -214,6 +231,10 @@ impl NexusCommand for TakeCommand {
         "take"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let count: usize = args
             .first()
@@ -256,6 +277,10 @@ impl NexusCommand for FlattenCommand {
         "flatten"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let depth: usize = args
             .first()
@@ -305,6 +330,10 @@ impl NexusCommand for CompactCommand {
         "compact"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(compact_value(stdin_value));
@@ -347,6 +376,10 @@ impl NexusCommand for ReverseCommand {
         "reverse"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(reverse_value(stdin_value));
@@ -385,6 +418,10 @@ impl NexusCommand for EnumerateCommand {
         "enumerate"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(enumerate_value(stdin_value));
@@ -432,6 +469,79 @@ fn enumerate_value(value: Value) -> Value {
     }
 }
 
+// ============================================================================
+// select - keep only the named columns
+// ============================================================================
+
+pub struct SelectCommand;
+
+impl NexusCommand for SelectCommand {
+    fn name(&self) -> &'static str {
+        "select"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let fields: Vec<String> = args.iter().flat_map(|a| a.split(',')).filter(|f| !f.is_empty()).map(String::from).collect();
+        if fields.is_empty() {
+            anyhow::bail!("usage: select <column>...");
+        }
+
+        match ctx.stdin.take() {
+            Some(value) => select_value(value, &fields),
+            None => Ok(Value::Unit),
+        }
+    }
+}
+
+/// `value` cut down to `fields`, in that order. A list of records or typed
+/// values becomes a table, missing fields showing as empty cells.
+fn select_value(value: Value, fields: &[String]) -> anyhow::Result<Value> {
+    match value {
+        Value::Table { columns, rows } => {
+            let indices = fields
+                .iter()
+                .map(|field| {
+                    columns
+                        .iter()
+                        .position(|c| c.name.eq_ignore_ascii_case(field))
+                        .ok_or_else(|| anyhow::anyhow!("no column named '{}'", field))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let columns = indices.iter().map(|&i| columns[i].clone()).collect();
+            let rows = rows
+                .into_iter()
+                .map(|row| indices.iter().map(|&i| row.get(i).cloned().unwrap_or(Value::Unit)).collect())
+                .collect();
+            Ok(Value::Table { columns, rows })
+        }
+        Value::List(items) => {
+            let columns = fields.iter().map(nexus_api::TableColumn::new).collect();
+            let rows = items
+                .iter()
+                .map(|item| fields.iter().map(|f| item.get_field(f).unwrap_or(Value::Unit)).collect())
+                .collect();
+            Ok(Value::Table { columns, rows })
+        }
+        Value::Record(entries) => Ok(Value::Record(
+            fields
+                .iter()
+                .filter_map(|f| entries.iter().find(|(k, _)| k == f).cloned())
+                .collect(),
+        )),
+        other if other.is_typed() => Ok(Value::Record(
+            fields
+                .iter()
+                .map(|f| (f.clone(), other.get_field(f).unwrap_or(Value::Unit)))
+                .collect(),
+        )),
+        other => anyhow::bail!("expected a table, list or record, got {}", other.type_name()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -488,4 +598,35 @@ mod tests {
             assert_eq!(items[2], Value::Int(1));
         }
     }
+
+    #[test]
+    fn test_select() {
+        let table = Value::Table {
+            columns: vec![
+                nexus_api::TableColumn::new("name"),
+                nexus_api::TableColumn::new("size"),
+                nexus_api::TableColumn::new("kind"),
+            ],
+            rows: vec![vec![Value::String("a".into()), Value::Int(3), Value::String("file".into())]],
+        };
+        let fields = vec!["kind".to_string(), "name".to_string()];
+        match select_value(table.clone(), &fields).unwrap() {
+            Value::Table { columns, rows } => {
+                assert_eq!(columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["kind", "name"]);
+                assert_eq!(rows, vec![vec![Value::String("file".into()), Value::String("a".into())]]);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+        assert!(select_value(table, &["owner".to_string()]).is_err());
+
+        let records = Value::List(vec![Value::Record(vec![
+            ("name".to_string(), Value::String("b".into())),
+            ("size".to_string(), Value::Int(1)),
+        ])]);
+        match select_value(records, &["size".to_string()]).unwrap() {
+            Value::Table { rows, .. } => assert_eq!(rows, vec![vec![Value::Int(1)]]),
+            other => panic!("expected a table, got {:?}", other),
+        }
+        assert!(select_value(Value::Int(1), &fields).is_err());
+    }
 }
@@ -82,6 +82,10 @@ impl NexusCommand for SeqCommand {
         "seq"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = SeqOptions::parse(args);
 
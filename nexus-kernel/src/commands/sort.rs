@@ -4,6 +4,9 @@
 //! - `ps | sort --by cpu` - sort processes by CPU usage
 //! - `ls | sort --by size` - sort files by size
 //! - `git log | sort --by date` - sort commits by date
+//!
+//! `sort-by` is the strict form for typed pipelines: it only takes a table
+//! or list, and names the fields to sort by, e.g. `ls |> sort-by size`.
 
 use super::{CommandContext, NexusCommand};
 use nexus_api::Value;
@@ -11,6 +14,8 @@ use std::cmp::Ordering;
 
 pub struct SortCommand;
 
+pub struct SortByCommand;
+
 struct SortOptions {
     reverse: bool,
     numeric: bool,
@@ -105,6 +110,10 @@ impl NexusCommand for SortCommand {
         "sort"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = SortOptions::parse(args);
 
@@ -116,6 +125,70 @@ impl NexusCommand for SortCommand {
     }
 }
 
+impl NexusCommand for SortByCommand {
+    fn name(&self) -> &'static str {
+        "sort-by"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let mut opts = SortOptions::parse(&[]);
+        for arg in args {
+            match arg.as_str() {
+                "-r" | "--reverse" => opts.reverse = true,
+                _ => opts.by_fields.extend(arg.split(',').filter(|f| !f.is_empty()).map(String::from)),
+            }
+        }
+        if opts.by_fields.is_empty() {
+            anyhow::bail!("usage: sort-by [-r] <field>...");
+        }
+
+        match ctx.stdin.take() {
+            Some(value) => sort_by_fields(value, &opts),
+            None => Ok(Value::Unit),
+        }
+    }
+}
+
+/// A table or list sorted by each of `opts.by_fields` in turn.
+fn sort_by_fields(value: Value, opts: &SortOptions) -> anyhow::Result<Value> {
+    match value {
+        Value::List(mut items) => {
+            sort_generic(&mut items, opts);
+            Ok(Value::List(items))
+        }
+        Value::Table { columns, mut rows } => {
+            let keys = opts
+                .by_fields
+                .iter()
+                .map(|field| {
+                    columns
+                        .iter()
+                        .position(|c| c.name.eq_ignore_ascii_case(field))
+                        .ok_or_else(|| anyhow::anyhow!("no column named '{}'", field))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            rows.sort_by(|a, b| {
+                let cmp = keys
+                    .iter()
+                    .map(|&k| compare_values(a.get(k).unwrap_or(&Value::Unit), b.get(k).unwrap_or(&Value::Unit), opts))
+                    .find(|cmp| *cmp != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal);
+                if opts.reverse {
+                    cmp.reverse()
+                } else {
+                    cmp
+                }
+            });
+            Ok(Value::Table { columns, rows })
+        }
+        other => anyhow::bail!("expected a table or list, got {}", other.type_name()),
+    }
+}
+
 fn sort_value(value: Value, opts: &SortOptions) -> Value {
     match value {
         Value::List(mut items) => {
@@ -791,4 +864,30 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_sort_by_fields() {
+        let table = Value::Table {
+            columns: vec![nexus_api::TableColumn::new("name"), nexus_api::TableColumn::new("size")],
+            rows: vec![
+                vec![Value::String("b".into()), Value::Int(2)],
+                vec![Value::String("c".into()), Value::Int(1)],
+                vec![Value::String("a".into()), Value::Int(2)],
+            ],
+        };
+        let mut opts = SortOptions::parse(&[]);
+        opts.by_fields = vec!["size".into(), "name".into()];
+        opts.reverse = true;
+        match sort_by_fields(table.clone(), &opts).unwrap() {
+            Value::Table { rows, .. } => {
+                let names: Vec<_> = rows.iter().map(|r| r[0].to_text()).collect();
+                assert_eq!(names, ["b", "a", "c"]);
+            }
+            other => panic!("expected a table, got {:?}", other),
+        }
+
+        opts.by_fields = vec!["owner".into()];
+        assert!(sort_by_fields(table, &opts).is_err());
+        assert!(sort_by_fields(Value::String("x".into()), &opts).is_err());
+    }
 }
@@ -16,6 +16,10 @@ impl NexusCommand for LinesCommand {
         "lines"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(lines_value(stdin_value));
@@ -78,6 +82,10 @@ impl NexusCommand for WordsCommand {
         "words"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(words_value(stdin_value));
@@ -163,6 +171,10 @@ impl NexusCommand for CharsCommand {
         "chars"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(chars_value(stdin_value));
@@ -210,6 +222,10 @@ impl NexusCommand for BytesCommand {
         "bytes"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if let Some(stdin_value) = ctx.stdin.take() {
             return Ok(bytes_value(stdin_value));
@@ -250,6 +266,10 @@ impl NexusCommand for SplitCommand {
         "split"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let delimiter = args.first().map(|s| s.as_str()).unwrap_or("\n");
 
@@ -298,6 +318,10 @@ impl NexusCommand for JoinCommand {
         "join"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let delimiter = args.first().map(|s| s.as_str()).unwrap_or("\n");
 
@@ -0,0 +1,146 @@
+//! storage - Disk usage and cleanup of the persistence store.
+//!
+//! ```text
+//! storage                     per-session usage table
+//! storage purge <age>         drop outputs older than <age> (e.g. 7d, 12h)
+//! storage vacuum              apply the retention policy now and compact
+//! storage policy              show the active retention policy
+//! storage encrypt             encrypt rows stored before encryption was enabled
+//! ```
+
+use super::{CommandContext, NexusCommand};
+use crate::persistence::{RetentionPolicy, Store};
+use nexus_api::{DisplayFormat, TableColumn, Value};
+use std::time::Duration;
+
+pub struct StorageCommand;
+
+impl NexusCommand for StorageCommand {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    fn description(&self) -> &'static str {
+        "Show session disk usage and purge stored outputs"
+    }
+
+    fn structured_output(&self) -> bool {
+        true
+    }
+
+    fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let store = Store::open_default()?;
+
+        match args.first().map(String::as_str) {
+            None | Some("usage") => usage(&store),
+            Some("purge") => {
+                let age = match args.get(1).map(String::as_str) {
+                    Some("--older-than") => args.get(2),
+                    _ => args.get(1),
+                };
+                let Some(age) = age else {
+                    anyhow::bail!("storage: purge: missing age (e.g. `storage purge 7d`)");
+                };
+                let duration = parse_age(age).map_err(|e| anyhow::anyhow!("storage: purge: {}", e))?;
+                let purged = store.purge_outputs_older_than(duration)?;
+                Ok(Value::String(format!("Purged output of {} blocks older than {}", purged, age)))
+            }
+            Some("vacuum") => {
+                let before = store.db_size()?;
+                let stats = store.apply_retention(&RetentionPolicy::from_env())?;
+                store.vacuum()?;
+                Ok(Value::Record(vec![
+                    ("sessions_removed".to_string(), Value::Int(stats.sessions as i64)),
+                    ("blocks_removed".to_string(), Value::Int(stats.blocks as i64)),
+                    ("size_before".to_string(), Value::Int(before as i64)),
+                    ("size_after".to_string(), Value::Int(store.db_size()? as i64)),
+                ]))
+            }
+            Some("policy") => Ok(policy_record(&RetentionPolicy::from_env(), store.is_encrypted())),
+            Some("encrypt") => {
+                let sealed = store.encrypt_existing().map_err(|e| anyhow::anyhow!("storage: {}", e))?;
+                Ok(Value::String(format!("Encrypted {} previously stored blocks", sealed)))
+            }
+            Some(other) => anyhow::bail!(
+                "storage: unknown subcommand '{}' (expected usage, purge, vacuum, policy, encrypt)",
+                other
+            ),
+        }
+    }
+}
+
+fn usage(store: &Store) -> anyhow::Result<Value> {
+    let rows: Vec<Vec<Value>> = store
+        .session_usage()?
+        .into_iter()
+        .map(|u| {
+            vec![
+                Value::Int(u.session.id),
+                Value::Int(u.session.started_at.timestamp()),
+                Value::String(u.session.cwd),
+                Value::Int(u.blocks as i64),
+                Value::Int(u.bytes as i64),
+            ]
+        })
+        .collect();
+
+    Ok(Value::Table {
+        columns: vec![
+            TableColumn::new("session"),
+            TableColumn::with_format("started", DisplayFormat::RelativeTime),
+            TableColumn::new("cwd"),
+            TableColumn::new("blocks"),
+            TableColumn::with_format("size", DisplayFormat::HumanBytes),
+        ],
+        rows,
+    })
+}
+
+fn policy_record(policy: &RetentionPolicy, encrypted: bool) -> Value {
+    let limit = |v: Option<u64>| v.map_or(Value::String("unlimited".to_string()), |n| Value::Int(n as i64));
+    Value::Record(vec![
+        ("max_sessions".to_string(), limit(policy.max_sessions.map(|n| n as u64))),
+        ("max_age_days".to_string(), limit(policy.max_age.map(|d| d.as_secs() / 86_400))),
+        ("max_size_mb".to_string(), limit(policy.max_db_bytes.map(|b| b / (1024 * 1024)))),
+        ("encrypted".to_string(), Value::Bool(encrypted)),
+    ])
+}
+
+/// Parse an age like `30d`, `12h`, `45m`, `90s` or `2w`. Bare numbers are days.
+fn parse_age(s: &str) -> anyhow::Result<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: u64 = num.parse().map_err(|_| anyhow::anyhow!("invalid age '{}'", s))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!("invalid age '{}' (expected a unit of s, m, h, d or w)", s),
+    };
+    let secs = n.checked_mul(secs).ok_or_else(|| anyhow::anyhow!("age '{}' is too large", s))?;
+    Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("7d").unwrap(), Duration::from_secs(7 * 86_400));
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("30").unwrap(), Duration::from_secs(30 * 86_400));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86_400));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("5y").is_err());
+    }
+
+    #[test]
+    fn test_parse_age_overflow() {
+        let err = parse_age("99999999999999999w").unwrap_err();
+        assert!(err.to_string().contains("too large"), "{}", err);
+    }
+}
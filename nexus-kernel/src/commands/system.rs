@@ -84,6 +84,10 @@ impl NexusCommand for UnameCommand {
         "uname"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], _ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut show_sysname = false;
         let mut show_nodename = false;
@@ -56,6 +56,10 @@ impl NexusCommand for TailCommand {
         "tail"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = TailOptions::parse(args);
 
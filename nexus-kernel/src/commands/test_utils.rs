@@ -5,7 +5,8 @@
 
 #[cfg(test)]
 pub mod test_helpers {
-    use crate::commands::CommandContext;
+    use crate::commands::{CommandContext, OutputMode};
+    use crate::replay::EventSender;
     use crate::state::ShellState;
     use nexus_api::{BlockId, ShellEvent, Value};
     use std::path::PathBuf;
@@ -14,7 +15,7 @@ pub mod test_helpers {
     /// A test context that owns all the resources needed for CommandContext.
     pub struct TestContext {
         pub state: ShellState,
-        sender: broadcast::Sender<ShellEvent>,
+        sender: EventSender,
         #[allow(dead_code)]
         receiver: broadcast::Receiver<ShellEvent>,
     }
@@ -22,7 +23,7 @@ pub mod test_helpers {
     impl TestContext {
         /// Create a new test context with the given working directory.
         pub fn new(cwd: PathBuf) -> Self {
-            let (sender, receiver) = broadcast::channel(16);
+            let (sender, receiver) = EventSender::channel(16);
             Self {
                 state: ShellState::from_cwd(cwd),
                 sender,
@@ -42,6 +43,7 @@ pub mod test_helpers {
                 events: &self.sender,
                 block_id: BlockId(1),
                 stdin: None,
+                output: OutputMode::Value,
             }
         }
 
@@ -52,6 +54,7 @@ pub mod test_helpers {
                 events: &self.sender,
                 block_id: BlockId(1),
                 stdin: Some(stdin),
+                output: OutputMode::Value,
             }
         }
     }
@@ -17,6 +17,10 @@ impl NexusCommand for TopCommand {
         "top"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut interval_ms: u64 = 2000;
 
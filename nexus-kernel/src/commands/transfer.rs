@@ -0,0 +1,440 @@
+//! push / pull - Copy files to and from an ssh host, with progress.
+//!
+//! ```text
+//! push <local>... <host>:<path>     upload
+//! pull <host>:<path>... <local>     download
+//!   -p <port>  -i <identity file>
+//! ```
+//!
+//! Both run rsync over ssh and show the transfer as a file operation with
+//! throughput and ETA. Partial files are kept, so running an interrupted
+//! transfer again picks up where it stopped. Transfers to the same host
+//! share one multiplexed ssh connection, so a run of them authenticates
+//! once.
+
+use super::{is_cancelled, CommandContext, NexusCommand};
+use nexus_api::{CommandError, CommandErrorKind, FileOpError, FileOpInfo, FileOpKind, FileOpPhase, ShellEvent, Value};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a shared ssh connection outlives its last transfer.
+const CONTROL_PERSIST: &str = "10m";
+
+/// How often progress is sent to the block.
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct PushCommand;
+
+impl NexusCommand for PushCommand {
+    fn name(&self) -> &'static str {
+        "push"
+    }
+
+    fn description(&self) -> &'static str {
+        "Upload files to an ssh host, with progress (resumable)"
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        transfer(Direction::Push, args, ctx)
+    }
+}
+
+pub struct PullCommand;
+
+impl NexusCommand for PullCommand {
+    fn name(&self) -> &'static str {
+        "pull"
+    }
+
+    fn description(&self) -> &'static str {
+        "Download files from an ssh host, with progress (resumable)"
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        transfer(Direction::Pull, args, ctx)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    Push,
+    Pull,
+}
+
+impl Direction {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Push => "push",
+            Self::Pull => "pull",
+        }
+    }
+}
+
+/// A transfer as given on the command line.
+#[derive(Debug, PartialEq)]
+struct Transfer {
+    sources: Vec<String>,
+    dest: String,
+    port: Option<String>,
+    identity: Option<String>,
+}
+
+fn parse_args(direction: Direction, args: &[String]) -> Result<Transfer, CommandError> {
+    let name = direction.name();
+    let mut operands = Vec::new();
+    let (mut port, mut identity) = (None, None);
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-p" | "-i" => {
+                let value = iter.next().ok_or_else(|| CommandError::usage(name, format!("{} needs a value", arg)))?;
+                if arg == "-p" {
+                    port = Some(value.clone());
+                } else {
+                    identity = Some(value.clone());
+                }
+            }
+            // rsync copies directories whole either way.
+            "-r" | "-R" | "--recursive" => {}
+            s if s.starts_with('-') => return Err(CommandError::usage(name, format!("unknown option '{}'", s))),
+            _ => operands.push(arg.clone()),
+        }
+    }
+    let Some(dest) = operands.pop().filter(|_| !operands.is_empty()) else {
+        return Err(CommandError::usage(
+            name,
+            match direction {
+                Direction::Push => "usage: push <local>... <host>:<path>",
+                Direction::Pull => "usage: pull <host>:<path>... <local>",
+            },
+        ));
+    };
+    let remote_ok = match direction {
+        Direction::Push => is_remote(&dest) && !operands.iter().any(|s| is_remote(s)),
+        Direction::Pull => !is_remote(&dest) && operands.iter().all(|s| is_remote(s)),
+    };
+    if !remote_ok {
+        return Err(CommandError::usage(
+            name,
+            match direction {
+                Direction::Push => "the destination must be <host>:<path> and the sources local",
+                Direction::Pull => "the sources must be <host>:<path> and the destination local",
+            },
+        ));
+    }
+    Ok(Transfer { sources: operands, dest, port, identity })
+}
+
+/// Whether `spec` names a remote path (`host:path`, `user@host:path`).
+/// A colon after a slash is part of a local path.
+fn is_remote(spec: &str) -> bool {
+    spec.split_once(':').is_some_and(|(host, _)| !host.is_empty() && !host.contains('/'))
+}
+
+/// The ssh command rsync runs. Connections are multiplexed through a
+/// control socket per host under `~/.nexus/ssh`, kept open for a while
+/// after the last transfer.
+fn remote_shell(transfer: &Transfer) -> String {
+    let mut shell = vec!["ssh".to_string(), "-o".to_string(), "BatchMode=yes".to_string()];
+    if let Some(dir) = control_dir() {
+        shell.extend([
+            "-o".to_string(),
+            "ControlMaster=auto".to_string(),
+            "-o".to_string(),
+            quote(&format!("ControlPath={}/%C", dir.display())),
+            "-o".to_string(),
+            format!("ControlPersist={}", CONTROL_PERSIST),
+        ]);
+    }
+    if let Some(port) = &transfer.port {
+        shell.extend(["-p".to_string(), quote(port)]);
+    }
+    if let Some(identity) = &transfer.identity {
+        shell.extend(["-i".to_string(), quote(identity)]);
+    }
+    shell.join(" ")
+}
+
+/// The directory holding control sockets, created private on first use.
+fn control_dir() -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os("HOME")?).join(".nexus").join("ssh");
+    if !dir.is_dir() {
+        std::fs::create_dir_all(&dir).ok()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).ok()?;
+        }
+    }
+    Some(dir)
+}
+
+/// Quote a word for rsync's splitting of the `-e` command.
+fn quote(word: &str) -> String {
+    if word.contains(|c: char| c.is_whitespace() || c == '\'' || c == '"') {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    } else {
+        word.to_string()
+    }
+}
+
+fn rsync(transfer: &Transfer, cwd: &Path, extra: &[&str]) -> Command {
+    let mut cmd = Command::new("rsync");
+    cmd.args(["-a", "--partial"])
+        .args(extra)
+        .arg("-e")
+        .arg(remote_shell(transfer))
+        .args(&transfer.sources)
+        .arg(&transfer.dest)
+        .current_dir(cwd)
+        .stdin(Stdio::null());
+    cmd
+}
+
+/// What `rsync --dry-run --stats` says will be sent: bytes and files.
+fn parse_stats(text: &str) -> (Option<u64>, Option<usize>) {
+    let number = |line: &str| -> Option<u64> {
+        let value = line.split_once(':')?.1.trim();
+        let digits: String = value.chars().take_while(|c| c.is_ascii_digit() || *c == ',').filter(|c| *c != ',').collect();
+        digits.parse().ok()
+    };
+    let mut bytes = None;
+    let mut files = None;
+    for line in text.lines() {
+        if line.starts_with("Total transferred file size:") {
+            bytes = number(line);
+        } else if line.starts_with("Number of regular files transferred:") || line.starts_with("Number of files transferred:") {
+            files = number(line).map(|n| n as usize);
+        }
+    }
+    (bytes, files)
+}
+
+/// A line of `rsync --progress` output.
+#[derive(Debug, PartialEq)]
+enum Progress {
+    /// A file started.
+    File(String),
+    /// Bytes of the current file sent so far; `done` once it's finished.
+    Bytes { bytes: u64, done: bool },
+}
+
+/// Lines rsync prints around the file list that aren't files.
+const NOT_FILES: [&str; 7] = [
+    "sending incremental file list",
+    "receiving incremental file list",
+    "building file list",
+    "receiving file list",
+    "sent ",
+    "total size is",
+    "created directory",
+];
+
+fn parse_progress(line: &str) -> Option<Progress> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    if line.starts_with(char::is_whitespace) {
+        let mut words = line.split_whitespace();
+        let bytes: u64 = words.next()?.replace(',', "").parse().ok()?;
+        words.next().filter(|pct| pct.ends_with('%'))?;
+        let done = line.contains("xfer#") || line.contains("xfr#");
+        return Some(Progress::Bytes { bytes, done });
+    }
+    if line.ends_with('/') || NOT_FILES.iter().any(|prefix| line.starts_with(prefix)) {
+        return None;
+    }
+    Some(Progress::File(line.to_string()))
+}
+
+fn emit(ctx: &CommandContext, seq: &mut u64, info: &FileOpInfo) {
+    *seq += 1;
+    let _ = ctx.events.send(ShellEvent::StreamingUpdate {
+        block_id: ctx.block_id,
+        seq: *seq,
+        update: Value::file_op(info.clone()),
+        coalesce: true,
+    });
+}
+
+fn transfer(direction: Direction, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+    let name = direction.name();
+    let transfer = parse_args(direction, args)?;
+    let cwd = ctx.state.cwd.clone();
+    let path = |spec: &String| if is_remote(spec) { PathBuf::from(spec) } else { cwd.join(spec) };
+
+    let mut info = FileOpInfo {
+        op_type: match direction {
+            Direction::Push => FileOpKind::Upload,
+            Direction::Pull => FileOpKind::Download,
+        },
+        phase: FileOpPhase::Planning,
+        sources: transfer.sources.iter().map(path).collect(),
+        dest: Some(path(&transfer.dest)),
+        total_bytes: None,
+        bytes_processed: 0,
+        files_total: None,
+        files_processed: 0,
+        current_file: None,
+        start_time_ms: 0,
+        errors: Vec::new(),
+    };
+    let mut seq = 0;
+    emit(ctx, &mut seq, &info);
+
+    // Planning: ask rsync what it would send, which also connects (and
+    // leaves the shared connection open for the transfer).
+    let plan = rsync(&transfer, &cwd, &["--dry-run", "--stats"]).output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            CommandError::new(name, CommandErrorKind::NotFound, "rsync is not installed")
+        } else {
+            CommandError::new(name, CommandErrorKind::Io, e.to_string())
+        }
+    })?;
+    if !plan.status.success() {
+        let stderr = String::from_utf8_lossy(&plan.stderr);
+        info.phase = FileOpPhase::Failed;
+        info.errors.push(FileOpError {
+            path: info.dest.clone().unwrap_or_default(),
+            message: stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("rsync failed").to_string(),
+        });
+        return Ok(Value::file_op(info));
+    }
+    (info.total_bytes, info.files_total) = parse_stats(&String::from_utf8_lossy(&plan.stdout));
+    info.phase = FileOpPhase::Executing;
+    info.start_time_ms = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    emit(ctx, &mut seq, &info);
+
+    let mut child = rsync(&transfer, &cwd, &["--progress"]).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+    // rsync redraws progress with `\r`; split on both line ends.
+    let (tx, rx) = mpsc::channel();
+    let mut stdout = child.stdout.take().expect("piped stdout");
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        let mut line = Vec::new();
+        while let Ok(n) = stdout.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            for &byte in &buf[..n] {
+                if byte == b'\r' || byte == b'\n' {
+                    if tx.send(String::from_utf8_lossy(&line).into_owned()).is_err() {
+                        return;
+                    }
+                    line.clear();
+                } else {
+                    line.push(byte);
+                }
+            }
+        }
+    });
+    let mut stderr = child.stderr.take().expect("piped stderr");
+    let stderr = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+
+    let dest = info.dest.clone().unwrap_or_default();
+    let mut finished_bytes = 0;
+    let mut last_emit = Instant::now();
+    let mut cancelled = false;
+    loop {
+        match rx.recv_timeout(EMIT_INTERVAL) {
+            Ok(line) => match parse_progress(&line) {
+                Some(Progress::File(file)) => info.current_file = Some(dest.join(file)),
+                Some(Progress::Bytes { bytes, done }) => {
+                    info.bytes_processed = finished_bytes + bytes;
+                    if done {
+                        finished_bytes += bytes;
+                        info.files_processed += 1;
+                    }
+                }
+                None => {}
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        if !cancelled && is_cancelled(ctx.block_id) {
+            cancelled = true;
+            let _ = child.kill();
+        }
+        if last_emit.elapsed() >= EMIT_INTERVAL {
+            emit(ctx, &mut seq, &info);
+            last_emit = Instant::now();
+        }
+    }
+
+    let status = child.wait()?;
+    let stderr = stderr.join().unwrap_or_default();
+    info.current_file = None;
+    if status.success() {
+        info.phase = FileOpPhase::Completed;
+        info.bytes_processed = info.total_bytes.unwrap_or(info.bytes_processed).max(info.bytes_processed);
+        info.files_processed = info.files_total.unwrap_or(info.files_processed).max(info.files_processed);
+    } else {
+        info.phase = FileOpPhase::Failed;
+        let message = if cancelled {
+            format!("interrupted; run `{}` again to resume", name)
+        } else {
+            stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("rsync failed").to_string()
+        };
+        info.errors.push(FileOpError { path: dest, message });
+    }
+    Ok(Value::file_op(info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let push = parse_args(Direction::Push, &args(&["-p", "2222", "build/app", "notes.txt", "me@web:/srv/"])).unwrap();
+        assert_eq!(push.sources, ["build/app", "notes.txt"]);
+        assert_eq!(push.dest, "me@web:/srv/");
+        assert_eq!(push.port.as_deref(), Some("2222"));
+
+        let pull = parse_args(Direction::Pull, &args(&["web:logs/a.log", "web:logs/b.log", "."])).unwrap();
+        assert_eq!(pull.sources.len(), 2);
+
+        assert!(parse_args(Direction::Push, &args(&["web:/srv"])).is_err());
+        assert!(parse_args(Direction::Push, &args(&["a.txt", "./b:c"])).is_err());
+        assert!(parse_args(Direction::Pull, &args(&["a.txt", "."])).is_err());
+        assert!(parse_args(Direction::Pull, &args(&["-i"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let v3 = "Number of files: 4 (reg: 3, dir: 1)\nNumber of regular files transferred: 2\n\
+                  Total file size: 9,000 bytes\nTotal transferred file size: 1,234,567 bytes\n";
+        assert_eq!(parse_stats(v3), (Some(1_234_567), Some(2)));
+        let old = "Number of files: 4\nNumber of files transferred: 3\nTotal transferred file size: 512 bytes\n";
+        assert_eq!(parse_stats(old), (Some(512), Some(3)));
+        assert_eq!(parse_stats(""), (None, None));
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        assert_eq!(parse_progress("build/app"), Some(Progress::File("build/app".to_string())));
+        assert_eq!(
+            parse_progress("     32,768  50%    1.20MB/s    0:00:01"),
+            Some(Progress::Bytes { bytes: 32_768, done: false })
+        );
+        assert_eq!(
+            parse_progress("     65,536 100%    1.20MB/s    0:00:02 (xfr#1, to-chk=2/4)"),
+            Some(Progress::Bytes { bytes: 65_536, done: true })
+        );
+        assert_eq!(parse_progress("sending incremental file list"), None);
+        assert_eq!(parse_progress("build/"), None);
+        assert_eq!(parse_progress(""), None);
+    }
+}
@@ -12,6 +12,10 @@ impl NexusCommand for TreeCommand {
         "tree"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut max_depth: Option<usize> = None;
         let mut show_hidden = false;
@@ -52,6 +52,10 @@ impl NexusCommand for UniqCommand {
         "uniq"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = UniqOptions::parse(args);
 
@@ -72,6 +72,10 @@ impl NexusCommand for WcCommand {
         "wc"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let opts = WcOptions::parse(args);
 
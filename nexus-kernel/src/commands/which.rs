@@ -11,6 +11,10 @@ impl NexusCommand for WhichCommand {
         "which"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         let mut all = false;
         let mut commands = Vec::new();
@@ -102,6 +106,10 @@ impl NexusCommand for TypeCommand {
         "type"
     }
 
+    fn structured_output(&self) -> bool {
+        true
+    }
+
     fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
         if args.is_empty() {
             return Ok(Value::Unit);
@@ -5,6 +5,13 @@
 //! - Commands (PATH, builtins, native commands)
 //! - Git branches and files (when in git context)
 //! - Command-specific flags
+//!
+//! The word being completed is found with the tree-sitter parse (or, while
+//! a quote is still open and the line doesn't parse, by scanning for
+//! unquoted separators). Paths are matched against the word with its quotes
+//! and escapes removed, then written back the way the word was started:
+//! inside the same quotes, closed once a file is chosen, or with
+//! backslashes before spaces and other special characters.
 
 use std::path::{Path, PathBuf};
 use std::collections::HashSet;
@@ -16,6 +23,8 @@ const SHELL_META_CHARS: &[char] = &[
 ];
 
 use crate::commands::CommandRegistry;
+use crate::outputs::value_shape;
+use crate::project_tasks::{self, TaskTool};
 use crate::ShellState;
 
 /// A completion suggestion.
@@ -54,6 +63,8 @@ pub enum CompletionKind {
     GitBranch,
     /// A command flag/option.
     Flag,
+    /// A project task: a make target, just recipe, npm script or cargo binary.
+    Task,
 }
 
 impl CompletionKind {
@@ -70,6 +81,7 @@ impl CompletionKind {
             CompletionKind::Variable => "$",
             CompletionKind::GitBranch => "",
             CompletionKind::Flag => "-",
+            CompletionKind::Task => "▶",
         }
     }
 }
@@ -104,37 +116,34 @@ impl<'a> CompletionEngine<'a> {
         // Determine completion context
         let context = self.determine_context(input, word_start);
 
-        let completions = match context {
+        let mut completions = match context {
             CompletionContext::Command => self.complete_command(&word),
-            CompletionContext::Path => self.complete_path(&word),
-            CompletionContext::CommandPath => self.complete_path_executables(&word),
+            CompletionContext::Path => self.complete_quoted_path(&word, false),
+            CompletionContext::CommandPath => self.complete_quoted_path(&word, true),
             CompletionContext::Variable => self.complete_variable(&word),
             CompletionContext::GitBranch(cmd) => self.complete_git(&cmd, &word),
             CompletionContext::Flag(cmd) => self.complete_flags(&cmd, &word),
+            CompletionContext::Task(tool) => self.complete_task(tool, &word),
         };
 
+        // A configured snippet name expands to its text, ahead of other matches.
+        if let Some(snippet) = self.state.config.snippet(&word) {
+            completions.insert(0, Completion {
+                text: snippet.to_string(),
+                display: format!("{} {} \u{2192} {}", CompletionKind::Alias.icon(), word, snippet),
+                kind: CompletionKind::Alias,
+                score: i32::MAX,
+            });
+        }
+
         (completions, word_start)
     }
 
-    /// Find the word at the cursor position.
+    /// Find the word at the cursor position (the end of `input`), as
+    /// typed: quotes and escapes included.
     fn find_current_word(&self, input: &str) -> (String, usize) {
-        // Work backwards from cursor to find word start
-        let bytes = input.as_bytes();
-        let mut start = input.len();
-
-        for i in (0..input.len()).rev() {
-            let c = bytes[i] as char;
-            if c.is_whitespace() || c == '|' || c == ';' || c == '&' || c == '>' || c == '<' {
-                start = i + 1;
-                break;
-            }
-            if i == 0 {
-                start = 0;
-            }
-        }
-
-        let word = input[start..].to_string();
-        (word, start)
+        let start = parsed_word_start(input).unwrap_or_else(|| scanned_word_start(input));
+        (input[start..].to_string(), start)
     }
 
     /// Determine what kind of completion to provide.
@@ -169,6 +178,13 @@ impl<'a> CompletionEngine<'a> {
             return CompletionContext::Command;
         }
 
+        // Task names after `make`, `just`, `npm run` and `cargo run --bin`
+        let last_command = before_word.rsplit(['|', ';', '&']).next().unwrap_or_default();
+        let words: Vec<&str> = last_command.split_whitespace().collect();
+        if let Some(tool) = TaskTool::expecting_task(&words) {
+            return CompletionContext::Task(tool);
+        }
+
         // Git-specific completion
         if let Some(cmd) = self.find_command_name(before_word) {
             if cmd == "git" {
@@ -206,10 +222,10 @@ impl<'a> CompletionEngine<'a> {
 
         // Builtins
         let builtins = [
-            "cd", "exit", "export", "unset", "set", "alias", "unalias",
+            "cd", "exit", "export", "unset", "set", "shopt", "alias", "unalias",
             "source", "eval", "read", "shift", "return", "break", "continue",
-            "readonly", "command", "getopts", "trap", "exec", "local",
-            "test", "[",
+            "readonly", "command", "getopts", "trap", "exec", "local", "profile", "time", "debug",
+            "test", "[", "activate", "deactivate",
         ];
 
         for name in builtins {
@@ -247,8 +263,9 @@ impl<'a> CompletionEngine<'a> {
             }
         }
 
-        // Aliases
-        for name in self.state.aliases.keys() {
+        // Aliases, including those from configuration
+        let config_aliases = self.state.config.aliases.keys().filter(|name| !self.state.aliases.contains_key(*name));
+        for name in self.state.aliases.keys().chain(config_aliases) {
             if name.to_lowercase().starts_with(&prefix_lower) {
                 completions.push(Completion {
                     text: name.clone(),
@@ -289,7 +306,23 @@ impl<'a> CompletionEngine<'a> {
         completions
     }
 
-    /// Complete file/directory paths.
+    /// Complete a path word as typed (quoted or escaped), writing each
+    /// completion back in the same style.
+    fn complete_quoted_path(&self, word: &str, executables_only: bool) -> Vec<Completion> {
+        let unquoted = unquote(word);
+        let mut completions = if executables_only {
+            self.complete_path_executables(&unquoted.text)
+        } else {
+            self.complete_path(&unquoted.text)
+        };
+        for completion in &mut completions {
+            completion.text = quote_path(&completion.text, unquoted.quote, completion.kind == CompletionKind::Directory);
+        }
+        completions
+    }
+
+    /// Complete file/directory paths. `prefix` and the completion texts are
+    /// unquoted; [`Self::complete_quoted_path`] handles quoting.
     fn complete_path(&self, prefix: &str) -> Vec<Completion> {
         let mut completions = Vec::new();
 
@@ -353,28 +386,27 @@ impl<'a> CompletionEngine<'a> {
                     let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
                     let is_exec = !is_dir && is_executable(&entry.path());
 
-                    // Build the completion text with shell escaping
-                    let escaped_name = shell_escape(&name);
+                    // Build the completion text
                     let completion_text = if prefix.contains('/') {
                         // Preserve the path prefix
                         let path_prefix = prefix.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
                         if is_dir {
-                            format!("{}/{}/", path_prefix, escaped_name)
+                            format!("{}/{}/", path_prefix, name)
                         } else {
-                            format!("{}/{}", path_prefix, escaped_name)
+                            format!("{}/{}", path_prefix, name)
                         }
                     } else if prefix.starts_with('~') {
                         // Preserve tilde
                         if is_dir {
-                            format!("~/{}/", escaped_name)
+                            format!("~/{}/", name)
                         } else {
-                            format!("~/{}", escaped_name)
+                            format!("~/{}", name)
                         }
                     } else {
                         if is_dir {
-                            format!("{}/", escaped_name)
+                            format!("{}/", name)
                         } else {
-                            escaped_name
+                            name.clone()
                         }
                     };
 
@@ -413,41 +445,81 @@ impl<'a> CompletionEngine<'a> {
             .collect()
     }
 
-    /// Complete variable names.
+    /// Complete variable names: `$_`/`$_N` output references first (with a
+    /// preview of the stored value), then shell, environment, and special
+    /// variables. `$(` starts a command substitution, so it completes commands.
     fn complete_variable(&self, prefix: &str) -> Vec<Completion> {
+        if let Some(command) = prefix.strip_prefix("$(") {
+            let mut completions = self.complete_command(command);
+            for completion in &mut completions {
+                completion.text = format!("$({}", completion.text);
+            }
+            completions.sort_by_key(|c| std::cmp::Reverse(c.score));
+            return completions;
+        }
+
         let mut completions = Vec::new();
         let var_prefix = prefix.trim_start_matches('$').trim_start_matches('{');
         let needs_brace = prefix.contains('{');
+        let text_for = |name: &str| {
+            if needs_brace {
+                format!("${{{}}}", name)
+            } else {
+                format!("${}", name)
+            }
+        };
 
-        // Shell variables
-        for name in self.state.vars.keys() {
+        // Previous outputs: $_ / $prev, then $_1 (most recent), $_2, ...
+        let last = self.state.get_last_output().map(value_shape).unwrap_or_else(|| "empty".to_string());
+        for name in ["_", "prev"] {
             if name.starts_with(var_prefix) {
-                let text = if needs_brace {
-                    format!("${{{}}}", name)
-                } else {
-                    format!("${}", name)
-                };
+                completions.push(Completion {
+                    text: text_for(name),
+                    display: format!("$ {} (last output: {})", name, last),
+                    kind: CompletionKind::Variable,
+                    score: 110,
+                });
+            }
+        }
+        for (i, output) in self.state.block_outputs.iter().enumerate() {
+            let name = format!("_{}", i + 1);
+            if name.starts_with(var_prefix) {
+                completions.push(Completion {
+                    text: text_for(&name),
+                    display: format!("$ {} ({} \u{2190} {})", name, value_shape(&output.value), output.command),
+                    kind: CompletionKind::Variable,
+                    score: 100 - i as i32,
+                });
+            }
+        }
 
+        // Shell variables, including structured ones
+        for name in self.state.vars.keys() {
+            if name.starts_with(var_prefix) {
                 completions.push(Completion {
-                    text,
+                    text: text_for(name),
                     display: format!("$ {} (shell)", name),
                     kind: CompletionKind::Variable,
                     score: 90,
                 });
             }
         }
+        for (name, value) in &self.state.rich_vars {
+            if name.starts_with(var_prefix) {
+                completions.push(Completion {
+                    text: text_for(name),
+                    display: format!("$ {} (shell: {})", name, value_shape(value)),
+                    kind: CompletionKind::Variable,
+                    score: 90,
+                });
+            }
+        }
 
         // Environment variables
         for name in self.state.env.keys() {
             if name.starts_with(var_prefix) {
-                let text = if needs_brace {
-                    format!("${{{}}}", name)
-                } else {
-                    format!("${}", name)
-                };
-
                 completions.push(Completion {
-                    text,
+                    text: text_for(name),
                     display: format!("$ {} (env)", name),
                     kind: CompletionKind::Variable,
                     score: 80,
@@ -456,7 +528,7 @@ impl<'a> CompletionEngine<'a> {
         }
 
         // Special variables
-        let special = ["?", "$", "!", "#", "@", "*", "0", "_"];
+        let special = ["?", "$", "!", "#", "@", "*", "0"];
         for name in special {
             if name.starts_with(var_prefix) {
                 completions.push(Completion {
@@ -468,6 +540,10 @@ impl<'a> CompletionEngine<'a> {
             }
         }
 
+        // Highest score first; a name in several sources keeps its best entry.
+        completions.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.text.cmp(&b.text)));
+        let mut seen = HashSet::new();
+        completions.retain(|c| seen.insert(c.text.clone()));
         completions
     }
 
@@ -503,6 +579,26 @@ impl<'a> CompletionEngine<'a> {
         completions
     }
 
+    /// Complete the project's task names, described when the project
+    /// describes them. Falls back to paths when the project has none.
+    fn complete_task(&self, tool: TaskTool, prefix: &str) -> Vec<Completion> {
+        let tasks = project_tasks::discover_tool(&self.state.cwd, tool);
+        if tasks.is_empty() {
+            return self.complete_path(prefix);
+        }
+        tasks
+            .into_iter()
+            .filter(|task| task.name.starts_with(prefix))
+            .map(|task| {
+                let display = match &task.description {
+                    Some(description) => format!("{} {} \u{2014} {}", CompletionKind::Task.icon(), task.name, description),
+                    None => format!("{} {}", CompletionKind::Task.icon(), task.name),
+                };
+                Completion { text: task.name, display, kind: CompletionKind::Task, score: 90 }
+            })
+            .collect()
+    }
+
     /// Complete command flags.
     fn complete_flags(&self, cmd: &str, prefix: &str) -> Vec<Completion> {
         let mut completions = Vec::new();
@@ -557,6 +653,142 @@ enum CompletionContext {
     GitBranch(String),
     /// Completing a command flag.
     Flag(String),
+    /// Completing a project task name.
+    Task(TaskTool),
+}
+
+/// Node kinds that make up one shell word in the tree-sitter parse.
+const WORD_NODES: &[&str] = &["word", "string", "raw_string", "concatenation", "simple_expansion", "expansion", "number"];
+
+/// Start of the word ending at the end of `input`, from the tree-sitter
+/// parse. `None` if the line doesn't parse, as with an unclosed quote.
+fn parsed_word_start(input: &str) -> Option<usize> {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(&tree_sitter_bash::LANGUAGE.into()).ok()?;
+    let tree = parser.parse(input, None)?;
+    let root = tree.root_node();
+    if root.has_error() {
+        return None;
+    }
+    let end = input.len();
+    let mut node = root.descendant_for_byte_range(end.saturating_sub(1), end)?;
+    // Climb to the whole word: `~/a"b c"` is a concatenation of three nodes.
+    while let Some(parent) = node.parent()
+        && WORD_NODES.contains(&parent.kind())
+        && parent.end_byte() == end
+    {
+        node = parent;
+    }
+    if WORD_NODES.contains(&node.kind()) && node.end_byte() == end {
+        Some(node.start_byte())
+    } else {
+        // After a space or separator: a new, empty word.
+        Some(end)
+    }
+}
+
+/// Start of the word ending at the end of `input`, after the last
+/// separator that isn't quoted or escaped.
+fn scanned_word_start(input: &str) -> usize {
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some(_), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c.is_whitespace() || matches!(c, '|' | ';' | '&' | '>' | '<') => start = i + c.len_utf8(),
+            (None, _) => {}
+        }
+    }
+    start
+}
+
+/// A word with its quoting removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnquotedWord {
+    pub text: String,
+    /// The first quote character the word uses, if any.
+    pub quote: Option<char>,
+    /// A quote is still open at the end.
+    pub open: bool,
+}
+
+/// Remove quotes and backslash escapes from `word` as the shell would.
+pub fn unquote(word: &str) -> UnquotedWord {
+    let mut text = String::with_capacity(word.len());
+    let mut first_quote = None;
+    let mut quote = None;
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('"'), '\\') => match chars.peek() {
+                Some(&next) if matches!(next, '"' | '\\' | '$' | '`') => {
+                    text.push(next);
+                    chars.next();
+                }
+                _ => text.push('\\'),
+            },
+            (None, '\\') => text.extend(chars.next()),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                first_quote.get_or_insert(c);
+            }
+            (_, c) => text.push(c),
+        }
+    }
+    UnquotedWord { text, quote: first_quote, open: quote.is_some() }
+}
+
+/// Write an unquoted path back into the line. In `quote`s the quote is left
+/// open after a directory, so completion can continue inside it, and closed
+/// after a file; a leading `~/` stays outside so it still expands. Without
+/// quotes, special characters are backslash-escaped.
+fn quote_path(path: &str, quote: Option<char>, is_dir: bool) -> String {
+    let (tilde, rest) = match path.strip_prefix("~/") {
+        Some(rest) => ("~/", rest),
+        None if path == "~" => ("~", ""),
+        None => ("", path),
+    };
+    let mut out = String::with_capacity(path.len() + 4);
+    out.push_str(tilde);
+    match quote {
+        Some('\'') => {
+            out.push('\'');
+            out.push_str(&rest.replace('\'', "'\\''"));
+        }
+        Some(q) => {
+            out.push(q);
+            for c in rest.chars() {
+                if matches!(c, '"' | '\\' | '$' | '`') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        }
+        None => {
+            for c in rest.chars() {
+                if SHELL_META_CHARS.contains(&c) {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            return out;
+        }
+    }
+    if !is_dir {
+        out.push(quote.unwrap_or('"'));
+    }
+    out
 }
 
 /// Escape shell metacharacters in a completion string using backslashes.
@@ -604,7 +836,7 @@ pub fn longest_common_prefix(completions: &[Completion]) -> &str {
 
 /// Check if a path is executable.
 #[cfg(unix)]
-fn is_executable(path: &Path) -> bool {
+pub(crate) fn is_executable(path: &Path) -> bool {
     use std::os::unix::fs::PermissionsExt;
 
     if let Ok(metadata) = path.metadata() {
@@ -616,7 +848,7 @@ fn is_executable(path: &Path) -> bool {
 }
 
 #[cfg(not(unix))]
-fn is_executable(path: &Path) -> bool {
+pub(crate) fn is_executable(path: &Path) -> bool {
     // On non-Unix, check for common executable extensions
     path.extension()
         .map(|ext| {
@@ -629,6 +861,7 @@ fn is_executable(path: &Path) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nexus_api::Value;
 
     #[test]
     fn test_find_current_word() {
@@ -649,6 +882,67 @@ mod tests {
         assert_eq!(start, 18);
     }
 
+    #[test]
+    fn test_find_current_word_respects_quotes() {
+        let state = ShellState::from_cwd(std::env::current_dir().unwrap());
+        let commands = CommandRegistry::new();
+        let engine = CompletionEngine::new(&state, &commands);
+
+        assert_eq!(engine.find_current_word("ls My\\ Do"), ("My\\ Do".to_string(), 3));
+        assert_eq!(engine.find_current_word("ls \"My Do"), ("\"My Do".to_string(), 3));
+        assert_eq!(engine.find_current_word("cat 'a b' 'c; d"), ("'c; d".to_string(), 10));
+        assert_eq!(engine.find_current_word("ls ~/a\"b c\"x"), ("~/a\"b c\"x".to_string(), 3));
+        assert_eq!(engine.find_current_word("ls My\\ "), ("My\\ ".to_string(), 3));
+    }
+
+    #[test]
+    fn test_unquote_and_requote() {
+        let word = unquote("\"My Do");
+        assert_eq!((word.text.as_str(), word.quote, word.open), ("My Do", Some('"'), true));
+        assert_eq!(unquote("My\\ Docs/a\\&b").text, "My Docs/a&b");
+        assert_eq!(unquote("'it'\\''s'").text, "it's");
+
+        assert_eq!(quote_path("My Docs/", Some('"'), true), "\"My Docs/");
+        assert_eq!(quote_path("My Docs/a.txt", Some('"'), false), "\"My Docs/a.txt\"");
+        assert_eq!(quote_path("it's", Some('\''), false), "'it'\\''s'");
+        assert_eq!(quote_path("~/My Docs/", Some('"'), true), "~/\"My Docs/");
+        assert_eq!(quote_path("My Docs/(1).txt", None, false), "My\\ Docs/\\(1\\).txt");
+    }
+
+    #[test]
+    fn test_complete_path_inside_quotes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("My Docs")).unwrap();
+        std::fs::write(dir.path().join("My Docs").join("notes.txt"), "").unwrap();
+        let state = ShellState::from_cwd(dir.path().to_path_buf());
+        let commands = CommandRegistry::new();
+        let engine = CompletionEngine::new(&state, &commands);
+
+        let texts = |input: &str| -> Vec<String> { engine.complete(input, input.len()).0.into_iter().map(|c| c.text).collect() };
+        assert_eq!(texts("cd \"My"), ["\"My Docs/"]);
+        assert_eq!(texts("cat \"My Docs/no"), ["\"My Docs/notes.txt\""]);
+        assert_eq!(texts("cat My\\ D"), ["My\\ Docs/"]);
+        assert_eq!(texts("cat 'My Docs'/n"), ["'My Docs/notes.txt'"]);
+    }
+
+    #[test]
+    fn test_complete_task() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Makefile"), "build: ## Compile\ntest:\n").unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"scripts": {"lint": "eslint ."}}"#).unwrap();
+        let state = ShellState::from_cwd(dir.path().to_path_buf());
+        let commands = CommandRegistry::new();
+        let engine = CompletionEngine::new(&state, &commands);
+
+        let (completions, start) = engine.complete("make b", 6);
+        assert_eq!(start, 5);
+        assert_eq!(completions.len(), 1);
+        assert_eq!((completions[0].text.as_str(), completions[0].kind), ("build", CompletionKind::Task));
+        assert!(completions[0].display.ends_with("build \u{2014} Compile"));
+        let (completions, _) = engine.complete("ls && npm run ", 14);
+        assert_eq!(completions.iter().map(|c| c.text.as_str()).collect::<Vec<_>>(), ["lint"]);
+    }
+
     #[test]
     fn test_complete_command() {
         let state = ShellState::from_cwd(std::env::current_dir().unwrap());
@@ -658,4 +952,28 @@ mod tests {
         let completions = engine.complete_command("ec");
         assert!(completions.iter().any(|c| c.text == "echo"));
     }
+
+    #[test]
+    fn test_complete_variable_outputs_and_functions() {
+        let mut state = ShellState::from_cwd(std::env::current_dir().unwrap());
+        state.store_output(nexus_api::BlockId(1), "seq 3".to_string(), Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]));
+        state.store_output(nexus_api::BlockId(2), "whoami".to_string(), Value::String("ada".to_string()));
+        state.set_var_value("cfg", Value::Record(vec![("a".to_string(), Value::Int(1))]));
+        state.define_function("my_fn".to_string(), crate::parser::FunctionDef { name: "my_fn".to_string(), body: Vec::new() });
+        let commands = CommandRegistry::new();
+        let engine = CompletionEngine::new(&state, &commands);
+
+        let completions = engine.complete("echo $_", 7).0;
+        let texts: Vec<&str> = completions.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, ["$_", "$_1", "$_2"]);
+        assert!(completions[0].display.contains("string"));
+        assert!(completions[2].display.contains("list of 3"));
+
+        let completions = engine.complete("echo $cf", 8).0;
+        assert_eq!(completions[0].text, "$cfg");
+        assert!(completions[0].display.contains("record, 1 fields"));
+
+        let completions = engine.complete("echo $(my_", 10).0;
+        assert!(completions.iter().any(|c| c.text == "$(my_fn"));
+    }
 }
@@ -0,0 +1,1319 @@
+//! Layered configuration: `~/.nexus/config.toml`, overlaid by project
+//! `.nexus/config.toml` files found walking up from the working directory.
+//!
+//! ```toml
+//! [theme]
+//! accent = "#5fafff"      # focus ring
+//! path = "#87d787"        # working directory in the prompt
+//! symbols = true          # pair status colors with symbols, for color-blind users
+//!
+//! [aliases]
+//! t = "cargo test --workspace"
+//!
+//! [snippets]
+//! gcm = 'git commit -m ""'
+//!
+//! [workflows.release]
+//! description = "Test, tag and publish"
+//! steps = ["cargo test", "git tag v$VERSION", "cargo publish"]
+//!
+//! [open]
+//! pdf = "Preview"         # app for `open` and clicked paths, by file extension
+//!
+//! [font]
+//! size = 13
+//! ambiguous_wide = true   # box drawing, `±`, `…` take two columns, as in CJK fonts
+//!
+//! [keybindings]
+//! clear_screen = "cmd+l"
+//! release_focus = "ctrl+shift+q"   # take keys back from a terminal block
+//!
+//! [history]
+//! record = true           # write commands to the shell's history file
+//! ignore_space = true     # ...except those typed with a leading space
+//! restore = true          # show the previous session's blocks at startup
+//!
+//! [agent]
+//! max_turns = 50
+//! queue_offline = true    # hold queries typed while offline until the network returns
+//! ghost_completions = true # suggest the rest of a shell command after a pause in typing
+//! max_background = 2      # background tasks (`& prompt`) run at once
+//!
+//! [schedule]
+//! catch_up = "once"       # runs missed while Nexus was closed: "skip", "once" or "all"
+//!
+//! [sandbox]
+//! agent = "ask"           # or "accept-edits", "read-only"
+//! network = ["crates.io"] # hosts the agent may reach; or "any", "deny", "loopback"
+//!
+//! [env]
+//! EDITOR = "nvim"
+//!
+//! [path]
+//! prepend = ["~/.cargo/bin", "$HOME/bin"]
+//! append = ["/opt/tools/bin"]
+//!
+//! [environments]
+//! auto = true             # activate a directory's virtualenv, conda env or .nvmrc Node on cd
+//!
+//! [updates]
+//! check = true            # look for new releases at startup (off by default)
+//! feed = "https://api.github.com/repos/Deep-ai-inc/nexus/releases/latest"
+//!
+//! [crash]
+//! upload_url = "https://crash.example.com/nexus"   # where submitted crash reports go
+//!
+//! [pager]
+//! mode = "disable"        # pagers in kernel-run commands: "disable", "capture" or "keep"
+//!
+//! [pager.commands]
+//! git = "capture"         # show `git log` and friends in Nexus's own pager
+//!
+//! [color]
+//! mode = "auto"           # color from external commands: "auto", "always" or "never"
+//!
+//! [color.commands]
+//! terraform = "never"
+//!
+//! [fullscreen]
+//! commands = ["vim", "nvim", "htop", "ssh", "tmux"]   # run in a full-window terminal
+//!
+//! [lint]
+//! useless-cat = "off"     # per rule: "off", "info", "warning" or "error"
+//!
+//! [warnings]
+//! node-version = false    # hide a provider's warning above the input, by id
+//! ```
+//!
+//! Layers merge key by key, later ones winning: the user file, then project
+//! files from the outermost directory to the nearest. Every merged value
+//! remembers the file it came from, which `config show --origin` displays.
+//! `[aliases]`, `[open]`, `[history]`, `[agent]`, `[schedule]`, `[sandbox]`,
+//! `[env]`, `[path]`, `[environments]`, `[updates]` and `[crash]` are only
+//! read from the user file, so a checked-out repository cannot redefine
+//! commands or the programs files open with, loosen them, put its own
+//! programs on `PATH`, or point the updater or crash reports somewhere else.
+//!
+//! [`set_setting`] and [`unset_setting`] edit a file in place, keeping its
+//! comments and layout.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+/// Directory holding a project's configuration.
+pub const PROJECT_DIR: &str = ".nexus";
+
+/// Configuration file name, in `~/.nexus` and in project directories.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// One configuration file as written.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    theme: ThemeSection,
+    #[serde(default)]
+    aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    snippets: BTreeMap<String, String>,
+    #[serde(default)]
+    workflows: BTreeMap<String, Workflow>,
+    #[serde(default)]
+    open: BTreeMap<String, String>,
+    #[serde(default)]
+    font: FontSection,
+    #[serde(default)]
+    keybindings: BTreeMap<String, String>,
+    history: Option<HistorySection>,
+    agent: Option<AgentSection>,
+    schedule: Option<ScheduleSection>,
+    sandbox: Option<SandboxSection>,
+    env: Option<BTreeMap<String, String>>,
+    path: Option<PathSection>,
+    environments: Option<EnvironmentsSection>,
+    updates: Option<UpdatesSection>,
+    crash: Option<CrashSection>,
+    #[serde(default)]
+    pager: PagerSection,
+    #[serde(default)]
+    color: ColorSection,
+    #[serde(default)]
+    fullscreen: FullscreenSection,
+    #[serde(default)]
+    lint: BTreeMap<String, LintSeverity>,
+    #[serde(default)]
+    warnings: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeSection {
+    accent: Option<String>,
+    path: Option<String>,
+    symbols: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FontSection {
+    size: Option<f32>,
+    ambiguous_wide: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HistorySection {
+    record: Option<bool>,
+    ignore_space: Option<bool>,
+    restore: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AgentSection {
+    max_turns: Option<u32>,
+    queue_offline: Option<bool>,
+    ghost_completions: Option<bool>,
+    max_background: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleSection {
+    catch_up: Option<CatchUp>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SandboxSection {
+    agent: Option<SandboxPolicy>,
+    network: Option<NetworkPolicy>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathSection {
+    #[serde(default)]
+    prepend: Vec<String>,
+    #[serde(default)]
+    append: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EnvironmentsSection {
+    auto: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdatesSection {
+    check: Option<bool>,
+    feed: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CrashSection {
+    upload_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PagerSection {
+    mode: Option<PagerMode>,
+    #[serde(default)]
+    commands: BTreeMap<String, PagerMode>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorSection {
+    mode: Option<ColorMode>,
+    #[serde(default)]
+    commands: BTreeMap<String, ColorMode>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FullscreenSection {
+    commands: Option<Vec<String>>,
+}
+
+/// Programs that get the whole window when `[fullscreen] commands` is unset.
+pub const DEFAULT_FULLSCREEN_COMMANDS: &[&str] = &["vim", "nvim", "htop", "ssh", "tmux"];
+
+/// Background agent tasks run at once when `[agent] max_background` is unset.
+pub const DEFAULT_MAX_BACKGROUND: usize = 2;
+
+/// What happens when an external command run by the kernel would start a
+/// pager. Its output goes to a block, so nobody could scroll or quit one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PagerMode {
+    /// Point `PAGER` and friends at `cat`, so output goes straight to the block.
+    #[default]
+    Disable,
+    /// As `Disable`, and collect the output into Nexus's pager viewer.
+    Capture,
+    /// Leave the environment alone.
+    Keep,
+}
+
+impl PagerMode {
+    pub const ALL: [Self; 3] = [Self::Disable, Self::Capture, Self::Keep];
+
+    /// The name used in the config file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Disable => "disable",
+            Self::Capture => "capture",
+            Self::Keep => "keep",
+        }
+    }
+
+    /// Environment overrides that keep common programs from paging.
+    pub fn env(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Disable | Self::Capture => &[
+                ("PAGER", "cat"),
+                ("GIT_PAGER", "cat"),
+                ("MANPAGER", "cat"),
+                ("SYSTEMD_PAGER", "cat"),
+                ("AWS_PAGER", ""),
+            ],
+            Self::Keep => &[],
+        }
+    }
+}
+
+/// Whether external commands run by the kernel are told to use color. A
+/// block renders color whether the command wrote to a terminal or a pipe;
+/// see [`crate::process::color`] for the environment each mode sets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorMode {
+    /// Color wherever the output ends up in a block, none when it is parsed.
+    #[default]
+    Auto,
+    /// Ask for color even when the output is parsed (escapes are stripped).
+    Always,
+    /// Ask for no color, even on a terminal.
+    Never,
+}
+
+impl ColorMode {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Always, Self::Never];
+
+    /// The name used in the config file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Always => "always",
+            Self::Never => "never",
+        }
+    }
+}
+
+/// How a lint rule's findings are shown, see [`crate::lint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintSeverity {
+    /// Not checked.
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl LintSeverity {
+    pub const ALL: [Self; 4] = [Self::Off, Self::Info, Self::Warning, Self::Error];
+
+    /// The name used in the config file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// What to do about a scheduled command's runs that were missed while
+/// Nexus wasn't running, see [`crate::scheduler`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CatchUp {
+    /// Drop them; only runs due just now happen.
+    Skip,
+    /// Run once for however many were missed.
+    #[default]
+    Once,
+    /// Run once for each one missed.
+    All,
+}
+
+impl CatchUp {
+    pub const ALL: [Self; 3] = [Self::Skip, Self::Once, Self::All];
+
+    /// The name used in the config file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Once => "once",
+            Self::All => "all",
+        }
+    }
+}
+
+/// What the agent may do without asking first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SandboxPolicy {
+    /// Read freely; ask before running commands or changing files.
+    #[default]
+    Ask,
+    /// Change files freely; ask before running commands.
+    AcceptEdits,
+    /// Never run commands or change files.
+    ReadOnly,
+}
+
+impl SandboxPolicy {
+    pub const ALL: [Self; 3] = [Self::Ask, Self::AcceptEdits, Self::ReadOnly];
+
+    /// The name used in the config file.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ask => "ask",
+            Self::AcceptEdits => "accept-edits",
+            Self::ReadOnly => "read-only",
+        }
+    }
+}
+
+/// The hosts the agent may reach, enforced by [`crate::egress`]: its
+/// process can only connect to Nexus, which forwards the connections this
+/// lets through.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawNetworkPolicy")]
+pub enum NetworkPolicy {
+    /// Anywhere; the agent runs unconfined.
+    #[default]
+    Any,
+    /// Nowhere but the agent's own service.
+    Deny,
+    /// Only this machine: `localhost` and loopback addresses.
+    AllowLoopback,
+    /// These domains and their subdomains, and this machine.
+    AllowList(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawNetworkPolicy {
+    Name(String),
+    Hosts(Vec<String>),
+}
+
+impl TryFrom<RawNetworkPolicy> for NetworkPolicy {
+    type Error = String;
+
+    fn try_from(raw: RawNetworkPolicy) -> Result<Self, String> {
+        match raw {
+            RawNetworkPolicy::Name(name) => match name.as_str() {
+                "any" => Ok(Self::Any),
+                "deny" => Ok(Self::Deny),
+                "loopback" => Ok(Self::AllowLoopback),
+                _ => Err(format!("unknown network policy `{name}`: expected \"any\", \"deny\", \"loopback\" or a list of hosts")),
+            },
+            RawNetworkPolicy::Hosts(hosts) => Ok(Self::AllowList(
+                hosts.iter().map(|h| h.trim().trim_start_matches("*.").trim_end_matches('.').to_ascii_lowercase()).collect(),
+            )),
+        }
+    }
+}
+
+impl NetworkPolicy {
+    /// Whether any host is off limits.
+    pub fn restricts(&self) -> bool {
+        *self != Self::Any
+    }
+
+    /// Whether `host` (a name or address, without port) may be reached.
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']').trim_end_matches('.').to_ascii_lowercase();
+        let loopback = host == "localhost"
+            || host.ends_with(".localhost")
+            || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback());
+        match self {
+            Self::Any => true,
+            Self::Deny => false,
+            Self::AllowLoopback => loopback,
+            Self::AllowList(domains) => loopback || domains.iter().any(|domain| in_domain(&host, domain)),
+        }
+    }
+
+    /// How the policy reads in the config file.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Any => "any".to_string(),
+            Self::Deny => "deny".to_string(),
+            Self::AllowLoopback => "loopback".to_string(),
+            Self::AllowList(domains) => domains.join(" "),
+        }
+    }
+}
+
+/// Whether lowercase `host` is `domain` or one of its subdomains.
+pub fn in_domain(host: &str, domain: &str) -> bool {
+    host == domain || host.strip_suffix(domain).is_some_and(|rest| rest.ends_with('.'))
+}
+
+/// A named sequence of commands, run one after another.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<String>,
+}
+
+impl Workflow {
+    /// The steps as one command line that stops at the first failure.
+    pub fn command_line(&self) -> String {
+        self.steps.join(" && ")
+    }
+}
+
+/// Where a setting came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    User(PathBuf),
+    Project(PathBuf),
+}
+
+impl Origin {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::User(path) | Self::Project(path) => path,
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(path) => write!(f, "user ({})", path.display()),
+            Self::Project(path) => write!(f, "project ({})", path.display()),
+        }
+    }
+}
+
+/// A merged value and the file that set it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub origin: Origin,
+}
+
+/// The merged configuration for one working directory.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub accent: Option<Setting<String>>,
+    pub path_color: Option<Setting<String>>,
+    /// Pair status colors with symbols: tool status shapes, and a gutter
+    /// marking red, green and yellow terminal rows.
+    pub theme_symbols: Option<Setting<bool>>,
+    pub aliases: BTreeMap<String, Setting<String>>,
+    pub snippets: BTreeMap<String, Setting<String>>,
+    pub workflows: BTreeMap<String, Setting<Workflow>>,
+    /// Applications to open files with, by file extension.
+    pub openers: BTreeMap<String, Setting<String>>,
+    pub font_size: Option<Setting<f32>>,
+    pub font_ambiguous_wide: Option<Setting<bool>>,
+    /// Key chords by UI action name.
+    pub keybindings: BTreeMap<String, Setting<String>>,
+    pub history_record: Option<Setting<bool>>,
+    pub history_ignore_space: Option<Setting<bool>>,
+    pub history_restore: Option<Setting<bool>>,
+    pub agent_max_turns: Option<Setting<u32>>,
+    pub agent_queue_offline: Option<Setting<bool>>,
+    pub agent_ghost_completions: Option<Setting<bool>>,
+    pub agent_max_background: Option<Setting<usize>>,
+    pub schedule_catch_up: Option<Setting<CatchUp>>,
+    pub sandbox: Option<Setting<SandboxPolicy>>,
+    pub sandbox_network: Option<Setting<NetworkPolicy>>,
+    /// Environment variables set at startup; values may use `~` and `$VAR`.
+    pub env: BTreeMap<String, Setting<String>>,
+    /// Directories put in front of, and after, the inherited `PATH`.
+    pub path_prepend: Vec<Setting<String>>,
+    pub path_append: Vec<Setting<String>>,
+    /// Whether entering a directory activates its project environments.
+    pub environments_auto: Option<Setting<bool>>,
+    pub updates_check: Option<Setting<bool>>,
+    /// Release feed URL, replacing [`crate::update::DEFAULT_FEED`].
+    pub updates_feed: Option<Setting<String>>,
+    /// Where submitted crash reports are posted; without it they stay local.
+    pub crash_upload_url: Option<Setting<String>>,
+    pub pager_mode: Option<Setting<PagerMode>>,
+    /// Pager handling by command name, overriding `pager_mode`.
+    pub pager_commands: BTreeMap<String, Setting<PagerMode>>,
+    pub color_mode: Option<Setting<ColorMode>>,
+    /// Color policy by command name, overriding `color_mode`.
+    pub color_commands: BTreeMap<String, Setting<ColorMode>>,
+    /// Programs run in a full-window terminal, replacing
+    /// [`DEFAULT_FULLSCREEN_COMMANDS`].
+    pub fullscreen_commands: Option<Setting<Vec<String>>>,
+    /// Severity by lint rule name, overriding the rule's default.
+    pub lint: BTreeMap<String, Setting<LintSeverity>>,
+    /// Whether each provider warning is shown above the input, by id.
+    pub warnings: BTreeMap<String, Setting<bool>>,
+    /// Files that were merged, lowest precedence first.
+    pub sources: Vec<Origin>,
+    /// Files that exist but could not be used, with the reason.
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl Config {
+    /// The configuration in effect in `cwd`.
+    pub fn load(cwd: &Path) -> Self {
+        Self::load_layers(user_config_path().as_deref(), cwd)
+    }
+
+    pub(crate) fn load_layers(user: Option<&Path>, cwd: &Path) -> Self {
+        let mut config = Self::default();
+        if let Some(user) = user {
+            config.merge_file(Origin::User(user.to_path_buf()));
+        }
+        let mut projects: Vec<PathBuf> = cwd
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_DIR).join(CONFIG_FILE))
+            // `~/.nexus/config.toml` is the user file, not a project's.
+            .filter(|path| Some(path.as_path()) != user && path.is_file())
+            .collect();
+        projects.reverse();
+        for path in projects {
+            config.merge_file(Origin::Project(path));
+        }
+        config
+    }
+
+    fn merge_file(&mut self, origin: Origin) {
+        let path = origin.path();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                self.errors.push((path.to_path_buf(), e.to_string()));
+                return;
+            }
+        };
+        match toml::from_str::<ConfigFile>(&text) {
+            Ok(file) => self.merge(file, origin),
+            Err(e) => self.errors.push((path.to_path_buf(), e.message().to_string())),
+        }
+    }
+
+    fn merge(&mut self, file: ConfigFile, origin: Origin) {
+        fn overlay<T>(into: &mut BTreeMap<String, Setting<T>>, from: BTreeMap<String, T>, origin: &Origin) {
+            into.extend(from.into_iter().map(|(k, value)| (k, Setting { value, origin: origin.clone() })));
+        }
+        fn set<T>(slot: &mut Option<Setting<T>>, value: Option<T>, origin: &Origin) {
+            if let Some(value) = value {
+                *slot = Some(Setting { value, origin: origin.clone() });
+            }
+        }
+        set(&mut self.accent, file.theme.accent, &origin);
+        set(&mut self.path_color, file.theme.path, &origin);
+        set(&mut self.theme_symbols, file.theme.symbols, &origin);
+        set(&mut self.font_size, file.font.size, &origin);
+        set(&mut self.font_ambiguous_wide, file.font.ambiguous_wide, &origin);
+        overlay(&mut self.snippets, file.snippets, &origin);
+        overlay(&mut self.workflows, file.workflows, &origin);
+        overlay(&mut self.keybindings, file.keybindings, &origin);
+        set(&mut self.pager_mode, file.pager.mode, &origin);
+        overlay(&mut self.pager_commands, file.pager.commands, &origin);
+        set(&mut self.color_mode, file.color.mode, &origin);
+        overlay(&mut self.color_commands, file.color.commands, &origin);
+        set(&mut self.fullscreen_commands, file.fullscreen.commands, &origin);
+        overlay(&mut self.lint, file.lint, &origin);
+        overlay(&mut self.warnings, file.warnings, &origin);
+
+        if let Origin::Project(path) = &origin
+            && (!file.aliases.is_empty()
+                || !file.open.is_empty()
+                || file.history.is_some()
+                || file.agent.is_some()
+                || file.schedule.is_some()
+                || file.sandbox.is_some()
+                || file.env.is_some()
+                || file.path.is_some()
+                || file.environments.is_some()
+                || file.updates.is_some()
+                || file.crash.is_some())
+        {
+            self.errors.push((
+                path.clone(),
+                "[aliases], [open], [history], [agent], [schedule], [sandbox], [env], [path], [environments], [updates] and [crash] are only read from the user config"
+                    .to_string(),
+            ));
+        } else {
+            overlay(&mut self.aliases, file.aliases, &origin);
+            overlay(&mut self.openers, file.open, &origin);
+            let history = file.history.unwrap_or_default();
+            set(&mut self.history_record, history.record, &origin);
+            set(&mut self.history_ignore_space, history.ignore_space, &origin);
+            set(&mut self.history_restore, history.restore, &origin);
+            let agent = file.agent.unwrap_or_default();
+            set(&mut self.agent_max_turns, agent.max_turns, &origin);
+            set(&mut self.agent_queue_offline, agent.queue_offline, &origin);
+            set(&mut self.agent_ghost_completions, agent.ghost_completions, &origin);
+            set(&mut self.agent_max_background, agent.max_background, &origin);
+            set(&mut self.schedule_catch_up, file.schedule.unwrap_or_default().catch_up, &origin);
+            let sandbox = file.sandbox.unwrap_or_default();
+            set(&mut self.sandbox, sandbox.agent, &origin);
+            set(&mut self.sandbox_network, sandbox.network, &origin);
+            overlay(&mut self.env, file.env.unwrap_or_default(), &origin);
+            let path = file.path.unwrap_or_default();
+            let setting = |value| Setting { value, origin: origin.clone() };
+            self.path_prepend.extend(path.prepend.into_iter().map(setting));
+            self.path_append.extend(path.append.into_iter().map(setting));
+            set(&mut self.environments_auto, file.environments.unwrap_or_default().auto, &origin);
+            let updates = file.updates.unwrap_or_default();
+            set(&mut self.updates_check, updates.check, &origin);
+            set(&mut self.updates_feed, updates.feed, &origin);
+            set(&mut self.crash_upload_url, file.crash.unwrap_or_default().upload_url, &origin);
+        }
+        self.sources.push(origin);
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(|s| s.value.as_str())
+    }
+
+    pub fn snippet(&self, name: &str) -> Option<&str> {
+        self.snippets.get(name).map(|s| s.value.as_str())
+    }
+
+    pub fn keybinding(&self, action: &str) -> Option<&str> {
+        self.keybindings.get(action).map(|s| s.value.as_str())
+    }
+
+    pub fn font_size(&self) -> Option<f32> {
+        self.font_size.as_ref().map(|s| s.value)
+    }
+
+    /// Whether status colors are paired with symbols.
+    pub fn symbols(&self) -> bool {
+        self.theme_symbols.as_ref().is_some_and(|s| s.value)
+    }
+
+    /// Whether East Asian ambiguous-width characters take two columns.
+    pub fn ambiguous_wide(&self) -> bool {
+        self.font_ambiguous_wide.as_ref().is_some_and(|s| s.value)
+    }
+
+    /// Whether a submitted command goes to the shell history file.
+    /// `leading_space` is whether it was typed with one.
+    pub fn records_history(&self, leading_space: bool) -> bool {
+        let record = self.history_record.as_ref().is_none_or(|s| s.value);
+        let ignore_space = self.history_ignore_space.as_ref().is_some_and(|s| s.value);
+        record && !(leading_space && ignore_space)
+    }
+
+    /// Whether startup shows the previous session's blocks above the prompt.
+    pub fn restores_session(&self) -> bool {
+        self.history_restore.as_ref().is_none_or(|s| s.value)
+    }
+
+    pub fn agent_max_turns(&self) -> Option<u32> {
+        self.agent_max_turns.as_ref().map(|s| s.value)
+    }
+
+    /// Whether agent queries typed while offline wait for the network
+    /// instead of being sent anyway.
+    pub fn queues_agent_offline(&self) -> bool {
+        self.agent_queue_offline.as_ref().is_some_and(|s| s.value)
+    }
+
+    /// Whether the agent suggests the rest of a shell command as ghost
+    /// text once typing pauses. Off unless turned on: each suggestion is
+    /// a model call.
+    pub fn agent_ghost_completions(&self) -> bool {
+        self.agent_ghost_completions.as_ref().is_some_and(|s| s.value)
+    }
+
+    /// How many background agent tasks may run at once; the rest wait.
+    pub fn agent_max_background(&self) -> usize {
+        self.agent_max_background.as_ref().map_or(DEFAULT_MAX_BACKGROUND, |s| s.value.max(1))
+    }
+
+    /// What to do about scheduled runs missed while Nexus was closed.
+    pub fn schedule_catch_up(&self) -> CatchUp {
+        self.schedule_catch_up.as_ref().map(|s| s.value).unwrap_or_default()
+    }
+
+    pub fn sandbox_policy(&self) -> SandboxPolicy {
+        self.sandbox.as_ref().map(|s| s.value).unwrap_or_default()
+    }
+
+    /// The hosts the agent may reach; any unless restricted.
+    pub fn network_policy(&self) -> NetworkPolicy {
+        self.sandbox_network.as_ref().map(|s| s.value.clone()).unwrap_or_default()
+    }
+
+    /// Whether entering a directory activates the virtualenv, conda
+    /// environment or Node version it has (see [`crate::dev_env`]). Off
+    /// unless turned on: it puts a checkout's programs first on `PATH`.
+    pub fn auto_activates_environments(&self) -> bool {
+        self.environments_auto.as_ref().is_some_and(|s| s.value)
+    }
+
+    /// Whether to look for a new release at startup. Off unless the user
+    /// opts in.
+    pub fn checks_for_updates(&self) -> bool {
+        self.updates_check.as_ref().is_some_and(|s| s.value)
+    }
+
+    pub fn update_feed(&self) -> &str {
+        self.updates_feed.as_ref().map_or(crate::update::DEFAULT_FEED, |s| s.value.as_str())
+    }
+
+    pub fn crash_upload_url(&self) -> Option<&str> {
+        self.crash_upload_url.as_ref().map(|s| s.value.as_str())
+    }
+
+    /// How to handle a pager started by `command` (a name or path).
+    pub fn pager_mode(&self, command: &str) -> PagerMode {
+        let name = command.rsplit('/').next().unwrap_or(command);
+        self.pager_commands
+            .get(name)
+            .or(self.pager_mode.as_ref())
+            .map(|s| s.value)
+            .unwrap_or_default()
+    }
+
+    /// The color policy for external command `command`.
+    pub fn color_mode(&self, command: &str) -> ColorMode {
+        let name = command.rsplit('/').next().unwrap_or(command);
+        self.color_commands
+            .get(name)
+            .or(self.color_mode.as_ref())
+            .map(|s| s.value)
+            .unwrap_or_default()
+    }
+
+    /// The configured severity of the lint rule `rule`, if any.
+    pub fn lint_severity(&self, rule: &str) -> Option<LintSeverity> {
+        self.lint.get(rule).map(|s| s.value)
+    }
+
+    /// Whether the provider warning `id` is shown; all are unless turned off.
+    pub fn shows_warning(&self, id: &str) -> bool {
+        self.warnings.get(id).is_none_or(|s| s.value)
+    }
+
+    /// Whether `command_line` starts a program that gets the whole window,
+    /// looking past leading `NAME=value` assignments and `sudo`.
+    pub fn runs_fullscreen(&self, command_line: &str) -> bool {
+        let Some(program) = command_line
+            .split_whitespace()
+            .find(|word| *word != "sudo" && !word.contains('='))
+        else {
+            return false;
+        };
+        let name = program.rsplit('/').next().unwrap_or(program);
+        match &self.fullscreen_commands {
+            Some(setting) => setting.value.iter().any(|c| c == name),
+            None => DEFAULT_FULLSCREEN_COMMANDS.contains(&name),
+        }
+    }
+
+    /// Bring `env` up to date with `[env]` and `[path]`. Only variables
+    /// whose configured value differs from `previous` are written, so a
+    /// reload doesn't undo an `export` made since; `PATH` gains the
+    /// configured directories it doesn't already have.
+    pub fn apply_env(&self, previous: &Config, env: &mut HashMap<String, String>) {
+        for (name, setting) in &self.env {
+            if previous.env.get(name).map(|s| &s.value) != Some(&setting.value) {
+                let value = expand_vars(&setting.value, env);
+                env.insert(name.clone(), value);
+            }
+        }
+
+        let path = env.get("PATH").cloned().unwrap_or_default();
+        let mut dirs: Vec<String> = std::env::split_paths(&path)
+            .map(|p| p.to_string_lossy().into_owned())
+            .filter(|d| !d.is_empty())
+            .collect();
+        let expand = |list: &[Setting<String>]| -> Vec<String> {
+            list.iter().map(|s| expand_vars(&s.value, env)).filter(|d| !dirs.contains(d)).collect()
+        };
+        let (front, back) = (expand(&self.path_prepend), expand(&self.path_append));
+        if front.is_empty() && back.is_empty() {
+            return;
+        }
+        dirs.splice(0..0, front);
+        dirs.extend(back);
+        env.insert("PATH".to_string(), dirs.join(":"));
+    }
+
+    /// The application configured to open `file`, chosen by its extension.
+    pub fn opener_for(&self, file: &Path) -> Option<&str> {
+        let ext = file.extension()?.to_str()?;
+        self.openers.get(ext).map(|s| s.value.as_str())
+    }
+
+    /// Every setting as `(key, value, origin)`, sorted by key.
+    pub fn entries(&self) -> Vec<(String, String, &Origin)> {
+        let mut rows = Vec::new();
+        let scalars = [
+            ("theme.accent", self.accent.as_ref().map(|s| (s.value.clone(), &s.origin))),
+            ("theme.path", self.path_color.as_ref().map(|s| (s.value.clone(), &s.origin))),
+            ("theme.symbols", self.theme_symbols.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("font.size", self.font_size.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("font.ambiguous_wide", self.font_ambiguous_wide.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("history.record", self.history_record.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("history.ignore_space", self.history_ignore_space.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("history.restore", self.history_restore.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("agent.max_turns", self.agent_max_turns.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("agent.queue_offline", self.agent_queue_offline.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("agent.ghost_completions", self.agent_ghost_completions.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("agent.max_background", self.agent_max_background.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("schedule.catch_up", self.schedule_catch_up.as_ref().map(|s| (s.value.as_str().to_string(), &s.origin))),
+            ("sandbox.agent", self.sandbox.as_ref().map(|s| (s.value.as_str().to_string(), &s.origin))),
+            ("sandbox.network", self.sandbox_network.as_ref().map(|s| (s.value.describe(), &s.origin))),
+            ("environments.auto", self.environments_auto.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("updates.check", self.updates_check.as_ref().map(|s| (s.value.to_string(), &s.origin))),
+            ("updates.feed", self.updates_feed.as_ref().map(|s| (s.value.clone(), &s.origin))),
+            ("crash.upload_url", self.crash_upload_url.as_ref().map(|s| (s.value.clone(), &s.origin))),
+            ("pager.mode", self.pager_mode.as_ref().map(|s| (s.value.as_str().to_string(), &s.origin))),
+            ("color.mode", self.color_mode.as_ref().map(|s| (s.value.as_str().to_string(), &s.origin))),
+            ("fullscreen.commands", self.fullscreen_commands.as_ref().map(|s| (s.value.join(" "), &s.origin))),
+        ];
+        for (key, setting) in scalars {
+            if let Some((value, origin)) = setting {
+                rows.push((key.to_string(), value, origin));
+            }
+        }
+        let sections = [
+            ("aliases", &self.aliases),
+            ("snippets", &self.snippets),
+            ("open", &self.openers),
+            ("keybindings", &self.keybindings),
+        ];
+        for (section, map) in sections {
+            rows.extend(map.iter().map(|(k, s)| (format!("{}.{}", section, k), s.value.clone(), &s.origin)));
+        }
+        rows.extend(self.env.iter().map(|(k, s)| (format!("env.{}", k), s.value.clone(), &s.origin)));
+        rows.extend(
+            self.pager_commands
+                .iter()
+                .map(|(k, s)| (format!("pager.commands.{}", k), s.value.as_str().to_string(), &s.origin)),
+        );
+        rows.extend(
+            self.color_commands
+                .iter()
+                .map(|(k, s)| (format!("color.commands.{}", k), s.value.as_str().to_string(), &s.origin)),
+        );
+        rows.extend(self.lint.iter().map(|(k, s)| (format!("lint.{}", k), s.value.as_str().to_string(), &s.origin)));
+        rows.extend(self.warnings.iter().map(|(k, s)| (format!("warnings.{}", k), s.value.to_string(), &s.origin)));
+        for (key, list) in [("path.prepend", &self.path_prepend), ("path.append", &self.path_append)] {
+            if let Some(first) = list.first() {
+                let value = list.iter().map(|s| s.value.as_str()).collect::<Vec<_>>().join(":");
+                rows.push((key.to_string(), value, &first.origin));
+            }
+        }
+        rows.extend(
+            self.workflows
+                .iter()
+                .map(|(k, s)| (format!("workflows.{}", k), s.value.command_line(), &s.origin)),
+        );
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        rows
+    }
+}
+
+/// `~/.nexus/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".nexus").join(CONFIG_FILE))
+}
+
+/// Set the dotted `key` (e.g. `font.size`) in the config file at `path`,
+/// creating the file if needed. The edit is refused, leaving the file as it
+/// was, if the result would not be a valid configuration.
+pub fn set_setting(path: &Path, key: &str, value: impl Into<toml_edit::Value>) -> anyhow::Result<()> {
+    set_settings(path, [(key.to_string(), value.into())])
+}
+
+/// Set several dotted keys in one write; nothing is written unless all of
+/// them can be.
+pub fn set_settings(
+    path: &Path,
+    settings: impl IntoIterator<Item = (String, toml_edit::Value)>,
+) -> anyhow::Result<()> {
+    let (_, text) = preview_settings(path, settings)?;
+    write_file(path, text)
+}
+
+/// The text of the config file at `path` before and after
+/// [`set_settings`], without writing it.
+pub fn preview_settings(
+    path: &Path,
+    settings: impl IntoIterator<Item = (String, toml_edit::Value)>,
+) -> anyhow::Result<(String, String)> {
+    edited_text(path, |doc| {
+        for (key, value) in settings {
+            let (table, name) = parent_table(doc, &key)?;
+            table.insert(name, toml_edit::Item::Value(value));
+        }
+        Ok(())
+    })
+}
+
+/// Remove the dotted `key` from the config file at `path`, so a lower layer
+/// or the default applies again.
+pub fn unset_setting(path: &Path, key: &str) -> anyhow::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    edit_file(path, |doc| {
+        let (table, name) = parent_table(doc, key)?;
+        table.remove(name);
+        Ok(())
+    })
+}
+
+fn parent_table<'d, 'k>(
+    doc: &'d mut toml_edit::DocumentMut,
+    key: &'k str,
+) -> anyhow::Result<(&'d mut toml_edit::Table, &'k str)> {
+    let (section, name) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut table = doc.as_table_mut();
+    for part in section.split('.').filter(|p| !p.is_empty()) {
+        table = table
+            .entry(part)
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .with_context(|| format!("'{}' is not a section", part))?;
+    }
+    Ok((table, name))
+}
+
+fn edit_file(path: &Path, edit: impl FnOnce(&mut toml_edit::DocumentMut) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let (_, text) = edited_text(path, edit)?;
+    write_file(path, text)
+}
+
+/// The file's current text and its text after `edit`, checked to still be
+/// a valid configuration.
+fn edited_text(
+    path: &Path,
+    edit: impl FnOnce(&mut toml_edit::DocumentMut) -> anyhow::Result<()>,
+) -> anyhow::Result<(String, String)> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let mut doc: toml_edit::DocumentMut = text.parse().with_context(|| format!("invalid {}", path.display()))?;
+    edit(&mut doc)?;
+    let edited = doc.to_string();
+    toml::from_str::<ConfigFile>(&edited).map_err(|e| anyhow::anyhow!("{}", e.message()))?;
+    Ok((text, edited))
+}
+
+fn write_file(path: &Path, text: String) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let tmp = path.with_extension("toml.tmp");
+    std::fs::write(&tmp, text).with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Expand a leading `~` and `$VAR` / `${VAR}` from `env`. Unset variables
+/// expand to nothing, as in the shell.
+fn expand_vars(value: &str, env: &HashMap<String, String>) -> String {
+    let home = env.get("HOME").map(String::as_str).unwrap_or("");
+    let value = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => value.to_string(),
+    };
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value.as_str();
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, tail) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after),
+            },
+            None => {
+                let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if name.is_empty() {
+            out.push('$');
+        } else {
+            out.push_str(env.get(name).map(String::as_str).unwrap_or(""));
+        }
+        rest = tail;
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, text: &str) -> PathBuf {
+        let path = dir.join(PROJECT_DIR).join(CONFIG_FILE);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_nearest_project_wins_over_outer_and_user() {
+        let home = tempfile::tempdir().unwrap();
+        let user = write(home.path(), "[snippets]\nt = \"user\"\nb = \"build\"\n[theme]\naccent = \"#111111\"\n");
+        let outer = home.path().join("work");
+        let outer_file = write(&outer, "[snippets]\nt = \"outer\"\n");
+        let inner = outer.join("crate");
+        let inner_file = write(&inner, "[snippets]\nt = \"inner\"\n");
+
+        let config = Config::load_layers(Some(&user), &inner.join("src"));
+        assert_eq!(config.snippets["t"].value, "inner");
+        assert_eq!(config.snippets["t"].origin, Origin::Project(inner_file));
+        assert_eq!(config.snippets["b"].value, "build");
+        assert_eq!(config.accent.as_ref().unwrap().origin, Origin::User(user.clone()));
+        assert_eq!(config.sources.len(), 3);
+        assert_eq!(config.sources[1], Origin::Project(outer_file));
+    }
+
+    #[test]
+    fn test_user_file_is_not_read_twice_as_project() {
+        let home = tempfile::tempdir().unwrap();
+        let user = write(home.path(), "[aliases]\nt = \"user\"\n");
+        let config = Config::load_layers(Some(&user), home.path());
+        assert_eq!(config.sources, vec![Origin::User(user)]);
+    }
+
+    #[test]
+    fn test_invalid_file_is_reported_and_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "[aliases]\nt = 3\n");
+        let config = Config::load_layers(None, dir.path());
+        assert!(config.aliases.is_empty());
+        assert_eq!(config.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_opener_for_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = write(dir.path(), "[open]\npdf = \"Preview\"\n");
+        let config = Config::load_layers(Some(&user), Path::new("/"));
+        assert_eq!(config.opener_for(Path::new("docs/spec.pdf")), Some("Preview"));
+        assert!(config.opener_for(Path::new("README.md")).is_none());
+        assert!(config.entries().iter().any(|(key, value, _)| key == "open.pdf" && value == "Preview"));
+    }
+
+    #[test]
+    fn test_project_cannot_set_user_only_sections() {
+        let home = tempfile::tempdir().unwrap();
+        let user = write(home.path(), "[sandbox]\nagent = \"read-only\"\n");
+        let project = home.path().join("repo");
+        write(&project, "[aliases]\nls = \"sh evil.sh\"\n[open]\npdf = \"sh\"\n[sandbox]\nagent = \"accept-edits\"\n[history]\nrecord = false\n[font]\nsize = 16\n[path]\nprepend = [\"./bin\"]\n[environments]\nauto = true\n[updates]\nfeed = \"https://example.com/feed\"\n");
+
+        let config = Config::load_layers(Some(&user), &project);
+        assert!(config.alias("ls").is_none());
+        assert!(config.opener_for(Path::new("a.pdf")).is_none());
+        assert_eq!(config.sandbox_policy(), SandboxPolicy::ReadOnly);
+        assert!(config.records_history(false));
+        assert_eq!(config.font_size(), Some(16.0));
+        assert!(config.path_prepend.is_empty());
+        assert!(!config.auto_activates_environments());
+        assert_eq!(config.update_feed(), crate::update::DEFAULT_FEED);
+        assert_eq!(config.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_sandbox_network_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load_layers(None, Path::new("/"));
+        assert_eq!(config.network_policy(), NetworkPolicy::Any);
+
+        let user = write(dir.path(), "[sandbox]\nnetwork = [\"crates.io\", \"*.npmjs.org\"]\n");
+        let policy = Config::load_layers(Some(&user), Path::new("/")).network_policy();
+        assert!(policy.allows("crates.io") && policy.allows("static.crates.io") && policy.allows("registry.npmjs.org"));
+        assert!(policy.allows("localhost") && policy.allows("[::1]"));
+        assert!(!policy.allows("evilcrates.io") && !policy.allows("example.com"));
+
+        let user = write(dir.path(), "[sandbox]\nnetwork = \"loopback\"\n");
+        let policy = Config::load_layers(Some(&user), Path::new("/")).network_policy();
+        assert!(policy.allows("127.0.0.1") && !policy.allows("crates.io"));
+
+        let user = write(dir.path(), "[sandbox]\nnetwork = \"deny\"\n");
+        let config = Config::load_layers(Some(&user), Path::new("/"));
+        assert!(!config.network_policy().allows("localhost"));
+        assert!(config.entries().iter().any(|(key, value, _)| key == "sandbox.network" && value == "deny"));
+
+        let user = write(dir.path(), "[sandbox]\nnetwork = \"some\"\n");
+        assert_eq!(Config::load_layers(Some(&user), Path::new("/")).errors.len(), 1);
+    }
+
+    #[test]
+    fn test_records_history_honors_ignore_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = write(dir.path(), "[history]\nignore_space = true\n");
+        let config = Config::load_layers(Some(&user), Path::new("/"));
+        assert!(config.records_history(false));
+        assert!(!config.records_history(true));
+    }
+
+    #[test]
+    fn test_restores_session_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load_layers(None, Path::new("/"));
+        assert!(config.restores_session());
+        let user = write(dir.path(), "[history]\nrestore = false\n");
+        let config = Config::load_layers(Some(&user), Path::new("/"));
+        assert!(!config.restores_session());
+    }
+
+    #[test]
+    fn test_set_setting_keeps_comments_and_validates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "# mine\n[aliases]\nt = \"cargo test\" # keep\n");
+
+        set_setting(&path, "font.size", 15.0).unwrap();
+        set_setting(&path, "aliases.b", "cargo build").unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# mine\n"));
+        assert!(text.contains("# keep"));
+
+        assert!(set_setting(&path, "font.size", "big").is_err());
+        assert!(set_setting(&path, "sandbox.agent", "yolo").is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+
+        unset_setting(&path, "aliases.t").unwrap();
+        let config = Config::load_layers(Some(&path), Path::new("/"));
+        assert_eq!(config.font_size(), Some(15.0));
+        assert_eq!(config.alias("b"), Some("cargo build"));
+        assert!(config.alias("t").is_none());
+    }
+
+    #[test]
+    fn test_apply_env_expands_and_keeps_later_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = write(
+            dir.path(),
+            "[env]\nEDITOR = \"nvim\"\nGOPATH = \"$HOME/go\"\n[path]\nprepend = [\"~/bin\", \"/usr/bin\"]\nappend = [\"${GOPATH}/bin\"]\n",
+        );
+        let config = Config::load_layers(Some(&user), Path::new("/"));
+        let mut env = HashMap::from([
+            ("HOME".to_string(), "/home/me".to_string()),
+            ("PATH".to_string(), "/usr/bin:/bin".to_string()),
+        ]);
+
+        config.apply_env(&Config::default(), &mut env);
+        assert_eq!(env["GOPATH"], "/home/me/go");
+        assert_eq!(env["PATH"], "/home/me/bin:/usr/bin:/bin:/home/me/go/bin");
+
+        env.insert("EDITOR".to_string(), "vi".to_string());
+        config.apply_env(&config, &mut env);
+        assert_eq!(env["EDITOR"], "vi");
+        assert_eq!(env["PATH"], "/home/me/bin:/usr/bin:/bin:/home/me/go/bin");
+    }
+
+    #[test]
+    fn test_pager_mode_per_command() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "[pager]\nmode = \"keep\"\n[pager.commands]\ngit = \"capture\"\n");
+        let config = Config::load_layers(None, dir.path());
+        assert_eq!(config.pager_mode("git"), PagerMode::Capture);
+        assert_eq!(config.pager_mode("/usr/bin/git"), PagerMode::Capture);
+        assert_eq!(config.pager_mode("psql"), PagerMode::Keep);
+        assert_eq!(Config::default().pager_mode("git"), PagerMode::Disable);
+        assert!(config.entries().iter().any(|(k, v, _)| k == "pager.commands.git" && v == "capture"));
+    }
+
+    #[test]
+    fn test_ambiguous_wide() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!Config::load_layers(None, dir.path()).ambiguous_wide());
+        write(dir.path(), "[font]\nambiguous_wide = true\n");
+        assert!(Config::load_layers(None, dir.path()).ambiguous_wide());
+    }
+
+    #[test]
+    fn test_theme_symbols() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!Config::load_layers(None, dir.path()).symbols());
+        write(dir.path(), "[theme]\nsymbols = true\n");
+        let config = Config::load_layers(None, dir.path());
+        assert!(config.symbols());
+        assert!(config.entries().iter().any(|(key, value, _)| key == "theme.symbols" && value == "true"));
+    }
+
+    #[test]
+    fn test_color_mode_per_command() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "[color]\nmode = \"always\"\n[color.commands]\nterraform = \"never\"\n");
+        let config = Config::load_layers(None, dir.path());
+        assert_eq!(config.color_mode("/usr/local/bin/terraform"), ColorMode::Never);
+        assert_eq!(config.color_mode("ls"), ColorMode::Always);
+        assert_eq!(Config::default().color_mode("ls"), ColorMode::Auto);
+        assert!(config.entries().iter().any(|(k, v, _)| k == "color.commands.terraform" && v == "never"));
+    }
+
+    #[test]
+    fn test_warnings_can_be_turned_off_per_project() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "[warnings]\nnode-version = false\n");
+        let config = Config::load_layers(None, dir.path());
+        assert!(!config.shows_warning("node-version"));
+        assert!(config.shows_warning("submodules"));
+        assert!(config.entries().iter().any(|(k, v, _)| k == "warnings.node-version" && v == "false"));
+    }
+
+    #[test]
+    fn test_runs_fullscreen() {
+        let config = Config::default();
+        assert!(config.runs_fullscreen("vim src/main.rs"));
+        assert!(config.runs_fullscreen("TERM=xterm sudo /usr/bin/htop"));
+        assert!(!config.runs_fullscreen("cargo build"));
+
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "[fullscreen]\ncommands = [\"btop\"]\n");
+        let config = Config::load_layers(None, dir.path());
+        assert!(config.runs_fullscreen("btop"));
+        assert!(!config.runs_fullscreen("vim"));
+        assert!(config.entries().iter().any(|(k, v, _)| k == "fullscreen.commands" && v == "btop"));
+    }
+
+    #[test]
+    fn test_preview_settings_leaves_file_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "[aliases]\nt = \"cargo test\"\n");
+        let settings = [("aliases.ll".to_string(), "ls -l".into()), ("env.EDITOR".to_string(), "vim".into())];
+        let (before, after) = preview_settings(&path, settings.clone()).unwrap();
+        assert_eq!(before, std::fs::read_to_string(&path).unwrap());
+        assert!(after.contains("ll = \"ls -l\"") && after.contains("[env]"));
+
+        set_settings(&path, settings).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), after);
+    }
+}
@@ -0,0 +1,204 @@
+//! Seeded fuzzing for the parser and evaluator.
+//!
+//! Two kinds of input are produced:
+//! - grammar-generated scripts built from a small, side-effect-free
+//!   vocabulary (`echo`, `printf`, `test`, finite loops, expansions). These
+//!   are evaluated by both shells and compared like corpus cases.
+//! - byte-level mutations of corpus cases. These may be arbitrary garbage,
+//!   so they are only parsed (`bash -n`), never run.
+//!
+//! A panic in either path is always a bug; differing exit status or
+//! acceptance is reported as a divergence for triage.
+
+use super::{Case, Verdict, bash_path, corpus, run_case};
+use crate::{Parser, supervisor};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const WORDS: &[&str] = &[
+    "a", "foo", "1", "42", "-n", "''", "\"\"", "'x y'", "\"q $v\"", "$v", "${v}", "${v:-d}", "${#v}", "${w%o}",
+    "$((1 + 2))", "$((v * 3))", "$(echo s)", "\"$(echo s t)\"", "$?", "$#", "a\\ b", "*.none",
+];
+
+/// Tokens that tend to break the grammar; used sparingly.
+const NOISE: &[&str] = &["\"", "'", "$(", "${", ")", "}", ";;", "\\", "|", "&&", "fi", "done", "then", "esac"];
+
+/// Characters inserted by the mutator.
+const MUTATION_CHARS: &[u8] = b"\"'$(){}[]|&;\\` \n#=*";
+
+/// Something the fuzzer found.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub input: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FuzzReport {
+    pub iterations: usize,
+    pub panics: Vec<Finding>,
+    pub divergences: Vec<Finding>,
+}
+
+/// Run `iterations` fuzz inputs derived from `seed`.
+pub fn run(seed: u64, iterations: usize) -> FuzzReport {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let bash = bash_path();
+    let seeds: Vec<String> = corpus().into_iter().map(|c| c.script).collect();
+    let mut report = FuzzReport { iterations, ..Default::default() };
+
+    for i in 0..iterations {
+        if i % 2 == 0 {
+            let script = generate(&mut rng);
+            evaluate(&script, bash.as_deref(), &mut report);
+        } else {
+            let seed_script = seeds.choose(&mut rng).cloned().unwrap_or_default();
+            let script = mutate(&mut rng, &seed_script);
+            parse_only(&script, bash.as_deref(), &mut report);
+        }
+    }
+    report
+}
+
+fn evaluate(script: &str, bash: Option<&Path>, report: &mut FuzzReport) {
+    let case = Case { category: "fuzz".to_string(), script: script.to_string(), xfail: false };
+    let result = run_case(case, bash);
+    let finding = |detail: String| Finding { input: script.to_string(), detail };
+    match result.verdict {
+        Verdict::Panic => report.panics.push(finding("kernel panicked".to_string())),
+        Verdict::ExitMismatch | Verdict::OutputMismatch => {
+            let bash = result.bash.unwrap_or_else(|| super::Outcome::exited(-1));
+            report.divergences.push(finding(format!(
+                "{}: nexus exited {}, bash exited {}",
+                result.verdict.as_str(),
+                result.nexus.exit_code,
+                bash.exit_code
+            )));
+        }
+        Verdict::Pass | Verdict::Skipped => {}
+    }
+}
+
+fn parse_only(script: &str, bash: Option<&Path>, report: &mut FuzzReport) {
+    let nexus_accepts = match supervisor::run(|| Parser::new().is_ok_and(|mut p| p.parse(script).is_ok())) {
+        Ok(accepts) => accepts,
+        Err(panic) => {
+            report.panics.push(Finding { input: script.to_string(), detail: format!("parser panicked: {}", panic.message) });
+            return;
+        }
+    };
+    let Some(bash_accepts) = bash.and_then(|b| bash_syntax_ok(b, script)) else {
+        return;
+    };
+    if nexus_accepts != bash_accepts {
+        let detail = if bash_accepts { "bash accepts, nexus rejects" } else { "nexus accepts, bash rejects" };
+        report.divergences.push(Finding { input: script.to_string(), detail: detail.to_string() });
+    }
+}
+
+/// `bash -n`: parse without executing.
+fn bash_syntax_ok(bash: &Path, script: &str) -> Option<bool> {
+    Command::new(bash)
+        .args(["--norc", "--noprofile", "-n", "-c", script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()
+        .map(|s| s.success())
+}
+
+/// Build a random script from the safe vocabulary.
+pub fn generate(rng: &mut impl Rng) -> String {
+    list(rng, 2)
+}
+
+fn list(rng: &mut impl Rng, depth: u32) -> String {
+    let mut out = command(rng, depth);
+    for _ in 0..rng.gen_range(0..3) {
+        let connector = ["; ", " && ", " || ", " | "].choose(rng).unwrap();
+        out.push_str(connector);
+        out.push_str(&command(rng, depth));
+    }
+    out
+}
+
+fn command(rng: &mut impl Rng, depth: u32) -> String {
+    let compound = if depth == 0 { 0 } else { rng.gen_range(0..10) };
+    match compound {
+        1 => format!("if {}; then {}; fi", simple(rng), list(rng, depth - 1)),
+        2 => format!("if {}; then {}; else {}; fi", simple(rng), list(rng, depth - 1), list(rng, depth - 1)),
+        3 => format!("for v in {}; do {}; done", words(rng, 1..4), list(rng, depth - 1)),
+        4 => format!("case {} in {}) {};; *) {};; esac", word(rng), word(rng), simple(rng), simple(rng)),
+        5 => format!("{{ {}; }}", list(rng, depth - 1)),
+        6 => format!("({})", list(rng, depth - 1)),
+        _ => simple(rng),
+    }
+}
+
+fn simple(rng: &mut impl Rng) -> String {
+    match rng.gen_range(0..8) {
+        0 => format!("v={}", word(rng)),
+        1 => format!("w={}", word(rng)),
+        2 => format!("[ {} = {} ]", word(rng), word(rng)),
+        3 => format!("test {} {}", word(rng), word(rng)),
+        4 => format!("printf '%s\\n' {}", words(rng, 0..3)),
+        5 => ["true", "false", ":"].choose(rng).unwrap().to_string(),
+        _ => format!("echo {}", words(rng, 0..4)),
+    }
+}
+
+fn words(rng: &mut impl Rng, count: std::ops::Range<usize>) -> String {
+    let n = rng.gen_range(count);
+    (0..n).map(|_| word(rng)).collect::<Vec<_>>().join(" ")
+}
+
+fn word(rng: &mut impl Rng) -> String {
+    if rng.gen_ratio(1, 25) {
+        NOISE.choose(rng).unwrap().to_string()
+    } else {
+        WORDS.choose(rng).unwrap().to_string()
+    }
+}
+
+/// Randomly delete, insert or duplicate a few characters.
+pub fn mutate(rng: &mut impl Rng, script: &str) -> String {
+    let mut bytes = script.as_bytes().to_vec();
+    for _ in 0..rng.gen_range(1..4) {
+        let pos = rng.gen_range(0..=bytes.len());
+        match rng.gen_range(0..3) {
+            0 if pos < bytes.len() => {
+                bytes.remove(pos);
+            }
+            1 if pos < bytes.len() => {
+                let end = rng.gen_range(pos..bytes.len()).min(pos + 8);
+                let slice = bytes[pos..=end].to_vec();
+                bytes.splice(pos..pos, slice);
+            }
+            _ => bytes.insert(pos, *MUTATION_CHARS.choose(rng).unwrap()),
+        }
+    }
+    // The corpus and mutation alphabet are ASCII, so this is lossless.
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generation_is_deterministic() {
+        let a: Vec<String> = (0..20).scan(StdRng::seed_from_u64(7), |rng, _| Some(generate(rng))).collect();
+        let b: Vec<String> = (0..20).scan(StdRng::seed_from_u64(7), |rng, _| Some(generate(rng))).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_fuzz_finds_no_panics() {
+        let report = run(0x6e65_7875, 200);
+        assert!(report.panics.is_empty(), "panics: {:#?}", report.panics);
+    }
+}
@@ -0,0 +1,316 @@
+//! Conformance harness for the shell parser and evaluator.
+//!
+//! A corpus of POSIX/bash snippets ([`corpus`]) is run through a throwaway
+//! kernel and through `/bin/bash -c`, comparing exit status and stdout. The
+//! [`fuzz`] module generates further inputs to shake out panics and parse
+//! divergences. Results are surfaced by the `conformance` command, which also
+//! keeps a history so coverage of shell semantics can be tracked over time.
+//!
+//! Only stdout is compared: Nexus formats its own diagnostics, so stderr
+//! text is expected to differ from bash.
+
+pub mod fuzz;
+
+use crate::{Kernel, Parser, supervisor};
+use nexus_api::{BlockId, ShellEvent, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const CORPUS: &str = include_str!("corpus.txt");
+
+/// Block id used for conformance runs; never shown in the UI.
+const CONFORMANCE_BLOCK: BlockId = BlockId(u64::MAX - 1);
+
+/// Exit status bash uses for syntax errors.
+const SYNTAX_ERROR_EXIT: i32 = 2;
+
+/// One snippet from the corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub category: String,
+    pub script: String,
+    /// Known divergence: reported, but not treated as a regression.
+    pub xfail: bool,
+}
+
+/// Parse the built-in corpus.
+pub fn corpus() -> Vec<Case> {
+    parse_corpus(CORPUS)
+}
+
+fn parse_corpus(text: &str) -> Vec<Case> {
+    let mut category = "uncategorized".to_string();
+    let mut cases = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            category = name.to_string();
+            continue;
+        }
+        let (script, xfail) = match line.strip_prefix("xfail:") {
+            Some(rest) => (rest.trim(), true),
+            None => (line, false),
+        };
+        cases.push(Case { category: category.clone(), script: script.to_string(), xfail });
+    }
+    cases
+}
+
+/// What running a script produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub exit_code: i32,
+    pub stdout: String,
+    /// The kernel panicked (contained by the supervisor).
+    pub panicked: bool,
+}
+
+impl Outcome {
+    fn exited(exit_code: i32) -> Self {
+        Self { exit_code, stdout: String::new(), panicked: false }
+    }
+}
+
+/// How a case compared against bash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    ExitMismatch,
+    OutputMismatch,
+    /// The kernel panicked (contained by the supervisor).
+    Panic,
+    /// No bash on this machine to compare against.
+    Skipped,
+}
+
+impl Verdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Pass => "pass",
+            Verdict::ExitMismatch => "exit mismatch",
+            Verdict::OutputMismatch => "output mismatch",
+            Verdict::Panic => "panic",
+            Verdict::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub case: Case,
+    pub nexus: Outcome,
+    pub bash: Option<Outcome>,
+    pub verdict: Verdict,
+}
+
+impl CaseResult {
+    /// A failure that is not covered by an `xfail` marker.
+    pub fn is_regression(&self) -> bool {
+        match self.verdict {
+            Verdict::Panic => true,
+            Verdict::ExitMismatch | Verdict::OutputMismatch => !self.case.xfail,
+            Verdict::Pass | Verdict::Skipped => false,
+        }
+    }
+}
+
+/// Pass counts for one category.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategorySummary {
+    pub category: String,
+    pub cases: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl CategorySummary {
+    /// Fraction of compared (not skipped) cases that pass.
+    pub fn pass_rate(&self) -> f64 {
+        let compared = self.cases - self.skipped;
+        if compared == 0 { 0.0 } else { self.passed as f64 / compared as f64 }
+    }
+}
+
+/// Run every case whose category matches `filter` (all when `None`).
+pub fn run_corpus(filter: Option<&str>) -> Vec<CaseResult> {
+    let bash = bash_path();
+    corpus()
+        .into_iter()
+        .filter(|case| filter.is_none_or(|f| case.category == f))
+        .map(|case| run_case(case, bash.as_deref()))
+        .collect()
+}
+
+/// Run one case through Nexus and (if available) bash, in a fresh scratch
+/// directory so files left by other cases (or concurrent runs) can't leak in.
+pub fn run_case(case: Case, bash: Option<&Path>) -> CaseResult {
+    let workdir = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("conformance: cannot create a scratch directory: {}", e);
+            return CaseResult { case, nexus: Outcome::exited(-1), bash: None, verdict: Verdict::Skipped };
+        }
+    };
+    let cwd = workdir.path();
+    let nexus = run_nexus(&case.script, cwd);
+    let bash = bash.map(|b| run_bash(b, &case.script, cwd));
+    let verdict = compare(&nexus, bash.as_ref());
+    CaseResult { case, nexus, bash, verdict }
+}
+
+fn compare(nexus: &Outcome, bash: Option<&Outcome>) -> Verdict {
+    if nexus.panicked {
+        return Verdict::Panic;
+    }
+    let Some(bash) = bash else {
+        return Verdict::Skipped;
+    };
+    if nexus.exit_code != bash.exit_code {
+        Verdict::ExitMismatch
+    } else if normalize(&nexus.stdout) != normalize(&bash.stdout) {
+        Verdict::OutputMismatch
+    } else {
+        Verdict::Pass
+    }
+}
+
+/// PTY output uses CRLF, and a trailing newline is not semantically
+/// interesting when native commands return values instead of bytes.
+fn normalize(s: &str) -> String {
+    s.replace("\r\n", "\n").trim_end().to_string()
+}
+
+/// Summarize results per category, in corpus order.
+pub fn summarize(results: &[CaseResult]) -> Vec<CategorySummary> {
+    let mut summaries: Vec<CategorySummary> = Vec::new();
+    for result in results {
+        let idx = match summaries.iter().position(|s| s.category == result.case.category) {
+            Some(idx) => idx,
+            None => {
+                summaries.push(CategorySummary { category: result.case.category.clone(), ..Default::default() });
+                summaries.len() - 1
+            }
+        };
+        let summary = &mut summaries[idx];
+        summary.cases += 1;
+        match result.verdict {
+            Verdict::Pass => summary.passed += 1,
+            Verdict::Skipped => summary.skipped += 1,
+            _ => summary.failed += 1,
+        }
+    }
+    summaries
+}
+
+/// Run a script through a fresh, store-less kernel and capture its stdout.
+///
+/// Scripts that fail to parse report bash's syntax-error status, so "both
+/// shells reject this" counts as agreement.
+pub fn run_nexus(script: &str, cwd: &Path) -> Outcome {
+    match supervisor::run(|| Parser::new().is_ok_and(|mut p| p.parse(script).is_ok())) {
+        Ok(true) => {}
+        Ok(false) => return Outcome::exited(SYNTAX_ERROR_EXIT),
+        Err(_) => return Outcome { panicked: true, ..Outcome::exited(supervisor::PANIC_EXIT_CODE) },
+    }
+
+    let (mut kernel, mut rx) = match Kernel::ephemeral() {
+        Ok(k) => k,
+        Err(e) => {
+            tracing::warn!("conformance: failed to create kernel: {}", e);
+            return Outcome::exited(-1);
+        }
+    };
+    kernel.state_mut().cwd = cwd.to_path_buf();
+    kernel.state_mut().interactive = false;
+
+    // Evaluation errors surface as a failed command, like bash's `1`.
+    let exit_code = kernel.execute_supervised(script, CONFORMANCE_BLOCK).unwrap_or(1);
+
+    let mut stdout = Vec::new();
+    let mut panicked = false;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            ShellEvent::KernelPanic { .. } => panicked = true,
+            ShellEvent::StdoutChunk { data, .. } => stdout.extend_from_slice(&data),
+            ShellEvent::CommandOutput { value, .. } => {
+                if matches!(value, Value::Unit) {
+                    continue;
+                }
+                stdout.extend_from_slice(value.to_text().as_bytes());
+                if stdout.last() != Some(&b'\n') {
+                    stdout.push(b'\n');
+                }
+            }
+            _ => {}
+        }
+    }
+    Outcome { exit_code, stdout: String::from_utf8_lossy(&stdout).into_owned(), panicked }
+}
+
+/// Run a script through `bash -c` with no startup files.
+pub fn run_bash(bash: &Path, script: &str, cwd: &Path) -> Outcome {
+    match Command::new(bash)
+        .args(["--norc", "--noprofile", "-c", script])
+        .current_dir(cwd)
+        .stdin(std::process::Stdio::null())
+        .output()
+    {
+        Ok(output) => Outcome {
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            panicked: false,
+        },
+        Err(e) => {
+            tracing::warn!("conformance: failed to run bash: {}", e);
+            Outcome::exited(-1)
+        }
+    }
+}
+
+/// The reference shell, if installed.
+pub fn bash_path() -> Option<PathBuf> {
+    let path = PathBuf::from("/bin/bash");
+    path.exists().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_corpus() {
+        let cases = parse_corpus("# comment\n[a]\necho 1\n\n[b]\nxfail: echo 2\n");
+        assert_eq!(
+            cases,
+            vec![
+                Case { category: "a".into(), script: "echo 1".into(), xfail: false },
+                Case { category: "b".into(), script: "echo 2".into(), xfail: true },
+            ]
+        );
+        assert!(corpus().len() > 50);
+    }
+
+    #[test]
+    fn test_compare() {
+        let out = |code, s: &str| Outcome { exit_code: code, stdout: s.to_string(), panicked: false };
+        assert_eq!(compare(&out(0, "hi\r\n"), Some(&out(0, "hi\n"))), Verdict::Pass);
+        assert_eq!(compare(&out(1, ""), Some(&out(0, ""))), Verdict::ExitMismatch);
+        assert_eq!(compare(&out(0, "a"), Some(&out(0, "b"))), Verdict::OutputMismatch);
+        assert_eq!(compare(&out(0, "a"), None), Verdict::Skipped);
+        let panicked = Outcome { panicked: true, ..Outcome::exited(supervisor::PANIC_EXIT_CODE) };
+        assert_eq!(compare(&panicked, Some(&out(0, ""))), Verdict::Panic);
+    }
+
+    #[test]
+    fn test_cases_get_their_own_directory() {
+        let case = |script: &str| Case { category: "fs".into(), script: script.into(), xfail: false };
+        run_case(case("touch leftover"), None);
+        let listing = run_case(case("ls"), None);
+        assert_eq!(listing.nexus.exit_code, 0);
+        assert!(!listing.nexus.stdout.contains("leftover"));
+    }
+}
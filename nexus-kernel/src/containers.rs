@@ -0,0 +1,189 @@
+//! Commands run inside a container.
+//!
+//! `nexus exec -c <container> [-u <user>] [--docker|--podman] <command>`
+//! runs one command line inside a running container and streams it into
+//! the block like any other command:
+//!
+//! ```text
+//! nexus exec -c web ls /app
+//! nexus exec -c db -u postgres psql
+//! nexus exec -c api --podman sh -c 'env | sort'
+//! ```
+//!
+//! The line runs under `sh -c` in the container, through `docker exec -it`
+//! (or `podman exec -it`) on a terminal, so interactive tools work. Unlike
+//! `docker exec <container>`, which connects the whole session to the
+//! container, this leaves the session where it is.
+
+use std::path::Path;
+
+use nexus_api::{BlockId, ShellEvent};
+
+use crate::commands::{register_cancel, unregister_cancel};
+use crate::replay::EventSender;
+use crate::state::get_or_create_block_id;
+use crate::{process, ShellState};
+
+/// The container engine a command runs through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runtime {
+    Docker,
+    Podman,
+}
+
+impl Runtime {
+    pub fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+        }
+    }
+
+    /// Docker when it is on `path`, else Podman when that is, else Docker.
+    pub fn detect(path: &str) -> Self {
+        let found = |program: &str| path.split(':').any(|dir| Path::new(dir).join(program).is_file());
+        if !found("docker") && found("podman") { Runtime::Podman } else { Runtime::Docker }
+    }
+}
+
+/// Which container a command runs in, and as whom.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerExec {
+    pub container: String,
+    pub user: Option<String>,
+    /// The engine asked for; detected from `PATH` when `None`.
+    pub runtime: Option<Runtime>,
+}
+
+impl ContainerExec {
+    /// Split a `nexus exec` prefix off `line`, returning where to run and
+    /// the command line to run there. `None` when the line has no prefix,
+    /// names no container, or nothing follows it.
+    pub fn split(line: &str) -> Option<(Self, &str)> {
+        let rest = line.trim_start().strip_prefix("nexus")?;
+        let rest = rest.strip_prefix(char::is_whitespace)?.trim_start().strip_prefix("exec")?;
+        let mut rest = rest.strip_prefix(char::is_whitespace)?.trim_start();
+        let mut parsed = Self::default();
+        let command = loop {
+            let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let after = after.trim_start();
+            let value = || after.split_once(char::is_whitespace).map_or((after, ""), |(v, a)| (v, a.trim_start()));
+            rest = match word {
+                "-c" | "--container" => {
+                    let (name, after) = value();
+                    parsed.container = name.to_string();
+                    after
+                }
+                "-u" | "--user" => {
+                    let (user, after) = value();
+                    parsed.user = Some(user.to_string());
+                    after
+                }
+                "--docker" => {
+                    parsed.runtime = Some(Runtime::Docker);
+                    after
+                }
+                "--podman" => {
+                    parsed.runtime = Some(Runtime::Podman);
+                    after
+                }
+                "--" => break after,
+                _ => break rest,
+            };
+        };
+        (!parsed.container.is_empty() && !command.is_empty()).then_some((parsed, command))
+    }
+
+    /// The engine to use, given the session's `PATH`.
+    pub fn runtime(&self, path: &str) -> Runtime {
+        self.runtime.unwrap_or_else(|| Runtime::detect(path))
+    }
+
+    /// The `exec` invocation that runs `command` in the container on a
+    /// terminal.
+    pub fn argv(&self, runtime: Runtime, command: &str) -> Vec<String> {
+        let mut argv = vec![runtime.program().to_string(), "exec".to_string(), "-it".to_string()];
+        if let Some(user) = &self.user {
+            argv.extend(["-u".to_string(), user.clone()]);
+        }
+        argv.extend([self.container.clone(), "sh".to_string(), "-c".to_string(), command.to_string()]);
+        argv
+    }
+
+    /// [`ContainerExec::argv`] as a line for `sh -c`, for commands run in a
+    /// terminal rather than by the kernel.
+    pub fn shell_line(&self, runtime: Runtime, command: &str) -> String {
+        let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+        self.argv(runtime, command).iter().map(|arg| quote(arg)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Run `command` in the container `exec` names, streaming its terminal
+/// output into the block.
+pub(crate) fn execute(
+    state: &mut ShellState,
+    exec: &ContainerExec,
+    command: &str,
+    events: &EventSender,
+    external_block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let block_id = get_or_create_block_id(external_block_id);
+    if external_block_id.is_none() {
+        let _ = events.send(ShellEvent::CommandStarted {
+            block_id,
+            command: command.to_string(),
+            cwd: state.cwd.clone(),
+            container: Some(exec.container.clone()),
+        });
+    }
+
+    let runtime = exec.runtime(state.get_env("PATH").unwrap_or_default());
+    register_cancel(block_id);
+    let exit_code = process::spawn(&exec.argv(runtime, command), &state.cwd, &state.env, &[], &[])
+        .and_then(|handle| process::wait_with_events(handle, block_id, events));
+    unregister_cancel(block_id);
+    let exit_code = exit_code?;
+    state.last_exit_code = exit_code;
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let (exec, rest) = ContainerExec::split("nexus exec -c web ls -la /app").unwrap();
+        assert_eq!(exec, ContainerExec { container: "web".into(), user: None, runtime: None });
+        assert_eq!(rest, "ls -la /app");
+
+        let (exec, rest) = ContainerExec::split("nexus exec --podman -u postgres --container db -- psql -c 'select 1'").unwrap();
+        assert_eq!((exec.user.as_deref(), exec.runtime), (Some("postgres"), Some(Runtime::Podman)));
+        assert_eq!(rest, "psql -c 'select 1'");
+
+        assert!(ContainerExec::split("nexus exec ls").is_none());
+        assert!(ContainerExec::split("nexus exec -c web").is_none());
+        assert!(ContainerExec::split("nexusexec -c web ls").is_none());
+        assert!(ContainerExec::split("echo nexus exec -c web ls").is_none());
+    }
+
+    #[test]
+    fn test_argv_allocates_terminal() {
+        let (exec, rest) = ContainerExec::split("nexus exec -c web -u app echo 'it''s'").unwrap();
+        assert_eq!(exec.argv(Runtime::Docker, rest), ["docker", "exec", "-it", "-u", "app", "web", "sh", "-c", "echo 'it''s'"]);
+        assert_eq!(
+            exec.shell_line(Runtime::Podman, "echo 'hi'"),
+            r#"'podman' 'exec' '-it' '-u' 'app' 'web' 'sh' '-c' 'echo '\''hi'\'''"#
+        );
+    }
+
+    #[test]
+    fn test_detect_runtime() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Runtime::detect(&dir.path().to_string_lossy()), Runtime::Docker);
+        std::fs::write(dir.path().join("podman"), "").unwrap();
+        assert_eq!(Runtime::detect(&dir.path().to_string_lossy()), Runtime::Podman);
+        std::fs::write(dir.path().join("docker"), "").unwrap();
+        assert_eq!(Runtime::detect(&dir.path().to_string_lossy()), Runtime::Docker);
+    }
+}
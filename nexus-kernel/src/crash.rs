@@ -0,0 +1,307 @@
+//! Crash reports.
+//!
+//! [`install`] chains a panic hook that writes a crash bundle to
+//! `~/.nexus/crashes`: the panic message and location, a backtrace, the
+//! tail of the log, and a little about the environment, with the home
+//! directory and user name scrubbed out. Panics the kernel supervisor
+//! catches are left alone; those are already reported in their block.
+//!
+//! Nothing leaves the machine unless the user submits a bundle, which
+//! posts it to `[crash] upload_url`. A `.status` file next to each bundle
+//! records whether it was dismissed or submitted; the UI offers new ones
+//! on the next start, and `diagnostics crash list` shows them all.
+
+use std::fmt::Write as _;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use anyhow::Context;
+
+use crate::diagnostics::{self, WarningRecord};
+use crate::supervisor;
+
+const BUNDLE_PREFIX: &str = "crash-";
+const BUNDLE_EXT: &str = "txt";
+
+/// What the user did with a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashStatus {
+    /// Not yet offered, or offered and left alone.
+    New,
+    Dismissed,
+    Submitted,
+}
+
+impl CrashStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Dismissed => "dismissed",
+            Self::Submitted => "submitted",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s.trim() {
+            "dismissed" => Self::Dismissed,
+            "submitted" => Self::Submitted,
+            _ => Self::New,
+        }
+    }
+}
+
+/// A crash bundle on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashSummary {
+    pub path: PathBuf,
+    /// Unix time in milliseconds.
+    pub at_ms: i64,
+    /// First line of the panic message.
+    pub message: String,
+    pub location: Option<String>,
+    pub status: CrashStatus,
+}
+
+/// `~/.nexus/crashes`.
+pub fn crashes_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".nexus").join("crashes"))
+}
+
+/// Write a crash bundle for every unsupervised panic. The previous hook
+/// still runs (and prints the panic).
+pub fn install() {
+    static INSTALLED: Once = Once::new();
+    let Some(dir) = crashes_dir() else { return };
+    let scrubber = Scrubber::from_env();
+    INSTALLED.call_once(move || {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !supervisor::is_supervised() {
+                match write_bundle(&dir, info, &scrubber) {
+                    Ok(path) => eprintln!("nexus: crash report written to {}", path.display()),
+                    Err(e) => eprintln!("nexus: failed to write crash report: {}", e),
+                }
+            }
+            previous(info);
+        }));
+    });
+}
+
+fn write_bundle(dir: &Path, info: &PanicHookInfo<'_>, scrubber: &Scrubber) -> std::io::Result<PathBuf> {
+    let at_ms = chrono::Utc::now().timestamp_millis();
+    let thread = std::thread::current();
+    let text = render(&Crash {
+        at_ms,
+        message: &supervisor::panic_message(info.payload()),
+        location: info.location().map(|l| l.to_string()),
+        thread: thread.name().unwrap_or("unnamed"),
+        backtrace: &std::backtrace::Backtrace::force_capture().to_string(),
+        log: &diagnostics::try_log_tail().unwrap_or_default(),
+    });
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}{}.{}", BUNDLE_PREFIX, at_ms, BUNDLE_EXT));
+    std::fs::write(&path, scrubber.scrub(&text))?;
+    Ok(path)
+}
+
+struct Crash<'a> {
+    at_ms: i64,
+    message: &'a str,
+    location: Option<String>,
+    thread: &'a str,
+    backtrace: &'a str,
+    log: &'a [WarningRecord],
+}
+
+/// The bundle text: a `key: value` header, then backtrace and log.
+fn render(crash: &Crash<'_>) -> String {
+    let mut out = String::new();
+    let time = chrono::DateTime::from_timestamp_millis(crash.at_ms).map(|t| t.to_rfc3339()).unwrap_or_default();
+    let _ = writeln!(out, "# Nexus crash report");
+    let _ = writeln!(out, "time: {}", time);
+    let _ = writeln!(out, "message: {}", crash.message.lines().next().unwrap_or_default());
+    if let Some(location) = &crash.location {
+        let _ = writeln!(out, "location: {}", location);
+    }
+    let _ = writeln!(out, "thread: {}", crash.thread);
+    let _ = writeln!(out, "version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(out, "uptime: {}s", diagnostics::uptime().as_secs());
+    let _ = writeln!(out, "low_power: {}", crate::power::is_low_power());
+    let shell = std::env::var("SHELL").ok();
+    let shell = shell.as_deref().and_then(|s| s.rsplit('/').next()).unwrap_or("unknown");
+    let _ = writeln!(out, "shell: {}", shell);
+    for var in ["TERM", "LANG"] {
+        let _ = writeln!(out, "{}: {}", var.to_ascii_lowercase(), std::env::var(var).unwrap_or_default());
+    }
+
+    if crash.message.lines().nth(1).is_some() {
+        let _ = writeln!(out, "\n## Message\n{}", crash.message);
+    }
+    let _ = writeln!(out, "\n## Backtrace\n{}", crash.backtrace.trim_end());
+    let _ = writeln!(out, "\n## Recent log");
+    for w in crash.log {
+        let time = chrono::DateTime::from_timestamp_millis(w.at_ms).map(|t| t.to_rfc3339()).unwrap_or_default();
+        let _ = writeln!(out, "{} {} {}: {}", time, w.level, w.target, w.message);
+    }
+    out
+}
+
+/// Removes the home directory and user name from bundle text.
+struct Scrubber {
+    home: Option<String>,
+    user: Option<String>,
+}
+
+impl Scrubber {
+    fn from_env() -> Self {
+        let home = std::env::var("HOME").ok().filter(|h| h.len() > 1);
+        // Short names would scrub unrelated text.
+        let user = std::env::var("USER").ok().filter(|u| u.len() >= 3);
+        Self { home, user }
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        let mut text = match &self.home {
+            Some(home) => text.replace(home.as_str(), "~"),
+            None => text.to_string(),
+        };
+        if let Some(user) = &self.user {
+            text = replace_word(&text, user, "<user>");
+        }
+        text
+    }
+}
+
+/// Replace `word` where it isn't part of a longer identifier, so scrubbing
+/// the user `ada` leaves `metadata` alone.
+fn replace_word(text: &str, word: &str, with: &str) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find(word) {
+        let before = rest[..i].chars().next_back().or_else(|| out.chars().next_back());
+        let after = rest[i + word.len()..].chars().next();
+        out.push_str(&rest[..i]);
+        if before.is_some_and(is_ident) || after.is_some_and(is_ident) {
+            out.push_str(word);
+        } else {
+            out.push_str(with);
+        }
+        rest = &rest[i + word.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Every bundle in `dir`, newest first.
+pub fn list(dir: &Path) -> Vec<CrashSummary> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut crashes: Vec<CrashSummary> = entries.filter_map(|e| summary(&e.ok()?.path())).collect();
+    crashes.sort_by_key(|c| std::cmp::Reverse(c.at_ms));
+    crashes
+}
+
+/// Bundles the user hasn't dismissed or submitted, newest first.
+pub fn pending(dir: &Path) -> Vec<CrashSummary> {
+    list(dir).into_iter().filter(|c| c.status == CrashStatus::New).collect()
+}
+
+fn summary(path: &Path) -> Option<CrashSummary> {
+    let at_ms = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(BUNDLE_PREFIX)?
+        .strip_suffix(BUNDLE_EXT)?
+        .strip_suffix('.')?
+        .parse()
+        .ok()?;
+    let text = std::fs::read_to_string(path).ok()?;
+    let header = |key: &str| {
+        text.lines()
+            .take_while(|l| !l.is_empty())
+            .find_map(|l| l.strip_prefix(key)?.strip_prefix(": "))
+            .map(str::to_string)
+    };
+    let status = std::fs::read_to_string(status_path(path)).map(|s| CrashStatus::parse(&s)).unwrap_or(CrashStatus::New);
+    Some(CrashSummary {
+        path: path.to_path_buf(),
+        at_ms,
+        message: header("message").unwrap_or_default(),
+        location: header("location"),
+        status,
+    })
+}
+
+fn status_path(bundle: &Path) -> PathBuf {
+    bundle.with_extension("status")
+}
+
+pub fn set_status(bundle: &Path, status: CrashStatus) -> std::io::Result<()> {
+    std::fs::write(status_path(bundle), status.as_str())
+}
+
+/// Post the bundle to `url` as plain text and mark it submitted.
+pub fn submit(bundle: &Path, url: &str) -> anyhow::Result<()> {
+    crate::network::require_online("crash")?;
+    let data = format!("@{}", bundle.display());
+    crate::update::curl(&["--max-time", "30", "-H", "Content-Type: text/plain", "--data-binary", &data, url])
+        .with_context(|| format!("crash: failed to submit {}", bundle.display()))?;
+    set_status(bundle, CrashStatus::Submitted).with_context(|| format!("crash: failed to mark {} submitted", bundle.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_is_scrubbed() {
+        let log = [WarningRecord {
+            at_ms: 0,
+            level: tracing::Level::WARN,
+            target: "nexus".to_string(),
+            message: "opened /Users/ada/project".to_string(),
+            span: None,
+        }];
+        let text = render(&Crash {
+            at_ms: 1_700_000_000_000,
+            message: "index out of bounds\nsecond line",
+            location: Some("src/lib.rs:1:2".to_string()),
+            thread: "main",
+            backtrace: "0: nexus::main\n   at /Users/ada/src/nexus/main.rs",
+            log: &log,
+        });
+        let scrubber = Scrubber { home: Some("/Users/ada".to_string()), user: Some("ada".to_string()) };
+        let text = scrubber.scrub(&text);
+
+        assert!(text.contains("message: index out of bounds\n"));
+        assert!(text.contains("## Message\nindex out of bounds\nsecond line"));
+        assert!(text.contains("at ~/src/nexus/main.rs"));
+        assert!(text.contains("opened ~/project"));
+        assert!(!text.contains("/ada"));
+        assert_eq!(replace_word("ada@host metadata ada", "ada", "<user>"), "<user>@host metadata <user>");
+    }
+
+    #[test]
+    fn test_list_reads_header_and_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("crash-1000.txt");
+        let new = dir.path().join("crash-2000.txt");
+        std::fs::write(&old, "# Nexus crash report\nmessage: first\n\n## Backtrace\nlocation: not a header\n").unwrap();
+        std::fs::write(&new, "# Nexus crash report\nmessage: second\nlocation: a.rs:1:1\n").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let crashes = list(dir.path());
+        assert_eq!(crashes.len(), 2);
+        assert_eq!((crashes[0].at_ms, crashes[0].message.as_str()), (2000, "second"));
+        assert_eq!(crashes[0].location.as_deref(), Some("a.rs:1:1"));
+        assert_eq!(crashes[1].location, None);
+
+        set_status(&new, CrashStatus::Dismissed).unwrap();
+        let pending = pending(dir.path());
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path, old);
+        assert_eq!(list(dir.path())[0].status, CrashStatus::Dismissed);
+    }
+}
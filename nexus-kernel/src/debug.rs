@@ -0,0 +1,111 @@
+//! Stepping through scripts with `debug <script>`.
+//!
+//! The evaluator runs the script one top-level command at a time. Before
+//! each it sends `ShellEvent::DebugPaused` with the command about to run and
+//! waits on the block's pause channel for a [`DebugAction`], which the UI
+//! delivers with [`send`]. Like the cancel registry, channels are keyed by
+//! block and exist only while the script runs.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use nexus_api::BlockId;
+
+use crate::ShellState;
+
+/// What a paused script should do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Run the next command, then pause again.
+    Step,
+    /// Run the rest of the script without pausing.
+    Continue,
+    /// Stop the script.
+    Abort,
+}
+
+static SESSIONS: std::sync::LazyLock<Mutex<HashMap<BlockId, Sender<DebugAction>>>> =
+    std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Open the pause channel for a block's script.
+pub(crate) fn register(block_id: BlockId) -> Receiver<DebugAction> {
+    let (tx, rx) = mpsc::channel();
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).insert(block_id, tx);
+    rx
+}
+
+/// Close a block's pause channel once its script is done.
+pub(crate) fn unregister(block_id: BlockId) {
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).remove(&block_id);
+}
+
+/// Resume a paused script. Returns false if the block isn't debugging.
+pub fn send(block_id: BlockId, action: DebugAction) -> bool {
+    let sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    sessions.get(&block_id).is_some_and(|tx| tx.send(action).is_ok())
+}
+
+/// `text` with `$NAME`, `${NAME}` and `$?` replaced by their values, for
+/// showing the command about to run. Command substitutions are left as
+/// written: expanding them would run them.
+pub fn preview(text: &str, state: &ShellState) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, consumed) = match after.strip_prefix('{') {
+            Some(inner) => match inner.find('}') {
+                Some(end) => (&inner[..end], end + 2),
+                None => ("", 0),
+            },
+            None if after.starts_with('?') => ("?", 1),
+            None => {
+                let len = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..len], len)
+            }
+        };
+        let value = match name {
+            "" => None,
+            "?" => Some(state.last_exit_code.to_string()),
+            _ => state.get_var_value(name).map(|value| value.to_text()),
+        };
+        match value {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[i..i + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_expands_variables_only() {
+        let mut state = ShellState::from_cwd(std::env::current_dir().unwrap());
+        state.set_var("TARGET", "release");
+        state.last_exit_code = 2;
+        assert_eq!(
+            preview("cargo build --$TARGET ${TARGET}x $? $(date) $UNSET_NAME_X", &state),
+            "cargo build --release releasex 2 $(date) $UNSET_NAME_X"
+        );
+    }
+
+    #[test]
+    fn test_send_reaches_registered_block() {
+        let block_id = BlockId(10_101);
+        assert!(!send(block_id, DebugAction::Step));
+        let rx = register(block_id);
+        assert!(send(block_id, DebugAction::Continue));
+        assert_eq!(rx.recv().unwrap(), DebugAction::Continue);
+        unregister(block_id);
+        assert!(!send(block_id, DebugAction::Abort));
+    }
+}
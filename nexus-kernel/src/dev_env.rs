@@ -0,0 +1,349 @@
+//! Project environments: Python virtualenvs, conda environments and the
+//! Node version an `.nvmrc` asks for, found for the working directory and
+//! activated as changes to the session's environment.
+//!
+//! Activating one puts its `bin` first on `PATH` and sets the variables its
+//! own activation script would (`VIRTUAL_ENV`, `CONDA_PREFIX`, `NVM_BIN`);
+//! deactivating it takes its directory back off `PATH` and restores what
+//! the variables held before. Leaving the directory an environment was
+//! found for deactivates it, and with `[environments] auto = true` entering
+//! one activates what it has.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ShellState;
+
+/// Kinds of environment, in the order they are looked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvKind {
+    Venv,
+    Conda,
+    Node,
+}
+
+impl EnvKind {
+    pub const ALL: [Self; 3] = [Self::Venv, Self::Conda, Self::Node];
+
+    /// The name `activate` and `deactivate` take.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Venv => "venv",
+            Self::Conda => "conda",
+            Self::Node => "node",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+}
+
+/// An environment found for a directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevEnv {
+    pub kind: EnvKind,
+    /// What the prompt shows: the virtualenv's prompt, the conda
+    /// environment's name, or the Node version.
+    pub name: String,
+    /// The directory it was found for; leaving it deactivates it.
+    pub root: PathBuf,
+    /// The environment's own directory, whose `bin` goes on `PATH`.
+    pub prefix: PathBuf,
+}
+
+impl DevEnv {
+    pub fn bin(&self) -> PathBuf {
+        self.prefix.join("bin")
+    }
+
+    /// `venv .venv`, `conda ml`, `node v20.11.0`.
+    pub fn label(&self) -> String {
+        format!("{} {}", self.kind.as_str(), self.name)
+    }
+
+    /// The variables its activation script sets, and those it unsets.
+    fn vars(&self) -> (Vec<(&'static str, String)>, &'static [&'static str]) {
+        let prefix = self.prefix.to_string_lossy().into_owned();
+        match self.kind {
+            EnvKind::Venv => (vec![("VIRTUAL_ENV", prefix), ("VIRTUAL_ENV_PROMPT", self.name.clone())], &["PYTHONHOME"]),
+            EnvKind::Conda => (vec![("CONDA_PREFIX", prefix), ("CONDA_DEFAULT_ENV", self.name.clone())], &[]),
+            EnvKind::Node => (vec![("NVM_BIN", self.bin().to_string_lossy().into_owned())], &[]),
+        }
+    }
+}
+
+/// An activated environment, and what it changed.
+#[derive(Debug, Clone)]
+pub struct ActiveEnv {
+    pub env: DevEnv,
+    /// The variables it set or unset, with their values before.
+    saved: Vec<(&'static str, Option<String>)>,
+    /// The `PATH` entry it added, unless `PATH` already had it.
+    path_entry: Option<String>,
+}
+
+/// The environments for `cwd`, at most one of each kind, each from the
+/// nearest directory that declares one.
+pub fn detect(cwd: &Path, env: &HashMap<String, String>) -> Vec<DevEnv> {
+    EnvKind::ALL
+        .into_iter()
+        .filter_map(|kind| {
+            cwd.ancestors().find_map(|dir| match kind {
+                EnvKind::Venv => venv_in(dir),
+                EnvKind::Conda => conda_in(dir, env),
+                EnvKind::Node => node_in(dir, env),
+            })
+        })
+        .collect()
+}
+
+/// The environments for the working directory, each with whether it is
+/// active, followed by active ones found elsewhere.
+pub fn status(state: &ShellState) -> Vec<(DevEnv, bool)> {
+    let mut found: Vec<(DevEnv, bool)> = detect(&state.cwd, &state.env)
+        .into_iter()
+        .map(|env| {
+            let active = state.dev_envs.iter().any(|a| a.env == env);
+            (env, active)
+        })
+        .collect();
+    for active in &state.dev_envs {
+        if !found.iter().any(|(env, _)| *env == active.env) {
+            found.push((active.env.clone(), true));
+        }
+    }
+    found
+}
+
+/// Activate `env`, replacing an active environment of the same kind.
+pub fn activate(state: &mut ShellState, env: DevEnv) {
+    deactivate(state, env.kind);
+    let (set, unset) = env.vars();
+    let mut saved = Vec::new();
+    for (name, value) in set {
+        saved.push((name, state.env.insert(name.to_string(), value)));
+    }
+    for &name in unset {
+        saved.push((name, state.env.remove(name)));
+    }
+
+    let bin = env.bin().to_string_lossy().into_owned();
+    let path = state.env.get("PATH").cloned().unwrap_or_default();
+    let path_entry = if path.split(':').any(|dir| dir == bin) {
+        None
+    } else {
+        let joined = if path.is_empty() { bin.clone() } else { format!("{}:{}", bin, path) };
+        state.env.insert("PATH".to_string(), joined);
+        Some(bin)
+    };
+    state.dev_envs.push(ActiveEnv { env, saved, path_entry });
+}
+
+/// Deactivate the active environment of `kind`, if there is one.
+pub fn deactivate(state: &mut ShellState, kind: EnvKind) -> Option<DevEnv> {
+    let index = state.dev_envs.iter().position(|a| a.env.kind == kind)?;
+    let active = state.dev_envs.remove(index);
+    for (name, value) in active.saved {
+        match value {
+            Some(value) => state.env.insert(name.to_string(), value),
+            None => state.env.remove(name),
+        };
+    }
+    if let Some(entry) = active.path_entry
+        && let Some(path) = state.env.get("PATH")
+    {
+        let mut dirs: Vec<&str> = path.split(':').collect();
+        if let Some(at) = dirs.iter().position(|dir| *dir == entry) {
+            dirs.remove(at);
+        }
+        let path = dirs.join(":");
+        state.env.insert("PATH".to_string(), path);
+    }
+    Some(active.env)
+}
+
+/// Bring the active environments in line with a new working directory:
+/// deactivate those it is outside of, and when configured, activate those
+/// it has.
+pub(crate) fn follow_cwd(state: &mut ShellState) {
+    let left: Vec<EnvKind> = state
+        .dev_envs
+        .iter()
+        .filter(|a| !state.cwd.starts_with(&a.env.root))
+        .map(|a| a.env.kind)
+        .collect();
+    for kind in left {
+        deactivate(state, kind);
+    }
+    if !state.config.auto_activates_environments() {
+        return;
+    }
+    for env in detect(&state.cwd, &state.env) {
+        if !state.dev_envs.iter().any(|a| a.env == env) {
+            activate(state, env);
+        }
+    }
+}
+
+/// A virtualenv in `dir`: `.venv` or `venv` with a `pyvenv.cfg`.
+fn venv_in(dir: &Path) -> Option<DevEnv> {
+    let (prefix, cfg) = [".venv", "venv"].into_iter().find_map(|name| {
+        let prefix = dir.join(name);
+        let cfg = std::fs::read_to_string(prefix.join("pyvenv.cfg")).ok()?;
+        Some((prefix, cfg))
+    })?;
+    // `python -m venv --prompt` records the prompt; otherwise it is the
+    // environment directory's name.
+    let name = cfg
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim() == "prompt")
+        .map(|(_, value)| value.trim().trim_matches(|c| c == '\'' || c == '"').to_string())
+        .unwrap_or_else(|| prefix.file_name().unwrap_or_default().to_string_lossy().into_owned());
+    Some(DevEnv { kind: EnvKind::Venv, name, root: dir.to_path_buf(), prefix })
+}
+
+/// The conda environment an `environment.yml` in `dir` names, when it
+/// has been created.
+fn conda_in(dir: &Path, env: &HashMap<String, String>) -> Option<DevEnv> {
+    let spec = ["environment.yml", "environment.yaml"]
+        .into_iter()
+        .find_map(|name| std::fs::read_to_string(dir.join(name)).ok())?;
+    let name = spec
+        .lines()
+        .find_map(|line| line.strip_prefix("name:"))
+        .map(|name| name.trim().trim_matches(|c| c == '\'' || c == '"').to_string())
+        .filter(|name| !name.is_empty())?;
+    let prefix = conda_bases(env).into_iter().map(|base| base.join("envs").join(&name)).find(|p| p.is_dir())?;
+    Some(DevEnv { kind: EnvKind::Conda, name, root: dir.to_path_buf(), prefix })
+}
+
+/// Where conda may be installed: beside `$CONDA_EXE`, then the usual
+/// places in the home directory.
+fn conda_bases(env: &HashMap<String, String>) -> Vec<PathBuf> {
+    let mut bases: Vec<PathBuf> = env
+        .get("CONDA_EXE")
+        .and_then(|exe| Path::new(exe).parent()?.parent().map(Path::to_path_buf))
+        .into_iter()
+        .collect();
+    if let Some(home) = env.get("HOME") {
+        bases.extend(["miniconda3", "anaconda3", "miniforge3", "mambaforge"].map(|dir| Path::new(home).join(dir)));
+    }
+    bases
+}
+
+/// The installed Node an `.nvmrc` or `.node-version` in `dir` asks for:
+/// the newest nvm version matching it.
+fn node_in(dir: &Path, env: &HashMap<String, String>) -> Option<DevEnv> {
+    let wanted = [".nvmrc", ".node-version"]
+        .into_iter()
+        .find_map(|name| std::fs::read_to_string(dir.join(name)).ok())?;
+    let nvm = env
+        .get("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| env.get("HOME").map(|home| Path::new(home).join(".nvm")))?;
+    let installed = std::fs::read_dir(nvm.join("versions").join("node")).ok()?;
+    let names: Vec<String> = installed.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect();
+    let version = best_node_version(wanted.trim(), names.iter().map(String::as_str))?.to_string();
+    let prefix = nvm.join("versions").join("node").join(&version);
+    Some(DevEnv { kind: EnvKind::Node, name: version, root: dir.to_path_buf(), prefix })
+}
+
+/// The newest of `installed` (`v20.11.0`, ...) that `wanted` (`20`,
+/// `v20.11`, `20.11.0`) names. Aliases such as `lts/*` match nothing.
+fn best_node_version<'a>(wanted: &str, installed: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let parse = |version: &str| -> Option<Vec<u32>> {
+        version.trim_start_matches('v').split('.').map(|part| part.parse().ok()).collect()
+    };
+    let wanted = parse(wanted).filter(|parts| !parts.is_empty())?;
+    installed
+        .filter_map(|name| Some((parse(name)?, name)))
+        .filter(|(parts, _)| parts.starts_with(&wanted))
+        .max()
+        .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_in(cwd: &Path, home: &Path) -> ShellState {
+        let mut state = ShellState::from_cwd(cwd.to_path_buf());
+        state.env.insert("PATH".into(), "/usr/bin:/bin".into());
+        state.env.insert("HOME".into(), home.to_string_lossy().into_owned());
+        for name in ["VIRTUAL_ENV", "NVM_DIR", "CONDA_EXE"] {
+            state.env.remove(name);
+        }
+        state.env.insert("PYTHONHOME".into(), "/opt/python".into());
+        state
+    }
+
+    #[test]
+    fn test_best_node_version() {
+        let installed = ["v18.19.0", "v20.9.0", "v20.11.1", "v20.11.0"];
+        assert_eq!(best_node_version("20", installed.into_iter()), Some("v20.11.1"));
+        assert_eq!(best_node_version("v20.9", installed.into_iter()), Some("v20.9.0"));
+        assert_eq!(best_node_version("18.19.0", installed.into_iter()), Some("v18.19.0"));
+        assert_eq!(best_node_version("lts/*", installed.into_iter()), None);
+        assert_eq!(best_node_version("21", installed.into_iter()), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join(".venv")).unwrap();
+        std::fs::write(project.join(".venv/pyvenv.cfg"), "home = /usr/bin\nprompt = 'api'\n").unwrap();
+        std::fs::write(project.join(".nvmrc"), "20\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("home/.nvm/versions/node/v20.11.0")).unwrap();
+        std::fs::write(project.join("environment.yml"), "name: ml\ndependencies:\n  - numpy\n").unwrap();
+        std::fs::create_dir_all(project.join("src")).unwrap();
+
+        let state = state_in(&project.join("src"), &dir.path().join("home"));
+        let found = detect(&state.cwd, &state.env);
+        let labels: Vec<String> = found.iter().map(DevEnv::label).collect();
+        // The conda environment was never created.
+        assert_eq!(labels, vec!["venv api", "node v20.11.0"]);
+        assert!(found.iter().all(|env| env.root == project));
+
+        std::fs::create_dir_all(dir.path().join("home/miniconda3/envs/ml")).unwrap();
+        let found = detect(&state.cwd, &state.env);
+        assert_eq!(found[1].prefix, dir.path().join("home/miniconda3/envs/ml"));
+    }
+
+    #[test]
+    fn test_activate_and_leave() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("venv")).unwrap();
+        std::fs::write(project.join("venv/pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        let mut state = state_in(&project, &dir.path().join("home"));
+
+        let env = detect(&state.cwd, &state.env).remove(0);
+        assert_eq!(env.label(), "venv venv");
+        activate(&mut state, env.clone());
+        let bin = project.join("venv/bin").to_string_lossy().into_owned();
+        assert_eq!(state.get_env("PATH"), Some(format!("{}:/usr/bin:/bin", bin).as_str()));
+        assert_eq!(state.get_env("VIRTUAL_ENV"), Some(project.join("venv").to_string_lossy().as_ref()));
+        assert_eq!(state.get_env("PYTHONHOME"), None);
+        assert_eq!(status(&state), vec![(env.clone(), true)]);
+
+        // Activating again doesn't stack.
+        activate(&mut state, env);
+        assert_eq!(state.dev_envs.len(), 1);
+        assert_eq!(state.get_env("PATH").unwrap().matches(&bin).count(), 1);
+
+        // A PATH changed since keeps its other changes.
+        let path = format!("/extra:{}", state.get_env("PATH").unwrap());
+        state.set_env("PATH", path);
+        state.set_cwd(project.join("venv")).unwrap();
+        assert_eq!(state.dev_envs.len(), 1, "still inside the project");
+        state.set_cwd(dir.path().to_path_buf()).unwrap();
+        assert!(state.dev_envs.is_empty());
+        assert_eq!(state.get_env("PATH"), Some("/extra:/usr/bin:/bin"));
+        assert_eq!(state.get_env("VIRTUAL_ENV"), None);
+        assert_eq!(state.get_env("PYTHONHOME"), Some("/opt/python"));
+        assert_eq!(deactivate(&mut state, EnvKind::Venv), None);
+    }
+}
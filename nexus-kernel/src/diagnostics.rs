@@ -0,0 +1,420 @@
+//! Process-wide runtime diagnostics.
+//!
+//! Collects what a bug report needs without asking the user to rerun with
+//! `RUST_LOG`: the most recent warnings and errors, a short tail of the
+//! log at info level for crash reports, timings of the tracing
+//! spans around kernel execution, PTY I/O, agent events and frame
+//! rendering, and the depth and drop count of the event channels between
+//! the kernel, PTYs and UI. The `diagnostics` command reads it back and
+//! assembles a bundle.
+//!
+//! Warnings and span timings are fed by [`layer`], installed next to the
+//! log formatter. Channels register a depth probe with [`register_channel`]
+//! and report drops through the counter it returns. Parts of the app the
+//! kernel can't see into (the renderer, the permission server) add their
+//! own `doctor` checks with [`register_check`].
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// Warnings kept for the console; older ones are dropped.
+pub const WARNING_CAPACITY: usize = 200;
+
+/// Log events (info and above) kept for crash reports.
+pub const LOG_TAIL_CAPACITY: usize = 100;
+
+/// A log event seen by the diagnostics layer.
+#[derive(Debug, Clone)]
+pub struct WarningRecord {
+    /// Unix time in milliseconds.
+    pub at_ms: i64,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// Innermost span the event was emitted in, if any.
+    pub span: Option<String>,
+}
+
+/// Aggregate timings of one span name.
+#[derive(Debug, Clone, Default)]
+pub struct SpanStats {
+    pub name: String,
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl SpanStats {
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 { Duration::ZERO } else { self.total / self.count as u32 }
+    }
+}
+
+/// Current health of a registered event channel.
+#[derive(Debug, Clone)]
+pub struct ChannelReport {
+    pub name: String,
+    /// Messages waiting to be consumed.
+    pub depth: usize,
+    /// Bounded channels only.
+    pub capacity: Option<usize>,
+    /// Messages lost because the consumer fell behind.
+    pub dropped: u64,
+}
+
+/// Outcome of a `doctor` check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "pass",
+            CheckStatus::Warn => "warn",
+            CheckStatus::Fail => "fail",
+        }
+    }
+}
+
+/// One row of `doctor` output.
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure.
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Pass, detail: detail.into(), fix: None }
+    }
+
+    pub fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    pub fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name: name.into(), status: CheckStatus::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+}
+
+type CheckProbe = Box<dyn Fn() -> Option<CheckResult> + Send + Sync>;
+
+type DepthProbe = Box<dyn Fn() -> Option<usize> + Send + Sync>;
+
+struct Channel {
+    name: String,
+    capacity: Option<usize>,
+    /// Returns `None` once the channel is gone; the entry is then pruned.
+    depth: DepthProbe,
+    dropped: Arc<AtomicU64>,
+}
+
+#[derive(Default)]
+struct Registry {
+    warnings: VecDeque<WarningRecord>,
+    log_tail: VecDeque<WarningRecord>,
+    spans: HashMap<&'static str, SpanStats>,
+    channels: Vec<Channel>,
+    /// Each returns `None` once what it checks is gone.
+    checks: Vec<CheckProbe>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::default()));
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Register an event channel. `depth` is polled when diagnostics are read
+/// and should return `None` once the channel is closed. Add lost messages
+/// to the returned counter.
+pub fn register_channel(
+    name: impl Into<String>,
+    capacity: Option<usize>,
+    depth: impl Fn() -> Option<usize> + Send + Sync + 'static,
+) -> Arc<AtomicU64> {
+    let dropped = Arc::new(AtomicU64::new(0));
+    registry().channels.push(Channel { name: name.into(), capacity, depth: Box::new(depth), dropped: dropped.clone() });
+    dropped
+}
+
+/// Snapshot of all live channels. Closed channels are forgotten.
+pub fn channels() -> Vec<ChannelReport> {
+    let mut registry = registry();
+    let mut reports = Vec::new();
+    registry.channels.retain(|channel| match (channel.depth)() {
+        Some(depth) => {
+            reports.push(ChannelReport {
+                name: channel.name.clone(),
+                depth,
+                capacity: channel.capacity,
+                dropped: channel.dropped.load(Ordering::Relaxed),
+            });
+            true
+        }
+        None => false,
+    });
+    reports
+}
+
+/// Register a `doctor` check. `check` runs each time `doctor` does and
+/// should return `None` once what it checks no longer exists.
+pub fn register_check(check: impl Fn() -> Option<CheckResult> + Send + Sync + 'static) {
+    registry().checks.push(Box::new(check));
+}
+
+/// Run every registered check. Checks whose subject is gone are forgotten.
+pub fn run_checks() -> Vec<CheckResult> {
+    let mut registry = registry();
+    let mut results = Vec::new();
+    registry.checks.retain(|check| match check() {
+        Some(result) => {
+            results.push(result);
+            true
+        }
+        None => false,
+    });
+    results
+}
+
+/// Recent warnings and errors, oldest first.
+pub fn warnings() -> Vec<WarningRecord> {
+    registry().warnings.iter().cloned().collect()
+}
+
+/// The most recent log events at info level and above, oldest first.
+pub fn log_tail() -> Vec<WarningRecord> {
+    registry().log_tail.iter().cloned().collect()
+}
+
+/// [`log_tail`] for a panic hook: `None` rather than waiting if the
+/// registry is locked (possibly by the panicking thread).
+pub(crate) fn try_log_tail() -> Option<Vec<WarningRecord>> {
+    let registry = match REGISTRY.try_lock() {
+        Ok(registry) => registry,
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return None,
+    };
+    Some(registry.log_tail.iter().cloned().collect())
+}
+
+/// Span timings, slowest total first.
+pub fn spans() -> Vec<SpanStats> {
+    let mut spans: Vec<SpanStats> = registry().spans.values().cloned().collect();
+    spans.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+    spans
+}
+
+/// Time since diagnostics were first touched (roughly process start).
+pub fn uptime() -> Duration {
+    STARTED.elapsed()
+}
+
+fn record_event(record: WarningRecord) {
+    let mut registry = registry();
+    if registry.log_tail.len() == LOG_TAIL_CAPACITY {
+        registry.log_tail.pop_front();
+    }
+    if record.level <= Level::WARN {
+        if registry.warnings.len() == WARNING_CAPACITY {
+            registry.warnings.pop_front();
+        }
+        registry.warnings.push_back(record.clone());
+    }
+    registry.log_tail.push_back(record);
+}
+
+fn record_span(name: &'static str, elapsed: Duration) {
+    let mut registry = registry();
+    let stats = registry.spans.entry(name).or_insert_with(|| SpanStats { name: name.to_string(), ..Default::default() });
+    stats.count += 1;
+    stats.total += elapsed;
+    stats.max = stats.max.max(elapsed);
+}
+
+/// Plain-text report of everything collected, for pasting into bug reports.
+pub fn bundle() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Nexus diagnostics");
+    let _ = writeln!(out, "version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(out, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(out, "uptime: {}s", uptime().as_secs());
+    let _ = writeln!(out, "low_power: {}", crate::power::is_low_power());
+
+    let _ = writeln!(out, "\n## Channels");
+    for c in channels() {
+        let capacity = c.capacity.map(|c| c.to_string()).unwrap_or_else(|| "unbounded".to_string());
+        let _ = writeln!(out, "{}: depth={} capacity={} dropped={}", c.name, c.depth, capacity, c.dropped);
+    }
+
+    let _ = writeln!(out, "\n## Spans");
+    for s in spans() {
+        let _ = writeln!(
+            out,
+            "{}: count={} mean={:.2}ms max={:.2}ms",
+            s.name,
+            s.count,
+            s.mean().as_secs_f64() * 1000.0,
+            s.max.as_secs_f64() * 1000.0
+        );
+    }
+
+    let _ = writeln!(out, "\n## Recent warnings");
+    for w in warnings() {
+        let time = chrono::DateTime::from_timestamp_millis(w.at_ms).map(|t| t.to_rfc3339()).unwrap_or_default();
+        let span = w.span.map(|s| format!(" [{}]", s)).unwrap_or_default();
+        let _ = writeln!(out, "{} {} {}{}: {}", time, w.level, w.target, span, w.message);
+    }
+    out
+}
+
+// ---- tracing layer ----
+
+/// Tracing layer feeding the diagnostics registry: records every span
+/// (debug and above) and keeps info, warning and error events.
+pub fn layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    // Touch the clock so uptime counts from subscriber setup.
+    LazyLock::force(&STARTED);
+    DiagnosticsLayer.with_filter(filter_fn(|meta| {
+        if meta.is_span() { *meta.level() <= Level::DEBUG } else { *meta.level() <= Level::INFO }
+    }))
+}
+
+struct DiagnosticsLayer;
+
+/// When a span was created, stored in its extensions.
+struct SpanStart(Instant);
+
+impl<S> Layer<S> for DiagnosticsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &tracing::span::Attributes<'_>, id: &tracing::span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: tracing::span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id)
+            && let Some(start) = span.extensions().get::<SpanStart>()
+        {
+            record_span(span.metadata().name(), start.0.elapsed());
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        record_event(WarningRecord {
+            at_ms: chrono::Utc::now().timestamp_millis(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message,
+            span: ctx.event_span(event).map(|span| span.name().to_string()),
+        });
+    }
+}
+
+/// Flattens an event's fields: the message, then `key=value` pairs.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.message);
+            let _ = write!(self.message, "{:?}{}", value, fields);
+        } else {
+            let _ = write!(self.message, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_layer_records_warnings_and_spans() {
+        let subscriber = tracing_subscriber::registry().with(layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::debug_span!("diagnostics.test_span");
+            let _entered = span.enter();
+            tracing::warn!(code = 7, "diagnostics test warning");
+            tracing::info!("not kept");
+            tracing::debug!("not in the tail");
+        });
+
+        let warning = warnings().into_iter().rev().find(|w| w.message.starts_with("diagnostics test warning")).unwrap();
+        assert_eq!(warning.level, Level::WARN);
+        assert_eq!(warning.message, "diagnostics test warning code=7");
+        assert_eq!(warning.span.as_deref(), Some("diagnostics.test_span"));
+        assert!(!warnings().iter().any(|w| w.message == "not kept"));
+        assert!(log_tail().iter().any(|w| w.message == "not kept"));
+        assert!(!log_tail().iter().any(|w| w.message == "not in the tail"));
+
+        let stats = spans().into_iter().find(|s| s.name == "diagnostics.test_span").unwrap();
+        assert!(stats.count >= 1);
+    }
+
+    #[test]
+    fn test_closed_channels_are_pruned() {
+        let alive = Arc::new(());
+        let weak = Arc::downgrade(&alive);
+        let dropped = register_channel("diagnostics.test", Some(8), move || weak.upgrade().map(|_| 3));
+        dropped.fetch_add(2, Ordering::Relaxed);
+
+        let report = channels().into_iter().find(|c| c.name == "diagnostics.test").unwrap();
+        assert_eq!((report.depth, report.capacity, report.dropped), (3, Some(8), 2));
+
+        drop(alive);
+        assert!(!channels().iter().any(|c| c.name == "diagnostics.test"));
+    }
+
+    #[test]
+    fn test_checks_are_pruned() {
+        let alive = Arc::new(());
+        let weak = Arc::downgrade(&alive);
+        register_check(move || weak.upgrade().map(|_| CheckResult::warn("diagnostics.check", "odd", "fix it")));
+
+        let result = run_checks().into_iter().find(|c| c.name == "diagnostics.check").unwrap();
+        assert_eq!(result.status, CheckStatus::Warn);
+        assert_eq!(result.fix.as_deref(), Some("fix it"));
+
+        drop(alive);
+        assert!(!run_checks().iter().any(|c| c.name == "diagnostics.check"));
+    }
+
+    #[test]
+    fn test_bundle_has_sections() {
+        let bundle = bundle();
+        for section in ["## Channels", "## Spans", "## Recent warnings"] {
+            assert!(bundle.contains(section), "missing {section}");
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! Holding the agent to a [`NetworkPolicy`].
+//!
+//! [`Egress::start`] opens a proxy on a loopback port that forwards HTTP
+//! `CONNECT` tunnels and plain `http://` requests to the hosts the policy
+//! allows, and answers 403 to the rest. [`Egress::command`] builds a
+//! command whose process, and everything it starts, can only open TCP
+//! connections to that port and the loopback ports the proxy was given,
+//! with the proxy variables pointing at it:
+//!
+//! - on macOS it runs under `sandbox-exec`, with a profile that denies
+//!   outbound network but to those ports;
+//! - on Linux a Landlock ruleset (Linux 6.7 or later) lets TCP connect to
+//!   those ports only, and a seccomp filter refuses UDP and other sockets
+//!   Landlock doesn't see, and io_uring, which could open them behind the
+//!   filter's back. Landlock matches the port, not the address.
+//!
+//! A program that ignores the proxy variables can't connect at all. Names
+//! are still looked up through the system resolver, and Unix sockets stay
+//! open. Where the sandbox can't be set up, [`Egress::start`] fails rather
+//! than run the agent unconfined.
+
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::config::{NetworkPolicy, in_domain};
+
+/// Longest request head the proxy reads before giving up on a client.
+const MAX_HEAD: usize = 16 * 1024;
+/// How long a client has to send its request head, and the proxy to reach
+/// the host it names.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Variables HTTP clients take their proxy from.
+const PROXY_VARS: &[&str] = &["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY", "http_proxy", "https_proxy", "all_proxy"];
+
+/// A running proxy, and the sandbox that makes a process use it. The proxy
+/// stops when this is dropped.
+#[derive(Debug)]
+pub struct Egress {
+    port: u16,
+    /// Loopback ports, besides the proxy's, the sandboxed process may reach.
+    loopback: Vec<u16>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Egress {
+    /// Start a proxy to the hosts `policy` allows and the domains in
+    /// `service`, the ones the sandboxed program can't work without. The
+    /// program may also reach `loopback` ports directly.
+    pub fn start(policy: NetworkPolicy, service: &[&str], loopback: &[u16]) -> io::Result<Self> {
+        platform::check()?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let rules = Arc::new(Rules { policy, service: service.iter().map(|d| d.to_ascii_lowercase()).collect() });
+        let stop = stopped.clone();
+        std::thread::Builder::new().name("nexus-egress".into()).spawn(move || {
+            for client in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                let Ok(client) = client else { continue };
+                let rules = rules.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = serve(client, &rules) {
+                        tracing::debug!("egress connection ended: {}", e);
+                    }
+                });
+            }
+        })?;
+        Ok(Self { port, loopback: loopback.to_vec(), stopped })
+    }
+
+    /// The proxy's port on `127.0.0.1`.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// A command for `program` that can only reach the network through
+    /// this proxy.
+    pub fn command(&self, program: &str) -> io::Result<Command> {
+        let mut ports = vec![self.port];
+        ports.extend(&self.loopback);
+        let mut cmd = platform::command(program, &ports)?;
+        let url = format!("http://127.0.0.1:{}", self.port);
+        for var in PROXY_VARS {
+            cmd.env(var, &url);
+        }
+        cmd.env_remove("NO_PROXY").env_remove("no_proxy");
+        Ok(cmd)
+    }
+}
+
+impl Drop for Egress {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+    }
+}
+
+struct Rules {
+    policy: NetworkPolicy,
+    service: Vec<String>,
+}
+
+impl Rules {
+    fn allows(&self, host: &str) -> bool {
+        let name = host.trim_end_matches('.').to_ascii_lowercase();
+        self.policy.allows(host) || self.service.iter().any(|domain| in_domain(&name, domain))
+    }
+}
+
+/// One client connection: read its request head, check the host it names,
+/// then pass bytes both ways.
+fn serve(mut client: TcpStream, rules: &Rules) -> io::Result<()> {
+    client.set_read_timeout(Some(TIMEOUT))?;
+    let (head, rest) = read_head(&mut client)?;
+    let Some(target) = Target::parse(&head) else {
+        return client.write_all(b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n");
+    };
+    if !rules.allows(&target.host) {
+        tracing::info!("egress: {} is outside [sandbox] network", target.host);
+        let body = format!("nexus: {} is not allowed by [sandbox] network\n", target.host);
+        let response = format!(
+            "HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        return client.write_all(response.as_bytes());
+    }
+    let upstream = match connect(&target.host, target.port) {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            client.write_all(b"HTTP/1.1 502 Bad Gateway\r\nConnection: close\r\n\r\n")?;
+            return Err(e);
+        }
+    };
+    client.set_read_timeout(None)?;
+    let mut upstream_writer = upstream.try_clone()?;
+    if target.tunnel {
+        client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")?;
+    } else {
+        upstream_writer.write_all(head.as_bytes())?;
+    }
+    upstream_writer.write_all(&rest)?;
+    splice(client, upstream)
+}
+
+/// Read up to the blank line ending a request head. Returns the head and
+/// any bytes that came after it.
+fn read_head(client: &mut TcpStream) -> io::Result<(String, Vec<u8>)> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buf.split_off(end + 4);
+            return Ok((String::from_utf8_lossy(&buf).into_owned(), rest));
+        }
+        if buf.len() > MAX_HEAD {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request head too long"));
+        }
+        let n = client.read(&mut chunk)?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "no address");
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last = e,
+        }
+    }
+    Err(last)
+}
+
+/// Copy bytes both ways until each side has finished sending.
+fn splice(client: TcpStream, upstream: TcpStream) -> io::Result<()> {
+    let (mut client_reader, mut upstream_writer) = (client.try_clone()?, upstream.try_clone()?);
+    let up = std::thread::spawn(move || {
+        let _ = io::copy(&mut client_reader, &mut upstream_writer);
+        let _ = upstream_writer.shutdown(Shutdown::Write);
+    });
+    let (mut upstream_reader, mut client_writer) = (upstream, client);
+    let _ = io::copy(&mut upstream_reader, &mut client_writer);
+    let _ = client_writer.shutdown(Shutdown::Write);
+    let _ = up.join();
+    Ok(())
+}
+
+/// Where a request wants to go.
+#[derive(Debug, PartialEq)]
+struct Target {
+    host: String,
+    port: u16,
+    /// `CONNECT`, as opposed to a plain `http://` request.
+    tunnel: bool,
+}
+
+impl Target {
+    /// From a request line: `CONNECT host:port HTTP/1.1` or
+    /// `GET http://host[:port]/path HTTP/1.1`.
+    fn parse(head: &str) -> Option<Self> {
+        let mut words = head.lines().next()?.split_whitespace();
+        let (method, target) = (words.next()?, words.next()?);
+        if method.eq_ignore_ascii_case("CONNECT") {
+            let (host, port) = split_port(target)?;
+            return Some(Self { host: host.to_string(), port: port?, tunnel: true });
+        }
+        let rest = target.get(..7).filter(|s| s.eq_ignore_ascii_case("http://")).map(|_| &target[7..])?;
+        let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+        let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+        let (host, port) = split_port(authority)?;
+        Some(Self { host: host.to_string(), port: port.unwrap_or(80), tunnel: false })
+    }
+}
+
+/// Split `host[:port]`, where `host` may be a bracketed IPv6 address.
+/// The port is `None` when absent and the whole thing `None` when it
+/// doesn't parse.
+fn split_port(authority: &str) -> Option<(&str, Option<u16>)> {
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(v6) => {
+            let end = v6.find(']')?;
+            (&authority[..end + 2], v6[end + 1..].strip_prefix(':'))
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    if host.is_empty() {
+        return None;
+    }
+    match port {
+        Some(port) => Some((host, Some(port.parse().ok()?))),
+        None => Some((host, None)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::io;
+    use std::path::Path;
+    use std::process::Command;
+
+    const SANDBOX_EXEC: &str = "/usr/bin/sandbox-exec";
+
+    pub fn check() -> io::Result<()> {
+        if Path::new(SANDBOX_EXEC).exists() {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "sandbox-exec is missing"))
+        }
+    }
+
+    pub fn command(program: &str, ports: &[u16]) -> io::Result<Command> {
+        let mut cmd = Command::new(SANDBOX_EXEC);
+        cmd.args(["-p", &profile(ports), program]);
+        Ok(cmd)
+    }
+
+    /// Everything but outbound network, which may only go to `ports` on
+    /// this machine and to Unix sockets.
+    pub(super) fn profile(ports: &[u16]) -> String {
+        let mut profile = String::from(
+            "(version 1)\n(allow default)\n(deny network-outbound)\n(allow network-outbound (remote unix-socket))\n",
+        );
+        for port in ports {
+            profile.push_str(&format!("(allow network-outbound (remote tcp \"localhost:{}\"))\n", port));
+        }
+        profile
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1;
+    /// The first Landlock ABI with network rules.
+    const LANDLOCK_NET_ABI: libc::c_long = 4;
+    const LANDLOCK_ACCESS_NET_CONNECT_TCP: u64 = 2;
+    const LANDLOCK_RULE_NET_PORT: libc::c_int = 2;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xC000_003E;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xC000_00B7;
+    /// x32 system calls on x86_64 have this bit set.
+    const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C)]
+    struct NetPortAttr {
+        allowed_access: u64,
+        port: u64,
+    }
+
+    pub fn check() -> io::Result<()> {
+        if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "network sandboxing needs x86_64 or aarch64"));
+        }
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < LANDLOCK_NET_ABI {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "network sandboxing needs Landlock network rules (Linux 6.7 or later)",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn command(program: &str, ports: &[u16]) -> io::Result<Command> {
+        check()?;
+        let ruleset = ruleset(ports)?;
+        let filter = socket_filter();
+        let mut cmd = Command::new(program);
+        // Only system calls between fork and exec: the ruleset and filter
+        // are built above.
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0
+                    || libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0u32) != 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                let program = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_ptr() as *mut _ };
+                if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &program as *const libc::sock_fprog) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(cmd)
+    }
+
+    /// A Landlock ruleset that lets TCP connect to `ports` only.
+    fn ruleset(ports: &[u16]) -> io::Result<OwnedFd> {
+        let attr = RulesetAttr { handled_access_fs: 0, handled_access_net: LANDLOCK_ACCESS_NET_CONNECT_TCP };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                std::mem::size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+        for &port in ports {
+            let rule = NetPortAttr { allowed_access: LANDLOCK_ACCESS_NET_CONNECT_TCP, port: port.into() };
+            let added = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    LANDLOCK_RULE_NET_PORT,
+                    &rule as *const NetPortAttr,
+                    0u32,
+                )
+            };
+            if added != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(ruleset)
+    }
+
+    /// A seccomp filter allowing only the sockets Landlock governs, TCP
+    /// over IPv4 and IPv6, besides Unix and netlink ones; it refuses
+    /// io_uring and system calls of another architecture outright.
+    pub(super) fn socket_filter() -> Vec<libc::sock_filter> {
+        const ALLOW: u8 = 17;
+        const DENY: u8 = 18;
+        let load = |offset: u32| stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset);
+        let eq = |at: u8, k: u32, jt: u8, jf: u8| jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, k, jt, jf, at);
+        vec![
+            /* 0 */ load(4),
+            /* 1 */ eq(1, AUDIT_ARCH, 2, DENY),
+            /* 2 */ load(0),
+            /* 3 */ jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, X32_SYSCALL_BIT, DENY, 4, 3),
+            /* 4 */ eq(4, libc::SYS_io_uring_setup as u32, DENY, 5),
+            /* 5 */ eq(5, libc::SYS_socket as u32, 6, ALLOW),
+            /* 6 */ load(16),
+            /* 7 */ eq(7, libc::AF_UNIX as u32, ALLOW, 8),
+            /* 8 */ eq(8, libc::AF_NETLINK as u32, ALLOW, 9),
+            /* 9 */ eq(9, libc::AF_INET as u32, 11, 10),
+            /* 10 */ eq(10, libc::AF_INET6 as u32, 11, DENY),
+            /* 11 */ load(24),
+            /* 12 */ stmt(libc::BPF_ALU | libc::BPF_AND | libc::BPF_K, 0xf),
+            /* 13 */ eq(13, libc::SOCK_STREAM as u32, 14, DENY),
+            /* 14 */ load(32),
+            /* 15 */ eq(15, 0, ALLOW, 16),
+            /* 16 */ eq(16, libc::IPPROTO_TCP as u32, ALLOW, DENY),
+            /* ALLOW */ stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW),
+            /* DENY */ stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ERRNO | libc::EACCES as u32),
+        ]
+    }
+
+    fn stmt(code: u32, k: u32) -> libc::sock_filter {
+        libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+    }
+
+    /// A conditional jump at instruction `at` to instructions `jt` and `jf`,
+    /// which BPF counts from the next one.
+    fn jump(code: u32, k: u32, jt: u8, jf: u8, at: u8) -> libc::sock_filter {
+        libc::sock_filter { code: code as u16, jt: jt - at - 1, jf: jf - at - 1, k }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod platform {
+    use std::io;
+    use std::process::Command;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, "network sandboxing isn't available on this platform")
+    }
+
+    pub fn check() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn command(_program: &str, _ports: &[u16]) -> io::Result<Command> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_parse() {
+        let parse = |line: &str| Target::parse(&format!("{}\r\nHost: x\r\n\r\n", line));
+        let target = |host: &str, port, tunnel| Some(Target { host: host.to_string(), port, tunnel });
+        assert_eq!(parse("CONNECT crates.io:443 HTTP/1.1"), target("crates.io", 443, true));
+        assert_eq!(parse("CONNECT [::1]:8443 HTTP/1.1"), target("[::1]", 8443, true));
+        assert_eq!(parse("GET http://example.com/a?b HTTP/1.1"), target("example.com", 80, false));
+        assert_eq!(parse("GET http://me@localhost:3000/ HTTP/1.1"), target("localhost", 3000, false));
+        assert_eq!(parse("CONNECT crates.io HTTP/1.1"), None);
+        assert_eq!(parse("GET /index.html HTTP/1.1"), None);
+        assert_eq!(parse("GET https://example.com/ HTTP/1.1"), None);
+    }
+
+    fn request(port: u16, head: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        stream.write_all(head.as_bytes()).unwrap();
+        let mut reply = String::new();
+        let _ = stream.read_to_string(&mut reply);
+        reply
+    }
+
+    #[test]
+    fn test_proxy_forwards_only_allowed_hosts() {
+        let Ok(egress) = Egress::start(NetworkPolicy::AllowLoopback, &[], &[]) else {
+            return; // No sandbox here.
+        };
+        let upstream = TcpListener::bind("127.0.0.1:0").unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (mut conn, _) = upstream.accept().unwrap();
+            let _ = read_head(&mut conn);
+            conn.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok").unwrap();
+        });
+
+        let reply = request(egress.port(), &format!("GET http://127.0.0.1:{}/ HTTP/1.1\r\n\r\n", upstream_port));
+        assert!(reply.ends_with("ok"), "{}", reply);
+
+        let reply = request(egress.port(), "CONNECT example.com:443 HTTP/1.1\r\n\r\n");
+        assert!(reply.starts_with("HTTP/1.1 403"), "{}", reply);
+    }
+
+    #[test]
+    fn test_service_domains_pass() {
+        let rules = Rules { policy: NetworkPolicy::Deny, service: vec!["anthropic.com".to_string()] };
+        assert!(rules.allows("api.anthropic.com") && rules.allows("anthropic.com."));
+        assert!(!rules.allows("localhost") && !rules.allows("example.com"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sandboxed_command_reaches_only_the_proxy() {
+        let Ok(egress) = Egress::start(NetworkPolicy::Deny, &[], &[]) else {
+            return; // Landlock without network rules.
+        };
+        let other = TcpListener::bind("127.0.0.1:0").unwrap();
+        let script = format!(
+            "exec 3<>/dev/tcp/127.0.0.1/{} && echo proxy; exec 4<>/dev/tcp/127.0.0.1/{} && echo other; exec 5<>/dev/udp/127.0.0.1/53 && echo udp; echo $HTTPS_PROXY",
+            egress.port(),
+            other.local_addr().unwrap().port()
+        );
+        let output = egress.command("bash").unwrap().args(["-c", &script]).output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(stdout, format!("proxy\nhttp://127.0.0.1:{}\n", egress.port()));
+    }
+}
@@ -0,0 +1,249 @@
+//! At-rest encryption for the persistence store.
+//!
+//! Commands and captured outputs are sealed with ChaCha20-Poly1305 before
+//! they reach SQLite, so a copied `nexus.db` (backups, sync folders) does not
+//! leak secrets that scrolled past in a terminal. Each value gets a fresh
+//! nonce and is stored as `base64(nonce || ciphertext)`. Which rows are
+//! sealed is recorded beside them (the `sealed` column), never guessed from
+//! the text, so rows written before encryption was enabled stay readable as
+//! plaintext.
+//!
+//! The 256-bit key lives in the macOS Keychain (service `nexus`, account
+//! `store-key`). Other platforms fall back to `~/.nexus/store.key` with
+//! mode 0600, which only helps when the database travels without it.
+//!
+//! Enabled with `NEXUS_ENCRYPT_STORE=on`. Once a database has been
+//! encrypted it stays encrypted until `NEXUS_ENCRYPT_STORE=off`.
+//!
+//! Outputs are stored once per distinct content, keyed by a digest. With
+//! encryption on that digest is keyed too ([`StoreCipher::digest`]), so
+//! it does not reveal which outputs are equal to a known text.
+//!
+//! Native shell history (`~/.zsh_history`) belongs to the user's shell and
+//! is not covered.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// What `NEXUS_ENCRYPT_STORE` asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionSetting {
+    On,
+    Off,
+    /// Unset: keep whatever the database already uses.
+    Auto,
+}
+
+impl EncryptionSetting {
+    pub fn from_env() -> Self {
+        std::env::var("NEXUS_ENCRYPT_STORE")
+            .ok()
+            .and_then(|v| Self::parse(&v))
+            .unwrap_or(Self::Auto)
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "on" | "1" | "true" => Some(Self::On),
+            "off" | "0" | "false" => Some(Self::Off),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+
+    /// Whether to encrypt, given whether the database was encrypted before.
+    pub fn resolve(self, previously_encrypted: bool) -> bool {
+        match self {
+            Self::On => true,
+            Self::Off => false,
+            Self::Auto => previously_encrypted,
+        }
+    }
+}
+
+/// Seals and opens individual column values.
+#[derive(Clone)]
+pub struct StoreCipher {
+    aead: ChaCha20Poly1305,
+    key: [u8; KEY_LEN],
+}
+
+impl StoreCipher {
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self { aead: ChaCha20Poly1305::new(Key::from_slice(key)), key: *key }
+    }
+
+    /// Load the store key from the keychain, generating one on first use.
+    pub fn load_or_create() -> Result<Self> {
+        let key = match keystore::load()? {
+            Some(key) => key,
+            None => {
+                let key: [u8; KEY_LEN] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+                keystore::save(&key)?;
+                tracing::info!("Created store encryption key");
+                key
+            }
+        };
+        Ok(Self::new(&key))
+    }
+
+    /// Encrypt a value for storage.
+    pub fn seal(&self, plaintext: &str) -> String {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .aead
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("ChaCha20-Poly1305 encryption is infallible for in-memory buffers");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        BASE64.encode(sealed)
+    }
+
+    /// Hex HMAC-SHA256 of `plaintext` under the store key: equal values
+    /// get equal digests, which only the key holder can compute.
+    pub fn digest(&self, plaintext: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        const BLOCK_LEN: usize = 64;
+        let mut ipad = [0x36u8; BLOCK_LEN];
+        let mut opad = [0x5cu8; BLOCK_LEN];
+        for (i, b) in self.key.iter().enumerate() {
+            ipad[i] ^= b;
+            opad[i] ^= b;
+        }
+        let inner = Sha256::new().chain_update(ipad).chain_update(plaintext).finalize();
+        let digest = Sha256::new().chain_update(opad).chain_update(inner).finalize();
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Decrypt a value produced by [`StoreCipher::seal`].
+    pub fn open(&self, stored: &str) -> Result<String> {
+        let sealed = BASE64.decode(stored).context("Corrupt encrypted value")?;
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Corrupt encrypted value");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .aead
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt stored value (wrong key?)"))?;
+        String::from_utf8(plaintext).context("Decrypted value is not UTF-8")
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod keystore {
+    use super::KEY_LEN;
+    use anyhow::Result;
+    use security_framework::passwords::{get_generic_password, set_generic_password};
+
+    const SERVICE: &str = "nexus";
+    const ACCOUNT: &str = "store-key";
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+    pub fn load() -> Result<Option<[u8; KEY_LEN]>> {
+        match get_generic_password(SERVICE, ACCOUNT) {
+            Ok(bytes) => bytes
+                .try_into()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("Keychain item {SERVICE}/{ACCOUNT} is not a store key")),
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("Failed to read store key from Keychain: {e}")),
+        }
+    }
+
+    pub fn save(key: &[u8; KEY_LEN]) -> Result<()> {
+        set_generic_password(SERVICE, ACCOUNT, key)
+            .map_err(|e| anyhow::anyhow!("Failed to save store key to Keychain: {e}"))
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+mod keystore {
+    use super::KEY_LEN;
+    use anyhow::{Context, Result};
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    fn key_path() -> Result<PathBuf> {
+        let home = std::env::var("HOME").context("HOME environment variable not set")?;
+        Ok(PathBuf::from(home).join(".nexus").join("store.key"))
+    }
+
+    pub fn load() -> Result<Option<[u8; KEY_LEN]>> {
+        let path = key_path()?;
+        match std::fs::read(&path) {
+            Ok(bytes) => bytes
+                .try_into()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("{} is not a store key", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(key: &[u8; KEY_LEN]) -> Result<()> {
+        let path = key_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        file.write_all(key)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let cipher = StoreCipher::new(&[7; KEY_LEN]);
+        let sealed = cipher.seal("export TOKEN=hunter2");
+        assert!(!sealed.contains("hunter2"));
+        assert_eq!(cipher.open(&sealed).unwrap(), "export TOKEN=hunter2");
+        // Fresh nonce per value.
+        assert_ne!(sealed, cipher.seal("export TOKEN=hunter2"));
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let sealed = StoreCipher::new(&[1; KEY_LEN]).seal("secret");
+        assert!(StoreCipher::new(&[2; KEY_LEN]).open(&sealed).is_err());
+        assert!(StoreCipher::new(&[1; KEY_LEN]).open("AAAA").is_err());
+        assert!(StoreCipher::new(&[1; KEY_LEN]).open("ls -la").is_err());
+    }
+
+    #[test]
+    fn test_digest_is_keyed() {
+        let a = StoreCipher::new(&[1; KEY_LEN]);
+        assert_eq!(a.digest(b"out"), a.digest(b"out"));
+        assert_ne!(a.digest(b"out"), a.digest(b"other"));
+        assert_ne!(a.digest(b"out"), StoreCipher::new(&[2; KEY_LEN]).digest(b"out"));
+        // Matches a reference HMAC-SHA256 implementation.
+        assert_eq!(a.digest(b"out"), "fcd33c4d88fe3e7b31cd3091ce7145b072aea9f845750f6abb91c6dc87e53ba2");
+    }
+
+    #[test]
+    fn test_setting_resolution() {
+        assert_eq!(EncryptionSetting::parse("ON"), Some(EncryptionSetting::On));
+        assert_eq!(EncryptionSetting::parse("0"), Some(EncryptionSetting::Off));
+        assert_eq!(EncryptionSetting::parse("maybe"), None);
+        assert!(EncryptionSetting::Auto.resolve(true));
+        assert!(!EncryptionSetting::Auto.resolve(false));
+        assert!(!EncryptionSetting::Off.resolve(true));
+        assert!(EncryptionSetting::On.resolve(false));
+    }
+}
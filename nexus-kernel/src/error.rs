@@ -1,5 +1,8 @@
 //! Shell error types.
 
+use std::path::Path;
+
+use nexus_api::{CommandError, CommandErrorKind};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -13,12 +16,142 @@ pub enum ShellError {
     #[error("syntax error: {0}")]
     Syntax(String),
 
+    #[error("{0}: event not found")]
+    EventNotFound(String),
+
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 
     #[error("nix error: {0}")]
     Nix(#[from] nix::Error),
 
+    /// Ctrl-C ended the block (INT not trapped).
+    #[error("interrupted")]
+    Interrupted,
+
+    /// `set -e` or `set -u` ended the block with this status.
+    #[error("aborted with status {0}")]
+    Aborted(i32),
+
     #[error("{0}")]
     Other(String),
 }
+
+/// Turn a native command's error into a [`CommandError`]. Commands either
+/// return one directly or an ad-hoc `anyhow` error; for the latter, the kind
+/// and errno come from an `io::Error` in the chain when there is one and
+/// the message is the error text without a repeated `name: ` prefix.
+/// Returns `None` for errors with no text (`false`, `exit`-like failures).
+pub fn command_error(name: &str, err: &anyhow::Error) -> Option<CommandError> {
+    if let Some(error) = err.downcast_ref::<CommandError>() {
+        let mut error = error.clone();
+        if error.command.is_empty() {
+            error.command = name.to_string();
+        }
+        return Some(error);
+    }
+
+    let text = err.to_string();
+    let message = text.strip_prefix(name).and_then(|rest| rest.strip_prefix(": ")).unwrap_or(&text);
+    if message.is_empty() {
+        return None;
+    }
+
+    let mut error = CommandError::new(name, CommandErrorKind::Other, message);
+    if let Some(io) = err.chain().find_map(|cause| cause.downcast_ref::<std::io::Error>()) {
+        error.kind = CommandErrorKind::from_io(io.kind());
+        error.errno = io.raw_os_error();
+    } else if message.contains("missing operand") || message.starts_with("usage:") {
+        error.kind = CommandErrorKind::Usage;
+    }
+    Some(error)
+}
+
+/// For a missing path, suggest a similarly named file next to it. Relative
+/// paths are resolved against `cwd`.
+pub fn add_path_suggestion(error: &mut CommandError, cwd: &Path) {
+    if error.kind != CommandErrorKind::NotFound || error.suggestion.is_some() {
+        return;
+    }
+    if let Some(path) = &error.path {
+        error.suggestion = suggest_similar_path(&cwd.join(path));
+    }
+}
+
+/// Suggest a sibling of a missing `path` with a similar name.
+pub fn suggest_similar_path(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let best = std::fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .map(|candidate| (edit_distance(name, &candidate), candidate))
+        .filter(|(distance, _)| *distance > 0 && *distance <= 2)
+        .min()?;
+    Some(format!("did you mean '{}'?", best.1))
+}
+
+/// Edits (insert, delete, substitute, or swap two neighbours) turning
+/// `a` into `b`.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Three rows: the one before last is needed for swaps.
+    let mut before: Vec<usize> = Vec::new();
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut last, row);
+    }
+    last[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_error_in_chain_sets_kind() {
+        let io = std::io::Error::from_raw_os_error(13);
+        let err = anyhow::Error::new(io).context("rm: cannot remove 'x'");
+        let error = command_error("rm", &err).unwrap();
+        assert_eq!(error.kind, CommandErrorKind::PermissionDenied);
+        assert_eq!(error.errno, Some(13));
+        assert_eq!(error.message, "cannot remove 'x'");
+    }
+
+    #[test]
+    fn test_typed_error_passes_through_and_empty_is_none() {
+        let err: anyhow::Error = CommandError::usage("", "missing operand").into();
+        assert_eq!(command_error("mkdir", &err).unwrap().command, "mkdir");
+        assert!(command_error("false", &anyhow::anyhow!("")).is_none());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("git", "git"), 0);
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("carg", "cargo"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_suggest_similar_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+        let suggestion = suggest_similar_path(&dir.path().join("REDME.md"));
+        assert_eq!(suggestion.as_deref(), Some("did you mean 'README.md'?"));
+        assert!(suggest_similar_path(&dir.path().join("unrelated")).is_none());
+    }
+}
@@ -0,0 +1,530 @@
+//! Arithmetic evaluation - `$((expr))`, `((expr))` and `for ((init; cond; step))`.
+//!
+//! Integers are 64-bit and wrap on overflow, as in bash. A name is a shell
+//! variable; one that's unset or doesn't hold a number is 0. Assignments
+//! (`=`, `+=`, ...) and `++`/`--` don't touch the state directly: they're
+//! returned in [`Evaluation::assignments`] for the caller to store, because
+//! `$((..))` is expanded against a read-only state. [`run`] evaluates and
+//! stores in one go.
+//!
+//! Supports:
+//! - Integers: 42, 0x2a, 052, 16#2a
+//! - Variables: x, $x, ${x}
+//! - Operators: + - * / % ** (power)
+//! - Comparison: < > <= >= == !=
+//! - Logical: && || ! (short-circuiting)
+//! - Bitwise: & | ^ ~ << >>
+//! - Ternary: a ? b : c
+//! - Assignment: = += -= *= /= %= <<= >>= &= ^= |=
+//! - Increment/decrement: ++x x++ --x x--
+//! - Comma: a, b
+//! - Parentheses for grouping
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ShellState;
+
+/// The result of an expression, and the variables it assigned, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    pub value: i64,
+    pub assignments: Vec<(String, i64)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArithError(String);
+
+impl fmt::Display for ArithError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ArithError {}
+
+/// Evaluate `expr`. An empty expression is 0.
+pub fn evaluate(expr: &str, state: &ShellState) -> Result<Evaluation, ArithError> {
+    let tokens = tokenize(expr, state)?;
+    let mut parser = Parser { tokens, pos: 0, state, assigned: HashMap::new(), order: Vec::new(), skip: 0 };
+    let value = if parser.tokens.is_empty() { 0 } else { parser.comma()? };
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(ArithError(format!("syntax error in expression (error token is \"{}\")", token)));
+    }
+    let assignments = parser.order.into_iter().map(|name| {
+        let value = parser.assigned[&name];
+        (name, value)
+    });
+    Ok(Evaluation { value, assignments: assignments.collect() })
+}
+
+/// Evaluate `expr` and store what it assigned.
+pub fn run(expr: &str, state: &mut ShellState) -> Result<i64, ArithError> {
+    let evaluation = evaluate(expr, state)?;
+    for (name, value) in evaluation.assignments {
+        state.set_var(name, value.to_string());
+    }
+    Ok(evaluation.value)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Num(i64),
+    Name(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Num(n) => write!(f, "{}", n),
+            Token::Name(name) => f.write_str(name),
+            Token::Op(op) => f.write_str(op),
+            Token::LParen => f.write_str("("),
+            Token::RParen => f.write_str(")"),
+        }
+    }
+}
+
+/// Operators, longest first so `<<=` isn't read as `<<` then `=`.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=", "-=", "*=", "/=", "%=",
+    "&=", "|=", "^=", "+", "-", "*", "/", "%", "<", ">", "=", "!", "~", "&", "|", "^", "?", ":", ",",
+];
+
+const ASSIGNMENT_OPERATORS: &[&str] = &["=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "^=", "|="];
+
+fn tokenize(expr: &str, state: &ShellState) -> Result<Vec<Token>, ArithError> {
+    let mut tokens = Vec::new();
+    let mut rest = expr;
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Num(parse_number(&rest[..end])?));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else if c == '$' {
+            // $x and ${x} are replaced by the value, like bash does textually
+            let (name, after) = match rest[1..].strip_prefix('{') {
+                Some(braced) => {
+                    let close = braced.find('}').ok_or_else(|| ArithError("bad substitution".into()))?;
+                    (&braced[..close], &braced[close + 1..])
+                }
+                None => {
+                    let end = rest[1..].find(|c: char| !(c.is_alphanumeric() || c == '_')).map_or(rest.len(), |i| i + 1);
+                    (&rest[1..end], &rest[end..])
+                }
+            };
+            tokens.push(Token::Num(variable_value(state.get_var(name))));
+            rest = after;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            rest = &rest[1..];
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            rest = &rest[1..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else {
+            return Err(ArithError(format!("syntax error: invalid arithmetic operator (error token is \"{}\")", rest)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A literal: decimal, `0x` hex, `0` octal or `base#digits`.
+fn parse_number(text: &str) -> Result<i64, ArithError> {
+    let invalid = || ArithError(format!("{}: value too great for base (error token is \"{}\")", text, text));
+    let (digits, base) = if let Some((base, digits)) = text.split_once('#') {
+        let base: u32 = base.parse().ok().filter(|b| (2..=36).contains(b)).ok_or_else(|| {
+            ArithError(format!("{}: invalid arithmetic base (error token is \"{}\")", text, text))
+        })?;
+        (digits, base)
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (hex, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (&text[1..], 8)
+    } else {
+        (text, 10)
+    };
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+    // Parse wrapping, as bash does for literals past i64::MAX
+    let mut value: i64 = 0;
+    for c in digits.chars() {
+        let digit = c.to_digit(base).ok_or_else(invalid)?;
+        value = value.wrapping_mul(base as i64).wrapping_add(digit as i64);
+    }
+    Ok(value)
+}
+
+/// A variable's value as a number; 0 when unset or not a number.
+fn variable_value(value: Option<&str>) -> i64 {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return 0;
+    };
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    match parse_number(digits) {
+        Ok(n) if negative => n.wrapping_neg(),
+        Ok(n) => n,
+        Err(_) => 0,
+    }
+}
+
+/// Recursive descent over the tokens, lowest precedence first.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    state: &'a ShellState,
+    /// Values assigned so far, which later reads see.
+    assigned: HashMap<String, i64>,
+    /// Names in the order first assigned.
+    order: Vec<String>,
+    /// Inside a branch that isn't taken (`0 && x++`): evaluate for syntax
+    /// only, without assigning or failing on division by zero.
+    skip: usize,
+}
+
+impl Parser<'_> {
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if self.peek_op() == Some(op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value_of(&self, name: &str) -> i64 {
+        match self.assigned.get(name) {
+            Some(value) => *value,
+            None => variable_value(self.state.get_var(name)),
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: i64) {
+        if self.skip > 0 {
+            return;
+        }
+        if self.assigned.insert(name.to_string(), value).is_none() {
+            self.order.push(name.to_string());
+        }
+    }
+
+    fn syntax_error(&self) -> ArithError {
+        match self.tokens.get(self.pos) {
+            Some(token) => ArithError(format!("syntax error: operand expected (error token is \"{}\")", token)),
+            None => ArithError("syntax error: operand expected".into()),
+        }
+    }
+
+    fn comma(&mut self) -> Result<i64, ArithError> {
+        let mut value = self.assignment()?;
+        while self.eat(",") {
+            value = self.assignment()?;
+        }
+        Ok(value)
+    }
+
+    fn assignment(&mut self) -> Result<i64, ArithError> {
+        if let (Some(Token::Name(name)), Some(Token::Op(op))) = (self.tokens.get(self.pos), self.tokens.get(self.pos + 1))
+            && ASSIGNMENT_OPERATORS.contains(op)
+        {
+            let (name, op) = (name.clone(), *op);
+            self.pos += 2;
+            let rhs = self.assignment()?;
+            let value = match op {
+                "=" => rhs,
+                _ => self.binary(&op[..op.len() - 1], self.value_of(&name), rhs)?,
+            };
+            self.assign(&name, value);
+            return Ok(value);
+        }
+        self.ternary()
+    }
+
+    fn ternary(&mut self) -> Result<i64, ArithError> {
+        let cond = self.logical_or()?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let then_value = self.branch(cond != 0, Self::comma)?;
+        if !self.eat(":") {
+            return Err(ArithError("`:' expected for conditional expression".into()));
+        }
+        let else_value = self.branch(cond == 0, Self::assignment)?;
+        Ok(if cond != 0 { then_value } else { else_value })
+    }
+
+    /// Parse with `parse`, evaluating only if `taken`.
+    fn branch(&mut self, taken: bool, parse: fn(&mut Self) -> Result<i64, ArithError>) -> Result<i64, ArithError> {
+        if !taken {
+            self.skip += 1;
+        }
+        let value = parse(self);
+        if !taken {
+            self.skip -= 1;
+        }
+        value
+    }
+
+    fn logical_or(&mut self) -> Result<i64, ArithError> {
+        let mut left = self.logical_and()?;
+        while self.eat("||") {
+            let right = self.branch(left == 0, Self::logical_and)?;
+            left = (left != 0 || right != 0) as i64;
+        }
+        Ok(left)
+    }
+
+    fn logical_and(&mut self) -> Result<i64, ArithError> {
+        let mut left = self.binary_level(0)?;
+        while self.eat("&&") {
+            let right = self.branch(left != 0, |p| p.binary_level(0))?;
+            left = (left != 0 && right != 0) as i64;
+        }
+        Ok(left)
+    }
+
+    /// Left-associative binary operators, loosest level first.
+    fn binary_level(&mut self, level: usize) -> Result<i64, ArithError> {
+        const LEVELS: &[&[&str]] = &[
+            &["|"],
+            &["^"],
+            &["&"],
+            &["==", "!="],
+            &["<", ">", "<=", ">="],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        let Some(ops) = LEVELS.get(level) else {
+            return self.power();
+        };
+        let mut left = self.binary_level(level + 1)?;
+        while let Some(op) = self.peek_op().filter(|op| ops.contains(op)) {
+            self.pos += 1;
+            let right = self.binary_level(level + 1)?;
+            left = self.binary(op, left, right)?;
+        }
+        Ok(left)
+    }
+
+    fn binary(&self, op: &str, left: i64, right: i64) -> Result<i64, ArithError> {
+        let divide_by_zero = matches!(op, "/" | "%") && right == 0;
+        if divide_by_zero {
+            return if self.skip > 0 { Ok(0) } else { Err(ArithError("division by 0".into())) };
+        }
+        Ok(match op {
+            "|" => left | right,
+            "^" => left ^ right,
+            "&" => left & right,
+            "==" => (left == right) as i64,
+            "!=" => (left != right) as i64,
+            "<" => (left < right) as i64,
+            ">" => (left > right) as i64,
+            "<=" => (left <= right) as i64,
+            ">=" => (left >= right) as i64,
+            "<<" => left.wrapping_shl(right as u32),
+            ">>" => left.wrapping_shr(right as u32),
+            "+" => left.wrapping_add(right),
+            "-" => left.wrapping_sub(right),
+            "*" => left.wrapping_mul(right),
+            "/" => left.wrapping_div(right),
+            "%" => left.wrapping_rem(right),
+            "**" => power(left, right, self.skip > 0)?,
+            _ => unreachable!("not a binary operator: {}", op),
+        })
+    }
+
+    fn power(&mut self) -> Result<i64, ArithError> {
+        let base = self.unary()?;
+        if self.eat("**") {
+            // Right associative
+            let exponent = self.power()?;
+            return self.binary("**", base, exponent);
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<i64, ArithError> {
+        let Some(op) = self.peek_op() else {
+            return self.postfix();
+        };
+        match op {
+            "++" | "--" => {
+                self.pos += 1;
+                let Some(Token::Name(name)) = self.tokens.get(self.pos).cloned() else {
+                    // Not a variable: two signs, so `--5` is 5
+                    let value = self.unary()?;
+                    return Ok(value);
+                };
+                self.pos += 1;
+                let delta = if op == "++" { 1 } else { -1 };
+                let value = self.value_of(&name).wrapping_add(delta);
+                self.assign(&name, value);
+                Ok(value)
+            }
+            "-" => {
+                self.pos += 1;
+                Ok(self.unary()?.wrapping_neg())
+            }
+            "+" => {
+                self.pos += 1;
+                self.unary()
+            }
+            "!" => {
+                self.pos += 1;
+                Ok((self.unary()? == 0) as i64)
+            }
+            "~" => {
+                self.pos += 1;
+                Ok(!self.unary()?)
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<i64, ArithError> {
+        if let Some(Token::Name(name)) = self.tokens.get(self.pos).cloned() {
+            self.pos += 1;
+            let value = self.value_of(&name);
+            if let Some(op) = self.peek_op().filter(|op| matches!(*op, "++" | "--")) {
+                self.pos += 1;
+                let delta = if op == "++" { 1 } else { -1 };
+                self.assign(&name, value.wrapping_add(delta));
+            }
+            return Ok(value);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<i64, ArithError> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Num(n)) => {
+                let n = *n;
+                self.pos += 1;
+                Ok(n)
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let value = self.comma()?;
+                if self.tokens.get(self.pos) != Some(&Token::RParen) {
+                    return Err(ArithError("missing `)'".into()));
+                }
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => Err(self.syntax_error()),
+        }
+    }
+}
+
+fn power(base: i64, exponent: i64, skip: bool) -> Result<i64, ArithError> {
+    if exponent < 0 {
+        return if skip { Ok(0) } else { Err(ArithError("exponent less than 0".into())) };
+    }
+    let mut result: i64 = 1;
+    for _ in 0..exponent.min(64) {
+        result = result.wrapping_mul(base);
+    }
+    // Past 64 only 0, 1 and -1 can still change, and they cycle.
+    if exponent > 64 && base == -1 {
+        result = if exponent % 2 == 0 { 1 } else { -1 };
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn state_with(vars: &[(&str, &str)]) -> ShellState {
+        let mut state = ShellState::from_cwd(PathBuf::from("/tmp"));
+        for (name, value) in vars {
+            state.set_var(*name, *value);
+        }
+        state
+    }
+
+    fn eval(expr: &str, state: &ShellState) -> i64 {
+        evaluate(expr, state).unwrap().value
+    }
+
+    #[test]
+    fn test_increment_and_assignment_are_returned() {
+        let state = state_with(&[("i", "4")]);
+        let evaluation = evaluate("i++, j = i * 2, j += 1", &state).unwrap();
+        assert_eq!(evaluation.value, 11);
+        assert_eq!(evaluation.assignments, vec![("i".into(), 5), ("j".into(), 11)]);
+        assert_eq!(state.get_var("i"), Some("4"));
+
+        assert_eq!(eval("++i", &state), 5);
+        assert_eq!(eval("i--", &state), 4);
+        assert_eq!(eval("x <<= 2", &state_with(&[("x", "3")])), 12);
+    }
+
+    #[test]
+    fn test_run_stores_assignments() {
+        let mut state = state_with(&[]);
+        assert_eq!(run("n = 10 % 4", &mut state), Ok(2));
+        assert_eq!(state.get_var("n"), Some("2"));
+    }
+
+    #[test]
+    fn test_short_circuit_skips_side_effects() {
+        let state = state_with(&[("x", "0")]);
+        assert_eq!(evaluate("0 && x++", &state).unwrap().assignments, vec![]);
+        assert_eq!(evaluate("1 || x++", &state).unwrap().assignments, vec![]);
+        assert_eq!(evaluate("1 ? 5 : (x = 9)", &state).unwrap().assignments, vec![]);
+        assert_eq!(eval("0 && 1 / 0", &state), 0);
+    }
+
+    #[test]
+    fn test_number_bases() {
+        let state = state_with(&[("h", "0x10"), ("word", "abc")]);
+        assert_eq!(eval("0x1f + 010 + 2#101", &state), 31 + 8 + 5);
+        assert_eq!(eval("h + word", &state), 16);
+        assert!(evaluate("09", &state).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let state = state_with(&[]);
+        assert_eq!(evaluate("5 / 0", &state).unwrap_err().to_string(), "division by 0");
+        assert!(evaluate("1 +", &state).is_err());
+        assert!(evaluate("(1 + 2", &state).is_err());
+        assert!(evaluate("1 2", &state).is_err());
+        assert!(evaluate("2 ** -1", &state).is_err());
+        assert_eq!(eval("", &state), 0);
+    }
+
+    #[test]
+    fn test_overflow_wraps() {
+        let state = state_with(&[]);
+        assert_eq!(eval("9223372036854775807 + 1", &state), i64::MIN);
+        assert_eq!(eval("2 ** 64", &state), 0);
+    }
+}
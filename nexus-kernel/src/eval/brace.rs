@@ -0,0 +1,381 @@
+//! Brace expansion, the first expansion a word goes through.
+//!
+//! Works on the word as written, before quote removal and parameter
+//! expansion, as bash does:
+//! - lists: `{a,b,c}`, with empty and nested alternatives (`x{,y{1,2}}`)
+//! - sequences: `{1..10}`, `{10..1}`, `{a..e}`, with an optional step
+//!   (`{1..10..3}`; its sign is ignored) and zero-padding when an endpoint
+//!   is written with a leading zero (`{01..20..2}` → `01 03 … 19`)
+//!
+//! Braces inside quotes, after a backslash, or within `${...}`, `$(...)`
+//! and backquotes are left alone. A brace pair that is neither a list nor
+//! a valid sequence (`{foo}`, `{1..x}`) stays as written.
+
+/// The words `word` expands to, in order. A word without a brace
+/// expression expands to itself; alternatives that leave nothing (the
+/// empty one in `{,a}`) are dropped.
+pub fn expand(word: &str) -> Vec<String> {
+    let words = expand_all(word);
+    if words.len() == 1 {
+        return words;
+    }
+    words.into_iter().filter(|w| !w.is_empty()).collect()
+}
+
+fn expand_all(word: &str) -> Vec<String> {
+    let Some((start, end, alternatives)) = find_expression(word) else {
+        return vec![word.to_string()];
+    };
+    let preamble = &word[..start];
+    let postscripts = expand_all(&word[end + 1..]);
+    let mut words = Vec::new();
+    for alternative in alternatives {
+        for expanded in expand_all(&alternative) {
+            for postscript in &postscripts {
+                words.push(format!("{preamble}{expanded}{postscript}"));
+            }
+        }
+    }
+    words
+}
+
+/// The first brace expression in `word`: the byte offsets of its `{` and
+/// `}`, and its alternatives before they are themselves expanded.
+fn find_expression(word: &str) -> Option<(usize, usize, Vec<String>)> {
+    let bytes = word.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(next) = skip_protected(bytes, i) {
+            i = next;
+            continue;
+        }
+        if bytes[i] == b'{'
+            && let Some(close) = closing(bytes, i, b'{', b'}')
+        {
+            let content = &word[i + 1..close];
+            let commas = top_level_commas(content);
+            if !commas.is_empty() {
+                return Some((i, close, split_at(content, &commas)));
+            }
+            if let Some(sequence) = sequence(content) {
+                return Some((i, close, sequence));
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// If a quoted or otherwise protected span starts at `i`, the offset just
+/// past it: `'...'`, `"..."`, `\x`, `${...}`, `$(...)` or `` `...` ``.
+fn skip_protected(bytes: &[u8], i: usize) -> Option<usize> {
+    let end = |from: usize, close: u8| -> usize {
+        let mut j = from;
+        while j < bytes.len() {
+            if bytes[j] == b'\\' && close != b'\'' {
+                j += 2;
+                continue;
+            }
+            if bytes[j] == close {
+                return j + 1;
+            }
+            j += 1;
+        }
+        bytes.len()
+    };
+    match bytes[i] {
+        b'\\' => Some((i + 2).min(bytes.len())),
+        b'\'' => Some(end(i + 1, b'\'')),
+        b'"' => Some(end(i + 1, b'"')),
+        b'`' => Some(end(i + 1, b'`')),
+        b'$' => match bytes.get(i + 1) {
+            Some(b'{') => Some(closing(bytes, i + 1, b'{', b'}').map_or(bytes.len(), |close| close + 1)),
+            Some(b'(') => Some(closing(bytes, i + 1, b'(', b')').map_or(bytes.len(), |close| close + 1)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The `close` matching the `open` at `start`, if there is one.
+fn closing(bytes: &[u8], start: usize, open: u8, close: u8) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        if i > start
+            && let Some(next) = skip_protected(bytes, i)
+        {
+            i = next;
+            continue;
+        }
+        if bytes[i] == open {
+            depth += 1;
+        } else if bytes[i] == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Offsets of the commas in `content` that are not nested in braces or
+/// protected.
+fn top_level_commas(content: &str) -> Vec<usize> {
+    let bytes = content.as_bytes();
+    let mut commas = Vec::new();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(next) = skip_protected(bytes, i) {
+            i = next;
+            continue;
+        }
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' if depth > 0 => depth -= 1,
+            b',' if depth == 0 => commas.push(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    commas
+}
+
+fn split_at(content: &str, commas: &[usize]) -> Vec<String> {
+    let mut parts = Vec::with_capacity(commas.len() + 1);
+    let mut start = 0;
+    for &comma in commas {
+        parts.push(content[start..comma].to_string());
+        start = comma + 1;
+    }
+    parts.push(content[start..].to_string());
+    parts
+}
+
+/// The words of a sequence expression, `x..y` or `x..y..step`.
+fn sequence(content: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = content.split("..").collect();
+    let (start, end, step) = match parts[..] {
+        [start, end] => (start, end, 1),
+        [start, end, step] => (start, end, step.parse::<i64>().ok()?.unsigned_abs().max(1)),
+        _ => return None,
+    };
+
+    if let (Some(first), Some(last)) = (integer(start), integer(end)) {
+        let width = if padded(start) || padded(end) { start.len().max(end.len()) } else { 0 };
+        return Some(steps(first, last, step).map(|n| format!("{n:0width$}")).collect());
+    }
+
+    match (single_letter(start), single_letter(end)) {
+        (Some(first), Some(last)) => Some(
+            steps(first as i64, last as i64, step)
+                .map(|n| char::from(n as u8).to_string())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// `first` to `last` inclusive, counting by `step` towards `last`.
+fn steps(first: i64, last: i64, step: u64) -> impl Iterator<Item = i64> {
+    let count = first.abs_diff(last) / step + 1;
+    let step = step as i128 * if first <= last { 1 } else { -1 };
+    (0..count).map(move |k| (first as i128 + k as i128 * step) as i64)
+}
+
+fn integer(s: &str) -> Option<i64> {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Whether an endpoint asks for zero-padding: `01`, `-007`.
+fn padded(s: &str) -> bool {
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    digits.len() > 1 && digits.starts_with('0')
+}
+
+fn single_letter(s: &str) -> Option<u8> {
+    match s.as_bytes() {
+        [c] if c.is_ascii_alphabetic() => Some(*c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_empty_and_nested_alternatives() {
+        assert_eq!(expand("x{,y}"), ["x", "xy"]);
+        assert_eq!(expand("{,a}"), ["a"]);
+        assert_eq!(expand("{a,{b..d}}"), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(expand("{3..-1}"), ["3", "2", "1", "0", "-1"]);
+        assert_eq!(expand("{1..10..3}"), ["1", "4", "7", "10"]);
+        assert_eq!(expand("{10..1..-4}"), ["10", "6", "2"]);
+        assert_eq!(expand("{a..e..2}"), ["a", "c", "e"]);
+        assert_eq!(expand("{C..A}"), ["C", "B", "A"]);
+        assert_eq!(expand("{1..3..0}"), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn test_zero_padding() {
+        assert_eq!(expand("{01..20..4}"), ["01", "05", "09", "13", "17"]);
+        assert_eq!(expand("{1..010..3}"), ["001", "004", "007", "010"]);
+        assert_eq!(expand("{-05..1..2}"), ["-05", "-03", "-01", "001"]);
+        assert_eq!(expand("img{08..10}.png"), ["img08.png", "img09.png", "img10.png"]);
+    }
+
+    #[test]
+    fn test_not_expressions() {
+        for word in ["{foo}", "{}", "{a,b", "a}b", "{1..x}", "{a..zz}", "{1..2..3..4}", "{1..5..x}"] {
+            assert_eq!(expand(word), [word]);
+        }
+        assert_eq!(expand("{x{a,b}}"), ["{xa}", "{xb}"]);
+    }
+
+    #[test]
+    fn test_protected_braces() {
+        for word in ["'{a,b}'", "\"{1..3}\"", "\\{a,b}", "${x:-a,b}", "$(echo {a,b})", "`echo {a,b}`"] {
+            assert_eq!(expand(word), [word]);
+        }
+        assert_eq!(expand("\"$x\"{a,b}"), ["\"$x\"a", "\"$x\"b"]);
+        assert_eq!(expand("{'a b',c}"), ["'a b'", "c"]);
+        assert_eq!(expand("$x{a,b}"), ["$xa", "$xb"]);
+    }
+
+
+    #[test]
+    fn test_brace_comma_simple() {
+        assert_eq!(expand("{a,b,c}"), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_brace_comma_with_preamble() {
+        assert_eq!(expand("file{1,2,3}.txt"), vec!["file1.txt", "file2.txt", "file3.txt"]);
+    }
+
+    #[test]
+    fn test_brace_comma_multiple() {
+        assert_eq!(expand("{a,b}{1,2}"), vec!["a1", "a2", "b1", "b2"]);
+    }
+
+    #[test]
+    fn test_brace_numeric_range() {
+        assert_eq!(expand("{1..5}"), vec!["1", "2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn test_brace_numeric_range_reverse() {
+        assert_eq!(expand("{5..1}"), vec!["5", "4", "3", "2", "1"]);
+    }
+
+    #[test]
+    fn test_brace_numeric_range_step() {
+        assert_eq!(expand("{1..10..2}"), vec!["1", "3", "5", "7", "9"]);
+    }
+
+    #[test]
+    fn test_brace_alpha_range() {
+        assert_eq!(expand("{a..e}"), vec!["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_brace_alpha_range_upper() {
+        assert_eq!(expand("{A..E}"), vec!["A", "B", "C", "D", "E"]);
+    }
+
+    #[test]
+    fn test_brace_alpha_range_reverse() {
+        assert_eq!(expand("{e..a}"), vec!["e", "d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_brace_nested() {
+        assert_eq!(expand("{a,b{1,2}}"), vec!["a", "b1", "b2"]);
+    }
+
+    #[test]
+    fn test_brace_no_expansion() {
+        // No comma or range - not a brace expansion
+        assert_eq!(expand("{foo}"), vec!["{foo}"]);
+    }
+
+    #[test]
+    fn test_brace_mkdir_pattern() {
+        assert_eq!(
+            expand("src/{components,utils,hooks}"),
+            vec!["src/components", "src/utils", "src/hooks"]
+        );
+    }
+
+    #[test]
+    fn test_brace_mv_pattern() {
+        assert_eq!(expand("app.{js,ts}"), vec!["app.js", "app.ts"]);
+    }
+
+    /// Random lists of plain alternatives: the expansion is every
+    /// combination, in order, and nothing else.
+    #[test]
+    fn test_property_lists_are_cartesian_products() {
+        let mut rng = StdRng::seed_from_u64(0x6272_6163);
+        let pool = ["a", "bb", "c1", "d-e", "f.g", "x"];
+        for _ in 0..200 {
+            let groups: Vec<Vec<&str>> = (0..rng.gen_range(1..4))
+                .map(|_| {
+                    let mut group: Vec<&str> = pool.to_vec();
+                    group.shuffle(&mut rng);
+                    group.truncate(rng.gen_range(2..5));
+                    group
+                })
+                .collect();
+            let word: String = groups.iter().map(|g| format!("p{{{}}}", g.join(","))).collect();
+
+            let mut expected = vec![String::new()];
+            for group in &groups {
+                expected = expected.iter().flat_map(|w| group.iter().map(move |alt| format!("{w}p{alt}"))).collect();
+            }
+            assert_eq!(expand(&word), expected, "{word}");
+        }
+    }
+
+    /// Random numeric sequences: endpoints are included when the step
+    /// lands on them, values move by the step towards the end, and padded
+    /// sequences have one width.
+    #[test]
+    fn test_property_sequences() {
+        let mut rng = StdRng::seed_from_u64(0x7365_7173);
+        for _ in 0..500 {
+            let (first, last) = (rng.gen_range(-50i64..50), rng.gen_range(-50i64..50));
+            let step = rng.gen_range(-7i64..8);
+            let pad = rng.gen_bool(0.3);
+            let endpoint = |n: i64| if pad && n >= 0 { format!("0{n}") } else { n.to_string() };
+            let word = format!("{{{}..{}..{step}}}", endpoint(first), endpoint(last));
+
+            let out = expand(&word);
+            let values: Vec<i64> = out.iter().map(|w| w.parse().unwrap()).collect();
+            let stride = step.unsigned_abs().max(1) as i64 * if first <= last { 1 } else { -1 };
+            assert_eq!(values[0], first, "{word}");
+            assert!(values.windows(2).all(|w| w[1] - w[0] == stride), "{word}: {out:?}");
+            assert!(values.iter().all(|v| (first.min(last)..=first.max(last)).contains(v)), "{word}");
+            assert!((values.last().unwrap() - last).abs() < stride.abs(), "{word}");
+            if pad && first >= 0 && last >= 0 {
+                let width = out[0].len();
+                assert!(out.iter().all(|w| w.len() == width), "{word}: {out:?}");
+            }
+        }
+    }
+}
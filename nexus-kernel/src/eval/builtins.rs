@@ -6,14 +6,17 @@
 //! - Modify shell variables (shift, read, getopts)
 //! - Replace shell process (exec)
 //! - Register signal handlers (trap)
+//! - Activate project environments (activate, deactivate)
 
 use nexus_api::{ShellEvent, TableColumn, Value};
 use std::io::{BufRead, Write};
 use std::path::PathBuf;
-use tokio::sync::broadcast::Sender;
+use crate::replay::EventSender;
 
 use crate::commands::CommandRegistry;
+use crate::dev_env::{self, EnvKind};
 use crate::state::TrapAction;
+use crate::traps;
 use crate::ShellState;
 
 /// Check if a command name is a builtin.
@@ -24,6 +27,7 @@ pub fn is_builtin(name: &str) -> bool {
             | "export"
             | "unset"
             | "set"
+            | "shopt"
             | ":"
             | "test"
             | "["
@@ -44,6 +48,11 @@ pub fn is_builtin(name: &str) -> bool {
             | "trap"
             | "exec"
             | "local"
+            | "profile"
+            | "time"
+            | "debug"
+            | "activate"
+            | "deactivate"
     )
 }
 
@@ -52,15 +61,16 @@ pub fn try_builtin(
     name: &str,
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
 ) -> anyhow::Result<Option<i32>> {
     match name {
         "cd" => Ok(Some(builtin_cd(args, state, events)?)),
-        "exit" => Ok(Some(builtin_exit(args)?)),
+        "exit" => Ok(Some(builtin_exit(args, state, events, commands)?)),
         "export" => Ok(Some(builtin_export(args, state, events)?)),
         "unset" => Ok(Some(builtin_unset(args, state, events)?)),
         "set" => Ok(Some(builtin_set(args, state)?)),
+        "shopt" => Ok(Some(builtin_shopt(args, state))),
         ":" => Ok(Some(0)),
         "test" | "[" => Ok(Some(builtin_test(args)?)),
         "[[" => Ok(Some(builtin_extended_test(args)?)),
@@ -82,6 +92,9 @@ pub fn try_builtin(
         "return" => Ok(Some(builtin_return(args)?)),
         // Variable scoping
         "local" => Ok(Some(builtin_local(args, state)?)),
+        // Project environments
+        "activate" => Ok(Some(builtin_activate(args, state))),
+        "deactivate" => Ok(Some(builtin_deactivate(args, state))),
         _ => Ok(None),
     }
 }
@@ -142,10 +155,38 @@ pub fn try_builtin_value(
                 rows,
             })
         }
+        "set" if args == ["-o"] => {
+            let rows = state
+                .options
+                .named_options()
+                .iter()
+                .map(|(name, on)| vec![Value::String(name.to_string()), Value::Bool(*on)])
+                .collect();
+            Some(Value::Table {
+                columns: vec![
+                    TableColumn::new("option"),
+                    TableColumn::new("enabled"),
+                ],
+                rows,
+            })
+        }
+        "trap" if args.is_empty() || args[0] == "-p" => Some(trap_listing(args, state)),
         "alias" if args.is_empty() => {
+            let configured = state
+                .config
+                .aliases
+                .iter()
+                .filter(|(k, _)| !state.aliases.contains_key(*k))
+                .map(|(k, s)| (k, &s.value));
+            let imported = state
+                .rc_aliases
+                .iter()
+                .filter(|(k, _)| !state.aliases.contains_key(*k) && state.config.alias(k).is_none());
             let rows: Vec<Vec<Value>> = state
                 .aliases
                 .iter()
+                .chain(configured)
+                .chain(imported)
                 .map(|(k, v)| {
                     vec![
                         Value::String(k.clone()),
@@ -168,7 +209,7 @@ pub fn try_builtin_value(
 fn builtin_cd(
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
 ) -> anyhow::Result<i32> {
     let target = if args.is_empty() {
         // cd with no args goes to $HOME
@@ -211,15 +252,69 @@ fn builtin_cd(
     }
 }
 
-fn builtin_exit(args: &[String]) -> anyhow::Result<i32> {
+/// The environment kinds named in `args`, or all of them.
+fn env_kinds(name: &str, args: &[String]) -> Option<Vec<EnvKind>> {
+    if args.is_empty() {
+        return Some(EnvKind::ALL.to_vec());
+    }
+    args.iter()
+        .map(|arg| {
+            EnvKind::parse(arg).or_else(|| {
+                eprintln!("{}: {}: expected venv, conda or node", name, arg);
+                None
+            })
+        })
+        .collect()
+}
+
+/// `activate [venv|conda|node]...`: activate the project environments
+/// found for the working directory (see [`crate::dev_env`]).
+fn builtin_activate(args: &[String], state: &mut ShellState) -> i32 {
+    let Some(kinds) = env_kinds("activate", args) else {
+        return 2;
+    };
+    let found: Vec<_> = dev_env::detect(&state.cwd, &state.env)
+        .into_iter()
+        .filter(|env| kinds.contains(&env.kind))
+        .collect();
+    if found.is_empty() {
+        eprintln!("activate: no virtualenv, conda environment or Node version file here");
+        return 1;
+    }
+    for env in found {
+        dev_env::activate(state, env);
+    }
+    0
+}
+
+/// `deactivate [venv|conda|node]...`: undo `activate`.
+fn builtin_deactivate(args: &[String], state: &mut ShellState) -> i32 {
+    let Some(kinds) = env_kinds("deactivate", args) else {
+        return 2;
+    };
+    let deactivated = kinds.into_iter().filter_map(|kind| dev_env::deactivate(state, kind)).count();
+    if deactivated == 0 {
+        eprintln!("deactivate: no environment is active");
+        return 1;
+    }
+    0
+}
+
+fn builtin_exit(
+    args: &[String],
+    state: &mut ShellState,
+    events: &EventSender,
+    commands: &CommandRegistry,
+) -> anyhow::Result<i32> {
     let code = args.first().and_then(|s| s.parse().ok()).unwrap_or(0);
+    traps::run(state, traps::EXIT, events, commands, None)?;
     std::process::exit(code);
 }
 
 fn builtin_export(
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
 ) -> anyhow::Result<i32> {
     if args.is_empty() {
         // Print all exported variables
@@ -254,7 +349,7 @@ fn builtin_export(
 fn builtin_unset(
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
 ) -> anyhow::Result<i32> {
     for arg in args {
         state.unset_env(arg);
@@ -294,23 +389,7 @@ fn builtin_set(args: &[String], state: &mut ShellState) -> anyhow::Result<i32> {
                         i += 1;
                         if i < args.len() {
                             let opt_name = &args[i];
-                            // Map long option names to short flags
-                            let short_flag = match opt_name.as_str() {
-                                "errexit" => Some('e'),
-                                "nounset" => Some('u'),
-                                "xtrace" => Some('x'),
-                                "verbose" => Some('v'),
-                                "noexec" => Some('n'),
-                                "noglob" => Some('f'),
-                                "noclobber" => Some('C'),
-                                "allexport" => Some('a'),
-                                "notify" => Some('b'),
-                                "hashall" => Some('h'),
-                                _ => None,
-                            };
-                            if let Some(f) = short_flag {
-                                state.options.set_option(f, enable);
-                            } else {
+                            if !state.options.set_named(opt_name, enable) {
                                 eprintln!("set: {}: invalid option name", opt_name);
                                 return Ok(1);
                             }
@@ -346,6 +425,79 @@ fn builtin_set(args: &[String], state: &mut ShellState) -> anyhow::Result<i32> {
     Ok(0)
 }
 
+/// `shopt [-s|-u] [-pq] [name...]`: turn the glob options of
+/// [`crate::state::ShellOptions::shopt_options`] on or off, or show them.
+fn builtin_shopt(args: &[String], state: &mut ShellState) -> i32 {
+    let mut set = None;
+    let mut quiet = false;
+    let mut reusable = false;
+    let mut names = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() => {
+                for flag in flags.chars() {
+                    match flag {
+                        's' => set = Some(true),
+                        'u' => set = Some(false),
+                        'q' => quiet = true,
+                        'p' => reusable = true,
+                        _ => {
+                            eprintln!("shopt: -{}: invalid option", flag);
+                            eprintln!("shopt: usage: shopt [-pqsu] [optname ...]");
+                            return 2;
+                        }
+                    }
+                }
+            }
+            _ => names.push(arg.as_str()),
+        }
+    }
+
+    let mut status = 0;
+    if let Some(value) = set
+        && !names.is_empty()
+    {
+        for name in names {
+            if !state.options.set_shopt(name, value) {
+                eprintln!("shopt: {}: invalid shell option name", name);
+                status = 1;
+            }
+        }
+        return status;
+    }
+
+    let options = state.options.shopt_options();
+    let mut shown = Vec::new();
+    for name in &names {
+        match options.iter().find(|(option, _)| option == name) {
+            Some(&(option, on)) => {
+                shown.push((option, on));
+                if !on {
+                    status = 1;
+                }
+            }
+            None => {
+                eprintln!("shopt: {}: invalid shell option name", name);
+                status = 1;
+            }
+        }
+    }
+    if names.is_empty() {
+        // `shopt -s` and `shopt -u` alone list the options that are on, or off.
+        shown.extend(options.into_iter().filter(|&(_, on)| set.is_none_or(|value| value == on)));
+    }
+    if !quiet {
+        for (name, on) in shown {
+            if reusable {
+                println!("shopt {} {}", if on { "-s" } else { "-u" }, name);
+            } else {
+                println!("{:<15}\t{}", name, if on { "on" } else { "off" });
+            }
+        }
+    }
+    status
+}
+
 fn builtin_test(args: &[String]) -> anyhow::Result<i32> {
     // Remove trailing ] if present
     let args: Vec<&str> = args
@@ -652,124 +804,20 @@ fn builtin_extended_test(args: &[String]) -> anyhow::Result<i32> {
     Ok(1)
 }
 
-/// Pattern matching for [[ == ]] operator.
-/// Supports shell glob patterns: *, ?, [...]
+/// Pattern matching for [[ == ]] operator. Extglob groups always work
+/// here, as in bash.
 fn extended_pattern_match(s: &str, pattern: &str) -> bool {
-    // Check for glob chars
-    if !pattern.chars().any(|c| c == '*' || c == '?' || c == '[') {
-        return s == pattern;
-    }
-
-    // Simple glob matching
     glob_match_str(s, pattern)
 }
 
-/// Simple glob matching for pattern strings.
 fn glob_match_str(s: &str, pattern: &str) -> bool {
-    let s_chars: Vec<char> = s.chars().collect();
-    let p_chars: Vec<char> = pattern.chars().collect();
-    glob_match_impl(&s_chars, &p_chars, 0, 0)
-}
-
-fn glob_match_impl(s: &[char], p: &[char], mut si: usize, mut pi: usize) -> bool {
-    while pi < p.len() || si < s.len() {
-        if pi < p.len() {
-            match p[pi] {
-                '*' => {
-                    // Skip consecutive stars
-                    while pi < p.len() && p[pi] == '*' {
-                        pi += 1;
-                    }
-                    // * at end matches everything
-                    if pi == p.len() {
-                        return true;
-                    }
-                    // Try matching * against 0 or more chars
-                    while si <= s.len() {
-                        if glob_match_impl(s, p, si, pi) {
-                            return true;
-                        }
-                        si += 1;
-                    }
-                    return false;
-                }
-                '?' => {
-                    if si >= s.len() {
-                        return false;
-                    }
-                    si += 1;
-                    pi += 1;
-                }
-                '[' => {
-                    if si >= s.len() {
-                        return false;
-                    }
-                    let (matched, consumed) = match_char_class(&p[pi..], s[si]);
-                    if !matched {
-                        return false;
-                    }
-                    si += 1;
-                    pi += consumed;
-                }
-                c => {
-                    if si >= s.len() || s[si] != c {
-                        return false;
-                    }
-                    si += 1;
-                    pi += 1;
-                }
-            }
-        } else {
-            return false;
-        }
-    }
-    true
-}
-
-/// Match a character class like [abc] or [a-z] or [!abc].
-fn match_char_class(pattern: &[char], c: char) -> (bool, usize) {
-    if pattern.is_empty() || pattern[0] != '[' {
-        return (false, 0);
-    }
-
-    let mut i = 1;
-    let negated = if i < pattern.len() && (pattern[i] == '!' || pattern[i] == '^') {
-        i += 1;
-        true
-    } else {
-        false
-    };
-
-    let mut matched = false;
-
-    while i < pattern.len() && pattern[i] != ']' {
-        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
-            let start = pattern[i];
-            let end = pattern[i + 2];
-            if c >= start && c <= end {
-                matched = true;
-            }
-            i += 3;
-        } else {
-            if pattern[i] == c {
-                matched = true;
-            }
-            i += 1;
-        }
-    }
-
-    if i < pattern.len() && pattern[i] == ']' {
-        i += 1;
-    }
-
-    let result = if negated { !matched } else { matched };
-    (result, i)
+    super::glob::matches(pattern, s, true)
 }
 
 fn builtin_source(
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
 ) -> anyhow::Result<i32> {
     if args.is_empty() {
@@ -819,7 +867,7 @@ fn builtin_source(
 fn builtin_eval(
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
 ) -> anyhow::Result<i32> {
     if args.is_empty() {
@@ -868,7 +916,7 @@ fn builtin_readonly(args: &[String], state: &mut ShellState) -> anyhow::Result<i
 fn builtin_command(
     args: &[String],
     state: &mut ShellState,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
 ) -> anyhow::Result<i32> {
     // command runs a command bypassing aliases and functions
@@ -893,11 +941,11 @@ fn builtin_command(
                 println!("{}", arg);
             } else if commands.contains(arg) {
                 println!("{}", arg);
-            } else if state.aliases.contains_key(arg) {
+            } else if let Some(alias) = state.alias(arg) {
                 if name == "-V" {
-                    println!("{} is aliased to `{}'", arg, state.aliases.get(arg).unwrap());
+                    println!("{} is aliased to `{}'", arg, alias);
                 } else {
-                    println!("alias {}='{}'", arg, state.aliases.get(arg).unwrap());
+                    println!("alias {}='{}'", arg, alias);
                 }
             } else if let Some(path) = find_in_path(arg, state) {
                 println!("{}", path.display());
@@ -934,7 +982,7 @@ fn builtin_command(
 }
 
 /// Find a command in PATH.
-fn find_in_path(cmd: &str, state: &ShellState) -> Option<PathBuf> {
+pub(super) fn find_in_path(cmd: &str, state: &ShellState) -> Option<PathBuf> {
     let path_var = state.get_env("PATH")?;
 
     for dir in path_var.split(':') {
@@ -980,7 +1028,7 @@ fn builtin_alias(args: &[String], state: &mut ShellState) -> anyhow::Result<i32>
             state.aliases.insert(name.to_string(), value.to_string());
         } else {
             // Print specific alias
-            if let Some(value) = state.aliases.get(arg) {
+            if let Some(value) = state.alias(arg) {
                 println!("alias {}='{}'", arg, value);
             } else {
                 eprintln!("alias: {}: not found", arg);
@@ -1000,6 +1048,7 @@ fn builtin_unalias(args: &[String], state: &mut ShellState) -> anyhow::Result<i3
     if args.len() == 1 && args[0] == "-a" {
         // Remove all aliases
         state.aliases.clear();
+        state.rc_aliases.clear();
         return Ok(0);
     }
 
@@ -1008,7 +1057,8 @@ fn builtin_unalias(args: &[String], state: &mut ShellState) -> anyhow::Result<i3
         if arg == "-a" {
             continue;
         }
-        if state.aliases.remove(arg).is_none() {
+        let removed = state.aliases.remove(arg).is_some();
+        if !(state.rc_aliases.remove(arg).is_some() || removed) {
             eprintln!("unalias: {}: not found", arg);
             exit_code = 1;
         }
@@ -1211,22 +1261,11 @@ fn builtin_getopts(args: &[String], state: &mut ShellState) -> anyhow::Result<i3
 }
 
 // ============================================================================
-// trap - Register signal handlers (simplified)
+// trap - Register signal handlers (run by crate::traps)
 // ============================================================================
 
 fn builtin_trap(args: &[String], state: &mut ShellState) -> anyhow::Result<i32> {
-    if args.is_empty() {
-        // Print current traps
-        for (signal, action) in &state.traps {
-            let sig_name = signal_name(*signal);
-            match action {
-                TrapAction::Default => {}
-                TrapAction::Ignore => println!("trap -- '' {}", sig_name),
-                TrapAction::Command(cmd) => println!("trap -- '{}' {}", cmd, sig_name),
-            }
-        }
-        return Ok(0);
-    }
+    // `trap` and `trap -p` list as values, in `try_builtin_value`.
 
     // trap -l: list signal names
     if args.len() == 1 && args[0] == "-l" {
@@ -1237,50 +1276,35 @@ fn builtin_trap(args: &[String], state: &mut ShellState) -> anyhow::Result<i32>
         return Ok(0);
     }
 
-    // Parse: trap [-p] [[action] signal ...]
-    let mut list_mode = false;
-    let mut arg_start = 0;
-
-    if args[0] == "-p" {
-        list_mode = true;
-        arg_start = 1;
-    }
-
-    if list_mode {
-        // Print traps for specified signals
-        for sig_arg in &args[arg_start..] {
-            if let Some(sig_num) = parse_signal(sig_arg) {
-                if let Some(action) = state.traps.get(&sig_num) {
-                    let sig_name = signal_name(sig_num);
-                    match action {
-                        TrapAction::Ignore => println!("trap -- '' {}", sig_name),
-                        TrapAction::Command(cmd) => println!("trap -- '{}' {}", cmd, sig_name),
-                        TrapAction::Default => {}
-                    }
-                }
-            }
+    // trap [--] [action] signal [signal ...]
+    let args = match args.first() {
+        Some(first) if first == "--" => &args[1..],
+        _ => args,
+    };
+    let (action, signals) = match args {
+        [] => {
+            eprintln!("trap: usage: trap [action] signal [signal ...]");
+            return Ok(2);
         }
-        return Ok(0);
-    }
-
-    // trap action signal [signal ...]
-    if args.len() < 2 {
+        // A lone signal is reset, as with `-`.
+        [signal] if traps::parse_signal(signal).is_some() => (TrapAction::Default, args),
+        [action_str, signals @ ..] => {
+            let action = match action_str.as_str() {
+                "-" => TrapAction::Default,
+                "" | "''" | "\"\"" => TrapAction::Ignore,
+                command => TrapAction::Command(command.to_string()),
+            };
+            (action, signals)
+        }
+    };
+    if signals.is_empty() {
         eprintln!("trap: usage: trap [action] signal [signal ...]");
         return Ok(2);
     }
 
-    let action_str = &args[0];
-    let action = if action_str.is_empty() || action_str == "-" {
-        TrapAction::Default
-    } else if action_str == "''" || action_str == "\"\"" {
-        TrapAction::Ignore
-    } else {
-        TrapAction::Command(action_str.clone())
-    };
-
-    for sig_arg in &args[1..] {
-        if let Some(sig_num) = parse_signal(sig_arg) {
-            state.traps.insert(sig_num, action.clone());
+    for sig_arg in signals {
+        if let Some(sig_num) = traps::parse_signal(sig_arg) {
+            traps::set(state, sig_num, action.clone());
         } else {
             eprintln!("trap: {}: invalid signal specification", sig_arg);
             return Ok(1);
@@ -1290,63 +1314,35 @@ fn builtin_trap(args: &[String], state: &mut ShellState) -> anyhow::Result<i32>
     Ok(0)
 }
 
-fn parse_signal(s: &str) -> Option<i32> {
-    // Try as number
-    if let Ok(n) = s.parse::<i32>() {
-        return Some(n);
-    }
-
-    // Try as signal name
-    let s = s.to_uppercase();
-    let s = s.strip_prefix("SIG").unwrap_or(&s);
-
-    match s.as_ref() {
-        "EXIT" | "0" => Some(0),
-        "HUP" => Some(1),
-        "INT" => Some(2),
-        "QUIT" => Some(3),
-        "ILL" => Some(4),
-        "TRAP" => Some(5),
-        "ABRT" => Some(6),
-        "KILL" => Some(9),
-        "USR1" => Some(10),
-        "SEGV" => Some(11),
-        "USR2" => Some(12),
-        "PIPE" => Some(13),
-        "ALRM" => Some(14),
-        "TERM" => Some(15),
-        "CHLD" => Some(17),
-        "CONT" => Some(18),
-        "STOP" => Some(19),
-        "ERR" => Some(-1),   // Special: on error
-        "DEBUG" => Some(-2), // Special: before each command
-        _ => None,
-    }
-}
+/// `trap` as a table of signal and action, or `trap -p [signal ...]` as the
+/// commands that would set them again.
+fn trap_listing(args: &[String], state: &ShellState) -> Value {
+    let mut traps: Vec<(i32, &str)> = state
+        .traps
+        .iter()
+        .filter_map(|(&sig, action)| match action {
+            TrapAction::Default => None,
+            TrapAction::Ignore => Some((sig, "")),
+            TrapAction::Command(cmd) => Some((sig, cmd.as_str())),
+        })
+        .collect();
+    traps.sort_by_key(|&(sig, _)| sig);
 
-fn signal_name(sig: i32) -> &'static str {
-    match sig {
-        0 => "EXIT",
-        1 => "SIGHUP",
-        2 => "SIGINT",
-        3 => "SIGQUIT",
-        4 => "SIGILL",
-        5 => "SIGTRAP",
-        6 => "SIGABRT",
-        9 => "SIGKILL",
-        10 => "SIGUSR1",
-        11 => "SIGSEGV",
-        12 => "SIGUSR2",
-        13 => "SIGPIPE",
-        14 => "SIGALRM",
-        15 => "SIGTERM",
-        17 => "SIGCHLD",
-        18 => "SIGCONT",
-        19 => "SIGSTOP",
-        -1 => "ERR",
-        -2 => "DEBUG",
-        _ => "UNKNOWN",
-    }
+    if args.is_empty() {
+        let rows = traps
+            .into_iter()
+            .map(|(sig, cmd)| vec![Value::String(traps::signal_name(sig).to_string()), Value::String(cmd.to_string())])
+            .collect();
+        return Value::Table { columns: vec![TableColumn::new("signal"), TableColumn::new("action")], rows };
+    }
+
+    let wanted: Vec<i32> = args[1..].iter().filter_map(|arg| traps::parse_signal(arg)).collect();
+    let lines: Vec<String> = traps
+        .into_iter()
+        .filter(|(sig, _)| wanted.is_empty() || wanted.contains(sig))
+        .map(|(sig, cmd)| format!("trap -- '{}' {}", cmd.replace('\'', "'\\''"), traps::signal_name(sig)))
+        .collect();
+    Value::String(lines.join("\n"))
 }
 
 // ============================================================================
@@ -1483,6 +1479,7 @@ mod tests {
         assert!(is_builtin("readonly"));
         assert!(is_builtin("command"));
         assert!(is_builtin("getopts"));
+        assert!(is_builtin("shopt"));
         assert!(is_builtin("trap"));
         assert!(is_builtin("exec"));
         assert!(is_builtin("local"));
@@ -1500,87 +1497,6 @@ mod tests {
         assert!(!is_builtin("Exit"));
     }
 
-    // =========================================================================
-    // parse_signal tests
-    // =========================================================================
-
-    #[test]
-    fn test_parse_signal_numeric() {
-        assert_eq!(parse_signal("1"), Some(1));
-        assert_eq!(parse_signal("2"), Some(2));
-        assert_eq!(parse_signal("9"), Some(9));
-        assert_eq!(parse_signal("15"), Some(15));
-        assert_eq!(parse_signal("0"), Some(0));
-    }
-
-    #[test]
-    fn test_parse_signal_named() {
-        assert_eq!(parse_signal("HUP"), Some(1));
-        assert_eq!(parse_signal("INT"), Some(2));
-        assert_eq!(parse_signal("QUIT"), Some(3));
-        assert_eq!(parse_signal("KILL"), Some(9));
-        assert_eq!(parse_signal("TERM"), Some(15));
-        assert_eq!(parse_signal("USR1"), Some(10));
-        assert_eq!(parse_signal("USR2"), Some(12));
-    }
-
-    #[test]
-    fn test_parse_signal_with_sig_prefix() {
-        assert_eq!(parse_signal("SIGHUP"), Some(1));
-        assert_eq!(parse_signal("SIGINT"), Some(2));
-        assert_eq!(parse_signal("SIGKILL"), Some(9));
-        assert_eq!(parse_signal("SIGTERM"), Some(15));
-    }
-
-    #[test]
-    fn test_parse_signal_case_insensitive() {
-        assert_eq!(parse_signal("hup"), Some(1));
-        assert_eq!(parse_signal("Hup"), Some(1));
-        assert_eq!(parse_signal("sigint"), Some(2));
-        assert_eq!(parse_signal("SigInt"), Some(2));
-    }
-
-    #[test]
-    fn test_parse_signal_special() {
-        assert_eq!(parse_signal("EXIT"), Some(0));
-        assert_eq!(parse_signal("ERR"), Some(-1));
-        assert_eq!(parse_signal("DEBUG"), Some(-2));
-    }
-
-    #[test]
-    fn test_parse_signal_invalid() {
-        assert_eq!(parse_signal("INVALID"), None);
-        assert_eq!(parse_signal("SIGFOO"), None);
-        assert_eq!(parse_signal(""), None);
-        assert_eq!(parse_signal("abc"), None);
-    }
-
-    // =========================================================================
-    // signal_name tests
-    // =========================================================================
-
-    #[test]
-    fn test_signal_name_common() {
-        assert_eq!(signal_name(0), "EXIT");
-        assert_eq!(signal_name(1), "SIGHUP");
-        assert_eq!(signal_name(2), "SIGINT");
-        assert_eq!(signal_name(9), "SIGKILL");
-        assert_eq!(signal_name(15), "SIGTERM");
-    }
-
-    #[test]
-    fn test_signal_name_special() {
-        assert_eq!(signal_name(-1), "ERR");
-        assert_eq!(signal_name(-2), "DEBUG");
-    }
-
-    #[test]
-    fn test_signal_name_unknown() {
-        assert_eq!(signal_name(99), "UNKNOWN");
-        assert_eq!(signal_name(-99), "UNKNOWN");
-        assert_eq!(signal_name(1000), "UNKNOWN");
-    }
-
     // =========================================================================
     // glob_match_str tests
     // =========================================================================
@@ -1699,51 +1615,47 @@ mod tests {
     }
 
     // =========================================================================
-    // match_char_class tests
+    // Character class tests
     // =========================================================================
 
     #[test]
-    fn test_match_char_class_simple() {
-        let pattern: Vec<char> = "[abc]".chars().collect();
-        assert_eq!(match_char_class(&pattern, 'a'), (true, 5));
-        assert_eq!(match_char_class(&pattern, 'b'), (true, 5));
-        assert_eq!(match_char_class(&pattern, 'c'), (true, 5));
-        assert_eq!(match_char_class(&pattern, 'd'), (false, 5));
+    fn test_char_class_simple() {
+        assert!(glob_match_str("a", "[abc]"));
+        assert!(glob_match_str("b", "[abc]"));
+        assert!(glob_match_str("c", "[abc]"));
+        assert!(!glob_match_str("d", "[abc]"));
     }
 
     #[test]
-    fn test_match_char_class_range() {
-        let pattern: Vec<char> = "[a-z]".chars().collect();
-        assert_eq!(match_char_class(&pattern, 'a'), (true, 5));
-        assert_eq!(match_char_class(&pattern, 'm'), (true, 5));
-        assert_eq!(match_char_class(&pattern, 'z'), (true, 5));
-        assert_eq!(match_char_class(&pattern, 'A'), (false, 5));
+    fn test_char_class_range() {
+        assert!(glob_match_str("a", "[a-z]"));
+        assert!(glob_match_str("m", "[a-z]"));
+        assert!(glob_match_str("z", "[a-z]"));
+        assert!(!glob_match_str("A", "[a-z]"));
     }
 
     #[test]
-    fn test_match_char_class_negation_exclamation() {
-        let pattern: Vec<char> = "[!abc]".chars().collect();
-        assert_eq!(match_char_class(&pattern, 'a'), (false, 6));
-        assert_eq!(match_char_class(&pattern, 'd'), (true, 6));
+    fn test_char_class_negation_exclamation() {
+        assert!(!glob_match_str("a", "[!abc]"));
+        assert!(glob_match_str("d", "[!abc]"));
     }
 
     #[test]
-    fn test_match_char_class_negation_caret() {
-        let pattern: Vec<char> = "[^abc]".chars().collect();
-        assert_eq!(match_char_class(&pattern, 'a'), (false, 6));
-        assert_eq!(match_char_class(&pattern, 'd'), (true, 6));
+    fn test_char_class_negation_caret() {
+        assert!(!glob_match_str("a", "[^abc]"));
+        assert!(glob_match_str("d", "[^abc]"));
     }
 
     #[test]
-    fn test_match_char_class_not_a_class() {
-        let pattern: Vec<char> = "abc".chars().collect();
-        assert_eq!(match_char_class(&pattern, 'a'), (false, 0));
+    fn test_char_class_unclosed_is_literal() {
+        assert!(glob_match_str("[ab", "[ab"));
+        assert!(!glob_match_str("a", "[ab"));
     }
 
     #[test]
-    fn test_match_char_class_empty() {
-        let pattern: Vec<char> = vec![];
-        assert_eq!(match_char_class(&pattern, 'a'), (false, 0));
+    fn test_char_class_named() {
+        assert!(glob_match_str("x1", "[[:alpha:]][[:digit:]]"));
+        assert!(!glob_match_str("1x", "[[:alpha:]][[:digit:]]"));
     }
 
     // =========================================================================
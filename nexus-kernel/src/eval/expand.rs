@@ -9,10 +9,39 @@
 //! 6. Word splitting
 //! 7. Pathname expansion (globbing)
 
-use crate::parser::Word;
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use crate::commands::CommandRegistry;
+use crate::parser::{Redirect, RedirectOp, Word};
 use crate::ShellState;
 use nexus_api::Value;
 
+thread_local! {
+    /// The first unset variable expanded under `set -u`, until taken.
+    static UNBOUND: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Take the first unset variable a plain `$name` referred to under
+/// `set -u` since the last call. Expansion can't fail, so the evaluator
+/// checks this once a command's words are expanded.
+pub(crate) fn take_unbound() -> Option<String> {
+    UNBOUND.with(|unbound| unbound.borrow_mut().take())
+}
+
+/// Note `name` as unbound if `set -u` is on. Special names (`$_`, `$@`,
+/// `$1`) and those with a modifier (`${x:-}`) never are.
+fn check_bound(name: &str, state: &ShellState) {
+    let identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let special = name == "prev" || name.strip_prefix('_').is_some_and(|n| n.is_empty() || n.parse::<usize>().is_ok());
+    if state.options.nounset && identifier && !special {
+        UNBOUND.with(|unbound| {
+            unbound.borrow_mut().get_or_insert_with(|| name.to_string());
+        });
+    }
+}
+
 /// Expand a word to a Value, preserving rich types.
 ///
 /// This is the Mathematica-style expansion that preserves non-string values
@@ -22,18 +51,22 @@ use nexus_api::Value;
 pub fn expand_word_to_value(word: &Word, state: &ShellState) -> Value {
     match word {
         Word::Literal(s) => {
-            // Literals expand to strings
-            Value::String(expand_literal(s, state))
+            // A lone $((expr)) stays a number; other literals expand to strings
+            match whole_arithmetic(s) {
+                Some(expr) => Value::Int(evaluate_arithmetic(&expr, state)),
+                None => Value::String(expand_literal(s, state)),
+            }
         }
         Word::Variable(name) => {
             // Check if it's a rich variable
             expand_variable_to_value(name, state)
         }
         Word::CommandSubstitution(cmd) => {
-            // Command substitution - for now returns string, but could be enhanced
-            // to capture rich values from subshell execution
-            Value::String(expand_command_substitution(cmd, state))
+            // The inner command's value, when it was native
+            let output = super::substitute(cmd, state, substitution_registry());
+            output.value.unwrap_or(Value::String(output.text))
         }
+        Word::ProcessSubstitution(source) => Value::String(super::procsub::open(source, state, substitution_registry())),
     }
 }
 
@@ -77,7 +110,10 @@ fn expand_variable_to_value(name: &str, state: &ShellState) -> Value {
     }
 
     // Use get_var_value which checks rich_vars first
-    state.get_var_value(name).unwrap_or(Value::Unit)
+    state.get_var_value(name).unwrap_or_else(|| {
+        check_bound(name, state);
+        Value::Unit
+    })
 }
 
 /// Expand a word to a string (no glob expansion).
@@ -86,6 +122,7 @@ pub fn expand_word_to_string(word: &Word, state: &ShellState) -> String {
         Word::Literal(s) => expand_literal(s, state),
         Word::Variable(name) => expand_variable(name, state),
         Word::CommandSubstitution(cmd) => expand_command_substitution(cmd, state),
+        Word::ProcessSubstitution(source) => super::procsub::open(source, state, substitution_registry()),
     }
 }
 
@@ -94,13 +131,34 @@ pub fn expand_word_to_string(word: &Word, state: &ShellState) -> String {
 /// This is the full expansion including:
 /// - Brace expansion: `{a,b,c}` → `a b c`, `{1..5}` → `1 2 3 4 5`
 /// - Pathname expansion (globbing): `*.txt` → matching files
+/// - Field splitting of an unquoted `$(cmd)`: `$(cat dirs.txt)` → one word per line
 ///
 /// Use this for command arguments.
 pub fn expand_word_to_strings(word: &Word, state: &ShellState) -> Vec<String> {
-    let expanded = expand_word_to_string(word, state);
+    // An unquoted substitution splits into one word per field of its output
+    if let Word::CommandSubstitution(cmd) = word {
+        let output = expand_command_substitution(cmd, state);
+        let mut results = Vec::new();
+        for field in output.split_whitespace() {
+            if state.options.noglob || !is_glob(field, state) {
+                results.push(field.to_string());
+                continue;
+            }
+            let matches = expand_glob(field, state);
+            if matches.is_empty() && !state.options.nullglob {
+                results.push(field.to_string());
+            }
+            results.extend(matches);
+        }
+        return results;
+    }
 
-    // Step 1: Brace expansion (before glob expansion)
-    let brace_expanded = expand_braces(&expanded);
+    // Step 1: Brace expansion, on the word as written so quoted braces and
+    // those in variable values stay literal
+    let brace_expanded: Vec<String> = match word {
+        Word::Literal(s) => super::brace::expand(s).iter().map(|w| expand_literal(w, state)).collect(),
+        _ => vec![expand_word_to_string(word, state)],
+    };
 
     // Step 2: Glob expansion on each brace-expanded word
     let mut results = Vec::new();
@@ -113,7 +171,7 @@ pub fn expand_word_to_strings(word: &Word, state: &ShellState) -> Vec<String> {
         }
 
         // Check if the expanded string contains glob metacharacters
-        if !contains_glob_chars(&word) {
+        if !is_glob(&word, state) {
             results.push(word);
             continue;
         }
@@ -121,7 +179,7 @@ pub fn expand_word_to_strings(word: &Word, state: &ShellState) -> Vec<String> {
         // Perform pathname expansion
         let matches = expand_glob(&word, state);
 
-        if matches.is_empty() {
+        if matches.is_empty() && !state.options.nullglob {
             // No matches - return the original pattern (POSIX behavior)
             results.push(word);
         } else {
@@ -132,262 +190,6 @@ pub fn expand_word_to_strings(word: &Word, state: &ShellState) -> Vec<String> {
     results
 }
 
-// ============================================================================
-// Brace Expansion
-// ============================================================================
-
-/// Expand brace expressions like `{a,b,c}` and `{1..10}`.
-///
-/// Examples:
-/// - `{a,b,c}` → `["a", "b", "c"]`
-/// - `{1..5}` → `["1", "2", "3", "4", "5"]`
-/// - `{1..10..2}` → `["1", "3", "5", "7", "9"]`
-/// - `{a..e}` → `["a", "b", "c", "d", "e"]`
-/// - `file{1,2}.txt` → `["file1.txt", "file2.txt"]`
-/// - `{a,b}{1,2}` → `["a1", "a2", "b1", "b2"]`
-pub fn expand_braces(s: &str) -> Vec<String> {
-    // Find the first brace expression (respecting nesting)
-    let Some((start, end, content)) = find_brace_expr(s) else {
-        return vec![s.to_string()];
-    };
-
-    let preamble = &s[..start];
-    let postscript = &s[end + 1..];
-
-    // Parse the brace content
-    let alternatives = parse_brace_content(content);
-
-    // Generate expansions
-    let mut results = Vec::new();
-    for alt in alternatives {
-        let expanded = format!("{}{}{}", preamble, alt, postscript);
-        // Recursively expand remaining braces
-        results.extend(expand_braces(&expanded));
-    }
-
-    results
-}
-
-/// Find the first top-level brace expression in a string.
-/// Returns (start_index, end_index, content_between_braces).
-/// Skips over quoted content (single and double quotes) since brace expansion
-/// should not apply to quoted strings.
-fn find_brace_expr(s: &str) -> Option<(usize, usize, &str)> {
-    let bytes = s.as_bytes();
-    let mut depth = 0;
-    let mut start_idx = None;
-    let mut in_single_quote = false;
-    let mut in_double_quote = false;
-    let mut i = 0;
-
-    while i < bytes.len() {
-        let b = bytes[i];
-
-        // Handle escape sequences
-        if b == b'\\' && i + 1 < bytes.len() {
-            i += 2; // Skip escaped character
-            continue;
-        }
-
-        // Track quote state
-        if b == b'\'' && !in_double_quote {
-            in_single_quote = !in_single_quote;
-            i += 1;
-            continue;
-        }
-        if b == b'"' && !in_single_quote {
-            in_double_quote = !in_double_quote;
-            i += 1;
-            continue;
-        }
-
-        // Skip brace handling when inside quotes
-        if in_single_quote || in_double_quote {
-            i += 1;
-            continue;
-        }
-
-        match b {
-            b'{' => {
-                if depth == 0 {
-                    start_idx = Some(i);
-                }
-                depth += 1;
-            }
-            b'}' => {
-                if depth > 0 {
-                    depth -= 1;
-                    if depth == 0 {
-                        if let Some(start) = start_idx {
-                            let content = &s[start + 1..i];
-                            // Only treat as brace expansion if it contains , or ..
-                            // AND doesn't look like JSON (has unquoted : which is JSON field separator)
-                            if (content.contains(',') || content.contains(".."))
-                                && !looks_like_json(content)
-                            {
-                                return Some((start, i, content));
-                            }
-                            // Otherwise, not a brace expansion - continue looking
-                            start_idx = None;
-                        }
-                    }
-                }
-            }
-            _ => {}
-        }
-        i += 1;
-    }
-
-    None
-}
-
-/// Check if content looks like JSON (has quoted keys followed by colons).
-/// This helps avoid treating JSON objects as brace expansion.
-fn looks_like_json(content: &str) -> bool {
-    // JSON typically has patterns like "key": or 'key':
-    // Simple heuristic: if we see a quote followed by : it's likely JSON
-    let bytes = content.as_bytes();
-    for i in 0..bytes.len().saturating_sub(1) {
-        if (bytes[i] == b'"' || bytes[i] == b'\'') && bytes[i + 1] == b':' {
-            return true;
-        }
-    }
-    false
-}
-
-/// Parse the content of a brace expression.
-/// Handles both comma-separated lists and range expressions.
-fn parse_brace_content(content: &str) -> Vec<String> {
-    // Check for range expression: {1..10} or {a..z} or {1..10..2}
-    if let Some(range_result) = try_parse_range(content) {
-        return range_result;
-    }
-
-    // Parse comma-separated alternatives (respecting nested braces)
-    parse_comma_list(content)
-}
-
-/// Try to parse a range expression like `1..10` or `a..z` or `1..10..2`.
-fn try_parse_range(content: &str) -> Option<Vec<String>> {
-    let parts: Vec<&str> = content.split("..").collect();
-
-    if parts.len() < 2 || parts.len() > 3 {
-        return None;
-    }
-
-    let start_str = parts[0].trim();
-    let end_str = parts[1].trim();
-    let step_str = parts.get(2).map(|s| s.trim());
-
-    // Try numeric range
-    if let (Ok(start), Ok(end)) = (start_str.parse::<i64>(), end_str.parse::<i64>()) {
-        let step: i64 = step_str
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(if start <= end { 1 } else { -1 });
-
-        if step == 0 {
-            return Some(vec![content.to_string()]);
-        }
-
-        let mut results = Vec::new();
-        let mut current = start;
-
-        if step > 0 {
-            while current <= end {
-                results.push(current.to_string());
-                current += step;
-            }
-        } else {
-            while current >= end {
-                results.push(current.to_string());
-                current += step;
-            }
-        }
-
-        return Some(results);
-    }
-
-    // Try character range (single characters only)
-    let start_chars: Vec<char> = start_str.chars().collect();
-    let end_chars: Vec<char> = end_str.chars().collect();
-
-    if start_chars.len() == 1 && end_chars.len() == 1 {
-        let start_char = start_chars[0];
-        let end_char = end_chars[0];
-
-        // Only expand if both are letters or both are digits
-        let valid = (start_char.is_ascii_lowercase() && end_char.is_ascii_lowercase())
-            || (start_char.is_ascii_uppercase() && end_char.is_ascii_uppercase())
-            || (start_char.is_ascii_digit() && end_char.is_ascii_digit());
-
-        if valid {
-            let step: i32 = step_str
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(if start_char <= end_char { 1 } else { -1 });
-
-            if step == 0 {
-                return Some(vec![content.to_string()]);
-            }
-
-            let mut results = Vec::new();
-            let mut current = start_char as i32;
-            let end_val = end_char as i32;
-
-            if step > 0 {
-                while current <= end_val {
-                    if let Some(c) = char::from_u32(current as u32) {
-                        results.push(c.to_string());
-                    }
-                    current += step;
-                }
-            } else {
-                while current >= end_val {
-                    if let Some(c) = char::from_u32(current as u32) {
-                        results.push(c.to_string());
-                    }
-                    current += step;
-                }
-            }
-
-            return Some(results);
-        }
-    }
-
-    None
-}
-
-/// Parse a comma-separated list, respecting nested braces.
-fn parse_comma_list(content: &str) -> Vec<String> {
-    let mut results = Vec::new();
-    let mut current = String::new();
-    let mut depth = 0;
-
-    for c in content.chars() {
-        match c {
-            '{' => {
-                depth += 1;
-                current.push(c);
-            }
-            '}' => {
-                depth -= 1;
-                current.push(c);
-            }
-            ',' if depth == 0 => {
-                results.push(current);
-                current = String::new();
-            }
-            _ => {
-                current.push(c);
-            }
-        }
-    }
-
-    // Don't forget the last element
-    results.push(current);
-
-    results
-}
-
 // ============================================================================
 // Arithmetic Expansion
 // ============================================================================
@@ -418,441 +220,24 @@ fn collect_arithmetic_expr(chars: &mut std::iter::Peekable<std::str::Chars>) ->
     expr
 }
 
-/// Evaluate an arithmetic expression.
-///
-/// Supports:
-/// - Integers: 42, -17
-/// - Variables: x, $x, ${x}
-/// - Operators: + - * / % ** (power)
-/// - Comparison: < > <= >= == !=
-/// - Logical: && || !
-/// - Bitwise: & | ^ ~ << >>
-/// - Ternary: a ? b : c
-/// - Parentheses for grouping
-/// - Assignment: x = 5, x += 1
+/// Evaluate an arithmetic expression for `$((expr))`. Errors evaluate to
+/// 0, and assignments are dropped: expansion can't change the state (see
+/// [`super::arith`]).
 pub fn evaluate_arithmetic(expr: &str, state: &ShellState) -> i64 {
-    let expr = expr.trim();
-    if expr.is_empty() {
-        return 0;
-    }
-
-    // Tokenize
-    let tokens = tokenize_arithmetic(expr, state);
-
-    // Parse and evaluate using a simple recursive descent parser
-    let mut parser = ArithParser::new(tokens);
-    parser.parse_expr()
-}
-
-/// Arithmetic token types.
-#[derive(Debug, Clone, PartialEq)]
-enum ArithToken {
-    Num(i64),
-    Op(String),
-    LParen,
-    RParen,
-}
-
-/// Tokenize an arithmetic expression.
-fn tokenize_arithmetic(expr: &str, state: &ShellState) -> Vec<ArithToken> {
-    let mut tokens = Vec::new();
-    let mut chars = expr.chars().peekable();
-
-    while let Some(&c) = chars.peek() {
-        match c {
-            ' ' | '\t' | '\n' => {
-                chars.next();
-            }
-            '0'..='9' => {
-                let mut num = String::new();
-                while let Some(&c) = chars.peek() {
-                    if c.is_ascii_digit() {
-                        num.push(c);
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                }
-                tokens.push(ArithToken::Num(num.parse().unwrap_or(0)));
-            }
-            'a'..='z' | 'A'..='Z' | '_' => {
-                let mut name = String::new();
-                while let Some(&c) = chars.peek() {
-                    if c.is_alphanumeric() || c == '_' {
-                        name.push(c);
-                        chars.next();
-                    } else {
-                        break;
-                    }
-                }
-                // Look up variable value
-                let val = state
-                    .get_var(&name)
-                    .and_then(|s| s.parse::<i64>().ok())
-                    .unwrap_or(0);
-                tokens.push(ArithToken::Num(val));
-            }
-            '$' => {
-                chars.next();
-                // Handle $var or ${var}
-                if chars.peek() == Some(&'{') {
-                    chars.next();
-                    let mut name = String::new();
-                    while let Some(&c) = chars.peek() {
-                        if c == '}' {
-                            chars.next();
-                            break;
-                        }
-                        name.push(c);
-                        chars.next();
-                    }
-                    let val = state
-                        .get_var(&name)
-                        .and_then(|s| s.parse::<i64>().ok())
-                        .unwrap_or(0);
-                    tokens.push(ArithToken::Num(val));
-                } else {
-                    let mut name = String::new();
-                    while let Some(&c) = chars.peek() {
-                        if c.is_alphanumeric() || c == '_' {
-                            name.push(c);
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
-                    let val = state
-                        .get_var(&name)
-                        .and_then(|s| s.parse::<i64>().ok())
-                        .unwrap_or(0);
-                    tokens.push(ArithToken::Num(val));
-                }
-            }
-            '(' => {
-                chars.next();
-                tokens.push(ArithToken::LParen);
-            }
-            ')' => {
-                chars.next();
-                tokens.push(ArithToken::RParen);
-            }
-            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '~' | '<' | '>' | '=' | '!' | '?' | ':' => {
-                let mut op = String::new();
-                op.push(c);
-                chars.next();
-
-                // Check for two-character operators
-                if let Some(&next) = chars.peek() {
-                    let two_char = format!("{}{}", c, next);
-                    if matches!(
-                        two_char.as_str(),
-                        "**" | "<<" | ">>" | "<=" | ">=" | "==" | "!=" | "&&" | "||" | "+=" | "-=" | "*=" | "/="
-                    ) {
-                        op.push(next);
-                        chars.next();
-                    }
-                }
-                tokens.push(ArithToken::Op(op));
-            }
-            _ => {
-                chars.next(); // Skip unknown characters
-            }
+    match super::arith::evaluate(expr, state) {
+        Ok(evaluation) => evaluation.value,
+        Err(e) => {
+            tracing::debug!("arithmetic: {}: {}", expr.trim(), e);
+            0
         }
     }
-
-    tokens
 }
 
-/// Simple recursive descent parser for arithmetic expressions.
-struct ArithParser {
-    tokens: Vec<ArithToken>,
-    pos: usize,
-}
-
-impl ArithParser {
-    fn new(tokens: Vec<ArithToken>) -> Self {
-        Self { tokens, pos: 0 }
-    }
-
-    fn peek(&self) -> Option<&ArithToken> {
-        self.tokens.get(self.pos)
-    }
-
-    fn next(&mut self) -> Option<ArithToken> {
-        let tok = self.tokens.get(self.pos).cloned();
-        if tok.is_some() {
-            self.pos += 1;
-        }
-        tok
-    }
-
-    fn parse_expr(&mut self) -> i64 {
-        self.parse_ternary()
-    }
-
-    fn parse_ternary(&mut self) -> i64 {
-        let cond = self.parse_logical_or();
-
-        if matches!(self.peek(), Some(ArithToken::Op(op)) if op == "?") {
-            self.next(); // consume ?
-            let then_val = self.parse_expr();
-            if matches!(self.peek(), Some(ArithToken::Op(op)) if op == ":") {
-                self.next(); // consume :
-            }
-            let else_val = self.parse_expr();
-            if cond != 0 { then_val } else { else_val }
-        } else {
-            cond
-        }
-    }
-
-    fn parse_logical_or(&mut self) -> i64 {
-        let mut left = self.parse_logical_and();
-
-        while matches!(self.peek(), Some(ArithToken::Op(op)) if op == "||") {
-            self.next();
-            let right = self.parse_logical_and();
-            left = if left != 0 || right != 0 { 1 } else { 0 };
-        }
-
-        left
-    }
-
-    fn parse_logical_and(&mut self) -> i64 {
-        let mut left = self.parse_bitwise_or();
-
-        while matches!(self.peek(), Some(ArithToken::Op(op)) if op == "&&") {
-            self.next();
-            let right = self.parse_bitwise_or();
-            left = if left != 0 && right != 0 { 1 } else { 0 };
-        }
-
-        left
-    }
-
-    fn parse_bitwise_or(&mut self) -> i64 {
-        let mut left = self.parse_bitwise_xor();
-
-        while matches!(self.peek(), Some(ArithToken::Op(op)) if op == "|" && !matches!(self.tokens.get(self.pos + 1), Some(ArithToken::Op(o)) if o == "|")) {
-            self.next();
-            let right = self.parse_bitwise_xor();
-            left |= right;
-        }
-
-        left
-    }
-
-    fn parse_bitwise_xor(&mut self) -> i64 {
-        let mut left = self.parse_bitwise_and();
-
-        while matches!(self.peek(), Some(ArithToken::Op(op)) if op == "^") {
-            self.next();
-            let right = self.parse_bitwise_and();
-            left ^= right;
-        }
-
-        left
-    }
-
-    fn parse_bitwise_and(&mut self) -> i64 {
-        let mut left = self.parse_equality();
-
-        while matches!(self.peek(), Some(ArithToken::Op(op)) if op == "&" && !matches!(self.tokens.get(self.pos + 1), Some(ArithToken::Op(o)) if o == "&")) {
-            self.next();
-            let right = self.parse_equality();
-            left &= right;
-        }
-
-        left
-    }
-
-    fn parse_equality(&mut self) -> i64 {
-        let mut left = self.parse_comparison();
-
-        loop {
-            match self.peek() {
-                Some(ArithToken::Op(op)) if op == "==" => {
-                    self.next();
-                    let right = self.parse_comparison();
-                    left = if left == right { 1 } else { 0 };
-                }
-                Some(ArithToken::Op(op)) if op == "!=" => {
-                    self.next();
-                    let right = self.parse_comparison();
-                    left = if left != right { 1 } else { 0 };
-                }
-                _ => break,
-            }
-        }
-
-        left
-    }
-
-    fn parse_comparison(&mut self) -> i64 {
-        let mut left = self.parse_shift();
-
-        loop {
-            match self.peek() {
-                Some(ArithToken::Op(op)) if op == "<" => {
-                    self.next();
-                    let right = self.parse_shift();
-                    left = if left < right { 1 } else { 0 };
-                }
-                Some(ArithToken::Op(op)) if op == ">" => {
-                    self.next();
-                    let right = self.parse_shift();
-                    left = if left > right { 1 } else { 0 };
-                }
-                Some(ArithToken::Op(op)) if op == "<=" => {
-                    self.next();
-                    let right = self.parse_shift();
-                    left = if left <= right { 1 } else { 0 };
-                }
-                Some(ArithToken::Op(op)) if op == ">=" => {
-                    self.next();
-                    let right = self.parse_shift();
-                    left = if left >= right { 1 } else { 0 };
-                }
-                _ => break,
-            }
-        }
-
-        left
-    }
-
-    fn parse_shift(&mut self) -> i64 {
-        let mut left = self.parse_additive();
-
-        loop {
-            match self.peek() {
-                Some(ArithToken::Op(op)) if op == "<<" => {
-                    self.next();
-                    let right = self.parse_additive();
-                    left <<= right;
-                }
-                Some(ArithToken::Op(op)) if op == ">>" => {
-                    self.next();
-                    let right = self.parse_additive();
-                    left >>= right;
-                }
-                _ => break,
-            }
-        }
-
-        left
-    }
-
-    fn parse_additive(&mut self) -> i64 {
-        let mut left = self.parse_multiplicative();
-
-        loop {
-            match self.peek() {
-                Some(ArithToken::Op(op)) if op == "+" => {
-                    self.next();
-                    let right = self.parse_multiplicative();
-                    left += right;
-                }
-                Some(ArithToken::Op(op)) if op == "-" => {
-                    self.next();
-                    let right = self.parse_multiplicative();
-                    left -= right;
-                }
-                _ => break,
-            }
-        }
-
-        left
-    }
-
-    fn parse_multiplicative(&mut self) -> i64 {
-        let mut left = self.parse_power();
-
-        loop {
-            match self.peek() {
-                Some(ArithToken::Op(op)) if op == "*" && !matches!(self.tokens.get(self.pos + 1), Some(ArithToken::Op(o)) if o == "*") => {
-                    self.next();
-                    let right = self.parse_power();
-                    left *= right;
-                }
-                Some(ArithToken::Op(op)) if op == "/" => {
-                    self.next();
-                    let right = self.parse_power();
-                    if right != 0 {
-                        left /= right;
-                    } else {
-                        left = 0; // Division by zero
-                    }
-                }
-                Some(ArithToken::Op(op)) if op == "%" => {
-                    self.next();
-                    let right = self.parse_power();
-                    if right != 0 {
-                        left %= right;
-                    } else {
-                        left = 0;
-                    }
-                }
-                _ => break,
-            }
-        }
-
-        left
-    }
-
-    fn parse_power(&mut self) -> i64 {
-        let base = self.parse_unary();
-
-        if matches!(self.peek(), Some(ArithToken::Op(op)) if op == "**") {
-            self.next();
-            let exp = self.parse_power(); // Right associative
-            base.pow(exp as u32)
-        } else {
-            base
-        }
-    }
-
-    fn parse_unary(&mut self) -> i64 {
-        match self.peek() {
-            Some(ArithToken::Op(op)) if op == "-" => {
-                self.next();
-                -self.parse_unary()
-            }
-            Some(ArithToken::Op(op)) if op == "+" => {
-                self.next();
-                self.parse_unary()
-            }
-            Some(ArithToken::Op(op)) if op == "!" => {
-                self.next();
-                if self.parse_unary() == 0 { 1 } else { 0 }
-            }
-            Some(ArithToken::Op(op)) if op == "~" => {
-                self.next();
-                !self.parse_unary()
-            }
-            _ => self.parse_primary(),
-        }
-    }
-
-    fn parse_primary(&mut self) -> i64 {
-        match self.peek() {
-            Some(ArithToken::Num(_)) => {
-                if let Some(ArithToken::Num(n)) = self.next() {
-                    n
-                } else {
-                    0
-                }
-            }
-            Some(ArithToken::LParen) => {
-                self.next(); // consume (
-                let val = self.parse_expr();
-                if matches!(self.peek(), Some(ArithToken::RParen)) {
-                    self.next(); // consume )
-                }
-                val
-            }
-            _ => 0,
-        }
-    }
+/// The expression of a word that is exactly one `$((expr))`.
+fn whole_arithmetic(s: &str) -> Option<String> {
+    let mut chars = s.strip_prefix("$((")?.chars().peekable();
+    let expr = collect_arithmetic_expr(&mut chars);
+    (chars.next().is_none() && s.ends_with("))")).then_some(expr)
 }
 
 /// Check if a string contains glob metacharacters.
@@ -862,175 +247,20 @@ fn contains_glob_chars(s: &str) -> bool {
     s.chars().any(|c| c == '*' || c == '?' || c == '[')
 }
 
-/// Expand a glob pattern to matching paths.
-fn expand_glob(pattern: &str, state: &ShellState) -> Vec<String> {
-    // Split pattern into directory part and filename part
-    let (dir_part, file_pattern) = if let Some(pos) = pattern.rfind('/') {
-        let dir = &pattern[..=pos];
-        let file = &pattern[pos + 1..];
-        (dir.to_string(), file.to_string())
-    } else {
-        (".".to_string(), pattern.to_string())
-    };
-
-    // Resolve the directory relative to cwd
-    let search_dir = if dir_part == "." {
-        state.cwd.clone()
-    } else if dir_part.starts_with('/') {
-        std::path::PathBuf::from(&dir_part)
-    } else {
-        state.cwd.join(&dir_part)
-    };
-
-    // If the directory doesn't exist, no matches
-    if !search_dir.is_dir() {
-        return vec![];
-    }
-
-    // Read directory and match entries
-    let mut matches: Vec<String> = Vec::new();
-
-    if let Ok(entries) = std::fs::read_dir(&search_dir) {
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
-
-            // Skip hidden files unless pattern starts with .
-            if name.starts_with('.') && !file_pattern.starts_with('.') {
-                continue;
-            }
-
-            if glob_match(&file_pattern, &name) {
-                let full_path = if dir_part == "." {
-                    name
-                } else {
-                    format!("{}{}", dir_part, name)
-                };
-                matches.push(full_path);
-            }
-        }
-    }
-
-    // Sort matches alphabetically (POSIX requirement)
-    matches.sort();
-    matches
-}
-
-/// Match a filename against a glob pattern.
-///
-/// Supports:
-/// - `*` matches any sequence of characters
-/// - `?` matches any single character
-/// - `[abc]` matches any character in the set
-/// - `[a-z]` matches any character in the range
-/// - `[!abc]` or `[^abc]` matches any character not in the set
-fn glob_match(pattern: &str, name: &str) -> bool {
-    let pattern_chars: Vec<char> = pattern.chars().collect();
-    let name_chars: Vec<char> = name.chars().collect();
-    glob_match_impl(&pattern_chars, &name_chars, 0, 0)
+/// Whether pathname expansion applies to `word`.
+fn is_glob(word: &str, state: &ShellState) -> bool {
+    contains_glob_chars(word) || (state.options.extglob && super::glob::has_extglob(word))
 }
 
-fn glob_match_impl(pattern: &[char], name: &[char], mut pi: usize, mut ni: usize) -> bool {
-    while pi < pattern.len() || ni < name.len() {
-        if pi < pattern.len() {
-            match pattern[pi] {
-                '*' => {
-                    // Try matching zero or more characters
-                    // First, skip consecutive *'s
-                    while pi < pattern.len() && pattern[pi] == '*' {
-                        pi += 1;
-                    }
-                    // If * is at end of pattern, it matches everything
-                    if pi == pattern.len() {
-                        return true;
-                    }
-                    // Try matching * against increasingly longer substrings
-                    while ni <= name.len() {
-                        if glob_match_impl(pattern, name, pi, ni) {
-                            return true;
-                        }
-                        ni += 1;
-                    }
-                    return false;
-                }
-                '?' => {
-                    // Must have a character to match
-                    if ni >= name.len() {
-                        return false;
-                    }
-                    pi += 1;
-                    ni += 1;
-                }
-                '[' => {
-                    // Character class
-                    if ni >= name.len() {
-                        return false;
-                    }
-                    let (matched, new_pi) = match_char_class(&pattern[pi..], name[ni]);
-                    if !matched {
-                        return false;
-                    }
-                    pi += new_pi;
-                    ni += 1;
-                }
-                c => {
-                    // Literal character
-                    if ni >= name.len() || name[ni] != c {
-                        return false;
-                    }
-                    pi += 1;
-                    ni += 1;
-                }
-            }
-        } else {
-            // Pattern exhausted but name has more characters
-            return false;
-        }
-    }
-    true
+/// Expand a glob pattern to matching paths, see [`super::glob::expand`].
+fn expand_glob(pattern: &str, state: &ShellState) -> Vec<String> {
+    super::glob::expand(pattern, &state.cwd, &state.options)
 }
 
-/// Match a character class like [abc] or [a-z] or [!abc].
-/// Returns (matched, chars_consumed_from_pattern).
-fn match_char_class(pattern: &[char], c: char) -> (bool, usize) {
-    if pattern.is_empty() || pattern[0] != '[' {
-        return (false, 0);
-    }
-
-    let mut i = 1;
-    let negated = if i < pattern.len() && (pattern[i] == '!' || pattern[i] == '^') {
-        i += 1;
-        true
-    } else {
-        false
-    };
-
-    let mut matched = false;
-
-    while i < pattern.len() && pattern[i] != ']' {
-        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
-            // Range like a-z
-            let start = pattern[i];
-            let end = pattern[i + 2];
-            if c >= start && c <= end {
-                matched = true;
-            }
-            i += 3;
-        } else {
-            // Single character
-            if pattern[i] == c {
-                matched = true;
-            }
-            i += 1;
-        }
-    }
-
-    // Skip closing ]
-    if i < pattern.len() && pattern[i] == ']' {
-        i += 1;
-    }
-
-    let result = if negated { !matched } else { matched };
-    (result, i)
+/// Match a filename against a glob pattern: `*`, `?` and bracket
+/// classes like `[a-z]` and `[!abc]`.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    super::glob::matches(pattern, name, false)
 }
 
 /// Expand a literal string, handling embedded variables and escapes.
@@ -1049,59 +279,7 @@ fn expand_literal(s: &str, state: &ShellState) -> String {
                     result.push(next);
                 }
             }
-            '$' => {
-                // Check for arithmetic expansion $((expr))
-                if chars.peek() == Some(&'(') {
-                    chars.next(); // consume first '('
-                    if chars.peek() == Some(&'(') {
-                        chars.next(); // consume second '('
-                        // Find matching ))
-                        let expr = collect_arithmetic_expr(&mut chars);
-                        let value = evaluate_arithmetic(&expr, state);
-                        result.push_str(&value.to_string());
-                        continue;
-                    } else {
-                        // Command substitution $(cmd) - collect until matching )
-                        let mut depth = 1;
-                        let mut cmd = String::new();
-                        while let Some(ch) = chars.next() {
-                            if ch == '(' {
-                                depth += 1;
-                                cmd.push(ch);
-                            } else if ch == ')' {
-                                depth -= 1;
-                                if depth == 0 {
-                                    break;
-                                }
-                                cmd.push(ch);
-                            } else {
-                                cmd.push(ch);
-                            }
-                        }
-                        result.push_str(&expand_command_substitution(&format!("$({})", cmd), state));
-                        continue;
-                    }
-                }
-
-                // Variable expansion
-                if chars.peek() == Some(&'{') {
-                    // ${var} form
-                    chars.next(); // consume '{'
-                    let var_name: String = chars.by_ref().take_while(|&c| c != '}').collect();
-                    result.push_str(&expand_variable(&var_name, state));
-                } else {
-                    // $var form
-                    let var_name: String = chars
-                        .by_ref()
-                        .take_while(|&c| c.is_alphanumeric() || c == '_')
-                        .collect();
-                    if var_name.is_empty() {
-                        result.push('$');
-                    } else {
-                        result.push_str(&expand_variable(&var_name, state));
-                    }
-                }
-            }
+            '$' => expand_dollar(&mut chars, state, &mut result),
             '\'' => {
                 // Single quotes - literal, no expansion
                 let quoted: String = chars.by_ref().take_while(|&c| c != '\'').collect();
@@ -1118,22 +296,7 @@ fn expand_literal(s: &str, state: &ShellState) -> String {
                             quoted.push(next);
                         }
                     } else if c == '$' {
-                        if chars.peek() == Some(&'{') {
-                            chars.next();
-                            let var_name: String =
-                                chars.by_ref().take_while(|&c| c != '}').collect();
-                            quoted.push_str(&expand_variable(&var_name, state));
-                        } else {
-                            let var_name: String = chars
-                                .by_ref()
-                                .take_while(|&c| c.is_alphanumeric() || c == '_')
-                                .collect();
-                            if var_name.is_empty() {
-                                quoted.push('$');
-                            } else {
-                                quoted.push_str(&expand_variable(&var_name, state));
-                            }
-                        }
+                        expand_dollar(&mut chars, state, &mut quoted);
                     } else {
                         quoted.push(c);
                     }
@@ -1147,6 +310,116 @@ fn expand_literal(s: &str, state: &ShellState) -> String {
     result
 }
 
+/// Expand what follows a `$`: `$((expr))`, `$(cmd)`, `${var}` or `$var`.
+/// A `$` with nothing expandable after it stays literal.
+fn expand_dollar(chars: &mut std::iter::Peekable<std::str::Chars>, state: &ShellState, result: &mut String) {
+    // Check for arithmetic expansion $((expr))
+    if chars.peek() == Some(&'(') {
+        chars.next(); // consume first '('
+        if chars.peek() == Some(&'(') {
+            chars.next(); // consume second '('
+            // Find matching ))
+            let expr = collect_arithmetic_expr(chars);
+            let value = evaluate_arithmetic(&expr, state);
+            result.push_str(&value.to_string());
+            return;
+        } else {
+            // Command substitution $(cmd) - collect until matching )
+            let mut depth = 1;
+            let mut cmd = String::new();
+            while let Some(ch) = chars.next() {
+                if ch == '(' {
+                    depth += 1;
+                    cmd.push(ch);
+                } else if ch == ')' {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    cmd.push(ch);
+                } else {
+                    cmd.push(ch);
+                }
+            }
+            result.push_str(&expand_command_substitution(&format!("$({})", cmd), state));
+            return;
+        }
+    }
+
+    // Variable expansion
+    if chars.peek() == Some(&'{') {
+        // ${var} form
+        chars.next(); // consume '{'
+        let var_name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+        result.push_str(&expand_variable(&var_name, state));
+    } else {
+        // $var form; the character after the name is left for the caller
+        let mut var_name = String::new();
+        while let Some(c) = chars.next_if(|&c| c.is_alphanumeric() || c == '_') {
+            var_name.push(c);
+        }
+        if var_name.is_empty() {
+            result.push('$');
+        } else {
+            result.push_str(&expand_variable(&var_name, state));
+        }
+    }
+}
+
+/// Expand a here-document body: parameters, command substitutions and
+/// arithmetic, with `\` escaping only `$`, `` ` ``, `\` and newlines.
+/// Quotes are ordinary characters.
+pub fn expand_heredoc(body: &str, state: &ShellState) -> String {
+    let mut result = String::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some('$' | '`' | '\\') => result.extend(chars.next()),
+                // Line continuation
+                Some('\n') => {
+                    chars.next();
+                }
+                _ => result.push('\\'),
+            },
+            '$' => expand_dollar(&mut chars, state, &mut result),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// The text a here-document or here-string feeds its descriptor, or `None`
+/// for any other redirect.
+pub fn here_doc_input(redirect: &Redirect, state: &ShellState) -> Option<String> {
+    match redirect.op {
+        RedirectOp::Heredoc { expand: true } => Some(expand_heredoc(&redirect.target, state)),
+        RedirectOp::Heredoc { expand: false } => Some(redirect.target.clone()),
+        RedirectOp::HereString => {
+            Some(format!("{}\n", expand_literal(&redirect.target, state)))
+        }
+        _ => None,
+    }
+}
+
+/// Expand every here-document and here-string in `redirects` into a
+/// here-document with nothing left to expand, ready for a child process.
+pub fn expand_here_docs(redirects: &[Redirect], state: &ShellState) -> Vec<Redirect> {
+    redirects
+        .iter()
+        .map(|redirect| match here_doc_input(redirect, state) {
+            Some(text) => Redirect {
+                fd: redirect.fd,
+                op: RedirectOp::Heredoc { expand: false },
+                target: text,
+            },
+            None => redirect.clone(),
+        })
+        .collect()
+}
+
 /// Expand a variable reference.
 fn expand_variable(name: &str, state: &ShellState) -> String {
     // Handle special variables
@@ -1168,7 +441,10 @@ fn expand_variable(name: &str, state: &ShellState) -> String {
             if let Some((var, modifier)) = parse_parameter_expansion(name) {
                 apply_parameter_expansion(&var, &modifier, state)
             } else {
-                state.get_var(name).unwrap_or_default().to_string()
+                state.get_var(name).map(str::to_string).unwrap_or_else(|| {
+                    check_bound(name, state);
+                    String::new()
+                })
             }
         }
     }
@@ -1472,29 +748,17 @@ fn get_user_home(_username: &str) -> Option<String> {
     None
 }
 
-/// Expand command substitution $(cmd) or `cmd`.
-fn expand_command_substitution(cmd: &str, _state: &ShellState) -> String {
-    // Strip $() or ``
-    let inner = cmd
-        .trim_start_matches("$(")
-        .trim_end_matches(')')
-        .trim_start_matches('`')
-        .trim_end_matches('`');
-
-    // Execute the command and capture output
-    // For now, use std::process::Command as a simple implementation
-    match std::process::Command::new("sh")
-        .arg("-c")
-        .arg(inner)
-        .output()
-    {
-        Ok(output) => {
-            String::from_utf8_lossy(&output.stdout)
-                .trim_end_matches('\n')
-                .to_string()
-        }
-        Err(_) => String::new(),
-    }
+/// The registry substitutions inside words run native commands from.
+/// Expansion has no session registry to hand, and plugins are only loaded
+/// into that one.
+fn substitution_registry() -> &'static CommandRegistry {
+    static REGISTRY: OnceLock<CommandRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(CommandRegistry::new)
+}
+
+/// Expand command substitution $(cmd) or `cmd` to its output.
+fn expand_command_substitution(cmd: &str, state: &ShellState) -> String {
+    super::substitute(cmd, state, substitution_registry()).text
 }
 
 #[cfg(test)]
@@ -1646,79 +910,6 @@ mod tests {
         assert_eq!(result, vec!["*.txt"]);
     }
 
-    // ========================================================================
-    // Brace expansion tests
-    // ========================================================================
-
-    #[test]
-    fn test_brace_comma_simple() {
-        assert_eq!(expand_braces("{a,b,c}"), vec!["a", "b", "c"]);
-    }
-
-    #[test]
-    fn test_brace_comma_with_preamble() {
-        assert_eq!(expand_braces("file{1,2,3}.txt"), vec!["file1.txt", "file2.txt", "file3.txt"]);
-    }
-
-    #[test]
-    fn test_brace_comma_multiple() {
-        assert_eq!(expand_braces("{a,b}{1,2}"), vec!["a1", "a2", "b1", "b2"]);
-    }
-
-    #[test]
-    fn test_brace_numeric_range() {
-        assert_eq!(expand_braces("{1..5}"), vec!["1", "2", "3", "4", "5"]);
-    }
-
-    #[test]
-    fn test_brace_numeric_range_reverse() {
-        assert_eq!(expand_braces("{5..1}"), vec!["5", "4", "3", "2", "1"]);
-    }
-
-    #[test]
-    fn test_brace_numeric_range_step() {
-        assert_eq!(expand_braces("{1..10..2}"), vec!["1", "3", "5", "7", "9"]);
-    }
-
-    #[test]
-    fn test_brace_alpha_range() {
-        assert_eq!(expand_braces("{a..e}"), vec!["a", "b", "c", "d", "e"]);
-    }
-
-    #[test]
-    fn test_brace_alpha_range_upper() {
-        assert_eq!(expand_braces("{A..E}"), vec!["A", "B", "C", "D", "E"]);
-    }
-
-    #[test]
-    fn test_brace_alpha_range_reverse() {
-        assert_eq!(expand_braces("{e..a}"), vec!["e", "d", "c", "b", "a"]);
-    }
-
-    #[test]
-    fn test_brace_nested() {
-        assert_eq!(expand_braces("{a,b{1,2}}"), vec!["a", "b1", "b2"]);
-    }
-
-    #[test]
-    fn test_brace_no_expansion() {
-        // No comma or range - not a brace expansion
-        assert_eq!(expand_braces("{foo}"), vec!["{foo}"]);
-    }
-
-    #[test]
-    fn test_brace_mkdir_pattern() {
-        assert_eq!(
-            expand_braces("src/{components,utils,hooks}"),
-            vec!["src/components", "src/utils", "src/hooks"]
-        );
-    }
-
-    #[test]
-    fn test_brace_mv_pattern() {
-        assert_eq!(expand_braces("app.{js,ts}"), vec!["app.js", "app.ts"]);
-    }
-
     // ========================================================================
     // Arithmetic expansion tests
     // ========================================================================
@@ -1914,4 +1105,14 @@ mod tests {
         // ${var%%pattern} - remove longest suffix
         assert_eq!(expand_variable("FILE%%.*", &state), "document");
     }
+
+    #[test]
+    fn test_expand_heredoc() {
+        let mut state = make_state();
+        state.set_var("NAME".to_string(), "world".to_string());
+
+        assert_eq!(expand_heredoc("hello $NAME\n", &state), "hello world\n");
+        assert_eq!(expand_heredoc("${NAME}!\n", &state), "world!\n");
+        assert_eq!(expand_heredoc("cost: \\$5\n", &state), "cost: $5\n");
+    }
 }
@@ -0,0 +1,439 @@
+//! Shell patterns and pathname expansion.
+//!
+//! One matcher serves globbing, `case` and `[[ == ]]`: `*`, `?` and
+//! bracket classes, plus the extglob groups `?(a|b)` (zero or one),
+//! `*(a|b)` (any number), `+(a|b)` (one or more), `@(a|b)` (exactly one)
+//! and `!(a|b)` (anything else) when they are enabled. [`expand`] applies
+//! a pattern to the filesystem, honoring `shopt` `dotglob` and `globstar`
+//! (`**` spans any depth of directories); `nullglob` is up to the caller,
+//! which decides what an empty result turns into.
+
+use std::path::{Path, PathBuf};
+
+use crate::state::ShellOptions;
+
+/// A parsed shell pattern.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Literal(char),
+    /// `?`
+    One,
+    /// `*`
+    Any,
+    Class(Class),
+    Group(GroupKind, Vec<Vec<Token>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupKind {
+    /// `?(...)`
+    ZeroOrOne,
+    /// `*(...)`
+    ZeroOrMore,
+    /// `+(...)`
+    OneOrMore,
+    /// `@(...)`
+    One,
+    /// `!(...)`
+    Not,
+}
+
+impl GroupKind {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '?' => Some(Self::ZeroOrOne),
+            '*' => Some(Self::ZeroOrMore),
+            '+' => Some(Self::OneOrMore),
+            '@' => Some(Self::One),
+            '!' => Some(Self::Not),
+            _ => None,
+        }
+    }
+}
+
+/// A bracket expression: `[a-z]`, `[!0-9]`, `[[:alpha:]_]`.
+#[derive(Debug, Clone)]
+struct Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+    named: Vec<fn(char) -> bool>,
+}
+
+impl Class {
+    fn contains(&self, c: char) -> bool {
+        let hit = self.ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) || self.named.iter().any(|f| f(c));
+        hit != self.negated
+    }
+}
+
+impl Pattern {
+    /// Parse `pattern`. With `extglob` off, `@(` and friends are plain
+    /// characters. A backslash makes the next character literal.
+    pub fn new(pattern: &str, extglob: bool) -> Self {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut pos = 0;
+        let tokens = parse_sequence(&chars, &mut pos, extglob, false);
+        Self { tokens }
+    }
+
+    /// Whether `text` matches the whole pattern.
+    pub fn matches(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        match_sequence(&self.tokens, &text)
+    }
+
+    /// Whether the pattern starts with a literal `.`, which lets it match
+    /// hidden names without `dotglob`.
+    fn starts_with_dot(&self) -> bool {
+        matches!(self.tokens.first(), Some(Token::Literal('.')))
+    }
+}
+
+/// Whether `pattern` matches `text`, see [`Pattern`].
+pub fn matches(pattern: &str, text: &str, extglob: bool) -> bool {
+    Pattern::new(pattern, extglob).matches(text)
+}
+
+/// Whether `word` has an extglob group, `@(...)` and the like.
+pub fn has_extglob(word: &str) -> bool {
+    let chars: Vec<char> = word.chars().collect();
+    chars.windows(2).any(|pair| pair[1] == '(' && GroupKind::from_char(pair[0]).is_some())
+}
+
+fn is_pattern(component: &str, extglob: bool) -> bool {
+    component.contains(['*', '?', '[']) || (extglob && has_extglob(component))
+}
+
+fn parse_sequence(chars: &[char], pos: &mut usize, extglob: bool, in_group: bool) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    while let Some(&c) = chars.get(*pos) {
+        if in_group && (c == '|' || c == ')') {
+            break;
+        }
+        if extglob
+            && chars.get(*pos + 1) == Some(&'(')
+            && let Some(kind) = GroupKind::from_char(c)
+        {
+            let mut end = *pos + 2;
+            if let Some(alternatives) = parse_group(chars, &mut end) {
+                tokens.push(Token::Group(kind, alternatives));
+                *pos = end;
+                continue;
+            }
+        }
+        *pos += 1;
+        match c {
+            '\\' => {
+                let escaped = chars.get(*pos).copied().unwrap_or('\\');
+                *pos += 1;
+                tokens.push(Token::Literal(escaped));
+            }
+            '?' => tokens.push(Token::One),
+            '*' => {
+                if !matches!(tokens.last(), Some(Token::Any)) {
+                    tokens.push(Token::Any);
+                }
+            }
+            '[' => match parse_class(chars, *pos) {
+                Some((class, end)) => {
+                    tokens.push(Token::Class(class));
+                    *pos = end;
+                }
+                None => tokens.push(Token::Literal('[')),
+            },
+            c => tokens.push(Token::Literal(c)),
+        }
+    }
+    tokens
+}
+
+/// The alternatives of a group whose `(` is just before `pos`, leaving
+/// `pos` after its `)`. `None` if it is never closed.
+fn parse_group(chars: &[char], pos: &mut usize) -> Option<Vec<Vec<Token>>> {
+    let mut alternatives = Vec::new();
+    loop {
+        alternatives.push(parse_sequence(chars, pos, true, true));
+        match chars.get(*pos) {
+            Some('|') => *pos += 1,
+            Some(')') => {
+                *pos += 1;
+                return Some(alternatives);
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// A bracket expression starting just after its `[`, and the position
+/// after its `]`. `None` if it is never closed.
+fn parse_class(chars: &[char], mut pos: usize) -> Option<(Class, usize)> {
+    let mut class = Class { negated: false, ranges: Vec::new(), named: Vec::new() };
+    if matches!(chars.get(pos), Some('!' | '^')) {
+        class.negated = true;
+        pos += 1;
+    }
+    let start = pos;
+    loop {
+        let c = *chars.get(pos)?;
+        // A `]` first in the set is part of it.
+        if c == ']' && pos > start {
+            return Some((class, pos + 1));
+        }
+        if c == '['
+            && chars.get(pos + 1) == Some(&':')
+            && let Some(len) = chars[pos + 2..].windows(2).position(|w| w == [':', ']'])
+        {
+            let name: String = chars[pos + 2..pos + 2 + len].iter().collect();
+            class.named.push(named_class(&name)?);
+            pos += len + 4;
+            continue;
+        }
+        if chars.get(pos + 1) == Some(&'-') && chars.get(pos + 2).is_some_and(|&end| end != ']') {
+            class.ranges.push((c, chars[pos + 2]));
+            pos += 3;
+        } else {
+            class.ranges.push((c, c));
+            pos += 1;
+        }
+    }
+}
+
+fn named_class(name: &str) -> Option<fn(char) -> bool> {
+    Some(match name {
+        "alpha" => |c: char| c.is_alphabetic(),
+        "digit" => |c: char| c.is_ascii_digit(),
+        "alnum" => |c: char| c.is_alphanumeric(),
+        "upper" => |c: char| c.is_uppercase(),
+        "lower" => |c: char| c.is_lowercase(),
+        "space" => |c: char| c.is_whitespace(),
+        "blank" => |c: char| c == ' ' || c == '\t',
+        "punct" => |c: char| c.is_ascii_punctuation(),
+        "xdigit" => |c: char| c.is_ascii_hexdigit(),
+        "cntrl" => |c: char| c.is_control(),
+        "print" => |c: char| !c.is_control(),
+        "graph" => |c: char| !c.is_control() && !c.is_whitespace(),
+        _ => return None,
+    })
+}
+
+fn match_sequence(tokens: &[Token], text: &[char]) -> bool {
+    let Some((first, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+    match first {
+        Token::Literal(c) => text.first() == Some(c) && match_sequence(rest, &text[1..]),
+        Token::One => !text.is_empty() && match_sequence(rest, &text[1..]),
+        Token::Class(class) => text.first().is_some_and(|&c| class.contains(c)) && match_sequence(rest, &text[1..]),
+        Token::Any => (0..=text.len()).any(|n| match_sequence(rest, &text[n..])),
+        Token::Group(kind, alternatives) => {
+            let alternative = |n: usize| alternatives.iter().any(|alt| match_sequence(alt, &text[..n]));
+            match kind {
+                GroupKind::One => (0..=text.len()).any(|n| alternative(n) && match_sequence(rest, &text[n..])),
+                GroupKind::ZeroOrOne => {
+                    match_sequence(rest, text) || (0..=text.len()).any(|n| alternative(n) && match_sequence(rest, &text[n..]))
+                }
+                GroupKind::Not => (0..=text.len()).any(|n| !alternative(n) && match_sequence(rest, &text[n..])),
+                GroupKind::ZeroOrMore => match_repeated(alternatives, rest, text),
+                GroupKind::OneOrMore => {
+                    (1..=text.len()).any(|n| alternative(n) && match_repeated(alternatives, rest, &text[n..]))
+                }
+            }
+        }
+    }
+}
+
+/// Any number of `alternatives` back to back, then `rest`.
+fn match_repeated(alternatives: &[Vec<Token>], rest: &[Token], text: &[char]) -> bool {
+    match_sequence(rest, text)
+        || (1..=text.len()).any(|n| {
+            alternatives.iter().any(|alt| match_sequence(alt, &text[..n])) && match_repeated(alternatives, rest, &text[n..])
+        })
+}
+
+/// The paths matching `pattern`, sorted, written relative to `cwd` unless
+/// the pattern is absolute. Empty when nothing matches.
+pub fn expand(pattern: &str, cwd: &Path, options: &ShellOptions) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec![("/".to_string(), PathBuf::from("/"))], rest),
+        None => (vec![(String::new(), cwd.to_path_buf())], pattern),
+    };
+    let components: Vec<&str> = rest.split('/').collect();
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        if component.is_empty() {
+            if last {
+                // A trailing slash keeps directories only.
+                paths.retain(|(shown, path)| !shown.is_empty() && path.is_dir());
+                for (shown, _) in &mut paths {
+                    shown.push('/');
+                }
+            }
+            continue;
+        }
+        paths = if *component == "**" && options.globstar {
+            let mut found = Vec::new();
+            for (shown, path) in &paths {
+                // `**/x` also looks in the directory itself; `dir/**` lists
+                // `dir/` too.
+                if !last {
+                    found.push((shown.clone(), path.clone()));
+                } else if !shown.is_empty() {
+                    found.push((join(shown, ""), path.clone()));
+                }
+                walk(shown, path, !last, options.dotglob, &mut found);
+            }
+            found
+        } else if is_pattern(component, options.extglob) {
+            let pattern = Pattern::new(component, options.extglob);
+            let hidden = options.dotglob || pattern.starts_with_dot();
+            let mut found = Vec::new();
+            for (shown, path) in &paths {
+                for (name, child) in entries(path) {
+                    if (hidden || !name.starts_with('.')) && pattern.matches(&name) && (last || child.is_dir()) {
+                        found.push((join(shown, &name), child));
+                    }
+                }
+            }
+            found
+        } else {
+            paths
+                .into_iter()
+                .map(|(shown, path)| (join(&shown, component), path.join(component)))
+                .filter(|(_, path)| if last { path.symlink_metadata().is_ok() } else { path.is_dir() })
+                .collect()
+        };
+    }
+    let mut matches: Vec<String> = paths.into_iter().map(|(shown, _)| shown).filter(|shown| !shown.is_empty()).collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
+
+/// Everything below `dir`, or only its directories, depth first. Hidden
+/// names are skipped without `dotglob`, and symlinked directories are
+/// not followed.
+fn walk(shown: &str, dir: &Path, dirs_only: bool, dotglob: bool, found: &mut Vec<(String, PathBuf)>) {
+    for (name, child) in entries(dir) {
+        if !dotglob && name.starts_with('.') {
+            continue;
+        }
+        let is_dir = child.symlink_metadata().is_ok_and(|m| m.is_dir());
+        let child_shown = join(shown, &name);
+        if is_dir || !dirs_only {
+            found.push((child_shown.clone(), child.clone()));
+        }
+        if is_dir {
+            walk(&child_shown, &child, dirs_only, dotglob, found);
+        }
+    }
+}
+
+fn entries(dir: &Path) -> Vec<(String, PathBuf)> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| (entry.file_name().to_string_lossy().into_owned(), entry.path()))
+        .collect()
+}
+
+fn join(shown: &str, name: &str) -> String {
+    if shown.is_empty() || shown.ends_with('/') {
+        format!("{}{}", shown, name)
+    } else {
+        format!("{}/{}", shown, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(files: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for file in files {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            if !file.ends_with('/') {
+                std::fs::write(path, "").unwrap();
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn test_basic_patterns() {
+        assert!(matches("*.rs", "main.rs", false));
+        assert!(!matches("*.rs", "main.rs.bak", false));
+        assert!(matches("f?o", "foo", false));
+        assert!(matches("[!a-c]x", "dx", false));
+        assert!(matches("[]a]", "]", false));
+        assert!(matches("[[:digit:]][[:alpha:]]", "1a", false));
+        assert!(matches("\\*", "*", false));
+        assert!(!matches("\\*", "x", false));
+        assert!(matches("[abc", "[abc", false));
+    }
+
+    #[test]
+    fn test_extglob_groups() {
+        // Results checked against bash 5.2 with `shopt -s extglob`.
+        assert!(matches("@(foo|bar).txt", "bar.txt", true));
+        assert!(!matches("@(foo|bar).txt", "baz.txt", true));
+        assert!(matches("!(*.rs)", "notes.md", true));
+        assert!(!matches("!(*.rs)", "main.rs", true));
+        assert!(matches("?(x)y", "y", true));
+        assert!(matches("?(x)y", "xy", true));
+        assert!(!matches("?(x)y", "xxy", true));
+        assert!(matches("*(ab)c", "ababc", true));
+        assert!(matches("*(ab)c", "c", true));
+        assert!(!matches("+(ab)c", "c", true));
+        assert!(matches("+(ab|cd)", "abcdab", true));
+        assert!(matches("@(a|+(b))", "bbb", true));
+        // Without extglob the group is literal text.
+        assert!(!matches("@(foo|bar)", "foo", false));
+        assert!(matches("@(foo|bar)", "@(foo|bar)", false));
+        // An unclosed group is literal too.
+        assert!(matches("@(foo", "@(foo", true));
+    }
+
+    #[test]
+    fn test_expand_hides_dotfiles_unless_dotglob() {
+        let dir = tree(&["a.txt", ".hidden.txt", "b.md"]);
+        let mut options = ShellOptions::default();
+        assert_eq!(expand("*.txt", dir.path(), &options), ["a.txt"]);
+        assert_eq!(expand(".*.txt", dir.path(), &options), [".hidden.txt"]);
+        options.dotglob = true;
+        assert_eq!(expand("*.txt", dir.path(), &options), [".hidden.txt", "a.txt"]);
+    }
+
+    #[test]
+    fn test_expand_globstar() {
+        let dir = tree(&["top.rs", "src/lib.rs", "src/eval/glob.rs", "src/notes.md", ".git/hook.rs"]);
+        let mut options = ShellOptions::default();
+        // Without globstar `**` is `*`: one directory level.
+        assert_eq!(expand("**/*.rs", dir.path(), &options), ["src/lib.rs"]);
+        options.globstar = true;
+        assert_eq!(expand("**/*.rs", dir.path(), &options), ["src/eval/glob.rs", "src/lib.rs", "top.rs"]);
+        assert_eq!(expand("src/**", dir.path(), &options), ["src/", "src/eval", "src/eval/glob.rs", "src/lib.rs", "src/notes.md"]);
+        assert_eq!(expand("**/", dir.path(), &options), ["src/", "src/eval/"]);
+    }
+
+    #[test]
+    fn test_expand_extglob_and_dirs() {
+        let dir = tree(&["a.rs", "b.md", "c.txt", "docs/x.md"]);
+        let mut options = ShellOptions::default();
+        assert!(expand("@(a|b).*", dir.path(), &options).is_empty());
+        options.extglob = true;
+        assert_eq!(expand("@(a|b).*", dir.path(), &options), ["a.rs", "b.md"]);
+        assert_eq!(expand("!(*.rs)", dir.path(), &options), ["b.md", "c.txt", "docs"]);
+        assert_eq!(expand("*/", dir.path(), &options), ["docs/"]);
+        assert_eq!(expand("./d*/*.md", dir.path(), &options), ["./docs/x.md"]);
+        let absolute = format!("{}/*.rs", dir.path().display());
+        assert_eq!(expand(&absolute, Path::new("/"), &options), [format!("{}/a.rs", dir.path().display())]);
+    }
+}
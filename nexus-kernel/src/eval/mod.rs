@@ -1,22 +1,31 @@
 //! Evaluator - AST walker that executes commands.
 
+mod arith;
+mod brace;
 mod builtins;
 mod expand;
+mod glob;
+mod procsub;
 
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 
-use nexus_api::{ShellEvent, Value};
-use tokio::sync::broadcast::Sender;
+use nexus_api::{CommandError, CommandErrorKind, InteractiveRequest, ShellEvent, Value, ViewerKind};
+use crate::replay::EventSender;
 
 use nexus_api::BlockId;
+use tokio::sync::broadcast::error::TryRecvError;
 
-use crate::commands::{register_cancel, unregister_cancel, CommandContext, CommandRegistry};
+use crate::commands::{register_cancel, unregister_cancel, CommandContext, CommandRegistry, OutputMode};
+use crate::config::PagerMode;
+use crate::debug::DebugAction;
 use crate::parser::*;
-use crate::process;
+use crate::process::{self, color};
+use crate::profile::{Profile, ProfileKind};
 use crate::state::{get_or_create_block_id, ShellState};
 
 pub use builtins::is_builtin;
+pub(crate) use expand::glob_match;
 use builtins::{BREAK_EXIT_CODE, CONTINUE_EXIT_CODE, RETURN_EXIT_CODE};
 
 /// Check if an exit code represents a break signal.
@@ -60,7 +69,7 @@ fn decrement_level(exit_code: i32) -> i32 {
 pub fn execute(
     state: &mut ShellState,
     ast: &Ast,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
 ) -> anyhow::Result<i32> {
     execute_with_block_id(state, ast, events, commands, None)
@@ -70,16 +79,19 @@ pub fn execute(
 pub fn execute_with_block_id(
     state: &mut ShellState,
     ast: &Ast,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
+    // Closes substitutions expanded outside any command, e.g. in `for` items.
+    let _substitutions = procsub::Scope::enter();
     let mut last_exit = 0;
 
     for command in &ast.commands {
         last_exit = execute_command(state, command, events, commands, block_id)?;
         state.last_exit_code = last_exit;
     }
+    crate::traps::deliver(state, events, commands, block_id)?;
 
     Ok(last_exit)
 }
@@ -88,11 +100,12 @@ pub fn execute_with_block_id(
 fn execute_command(
     state: &mut ShellState,
     command: &Command,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
-    match command {
+    crate::traps::deliver(state, events, commands, block_id)?;
+    let exit_code = match command {
         Command::Simple(simple) => execute_simple(state, simple, events, commands, block_id),
         Command::Pipeline(pipeline) => execute_pipeline(state, pipeline, events, commands, block_id),
         Command::List(list) => execute_list(state, list, events, commands, block_id),
@@ -101,20 +114,62 @@ fn execute_command(
         Command::If(if_stmt) => execute_if(state, if_stmt, events, commands, block_id),
         Command::While(while_stmt) => execute_while(state, while_stmt, events, commands, block_id),
         Command::For(for_stmt) => execute_for(state, for_stmt, events, commands, block_id),
+        Command::ArithmeticFor(for_stmt) => execute_arithmetic_for(state, for_stmt, events, commands, block_id),
+        Command::Arithmetic(arith_cmd) => Ok(execute_arithmetic(state, &arith_cmd.expr, events, block_id)),
         Command::Function(func_def) => execute_function_def(state, func_def),
         Command::Case(case_stmt) => execute_case(state, case_stmt, events, commands, block_id),
         Command::Watch(watch) => execute_watch(state, watch, events, commands, block_id),
+    }?;
+    // Words a compound expands itself (`for` items, `case` subjects).
+    check_unbound(events, block_id)?;
+
+    // ERR fires for the command that failed, not the compound around it,
+    // and not for a test in a condition.
+    let failed = exit_code != 0 && exit_code < BREAK_EXIT_CODE && state.conditions == 0;
+    let simple = match command {
+        Command::Simple(_) => true,
+        Command::Pipeline(pipeline) => pipeline.commands.len() > 1,
+        _ => false,
+    };
+    if failed && simple {
+        state.last_exit_code = exit_code;
+        crate::traps::run(state, crate::traps::ERR, events, commands, block_id)?;
+        // `set -e` ends the block where ERR would fire.
+        if state.options.errexit {
+            return Err(crate::error::ShellError::Aborted(exit_code).into());
+        }
     }
+    Ok(exit_code)
 }
 
-/// Execute a simple command.
-fn execute_simple(
+/// Under `set -u`, end the block once a command's words referred to an
+/// unset variable.
+fn check_unbound(events: &EventSender, block_id: Option<BlockId>) -> anyhow::Result<()> {
+    let Some(name) = expand::take_unbound() else {
+        return Ok(());
+    };
+    let error = CommandError::new(&name, CommandErrorKind::Other, "unbound variable");
+    send_command_error(events, get_or_create_block_id(block_id), Some(error));
+    Err(crate::error::ShellError::Aborted(1).into())
+}
+
+/// Run a command whose status is tested (`if`, `while`, the left of `&&`
+/// and `||`), so its failure isn't an error.
+fn execute_condition(
     state: &mut ShellState,
-    cmd: &SimpleCommand,
-    events: &Sender<ShellEvent>,
+    command: &Command,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
+    state.conditions += 1;
+    let result = execute_command(state, command, events, commands, block_id);
+    state.conditions -= 1;
+    result
+}
+
+/// Expand a simple command's name, arguments and environment assignments.
+fn expand_simple(state: &ShellState, cmd: &SimpleCommand) -> (String, Vec<String>, Vec<(String, String)>) {
     // Expand the command name and arguments
     let name = expand::expand_word_to_string(&Word::Literal(cmd.name.clone()), state);
     // Use expand_word_to_strings to handle glob expansion (*.txt -> multiple files)
@@ -136,14 +191,56 @@ fn execute_simple(
         })
         .collect();
 
+    (name, args, env_overrides)
+}
+
+/// Execute a simple command.
+fn execute_simple(
+    state: &mut ShellState,
+    cmd: &SimpleCommand,
+    events: &EventSender,
+    commands: &CommandRegistry,
+    block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let _substitutions = procsub::Scope::enter();
+    let (name, args, env_overrides) = expand_simple(state, cmd);
+    let redirects = procsub::expand_redirects(&cmd.redirects, state, commands);
+    check_unbound(events, block_id)?;
+
+    if state.options.xtrace {
+        let line = env_overrides
+            .iter()
+            .map(|(n, v)| format!("{}={}", n, trace_word(v)))
+            .chain(std::iter::once(&name).chain(&args).map(|w| trace_word(w)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        xtrace(state, events, block_id, line);
+    }
+
+    if name == "profile" {
+        return execute_profile(state, &args, events, commands, block_id);
+    }
+    if name == "debug" {
+        return execute_debug(state, &args, events, commands, block_id);
+    }
+    if name == "time" {
+        return execute_time(state, &args, events, commands, block_id);
+    }
+
+    let kind = if state.functions.contains_key(&name) { ProfileKind::Function } else { ProfileKind::Command };
+    let started = state.profile.as_mut().map(|profile| profile.enter(kind, &name));
+
     // Check for builtins that return structured output (listing modes)
-    if let Some(value) = builtins::try_builtin_value(&name, &args, state) {
+    let result = if let Some(value) = builtins::try_builtin_value(&name, &args, state)
+        .or_else(|| arithmetic_echo(&name, cmd, state).filter(|_| cmd.redirects.is_empty()))
+    {
         let bid = get_or_create_block_id(block_id);
         if block_id.is_none() {
             let _ = events.send(ShellEvent::CommandStarted {
                 block_id: bid,
                 command: format!("{} {}", name, args.join(" ")),
                 cwd: state.cwd.clone(),
+                container: None,
             });
         }
         state.store_output(bid, name.clone(), value.clone());
@@ -156,11 +253,10 @@ fn execute_simple(
             exit_code: 0,
             duration_ms: 0,
         });
-        return Ok(0);
+        Ok(0)
     }
-
     // Check for builtins (shell-specific: cd, export, etc.)
-    if let Some(exit_code) = builtins::try_builtin(&name, &args, state, events, commands)? {
+    else if let Some(exit_code) = builtins::try_builtin(&name, &args, state, events, commands)? {
         // Emit CommandFinished for builtins when we have a block_id
         // (This happens when the UI created the block for us)
         if let Some(id) = block_id {
@@ -170,21 +266,245 @@ fn execute_simple(
                 duration_ms: 0, // Builtins are instant
             });
         }
-        return Ok(exit_code);
+        Ok(exit_code)
     }
-
     // Check for user-defined functions
-    if let Some(func_def) = state.get_function(&name).cloned() {
-        return execute_function_call(state, &func_def, &args, events, commands, block_id);
+    else if let Some(func_def) = state.get_function(&name).cloned() {
+        execute_function_call(state, &func_def, &args, events, commands, block_id)
     }
-
     // Check for native commands (in-process: ls, cat, etc.)
-    if let Some(native_cmd) = commands.get(&name) {
-        return execute_native(state, native_cmd, &args, &cmd.redirects, events, block_id);
+    else if let Some(native_cmd) = commands.get(&name) {
+        execute_native(state, native_cmd.as_ref(), &args, &redirects, events, block_id)
     }
-
     // External command - spawn a process via PTY (legacy)
-    execute_external(state, &name, args, env_overrides, &cmd.redirects, events, block_id)
+    else {
+        execute_external(state, &name, args, env_overrides, &redirects, events, block_id)
+    };
+
+    if let (Some(profile), Some(started)) = (state.profile.as_mut(), started) {
+        profile.exit(kind, &name, started);
+    }
+    result
+}
+
+/// `echo $((expr))` outputs the number itself, so a native pipeline gets
+/// an Int rather than its text.
+fn arithmetic_echo(name: &str, cmd: &SimpleCommand, state: &ShellState) -> Option<Value> {
+    match &cmd.args[..] {
+        [word @ Word::Literal(_)] if name == "echo" => match expand::expand_word_to_value(word, state) {
+            value @ Value::Int(_) => Some(value),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// With `set -x`, show a command line about to run: as a trace in the
+/// block, or on stderr outside one.
+fn xtrace(state: &ShellState, events: &EventSender, block_id: Option<BlockId>, line: String) {
+    let depth = state.function_depth() as u32 + 1;
+    match block_id {
+        Some(block_id) => {
+            let _ = events.send(ShellEvent::Trace { block_id, depth, line });
+        }
+        None => eprintln!("{} {}", "+".repeat(depth as usize), line),
+    }
+}
+
+/// A word as `set -x` shows it: single-quoted unless it is plainly safe.
+fn trace_word(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+^".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// `debug <script> [args...]`: run a script one top-level command at a
+/// time, pausing before each until the UI steps, continues or aborts.
+fn execute_debug(
+    state: &mut ShellState,
+    args: &[String],
+    events: &EventSender,
+    commands: &CommandRegistry,
+    external_block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let block_id = get_or_create_block_id(external_block_id);
+    if external_block_id.is_none() {
+        let _ = events.send(ShellEvent::CommandStarted {
+            block_id,
+            command: format!("debug {}", args.join(" ")),
+            cwd: state.cwd.clone(),
+            container: None,
+        });
+    }
+    let start = nexus_api::Stopwatch::start();
+    let finish = |exit_code: i32, message: Option<String>| {
+        if let Some(message) = message {
+            let _ = events.send(ShellEvent::StderrChunk { block_id, data: format!("debug: {}\n", message).into_bytes() });
+        }
+        let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code, duration_ms: start.elapsed_ms() });
+        Ok(exit_code)
+    };
+
+    let Some((script, script_args)) = args.split_first() else {
+        return finish(2, Some("usage: debug <script> [args...]".to_string()));
+    };
+    let content = match std::fs::read_to_string(state.cwd.join(script)) {
+        Ok(content) => content,
+        Err(e) => return finish(1, Some(format!("{}: {}", script, e))),
+    };
+    let steps = match crate::Parser::new()?.parse_steps(&content) {
+        Ok(steps) => steps,
+        Err(e) => return finish(2, Some(format!("{}: {}", script, e))),
+    };
+
+    let old_params = std::mem::replace(&mut state.positional_params, script_args.to_vec());
+    let actions = crate::debug::register(block_id);
+    let mut pausing = true;
+    let mut last_exit = 0;
+    let mut aborted = false;
+    for (i, (source, command)) in steps.iter().enumerate() {
+        if pausing {
+            let _ = events.send(ShellEvent::DebugPaused {
+                block_id,
+                step: i as u32 + 1,
+                total: steps.len() as u32,
+                command: crate::debug::preview(source, state),
+            });
+            // A closed channel means the UI is gone; stop rather than hang.
+            match actions.recv().unwrap_or(DebugAction::Abort) {
+                DebugAction::Step => {}
+                DebugAction::Continue => pausing = false,
+                DebugAction::Abort => {
+                    aborted = true;
+                    break;
+                }
+            }
+        }
+        last_exit = execute_command(state, command, events, commands, Some(block_id))?;
+        state.last_exit_code = last_exit;
+    }
+    crate::debug::unregister(block_id);
+    state.positional_params = old_params;
+
+    if aborted {
+        return finish(130, Some("aborted".to_string()));
+    }
+    finish(last_exit, None)
+}
+
+/// `profile <command line>`: run the line with timing on. The line's own
+/// output is not shown; the block's output is the time spent in each
+/// command and function, most first.
+fn execute_profile(
+    state: &mut ShellState,
+    args: &[String],
+    events: &EventSender,
+    commands: &CommandRegistry,
+    external_block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let block_id = get_or_create_block_id(external_block_id);
+    let line = args.join(" ");
+    if external_block_id.is_none() {
+        let _ = events.send(ShellEvent::CommandStarted {
+            block_id,
+            command: format!("profile {}", line),
+            cwd: state.cwd.clone(),
+            container: None,
+        });
+    }
+    let start = nexus_api::Stopwatch::start();
+
+    let outer = state.profile.replace(Profile::default());
+    let result = crate::Parser::new()
+        .and_then(|mut parser| Ok(parser.parse(&line)?))
+        .and_then(|ast| execute(state, &ast, events, commands));
+    let profile = std::mem::replace(&mut state.profile, outer).unwrap_or_default();
+
+    let exit_code = match result {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            let _ = events.send(ShellEvent::StderrChunk {
+                block_id,
+                data: format!("profile: {}\n", e).into_bytes(),
+            });
+            1
+        }
+    };
+    let value = profile.to_table();
+    state.store_output(block_id, format!("profile {}", line), value.clone());
+    let _ = events.send(ShellEvent::CommandOutput { block_id, value });
+    let _ = events.send(ShellEvent::CommandFinished {
+        block_id,
+        exit_code,
+        duration_ms: start.elapsed_ms(),
+    });
+    Ok(exit_code)
+}
+
+/// `time <command line>`: run the line and report how long it took and
+/// what its external processes used, like bash's `time` from the CPU
+/// totals of reaped children. As with `profile`, the line's own output
+/// goes to its own blocks. In-process commands count only toward `real_ms`.
+fn execute_time(
+    state: &mut ShellState,
+    args: &[String],
+    events: &EventSender,
+    commands: &CommandRegistry,
+    external_block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let block_id = get_or_create_block_id(external_block_id);
+    // The words are already expanded; quote them so they parse back as is.
+    let line = args.iter().map(|arg| trace_word(arg)).collect::<Vec<_>>().join(" ");
+    if external_block_id.is_none() {
+        let _ = events.send(ShellEvent::CommandStarted {
+            block_id,
+            command: format!("time {}", line),
+            cwd: state.cwd.clone(),
+            container: None,
+        });
+    }
+    let start = nexus_api::Stopwatch::start();
+    let before = process::usage::children();
+
+    let result = crate::Parser::new()
+        .and_then(|mut parser| Ok(parser.parse(&line)?))
+        .and_then(|ast| execute(state, &ast, events, commands));
+    let after = process::usage::children();
+
+    let exit_code = match result {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            let _ = events.send(ShellEvent::StderrChunk {
+                block_id,
+                data: format!("time: {}\n", e).into_bytes(),
+            });
+            1
+        }
+    };
+    let int = |n: u64| Value::Int(n.min(i64::MAX as u64) as i64);
+    // The children's peak only says something about this line if it grew.
+    let max_rss = if after.max_rss > before.max_rss { int(after.max_rss) } else { Value::Unit };
+    let value = Value::Record(vec![
+        ("real_ms".to_string(), int(start.elapsed_ms())),
+        ("user_ms".to_string(), int(after.user_ms.saturating_sub(before.user_ms))),
+        ("sys_ms".to_string(), int(after.sys_ms.saturating_sub(before.sys_ms))),
+        ("max_rss".to_string(), max_rss),
+        ("blocks_in".to_string(), int(after.blocks_in.saturating_sub(before.blocks_in))),
+        ("blocks_out".to_string(), int(after.blocks_out.saturating_sub(before.blocks_out))),
+        ("exit".to_string(), Value::Int(exit_code as i64)),
+    ]);
+    state.store_output(block_id, format!("time {}", line), value.clone());
+    let _ = events.send(ShellEvent::CommandOutput { block_id, value });
+    let _ = events.send(ShellEvent::CommandFinished {
+        block_id,
+        exit_code,
+        duration_ms: start.elapsed_ms(),
+    });
+    Ok(exit_code)
 }
 
 /// Execute a native (in-process) command.
@@ -193,7 +513,7 @@ fn execute_native(
     cmd: &dyn crate::commands::NexusCommand,
     args: &[String],
     redirects: &[Redirect],
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     external_block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
     let block_id = get_or_create_block_id(external_block_id);
@@ -205,10 +525,21 @@ fn execute_native(
             block_id,
             command: format!("{} {}", cmd.name(), args.join(" ")),
             cwd: state.cwd.clone(),
+            container: None,
         });
     }
 
-    let start = std::time::Instant::now();
+    let start = nexus_api::Stopwatch::start();
+
+    let mut args = args.to_vec();
+    let output = match OutputMode::take_flag_for(cmd, &mut args).map(Ok).unwrap_or_else(|| OutputMode::from_env(state)) {
+        Ok(output) => output,
+        Err(error) => {
+            send_command_error(events, block_id, Some(error));
+            let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code: 1, duration_ms: start.elapsed_ms() });
+            return Ok(1);
+        }
+    };
 
     // Check for stdout redirect (fd 1)
     let stdout_redirect = redirects.iter().find(|r| r.fd == 1);
@@ -222,9 +553,12 @@ fn execute_native(
 
     // Check for stdin redirect (fd 0, Read)
     let stdin_redirect = redirects.iter().find(|r| r.fd == 0 && r.op == RedirectOp::Read);
+    let here_doc = redirects.iter().filter(|r| r.fd == 0).find_map(|r| expand::here_doc_input(r, state));
 
-    // Handle input redirection: read file content as stdin
-    let stdin_value = if let Some(redirect) = stdin_redirect {
+    // Handle input redirection: a here-document's text, or file content, as stdin
+    let stdin_value = if let Some(text) = here_doc {
+        Some(Value::String(text))
+    } else if let Some(redirect) = stdin_redirect {
         let target_path = expand::expand_tilde(&redirect.target, state);
         let resolved = if std::path::Path::new(&target_path).is_absolute() {
             std::path::PathBuf::from(&target_path)
@@ -235,14 +569,11 @@ fn execute_native(
         match std::fs::read_to_string(&resolved) {
             Ok(content) => Some(Value::String(content)),
             Err(e) => {
-                let _ = events.send(ShellEvent::StderrChunk {
-                    block_id,
-                    data: format!("{}: {}: {}\n", cmd.name(), target_path, e).into_bytes(),
-                });
+                send_command_error(events, block_id, Some(CommandError::io(cmd.name(), &target_path, &e)));
                 let _ = events.send(ShellEvent::CommandFinished {
                     block_id,
                     exit_code: 1,
-                    duration_ms: start.elapsed().as_millis() as u64,
+                    duration_ms: start.elapsed_ms(),
                 });
                 return Ok(1);
             }
@@ -257,17 +588,18 @@ fn execute_native(
         events,
         block_id,
         stdin: stdin_value,
+        output,
     };
 
     // Register cancel flag so the UI can cancel this command
     register_cancel(block_id);
 
     // Execute the command
-    let result = cmd.execute(args, &mut ctx);
+    let result = cmd.execute(&args, &mut ctx);
 
     unregister_cancel(block_id);
 
-    let duration_ms = start.elapsed().as_millis() as u64;
+    let duration_ms = start.elapsed_ms();
 
     let command_str = format!("{} {}", cmd.name(), args.join(" "));
 
@@ -277,6 +609,7 @@ fn execute_native(
             if !matches!(value, Value::Unit) {
                 ctx.state.store_output(block_id, command_str.clone(), value.clone());
             }
+            let value = output.render(value);
 
             // Handle stdout redirect if present
             if let Some(redirect) = stdout_redirect {
@@ -291,10 +624,8 @@ fn execute_native(
                         .open(&target_path),
                     _ => {
                         // Unsupported redirect op for stdout
-                        let _ = events.send(ShellEvent::StderrChunk {
-                            block_id,
-                            data: format!("Unsupported redirect operator\n").into_bytes(),
-                        });
+                        let error = CommandError::new(cmd.name(), CommandErrorKind::Unsupported, "unsupported redirect operator");
+                        send_command_error(events, block_id, Some(error));
                         let _ = events.send(ShellEvent::CommandFinished {
                             block_id,
                             exit_code: 1,
@@ -307,10 +638,7 @@ fn execute_native(
                 match file_result {
                     Ok(mut file) => {
                         if let Err(e) = writeln!(file, "{}", text) {
-                            let _ = events.send(ShellEvent::StderrChunk {
-                                block_id,
-                                data: format!("{}: {}\n", cmd.name(), e).into_bytes(),
-                            });
+                            send_command_error(events, block_id, Some(CommandError::io(cmd.name(), &target_path, &e)));
                             let _ = events.send(ShellEvent::CommandFinished {
                                 block_id,
                                 exit_code: 1,
@@ -320,10 +648,7 @@ fn execute_native(
                         }
                     }
                     Err(e) => {
-                        let _ = events.send(ShellEvent::StderrChunk {
-                            block_id,
-                            data: format!("{}: {}: {}\n", cmd.name(), target_path, e).into_bytes(),
-                        });
+                        send_command_error(events, block_id, Some(CommandError::io(cmd.name(), &target_path, &e)));
                         let _ = events.send(ShellEvent::CommandFinished {
                             block_id,
                             exit_code: 1,
@@ -350,7 +675,11 @@ fn execute_native(
             Ok(0)
         }
         Err(e) => {
-            let error_msg = format!("{}: {}\n", cmd.name(), e);
+            let mut error = crate::error::command_error(cmd.name(), &e);
+            if let Some(error) = &mut error {
+                crate::error::add_path_suggestion(error, &ctx.state.cwd);
+            }
+            let error_msg = error.as_ref().map(|e| format!("{}\n", e)).unwrap_or_default();
 
             // Handle stderr redirect
             // Priority: 2>&1 (stderr to stdout destination) > 2>file > default (UI)
@@ -369,10 +698,7 @@ fn execute_native(
                             .append(true)
                             .open(&target_path),
                         _ => {
-                            let _ = events.send(ShellEvent::StderrChunk {
-                                block_id,
-                                data: error_msg.into_bytes(),
-                            });
+                            send_command_error(events, block_id, error);
                             let _ = events.send(ShellEvent::CommandFinished {
                                 block_id,
                                 exit_code: 1,
@@ -386,10 +712,7 @@ fn execute_native(
                     }
                 } else {
                     // stdout goes to UI, so stderr also goes to UI
-                    let _ = events.send(ShellEvent::StderrChunk {
-                        block_id,
-                        data: error_msg.into_bytes(),
-                    });
+                    send_command_error(events, block_id, error);
                 }
             } else if let Some(redirect) = stderr_redirect {
                 // Direct stderr redirect (2>file)
@@ -403,10 +726,7 @@ fn execute_native(
                         .open(&target_path),
                     _ => {
                         // Unsupported redirect op for stderr - emit to UI
-                        let _ = events.send(ShellEvent::StderrChunk {
-                            block_id,
-                            data: error_msg.into_bytes(),
-                        });
+                        send_command_error(events, block_id, error);
                         let _ = events.send(ShellEvent::CommandFinished {
                             block_id,
                             exit_code: 1,
@@ -422,10 +742,7 @@ fn execute_native(
                 // If file open failed, silently ignore (like shell behavior)
             } else {
                 // No redirect - emit error to UI
-                let _ = events.send(ShellEvent::StderrChunk {
-                    block_id,
-                    data: error_msg.into_bytes(),
-                });
+                send_command_error(events, block_id, error);
             }
 
             let _ = events.send(ShellEvent::CommandFinished {
@@ -439,6 +756,13 @@ fn execute_native(
     }
 }
 
+/// Report a native command failure to the UI.
+fn send_command_error(events: &EventSender, block_id: BlockId, error: Option<CommandError>) {
+    if let Some(error) = error {
+        let _ = events.send(ShellEvent::CommandError { block_id, error });
+    }
+}
+
 /// Execute an external command via PTY (legacy path).
 fn execute_external(
     state: &mut ShellState,
@@ -446,7 +770,7 @@ fn execute_external(
     args: Vec<String>,
     env_overrides: Vec<(String, String)>,
     redirects: &[Redirect],
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     external_block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
     let block_id = get_or_create_block_id(external_block_id);
@@ -457,9 +781,27 @@ fn execute_external(
             block_id,
             command: format!("{} {}", name, args.join(" ")),
             cwd: state.cwd.clone(),
+            container: None,
         });
     }
 
+    // Nobody can scroll or quit a pager in a block, so keep the command
+    // from starting one, and ask for color if its stdout will be a pipe.
+    // Assignments on the command line still win.
+    let pager = state.config.pager_mode(name);
+    let paged = pager == PagerMode::Capture && redirects.is_empty();
+    let sink = if paged { color::Sink::Block } else { color::Sink::Terminal };
+    let env_overrides: Vec<(String, String)> = pager
+        .env()
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .chain(color::env(state.config.color_mode(name), sink, &state.env))
+        .chain(env_overrides)
+        .collect();
+    if paged {
+        return execute_external_paged(state, name, &args, &env_overrides, events, block_id);
+    }
+
     // Build the full argv
     let mut argv = vec![name.to_string()];
     argv.extend(args);
@@ -468,7 +810,8 @@ fn execute_external(
     register_cancel(block_id);
 
     // Spawn the process
-    let handle = process::spawn(&argv, &state.cwd, &state.env, &env_overrides, redirects)?;
+    let redirects = expand::expand_here_docs(redirects, state);
+    let handle = process::spawn(&argv, &state.cwd, &state.env, &env_overrides, &redirects)?;
 
     // Wait for completion and stream output
     let exit_code = process::wait_with_events(handle, block_id, events)?;
@@ -478,14 +821,72 @@ fn execute_external(
     Ok(exit_code)
 }
 
+/// Run an external command with stdout collected into the pager viewer
+/// (`[pager]` mode `capture`). Stderr still streams into the block.
+fn execute_external_paged(
+    state: &mut ShellState,
+    name: &str,
+    args: &[String],
+    env_overrides: &[(String, String)],
+    events: &EventSender,
+    block_id: BlockId,
+) -> anyhow::Result<i32> {
+    let start = nexus_api::Stopwatch::start();
+    let output = std::process::Command::new(name)
+        .args(args)
+        .current_dir(&state.cwd)
+        .env_clear()
+        .envs(&state.env)
+        .envs(env_overrides.iter().map(|(k, v)| (k, v)))
+        .stdin(std::process::Stdio::null())
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            let _ = events.send(ShellEvent::StderrChunk {
+                block_id,
+                data: format!("{}: {}\n", name, e).into_bytes(),
+            });
+            let exit_code = if e.kind() == std::io::ErrorKind::NotFound { 127 } else { 126 };
+            let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code, duration_ms: start.elapsed_ms() });
+            return Ok(exit_code);
+        }
+    };
+
+    if !output.stderr.is_empty() {
+        let _ = events.send(ShellEvent::StderrChunk { block_id, data: output.stderr });
+    }
+    if !output.stdout.is_empty() {
+        let text = String::from_utf8_lossy(&output.stdout).into_owned();
+        // `_` gets the text, so the output can be piped on like any other.
+        let command = std::iter::once(name).chain(args.iter().map(String::as_str)).collect::<Vec<_>>().join(" ");
+        state.store_output(block_id, command, Value::String(text.clone()));
+        let value = Value::interactive(InteractiveRequest {
+            viewer: ViewerKind::Pager,
+            content: Value::String(text),
+        });
+        let _ = events.send(ShellEvent::CommandOutput { block_id, value });
+    }
+    let exit_code = output.status.code().unwrap_or(1);
+    let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code, duration_ms: start.elapsed_ms() });
+    Ok(exit_code)
+}
+
 /// Execute a pipeline.
 fn execute_pipeline(
     state: &mut ShellState,
     pipeline: &Pipeline,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     external_block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
+    let _substitutions = procsub::Scope::enter();
+    if pipeline.background {
+        return match pipeline.commands.as_slice() {
+            [cmd] => execute_background(state, cmd, events, commands, external_block_id),
+            _ => refuse_background(events, external_block_id, "", "pipelines"),
+        };
+    }
     if pipeline.commands.len() == 1 {
         return execute_command(state, &pipeline.commands[0], events, commands, external_block_id);
     }
@@ -499,23 +900,52 @@ fn execute_pipeline(
         }
     });
 
-    if has_native {
-        // Use native pipeline execution for mixed or all-native pipelines
+    if has_native || !pipeline.typed.is_empty() {
+        // Use native pipeline execution for mixed or all-native pipelines,
+        // and for `|>`, which reports bad stages there
         execute_native_pipeline(state, pipeline, events, commands, external_block_id)
     } else {
         // All external - use legacy path
         let block_id = get_or_create_block_id(external_block_id);
 
+        // Stages are expanded as they are spawned, so the trace shows them
+        // as written.
+        if state.options.xtrace {
+            xtrace(state, events, Some(block_id), pipeline_display_string(pipeline));
+        }
+
         if external_block_id.is_none() {
             let _ = events.send(ShellEvent::CommandStarted {
                 block_id,
                 command: "[pipeline]".to_string(),
                 cwd: state.cwd.clone(),
+                container: None,
             });
         }
 
-        let handles = process::spawn_pipeline(state, &pipeline.commands)?;
-        let exit_code = process::wait_pipeline(handles, block_id, events)?;
+        // Here-documents and process substitutions are expanded here; the
+        // children only write them out or open their paths.
+        let stages: Vec<Command> = pipeline
+            .commands
+            .iter()
+            .map(|cmd| match cmd {
+                Command::Simple(simple) => Command::Simple(SimpleCommand {
+                    args: simple
+                        .args
+                        .iter()
+                        .map(|word| match word {
+                            Word::ProcessSubstitution(source) => Word::Literal(procsub::open(source, state, commands)),
+                            other => other.clone(),
+                        })
+                        .collect(),
+                    redirects: expand::expand_here_docs(&procsub::expand_redirects(&simple.redirects, state, commands), state),
+                    ..simple.clone()
+                }),
+                other => other.clone(),
+            })
+            .collect();
+        let handles = process::spawn_pipeline(state, &stages)?;
+        let exit_code = process::wait_pipeline(handles, block_id, events, state.options.pipefail)?;
 
         Ok(exit_code)
     }
@@ -530,7 +960,7 @@ fn execute_pipeline(
 fn execute_native_pipeline(
     state: &mut ShellState,
     pipeline: &Pipeline,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     external_block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -539,23 +969,7 @@ fn execute_native_pipeline(
     let block_id = get_or_create_block_id(external_block_id);
 
     // Build command string for display
-    let cmd_str = pipeline
-        .commands
-        .iter()
-        .filter_map(|cmd| {
-            if let Command::Simple(s) = cmd {
-                let args_str = s.args.iter().filter_map(|w| w.as_literal()).collect::<Vec<_>>().join(" ");
-                if args_str.is_empty() {
-                    Some(s.name.clone())
-                } else {
-                    Some(format!("{} {}", s.name, args_str))
-                }
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" | ");
+    let cmd_str = pipeline_display_string(pipeline);
 
     // Only emit CommandStarted if we created a new block_id
     if external_block_id.is_none() {
@@ -563,12 +977,22 @@ fn execute_native_pipeline(
             block_id,
             command: cmd_str,
             cwd: state.cwd.clone(),
+            container: None,
         });
     }
 
-    let start = std::time::Instant::now();
+    let start = nexus_api::Stopwatch::start();
+    if let Some((name, message)) = typed_stage_error(pipeline, commands) {
+        send_command_error(events, block_id, crate::error::command_error(&name, &anyhow::anyhow!(message)));
+        let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code: 1, duration_ms: start.elapsed_ms() });
+        return Ok(1);
+    }
+
     let mut current_value: Option<Value> = None;
     let mut last_exit = 0;
+    let mut last_failure = 0;
+
+    let mut final_output = OutputMode::Value;
 
     for cmd in &pipeline.commands {
         let Command::Simple(simple) = cmd else {
@@ -577,23 +1001,46 @@ fn execute_native_pipeline(
 
         // Expand command name and args (with glob expansion)
         let name = expand::expand_word_to_string(&Word::Literal(simple.name.clone()), state);
-        let args: Vec<String> = simple
+        let mut args: Vec<String> = simple
             .args
             .iter()
             .flat_map(|w| expand::expand_word_to_strings(w, state))
             .collect();
+        check_unbound(events, Some(block_id))?;
+
+        if state.options.xtrace {
+            let line = std::iter::once(&name).chain(&args).map(|w| trace_word(w)).collect::<Vec<_>>().join(" ");
+            xtrace(state, events, Some(block_id), line);
+        }
+        let started = state.profile.as_mut().map(|profile| profile.enter(ProfileKind::Command, &name));
+
+        // A here-document replaces whatever the previous stage produced.
+        if let Some(text) = simple.redirects.iter().filter(|r| r.fd == 0).find_map(|r| expand::here_doc_input(r, state)) {
+            current_value = Some(Value::String(text));
+        }
 
         if let Some(native_cmd) = commands.get(&name) {
+            let typed = arithmetic_echo(&name, simple, state);
+            // `--output` converts its own stage; NEXUS_OUTPUT only what
+            // leaves the pipeline, after `$_` has kept the value.
+            let explicit = OutputMode::take_flag_for(native_cmd.as_ref(), &mut args);
+            let output = explicit.unwrap_or_default();
+            final_output = match explicit {
+                Some(_) => OutputMode::Value,
+                None => OutputMode::from_env(state).unwrap_or_default(),
+            };
             // Native command: pass Value via ctx.stdin
             let mut ctx = CommandContext {
                 state,
                 events,
                 block_id,
                 stdin: current_value.take(),
+                output,
             };
 
             match native_cmd.execute(&args, &mut ctx) {
                 Ok(value) => {
+                    let value = output.render(typed.unwrap_or(value));
                     // Don't pass Unit values down the pipeline
                     current_value = if matches!(value, Value::Unit) {
                         None
@@ -603,14 +1050,11 @@ fn execute_native_pipeline(
                     last_exit = 0;
                 }
                 Err(e) => {
-                    let _ = events.send(ShellEvent::StderrChunk {
-                        block_id,
-                        data: format!("{}: {}\n", name, e).into_bytes(),
-                    });
+                    send_command_error(events, block_id, crate::error::command_error(&name, &e));
                     let _ = events.send(ShellEvent::CommandFinished {
                         block_id,
                         exit_code: 1,
-                        duration_ms: start.elapsed().as_millis() as u64,
+                        duration_ms: start.elapsed_ms(),
                     });
                     return Ok(1);
                 }
@@ -628,26 +1072,17 @@ fn execute_native_pipeline(
             )?;
             current_value = None; // External commands produce bytes, not Value
         }
+        if last_exit != 0 {
+            last_failure = last_exit;
+        }
+
+        if let (Some(profile), Some(started)) = (state.profile.as_mut(), started) {
+            profile.exit(ProfileKind::Command, &name, started);
+        }
     }
 
     // Build command string for storage
-    let cmd_str = pipeline
-        .commands
-        .iter()
-        .filter_map(|cmd| {
-            if let Command::Simple(s) = cmd {
-                let args_str = s.args.iter().filter_map(|w| w.as_literal()).collect::<Vec<_>>().join(" ");
-                if args_str.is_empty() {
-                    Some(s.name.clone())
-                } else {
-                    Some(format!("{} {}", s.name, args_str))
-                }
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" | ");
+    let cmd_str = pipeline_display_string(pipeline);
 
     // Emit final output if we have a value from a native command
     if let Some(ref value) = current_value {
@@ -656,14 +1091,17 @@ fn execute_native_pipeline(
 
         let _ = events.send(ShellEvent::CommandOutput {
             block_id,
-            value: value.clone(),
+            value: final_output.render(value.clone()),
         });
     }
 
+    if state.options.pipefail {
+        last_exit = last_failure;
+    }
     let _ = events.send(ShellEvent::CommandFinished {
         block_id,
         exit_code: last_exit,
-        duration_ms: start.elapsed().as_millis() as u64,
+        duration_ms: start.elapsed_ms(),
     });
 
     Ok(last_exit)
@@ -673,7 +1111,7 @@ fn execute_native_pipeline(
 fn execute_list(
     state: &mut ShellState,
     list: &List,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -698,9 +1136,11 @@ fn execute_list(
                 .map(|op| *op == ListOperator::Background)
                 .unwrap_or(false);
 
+            let tested = matches!(list.operators.get(i), Some(ListOperator::And | ListOperator::Or));
             if is_background {
-                // TODO: Spawn in background
-                last_exit = execute_command(state, cmd, events, commands, block_id)?;
+                last_exit = execute_background(state, cmd, events, commands, block_id)?;
+            } else if tested {
+                last_exit = execute_condition(state, cmd, events, commands, block_id)?;
             } else {
                 last_exit = execute_command(state, cmd, events, commands, block_id)?;
             }
@@ -710,11 +1150,88 @@ fn execute_list(
     Ok(last_exit)
 }
 
+/// Start a command after `&` as a job. An external command is spawned and
+/// left running, and so is a native command with an executable of the same
+/// name on PATH (`sleep 60 &`). Only a process can be a job, so anything
+/// else (builtins, functions, compound commands, multi-stage pipelines) is
+/// refused rather than run in the foreground.
+fn execute_background(
+    state: &mut ShellState,
+    cmd: &Command,
+    events: &EventSender,
+    commands: &CommandRegistry,
+    external_block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let simple = match cmd {
+        Command::Simple(simple) => simple,
+        Command::Pipeline(pipeline) if pipeline.commands.len() == 1 => {
+            return execute_background(state, &pipeline.commands[0], events, commands, external_block_id);
+        }
+        Command::Pipeline(_) => return refuse_background(events, external_block_id, "", "pipelines"),
+        _ => return refuse_background(events, external_block_id, "", "compound commands"),
+    };
+    let (name, args, env_overrides) = expand_simple(state, simple);
+    let refused = if builtins::is_builtin(&name) {
+        Some("builtins")
+    } else if state.functions.contains_key(&name) {
+        Some("functions")
+    } else if commands.contains(&name) && builtins::find_in_path(&name, state).is_none() {
+        Some("native commands")
+    } else {
+        None
+    };
+    if let Some(what) = refused {
+        return refuse_background(events, external_block_id, &name, what);
+    }
+
+    let block_id = get_or_create_block_id(external_block_id);
+    let command = std::iter::once(&name).chain(&args).cloned().collect::<Vec<_>>().join(" ");
+    if external_block_id.is_none() {
+        let _ = events.send(ShellEvent::CommandStarted { block_id, command: command.clone(), cwd: state.cwd.clone(), container: None });
+    }
+    if state.options.xtrace {
+        xtrace(state, events, Some(block_id), format!("{} &", command));
+    }
+
+    let argv: Vec<String> = std::iter::once(name).chain(args).collect();
+    let redirects = expand::expand_here_docs(&procsub::expand_redirects(&simple.redirects, state, commands), state);
+    let pid = process::spawn_background(&argv, &state.cwd, &state.env, &env_overrides, &redirects, block_id, events)?;
+    let job = state.add_job(pid, command);
+    process::emit_job_state(events, job);
+    let job_id = job.id;
+
+    let _ = events.send(ShellEvent::StderrChunk {
+        block_id,
+        data: format!("[{}] {}\n", job_id, pid).into_bytes(),
+    });
+    let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code: 0, duration_ms: 0 });
+    Ok(0)
+}
+
+/// Report that `what` can't follow `&`, without running it.
+fn refuse_background(
+    events: &EventSender,
+    external_block_id: Option<BlockId>,
+    name: &str,
+    what: &str,
+) -> anyhow::Result<i32> {
+    let block_id = get_or_create_block_id(external_block_id);
+    let error = CommandError::new(
+        name,
+        CommandErrorKind::Unsupported,
+        format!("{} can't run in the background; only external commands can be jobs", what),
+    )
+    .with_suggestion("run it in a child shell: sh -c '…' &");
+    send_command_error(events, block_id, Some(error));
+    let _ = events.send(ShellEvent::CommandFinished { block_id, exit_code: 1, duration_ms: 0 });
+    Ok(1)
+}
+
 /// Execute a subshell.
 fn execute_subshell(
     state: &mut ShellState,
     subshell: &Subshell,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -729,6 +1246,9 @@ fn execute_subshell(
     for cmd in &subshell.commands {
         last_exit = execute_command(&mut subshell_state, cmd, events, commands, block_id)?;
     }
+    subshell_state.last_exit_code = last_exit;
+    crate::traps::run(&mut subshell_state, crate::traps::EXIT, events, commands, block_id)?;
+    crate::traps::release(&subshell_state);
 
     Ok(last_exit)
 }
@@ -737,58 +1257,164 @@ fn execute_subshell(
 fn execute_assignment(
     state: &mut ShellState,
     assignment: &Assignment,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
-    _block_id: Option<BlockId>,
+    block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
-    // Check if the RHS is a command substitution - if so, execute it through our
-    // evaluator to capture rich Value output (Mathematica-style)
-    if let Word::CommandSubstitution(cmd_str) = &assignment.value {
-        // Parse the inner command
-        let inner = cmd_str
-            .trim_start_matches("$(")
-            .trim_end_matches(')')
-            .trim_start_matches('`')
-            .trim_end_matches('`');
-
-        // Parse and execute through our evaluator
-        if let Ok(mut parser) = crate::parser::Parser::new() {
-            if let Ok(ast) = parser.parse(inner) {
-                // Execute the command - this will store output in state.last_output
-                let exit_code = execute(state, &ast, events, commands)?;
-
-                // Get the captured output value
-                if let Some(value) = state.get_last_output().cloned() {
-                    state.set_var_value(assignment.name.clone(), value);
-                } else {
-                    // No output - store empty string
-                    state.set_var_value(assignment.name.clone(), Value::String(String::new()));
-                }
-
-                return Ok(exit_code);
-            }
-        }
-        // If parsing fails, fall through to string expansion
+    // A command substitution keeps the inner command's rich value when it
+    // has one (Mathematica-style)
+    if let Word::CommandSubstitution(source) = &assignment.value {
+        let output = substitute(source, state, commands);
+        let value = output.value.unwrap_or(Value::String(output.text));
+        trace_assignment(state, events, block_id, &assignment.name, &value);
+        state.set_var_value(assignment.name.clone(), value);
+        return Ok(output.exit_code);
     }
 
     // Use Value-based expansion to preserve rich types for other word types
     let value = expand::expand_word_to_value(&assignment.value, state);
+    check_unbound(events, block_id)?;
+    trace_assignment(state, events, block_id, &assignment.name, &value);
     state.set_var_value(assignment.name.clone(), value);
     Ok(0)
 }
 
+/// What a command substitution produced.
+struct Substitution {
+    exit_code: i32,
+    /// The inner command's value, when it ran in-process and output exactly
+    /// one.
+    value: Option<Value>,
+    /// Its output as text, trailing newlines removed.
+    text: String,
+}
+
+/// Builtins that print nothing to the process's stdout, and so can run
+/// in-process inside a substitution.
+const QUIET_BUILTINS: &[&str] = &[
+    "cd", "unset", ":", "test", "[", "[[", "shift", "return", "break", "continue", "local",
+];
+
+/// Run a command substitution, `$(cmd)` or `` `cmd` ``. When every command
+/// in it is native, a function or a quiet builtin, it runs through this
+/// evaluator on a fork of `state` and its output is collected from the
+/// events; otherwise it runs under `sh` in the session's cwd and
+/// environment.
+fn substitute(source: &str, state: &ShellState, commands: &CommandRegistry) -> Substitution {
+    let inner = source
+        .strip_prefix("$(")
+        .and_then(|s| s.strip_suffix(')'))
+        .or_else(|| source.strip_prefix('`').and_then(|s| s.strip_suffix('`')))
+        .unwrap_or(source);
+
+    if let Some(ast) = in_process_ast(inner, state, commands) {
+        return capture(&ast, state, commands);
+    }
+
+    match std::process::Command::new("sh")
+        .arg("-c")
+        .arg(inner)
+        .current_dir(&state.cwd)
+        .env_clear()
+        .envs(&state.env)
+        .output()
+    {
+        Ok(output) => Substitution {
+            exit_code: output.status.code().unwrap_or(1),
+            value: None,
+            text: String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string(),
+        },
+        Err(_) => Substitution { exit_code: 127, value: None, text: String::new() },
+    }
+}
+
+/// `source` parsed, when every command in it can run in-process.
+fn in_process_ast(source: &str, state: &ShellState, commands: &CommandRegistry) -> Option<Ast> {
+    let ast = crate::parser::Parser::new().ok()?.parse(source).ok()?;
+    ast.commands.iter().all(|cmd| runs_in_process(cmd, state, commands)).then_some(ast)
+}
+
+/// Run `ast` on a fork of `state`, collecting its output from the events.
+fn capture(ast: &Ast, state: &ShellState, commands: &CommandRegistry) -> Substitution {
+    let mut fork = state.fork();
+    let (events, mut rx) = EventSender::channel(4096);
+    let exit_code = execute(&mut fork, ast, &events, commands).unwrap_or(1);
+
+    let mut values = Vec::new();
+    let mut text = String::new();
+    let mut streamed = false;
+    loop {
+        match rx.try_recv() {
+            Ok(ShellEvent::CommandOutput { value, .. }) if !matches!(value, Value::Unit) => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&value.to_text());
+                values.push(value);
+            }
+            Ok(ShellEvent::StdoutChunk { data, .. }) => {
+                streamed = true;
+                text.push_str(&String::from_utf8_lossy(&data));
+            }
+            Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+            Err(_) => break,
+        }
+    }
+    let value = match (values.len(), streamed) {
+        (1, false) => values.pop(),
+        _ => None,
+    };
+    Substitution { exit_code, value, text: text.trim_end_matches('\n').to_string() }
+}
+
+/// Whether `command` can run through the evaluator with its output caught
+/// from events rather than written to the terminal.
+fn runs_in_process(command: &Command, state: &ShellState, commands: &CommandRegistry) -> bool {
+    let all = |cmds: &[Command]| cmds.iter().all(|cmd| runs_in_process(cmd, state, commands));
+    match command {
+        Command::Simple(simple) => {
+            let name = simple.name.as_str();
+            state.alias(name).is_none()
+                && (QUIET_BUILTINS.contains(&name)
+                    || state.functions.contains_key(name)
+                    || (!builtins::is_builtin(name) && commands.get(name).is_some()))
+        }
+        Command::Pipeline(pipeline) => !pipeline.background && all(&pipeline.commands),
+        Command::List(list) => all(&list.items),
+        Command::Subshell(subshell) => all(&subshell.commands),
+        Command::Assignment(_) | Command::Function(_) => true,
+        Command::If(if_stmt) => {
+            all(&if_stmt.condition)
+                && all(&if_stmt.then_branch)
+                && if_stmt.else_branch.as_deref().is_none_or(all)
+        }
+        Command::While(while_stmt) => all(&while_stmt.condition) && all(&while_stmt.body),
+        Command::For(for_stmt) => all(&for_stmt.body),
+        Command::ArithmeticFor(for_stmt) => all(&for_stmt.body),
+        Command::Arithmetic(_) => true,
+        Command::Case(case_stmt) => case_stmt.cases.iter().all(|item| all(&item.commands)),
+        Command::Watch(_) => false,
+    }
+}
+
+fn trace_assignment(state: &ShellState, events: &EventSender, block_id: Option<BlockId>, name: &str, value: &Value) {
+    if state.options.xtrace {
+        xtrace(state, events, block_id, format!("{}={}", name, trace_word(&value.to_text())));
+    }
+}
+
 /// Execute an if statement.
 fn execute_if(
     state: &mut ShellState,
     if_stmt: &IfStatement,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
     // Execute condition
     let mut condition_exit = 0;
     for cmd in &if_stmt.condition {
-        condition_exit = execute_command(state, cmd, events, commands, block_id)?;
+        condition_exit = execute_condition(state, cmd, events, commands, block_id)?;
     }
 
     if condition_exit == 0 {
@@ -814,7 +1440,7 @@ fn execute_if(
 fn execute_while(
     state: &mut ShellState,
     while_stmt: &WhileStatement,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -824,7 +1450,7 @@ fn execute_while(
         // Execute condition
         let mut condition_exit = 0;
         for cmd in &while_stmt.condition {
-            condition_exit = execute_command(state, cmd, events, commands, block_id)?;
+            condition_exit = execute_condition(state, cmd, events, commands, block_id)?;
         }
 
         if condition_exit != 0 {
@@ -866,7 +1492,7 @@ fn execute_while(
 fn execute_for(
     state: &mut ShellState,
     for_stmt: &ForStatement,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -912,6 +1538,96 @@ fn execute_for(
     Ok(last_exit)
 }
 
+/// Run an arithmetic command, `((expr))`: exit 0 when `expr` is non-zero,
+/// 1 when it's zero or fails to evaluate.
+fn execute_arithmetic(state: &mut ShellState, expr: &str, events: &EventSender, block_id: Option<BlockId>) -> i32 {
+    match arithmetic_step(state, expr, events, block_id) {
+        Some(value) => (value == 0) as i32,
+        None => 1,
+    }
+}
+
+/// Evaluate `expr` and store its assignments, tracing it under `set -x`.
+/// `None` after reporting an error.
+fn arithmetic_step(state: &mut ShellState, expr: &str, events: &EventSender, block_id: Option<BlockId>) -> Option<i64> {
+    if state.options.xtrace {
+        xtrace(state, events, block_id, format!("(( {} ))", expr.trim()));
+    }
+    arith::run(expr, state)
+        .map_err(|e| shell_error(events, block_id, format!("(({})): {}", expr, e)))
+        .ok()
+}
+
+/// Execute a C-style for loop.
+fn execute_arithmetic_for(
+    state: &mut ShellState,
+    for_stmt: &ArithmeticForStatement,
+    events: &EventSender,
+    commands: &CommandRegistry,
+    block_id: Option<BlockId>,
+) -> anyhow::Result<i32> {
+    let mut last_exit = 0;
+
+    // An expression that fails to evaluate ends the loop
+    if !for_stmt.init.is_empty() && arithmetic_step(state, &for_stmt.init, events, block_id).is_none() {
+        return Ok(1);
+    }
+
+    'outer: loop {
+        // An empty condition is true
+        if !for_stmt.condition.is_empty() {
+            match arithmetic_step(state, &for_stmt.condition, events, block_id) {
+                Some(0) => break,
+                Some(_) => {}
+                None => return Ok(1),
+            }
+        }
+
+        for cmd in &for_stmt.body {
+            last_exit = execute_command(state, cmd, events, commands, block_id)?;
+
+            // Handle break
+            if let Some(level) = is_break(last_exit) {
+                if level == 1 {
+                    last_exit = 0;
+                    break 'outer;
+                } else {
+                    // Propagate break to outer loop
+                    return Ok(decrement_level(last_exit));
+                }
+            }
+
+            // Handle continue
+            if let Some(level) = is_continue(last_exit) {
+                if level == 1 {
+                    last_exit = 0;
+                    break;
+                } else {
+                    // Propagate continue to outer loop
+                    return Ok(decrement_level(last_exit));
+                }
+            }
+        }
+
+        if !for_stmt.update.is_empty() && arithmetic_step(state, &for_stmt.update, events, block_id).is_none() {
+            return Ok(1);
+        }
+    }
+
+    Ok(last_exit)
+}
+
+/// Report an error from the shell itself: into the block when there is
+/// one, otherwise on stderr.
+fn shell_error(events: &EventSender, block_id: Option<BlockId>, message: String) {
+    match block_id {
+        Some(block_id) => {
+            let _ = events.send(ShellEvent::StderrChunk { block_id, data: format!("nexus: {}\n", message).into_bytes() });
+        }
+        None => eprintln!("nexus: {}", message),
+    }
+}
+
 /// Define a function (store it in state).
 fn execute_function_def(state: &mut ShellState, func_def: &FunctionDef) -> anyhow::Result<i32> {
     state.define_function(func_def.name.clone(), func_def.clone());
@@ -923,7 +1639,7 @@ fn execute_function_call(
     state: &mut ShellState,
     func_def: &FunctionDef,
     args: &[String],
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -936,16 +1652,20 @@ fn execute_function_call(
     // Enter a new local scope
     state.push_scope();
 
-    let mut last_exit = 0;
+    let mut result = Ok(0);
 
     // Execute function body
     for cmd in &func_def.body {
-        last_exit = execute_command(state, cmd, events, commands, block_id)?;
-
-        // Handle return builtin (exit code >= RETURN_EXIT_CODE)
-        if last_exit >= RETURN_EXIT_CODE {
-            last_exit = last_exit - RETURN_EXIT_CODE;
-            break;
+        result = execute_command(state, cmd, events, commands, block_id);
+        match result {
+            // Handle return builtin (exit code >= RETURN_EXIT_CODE)
+            Ok(last_exit) if last_exit >= RETURN_EXIT_CODE => {
+                result = Ok(last_exit - RETURN_EXIT_CODE);
+                break;
+            }
+            Ok(_) => {}
+            // An error unwinds, but the caller's scope is put back.
+            Err(_) => break,
         }
     }
 
@@ -955,14 +1675,14 @@ fn execute_function_call(
     // Restore old positional parameters
     state.positional_params = old_params;
 
-    Ok(last_exit)
+    result
 }
 
 /// Execute a case statement.
 fn execute_case(
     state: &mut ShellState,
     case_stmt: &CaseStatement,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -976,7 +1696,7 @@ fn execute_case(
         let matches = case_item.patterns.iter().any(|pattern| {
             // Expand pattern (handles variables, etc.)
             let expanded_pattern = expand::expand_tilde(pattern, state);
-            pattern_matches(&word, &expanded_pattern)
+            glob::matches(&expanded_pattern, &word, state.options.extglob)
         });
 
         if matches {
@@ -995,7 +1715,7 @@ fn execute_case(
 fn execute_watch(
     state: &mut ShellState,
     watch: &WatchStatement,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     external_block_id: Option<BlockId>,
 ) -> anyhow::Result<i32> {
@@ -1005,26 +1725,44 @@ fn execute_watch(
     let block_id = get_or_create_block_id(external_block_id);
 
     // Build display string
-    let cmd_str = format!(
-        "watch -n {} {}",
-        if watch.interval_ms % 1000 == 0 {
-            format!("{}", watch.interval_ms / 1000)
-        } else {
-            format!("{}ms", watch.interval_ms)
-        },
-        pipeline_display_string(&watch.pipeline)
-    );
+    let trigger = if watch.on_change.is_empty() {
+        format!(
+            "-n {}",
+            if watch.interval_ms % 1000 == 0 {
+                format!("{}", watch.interval_ms / 1000)
+            } else {
+                format!("{}ms", watch.interval_ms)
+            }
+        )
+    } else {
+        watch.on_change.iter().map(|p| format!("--on-change {}", p)).collect::<Vec<_>>().join(" ")
+    };
+    let cmd_str = format!("watch {} {}", trigger, pipeline_display_string(&watch.pipeline));
 
     if external_block_id.is_none() {
         let _ = events.send(ShellEvent::CommandStarted {
             block_id,
             command: cmd_str,
             cwd: state.cwd.clone(),
+            container: None,
         });
     }
 
     // Register cancel token
     let cancel = register_cancel(block_id);
+    let start = nexus_api::Stopwatch::start();
+
+    // Snapshot before the first run, so files it writes count as changes
+    let mut watcher = (!watch.on_change.is_empty()).then(|| {
+        let watcher = crate::file_watch::FileWatcher::new(&state.cwd, &watch.on_change);
+        if watcher.is_empty() {
+            let _ = events.send(ShellEvent::StderrChunk {
+                block_id,
+                data: format!("watch: no files match {}\n", watch.on_change.join(" ")).into_bytes(),
+            });
+        }
+        watcher
+    });
 
     // Execute pipeline once for initial render
     match execute_pipeline_for_value(state, &watch.pipeline, events, commands, block_id) {
@@ -1037,17 +1775,25 @@ fn execute_watch(
         }
         Ok(None) => {}
         Err(e) => {
-            let _ = events.send(ShellEvent::StderrChunk {
-                block_id,
-                data: format!("watch: {}\n", e).into_bytes(),
-            });
+            send_command_error(events, block_id, crate::error::command_error("watch", &e));
         }
     }
 
-    // Refresh loop (fixed-delay)
+    // Refresh loop (fixed-delay, or on a change to a watched file)
     let mut seq: u64 = 0;
+    let requested = std::time::Duration::from_millis(watch.interval_ms);
     while !cancel.load(Ordering::Relaxed) {
-        std::thread::sleep(std::time::Duration::from_millis(watch.interval_ms));
+        if let Some(watcher) = &mut watcher {
+            let Some(changed) = watcher.wait(&cancel) else {
+                break;
+            };
+            let _ = events.send(ShellEvent::StderrChunk {
+                block_id,
+                data: format!("watch: changed {}\n", changed_paths_summary(&changed)).into_bytes(),
+            });
+        } else {
+            sleep_watch_interval(requested, &cancel);
+        }
         if cancel.load(Ordering::Relaxed) {
             break;
         }
@@ -1066,10 +1812,7 @@ fn execute_watch(
                 // No output this tick, skip
             }
             Err(e) => {
-                let _ = events.send(ShellEvent::StderrChunk {
-                    block_id,
-                    data: format!("watch: {}\n", e).into_bytes(),
-                });
+                send_command_error(events, block_id, crate::error::command_error("watch", &e));
                 // Continue to next tick
             }
         }
@@ -1079,21 +1822,56 @@ fn execute_watch(
     let _ = events.send(ShellEvent::CommandFinished {
         block_id,
         exit_code: 0,
-        duration_ms: 0,
+        duration_ms: start.elapsed_ms(),
     });
 
     Ok(0)
 }
 
+/// The paths that triggered a watch re-run, the first few by name.
+fn changed_paths_summary(paths: &[std::path::PathBuf]) -> String {
+    const SHOWN: usize = 3;
+    let names: Vec<_> = paths.iter().take(SHOWN).map(|p| p.display().to_string()).collect();
+    match paths.len().saturating_sub(SHOWN) {
+        0 => names.join(", "),
+        more => format!("{} and {} more", names.join(", "), more),
+    }
+}
+
+/// Wait out one watch interval, returning early on cancel.
+///
+/// `thread::sleep` doesn't count time the machine was asleep, so after a
+/// wake a long interval would keep showing pre-sleep output. Sleeping in
+/// slices against a sleep-aware stopwatch refreshes right after wake. The
+/// interval is re-read each slice so toggling low-power mode (which
+/// stretches it) applies to the wait already in progress.
+fn sleep_watch_interval(requested: std::time::Duration, cancel: &std::sync::atomic::AtomicBool) {
+    use std::sync::atomic::Ordering;
+    const SLICE: std::time::Duration = std::time::Duration::from_millis(250);
+
+    let tick = nexus_api::Stopwatch::start();
+    while !cancel.load(Ordering::Relaxed) {
+        let interval = crate::power::watch_interval(requested);
+        let elapsed = tick.elapsed();
+        if elapsed >= interval {
+            break;
+        }
+        std::thread::sleep((interval - elapsed).min(SLICE));
+    }
+}
+
 /// Execute a pipeline and return the final Value (if any).
 /// Used by watch to capture output without emitting CommandStarted/Finished.
 fn execute_pipeline_for_value(
     state: &mut ShellState,
     pipeline: &Pipeline,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
     commands: &CommandRegistry,
     block_id: BlockId,
 ) -> anyhow::Result<Option<Value>> {
+    if let Some((name, message)) = typed_stage_error(pipeline, commands) {
+        anyhow::bail!("{}: {}", name, message);
+    }
     let mut current_value: Option<Value> = None;
 
     for cmd in &pipeline.commands {
@@ -1120,15 +1898,19 @@ fn execute_pipeline_for_value(
 
         if let Some(native_cmd) = commands.get(&name) {
             // Native command
+            let mut args = args;
+            let output = OutputMode::take_flag_for(native_cmd.as_ref(), &mut args).unwrap_or_default();
             let mut ctx = CommandContext {
                 state,
                 events,
                 block_id,
                 stdin: current_value.take(),
+                output,
             };
 
             match native_cmd.execute(&args, &mut ctx) {
                 Ok(value) => {
+                    let value = output.render(value);
                     current_value = if matches!(value, Value::Unit) {
                         None
                     } else {
@@ -1168,50 +1950,38 @@ fn execute_pipeline_for_value(
 
 /// Build a display string for a pipeline.
 fn pipeline_display_string(pipeline: &Pipeline) -> String {
-    pipeline
-        .commands
-        .iter()
-        .filter_map(|cmd| {
-            if let Command::Simple(s) = cmd {
-                let args_str = s.args.iter().filter_map(|w| w.as_literal()).collect::<Vec<_>>().join(" ");
-                if args_str.is_empty() {
-                    Some(s.name.clone())
-                } else {
-                    Some(format!("{} {}", s.name, args_str))
-                }
-            } else {
-                None
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" | ")
+    let mut out = String::new();
+    for (i, cmd) in pipeline.commands.iter().enumerate() {
+        let Command::Simple(s) = cmd else {
+            continue;
+        };
+        if !out.is_empty() {
+            out.push_str(if pipeline.typed.contains(&i) { " |> " } else { " | " });
+        }
+        out.push_str(&s.name);
+        for arg in s.args.iter().filter_map(|w| w.as_literal()) {
+            out.push(' ');
+            out.push_str(arg);
+        }
+    }
+    out
 }
 
-/// Check if a word matches a shell pattern (glob-style).
-fn pattern_matches(word: &str, pattern: &str) -> bool {
-    // Handle special case: * matches everything
-    if pattern == "*" {
-        return true;
-    }
-
-    // Convert shell pattern to regex
-    let regex_pattern = pattern
-        .chars()
-        .map(|c| match c {
-            '*' => ".*".to_string(),
-            '?' => ".".to_string(),
-            '[' | ']' => c.to_string(), // Character classes pass through
-            '.' | '+' | '^' | '$' | '(' | ')' | '{' | '}' | '|' | '\\' => {
-                format!("\\{}", c)
-            }
-            _ => c.to_string(),
-        })
-        .collect::<String>();
-
-    // Anchor the pattern
-    let anchored = format!("^{}$", regex_pattern);
-
-    regex::Regex::new(&anchored)
-        .map(|re| re.is_match(word))
-        .unwrap_or(false)
+/// The first `|>` stage that can't run, with why: both sides of a `|>`
+/// must be native commands, since only they pass structured values.
+fn typed_stage_error(pipeline: &Pipeline, commands: &CommandRegistry) -> Option<(String, String)> {
+    let name = |i: usize| match pipeline.commands.get(i) {
+        Some(Command::Simple(s)) => s.name.clone(),
+        _ => String::new(),
+    };
+    pipeline.typed.iter().find_map(|&i| {
+        let receiver = name(i);
+        if !commands.contains(&receiver) {
+            return Some((receiver, "not a native command, so it can't take a structured value from |>".to_string()));
+        }
+        let source = i.checked_sub(1).map(name).unwrap_or_default();
+        (!commands.contains(&source))
+            .then(|| (source, "not a native command, so it has no structured value to pass through |>".to_string()))
+    })
 }
+
@@ -0,0 +1,140 @@
+//! Process substitution - `<(cmd)` and `>(cmd)`.
+//!
+//! The word expands to `/dev/fd/N`, one end of a pipe whose other end is
+//! `cmd`'s stdout (`<(cmd)`) or stdin (`>(cmd)`). A `<(cmd)` the evaluator
+//! can run in-process is run up front, like a command substitution, and its
+//! output's text written into the pipe from a thread; anything else runs
+//! under `sh` in the session's cwd and environment.
+//!
+//! The shell's ends are close-on-exec like every descriptor it opens;
+//! `process::spawn` keeps the ones named on a command line open in the
+//! child. They stay open until the [`Scope`] they were expanded in ends.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::Write;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::process::{Child, Stdio};
+
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+
+use crate::commands::CommandRegistry;
+use crate::parser::Redirect;
+use crate::ShellState;
+
+thread_local! {
+    static OPEN: RefCell<Vec<Pipe>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The shell's end of one substitution, and the `sh` on the other end.
+struct Pipe {
+    fd: Option<OwnedFd>,
+    child: Option<Child>,
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // Close our end first so a writer gets EPIPE and a reader EOF.
+        drop(self.fd.take());
+        if let Some(mut child) = self.child.take()
+            && !matches!(child.try_wait(), Ok(Some(_)))
+        {
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+/// Substitutions expanded while a scope is alive are closed when it drops.
+pub(super) struct Scope {
+    mark: usize,
+}
+
+impl Scope {
+    pub(super) fn enter() -> Self {
+        Scope { mark: OPEN.with(|open| open.borrow().len()) }
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let closed = OPEN.with(|open| {
+            let mut open = open.borrow_mut();
+            let mark = self.mark.min(open.len());
+            open.split_off(mark)
+        });
+        drop(closed);
+    }
+}
+
+/// Start the substitution `source` (`<(cmd)` or `>(cmd)`) and return the
+/// path that names it. On failure the word is left as written.
+pub(super) fn open(source: &str, state: &ShellState, commands: &CommandRegistry) -> String {
+    match start(source, state, commands) {
+        Ok(pipe) => {
+            let path = pipe.fd.as_ref().map(|fd| format!("/dev/fd/{}", fd.as_raw_fd())).unwrap_or_default();
+            OPEN.with(|open| open.borrow_mut().push(pipe));
+            path
+        }
+        Err(e) => {
+            tracing::warn!("process substitution {}: {}", source, e);
+            source.to_string()
+        }
+    }
+}
+
+/// `redirects` with process substitution targets (`< <(cmd)`) replaced by
+/// their paths.
+pub(super) fn expand_redirects<'a>(
+    redirects: &'a [Redirect],
+    state: &ShellState,
+    commands: &CommandRegistry,
+) -> Cow<'a, [Redirect]> {
+    if !redirects.iter().any(|r| is_substitution(&r.target)) {
+        return Cow::Borrowed(redirects);
+    }
+    redirects
+        .iter()
+        .map(|redirect| match is_substitution(&redirect.target) {
+            true => Redirect { target: open(&redirect.target, state, commands), ..redirect.clone() },
+            false => redirect.clone(),
+        })
+        .collect()
+}
+
+fn is_substitution(target: &str) -> bool {
+    (target.starts_with("<(") || target.starts_with(">(")) && target.ends_with(')')
+}
+
+fn start(source: &str, state: &ShellState, commands: &CommandRegistry) -> anyhow::Result<Pipe> {
+    anyhow::ensure!(is_substitution(source), "not a process substitution");
+    let inner = &source[2..source.len() - 1];
+    let (read, write) = nix::unistd::pipe()?;
+    for fd in [&read, &write] {
+        fcntl(fd.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+    }
+
+    if source.starts_with('>') {
+        let child = sh(inner, state).stdin(Stdio::from(read)).spawn()?;
+        return Ok(Pipe { fd: Some(write), child: Some(child) });
+    }
+
+    if let Some(ast) = super::in_process_ast(inner, state, commands) {
+        let text = super::capture(&ast, state, commands).text;
+        std::thread::spawn(move || {
+            let mut pipe = std::fs::File::from(write);
+            if !text.is_empty() {
+                let _ = writeln!(pipe, "{}", text);
+            }
+        });
+        return Ok(Pipe { fd: Some(read), child: None });
+    }
+
+    let child = sh(inner, state).stdin(Stdio::null()).stdout(Stdio::from(write)).spawn()?;
+    Ok(Pipe { fd: Some(read), child: Some(child) })
+}
+
+fn sh(source: &str, state: &ShellState) -> std::process::Command {
+    let mut cmd = std::process::Command::new("sh");
+    cmd.arg("-c").arg(source).current_dir(&state.cwd).env_clear().envs(&state.env);
+    cmd
+}
@@ -0,0 +1,245 @@
+//! Whether a command line's command exists, answered without spawning it.
+//!
+//! [`ExecutableIndex`] lists the executables in the `PATH` directories.
+//! [`Executables`] keeps one, built on a background thread and rebuilt
+//! when `PATH` or one of its directories changes, so asking never waits
+//! on a directory scan. [`crate::Kernel::command_status`] adds native
+//! commands, builtins, functions and aliases: the input bar marks an
+//! unknown command while it is typed, and submitting one fails at once
+//! with a did-you-mean instead of a shell reporting 127.
+
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::completion::is_executable;
+use crate::error::edit_distance;
+use crate::overrides::ExecOverride;
+
+/// How long an index is trusted while its directories look unchanged
+/// (mtime has coarse resolution on some filesystems).
+const INDEX_TTL: Duration = Duration::from_secs(30);
+
+/// What is known about the command a line starts with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandStatus {
+    /// Something by that name would run.
+    Known,
+    /// Nothing by that name, with a similarly named command if there is one.
+    Unknown { suggestion: Option<String> },
+    /// Not checked: the name comes from an expansion, or the index is
+    /// still being built.
+    Unchecked,
+}
+
+/// The executables in the directories of one `PATH`.
+#[derive(Debug)]
+pub struct ExecutableIndex {
+    path: String,
+    dirs: Vec<(PathBuf, Option<SystemTime>)>,
+    names: HashSet<String>,
+    built: Instant,
+}
+
+impl ExecutableIndex {
+    /// Scan the directories of `path`.
+    pub fn build(path: &str) -> Self {
+        let mut dirs = Vec::new();
+        let mut names = HashSet::new();
+        for dir in std::env::split_paths(path) {
+            if dir.as_os_str().is_empty() || dirs.iter().any(|(seen, _)| *seen == dir) {
+                continue;
+            }
+            let modified = std::fs::metadata(&dir).and_then(|m| m.modified()).ok();
+            for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+                if let Ok(name) = entry.file_name().into_string()
+                    && !names.contains(&name)
+                    && is_executable(&entry.path())
+                {
+                    names.insert(name);
+                }
+            }
+            dirs.push((dir, modified));
+        }
+        Self { path: path.to_string(), dirs, names, built: Instant::now() }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Whether this index still describes `path`: same directories, none
+    /// of them changed since the scan.
+    fn is_current(&self, path: &str) -> bool {
+        self.path == path
+            && self.dirs.iter().all(|(dir, modified)| std::fs::metadata(dir).and_then(|m| m.modified()).ok() == *modified)
+    }
+
+    /// The indexed or `other` name closest to `name`, at most two edits
+    /// away (one for names of three characters or fewer).
+    pub fn closest<'a>(&self, name: &str, other: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let limit = if name.chars().count() <= 3 { 1 } else { 2 };
+        let near = |candidate: &str| {
+            let distance = edit_distance(name, candidate);
+            (distance > 0 && distance <= limit).then(|| (distance, candidate.to_string()))
+        };
+        self.names
+            .iter()
+            .filter_map(|candidate| near(candidate))
+            .chain(other.into_iter().filter_map(near))
+            .min()
+            .map(|(_, candidate)| candidate)
+    }
+}
+
+#[derive(Default)]
+struct Cache {
+    index: Option<Arc<ExecutableIndex>>,
+    building: Option<String>,
+}
+
+/// The current [`ExecutableIndex`], kept up to date in the background.
+#[derive(Default, Clone)]
+pub struct Executables {
+    cache: Arc<Mutex<Cache>>,
+}
+
+impl Executables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The index for `path`. Starts a rebuild when there is none for it
+    /// or its directories changed, and returns `None` until that is done;
+    /// an index past its TTL is still returned while it is rebuilt.
+    pub fn get(&self, path: &str) -> Option<Arc<ExecutableIndex>> {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let index = cache.index.clone().filter(|index| index.is_current(path));
+        if index.as_ref().is_some_and(|index| index.built.elapsed() < INDEX_TTL) {
+            return index;
+        }
+        if cache.building.as_deref() != Some(path) {
+            cache.building = Some(path.to_string());
+            let this = self.clone();
+            let path = path.to_string();
+            std::thread::Builder::new()
+                .name("nexus-path-index".into())
+                .spawn(move || this.finish(ExecutableIndex::build(&path)))
+                .ok()?;
+        }
+        index
+    }
+
+    /// Build the index for `path` now, on this thread.
+    pub fn refresh(&self, path: &str) -> Arc<ExecutableIndex> {
+        let index = Arc::new(ExecutableIndex::build(path));
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.index = Some(index.clone());
+        index
+    }
+
+    fn finish(&self, index: ExecutableIndex) {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.building.as_deref() == Some(index.path.as_str()) {
+            cache.building = None;
+        }
+        cache.index = Some(Arc::new(index));
+    }
+}
+
+/// The word naming the command `line` runs, after any `in <dir>` prefix
+/// and `NAME=value` assignments. `None` when it is not a plain word
+/// (quoted, or built from an expansion), since only running it tells
+/// what it names.
+pub fn command_word(line: &str) -> Option<&str> {
+    command_word_range(line).map(|range| &line[range])
+}
+
+/// Byte range of [`command_word`] in `line`.
+pub fn command_word_range(line: &str) -> Option<Range<usize>> {
+    let rest = ExecOverride::split(line).map_or(line, |(_, rest)| rest);
+    let word = rest.split_whitespace().find(|word| !is_assignment(word))?;
+    let plain = word.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '+' | '/' | ':' | '@' | ',' | '%'));
+    if !plain || word.starts_with('-') {
+        return None;
+    }
+    // `word` is a slice of `line`.
+    let start = word.as_ptr() as usize - line.as_ptr() as usize;
+    Some(start..start + word.len())
+}
+
+/// Whether `word` is a `NAME=value` assignment.
+pub(crate) fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn make_executable(path: &Path) {
+        std::fs::write(path, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_index_lists_executables() {
+        let bin = tempfile::tempdir().unwrap();
+        make_executable(&bin.path().join("git"));
+        std::fs::write(bin.path().join("notes.txt"), "").unwrap();
+        let index = ExecutableIndex::build(&bin.path().to_string_lossy());
+        assert!(index.contains("git"));
+        #[cfg(unix)]
+        assert!(!index.contains("notes.txt"));
+        assert_eq!(index.closest("gti", []).as_deref(), Some("git"));
+        assert_eq!(index.closest("gx", []), None);
+        assert_eq!(index.closest("gitt", ["grep"]).as_deref(), Some("git"));
+    }
+
+    #[test]
+    fn test_index_follows_path_changes() {
+        let bin = tempfile::tempdir().unwrap();
+        let path = bin.path().to_string_lossy().into_owned();
+        let executables = Executables::new();
+        executables.refresh(&path);
+        assert!(executables.get(&path).is_some());
+        assert!(executables.get("/nonexistent").is_none());
+
+        // A new file changes the directory's mtime; coarse clocks need a moment.
+        std::thread::sleep(Duration::from_millis(1100));
+        make_executable(&bin.path().join("fresh"));
+        assert!(executables.get(&path).is_none_or(|index| index.contains("fresh")));
+    }
+
+    #[test]
+    fn test_command_word() {
+        assert_eq!(command_word("git status"), Some("git"));
+        assert_eq!(command_word("  RUST_LOG=debug cargo run"), Some("cargo"));
+        assert_eq!(command_word("./build.sh --release"), Some("./build.sh"));
+        assert_eq!(command_word("$EDITOR notes"), None);
+        assert_eq!(command_word("\"my tool\" x"), None);
+        assert_eq!(command_word("FOO=1"), None);
+        assert_eq!(command_word(""), None);
+        assert_eq!(command_word("in src cargo test"), Some("cargo"));
+        assert_eq!(command_word_range("  FOO=1 make"), Some(8..12));
+    }
+}
@@ -0,0 +1,222 @@
+//! File watching for `watch --on-change`.
+//!
+//! [`FileWatcher`] remembers the modification time and size of every file
+//! matching a set of glob patterns, and [`FileWatcher::wait`] polls them
+//! until something is added, changed or removed. A save usually touches
+//! several files in quick succession (editor swap files, formatters), so a
+//! change is only reported once the tree has been quiet for `DEBOUNCE`.
+//!
+//! Patterns are relative to the watch's cwd. `*`, `?` and `[..]` match
+//! within one path component; a `**` component matches any number of
+//! directories, skipping hidden ones as bash's `globstar` does.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// How often the files are checked.
+const POLL: Duration = Duration::from_millis(250);
+/// How long the files must stay unchanged before a change is reported.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Files tracked before the walk stops, so `**` over a huge tree stays quick.
+const MAX_FILES: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+pub struct FileWatcher {
+    root: PathBuf,
+    patterns: Vec<String>,
+    seen: HashMap<PathBuf, Stamp>,
+}
+
+impl FileWatcher {
+    /// Start watching the files under `root` matching `patterns`.
+    pub fn new(root: &Path, patterns: &[String]) -> Self {
+        let mut watcher = Self { root: root.to_path_buf(), patterns: patterns.to_vec(), seen: HashMap::new() };
+        watcher.seen = watcher.scan();
+        watcher
+    }
+
+    /// Number of files currently tracked.
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Paths added, changed or removed since the last poll, relative to
+    /// the root where possible, sorted.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = self.scan();
+        let mut changed: BTreeSet<&PathBuf> = BTreeSet::new();
+        for (path, stamp) in &now {
+            if self.seen.get(path) != Some(stamp) {
+                changed.insert(path);
+            }
+        }
+        for path in self.seen.keys() {
+            if !now.contains_key(path) {
+                changed.insert(path);
+            }
+        }
+        let changed = changed.into_iter().map(|path| self.display_path(path)).collect();
+        self.seen = now;
+        changed
+    }
+
+    /// Block until matching files change and then settle, returning the
+    /// paths that changed. `None` once `cancel` is set.
+    pub fn wait(&mut self, cancel: &AtomicBool) -> Option<Vec<PathBuf>> {
+        let mut changed = BTreeSet::new();
+        let mut quiet = Duration::ZERO;
+        loop {
+            std::thread::sleep(POLL);
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let batch = self.poll();
+            if batch.is_empty() {
+                quiet += POLL;
+                if !changed.is_empty() && quiet >= DEBOUNCE {
+                    return Some(changed.into_iter().collect());
+                }
+            } else {
+                changed.extend(batch);
+                quiet = Duration::ZERO;
+            }
+        }
+    }
+
+    fn display_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, Stamp> {
+        let mut found = HashMap::new();
+        for pattern in &self.patterns {
+            let base = if Path::new(pattern).is_absolute() { PathBuf::from("/") } else { self.root.clone() };
+            let parts: Vec<String> = Path::new(pattern)
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                    Component::ParentDir => Some("..".to_string()),
+                    _ => None,
+                })
+                .collect();
+            walk(&base, &parts, &mut found);
+        }
+        found
+    }
+}
+
+fn walk(dir: &Path, parts: &[String], found: &mut HashMap<PathBuf, Stamp>) {
+    let Some((part, rest)) = parts.split_first() else {
+        return;
+    };
+    if found.len() >= MAX_FILES {
+        return;
+    }
+
+    if part == "**" {
+        // Zero directories, then one more level with `**` still in force.
+        walk(dir, rest, found);
+        for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if !hidden && entry.file_type().is_ok_and(|t| t.is_dir()) {
+                walk(&entry.path(), parts, found);
+            }
+        }
+        return;
+    }
+
+    if !part.contains(['*', '?', '[']) {
+        visit(&dir.join(part), rest, found);
+        return;
+    }
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') && !part.starts_with('.') {
+            continue;
+        }
+        if crate::eval::glob_match(part, &name) {
+            visit(&entry.path(), rest, found);
+        }
+    }
+}
+
+/// Record `path` if it's the last component of the pattern, otherwise
+/// descend into it.
+fn visit(path: &Path, rest: &[String], found: &mut HashMap<PathBuf, Stamp>) {
+    if !rest.is_empty() {
+        if path.is_dir() {
+            walk(path, rest, found);
+        }
+        return;
+    }
+    if let Ok(meta) = std::fs::metadata(path)
+        && meta.is_file()
+    {
+        found.insert(path.to_path_buf(), Stamp { modified: meta.modified().ok(), len: meta.len() });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(list: &[&str]) -> Vec<String> {
+        list.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_double_star_matches_any_depth() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/a/b")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src/.hidden")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/a/b/deep.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/a/notes.md"), "").unwrap();
+        std::fs::write(dir.path().join("src/.hidden/skip.rs"), "").unwrap();
+
+        let watcher = FileWatcher::new(dir.path(), &patterns(&["src/**/*.rs"]));
+        let mut tracked: Vec<_> = watcher.seen.keys().map(|p| watcher.display_path(p)).collect();
+        tracked.sort();
+        assert_eq!(tracked, vec![PathBuf::from("src/a/b/deep.rs"), PathBuf::from("src/lib.rs")]);
+    }
+
+    #[test]
+    fn test_poll_reports_added_changed_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("keep.txt"), "one").unwrap();
+        std::fs::write(dir.path().join("gone.txt"), "").unwrap();
+        let mut watcher = FileWatcher::new(dir.path(), &patterns(&["*.txt", "Cargo.toml"]));
+        assert_eq!(watcher.len(), 2);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(dir.path().join("keep.txt"), "one two").unwrap();
+        std::fs::remove_file(dir.path().join("gone.txt")).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join("other.rs"), "").unwrap();
+        assert_eq!(watcher.poll(), vec![
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("gone.txt"),
+            PathBuf::from("keep.txt"),
+        ]);
+        assert!(watcher.poll().is_empty());
+    }
+
+    #[test]
+    fn test_wait_stops_on_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut watcher = FileWatcher::new(dir.path(), &patterns(&["*"]));
+        let cancel = AtomicBool::new(true);
+        assert_eq!(watcher.wait(&cancel), None);
+    }
+}
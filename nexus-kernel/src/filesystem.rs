@@ -0,0 +1,158 @@
+//! Filesystem lookups for the UI.
+//!
+//! [`FilesystemProvider`] previews a directory — how many folders and files
+//! it holds and the first few names — for the completion popup's
+//! documentation pane. Moving through the popup asks for the same few
+//! directories over and over, so previews are cached until the directory
+//! changes or goes stale.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Names listed in a preview.
+const PREVIEW_ENTRIES: usize = 8;
+/// Entries read before counting stops, so huge directories stay quick.
+const MAX_SCAN: usize = 5000;
+/// How long a preview is trusted even if the mtime looks unchanged (mtime
+/// has coarse resolution on some filesystems).
+const CACHE_TTL: Duration = Duration::from_secs(5);
+const CACHE_CAPACITY: usize = 64;
+
+/// What a directory holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryPreview {
+    pub dirs: usize,
+    pub files: usize,
+    /// Dotfiles, counted apart and not listed.
+    pub hidden: usize,
+    /// The first few visible entries, folders first, by name.
+    pub entries: Vec<PreviewEntry>,
+    /// Counting stopped at [`MAX_SCAN`] entries.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+impl DirectoryPreview {
+    /// Read `dir`. `None` if it can't be listed.
+    pub fn read(dir: &Path) -> Option<Self> {
+        let mut preview = Self { dirs: 0, files: 0, hidden: 0, entries: Vec::new(), truncated: false };
+        let mut visible = Vec::new();
+        for (i, entry) in std::fs::read_dir(dir).ok()?.flatten().enumerate() {
+            if i == MAX_SCAN {
+                preview.truncated = true;
+                break;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Follows symlinks, so a link to a folder counts as one.
+            let is_dir = entry.path().is_dir();
+            if name.starts_with('.') {
+                preview.hidden += 1;
+            } else {
+                if is_dir {
+                    preview.dirs += 1;
+                } else {
+                    preview.files += 1;
+                }
+                visible.push(PreviewEntry { name, is_dir });
+            }
+        }
+        visible.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+        visible.truncate(PREVIEW_ENTRIES);
+        preview.entries = visible;
+        Some(preview)
+    }
+
+    /// Visible entries not listed.
+    pub fn more(&self) -> usize {
+        (self.dirs + self.files).saturating_sub(self.entries.len())
+    }
+}
+
+struct Cached {
+    preview: DirectoryPreview,
+    modified: Option<SystemTime>,
+    at: Instant,
+}
+
+/// Directory previews, cached.
+#[derive(Default)]
+pub struct FilesystemProvider {
+    cache: Mutex<HashMap<PathBuf, Cached>>,
+}
+
+impl FilesystemProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn preview(&self, dir: &Path) -> Option<DirectoryPreview> {
+        let modified = std::fs::metadata(dir).ok()?.modified().ok();
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.get(dir)
+            && cached.modified == modified
+            && cached.at.elapsed() < CACHE_TTL
+        {
+            return Some(cached.preview.clone());
+        }
+        let preview = DirectoryPreview::read(dir)?;
+        if cache.len() >= CACHE_CAPACITY {
+            cache.retain(|_, c| c.at.elapsed() < CACHE_TTL);
+            if cache.len() >= CACHE_CAPACITY {
+                cache.clear();
+            }
+        }
+        cache.insert(dir.to_path_buf(), Cached { preview: preview.clone(), modified, at: Instant::now() });
+        Some(preview)
+    }
+}
+
+/// The path a completion's text names: quotes and escapes removed, `~`
+/// expanded with `home`, and relative paths taken from `cwd`.
+pub fn resolve_completion_path(text: &str, cwd: &Path, home: Option<&str>) -> PathBuf {
+    let unquoted = crate::completion::unquote(text).text;
+    let expanded = match (unquoted.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => unquoted,
+    };
+    cwd.join(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_lists_folders_first() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("README.md"), "").unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "").unwrap();
+
+        let provider = FilesystemProvider::new();
+        let preview = provider.preview(dir.path()).unwrap();
+        assert_eq!((preview.dirs, preview.files, preview.hidden), (1, 2, 1));
+        let names: Vec<&str> = preview.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["src", "Cargo.toml", "README.md"]);
+        assert_eq!(preview.more(), 0);
+
+        assert!(provider.preview(&dir.path().join("missing")).is_none());
+    }
+
+    #[test]
+    fn test_resolve_completion_path() {
+        let cwd = Path::new("/work");
+        assert_eq!(resolve_completion_path("My\\ Docs/", cwd, None), PathBuf::from("/work/My Docs/"));
+        assert_eq!(resolve_completion_path("\"My Docs/", cwd, None), PathBuf::from("/work/My Docs/"));
+        assert_eq!(resolve_completion_path("~/src/", cwd, Some("/home/ada")), PathBuf::from("/home/ada/src/"));
+        assert_eq!(resolve_completion_path("~bob/", cwd, Some("/home/ada")), PathBuf::from("/work/~bob/"));
+        assert_eq!(resolve_completion_path("/tmp/", cwd, None), PathBuf::from("/tmp/"));
+    }
+}
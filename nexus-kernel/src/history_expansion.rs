@@ -0,0 +1,333 @@
+//! History expansion (`!!`, `!N`, `!$`, `!*`, `^old^new`).
+//!
+//! Runs before parsing so the expanded line is what gets classified,
+//! recorded in history, and executed. The UI also calls this on every
+//! edit to preview the expansion in the input bar.
+//!
+//! Supported designators:
+//! - `!!` previous command, `!N` history entry N, `!-N` Nth previous,
+//!   `!prefix` most recent command starting with `prefix`
+//! - `!$` last word, `!^` first argument, `!*` all arguments of the
+//!   previous command
+//! - `:N`, `:$`, `:^`, `:*` word designators after any event (`!-2:$`)
+//! - `^old^new^` quick substitution on the previous command
+//!
+//! `$_` is Nexus's last-output reference, not bash's last argument, so it
+//! is left untouched here and resolved by the evaluator.
+
+use crate::ShellError;
+
+/// Expand history references in `input`.
+///
+/// `history` is oldest-first (as returned by `ShellHistory::entries`).
+/// Returns `Ok(None)` when the line contains no history references, so
+/// callers can skip work and avoid echoing an unchanged line.
+pub fn expand_history(input: &str, history: &[String]) -> Result<Option<String>, ShellError> {
+    let trimmed = input.trim_start();
+    if let Some(rest) = trimmed.strip_prefix('^') {
+        return quick_substitution(rest, history).map(Some);
+    }
+
+    if !input.contains('!') {
+        return Ok(None);
+    }
+
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut expanded = false;
+    let mut in_single = false;
+    // Inside double quotes `'` is literal, but `!` still expands, as in bash.
+    let mut in_double = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if !in_single => {
+                // `\!` suppresses expansion; keep the escape for the parser.
+                out.push(c);
+                if let Some(&next) = chars.get(i + 1) {
+                    out.push(next);
+                }
+                i += 2;
+            }
+            '\'' if !in_double => {
+                in_single = !in_single;
+                out.push(c);
+                i += 1;
+            }
+            '"' if !in_single => {
+                in_double = !in_double;
+                out.push(c);
+                i += 1;
+            }
+            '!' if !in_single && starts_designator(chars.get(i + 1).copied()) => {
+                let (text, consumed) = expand_one(&chars[i + 1..], history)?;
+                out.push_str(&text);
+                expanded = true;
+                i += 1 + consumed;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Ok(expanded.then_some(out))
+}
+
+/// Whether the character after `!` begins a history reference.
+///
+/// Mirrors bash: `!` before whitespace, `=`, `(` or end of line is literal,
+/// which keeps `[ a != b ]` and `!(pattern)` working.
+fn starts_designator(next: Option<char>) -> bool {
+    match next {
+        None => false,
+        Some(c) => !(c.is_whitespace() || c == '=' || c == '(' || c == '"'),
+    }
+}
+
+/// Expand a single reference. `chars` starts just after the `!`.
+/// Returns the replacement text and the number of chars consumed.
+fn expand_one(chars: &[char], history: &[String]) -> Result<(String, usize), ShellError> {
+    let previous = || {
+        history
+            .last()
+            .cloned()
+            .ok_or_else(|| ShellError::EventNotFound("!!".to_string()))
+    };
+
+    // Shorthand word designators on the previous command.
+    match chars[0] {
+        '$' | '^' | '*' => {
+            let words = split_words(&previous()?);
+            return Ok((select_words(&words, chars[0], "!")?, 1));
+        }
+        _ => {}
+    }
+
+    let (event, mut consumed) = if chars[0] == '!' {
+        (previous()?, 1)
+    } else if chars[0].is_ascii_digit() || chars[0] == '-' {
+        let neg = chars[0] == '-';
+        let start = usize::from(neg);
+        let digits: String = chars[start..].iter().take_while(|c| c.is_ascii_digit()).collect();
+        let token: String = chars[..start + digits.len()].iter().collect();
+        let n: usize = digits
+            .parse()
+            .map_err(|_| ShellError::EventNotFound(format!("!{token}")))?;
+        let index = if neg {
+            history.len().checked_sub(n)
+        } else {
+            n.checked_sub(1)
+        };
+        let entry = index
+            .and_then(|i| history.get(i))
+            .cloned()
+            .ok_or_else(|| ShellError::EventNotFound(format!("!{token}")))?;
+        (entry, token.chars().count())
+    } else {
+        let prefix: String = chars
+            .iter()
+            .take_while(|c| !c.is_whitespace() && !matches!(c, ':' | ';' | '|' | '&' | '\'' | '"'))
+            .collect();
+        let entry = history
+            .iter()
+            .rev()
+            .find(|cmd| cmd.starts_with(&prefix))
+            .cloned()
+            .ok_or_else(|| ShellError::EventNotFound(format!("!{prefix}")))?;
+        (entry, prefix.chars().count())
+    };
+
+    // Optional `:designator` after the event.
+    if chars.get(consumed) == Some(&':')
+        && let Some(&d) = chars.get(consumed + 1)
+    {
+        let words = split_words(&event);
+        if matches!(d, '$' | '^' | '*') {
+            return Ok((select_words(&words, d, "")?, consumed + 2));
+        }
+        let digits: String = chars[consumed + 1..].iter().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(n) = digits.parse::<usize>() {
+            let word = words
+                .get(n)
+                .cloned()
+                .ok_or_else(|| ShellError::Other(format!(":{n}: bad word specifier")))?;
+            consumed += 1 + digits.len();
+            return Ok((word, consumed));
+        }
+    }
+
+    Ok((event, consumed))
+}
+
+/// Resolve a `$`, `^` or `*` word designator against a command's words.
+fn select_words(words: &[String], designator: char, prefix: &str) -> Result<String, ShellError> {
+    let bad = || ShellError::Other(format!("{prefix}{designator}: bad word specifier"));
+    match designator {
+        '$' => words.last().cloned().ok_or_else(bad),
+        '^' => words.get(1).cloned().ok_or_else(bad),
+        // `!*` on a command with no arguments expands to nothing, like bash.
+        _ => Ok(words.get(1..).map(|w| w.join(" ")).unwrap_or_default()),
+    }
+}
+
+/// Handle `^old^new[^]`: replace the first `old` in the previous command.
+fn quick_substitution(rest: &str, history: &[String]) -> Result<String, ShellError> {
+    let mut parts = rest.splitn(3, '^');
+    let old = parts.next().unwrap_or_default();
+    let new = parts.next().unwrap_or_default();
+    let tail = parts.next().unwrap_or_default();
+
+    let previous = history
+        .last()
+        .ok_or_else(|| ShellError::EventNotFound("!!".to_string()))?;
+    if old.is_empty() || !previous.contains(old) {
+        return Err(ShellError::Other(format!("^{old}^{new}: substitution failed")));
+    }
+    Ok(format!("{}{}", previous.replacen(old, new, 1), tail))
+}
+
+/// Split a command line into words, keeping quotes intact so designators
+/// like `!$` reproduce the word exactly as typed.
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for c in line.chars() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+            continue;
+        }
+        match (c, quote) {
+            ('\\', q) if q != Some('\'') => {
+                current.push(c);
+                escaped = true;
+            }
+            ('\'' | '"', None) => {
+                quote = Some(c);
+                current.push(c);
+            }
+            (c, Some(q)) if c == q => {
+                quote = None;
+                current.push(c);
+            }
+            (c, None) if c.is_whitespace() => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> Vec<String> {
+        vec![
+            "ls -la /tmp".to_string(),
+            "git commit -m 'fix bug'".to_string(),
+            "cargo build --release".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_no_references() {
+        assert_eq!(expand_history("echo hello", &history()).unwrap(), None);
+        assert_eq!(expand_history("[ a != b ]", &history()).unwrap(), None);
+        assert_eq!(expand_history("echo hi!", &history()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_bang_bang() {
+        assert_eq!(
+            expand_history("sudo !!", &history()).unwrap().as_deref(),
+            Some("sudo cargo build --release")
+        );
+    }
+
+    #[test]
+    fn test_bang_number() {
+        assert_eq!(expand_history("!1", &history()).unwrap().as_deref(), Some("ls -la /tmp"));
+        assert_eq!(
+            expand_history("!-2", &history()).unwrap().as_deref(),
+            Some("git commit -m 'fix bug'")
+        );
+        assert!(matches!(expand_history("!42", &history()), Err(ShellError::EventNotFound(_))));
+    }
+
+    #[test]
+    fn test_bang_prefix() {
+        assert_eq!(expand_history("!ls", &history()).unwrap().as_deref(), Some("ls -la /tmp"));
+        assert!(expand_history("!nope", &history()).is_err());
+    }
+
+    #[test]
+    fn test_word_designators() {
+        assert_eq!(expand_history("echo !$", &history()).unwrap().as_deref(), Some("echo --release"));
+        assert_eq!(expand_history("echo !^", &history()).unwrap().as_deref(), Some("echo build"));
+        assert_eq!(
+            expand_history("echo !*", &history()).unwrap().as_deref(),
+            Some("echo build --release")
+        );
+        assert_eq!(
+            expand_history("echo !-2:$", &history()).unwrap().as_deref(),
+            Some("echo 'fix bug'")
+        );
+        assert_eq!(expand_history("cd !1:2", &history()).unwrap().as_deref(), Some("cd /tmp"));
+    }
+
+    #[test]
+    fn test_quoting_suppresses_expansion() {
+        assert_eq!(expand_history("echo '!!'", &history()).unwrap(), None);
+        assert_eq!(expand_history("echo \\!!", &history()).unwrap(), None);
+    }
+
+    #[test]
+    fn test_apostrophe_in_double_quotes() {
+        assert_eq!(expand_history("echo \"it's\" | grep x", &history()).unwrap(), None);
+        assert_eq!(
+            expand_history("echo \"it's\" | grep !$", &history()).unwrap().as_deref(),
+            Some("echo \"it's\" | grep --release")
+        );
+        assert_eq!(expand_history("echo '\"' !!", &history()).unwrap().as_deref(), Some("echo '\"' cargo build --release"));
+    }
+
+    #[test]
+    fn test_dollar_underscore_untouched() {
+        assert_eq!(
+            expand_history("echo $_ !$", &history()).unwrap().as_deref(),
+            Some("echo $_ --release")
+        );
+    }
+
+    #[test]
+    fn test_quick_substitution() {
+        assert_eq!(
+            expand_history("^release^debug", &history()).unwrap().as_deref(),
+            Some("cargo build --debug")
+        );
+        assert_eq!(
+            expand_history("^build^test^ -q", &history()).unwrap().as_deref(),
+            Some("cargo test --release -q")
+        );
+        assert!(expand_history("^missing^x", &history()).is_err());
+    }
+
+    #[test]
+    fn test_empty_history() {
+        assert!(matches!(expand_history("!!", &[]), Err(ShellError::EventNotFound(_))));
+    }
+}
@@ -0,0 +1,114 @@
+//! Local usage insights.
+//!
+//! The UI counts a few kinds of feature use in the store — completions
+//! taken or turned down, agent queries, palette actions run — so the
+//! insights view can show what gets used and point at what doesn't. The
+//! counts never leave the machine, and [`crate::Kernel::purge_insights`]
+//! forgets them.
+
+use crate::persistence::UsageCount;
+
+/// Something worth counting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UsageEvent {
+    /// A completion was inserted, from the popup or as the only match.
+    CompletionAccepted,
+    /// The completion popup was closed without taking one.
+    CompletionDismissed,
+    AgentQuery,
+    /// An action from the input's context menu, by label.
+    PaletteAction(String),
+}
+
+impl UsageEvent {
+    fn key(&self) -> (&'static str, &str) {
+        match self {
+            Self::CompletionAccepted => ("completion.accepted", ""),
+            Self::CompletionDismissed => ("completion.dismissed", ""),
+            Self::AgentQuery => ("agent.query", ""),
+            Self::PaletteAction(label) => ("palette.action", label),
+        }
+    }
+
+    pub(crate) fn record(&self, store: &crate::persistence::Store) -> anyhow::Result<()> {
+        let (event, detail) = self.key();
+        store.record_usage(event, detail)
+    }
+}
+
+/// Summary of the usage counts.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Insights {
+    pub completions_accepted: u64,
+    pub completions_dismissed: u64,
+    pub agent_queries: u64,
+    /// Palette actions by label, most used first.
+    pub palette_actions: Vec<(String, u64)>,
+    /// Suggestions for features that haven't been used yet.
+    pub tips: Vec<&'static str>,
+}
+
+impl Insights {
+    pub fn from_counts(counts: &[UsageCount]) -> Self {
+        let mut insights = Self::default();
+        for count in counts {
+            match count.event.as_str() {
+                "completion.accepted" => insights.completions_accepted += count.count,
+                "completion.dismissed" => insights.completions_dismissed += count.count,
+                "agent.query" => insights.agent_queries += count.count,
+                // Counts arrive most used first; this keeps that order.
+                "palette.action" => insights.palette_actions.push((count.detail.clone(), count.count)),
+                _ => {}
+            }
+        }
+        if insights.completions_accepted == 0 {
+            insights.tips.push("Press Tab to complete commands, paths and flags.");
+        }
+        if insights.agent_queries == 0 {
+            insights.tips.push("Switch the input to agent mode to ask Claude about a command or its output.");
+        }
+        if insights.palette_actions.is_empty() {
+            insights.tips.push("Add [workflows] to your config to run them from the input's right-click menu.");
+        }
+        insights
+    }
+
+    /// Share of completion popups that ended with a completion taken, in
+    /// percent; `None` before any completions.
+    pub fn acceptance_rate(&self) -> Option<u64> {
+        let total = self.completions_accepted + self.completions_dismissed;
+        (total > 0).then(|| self.completions_accepted * 100 / total)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.completions_accepted == 0 && self.completions_dismissed == 0 && self.agent_queries == 0 && self.palette_actions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::Store;
+
+    #[test]
+    fn test_insights_from_store() {
+        let store = Store::open_in_memory().unwrap();
+        assert!(Insights::from_counts(&store.usage_counts().unwrap()).is_empty());
+
+        for event in [
+            UsageEvent::CompletionAccepted,
+            UsageEvent::CompletionAccepted,
+            UsageEvent::CompletionAccepted,
+            UsageEvent::CompletionDismissed,
+            UsageEvent::PaletteAction("Run tests".to_string()),
+        ] {
+            event.record(&store).unwrap();
+        }
+
+        let insights = Insights::from_counts(&store.usage_counts().unwrap());
+        assert_eq!(insights.acceptance_rate(), Some(75));
+        assert_eq!(insights.palette_actions, [("Run tests".to_string(), 1)]);
+        assert_eq!(insights.tips.len(), 1);
+        assert!(insights.tips[0].contains("agent mode"));
+    }
+}
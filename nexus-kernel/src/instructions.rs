@@ -0,0 +1,82 @@
+//! Project instructions for the agent: the nearest `NEXUS.md` (or
+//! `.nexus/NEXUS.md`, or `CLAUDE.md`) in the working directory or above.
+//!
+//! The UI sends a `NEXUS.md` along with agent queries launched anywhere
+//! under its directory. A `CLAUDE.md` is found too, so the UI can say
+//! instructions are active, but isn't sent: the agent CLI reads it itself.
+
+use std::path::{Path, PathBuf};
+
+/// Files looked for in each directory, most preferred first.
+const CANDIDATES: [&str; 3] = ["NEXUS.md", ".nexus/NEXUS.md", "CLAUDE.md"];
+
+/// What `init` writes to a new `NEXUS.md`.
+pub const TEMPLATE: &str = "# Project instructions\n\n\
+Nexus includes this file with agent queries run in this directory or below.\n\n\
+- \n";
+
+/// An instructions file and its text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instructions {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+impl Instructions {
+    /// The nearest instructions file at or above `cwd` that can be read.
+    pub fn find(cwd: &Path) -> Option<Self> {
+        cwd.ancestors().find_map(|dir| {
+            CANDIDATES.iter().find_map(|name| {
+                let path = dir.join(name);
+                let text = std::fs::read_to_string(&path).ok()?;
+                Some(Self { path, text })
+            })
+        })
+    }
+
+    /// Whether the agent CLI reads this file on its own.
+    pub fn read_by_agent(&self) -> bool {
+        self.path.file_name().is_some_and(|name| name == "CLAUDE.md")
+    }
+
+    /// The section put ahead of an agent query, or None when the agent
+    /// reads the file itself or it's empty.
+    pub fn prompt_section(&self) -> Option<String> {
+        if self.read_by_agent() || self.text.trim().is_empty() {
+            return None;
+        }
+        Some(format!(
+            "<project_instructions source=\"{}\">\n{}\n</project_instructions>\n",
+            self.path.display(),
+            self.text.trim_end()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_file_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("crates/app");
+        std::fs::create_dir_all(sub.join(".nexus")).unwrap();
+        std::fs::write(dir.path().join("NEXUS.md"), "Use cargo nextest.\n").unwrap();
+        std::fs::write(dir.path().join("crates/CLAUDE.md"), "Claude notes\n").unwrap();
+
+        let found = Instructions::find(&sub).unwrap();
+        assert_eq!(found.path, dir.path().join("crates/CLAUDE.md"));
+        assert!(found.read_by_agent());
+        assert_eq!(found.prompt_section(), None);
+
+        std::fs::write(sub.join(".nexus/NEXUS.md"), "App rules\n").unwrap();
+        let found = Instructions::find(&sub).unwrap();
+        assert_eq!(found.path, sub.join(".nexus/NEXUS.md"));
+        let section = found.prompt_section().unwrap();
+        assert!(section.starts_with("<project_instructions source="));
+        assert!(section.contains("App rules\n</project_instructions>"));
+
+        assert_eq!(Instructions::find(dir.path()).unwrap().text, "Use cargo nextest.\n");
+    }
+}
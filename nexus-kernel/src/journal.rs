@@ -0,0 +1,425 @@
+//! Crash journal for in-flight blocks.
+//!
+//! Output lives in the UI's memory until a block finishes, so a crash
+//! mid-command loses everything it printed. The journal appends each block's
+//! start, output chunks and structured value to `~/.nexus/journal/` as JSON
+//! lines, one file per window. Every entry is a single `write`, so the data
+//! survives the process dying (not the machine losing power).
+//!
+//! When the store is encrypted, every entry is sealed with the store key and
+//! written wrapped in a `sealed` entry, so the journal never holds plaintext
+//! the database wouldn't.
+//!
+//! When no block is in flight the file is truncated, and a clean shutdown
+//! deletes it. On startup, journals left behind by processes that are no
+//! longer running are replayed: blocks that started but never finished come
+//! back as [`RecoveredBlock`]s with whatever output was captured.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Utc};
+use crate::encryption::StoreCipher;
+use crate::persistence::Store;
+use nexus_api::{BlockId, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Stop journaling a block's output past this many bytes; the tail of a
+/// runaway build log isn't worth unbounded disk writes.
+const MAX_BLOCK_BYTES: usize = 4 * 1024 * 1024;
+
+/// Distinguishes journals of several windows in one process.
+static NEXT_JOURNAL: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "snake_case")]
+enum Entry {
+    Start { id: u64, command: String, at: DateTime<Utc> },
+    /// Raw terminal bytes, base64-encoded.
+    Out { id: u64, data: String },
+    Value { id: u64, value: Value },
+    Truncated { id: u64 },
+    End { id: u64 },
+    /// Another entry's JSON, sealed with the store key.
+    Sealed { data: String },
+}
+
+/// A block that was still running when its process died.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredBlock {
+    pub block_id: BlockId,
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+    /// Raw terminal output captured before the crash.
+    pub output: Vec<u8>,
+    /// Structured output, for native commands.
+    pub value: Option<Value>,
+    /// Output exceeded the journal's per-block cap.
+    pub truncated: bool,
+}
+
+impl RecoveredBlock {
+    /// The captured output as a value suitable for the store.
+    pub fn output_value(&self) -> Value {
+        match &self.value {
+            Some(value) => value.clone(),
+            None => Value::String(String::from_utf8_lossy(&self.output).into_owned()),
+        }
+    }
+}
+
+/// Append-only journal of the blocks running in one window.
+#[derive(Default)]
+pub struct BlockJournal {
+    /// `None` when journaling is disabled (tests, unwritable home).
+    file: Option<(File, PathBuf)>,
+    /// In-flight blocks and the output bytes journaled for each.
+    in_flight: HashMap<BlockId, usize>,
+    /// Seals each line when the store is encrypted.
+    cipher: Option<StoreCipher>,
+}
+
+impl BlockJournal {
+    /// A journal that records nothing.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Open a fresh journal under `~/.nexus/journal/`, sealed when `store`
+    /// is encrypted. Falls back to a disabled journal (with a warning) if
+    /// the directory isn't writable, and when there is no store: then
+    /// there's no telling whether block content must be encrypted.
+    pub fn open_default(store: Option<&Store>) -> Self {
+        let Some(store) = store else {
+            tracing::warn!("Block journal disabled: no persistence store");
+            return Self::disabled();
+        };
+        let cipher = if store.is_encrypted() { store.cipher().cloned() } else { None };
+        match default_dir().and_then(|dir| Self::open_in(&dir, cipher)) {
+            Ok(journal) => journal,
+            Err(e) => {
+                tracing::warn!("Block journal disabled: {}", e);
+                Self::disabled()
+            }
+        }
+    }
+
+    /// Open a fresh journal file in `dir`, sealing lines with `cipher`.
+    pub fn open_in(dir: &Path, cipher: Option<StoreCipher>) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+        let n = NEXT_JOURNAL.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("{}-{}.jsonl", std::process::id(), n));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open journal: {:?}", path))?;
+        Ok(Self { file: Some((file, path)), in_flight: HashMap::new(), cipher })
+    }
+
+    /// Record that a block started running.
+    pub fn started(&mut self, block_id: BlockId, command: &str) {
+        if self.file.is_none() {
+            return;
+        }
+        self.in_flight.insert(block_id, 0);
+        self.append(&Entry::Start { id: block_id.0, command: command.to_string(), at: Utc::now() });
+    }
+
+    /// Record a chunk of terminal output.
+    pub fn output(&mut self, block_id: BlockId, data: &[u8]) {
+        let Some(written) = self.in_flight.get_mut(&block_id) else {
+            return;
+        };
+        if *written >= MAX_BLOCK_BYTES {
+            return;
+        }
+        *written += data.len();
+        let entry = if *written >= MAX_BLOCK_BYTES {
+            Entry::Truncated { id: block_id.0 }
+        } else {
+            Entry::Out { id: block_id.0, data: BASE64.encode(data) }
+        };
+        self.append(&entry);
+    }
+
+    /// Record a native command's structured output.
+    pub fn value(&mut self, block_id: BlockId, value: &Value) {
+        if self.in_flight.contains_key(&block_id) {
+            self.append(&Entry::Value { id: block_id.0, value: value.clone() });
+        }
+    }
+
+    /// Record that a block finished. Truncates the file once nothing is in flight.
+    pub fn finished(&mut self, block_id: BlockId) {
+        if self.in_flight.remove(&block_id).is_none() {
+            return;
+        }
+        if self.in_flight.is_empty() {
+            if let Some((file, _)) = &self.file
+                && let Err(e) = file.set_len(0)
+            {
+                tracing::warn!("Failed to compact block journal: {}", e);
+            }
+        } else {
+            self.append(&Entry::End { id: block_id.0 });
+        }
+    }
+
+    fn append(&mut self, entry: &Entry) {
+        let Some((file, _)) = &mut self.file else {
+            return;
+        };
+        let sealed;
+        let entry = match &self.cipher {
+            Some(cipher) => {
+                let Ok(json) = serde_json::to_string(entry) else {
+                    return;
+                };
+                sealed = Entry::Sealed { data: cipher.seal(&json) };
+                &sealed
+            }
+            None => entry,
+        };
+        let Ok(mut line) = serde_json::to_vec(entry) else {
+            return;
+        };
+        line.push(b'\n');
+        if let Err(e) = file.write_all(&line) {
+            tracing::warn!("Block journal write failed, disabling: {}", e);
+            self.file = None;
+        }
+    }
+
+    /// Replay and delete journals in `~/.nexus/journal/` left by dead
+    /// processes, opening sealed lines with the store key.
+    pub fn recover_default(store: Option<&Store>) -> Vec<RecoveredBlock> {
+        match default_dir() {
+            Ok(dir) => Self::recover_in(&dir, store.and_then(Store::cipher)),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Replay and delete journals in `dir` whose owning process has exited.
+    pub fn recover_in(dir: &Path, cipher: Option<&StoreCipher>) -> Vec<RecoveredBlock> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut recovered = Vec::new();
+        for path in entries.flatten().map(|e| e.path()) {
+            let Some(pid) = journal_pid(&path) else {
+                continue;
+            };
+            if process_alive(pid) {
+                continue;
+            }
+            match File::open(&path) {
+                Ok(file) => recovered.extend(replay(BufReader::new(file), cipher)),
+                Err(e) => tracing::warn!("Failed to read journal {:?}: {}", path, e),
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        recovered.sort_by_key(|b| b.started_at);
+        recovered
+    }
+}
+
+impl Drop for BlockJournal {
+    fn drop(&mut self) {
+        // Clean shutdown: nothing to recover.
+        if let Some((_, path)) = self.file.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Rebuild unfinished blocks from journal lines. A torn final line (the
+/// process died mid-write) is skipped, as are sealed lines `cipher` can't
+/// open.
+fn replay(reader: impl BufRead, cipher: Option<&StoreCipher>) -> Vec<RecoveredBlock> {
+    let mut blocks: BTreeMap<u64, RecoveredBlock> = BTreeMap::new();
+    let mut unreadable = 0;
+    for line in reader.lines().map_while(Result::ok) {
+        let Ok(mut entry) = serde_json::from_str::<Entry>(&line) else {
+            continue;
+        };
+        if let Entry::Sealed { data } = &entry {
+            let opened = cipher.and_then(|cipher| cipher.open(data).ok());
+            match opened.and_then(|json| serde_json::from_str(&json).ok()) {
+                Some(inner) => entry = inner,
+                None => {
+                    unreadable += 1;
+                    continue;
+                }
+            }
+        }
+        match entry {
+            Entry::Start { id, command, at } => {
+                blocks.insert(id, RecoveredBlock {
+                    block_id: BlockId(id),
+                    command,
+                    started_at: at,
+                    output: Vec::new(),
+                    value: None,
+                    truncated: false,
+                });
+            }
+            Entry::Out { id, data } => {
+                if let (Some(block), Ok(bytes)) = (blocks.get_mut(&id), BASE64.decode(data)) {
+                    block.output.extend_from_slice(&bytes);
+                }
+            }
+            Entry::Value { id, value } => {
+                if let Some(block) = blocks.get_mut(&id) {
+                    block.value = Some(value);
+                }
+            }
+            Entry::Truncated { id } => {
+                if let Some(block) = blocks.get_mut(&id) {
+                    block.truncated = true;
+                }
+            }
+            Entry::End { id } => {
+                blocks.remove(&id);
+            }
+            // Only written around another entry.
+            Entry::Sealed { .. } => {}
+        }
+    }
+    if unreadable > 0 {
+        tracing::warn!("Skipped {} encrypted journal entries (no store key)", unreadable);
+    }
+    blocks.into_values().collect()
+}
+
+fn default_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME environment variable not set")?;
+    Ok(PathBuf::from(home).join(".nexus").join("journal"))
+}
+
+/// Parse the owning pid out of `<pid>-<n>.jsonl`.
+fn journal_pid(path: &Path) -> Option<u32> {
+    if path.extension()? != "jsonl" {
+        return None;
+    }
+    path.file_stem()?.to_str()?.split('-').next()?.parse().ok()
+}
+
+fn process_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    // Signal 0 checks existence; EPERM means it exists but isn't ours.
+    !matches!(kill(Pid::from_raw(pid as i32), None), Err(Errno::ESRCH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(journal: &BlockJournal) -> Vec<RecoveredBlock> {
+        let (_, path) = journal.file.as_ref().unwrap();
+        replay(BufReader::new(File::open(path).unwrap()), journal.cipher.as_ref())
+    }
+
+    #[test]
+    fn test_unfinished_blocks_are_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = BlockJournal::open_in(dir.path(), None).unwrap();
+        journal.started(BlockId(1), "make");
+        journal.output(BlockId(1), b"compiling...\r\n");
+        journal.started(BlockId(2), "ls");
+        journal.value(BlockId(2), &Value::Int(3));
+        journal.finished(BlockId(2));
+        journal.output(BlockId(1), b"linking");
+
+        let recovered = read(&journal);
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].command, "make");
+        assert_eq!(recovered[0].output, b"compiling...\r\nlinking");
+        assert!(!recovered[0].truncated);
+    }
+
+    #[test]
+    fn test_idle_journal_is_compacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = BlockJournal::open_in(dir.path(), None).unwrap();
+        journal.started(BlockId(1), "sleep 1");
+        journal.output(BlockId(1), b"x");
+        journal.finished(BlockId(1));
+
+        let (_, path) = journal.file.as_ref().unwrap();
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_torn_line_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = BlockJournal::open_in(dir.path(), None).unwrap();
+        journal.started(BlockId(7), "yes");
+        journal.output(BlockId(7), &vec![b'y'; MAX_BLOCK_BYTES]);
+        journal.output(BlockId(7), b"more");
+        {
+            let (file, _) = journal.file.as_mut().unwrap();
+            file.write_all(b"{\"t\":\"out\",\"id\":7,\"da").unwrap();
+        }
+
+        let recovered = read(&journal);
+        assert_eq!(recovered.len(), 1);
+        assert!(recovered[0].truncated);
+        assert!(recovered[0].output.is_empty());
+    }
+
+    #[test]
+    fn test_recover_skips_live_processes_and_deletes_dead() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut live = BlockJournal::open_in(dir.path(), None).unwrap();
+        live.started(BlockId(1), "vim");
+
+        // A journal from a pid that can't exist.
+        let dead = dir.path().join(format!("{}-0.jsonl", i32::MAX));
+        std::fs::write(&dead, "{\"t\":\"start\",\"id\":3,\"command\":\"top\",\"at\":\"2026-01-01T00:00:00Z\"}\n").unwrap();
+
+        let recovered = BlockJournal::recover_in(dir.path(), None);
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].command, "top");
+        assert!(!dead.exists());
+        assert!(live.file.as_ref().unwrap().1.exists());
+    }
+
+    #[test]
+    fn test_sealed_journal_needs_the_store_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cipher = StoreCipher::new(&[7; 32]);
+        let mut journal = BlockJournal::open_in(dir.path(), Some(cipher.clone())).unwrap();
+        journal.started(BlockId(1), "cat secrets.txt");
+        journal.output(BlockId(1), b"hunter2");
+
+        let (_, path) = journal.file.as_ref().unwrap();
+        let raw = std::fs::read_to_string(path).unwrap();
+        assert!(!raw.contains("secrets.txt"));
+        assert!(raw.lines().all(|l| matches!(serde_json::from_str(l), Ok(Entry::Sealed { .. }))));
+
+        let recovered = read(&journal);
+        assert_eq!(recovered[0].command, "cat secrets.txt");
+        assert_eq!(recovered[0].output, b"hunter2");
+        assert!(replay(BufReader::new(File::open(path).unwrap()), None).is_empty());
+    }
+
+    #[test]
+    fn test_disabled_journal_is_inert() {
+        let mut journal = BlockJournal::disabled();
+        journal.started(BlockId(1), "ls");
+        journal.output(BlockId(1), b"x");
+        assert!(journal.in_flight.is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Kernel commands that run beside each other.
+//!
+//! The session's [`Kernel`] sits behind a lock, and a command used to hold
+//! it until it finished. A line that only reads the session runs on a
+//! [`KernelLease`] instead: a kernel of its own, with its own parser and a
+//! [`ShellState::fork`] of the session, taken and handed back under the lock
+//! but run without it. Lines that change the session — `cd`, `export`,
+//! assignments, function definitions, job control, background jobs — still
+//! run on the kernel itself, one at a time.
+//!
+//! [`Kernel::release`] copies back what a leased command leaves for later
+//! ones, its exit code and stored outputs; anything else it changes stays in
+//! the fork.
+
+use nexus_api::BlockId;
+
+use crate::containers::ContainerExec;
+use crate::overrides::ExecOverride;
+use tree_sitter::Node;
+
+use crate::parser::Parser;
+use crate::{Kernel, ShellState};
+
+/// Commands that change the session, or read the job table a fork doesn't
+/// have.
+const SESSION_COMMANDS: &[&str] = &[
+    "cd", "pushd", "popd", "export", "unset", "set", "shopt", "alias", "unalias", "source", ".", "eval",
+    "readonly", "local", "declare", "typeset", "let", "shift", "getopts", "read", "trap", "exec",
+    "exit", "hash", "jobs", "fg", "bg", "wait", "disown", "kill", "config", "for", "select",
+    "function", "activate", "deactivate",
+];
+
+/// Commands that run the command in their arguments.
+const PREFIX_WORDS: &[&str] = &["time", "profile", "builtin", "command"];
+
+/// A kernel for running one command beside the session's.
+pub struct KernelLease {
+    kernel: Kernel,
+    forked_at: u64,
+}
+
+impl KernelLease {
+    /// The forked state, e.g. to move it to the block's directory.
+    pub fn state_mut(&mut self) -> &mut ShellState {
+        &mut self.kernel.state
+    }
+
+    /// Run `input` as [`Kernel::execute_supervised`] would.
+    pub fn execute_supervised(&mut self, input: &str, block_id: BlockId) -> anyhow::Result<i32> {
+        self.kernel.execute_supervised(input, block_id)
+    }
+}
+
+impl Kernel {
+    /// A lease for running `input` without holding the kernel, or `None`
+    /// when it may change the session and must run here.
+    pub fn lease(&self, input: &str) -> Option<KernelLease> {
+        let mut parser = match Parser::new() {
+            Ok(parser) => parser,
+            Err(e) => {
+                tracing::warn!("Failed to create parser for lease: {}", e);
+                return None;
+            }
+        };
+        if mutates_state(&mut parser, input, &self.state) {
+            return None;
+        }
+        let kernel = Kernel {
+            state: self.state.fork(),
+            event_tx: self.event_tx.clone(),
+            parser,
+            commands: self.commands.clone(),
+            store: None,
+            session_id: None,
+            shell_history: None,
+            events_dropped: self.events_dropped.clone(),
+            filesystem: crate::filesystem::FilesystemProvider::new(),
+            executables: self.executables.clone(),
+            leased: true,
+        };
+        Some(KernelLease { kernel, forked_at: self.state.outputs_stored() })
+    }
+
+    /// Take back a lease once its command has finished: its exit code and
+    /// stored outputs become the session's.
+    pub fn release(&mut self, lease: KernelLease) {
+        self.state.last_exit_code = lease.kernel.state.last_exit_code;
+        self.state.adopt_outputs(&lease.kernel.state, lease.forked_at);
+    }
+}
+
+/// Whether `line` may change the session, judged from its syntax tree.
+/// Errs towards yes: assignments, declarations, arithmetic that assigns,
+/// session builtins, function calls and background jobs count, wherever
+/// they are in the line, and so does a line that doesn't parse.
+pub fn mutates_state(parser: &mut Parser, line: &str, state: &ShellState) -> bool {
+    let line = ExecOverride::split(line).map_or(line, |(_, rest)| rest);
+    // A container command changes the container, not the session.
+    if ContainerExec::split(line).is_some() {
+        return false;
+    }
+    let line = crate::expand_aliases(&crate::preprocess_input(line), state);
+    match parser.tree(&line) {
+        Ok(tree) => node_mutates(&tree.root_node(), &line, state),
+        Err(_) => true,
+    }
+}
+
+fn node_mutates(node: &Node, source: &str, state: &ShellState) -> bool {
+    let text = &source[node.byte_range()];
+    let mutates = match node.kind() {
+        "variable_assignment" | "declaration_command" | "unset_command" | "function_definition"
+        | "for_statement" | "c_style_for_statement" | "&" => true,
+        "arithmetic_expansion" => arithmetic_assigns(text),
+        // `${name:=default}`
+        "expansion" => text.contains(":="),
+        "command" | "test_command" if text.starts_with("((") => arithmetic_assigns(text),
+        "command" => command_mutates(node, source, state),
+        _ => false,
+    };
+    let mut cursor = node.walk();
+    mutates || node.children(&mut cursor).any(|child| node_mutates(&child, source, state))
+}
+
+/// Whether a simple command is a session builtin, a function, or
+/// `env refresh`, looking past `time` and the like.
+fn command_mutates(node: &Node, source: &str, state: &ShellState) -> bool {
+    let Some(name) = node.child_by_field_name("name") else {
+        return false;
+    };
+    let mut cursor = node.walk();
+    let mut words = std::iter::once(&source[name.byte_range()])
+        .chain(node.children_by_field_name("argument", &mut cursor).map(|arg| &source[arg.byte_range()]))
+        .skip_while(|word| PREFIX_WORDS.contains(word));
+    let name = words.next().unwrap_or("");
+    SESSION_COMMANDS.contains(&name)
+        || state.functions.contains_key(name)
+        || (name == "env" && words.next() == Some("refresh"))
+}
+
+/// Whether an arithmetic expression assigns: `=`, `+=` and the other
+/// compound operators, `++` and `--`, as opposed to `==`, `!=`, `<=`, `>=`.
+fn arithmetic_assigns(expr: &str) -> bool {
+    if expr.contains("++") || expr.contains("--") {
+        return true;
+    }
+    let bytes = expr.as_bytes();
+    bytes.iter().enumerate().any(|(i, &b)| {
+        let before = i.checked_sub(1).map(|j| bytes[j]);
+        b == b'='
+            && bytes.get(i + 1) != Some(&b'=')
+            && match before {
+                Some(b'=' | b'!') => false,
+                // `<<=` and `>>=` assign; `<=` and `>=` compare.
+                Some(c @ (b'<' | b'>')) => i >= 2 && bytes[i - 2] == c,
+                _ => true,
+            }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexus_api::Value;
+
+    #[test]
+    fn test_mutates_state() {
+        let mut parser = Parser::new().unwrap();
+        let mut state = ShellState::from_cwd(std::env::current_dir().unwrap());
+        for line in [
+            "ls | head", "echo a && echo b", "ls 2>&1 | wc", "in /tmp ls", "nexus exec -c web cd /app", "_2 | sort",
+            "if test -d x; then ls; fi", "env", "env FOO=1 printenv", "(( n > 5 ))", "(( a == b || a <= b ))",
+            "echo $((n + 1))", "grep -c x=1 file",
+        ] {
+            assert!(!mutates_state(&mut parser, line, &state), "{}", line);
+        }
+        for line in [
+            "cd /tmp", "ls && cd ..", "FOO=1", "sleep 5 &", "greet() { echo hi; }", "for x in 1 2; do echo $x; done",
+            "jobs", "profile source build.sh", "if true; then export A=1; fi", "command cd /", "! cd /",
+        ] {
+            assert!(mutates_state(&mut parser, line, &state), "{}", line);
+        }
+
+        state.aliases.insert("up".to_string(), "cd ..".to_string());
+        assert!(mutates_state(&mut parser, "up", &state));
+    }
+
+    #[test]
+    fn test_assignments_and_env_refresh_mutate() {
+        let mut parser = Parser::new().unwrap();
+        let state = ShellState::from_cwd(std::env::current_dir().unwrap());
+        for line in [
+            "env refresh", "ls; env refresh", "((i++))", "(( n = 5 ))", "(( n <<= 1 ))", "x+=1", "PATH+=:/opt/bin",
+            "echo $((i++))", "echo ${x:=3}",
+        ] {
+            assert!(mutates_state(&mut parser, line, &state), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_release_adopts_outputs() {
+        let (mut kernel, _rx) = Kernel::ephemeral().unwrap();
+        kernel.state.store_output(BlockId(1), "before".to_string(), Value::Int(1));
+        assert!(kernel.lease("cd /").is_none());
+
+        let mut lease = kernel.lease("echo leased").unwrap();
+        kernel.state.store_output(BlockId(2), "meanwhile".to_string(), Value::Int(2));
+        lease.execute_supervised("echo leased", BlockId(3)).unwrap();
+        kernel.release(lease);
+
+        let commands: Vec<_> = kernel.state.block_outputs.iter().map(|o| o.command.as_str()).collect();
+        assert_eq!(commands, ["echo leased", "meanwhile", "before"]);
+    }
+
+    #[test]
+    fn test_lease_leaves_session_traps_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("exited");
+        let (mut kernel, _rx) = Kernel::ephemeral().unwrap();
+        kernel.execute(&format!("trap 'touch {}' EXIT; trap '' INT", marker.display())).unwrap();
+
+        let mut lease = kernel.lease("echo leased").unwrap();
+        lease.execute_supervised("echo leased", BlockId(1)).unwrap();
+        kernel.release(lease);
+
+        assert!(!marker.exists());
+        assert!(kernel.state.traps.contains_key(&crate::traps::EXIT));
+        assert!(!crate::traps::interrupt(BlockId(2)));
+
+        drop(kernel);
+        assert!(marker.exists());
+        assert!(crate::traps::interrupt(BlockId(3)));
+        crate::traps::forget(BlockId(3));
+    }
+}
@@ -5,17 +5,80 @@
 //! - Evaluator (AST walker)
 //! - State management
 //! - In-process commands (ls, cat, etc.)
-//! - Persistence (SQLite-backed sessions and blocks)
+//! - Persistence (SQLite-backed sessions and blocks, optionally encrypted)
+//! - Crash journal for blocks still running when the UI dies
 //! - Native shell history integration
-//! - Tab completion
+//! - Importing aliases, environment and history from zsh, bash or fish
+//! - History expansion (`!!`, `!$`, `^old^new`)
+//! - Low-power state shared with long-running commands
+//! - Connectivity state, so network features fail fast while offline
+//! - Panic isolation for command evaluation
+//! - Conformance corpus and fuzzer comparing evaluation against bash
+//!   (`conformance` feature)
+//! - Runtime diagnostics (recent warnings, span timings, channel health)
+//! - Local usage insights (what gets used, what hasn't been tried)
+//! - Crash reports written by a panic hook, submitted only when asked
+//! - Replay log so lagging event subscribers can resync blocks losslessly
+//! - Sandboxed WebAssembly plugins providing extra commands
+//! - Layered user and per-project configuration (`.nexus/config.toml`)
+//! - Opt-in checks for new releases, and staging their downloads
+//! - Tab completion, with cached previews of directories being completed
+//! - Previews of the stored outputs `$_` / `$_N` refer to
+//! - Per-command directory and environment overrides (`in <dir> ...`)
+//! - Commands run inside a container (`nexus exec -c <container> ...`)
+//! - Leases for running commands that don't change the session concurrently
+//! - Command tracing (`set -x`), per-command timing (`profile`) and
+//!   resource usage (`time`)
+//! - Stepping through scripts (`debug <script>`)
+//! - ShellCheck-style lint of command lines and scripts (`lint <file>`)
+//! - Titles for finished blocks and summaries of past sessions
+//! - The login shell's environment, for sessions not started from a shell
 
 pub mod commands;
 pub mod completion;
+pub mod config;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+pub mod containers;
+pub mod crash;
+pub mod debug;
+pub mod dev_env;
+pub mod diagnostics;
+pub mod egress;
+pub mod encryption;
 pub mod eval;
+pub mod executables;
+pub mod file_watch;
+pub mod filesystem;
+pub mod history_expansion;
+pub mod insights;
+pub mod instructions;
+pub mod journal;
+pub mod lease;
+pub mod lint;
+pub mod login_env;
+pub mod network;
+pub mod opener;
+pub mod outputs;
+pub mod overrides;
 pub mod parser;
 pub mod persistence;
+pub mod plugins;
+pub mod power;
+pub mod problems;
 pub mod process;
+pub mod profile;
+pub mod project_tasks;
+pub mod repl;
+pub mod replay;
+pub mod scheduler;
 pub mod shell_history;
+pub mod shell_import;
+pub mod supervisor;
+pub mod test_report;
+pub mod titles;
+pub mod traps;
+pub mod update;
 
 mod error;
 mod state;
@@ -27,15 +90,16 @@ pub use eval::is_builtin;
 pub use parser::Parser;
 pub use persistence::Store;
 pub use shell_history::{ShellHistory, ShellHistoryEntry};
-pub use state::{ShellState, TrapAction};
+pub use state::{ShellState, StateCheckpoint, TrapAction};
 
 /// Check if a word is a shell keyword that tree-sitter parses as a statement
-/// (flow-control and pipeline modifiers handled by the kernel's parser/evaluator).
+/// (flow-control and pipeline modifiers handled by the kernel's parser/evaluator),
+/// or starts an arithmetic command, `((expr))`.
 fn is_shell_keyword(name: &str) -> bool {
     matches!(
         name,
         "if" | "while" | "until" | "for" | "case" | "function" | "watch"
-    )
+    ) || name.starts_with("((")
 }
 
 /// Check if a command is a remote transport command that should be intercepted
@@ -93,6 +157,16 @@ fn is_remote_transport(command: &str) -> bool {
     }
 }
 
+/// Check if any command in a line runs under `sudo`.
+///
+/// sudo reads its password from the controlling terminal, so such lines
+/// must run in a PTY (even pipelines) where the UI can answer the prompt.
+fn invokes_sudo(command: &str) -> bool {
+    command
+        .split(['|', ';', '&', '(', ')', '\n'])
+        .any(|segment| segment.split_whitespace().next() == Some("sudo"))
+}
+
 /// Classification of how a command should be executed.
 ///
 /// The UI uses this to decide whether to route through the kernel
@@ -113,13 +187,26 @@ pub enum CommandClassification {
     RemoteTransport,
 }
 
-use nexus_api::ShellEvent;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+
+use nexus_api::{BlockId, ShellEvent, Value};
 use tokio::sync::broadcast;
 
+/// Capacity of the kernel's event broadcast channel.
+const EVENT_CAPACITY: usize = 1024;
+
+/// Largest terminal grid, in cells, whose snapshot is kept with its
+/// colors; about 20,000 lines of 100 columns.
+pub const MAX_SNAPSHOT_CELLS: usize = 2_000_000;
+
+/// Status of a block Ctrl-C ended, as for a command killed by SIGINT.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
 /// The shell kernel - owns interpreter state and executes commands.
 pub struct Kernel {
     state: ShellState,
-    event_tx: broadcast::Sender<ShellEvent>,
+    event_tx: replay::EventSender,
     parser: parser::Parser,
     commands: CommandRegistry,
     /// SQLite-backed persistence for sessions and blocks.
@@ -128,12 +215,28 @@ pub struct Kernel {
     session_id: Option<i64>,
     /// Native shell history (reads/writes ~/.zsh_history or ~/.bash_history).
     shell_history: Option<ShellHistory>,
+    /// Events lost by subscribers that fell behind (reported in diagnostics).
+    events_dropped: Arc<AtomicU64>,
+    /// Directory previews for the completion popup.
+    filesystem: filesystem::FilesystemProvider,
+    /// Executables on `PATH`, for [`Kernel::command_status`].
+    executables: executables::Executables,
+    /// Set on a [`KernelLease`]'s kernel, which shares the session's traps
+    /// and so must not run or release them when it goes away.
+    leased: bool,
 }
 
 impl Kernel {
     /// Create a new kernel with an event broadcast channel.
     pub fn new() -> anyhow::Result<(Self, broadcast::Receiver<ShellEvent>)> {
-        let (event_tx, event_rx) = broadcast::channel(1024);
+        // Runs while the store and plugins open.
+        let login_env = (!login_env::inherited()).then(|| std::thread::spawn(login_env::capture_login_shell));
+
+        let (event_tx, event_rx) = replay::EventSender::channel(EVENT_CAPACITY);
+        let weak_tx = event_tx.broadcast().downgrade();
+        let events_dropped = diagnostics::register_channel("kernel.events", Some(EVENT_CAPACITY), move || {
+            weak_tx.upgrade().map(|tx| tx.len())
+        });
 
         // Try to open persistence store (non-fatal if it fails)
         let (store, session_id) = match Store::open_default() {
@@ -142,6 +245,10 @@ impl Kernel {
                     .map(|p| p.display().to_string())
                     .unwrap_or_else(|_| "/".to_string());
                 let session_id = store.start_session(&cwd).ok();
+                if let Some(id) = session_id {
+                    persistence::open_session(id);
+                }
+                persistence::spawn_retention_task();
                 (Some(store), session_id)
             }
             Err(e) => {
@@ -156,14 +263,69 @@ impl Kernel {
             tracing::warn!("Could not detect shell history file; history will be in-memory only");
         }
 
-        let kernel = Self {
-            state: ShellState::new()?,
+        let plugins = plugins::PluginHost::open(plugins::PluginHost::default_dirs());
+
+        let mut state = ShellState::new()?;
+        state.enable_config();
+        match login_env.map(|capture| capture.join()) {
+            Some(Ok(Ok(env))) => {
+                state.apply_login_env(env);
+            }
+            Some(Ok(Err(e))) => tracing::warn!("Could not read the login shell's environment: {:#}", e),
+            Some(Err(_)) | None => {}
+        }
+
+        let mut kernel = Self {
+            state,
             event_tx,
             parser: parser::Parser::new()?,
-            commands: CommandRegistry::new(),
+            commands: CommandRegistry::with_plugins(Arc::new(plugins)),
             store,
             session_id,
             shell_history,
+            events_dropped,
+            filesystem: filesystem::FilesystemProvider::new(),
+            executables: executables::Executables::new(),
+            leased: false,
+        };
+        kernel.import_rc_aliases();
+        Ok((kernel, event_rx))
+    }
+
+    /// Take up the aliases in the startup files of the shell the user came
+    /// from, read as `config import` reads them. Aliases that only add
+    /// flags to a command Nexus runs natively (`ls='ls -G'`) are left out:
+    /// those flags are for the external tool.
+    fn import_rc_aliases(&mut self) {
+        let Some(home) = std::env::var_os("HOME").map(std::path::PathBuf::from) else {
+            return;
+        };
+        let shell = shell_import::detect_shell(&home);
+        let commands = &self.commands;
+        self.state.rc_aliases = shell_import::ShellImport::scan(shell, &home)
+            .aliases
+            .into_iter()
+            .filter(|(name, value)| !(commands.contains(name) && value.split_whitespace().next() == Some(name)))
+            .collect();
+    }
+
+    /// Create a kernel with no persistence store or native history, for
+    /// throwaway evaluation (conformance runs, tests) that must not leave sessions
+    /// or history entries behind.
+    pub fn ephemeral() -> anyhow::Result<(Self, broadcast::Receiver<ShellEvent>)> {
+        let (event_tx, event_rx) = replay::EventSender::channel(EVENT_CAPACITY);
+        let kernel = Self {
+            state: ShellState::new()?,
+            event_tx,
+            parser: parser::Parser::new()?,
+            commands: CommandRegistry::new(),
+            store: None,
+            session_id: None,
+            shell_history: None,
+            events_dropped: Arc::new(AtomicU64::new(0)),
+            filesystem: filesystem::FilesystemProvider::new(),
+            executables: executables::Executables::new(),
+            leased: false,
         };
         Ok((kernel, event_rx))
     }
@@ -182,10 +344,13 @@ impl Kernel {
     ///
     /// Returns `CommandClassification::Pty` for:
     /// - Single external commands (git, vim, etc.)
+    /// - Anything invoking `sudo`, which needs a terminal for its password prompt
     ///
     /// This method centralizes the decision logic so both UI and tests
     /// use the same classification.
     pub fn classify_command(&self, command: &str) -> CommandClassification {
+        // `in <dir> ...` runs wherever the command after it would.
+        let command = overrides::ExecOverride::split(command).map_or(command, |(_, rest)| rest);
         let has_pipe = command.contains('|');
         let first_word = command.split_whitespace().next().unwrap_or("");
 
@@ -195,6 +360,11 @@ impl Kernel {
             return CommandClassification::RemoteTransport;
         }
 
+        // Container commands get a terminal for interactive tools.
+        if invokes_sudo(command) || containers::ContainerExec::split(command).is_some() {
+            return CommandClassification::Pty;
+        }
+
         let is_native = self.commands.contains(first_word);
         let is_shell_builtin = is_builtin(first_word);
         let is_keyword = is_shell_keyword(first_word);
@@ -206,6 +376,47 @@ impl Kernel {
         }
     }
 
+    /// Whether the command `line` starts with exists: a native command,
+    /// builtin, keyword, function, alias, path or executable on `PATH`.
+    /// Never waits for `PATH` to be scanned; the answer is
+    /// [`CommandStatus::Unchecked`] until it has been.
+    pub fn command_status(&self, line: &str) -> executables::CommandStatus {
+        use executables::CommandStatus;
+        // What exists inside a container is only known by running it.
+        if containers::ContainerExec::split(line).is_some() {
+            return CommandStatus::Unchecked;
+        }
+        let Some(name) = executables::command_word(line) else {
+            return CommandStatus::Unchecked;
+        };
+        if self.commands.contains(name)
+            || is_builtin(name)
+            || is_shell_keyword(name)
+            || self.state.functions.contains_key(name)
+            || self.state.alias(name).is_some()
+        {
+            return CommandStatus::Known;
+        }
+        if name.contains('/') {
+            // Relative to a directory `in` has yet to resolve.
+            if overrides::ExecOverride::split(line).is_some() {
+                return CommandStatus::Unchecked;
+            }
+            if self.state.cwd.join(name).exists() {
+                return CommandStatus::Known;
+            }
+            return CommandStatus::Unknown { suggestion: None };
+        }
+        let Some(index) = self.executables.get(self.state.get_var("PATH").unwrap_or_default()) else {
+            return CommandStatus::Unchecked;
+        };
+        if index.contains(name) {
+            return CommandStatus::Known;
+        }
+        let suggestion = index.closest(name, self.commands.names());
+        CommandStatus::Unknown { suggestion }
+    }
+
     /// Get a reference to the current shell state.
     pub fn state(&self) -> &ShellState {
         &self.state
@@ -249,19 +460,120 @@ impl Kernel {
         input: &str,
         block_id: Option<nexus_api::BlockId>,
     ) -> anyhow::Result<i32> {
+        let _span = tracing::info_span!("kernel.execute", block = ?block_id, command = %input).entered();
+
+        // `in <dir> [NAME=value ...] cmd`: run the rest with a scoped
+        // directory and environment, then put the session back.
+        if let Some((over, rest)) = overrides::ExecOverride::split(input) {
+            let scope = match self.state.push_override(&over) {
+                Ok(scope) => scope,
+                Err(e) => {
+                    let block_id = state::get_or_create_block_id(block_id);
+                    let dir = over.dir.unwrap_or_default();
+                    let _ = self.event_tx.send(ShellEvent::StderrChunk {
+                        block_id,
+                        data: format!("in: {}: {}\n", dir, e).into_bytes(),
+                    });
+                    let _ = self.event_tx.send(ShellEvent::CommandFinished { block_id, exit_code: 1, duration_ms: 0 });
+                    self.state.last_exit_code = 1;
+                    return Ok(1);
+                }
+            };
+            let result = self.execute_with_block_id(rest, block_id);
+            self.state.pop_override(scope);
+            return result;
+        }
+
+        // `nexus exec -c <container> cmd`: run the rest inside a container.
+        if let Some((exec, rest)) = containers::ContainerExec::split(input) {
+            return containers::execute(&mut self.state, &exec, rest, &self.event_tx, block_id);
+        }
+
         // Handle pipeline continuation: `| cmd` becomes `_ | cmd`
-        let processed_input = preprocess_input(input);
+        let processed_input = expand_aliases(&preprocess_input(input), &self.state);
 
         let ast = self.parser.parse(&processed_input)?;
-        let exit_code = eval::execute_with_block_id(
+        let result = eval::execute_with_block_id(
             &mut self.state,
             &ast,
             &self.event_tx,
             &self.commands,
             block_id,
-        )?;
+        );
+        if let Some(id) = block_id {
+            traps::forget(id);
+        }
 
-        Ok(exit_code)
+        match result {
+            Err(e) if matches!(e.downcast_ref::<ShellError>(), Some(ShellError::Interrupted)) => {
+                self.state.conditions = 0;
+                self.state.last_exit_code = INTERRUPTED_EXIT_CODE;
+                Ok(INTERRUPTED_EXIT_CODE)
+            }
+            Err(e) if let Some(&ShellError::Aborted(code)) = e.downcast_ref::<ShellError>() => {
+                self.state.conditions = 0;
+                self.state.last_exit_code = code;
+                Ok(code)
+            }
+            result => result,
+        }
+    }
+
+    /// Execute a command line, containing any panic to this block.
+    ///
+    /// On panic the shell state is rolled back to how it was before the
+    /// command, the parser is rebuilt, and the block is finished with
+    /// [`supervisor::PANIC_EXIT_CODE`] plus a `KernelPanic` crash report.
+    pub fn execute_supervised(&mut self, input: &str, block_id: nexus_api::BlockId) -> anyhow::Result<i32> {
+        let checkpoint = self.state.checkpoint();
+        let started = nexus_api::Stopwatch::start();
+
+        let report = match supervisor::run(|| self.execute_with_block_id(input, Some(block_id))) {
+            Ok(result) => return result,
+            Err(report) => report,
+        };
+
+        tracing::error!(
+            "Command panicked in block {:?}: {} ({})",
+            block_id,
+            report.message,
+            report.location.as_deref().unwrap_or("unknown location")
+        );
+        self.state.restore(checkpoint);
+        self.state.last_exit_code = supervisor::PANIC_EXIT_CODE;
+        match parser::Parser::new() {
+            Ok(parser) => self.parser = parser,
+            Err(e) => tracing::warn!("Failed to rebuild parser after panic: {}", e),
+        }
+        commands::unregister_cancel(block_id);
+
+        let _ = self.event_tx.send(ShellEvent::StderrChunk {
+            block_id,
+            data: format!("nexus: command panicked: {}\n", report.message).into_bytes(),
+        });
+        let _ = self.event_tx.send(ShellEvent::KernelPanic {
+            block_id,
+            message: report.message,
+            location: report.location,
+            backtrace: report.backtrace,
+        });
+        let _ = self.event_tx.send(ShellEvent::CommandFinished {
+            block_id,
+            exit_code: supervisor::PANIC_EXIT_CODE,
+            duration_ms: started.elapsed_ms(),
+        });
+        Ok(supervisor::PANIC_EXIT_CODE)
+    }
+
+    /// Check background jobs for stops and exits, emitting
+    /// `JobStateChanged` for each one that changed. Cheap enough to call on
+    /// every UI tick.
+    pub fn reap_jobs(&mut self) {
+        process::reap_jobs(&mut self.state, &self.event_tx);
+        // Traps for signals that arrived while idle.
+        if let Err(e) = traps::deliver(&mut self.state, &self.event_tx, &self.commands, None) {
+            tracing::warn!("trap failed: {:#}", e);
+        }
     }
 
     /// Get a reference to the persistence store.
@@ -269,14 +581,240 @@ impl Kernel {
         self.store.as_ref()
     }
 
+    /// Save a block recovered from a crash journal to the store under
+    /// `block_id`, the id it was given in this process. The exit code is
+    /// left empty: the command never finished.
+    pub fn save_recovered(&self, block_id: BlockId, block: &journal::RecoveredBlock) {
+        let (Some(store), Some(session_id)) = (&self.store, self.session_id) else {
+            return;
+        };
+        if let Err(e) = store.save_block(
+            block_id,
+            session_id,
+            &block.command,
+            Some(&block.output_value()),
+            None,
+            None,
+        ) {
+            tracing::warn!("Failed to save recovered block: {}", e);
+        }
+    }
+
+    /// Save a finished block to this session's store, without its output;
+    /// add that with [`Kernel::save_block_output`] or
+    /// [`Kernel::save_block_snapshot`].
+    pub fn record_block(&self, block_id: BlockId, command: &str, exit_code: i32, duration_ms: Option<u64>) {
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.save_block(block_id, session_id, command, None, Some(exit_code), duration_ms)
+        {
+            tracing::warn!("Failed to save block: {}", e);
+        }
+    }
+
+    /// Keep a finished block's structured output.
+    pub fn save_block_output(&self, block_id: BlockId, output: &Value) {
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.save_block_output(session_id, block_id, output)
+        {
+            tracing::warn!("Failed to save block output: {}", e);
+        }
+    }
+
+    /// Keep a finished block's terminal output, colors and all. A grid
+    /// larger than [`MAX_SNAPSHOT_CELLS`] is kept as plain text instead.
+    pub fn save_block_snapshot(&self, block_id: BlockId, grid: &nexus_term::TerminalGrid) {
+        if grid.cells().len() > MAX_SNAPSHOT_CELLS {
+            return self.save_block_output(block_id, &Value::String(grid.to_string()));
+        }
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.save_block_snapshot(session_id, block_id, grid)
+        {
+            tracing::warn!("Failed to save block snapshot: {}", e);
+        }
+    }
+
+    /// Delete this session's stored copy of a block the user removed.
+    pub fn forget_block(&self, block_id: BlockId) {
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.delete_block(session_id, block_id)
+        {
+            tracing::warn!("Failed to delete stored block: {}", e);
+        }
+    }
+
+    /// Remember the title a finished block was given, for this session's
+    /// summary.
+    pub fn record_block_title(&self, block_id: BlockId, title: &str, exit_code: i32) {
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.save_block_title(session_id, block_id, title, Some(exit_code))
+        {
+            tracing::warn!("Failed to save block title: {}", e);
+        }
+    }
+
+    /// Remember what a finished block's process used.
+    pub fn record_block_usage(&self, block_id: BlockId, usage: &nexus_api::ResourceUsage) {
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.save_block_usage(session_id, block_id, usage)
+        {
+            tracing::warn!("Failed to save block resource usage: {}", e);
+        }
+    }
+
+    /// Keep the notes on one of this session's blocks.
+    pub fn record_block_notes(&self, block_id: BlockId, notes: &[persistence::BlockNote]) {
+        if let (Some(store), Some(session_id)) = (&self.store, self.session_id)
+            && let Err(e) = store.save_block_notes(session_id, block_id, notes)
+        {
+            tracing::warn!("Failed to save block notes: {}", e);
+        }
+    }
+
+    /// Keep the notes on a block restored from an earlier session, stored
+    /// in row `row_id`.
+    pub fn save_restored_notes(&self, row_id: i64, notes: &[persistence::BlockNote]) {
+        if let Some(store) = &self.store
+            && let Err(e) = store.save_row_notes(row_id, notes)
+        {
+            tracing::warn!("Failed to save block notes: {}", e);
+        }
+    }
+
+    /// Summary of the last session before this one in which anything ran.
+    pub fn previous_session_summary(&self) -> Option<titles::SessionSummary> {
+        let (store, session_id) = (self.store.as_ref()?, self.session_id?);
+        let summary = store.previous_titled_session(session_id).and_then(|session| {
+            let Some(session) = session else {
+                return Ok(None);
+            };
+            Ok(titles::SessionSummary::new(&session, &store.block_titles(session.id)?))
+        });
+        summary.unwrap_or_else(|e| {
+            tracing::warn!("Failed to load the previous session: {}", e);
+            None
+        })
+    }
+
+    /// The blocks saved by the newest earlier session, without their
+    /// outputs; see [`Kernel::load_block_output`]. Empty without a store.
+    pub fn previous_session_blocks(&self) -> Vec<persistence::SessionBlock> {
+        let (Some(store), Some(session_id)) = (&self.store, self.session_id) else {
+            return Vec::new();
+        };
+        let blocks = store.previous_session_with_blocks(session_id).and_then(|session| match session {
+            Some(session) => store.load_session_blocks(session.id),
+            None => Ok(Vec::new()),
+        });
+        blocks.unwrap_or_else(|e| {
+            tracing::warn!("Failed to load the previous session's blocks: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// The structured output saved for a [`persistence::SessionBlock`].
+    pub fn load_block_output(&self, row_id: i64) -> Option<Value> {
+        let store = self.store.as_ref()?;
+        store.load_block_output(row_id).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load block output: {}", e);
+            None
+        })
+    }
+
+    /// The terminal snapshot saved for a [`persistence::SessionBlock`].
+    pub fn load_block_snapshot(&self, row_id: i64) -> Option<nexus_term::TerminalGrid> {
+        let store = self.store.as_ref()?;
+        store.load_block_snapshot(row_id).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load block snapshot: {}", e);
+            None
+        })
+    }
+
+    /// Count a use of a feature for the insights view. Without a store
+    /// nothing is counted.
+    pub fn record_usage(&self, event: &insights::UsageEvent) {
+        if let Some(store) = &self.store
+            && let Err(e) = event.record(store)
+        {
+            tracing::debug!("Failed to record usage: {}", e);
+        }
+    }
+
+    /// Summary of the recorded feature use.
+    pub fn insights(&self) -> anyhow::Result<insights::Insights> {
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("no persistence store"))?;
+        Ok(insights::Insights::from_counts(&store.usage_counts()?))
+    }
+
+    /// Claim the scheduled commands due now, see [`scheduler::take_due`].
+    /// Without a store nothing is ever due.
+    pub fn take_due_schedules(&self, policy: config::CatchUp) -> Vec<scheduler::DueRun> {
+        let Some(store) = &self.store else {
+            return Vec::new();
+        };
+        scheduler::take_due(store, policy, chrono::Local::now()).unwrap_or_else(|e| {
+            tracing::warn!("Failed to check schedules: {}", e);
+            Vec::new()
+        })
+    }
+
+    /// Record how a scheduled run ended; None for a run that never finished.
+    pub fn finish_schedule_run(&self, run_id: i64, exit_code: Option<i32>, duration_ms: Option<u64>) {
+        if let Some(store) = &self.store
+            && let Err(e) = store.finish_schedule_run(run_id, exit_code, duration_ms)
+        {
+            tracing::warn!("Failed to record scheduled run: {}", e);
+        }
+    }
+
+    /// Record the problems a run of `command` in `cwd` printed, and compare
+    /// them with the previous run's. None on the first run, or when neither
+    /// run had any problems.
+    pub fn record_problems(&self, command: &str, cwd: &str, problems: &[problems::Problem]) -> Option<problems::ProblemDelta> {
+        let store = self.store.as_ref()?;
+        let before = match store.problems(command, cwd) {
+            Ok(before) => before,
+            Err(e) => {
+                tracing::warn!("Failed to load problems: {}", e);
+                return None;
+            }
+        };
+        if before.is_none() && problems.is_empty() {
+            return None;
+        }
+        if let Err(e) = store.save_problems(command, cwd, problems) {
+            tracing::warn!("Failed to record problems: {}", e);
+        }
+        before.map(|before| problems::ProblemDelta::between(&before, problems))
+    }
+
+    /// Forget all recorded feature use.
+    pub fn purge_insights(&self) -> anyhow::Result<usize> {
+        let store = self.store.as_ref().ok_or_else(|| anyhow::anyhow!("no persistence store"))?;
+        store.purge_usage()
+    }
+
     /// Get the current session ID.
     pub fn session_id(&self) -> Option<i64> {
         self.session_id
     }
 
+    /// Counter for events a subscriber lost by lagging behind the channel.
+    /// The UI adds to it so drops show up in `diagnostics`.
+    pub fn dropped_events(&self) -> Arc<AtomicU64> {
+        self.events_dropped.clone()
+    }
+
     /// Get the event sender (for spawning commands that need to emit events).
+    ///
+    /// Events sent on it directly bypass the replay log.
     pub fn event_sender(&self) -> &broadcast::Sender<ShellEvent> {
-        &self.event_tx
+        self.event_tx.broadcast()
+    }
+
+    /// Replay log of recent blocks, for resyncing a lagging subscriber
+    /// without taking the kernel lock.
+    pub fn replay_log(&self) -> replay::ReplayLog {
+        self.event_tx.log().clone()
     }
 
     /// Check if there's a previous output available for pipeline continuation.
@@ -293,6 +831,34 @@ impl Kernel {
         engine.complete(input, cursor)
     }
 
+    /// Preview of the directory a `cd`, `ls` or `pushd` argument completes
+    /// to, for the completion popup. `line` is the input before the word
+    /// being completed.
+    pub fn preview_completion(&self, line: &str, completion: &Completion) -> Option<filesystem::DirectoryPreview> {
+        if completion.kind != CompletionKind::Directory {
+            return None;
+        }
+        let command = line.rsplit(['|', ';', '&']).next()?.split_whitespace().next()?;
+        if !matches!(command, "cd" | "ls" | "pushd") {
+            return None;
+        }
+        let path = filesystem::resolve_completion_path(&completion.text, &self.state.cwd, self.state.get_env("HOME"));
+        self.filesystem.preview(&path)
+    }
+
+    /// Preview the stored output named by the last `$_` / `$_N` reference
+    /// before `cursor` in `line`.
+    pub fn preview_output(&self, line: &str, cursor: usize) -> Option<outputs::OutputPreview> {
+        let reference = outputs::reference_before(line, cursor)?;
+        outputs::OutputPreview::lookup(&self.state, reference)
+    }
+
+    /// Lint findings for `line` under the session's config; none while it
+    /// doesn't parse.
+    pub fn lint(&mut self, line: &str) -> Vec<lint::Lint> {
+        lint::lint(&mut self.parser, line, &self.state.config).unwrap_or_default()
+    }
+
     /// Search command history using substring matching on native shell history.
     ///
     /// Returns matching history entries, most recent first.
@@ -313,6 +879,42 @@ impl Kernel {
             .unwrap_or_default()
     }
 
+    /// Expand history references (`!!`, `!N`, `!$`, `^old^new`, ...) in a
+    /// command line against native shell history.
+    ///
+    /// Returns `Ok(None)` if the line has no references. The UI calls this
+    /// before classification and history recording so the expanded line is
+    /// what runs, and on each edit to preview the expansion.
+    pub fn expand_history(&self, input: &str) -> Result<Option<String>, ShellError> {
+        let entries = self
+            .shell_history
+            .as_ref()
+            .map(|h| h.entries())
+            .unwrap_or_default();
+        history_expansion::expand_history(input, entries)
+    }
+
+    /// The history file commands are recorded to, if any.
+    pub fn history_path(&self) -> Option<&std::path::Path> {
+        self.shell_history.as_ref().map(|h| h.path())
+    }
+
+    /// Add commands imported from another shell to history, starting
+    /// `~/.nexus/history` when there is no native file to record to.
+    /// Returns how many were new.
+    pub fn import_history(&mut self, commands: &[String]) -> anyhow::Result<usize> {
+        if self.shell_history.is_none() {
+            let home = std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME is not set"))?;
+            let path = shell_history::nexus_history_path(std::path::Path::new(&home));
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            self.shell_history = Some(ShellHistory::open_path(path, shell_history::HistoryFormat::Plain));
+        }
+        let history = self.shell_history.as_mut().expect("opened above");
+        Ok(history.import(commands)?)
+    }
+
     /// Append a command to native shell history.
     ///
     /// Called from the UI on submit (before execution) so both kernel and PTY
@@ -324,16 +926,181 @@ impl Kernel {
     }
 }
 
+impl Drop for Kernel {
+    /// Run the EXIT trap, stop catching the signals this session trapped,
+    /// and end the session so retention may reclaim it. A lease's kernel
+    /// does none of this: the traps are the session's, and it outlives the
+    /// lease.
+    fn drop(&mut self) {
+        if self.leased {
+            return;
+        }
+        if let Err(e) = traps::run(&mut self.state, traps::EXIT, &self.event_tx, &self.commands, None) {
+            tracing::warn!("EXIT trap failed: {:#}", e);
+        }
+        traps::release(&self.state);
+        if let (Some(store), Some(id)) = (&self.store, self.session_id) {
+            persistence::close_session(id);
+            if let Err(e) = store.end_session(id) {
+                tracing::warn!("Failed to end session {}: {}", id, e);
+            }
+        }
+    }
+}
+
 /// Preprocess input to handle special syntax.
 ///
 /// - Lines starting with `|` become `_ | ...` (pipeline continuation)
+/// - Lines starting with `_N` become `_ N ...`, so any stored output can
+///   start a pipeline, not just `_1` to `_3`
 fn preprocess_input(input: &str) -> String {
     let trimmed = input.trim_start();
 
     // Pipeline continuation: `| cmd` -> `_ | cmd`
     if trimmed.starts_with('|') {
-        format!("_ {}", trimmed)
-    } else {
-        input.to_string()
+        return format!("_ {}", trimmed);
+    }
+
+    // Continuation from a specific output: `_12 | cmd` -> `_ 12 | cmd`
+    if let Some(rest) = trimmed.strip_prefix('_') {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let after = &rest[digits..];
+        if digits > 0 && (after.is_empty() || after.starts_with(|c: char| c.is_whitespace() || c == '|')) {
+            return format!("_ {}{}", &rest[..digits], after);
+        }
+    }
+
+    input.to_string()
+}
+
+/// Reserved words after which a command starts.
+const COMMAND_KEYWORDS: &[&str] = &["if", "then", "else", "elif", "do", "while", "until", "!", "{", "time"];
+
+/// Expand aliases in the first word of each command, as bash does before
+/// parsing. An alias isn't expanded again within its own expansion, so
+/// `ls='ls -G'` terminates, and one whose expansion ends in a blank makes
+/// the next word a command word too (`alias sudo='sudo '`). Quoted words,
+/// comments and here-document bodies are left alone.
+fn expand_aliases(input: &str, state: &ShellState) -> String {
+    let mut line = input.to_string();
+    // Expansions still being read: the alias, where its text ends in
+    // `line`, and whether that text ends in a blank.
+    let mut active: Vec<(String, usize, bool)> = Vec::new();
+    let mut heredocs: Vec<String> = Vec::new();
+    let mut command = true;
+    let mut pos = 0;
+    while let Some(c) = line[pos..].chars().next() {
+        while let Some(&(_, end, blank)) = active.last() {
+            if end > pos {
+                break;
+            }
+            active.pop();
+            command |= blank;
+        }
+        match c {
+            ' ' | '\t' => pos += 1,
+            '\n' => {
+                pos += 1;
+                command = true;
+                for delimiter in std::mem::take(&mut heredocs) {
+                    pos = skip_heredoc(&line, pos, &delimiter);
+                }
+            }
+            '#' => pos = line[pos..].find('\n').map_or(line.len(), |i| pos + i),
+            '<' | '>' => {
+                // The redirection's target isn't a command word.
+                let op = line[pos..].len() - line[pos..].trim_start_matches(['<', '>', '&', '|', '-']).len();
+                let heredoc = matches!(&line[pos..pos + op], "<<" | "<<-");
+                pos += op;
+                pos += line[pos..].len() - line[pos..].trim_start_matches([' ', '\t']).len();
+                let end = word_end(&line, pos);
+                if heredoc {
+                    heredocs.push(line[pos..end].replace(['\'', '"', '\\'], ""));
+                }
+                pos = end;
+            }
+            '&' if line[pos..].starts_with("&>") => pos += 1,
+            ';' | '|' | '&' | '(' | ')' => {
+                pos += 1;
+                command = true;
+            }
+            _ => {
+                let end = word_end(&line, pos).max(pos + c.len_utf8());
+                let word = line[pos..end].to_string();
+                if !command {
+                    pos = end;
+                    continue;
+                }
+                let quoted = word.contains(['\'', '"', '\\', '$', '`']);
+                let expansion = state.alias(&word).filter(|_| !quoted && !active.iter().any(|(name, ..)| *name == word));
+                if let Some(expansion) = expansion {
+                    let expansion = expansion.to_string();
+                    // Every open expansion contains this word.
+                    for (_, open_end, _) in &mut active {
+                        *open_end = (*open_end + expansion.len()).saturating_sub(word.len());
+                    }
+                    let blank = expansion.ends_with([' ', '\t']);
+                    line.replace_range(pos..end, &expansion);
+                    active.push((word, pos + expansion.len(), blank));
+                    continue;
+                }
+                command = COMMAND_KEYWORDS.contains(&word.as_str()) || executables::is_assignment(&word) || word.ends_with("()");
+                pos = end;
+            }
+        }
+    }
+    line
+}
+
+/// Where the shell word starting at `start` ends: at an unquoted blank or
+/// operator outside any `$(..)`, `${..}` or backquotes.
+fn word_end(line: &str, start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut prev = ' ';
+    let mut chars = line[start..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        let after_dollar = std::mem::replace(&mut prev, c) == '$';
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' | '`' => {
+                for (_, q) in chars.by_ref() {
+                    if q == c {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                while let Some((_, q)) = chars.next() {
+                    match q {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '(' => depth += 1,
+            '{' if after_dollar => depth += 1,
+            ')' | '}' if depth > 0 => depth -= 1,
+            ' ' | '\t' | '\n' | ';' | '|' | '&' | '<' | '>' | ')' if depth == 0 => return start + i,
+            _ => {}
+        }
+    }
+    line.len()
+}
+
+/// Where the here-document body starting at `pos` ends: after the line
+/// holding just `delimiter`, or at the end of the input.
+fn skip_heredoc(line: &str, mut pos: usize, delimiter: &str) -> usize {
+    while pos < line.len() {
+        let end = line[pos..].find('\n').map_or(line.len(), |i| pos + i + 1);
+        if line[pos..end].trim_end_matches('\n').trim_start_matches('\t') == delimiter {
+            return end;
+        }
+        pos = end;
     }
+    pos
 }
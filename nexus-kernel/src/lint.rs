@@ -0,0 +1,257 @@
+//! Static checks of command lines and scripts, in the spirit of ShellCheck.
+//!
+//! [`lint`] walks the syntax tree of a line and reports constructs that
+//! usually do something other than what was meant:
+//!
+//! - `unquoted-expansion`: `$VAR` as an argument, split on spaces and globbed
+//! - `rm-variable-path`: `rm -rf $DIR/...`, which starts from `/` when `DIR` is empty
+//! - `useless-cat`: `cat file | cmd`, where `cmd < file` does the same
+//! - `glob-without-dashes`: `cmd *`, where a file named `-rf` becomes an option
+//!
+//! The input bar underlines findings while a line is typed, and `lint <file>`
+//! checks a script. Nothing is refused: findings are advice. `[lint]` in the
+//! config changes a rule's severity or turns it off.
+
+use std::ops::Range;
+
+use tree_sitter::Node;
+
+use crate::config::{Config, LintSeverity};
+use crate::{Parser, ShellError};
+
+/// A check [`lint`] makes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintRule {
+    UnquotedExpansion,
+    RmVariablePath,
+    UselessCat,
+    GlobWithoutDashes,
+}
+
+impl LintRule {
+    pub const ALL: [Self; 4] = [Self::UnquotedExpansion, Self::RmVariablePath, Self::UselessCat, Self::GlobWithoutDashes];
+
+    /// The name used in the config file and in `lint` output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UnquotedExpansion => "unquoted-expansion",
+            Self::RmVariablePath => "rm-variable-path",
+            Self::UselessCat => "useless-cat",
+            Self::GlobWithoutDashes => "glob-without-dashes",
+        }
+    }
+
+    /// Why the rule exists and what to write instead.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Self::UnquotedExpansion => {
+                "An unquoted expansion is split on whitespace and each piece is globbed, so a value \
+                 with spaces or `*` becomes several arguments. Write \"$VAR\"."
+            }
+            Self::RmVariablePath => {
+                "If the variable is empty or unset the path starts at `/`. Write \"${VAR:?}\"/... \
+                 so rm refuses to run when it is."
+            }
+            Self::UselessCat => "The command can read the file itself: `cmd < file` or `cmd file` saves a process.",
+            Self::GlobWithoutDashes => {
+                "A file whose name starts with `-` would be read as an option. Write ./* or put -- \
+                 before the glob."
+            }
+        }
+    }
+
+    fn default_severity(self) -> LintSeverity {
+        match self {
+            Self::UnquotedExpansion | Self::GlobWithoutDashes => LintSeverity::Warning,
+            Self::RmVariablePath => LintSeverity::Error,
+            Self::UselessCat => LintSeverity::Info,
+        }
+    }
+}
+
+/// One finding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    /// Byte range of the offending text in the linted source.
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+/// Check `source`, with severities from `config`. Fails only if it doesn't
+/// parse.
+pub fn lint(parser: &mut Parser, source: &str, config: &Config) -> Result<Vec<Lint>, ShellError> {
+    let tree = parser.tree(source)?;
+    let mut found = Vec::new();
+    check(&tree.root_node(), source, &mut found);
+    Ok(found
+        .into_iter()
+        .filter_map(|(rule, range, message)| {
+            let severity = config.lint_severity(rule.name()).unwrap_or(rule.default_severity());
+            (severity != LintSeverity::Off).then_some(Lint { rule, severity, range, message })
+        })
+        .collect())
+}
+
+type Finding = (LintRule, Range<usize>, String);
+
+fn check(node: &Node, source: &str, found: &mut Vec<Finding>) {
+    let text = &source[node.byte_range()];
+    match node.kind() {
+        "simple_expansion" | "expansion" if is_split(node, source) && !is_numeric(text) => {
+            found.push((
+                LintRule::UnquotedExpansion,
+                node.byte_range(),
+                format!("{} is unquoted, so it is split on spaces and globbed", text),
+            ));
+        }
+        "command" => check_command(node, source, found),
+        "pipeline" => check_pipeline(node, source, found),
+        _ => {}
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        check(&child, source, found);
+    }
+}
+
+/// Whether an expansion sits where the shell splits and globs it: a command
+/// argument, a redirection target, or an operand of `[ ]`.
+fn is_split(node: &Node, source: &str) -> bool {
+    let mut parent = node.parent();
+    if parent.is_some_and(|p| p.kind() == "concatenation") {
+        parent = parent.and_then(|p| p.parent());
+    }
+    while let Some(p) = parent.filter(|p| matches!(p.kind(), "binary_expression" | "unary_expression")) {
+        parent = p.parent();
+        if parent.is_some_and(|p| p.kind() == "test_command") {
+            return parent.is_some_and(|p| !source[p.byte_range()].starts_with("[["));
+        }
+    }
+    parent.is_some_and(|p| matches!(p.kind(), "command" | "file_redirect"))
+}
+
+/// `$?`, `$#`, `$$`, `$!` and `${#VAR}` are numbers, which never split.
+fn is_numeric(expansion: &str) -> bool {
+    matches!(expansion, "$?" | "$#" | "$$" | "$!") || expansion.starts_with("${#")
+}
+
+fn check_command(node: &Node, source: &str, found: &mut Vec<Finding>) {
+    let text = |n: &Node| &source[n.byte_range()];
+    let Some(name) = node.child_by_field_name("name") else {
+        return;
+    };
+    let mut cursor = node.walk();
+    let args: Vec<Node> = node.children_by_field_name("argument", &mut cursor).collect();
+
+    if text(&name) == "rm" {
+        for arg in &args {
+            if let Some(var) = leading_variable(text(arg)) {
+                found.push((
+                    LintRule::RmVariablePath,
+                    arg.byte_range(),
+                    format!("if ${} is empty this removes from /", var),
+                ));
+            }
+        }
+    }
+
+    if !matches!(text(&name), "echo" | "printf") {
+        for arg in args.iter().take_while(|arg| text(arg) != "--") {
+            let unquoted = arg.kind() == "word"
+                || (arg.kind() == "concatenation" && arg.child(0).is_some_and(|c| c.kind() == "word"));
+            if unquoted && text(arg).starts_with(['*', '?']) {
+                found.push((
+                    LintRule::GlobWithoutDashes,
+                    arg.byte_range(),
+                    format!("{} can match a file named like an option: use ./{} or -- {}", text(arg), text(arg), text(arg)),
+                ));
+            }
+        }
+    }
+}
+
+/// The name of the `$NAME` or `${NAME}` at the start of `arg`, quoted or
+/// not, when a `/` follows it.
+fn leading_variable(arg: &str) -> Option<&str> {
+    let arg = arg.strip_prefix('"').unwrap_or(arg);
+    let rest = arg.strip_prefix('$')?;
+    let (name, after) = match rest.strip_prefix('{') {
+        Some(braced) => {
+            let end = braced.find('}')?;
+            (&braced[..end], &braced[end + 1..])
+        }
+        None => {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        }
+    };
+    let plain = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let after = after.strip_prefix('"').unwrap_or(after);
+    (plain && after.starts_with('/')).then_some(name)
+}
+
+fn check_pipeline(node: &Node, source: &str, found: &mut Vec<Finding>) {
+    let Some(first) = node.named_child(0).filter(|n| n.kind() == "command") else {
+        return;
+    };
+    if first.child_by_field_name("name").is_none_or(|name| &source[name.byte_range()] != "cat") {
+        return;
+    }
+    let mut cursor = first.walk();
+    let args: Vec<Node> = first.children_by_field_name("argument", &mut cursor).collect();
+    if let [file] = args.as_slice()
+        && !source[file.byte_range()].starts_with('-')
+    {
+        found.push((
+            LintRule::UselessCat,
+            first.byte_range(),
+            format!("cat of one file into a pipe: the next command can read < {}", &source[file.byte_range()]),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Origin, Setting};
+
+    fn rules(line: &str, config: &Config) -> Vec<(&'static str, String)> {
+        let mut parser = Parser::new().unwrap();
+        lint(&mut parser, line, config)
+            .unwrap()
+            .into_iter()
+            .map(|l| (l.rule.name(), line[l.range].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let config = Config::default();
+        assert_eq!(rules("echo $x \"$y\" $? ${#z}", &config), [("unquoted-expansion", "$x".to_string())]);
+        assert_eq!(rules("[ -n $x ] && [[ -n $x ]]", &config), [("unquoted-expansion", "$x".to_string())]);
+        assert!(rules("x=$y; echo \"$x\" > \"$out\"", &config).is_empty());
+        assert_eq!(rules("rm -rf \"$DIR/\"*", &config), [("rm-variable-path", "\"$DIR/\"*".to_string())]);
+        assert_eq!(rules("rm -rf \"${DIR:?}/\"*", &config), []);
+        assert_eq!(
+            rules("cat notes.txt | grep todo", &config),
+            [("useless-cat", "cat notes.txt".to_string())]
+        );
+        assert!(rules("cat a b | sort", &config).is_empty());
+        assert_eq!(rules("cp * /tmp", &config), [("glob-without-dashes", "*".to_string())]);
+        assert!(rules("cp -- * /tmp; cp ./* /tmp; echo *", &config).is_empty());
+    }
+
+    #[test]
+    fn test_config_turns_rules_off() {
+        let mut config = Config::default();
+        let origin = Origin::User("config.toml".into());
+        config.lint.insert("useless-cat".to_string(), Setting { value: LintSeverity::Off, origin: origin.clone() });
+        config.lint.insert("unquoted-expansion".to_string(), Setting { value: LintSeverity::Error, origin });
+        let mut parser = Parser::new().unwrap();
+        let lints = lint(&mut parser, "cat f | grep $x", &config).unwrap();
+        assert_eq!(lints.len(), 1);
+        assert_eq!((lints[0].rule, lints[0].severity), (LintRule::UnquotedExpansion, LintSeverity::Error));
+    }
+}
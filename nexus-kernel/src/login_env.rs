@@ -0,0 +1,186 @@
+//! The environment the user's login shell sets up.
+//!
+//! Started from the Dock or Finder, Nexus inherits launchd's bare
+//! environment: none of the `PATH` entries, version managers (nvm, pyenv,
+//! rbenv) or exports from `~/.zprofile`, `~/.zshrc` or `config.fish`. So
+//! at startup the login shell is run once, interactively, to print its
+//! environment, and that is applied to the session; `env refresh` does it
+//! again after editing those files.
+//!
+//! Nothing is captured when Nexus was started from a shell, which already
+//! handed down that environment. Startup files can check
+//! `NEXUS_LOGIN_ENV` to skip slow work that doesn't export anything.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long the login shell gets to start up and print its environment.
+pub const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Printed before the environment, so anything the startup files print
+/// can be told apart from it.
+const MARKER: &str = "__NEXUS_LOGIN_ENV__";
+
+/// Variables describing the capturing shell itself rather than the session.
+const SKIPPED: &[&str] = &["PWD", "OLDPWD", "SHLVL", "_", "NEXUS_LOGIN_ENV"];
+
+/// Whether Nexus was started from a shell, so its environment already has
+/// what a login shell sets up.
+pub fn inherited() -> bool {
+    std::env::var_os("SHLVL").is_some()
+}
+
+/// The user's login shell: `$SHELL`, or else their entry in the password
+/// database.
+pub fn login_shell() -> Option<PathBuf> {
+    if let Some(shell) = std::env::var_os("SHELL").filter(|s| !s.is_empty()) {
+        return Some(PathBuf::from(shell));
+    }
+    password_entry_shell()
+}
+
+#[cfg(unix)]
+fn password_entry_shell() -> Option<PathBuf> {
+    // SAFETY: getpwuid returns a pointer into static storage, read before
+    // any other password database call.
+    unsafe {
+        let pwd = libc::getpwuid(libc::getuid());
+        if pwd.is_null() || (*pwd).pw_shell.is_null() {
+            return None;
+        }
+        let shell = std::ffi::CStr::from_ptr((*pwd).pw_shell).to_string_lossy().into_owned();
+        (!shell.is_empty()).then(|| PathBuf::from(shell))
+    }
+}
+
+#[cfg(not(unix))]
+fn password_entry_shell() -> Option<PathBuf> {
+    None
+}
+
+/// Run the user's login shell and return its environment.
+pub fn capture_login_shell() -> anyhow::Result<HashMap<String, String>> {
+    let shell = login_shell().ok_or_else(|| anyhow::anyhow!("no login shell: SHELL is not set"))?;
+    capture(&shell, CAPTURE_TIMEOUT)
+}
+
+/// Run `shell` as an interactive login shell (`-l -i -c`), so it reads
+/// the same startup files as a terminal tab, and return the environment it
+/// ends up with. zsh, bash, fish and other shells taking those flags work.
+pub fn capture(shell: &Path, timeout: Duration) -> anyhow::Result<HashMap<String, String>> {
+    let mut child = Command::new(shell)
+        .args(["-l", "-i", "-c", &format!("echo {MARKER}; env -0")])
+        .env("NEXUS_LOGIN_ENV", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("failed to run {}: {}", shell.display(), e))?;
+
+    // Startup files can print any amount, so read while waiting.
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let reader = std::thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = stdout.read_to_end(&mut out);
+        out
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait()? {
+            Some(status) => break status,
+            None if started.elapsed() < timeout => std::thread::sleep(Duration::from_millis(20)),
+            None => {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!("{} took more than {}s to start", shell.display(), timeout.as_secs());
+            }
+        }
+    };
+    let out = reader.join().unwrap_or_default();
+
+    match parse(&out) {
+        Some(env) => Ok(env),
+        None if !status.success() => anyhow::bail!("{} exited with {}", shell.display(), status),
+        None => anyhow::bail!("{} did not print its environment", shell.display()),
+    }
+}
+
+/// The `env -0` output following the marker line, without [`SKIPPED`]
+/// variables.
+fn parse(out: &[u8]) -> Option<HashMap<String, String>> {
+    let out = String::from_utf8_lossy(out);
+    let (_, env) = out.split_once(&format!("{MARKER}\n"))?;
+    Some(
+        env.split('\0')
+            .filter_map(|entry| entry.split_once('='))
+            .filter(|(name, _)| !name.is_empty() && !SKIPPED.contains(name))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_shell(dir: &Path, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("fake-shell");
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_capture_skips_startup_output() {
+        let dir = tempfile::tempdir().unwrap();
+        // Reads "startup files", prints noise, then runs the -c script.
+        let shell = fake_shell(
+            dir.path(),
+            "echo 'Welcome back'\nexport PATH=\"/opt/nvm/bin:$PATH\" FROM_PROFILE='a=b\nc'\neval \"$4\"",
+        );
+
+        let env = capture(&shell, CAPTURE_TIMEOUT).unwrap();
+        assert!(env["PATH"].starts_with("/opt/nvm/bin:"));
+        assert_eq!(env["FROM_PROFILE"], "a=b\nc");
+        assert!(!env.contains_key("PWD"));
+        assert!(!env.contains_key("NEXUS_LOGIN_ENV"));
+    }
+
+    #[test]
+    fn test_configured_env_wins() {
+        use crate::config::{Origin, Setting};
+
+        let mut state = crate::ShellState::new().unwrap();
+        state.config.env.insert("EDITOR".to_string(), Setting { value: "nvim".to_string(), origin: Origin::User(Default::default()) });
+        state.set_env("EDITOR", "nvim");
+        state.set_env("UNCHANGED", "same");
+
+        let captured = HashMap::from([
+            ("EDITOR".to_string(), "nano".to_string()),
+            ("UNCHANGED".to_string(), "same".to_string()),
+            ("NVM_DIR".to_string(), "/home/me/.nvm".to_string()),
+        ]);
+        assert_eq!(state.apply_login_env(captured), vec!["NVM_DIR"]);
+        assert_eq!(state.get_env("EDITOR"), Some("nvim"));
+    }
+
+    #[test]
+    fn test_capture_failures() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let shell = fake_shell(dir.path(), "echo 'no env here'; exit 3");
+        let err = capture(&shell, CAPTURE_TIMEOUT).unwrap_err();
+        assert!(err.to_string().contains("exited with"), "{err}");
+
+        let shell = fake_shell(dir.path(), "sleep 5");
+        let err = capture(&shell, Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("to start"), "{err}");
+
+        assert!(capture(&dir.path().join("missing"), CAPTURE_TIMEOUT).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Process-wide connectivity state.
+//!
+//! The UI probes for a network route every few seconds and records the
+//! result here; the agent, the update checker and crash submission consult
+//! [`is_online`] so that losing the network costs a banner and a status-bar
+//! pill instead of a string of timeouts. Shared by every window, like
+//! [`crate::power`].
+//!
+//! The probe only asks the OS for a route: connecting a UDP socket sends
+//! nothing, so it is cheap enough to repeat and leaks no traffic. A route
+//! with a dead upstream still counts as online; requests made then fail
+//! the usual way.
+
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use nexus_api::{CommandError, CommandErrorKind};
+
+static ONLINE: AtomicBool = AtomicBool::new(true);
+
+/// Public resolvers, one per address family. Nothing is sent to them.
+const PROBE_ADDRS: [&str; 2] = ["1.1.1.1:53", "[2606:4700:4700::1111]:53"];
+
+/// First wait of a [`Backoff`]...
+const BACKOFF_START: Duration = Duration::from_secs(30);
+/// ...doubling up to this.
+const BACKOFF_MAX: Duration = Duration::from_secs(30 * 60);
+
+/// Whether the last probe found a route to the internet.
+pub fn is_online() -> bool {
+    ONLINE.load(Ordering::Relaxed)
+}
+
+pub fn set_online(online: bool) {
+    ONLINE.store(online, Ordering::Relaxed);
+}
+
+/// Look for a route and record the result. Returns whether one exists.
+pub fn probe() -> bool {
+    let online = PROBE_ADDRS.iter().any(|addr| {
+        let addr: SocketAddr = addr.parse().expect("probe address");
+        let local: SocketAddr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().expect("bind address");
+        UdpSocket::bind(local).and_then(|socket| socket.connect(addr)).is_ok()
+    });
+    set_online(online);
+    online
+}
+
+/// Fail at once when offline, rather than waiting out a timeout. `command`
+/// names what wanted the network, for the error.
+pub fn require_online(command: &str) -> Result<(), CommandError> {
+    if is_online() {
+        Ok(())
+    } else {
+        Err(CommandError::new(command, CommandErrorKind::Unsupported, "offline: no network connection"))
+    }
+}
+
+/// Waits between retries of background network work: doubling from
+/// thirty seconds to half an hour.
+#[derive(Debug)]
+pub struct Backoff {
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self { next: BACKOFF_START }
+    }
+
+    /// How long to wait before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(BACKOFF_MAX);
+        delay
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_to_cap() {
+        let mut backoff = Backoff::new();
+        let delays: Vec<u64> = (0..8).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 960, 1800, 1800]);
+    }
+
+    #[test]
+    fn test_require_online_fails_fast() {
+        set_online(false);
+        let err = require_online("update").unwrap_err();
+        assert_eq!(err.kind, CommandErrorKind::Unsupported);
+        set_online(true);
+        assert!(require_online("update").is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+//! Handing files and URLs to the desktop: opening them in their default
+//! (or a named) application and revealing them in the file manager.
+//!
+//! Used by the `open` command and by clicked paths and URLs in the UI, so
+//! both honour the `[open]` applications in [`crate::config`].
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Whether `target` is a URL rather than a path.
+pub fn is_url(target: &str) -> bool {
+    let scheme_end = match target.find(':') {
+        Some(i) => i,
+        None => return false,
+    };
+    let scheme = &target[..scheme_end];
+    let valid_scheme = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid_scheme && (target[scheme_end..].starts_with("://") || scheme == "mailto")
+}
+
+/// Open `target`, a path or URL, with `app` or the system default.
+pub fn open(target: &str, app: Option<&str>) -> io::Result<()> {
+    launch(open_command(target, app))
+}
+
+/// Show `path` selected in the file manager.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    match launch(reveal_command(path)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            // No file manager service to ask; open the containing folder.
+            let parent = path.parent().unwrap_or(path);
+            open(&parent.to_string_lossy(), None)
+        }
+        result => result,
+    }
+}
+
+/// Spawn detached from the shell's terminal, reaping the child off-thread.
+fn launch(mut cmd: Command) -> io::Result<()> {
+    let mut child = cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn open_command(target: &str, app: Option<&str>) -> Command {
+    let mut cmd = Command::new("open");
+    if let Some(app) = app {
+        cmd.args(["-a", app]);
+    }
+    cmd.arg(target);
+    cmd
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_command(target: &str, app: Option<&str>) -> Command {
+    let mut cmd = Command::new(app.unwrap_or("xdg-open"));
+    cmd.arg(target);
+    cmd
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(path: &Path) -> Command {
+    let mut cmd = Command::new("open");
+    cmd.arg("-R").arg(path);
+    cmd
+}
+
+/// The freedesktop `FileManager1` interface, which Nautilus, Dolphin,
+/// Nemo and most others implement.
+#[cfg(not(target_os = "macos"))]
+fn reveal_command(path: &Path) -> Command {
+    let mut cmd = Command::new("dbus-send");
+    cmd.args([
+        "--session",
+        "--dest=org.freedesktop.FileManager1",
+        "--type=method_call",
+        "/org/freedesktop/FileManager1",
+        "org.freedesktop.FileManager1.ShowItems",
+    ])
+    .arg(format!("array:string:file://{}", path.display()))
+    .arg("string:");
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/a?b=c"));
+        assert!(is_url("file:///tmp/x"));
+        assert!(is_url("mailto:someone@example.com"));
+        assert!(!is_url("src/main.rs"));
+        assert!(!is_url("/tmp/report:v2.txt"));
+        assert!(!is_url("C:/Users"));
+        assert!(!is_url("."));
+    }
+
+    #[test]
+    fn test_open_command_uses_app() {
+        let cmd = open_command("notes.md", Some("code"));
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args.last().unwrap().to_str(), Some("notes.md"));
+        #[cfg(target_os = "macos")]
+        assert_eq!(args, ["-a", "code", "notes.md"]);
+        #[cfg(not(target_os = "macos"))]
+        assert_eq!(cmd.get_program(), "code");
+    }
+}
@@ -0,0 +1,159 @@
+//! Previews of stored command outputs.
+//!
+//! `$_` / `$prev` name the last output and `$_1`, `$_2`, ... the recent ones
+//! (1 = most recent). [`OutputPreview`] summarizes what such a reference
+//! holds — its type, size, and first rows — so the input bar can show it
+//! while a pipeline continuation is being written.
+
+use nexus_api::Value;
+
+use crate::ShellState;
+
+/// Rows shown in a preview.
+const SAMPLE_ROWS: usize = 3;
+/// Characters kept from each sample row.
+const SAMPLE_WIDTH: usize = 80;
+
+/// What a `$_` / `$_N` reference holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputPreview {
+    /// The reference as written, without `$` or braces (`_`, `prev`, `_3`).
+    pub reference: String,
+    /// The command that produced the output, if known.
+    pub command: Option<String>,
+    /// Type and size, e.g. `table 12×3`.
+    pub shape: String,
+    /// Column names, for tables.
+    pub columns: Vec<String>,
+    /// The first few rows (or items, fields, lines) as text.
+    pub rows: Vec<String>,
+    /// Rows not shown.
+    pub more: usize,
+}
+
+impl OutputPreview {
+    /// Preview the output `reference` names. `None` if it names nothing.
+    pub fn lookup(state: &ShellState, reference: &str) -> Option<Self> {
+        let (command, value) = match reference {
+            "_" | "prev" => (state.block_outputs.front().map(|o| o.command.clone()), state.get_last_output()?),
+            _ => {
+                let index: usize = reference.strip_prefix('_')?.parse().ok()?;
+                let output = state.block_outputs.get(index.checked_sub(1)?)?;
+                (Some(output.command.clone()), &output.value)
+            }
+        };
+        Some(Self::of(reference, command, value))
+    }
+
+    fn of(reference: &str, command: Option<String>, value: &Value) -> Self {
+        let (columns, lines): (Vec<String>, Vec<String>) = match value {
+            Value::Table { columns, rows } => (
+                columns.iter().map(|c| c.name.clone()).collect(),
+                rows.iter()
+                    .map(|row| row.iter().map(Value::to_text).collect::<Vec<_>>().join("  "))
+                    .collect(),
+            ),
+            Value::List(items) => (Vec::new(), items.iter().map(Value::to_text).collect()),
+            Value::Record(fields) => (
+                Vec::new(),
+                fields.iter().map(|(k, v)| format!("{}: {}", k, v.to_text())).collect(),
+            ),
+            Value::Unit => (Vec::new(), Vec::new()),
+            other => (Vec::new(), other.to_text().lines().map(str::to_string).collect()),
+        };
+        let more = lines.len().saturating_sub(SAMPLE_ROWS);
+        let rows = lines
+            .into_iter()
+            .take(SAMPLE_ROWS)
+            .map(|line| truncate(line.replace('\n', " "), SAMPLE_WIDTH))
+            .collect();
+        Self { reference: reference.to_string(), command, shape: value_shape(value), columns, rows, more }
+    }
+}
+
+/// A short description of a value's type and size, e.g. `table 12×3`.
+pub fn value_shape(value: &Value) -> String {
+    match value {
+        Value::Table { columns, rows } => format!("table {}\u{d7}{}", rows.len(), columns.len()),
+        Value::List(items) => format!("list of {}", items.len()),
+        Value::Record(fields) => format!("record, {} fields", fields.len()),
+        Value::String(s) if s.contains('\n') => format!("text, {} lines", s.lines().count()),
+        Value::Bytes(b) => format!("bytes, {}", b.len()),
+        Value::Unit => "empty".to_string(),
+        other => other.type_name().to_string(),
+    }
+}
+
+/// The last output reference in `line` that starts before `cursor`:
+/// `$_`, `$prev`, `$_N` or the braced `${_N}`, returned without `$` or
+/// braces, or a leading `_` / `_N` continuation. `$_name` is an ordinary
+/// variable, not a reference.
+pub fn reference_before(line: &str, cursor: usize) -> Option<&str> {
+    let line = line.get(..cursor.min(line.len()))?;
+    let trimmed = line.trim_start();
+    let leading = trimmed.find(|c: char| c.is_whitespace() || c == '|').map(|end| &trimmed[..end]);
+    let mut found = leading.filter(|word| word.strip_prefix('_').is_some_and(|n| n.chars().all(|c| c.is_ascii_digit())));
+    for (i, _) in line.match_indices('$') {
+        let rest = &line[i + 1..];
+        let (body, braced) = match rest.strip_prefix('{') {
+            Some(inner) => (inner, true),
+            None => (rest, false),
+        };
+        let len = body.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(body.len());
+        let name = &body[..len];
+        let is_reference = name == "prev"
+            || name.strip_prefix('_').is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()));
+        if is_reference && (!braced || body[len..].starts_with('}') || len == body.len()) {
+            found = Some(name);
+        }
+    }
+    found
+}
+
+fn truncate(mut s: String, max: usize) -> String {
+    if let Some((end, _)) = s.char_indices().nth(max) {
+        s.truncate(end);
+        s.push('\u{2026}');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexus_api::{BlockId, TableColumn};
+
+    #[test]
+    fn test_reference_before() {
+        assert_eq!(reference_before("$_ | head", 9), Some("_"));
+        assert_eq!(reference_before("diff $_2 ${_10}", 15), Some("_10"));
+        assert_eq!(reference_before("diff $_2 ${_10}", 8), Some("_2"));
+        assert_eq!(reference_before("echo $prev", 10), Some("prev"));
+        assert_eq!(reference_before("echo $_name $HOME", 17), None);
+        assert_eq!(reference_before("echo hi", 7), None);
+        assert_eq!(reference_before("_4 | where size > 10", 20), Some("_4"));
+        assert_eq!(reference_before("_4 | diff $_1", 13), Some("_1"));
+        assert_eq!(reference_before("_4", 2), None);
+    }
+
+    #[test]
+    fn test_lookup_previews_table() {
+        let mut state = ShellState::from_cwd(std::env::current_dir().unwrap());
+        let rows = (1..=5).map(|i| vec![Value::String(format!("f{}", i)), Value::Int(i)]).collect();
+        let table = Value::Table { columns: vec![TableColumn::new("name"), TableColumn::new("size")], rows };
+        state.store_output(BlockId(1), "ls".to_string(), table);
+        state.store_output(BlockId(2), "whoami".to_string(), Value::String("ada".to_string()));
+
+        let preview = OutputPreview::lookup(&state, "_2").unwrap();
+        assert_eq!(preview.command.as_deref(), Some("ls"));
+        assert_eq!(preview.shape, "table 5\u{d7}2");
+        assert_eq!(preview.columns, ["name", "size"]);
+        assert_eq!(preview.rows, ["f1  1", "f2  2", "f3  3"]);
+        assert_eq!(preview.more, 2);
+
+        let last = OutputPreview::lookup(&state, "_").unwrap();
+        assert_eq!((last.command.as_deref(), last.rows.as_slice()), (Some("whoami"), ["ada".to_string()].as_slice()));
+        assert!(OutputPreview::lookup(&state, "_3").is_none());
+        assert!(OutputPreview::lookup(&state, "_0").is_none());
+    }
+}
@@ -0,0 +1,224 @@
+//! Per-command directory and environment overrides.
+//!
+//! `in <dir> [NAME=value ...] <command>` runs one command line in another
+//! directory, with extra environment, and leaves the session as it was:
+//!
+//! ```text
+//! in ~/src/app cargo test
+//! in ../web NODE_ENV=test npm test
+//! in RUST_LOG=debug cargo run      # environment only
+//! ```
+//!
+//! The kernel applies an override with [`ShellState::push_override`] around
+//! the command; the UI uses the same parse to spawn terminal commands and to
+//! show the override in the block header.
+
+use std::path::{Path, PathBuf};
+
+use crate::ShellState;
+
+/// Where, and with what environment, a single command runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecOverride {
+    /// The directory as written, before `~` and relative resolution.
+    pub dir: Option<String>,
+    /// Environment variables set for the command only.
+    pub env: Vec<(String, String)>,
+}
+
+impl ExecOverride {
+    /// Split an `in` prefix off `line`, returning the override and the
+    /// command it applies to. `None` when the line has no prefix, or
+    /// nothing follows it.
+    pub fn split(line: &str) -> Option<(Self, &str)> {
+        let rest = line.trim_start().strip_prefix("in")?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let mut parsed = Self::default();
+        let mut rest = rest.trim_start();
+        while let Some((word, after)) = next_word(rest) {
+            match assignment(&word) {
+                Some((name, value)) => parsed.env.push((name.to_string(), value.to_string())),
+                None if parsed.dir.is_none() && parsed.env.is_empty() => parsed.dir = Some(word),
+                None => break,
+            }
+            rest = after.trim_start();
+        }
+        (!rest.is_empty() && (parsed.dir.is_some() || !parsed.env.is_empty())).then_some((parsed, rest))
+    }
+
+    /// The directory, resolved against `cwd`, with a leading `~` meaning
+    /// `home`.
+    pub fn resolve_dir(&self, cwd: &Path, home: Option<&str>) -> Option<PathBuf> {
+        let dir = self.dir.as_deref()?;
+        let dir = match (dir.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+            _ => dir.to_string(),
+        };
+        let path = cwd.join(dir);
+        Some(path.canonicalize().unwrap_or(path))
+    }
+
+    /// `command` as a line for `sh -c` that changes directory and exports
+    /// the environment first, for commands run in a terminal rather than by
+    /// the kernel.
+    pub fn shell_line(&self, command: &str) -> String {
+        let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+        let mut line = String::new();
+        if let Some(dir) = &self.dir {
+            let dir = match dir.strip_prefix("~/") {
+                Some(rest) => format!("\"$HOME\"/{}", quote(rest)),
+                None if dir == "~" => "\"$HOME\"".to_string(),
+                None => quote(dir),
+            };
+            line.push_str(&format!("cd {} && ", dir));
+        }
+        for (name, value) in &self.env {
+            line.push_str(&format!("export {}={}; ", name, quote(value)));
+        }
+        line.push_str(command);
+        line
+    }
+
+    /// How a block header shows the override: `in ~/src/app · RUST_LOG=debug`.
+    pub fn label(&self) -> String {
+        let mut parts: Vec<String> = self.dir.iter().map(|dir| format!("in {}", dir)).collect();
+        parts.extend(self.env.iter().map(|(name, value)| format!("{}={}", name, value)));
+        parts.join(" \u{b7} ")
+    }
+}
+
+/// What an override replaced, handed back to [`ShellState::pop_override`].
+#[derive(Debug)]
+pub struct OverrideScope {
+    pub(crate) cwd: Option<PathBuf>,
+    pub(crate) env: Vec<(String, Option<String>)>,
+}
+
+/// The next whitespace-separated word with quotes and backslashes removed,
+/// and the text after it.
+fn next_word(s: &str) -> Option<(String, &str)> {
+    let mut word = String::new();
+    let mut chars = s.char_indices().peekable();
+    let mut quote = None;
+    while let Some(&(i, c)) = chars.peek() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => return Some((word, &s[i..])),
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '\\') | (Some('"'), '\\') => {
+                chars.next();
+                if let Some(&(_, escaped)) = chars.peek() {
+                    word.push(escaped);
+                }
+            }
+            (_, c) => word.push(c),
+        }
+        chars.next();
+    }
+    (!s.is_empty() && quote.is_none()).then_some((word, ""))
+}
+
+/// `NAME=value`, for a valid variable name.
+fn assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some((name, value))
+}
+
+impl ShellState {
+    /// Apply `over` for one command. Fails, changing nothing, when the
+    /// directory doesn't exist.
+    pub fn push_override(&mut self, over: &ExecOverride) -> std::io::Result<OverrideScope> {
+        let home = self.get_env("HOME").map(str::to_string);
+        let cwd = match over.resolve_dir(&self.cwd, home.as_deref()) {
+            Some(dir) => {
+                let previous = self.cwd.clone();
+                self.set_cwd(dir)?;
+                Some(previous)
+            }
+            None => None,
+        };
+        let env = over
+            .env
+            .iter()
+            .map(|(name, value)| {
+                let previous = self.env.insert(name.clone(), value.clone());
+                (name.clone(), previous)
+            })
+            .collect();
+        Ok(OverrideScope { cwd, env })
+    }
+
+    /// Undo [`ShellState::push_override`]: the directory and the overridden
+    /// variables go back to what they were, whatever the command did to
+    /// them.
+    pub fn pop_override(&mut self, scope: OverrideScope) {
+        for (name, previous) in scope.env.into_iter().rev() {
+            match previous {
+                Some(value) => self.env.insert(name, value),
+                None => self.env.remove(&name),
+            };
+        }
+        if let Some(cwd) = scope.cwd {
+            self.cwd = cwd;
+            self.reload_config();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        let (over, rest) = ExecOverride::split("in ~/src/app cargo test").unwrap();
+        assert_eq!(over.dir.as_deref(), Some("~/src/app"));
+        assert_eq!(rest, "cargo test");
+
+        let (over, rest) = ExecOverride::split("in 'my dir' FOO=\"a b\" BAR=1 make | head").unwrap();
+        assert_eq!(over.dir.as_deref(), Some("my dir"));
+        assert_eq!(over.env, [("FOO".to_string(), "a b".to_string()), ("BAR".to_string(), "1".to_string())]);
+        assert_eq!(rest, "make | head");
+        assert_eq!(over.label(), "in my dir \u{b7} FOO=a b \u{b7} BAR=1");
+
+        let (over, rest) = ExecOverride::split("in RUST_LOG=debug cargo run").unwrap();
+        assert_eq!((over.dir, rest), (None, "cargo run"));
+
+        assert!(ExecOverride::split("in /tmp").is_none());
+        assert!(ExecOverride::split("install foo").is_none());
+        assert!(ExecOverride::split("echo in /tmp ls").is_none());
+    }
+
+    #[test]
+    fn test_shell_line_quotes_values() {
+        let (over, rest) = ExecOverride::split("in ~/src MSG=\"it's\" echo $MSG").unwrap();
+        assert_eq!(over.shell_line(rest), r#"cd "$HOME"/'src' && export MSG='it'\''s'; echo $MSG"#);
+    }
+
+    #[test]
+    fn test_push_and_pop_restore_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        std::fs::create_dir(&sub).unwrap();
+        let mut state = ShellState::from_cwd(dir.path().to_path_buf());
+        state.set_env("KEEP", "1");
+
+        let (over, _) = ExecOverride::split("in sub KEEP=2 NEW=x ls").unwrap();
+        let scope = state.push_override(&over).unwrap();
+        assert_eq!(state.cwd, sub.canonicalize().unwrap());
+        assert_eq!((state.get_env("KEEP"), state.get_env("NEW")), (Some("2"), Some("x")));
+
+        state.set_env("KEEP", "3");
+        state.pop_override(scope);
+        assert_eq!(state.cwd, dir.path());
+        assert_eq!((state.get_env("KEEP"), state.get_env("NEW")), (Some("1"), None));
+
+        let (missing, _) = ExecOverride::split("in nope ls").unwrap();
+        assert!(state.push_override(&missing).is_err());
+        assert_eq!(state.cwd, dir.path());
+    }
+}
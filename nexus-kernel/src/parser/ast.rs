@@ -17,6 +17,8 @@ pub enum Command {
     If(IfStatement),
     While(WhileStatement),
     For(ForStatement),
+    ArithmeticFor(ArithmeticForStatement),
+    Arithmetic(ArithmeticCommand),
     Function(FunctionDef),
     Case(CaseStatement),
     Watch(WatchStatement),
@@ -37,6 +39,8 @@ pub enum Word {
     Literal(String),
     Variable(String),
     CommandSubstitution(String),
+    /// `<(cmd)` or `>(cmd)`, kept whole; expands to a `/dev/fd/N` path.
+    ProcessSubstitution(String),
     // TODO: Glob patterns, brace expansion, etc.
 }
 
@@ -54,6 +58,9 @@ impl Word {
 #[derive(Debug, Clone)]
 pub struct Pipeline {
     pub commands: Vec<Command>,
+    /// Indices of the stages joined by `|>` rather than `|`, which must
+    /// take the previous stage's structured value.
+    pub typed: Vec<usize>,
     pub background: bool,
 }
 
@@ -85,7 +92,8 @@ pub struct Assignment {
     pub value: Word,
 }
 
-/// A redirection: [n]op target
+/// A redirection: [n]op target. For here-documents and here-strings the
+/// target is the text fed to the descriptor rather than a path.
 #[derive(Debug, Clone)]
 pub struct Redirect {
     pub fd: i32,
@@ -100,6 +108,9 @@ pub enum RedirectOp {
     Read,     // <
     DupWrite, // >&
     DupRead,  // <&
+    /// `<<DELIM` / `<<-DELIM`; `expand` is false when the delimiter is quoted.
+    Heredoc { expand: bool },
+    HereString, // <<<
 }
 
 /// An if statement.
@@ -146,10 +157,29 @@ pub struct CaseItem {
     pub commands: Vec<Command>,
 }
 
-/// A watch statement: watch [-n interval] pipeline
+/// A C-style for loop: for ((init; condition; update)); do body; done
+/// An empty condition is always true.
+#[derive(Debug, Clone)]
+pub struct ArithmeticForStatement {
+    pub init: String,
+    pub condition: String,
+    pub update: String,
+    pub body: Vec<Command>,
+}
+
+/// An arithmetic command: ((expr)). Succeeds when expr is non-zero.
+#[derive(Debug, Clone)]
+pub struct ArithmeticCommand {
+    pub expr: String,
+}
+
+/// A watch statement: watch [-n interval] [--on-change pattern]... pipeline
 #[derive(Debug, Clone)]
 pub struct WatchStatement {
     pub interval_ms: u64,
+    /// Glob patterns (`src/**/*.rs`); when any are given the pipeline
+    /// re-runs on a change to a matching file instead of on the interval.
+    pub on_change: Vec<String>,
     pub pipeline: Pipeline,
 }
 
@@ -202,6 +232,7 @@ mod tests {
         assert_eq!(RedirectOp::Append, RedirectOp::Append);
         assert_eq!(RedirectOp::Read, RedirectOp::Read);
         assert_ne!(RedirectOp::Write, RedirectOp::Append);
+        assert_ne!(RedirectOp::Heredoc { expand: true }, RedirectOp::Heredoc { expand: false });
     }
 
     // -------------------------------------------------------------------------
@@ -224,6 +255,7 @@ mod tests {
     fn test_pipeline_debug() {
         let pipeline = Pipeline {
             commands: vec![],
+            typed: vec![],
             background: false,
         };
         let debug_str = format!("{:?}", pipeline);
@@ -279,8 +311,10 @@ mod tests {
     fn test_watch_statement_debug() {
         let watch = WatchStatement {
             interval_ms: 2000,
+            on_change: vec![],
             pipeline: Pipeline {
                 commands: vec![],
+                typed: vec![],
                 background: false,
             },
         };
@@ -24,6 +24,29 @@ impl Parser {
 
     /// Parse a command line into an AST.
     pub fn parse(&mut self, input: &str) -> Result<Ast, ShellError> {
+        let input = hide_extglobs(input);
+        let tree = self.tree(&input)?;
+        build_ast(&tree.root_node(), &input)
+    }
+
+    /// Parse a script into its top-level commands, each with its source
+    /// text.
+    pub fn parse_steps(&mut self, input: &str) -> Result<Vec<(String, Command)>, ShellError> {
+        let input = hide_extglobs(input);
+        let tree = self.tree(&input)?;
+        let root = tree.root_node();
+        let mut cursor = root.walk();
+        let mut steps = Vec::new();
+        for child in root.children(&mut cursor) {
+            if let Some(cmd) = build_command(&child, &input)? {
+                steps.push((node_text(&child, &input), cmd));
+            }
+        }
+        Ok(steps)
+    }
+
+    /// The syntax tree of `input`, refused if it has errors.
+    pub(crate) fn tree(&mut self, input: &str) -> Result<tree_sitter::Tree, ShellError> {
         let tree = self
             .parser
             .parse(input, None)
@@ -35,9 +58,119 @@ impl Parser {
             let error_msg = find_error_message(&root, input);
             return Err(ShellError::Syntax(error_msg));
         }
+        Ok(tree)
+    }
+}
+
+/// Stand-ins for the parentheses and bars of extglob groups (`@(a|b)`,
+/// `!(*.rs)`), which Tree-sitter only accepts in `case` patterns.
+/// [`hide_extglobs`] swaps them in before parsing, so a group stays one
+/// word, and [`node_text`] swaps them back.
+const EXTGLOB_OPEN: char = '\u{E000}';
+const EXTGLOB_BAR: char = '\u{E001}';
+const EXTGLOB_CLOSE: char = '\u{E002}';
+
+/// Marks the command after a `|>`, which Tree-sitter doesn't know.
+/// [`hide_extglobs`] turns `a |> b` into `a | ` followed by this and `b`,
+/// and [`build_pipeline`] takes it off the name and records the stage as
+/// typed.
+const TYPED_PIPE: char = '\u{E003}';
+
+/// `input` with each unquoted extglob group's `(`, `|` and `)` replaced
+/// by stand-ins, and each unquoted `|>` by a pipe and [`TYPED_PIPE`].
+/// Arithmetic (`$((...))`, `((...))`) is left alone, as are `$@(`, `$*(`
+/// and the like, and groups with whitespace in them.
+fn hide_extglobs(input: &str) -> std::borrow::Cow<'_, str> {
+    if !input.contains('(') && !input.contains("|>") {
+        return input.into();
+    }
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut quote = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if let Some(q) = quote {
+            if c == '\\' && q == '"' && i + 1 < chars.len() {
+                out.push(c);
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            if c == q {
+                quote = None;
+            }
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        match c {
+            '\\' => {
+                out.extend(&chars[i..(i + 2).min(chars.len())]);
+                i += 2;
+                continue;
+            }
+            '\'' | '"' => quote = Some(c),
+            '|' if chars.get(i + 1) == Some(&'|') => {
+                out.push_str("||");
+                i += 2;
+                continue;
+            }
+            // `>|` is a clobbering redirect, not a pipe.
+            '|' if chars.get(i + 1) == Some(&'>') && (i == 0 || chars[i - 1] != '>') => {
+                out.push_str("| ");
+                i += 2;
+                while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+                    i += 1;
+                }
+                out.push(TYPED_PIPE);
+                continue;
+            }
+            '(' if chars.get(i + 1) == Some(&'(') => {
+                let end = closing_paren(&chars, i).unwrap_or(chars.len() - 1);
+                out.extend(&chars[i..=end]);
+                i = end + 1;
+                continue;
+            }
+            '?' | '*' | '+' | '@' | '!' if chars.get(i + 1) == Some(&'(') && (i == 0 || chars[i - 1] != '$') => {
+                if let Some(end) = closing_paren(&chars, i + 1)
+                    && !chars[i + 1..end].iter().any(|c| c.is_whitespace())
+                {
+                    out.push(c);
+                    out.extend(chars[i + 1..=end].iter().map(|&c| match c {
+                        '(' => EXTGLOB_OPEN,
+                        '|' => EXTGLOB_BAR,
+                        ')' => EXTGLOB_CLOSE,
+                        c => c,
+                    }));
+                    i = end + 1;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+        i += 1;
+    }
+    out.into()
+}
 
-        build_ast(&root, input)
+/// Index of the `)` closing the `(` at `open`.
+fn closing_paren(chars: &[char], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
     }
+    None
 }
 
 /// Find the first error in the tree and return a descriptive message.
@@ -68,7 +201,12 @@ fn build_ast(node: &Node, source: &str) -> Result<Ast, ShellError> {
     let mut cursor = node.walk();
 
     for child in node.children(&mut cursor) {
-        if let Some(cmd) = build_command(&child, source)? {
+        if child.kind() == "&" {
+            // `cmd &` ends a statement; the command before it runs as a job.
+            if let Some(cmd) = commands.pop() {
+                commands.push(into_background(cmd));
+            }
+        } else if let Some(cmd) = build_command(&child, source)? {
             commands.push(cmd);
         }
     }
@@ -76,18 +214,40 @@ fn build_ast(node: &Node, source: &str) -> Result<Ast, ShellError> {
     Ok(Ast { commands })
 }
 
+/// Mark a command to run in the background, as a one-stage pipeline if it
+/// isn't one already.
+fn into_background(cmd: Command) -> Command {
+    match cmd {
+        Command::Pipeline(mut pipeline) => {
+            pipeline.background = true;
+            Command::Pipeline(pipeline)
+        }
+        cmd => Command::Pipeline(Pipeline {
+            commands: vec![cmd],
+            typed: Vec::new(),
+            background: true,
+        }),
+    }
+}
+
 /// Build a command from a Tree-sitter node.
 fn build_command(node: &Node, source: &str) -> Result<Option<Command>, ShellError> {
     match node.kind() {
+        "command" | "test_command" if arithmetic_command_expr(node, source).is_some() => {
+            let expr = arithmetic_command_expr(node, source).unwrap_or_default();
+            Ok(Some(Command::Arithmetic(ArithmeticCommand { expr })))
+        }
         "command" => {
             let cmd = build_simple_command(node, source)?;
             if cmd.name == "watch" {
                 match parse_watch_args(&cmd.args) {
-                    Some((interval_ms, source_cmd)) => {
+                    Some((interval_ms, on_change, source_cmd)) => {
                         Ok(Some(Command::Watch(WatchStatement {
                             interval_ms,
+                            on_change,
                             pipeline: Pipeline {
                                 commands: vec![Command::Simple(source_cmd)],
+                                typed: Vec::new(),
                                 background: false,
                             },
                         })))
@@ -134,6 +294,10 @@ fn build_command(node: &Node, source: &str) -> Result<Option<Command>, ShellErro
             let for_stmt = build_for_statement(node, source)?;
             Ok(Some(Command::For(for_stmt)))
         }
+        "c_style_for_statement" => {
+            let for_stmt = build_arithmetic_for_statement(node, source)?;
+            Ok(Some(Command::ArithmeticFor(for_stmt)))
+        }
         "function_definition" => {
             let func_def = build_function_definition(node, source)?;
             Ok(Some(Command::Function(func_def)))
@@ -156,6 +320,43 @@ fn build_command(node: &Node, source: &str) -> Result<Option<Command>, ShellErro
     }
 }
 
+/// The expression of an arithmetic command, `((expr))`. Tree-sitter reads
+/// one as a test command, or as a command named by an arithmetic expansion,
+/// depending on what's inside.
+fn arithmetic_command_expr(node: &Node, source: &str) -> Option<String> {
+    let text = node_text(node, source);
+    let expr = text.trim().strip_prefix("((")?.strip_suffix("))")?;
+    Some(expr.to_string())
+}
+
+/// Build `for ((init; condition; update))` from its header text, which
+/// keeps the expressions as written.
+fn build_arithmetic_for_statement(node: &Node, source: &str) -> Result<ArithmeticForStatement, ShellError> {
+    let mut cursor = node.walk();
+    let do_group = node
+        .children(&mut cursor)
+        .find(|child| child.kind() == "do_group")
+        .ok_or_else(|| ShellError::Parse("for: missing do".into()))?;
+    let header = source[node.start_byte()..do_group.start_byte()].trim();
+    let parts: Vec<&str> = header
+        .strip_prefix("for")
+        .map(str::trim)
+        .and_then(|h| h.strip_prefix("(("))
+        .and_then(|h| h.rfind("))").map(|end| &h[..end]))
+        .map(|inner| inner.split(';').collect())
+        .unwrap_or_default();
+    let [init, condition, update] = parts[..] else {
+        return Err(ShellError::Parse(format!("for: bad arithmetic header: {}", header)));
+    };
+
+    Ok(ArithmeticForStatement {
+        init: init.trim().to_string(),
+        condition: condition.trim().to_string(),
+        update: update.trim().to_string(),
+        body: build_do_group(&do_group, source)?,
+    })
+}
+
 fn build_simple_command(node: &Node, source: &str) -> Result<SimpleCommand, ShellError> {
     let mut name = None;
     let mut args = Vec::new();
@@ -168,28 +369,43 @@ fn build_simple_command(node: &Node, source: &str) -> Result<SimpleCommand, Shel
             "command_name" => {
                 name = Some(node_text(&child, source));
             }
-            "word" | "string" | "raw_string" | "concatenation" | "number" => {
+            "word" | "string" | "raw_string" | "concatenation" | "number" | "brace_expression" => {
                 if name.is_none() {
                     name = Some(node_text(&child, source));
                 } else {
                     args.push(Word::Literal(node_text(&child, source)));
                 }
             }
+            // Tree-sitter keeps `==` as an operator token even outside
+            // `[[ ]]`, as in `where size == 0`.
+            "==" if name.is_some() => {
+                args.push(Word::Literal(node_text(&child, source)));
+            }
             "simple_expansion" | "expansion" => {
                 args.push(Word::Variable(extract_variable_name(&child, source)));
             }
             "command_substitution" => {
                 args.push(Word::CommandSubstitution(node_text(&child, source)));
             }
+            "process_substitution" => {
+                args.push(Word::ProcessSubstitution(node_text(&child, source)));
+            }
             "arithmetic_expansion" => {
                 // $((expr)) — store as literal; expand_literal handles $((…))
                 args.push(Word::Literal(node_text(&child, source)));
             }
-            "file_redirect" | "heredoc_redirect" => {
+            "file_redirect" => {
                 if let Some(redir) = build_redirect(&child, source)? {
                     redirects.push(redir);
                 }
             }
+            "heredoc_redirect" => {
+                let (heredoc, _) = build_heredoc(&child, source)?;
+                redirects.extend(heredoc);
+            }
+            "herestring_redirect" => {
+                redirects.extend(build_herestring(&child, source));
+            }
             "variable_assignment" => {
                 let assignment = build_assignment(&child, source)?;
                 env_assignments.push(assignment);
@@ -215,16 +431,24 @@ fn build_simple_command(node: &Node, source: &str) -> Result<SimpleCommand, Shel
 
 fn build_pipeline(node: &Node, source: &str) -> Result<Pipeline, ShellError> {
     let mut commands = Vec::new();
+    let mut typed = Vec::new();
     let mut cursor = node.walk();
 
     for child in node.children(&mut cursor) {
-        if let Some(cmd) = build_command(&child, source)? {
+        if let Some(mut cmd) = build_command(&child, source)? {
+            if let Command::Simple(simple) = &mut cmd
+                && let Some(name) = simple.name.strip_prefix(TYPED_PIPE)
+            {
+                simple.name = name.to_string();
+                typed.push(commands.len());
+            }
             commands.push(cmd);
         }
     }
 
     Ok(Pipeline {
         commands,
+        typed,
         background: false,
     })
 }
@@ -267,15 +491,24 @@ fn build_subshell(node: &Node, source: &str) -> Result<Subshell, ShellError> {
 fn build_redirected_statement(node: &Node, source: &str) -> Result<Option<Command>, ShellError> {
     let mut inner_cmd = None;
     let mut redirects = Vec::new();
+    let mut tail = None;
     let mut cursor = node.walk();
 
     for child in node.children(&mut cursor) {
         match child.kind() {
-            "file_redirect" | "heredoc_redirect" => {
+            "file_redirect" => {
                 if let Some(redir) = build_redirect(&child, source)? {
                     redirects.push(redir);
                 }
             }
+            "heredoc_redirect" => {
+                let (heredoc, rest) = build_heredoc(&child, source)?;
+                redirects.extend(heredoc);
+                tail = tail.or(rest);
+            }
+            "herestring_redirect" => {
+                redirects.extend(build_herestring(&child, source));
+            }
             _ => {
                 if let Some(cmd) = build_command(&child, source)? {
                     inner_cmd = Some(cmd);
@@ -295,12 +528,90 @@ fn build_redirected_statement(node: &Node, source: &str) -> Result<Option<Comman
                 // This is a simplification; proper handling would need more work
             }
         }
-        Ok(Some(cmd))
+        Ok(Some(match tail {
+            Some(HeredocTail::Pipe(rest)) => Command::Pipeline(Pipeline {
+                commands: std::iter::once(cmd).chain(rest.commands).collect(),
+                typed: rest.typed.iter().map(|i| i + 1).collect(),
+                background: false,
+            }),
+            Some(HeredocTail::List(op, rest)) => Command::List(List {
+                items: vec![cmd, rest],
+                operators: vec![op],
+            }),
+            None => cmd,
+        }))
     } else {
         Ok(None)
     }
 }
 
+/// What follows a here-document's delimiter on its line. Tree-sitter nests
+/// the rest of `cat <<EOF | wc -l` (or `&& ...`) inside the redirect node.
+enum HeredocTail {
+    Pipe(Pipeline),
+    List(ListOperator, Command),
+}
+
+/// Build a here-document redirect, plus any redirects and commands that
+/// follow the delimiter on the same line.
+fn build_heredoc(node: &Node, source: &str) -> Result<(Vec<Redirect>, Option<HeredocTail>), ShellError> {
+    let mut fd = 0;
+    let mut strip_tabs = false;
+    let mut expand = true;
+    let mut body = String::new();
+    let mut redirects = Vec::new();
+    let mut operator = None;
+    let mut tail = None;
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "file_descriptor" => fd = node_text(&child, source).parse().unwrap_or(0),
+            "<<-" => strip_tabs = true,
+            "heredoc_start" => {
+                // Any quoting of the delimiter turns expansion off.
+                expand = !node_text(&child, source).contains(['\'', '"', '\\']);
+            }
+            "heredoc_body" => body = node_text(&child, source),
+            "file_redirect" => redirects.extend(build_redirect(&child, source)?),
+            "&&" => operator = Some(ListOperator::And),
+            "||" => operator = Some(ListOperator::Or),
+            ";" => operator = Some(ListOperator::Semi),
+            "pipeline" => tail = Some(HeredocTail::Pipe(build_pipeline(&child, source)?)),
+            _ if operator.is_some() => {
+                if let (Some(op), Some(cmd)) = (operator, build_command(&child, source)?) {
+                    tail = Some(HeredocTail::List(op, cmd));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if strip_tabs {
+        body = body.split_inclusive('\n').map(|line| line.trim_start_matches('\t')).collect();
+    }
+    redirects.insert(0, Redirect { fd, op: RedirectOp::Heredoc { expand }, target: body });
+    Ok((redirects, tail))
+}
+
+/// Build a here-string redirect (`<<< word`). The word is kept as written
+/// and expanded when the command runs.
+fn build_herestring(node: &Node, source: &str) -> Option<Redirect> {
+    let mut fd = 0;
+    let mut target = None;
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "file_descriptor" => fd = node_text(&child, source).parse().unwrap_or(0),
+            "<<<" => {}
+            _ => target = Some(node_text(&child, source)),
+        }
+    }
+
+    target.map(|target| Redirect { fd, op: RedirectOp::HereString, target })
+}
+
 fn build_redirect(node: &Node, source: &str) -> Result<Option<Redirect>, ShellError> {
     let mut fd = None;
     let mut op = None;
@@ -317,7 +628,7 @@ fn build_redirect(node: &Node, source: &str) -> Result<Option<Redirect>, ShellEr
             "<" => op = Some(RedirectOp::Read),
             ">&" => op = Some(RedirectOp::DupWrite),
             "<&" => op = Some(RedirectOp::DupRead),
-            "word" | "string" | "number" => {
+            "word" | "string" | "number" | "process_substitution" => {
                 target = Some(node_text(&child, source));
             }
             _ => {}
@@ -771,7 +1082,15 @@ fn build_case_item(node: &Node, source: &str) -> Result<CaseItem, ShellError> {
 }
 
 fn node_text(node: &Node, source: &str) -> String {
-    source[node.byte_range()].to_string()
+    source[node.byte_range()]
+        .chars()
+        .map(|c| match c {
+            EXTGLOB_OPEN => '(',
+            EXTGLOB_BAR => '|',
+            EXTGLOB_CLOSE => ')',
+            c => c,
+        })
+        .collect()
 }
 
 fn extract_variable_name(node: &Node, source: &str) -> String {
@@ -799,8 +1118,10 @@ fn try_extract_watch(pipeline: &Pipeline) -> Option<WatchStatement> {
             commands.extend(pipeline.commands[1..].iter().cloned());
             Some(WatchStatement {
                 interval_ms: watch.interval_ms,
+                on_change: watch.on_change.clone(),
                 pipeline: Pipeline {
                     commands,
+                    typed: pipeline.typed.clone(),
                     background: pipeline.background,
                 },
             })
@@ -808,7 +1129,7 @@ fn try_extract_watch(pipeline: &Pipeline) -> Option<WatchStatement> {
         // Case 2: first command is a Simple with name "watch"
         // (fallback in case build_command didn't transform it)
         Command::Simple(simple) if simple.name == "watch" => {
-            let (interval_ms, mut new_first) = parse_watch_args(&simple.args)?;
+            let (interval_ms, on_change, mut new_first) = parse_watch_args(&simple.args)?;
             new_first.redirects.extend(simple.redirects.clone());
 
             let mut commands = vec![Command::Simple(new_first)];
@@ -816,8 +1137,10 @@ fn try_extract_watch(pipeline: &Pipeline) -> Option<WatchStatement> {
 
             Some(WatchStatement {
                 interval_ms,
+                on_change,
                 pipeline: Pipeline {
                     commands,
+                    typed: pipeline.typed.clone(),
                     background: pipeline.background,
                 },
             })
@@ -826,10 +1149,11 @@ fn try_extract_watch(pipeline: &Pipeline) -> Option<WatchStatement> {
     }
 }
 
-/// Parse watch arguments: [-n interval] [--] command [args...]
-/// Returns (interval_ms, SimpleCommand) or None if no command found.
-fn parse_watch_args(args: &[Word]) -> Option<(u64, SimpleCommand)> {
+/// Parse watch arguments: [-n interval] [--on-change pattern]... [--] command [args...]
+/// Returns (interval_ms, on_change patterns, SimpleCommand) or None if no command found.
+fn parse_watch_args(args: &[Word]) -> Option<(u64, Vec<String>, SimpleCommand)> {
     let mut interval_ms: u64 = 2000;
+    let mut on_change = Vec::new();
     let mut i = 0;
     let mut flags_done = false;
 
@@ -854,6 +1178,15 @@ fn parse_watch_args(args: &[Word]) -> Option<(u64, SimpleCommand)> {
             } else {
                 i += 1;
             }
+        } else if s == "--on-change" {
+            // --on-change <pattern>, kept unexpanded for the watcher
+            if let Some(Word::Literal(pattern)) = args.get(i + 1) {
+                on_change.push(pattern.clone());
+            }
+            i += 2;
+        } else if let Some(pattern) = s.strip_prefix("--on-change=") {
+            on_change.push(pattern.to_string());
+            i += 1;
         } else if let Some(val) = s.strip_prefix("-n") {
             // -n<val> (no space)
             interval_ms = parse_interval(val);
@@ -874,7 +1207,7 @@ fn parse_watch_args(args: &[Word]) -> Option<(u64, SimpleCommand)> {
     let cmd_name = match &args[i] {
         Word::Literal(s) => s.clone(),
         Word::Variable(v) => format!("${}", v),
-        Word::CommandSubstitution(c) => c.clone(),
+        Word::CommandSubstitution(c) | Word::ProcessSubstitution(c) => c.clone(),
     };
     i += 1;
 
@@ -883,6 +1216,7 @@ fn parse_watch_args(args: &[Word]) -> Option<(u64, SimpleCommand)> {
 
     Some((
         interval_ms,
+        on_change,
         SimpleCommand {
             name: cmd_name,
             args: cmd_args,
@@ -931,6 +1265,139 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_extglob_group_is_one_word() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("ls @(a|b).txt !(*.rs) \"@(x|y)\" $((1+2))").unwrap();
+
+        if let Command::Simple(cmd) = &ast.commands[0] {
+            assert_eq!(cmd.args[0].as_literal(), Some("@(a|b).txt"));
+            assert_eq!(cmd.args[1].as_literal(), Some("!(*.rs)"));
+            assert_eq!(cmd.args.len(), 4);
+        } else {
+            panic!("Expected simple command, got {:?}", ast.commands[0]);
+        }
+
+        let steps = parser.parse_steps("echo +(ab|cd)*; true").unwrap();
+        assert_eq!(steps[0].0, "echo +(ab|cd)*");
+    }
+
+    #[test]
+    fn test_typed_pipe_marks_stage() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("ls |> sort-by size | head -1 |>select name; echo '|>' a||b").unwrap();
+
+        match &ast.commands[0] {
+            Command::Pipeline(pipeline) => {
+                let names: Vec<_> = pipeline
+                    .commands
+                    .iter()
+                    .filter_map(|c| if let Command::Simple(s) = c { Some(s.name.as_str()) } else { None })
+                    .collect();
+                assert_eq!(names, ["ls", "sort-by", "head", "select"]);
+                assert_eq!(pipeline.typed, [1, 3]);
+            }
+            other => panic!("Expected pipeline, got {:?}", other),
+        }
+        let echo = format!("{:?}", ast.commands[1..].to_vec());
+        assert!(echo.contains("|>") && !echo.contains('\u{E003}'));
+    }
+
+    #[test]
+    fn test_background_marks_preceding_command() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("sleep 5 & echo hi").unwrap();
+
+        assert_eq!(ast.commands.len(), 2);
+        match &ast.commands[0] {
+            Command::Pipeline(pipeline) => {
+                assert!(pipeline.background);
+                assert!(matches!(&pipeline.commands[..], [Command::Simple(cmd)] if cmd.name == "sleep"));
+            }
+            other => panic!("Expected background pipeline, got {:?}", other),
+        }
+        assert!(matches!(&ast.commands[1], Command::Simple(cmd) if cmd.name == "echo"));
+    }
+
+    #[test]
+    fn test_heredoc_collects_body() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("cat <<EOF\nhello $USER\nbye\nEOF").unwrap();
+
+        assert_eq!(ast.commands.len(), 1);
+        if let Command::Simple(cmd) = &ast.commands[0] {
+            assert_eq!(cmd.name, "cat");
+            assert_eq!(cmd.redirects.len(), 1);
+            assert_eq!(cmd.redirects[0].fd, 0);
+            assert_eq!(cmd.redirects[0].op, RedirectOp::Heredoc { expand: true });
+            assert_eq!(cmd.redirects[0].target, "hello $USER\nbye\n");
+        } else {
+            panic!("Expected simple command");
+        }
+    }
+
+    #[test]
+    fn test_heredoc_quoted_delimiter_disables_expansion() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("cat <<'EOF'\n$HOME\nEOF").unwrap();
+
+        if let Command::Simple(cmd) = &ast.commands[0] {
+            assert_eq!(cmd.redirects[0].op, RedirectOp::Heredoc { expand: false });
+            assert_eq!(cmd.redirects[0].target, "$HOME\n");
+        } else {
+            panic!("Expected simple command");
+        }
+    }
+
+    #[test]
+    fn test_heredoc_dash_strips_leading_tabs() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("cat <<-EOF\n\tone\n\t\ttwo\n\tEOF").unwrap();
+
+        if let Command::Simple(cmd) = &ast.commands[0] {
+            assert_eq!(cmd.redirects[0].target, "one\ntwo\n");
+        } else {
+            panic!("Expected simple command");
+        }
+    }
+
+    #[test]
+    fn test_heredoc_into_pipeline() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("cat <<EOF | wc -l\na\nb\nEOF").unwrap();
+
+        assert_eq!(ast.commands.len(), 1);
+        if let Command::Pipeline(pipeline) = &ast.commands[0] {
+            assert_eq!(pipeline.commands.len(), 2);
+            match &pipeline.commands[0] {
+                Command::Simple(cmd) => {
+                    assert_eq!(cmd.name, "cat");
+                    assert_eq!(cmd.redirects[0].target, "a\nb\n");
+                }
+                other => panic!("Expected simple command, got {:?}", other),
+            }
+            assert!(matches!(&pipeline.commands[1], Command::Simple(cmd) if cmd.name == "wc"));
+        } else {
+            panic!("Expected pipeline");
+        }
+    }
+
+    #[test]
+    fn test_herestring() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("wc -w <<< \"one two\"").unwrap();
+
+        if let Command::Simple(cmd) = &ast.commands[0] {
+            assert_eq!(cmd.name, "wc");
+            assert_eq!(cmd.redirects.len(), 1);
+            assert_eq!(cmd.redirects[0].fd, 0);
+            assert_eq!(cmd.redirects[0].op, RedirectOp::HereString);
+            assert_eq!(cmd.redirects[0].target, "\"one two\"");
+        } else {
+            panic!("Expected simple command");
+        }
+    }
+
     #[test]
     fn test_stdout_redirect() {
         let mut parser = Parser::new().unwrap();
@@ -1114,6 +1581,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_process_substitution() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("diff <(ls a) <(ls b)").unwrap();
+        let Command::Simple(cmd) = &ast.commands[0] else { panic!("Expected Simple, got {:?}", ast.commands[0]) };
+        assert!(matches!(&cmd.args[..], [Word::ProcessSubstitution(a), Word::ProcessSubstitution(b)] if a == "<(ls a)" && b == "<(ls b)"));
+
+        let ast = parser.parse("sort < <(ls)").unwrap();
+        let Command::Simple(cmd) = &ast.commands[0] else { panic!("Expected Simple, got {:?}", ast.commands[0]) };
+        assert_eq!(cmd.redirects[0].target, "<(ls)");
+    }
+
     #[test]
     fn test_assignment_with_variable() {
         let mut parser = Parser::new().unwrap();
@@ -1188,6 +1667,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_arithmetic_command() {
+        let mut parser = Parser::new().unwrap();
+        for (input, expr) in [("((i++))", "i++"), ("(( x = 1 + 2 ))", " x = 1 + 2 ")] {
+            let ast = parser.parse(input).unwrap();
+            let Command::Arithmetic(cmd) = &ast.commands[0] else {
+                panic!("Expected Arithmetic command for {}, got {:?}", input, ast.commands[0]);
+            };
+            assert_eq!(cmd.expr, expr);
+        }
+    }
+
+    #[test]
+    fn test_c_style_for() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("for ((i=0; i<3; i++)); do echo $i; done").unwrap();
+        let Command::ArithmeticFor(for_stmt) = &ast.commands[0] else {
+            panic!("Expected ArithmeticFor, got {:?}", ast.commands[0]);
+        };
+        assert_eq!((for_stmt.init.as_str(), for_stmt.condition.as_str(), for_stmt.update.as_str()), ("i=0", "i<3", "i++"));
+        assert_eq!(for_stmt.body.len(), 1);
+
+        let ast = parser.parse("for (( ; ; )); do break; done").unwrap();
+        let Command::ArithmeticFor(for_stmt) = &ast.commands[0] else {
+            panic!("Expected ArithmeticFor, got {:?}", ast.commands[0]);
+        };
+        assert!(for_stmt.init.is_empty() && for_stmt.condition.is_empty() && for_stmt.update.is_empty());
+    }
+
+    #[test]
+    fn test_watch_on_change() {
+        let mut parser = Parser::new().unwrap();
+        let ast = parser.parse("watch --on-change src/**/*.rs --on-change=Cargo.toml cargo test").unwrap();
+
+        let Command::Watch(watch) = &ast.commands[0] else {
+            panic!("Expected Watch command, got {:?}", ast.commands[0]);
+        };
+        assert_eq!(watch.on_change, vec!["src/**/*.rs", "Cargo.toml"]);
+        let Command::Simple(cmd) = &watch.pipeline.commands[0] else {
+            panic!("Expected simple command");
+        };
+        assert_eq!(cmd.name, "cargo");
+        assert_eq!(cmd.args.len(), 1);
+    }
+
     #[test]
     fn test_watch_with_double_dash() {
         let mut parser = Parser::new().unwrap();
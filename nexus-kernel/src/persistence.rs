@@ -2,23 +2,52 @@
 //!
 //! This module provides:
 //! - Session persistence (resume where you left off)
-//! - Block/output storage (infinite scrollback)
+//! - Block/output storage (infinite scrollback), each distinct output kept
+//!   once however many blocks produced it, compressed, and loaded on demand
+//!   when a session's blocks are rehydrated
+//! - Retention: pruning old sessions and outputs so the database stays bounded
+//! - Optional at-rest encryption of commands and outputs ([`crate::encryption`])
+//! - Local feature-usage counts behind [`crate::insights`]
+//! - Scheduled commands and their run history, see [`crate::scheduler`]
+//! - The last run's compiler problems per command, see [`crate::problems`]
+//! - Titles of finished blocks, for session summaries ([`crate::titles`])
+//! - CPU time, memory and IO used by each finished block's process
 //!
 //! Command history has moved to [`crate::shell_history`] which reads/writes
 //! the user's native shell history file.
 
+use crate::encryption::{EncryptionSetting, StoreCipher};
+use crate::problems::Problem;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use nexus_api::{BlockId, Value};
+use nexus_api::{BlockId, BlockIdAllocator, ResourceUsage, Value};
+use nexus_term::TerminalGrid;
 use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::collections::BTreeSet;
+use std::sync::{Mutex, Once};
+use std::time::Duration;
 
 /// Database version for migrations.
-const SCHEMA_VERSION: i32 = 1;
+const SCHEMA_VERSION: i32 = 12;
+
+/// Encryption marker once sealing is turned off: rows sealed before then
+/// still need the key.
+const ENCRYPTION_OFF: &str = "off";
+
+/// Marks a stored payload as deflate-compressed and base64-encoded.
+const COMPRESSED_PREFIX: &str = "z1:";
+/// Payloads shorter than this are stored as they are.
+const COMPRESS_MIN_BYTES: usize = 512;
 
 /// The persistence store backed by SQLite.
 pub struct Store {
     conn: Connection,
+    /// Key for sealed rows, if the database is (or was) encrypted.
+    cipher: Option<StoreCipher>,
+    /// Whether new commands and outputs are written sealed.
+    seal_writes: bool,
 }
 
 /// A stored session.
@@ -38,9 +67,140 @@ pub struct StoredBlock {
     pub session_id: i64,
     pub command: String,
     pub output_json: Option<String>,
+    /// Digest of the output; blocks with equal outputs share one copy.
+    pub output_hash: Option<String>,
+    /// Digest of the terminal grid saved with [`Store::save_block_snapshot`].
+    pub snapshot_hash: Option<String>,
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A stored block without its output, for rehydrating a session. The
+/// output and terminal snapshot are loaded when needed with
+/// [`Store::load_block_output`] and [`Store::load_block_snapshot`].
+#[derive(Debug, Clone)]
+pub struct SessionBlock {
+    /// Row id, the handle for loading the output.
+    pub id: i64,
+    pub block_id: BlockId,
+    pub command: String,
     pub exit_code: Option<i32>,
     pub duration_ms: Option<u64>,
     pub timestamp: DateTime<Utc>,
+    pub has_output: bool,
+    pub has_snapshot: bool,
+    pub notes: Vec<BlockNote>,
+}
+
+/// A note the user left on one line of a block's output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockNote {
+    /// Row of the output it is on, from 0.
+    pub line: usize,
+    pub text: String,
+}
+
+/// The title a finished block was given, see [`crate::titles`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTitle {
+    pub block_id: u64,
+    pub title: String,
+    pub exit_code: Option<i32>,
+}
+
+/// Limits applied by [`Store::apply_retention`]. `None` means unlimited,
+/// and the default keeps everything: pruning only happens when asked for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep at most this many sessions (newest first).
+    pub max_sessions: Option<usize>,
+    /// Drop sessions that started longer ago than this.
+    pub max_age: Option<Duration>,
+    /// Drop the oldest sessions until the live data fits in this many bytes.
+    pub max_db_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Read limits from `NEXUS_RETAIN_SESSIONS`, `NEXUS_RETAIN_DAYS` and
+    /// `NEXUS_RETAIN_MB`. Each accepts a number, or `0` / `never` for no
+    /// limit; unset or unparsable values leave that limit off.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(v) = env_limit("NEXUS_RETAIN_SESSIONS") {
+            policy.max_sessions = v.map(|n| n as usize);
+        }
+        if let Some(v) = env_limit("NEXUS_RETAIN_DAYS") {
+            policy.max_age = v.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+        }
+        if let Some(v) = env_limit("NEXUS_RETAIN_MB") {
+            policy.max_db_bytes = v.map(|mb| mb * 1024 * 1024);
+        }
+        policy
+    }
+}
+
+/// `Some(None)` = limit disabled, `Some(Some(n))` = limit, `None` = not set.
+fn env_limit(name: &str) -> Option<Option<u64>> {
+    parse_limit(&std::env::var(name).ok()?)
+}
+
+fn parse_limit(value: &str) -> Option<Option<u64>> {
+    match value.trim() {
+        "never" | "0" => Some(None),
+        n => n.parse().ok().map(Some),
+    }
+}
+
+/// What a retention pass removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PurgeStats {
+    pub sessions: usize,
+    pub blocks: usize,
+}
+
+/// How often one feature was used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageCount {
+    pub event: String,
+    /// What was used, for events that name it (e.g. a palette action).
+    pub detail: String,
+    pub count: u64,
+    pub last_at: DateTime<Utc>,
+}
+
+/// A command run on a schedule (`schedule "0 9 * * 1-5" 'git fetch --all'`).
+#[derive(Debug, Clone)]
+pub struct ScheduledCommand {
+    pub id: i64,
+    /// Cron expression, see [`crate::scheduler::CronExpr`].
+    pub expr: String,
+    pub command: String,
+    /// Directory the command runs in.
+    pub cwd: String,
+    pub created_at: DateTime<Utc>,
+    /// When runs were last taken care of, by running or skipping them.
+    pub last_run_at: Option<DateTime<Utc>>,
+}
+
+/// One run of a scheduled command.
+#[derive(Debug, Clone)]
+pub struct ScheduleRun {
+    pub id: i64,
+    pub schedule_id: i64,
+    pub started_at: DateTime<Utc>,
+    /// None while running, or if Nexus quit before it finished.
+    pub exit_code: Option<i32>,
+    pub duration_ms: Option<u64>,
+}
+
+/// Disk usage of one session.
+#[derive(Debug, Clone)]
+pub struct SessionUsage {
+    pub session: Session,
+    pub blocks: u64,
+    /// Bytes of stored commands and output JSON.
+    pub bytes: u64,
 }
 
 impl Store {
@@ -61,8 +221,9 @@ impl Store {
         let conn = Connection::open(path)
             .with_context(|| format!("Failed to open database: {:?}", path))?;
 
-        let mut store = Self { conn };
+        let mut store = Self { conn, cipher: None, seal_writes: false };
         store.initialize()?;
+        store.configure_encryption(EncryptionSetting::from_env(), StoreCipher::load_or_create)?;
         Ok(store)
     }
 
@@ -70,11 +231,19 @@ impl Store {
     #[cfg(test)]
     pub fn open_in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
-        let mut store = Self { conn };
+        let mut store = Self { conn, cipher: None, seal_writes: false };
         store.initialize()?;
         Ok(store)
     }
 
+    /// Open an in-memory database that seals writes with `key` (for testing).
+    #[cfg(test)]
+    fn open_in_memory_encrypted(key: &[u8; 32]) -> Result<Self> {
+        let mut store = Self::open_in_memory()?;
+        store.enable_encryption(StoreCipher::new(key))?;
+        Ok(store)
+    }
+
     /// Initialize the database schema.
     fn initialize(&mut self) -> Result<()> {
         let version = self.get_schema_version()?;
@@ -85,6 +254,11 @@ impl Store {
             self.migrate(version)?;
         }
 
+        // Stored ids come from the same allocator as new ones; never reuse them.
+        if let Some(max) = self.max_block_id()? {
+            BlockIdAllocator::global().advance_past(max);
+        }
+
         Ok(())
     }
 
@@ -120,15 +294,19 @@ impl Store {
                 value TEXT NOT NULL
             );
 
-            -- Sessions table
+            -- Sessions table. last_active is a heartbeat from the process
+            -- writing to the session, so other processes can tell it's live.
             CREATE TABLE IF NOT EXISTS sessions (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 started_at TEXT NOT NULL,
                 ended_at TEXT,
-                cwd TEXT NOT NULL
+                cwd TEXT NOT NULL,
+                last_active TEXT
             );
 
-            -- Blocks table (command + structured output)
+            -- Blocks table (command + structured output). sealed is set when
+            -- command and output_json are encrypted with the store key,
+            -- notes_sealed when notes are.
             CREATE TABLE IF NOT EXISTS blocks (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 block_id INTEGER NOT NULL,
@@ -138,25 +316,396 @@ impl Store {
                 exit_code INTEGER,
                 duration_ms INTEGER,
                 timestamp TEXT NOT NULL,
+                sealed INTEGER NOT NULL DEFAULT 0,
+                output_hash TEXT,
+                snapshot_hash TEXT,
+                notes TEXT,
+                notes_sealed INTEGER NOT NULL DEFAULT 0,
                 FOREIGN KEY (session_id) REFERENCES sessions(id)
             );
 
             -- Index for fast session lookup
             CREATE INDEX IF NOT EXISTS idx_blocks_session ON blocks(session_id);
 
+            -- Block outputs and terminal snapshots, one row per distinct content.
+            -- sealed is set when data is encrypted with the store key.
+            CREATE TABLE IF NOT EXISTS outputs (
+                hash TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                sealed INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE INDEX IF NOT EXISTS idx_blocks_output ON blocks(output_hash);
+            CREATE INDEX IF NOT EXISTS idx_blocks_snapshot ON blocks(snapshot_hash);
+
+            -- Feature usage counts (local insights)
+            CREATE TABLE IF NOT EXISTS usage (
+                event TEXT NOT NULL,
+                detail TEXT NOT NULL DEFAULT '',
+                count INTEGER NOT NULL,
+                last_at TEXT NOT NULL,
+                PRIMARY KEY (event, detail)
+            );
+
+            -- Scheduled commands and their runs. sealed is set when command
+            -- is encrypted with the store key.
+            CREATE TABLE IF NOT EXISTS schedules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                expr TEXT NOT NULL,
+                command TEXT NOT NULL,
+                cwd TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                last_run_at TEXT,
+                sealed INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE IF NOT EXISTS schedule_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                schedule_id INTEGER NOT NULL,
+                started_at TEXT NOT NULL,
+                exit_code INTEGER,
+                duration_ms INTEGER,
+                FOREIGN KEY (schedule_id) REFERENCES schedules(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_schedule_runs ON schedule_runs(schedule_id);
+
+            -- Compiler problems of the last run, per command and cwd. sealed
+            -- is set when problems is encrypted with the store key.
+            CREATE TABLE IF NOT EXISTS problem_lists (
+                key TEXT PRIMARY KEY,
+                problems TEXT NOT NULL,
+                recorded_at TEXT NOT NULL,
+                sealed INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Titles of finished blocks, for session summaries. sealed is
+            -- set when title is encrypted with the store key.
+            CREATE TABLE IF NOT EXISTS block_titles (
+                session_id INTEGER NOT NULL,
+                block_id INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                exit_code INTEGER,
+                sealed INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (session_id, block_id),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            -- Resources used by finished blocks' processes
+            CREATE TABLE IF NOT EXISTS block_usage (
+                session_id INTEGER NOT NULL,
+                block_id INTEGER NOT NULL,
+                user_ms INTEGER NOT NULL,
+                sys_ms INTEGER NOT NULL,
+                max_rss INTEGER NOT NULL,
+                blocks_in INTEGER NOT NULL,
+                blocks_out INTEGER NOT NULL,
+                PRIMARY KEY (session_id, block_id),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
             -- Set schema version
-            INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '1');
+            INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '12');
         "#)?;
 
         Ok(())
     }
 
     /// Migrate from an older schema version.
-    fn migrate(&mut self, _from_version: i32) -> Result<()> {
-        // Future migrations go here
+    fn migrate(&mut self, from_version: i32) -> Result<()> {
+        if from_version < 2 {
+            // Sessions from before the heartbeat read as not live.
+            self.conn.execute_batch(
+                "BEGIN;
+                 ALTER TABLE sessions ADD COLUMN last_active TEXT;
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '2');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 3 {
+            // Rows from before encryption are plaintext.
+            self.conn.execute_batch(
+                "BEGIN;
+                 ALTER TABLE blocks ADD COLUMN sealed INTEGER NOT NULL DEFAULT 0;
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '3');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 4 {
+            // Block ids used to restart at 1 in every process, so they repeat
+            // across sessions. Renumber them from the row id, which is
+            // unique and in insertion order; new ids are allocated past it.
+            self.conn.execute_batch(
+                "BEGIN;
+                 UPDATE blocks SET block_id = id;
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '4');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 5 {
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE TABLE IF NOT EXISTS usage (
+                     event TEXT NOT NULL,
+                     detail TEXT NOT NULL DEFAULT '',
+                     count INTEGER NOT NULL,
+                     last_at TEXT NOT NULL,
+                     PRIMARY KEY (event, detail)
+                 );
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '5');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 6 {
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE TABLE IF NOT EXISTS schedules (
+                     id INTEGER PRIMARY KEY AUTOINCREMENT,
+                     expr TEXT NOT NULL,
+                     command TEXT NOT NULL,
+                     cwd TEXT NOT NULL,
+                     created_at TEXT NOT NULL,
+                     last_run_at TEXT,
+                     sealed INTEGER NOT NULL DEFAULT 0
+                 );
+                 CREATE TABLE IF NOT EXISTS schedule_runs (
+                     id INTEGER PRIMARY KEY AUTOINCREMENT,
+                     schedule_id INTEGER NOT NULL,
+                     started_at TEXT NOT NULL,
+                     exit_code INTEGER,
+                     duration_ms INTEGER,
+                     FOREIGN KEY (schedule_id) REFERENCES schedules(id)
+                 );
+                 CREATE INDEX IF NOT EXISTS idx_schedule_runs ON schedule_runs(schedule_id);
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '6');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 7 {
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE TABLE IF NOT EXISTS problem_lists (
+                     key TEXT PRIMARY KEY,
+                     problems TEXT NOT NULL,
+                     recorded_at TEXT NOT NULL,
+                     sealed INTEGER NOT NULL DEFAULT 0
+                 );
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '7');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 8 {
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE TABLE IF NOT EXISTS block_titles (
+                     session_id INTEGER NOT NULL,
+                     block_id INTEGER NOT NULL,
+                     title TEXT NOT NULL,
+                     exit_code INTEGER,
+                     sealed INTEGER NOT NULL DEFAULT 0,
+                     PRIMARY KEY (session_id, block_id),
+                     FOREIGN KEY (session_id) REFERENCES sessions(id)
+                 );
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '8');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 9 {
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE TABLE IF NOT EXISTS block_usage (
+                     session_id INTEGER NOT NULL,
+                     block_id INTEGER NOT NULL,
+                     user_ms INTEGER NOT NULL,
+                     sys_ms INTEGER NOT NULL,
+                     max_rss INTEGER NOT NULL,
+                     blocks_in INTEGER NOT NULL,
+                     blocks_out INTEGER NOT NULL,
+                     PRIMARY KEY (session_id, block_id),
+                     FOREIGN KEY (session_id) REFERENCES sessions(id)
+                 );
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '9');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 10 {
+            // Older rows keep their inline output_json; reads take either.
+            if !self.has_column("blocks", "output_hash")? {
+                self.conn.execute("ALTER TABLE blocks ADD COLUMN output_hash TEXT", [])?;
+            }
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE TABLE IF NOT EXISTS outputs (
+                     hash TEXT PRIMARY KEY,
+                     output_json TEXT NOT NULL,
+                     sealed INTEGER NOT NULL DEFAULT 0
+                 );
+                 CREATE INDEX IF NOT EXISTS idx_blocks_output ON blocks(output_hash);
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '10');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 11 {
+            // The outputs table now holds terminal snapshots too.
+            if self.has_column("outputs", "output_json")? {
+                self.conn.execute("ALTER TABLE outputs RENAME COLUMN output_json TO data", [])?;
+            }
+            if !self.has_column("blocks", "snapshot_hash")? {
+                self.conn.execute("ALTER TABLE blocks ADD COLUMN snapshot_hash TEXT", [])?;
+            }
+            self.conn.execute_batch(
+                "BEGIN;
+                 CREATE INDEX IF NOT EXISTS idx_blocks_snapshot ON blocks(snapshot_hash);
+                 INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '11');
+                 COMMIT;",
+            )?;
+        }
+        if from_version < 12 {
+            if !self.has_column("blocks", "notes")? {
+                self.conn.execute("ALTER TABLE blocks ADD COLUMN notes TEXT", [])?;
+            }
+            if !self.has_column("blocks", "notes_sealed")? {
+                self.conn.execute("ALTER TABLE blocks ADD COLUMN notes_sealed INTEGER NOT NULL DEFAULT 0", [])?;
+            }
+            self.conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', '12')", [])?;
+        }
+        Ok(())
+    }
+
+    fn has_column(&self, table: &str, column: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    // =========================================================================
+    // Encryption
+    // =========================================================================
+
+    /// Apply `NEXUS_ENCRYPT_STORE` against what the database already uses.
+    ///
+    /// Fails closed: if encryption is wanted but the key can't be loaded,
+    /// the store is not opened rather than falling back to plaintext.
+    ///
+    /// Turning encryption off leaves the rows already sealed as they are, so
+    /// the marker stays, saying the key is still needed to read them.
+    fn configure_encryption(&mut self, setting: EncryptionSetting, load: impl Fn() -> Result<StoreCipher>) -> Result<()> {
+        let marker: Option<String> = self
+            .conn
+            .query_row("SELECT value FROM meta WHERE key = 'encryption'", [], |row| row.get(0))
+            .optional()?;
+        let previously_encrypted = marker.as_deref().is_some_and(|value| value != ENCRYPTION_OFF);
+
+        if setting.resolve(previously_encrypted) {
+            return self.enable_encryption(load()?);
+        }
+
+        if marker.is_some() {
+            // Off: keep reading sealed rows, write plaintext from now on.
+            match load() {
+                Ok(cipher) => self.cipher = Some(cipher),
+                Err(e) => tracing::warn!("Encrypted rows will be unreadable: {}", e),
+            }
+            self.conn.execute("UPDATE meta SET value = ?1 WHERE key = 'encryption'", [ENCRYPTION_OFF])?;
+        }
+        Ok(())
+    }
+
+    fn enable_encryption(&mut self, cipher: StoreCipher) -> Result<()> {
+        // Zero deleted content so purged plaintext doesn't linger in free pages.
+        self.conn.execute_batch(
+            "PRAGMA secure_delete = ON;
+             INSERT OR REPLACE INTO meta (key, value) VALUES ('encryption', 'chacha20poly1305');",
+        )?;
+        self.cipher = Some(cipher);
+        self.seal_writes = true;
         Ok(())
     }
 
+    /// Whether new commands and outputs are stored encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.seal_writes
+    }
+
+    /// The store key, when one is loaded: for sealing other files that hold
+    /// block content (the crash journal) and reading them back.
+    pub fn cipher(&self) -> Option<&StoreCipher> {
+        self.cipher.as_ref()
+    }
+
+    fn seal(&self, value: &str) -> String {
+        match &self.cipher {
+            Some(cipher) if self.seal_writes => cipher.seal(value),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Read a value from a row whose `sealed` column is `sealed`.
+    fn unseal(&self, sealed: bool, value: String) -> Result<String> {
+        if !sealed {
+            return Ok(value);
+        }
+        match &self.cipher {
+            Some(cipher) => cipher.open(&value),
+            None => anyhow::bail!("Stored block is encrypted but no store key is available"),
+        }
+    }
+
+    /// Seal rows written before encryption was enabled, then vacuum so the
+    /// old plaintext pages are gone. Returns the number of blocks rewritten.
+    pub fn encrypt_existing(&self) -> Result<usize> {
+        if !self.seal_writes {
+            anyhow::bail!("Store encryption is not enabled (set NEXUS_ENCRYPT_STORE=on)");
+        }
+
+        let plaintext: Vec<(i64, String, Option<String>)> = {
+            let mut stmt = self.conn.prepare("SELECT id, command, output_json FROM blocks WHERE sealed = 0")?;
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        // Plaintext outputs are also keyed by an unkeyed digest; move them
+        // to a keyed one along with sealing them.
+        let plain_outputs: Vec<(String, String)> = {
+            let mut stmt = self.conn.prepare("SELECT hash, data FROM outputs WHERE sealed = 0")?;
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let plain_notes: Vec<(i64, String)> = {
+            let mut stmt = self.conn.prepare("SELECT id, notes FROM blocks WHERE notes IS NOT NULL AND notes_sealed = 0")?;
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, notes) in &plain_notes {
+            tx.execute("UPDATE blocks SET notes = ?1, notes_sealed = 1 WHERE id = ?2", params![self.seal(notes), id])?;
+        }
+        for (id, command, output_json) in &plaintext {
+            tx.execute(
+                "UPDATE blocks SET command = ?1, output_json = ?2, sealed = 1 WHERE id = ?3",
+                params![self.seal(command), output_json.as_deref().map(|v| self.seal(v)), id],
+            )?;
+        }
+        for (hash, data) in &plain_outputs {
+            let keyed = self.output_hash(&decompress(data)?);
+            tx.execute(
+                "INSERT OR IGNORE INTO outputs (hash, data, sealed) VALUES (?1, ?2, 1)",
+                params![keyed, self.seal(data)],
+            )?;
+            tx.execute("UPDATE blocks SET output_hash = ?1 WHERE output_hash = ?2", params![keyed, hash])?;
+            tx.execute("UPDATE blocks SET snapshot_hash = ?1 WHERE snapshot_hash = ?2", params![keyed, hash])?;
+            tx.execute("DELETE FROM outputs WHERE hash = ?1", params![hash])?;
+        }
+        tx.commit()?;
+
+        if !plaintext.is_empty() || !plain_outputs.is_empty() {
+            self.vacuum()?;
+        }
+        Ok(plaintext.len())
+    }
+
     // =========================================================================
     // Session operations
     // =========================================================================
@@ -165,12 +714,35 @@ impl Store {
     pub fn start_session(&self, cwd: &str) -> Result<i64> {
         let now = Utc::now().to_rfc3339();
         self.conn.execute(
-            "INSERT INTO sessions (started_at, cwd) VALUES (?1, ?2)",
+            "INSERT INTO sessions (started_at, cwd, last_active) VALUES (?1, ?2, ?1)",
             params![now, cwd],
         )?;
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Record that the sessions in `ids` are still being written to.
+    pub fn touch_sessions(&self, ids: &[i64]) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        for id in ids {
+            self.conn.execute(
+                "UPDATE sessions SET last_active = ?1 WHERE id = ?2 AND ended_at IS NULL",
+                params![now, id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Sessions some process is still writing to: not ended, and touched
+    /// within [`LIVE_TIMEOUT`]. A session whose process died without ending
+    /// it stops counting once its heartbeat goes stale.
+    pub fn live_sessions(&self) -> Result<Vec<i64>> {
+        let cutoff = Utc::now() - chrono::Duration::from_std(LIVE_TIMEOUT)?;
+        self.session_ids(
+            "SELECT id FROM sessions WHERE ended_at IS NULL AND last_active >= ?1",
+            params![cutoff.to_rfc3339()],
+        )
+    }
+
     /// End a session.
     pub fn end_session(&self, session_id: i64) -> Result<()> {
         let now = Utc::now().to_rfc3339();
@@ -181,6 +753,48 @@ impl Store {
         Ok(())
     }
 
+    /// The newest session before `session_id` that has titled blocks.
+    pub fn previous_titled_session(&self, session_id: i64) -> Result<Option<Session>> {
+        self.conn
+            .query_row(
+                "SELECT id, started_at, ended_at, cwd FROM sessions s
+                 WHERE id < ?1 AND EXISTS (SELECT 1 FROM block_titles t WHERE t.session_id = s.id)
+                 ORDER BY id DESC LIMIT 1",
+                params![session_id],
+                |row| {
+                    Ok(Session {
+                        id: row.get(0)?,
+                        started_at: parse_datetime(row.get::<_, String>(1)?),
+                        ended_at: row.get::<_, Option<String>>(2)?.map(parse_datetime),
+                        cwd: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// The newest session before `session_id` that saved any blocks.
+    pub fn previous_session_with_blocks(&self, session_id: i64) -> Result<Option<Session>> {
+        self.conn
+            .query_row(
+                "SELECT id, started_at, ended_at, cwd FROM sessions s
+                 WHERE id < ?1 AND EXISTS (SELECT 1 FROM blocks b WHERE b.session_id = s.id)
+                 ORDER BY id DESC LIMIT 1",
+                params![session_id],
+                |row| {
+                    Ok(Session {
+                        id: row.get(0)?,
+                        started_at: parse_datetime(row.get::<_, String>(1)?),
+                        ended_at: row.get::<_, Option<String>>(2)?.map(parse_datetime),
+                        cwd: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
     /// Get the most recent session.
     pub fn get_latest_session(&self) -> Result<Option<Session>> {
         self.conn
@@ -204,7 +818,8 @@ impl Store {
     // Block operations
     // =========================================================================
 
-    /// Save a block (command + output).
+    /// Save a block (command + output). An output equal to one already
+    /// stored is not stored again; the block refers to the existing copy.
     pub fn save_block(
         &self,
         block_id: BlockId,
@@ -215,98 +830,1241 @@ impl Store {
         duration_ms: Option<u64>,
     ) -> Result<i64> {
         let now = Utc::now().to_rfc3339();
-        let output_json = output.map(|v| serde_json::to_string(v).unwrap_or_default());
+        let output_hash = output.map(|value| self.save_payload(&serde_json::to_vec(value)?)).transpose()?;
 
         self.conn.execute(
-            "INSERT INTO blocks (block_id, session_id, command, output_json, exit_code, duration_ms, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO blocks (block_id, session_id, command, output_hash, exit_code, duration_ms, timestamp, sealed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
             params![
                 block_id.0 as i64,
                 session_id,
-                command,
-                output_json,
+                self.seal(command),
+                output_hash,
                 exit_code,
                 duration_ms.map(|d| d as i64),
-                now
+                now,
+                self.seal_writes
             ],
         )?;
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Set the output of a block saved earlier with [`Store::save_block`],
+    /// replacing any it had. False if the session has no such block.
+    pub fn save_block_output(&self, session_id: i64, block_id: BlockId, output: &Value) -> Result<bool> {
+        let hash = self.save_payload(&serde_json::to_vec(output)?)?;
+        let updated = self.conn.execute(
+            "UPDATE blocks SET output_hash = ?1, output_json = NULL WHERE session_id = ?2 AND block_id = ?3",
+            params![hash, session_id, block_id.0 as i64],
+        )?;
+        self.prune_outputs()?;
+        Ok(updated > 0)
+    }
+
+    /// Keep the terminal grid (scrollback included) of a block saved
+    /// earlier, so its colored output can be shown again. False if the
+    /// session has no such block.
+    pub fn save_block_snapshot(&self, session_id: i64, block_id: BlockId, grid: &TerminalGrid) -> Result<bool> {
+        let hash = self.save_payload(&rmp_serde::to_vec(grid)?)?;
+        let updated = self.conn.execute(
+            "UPDATE blocks SET snapshot_hash = ?1 WHERE session_id = ?2 AND block_id = ?3",
+            params![hash, session_id, block_id.0 as i64],
+        )?;
+        self.prune_outputs()?;
+        Ok(updated > 0)
+    }
+
+    /// Set the notes on a block saved earlier, replacing any it had. False
+    /// if the session has no such block.
+    pub fn save_block_notes(&self, session_id: i64, block_id: BlockId, notes: &[BlockNote]) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE blocks SET notes = ?1, notes_sealed = ?2 WHERE session_id = ?3 AND block_id = ?4",
+            params![self.seal_notes(notes)?, self.seal_writes, session_id, block_id.0 as i64],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Set the notes on the block stored in row `id` (a [`SessionBlock::id`]),
+    /// for blocks restored from an earlier session.
+    pub fn save_row_notes(&self, id: i64, notes: &[BlockNote]) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE blocks SET notes = ?1, notes_sealed = ?2 WHERE id = ?3",
+            params![self.seal_notes(notes)?, self.seal_writes, id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    fn seal_notes(&self, notes: &[BlockNote]) -> Result<Option<String>> {
+        if notes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(self.seal(&serde_json::to_string(notes)?)))
+    }
+
+    /// Delete a session's stored copy of a block, and its title. False if
+    /// it had no stored copy.
+    pub fn delete_block(&self, session_id: i64, block_id: BlockId) -> Result<bool> {
+        self.conn.execute(
+            "DELETE FROM block_titles WHERE session_id = ?1 AND block_id = ?2",
+            params![session_id, block_id.0 as i64],
+        )?;
+        self.conn.execute(
+            "DELETE FROM block_usage WHERE session_id = ?1 AND block_id = ?2",
+            params![session_id, block_id.0 as i64],
+        )?;
+        let deleted = self.conn.execute(
+            "DELETE FROM blocks WHERE session_id = ?1 AND block_id = ?2",
+            params![session_id, block_id.0 as i64],
+        )?;
+        self.prune_outputs()?;
+        Ok(deleted > 0)
+    }
+
+    /// Largest block id stored, if any.
+    pub fn max_block_id(&self) -> Result<Option<BlockId>> {
+        let max: Option<i64> = self.conn.query_row("SELECT MAX(block_id) FROM blocks", [], |row| row.get(0))?;
+        Ok(max.map(|id| BlockId(id as u64)))
+    }
+
     /// Get blocks for a session.
     pub fn get_session_blocks(&self, session_id: i64) -> Result<Vec<StoredBlock>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, block_id, session_id, command, output_json, exit_code, duration_ms, timestamp
-             FROM blocks
-             WHERE session_id = ?1
-             ORDER BY id ASC"
+            "SELECT b.id, b.block_id, b.session_id, b.command, b.output_json, o.data,
+                    b.exit_code, b.duration_ms, b.timestamp, b.output_hash, b.snapshot_hash, b.sealed, o.sealed
+             FROM blocks b
+             LEFT JOIN outputs o ON o.hash = b.output_hash
+             WHERE b.session_id = ?1
+             ORDER BY b.id ASC"
         )?;
 
         let blocks = stmt
             .query_map(params![session_id], |row| {
-                Ok(StoredBlock {
+                let block = StoredBlock {
                     id: row.get(0)?,
                     block_id: row.get::<_, i64>(1)? as u64,
                     session_id: row.get(2)?,
                     command: row.get(3)?,
                     output_json: row.get(4)?,
-                    exit_code: row.get(5)?,
-                    duration_ms: row.get::<_, Option<i64>>(6)?.map(|d| d as u64),
-                    timestamp: parse_datetime(row.get::<_, String>(7)?),
-                })
+                    output_hash: row.get(9)?,
+                    snapshot_hash: row.get(10)?,
+                    exit_code: row.get(6)?,
+                    duration_ms: row.get::<_, Option<i64>>(7)?.map(|d| d as u64),
+                    timestamp: parse_datetime(row.get::<_, String>(8)?),
+                };
+                let shared = match row.get::<_, Option<String>>(5)? {
+                    Some(data) => Some((row.get::<_, bool>(12)?, data)),
+                    None => None,
+                };
+                Ok((block, row.get::<_, bool>(11)?, shared))
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(blocks)
+        blocks
+            .into_iter()
+            .map(|(block, sealed, shared)| {
+                let output_json = match shared {
+                    Some((data_sealed, data)) => Some(
+                        String::from_utf8(self.open_payload(data_sealed, data)?).context("Stored output is not UTF-8")?,
+                    ),
+                    None => block.output_json.map(|json| self.unseal(sealed, json)).transpose()?,
+                };
+                Ok(StoredBlock { command: self.unseal(sealed, block.command)?, output_json, ..block })
+            })
+            .collect()
     }
 
-    /// Parse stored output JSON back to Value.
-    pub fn parse_block_output(json: &str) -> Option<Value> {
-        serde_json::from_str(json).ok()
+    /// A session's blocks in the order they were saved, without their
+    /// outputs, which can be large; load those per block as it is shown.
+    pub fn load_session_blocks(&self, session_id: i64) -> Result<Vec<SessionBlock>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, block_id, command, exit_code, duration_ms, timestamp,
+                    output_hash IS NOT NULL OR output_json IS NOT NULL, snapshot_hash IS NOT NULL, sealed,
+                    notes, notes_sealed
+             FROM blocks
+             WHERE session_id = ?1
+             ORDER BY id ASC",
+        )?;
+        let blocks = stmt
+            .query_map(params![session_id], |row| {
+                let block = SessionBlock {
+                    id: row.get(0)?,
+                    block_id: BlockId(row.get::<_, i64>(1)? as u64),
+                    command: row.get(2)?,
+                    exit_code: row.get(3)?,
+                    duration_ms: row.get::<_, Option<i64>>(4)?.map(|d| d as u64),
+                    timestamp: parse_datetime(row.get::<_, String>(5)?),
+                    has_output: row.get(6)?,
+                    has_snapshot: row.get(7)?,
+                    notes: Vec::new(),
+                };
+                Ok((block, row.get::<_, bool>(8)?, row.get::<_, Option<String>>(9)?, row.get::<_, bool>(10)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        blocks
+            .into_iter()
+            .map(|(block, sealed, notes, notes_sealed)| {
+                let notes = match notes {
+                    Some(json) => serde_json::from_str(&self.unseal(notes_sealed, json)?).context("Corrupt block notes")?,
+                    None => Vec::new(),
+                };
+                Ok(SessionBlock { command: self.unseal(sealed, block.command)?, notes, ..block })
+            })
+            .collect()
     }
-}
 
-/// Get the default database path.
-fn default_db_path() -> Result<PathBuf> {
-    let home = std::env::var("HOME")
-        .context("HOME environment variable not set")?;
-    Ok(PathBuf::from(home).join(".nexus").join("nexus.db"))
-}
+    /// The output of the block stored in row `id` (a [`SessionBlock::id`]).
+    pub fn load_block_output(&self, id: i64) -> Result<Option<Value>> {
+        let row: Option<(Option<String>, Option<bool>, Option<String>, bool)> = self
+            .conn
+            .query_row(
+                "SELECT o.data, o.sealed, b.output_json, b.sealed FROM blocks b
+                 LEFT JOIN outputs o ON o.hash = b.output_hash
+                 WHERE b.id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?;
+        let json = match row {
+            Some((Some(data), data_sealed, _, _)) => self.open_payload(data_sealed.unwrap_or(false), data)?,
+            Some((None, _, Some(inline), sealed)) => self.unseal(sealed, inline)?.into_bytes(),
+            _ => return Ok(None),
+        };
+        Ok(Some(serde_json::from_slice(&json).context("Stored output is not a valid value")?))
+    }
 
-/// Parse an RFC3339 datetime string.
-fn parse_datetime(s: String) -> DateTime<Utc> {
-    DateTime::parse_from_rfc3339(&s)
-        .map(|dt| dt.with_timezone(&Utc))
-        .unwrap_or_else(|_| Utc::now())
-}
+    /// The terminal grid kept for the block stored in row `id`.
+    pub fn load_block_snapshot(&self, id: i64) -> Result<Option<TerminalGrid>> {
+        let data: Option<(String, bool)> = self
+            .conn
+            .query_row(
+                "SELECT o.data, o.sealed FROM blocks b JOIN outputs o ON o.hash = b.snapshot_hash WHERE b.id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match data {
+            Some((data, sealed)) => {
+                Ok(Some(rmp_serde::from_slice(&self.open_payload(sealed, data)?).context("Corrupt terminal snapshot")?))
+            }
+            None => Ok(None),
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Key of a payload in the `outputs` table: keyed with the store key
+    /// while writes are sealed, so the digest says nothing about content.
+    fn output_hash(&self, data: &[u8]) -> String {
+        match &self.cipher {
+            Some(cipher) if self.seal_writes => cipher.digest(data),
+            _ => sha256_hex(data),
+        }
+    }
 
-    #[test]
-    fn test_blocks() {
-        let store = Store::open_in_memory().unwrap();
-        let session_id = store.start_session("/home/user").unwrap();
+    /// Store `data` (compressed, and sealed if writes are) unless an equal
+    /// payload is already stored, returning its key.
+    fn save_payload(&self, data: &[u8]) -> Result<String> {
+        let hash = self.output_hash(data);
+        self.conn.execute(
+            "INSERT OR IGNORE INTO outputs (hash, data, sealed) VALUES (?1, ?2, ?3)",
+            params![hash, self.seal(&compress(data)?), self.seal_writes],
+        )?;
+        Ok(hash)
+    }
 
-        // Save a block with output
-        let output = Value::List(vec![Value::String("file1.txt".into()), Value::String("file2.txt".into())]);
-        store.save_block(
-            BlockId(1),
-            session_id,
-            "ls",
-            Some(&output),
-            Some(0),
-            Some(50),
-        ).unwrap();
+    fn open_payload(&self, sealed: bool, stored: String) -> Result<Vec<u8>> {
+        decompress(&self.unseal(sealed, stored)?)
+    }
 
-        // Get blocks
-        let blocks = store.get_session_blocks(session_id).unwrap();
+    /// Delete stored outputs no block refers to any more.
+    fn prune_outputs(&self) -> Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM outputs WHERE hash NOT IN
+                 (SELECT output_hash FROM blocks WHERE output_hash IS NOT NULL
+                  UNION SELECT snapshot_hash FROM blocks WHERE snapshot_hash IS NOT NULL)",
+            [],
+        )?)
+    }
+
+    /// Parse stored output JSON back to Value.
+    pub fn parse_block_output(json: &str) -> Option<Value> {
+        serde_json::from_str(json).ok()
+    }
+
+    /// Set the title of a finished block, replacing any it had (a rerun
+    /// in place finishes again).
+    pub fn save_block_title(&self, session_id: i64, block_id: BlockId, title: &str, exit_code: Option<i32>) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO block_titles (session_id, block_id, title, exit_code, sealed)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![session_id, block_id.0 as i64, self.seal(title), exit_code, self.seal_writes],
+        )?;
+        Ok(())
+    }
+
+    /// A session's block titles, in the order the blocks finished.
+    pub fn block_titles(&self, session_id: i64) -> Result<Vec<BlockTitle>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT block_id, title, exit_code, sealed FROM block_titles WHERE session_id = ?1 ORDER BY rowid ASC",
+        )?;
+        let titles = stmt
+            .query_map(params![session_id], |row| {
+                let title = BlockTitle {
+                    block_id: row.get::<_, i64>(0)? as u64,
+                    title: row.get(1)?,
+                    exit_code: row.get(2)?,
+                };
+                Ok((title, row.get::<_, bool>(3)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        titles
+            .into_iter()
+            .map(|(title, sealed)| Ok(BlockTitle { title: self.unseal(sealed, title.title)?, ..title }))
+            .collect()
+    }
+
+    /// Record what a finished block's process used, replacing any earlier
+    /// record (a rerun in place finishes again).
+    pub fn save_block_usage(&self, session_id: i64, block_id: BlockId, usage: &ResourceUsage) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO block_usage (session_id, block_id, user_ms, sys_ms, max_rss, blocks_in, blocks_out)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                session_id,
+                block_id.0 as i64,
+                usage.user_ms as i64,
+                usage.sys_ms as i64,
+                usage.max_rss as i64,
+                usage.blocks_in as i64,
+                usage.blocks_out as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// What a block's process used, if it was recorded.
+    pub fn block_usage(&self, session_id: i64, block_id: BlockId) -> Result<Option<ResourceUsage>> {
+        let usage = self
+            .conn
+            .query_row(
+                "SELECT user_ms, sys_ms, max_rss, blocks_in, blocks_out FROM block_usage
+                 WHERE session_id = ?1 AND block_id = ?2",
+                params![session_id, block_id.0 as i64],
+                |row| {
+                    Ok(ResourceUsage {
+                        user_ms: row.get::<_, i64>(0)? as u64,
+                        sys_ms: row.get::<_, i64>(1)? as u64,
+                        max_rss: row.get::<_, i64>(2)? as u64,
+                        blocks_in: row.get::<_, i64>(3)? as u64,
+                        blocks_out: row.get::<_, i64>(4)? as u64,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(usage)
+    }
+
+    // =========================================================================
+    // Usage
+    // =========================================================================
+
+    /// Count one use of `event` (and `detail`, if it names what was used).
+    pub fn record_usage(&self, event: &str, detail: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO usage (event, detail, count, last_at) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT (event, detail) DO UPDATE SET count = count + 1, last_at = excluded.last_at",
+            params![event, detail, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Every usage count, most used first.
+    pub fn usage_counts(&self) -> Result<Vec<UsageCount>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT event, detail, count, last_at FROM usage ORDER BY count DESC, event, detail"
+        )?;
+        let counts = stmt
+            .query_map([], |row| {
+                Ok(UsageCount {
+                    event: row.get(0)?,
+                    detail: row.get(1)?,
+                    count: row.get::<_, i64>(2)? as u64,
+                    last_at: parse_datetime(row.get::<_, String>(3)?),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(counts)
+    }
+
+    /// Forget all usage counts. Returns how many rows were removed.
+    pub fn purge_usage(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM usage", [])?)
+    }
+
+    // =========================================================================
+    // Schedules
+    // =========================================================================
+
+    /// Store a scheduled command. Returns its id.
+    pub fn add_schedule(&self, expr: &str, command: &str, cwd: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO schedules (expr, command, cwd, created_at, sealed) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![expr, self.seal(command), cwd, Utc::now().to_rfc3339(), self.seal_writes],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Every scheduled command, oldest first.
+    pub fn schedules(&self) -> Result<Vec<ScheduledCommand>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, expr, command, cwd, created_at, last_run_at, sealed FROM schedules ORDER BY id"
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                let schedule = ScheduledCommand {
+                    id: row.get(0)?,
+                    expr: row.get(1)?,
+                    command: row.get(2)?,
+                    cwd: row.get(3)?,
+                    created_at: parse_datetime(row.get::<_, String>(4)?),
+                    last_run_at: row.get::<_, Option<String>>(5)?.map(parse_datetime),
+                };
+                Ok((schedule, row.get::<_, bool>(6)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(mut schedule, sealed)| {
+                schedule.command = self.unseal(sealed, schedule.command)?;
+                Ok(schedule)
+            })
+            .collect()
+    }
+
+    /// Remove a scheduled command and its run history. False if there was
+    /// no such schedule.
+    pub fn remove_schedule(&self, id: i64) -> Result<bool> {
+        self.conn.execute("DELETE FROM schedule_runs WHERE schedule_id = ?1", params![id])?;
+        Ok(self.conn.execute("DELETE FROM schedules WHERE id = ?1", params![id])? > 0)
+    }
+
+    /// Note that a schedule's runs up to `at` have been taken care of,
+    /// unless someone else did since it was read with `last_run_at`.
+    /// False if they did.
+    pub fn claim_schedule(&self, id: i64, last_run_at: Option<DateTime<Utc>>, at: DateTime<Utc>) -> Result<bool> {
+        let claimed = self.conn.execute(
+            "UPDATE schedules SET last_run_at = ?1 WHERE id = ?2 AND last_run_at IS ?3",
+            params![at.to_rfc3339(), id, last_run_at.map(|t| t.to_rfc3339())],
+        )?;
+        Ok(claimed > 0)
+    }
+
+    /// Record that a run of a schedule started. Returns the run's id.
+    pub fn start_schedule_run(&self, schedule_id: i64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO schedule_runs (schedule_id, started_at) VALUES (?1, ?2)",
+            params![schedule_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Record how a run ended.
+    pub fn finish_schedule_run(&self, run_id: i64, exit_code: Option<i32>, duration_ms: Option<u64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE schedule_runs SET exit_code = ?1, duration_ms = ?2 WHERE id = ?3",
+            params![exit_code, duration_ms.map(|d| d as i64), run_id],
+        )?;
+        Ok(())
+    }
+
+    /// The latest `limit` runs of a schedule, newest first.
+    pub fn schedule_runs(&self, schedule_id: i64, limit: usize) -> Result<Vec<ScheduleRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, schedule_id, started_at, exit_code, duration_ms FROM schedule_runs
+             WHERE schedule_id = ?1 ORDER BY id DESC LIMIT ?2"
+        )?;
+        let runs = stmt
+            .query_map(params![schedule_id, limit as i64], |row| {
+                Ok(ScheduleRun {
+                    id: row.get(0)?,
+                    schedule_id: row.get(1)?,
+                    started_at: parse_datetime(row.get::<_, String>(2)?),
+                    exit_code: row.get(3)?,
+                    duration_ms: row.get::<_, Option<i64>>(4)?.map(|d| d as u64),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
+
+    // =========================================================================
+    // Problem lists
+    // =========================================================================
+
+    /// Replace the problems recorded for `command` run in `cwd`.
+    pub fn save_problems(&self, command: &str, cwd: &str, problems: &[Problem]) -> Result<()> {
+        let json = serde_json::to_string(problems)?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO problem_lists (key, problems, recorded_at, sealed) VALUES (?1, ?2, ?3, ?4)",
+            params![problem_key(command, cwd), self.seal(&json), Utc::now().to_rfc3339(), self.seal_writes],
+        )?;
+        Ok(())
+    }
+
+    /// The problems last recorded for `command` run in `cwd`, if any were.
+    pub fn problems(&self, command: &str, cwd: &str) -> Result<Option<Vec<Problem>>> {
+        let row: Option<(String, bool)> = self
+            .conn
+            .query_row(
+                "SELECT problems, sealed FROM problem_lists WHERE key = ?1",
+                params![problem_key(command, cwd)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(json, sealed)| Ok(serde_json::from_str(&self.unseal(sealed, json)?)?)).transpose()
+    }
+
+    // =========================================================================
+    // Retention
+    // =========================================================================
+
+    /// Per-session disk usage, newest session first. An output shared by
+    /// several blocks counts once per session that has it.
+    pub fn session_usage(&self) -> Result<Vec<SessionUsage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.started_at, s.ended_at, s.cwd, COUNT(b.id),
+                    COALESCE(SUM(LENGTH(b.command) + COALESCE(LENGTH(b.output_json), 0)), 0)
+                    + (SELECT COALESCE(SUM(LENGTH(o.data)), 0) FROM outputs o
+                       WHERE o.hash IN (SELECT output_hash FROM blocks WHERE session_id = s.id
+                                        UNION SELECT snapshot_hash FROM blocks WHERE session_id = s.id))
+             FROM sessions s
+             LEFT JOIN blocks b ON b.session_id = s.id
+             GROUP BY s.id
+             ORDER BY s.id DESC"
+        )?;
+
+        let usage = stmt
+            .query_map([], |row| {
+                Ok(SessionUsage {
+                    session: Session {
+                        id: row.get(0)?,
+                        started_at: parse_datetime(row.get::<_, String>(1)?),
+                        ended_at: row.get::<_, Option<String>>(2)?.map(parse_datetime),
+                        cwd: row.get(3)?,
+                    },
+                    blocks: row.get::<_, i64>(4)? as u64,
+                    bytes: row.get::<_, i64>(5)? as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(usage)
+    }
+
+    /// Size of the database file in bytes, including free pages.
+    pub fn db_size(&self) -> Result<u64> {
+        let pages: i64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok((pages * page_size) as u64)
+    }
+
+    /// Bytes in use, excluding pages on the free list (what `VACUUM` would leave).
+    fn live_size(&self) -> Result<u64> {
+        let free: i64 = self.conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        let page_size: i64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok(self.db_size()?.saturating_sub((free * page_size) as u64))
+    }
+
+    /// Delete sessions (and their blocks) that fall outside `policy`.
+    ///
+    /// Live sessions ([`Store::live_sessions`]), in this process or any
+    /// other, are never deleted. The database is vacuumed afterwards if
+    /// anything was removed.
+    pub fn apply_retention(&self, policy: &RetentionPolicy) -> Result<PurgeStats> {
+        let keep = self.live_sessions()?;
+        let unkept = |ids: Vec<i64>| -> Vec<i64> { ids.into_iter().filter(|id| !keep.contains(id)).collect() };
+        let mut stats = PurgeStats::default();
+
+        if let Some(max_age) = policy.max_age {
+            let cutoff = Utc::now() - chrono::Duration::from_std(max_age)?;
+            let ids = self.session_ids(
+                "SELECT id FROM sessions WHERE started_at < ?1",
+                params![cutoff.to_rfc3339()],
+            )?;
+            stats = stats.add(self.delete_sessions(&unkept(ids))?);
+        }
+
+        if let Some(max_sessions) = policy.max_sessions {
+            // Kept sessions count toward the limit.
+            let newest_first = self.session_ids("SELECT id FROM sessions ORDER BY id DESC", [])?;
+            let kept = newest_first.iter().filter(|id| keep.contains(id)).count();
+            let ids: Vec<i64> = unkept(newest_first)
+                .into_iter()
+                .skip(max_sessions.saturating_sub(kept))
+                .collect();
+            stats = stats.add(self.delete_sessions(&ids)?);
+        }
+
+        if let Some(max_bytes) = policy.max_db_bytes {
+            let oldest_first = unkept(self.session_ids("SELECT id FROM sessions ORDER BY id ASC", [])?);
+            for id in oldest_first {
+                if self.live_size()? <= max_bytes {
+                    break;
+                }
+                stats = stats.add(self.delete_sessions(&[id])?);
+            }
+        }
+
+        if stats.sessions > 0 {
+            self.vacuum()?;
+        }
+        Ok(stats)
+    }
+
+    /// Drop the stored output of blocks older than `age`, keeping the
+    /// commands themselves. Returns the number of blocks affected.
+    pub fn purge_outputs_older_than(&self, age: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - chrono::Duration::from_std(age)?;
+        let purged = self.conn.execute(
+            "UPDATE blocks SET output_json = NULL, output_hash = NULL, snapshot_hash = NULL
+             WHERE timestamp < ?1
+               AND (output_json IS NOT NULL OR output_hash IS NOT NULL OR snapshot_hash IS NOT NULL)",
+            params![cutoff.to_rfc3339()],
+        )?;
+        if purged > 0 {
+            self.prune_outputs()?;
+            self.vacuum()?;
+        }
+        Ok(purged)
+    }
+
+    /// Problems reported by SQLite's `PRAGMA integrity_check`; empty when
+    /// the database is sound.
+    pub fn integrity_check(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+        let problems = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(problems.into_iter().filter(|p| p != "ok").collect())
+    }
+
+    /// Rebuild the database file, returning freed pages to the filesystem.
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM")?;
+        Ok(())
+    }
+
+    fn session_ids(&self, sql: &str, params: impl rusqlite::Params) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let ids = stmt
+            .query_map(params, |row| row.get(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+        Ok(ids)
+    }
+
+    fn delete_sessions(&self, ids: &[i64]) -> Result<PurgeStats> {
+        let mut stats = PurgeStats::default();
+        for id in ids {
+            self.conn.execute("DELETE FROM block_titles WHERE session_id = ?1", params![id])?;
+            self.conn.execute("DELETE FROM block_usage WHERE session_id = ?1", params![id])?;
+            stats.blocks += self.conn.execute("DELETE FROM blocks WHERE session_id = ?1", params![id])?;
+            stats.sessions += self.conn.execute("DELETE FROM sessions WHERE id = ?1", params![id])?;
+        }
+        if stats.blocks > 0 {
+            self.prune_outputs()?;
+        }
+        Ok(stats)
+    }
+}
+
+impl PurgeStats {
+    fn add(self, other: Self) -> Self {
+        Self {
+            sessions: self.sessions + other.sessions,
+            blocks: self.blocks + other.blocks,
+        }
+    }
+}
+
+/// How often the background task marks this process's sessions live.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long a session stays live without a heartbeat.
+const LIVE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How often the background task re-applies the retention policy.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Sessions opened by kernels in this process, which the background task
+/// keeps marked live in the database.
+static OPEN_SESSIONS: Mutex<BTreeSet<i64>> = Mutex::new(BTreeSet::new());
+
+/// Keep `id` marked live until [`close_session`].
+pub(crate) fn open_session(id: i64) {
+    OPEN_SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).insert(id);
+}
+
+/// Stop the heartbeat for a session passed to [`open_session`]. The caller
+/// ends it in the store.
+pub(crate) fn close_session(id: i64) {
+    OPEN_SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).remove(&id);
+}
+
+/// Start the background session task (once per process).
+///
+/// Every [`HEARTBEAT_INTERVAL`] it touches this process's open sessions,
+/// and every [`RETENTION_INTERVAL`] it applies the retention policy, if one
+/// is set. It has its own connection so pruning and `VACUUM` never hold up
+/// the kernel.
+pub fn spawn_retention_task() {
+    static STARTED: Once = Once::new();
+    STARTED.call_once(|| {
+        let policy = RetentionPolicy::from_env();
+        let spawned = std::thread::Builder::new()
+            .name("nexus-retention".into())
+            .spawn(move || {
+                let store = match Store::open_default() {
+                    Ok(store) => store,
+                    Err(e) => return tracing::warn!("Retention task has no store: {}", e),
+                };
+                let mut last_pass: Option<std::time::Instant> = None;
+                loop {
+                    let open: Vec<i64> =
+                        OPEN_SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).iter().copied().collect();
+                    if let Err(e) = store.touch_sessions(&open) {
+                        tracing::warn!("Session heartbeat failed: {}", e);
+                    }
+                    let due = last_pass.is_none_or(|t| t.elapsed() >= RETENTION_INTERVAL);
+                    if due && policy != RetentionPolicy::default() {
+                        last_pass = Some(std::time::Instant::now());
+                        match store.apply_retention(&policy) {
+                            Ok(stats) if stats.sessions > 0 => tracing::info!(
+                                "Retention removed {} sessions ({} blocks)",
+                                stats.sessions,
+                                stats.blocks
+                            ),
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("Retention pass failed: {}", e),
+                        }
+                    }
+                    std::thread::sleep(HEARTBEAT_INTERVAL);
+                }
+            });
+        if let Err(e) = spawned {
+            tracing::warn!("Failed to start retention task: {}", e);
+        }
+    });
+}
+
+/// Get the default database path.
+pub(crate) fn default_db_path() -> Result<PathBuf> {
+    let home = std::env::var("HOME")
+        .context("HOME environment variable not set")?;
+    Ok(PathBuf::from(home).join(".nexus").join("nexus.db"))
+}
+
+/// Row key of a command's problem list: a hash, so the commands and
+/// directories themselves aren't stored.
+fn problem_key(command: &str, cwd: &str) -> String {
+    sha256_hex(format!("{}\0{}", cwd, command))
+}
+
+fn sha256_hex(data: impl AsRef<[u8]>) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `data` as stored text: short UTF-8 as it is, anything else deflated and
+/// base64-encoded behind [`COMPRESSED_PREFIX`]. JSON never starts with the
+/// prefix, so the two can't be confused.
+fn compress(data: &[u8]) -> Result<String> {
+    use base64::Engine;
+    use std::io::Write;
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.len() < COMPRESS_MIN_BYTES
+    {
+        return Ok(text.to_string());
+    }
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    let deflated = encoder.finish()?;
+    Ok(format!("{}{}", COMPRESSED_PREFIX, base64::engine::general_purpose::STANDARD.encode(deflated)))
+}
+
+fn decompress(stored: &str) -> Result<Vec<u8>> {
+    use base64::Engine;
+    use std::io::Read;
+    let Some(encoded) = stored.strip_prefix(COMPRESSED_PREFIX) else {
+        return Ok(stored.as_bytes().to_vec());
+    };
+    let deflated = base64::engine::general_purpose::STANDARD.decode(encoded).context("Corrupt compressed output")?;
+    let mut data = Vec::new();
+    flate2::read::DeflateDecoder::new(deflated.as_slice())
+        .read_to_end(&mut data)
+        .context("Corrupt compressed output")?;
+    Ok(data)
+}
+
+/// Parse an RFC3339 datetime string.
+fn parse_datetime(s: String) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(&s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexus_term::{Cell, CellFlags, Color};
+
+    #[test]
+    fn test_blocks() {
+        let store = Store::open_in_memory().unwrap();
+        let session_id = store.start_session("/home/user").unwrap();
+
+        // Save a block with output
+        let output = Value::List(vec![Value::String("file1.txt".into()), Value::String("file2.txt".into())]);
+        store.save_block(
+            BlockId(1),
+            session_id,
+            "ls",
+            Some(&output),
+            Some(0),
+            Some(50),
+        ).unwrap();
+
+        // Get blocks
+        let blocks = store.get_session_blocks(session_id).unwrap();
         assert_eq!(blocks.len(), 1);
         assert_eq!(blocks[0].command, "ls");
 
         // Parse output
         let parsed = Store::parse_block_output(blocks[0].output_json.as_ref().unwrap());
         assert!(parsed.is_some());
+
+        assert!(store.delete_block(session_id, BlockId(1)).unwrap());
+        assert!(!store.delete_block(session_id, BlockId(1)).unwrap());
+        assert!(store.get_session_blocks(session_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_old_block_ids_are_renumbered_and_reserved() {
+        let conn = Connection::open_in_memory().unwrap();
+        let mut store = Store { conn, cipher: None, seal_writes: false };
+        store.create_schema().unwrap();
+        store.conn.execute("UPDATE meta SET value = '3' WHERE key = 'schema_version'", []).unwrap();
+        let s1 = store.start_session("/a").unwrap();
+        let s2 = store.start_session("/b").unwrap();
+        // Both older processes started counting at 1.
+        store.save_block(BlockId(1), s1, "ls", None, Some(0), None).unwrap();
+        store.save_block(BlockId(1), s2, "pwd", None, Some(0), None).unwrap();
+        store.save_block(BlockId(2), s2, "date", None, Some(0), None).unwrap();
+
+        store.initialize().unwrap();
+        assert_eq!(store.get_schema_version().unwrap(), SCHEMA_VERSION);
+        let ids: Vec<u64> = [s1, s2]
+            .iter()
+            .flat_map(|s| store.get_session_blocks(*s).unwrap())
+            .map(|b| b.block_id)
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+        assert!(BlockIdAllocator::global().peek().0 > 3);
+    }
+
+    fn save(store: &Store, session_id: i64, command: &str) {
+        let output = Value::String("x".repeat(100));
+        store.save_block(BlockId(1), session_id, command, Some(&output), Some(0), None).unwrap();
+    }
+
+    #[test]
+    fn test_session_usage() {
+        let store = Store::open_in_memory().unwrap();
+        let a = store.start_session("/a").unwrap();
+        let b = store.start_session("/b").unwrap();
+        save(&store, a, "ls");
+        save(&store, a, "pwd");
+
+        let usage = store.session_usage().unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].session.id, b);
+        assert_eq!((usage[0].blocks, usage[0].bytes), (0, 0));
+        assert_eq!(usage[1].blocks, 2);
+        // Both blocks have the same output, stored and counted once.
+        assert!(usage[1].bytes > 100 && usage[1].bytes < 200);
+    }
+
+    #[test]
+    fn test_retention_max_sessions_keeps_live_session() {
+        let store = Store::open_in_memory().unwrap();
+        let ids: Vec<i64> = (0..5).map(|_| store.start_session("/").unwrap()).collect();
+        for id in &ids {
+            save(&store, *id, "ls");
+        }
+        for id in &ids[1..] {
+            store.end_session(*id).unwrap();
+        }
+
+        let policy = RetentionPolicy { max_sessions: Some(2), max_age: None, max_db_bytes: None };
+        let stats = store.apply_retention(&policy).unwrap();
+        assert_eq!(stats, PurgeStats { sessions: 3, blocks: 3 });
+
+        let left: Vec<i64> = store.session_usage().unwrap().iter().map(|u| u.session.id).collect();
+        assert_eq!(left, vec![ids[4], ids[0]]);
+    }
+
+    #[test]
+    fn test_retention_keeps_other_processes_live_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nexus.db");
+        let other = Store::open(&path).unwrap();
+        let store = Store::open(&path).unwrap();
+
+        let live = other.start_session("/").unwrap();
+        let crashed = other.start_session("/").unwrap();
+        let long_ago = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        other.conn.execute("UPDATE sessions SET last_active = ?1 WHERE id = ?2", params![long_ago, crashed]).unwrap();
+        for _ in 0..3 {
+            let id = store.start_session("/").unwrap();
+            store.end_session(id).unwrap();
+        }
+        assert_eq!(store.live_sessions().unwrap(), vec![live]);
+
+        let policy = RetentionPolicy { max_sessions: Some(1), max_age: None, max_db_bytes: None };
+        assert_eq!(store.apply_retention(&policy).unwrap().sessions, 4);
+        let left: Vec<i64> = store.session_usage().unwrap().iter().map(|u| u.session.id).collect();
+        assert_eq!(left, vec![live]);
+    }
+
+    #[test]
+    fn test_default_policy_keeps_everything() {
+        let store = Store::open_in_memory().unwrap();
+        for _ in 0..3 {
+            let id = store.start_session("/").unwrap();
+            store.end_session(id).unwrap();
+        }
+        assert_eq!(store.apply_retention(&RetentionPolicy::default()).unwrap(), PurgeStats::default());
+        assert_eq!(store.session_usage().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_retention_max_age() {
+        let store = Store::open_in_memory().unwrap();
+        let old = store.start_session("/").unwrap();
+        let recent = store.start_session("/").unwrap();
+        let long_ago = (Utc::now() - chrono::Duration::days(30)).to_rfc3339();
+        store.conn.execute("UPDATE sessions SET started_at = ?1 WHERE id = ?2", params![long_ago, old]).unwrap();
+        store.end_session(old).unwrap();
+
+        let policy = RetentionPolicy {
+            max_sessions: None,
+            max_age: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            max_db_bytes: None,
+        };
+        assert_eq!(store.apply_retention(&policy).unwrap().sessions, 1);
+        assert_eq!(store.get_latest_session().unwrap().unwrap().id, recent);
+        assert_eq!(store.session_usage().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_purge_outputs_keeps_commands() {
+        let store = Store::open_in_memory().unwrap();
+        let session = store.start_session("/").unwrap();
+        save(&store, session, "old");
+        save(&store, session, "new");
+        let long_ago = (Utc::now() - chrono::Duration::hours(2)).to_rfc3339();
+        store.conn.execute("UPDATE blocks SET timestamp = ?1 WHERE command = 'old'", params![long_ago]).unwrap();
+
+        assert_eq!(store.purge_outputs_older_than(Duration::from_secs(3600)).unwrap(), 1);
+        let blocks = store.get_session_blocks(session).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0].output_json.is_none());
+        assert!(blocks[1].output_json.is_some());
+    }
+
+    #[test]
+    fn test_encrypted_blocks_roundtrip() {
+        let store = Store::open_in_memory_encrypted(&[9; 32]).unwrap();
+        let session = store.start_session("/").unwrap();
+        let output = Value::String("AWS_SECRET=abc123".into());
+        store.save_block(BlockId(1), session, "env | grep AWS", Some(&output), Some(0), None).unwrap();
+
+        let command: String = store.conn.query_row("SELECT command FROM blocks", [], |row| row.get(0)).unwrap();
+        let json: String = store.conn.query_row("SELECT data FROM outputs", [], |row| row.get(0)).unwrap();
+        assert!(!command.contains("AWS") && !json.contains("abc123"));
+        assert_ne!(store.get_session_blocks(session).unwrap()[0].output_hash, Some(sha256_hex(&json)));
+
+        let blocks = store.get_session_blocks(session).unwrap();
+        assert_eq!(blocks[0].command, "env | grep AWS");
+        assert_eq!(Store::parse_block_output(blocks[0].output_json.as_ref().unwrap()), Some(output));
+    }
+
+    #[test]
+    fn test_identical_outputs_are_stored_once() {
+        let store = Store::open_in_memory().unwrap();
+        let session = store.start_session("/").unwrap();
+        let output = Value::String("Every 2s: uptime\n up 3 days".into());
+        store.save_block(BlockId(1), session, "uptime", Some(&output), Some(0), None).unwrap();
+        store.save_block(BlockId(2), session, "uptime", Some(&output), Some(0), None).unwrap();
+        store.save_block(BlockId(3), session, "uptime", Some(&Value::String("changed".into())), Some(0), None).unwrap();
+
+        let count: i64 = store.conn.query_row("SELECT COUNT(*) FROM outputs", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        let blocks = store.get_session_blocks(session).unwrap();
+        assert_eq!(blocks[0].output_hash, blocks[1].output_hash);
+        assert_ne!(blocks[1].output_hash, blocks[2].output_hash);
+        assert_eq!(Store::parse_block_output(blocks[1].output_json.as_ref().unwrap()), Some(output));
+
+        // The shared copy goes only with the last block using it.
+        store.delete_block(session, BlockId(1)).unwrap();
+        assert!(store.get_session_blocks(session).unwrap()[0].output_json.is_some());
+        store.delete_block(session, BlockId(2)).unwrap();
+        let count: i64 = store.conn.query_row("SELECT COUNT(*) FROM outputs", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_large_outputs_are_compressed() {
+        let store = Store::open_in_memory().unwrap();
+        let session = store.start_session("/").unwrap();
+        let output = Value::String("drwxr-xr-x  staff  src\n".repeat(200));
+        store.save_block(BlockId(1), session, "ls -l", Some(&output), Some(0), None).unwrap();
+
+        let data: String = store.conn.query_row("SELECT data FROM outputs", [], |row| row.get(0)).unwrap();
+        assert!(data.starts_with(COMPRESSED_PREFIX) && data.len() < 1000);
+        let blocks = store.get_session_blocks(session).unwrap();
+        assert_eq!(Store::parse_block_output(blocks[0].output_json.as_ref().unwrap()), Some(output));
+
+        assert_eq!(decompress(&compress(b"short").unwrap()).unwrap(), b"short");
+        assert_eq!(decompress(&compress(&[0xff, 0, 1]).unwrap()).unwrap(), [0xff, 0, 1]);
+    }
+
+    #[test]
+    fn test_load_session_blocks_lazily() {
+        let store = Store::open_in_memory_encrypted(&[9; 32]).unwrap();
+        let session = store.start_session("/").unwrap();
+        store.save_block(BlockId(1), session, "ls", None, Some(0), Some(12)).unwrap();
+        store.save_block(BlockId(2), session, "cargo build", None, Some(1), None).unwrap();
+        let output = Value::List(vec![Value::String("a".into()), Value::Int(2)]);
+        assert!(store.save_block_output(session, BlockId(1), &output).unwrap());
+        assert!(!store.save_block_output(session, BlockId(9), &output).unwrap());
+
+        let mut grid = TerminalGrid::new(20, 2);
+        let red = Cell { c: 'E', fg: Color::Named(1), flags: CellFlags { bold: true, ..Default::default() }, ..Default::default() };
+        grid.set(0, 0, red);
+        assert!(store.save_block_snapshot(session, BlockId(2), &grid).unwrap());
+
+        let blocks = store.load_session_blocks(session).unwrap();
+        assert_eq!(blocks.iter().map(|b| b.command.as_str()).collect::<Vec<_>>(), ["ls", "cargo build"]);
+        assert!(blocks[0].has_output && !blocks[0].has_snapshot);
+        assert!(!blocks[1].has_output && blocks[1].has_snapshot);
+        assert_eq!(blocks[0].duration_ms, Some(12));
+
+        assert_eq!(store.load_block_output(blocks[0].id).unwrap(), Some(output));
+        assert_eq!(store.load_block_output(blocks[1].id).unwrap(), None);
+        assert!(store.load_block_snapshot(blocks[0].id).unwrap().is_none());
+        let restored = store.load_block_snapshot(blocks[1].id).unwrap().unwrap();
+        assert_eq!(restored.size(), (20, 2));
+        assert_eq!(restored.get(0, 0).unwrap().fg, Color::Named(1));
+        assert!(restored.get(0, 0).unwrap().flags.bold);
+
+        let next = store.start_session("/").unwrap();
+        assert_eq!(store.previous_session_with_blocks(next).unwrap().map(|s| s.id), Some(session));
+        assert!(store.previous_session_with_blocks(session).unwrap().is_none());
+
+        // Snapshots count as references when pruning.
+        store.delete_block(session, BlockId(1)).unwrap();
+        let count: i64 = store.conn.query_row("SELECT COUNT(*) FROM outputs", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_encrypt_existing_rows() {
+        let mut store = Store::open_in_memory().unwrap();
+        let session = store.start_session("/").unwrap();
+        save(&store, session, "echo plaintext");
+        assert!(store.encrypt_existing().is_err());
+
+        store.enable_encryption(StoreCipher::new(&[9; 32])).unwrap();
+        assert_eq!(store.encrypt_existing().unwrap(), 1);
+        assert_eq!(store.encrypt_existing().unwrap(), 0);
+
+        let (raw, sealed): (String, bool) =
+            store.conn.query_row("SELECT command, sealed FROM blocks", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert!(sealed && !raw.contains("plaintext"));
+        let (hash, raw, sealed): (String, String, bool) = store
+            .conn
+            .query_row("SELECT hash, data, sealed FROM outputs", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap();
+        assert!(sealed && !raw.contains("plaintext"));
+        assert_eq!(store.get_session_blocks(session).unwrap()[0].output_hash, Some(hash));
+        assert_eq!(store.get_session_blocks(session).unwrap()[0].command, "echo plaintext");
+    }
+
+    #[test]
+    fn test_sealed_rows_stay_readable_after_turning_encryption_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nexus.db");
+        let open = |setting| {
+            let mut store = Store { conn: Connection::open(&path).unwrap(), cipher: None, seal_writes: false };
+            store.initialize().unwrap();
+            store.configure_encryption(setting, || Ok(StoreCipher::new(&[5; 32]))).unwrap();
+            store
+        };
+
+        let store = open(EncryptionSetting::On);
+        let session = store.start_session("/").unwrap();
+        save(&store, session, "echo sealed");
+        drop(store);
+
+        let store = open(EncryptionSetting::Off);
+        assert!(!store.is_encrypted());
+        drop(store);
+
+        let store = open(EncryptionSetting::Auto);
+        assert!(!store.is_encrypted());
+        assert_eq!(store.get_session_blocks(session).unwrap()[0].command, "echo sealed");
+    }
+
+    #[test]
+    fn test_usage_counts() {
+        let store = Store::open_in_memory().unwrap();
+        store.record_usage("agent.query", "").unwrap();
+        store.record_usage("palette.action", "Run tests").unwrap();
+        store.record_usage("palette.action", "Run tests").unwrap();
+        store.record_usage("palette.action", "Deploy").unwrap();
+
+        let counts = store.usage_counts().unwrap();
+        assert_eq!(counts.len(), 3);
+        assert_eq!((counts[0].detail.as_str(), counts[0].count), ("Run tests", 2));
+
+        assert_eq!(store.purge_usage().unwrap(), 3);
+        assert!(store.usage_counts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_schedules_and_runs() {
+        let store = Store::open_in_memory().unwrap();
+        let id = store.add_schedule("0 9 * * 1-5", "git fetch --all", "/repo").unwrap();
+        let other = store.add_schedule("@hourly", "date", "/").unwrap();
+
+        let run = store.start_schedule_run(id).unwrap();
+        store.finish_schedule_run(run, Some(0), Some(1200)).unwrap();
+        store.start_schedule_run(id).unwrap();
+        let now = Utc::now();
+        assert!(store.claim_schedule(id, None, now).unwrap());
+        assert!(!store.claim_schedule(id, None, now).unwrap());
+        assert!(store.claim_schedule(id, Some(now), Utc::now()).unwrap());
+
+        let schedules = store.schedules().unwrap();
+        assert_eq!(schedules.len(), 2);
+        assert_eq!(schedules[0].command, "git fetch --all");
+        assert!(schedules[0].last_run_at.is_some());
+        assert!(schedules[1].last_run_at.is_none());
+
+        let runs = store.schedule_runs(id, 10).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].exit_code, None);
+        assert_eq!((runs[1].exit_code, runs[1].duration_ms), (Some(0), Some(1200)));
+
+        assert!(store.remove_schedule(id).unwrap());
+        assert!(!store.remove_schedule(id).unwrap());
+        assert!(store.schedule_runs(id, 10).unwrap().is_empty());
+        assert_eq!(store.schedules().unwrap()[0].id, other);
+    }
+
+    #[test]
+    fn test_schedules_from_before_encryption_stay_readable() {
+        let mut store = Store::open_in_memory().unwrap();
+        store.add_schedule("@hourly", "date", "/").unwrap();
+        store.enable_encryption(StoreCipher::new(&[3; 32])).unwrap();
+        store.add_schedule("@daily", "git fetch", "/repo").unwrap();
+
+        let raw: String = store.conn.query_row("SELECT command FROM schedules WHERE id = 2", [], |row| row.get(0)).unwrap();
+        assert_ne!(raw, "git fetch");
+        let commands: Vec<_> = store.schedules().unwrap().into_iter().map(|s| s.command).collect();
+        assert_eq!(commands, ["date", "git fetch"]);
+    }
+
+    #[test]
+    fn test_problem_lists() {
+        use crate::problems::Severity;
+
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(store.problems("cargo build", "/repo").unwrap(), None);
+        let problems = vec![Problem {
+            severity: Severity::Warning,
+            file: "src/main.rs".to_string(),
+            line: 3,
+            column: Some(9),
+            message: "unused variable: `x`".to_string(),
+        }];
+        store.save_problems("cargo build", "/repo", &problems).unwrap();
+        store.save_problems("cargo build", "/other", &[]).unwrap();
+        assert_eq!(store.problems("cargo build", "/repo").unwrap(), Some(problems));
+        assert_eq!(store.problems("cargo build", "/other").unwrap(), Some(Vec::new()));
+        assert_eq!(store.problems("cargo test", "/repo").unwrap(), None);
+    }
+
+    #[test]
+    fn test_block_titles() {
+        let store = Store::open_in_memory().unwrap();
+        let a = store.start_session("/a").unwrap();
+        let b = store.start_session("/b").unwrap();
+        assert!(store.previous_titled_session(b).unwrap().is_none());
+
+        store.save_block_title(a, BlockId(2), "cargo build", Some(0)).unwrap();
+        store.save_block_title(a, BlockId(1), "git status", Some(0)).unwrap();
+        store.save_block_title(a, BlockId(2), "cargo build \u{2014} 1 error", Some(101)).unwrap();
+        let titles = store.block_titles(a).unwrap();
+        assert_eq!(titles.iter().map(|t| t.title.as_str()).collect::<Vec<_>>(), ["git status", "cargo build \u{2014} 1 error"]);
+        assert_eq!(titles[1].exit_code, Some(101));
+        assert_eq!(store.previous_titled_session(b).unwrap().map(|s| s.id), Some(a));
+
+        store.delete_block(a, BlockId(1)).unwrap();
+        assert_eq!(store.block_titles(a).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_block_usage() {
+        let store = Store::open_in_memory().unwrap();
+        let session = store.start_session("/a").unwrap();
+        assert_eq!(store.block_usage(session, BlockId(1)).unwrap(), None);
+
+        let usage = ResourceUsage { user_ms: 1200, sys_ms: 300, max_rss: 210 << 20, blocks_in: 8, blocks_out: 0 };
+        store.save_block_usage(session, BlockId(1), &ResourceUsage::default()).unwrap();
+        store.save_block_usage(session, BlockId(1), &usage).unwrap();
+        assert_eq!(store.block_usage(session, BlockId(1)).unwrap(), Some(usage));
+
+        store.delete_block(session, BlockId(1)).unwrap();
+        assert_eq!(store.block_usage(session, BlockId(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_block_notes() {
+        let store = Store::open_in_memory_encrypted(&[3; 32]).unwrap();
+        let session = store.start_session("/a").unwrap();
+        store.save_block(BlockId(1), session, "make", None, Some(2), None).unwrap();
+        let notes = vec![
+            BlockNote { line: 0, text: "flaky on CI".into() },
+            BlockNote { line: 14, text: "this is the real error".into() },
+        ];
+        assert!(store.save_block_notes(session, BlockId(1), &notes).unwrap());
+        assert!(!store.save_block_notes(session, BlockId(9), &notes).unwrap());
+        let blocks = store.load_session_blocks(session).unwrap();
+        assert_eq!(blocks[0].notes, notes);
+
+        let (raw, sealed): (String, bool) =
+            store.conn.query_row("SELECT notes, notes_sealed FROM blocks", [], |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert!(sealed && !raw.contains("flaky"));
+
+        assert!(store.save_row_notes(blocks[0].id, &[]).unwrap());
+        let stored: Option<String> = store.conn.query_row("SELECT notes FROM blocks", [], |row| row.get(0)).unwrap();
+        assert_eq!(stored, None);
+        assert!(store.load_session_blocks(session).unwrap()[0].notes.is_empty());
+    }
+
+    #[test]
+    fn test_parse_limit() {
+        assert_eq!(parse_limit("never"), Some(None));
+        assert_eq!(parse_limit("0"), Some(None));
+        assert_eq!(parse_limit(" 30 "), Some(Some(30)));
+        assert_eq!(parse_limit("lots"), None);
     }
 }
@@ -0,0 +1,223 @@
+//! `plugin.json`: what a plugin provides and what it may touch.
+//!
+//! ```json
+//! {
+//!   "name": "notes",
+//!   "version": "0.1.0",
+//!   "description": "Search my notes",
+//!   "module": "notes.wasm",
+//!   "commands": [{ "name": "notes", "description": "Search ~/notes" }],
+//!   "capabilities": { "read": ["~/notes"], "write": [], "net": [], "env": ["EDITOR"] },
+//!   "renderers": [{ "command": "notes", "shape": "record", "style": "card", "title": "file" }]
+//! }
+//! ```
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File name of the manifest inside a plugin directory.
+pub const MANIFEST_FILE: &str = "plugin.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    /// Module file, relative to the plugin directory.
+    #[serde(default = "default_module")]
+    pub module: String,
+    pub commands: Vec<CommandSpec>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    /// How the UI should display this plugin's output.
+    #[serde(default)]
+    pub renderers: Vec<RendererSpec>,
+}
+
+/// A command the plugin's module answers to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// What the sandbox lets the plugin reach. Everything else is denied.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Directories the plugin may read, recursively. `~` is the home
+    /// directory and `.` the shell's working directory at invocation.
+    #[serde(default)]
+    pub read: Vec<String>,
+    /// Directories the plugin may write. Implies read.
+    #[serde(default)]
+    pub write: Vec<String>,
+    /// Hosts the plugin may connect to, as `host` (any port) or `host:port`.
+    #[serde(default)]
+    pub net: Vec<String>,
+    /// Environment variables the plugin sees, by name, or by prefix as
+    /// `NAME_*`.
+    #[serde(default)]
+    pub env: Vec<String>,
+}
+
+impl Capabilities {
+    /// One-line summary for `plugin list`.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.read.is_empty() {
+            parts.push(format!("read {}", self.read.join(",")));
+        }
+        if !self.write.is_empty() {
+            parts.push(format!("write {}", self.write.join(",")));
+        }
+        if !self.net.is_empty() {
+            parts.push(format!("net {}", self.net.join(",")));
+        }
+        if !self.env.is_empty() {
+            parts.push(format!("env {}", self.env.join(",")));
+        }
+        if parts.is_empty() { "none".to_string() } else { parts.join("; ") }
+    }
+}
+
+/// A declarative renderer for values of a given shape, optionally only
+/// from one command. The UI reads these from plugin manifests and from
+/// `~/.nexus/renderers.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RendererSpec {
+    /// Command whose output this applies to (the first word of the command
+    /// line). Any command when absent.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Value shape this applies to. Any shape when absent.
+    #[serde(default)]
+    pub shape: Option<ValueShape>,
+    /// `kind` a structured value must have.
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Fields a record or structured value must all have.
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Higher wins when several renderers match.
+    #[serde(default)]
+    pub priority: i32,
+    pub style: RenderStyle,
+    /// Card: field shown as the heading.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Card: field shown under the heading.
+    #[serde(default)]
+    pub subtitle: Option<String>,
+    /// Card: fields listed in the body, in order. All remaining fields when empty.
+    #[serde(default)]
+    pub show: Vec<String>,
+    /// Template: text with `{field}` placeholders.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueShape {
+    Record,
+    Structured,
+    List,
+    Table,
+    String,
+    Number,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderStyle {
+    /// A bordered card with a heading, subtitle and key/value body.
+    Card,
+    /// One line per value, from `template`.
+    Template,
+}
+
+fn default_module() -> String {
+    "plugin.wasm".to_string()
+}
+
+impl Manifest {
+    /// Read and validate `dir/plugin.json`.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let text = std::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let manifest: Self =
+            serde_json::from_str(&text).with_context(|| format!("invalid manifest {}", path.display()))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if !is_command_name(&self.name) {
+            anyhow::bail!("invalid plugin name '{}'", self.name);
+        }
+        if self.commands.is_empty() {
+            anyhow::bail!("plugin '{}' declares no commands", self.name);
+        }
+        if let Some(cmd) = self.commands.iter().find(|c| !is_command_name(&c.name)) {
+            anyhow::bail!("plugin '{}': invalid command name '{}'", self.name, cmd.name);
+        }
+        if Path::new(&self.module).components().any(|c| !matches!(c, std::path::Component::Normal(_))) {
+            anyhow::bail!("plugin '{}': module must be a path inside the plugin directory", self.name);
+        }
+        Ok(())
+    }
+}
+
+/// Names that can be typed as a command without quoting.
+fn is_command_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_with_defaults() {
+        let manifest: Manifest =
+            serde_json::from_str(r#"{"name": "notes", "commands": [{"name": "notes"}]}"#).unwrap();
+        assert_eq!(manifest.module, "plugin.wasm");
+        assert_eq!(manifest.capabilities, Capabilities::default());
+        assert!(manifest.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rejects_module_outside_plugin_dir() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{"name": "notes", "module": "../other.wasm", "commands": [{"name": "notes"}]}"#,
+        )
+        .unwrap();
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_rejects_unquotable_command_names() {
+        let manifest: Manifest =
+            serde_json::from_str(r#"{"name": "notes", "commands": [{"name": "rm -rf"}]}"#).unwrap();
+        assert!(manifest.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_renderers() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{"name": "weather", "commands": [{"name": "weather"}],
+                "renderers": [{"command": "weather", "shape": "record", "style": "card", "title": "city"}]}"#,
+        )
+        .unwrap();
+        let spec = &manifest.renderers[0];
+        assert_eq!(spec.shape, Some(ValueShape::Record));
+        assert_eq!(spec.style, RenderStyle::Card);
+        assert_eq!(spec.title.as_deref(), Some("city"));
+    }
+}
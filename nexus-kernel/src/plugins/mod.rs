@@ -0,0 +1,428 @@
+//! WebAssembly plugins that add native commands.
+//!
+//! A plugin is a directory under `~/.nexus/plugins` or
+//! `~/.config/nexus/plugins` holding a [`plugin.json`](manifest) and a wasm
+//! module; when both have a plugin of the same name, `~/.nexus` wins. Its commands behave like
+//! built-in ones: they take arguments and piped `Value`s and return a
+//! `Value`. The module runs under wasmtime and can reach only what its
+//! manifest's capabilities grant (see [`sandbox`]); the interface it
+//! implements is described in [`runtime`].
+//!
+//! Discovered plugins start out disabled. `plugin enable` records the
+//! choice in `~/.nexus/plugins/enabled.json` and loads the module, so
+//! granting a plugin's capabilities is always a deliberate step. Built-in
+//! commands take precedence over plugin commands of the same name.
+
+pub mod manifest;
+pub mod runtime;
+pub mod sandbox;
+
+use anyhow::Context;
+use nexus_api::{ApiCaps, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use crate::commands::{CommandContext, NexusCommand};
+use manifest::Manifest;
+use runtime::{Invocation, PluginModule};
+use sandbox::Sandbox;
+
+/// File in the plugin directory listing the enabled plugins.
+const ENABLED_FILE: &str = "enabled.json";
+
+/// Where a discovered plugin stands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginStatus {
+    Disabled,
+    Loaded,
+    /// The manifest or module could not be loaded.
+    Failed(String),
+}
+
+/// A discovered plugin, for `plugin list`.
+#[derive(Debug, Clone)]
+pub struct PluginInfo {
+    pub name: String,
+    pub dir: PathBuf,
+    pub manifest: Option<Manifest>,
+    pub status: PluginStatus,
+}
+
+/// Discovers, loads and dispatches to plugins.
+pub struct PluginHost {
+    /// Directories searched for plugins, in order of precedence. The first
+    /// holds `enabled.json`. Empty for a host that never loads anything
+    /// (tests, ephemeral kernels).
+    dirs: Vec<PathBuf>,
+    state: RwLock<HostState>,
+}
+
+#[derive(Default)]
+struct HostState {
+    plugins: BTreeMap<String, PluginInfo>,
+    commands: HashMap<&'static str, Arc<WasmCommand>>,
+}
+
+impl PluginHost {
+    /// A host with no plugin directory.
+    pub fn empty() -> Self {
+        Self { dirs: Vec::new(), state: RwLock::new(HostState::default()) }
+    }
+
+    /// A host searching `dirs`, with the enabled plugins loaded. Failures
+    /// are logged and shown by `plugin list`; they never stop the shell.
+    pub fn open(dirs: Vec<PathBuf>) -> Self {
+        let host = Self { dirs, state: RwLock::new(HostState::default()) };
+        if let Err(e) = host.reload(None) {
+            tracing::warn!("plugins: {:#}", e);
+        }
+        host
+    }
+
+    /// `~/.nexus/plugins`, then `~/.config/nexus/plugins`.
+    pub fn default_dirs() -> Vec<PathBuf> {
+        let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+            return Vec::new();
+        };
+        vec![home.join(".nexus").join("plugins"), home.join(".config").join("nexus").join("plugins")]
+    }
+
+    /// The directories searched for plugins, in order of precedence.
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Look up a command provided by a loaded plugin.
+    pub fn command(&self, name: &str) -> Option<Arc<WasmCommand>> {
+        self.state.read().unwrap().commands.get(name).cloned()
+    }
+
+    /// Names of all commands provided by loaded plugins.
+    pub fn command_names(&self) -> Vec<&'static str> {
+        self.state.read().unwrap().commands.keys().copied().collect()
+    }
+
+    /// All discovered plugins, by name.
+    pub fn list(&self) -> Vec<PluginInfo> {
+        self.state.read().unwrap().plugins.values().cloned().collect()
+    }
+
+    /// Rescan the plugin directory and recompile enabled modules: all of
+    /// them, or only `only` (the others keep their compiled modules).
+    pub fn reload(&self, only: Option<&str>) -> anyhow::Result<()> {
+        let Some(dir) = self.dirs.first() else {
+            return Ok(());
+        };
+        let enabled = read_enabled(dir);
+        let mut found = discover(&self.dirs)?;
+        if let Some(name) = only
+            && !found.contains_key(name)
+        {
+            anyhow::bail!("no plugin named '{}' in {}", name, self.searched());
+        }
+
+        let mut state = self.state.write().unwrap();
+        let mut modules = HashMap::new();
+        for info in found.values_mut() {
+            let Some(manifest) = info.manifest.as_ref().filter(|_| enabled.contains(&info.name)) else {
+                continue;
+            };
+            let keep = only.is_some_and(|name| name != info.name);
+            let existing = state.commands.values().find(|c| c.plugin == info.name).map(|c| c.module.clone());
+            let module = match existing.filter(|_| keep) {
+                Some(module) => Ok(module),
+                None => PluginModule::load(&info.dir.join(&manifest.module)).map(Arc::new),
+            };
+            match module {
+                Ok(module) => {
+                    info.status = PluginStatus::Loaded;
+                    modules.insert(info.name.clone(), module);
+                }
+                Err(e) => {
+                    tracing::warn!("plugins: {} failed to load: {:#}", info.name, e);
+                    info.status = PluginStatus::Failed(format!("{:#}", e));
+                }
+            }
+        }
+
+        let mut commands: HashMap<&'static str, Arc<WasmCommand>> = HashMap::new();
+        for info in found.values() {
+            let (Some(manifest), Some(module)) = (&info.manifest, modules.get(&info.name)) else {
+                continue;
+            };
+            for spec in &manifest.commands {
+                let name = intern(&spec.name);
+                if let Some(other) = commands.get(name) {
+                    tracing::warn!("plugins: '{}' from {} is already provided by {}", name, info.name, other.plugin);
+                    continue;
+                }
+                let command = WasmCommand {
+                    name,
+                    description: intern(&spec.description),
+                    plugin: info.name.clone(),
+                    capabilities: manifest.capabilities.clone(),
+                    module: module.clone(),
+                };
+                commands.insert(name, Arc::new(command));
+            }
+        }
+
+        state.plugins = found;
+        state.commands = commands;
+        Ok(())
+    }
+
+    /// Enable `name` and load it.
+    pub fn enable(&self, name: &str) -> anyhow::Result<PluginInfo> {
+        self.set_enabled(name, true)?;
+        self.reload(Some(name))?;
+        self.info(name)
+    }
+
+    /// Disable `name` and unload its commands.
+    pub fn disable(&self, name: &str) -> anyhow::Result<PluginInfo> {
+        self.set_enabled(name, false)?;
+        self.reload(Some(name))?;
+        self.info(name)
+    }
+
+    fn info(&self, name: &str) -> anyhow::Result<PluginInfo> {
+        self.state.read().unwrap().plugins.get(name).cloned().with_context(|| format!("no plugin named '{}'", name))
+    }
+
+    fn set_enabled(&self, name: &str, on: bool) -> anyhow::Result<()> {
+        let dir = self.dirs.first().context("plugins are not available in this shell")?;
+        if !discover(&self.dirs)?.contains_key(name) {
+            anyhow::bail!("no plugin named '{}' in {}", name, self.searched());
+        }
+        let mut enabled = read_enabled(dir);
+        if on {
+            enabled.insert(name.to_string());
+        } else {
+            enabled.remove(name);
+        }
+        let path = dir.join(ENABLED_FILE);
+        std::fs::write(&path, serde_json::to_vec_pretty(&enabled)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// The searched directories, for errors.
+    fn searched(&self) -> String {
+        self.dirs.iter().map(|d| d.display().to_string()).collect::<Vec<_>>().join(" or ")
+    }
+}
+
+/// The plugins in `dirs`, as disabled plugins. A name found in more than
+/// one directory is taken from the first.
+fn discover(dirs: &[PathBuf]) -> anyhow::Result<BTreeMap<String, PluginInfo>> {
+    let mut found = BTreeMap::new();
+    for dir in dirs {
+        for (name, info) in discover_in(dir)? {
+            match found.get(&name) {
+                Some(PluginInfo { dir: first, .. }) => {
+                    tracing::warn!("plugins: ignoring {}, already found in {}", info.dir.display(), first.display());
+                }
+                None => {
+                    found.insert(name, info);
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Every subdirectory of `dir` with a manifest, as disabled plugins.
+fn discover_in(dir: &Path) -> anyhow::Result<BTreeMap<String, PluginInfo>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", dir.display())),
+    };
+
+    let mut found = BTreeMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.join(manifest::MANIFEST_FILE).is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let info = match Manifest::load(&path) {
+            Ok(manifest) if manifest.name == name => {
+                PluginInfo { name: name.clone(), dir: path, manifest: Some(manifest), status: PluginStatus::Disabled }
+            }
+            Ok(manifest) => PluginInfo {
+                name: name.clone(),
+                dir: path,
+                status: PluginStatus::Failed(format!("manifest names plugin '{}'", manifest.name)),
+                manifest: None,
+            },
+            Err(e) => PluginInfo {
+                name: name.clone(),
+                dir: path,
+                manifest: None,
+                status: PluginStatus::Failed(format!("{:#}", e)),
+            },
+        };
+        found.insert(name, info);
+    }
+    Ok(found)
+}
+
+fn read_enabled(dir: &Path) -> BTreeSet<String> {
+    std::fs::read(dir.join(ENABLED_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// `NexusCommand` names are `&'static str`. Plugin names are few and
+/// reloading reuses them, so leaking each distinct name once is fine.
+fn intern(name: &str) -> &'static str {
+    static NAMES: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut names = NAMES.lock().unwrap();
+    if let Some(&interned) = names.get(name) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(name.to_owned().into_boxed_str());
+    names.insert(interned);
+    interned
+}
+
+/// A command provided by a plugin.
+pub struct WasmCommand {
+    name: &'static str,
+    description: &'static str,
+    plugin: String,
+    capabilities: manifest::Capabilities,
+    module: Arc<PluginModule>,
+}
+
+impl WasmCommand {
+    /// The plugin that provides this command.
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+}
+
+impl NexusCommand for WasmCommand {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn execute(&self, args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+        let sandbox = Sandbox::new(&self.capabilities, &ctx.state.cwd);
+        let invocation = Invocation {
+            command: self.name.to_string(),
+            args: args.to_vec(),
+            cwd: ctx.state.cwd.clone(),
+            env: sandbox.env(&ctx.state.env),
+            stdin: ctx.stdin.take().map(|v| v.downgrade(&ApiCaps::baseline())),
+        };
+        let _span = tracing::info_span!("plugin", plugin = %self.plugin, command = self.name).entered();
+        self.module.run(&invocation, sandbox, ctx.block_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_utils::test_helpers::TestContext;
+
+    fn install(dir: &Path, name: &str, commands: &[&str]) {
+        let plugin = dir.join(name);
+        std::fs::create_dir_all(&plugin).unwrap();
+        let commands: Vec<_> = commands.iter().map(|c| serde_json::json!({ "name": c })).collect();
+        let manifest = serde_json::json!({
+            "name": name,
+            "module": "plugin.wat",
+            "commands": commands,
+            "capabilities": { "read": ["."] },
+        });
+        std::fs::write(plugin.join(manifest::MANIFEST_FILE), manifest.to_string()).unwrap();
+        std::fs::write(plugin.join("plugin.wat"), runtime::tests::READ_REPLY_WAT).unwrap();
+    }
+
+    #[test]
+    fn test_discovered_plugins_start_disabled() {
+        let tmp = tempfile::tempdir().unwrap();
+        install(tmp.path(), "hello", &["hello"]);
+        let host = PluginHost::open(vec![tmp.path().to_path_buf()]);
+
+        let list = host.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].status, PluginStatus::Disabled);
+        assert!(host.command("hello").is_none());
+    }
+
+    #[test]
+    fn test_enable_loads_and_persists() {
+        let tmp = tempfile::tempdir().unwrap();
+        install(tmp.path(), "hello", &["hello", "hi"]);
+        let host = PluginHost::open(vec![tmp.path().to_path_buf()]);
+
+        assert_eq!(host.enable("hello").unwrap().status, PluginStatus::Loaded);
+        let mut names = host.command_names();
+        names.sort();
+        assert_eq!(names, ["hello", "hi"]);
+
+        // A new host picks the choice up from disk.
+        let reopened = PluginHost::open(vec![tmp.path().to_path_buf()]);
+        assert!(reopened.command("hello").is_some());
+
+        host.disable("hello").unwrap();
+        assert!(host.command("hello").is_none());
+    }
+
+    #[test]
+    fn test_discovers_plugins_in_every_dir() {
+        let nexus = tempfile::tempdir().unwrap();
+        let config = tempfile::tempdir().unwrap();
+        install(config.path(), "hello", &["hello"]);
+        install(config.path(), "shared", &["from-config"]);
+        install(nexus.path(), "shared", &["from-nexus"]);
+        let host = PluginHost::open(vec![nexus.path().to_path_buf(), config.path().to_path_buf()]);
+
+        let names: Vec<String> = host.list().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, ["hello", "shared"]);
+
+        host.enable("hello").unwrap();
+        host.enable("shared").unwrap();
+        assert!(host.command("hello").is_some());
+        // The first directory wins a name clash and keeps the enabled list.
+        assert!(host.command("from-nexus").is_some());
+        assert!(host.command("from-config").is_none());
+        assert!(nexus.path().join(ENABLED_FILE).is_file());
+        assert!(!config.path().join(ENABLED_FILE).exists());
+    }
+
+    #[test]
+    fn test_enabled_command_runs_in_sandbox() {
+        let tmp = tempfile::tempdir().unwrap();
+        install(tmp.path(), "hello", &["hello"]);
+        let host = PluginHost::open(vec![tmp.path().to_path_buf()]);
+        host.enable("hello").unwrap();
+
+        let work = tempfile::tempdir().unwrap();
+        std::fs::write(work.path().join("reply.json"), r#"{"ok":{"String":"hi"}}"#).unwrap();
+        let mut test_ctx = TestContext::new(work.path().to_path_buf());
+        let result = host.command("hello").unwrap().execute(&[], &mut test_ctx.ctx()).unwrap();
+        assert_eq!(result, Value::String("hi".into()));
+    }
+
+    #[test]
+    fn test_broken_module_is_reported_not_fatal() {
+        let tmp = tempfile::tempdir().unwrap();
+        install(tmp.path(), "broken", &["broken"]);
+        std::fs::write(tmp.path().join("broken").join("plugin.wat"), "(module").unwrap();
+        let host = PluginHost::open(vec![tmp.path().to_path_buf()]);
+
+        let info = host.enable("broken").unwrap();
+        assert!(matches!(info.status, PluginStatus::Failed(_)));
+        assert!(host.command("broken").is_none());
+    }
+}
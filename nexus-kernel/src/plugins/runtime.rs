@@ -0,0 +1,390 @@
+//! Running plugin modules under wasmtime.
+//!
+//! ABI version 1. A module exports:
+//!
+//! - `memory`
+//! - `nexus_abi_version() -> i32`, returning [`ABI_VERSION`]
+//! - `nexus_alloc(len: i32) -> i32`, returning a buffer the host may fill
+//! - `nexus_run(ptr: i32, len: i32) -> i64`
+//!
+//! `nexus_run` receives a JSON [`Invocation`] and returns `ptr << 32 | len`
+//! of a JSON reply, `{"ok": <value>}` or `{"error": "<message>"}`. Values
+//! use the JSON form of `nexus_api::Value` (`{"String": "hi"}`,
+//! `{"List": [...]}`). Input is limited to the baseline variants of
+//! `ApiCaps::baseline`, whose encoding does not change between releases.
+//!
+//! The host functions, imported from module `nexus`, return buffers the same
+//! way (allocated with `nexus_alloc`), or a negative `ERR_*` code:
+//!
+//! - `log(ptr, len)`
+//! - `cancelled() -> i32`: 1 once the user has interrupted the command
+//! - `read_file(path_ptr, path_len) -> i64`
+//! - `list_dir(path_ptr, path_len) -> i64`: JSON array of entry names
+//! - `write_file(path_ptr, path_len, data_ptr, data_len) -> i32`: 0 on success
+//! - `net_request(addr_ptr, addr_len, data_ptr, data_len) -> i64`: connect to
+//!   `host:port`, send the data and return everything read until the peer
+//!   closes the connection
+//!
+//! The invocation carries the command's arguments, the working directory,
+//! the environment variables the manifest's `env` capability grants and the
+//! piped value, if any.
+//!
+//! Each invocation gets a fresh instance, so no state leaks between runs.
+
+use anyhow::{Context, anyhow};
+use nexus_api::{BlockId, CommandError, CommandErrorKind, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, UpdateDeadline,
+};
+
+use super::sandbox::Sandbox;
+use crate::commands::is_cancelled;
+
+/// Version of the guest interface described above.
+pub const ABI_VERSION: i32 = 1;
+
+/// The capability check failed.
+pub const ERR_DENIED: i32 = -1;
+/// The file or host does not exist.
+pub const ERR_NOT_FOUND: i32 = -2;
+/// Any other I/O failure.
+pub const ERR_IO: i32 = -3;
+/// The result is larger than [`MAX_TRANSFER`].
+pub const ERR_TOO_LARGE: i32 = -4;
+
+/// Largest buffer passed across the boundary in one call.
+pub const MAX_TRANSFER: usize = 16 << 20;
+
+const MEMORY_LIMIT: usize = 256 << 20;
+const NET_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often running plugins check for cancellation.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// One engine for all plugins, with a ticker that lets stores interrupt
+/// guest code that never returns to the host.
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).expect("static wasmtime config is valid");
+    let ticker = engine.clone();
+    let spawned = std::thread::Builder::new().name("plugin-epoch".into()).spawn(move || {
+        loop {
+            std::thread::sleep(EPOCH_TICK);
+            ticker.increment_epoch();
+        }
+    });
+    if let Err(e) = spawned {
+        tracing::warn!("plugins: failed to start epoch ticker, plugins cannot be interrupted: {}", e);
+    }
+    engine
+});
+
+/// What `nexus_run` receives.
+#[derive(Debug, Clone, Serialize)]
+pub struct Invocation {
+    pub command: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+    pub env: BTreeMap<String, String>,
+    pub stdin: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Reply {
+    Ok(Value),
+    Error(String),
+}
+
+struct HostState {
+    sandbox: Sandbox,
+    block_id: BlockId,
+    limits: StoreLimits,
+}
+
+/// A compiled plugin module, shared by all of its commands.
+pub struct PluginModule {
+    module: Module,
+    linker: Linker<HostState>,
+}
+
+impl PluginModule {
+    /// Compile `path` (binary or text format) and check that it speaks
+    /// [`ABI_VERSION`].
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let module = Module::from_file(&ENGINE, path)?;
+        Self::new(module)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_wat(wat: &str) -> anyhow::Result<Self> {
+        Self::new(Module::new(&ENGINE, wat)?)
+    }
+
+    fn new(module: Module) -> anyhow::Result<Self> {
+        let this = Self { module, linker: linker()? };
+        let mut store = this.store(Sandbox::default(), BlockId(0));
+        let instance = this.linker.instantiate(&mut store, &this.module)?;
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, "nexus_abi_version")?
+            .call(&mut store, ())?;
+        if version != ABI_VERSION {
+            anyhow::bail!("plugin ABI version {} is not supported (expected {})", version, ABI_VERSION);
+        }
+        instance.get_typed_func::<i32, i32>(&mut store, "nexus_alloc")?;
+        instance.get_typed_func::<(i32, i32), i64>(&mut store, "nexus_run")?;
+        instance.get_memory(&mut store, "memory").context("plugin does not export memory")?;
+        Ok(this)
+    }
+
+    /// Run one command in a fresh instance.
+    pub fn run(&self, invocation: &Invocation, sandbox: Sandbox, block_id: BlockId) -> anyhow::Result<Value> {
+        let mut store = self.store(sandbox, block_id);
+        let result = self.call(&mut store, invocation);
+        if result.is_err() && is_cancelled(block_id) {
+            return Err(CommandError::new(&invocation.command, CommandErrorKind::Interrupted, "interrupted").into());
+        }
+        result
+    }
+
+    fn call(&self, store: &mut Store<HostState>, invocation: &Invocation) -> anyhow::Result<Value> {
+        let instance = self.linker.instantiate(&mut *store, &self.module)?;
+        let memory = instance.get_memory(&mut *store, "memory").context("plugin does not export memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut *store, "nexus_alloc")?;
+        let run = instance.get_typed_func::<(i32, i32), i64>(&mut *store, "nexus_run")?;
+
+        let input = serde_json::to_vec(invocation)?;
+        let len = i32::try_from(input.len()).context("input too large for plugin")?;
+        let ptr = alloc.call(&mut *store, len)?;
+        memory.write(&mut *store, ptr as u32 as usize, &input)?;
+
+        let packed = run.call(&mut *store, (ptr, len))?;
+        let reply = slice(memory.data(&*store), packed).context("plugin returned an invalid buffer")?;
+        match serde_json::from_slice(reply).context("plugin returned a malformed reply")? {
+            Reply::Ok(value) => Ok(value),
+            Reply::Error(message) => Err(anyhow!(message)),
+        }
+    }
+
+    fn store(&self, sandbox: Sandbox, block_id: BlockId) -> Store<HostState> {
+        let limits = StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build();
+        let mut store = Store::new(&ENGINE, HostState { sandbox, block_id, limits });
+        store.limiter(|state| &mut state.limits);
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(|ctx| {
+            Ok(if is_cancelled(ctx.data().block_id) { UpdateDeadline::Interrupt } else { UpdateDeadline::Continue(1) })
+        });
+        store
+    }
+}
+
+fn linker() -> anyhow::Result<Linker<HostState>> {
+    let mut linker = Linker::new(&ENGINE);
+
+    linker.func_wrap("nexus", "log", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let message = guest_str(&mut caller, ptr, len)?;
+        tracing::debug!(target: "nexus::plugin", "{}", message);
+        anyhow::Ok(())
+    })?;
+
+    linker.func_wrap("nexus", "cancelled", |caller: Caller<'_, HostState>| {
+        is_cancelled(caller.data().block_id) as i32
+    })?;
+
+    linker.func_wrap("nexus", "read_file", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let path = guest_str(&mut caller, ptr, len)?;
+        let Some(path) = caller.data().sandbox.allow_read(&path) else {
+            return Ok(ERR_DENIED.into());
+        };
+        match std::fs::read(&path) {
+            Ok(bytes) => give(&mut caller, &bytes),
+            Err(e) => Ok(io_code(&e).into()),
+        }
+    })?;
+
+    linker.func_wrap("nexus", "list_dir", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let path = guest_str(&mut caller, ptr, len)?;
+        let Some(path) = caller.data().sandbox.allow_read(&path) else {
+            return Ok(ERR_DENIED.into());
+        };
+        let entries = std::fs::read_dir(&path).and_then(|dir| {
+            let mut names = dir
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<std::io::Result<Vec<_>>>()?;
+            names.sort();
+            Ok(names)
+        });
+        match entries {
+            Ok(names) => give(&mut caller, &serde_json::to_vec(&names)?),
+            Err(e) => Ok(io_code(&e).into()),
+        }
+    })?;
+
+    linker.func_wrap(
+        "nexus",
+        "write_file",
+        |mut caller: Caller<'_, HostState>, path_ptr: i32, path_len: i32, data_ptr: i32, data_len: i32| {
+            let path = guest_str(&mut caller, path_ptr, path_len)?;
+            let data = guest_bytes(&mut caller, data_ptr, data_len)?;
+            let Some(path) = caller.data().sandbox.allow_write(&path) else {
+                return Ok(ERR_DENIED);
+            };
+            anyhow::Ok(match std::fs::write(&path, data) {
+                Ok(()) => 0,
+                Err(e) => io_code(&e),
+            })
+        },
+    )?;
+
+    linker.func_wrap(
+        "nexus",
+        "net_request",
+        |mut caller: Caller<'_, HostState>, addr_ptr: i32, addr_len: i32, data_ptr: i32, data_len: i32| {
+            let addr = guest_str(&mut caller, addr_ptr, addr_len)?;
+            let data = guest_bytes(&mut caller, data_ptr, data_len)?;
+            if !caller.data().sandbox.allow_net(&addr) {
+                return Ok(ERR_DENIED.into());
+            }
+            match net_request(&addr, &data) {
+                Ok(reply) => give(&mut caller, &reply),
+                Err(e) => Ok(io_code(&e).into()),
+            }
+        },
+    )?;
+
+    Ok(linker)
+}
+
+fn net_request(addr: &str, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let target = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no address"))?;
+    let mut stream = TcpStream::connect_timeout(&target, NET_TIMEOUT)?;
+    stream.set_read_timeout(Some(NET_TIMEOUT))?;
+    stream.set_write_timeout(Some(NET_TIMEOUT))?;
+    stream.write_all(data)?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut reply = Vec::new();
+    stream.take(MAX_TRANSFER as u64 + 1).read_to_end(&mut reply)?;
+    Ok(reply)
+}
+
+fn io_code(e: &std::io::Error) -> i32 {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => ERR_NOT_FOUND,
+        std::io::ErrorKind::PermissionDenied => ERR_DENIED,
+        _ => ERR_IO,
+    }
+}
+
+fn guest_memory(caller: &mut Caller<'_, HostState>) -> anyhow::Result<Memory> {
+    caller.get_export("memory").and_then(Extern::into_memory).context("plugin does not export memory")
+}
+
+fn guest_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> anyhow::Result<Vec<u8>> {
+    let memory = guest_memory(caller)?;
+    let data = memory.data(&*caller);
+    let start = ptr as u32 as usize;
+    data.get(start..start + len as u32 as usize).map(<[u8]>::to_vec).context("plugin passed an invalid buffer")
+}
+
+fn guest_str(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> anyhow::Result<String> {
+    String::from_utf8(guest_bytes(caller, ptr, len)?).context("plugin passed invalid UTF-8")
+}
+
+/// Copy `bytes` into a guest allocation and return it packed.
+fn give(caller: &mut Caller<'_, HostState>, bytes: &[u8]) -> anyhow::Result<i64> {
+    if bytes.len() > MAX_TRANSFER {
+        return Ok(ERR_TOO_LARGE.into());
+    }
+    let alloc = caller
+        .get_export("nexus_alloc")
+        .and_then(Extern::into_func)
+        .context("plugin does not export nexus_alloc")?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    guest_memory(caller)?.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    (((ptr as u32 as u64) << 32) | len as u64) as i64
+}
+
+fn slice(memory: &[u8], packed: i64) -> Option<&[u8]> {
+    if packed < 0 {
+        return None;
+    }
+    let start = (packed as u64 >> 32) as usize;
+    let len = (packed as u64 & 0xffff_ffff) as usize;
+    memory.get(start..start + len)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::plugins::manifest::Capabilities;
+
+    /// A plugin whose reply is the contents of `reply.json` in the cwd, or
+    /// `{"error": "denied"}` when the sandbox refuses the read.
+    pub(crate) const READ_REPLY_WAT: &str = r#"
+        (module
+          (import "nexus" "read_file" (func $read_file (param i32 i32) (result i64)))
+          (memory (export "memory") 2)
+          (global $heap (mut i32) (i32.const 1024))
+          (data (i32.const 0) "reply.json")
+          (data (i32.const 16) "{\"error\":\"denied\"}")
+          (func (export "nexus_abi_version") (result i32) (i32.const 1))
+          (func (export "nexus_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $heap))
+            (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+            (local.get $ptr))
+          (func (export "nexus_run") (param i32 i32) (result i64)
+            (local $r i64)
+            (local.set $r (call $read_file (i32.const 0) (i32.const 10)))
+            (if (result i64) (i64.lt_s (local.get $r) (i64.const 0))
+              (then (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 18)))
+              (else (local.get $r)))))
+    "#;
+
+    fn invocation(cwd: &Path) -> Invocation {
+        Invocation { command: "reply".into(), args: vec![], cwd: cwd.to_path_buf(), env: BTreeMap::new(), stdin: None }
+    }
+
+    #[test]
+    fn test_reply_round_trip() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("reply.json"), r#"{"ok":{"List":[{"Int":1},{"String":"two"}]}}"#).unwrap();
+        let module = PluginModule::from_wat(READ_REPLY_WAT).unwrap();
+        let sandbox = Sandbox::new(&Capabilities { read: vec![".".into()], ..Default::default() }, tmp.path());
+
+        let value = module.run(&invocation(tmp.path()), sandbox, BlockId(1)).unwrap();
+        assert_eq!(value, Value::List(vec![Value::Int(1), Value::String("two".into())]));
+    }
+
+    #[test]
+    fn test_reads_outside_capabilities_are_denied() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::write(tmp.path().join("reply.json"), r#"{"ok":{"String":"secret"}}"#).unwrap();
+        let module = PluginModule::from_wat(READ_REPLY_WAT).unwrap();
+
+        let err = module.run(&invocation(tmp.path()), Sandbox::default(), BlockId(1)).unwrap_err();
+        assert_eq!(err.to_string(), "denied");
+    }
+
+    #[test]
+    fn test_rejects_other_abi_versions() {
+        let wat = READ_REPLY_WAT.replace("(result i32) (i32.const 1))", "(result i32) (i32.const 99))");
+        let err = PluginModule::from_wat(&wat).err().unwrap();
+        assert!(err.to_string().contains("ABI version 99"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Capability checks for plugin host calls.
+//!
+//! Paths are resolved against the shell's working directory and then
+//! canonicalized, so `..` and symlinks cannot lead out of a granted
+//! directory. A path that does not exist yet (a file about to be written)
+//! is checked through its parent.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use super::manifest::Capabilities;
+
+/// The filesystem and network a single plugin invocation may reach.
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+    net: Vec<String>,
+    env: Vec<String>,
+    cwd: PathBuf,
+}
+
+impl Sandbox {
+    /// Grant `caps`, with `.` meaning `cwd`. Granted directories that do not
+    /// exist are ignored.
+    pub fn new(caps: &Capabilities, cwd: &Path) -> Self {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let roots = |dirs: &[String]| -> Vec<PathBuf> {
+            dirs.iter().filter_map(|d| expand_root(d, cwd, home.as_deref())).collect()
+        };
+        let write = roots(&caps.write);
+        let mut read = roots(&caps.read);
+        read.extend(write.iter().cloned());
+        Self { read, write, net: caps.net.clone(), env: caps.env.clone(), cwd: cwd.to_path_buf() }
+    }
+
+    /// The canonical path to read, if `path` is inside a readable directory.
+    pub fn allow_read(&self, path: &str) -> Option<PathBuf> {
+        self.resolve(path).filter(|p| self.read.iter().any(|root| p.starts_with(root)))
+    }
+
+    /// The canonical path to write, if `path` is inside a writable directory.
+    pub fn allow_write(&self, path: &str) -> Option<PathBuf> {
+        self.resolve(path).filter(|p| self.write.iter().any(|root| p.starts_with(root)))
+    }
+
+    /// Whether `addr` (`host:port`) may be connected to.
+    pub fn allow_net(&self, addr: &str) -> bool {
+        let Some((host, port)) = addr.rsplit_once(':') else {
+            return false;
+        };
+        self.net.iter().any(|entry| match entry.rsplit_once(':') {
+            Some((h, p)) => h.eq_ignore_ascii_case(host) && p == port,
+            None => entry.eq_ignore_ascii_case(host),
+        })
+    }
+
+    /// The variables of `vars` the plugin may see.
+    pub fn env(&self, vars: &HashMap<String, String>) -> BTreeMap<String, String> {
+        let granted = |name: &str| {
+            self.env.iter().any(|entry| match entry.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => entry == name,
+            })
+        };
+        vars.iter().filter(|(name, _)| granted(name)).map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let path = self.cwd.join(path);
+        match path.canonicalize() {
+            Ok(p) => Some(p),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let name = match path.components().next_back()? {
+                    Component::Normal(name) => name.to_owned(),
+                    _ => return None,
+                };
+                Some(path.parent()?.canonicalize().ok()?.join(name))
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+fn expand_root(dir: &str, cwd: &Path, home: Option<&Path>) -> Option<PathBuf> {
+    let path = if dir == "." {
+        cwd.to_path_buf()
+    } else if dir == "~" {
+        home?.to_path_buf()
+    } else if let Some(rest) = dir.strip_prefix("~/") {
+        home?.join(rest)
+    } else if Path::new(dir).is_absolute() {
+        PathBuf::from(dir)
+    } else {
+        // Relative roots would silently change meaning with the cwd.
+        return None;
+    };
+    path.canonicalize().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(dir: &Path, caps: Capabilities) -> Sandbox {
+        Sandbox::new(&caps, dir)
+    }
+
+    #[test]
+    fn test_read_is_limited_to_granted_dirs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data = tmp.path().join("data");
+        std::fs::create_dir(&data).unwrap();
+        std::fs::write(data.join("a.txt"), "a").unwrap();
+        std::fs::write(tmp.path().join("secret.txt"), "s").unwrap();
+
+        let caps = Capabilities { read: vec![data.display().to_string()], ..Default::default() };
+        let sb = sandbox(&data, caps);
+        assert!(sb.allow_read("a.txt").is_some());
+        assert!(sb.allow_read("../secret.txt").is_none());
+        assert!(sb.allow_write("a.txt").is_none());
+    }
+
+    #[test]
+    fn test_symlinks_cannot_escape() {
+        let tmp = tempfile::tempdir().unwrap();
+        let data = tmp.path().join("data");
+        std::fs::create_dir(&data).unwrap();
+        std::fs::write(tmp.path().join("secret.txt"), "s").unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("secret.txt"), data.join("link")).unwrap();
+
+        let sb = sandbox(&data, Capabilities { read: vec![".".into()], ..Default::default() });
+        assert!(sb.allow_read("link").is_none());
+    }
+
+    #[test]
+    fn test_write_allows_new_files_in_granted_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let sb = sandbox(tmp.path(), Capabilities { write: vec![".".into()], ..Default::default() });
+        assert!(sb.allow_write("new.txt").is_some());
+        assert!(sb.allow_read("new.txt").is_some());
+        assert!(sb.allow_write("missing/new.txt").is_none());
+    }
+
+    #[test]
+    fn test_net_allowlist() {
+        let caps = Capabilities { net: vec!["example.com".into(), "localhost:8080".into()], ..Default::default() };
+        let sb = sandbox(Path::new("/"), caps);
+        assert!(sb.allow_net("example.com:443"));
+        assert!(sb.allow_net("localhost:8080"));
+        assert!(!sb.allow_net("localhost:22"));
+        assert!(!sb.allow_net("evil.com:443"));
+    }
+
+    #[test]
+    fn test_env_allowlist() {
+        let vars: HashMap<String, String> =
+            [("EDITOR", "vi"), ("NOTES_DIR", "~/n"), ("NOTES_KEY", "k"), ("AWS_SECRET", "s")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+        let caps = Capabilities { env: vec!["EDITOR".into(), "NOTES_*".into()], ..Default::default() };
+        let env = sandbox(Path::new("/"), caps).env(&vars);
+        assert_eq!(env.keys().collect::<Vec<_>>(), ["EDITOR", "NOTES_DIR", "NOTES_KEY"]);
+        assert!(Sandbox::default().env(&vars).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+//! Process-wide low-power state.
+//!
+//! The UI reports the platform's battery / Low Power Mode state and the
+//! user's override from the status bar; long-running commands such as
+//! `watch` consult `is_low_power()` to back off. Shared by every window,
+//! so all of them agree on the mode.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::Duration;
+
+static SYSTEM_LOW_POWER: AtomicBool = AtomicBool::new(false);
+static OVERRIDE: AtomicU8 = AtomicU8::new(PowerOverride::Auto as u8);
+
+/// Watch intervals are stretched by this factor in low-power mode...
+const WATCH_STRETCH: u32 = 4;
+/// ...and never refresh faster than this.
+const WATCH_MIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// User override for low-power mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PowerOverride {
+    /// Follow the system: low power on battery or in OS Low Power Mode.
+    Auto = 0,
+    /// Always throttle.
+    On = 1,
+    /// Never throttle.
+    Off = 2,
+}
+
+impl PowerOverride {
+    /// Parse `NEXUS_LOW_POWER` values (`auto`, `on`, `off`).
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "on" | "1" | "true" => Some(Self::On),
+            "off" | "0" | "false" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    /// Next value when cycling from the status bar: auto → on → off → auto.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::On,
+            Self::On => Self::Off,
+            Self::Off => Self::Auto,
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            1 => Self::On,
+            2 => Self::Off,
+            _ => Self::Auto,
+        }
+    }
+
+    fn resolve(self, system_low_power: bool) -> bool {
+        match self {
+            Self::Auto => system_low_power,
+            Self::On => true,
+            Self::Off => false,
+        }
+    }
+}
+
+/// Record whether the platform is on battery or in Low Power Mode.
+pub fn set_system_low_power(on: bool) {
+    SYSTEM_LOW_POWER.store(on, Ordering::Relaxed);
+}
+
+pub fn set_override(value: PowerOverride) {
+    OVERRIDE.store(value as u8, Ordering::Relaxed);
+}
+
+pub fn power_override() -> PowerOverride {
+    PowerOverride::from_u8(OVERRIDE.load(Ordering::Relaxed))
+}
+
+/// Whether long-running work should throttle itself right now.
+pub fn is_low_power() -> bool {
+    power_override().resolve(SYSTEM_LOW_POWER.load(Ordering::Relaxed))
+}
+
+/// The effective refresh interval for `watch` given the requested one.
+pub fn watch_interval(requested: Duration) -> Duration {
+    if is_low_power() {
+        stretch_watch_interval(requested)
+    } else {
+        requested
+    }
+}
+
+fn stretch_watch_interval(requested: Duration) -> Duration {
+    (requested * WATCH_STRETCH).max(WATCH_MIN_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_resolution() {
+        assert!(PowerOverride::Auto.resolve(true));
+        assert!(!PowerOverride::Auto.resolve(false));
+        assert!(PowerOverride::On.resolve(false));
+        assert!(!PowerOverride::Off.resolve(true));
+    }
+
+    #[test]
+    fn test_override_parse_and_cycle() {
+        assert_eq!(PowerOverride::parse("ON"), Some(PowerOverride::On));
+        assert_eq!(PowerOverride::parse(" off "), Some(PowerOverride::Off));
+        assert_eq!(PowerOverride::parse("auto"), Some(PowerOverride::Auto));
+        assert_eq!(PowerOverride::parse("maybe"), None);
+        assert_eq!(PowerOverride::Auto.next().next().next(), PowerOverride::Auto);
+    }
+
+    #[test]
+    fn test_watch_interval_stretch() {
+        assert_eq!(stretch_watch_interval(Duration::from_millis(500)), Duration::from_secs(5));
+        assert_eq!(stretch_watch_interval(Duration::from_secs(10)), Duration::from_secs(40));
+    }
+}
@@ -0,0 +1,233 @@
+//! Compiler problems parsed from build output, and how they changed since
+//! the last run of the same command.
+//!
+//! Understands rustc/cargo (`warning: ...` then ` --> file:line:col`),
+//! gcc/clang/go/mypy style `file:line:col: error: ...` and tsc's
+//! `file(line,col): error TS1234: ...`.
+//!
+//! Problems are matched across runs by severity, file and message, not
+//! line: a warning that moved because code was added above it is the same
+//! warning.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// `file:line[:col]: error|warning: message`
+static LOCATED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\S[^:]*):(\d+):(?:(\d+):)?\s*(fatal error|error|warning)(?:\[[^\]]*\])?:\s*(.+)$").unwrap()
+});
+/// `file(line,col): error TS1234: message`
+static TSC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\S[^(]*)\((\d+),(\d+)\):\s*(error|warning)\s+\w+:\s*(.+)$").unwrap());
+/// `error[E0308]: message` / `warning: message`
+static RUSTC_HEADER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(error|warning)(?:\[[^\]]*\])?:\s*(.+)$").unwrap());
+/// ` --> file:line:col`
+static RUSTC_LOCATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*-->\s*(.+?):(\d+):(\d+)$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Self {
+        if s.ends_with("error") { Self::Error } else { Self::Warning }
+    }
+
+    fn noun(self, count: usize) -> &'static str {
+        match (self, count) {
+            (Self::Error, 1) => "error",
+            (Self::Error, _) => "errors",
+            (Self::Warning, 1) => "warning",
+            (Self::Warning, _) => "warnings",
+        }
+    }
+}
+
+/// One error or warning, at a place in a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    pub severity: Severity,
+    /// As printed, usually relative to where the command ran.
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl Problem {
+    fn key(&self) -> (Severity, &str, &str) {
+        (self.severity, &self.file, &self.message)
+    }
+}
+
+/// The problems in `output`, in order. Lines without a location (cargo's
+/// "generated 3 warnings" summaries) are skipped.
+pub fn parse(output: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    // A rustc header waiting for its ` --> ` line.
+    let mut pending: Option<(Severity, String)> = None;
+    for line in output.lines() {
+        let line = line.trim_end();
+        if let Some(caps) = LOCATED.captures(line).or_else(|| TSC.captures(line)) {
+            pending = None;
+            problems.push(Problem {
+                severity: Severity::parse(&caps[4]),
+                file: caps[1].to_string(),
+                line: caps[2].parse().unwrap_or(0),
+                column: caps.get(3).and_then(|c| c.as_str().parse().ok()),
+                message: caps[5].trim().to_string(),
+            });
+        } else if let Some(caps) = RUSTC_HEADER.captures(line) {
+            pending = Some((Severity::parse(&caps[1]), caps[2].trim().to_string()));
+        } else if let Some(caps) = RUSTC_LOCATION.captures(line) {
+            if let Some((severity, message)) = pending.take() {
+                problems.push(Problem {
+                    severity,
+                    file: caps[1].to_string(),
+                    line: caps[2].parse().unwrap_or(0),
+                    column: caps[3].parse().ok(),
+                    message,
+                });
+            }
+        } else if line.is_empty() {
+            pending = None;
+        }
+    }
+    problems
+}
+
+/// How a rerun's problems differ from the previous run's.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProblemDelta {
+    /// Problems the previous run didn't have, in output order.
+    pub new: Vec<Problem>,
+    /// Problems of the previous run that are gone.
+    pub fixed: Vec<Problem>,
+}
+
+impl ProblemDelta {
+    /// Compare `after` with `before`. Repeated problems count separately.
+    pub fn between(before: &[Problem], after: &[Problem]) -> Self {
+        let mut remaining: HashMap<_, usize> = HashMap::new();
+        for problem in before {
+            *remaining.entry(problem.key()).or_default() += 1;
+        }
+        let new = after
+            .iter()
+            .filter(|problem| match remaining.get_mut(&problem.key()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        let mut fixed = Vec::new();
+        for problem in before.iter().rev() {
+            if let Some(count) = remaining.get_mut(&problem.key())
+                && *count > 0
+            {
+                *count -= 1;
+                fixed.push(problem.clone());
+            }
+        }
+        fixed.reverse();
+        Self { new, fixed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new.is_empty() && self.fixed.is_empty()
+    }
+
+    /// "1 new error, 2 new warnings, 5 fixed"; None when nothing changed.
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        for severity in [Severity::Error, Severity::Warning] {
+            let count = self.new.iter().filter(|p| p.severity == severity).count();
+            if count > 0 {
+                parts.push(format!("{} new {}", count, severity.noun(count)));
+            }
+        }
+        if !self.fixed.is_empty() {
+            parts.push(format!("{} fixed", self.fixed.len()));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO: &str = "\
+   Compiling app v0.1.0 (/src/app)
+warning: unused import: `std::fmt`
+ --> src/main.rs:1:5
+  |
+1 | use std::fmt;
+  |     ^^^^^^^^
+
+error[E0308]: mismatched types
+  --> src/lib.rs:10:18
+   |
+warning: `app` (bin \"app\") generated 1 warning
+error: could not compile `app` due to 1 previous error
+";
+
+    #[test]
+    fn test_parse() {
+        let problems = parse(CARGO);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0], Problem {
+            severity: Severity::Warning,
+            file: "src/main.rs".to_string(),
+            line: 1,
+            column: Some(5),
+            message: "unused import: `std::fmt`".to_string(),
+        });
+        assert_eq!((problems[1].severity, problems[1].line), (Severity::Error, 10));
+
+        let gcc = parse("main.c:3:10: fatal error: foo.h: No such file\nutil.py:7: error: Name \"x\" is not defined\n");
+        assert_eq!((gcc[0].severity, gcc[0].message.as_str()), (Severity::Error, "foo.h: No such file"));
+        assert_eq!((gcc[1].file.as_str(), gcc[1].line, gcc[1].column), ("util.py", 7, None));
+
+        let tsc = parse("src/app.ts(4,7): error TS2322: Type 'string' is not assignable to type 'number'.");
+        assert_eq!((tsc[0].file.as_str(), tsc[0].line, tsc[0].column), ("src/app.ts", 4, Some(7)));
+    }
+
+    #[test]
+    fn test_delta() {
+        let problem = |severity, file: &str, line, message: &str| Problem {
+            severity,
+            file: file.to_string(),
+            line,
+            column: None,
+            message: message.to_string(),
+        };
+        let before = vec![
+            problem(Severity::Warning, "a.rs", 1, "unused variable: `x`"),
+            problem(Severity::Warning, "a.rs", 9, "unused variable: `x`"),
+            problem(Severity::Error, "b.rs", 4, "mismatched types"),
+        ];
+        // The first warning moved down; one copy of it and the error were fixed.
+        let after = vec![
+            problem(Severity::Warning, "a.rs", 3, "unused variable: `x`"),
+            problem(Severity::Warning, "c.rs", 2, "unused import"),
+            problem(Severity::Warning, "c.rs", 5, "dead code"),
+        ];
+        let delta = ProblemDelta::between(&before, &after);
+        assert_eq!(delta.new, after[1..]);
+        assert_eq!(delta.fixed, [before[1].clone(), before[2].clone()]);
+        assert_eq!(delta.summary().as_deref(), Some("2 new warnings, 2 fixed"));
+
+        assert!(ProblemDelta::between(&before, &before).is_empty());
+        assert_eq!(ProblemDelta::between(&before, &before).summary(), None);
+    }
+}
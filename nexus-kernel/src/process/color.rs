@@ -0,0 +1,117 @@
+//! Color policy for external commands.
+//!
+//! A command on a PTY sees a terminal and colors as it would anywhere. One
+//! whose stdout is a pipe (the last external stage of a native pipeline,
+//! `[pager]` capture) sees none and goes plain, though its block would
+//! render the color. One captured into a `Value` for a native command to
+//! parse should emit no escapes at all. [`env`] picks the variables the
+//! common conventions read: `CLICOLOR_FORCE` (BSD tools, CMake),
+//! `FORCE_COLOR` (Node and Python tooling) and `NO_COLOR`. [`strip`]
+//! removes whatever escapes get through anyway.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::config::ColorMode;
+
+/// Where an external command's stdout goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sink {
+    /// A PTY (or a file or pipe the command line redirected it to).
+    Terminal,
+    /// A pipe the kernel reads into a block.
+    Block,
+    /// A pipe the kernel reads into a `Value`.
+    Parser,
+}
+
+const FORCE: &[(&str, &str)] = &[("CLICOLOR", "1"), ("CLICOLOR_FORCE", "1"), ("FORCE_COLOR", "1")];
+const SUPPRESS: &[(&str, &str)] = &[("NO_COLOR", "1"), ("FORCE_COLOR", "0")];
+
+/// Environment overrides for a command in `mode` writing to `sink`, given
+/// the session environment `env`.
+pub fn env(mode: ColorMode, sink: Sink, env: &HashMap<String, String>) -> Vec<(String, String)> {
+    let vars = match (mode, sink) {
+        (ColorMode::Auto, Sink::Terminal) => &[][..],
+        (ColorMode::Auto, Sink::Block) | (ColorMode::Always, _) => FORCE,
+        (ColorMode::Auto, Sink::Parser) | (ColorMode::Never, _) => SUPPRESS,
+    };
+    let mut overrides: Vec<(String, String)> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    // Forced color still goes through terminfo for some programs.
+    if vars == FORCE && env.get("TERM").is_none_or(|term| term.is_empty() || term == "dumb") {
+        overrides.push(("TERM".to_string(), "xterm-256color".to_string()));
+    }
+    overrides
+}
+
+/// `text` without terminal escape sequences: CSI (colors, cursor motion),
+/// OSC (titles, hyperlinks) and the short `ESC x` forms.
+pub fn strip(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1b') {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                // Parameters and intermediates, then one final byte.
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                // Terminated by BEL or ST (ESC \).
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Charset designations and the like take one more byte.
+            Some('\x20'..='\x2f') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(overrides: &[(String, String)]) -> Vec<&str> {
+        overrides.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    #[test]
+    fn test_env_by_mode_and_sink() {
+        let term = HashMap::from([("TERM".to_string(), "xterm-256color".to_string())]);
+        assert!(env(ColorMode::Auto, Sink::Terminal, &term).is_empty());
+        assert_eq!(keys(&env(ColorMode::Auto, Sink::Block, &term)), ["CLICOLOR", "CLICOLOR_FORCE", "FORCE_COLOR"]);
+        assert_eq!(keys(&env(ColorMode::Auto, Sink::Parser, &term)), ["NO_COLOR", "FORCE_COLOR"]);
+        assert_eq!(keys(&env(ColorMode::Never, Sink::Terminal, &term)), ["NO_COLOR", "FORCE_COLOR"]);
+        assert_eq!(env(ColorMode::Always, Sink::Parser, &term).len(), 3);
+
+        let dumb = HashMap::from([("TERM".to_string(), "dumb".to_string())]);
+        assert!(env(ColorMode::Always, Sink::Terminal, &dumb).contains(&("TERM".into(), "xterm-256color".into())));
+        assert!(env(ColorMode::Auto, Sink::Block, &HashMap::new()).iter().any(|(k, _)| k == "TERM"));
+    }
+
+    #[test]
+    fn test_strip() {
+        assert!(matches!(strip("plain"), Cow::Borrowed("plain")));
+        assert_eq!(strip("\x1b[1;31merror\x1b[0m: x"), "error: x");
+        assert_eq!(strip("\x1b]8;;https://x.dev\x1b\\link\x1b]8;;\x1b\\"), "link");
+        assert_eq!(strip("\x1b]0;title\x07a\x1b(Bb"), "ab");
+        assert_eq!(strip("tab\tand\nnewline"), "tab\tand\nnewline");
+    }
+}
@@ -1,5 +1,6 @@
 //! Job control structures.
 
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
 
 /// State of a job.
@@ -10,6 +11,16 @@ pub enum JobState {
     Done(i32),
 }
 
+impl From<JobState> for nexus_api::JobState {
+    fn from(state: JobState) -> Self {
+        match state {
+            JobState::Running => nexus_api::JobState::Running,
+            JobState::Stopped => nexus_api::JobState::Stopped,
+            JobState::Done(code) => nexus_api::JobState::Done(code),
+        }
+    }
+}
+
 /// A background job.
 #[derive(Debug)]
 pub struct Job {
@@ -63,6 +74,27 @@ impl Job {
             _ => None,
         }
     }
+
+    /// Check with the OS whether the job has stopped, continued, or exited,
+    /// without blocking. Returns true if the state changed.
+    pub fn poll(&mut self) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        let flags = WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED;
+        let state = match waitpid(self.pgid, Some(flags)) {
+            Ok(WaitStatus::Exited(_, code)) => JobState::Done(code),
+            Ok(WaitStatus::Signaled(_, sig, _)) => JobState::Done(128 + sig as i32),
+            Ok(WaitStatus::Stopped(_, _)) => JobState::Stopped,
+            Ok(WaitStatus::Continued(_)) => JobState::Running,
+            Ok(_) => return false,
+            // Already reaped elsewhere, or never our child.
+            Err(_) => JobState::Done(0),
+        };
+        let changed = state != self.state;
+        self.state = state;
+        changed
+    }
 }
 
 #[cfg(test)]
@@ -234,6 +266,27 @@ mod tests {
         assert!(job.pids.contains(&Pid::from_raw(101)));
     }
 
+    #[test]
+    #[allow(clippy::zombie_processes)] // `Job::poll` is what reaps it.
+    fn test_job_poll_reaps_exited_child() {
+        let child = std::process::Command::new("true").spawn().unwrap();
+        let pid = Pid::from_raw(child.id() as i32);
+        let mut job = Job::new(1, pid, "true".to_string());
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !job.poll() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(job.state, JobState::Done(0));
+        assert!(!job.poll());
+    }
+
+    #[test]
+    fn test_job_state_into_api() {
+        assert_eq!(nexus_api::JobState::from(JobState::Stopped), nexus_api::JobState::Stopped);
+        assert_eq!(nexus_api::JobState::from(JobState::Done(3)), nexus_api::JobState::Done(3));
+    }
+
     #[test]
     fn test_job_debug_output() {
         let job = Job::new(1, Pid::from_raw(100), "test".to_string());
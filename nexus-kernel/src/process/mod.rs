@@ -1,7 +1,10 @@
 //! Process management - PTY allocation, job control, signals.
 
 mod pty;
+pub mod color;
+pub mod io;
 pub mod job;
+pub mod usage;
 
 pub use job::{Job, JobState};
 pub use pty::PtyHandle;
@@ -12,12 +15,12 @@ use std::path::Path;
 use std::io::Read;
 use std::time::Instant;
 
-use tokio::sync::broadcast::Sender;
+use crate::replay::EventSender;
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::{close, dup2, execvp, fork, ForkResult, Pid};
 use nix::fcntl::{open, OFlag};
 use nix::sys::stat::Mode;
-use nexus_api::{BlockId, ShellEvent};
+use nexus_api::{BlockId, ShellEvent, Stopwatch};
 
 use crate::commands::is_cancelled;
 use crate::parser::{Command, Redirect, RedirectOp};
@@ -68,6 +71,8 @@ pub fn spawn(
                 std::process::exit(1);
             }
 
+            inherit_substitutions(argv);
+
             // Convert argv to CStrings
             let argv_cstr: Vec<CString> = argv
                 .iter()
@@ -92,6 +97,59 @@ pub fn spawn(
     }
 }
 
+/// Spawn a process as a background job. Like any PTY child it leads its
+/// own session and process group; a reader thread streams its output into
+/// `block_id` until the PTY closes. Reaping is left to [`reap_jobs`].
+pub fn spawn_background(
+    argv: &[String],
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    env_overrides: &[(String, String)],
+    redirects: &[Redirect],
+    block_id: BlockId,
+    events: &EventSender,
+) -> anyhow::Result<Pid> {
+    let handle = spawn(argv, cwd, env, env_overrides, redirects)?;
+    if let Some(mut pty) = handle.pty {
+        let events = events.clone();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+            // Reads fail with EIO once the job and anything it forked exit.
+            while let Ok(n) = pty.master.read(&mut buffer) {
+                if n == 0 {
+                    break;
+                }
+                let _ = events.send(ShellEvent::StdoutChunk {
+                    block_id,
+                    data: buffer[..n].to_vec(),
+                    last_echo_epoch: 0,
+                });
+            }
+        });
+    }
+    Ok(handle.pid)
+}
+
+/// Poll every job without blocking and report the ones that stopped,
+/// continued, or finished. Finished jobs stay in the table until `jobs`,
+/// `fg` or `wait` has shown them.
+pub fn reap_jobs(state: &mut ShellState, events: &EventSender) {
+    for job in &mut state.jobs {
+        if job.poll() {
+            emit_job_state(events, job);
+        }
+    }
+}
+
+/// Tell the UI about a job's current state.
+pub fn emit_job_state(events: &EventSender, job: &Job) {
+    let _ = events.send(ShellEvent::JobStateChanged {
+        job_id: job.id,
+        command: job.command.clone(),
+        state: job.state.into(),
+    });
+}
+
 /// Apply file redirections to the current process.
 /// This should be called in the child process after fork, before exec.
 fn apply_redirects(redirects: &[Redirect]) -> anyhow::Result<()> {
@@ -149,22 +207,47 @@ fn apply_redirects(redirects: &[Redirect]) -> anyhow::Result<()> {
                     dup2(target_fd, redirect.fd)?;
                 }
             }
+            RedirectOp::Heredoc { .. } | RedirectOp::HereString => {
+                // fd << text - already expanded by the evaluator
+                let fd = here_doc_fd(&redirect.target)?;
+                dup2(fd, redirect.fd)?;
+                close(fd)?;
+            }
         }
     }
     Ok(())
 }
 
-/// Wait for a process to complete, emitting events for output.
+/// Open a descriptor that reads back `text`. Like bash, the text goes
+/// through an unlinked temporary file, so it can be any size.
+fn here_doc_fd(text: &str) -> anyhow::Result<std::os::fd::RawFd> {
+    use std::io::{Seek, Write};
+    use std::os::fd::IntoRawFd;
+
+    let path = std::env::temp_dir().join(format!("nexus-heredoc-{}", std::process::id()));
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)?;
+    file.write_all(text.as_bytes())?;
+    file.rewind()?;
+    Ok(file.into_raw_fd())
+}
+
+/// Wait for a process to complete, emitting events for output, then its
+/// resource usage and `CommandFinished`.
 ///
-/// Checks the cancel registry each iteration. On cancel:
-/// - Sends SIGTERM to the process
-/// - If still alive after ~100ms (10 iterations), sends SIGKILL
+/// Checks the cancel registry each iteration, stopping the process with
+/// [`signal_cancelled`] on cancel.
 pub fn wait_with_events(
     handle: ProcessHandle,
     block_id: BlockId,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
 ) -> anyhow::Result<i32> {
-    let start = Instant::now();
+    let start = Stopwatch::start();
     // Track how many iterations since we sent SIGTERM for escalation to SIGKILL
     let mut term_sent_iters: Option<u32> = None;
 
@@ -177,7 +260,8 @@ pub fn wait_with_events(
 
         loop {
             // Check if process has exited
-            match waitpid(handle.pid, Some(WaitPidFlag::WNOHANG))? {
+            let (status, used) = usage::wait4(handle.pid, Some(WaitPidFlag::WNOHANG))?;
+            match status {
                 WaitStatus::Exited(_, code) => {
                     // Read any remaining output
                     while let Ok(n) = pty.master.read(&mut buffer) {
@@ -191,19 +275,21 @@ pub fn wait_with_events(
                         });
                     }
 
+                    send_usage(events, block_id, used);
                     let _ = events.send(ShellEvent::CommandFinished {
                         block_id,
                         exit_code: code,
-                        duration_ms: start.elapsed().as_millis() as u64,
+                        duration_ms: start.elapsed_ms(),
                     });
 
                     return Ok(code);
                 }
                 WaitStatus::Signaled(_, signal, _) => {
+                    send_usage(events, block_id, used);
                     let _ = events.send(ShellEvent::CommandFinished {
                         block_id,
                         exit_code: 128 + signal as i32,
-                        duration_ms: start.elapsed().as_millis() as u64,
+                        duration_ms: start.elapsed_ms(),
                     });
 
                     return Ok(128 + signal as i32);
@@ -211,20 +297,7 @@ pub fn wait_with_events(
                 WaitStatus::StillAlive => {
                     // Check cancel flag
                     if is_cancelled(block_id) {
-                        match term_sent_iters {
-                            None => {
-                                // First cancel check — send SIGTERM
-                                let _ = nix::sys::signal::kill(handle.pid, nix::sys::signal::Signal::SIGTERM);
-                                term_sent_iters = Some(0);
-                            }
-                            Some(n) if n >= 10 => {
-                                // ~100ms since SIGTERM — escalate to SIGKILL
-                                let _ = nix::sys::signal::kill(handle.pid, nix::sys::signal::Signal::SIGKILL);
-                            }
-                            Some(n) => {
-                                term_sent_iters = Some(n + 1);
-                            }
-                        }
+                        signal_cancelled(handle.pid, block_id, &mut term_sent_iters);
                     }
 
                     // Process still running, read available output
@@ -257,39 +330,31 @@ pub fn wait_with_events(
     } else {
         // No PTY — poll loop so we can check cancellation
         loop {
-            match waitpid(handle.pid, Some(WaitPidFlag::WNOHANG))? {
+            let (status, used) = usage::wait4(handle.pid, Some(WaitPidFlag::WNOHANG))?;
+            match status {
                 WaitStatus::Exited(_, code) => {
+                    send_usage(events, block_id, used);
                     let _ = events.send(ShellEvent::CommandFinished {
                         block_id,
                         exit_code: code,
-                        duration_ms: start.elapsed().as_millis() as u64,
+                        duration_ms: start.elapsed_ms(),
                     });
                     return Ok(code);
                 }
                 WaitStatus::Signaled(_, signal, _) => {
                     let code = 128 + signal as i32;
+                    send_usage(events, block_id, used);
                     let _ = events.send(ShellEvent::CommandFinished {
                         block_id,
                         exit_code: code,
-                        duration_ms: start.elapsed().as_millis() as u64,
+                        duration_ms: start.elapsed_ms(),
                     });
                     return Ok(code);
                 }
                 WaitStatus::StillAlive => {
                     // Check cancel flag
                     if is_cancelled(block_id) {
-                        match term_sent_iters {
-                            None => {
-                                let _ = nix::sys::signal::kill(handle.pid, nix::sys::signal::Signal::SIGTERM);
-                                term_sent_iters = Some(0);
-                            }
-                            Some(n) if n >= 10 => {
-                                let _ = nix::sys::signal::kill(handle.pid, nix::sys::signal::Signal::SIGKILL);
-                            }
-                            Some(n) => {
-                                term_sent_iters = Some(n + 1);
-                            }
-                        }
+                        signal_cancelled(handle.pid, block_id, &mut term_sent_iters);
                     }
                     std::thread::sleep(std::time::Duration::from_millis(10));
                 }
@@ -301,6 +366,33 @@ pub fn wait_with_events(
     }
 }
 
+/// Stop a cancelled process. Ctrl-C ([`crate::traps::interrupt`]) sends
+/// SIGINT once, which it may handle; otherwise SIGTERM, then SIGKILL if it
+/// is still alive ~100ms (10 iterations) later.
+fn signal_cancelled(pid: Pid, block_id: BlockId, term_sent_iters: &mut Option<u32>) {
+    use nix::sys::signal::{kill, Signal};
+    match *term_sent_iters {
+        None if crate::traps::interrupted(block_id) => {
+            let _ = kill(pid, Signal::SIGINT);
+            *term_sent_iters = Some(u32::MAX);
+        }
+        // Interrupted: the process decides.
+        Some(u32::MAX) => {}
+        None => {
+            // First cancel check — send SIGTERM
+            let _ = kill(pid, Signal::SIGTERM);
+            *term_sent_iters = Some(0);
+        }
+        Some(n) if n >= 10 => {
+            // ~100ms since SIGTERM — escalate to SIGKILL
+            let _ = kill(pid, Signal::SIGKILL);
+        }
+        Some(n) => {
+            *term_sent_iters = Some(n + 1);
+        }
+    }
+}
+
 /// Spawn a pipeline of external commands connected by real pipes.
 ///
 /// Creates pipe pairs between adjacent processes:
@@ -325,7 +417,8 @@ pub fn spawn_pipeline(
             let argv: Vec<String> = std::iter::once(simple.name.clone())
                 .chain(simple.args.iter().filter_map(|w| w.as_literal().map(String::from)))
                 .collect();
-            let handle = spawn(&argv, &state.cwd, &state.env, &[], &simple.redirects)?;
+            let color_env = color::env(state.config.color_mode(&simple.name), color::Sink::Terminal, &state.env);
+            let handle = spawn(&argv, &state.cwd, &state.env, &color_env, &simple.redirects)?;
             return Ok(vec![handle]);
         }
         return Ok(vec![]);
@@ -358,6 +451,7 @@ pub fn spawn_pipeline(
         let argv: Vec<String> = std::iter::once(simple.name.clone())
             .chain(simple.args.iter().filter_map(|w| w.as_literal().map(String::from)))
             .collect();
+        let color_env = color::env(state.config.color_mode(&simple.name), color::Sink::Terminal, &state.env);
 
         match unsafe { fork() }? {
             ForkResult::Child => {
@@ -383,7 +477,7 @@ pub fn spawn_pipeline(
                 // and will be closed on exec. Set cwd and env.
                 let _ = std::env::set_current_dir(&state.cwd);
                 unsafe {
-                    for (key, value) in &state.env {
+                    for (key, value) in state.env.iter().chain(color_env.iter().map(|(k, v)| (k, v))) {
                         std::env::set_var(key, value);
                     }
                 }
@@ -392,6 +486,7 @@ pub fn spawn_pipeline(
                     eprintln!("redirect error: {}", e);
                     std::process::exit(1);
                 }
+                inherit_substitutions(&argv);
 
                 let argv_cstr: Vec<CString> = argv
                     .iter()
@@ -432,6 +527,15 @@ pub fn spawn_pipeline(
     Ok(handles)
 }
 
+/// Keep open across exec the descriptors process substitutions named on
+/// the command line (`/dev/fd/N`); the shell opens them close-on-exec.
+fn inherit_substitutions(argv: &[String]) {
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+    for fd in argv.iter().filter_map(|arg| arg.strip_prefix("/dev/fd/")?.parse::<i32>().ok()) {
+        let _ = fcntl(fd, FcntlArg::F_SETFD(FdFlag::empty()));
+    }
+}
+
 /// Set FD_CLOEXEC on a file descriptor.
 fn set_cloexec(fd: i32) -> anyhow::Result<()> {
     use nix::fcntl::{fcntl, FcntlArg, FdFlag};
@@ -442,6 +546,12 @@ fn set_cloexec(fd: i32) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn send_usage(events: &EventSender, block_id: BlockId, usage: Option<nexus_api::ResourceUsage>) {
+    if let Some(usage) = usage {
+        let _ = events.send(ShellEvent::ResourceUsage { block_id, usage });
+    }
+}
+
 /// Wait for all processes in a pipeline.
 ///
 /// Waits for the LAST process first (the one with the PTY, producing output).
@@ -452,7 +562,8 @@ fn set_cloexec(fd: i32) -> anyhow::Result<()> {
 pub fn wait_pipeline(
     mut handles: Vec<ProcessHandle>,
     block_id: BlockId,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
+    pipefail: bool,
 ) -> anyhow::Result<i32> {
     if handles.is_empty() {
         return Ok(0);
@@ -463,18 +574,25 @@ pub fn wait_pipeline(
     let last_exit = wait_with_events(last, block_id, events)?;
 
     // Reap remaining processes (they should be done or dying from SIGPIPE)
+    let mut failed = Vec::new();
     for handle in handles {
-        match waitpid(handle.pid, Some(WaitPidFlag::WNOHANG)) {
-            Ok(WaitStatus::StillAlive) => {
-                // Still running — give it a moment then block-wait
-                let _ = waitpid(handle.pid, None);
-            }
-            Ok(_) => {} // Already exited
-            Err(_) => {} // Already reaped
+        let status = match waitpid(handle.pid, Some(WaitPidFlag::WNOHANG)) {
+            // Still running — give it a moment then block-wait
+            Ok(WaitStatus::StillAlive) => waitpid(handle.pid, None),
+            status => status, // Already exited, or already reaped
+        };
+        match status {
+            Ok(WaitStatus::Exited(_, code)) if code != 0 => failed.push(code),
+            Ok(WaitStatus::Signaled(_, signal, _)) => failed.push(128 + signal as i32),
+            _ => {}
         }
     }
 
-    Ok(last_exit)
+    // With pipefail the rightmost stage that failed decides.
+    match failed.last() {
+        Some(&code) if pipefail && last_exit == 0 => Ok(code),
+        _ => Ok(last_exit),
+    }
 }
 
 /// Spawn an external command with optional stdin input (for native→external piping).
@@ -490,7 +608,7 @@ pub fn spawn_with_stdin(
     stdin_text: Option<String>,
     state: &ShellState,
     block_id: BlockId,
-    events: &Sender<ShellEvent>,
+    events: &EventSender,
 ) -> anyhow::Result<i32> {
     use std::io::Write;
     use std::process::{Command, Stdio};
@@ -509,10 +627,20 @@ pub fn spawn_with_stdin(
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
-    // Set environment
+    // Set environment; the output reaches a block through a pipe.
     for (key, value) in &state.env {
         cmd.env(key, value);
     }
+    cmd.envs(color::env(state.config.color_mode(name), color::Sink::Block, &state.env));
+
+    let argv: Vec<String> = args.to_vec();
+    // SAFETY: only fcntl runs between fork and exec.
+    unsafe {
+        std::os::unix::process::CommandExt::pre_exec(&mut cmd, move || {
+            inherit_substitutions(&argv);
+            Ok(())
+        });
+    }
 
     // Spawn the process
     let mut child = cmd.spawn()?;
@@ -585,8 +713,9 @@ pub fn spawn_with_stdin(
     Ok(exit_code)
 }
 
-/// Spawn an external command and capture its stdout as a String.
-/// Used by `watch` to get output without emitting streaming events.
+/// Spawn an external command and capture its stdout as a String, without
+/// color escapes. Used by `watch` to get output without emitting streaming
+/// events.
 pub fn spawn_capture_stdout(
     name: &str,
     args: &[String],
@@ -610,6 +739,7 @@ pub fn spawn_capture_stdout(
     for (key, value) in &state.env {
         cmd.env(key, value);
     }
+    cmd.envs(color::env(state.config.color_mode(name), color::Sink::Parser, &state.env));
 
     let mut child = cmd.spawn()?;
 
@@ -622,5 +752,5 @@ pub fn spawn_capture_stdout(
     }
 
     let output = child.wait_with_output()?;
-    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    Ok(color::strip(&String::from_utf8_lossy(&output.stdout)).into_owned())
 }
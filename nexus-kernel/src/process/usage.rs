@@ -0,0 +1,89 @@
+//! Reaping a child together with the resources it used.
+//!
+//! `waitpid` throws the child's rusage away; `wait4` hands it back with
+//! the status, so the CPU time, peak memory and disk IO of every external
+//! command can be shown on its block. `ru_maxrss` is in kilobytes on
+//! Linux and in bytes on macOS.
+
+use nexus_api::ResourceUsage;
+use nix::errno::Errno;
+use nix::sys::wait::{WaitPidFlag, WaitStatus};
+use nix::unistd::Pid;
+
+/// Like `waitpid`, also returning the child's usage once it has exited or
+/// been killed.
+pub fn wait4(pid: Pid, flags: Option<WaitPidFlag>) -> nix::Result<(WaitStatus, Option<ResourceUsage>)> {
+    let mut status: libc::c_int = 0;
+    // SAFETY: rusage is plain data and wait4 fills it in.
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    let options = flags.map_or(0, |f| f.bits());
+    // SAFETY: both pointers are to locals that outlive the call.
+    let res = unsafe { libc::wait4(pid.as_raw(), &mut status, options, &mut rusage) };
+    match Errno::result(res)? {
+        0 => Ok((WaitStatus::StillAlive, None)),
+        pid => {
+            let status = WaitStatus::from_raw(Pid::from_raw(pid), status)?;
+            let usage = matches!(status, WaitStatus::Exited(..) | WaitStatus::Signaled(..)).then(|| from_rusage(&rusage));
+            Ok((status, usage))
+        }
+    }
+}
+
+/// Block until `pid` exits, returning its exit code (128 + signal if it
+/// was killed) and usage. None if it can't be waited for, e.g. it isn't
+/// our child or was already reaped.
+pub fn wait_exit(pid: u32) -> Option<(i32, ResourceUsage)> {
+    let pid = Pid::from_raw(pid as i32);
+    loop {
+        match wait4(pid, None) {
+            Ok((WaitStatus::Exited(_, code), Some(usage))) => return Some((code, usage)),
+            Ok((WaitStatus::Signaled(_, signal, _), Some(usage))) => return Some((128 + signal as i32, usage)),
+            // Stopped or continued: keep waiting for the exit.
+            Ok(_) | Err(Errno::EINTR) => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Totals for every child this process has reaped so far. `max_rss` is
+/// the peak of the largest one.
+pub fn children() -> ResourceUsage {
+    // SAFETY: rusage is plain data and getrusage fills it in.
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: the pointer is to a local that outlives the call.
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, &mut rusage) } != 0 {
+        return ResourceUsage::default();
+    }
+    from_rusage(&rusage)
+}
+
+fn from_rusage(ru: &libc::rusage) -> ResourceUsage {
+    let ms = |tv: libc::timeval| tv.tv_sec as u64 * 1000 + tv.tv_usec as u64 / 1000;
+    let max_rss = ru.ru_maxrss.max(0) as u64;
+    ResourceUsage {
+        user_ms: ms(ru.ru_utime),
+        sys_ms: ms(ru.ru_stime),
+        max_rss: if cfg!(target_os = "macos") { max_rss } else { max_rss * 1024 },
+        blocks_in: ru.ru_inblock.max(0) as u64,
+        blocks_out: ru.ru_oublock.max(0) as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::zombie_processes)] // reaped by wait_exit
+    fn test_wait_exit_reports_usage() {
+        // Burn a little CPU so the child has something to report.
+        let child = std::process::Command::new("sh")
+            .args(["-c", "i=0; while [ $i -lt 20000 ]; do i=$((i+1)); done; exit 3"])
+            .spawn()
+            .unwrap();
+        let (code, usage) = wait_exit(child.id()).unwrap();
+        assert_eq!(code, 3);
+        assert!(usage.max_rss > 0);
+        assert!(wait_exit(child.id()).is_none(), "already reaped");
+    }
+}
@@ -0,0 +1,116 @@
+//! Timings collected by `profile <command line>`.
+//!
+//! While [`ShellState::profile`](crate::ShellState::profile) is set, the
+//! evaluator times every command and function call it runs. A function's
+//! time includes the commands it runs, and a recursive call is counted once
+//! in the time of its outermost call. [`Profile::to_table`] turns the
+//! timings into the block's output, most time first.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nexus_api::{TableColumn, Value};
+
+/// What a profile entry timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProfileKind {
+    Command,
+    Function,
+}
+
+impl ProfileKind {
+    fn label(self) -> &'static str {
+        match self {
+            ProfileKind::Command => "command",
+            ProfileKind::Function => "function",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProfileEntry {
+    calls: u64,
+    total: Duration,
+    /// Calls currently running, so recursion isn't timed twice.
+    active: usize,
+}
+
+/// Calls and cumulative time per command and function name.
+#[derive(Debug, Default)]
+pub struct Profile {
+    entries: HashMap<(ProfileKind, String), ProfileEntry>,
+}
+
+impl Profile {
+    /// Start timing a call; hand the result to [`Profile::exit`].
+    pub fn enter(&mut self, kind: ProfileKind, name: &str) -> Instant {
+        self.entries.entry((kind, name.to_string())).or_default().active += 1;
+        Instant::now()
+    }
+
+    /// Finish timing a call started with [`Profile::enter`].
+    pub fn exit(&mut self, kind: ProfileKind, name: &str, started: Instant) {
+        let entry = self.entries.entry((kind, name.to_string())).or_default();
+        entry.calls += 1;
+        entry.active = entry.active.saturating_sub(1);
+        if entry.active == 0 {
+            entry.total += started.elapsed();
+        }
+    }
+
+    /// One row per command and function, sorted by cumulative time.
+    pub fn to_table(&self) -> Value {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by(|(a_key, a), (b_key, b)| b.total.cmp(&a.total).then_with(|| a_key.1.cmp(&b_key.1)));
+        let millis = |d: Duration| Value::Float((d.as_secs_f64() * 100_000.0).round() / 100.0);
+        let rows = entries
+            .into_iter()
+            .map(|((kind, name), entry)| {
+                vec![
+                    Value::String(name.clone()),
+                    Value::String(kind.label().to_string()),
+                    Value::Int(entry.calls as i64),
+                    millis(entry.total),
+                    millis(entry.total / entry.calls.max(1) as u32),
+                ]
+            })
+            .collect();
+        Value::Table {
+            columns: vec![
+                TableColumn::new("name"),
+                TableColumn::new("kind"),
+                TableColumn::new("calls"),
+                TableColumn::new("total_ms"),
+                TableColumn::new("mean_ms"),
+            ],
+            rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recursion_is_timed_once() {
+        let mut profile = Profile::default();
+        let outer = profile.enter(ProfileKind::Function, "walk");
+        let inner = profile.enter(ProfileKind::Function, "walk");
+        let cmd = profile.enter(ProfileKind::Command, "ls");
+        profile.exit(ProfileKind::Command, "ls", cmd);
+        profile.exit(ProfileKind::Function, "walk", inner);
+        std::thread::sleep(Duration::from_millis(5));
+        profile.exit(ProfileKind::Function, "walk", outer);
+
+        let Value::Table { rows, .. } = profile.to_table() else {
+            panic!("expected table");
+        };
+        assert_eq!(rows[0][..3], [Value::String("walk".into()), Value::String("function".into()), Value::Int(2)]);
+        assert_eq!(rows[1][..3], [Value::String("ls".into()), Value::String("command".into()), Value::Int(1)]);
+        let Value::Float(total) = rows[0][3] else {
+            panic!("expected float");
+        };
+        assert!(total >= 5.0);
+    }
+}
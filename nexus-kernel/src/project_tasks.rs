@@ -0,0 +1,302 @@
+//! Runnable tasks of the project in a directory: Makefile targets, just
+//! recipes, package.json scripts and cargo binaries and examples.
+//!
+//! Used to complete the argument of `make`, `just`, `npm run` and
+//! `cargo run --bin/--example`, and to list the project's tasks in the UI.
+//! A Makefile is only looked for in the directory itself, as `make` does;
+//! the others are found in the nearest ancestor that has one.
+
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// `target: deps ## description`, not `VAR := value` or `%.o: %.c`.
+static MAKE_TARGET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z0-9_][A-Za-z0-9_./-]*)\s*:([^=].*)?$").unwrap());
+/// `[@]recipe arg *args: deps`, not `alias b := build` or `set x := y`.
+static JUST_RECIPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@?([A-Za-z][A-Za-z0-9_-]*)(?:\s+[^:]*)?:([^=].*)?$").unwrap());
+
+const MAKEFILES: [&str; 3] = ["GNUmakefile", "makefile", "Makefile"];
+const JUSTFILES: [&str; 3] = ["justfile", "Justfile", ".justfile"];
+
+/// The tool a task is run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskTool {
+    Make,
+    Just,
+    Npm,
+    /// `cargo run --bin`.
+    CargoBin,
+    /// `cargo run --example`.
+    CargoExample,
+}
+
+impl TaskTool {
+    /// The command a task's name is appended to.
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Make => "make",
+            Self::Just => "just",
+            Self::Npm => "npm run",
+            Self::CargoBin => "cargo run --bin",
+            Self::CargoExample => "cargo run --example",
+        }
+    }
+
+    /// The tool whose task comes next after `words`, the command so far.
+    pub fn expecting_task(words: &[&str]) -> Option<Self> {
+        match words {
+            ["make"] | ["gmake"] => Some(Self::Make),
+            ["just"] => Some(Self::Just),
+            ["npm" | "pnpm", "run" | "run-script"] | ["yarn", "run"] => Some(Self::Npm),
+            ["cargo", rest @ ..] if rest.len() >= 2 && matches!(rest[0], "run" | "build") => {
+                match rest[rest.len() - 1] {
+                    "--bin" => Some(Self::CargoBin),
+                    "--example" => Some(Self::CargoExample),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Task {
+    pub tool: TaskTool,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+impl Task {
+    /// The command line that runs the task.
+    pub fn command_line(&self) -> String {
+        format!("{} {}", self.tool.command(), self.name)
+    }
+}
+
+/// Every task of the project at `dir`.
+pub fn discover(dir: &Path) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    for tool in [TaskTool::Make, TaskTool::Just, TaskTool::Npm] {
+        tasks.extend(discover_tool(dir, tool));
+    }
+    if let Some(metadata) = cargo_metadata(dir) {
+        tasks.extend(cargo_targets(&metadata));
+    }
+    tasks
+}
+
+/// The tasks of one tool at `dir`.
+pub fn discover_tool(dir: &Path, tool: TaskTool) -> Vec<Task> {
+    let read = |path: PathBuf| std::fs::read_to_string(path).ok();
+    match tool {
+        TaskTool::Make => MAKEFILES.iter().find_map(|name| read(dir.join(name))).map_or_else(Vec::new, |text| makefile_targets(&text)),
+        TaskTool::Just => find_up(dir, &JUSTFILES).and_then(read).map_or_else(Vec::new, |text| justfile_recipes(&text)),
+        TaskTool::Npm => find_up(dir, &["package.json"]).and_then(read).map_or_else(Vec::new, |text| npm_scripts(&text)),
+        TaskTool::CargoBin | TaskTool::CargoExample => cargo_metadata(dir)
+            .map(|metadata| cargo_targets(&metadata).into_iter().filter(|task| task.tool == tool).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// The first of `names` in `dir` or the nearest ancestor with one.
+fn find_up(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    dir.ancestors().find_map(|dir| names.iter().map(|name| dir.join(name)).find(|path| path.is_file()))
+}
+
+/// Makefile targets, described by a `## comment` after the target or a
+/// `#` comment on the line above. Special (`.PHONY`) and pattern targets
+/// are left out.
+pub fn makefile_targets(text: &str) -> Vec<Task> {
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut comment: Option<&str> = None;
+    for line in text.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim());
+            continue;
+        }
+        if let Some(caps) = MAKE_TARGET.captures(line) {
+            let name = &caps[1];
+            let trailing = caps.get(2).and_then(|rest| rest.as_str().split_once("##")).map(|(_, text)| text.trim());
+            if !tasks.iter().any(|task| task.name == name) {
+                tasks.push(Task {
+                    tool: TaskTool::Make,
+                    name: name.to_string(),
+                    description: trailing.or(comment).filter(|text| !text.is_empty()).map(str::to_string),
+                });
+            }
+        }
+        comment = None;
+    }
+    tasks
+}
+
+/// justfile recipes, described by the comment above them. Private
+/// recipes (`_name`, `[private]`) are left out.
+pub fn justfile_recipes(text: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut comment: Option<&str> = None;
+    let mut private = false;
+    for line in text.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            if !text.starts_with('!') {
+                comment = Some(text.trim());
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+        if let Some(caps) = JUST_RECIPE.captures(line)
+            && !private
+        {
+            tasks.push(Task {
+                tool: TaskTool::Just,
+                name: caps[1].to_string(),
+                description: comment.filter(|text| !text.is_empty()).map(str::to_string),
+            });
+        }
+        comment = None;
+        private = false;
+    }
+    tasks
+}
+
+/// package.json scripts, described by what they run.
+pub fn npm_scripts(json: &str) -> Vec<Task> {
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(json) else {
+        return Vec::new();
+    };
+    let Some(scripts) = package.get("scripts").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+    scripts
+        .iter()
+        .map(|(name, script)| Task {
+            tool: TaskTool::Npm,
+            name: name.clone(),
+            description: script.as_str().map(str::to_string),
+        })
+        .collect()
+}
+
+/// `cargo metadata` for the workspace at `dir`, without resolving
+/// dependencies.
+fn cargo_metadata(dir: &Path) -> Option<String> {
+    find_up(dir, &["Cargo.toml"])?;
+    let output = std::process::Command::new("cargo")
+        .args(["metadata", "--no-deps", "--offline", "--format-version", "1"])
+        .current_dir(dir)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Binaries and examples of the workspace's packages, described by their
+/// package.
+pub fn cargo_targets(metadata: &str) -> Vec<Task> {
+    let Ok(metadata) = serde_json::from_str::<serde_json::Value>(metadata) else {
+        return Vec::new();
+    };
+    let packages = metadata.get("packages").and_then(|p| p.as_array()).map_or(&[][..], Vec::as_slice);
+    let mut tasks = Vec::new();
+    for package in packages {
+        let package_name = package.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        let targets = package.get("targets").and_then(|t| t.as_array()).map_or(&[][..], Vec::as_slice);
+        for target in targets {
+            let kinds = target.get("kind").and_then(|k| k.as_array()).map_or(&[][..], Vec::as_slice);
+            let tool = if kinds.iter().any(|k| k == "bin") {
+                TaskTool::CargoBin
+            } else if kinds.iter().any(|k| k == "example") {
+                TaskTool::CargoExample
+            } else {
+                continue;
+            };
+            let Some(name) = target.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            tasks.push(Task { tool, name: name.to_string(), description: Some(format!("in {}", package_name)) });
+        }
+    }
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(tasks: &[Task]) -> Vec<(&str, Option<&str>)> {
+        tasks.iter().map(|t| (t.name.as_str(), t.description.as_deref())).collect()
+    }
+
+    #[test]
+    fn test_makefile_targets() {
+        let text = "\
+CC := gcc
+.PHONY: build test
+# Build everything
+build: deps
+\t$(CC) -o app main.c
+test: build ## Run the tests
+%.o: %.c
+\t$(CC) -c $<
+deps:
+";
+        assert_eq!(
+            names(&makefile_targets(text)),
+            [("build", Some("Build everything")), ("test", Some("Run the tests")), ("deps", None)]
+        );
+    }
+
+    #[test]
+    fn test_justfile_recipes() {
+        let text = "\
+set shell := [\"bash\", \"-c\"]
+alias b := build
+
+# Compile the app
+build profile='dev':
+    cargo build --profile {{profile}}
+
+[private]
+helper:
+    echo hi
+
+_hidden:
+    echo no
+
+@test *args: build
+    cargo test {{args}}
+";
+        assert_eq!(names(&justfile_recipes(text)), [("build", Some("Compile the app")), ("test", None)]);
+    }
+
+    #[test]
+    fn test_npm_and_cargo() {
+        let tasks = npm_scripts(r#"{"name": "app", "scripts": {"build": "tsc", "test": "jest"}}"#);
+        assert_eq!(names(&tasks), [("build", Some("tsc")), ("test", Some("jest"))]);
+        assert_eq!(tasks[0].command_line(), "npm run build");
+
+        let metadata = r#"{"packages": [{"name": "app", "targets": [
+            {"name": "app", "kind": ["lib"]},
+            {"name": "server", "kind": ["bin"]},
+            {"name": "demo", "kind": ["example"]}
+        ]}]}"#;
+        let tasks = cargo_targets(metadata);
+        assert_eq!(names(&tasks), [("server", Some("in app")), ("demo", Some("in app"))]);
+        assert_eq!(tasks[1].command_line(), "cargo run --example demo");
+    }
+
+    #[test]
+    fn test_expecting_task() {
+        assert_eq!(TaskTool::expecting_task(&["make"]), Some(TaskTool::Make));
+        assert_eq!(TaskTool::expecting_task(&["npm", "run"]), Some(TaskTool::Npm));
+        assert_eq!(TaskTool::expecting_task(&["cargo", "run", "--release", "--bin"]), Some(TaskTool::CargoBin));
+        assert_eq!(TaskTool::expecting_task(&["cargo", "run"]), None);
+        assert_eq!(TaskTool::expecting_task(&["make", "build"]), None);
+    }
+}
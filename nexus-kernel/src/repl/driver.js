@@ -0,0 +1,90 @@
+// Nexus REPL driver for Node.
+//
+// Reads one JSON cell per line on stdin ({"cell": n, "code": "..."}) and
+// runs it in this process's global scope, so declarations outlive the cell.
+// Console output and the cell's value (awaited if it is a promise) come
+// back as JSON lines on stdout, ending with
+// {"cell": n, "done": true, "error": <stack|null>}.
+
+const readline = require("readline");
+const util = require("util");
+const vm = require("vm");
+
+// Rows of a table sent back; the rest are left out.
+const TABLE_ROWS = 500;
+
+const stdoutWrite = process.stdout.write.bind(process.stdout);
+let cell = 0;
+
+const send = (message) => stdoutWrite(JSON.stringify(message) + "\n");
+const printer = (stderr) => (...args) => {
+  send({ cell, text: util.format(...args) + "\n", stderr });
+};
+
+console.log = console.info = console.debug = printer(false);
+console.warn = console.error = printer(true);
+globalThis.require = require;
+
+// A {columns, rows} table for lists of plain objects and values with a
+// nexusTable() method; null for anything else.
+function table(value) {
+  if (value && typeof value.nexusTable === "function") {
+    return value.nexusTable();
+  }
+  const isRecord = (row) => row !== null && typeof row === "object" && !Array.isArray(row);
+  if (Array.isArray(value) && value.length > 0 && value.every(isRecord)) {
+    const rows = value.slice(0, TABLE_ROWS);
+    const columns = [...new Set(rows.flatMap(Object.keys))];
+    return { columns, rows: rows.map((row) => columns.map((c) => (row[c] === undefined ? null : row[c]))) };
+  }
+  return null;
+}
+
+const queue = [];
+let busy = false;
+let closed = false;
+
+async function pump() {
+  if (busy) {
+    return;
+  }
+  busy = true;
+  while (queue.length > 0) {
+    const message = queue.shift();
+    cell = message.cell;
+    let error = null;
+    try {
+      let value = vm.runInThisContext(message.code, { filename: "<cell>" });
+      if (value && typeof value.then === "function") {
+        value = await value;
+      }
+      if (value !== undefined) {
+        globalThis._ = value;
+        const rows = table(value);
+        send(rows ? { cell, table: rows } : { cell, repr: util.inspect(value, { depth: 4 }) });
+      }
+    } catch (e) {
+      // Drop the driver's frames from the stack.
+      const stack = (e && e.stack ? e.stack : String(e)).split("\n");
+      const driver = stack.findIndex((line) => line.includes("runInThisContext"));
+      error = (driver === -1 ? stack : stack.slice(0, driver)).join("\n");
+    }
+    send({ cell, done: true, error });
+  }
+  busy = false;
+  if (closed) {
+    process.exit(0);
+  }
+}
+
+const input = readline.createInterface({ input: process.stdin });
+input.on("line", (line) => {
+  queue.push(JSON.parse(line));
+  pump();
+});
+input.on("close", () => {
+  closed = true;
+  if (!busy) {
+    process.exit(0);
+  }
+});
@@ -0,0 +1,105 @@
+# Nexus REPL driver for Python.
+#
+# Reads one JSON cell per line on stdin ({"cell": n, "code": "..."}) and
+# runs it in a namespace kept for the whole session. Everything the cell
+# prints and the value of its last expression come back as JSON lines on
+# stdout, ending with {"cell": n, "done": true, "error": <traceback|null>}.
+
+import ast
+import io
+import json
+import sys
+import traceback
+
+_out = sys.stdout
+_in = sys.stdin
+_cell = 0
+# Rows of a table sent back; the rest are left out.
+_TABLE_ROWS = 500
+
+
+def _send(message):
+    _out.write(json.dumps(message, default=str) + "\n")
+    _out.flush()
+
+
+class _Stream(io.TextIOBase):
+    def __init__(self, stderr):
+        self._stderr = stderr
+
+    def writable(self):
+        return True
+
+    def write(self, text):
+        if text:
+            _send({"cell": _cell, "text": text, "stderr": self._stderr})
+        return len(text)
+
+
+def _table(value):
+    """A {"columns", "rows"} table for dataframes, lists of dicts and
+    objects with a __nexus_table__() method; None for anything else."""
+    if hasattr(value, "__nexus_table__"):
+        return value.__nexus_table__()
+    module = type(value).__module__
+    if module.startswith("pandas") and hasattr(value, "columns"):
+        head = value.head(_TABLE_ROWS)
+        return {"columns": [str(c) for c in head.columns], "rows": head.to_dict("split")["data"]}
+    if module.startswith("polars") and hasattr(value, "columns"):
+        head = value.head(_TABLE_ROWS)
+        return {"columns": list(head.columns), "rows": [list(r) for r in head.rows()]}
+    if isinstance(value, list) and value and all(isinstance(r, dict) for r in value):
+        columns = []
+        for row in value[:_TABLE_ROWS]:
+            columns.extend(k for k in row if k not in columns)
+        return {
+            "columns": [str(c) for c in columns],
+            "rows": [[row.get(c) for c in columns] for row in value[:_TABLE_ROWS]],
+        }
+    return None
+
+
+def _run(code, namespace):
+    tree = ast.parse(code, "<cell>", "exec")
+    last = None
+    if tree.body and isinstance(tree.body[-1], ast.Expr):
+        last = ast.Expression(tree.body.pop().value)
+    exec(compile(tree, "<cell>", "exec"), namespace)
+    if last is not None:
+        return eval(compile(last, "<cell>", "eval"), namespace)
+    return None
+
+
+def _main():
+    global _cell
+    namespace = {"__name__": "__main__"}
+    sys.stdout = _Stream(False)
+    sys.stderr = _Stream(True)
+    # Cells arrive on the real stdin; input() gets end of file.
+    sys.stdin = io.StringIO()
+    for line in _in:
+        message = json.loads(line)
+        _cell = message["cell"]
+        error = None
+        try:
+            value = _run(message["code"], namespace)
+            if value is not None:
+                namespace["_"] = value
+                table = _table(value)
+                if table is not None:
+                    _send({"cell": _cell, "table": table})
+                else:
+                    _send({"cell": _cell, "repr": repr(value)})
+        except SystemExit:
+            _send({"cell": _cell, "done": True, "error": None})
+            return
+        except BaseException:
+            kind, exc, tb = sys.exc_info()
+            # Start the traceback at the cell's own frames.
+            while tb is not None and tb.tb_frame.f_code.co_filename != "<cell>":
+                tb = tb.tb_next
+            error = "".join(traceback.format_exception(kind, exc, tb)).rstrip()
+        _send({"cell": _cell, "done": True, "error": error})
+
+
+_main()
@@ -0,0 +1,360 @@
+//! Persistent interpreter sessions for `repl python` and `repl node`.
+//!
+//! The interpreter runs a small driver (`driver.py`, `driver.js`) that
+//! takes one JSON cell per line on stdin and answers with JSON lines on
+//! stdout: printed text, the value of the cell's last expression (as a
+//! table when it looks like one), and a final `done` line carrying the
+//! traceback if the cell raised. Anything else the process prints counts
+//! as the current cell's output.
+//!
+//! The UI sends cells and restarts with [`send`]. Like `debug`'s pause
+//! channels, inputs are keyed by block and exist only while the session
+//! runs. Progress goes out as `ShellEvent::Repl`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nexus_api::{BlockId, ReplUpdate, ShellEvent, TableColumn, Value};
+use serde::Deserialize;
+
+use crate::replay::EventSender;
+
+const PYTHON_DRIVER: &str = include_str!("driver.py");
+const NODE_DRIVER: &str = include_str!("driver.js");
+
+/// How often the session checks for cancellation and interpreter exit.
+const POLL: Duration = Duration::from_millis(100);
+
+/// The interpreters `repl` can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    Node,
+}
+
+impl Language {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "python" | "python3" | "py" => Some(Self::Python),
+            "node" | "js" | "javascript" => Some(Self::Node),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Python => "python",
+            Self::Node => "node",
+        }
+    }
+
+    fn command(self) -> Command {
+        match self {
+            Self::Python => {
+                let mut cmd = Command::new("python3");
+                cmd.args(["-u", "-c", PYTHON_DRIVER]);
+                cmd
+            }
+            Self::Node => {
+                let mut cmd = Command::new("node");
+                cmd.args(["-e", NODE_DRIVER]);
+                cmd
+            }
+        }
+    }
+}
+
+/// What the UI can ask of a running session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplInput {
+    /// Run this code in the session.
+    Cell(String),
+    /// Kill the interpreter and start a fresh one.
+    Restart,
+}
+
+static SESSIONS: std::sync::LazyLock<Mutex<HashMap<BlockId, Sender<ReplInput>>>> =
+    std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn register(block_id: BlockId) -> Receiver<ReplInput> {
+    let (tx, rx) = mpsc::channel();
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).insert(block_id, tx);
+    rx
+}
+
+fn unregister(block_id: BlockId) {
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).remove(&block_id);
+}
+
+/// Hand input to a block's session. Returns false if the block isn't
+/// running a REPL.
+pub fn send(block_id: BlockId, input: ReplInput) -> bool {
+    let sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    sessions.get(&block_id).is_some_and(|tx| tx.send(input).is_ok())
+}
+
+/// A running interpreter and the threads forwarding its output.
+struct Interpreter {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl Interpreter {
+    /// Start `language`'s interpreter in `cwd`. Output is reported against
+    /// `cell`, the cell running now.
+    fn spawn(
+        language: Language,
+        cwd: &Path,
+        env: &HashMap<String, String>,
+        block_id: BlockId,
+        events: &EventSender,
+        cell: &Arc<AtomicU32>,
+    ) -> std::io::Result<Self> {
+        let mut child = language
+            .command()
+            .current_dir(cwd)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("piped stdin");
+
+        let stdout = child.stdout.take().expect("piped stdout");
+        let (tx, current) = (events.clone(), cell.clone());
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                let update = parse_message(&line, current.load(Ordering::Relaxed));
+                let _ = tx.send(ShellEvent::Repl { block_id, update });
+            }
+        });
+        let stderr = child.stderr.take().expect("piped stderr");
+        let (tx, current) = (events.clone(), cell.clone());
+        std::thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let update = ReplUpdate::Output { cell: current.load(Ordering::Relaxed), text: line + "\n", stderr: true };
+                let _ = tx.send(ShellEvent::Repl { block_id, update });
+            }
+        });
+
+        Ok(Self { child, stdin })
+    }
+
+    fn run_cell(&mut self, cell: u32, code: &str) -> std::io::Result<()> {
+        let line = serde_json::json!({ "cell": cell, "code": code }).to_string();
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    /// The interpreter's exit code, once it has exited.
+    fn exited(&mut self) -> Option<i32> {
+        self.child.try_wait().ok().flatten().map(|status| status.code().unwrap_or(1))
+    }
+}
+
+impl Drop for Interpreter {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Run a `language` session for `block_id` until `cancel` is set or the
+/// interpreter exits on its own (`exit()`, `process.exit()`). Returns the
+/// exit code for the block.
+pub fn run(
+    language: Language,
+    cwd: &Path,
+    env: &HashMap<String, String>,
+    block_id: BlockId,
+    events: &EventSender,
+    cancel: &AtomicBool,
+) -> std::io::Result<i32> {
+    let cell = Arc::new(AtomicU32::new(0));
+    let mut interpreter = Interpreter::spawn(language, cwd, env, block_id, events, &cell)?;
+    let inputs = register(block_id);
+    let ready = || {
+        let _ = events.send(ShellEvent::Repl { block_id, update: ReplUpdate::Ready { language: language.name().to_string() } });
+    };
+    ready();
+
+    let exit_code = loop {
+        if cancel.load(Ordering::Relaxed) {
+            break 0;
+        }
+        if let Some(code) = interpreter.exited() {
+            break code;
+        }
+        match inputs.recv_timeout(POLL) {
+            Ok(ReplInput::Cell(code)) => {
+                let number = cell.fetch_add(1, Ordering::Relaxed) + 1;
+                let _ = events.send(ShellEvent::Repl {
+                    block_id,
+                    update: ReplUpdate::CellStarted { cell: number, code: code.clone() },
+                });
+                if let Err(e) = interpreter.run_cell(number, &code) {
+                    let _ = events.send(ShellEvent::Repl {
+                        block_id,
+                        update: ReplUpdate::CellFinished { cell: number, error: Some(e.to_string()) },
+                    });
+                }
+            }
+            Ok(ReplInput::Restart) => {
+                drop(interpreter);
+                cell.store(0, Ordering::Relaxed);
+                interpreter = match Interpreter::spawn(language, cwd, env, block_id, events, &cell) {
+                    Ok(interpreter) => interpreter,
+                    Err(e) => {
+                        unregister(block_id);
+                        return Err(e);
+                    }
+                };
+                ready();
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break 0,
+        }
+    };
+    unregister(block_id);
+    Ok(exit_code)
+}
+
+/// A line from the driver.
+#[derive(Debug, Deserialize)]
+struct Message {
+    cell: u32,
+    text: Option<String>,
+    #[serde(default)]
+    stderr: bool,
+    repr: Option<String>,
+    table: Option<TableMessage>,
+    #[serde(default)]
+    done: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TableMessage {
+    columns: Vec<String>,
+    rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// The update a line of driver output stands for. A line that isn't a
+/// driver message is text the cell running now (`cell`) printed some
+/// other way, e.g. from native code.
+fn parse_message(line: &str, cell: u32) -> ReplUpdate {
+    let Ok(message) = serde_json::from_str::<Message>(line) else {
+        return ReplUpdate::Output { cell, text: format!("{}\n", line), stderr: false };
+    };
+    let cell = message.cell;
+    if message.done {
+        return ReplUpdate::CellFinished { cell, error: message.error };
+    }
+    if let Some(table) = message.table {
+        let value = Value::Table {
+            columns: table.columns.into_iter().map(TableColumn::new).collect(),
+            rows: table.rows.into_iter().map(|row| row.into_iter().map(crate::commands::json::json_to_value).collect()).collect(),
+        };
+        return ReplUpdate::Value { cell, value };
+    }
+    if let Some(repr) = message.repr {
+        return ReplUpdate::Value { cell, value: Value::String(repr) };
+    }
+    ReplUpdate::Output { cell, text: message.text.unwrap_or_default(), stderr: message.stderr }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message() {
+        assert_eq!(
+            parse_message(r#"{"cell": 2, "text": "hi\n", "stderr": false}"#, 2),
+            ReplUpdate::Output { cell: 2, text: "hi\n".into(), stderr: false }
+        );
+        assert_eq!(
+            parse_message(r#"{"cell": 2, "repr": "[1, 2]"}"#, 2),
+            ReplUpdate::Value { cell: 2, value: Value::String("[1, 2]".into()) }
+        );
+        assert_eq!(
+            parse_message(r#"{"cell": 3, "done": true, "error": "NameError: x"}"#, 3),
+            ReplUpdate::CellFinished { cell: 3, error: Some("NameError: x".into()) }
+        );
+        assert_eq!(
+            parse_message("segfault in libfoo", 4),
+            ReplUpdate::Output { cell: 4, text: "segfault in libfoo\n".into(), stderr: false }
+        );
+
+        let ReplUpdate::Value { value: Value::Table { columns, rows }, .. } =
+            parse_message(r#"{"cell": 1, "table": {"columns": ["a", "b"], "rows": [[1, "x"], [2.5, null]]}}"#, 1)
+        else {
+            panic!("expected a table");
+        };
+        assert_eq!(columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(rows[1], [Value::Float(2.5), Value::Unit]);
+    }
+
+    #[test]
+    fn test_send_reaches_registered_block() {
+        let block_id = BlockId(10_201);
+        assert!(!send(block_id, ReplInput::Restart));
+        let rx = register(block_id);
+        assert!(send(block_id, ReplInput::Cell("1 + 1".into())));
+        assert_eq!(rx.recv().unwrap(), ReplInput::Cell("1 + 1".into()));
+        unregister(block_id);
+        assert!(!send(block_id, ReplInput::Restart));
+    }
+
+    #[test]
+    fn test_python_session_keeps_state() {
+        if Command::new("python3").arg("--version").output().is_err() {
+            return;
+        }
+        let (events, mut rx) = EventSender::channel(256);
+        let block_id = BlockId(10_202);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let session = {
+            let (events, cancel) = (events.clone(), cancel.clone());
+            std::thread::spawn(move || {
+                run(Language::Python, &std::env::temp_dir(), &HashMap::new(), block_id, &events, &cancel)
+            })
+        };
+
+        let mut updates = Vec::new();
+        let mut next = || loop {
+            if let Ok(ShellEvent::Repl { update, .. }) = rx.blocking_recv() {
+                return update;
+            }
+        };
+        assert!(matches!(next(), ReplUpdate::Ready { .. }));
+        while !send(block_id, ReplInput::Cell("x = 20\nprint('set')".into())) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(send(block_id, ReplInput::Cell("[{'n': x + 1}]".into())));
+        assert!(send(block_id, ReplInput::Cell("1 / 0".into())));
+        while updates.iter().filter(|u| matches!(u, ReplUpdate::CellFinished { .. })).count() < 3 {
+            updates.push(next());
+        }
+        cancel.store(true, Ordering::Relaxed);
+        assert_eq!(session.join().unwrap().unwrap(), 0);
+
+        assert!(updates.contains(&ReplUpdate::Output { cell: 1, text: "set".into(), stderr: false }));
+        assert!(updates.contains(&ReplUpdate::Value {
+            cell: 2,
+            value: Value::Table { columns: vec![TableColumn::new("n")], rows: vec![vec![Value::Int(21)]] },
+        }));
+        let Some(ReplUpdate::CellFinished { error: Some(error), .. }) =
+            updates.iter().find(|u| matches!(u, ReplUpdate::CellFinished { cell: 3, .. }))
+        else {
+            panic!("expected the third cell to fail");
+        };
+        assert!(error.ends_with("ZeroDivisionError: division by zero"), "{}", error);
+    }
+}
@@ -0,0 +1,521 @@
+//! Lossless replay of kernel events for subscribers that fall behind.
+//!
+//! The kernel's event channel is a bounded broadcast: a subscriber that
+//! lags more than its capacity loses the oldest events, which for a block
+//! means truncated or garbled output. Every event the kernel emits goes
+//! through an [`EventSender`], which records it in a [`ReplayLog`] before
+//! broadcasting it, under the same lock.
+//!
+//! Resync protocol: a subscriber that sees `RecvError::Lagged` calls
+//! [`ReplayLog::resync`] with its receiver. Under the log lock the receiver
+//! is drained (everything queued is already in the log) and the event
+//! history of every recent block is snapshotted. The subscriber rebuilds
+//! those blocks from the replay; events received afterwards are strictly
+//! newer.
+//!
+//! Output (stdout and stderr) is the bulk of the log. The first
+//! [`MEMORY_LIMIT`] bytes of each block stay in memory; bursts beyond that
+//! spill to a file under the temp directory, up to [`SPILL_LIMIT`], after
+//! which the block is marked truncated. Spilled output is read back lazily
+//! by [`ReplayEvents`], after the lock is released, so a large replay never
+//! stalls the kernel's event sender.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use nexus_api::{BlockId, JobState, ShellEvent};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::{SendError, TryRecvError};
+
+/// Blocks kept for replay; the oldest is evicted first.
+pub const MAX_BLOCKS: usize = 32;
+/// Output bytes per block kept in memory before spilling to disk.
+pub const MEMORY_LIMIT: usize = 256 * 1024;
+/// Output bytes per block spilled to disk before output is truncated.
+pub const SPILL_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// Sending half of the kernel's event channel. Records each event for
+/// replay before broadcasting it.
+#[derive(Clone)]
+pub struct EventSender {
+    tx: broadcast::Sender<ShellEvent>,
+    log: ReplayLog,
+}
+
+impl EventSender {
+    /// Create a recorded broadcast channel.
+    pub fn channel(capacity: usize) -> (Self, broadcast::Receiver<ShellEvent>) {
+        let (tx, rx) = broadcast::channel(capacity);
+        (Self { tx, log: ReplayLog::default() }, rx)
+    }
+
+    /// Record and broadcast an event. Like `broadcast::Sender::send`, fails
+    /// only when there are no receivers; the event is recorded regardless.
+    #[allow(clippy::result_large_err)] // Same signature as `broadcast::Sender::send`.
+    pub fn send(&self, event: ShellEvent) -> Result<usize, SendError<ShellEvent>> {
+        let mut inner = self.log.lock();
+        inner.record(&event);
+        self.tx.send(event)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ShellEvent> {
+        self.tx.subscribe()
+    }
+
+    /// The underlying channel. Events sent on it directly are not recorded.
+    pub fn broadcast(&self) -> &broadcast::Sender<ShellEvent> {
+        &self.tx
+    }
+
+    pub fn log(&self) -> &ReplayLog {
+        &self.log
+    }
+}
+
+/// Recorded event history of recent blocks. Cheap to clone; clones share
+/// the log.
+#[derive(Clone, Default)]
+pub struct ReplayLog {
+    inner: Arc<Mutex<Inner>>,
+}
+
+/// Everything needed to rebuild one block.
+#[derive(Debug)]
+pub struct BlockReplay {
+    pub block_id: BlockId,
+    /// The block's events in emission order, output re-chunked from the log.
+    pub events: ReplayEvents,
+    /// Output beyond [`SPILL_LIMIT`] was lost. Spill that turns out to be
+    /// unreadable is reported by [`ReplayEvents::lost`] instead.
+    pub truncated: bool,
+}
+
+/// A block's replayed events. Spilled output is read from disk one chunk at
+/// a time as the iterator advances, without holding the log lock.
+#[derive(Debug)]
+pub struct ReplayEvents {
+    block_id: BlockId,
+    entries: std::vec::IntoIter<Entry>,
+    memory: Vec<u8>,
+    spill: Option<Arc<Spill>>,
+    reader: Option<File>,
+    lost: bool,
+}
+
+impl ReplayEvents {
+    /// Whether a spilled chunk could not be read back and was skipped.
+    pub fn lost(&self) -> bool {
+        self.lost
+    }
+
+    fn read(&mut self, location: Location) -> Option<Vec<u8>> {
+        match location {
+            Location::Memory { start, len } => self.memory.get(start..start + len).map(<[u8]>::to_vec),
+            Location::Disk { start, len } => {
+                if self.reader.is_none() {
+                    self.reader = Some(File::open(&self.spill.as_ref()?.path).ok()?);
+                }
+                let file = self.reader.as_mut()?;
+                file.seek(SeekFrom::Start(start)).ok()?;
+                let mut data = vec![0; len];
+                file.read_exact(&mut data).ok()?;
+                Some(data)
+            }
+        }
+    }
+}
+
+impl Iterator for ReplayEvents {
+    type Item = ShellEvent;
+
+    fn next(&mut self) -> Option<ShellEvent> {
+        loop {
+            let (stream, location) = match self.entries.next()? {
+                Entry::Event(event) => return Some(event),
+                Entry::Output { stream, location } => (stream, location),
+            };
+            match self.read(location) {
+                Some(data) => return Some(stream.event(self.block_id, data)),
+                None => self.lost = true,
+            }
+        }
+    }
+}
+
+/// Result of [`ReplayLog::resync`].
+#[derive(Debug, Default)]
+pub struct Resync {
+    /// Queued events discarded from the receiver (covered by the replay).
+    pub discarded: usize,
+    /// Recent blocks, oldest first.
+    pub blocks: Vec<BlockReplay>,
+    /// Latest working directory reported by the kernel, if any.
+    pub cwd: Option<PathBuf>,
+    /// Latest command and state of every job seen, by job id.
+    pub jobs: Vec<(u32, String, JobState)>,
+}
+
+impl ReplayLog {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Bring a lagging receiver back in step: drain it and return the
+    /// history of every recent block. No event can be sent in between, so
+    /// whatever the receiver yields next is newer than the replay. Only the
+    /// snapshot is taken under the lock; spilled output is read as the
+    /// replay is consumed.
+    pub fn resync(&self, rx: &mut broadcast::Receiver<ShellEvent>) -> Resync {
+        let inner = self.lock();
+        let mut discarded = 0;
+        loop {
+            match rx.try_recv() {
+                Ok(_) => discarded += 1,
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+        Resync {
+            discarded,
+            blocks: inner.blocks.iter().map(BlockLog::snapshot).collect(),
+            cwd: inner.cwd.clone(),
+            jobs: inner.jobs.iter().map(|(id, (command, state))| (*id, command.clone(), *state)).collect(),
+        }
+    }
+
+    /// Replay of a single block, if it is still in the log.
+    pub fn block(&self, block_id: BlockId) -> Option<BlockReplay> {
+        self.lock().blocks.iter().find(|b| b.block_id == block_id).map(BlockLog::snapshot)
+    }
+
+    /// Ids of the blocks currently held, oldest first.
+    pub fn block_ids(&self) -> Vec<BlockId> {
+        self.lock().blocks.iter().map(|b| b.block_id).collect()
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    blocks: VecDeque<BlockLog>,
+    cwd: Option<PathBuf>,
+    jobs: BTreeMap<u32, (String, JobState)>,
+}
+
+impl Inner {
+    fn record(&mut self, event: &ShellEvent) {
+        let block_id = match event {
+            ShellEvent::CwdChanged { new, .. } => {
+                self.cwd = Some(new.clone());
+                return;
+            }
+            ShellEvent::CommandStarted { block_id, .. }
+            | ShellEvent::StdoutChunk { block_id, .. }
+            | ShellEvent::StderrChunk { block_id, .. }
+            | ShellEvent::CommandOutput { block_id, .. }
+            | ShellEvent::CommandError { block_id, .. }
+            | ShellEvent::Trace { block_id, .. }
+            | ShellEvent::DebugPaused { block_id, .. }
+            | ShellEvent::Repl { block_id, .. }
+            | ShellEvent::ResourceUsage { block_id, .. }
+            | ShellEvent::CommandFinished { block_id, .. }
+            | ShellEvent::RemoteConnectProgress { block_id, .. }
+            | ShellEvent::StreamingUpdate { block_id, .. }
+            | ShellEvent::TerminalSnapshot { block_id, .. }
+            | ShellEvent::TerminalModeChanged { block_id, .. }
+            | ShellEvent::KernelPanic { block_id, .. }
+            | ShellEvent::ScrollbackHistory { block_id, .. } => *block_id,
+            ShellEvent::JobStateChanged { job_id, command, state } => {
+                self.jobs.insert(*job_id, (command.clone(), *state));
+                return;
+            }
+            // The UI does not track the environment.
+            ShellEvent::EnvChanged { .. } => return,
+        };
+        self.block_mut(block_id).record(event);
+    }
+
+    fn block_mut(&mut self, block_id: BlockId) -> &mut BlockLog {
+        if let Some(i) = self.blocks.iter().position(|b| b.block_id == block_id) {
+            return &mut self.blocks[i];
+        }
+        if self.blocks.len() == MAX_BLOCKS {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back(BlockLog::new(block_id));
+        self.blocks.back_mut().expect("just pushed")
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Entry {
+    Event(ShellEvent),
+    Output { stream: Stream, location: Location },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Stream {
+    Stdout { last_echo_epoch: u64 },
+    Stderr,
+}
+
+impl Stream {
+    fn event(self, block_id: BlockId, data: Vec<u8>) -> ShellEvent {
+        match self {
+            Stream::Stdout { last_echo_epoch } => ShellEvent::StdoutChunk { block_id, data, last_echo_epoch },
+            Stream::Stderr => ShellEvent::StderrChunk { block_id, data },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Location {
+    Memory { start: usize, len: usize },
+    Disk { start: u64, len: usize },
+}
+
+struct BlockLog {
+    block_id: BlockId,
+    entries: Vec<Entry>,
+    output: OutputBuffer,
+    truncated: bool,
+}
+
+impl BlockLog {
+    fn new(block_id: BlockId) -> Self {
+        Self { block_id, entries: Vec::new(), output: OutputBuffer::new(block_id), truncated: false }
+    }
+
+    fn record(&mut self, event: &ShellEvent) {
+        match event {
+            ShellEvent::StdoutChunk { data, last_echo_epoch, .. } => {
+                self.record_output(Stream::Stdout { last_echo_epoch: *last_echo_epoch }, data)
+            }
+            ShellEvent::StderrChunk { data, .. } => self.record_output(Stream::Stderr, data),
+            // A coalescing update replaces the previous one; keep only the latest.
+            ShellEvent::StreamingUpdate { coalesce: true, .. } => {
+                self.entries.retain(|e| !matches!(e, Entry::Event(ShellEvent::StreamingUpdate { coalesce: true, .. })));
+                self.entries.push(Entry::Event(event.clone()));
+            }
+            _ => self.entries.push(Entry::Event(event.clone())),
+        }
+    }
+
+    fn record_output(&mut self, stream: Stream, data: &[u8]) {
+        match self.output.append(data) {
+            Some(location) => self.entries.push(Entry::Output { stream, location }),
+            None => self.truncated = true,
+        }
+    }
+
+    /// Copy what a replay needs out of the log. Spilled output stays on
+    /// disk; the shared [`Spill`] keeps the file alive until the replay is
+    /// dropped, even if the block is evicted meanwhile.
+    fn snapshot(&self) -> BlockReplay {
+        let events = ReplayEvents {
+            block_id: self.block_id,
+            entries: self.entries.clone().into_iter(),
+            memory: self.output.memory.clone(),
+            spill: self.output.spill.as_ref().map(|(spill, _)| spill.clone()),
+            reader: None,
+            lost: false,
+        };
+        BlockReplay { block_id: self.block_id, events, truncated: self.truncated }
+    }
+}
+
+/// A block's output: in memory up to [`MEMORY_LIMIT`], then in a spill file.
+struct OutputBuffer {
+    block_id: BlockId,
+    memory: Vec<u8>,
+    spill: Option<(Arc<Spill>, File)>,
+    spilled: u64,
+}
+
+impl OutputBuffer {
+    fn new(block_id: BlockId) -> Self {
+        Self { block_id, memory: Vec::new(), spill: None, spilled: 0 }
+    }
+
+    /// Store a chunk. `None` when it could not be kept (limit reached or
+    /// the spill file failed).
+    fn append(&mut self, data: &[u8]) -> Option<Location> {
+        // Once spilling has started, stay on disk so order is preserved.
+        if self.spill.is_none() && self.memory.len() + data.len() <= MEMORY_LIMIT {
+            let start = self.memory.len();
+            self.memory.extend_from_slice(data);
+            return Some(Location::Memory { start, len: data.len() });
+        }
+        if self.spilled + data.len() as u64 > SPILL_LIMIT {
+            return None;
+        }
+        if self.spill.is_none() {
+            self.spill = Some(open_spill(self.block_id).map_err(|e| tracing::warn!("replay: cannot spill output: {}", e)).ok()?);
+        }
+        let (_, file) = self.spill.as_mut()?;
+        if let Err(e) = file.seek(SeekFrom::End(0)).and_then(|_| file.write_all(data)) {
+            tracing::warn!("replay: failed to spill output: {}", e);
+            return None;
+        }
+        let start = self.spilled;
+        self.spilled += data.len() as u64;
+        Some(Location::Disk { start, len: data.len() })
+    }
+}
+
+/// A spill file, removed once the log and every replay reading it are done.
+#[derive(Debug)]
+struct Spill {
+    path: PathBuf,
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Spill files live in a per-process directory under the temp dir.
+fn open_spill(block_id: BlockId) -> std::io::Result<(Arc<Spill>, File)> {
+    let dir = std::env::temp_dir().join(format!("nexus-replay-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}-{}.out", block_id.0, rand::random::<u32>()));
+    let file = std::fs::OpenOptions::new().create(true).truncate(true).read(true).write(true).open(&path)?;
+    Ok((Arc::new(Spill { path }), file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stdout(block: u64, data: &[u8]) -> ShellEvent {
+        ShellEvent::StdoutChunk { block_id: BlockId(block), data: data.to_vec(), last_echo_epoch: 0 }
+    }
+
+    fn replayed_stdout(events: &[ShellEvent]) -> Vec<u8> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                ShellEvent::StdoutChunk { data, .. } => Some(data.as_slice()),
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_resync_recovers_lagged_events() {
+        let (tx, mut rx) = EventSender::channel(4);
+        tx.send(ShellEvent::CommandStarted { block_id: BlockId(1), command: "yes".into(), cwd: "/".into(), container: None }).unwrap();
+        for i in 0..20u8 {
+            tx.send(stdout(1, &[b'a' + i])).unwrap();
+        }
+        tx.send(ShellEvent::CommandFinished { block_id: BlockId(1), exit_code: 0, duration_ms: 1 }).unwrap();
+
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Lagged(_))));
+        let resync = tx.log().resync(&mut rx);
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+
+        let block = resync.blocks.into_iter().next().unwrap();
+        assert!(!block.truncated);
+        let events: Vec<_> = block.events.collect();
+        assert!(matches!(events.first(), Some(ShellEvent::CommandStarted { .. })));
+        assert!(matches!(events.last(), Some(ShellEvent::CommandFinished { .. })));
+        assert_eq!(replayed_stdout(&events), b"abcdefghijklmnopqrst");
+
+        // Newer events arrive normally after the resync.
+        tx.send(stdout(1, b"z")).unwrap();
+        assert!(matches!(rx.try_recv(), Ok(ShellEvent::StdoutChunk { .. })));
+    }
+
+    #[test]
+    fn test_stdout_spills_to_disk_in_order() {
+        let (tx, _rx) = EventSender::channel(4);
+        let chunk = vec![b'x'; 64 * 1024];
+        for _ in 0..(MEMORY_LIMIT / chunk.len() + 3) {
+            tx.send(stdout(7, &chunk)).unwrap();
+        }
+        tx.send(stdout(7, b"tail")).unwrap();
+
+        let replay = tx.log().block(BlockId(7)).unwrap();
+        assert!(!replay.truncated);
+        let mut events = replay.events;
+        let out = replayed_stdout(&events.by_ref().collect::<Vec<_>>());
+        assert_eq!(out.len(), (MEMORY_LIMIT / chunk.len() + 3) * chunk.len() + 4);
+        assert!(out.ends_with(b"xtail"));
+        assert!(!events.lost());
+    }
+
+    #[test]
+    fn test_stderr_spills_with_stdout() {
+        let (tx, _rx) = EventSender::channel(4);
+        let chunk = vec![b'e'; 64 * 1024];
+        for _ in 0..(MEMORY_LIMIT / chunk.len() + 2) {
+            tx.send(ShellEvent::StderrChunk { block_id: BlockId(3), data: chunk.clone() }).unwrap();
+        }
+        tx.send(stdout(3, b"out")).unwrap();
+        assert!(tx.log().lock().blocks[0].output.spill.is_some());
+
+        let events: Vec<_> = tx.log().block(BlockId(3)).unwrap().events.collect();
+        let stderr: usize = events
+            .iter()
+            .map(|e| match e {
+                ShellEvent::StderrChunk { data, .. } => data.len(),
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(stderr, (MEMORY_LIMIT / chunk.len() + 2) * chunk.len());
+        assert_eq!(replayed_stdout(&events), b"out");
+    }
+
+    #[test]
+    fn test_replay_reads_spill_after_the_lock_is_released() {
+        let (tx, _rx) = EventSender::channel(4);
+        let chunk = vec![b'x'; 64 * 1024];
+        for _ in 0..(MEMORY_LIMIT / chunk.len() + 2) {
+            tx.send(stdout(0, &chunk)).unwrap();
+        }
+        let mut events = tx.log().block(BlockId(0)).unwrap().events;
+        assert!(events.next().is_some());
+
+        // The sender is not blocked while the replay is being read, and
+        // evicting the block does not pull the spill file from under it.
+        for block in 1..=(MAX_BLOCKS as u64) {
+            tx.send(stdout(block, b"out")).unwrap();
+        }
+        assert!(!tx.log().block_ids().contains(&BlockId(0)));
+        assert_eq!(events.by_ref().count(), MEMORY_LIMIT / chunk.len() + 1);
+        assert!(!events.lost());
+    }
+
+    #[test]
+    fn test_oldest_blocks_are_evicted() {
+        let (tx, _rx) = EventSender::channel(4);
+        for block in 0..(MAX_BLOCKS as u64 + 5) {
+            tx.send(stdout(block, b"out")).unwrap();
+        }
+        let ids = tx.log().block_ids();
+        assert_eq!(ids.len(), MAX_BLOCKS);
+        assert_eq!(ids[0], BlockId(5));
+    }
+
+    #[test]
+    fn test_coalesced_updates_keep_latest() {
+        let (tx, _rx) = EventSender::channel(4);
+        for seq in 0..3 {
+            tx.send(ShellEvent::StreamingUpdate {
+                block_id: BlockId(2),
+                seq,
+                update: nexus_api::Value::Int(seq as i64),
+                coalesce: true,
+            })
+            .unwrap();
+        }
+        let events: Vec<_> = tx.log().block(BlockId(2)).unwrap().events.collect();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0], ShellEvent::StreamingUpdate { seq: 2, .. }));
+    }
+}
@@ -0,0 +1,274 @@
+//! Scheduled commands: cron-style recurring commands kept in the
+//! persistence store and run by the UI while Nexus is open.
+//!
+//! `schedule "0 9 * * 1-5" 'git fetch --all'` stores the command with the
+//! directory it was scheduled from. The UI polls [`take_due`] and runs what
+//! comes back as ordinary blocks, recording each run's exit code and
+//! duration. Runs that fell due while Nexus was closed are made up as
+//! `[schedule] catch_up` says.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeDelta, Timelike, Utc};
+
+use crate::config::CatchUp;
+use crate::persistence::Store;
+
+/// Missed runs beyond this many aren't made up, even with `catch_up = "all"`.
+const MAX_CATCH_UP: usize = 10;
+
+/// How late a run may start and still count as on time under
+/// `catch_up = "skip"`. Covers the UI's polling interval and a short sleep.
+const GRACE: TimeDelta = TimeDelta::minutes(2);
+
+/// How far ahead to look for the next run before deciding there is none
+/// (`0 0 31 2 *`). Five years covers every leap day.
+const HORIZON_DAYS: i64 = 5 * 366;
+
+/// A parsed cron expression: minute, hour, day of month, month and day of
+/// week, each `*`, a number, a range `a-b`, a list `a,b` or a step `*/n`.
+/// Day of week runs from 0 (Sunday) to 6; 7 is Sunday too. `@hourly`,
+/// `@daily`, `@weekly`, `@monthly` and `@yearly` are accepted as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Both day fields were restricted, so a day matching either one
+    /// matches, as in cron.
+    either_day: bool,
+}
+
+impl FromStr for CronExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("expected 5 fields (minute hour day month weekday), got {}", fields.len()));
+        };
+        let weekdays = parse_field(weekday, "weekday", 0, 7)?;
+        Ok(Self {
+            minutes: parse_field(minute, "minute", 0, 59)?,
+            hours: parse_field(hour, "hour", 0, 23)? as u32,
+            days: parse_field(day, "day", 1, 31)? as u32,
+            months: parse_field(month, "month", 1, 12)? as u16,
+            // Fold 7 onto Sunday.
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            either_day: day != "*" && weekday != "*",
+        })
+    }
+}
+
+/// Parse one field into a bitmask of the values it allows.
+fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("{}: bad step '{}'", name, step))?;
+                if step == 0 {
+                    return Err(format!("{}: step must be at least 1", name));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let value = |text: &str| -> Result<u32, String> {
+            let n: u32 = text.parse().map_err(|_| format!("{}: bad value '{}'", name, text))?;
+            if n < min || n > max {
+                return Err(format!("{}: {} is out of range {}-{}", name, n, min, max));
+            }
+            Ok(n)
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                // `5/15` runs from 5 to the end of the range.
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("{}: range {}-{} runs backwards", name, start, end));
+        }
+        for n in (start..=end).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+impl CronExpr {
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.either_day { day || weekday } else { day && weekday }
+    }
+
+    /// The first time after `t` (in the schedule's local time) the
+    /// expression matches, or None if it never does.
+    pub fn next_after(&self, t: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = t.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+        let horizon = t + TimeDelta::days(HORIZON_DAYS);
+        while t < horizon {
+            if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.with_minute(0)? + TimeDelta::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += TimeDelta::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+/// How many times a schedule should run now, given it was last taken care
+/// of at `since`. None if nothing fell due since then; Some(0) if runs fell
+/// due but `policy` drops them.
+pub fn runs_due(expr: &CronExpr, since: NaiveDateTime, now: NaiveDateTime, policy: CatchUp) -> Option<usize> {
+    let mut missed = 0;
+    let mut latest = None;
+    let mut t = since;
+    while let Some(next) = expr.next_after(t).filter(|next| *next <= now) {
+        missed += 1;
+        latest = Some(next);
+        t = next;
+        if missed == MAX_CATCH_UP {
+            break;
+        }
+    }
+    let latest = latest?;
+    Some(match policy {
+        CatchUp::Skip => usize::from(now - latest < GRACE),
+        CatchUp::Once => 1,
+        CatchUp::All => missed,
+    })
+}
+
+/// A scheduled command to run now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DueRun {
+    /// The run as recorded in the store, for [`Store::finish_schedule_run`].
+    pub run_id: i64,
+    pub schedule_id: i64,
+    pub command: String,
+    pub cwd: String,
+}
+
+/// Claim the runs that have fallen due by `now` and record them as
+/// started. A schedule is claimed in the store before it runs, so two
+/// windows polling at once don't both run it.
+pub fn take_due(store: &Store, policy: CatchUp, now: DateTime<Local>) -> anyhow::Result<Vec<DueRun>> {
+    let mut due = Vec::new();
+    for schedule in store.schedules()? {
+        let expr: CronExpr = match schedule.expr.parse() {
+            Ok(expr) => expr,
+            Err(e) => {
+                tracing::warn!("schedule #{}: {}", schedule.id, e);
+                continue;
+            }
+        };
+        let since = schedule.last_run_at.unwrap_or(schedule.created_at).with_timezone(&Local);
+        let Some(runs) = runs_due(&expr, since.naive_local(), now.naive_local(), policy) else {
+            continue;
+        };
+        if !store.claim_schedule(schedule.id, schedule.last_run_at, now.with_timezone(&Utc))? {
+            continue;
+        }
+        for _ in 0..runs {
+            due.push(DueRun {
+                run_id: store.start_schedule_run(schedule.id)?,
+                schedule_id: schedule.id,
+                command: schedule.command.clone(),
+                cwd: schedule.cwd.clone(),
+            });
+        }
+    }
+    Ok(due)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!("0 9 * * 1-5".parse::<CronExpr>().is_ok());
+        assert!("*/15 0-6,22 1 */2 7".parse::<CronExpr>().is_ok());
+        assert_eq!("@daily".parse::<CronExpr>(), "0 0 * * *".parse::<CronExpr>());
+        assert_eq!("0 0 * * 7".parse::<CronExpr>(), "0 0 * * 0".parse::<CronExpr>());
+        assert!("0 9 * *".parse::<CronExpr>().unwrap_err().contains("5 fields"));
+        assert!("60 * * * *".parse::<CronExpr>().unwrap_err().contains("out of range"));
+        assert!("0 5-1 * * *".parse::<CronExpr>().is_err());
+        assert!("*/0 * * * *".parse::<CronExpr>().is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 2024-03-01 is a Friday.
+        let weekdays: CronExpr = "0 9 * * 1-5".parse().unwrap();
+        assert_eq!(weekdays.next_after(at("2024-03-01 08:30")), Some(at("2024-03-01 09:00")));
+        assert_eq!(weekdays.next_after(at("2024-03-01 09:00")), Some(at("2024-03-04 09:00")));
+
+        let quarter: CronExpr = "*/15 * * * *".parse().unwrap();
+        assert_eq!(quarter.next_after(at("2024-03-01 23:50")), Some(at("2024-03-02 00:00")));
+
+        // Day of month and day of week both set: either matches.
+        let either: CronExpr = "0 0 13 * 5".parse().unwrap();
+        assert_eq!(either.next_after(at("2024-03-02 00:00")), Some(at("2024-03-08 00:00")));
+
+        assert_eq!("0 0 29 2 *".parse::<CronExpr>().unwrap().next_after(at("2024-03-01 00:00")), Some(at("2028-02-29 00:00")));
+        assert_eq!("0 0 31 2 *".parse::<CronExpr>().unwrap().next_after(at("2024-03-01 00:00")), None);
+    }
+
+    #[test]
+    fn test_runs_due_by_policy() {
+        let hourly: CronExpr = "@hourly".parse().unwrap();
+        let since = at("2024-03-01 08:30");
+        assert_eq!(runs_due(&hourly, since, at("2024-03-01 08:59"), CatchUp::Once), None);
+
+        let now = at("2024-03-01 12:01");
+        assert_eq!(runs_due(&hourly, since, now, CatchUp::All), Some(4));
+        assert_eq!(runs_due(&hourly, since, now, CatchUp::Once), Some(1));
+        assert_eq!(runs_due(&hourly, since, now, CatchUp::Skip), Some(1));
+        assert_eq!(runs_due(&hourly, since, at("2024-03-01 12:30"), CatchUp::Skip), Some(0));
+        assert_eq!(runs_due(&hourly, at("2024-01-01 00:00"), now, CatchUp::All), Some(MAX_CATCH_UP));
+    }
+
+    #[test]
+    fn test_take_due_claims_once() {
+        let store = Store::open_in_memory().unwrap();
+        let id = store.add_schedule("* * * * *", "date", "/tmp").unwrap();
+        let later = Local::now() + TimeDelta::minutes(3);
+
+        let due = take_due(&store, CatchUp::All, later).unwrap();
+        assert!(!due.is_empty());
+        assert!(due.iter().all(|run| run.schedule_id == id && run.command == "date" && run.cwd == "/tmp"));
+        assert_eq!(store.schedule_runs(id, 10).unwrap().len(), due.len());
+
+        // Claimed up to `later`: nothing is due again then.
+        assert!(take_due(&store, CatchUp::All, later).unwrap().is_empty());
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Reads from and writes to the user's native shell history file (~/.zsh_history,
 //! ~/.bash_history) so Nexus shares history with regular shell sessions.
+//! Shells whose format Nexus can't share (fish) use ~/.nexus/history, which
+//! onboarding can seed from the shell's own history.
 
 use std::fs::{File, OpenOptions};
 use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
@@ -17,6 +19,7 @@ use std::time::{SystemTime, UNIX_EPOCH};
 pub enum ShellKind {
     Zsh,
     Bash,
+    Fish,
     Unknown,
 }
 
@@ -131,6 +134,23 @@ impl ShellHistory {
         self.entries.push(command.to_string());
     }
 
+    /// Append many commands at once (history import), skipping any already
+    /// present. Returns how many were added.
+    pub fn import(&mut self, commands: &[String]) -> io::Result<usize> {
+        let known: std::collections::HashSet<&str> = self.entries.iter().map(String::as_str).collect();
+        let mut added = Vec::new();
+        for command in commands.iter().map(|c| c.trim()) {
+            if !command.is_empty() && !known.contains(command) && added.last().map(String::as_str) != Some(command) {
+                added.push(command.to_string());
+            }
+        }
+        let text: String = added.iter().map(|c| format_entry(c, self.format)).collect();
+        append_to_file(&self.path, &text)?;
+        let count = added.len();
+        self.entries.extend(added);
+        Ok(count)
+    }
+
     /// Get the detected format.
     pub fn format(&self) -> HistoryFormat {
         self.format
@@ -184,7 +204,7 @@ fn detect_shell_and_path() -> Option<(ShellKind, PathBuf)> {
                 return Some((shell, p));
             }
         }
-        ShellKind::Unknown => {}
+        ShellKind::Fish | ShellKind::Unknown => {}
     }
 
     // Last resort: try both
@@ -200,10 +220,16 @@ fn detect_shell_and_path() -> Option<(ShellKind, PathBuf)> {
         }
     }
 
-    None
+    let p = nexus_history_path(&home);
+    p.exists().then_some((shell, p))
 }
 
-fn detect_shell_kind() -> ShellKind {
+/// `~/.nexus/history`, used when there is no zsh or bash history to share.
+pub fn nexus_history_path(home: &Path) -> PathBuf {
+    home.join(".nexus").join("history")
+}
+
+pub(crate) fn detect_shell_kind() -> ShellKind {
     if let Ok(shell) = std::env::var("SHELL") {
         let basename = Path::new(&shell)
             .file_name()
@@ -212,6 +238,7 @@ fn detect_shell_kind() -> ShellKind {
         match basename {
             "zsh" => ShellKind::Zsh,
             "bash" => ShellKind::Bash,
+            "fish" => ShellKind::Fish,
             _ => ShellKind::Unknown,
         }
     } else {
@@ -219,6 +246,13 @@ fn detect_shell_kind() -> ShellKind {
     }
 }
 
+/// The last 100,000 commands in the zsh or bash history file at `path`,
+/// oldest first.
+pub(crate) fn read_history_file(path: &Path) -> Vec<String> {
+    let format = detect_format(path).unwrap_or(HistoryFormat::Plain);
+    read_tail(path, 100_000, format).unwrap_or_default()
+}
+
 // =========================================================================
 // Format detection
 // =========================================================================
@@ -759,4 +793,15 @@ mod tests {
         assert_eq!(hist2.entries()[1], "if true; then\necho yes\nfi");
         assert_eq!(hist2.entries()[2], "git commit -m 'test'");
     }
+
+    #[test]
+    fn test_import_skips_known_commands() {
+        let (_dir, path) = write_temp_file("ls\ngit status\n");
+        let mut hist = ShellHistory::open_path(path.clone(), HistoryFormat::Plain);
+        let commands = ["git status", "make", "make", "", "cargo test"].map(String::from);
+        assert_eq!(hist.import(&commands).unwrap(), 2);
+
+        let hist2 = ShellHistory::open_path(path, HistoryFormat::Plain);
+        assert_eq!(hist2.entries(), &["ls", "git status", "make", "cargo test"]);
+    }
 }
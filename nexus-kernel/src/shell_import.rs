@@ -0,0 +1,623 @@
+//! Import settings from the shell the user is switching from.
+//!
+//! The rc files of zsh, bash or fish are read line by line — never run —
+//! for what has a `~/.nexus/config.toml` equivalent: aliases, exported
+//! variables and `PATH` additions. Lines that would need evaluating
+//! (command substitution, pipelines, anything inside a conditional or a
+//! function) are left out and listed, so the review shows what didn't come
+//! across.
+//!
+//! The shell's history can be imported too when Nexus doesn't already
+//! record to the same file.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use nexus_api::{DiffFileInfo, GitChangeType};
+
+use crate::commands::diff::diff_hunks;
+use crate::config::{self, Config, Origin};
+use crate::shell_history::{self, ShellKind};
+
+/// What was found in a shell's rc files.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ShellImport {
+    /// Files read, in the order the shell reads them.
+    pub files: Vec<PathBuf>,
+    pub aliases: BTreeMap<String, String>,
+    pub env: BTreeMap<String, String>,
+    pub path_prepend: Vec<String>,
+    pub path_append: Vec<String>,
+    /// Alias, export and PATH lines that could not be imported.
+    pub skipped: Vec<String>,
+}
+
+/// The shell the user is switching from: `$SHELL`, or else the first one
+/// with rc files in `home`.
+pub fn detect_shell(home: &Path) -> ShellKind {
+    match shell_history::detect_shell_kind() {
+        ShellKind::Unknown => [ShellKind::Zsh, ShellKind::Bash, ShellKind::Fish]
+            .into_iter()
+            .find(|shell| rc_files(*shell, home).iter().any(|p| p.is_file()))
+            .unwrap_or(ShellKind::Unknown),
+        shell => shell,
+    }
+}
+
+/// Startup files `shell` reads, in order.
+pub fn rc_files(shell: ShellKind, home: &Path) -> Vec<PathBuf> {
+    let names: &[&str] = match shell {
+        ShellKind::Zsh => &[".zshenv", ".zprofile", ".zshrc"],
+        ShellKind::Bash => &[".profile", ".bash_profile", ".bashrc"],
+        ShellKind::Fish => &[".config/fish/config.fish"],
+        ShellKind::Unknown => &[],
+    };
+    names.iter().map(|name| home.join(name)).collect()
+}
+
+/// The history file `shell` keeps in `home`.
+pub fn history_file(shell: ShellKind, home: &Path) -> Option<PathBuf> {
+    let path = match shell {
+        ShellKind::Zsh | ShellKind::Bash => match std::env::var_os("HISTFILE") {
+            Some(file) => PathBuf::from(file),
+            None if shell == ShellKind::Zsh => home.join(".zsh_history"),
+            None => home.join(".bash_history"),
+        },
+        ShellKind::Fish => std::env::var_os("XDG_DATA_HOME")
+            .map_or_else(|| home.join(".local/share"), PathBuf::from)
+            .join("fish/fish_history"),
+        ShellKind::Unknown => return None,
+    };
+    path.is_file().then_some(path)
+}
+
+/// Commands in `shell`'s history, oldest first, unless `recording_to` (the
+/// file Nexus records history to) is that same file.
+pub fn history_to_import(shell: ShellKind, home: &Path, recording_to: Option<&Path>) -> Vec<String> {
+    let Some(path) = history_file(shell, home) else {
+        return Vec::new();
+    };
+    if recording_to.is_some_and(|to| same_file(to, &path)) {
+        return Vec::new();
+    }
+    match shell {
+        ShellKind::Fish => std::fs::read_to_string(&path).map(|text| parse_fish_history(&text)).unwrap_or_default(),
+        _ => shell_history::read_history_file(&path),
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Commands from fish's `- cmd: ...` history entries, which escape
+/// newlines as `\n` and backslashes as `\\`.
+fn parse_fish_history(text: &str) -> Vec<String> {
+    let unescape = |cmd: &str| {
+        let mut out = String::with_capacity(cmd.len());
+        let mut chars = cmd.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('n')) => {
+                    out.push('\n');
+                    chars.next();
+                }
+                ('\\', Some('\\')) => {
+                    out.push('\\');
+                    chars.next();
+                }
+                (c, _) => out.push(c),
+            }
+        }
+        out
+    };
+    text.lines().filter_map(|line| line.strip_prefix("- cmd: ")).map(unescape).collect()
+}
+
+impl ShellImport {
+    /// Read `shell`'s rc files in `home`.
+    pub fn scan(shell: ShellKind, home: &Path) -> Self {
+        let mut import = Self::default();
+        for path in rc_files(shell, home) {
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                import.read(shell, &text);
+                import.files.push(path);
+            }
+        }
+        import
+    }
+
+    /// Pick the importable lines out of one rc file.
+    pub fn read(&mut self, shell: ShellKind, text: &str) {
+        let mut depth = 0usize;
+        // sh variables assigned but not (yet) exported.
+        let mut assigned = BTreeMap::new();
+        for line in logical_lines(text) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (opened, closed) = block_keywords(shell, line);
+            let nested = depth > 0 || opened > 0;
+            depth = (depth + opened).saturating_sub(closed);
+
+            let Some(kind) = line.split_whitespace().next().and_then(|w| statement(shell, w)) else {
+                continue;
+            };
+            let imported = !nested
+                && split_words(line).is_some_and(|words| match shell {
+                    ShellKind::Fish => self.read_fish(kind, &words),
+                    _ => self.read_sh(kind, &words, &mut assigned),
+                });
+            if !imported {
+                self.skipped.push(line.to_string());
+            }
+        }
+    }
+
+    fn read_sh(&mut self, kind: Statement, words: &[String], assigned: &mut BTreeMap<String, String>) -> bool {
+        match kind {
+            Statement::Alias => {
+                // Global (`-g`) and suffix (`-s`) aliases have no equivalent.
+                if words[1..].iter().any(|w| w.starts_with('-') && w != "--") {
+                    return false;
+                }
+                let mut ok = true;
+                for (name, value) in words[1..].iter().filter_map(|w| w.split_once('=')) {
+                    ok &= self.add_alias(name, value);
+                }
+                ok
+            }
+            Statement::Export => {
+                let exported = match words[0].as_str() {
+                    "export" => true,
+                    _ => words[1..].iter().any(|w| w.starts_with('-') && w.contains('x')),
+                };
+                if !exported {
+                    // `declare`/`typeset` of a shell-only variable.
+                    return true;
+                }
+                let mut ok = true;
+                for arg in words[1..].iter().filter(|w| !w.starts_with('-')) {
+                    match arg.split_once('=') {
+                        Some((name, value)) if is_name(name) => self.set_var(name, value),
+                        None if is_name(arg) => {
+                            if let Some(value) = assigned.remove(arg.as_str()) {
+                                self.set_var(arg, &value);
+                            }
+                        }
+                        _ => ok = false,
+                    }
+                }
+                ok
+            }
+            Statement::Assign => {
+                // `NAME=value command` only sets it for that command.
+                let [word] = words else {
+                    return false;
+                };
+                if let Some(list) = word.strip_prefix("path=(").or_else(|| word.strip_prefix("path+=(")) {
+                    let list = list.strip_suffix(')').unwrap_or(list);
+                    let entries: Vec<&str> = list.split_whitespace().collect();
+                    let entries = if word.starts_with("path+=") { [&["$path"], &entries[..]].concat() } else { entries };
+                    self.add_path(&entries);
+                    return true;
+                }
+                let Some((name, value)) = word.split_once('=') else {
+                    return false;
+                };
+                if name == "PATH" || self.env.contains_key(name) {
+                    self.set_var(name, value);
+                } else {
+                    assigned.insert(name.to_string(), value.to_string());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn read_fish(&mut self, kind: Statement, words: &[String]) -> bool {
+        let args: Vec<&str> = words[1..].iter().map(String::as_str).collect();
+        let (flags, rest): (Vec<&str>, Vec<&str>) = match args.iter().position(|a| !a.starts_with('-')) {
+            Some(i) => (args[..i].to_vec(), args[i..].to_vec()),
+            None => (args.clone(), Vec::new()),
+        };
+        match kind {
+            Statement::Alias => match rest.as_slice() {
+                [definition] => definition.split_once('=').is_some_and(|(name, value)| self.add_alias(name, value)),
+                [name, value @ ..] if !value.is_empty() => self.add_alias(name, &value.join(" ")),
+                _ => false,
+            },
+            Statement::Abbr => {
+                let known = ["-a", "--add", "-g", "--global", "-U", "--universal"];
+                match rest.as_slice() {
+                    [name, value @ ..] if !value.is_empty() && flags.iter().all(|f| known.contains(f)) => {
+                        self.add_alias(name, &value.join(" "))
+                    }
+                    _ => false,
+                }
+            }
+            Statement::Set => {
+                let [name, values @ ..] = rest.as_slice() else {
+                    return false;
+                };
+                let short = |c| flags.iter().any(|f| !f.starts_with("--") && f.contains(c));
+                if short('e') || short('q') || flags.contains(&"--erase") {
+                    return false;
+                }
+                if matches!(*name, "PATH" | "fish_user_paths") {
+                    let marker = format!("${}", name);
+                    let entries: Vec<&str> = values.iter().map(|v| if *v == marker { "$PATH" } else { v }).collect();
+                    self.add_path(&entries);
+                    return true;
+                }
+                if !(short('x') || flags.contains(&"--export")) {
+                    // A fish-only variable; nothing to carry over.
+                    return true;
+                }
+                if !is_name(name) {
+                    return false;
+                }
+                let separator = if name.ends_with("PATH") { ":" } else { " " };
+                self.set_var(name, &values.join(separator));
+                true
+            }
+            Statement::AddPath => {
+                let append = flags.iter().any(|f| matches!(*f, "-a" | "--append"));
+                if append {
+                    self.add_path(&[&["$PATH"], &rest[..]].concat());
+                } else {
+                    self.add_path(&[&rest[..], &["$PATH"]].concat());
+                }
+                !rest.is_empty()
+            }
+            _ => false,
+        }
+    }
+
+    fn add_alias(&mut self, name: &str, value: &str) -> bool {
+        // Config keys are dotted paths, so a dot can't be part of a name.
+        if name.is_empty() || name.contains('.') {
+            return false;
+        }
+        self.aliases.insert(name.to_string(), value.to_string());
+        true
+    }
+
+    fn set_var(&mut self, name: &str, value: &str) {
+        if name == "PATH" {
+            self.add_path(&value.split(':').collect::<Vec<_>>());
+        } else {
+            self.env.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    /// Record a new `PATH` given as entries, where `$PATH` (or zsh's
+    /// `$path`) stands for the old one. Without it, every entry counts as
+    /// prepended.
+    fn add_path(&mut self, entries: &[&str]) {
+        let is_old = |e: &&str| matches!(*e, "$PATH" | "${PATH}" | "$path" | "${path[@]}");
+        let split = entries.iter().position(is_old).unwrap_or(entries.len());
+        let keep = |e: &&&str| !e.is_empty() && !is_old(e);
+        let front: Vec<String> = entries[..split].iter().filter(keep).map(|e| e.to_string()).collect();
+        let back: Vec<String> = entries[split..].iter().filter(keep).map(|e| e.to_string()).collect();
+
+        // A later line's additions end up in front of (or after) earlier ones.
+        self.path_prepend.retain(|e| !front.contains(e));
+        self.path_prepend.splice(0..0, front);
+        self.path_append.retain(|e| !back.contains(e));
+        self.path_append.extend(back);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty() && self.env.is_empty() && self.path_prepend.is_empty() && self.path_append.is_empty()
+    }
+
+    /// The user-config settings to write, leaving out what `config`
+    /// already has there.
+    pub fn settings(&self, config: &Config) -> Vec<(String, toml_edit::Value)> {
+        let user = |s: &config::Setting<String>| matches!(s.origin, Origin::User(_));
+        let mut settings = Vec::new();
+        for (section, imported, current) in [("aliases", &self.aliases, &config.aliases), ("env", &self.env, &config.env)] {
+            for (name, value) in imported {
+                if !current.get(name).is_some_and(|s| user(s) && &s.value == value) {
+                    settings.push((format!("{}.{}", section, name), value.as_str().into()));
+                }
+            }
+        }
+        for (key, imported, current) in [
+            ("path.prepend", &self.path_prepend, &config.path_prepend),
+            ("path.append", &self.path_append, &config.path_append),
+        ] {
+            let mut list: Vec<&str> = current.iter().map(|s| s.value.as_str()).collect();
+            let before = list.len();
+            list.extend(imported.iter().map(String::as_str).filter(|e| !current.iter().any(|s| s.value == *e)));
+            if list.len() > before {
+                settings.push((key.to_string(), toml_edit::Value::from_iter(list)));
+            }
+        }
+        settings
+    }
+
+    /// How importing would change the config file at `path`, or `None`
+    /// when it wouldn't.
+    pub fn diff(&self, config: &Config, path: &Path) -> anyhow::Result<Option<DiffFileInfo>> {
+        let (before, after) = config::preview_settings(path, self.settings(config))?;
+        let (hunks, additions, deletions) = diff_hunks(&before, &after, 3);
+        if hunks.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(DiffFileInfo {
+            file_path: path.display().to_string(),
+            old_path: None,
+            change_type: if before.is_empty() { GitChangeType::Added } else { GitChangeType::Modified },
+            hunks,
+            additions,
+            deletions,
+        }))
+    }
+
+    /// Write the settings to the config file at `path`. Returns how many
+    /// were written.
+    pub fn apply(&self, config: &Config, path: &Path) -> anyhow::Result<usize> {
+        let settings = self.settings(config);
+        let count = settings.len();
+        if count > 0 {
+            config::set_settings(path, settings)?;
+        }
+        Ok(count)
+    }
+}
+
+/// Lines with backslash-newline continuations joined.
+fn logical_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in text.lines() {
+        match line.strip_suffix('\\') {
+            Some(head) => current.push_str(head),
+            None => {
+                current.push_str(line);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// The forms of statement the importer looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Statement {
+    Alias,
+    Export,
+    Assign,
+    Abbr,
+    Set,
+    AddPath,
+}
+
+fn statement(shell: ShellKind, first: &str) -> Option<Statement> {
+    match (shell, first) {
+        (_, "alias") => Some(Statement::Alias),
+        (ShellKind::Fish, "abbr") => Some(Statement::Abbr),
+        (ShellKind::Fish, "set") => Some(Statement::Set),
+        (ShellKind::Fish, "fish_add_path") => Some(Statement::AddPath),
+        (ShellKind::Fish, _) => None,
+        (_, "export" | "declare" | "typeset") => Some(Statement::Export),
+        (_, word) => {
+            let name = word.split_once('=').map(|(n, _)| n.strip_suffix('+').unwrap_or(n))?;
+            is_name(name).then_some(Statement::Assign)
+        }
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// How many blocks (conditionals, loops, functions) `line` opens and closes.
+fn block_keywords(shell: ShellKind, line: &str) -> (usize, usize) {
+    let (mut opened, mut closed) = (0, 0);
+    for statement in line.split([';', '&', '|']) {
+        let words: Vec<&str> = statement.split_whitespace().collect();
+        let Some(first) = words.first() else {
+            continue;
+        };
+        match shell {
+            ShellKind::Fish => match *first {
+                "if" | "for" | "while" | "function" | "switch" | "begin" => opened += 1,
+                "end" => closed += 1,
+                _ => {}
+            },
+            _ => {
+                match *first {
+                    "if" | "for" | "while" | "until" | "case" | "select" => opened += 1,
+                    "fi" | "done" | "esac" => closed += 1,
+                    _ => {}
+                }
+                opened += words.iter().filter(|w| **w == "{" || w.ends_with("(){")).count();
+                closed += words.iter().filter(|w| **w == "}").count();
+            }
+        }
+    }
+    (opened, closed)
+}
+
+/// Split a line into words with quotes removed, or `None` if it does
+/// anything beyond plain words: command substitution, pipes, redirection,
+/// several commands. A `#` starting a word ends the line.
+fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), c) => current.push(c),
+            (Some(_), '"') => quote = None,
+            (Some(_), '\\') => match chars.next() {
+                Some(next @ ('$' | '`' | '"' | '\\')) => current.push(next),
+                Some(next) => {
+                    current.push('\\');
+                    current.push(next);
+                }
+                None => current.push('\\'),
+            },
+            (_, '`') => return None,
+            (_, '$') if chars.peek() == Some(&'(') => return None,
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                current.extend(chars.next());
+                in_word = true;
+            }
+            (None, '#') if !in_word => break,
+            (None, ';' | '|' | '&' | '<' | '>') => return None,
+            // zsh `path=(...)` arrays stay one word; other parentheses are
+            // subshells or fish command substitution.
+            (None, '(') if current.ends_with("path=") || current.ends_with("path+=") => {
+                let rest: String = chars.by_ref().take_while(|c| *c != ')').collect();
+                current.push('(');
+                current.push_str(&rest);
+                current.push(')');
+            }
+            (None, '(' | ')') => return None,
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return None;
+    }
+    if in_word {
+        words.push(current);
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(shell: ShellKind, text: &str) -> ShellImport {
+        let mut import = ShellImport::default();
+        import.read(shell, text);
+        import
+    }
+
+    #[test]
+    fn test_zsh_rc() {
+        let import = read(
+            ShellKind::Zsh,
+            r#"
+# aliases
+alias ll='ls -la' gs="git status"
+alias -g G='| grep'
+export EDITOR=nvim
+GOPATH=$HOME/go
+export GOPATH
+export PATH="$HOME/.cargo/bin:$PATH"
+path+=(/opt/tools/bin)
+export NVM_DIR="$(brew --prefix nvm)"
+if [[ -f ~/.work ]]; then
+  export WORK=1
+fi
+PS1='%~ $ '
+"#,
+        );
+        assert_eq!(import.aliases["ll"], "ls -la");
+        assert_eq!(import.aliases["gs"], "git status");
+        assert_eq!(import.env["EDITOR"], "nvim");
+        assert_eq!(import.env["GOPATH"], "$HOME/go");
+        assert!(!import.env.contains_key("PS1"));
+        assert_eq!(import.path_prepend, ["$HOME/.cargo/bin"]);
+        assert_eq!(import.path_append, ["/opt/tools/bin"]);
+        assert_eq!(
+            import.skipped,
+            ["alias -g G='| grep'", "export NVM_DIR=\"$(brew --prefix nvm)\"", "export WORK=1"]
+        );
+    }
+
+    #[test]
+    fn test_fish_config() {
+        let import = read(
+            ShellKind::Fish,
+            r#"
+alias gco 'git checkout'
+abbr -a gp git push
+set -gx EDITOR hx
+set -x GOPATH ~/go
+set fish_greeting
+fish_add_path ~/.local/bin
+set -gx PATH /usr/local/sbin $PATH
+function fish_prompt
+    set -gx INSIDE 1
+end
+"#,
+        );
+        assert_eq!(import.aliases["gco"], "git checkout");
+        assert_eq!(import.aliases["gp"], "git push");
+        assert_eq!(import.env["EDITOR"], "hx");
+        assert_eq!(import.env["GOPATH"], "~/go");
+        assert_eq!(import.path_prepend, ["/usr/local/sbin", "~/.local/bin"]);
+        assert_eq!(import.skipped, ["set -gx INSIDE 1"]);
+    }
+
+    #[test]
+    fn test_settings_skip_what_config_has_and_diff_previews() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[aliases]\nll = \"ls -la\"\n[path]\nprepend = [\"~/bin\"]\n").unwrap();
+        let config = Config {
+            aliases: BTreeMap::from([(
+                "ll".to_string(),
+                config::Setting { value: "ls -la".to_string(), origin: Origin::User(path.clone()) },
+            )]),
+            path_prepend: vec![config::Setting { value: "~/bin".to_string(), origin: Origin::User(path.clone()) }],
+            ..Config::default()
+        };
+
+        let import = read(ShellKind::Bash, "alias ll='ls -la'\nalias la='ls -A'\nexport PATH=~/bin:/opt/bin:$PATH\n");
+        let keys: Vec<String> = import.settings(&config).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["aliases.la", "path.prepend"]);
+
+        let diff = import.diff(&config, &path).unwrap().unwrap();
+        assert_eq!(diff.additions, 2);
+        assert_eq!(diff.deletions, 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 4);
+
+        assert_eq!(import.apply(&config, &path).unwrap(), 2);
+        let loaded = Config::load_layers(Some(&path), Path::new("/"));
+        assert_eq!(loaded.alias("la"), Some("ls -A"));
+        let prepend: Vec<&str> = loaded.path_prepend.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(prepend, ["~/bin", "/opt/bin"]);
+    }
+
+    #[test]
+    fn test_parse_fish_history() {
+        let text = "- cmd: ls\n  when: 1700000000\n- cmd: echo a\\\\nb\n  when: 1700000001\n  paths:\n    - a\n";
+        assert_eq!(parse_fish_history(text), ["ls", "echo a\\nb"]);
+    }
+}
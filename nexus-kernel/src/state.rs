@@ -2,12 +2,15 @@
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicU64, Ordering};
 
-use nexus_api::{BlockId, Value};
+use nexus_api::{BlockId, BlockIdAllocator, Value};
+use nix::unistd::Pid;
 
+use crate::dev_env::ActiveEnv;
+use crate::config::Config;
 use crate::parser::FunctionDef;
 use crate::process::Job;
+use crate::profile::Profile;
 
 /// Stored output from a command block.
 #[derive(Debug, Clone)]
@@ -33,12 +36,9 @@ pub enum TrapAction {
     Command(String),
 }
 
-/// Counter for generating unique block IDs.
-static BLOCK_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
-
 /// Generate a new unique block ID.
 pub fn next_block_id() -> BlockId {
-    BlockId(BLOCK_ID_COUNTER.fetch_add(1, Ordering::SeqCst))
+    BlockIdAllocator::global().reserve()
 }
 
 /// Get the current block ID for this execution context.
@@ -81,6 +81,16 @@ pub struct ShellState {
     /// Shell aliases (name -> expansion).
     pub aliases: HashMap<String, String>,
 
+    /// Aliases read from the user's zsh, bash or fish startup files, used
+    /// for names neither `alias` nor configuration defines.
+    pub rc_aliases: HashMap<String, String>,
+
+    /// User and project configuration for `cwd`. Empty unless enabled.
+    pub config: Config,
+
+    /// Whether `config` is loaded and follows `cwd`.
+    config_enabled: bool,
+
     /// Read-only variables (cannot be unset or modified).
     pub readonly_vars: HashSet<String>,
 
@@ -93,6 +103,10 @@ pub struct ShellState {
     /// Signal traps (signal number -> action).
     pub traps: HashMap<i32, TrapAction>,
 
+    /// Conditions being run (`if`, `while`, the left of `&&`/`||`); a
+    /// failure inside one doesn't fire the ERR trap.
+    pub conditions: u32,
+
     /// Cached command paths (for hash builtin).
     pub command_hash: HashMap<String, PathBuf>,
 
@@ -113,10 +127,19 @@ pub struct ShellState {
 
     /// Maximum number of block outputs to retain
     pub max_block_outputs: usize,
+
+    /// Outputs stored so far, counting those since trimmed.
+    outputs_stored: u64,
+
+    /// Timings being collected by `profile`.
+    pub profile: Option<Profile>,
+
+    /// Project environments activated (see [`crate::dev_env`]).
+    pub dev_envs: Vec<ActiveEnv>,
 }
 
 /// Shell options controlled by `set` builtin.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ShellOptions {
     /// -e: Exit on error.
     pub errexit: bool,
@@ -138,6 +161,38 @@ pub struct ShellOptions {
     pub notify: bool,
     /// -h: Remember command locations.
     pub hashall: bool,
+    /// -o pipefail: A pipeline fails with its rightmost failing stage.
+    pub pipefail: bool,
+    /// shopt dotglob: globs match names starting with `.`.
+    pub dotglob: bool,
+    /// shopt nullglob: a glob that matches nothing expands to no words.
+    pub nullglob: bool,
+    /// shopt globstar: `**` matches files and directories at any depth.
+    pub globstar: bool,
+    /// shopt extglob: `?(..)`, `*(..)`, `+(..)`, `@(..)` and `!(..)`.
+    pub extglob: bool,
+}
+
+/// The configuration half of [`ShellState`], saved before each supervised
+/// command so a panicking command can be rolled back.
+///
+/// Outputs (`last_output`, `block_outputs`, `rich_vars`) and jobs are left
+/// out: they are cheap to keep but expensive to copy, and are only written
+/// once a command has produced its result or spawned its process.
+#[derive(Debug, Clone)]
+pub struct StateCheckpoint {
+    env: HashMap<String, String>,
+    vars: HashMap<String, String>,
+    cwd: PathBuf,
+    aliases: HashMap<String, String>,
+    readonly_vars: HashSet<String>,
+    positional_params: Vec<String>,
+    options: ShellOptions,
+    traps: HashMap<i32, TrapAction>,
+    functions: HashMap<String, FunctionDef>,
+    local_scopes: Vec<HashMap<String, String>>,
+    last_exit_code: i32,
+    dev_envs: Vec<ActiveEnv>,
 }
 
 impl ShellState {
@@ -157,16 +212,23 @@ impl ShellState {
             last_exit_code: 0,
             last_bg_pid: None,
             aliases: HashMap::new(),
+            rc_aliases: HashMap::new(),
+            config: Config::default(),
+            config_enabled: false,
             readonly_vars: HashSet::new(),
             positional_params: Vec::new(),
             options: ShellOptions::default(),
             traps: HashMap::new(),
+            conditions: 0,
             command_hash: HashMap::new(),
             functions: HashMap::new(),
             local_scopes: Vec::new(),
             last_output: None,
             block_outputs: VecDeque::new(),
             max_block_outputs: 100, // Keep last 100 outputs
+            outputs_stored: 0,
+            profile: None,
+            dev_envs: Vec::new(),
         })
     }
 
@@ -186,16 +248,57 @@ impl ShellState {
             last_exit_code: 0,
             last_bg_pid: None,
             aliases: HashMap::new(),
+            rc_aliases: HashMap::new(),
+            config: Config::default(),
+            config_enabled: false,
             readonly_vars: HashSet::new(),
             positional_params: Vec::new(),
             options: ShellOptions::default(),
             traps: HashMap::new(),
+            conditions: 0,
             command_hash: HashMap::new(),
             functions: HashMap::new(),
             local_scopes: Vec::new(),
             last_output: None,
             block_outputs: VecDeque::new(),
             max_block_outputs: 100,
+            outputs_stored: 0,
+            profile: None,
+            dev_envs: Vec::new(),
+        }
+    }
+
+    /// A copy of this state for running one command beside the session:
+    /// everything but the job table, which stays with the session.
+    pub fn fork(&self) -> Self {
+        Self {
+            env: self.env.clone(),
+            vars: self.vars.clone(),
+            rich_vars: self.rich_vars.clone(),
+            cwd: self.cwd.clone(),
+            jobs: Vec::new(),
+            next_job_id: self.next_job_id,
+            interactive: self.interactive,
+            last_exit_code: self.last_exit_code,
+            last_bg_pid: self.last_bg_pid,
+            aliases: self.aliases.clone(),
+            rc_aliases: self.rc_aliases.clone(),
+            config: self.config.clone(),
+            config_enabled: self.config_enabled,
+            readonly_vars: self.readonly_vars.clone(),
+            positional_params: self.positional_params.clone(),
+            options: self.options.clone(),
+            traps: self.traps.clone(),
+            conditions: self.conditions,
+            command_hash: self.command_hash.clone(),
+            functions: self.functions.clone(),
+            local_scopes: self.local_scopes.clone(),
+            last_output: self.last_output.clone(),
+            block_outputs: self.block_outputs.clone(),
+            max_block_outputs: self.max_block_outputs,
+            outputs_stored: self.outputs_stored,
+            profile: None,
+            dev_envs: self.dev_envs.clone(),
         }
     }
 
@@ -283,7 +386,8 @@ impl ShellState {
     /// Only updates the kernel's internal CWD — does NOT call
     /// `std::env::set_current_dir()`. The process-level CWD is
     /// meaningless in multi-window mode; child processes receive
-    /// the correct CWD via fork/exec setup.
+    /// the correct CWD via fork/exec setup. Project environments left
+    /// behind are deactivated (see [`crate::dev_env`]).
     pub fn set_cwd(&mut self, path: PathBuf) -> std::io::Result<()> {
         if !path.is_dir() {
             return Err(std::io::Error::new(
@@ -292,9 +396,109 @@ impl ShellState {
             ));
         }
         self.cwd = path;
+        self.reload_config();
+        crate::dev_env::follow_cwd(self);
         Ok(())
     }
 
+    /// Load configuration for `cwd`, and again whenever it changes.
+    pub fn enable_config(&mut self) {
+        self.config_enabled = true;
+        self.reload_config();
+    }
+
+    /// Re-read configuration files for the current directory.
+    pub fn reload_config(&mut self) {
+        if self.config_enabled {
+            let previous = std::mem::replace(&mut self.config, Config::load(&self.cwd));
+            self.config.apply_env(&previous, &mut self.env);
+            for (path, error) in &self.config.errors {
+                tracing::warn!("config: ignoring {}: {}", path.display(), error);
+            }
+        }
+    }
+
+    /// Take on the variables of a login shell's environment (see
+    /// [`crate::login_env`]), except those configuration sets, and re-add
+    /// configured `PATH` entries. Returns the names whose values changed.
+    pub fn apply_login_env(&mut self, captured: HashMap<String, String>) -> Vec<String> {
+        let before = self.env.clone();
+        for (name, value) in captured {
+            if !self.config.env.contains_key(&name) {
+                self.env.insert(name, value);
+            }
+        }
+        self.config.apply_env(&self.config, &mut self.env);
+        let mut changed: Vec<String> =
+            self.env.iter().filter(|(name, value)| before.get(*name) != Some(value)).map(|(name, _)| name.clone()).collect();
+        changed.sort();
+        changed
+    }
+
+    /// The expansion of alias `name`: one defined with `alias` first, then
+    /// configuration, then the shell's startup files.
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases
+            .get(name)
+            .map(String::as_str)
+            .or_else(|| self.config.alias(name))
+            .or_else(|| self.rc_aliases.get(name).map(String::as_str))
+    }
+
+    /// Save the state a panicking command could leave half-modified.
+    pub fn checkpoint(&self) -> StateCheckpoint {
+        StateCheckpoint {
+            env: self.env.clone(),
+            vars: self.vars.clone(),
+            cwd: self.cwd.clone(),
+            aliases: self.aliases.clone(),
+            readonly_vars: self.readonly_vars.clone(),
+            positional_params: self.positional_params.clone(),
+            options: self.options.clone(),
+            traps: self.traps.clone(),
+            functions: self.functions.clone(),
+            local_scopes: self.local_scopes.clone(),
+            last_exit_code: self.last_exit_code,
+            dev_envs: self.dev_envs.clone(),
+        }
+    }
+
+    /// Roll back to a checkpoint taken with [`ShellState::checkpoint`].
+    pub fn restore(&mut self, checkpoint: StateCheckpoint) {
+        let StateCheckpoint {
+            env,
+            vars,
+            cwd,
+            aliases,
+            readonly_vars,
+            positional_params,
+            options,
+            traps,
+            functions,
+            local_scopes,
+            last_exit_code,
+            dev_envs,
+        } = checkpoint;
+        let cwd_changed = self.cwd != cwd;
+        self.env = env;
+        self.vars = vars;
+        self.cwd = cwd;
+        self.aliases = aliases;
+        self.readonly_vars = readonly_vars;
+        self.positional_params = positional_params;
+        self.options = options;
+        self.traps = traps;
+        // A panic inside a condition skipped its way out.
+        self.conditions = 0;
+        self.functions = functions;
+        self.local_scopes = local_scopes;
+        self.last_exit_code = last_exit_code;
+        self.dev_envs = dev_envs;
+        if cwd_changed {
+            self.reload_config();
+        }
+    }
+
     /// Check if a variable is readonly.
     pub fn is_readonly(&self, name: &str) -> bool {
         self.readonly_vars.contains(name)
@@ -311,10 +515,6 @@ impl ShellState {
     pub fn store_output(&mut self, block_id: BlockId, command: String, value: Value) {
         use std::time::{SystemTime, UNIX_EPOCH};
 
-        // Update last_output for $_ / $prev
-        self.last_output = Some(value.clone());
-
-        // Add to block_outputs ring buffer
         let output = BlockOutput {
             id: block_id,
             command,
@@ -324,8 +524,16 @@ impl ShellState {
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
         };
+        self.push_output(output);
+    }
 
+    fn push_output(&mut self, output: BlockOutput) {
+        // Update last_output for $_ / $prev
+        self.last_output = Some(output.value.clone());
+
+        // Add to block_outputs ring buffer
         self.block_outputs.push_front(output);
+        self.outputs_stored += 1;
 
         // Trim if over max
         while self.block_outputs.len() > self.max_block_outputs {
@@ -333,6 +541,33 @@ impl ShellState {
         }
     }
 
+    /// Take the outputs a [`ShellState::fork`] of this state stored, oldest
+    /// first, so they become the most recent `$_N` here too.
+    pub fn adopt_outputs(&mut self, fork: &ShellState, forked_at: u64) {
+        let new = fork.outputs_stored.saturating_sub(forked_at) as usize;
+        for output in fork.block_outputs.iter().take(new).rev() {
+            self.push_output(output.clone());
+        }
+    }
+
+    /// The number of outputs stored so far, to pass to
+    /// [`ShellState::adopt_outputs`].
+    pub fn outputs_stored(&self) -> u64 {
+        self.outputs_stored
+    }
+
+    /// Add a running job to the job table. Job numbers start over once the
+    /// table is empty, as in bash.
+    pub fn add_job(&mut self, pgid: Pid, command: String) -> &Job {
+        if self.jobs.is_empty() {
+            self.next_job_id = 1;
+        }
+        self.jobs.push(Job::new(self.next_job_id, pgid, command));
+        self.next_job_id += 1;
+        self.last_bg_pid = Some(pgid.as_raw() as u32);
+        &self.jobs[self.jobs.len() - 1]
+    }
+
     /// Get the last output ($_ or $prev).
     pub fn get_last_output(&self) -> Option<&Value> {
         self.last_output.as_ref()
@@ -346,6 +581,12 @@ impl ShellState {
         self.block_outputs.get(index - 1).map(|o| &o.value)
     }
 
+    /// The index (1 = most recent) a block's output is stored at, for
+    /// referring to it as `_N` / `$_N`. `None` once it has aged out.
+    pub fn output_index(&self, block_id: BlockId) -> Option<usize> {
+        self.block_outputs.iter().position(|o| o.id == block_id).map(|i| i + 1)
+    }
+
     /// Get output by block ID.
     pub fn get_output_by_id(&self, block_id: BlockId) -> Option<&Value> {
         self.block_outputs
@@ -373,6 +614,11 @@ impl ShellState {
 
     // === Local Variable Scope Methods ===
 
+    /// How many function calls deep evaluation is.
+    pub fn function_depth(&self) -> usize {
+        self.local_scopes.len()
+    }
+
     /// Enter a new local scope (for function calls).
     pub fn push_scope(&mut self) {
         self.local_scopes.push(HashMap::new());
@@ -459,21 +705,71 @@ impl ShellOptions {
         }
     }
 
-    /// Print current options in a format suitable for `set -o`.
-    pub fn print_options(&self) -> String {
-        let opts = [
+    /// The options `shopt` sets, by name.
+    pub fn shopt_options(&self) -> [(&'static str, bool); 4] {
+        [
+            ("dotglob", self.dotglob),
+            ("extglob", self.extglob),
+            ("globstar", self.globstar),
+            ("nullglob", self.nullglob),
+        ]
+    }
+
+    /// Set a `shopt` option by name. False if there is none by that name.
+    pub fn set_shopt(&mut self, name: &str, value: bool) -> bool {
+        match name {
+            "dotglob" => self.dotglob = value,
+            "extglob" => self.extglob = value,
+            "globstar" => self.globstar = value,
+            "nullglob" => self.nullglob = value,
+            _ => return false,
+        }
+        true
+    }
+
+    /// The options `set -o` names, with whether each is on.
+    pub fn named_options(&self) -> [(&'static str, bool); 11] {
+        [
+            ("allexport", self.allexport),
             ("errexit", self.errexit),
-            ("nounset", self.nounset),
-            ("xtrace", self.xtrace),
-            ("verbose", self.verbose),
+            ("hashall", self.hashall),
+            ("noclobber", self.noclobber),
             ("noexec", self.noexec),
             ("noglob", self.noglob),
-            ("noclobber", self.noclobber),
-            ("allexport", self.allexport),
             ("notify", self.notify),
-            ("hashall", self.hashall),
-        ];
-        opts.iter()
+            ("nounset", self.nounset),
+            ("pipefail", self.pipefail),
+            ("verbose", self.verbose),
+            ("xtrace", self.xtrace),
+        ]
+    }
+
+    /// Set an option by its `set -o` name. False if there is none by that name.
+    pub fn set_named(&mut self, name: &str, value: bool) -> bool {
+        let flag = match name {
+            "pipefail" => {
+                self.pipefail = value;
+                return true;
+            }
+            "errexit" => 'e',
+            "nounset" => 'u',
+            "xtrace" => 'x',
+            "verbose" => 'v',
+            "noexec" => 'n',
+            "noglob" => 'f',
+            "noclobber" => 'C',
+            "allexport" => 'a',
+            "notify" => 'b',
+            "hashall" => 'h',
+            _ => return false,
+        };
+        self.set_option(flag, value)
+    }
+
+    /// Print current options in a format suitable for `set -o`.
+    pub fn print_options(&self) -> String {
+        self.named_options()
+            .iter()
             .map(|(name, val)| format!("set {}o {}", if *val { "-" } else { "+" }, name))
             .collect::<Vec<_>>()
             .join("\n")
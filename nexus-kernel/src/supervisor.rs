@@ -0,0 +1,145 @@
+//! Panic isolation for command evaluation.
+//!
+//! A panic in a native command or the parser must not take the window (or
+//! the remote agent) down with it. [`Kernel::execute_supervised`] evaluates
+//! under `catch_unwind`; on panic the shell state is rolled back to its
+//! pre-command checkpoint, the parser is rebuilt, the block is finished with
+//! exit code 101 (Rust's panic exit status), and a `ShellEvent::KernelPanic`
+//! crash report is emitted.
+//!
+//! [`Kernel::execute_supervised`]: crate::Kernel::execute_supervised
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+/// Exit code reported for a block whose command panicked.
+pub const PANIC_EXIT_CODE: i32 = 101;
+
+/// What went wrong, for the crash-report event.
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+}
+
+thread_local! {
+    /// Set while this thread is inside `run`, so the hook only pays for a
+    /// backtrace when someone will read it.
+    static SUPERVISED: Cell<bool> = const { Cell::new(false) };
+    static LAST_PANIC: RefCell<Option<(Option<String>, String)>> = const { RefCell::new(None) };
+}
+
+/// Chain a hook in front of the existing one that records where a
+/// supervised panic happened. The previous hook still runs (and logs).
+fn install_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if SUPERVISED.with(Cell::get) {
+                let location = info.location().map(|l| l.to_string());
+                let backtrace = Backtrace::force_capture().to_string();
+                LAST_PANIC.with(|p| *p.borrow_mut() = Some((location, backtrace)));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Whether a panic on this thread right now would be caught by [`run`].
+pub(crate) fn is_supervised() -> bool {
+    SUPERVISED.with(Cell::get)
+}
+
+/// Run `f`, turning a panic into a [`PanicReport`].
+pub(crate) fn run<T>(f: impl FnOnce() -> T) -> Result<T, PanicReport> {
+    install_hook();
+    let was_supervised = SUPERVISED.with(|s| s.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    SUPERVISED.with(|s| s.set(was_supervised));
+
+    result.map_err(|payload| {
+        let (location, backtrace) = LAST_PANIC.with(|p| p.borrow_mut().take()).unwrap_or_default();
+        PanicReport { message: panic_message(payload.as_ref()), location, backtrace }
+    })
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Kernel;
+    use crate::commands::{CommandContext, NexusCommand};
+    use nexus_api::{BlockId, ShellEvent, Value};
+
+    #[test]
+    fn test_panic_becomes_report() {
+        let report = run(|| -> i32 { panic!("boom {}", 42) }).unwrap_err();
+        assert_eq!(report.message, "boom 42");
+        assert!(report.location.unwrap().contains("supervisor.rs"));
+    }
+
+    #[test]
+    fn test_ok_passes_through() {
+        assert_eq!(run(|| 7).unwrap(), 7);
+    }
+
+    struct PanicCommand;
+
+    impl NexusCommand for PanicCommand {
+        fn name(&self) -> &'static str {
+            "test-panic"
+        }
+
+        fn execute(&self, _args: &[String], ctx: &mut CommandContext) -> anyhow::Result<Value> {
+            ctx.state.env.insert("HALF_DONE".to_string(), "1".to_string());
+            panic!("native command bug");
+        }
+    }
+
+    #[test]
+    fn test_kernel_survives_command_panic() {
+        let (mut kernel, mut rx) = Kernel::new().unwrap();
+        kernel.commands.register(PanicCommand);
+        kernel.state_mut().env.insert("BEFORE".to_string(), "1".to_string());
+
+        let block_id = BlockId(90_001);
+        let exit = kernel.execute_supervised("test-panic", block_id).unwrap();
+        assert_eq!(exit, PANIC_EXIT_CODE);
+        assert!(!kernel.state().env.contains_key("HALF_DONE"));
+        assert!(kernel.state().env.contains_key("BEFORE"));
+
+        let mut saw_report = false;
+        let mut finished = None;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                ShellEvent::KernelPanic { block_id: id, message, .. } if id == block_id => {
+                    assert_eq!(message, "native command bug");
+                    saw_report = true;
+                }
+                ShellEvent::CommandFinished { block_id: id, exit_code, .. } if id == block_id => {
+                    finished = Some(exit_code);
+                }
+                _ => {}
+            }
+        }
+        assert!(saw_report);
+        assert_eq!(finished, Some(PANIC_EXIT_CODE));
+
+        // The kernel keeps working afterwards.
+        assert_eq!(kernel.execute("true").unwrap(), 0);
+    }
+}
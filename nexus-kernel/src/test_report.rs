@@ -0,0 +1,442 @@
+//! Test results parsed from test runner output: `cargo test`, `pytest`
+//! and `jest`.
+//!
+//! Suites are cargo's module paths, pytest's files and jest's test files.
+//! pytest only names passing tests with `-v`; without it the report holds
+//! the failures from the short summary. Durations appear where the runner
+//! prints them (`--report-time`, `--durations`, jest's verbose output).
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+static CARGO_BINARY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:Running (?:unittests )?(\S+)|Doc-tests (\S+))").unwrap());
+static CARGO_TEST: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^test (.+?) \.\.\. (ok|FAILED|ignored)\b(?:.*<(\d+(?:\.\d+)?)s>)?").unwrap());
+static CARGO_FAILURE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^---- (.+?) stdout ----$").unwrap());
+
+static PYTEST_TEST: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\S+?)::(\S.*?) (PASSED|FAILED|ERROR|SKIPPED|XFAIL|XPASS)(?: \(.*\))?(?:\s+\[\s*\d+%\])?$").unwrap()
+});
+static PYTEST_SUMMARY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(FAILED|ERROR) (\S+?)::(\S+)(?: - .*)?$").unwrap());
+static PYTEST_SECTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^_{3,} (.+?) _{3,}$").unwrap());
+static PYTEST_DURATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\d+(?:\.\d+)?)s call\s+(\S+?)::(\S+)$").unwrap());
+
+static JEST_SUITE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(PASS|FAIL) (\S+)").unwrap());
+static JEST_TEST: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\s+)([✓✕○√×]) (?:(?:skipped|todo) )?(.+?)(?: \((\d+(?:\.\d+)?) ?(ms|s)\))?$").unwrap()
+});
+static JEST_FAILURE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s+● (.+)$").unwrap());
+
+/// A test runner whose output can be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    Cargo,
+    Pytest,
+    Jest,
+}
+
+impl Runner {
+    /// The runner `command` invokes, if it's one of ours.
+    pub fn detect(command: &str) -> Option<Self> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        runner_word(&words).map(|(runner, _)| runner)
+    }
+}
+
+/// Where the runner's own name is in `words`, for keeping what comes
+/// before it when building a rerun command.
+fn runner_word(words: &[&str]) -> Option<(Runner, usize)> {
+    let base = |word: &str| word.rsplit('/').next().unwrap_or(word).to_string();
+    for (i, word) in words.iter().enumerate() {
+        let name = base(word);
+        if name == "cargo" {
+            let sub = words[i + 1..].iter().find(|w| !w.starts_with('+') && !w.starts_with('-'));
+            if sub == Some(&"test") {
+                return Some((Runner::Cargo, i));
+            }
+        } else if name == "pytest" || name == "py.test" || (*word == "-m" && words.get(i + 1) == Some(&"pytest")) {
+            return Some((Runner::Pytest, if *word == "-m" { i + 1 } else { i }));
+        } else if name == "jest" {
+            return Some((Runner::Jest, i));
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCase {
+    /// As the runner takes it back: cargo's full path, pytest's node name
+    /// within the file, jest's describe blocks and title joined by spaces.
+    pub name: String,
+    pub status: TestStatus,
+    pub duration_ms: Option<u64>,
+    /// What the runner printed about the failure.
+    pub failure: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSuite {
+    pub name: String,
+    pub tests: Vec<TestCase>,
+}
+
+impl TestSuite {
+    pub fn counts(&self) -> TestCounts {
+        let mut counts = TestCounts::default();
+        for test in &self.tests {
+            match test.status {
+                TestStatus::Passed => counts.passed += 1,
+                TestStatus::Failed => counts.failed += 1,
+                TestStatus::Skipped => counts.skipped += 1,
+            }
+        }
+        counts
+    }
+
+    fn test_mut(&mut self, name: &str) -> &mut TestCase {
+        let index = match self.tests.iter().position(|t| t.name == name) {
+            Some(index) => index,
+            None => {
+                self.tests.push(TestCase { name: name.to_string(), status: TestStatus::Failed, duration_ms: None, failure: None });
+                self.tests.len() - 1
+            }
+        };
+        &mut self.tests[index]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TestCounts {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestReport {
+    pub runner: Runner,
+    pub suites: Vec<TestSuite>,
+}
+
+impl TestReport {
+    /// Parse `output` of a `runner` run. None when it names no tests.
+    pub fn parse(runner: Runner, output: &str) -> Option<Self> {
+        let mut report = Self { runner, suites: Vec::new() };
+        match runner {
+            Runner::Cargo => report.parse_cargo(output),
+            Runner::Pytest => report.parse_pytest(output),
+            Runner::Jest => report.parse_jest(output),
+        }
+        report.suites.retain(|suite| !suite.tests.is_empty());
+        (!report.suites.is_empty()).then_some(report)
+    }
+
+    pub fn counts(&self) -> TestCounts {
+        self.suites.iter().map(TestSuite::counts).fold(TestCounts::default(), |a, b| TestCounts {
+            passed: a.passed + b.passed,
+            failed: a.failed + b.failed,
+            skipped: a.skipped + b.skipped,
+        })
+    }
+
+    fn suite_mut(&mut self, name: &str) -> &mut TestSuite {
+        let index = match self.suites.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                self.suites.push(TestSuite { name: name.to_string(), tests: Vec::new() });
+                self.suites.len() - 1
+            }
+        };
+        &mut self.suites[index]
+    }
+
+    /// The last failed test whose name `matches`.
+    fn failed_test_mut(&mut self, matches: impl Fn(&str) -> bool) -> Option<&mut TestCase> {
+        self.suites
+            .iter_mut()
+            .rev()
+            .flat_map(|suite| suite.tests.iter_mut())
+            .find(|test| test.status == TestStatus::Failed && matches(&test.name))
+    }
+
+    fn failed(&self) -> impl Iterator<Item = (&TestSuite, &TestCase)> {
+        self.suites
+            .iter()
+            .flat_map(|suite| suite.tests.iter().map(move |test| (suite, test)))
+            .filter(|(_, test)| test.status == TestStatus::Failed)
+    }
+
+    /// `command` narrowed down to the tests that failed; None if none did.
+    pub fn rerun_failed(&self, command: &str) -> Option<String> {
+        let failed: Vec<_> = self.failed().collect();
+        if failed.is_empty() {
+            return None;
+        }
+        let words: Vec<&str> = command.split_whitespace().collect();
+        let (_, at) = runner_word(&words)?;
+        let runner = words[..=at].join(" ");
+        let quoted = |names: Vec<String>| names.iter().map(|name| shell_quote(name)).collect::<Vec<_>>().join(" ");
+        Some(match self.runner {
+            Runner::Cargo => {
+                // Keep cargo's own arguments, replace the test binary's.
+                let cargo_args = command.split(" -- ").next().unwrap_or(command).trim_end();
+                let names = failed.iter().map(|(_, test)| test.name.clone()).collect();
+                format!("{} -- --exact {}", cargo_args, quoted(names))
+            }
+            Runner::Pytest => {
+                let ids = failed.iter().map(|(suite, test)| format!("{}::{}", suite.name, test.name)).collect();
+                format!("{} {}", runner, quoted(ids))
+            }
+            Runner::Jest => {
+                let mut files: Vec<String> = failed.iter().map(|(suite, _)| suite.name.clone()).collect();
+                files.dedup();
+                let names: Vec<String> = failed.iter().map(|(_, test)| regex::escape(&test.name)).collect();
+                let pattern = format!("^({})$", names.join("|"));
+                format!("{} {} -t {}", runner, quoted(files), shell_quote(&pattern))
+            }
+        })
+    }
+
+    fn parse_cargo(&mut self, output: &str) {
+        let mut binary = String::new();
+        let mut failure: Option<(String, Vec<&str>)> = None;
+        for line in output.lines() {
+            if let Some(caps) = CARGO_BINARY.captures(line) {
+                binary = caps.get(1).or(caps.get(2)).map_or("", |m| m.as_str()).to_string();
+            } else if let Some(caps) = CARGO_TEST.captures(line) {
+                let name = &caps[1];
+                let suite = match name.rsplit_once("::") {
+                    Some((module, _)) => module.to_string(),
+                    None => binary.clone(),
+                };
+                let test = self.suite_mut(&suite).test_mut(name);
+                test.status = match &caps[2] {
+                    "ok" => TestStatus::Passed,
+                    "ignored" => TestStatus::Skipped,
+                    _ => TestStatus::Failed,
+                };
+                test.duration_ms = caps.get(3).and_then(|s| s.as_str().parse::<f64>().ok()).map(|s| (s * 1000.0) as u64);
+            } else if let Some(caps) = CARGO_FAILURE.captures(line) {
+                self.attach_failure(failure.take(), |test, name| test == name);
+                failure = Some((caps[1].to_string(), Vec::new()));
+            } else if line == "failures:" || line.starts_with("test result:") {
+                self.attach_failure(failure.take(), |test, name| test == name);
+            } else if let Some((_, lines)) = &mut failure {
+                lines.push(line);
+            }
+        }
+        self.attach_failure(failure, |test, name| test == name);
+    }
+
+    fn parse_pytest(&mut self, output: &str) {
+        let mut failure: Option<(String, Vec<&str>)> = None;
+        // pytest's section titles use dots where node ids use `::`.
+        let section_matches = |test: &str, title: &str| test.replace("::", ".") == title;
+        for line in output.lines() {
+            if let Some(caps) = PYTEST_TEST.captures(line) {
+                let test = self.suite_mut(&caps[1]).test_mut(&caps[2]);
+                test.status = match &caps[3] {
+                    "PASSED" | "XFAIL" | "XPASS" => TestStatus::Passed,
+                    "SKIPPED" => TestStatus::Skipped,
+                    _ => TestStatus::Failed,
+                };
+            } else if let Some(caps) = PYTEST_SUMMARY.captures(line) {
+                self.attach_failure(failure.take(), section_matches);
+                self.suite_mut(&caps[2]).test_mut(&caps[3]).status = TestStatus::Failed;
+            } else if let Some(caps) = PYTEST_DURATION.captures(line) {
+                let seconds: f64 = caps[1].parse().unwrap_or(0.0);
+                if let Some(test) = self.suites.iter_mut().find(|s| s.name == caps[2]).and_then(|s| s.tests.iter_mut().find(|t| t.name == caps[3])) {
+                    test.duration_ms = Some((seconds * 1000.0) as u64);
+                }
+            } else if let Some(caps) = PYTEST_SECTION.captures(line) {
+                self.attach_failure(failure.take(), section_matches);
+                failure = Some((caps[1].to_string(), Vec::new()));
+            } else if line.starts_with("===") {
+                self.attach_failure(failure.take(), section_matches);
+            } else if let Some((_, lines)) = &mut failure {
+                lines.push(line);
+            }
+        }
+        self.attach_failure(failure, section_matches);
+    }
+
+    fn parse_jest(&mut self, output: &str) {
+        let mut suite = String::new();
+        // Enclosing describe blocks: (indent, title).
+        let mut describes: Vec<(usize, &str)> = Vec::new();
+        let mut failure: Option<(String, Vec<&str>)> = None;
+        for line in output.lines() {
+            if let Some(caps) = JEST_SUITE.captures(line) {
+                self.attach_failure(failure.take(), |test, name| test == name);
+                suite = caps[2].to_string();
+                describes.clear();
+            } else if line.starts_with("Test Suites:") {
+                self.attach_failure(failure.take(), |test, name| test == name);
+            } else if let Some(caps) = JEST_FAILURE.captures(line) {
+                self.attach_failure(failure.take(), |test, name| test == name);
+                let name = caps[1].replace(" › ", " ");
+                self.suite_mut(&suite).test_mut(&name);
+                failure = Some((name, Vec::new()));
+            } else if let Some((_, lines)) = &mut failure {
+                lines.push(line);
+            } else if let Some(caps) = JEST_TEST.captures(line) {
+                let indent = caps[1].len();
+                describes.retain(|&(at, _)| at < indent);
+                let mut name: Vec<&str> = describes.iter().map(|&(_, title)| title).collect();
+                name.push(caps.get(3).map_or("", |m| m.as_str()));
+                let duration_ms = caps.get(4).and_then(|n| n.as_str().parse::<f64>().ok()).map(|n| {
+                    if &caps[5] == "s" { (n * 1000.0) as u64 } else { n as u64 }
+                });
+                let test = self.suite_mut(&suite).test_mut(&name.join(" "));
+                test.status = match &caps[2] {
+                    "✓" | "√" => TestStatus::Passed,
+                    "○" => TestStatus::Skipped,
+                    _ => TestStatus::Failed,
+                };
+                test.duration_ms = duration_ms;
+            } else if !suite.is_empty() && line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+                let indent = line.len() - line.trim_start().len();
+                describes.retain(|&(at, _)| at < indent);
+                describes.push((indent, line.trim()));
+            }
+        }
+        self.attach_failure(failure, |test, name| test == name);
+    }
+
+    /// Hand a failure's collected output to the failed test it's about.
+    fn attach_failure(&mut self, failure: Option<(String, Vec<&str>)>, matches: impl Fn(&str, &str) -> bool) {
+        let Some((name, lines)) = failure else {
+            return;
+        };
+        let text = lines.join("\n").trim_matches('\n').trim_end().to_string();
+        if let Some(test) = self.failed_test_mut(|test| matches(test, &name))
+            && !text.is_empty()
+        {
+            test.failure = Some(text);
+        }
+    }
+}
+
+fn shell_quote(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '_' | '-' | ':')) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Runner::detect("cargo test -p nexus-kernel"), Some(Runner::Cargo));
+        assert_eq!(Runner::detect("cargo +nightly test"), Some(Runner::Cargo));
+        assert_eq!(Runner::detect("cargo build"), None);
+        assert_eq!(Runner::detect("python -m pytest tests/"), Some(Runner::Pytest));
+        assert_eq!(Runner::detect("./node_modules/.bin/jest --verbose"), Some(Runner::Jest));
+        assert_eq!(Runner::detect("make test"), None);
+    }
+
+    #[test]
+    fn test_cargo() {
+        let output = "\
+     Running unittests src/lib.rs (target/debug/deps/app-1a2b)
+
+running 3 tests
+test parser::tests::test_words ... ok
+test parser::tests::test_quotes ... FAILED
+test smoke ... ignored
+
+failures:
+
+---- parser::tests::test_quotes stdout ----
+thread 'parser::tests::test_quotes' panicked at src/parser.rs:40:9:
+assertion `left == right` failed
+
+failures:
+    parser::tests::test_quotes
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+";
+        let report = TestReport::parse(Runner::Cargo, output).unwrap();
+        assert_eq!(report.counts(), TestCounts { passed: 1, failed: 1, skipped: 1 });
+        assert_eq!(report.suites.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), ["parser::tests", "src/lib.rs"]);
+        let failed = &report.suites[0].tests[1];
+        assert!(failed.failure.as_deref().unwrap().ends_with("assertion `left == right` failed"));
+        assert_eq!(
+            report.rerun_failed("cargo test -p app -- --nocapture").as_deref(),
+            Some("cargo test -p app -- --exact parser::tests::test_quotes")
+        );
+        assert_eq!(TestReport::parse(Runner::Cargo, "   Compiling app v0.1.0\n"), None);
+    }
+
+    #[test]
+    fn test_pytest() {
+        let output = "\
+tests/test_math.py::test_add PASSED                                      [ 33%]
+tests/test_math.py::TestDiv::test_zero FAILED                            [ 66%]
+tests/test_io.py::test_read SKIPPED (no fixture)                         [100%]
+
+=================================== FAILURES ===================================
+______________________________ TestDiv.test_zero _______________________________
+
+    def test_zero(self):
+>       assert div(1, 0) == 0
+E       ZeroDivisionError: division by zero
+============================= slowest durations ==============================
+0.25s call     tests/test_math.py::TestDiv::test_zero
+=========================== short test summary info ============================
+FAILED tests/test_math.py::TestDiv::test_zero - ZeroDivisionError: division by zero
+";
+        let report = TestReport::parse(Runner::Pytest, output).unwrap();
+        assert_eq!(report.counts(), TestCounts { passed: 1, failed: 1, skipped: 1 });
+        let failed = &report.suites[0].tests[1];
+        assert_eq!(failed.duration_ms, Some(250));
+        assert!(failed.failure.as_deref().unwrap().contains("ZeroDivisionError"));
+        assert_eq!(
+            report.rerun_failed("python -m pytest -x tests/").as_deref(),
+            Some("python -m pytest tests/test_math.py::TestDiv::test_zero")
+        );
+    }
+
+    #[test]
+    fn test_jest() {
+        let output = "\
+FAIL src/math.test.js
+  math
+    ✓ adds (3 ms)
+    ✕ divides by zero (12 ms)
+    ○ skipped rounds
+
+  ● math › divides by zero
+
+    expect(received).toBe(expected)
+
+PASS src/io.test.js (1.2 s)
+  ✓ reads (1 ms)
+
+Test Suites: 1 failed, 1 passed, 2 total
+";
+        let report = TestReport::parse(Runner::Jest, output).unwrap();
+        assert_eq!(report.counts(), TestCounts { passed: 2, failed: 1, skipped: 1 });
+        let failed = &report.suites[0].tests[1];
+        assert_eq!((failed.name.as_str(), failed.duration_ms), ("math divides by zero", Some(12)));
+        assert_eq!(failed.failure.as_deref(), Some("    expect(received).toBe(expected)"));
+        assert_eq!(
+            report.rerun_failed("npx jest --verbose").as_deref(),
+            Some("npx jest src/math.test.js -t '^(math divides by zero)$'")
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Short titles for finished blocks — the command and what came of it,
+//! e.g. `cargo build — 2 errors` — and the summary of a session built from
+//! them.
+//!
+//! Titles come from local parsing only: the program (and subcommand, for
+//! tools that have them), the compiler problems and test results in the
+//! output ([`crate::problems`], [`crate::test_report`]) and the exit code.
+
+use crate::containers::ContainerExec;
+use crate::overrides::ExecOverride;
+use crate::persistence::{BlockTitle, Session};
+use crate::problems::{self, Severity};
+use crate::test_report::{Runner, TestCounts, TestReport};
+
+/// Programs whose first plain argument says what they did.
+const SUBCOMMAND_TOOLS: &[&str] = &[
+    "apt", "brew", "bun", "cargo", "deno", "docker", "gh", "git", "go", "helm", "kubectl", "make", "mix", "npm",
+    "pip", "pip3", "pnpm", "poetry", "rustup", "swift", "systemctl", "terraform", "uv", "yarn",
+];
+
+/// Wrappers that run the command after them.
+const WRAPPERS: &[&str] = &["sudo", "time", "nice", "env", "command", "exec", "nohup"];
+
+/// Titles shown in a session summary.
+const SUMMARY_TITLES: usize = 5;
+
+/// What a finished command's output and exit code say about how it went.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Outcome {
+    pub exit_code: i32,
+    pub errors: usize,
+    pub warnings: usize,
+    /// Set when the command was a test run whose results could be parsed.
+    pub tests: Option<TestCounts>,
+}
+
+impl Outcome {
+    /// Read the outcome of `command` from its `output`.
+    pub fn parse(command: &str, output: &str, exit_code: i32) -> Self {
+        let problems = problems::parse(output);
+        let errors = problems.iter().filter(|p| p.severity == Severity::Error).count();
+        let tests = Runner::detect(command)
+            .and_then(|runner| TestReport::parse(runner, output))
+            .map(|report| report.counts());
+        Self { exit_code, errors, warnings: problems.len() - errors, tests }
+    }
+
+    /// The part of the outcome worth naming, if any: test results first,
+    /// then compiler problems, then a failing exit code.
+    fn label(&self) -> Option<String> {
+        if let Some(tests) = self.tests {
+            let total = tests.passed + tests.failed;
+            return Some(if tests.failed > 0 {
+                format!("{} of {} tests failed", tests.failed, total)
+            } else {
+                format!("{} {} passed", total, plural(total, "test"))
+            });
+        }
+        let mut parts = Vec::new();
+        if self.errors > 0 {
+            parts.push(format!("{} {}", self.errors, plural(self.errors, "error")));
+        }
+        if self.warnings > 0 {
+            parts.push(format!("{} {}", self.warnings, plural(self.warnings, "warning")));
+        }
+        if !parts.is_empty() {
+            return Some(parts.join(", "));
+        }
+        match self.exit_code {
+            0 => None,
+            130 => Some("interrupted".to_string()),
+            code => Some(format!("exit {}", code)),
+        }
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 { noun.to_string() } else { format!("{}s", noun) }
+}
+
+/// The program `command` runs, with its subcommand for tools like git and
+/// cargo. Paths, `in` and `nexus exec` prefixes, variable assignments and wrappers such as
+/// `sudo` are left out.
+pub fn command_name(command: &str) -> String {
+    let line = command.lines().next().unwrap_or("").trim();
+    let line = ExecOverride::split(line).map_or(line, |(_, rest)| rest);
+    let line = ContainerExec::split(line).map_or(line, |(_, rest)| rest);
+    let mut words = line
+        .split_whitespace()
+        .skip_while(|word| word.contains('=') || WRAPPERS.contains(word) || word.starts_with('-'));
+    let Some(program) = words.next() else {
+        return line.to_string();
+    };
+    let program = program.rsplit('/').next().unwrap_or(program);
+    if !SUBCOMMAND_TOOLS.contains(&program) {
+        return program.to_string();
+    }
+    let subcommand = words
+        .find(|word| !word.starts_with('-') && !word.starts_with('+'))
+        .filter(|word| word.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == ':'));
+    match subcommand {
+        Some(sub) => format!("{} {}", program, sub),
+        None => program.to_string(),
+    }
+}
+
+/// `cargo build — 2 errors`: the command's name and, when it says
+/// something, its outcome.
+pub fn block_title(command: &str, outcome: &Outcome) -> String {
+    let name = command_name(command);
+    match outcome.label() {
+        Some(label) => format!("{} \u{2014} {}", name, label),
+        None => name,
+    }
+}
+
+/// What happened in a past session, for showing when it's reopened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSummary {
+    pub cwd: String,
+    pub commands: usize,
+    pub failed: usize,
+    /// The titles worth seeing again, oldest first: what failed, then the
+    /// latest of the rest. Repeats are folded into `title ×N`.
+    pub highlights: Vec<String>,
+}
+
+impl SessionSummary {
+    /// Summarize `session` from its blocks' titles, in run order. None if
+    /// nothing ran.
+    pub fn new(session: &Session, titles: &[BlockTitle]) -> Option<Self> {
+        if titles.is_empty() {
+            return None;
+        }
+
+        // Fold repeats, keeping where each title last appeared.
+        let mut folded: Vec<(usize, &str, bool, usize)> = Vec::new();
+        for (i, block) in titles.iter().enumerate() {
+            let failed = block.exit_code.is_some_and(|code| code != 0);
+            match folded.iter_mut().find(|(_, title, ..)| *title == block.title) {
+                Some(entry) => {
+                    entry.0 = i;
+                    entry.2 = failed;
+                    entry.3 += 1;
+                }
+                None => folded.push((i, &block.title, failed, 1)),
+            }
+        }
+        folded.sort_by_key(|(i, ..)| *i);
+
+        let mut picked: Vec<_> = folded.iter().filter(|(_, _, failed, _)| *failed).rev().take(SUMMARY_TITLES).collect();
+        let room = SUMMARY_TITLES - picked.len();
+        picked.extend(folded.iter().filter(|(_, _, failed, _)| !*failed).rev().take(room));
+        picked.sort_by_key(|(i, ..)| *i);
+
+        Some(Self {
+            cwd: session.cwd.clone(),
+            commands: titles.len(),
+            failed: titles.iter().filter(|b| b.exit_code.is_some_and(|code| code != 0)).count(),
+            highlights: picked
+                .into_iter()
+                .map(|&(_, title, _, count)| if count > 1 { format!("{} \u{00D7}{}", title, count) } else { title.to_string() })
+                .collect(),
+        })
+    }
+
+    /// `12 commands, 2 failed`
+    pub fn headline(&self) -> String {
+        let mut headline = format!("{} {}", self.commands, plural(self.commands, "command"));
+        if self.failed > 0 {
+            headline.push_str(&format!(", {} failed", self.failed));
+        }
+        headline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(command_name("cargo +nightly build --release"), "cargo build");
+        assert_eq!(command_name("RUST_LOG=debug sudo /usr/bin/git status -s"), "git status");
+        assert_eq!(command_name("in ~/src/app cargo test -p core"), "cargo test");
+        assert_eq!(command_name("nexus exec -c web npm run build"), "npm run");
+        assert_eq!(command_name("ls -la src"), "ls");
+        assert_eq!(command_name("npm"), "npm");
+        assert_eq!(command_name("make ./out/app.o"), "make");
+    }
+
+    #[test]
+    fn test_block_title() {
+        let output = "error[E0308]: mismatched types\n --> src/main.rs:4:5\n\
+                      error: aborting\n --> src/lib.rs:1:1\n\
+                      warning: unused variable\n --> src/lib.rs:9:9\n";
+        let outcome = Outcome::parse("cargo build", output, 101);
+        assert_eq!((outcome.errors, outcome.warnings), (2, 1));
+        assert_eq!(block_title("cargo build", &outcome), "cargo build \u{2014} 2 errors, 1 warning");
+
+        let output = "running 2 tests\ntest a ... ok\ntest b ... FAILED\n";
+        let outcome = Outcome::parse("cargo test", output, 101);
+        assert_eq!(block_title("cargo test", &outcome), "cargo test \u{2014} 1 of 2 tests failed");
+
+        assert_eq!(block_title("ls", &Outcome::parse("ls", "a b", 0)), "ls");
+        assert_eq!(block_title("grep x f", &Outcome::parse("grep x f", "", 1)), "grep \u{2014} exit 1");
+    }
+
+    #[test]
+    fn test_session_summary() {
+        let session = Session { id: 1, started_at: Utc::now(), ended_at: None, cwd: "/src".into() };
+        let title = |title: &str, exit_code| BlockTitle { block_id: 0, title: title.into(), exit_code: Some(exit_code) };
+        assert!(SessionSummary::new(&session, &[]).is_none());
+
+        let mut titles = vec![title("cargo build \u{2014} 2 errors", 101)];
+        titles.extend((0..3).map(|_| title("git status", 0)));
+        titles.extend((0..6).map(|i| title(&format!("ls {}", i), 0)));
+        let summary = SessionSummary::new(&session, &titles).unwrap();
+        assert_eq!(summary.headline(), "10 commands, 1 failed");
+        assert_eq!(summary.highlights, ["cargo build \u{2014} 2 errors", "ls 2", "ls 3", "ls 4", "ls 5"]);
+
+        let summary = SessionSummary::new(&session, &titles[..4]).unwrap();
+        assert_eq!(summary.highlights, ["cargo build \u{2014} 2 errors", "git status \u{00D7}3"]);
+    }
+}
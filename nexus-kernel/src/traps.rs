@@ -0,0 +1,383 @@
+//! Trap delivery — running the commands `trap` registers.
+//!
+//! `trap` records an action per signal in [`ShellState::traps`]; this module
+//! makes them fire:
+//! - Real signals (INT, TERM, HUP, USR1, CHLD, ...) are caught only while a
+//!   trap is set for them. The handler just marks them pending; the
+//!   evaluator runs their commands before the next command, and
+//!   [`crate::Kernel::reap_jobs`] while the shell is idle.
+//! - Ctrl-C in the UI reaches a block the kernel is running through
+//!   [`interrupt`]: its foreground process gets SIGINT and the rest of the
+//!   block is skipped, unless INT is trapped (the trap runs and the block
+//!   goes on) or ignored (nothing happens).
+//! - `EXIT` runs when the kernel is torn down, at `exit`, and at the end of
+//!   a subshell; `ERR` after a command fails outside a condition.
+//!
+//! Trap keys are the numbers [`parse_signal`] gives, the same on every
+//! platform; [`os_signal`] maps them to the platform's signals by name.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use nexus_api::BlockId;
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+use crate::commands::CommandRegistry;
+use crate::error::ShellError;
+use crate::replay::EventSender;
+use crate::state::TrapAction;
+use crate::ShellState;
+
+/// Runs when the shell exits.
+pub const EXIT: i32 = 0;
+/// Runs after a command fails.
+pub const ERR: i32 = -1;
+/// Runs before each command (accepted, not run).
+pub const DEBUG: i32 = -2;
+/// SIGINT's key.
+const INT: i32 = 2;
+
+/// Signals a trap may catch. Faults (SEGV, ILL, ...) are left alone: their
+/// handler would return into the fault.
+const CATCHABLE: &[Signal] = &[
+    Signal::SIGHUP,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGPIPE,
+    Signal::SIGALRM,
+    Signal::SIGTERM,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+    Signal::SIGCHLD,
+    Signal::SIGCONT,
+];
+
+/// Caught signals not yet delivered, one bit per platform signal number.
+static PENDING: AtomicU64 = AtomicU64::new(0);
+
+/// How signals were handled before a trap caught them, by key.
+static PREVIOUS: LazyLock<Mutex<HashMap<i32, SigAction>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// What Ctrl-C does: one of `INT_DEFAULT`, `INT_IGNORED`, `INT_TRAPPED`.
+static INT_ACTION: AtomicU8 = AtomicU8::new(INT_DEFAULT);
+const INT_DEFAULT: u8 = 0;
+const INT_IGNORED: u8 = 1;
+const INT_TRAPPED: u8 = 2;
+
+/// Blocks Ctrl-C was pressed in, until the evaluator acts on it.
+static INTERRUPTED: LazyLock<Mutex<HashSet<BlockId>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Set `key`'s trap, catching the signal unless the action is the default.
+pub(crate) fn set(state: &mut ShellState, key: i32, action: TrapAction) {
+    let int_action = match action {
+        TrapAction::Default => INT_DEFAULT,
+        TrapAction::Ignore => INT_IGNORED,
+        TrapAction::Command(_) => INT_TRAPPED,
+    };
+    if key == INT {
+        INT_ACTION.store(int_action, Ordering::SeqCst);
+    }
+    catch(key, int_action != INT_DEFAULT);
+    state.traps.insert(key, action);
+}
+
+/// Stop catching the signals `state` trapped, when its shell goes away.
+pub(crate) fn release(state: &ShellState) {
+    for &key in state.traps.keys() {
+        if key == INT {
+            INT_ACTION.store(INT_DEFAULT, Ordering::SeqCst);
+        }
+        catch(key, false);
+    }
+}
+
+/// Ctrl-C in the UI for a block the kernel is running. Returns false when
+/// INT is ignored and nothing happens.
+pub fn interrupt(block_id: BlockId) -> bool {
+    if INT_ACTION.load(Ordering::SeqCst) == INT_IGNORED {
+        return false;
+    }
+    INTERRUPTED.lock().unwrap_or_else(|e| e.into_inner()).insert(block_id);
+    crate::commands::cancel_block(block_id);
+    true
+}
+
+/// Whether Ctrl-C was pressed in `block_id` and not yet acted on, so its
+/// process is sent SIGINT rather than SIGTERM.
+pub(crate) fn interrupted(block_id: BlockId) -> bool {
+    INTERRUPTED.lock().unwrap_or_else(|e| e.into_inner()).contains(&block_id)
+}
+
+/// Drop a Ctrl-C that arrived after the block's last command.
+pub(crate) fn forget(block_id: BlockId) {
+    INTERRUPTED.lock().unwrap_or_else(|e| e.into_inner()).remove(&block_id);
+}
+
+/// Run the traps for signals that arrived since the last call. A Ctrl-C in
+/// `block_id` with INT untrapped is [`ShellError::Interrupted`], which ends
+/// the block.
+pub(crate) fn deliver(
+    state: &mut ShellState,
+    events: &EventSender,
+    commands: &CommandRegistry,
+    block_id: Option<BlockId>,
+) -> anyhow::Result<()> {
+    if let Some(id) = block_id
+        && INTERRUPTED.lock().unwrap_or_else(|e| e.into_inner()).remove(&id)
+    {
+        match state.traps.get(&INT) {
+            Some(TrapAction::Command(_)) => run(state, INT, events, commands, block_id)?,
+            Some(TrapAction::Ignore) => {}
+            Some(TrapAction::Default) | None => return Err(ShellError::Interrupted.into()),
+        }
+    }
+
+    let trapped: Vec<(i32, Signal)> = state
+        .traps
+        .iter()
+        .filter(|(_, action)| matches!(action, TrapAction::Command(_)))
+        .filter_map(|(&key, _)| Some((key, os_signal(key)?)))
+        .collect();
+    let mask = trapped.iter().fold(0u64, |mask, &(_, signal)| mask | bit(signal));
+    if mask == 0 {
+        return Ok(());
+    }
+    let fired = PENDING.fetch_and(!mask, Ordering::SeqCst) & mask;
+    for (key, signal) in trapped {
+        if fired & bit(signal) != 0 {
+            run(state, key, events, commands, block_id)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `key`'s trap command, if it has one. `$?` is kept across it, and
+/// the trap doesn't fire again from inside itself (an ERR trap whose
+/// command fails).
+pub(crate) fn run(
+    state: &mut ShellState,
+    key: i32,
+    events: &EventSender,
+    commands: &CommandRegistry,
+    block_id: Option<BlockId>,
+) -> anyhow::Result<()> {
+    let Some(TrapAction::Command(source)) = state.traps.get(&key) else {
+        return Ok(());
+    };
+    let ast = match crate::parser::Parser::new()?.parse(source) {
+        Ok(ast) => ast,
+        Err(e) => {
+            eprintln!("trap: {}: {}", signal_name(key), e);
+            return Ok(());
+        }
+    };
+
+    let action = state.traps.remove(&key);
+    let status = state.last_exit_code;
+    let result = crate::eval::execute_with_block_id(state, &ast, events, commands, block_id);
+    if let Some(action) = action {
+        state.traps.entry(key).or_insert(action);
+    }
+    state.last_exit_code = status;
+    result.map(|_| ())
+}
+
+/// The platform signal a trap key names, when a trap may catch it.
+fn os_signal(key: i32) -> Option<Signal> {
+    let signal: Signal = signal_name(key).parse().ok()?;
+    CATCHABLE.contains(&signal).then_some(signal)
+}
+
+fn bit(signal: Signal) -> u64 {
+    1 << (signal as i32 as u64 % 64)
+}
+
+extern "C" fn on_signal(signo: nix::libc::c_int) {
+    if let Ok(signal) = Signal::try_from(signo) {
+        PENDING.fetch_or(bit(signal), Ordering::SeqCst);
+    }
+}
+
+/// Start or stop catching `key`'s signal, restoring how it was handled
+/// before.
+fn catch(key: i32, on: bool) {
+    let Some(signal) = os_signal(key) else {
+        return;
+    };
+    let mut previous = PREVIOUS.lock().unwrap_or_else(|e| e.into_inner());
+    if on && !previous.contains_key(&key) {
+        // A signal from before the trap was set isn't for it.
+        PENDING.fetch_and(!bit(signal), Ordering::SeqCst);
+        let action = SigAction::new(SigHandler::Handler(on_signal), SaFlags::SA_RESTART, SigSet::empty());
+        // SAFETY: the handler only sets a bit in an atomic.
+        match unsafe { sigaction(signal, &action) } {
+            Ok(old) => {
+                previous.insert(key, old);
+            }
+            Err(e) => tracing::warn!("trap: cannot catch {}: {}", signal, e),
+        }
+    } else if !on && let Some(old) = previous.remove(&key) {
+        // SAFETY: puts back the disposition replaced above.
+        let _ = unsafe { sigaction(signal, &old) };
+    }
+}
+
+/// A trap key from a signal number or name (`INT`, `SIGINT`, `EXIT`,
+/// `ERR`).
+pub(crate) fn parse_signal(s: &str) -> Option<i32> {
+    // Try as number
+    if let Ok(n) = s.parse::<i32>() {
+        return Some(n);
+    }
+
+    // Try as signal name
+    let s = s.to_uppercase();
+    let s = s.strip_prefix("SIG").unwrap_or(&s);
+
+    match s {
+        "EXIT" | "0" => Some(EXIT),
+        "HUP" => Some(1),
+        "INT" => Some(2),
+        "QUIT" => Some(3),
+        "ILL" => Some(4),
+        "TRAP" => Some(5),
+        "ABRT" => Some(6),
+        "KILL" => Some(9),
+        "USR1" => Some(10),
+        "SEGV" => Some(11),
+        "USR2" => Some(12),
+        "PIPE" => Some(13),
+        "ALRM" => Some(14),
+        "TERM" => Some(15),
+        "CHLD" => Some(17),
+        "CONT" => Some(18),
+        "STOP" => Some(19),
+        "ERR" => Some(ERR),     // Special: on error
+        "DEBUG" => Some(DEBUG), // Special: before each command
+        _ => None,
+    }
+}
+
+/// A trap key's name, as `trap -p` shows it.
+pub(crate) fn signal_name(sig: i32) -> &'static str {
+    match sig {
+        EXIT => "EXIT",
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        9 => "SIGKILL",
+        10 => "SIGUSR1",
+        11 => "SIGSEGV",
+        12 => "SIGUSR2",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        17 => "SIGCHLD",
+        18 => "SIGCONT",
+        19 => "SIGSTOP",
+        ERR => "ERR",
+        DEBUG => "DEBUG",
+        _ => "UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // =========================================================================
+    // parse_signal tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_signal_numeric() {
+        assert_eq!(parse_signal("1"), Some(1));
+        assert_eq!(parse_signal("2"), Some(2));
+        assert_eq!(parse_signal("9"), Some(9));
+        assert_eq!(parse_signal("15"), Some(15));
+        assert_eq!(parse_signal("0"), Some(0));
+    }
+
+    #[test]
+    fn test_parse_signal_named() {
+        assert_eq!(parse_signal("HUP"), Some(1));
+        assert_eq!(parse_signal("INT"), Some(2));
+        assert_eq!(parse_signal("QUIT"), Some(3));
+        assert_eq!(parse_signal("KILL"), Some(9));
+        assert_eq!(parse_signal("TERM"), Some(15));
+        assert_eq!(parse_signal("USR1"), Some(10));
+        assert_eq!(parse_signal("USR2"), Some(12));
+    }
+
+    #[test]
+    fn test_parse_signal_with_sig_prefix() {
+        assert_eq!(parse_signal("SIGHUP"), Some(1));
+        assert_eq!(parse_signal("SIGINT"), Some(2));
+        assert_eq!(parse_signal("SIGKILL"), Some(9));
+        assert_eq!(parse_signal("SIGTERM"), Some(15));
+    }
+
+    #[test]
+    fn test_parse_signal_case_insensitive() {
+        assert_eq!(parse_signal("hup"), Some(1));
+        assert_eq!(parse_signal("Hup"), Some(1));
+        assert_eq!(parse_signal("sigint"), Some(2));
+        assert_eq!(parse_signal("SigInt"), Some(2));
+    }
+
+    #[test]
+    fn test_parse_signal_special() {
+        assert_eq!(parse_signal("EXIT"), Some(0));
+        assert_eq!(parse_signal("ERR"), Some(-1));
+        assert_eq!(parse_signal("DEBUG"), Some(-2));
+    }
+
+    #[test]
+    fn test_parse_signal_invalid() {
+        assert_eq!(parse_signal("INVALID"), None);
+        assert_eq!(parse_signal("SIGFOO"), None);
+        assert_eq!(parse_signal(""), None);
+        assert_eq!(parse_signal("abc"), None);
+    }
+
+    // =========================================================================
+    // signal_name tests
+    // =========================================================================
+
+    #[test]
+    fn test_signal_name_common() {
+        assert_eq!(signal_name(0), "EXIT");
+        assert_eq!(signal_name(1), "SIGHUP");
+        assert_eq!(signal_name(2), "SIGINT");
+        assert_eq!(signal_name(9), "SIGKILL");
+        assert_eq!(signal_name(15), "SIGTERM");
+    }
+
+    #[test]
+    fn test_signal_name_special() {
+        assert_eq!(signal_name(-1), "ERR");
+        assert_eq!(signal_name(-2), "DEBUG");
+    }
+
+    #[test]
+    fn test_signal_name_unknown() {
+        assert_eq!(signal_name(99), "UNKNOWN");
+        assert_eq!(signal_name(-99), "UNKNOWN");
+        assert_eq!(signal_name(1000), "UNKNOWN");
+    }
+
+    #[test]
+    fn test_os_signal() {
+        assert_eq!(os_signal(10), Some(Signal::SIGUSR1));
+        assert_eq!(os_signal(17), Some(Signal::SIGCHLD));
+        // Faults and pseudo-signals are never caught.
+        assert_eq!(os_signal(11), None);
+        assert_eq!(os_signal(9), None);
+        assert_eq!(os_signal(EXIT), None);
+        assert_eq!(os_signal(ERR), None);
+    }
+}
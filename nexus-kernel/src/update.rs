@@ -0,0 +1,268 @@
+//! Opt-in update checks.
+//!
+//! Nothing here runs unless `[updates] check = true` is set in the user
+//! config. The feed is a "latest release" document in the GitHub releases
+//! API format: a tag, markdown release notes, and downloadable assets.
+//! Requests go through `curl`, which every macOS install has, rather than
+//! an HTTP stack linked into the binary.
+//!
+//! Staging downloads the macOS asset into `~/.nexus/updates` and checks it
+//! against the `<asset>.sha256` published next to it; a release without
+//! one is refused. Installing it is left to the user.
+//!
+//! Both fail at once while [`crate::network`] says the machine is offline.
+
+use std::cmp::Ordering;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Where releases are published.
+pub const DEFAULT_FEED: &str = "https://api.github.com/repos/Deep-ai-inc/nexus/releases/latest";
+
+/// The version this build reports.
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Longest a feed request or download may take, in seconds.
+const FEED_TIMEOUT_SECS: u32 = 20;
+const DOWNLOAD_TIMEOUT_SECS: u32 = 600;
+
+/// A published release.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    /// Without the tag's leading `v`.
+    pub version: String,
+    /// Release notes, in markdown.
+    pub notes: String,
+    /// Web page for the release.
+    pub page: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Asset {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct FeedRelease {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    html_url: Option<String>,
+    #[serde(default)]
+    assets: Vec<FeedAsset>,
+}
+
+#[derive(Deserialize)]
+struct FeedAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    /// The download for macOS: a `.dmg`, or failing that a `.zip`, whose
+    /// name mentions macOS (or no platform at all).
+    pub fn macos_asset(&self) -> Option<&Asset> {
+        let others = ["linux", "windows", "win64", ".exe"];
+        let candidates = || {
+            self.assets.iter().filter(move |a| {
+                let name = a.name.to_ascii_lowercase();
+                !others.iter().any(|o| name.contains(o))
+            })
+        };
+        candidates()
+            .find(|a| a.name.ends_with(".dmg"))
+            .or_else(|| candidates().find(|a| a.name.ends_with(".zip")))
+    }
+
+    /// The published SHA-256 of `asset`, if any.
+    fn checksum_for(&self, asset: &Asset) -> Option<&Asset> {
+        let name = format!("{}.sha256", asset.name);
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// Parse a feed document.
+pub fn parse_release(json: &str) -> anyhow::Result<Release> {
+    let feed: FeedRelease = serde_json::from_str(json).context("update: unexpected release feed format")?;
+    let version = feed.tag_name.trim_start_matches('v').to_string();
+    if version.is_empty() {
+        bail!("update: release has no version");
+    }
+    Ok(Release {
+        version,
+        notes: feed.body.unwrap_or_default(),
+        page: feed.html_url,
+        assets: feed
+            .assets
+            .into_iter()
+            .map(|a| Asset { name: a.name, url: a.browser_download_url })
+            .collect(),
+    })
+}
+
+/// Compare dotted versions numerically; a pre-release (`1.2.0-beta.1`)
+/// sorts before the release it precedes.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn split(v: &str) -> (Vec<u64>, Option<&str>) {
+        let (core, pre) = match v.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (v, None),
+        };
+        (core.split('.').map(|p| p.parse().unwrap_or(0)).collect(), pre)
+    }
+    let (a_core, a_pre) = split(a);
+    let (b_core, b_pre) = split(b);
+    let len = a_core.len().max(b_core.len());
+    let part = |core: &[u64], i| core.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| part(&a_core, i).cmp(&part(&b_core, i)))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| match (a_pre, b_pre) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        })
+}
+
+/// The release at `feed` if it is newer than this build.
+pub fn check(feed: &str) -> anyhow::Result<Option<Release>> {
+    crate::network::require_online("update")?;
+    let body = curl(&["--max-time", &FEED_TIMEOUT_SECS.to_string(), "-H", "Accept: application/vnd.github+json", feed])
+        .context("update: failed to fetch the release feed")?;
+    let release = parse_release(&String::from_utf8_lossy(&body))?;
+    Ok((compare_versions(&release.version, CURRENT_VERSION) == Ordering::Greater).then_some(release))
+}
+
+/// `~/.nexus/updates`, where downloads are staged.
+pub fn updates_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".nexus").join("updates"))
+}
+
+/// Download `release`'s macOS asset into `dir` and verify it against its
+/// published checksum. Returns the path of the staged file.
+pub fn stage(release: &Release, dir: &Path) -> anyhow::Result<PathBuf> {
+    let asset = release.macos_asset().context("update: this release has no macOS download")?;
+    if asset.name.contains('/') || asset.name.starts_with('.') {
+        bail!("update: refusing asset name '{}'", asset.name);
+    }
+    let checksum = release
+        .checksum_for(asset)
+        .with_context(|| format!("update: refusing {}: no {}.sha256 published to verify it", asset.name, asset.name))?;
+    crate::network::require_online("update")?;
+    std::fs::create_dir_all(dir).with_context(|| format!("update: failed to create {}", dir.display()))?;
+
+    let path = dir.join(&asset.name);
+    let partial = dir.join(format!("{}.part", asset.name));
+    let timeout = DOWNLOAD_TIMEOUT_SECS.to_string();
+    curl(&["--max-time", &timeout, "-o", &partial.to_string_lossy(), &asset.url])
+        .with_context(|| format!("update: failed to download {}", asset.name))?;
+
+    let expected = match curl(&["--max-time", &FEED_TIMEOUT_SECS.to_string(), &checksum.url]) {
+        Ok(expected) => expected,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e.context(format!("update: failed to download {}", checksum.name)));
+        }
+    };
+    let expected = String::from_utf8_lossy(&expected);
+    let expected = expected.split_whitespace().next().unwrap_or_default();
+    if let Err(e) = verify_sha256(&partial, expected) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, &path).with_context(|| format!("update: failed to stage {}", path.display()))?;
+    Ok(path)
+}
+
+/// Fail unless the file at `path` hashes to `expected` (hex).
+fn verify_sha256(path: &Path, expected: &str) -> anyhow::Result<()> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("update: failed to read {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let actual = format!("{:x}", hasher.finalize());
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("update: checksum mismatch for {} (expected {}, got {})", path.display(), expected, actual);
+    }
+    Ok(())
+}
+
+/// Run `curl -fsSL` with `args`, returning its output.
+pub(crate) fn curl(args: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let output = Command::new("curl").arg("-fsSL").args(args).output().context("failed to run curl")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r###"{
+        "tag_name": "v0.4.0",
+        "html_url": "https://github.com/Deep-ai-inc/nexus/releases/tag/v0.4.0",
+        "body": "## What's new\n- Faster tables",
+        "assets": [
+            {"name": "nexus-0.4.0-linux.tar.gz", "browser_download_url": "https://example.com/linux"},
+            {"name": "Nexus-0.4.0-macos.zip", "browser_download_url": "https://example.com/zip"},
+            {"name": "Nexus-0.4.0.dmg", "browser_download_url": "https://example.com/dmg"},
+            {"name": "Nexus-0.4.0.dmg.sha256", "browser_download_url": "https://example.com/sha"}
+        ]
+    }"###;
+
+    #[test]
+    fn test_parse_release_and_pick_macos_asset() {
+        let release = parse_release(FEED).unwrap();
+        assert_eq!(release.version, "0.4.0");
+        assert!(release.notes.starts_with("## What's new"));
+        let asset = release.macos_asset().unwrap();
+        assert_eq!(asset.name, "Nexus-0.4.0.dmg");
+        assert_eq!(release.checksum_for(asset).unwrap().url, "https://example.com/sha");
+
+        assert!(parse_release(r#"{"message": "Not Found"}"#).is_err());
+    }
+
+    #[test]
+    fn test_stage_refuses_release_without_checksum() {
+        let release = parse_release(&FEED.replace("Nexus-0.4.0.dmg.sha256", "notes.txt")).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let err = stage(&release, dir.path()).unwrap_err();
+        assert!(err.to_string().contains("no Nexus-0.4.0.dmg.sha256"), "{}", err);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("0.10.0", "0.9.3"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.0-beta.1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.1-beta.1", "1.0.0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_verify_sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asset");
+        std::fs::write(&path, "abc").unwrap();
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        verify_sha256(&path, digest).unwrap();
+        assert!(verify_sha256(&path, &digest.replace('b', "c")).is_err());
+    }
+}
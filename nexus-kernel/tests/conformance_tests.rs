@@ -0,0 +1,38 @@
+//! Shell conformance: the built-in corpus must match bash, apart from
+//! cases marked `xfail:` in `src/conformance/corpus.txt`.
+//!
+//! Skipped (not failed) on machines without `/bin/bash`.
+
+use nexus_kernel::conformance::{self, Verdict};
+
+#[test]
+fn corpus_matches_bash() {
+    let results = conformance::run_corpus(None);
+    let regressions: Vec<String> = results
+        .iter()
+        .filter(|r| r.is_regression())
+        .map(|r| {
+            format!(
+                "[{}] {}\n    {}: nexus exit {} {:?}, bash {:?}",
+                r.case.category,
+                r.case.script,
+                r.verdict.as_str(),
+                r.nexus.exit_code,
+                r.nexus.stdout,
+                r.bash.as_ref().map(|b| (b.exit_code, b.stdout.as_str())),
+            )
+        })
+        .collect();
+    assert!(regressions.is_empty(), "{} conformance regressions:\n{}", regressions.len(), regressions.join("\n"));
+}
+
+#[test]
+fn xfail_cases_still_fail() {
+    // An xfail that passes should have its marker removed.
+    let fixed: Vec<String> = conformance::run_corpus(None)
+        .into_iter()
+        .filter(|r| r.case.xfail && r.verdict == Verdict::Pass)
+        .map(|r| r.case.script)
+        .collect();
+    assert!(fixed.is_empty(), "now passing, remove `xfail:`: {:#?}", fixed);
+}
@@ -814,6 +814,17 @@ fn test_prev_output_chain() {
     t.expect_int("_ | count", 10);
 }
 
+#[test]
+fn test_indexed_output_continuation() {
+    // `_N | cmd` pipes from any stored output, not just the last three
+    let mut t = PipelineTest::new();
+    t.run("seq 1 9");
+    for _ in 0..4 {
+        t.run("seq 1 2");
+    }
+    t.expect_int("_5 | count", 9);
+}
+
 // --- Combining multiple data sources ---
 
 #[test]
@@ -1727,9 +1738,46 @@ fn test_classify_external_commands_as_pty() {
     assert_eq!(kernel.classify_command("cargo build"), CommandClassification::Pty);
     assert_eq!(kernel.classify_command("npm install"), CommandClassification::Pty);
     assert_eq!(kernel.classify_command("htop"), CommandClassification::Pty);
+    assert_eq!(kernel.classify_command("nexus exec -c web ls | head"), CommandClassification::Pty);
     assert_eq!(kernel.classify_command("ssh user@host"), CommandClassification::RemoteTransport);
 }
 
+#[test]
+fn test_command_status() {
+    use nexus_kernel::executables::CommandStatus;
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin = tempfile::tempdir().unwrap();
+    let tool = bin.path().join("frobnicate");
+    std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let (mut kernel, _rx) = Kernel::new().expect("Failed to create kernel");
+    kernel.state_mut().set_env("PATH", bin.path().to_string_lossy());
+    // The PATH scan runs in the background; nothing is claimed until it is done.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while kernel.command_status("frobnicate") == CommandStatus::Unchecked && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    assert_eq!(kernel.command_status("frobnicate --all"), CommandStatus::Known);
+    assert_eq!(kernel.command_status("DEBUG=1 frobnicate"), CommandStatus::Known);
+    assert_eq!(
+        kernel.command_status("frobnicat x"),
+        CommandStatus::Unknown { suggestion: Some("frobnicate".into()) }
+    );
+    assert_eq!(kernel.command_status("xyzzy"), CommandStatus::Unknown { suggestion: None });
+    // Native commands, builtins and keywords need no PATH entry
+    assert_eq!(kernel.command_status("ls -la"), CommandStatus::Known);
+    assert_eq!(kernel.command_status("cd /tmp"), CommandStatus::Known);
+    assert_eq!(kernel.command_status("for i in 1 2; do echo $i; done"), CommandStatus::Known);
+    assert_eq!(kernel.command_status("$EDITOR notes"), CommandStatus::Unchecked);
+    assert_eq!(kernel.command_status("./no-such-script"), CommandStatus::Unknown { suggestion: None });
+
+    kernel.execute("alias frob='frobnicate'").unwrap();
+    assert_eq!(kernel.command_status("frob"), CommandStatus::Known);
+}
+
 #[test]
 fn test_classify_remote_transport() {
     let (kernel, _rx) = Kernel::new().expect("Failed to create kernel");
@@ -1747,6 +1795,18 @@ fn test_classify_remote_transport() {
     assert_eq!(kernel.classify_command("docker build ."), CommandClassification::Pty);
 }
 
+#[test]
+fn test_classify_sudo_as_pty() {
+    let (kernel, _rx) = Kernel::new().expect("Failed to create kernel");
+    // sudo needs a terminal for its password prompt, even inside pipelines
+    assert_eq!(kernel.classify_command("sudo ls"), CommandClassification::Pty);
+    assert_eq!(kernel.classify_command("sudo cat /etc/shadow | grep root"), CommandClassification::Pty);
+    assert_eq!(kernel.classify_command("echo hi | sudo tee /etc/motd"), CommandClassification::Pty);
+    assert_eq!(kernel.classify_command("make && sudo make install"), CommandClassification::Pty);
+    // Mentioning sudo as an argument doesn't count
+    assert_eq!(kernel.classify_command("man sudo | head"), CommandClassification::Kernel);
+}
+
 #[test]
 fn test_classify_external_with_pipe_as_kernel() {
     let (kernel, _rx) = Kernel::new().expect("Failed to create kernel");
@@ -1775,6 +1835,8 @@ fn test_classify_keywords_as_kernel() {
     assert_eq!(kernel.classify_command("while true; do echo loop; done"), CommandClassification::Kernel);
     assert_eq!(kernel.classify_command("for x in a b c; do echo $x; done"), CommandClassification::Kernel);
     assert_eq!(kernel.classify_command("case $x in a) echo a;; esac"), CommandClassification::Kernel);
+    assert_eq!(kernel.classify_command("((i++))"), CommandClassification::Kernel);
+    assert_eq!(kernel.classify_command("for ((i=0; i<3; i++)); do echo $i; done"), CommandClassification::Kernel);
 }
 
 // ============================================================================
@@ -1905,6 +1967,48 @@ fn test_watch_pipeline_native() {
     let _ = got_output;
 }
 
+#[test]
+fn test_watch_on_change_reruns_with_trigger() {
+    // A file matching the pattern appears: the block shows it, then re-runs
+    let dir = tempfile::tempdir().unwrap();
+    let (mut kernel, rx) = Kernel::new().expect("Failed to create kernel");
+    kernel.execute(&format!("cd {}", dir.path().display())).unwrap();
+    let block_id = nexus_api::BlockId(8003);
+    let bid = block_id;
+
+    let created = dir.path().join("src");
+    std::fs::create_dir(&created).unwrap();
+    std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        std::fs::write(created.join("main.rs"), "fn main() {}").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(1500));
+        while !nexus_kernel::commands::cancel_block(bid) {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    });
+
+    let result = kernel.execute_with_block_id("watch --on-change src/**/*.rs echo rebuilt", Some(block_id));
+    assert!(result.is_ok());
+
+    let mut trigger = String::new();
+    let mut streaming_count = 0;
+    let mut rx = rx;
+    loop {
+        match rx.try_recv() {
+            Ok(ShellEvent::StderrChunk { block_id: bid, data }) if bid == block_id => {
+                trigger.push_str(&String::from_utf8_lossy(&data));
+            }
+            Ok(ShellEvent::StreamingUpdate { block_id: bid, .. }) if bid == block_id => {
+                streaming_count += 1;
+            }
+            Ok(_) => continue,
+            Err(_) => break,
+        }
+    }
+    assert_eq!(trigger, "watch: no files match src/**/*.rs\nwatch: changed src/main.rs\n");
+    assert_eq!(streaming_count, 1);
+}
+
 #[test]
 fn test_watch_no_args_is_error() {
     let (mut kernel, _rx) = Kernel::new().expect("Failed to create kernel");
@@ -1918,3 +2022,580 @@ fn test_watch_only_flags_is_error() {
     let result = kernel.execute("watch -n 1");
     assert!(result.is_err(), "watch -n 1 with no command should be a parse error");
 }
+
+#[test]
+fn test_native_failure_is_a_structured_error() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "hi").unwrap();
+    let (mut kernel, mut rx) = Kernel::new().expect("Failed to create kernel");
+    kernel.state_mut().cwd = dir.path().to_path_buf();
+    kernel.execute("cat note.txt").unwrap();
+
+    let mut error = None;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            ShellEvent::CommandError { error: e, .. } => error = Some(e),
+            ShellEvent::StderrChunk { data, .. } => panic!("unexpected stderr: {}", String::from_utf8_lossy(&data)),
+            _ => {}
+        }
+    }
+    let error = error.expect("Expected CommandError");
+    assert_eq!(error.command, "cat");
+    assert_eq!(error.kind, nexus_api::CommandErrorKind::NotFound);
+    assert_eq!(error.path.as_deref(), Some(std::path::Path::new("note.txt")));
+    assert!(error.errno.is_some());
+    assert_eq!(error.suggestion.as_deref(), Some("did you mean 'notes.txt'?"));
+}
+
+#[test]
+fn test_project_config_cannot_define_aliases() {
+    let root = tempfile::tempdir().unwrap();
+    let project = root.path().join("project");
+    std::fs::create_dir_all(project.join(".nexus")).unwrap();
+    std::fs::write(project.join(".nexus/config.toml"), "[aliases]\nls = \"seq 3\"\n").unwrap();
+
+    // Entering a checkout must not redefine commands.
+    let mut t = PipelineTest::new();
+    t.run(&format!("cd {}", project.display()));
+    assert!(t.kernel.state().alias("ls").is_none());
+}
+
+#[test]
+fn test_project_pager_capture_opens_viewer() {
+    let root = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(root.path().join(".nexus")).unwrap();
+    std::fs::write(root.path().join(".nexus/config.toml"), "[pager.commands]\nsh = \"capture\"\n").unwrap();
+
+    let mut t = PipelineTest::new();
+    t.run(&format!("cd {}", root.path().display()));
+    let value = t.run("sh -c 'echo \"pager=$GIT_PAGER\"'");
+    let Some(nexus_api::DomainValue::Interactive(req)) = value.as_ref().and_then(Value::as_domain) else {
+        panic!("Expected Interactive value, got {:?}", value);
+    };
+    assert!(matches!(req.viewer, nexus_api::ViewerKind::Pager));
+    assert_eq!(req.content, Value::String("pager=cat\n".to_string()));
+    t.expect_string("_", "pager=cat\n");
+}
+
+#[test]
+fn test_in_dir_scopes_cwd_and_env() {
+    let root = tempfile::tempdir().unwrap();
+    let root_path = root.path().canonicalize().unwrap();
+    std::fs::create_dir(root_path.join("sub")).unwrap();
+
+    let mut t = PipelineTest::new();
+    t.run(&format!("cd {}", root_path.display()));
+    assert_eq!(t.run("in sub pwd"), Some(Value::Path(root_path.join("sub"))));
+    t.expect_string("in sub GREETING=hi printenv GREETING", "hi");
+
+    // Neither the directory nor the variable outlives the command.
+    assert_eq!(t.run("pwd"), Some(Value::Path(root_path.clone())));
+    assert!(t.kernel.state().get_env("GREETING").is_none());
+    assert_eq!(t.kernel.classify_command("in sub ls"), t.kernel.classify_command("ls"));
+
+    t.run("in missing pwd");
+    assert_eq!(t.kernel.state().last_exit_code, 1);
+}
+
+#[test]
+fn test_xtrace_and_profile() {
+    let mut t = PipelineTest::new();
+    t.run("set -x");
+    t.kernel.execute_with_block_id("seq 1 3 | sum", Some(nexus_api::BlockId(9000))).unwrap();
+    let mut traces = Vec::new();
+    while let Ok(event) = t.rx.try_recv() {
+        if let ShellEvent::Trace { depth, line, .. } = event {
+            traces.push((depth, line));
+        }
+    }
+    assert_eq!(traces, [(1, "seq 1 3".to_string()), (1, "sum".to_string())]);
+    t.run("set +x");
+
+    t.run("tally() { seq 1 50 | sum; }");
+    let (columns, rows) = t.expect_table("profile tally");
+    assert_eq!(columns[0].name, "name");
+    let names: Vec<_> = rows.iter().map(|row| row[0].to_text()).collect();
+    assert_eq!(names[0], "tally");
+    assert!(names.contains(&"seq".to_string()) && names.contains(&"sum".to_string()));
+}
+
+#[test]
+fn test_resource_usage_and_time() {
+    let mut t = PipelineTest::new();
+    let block_id = nexus_api::BlockId(9010);
+    t.kernel.execute_with_block_id("sh -c 'exit 3'", Some(block_id)).unwrap();
+    let mut order = Vec::new();
+    while let Ok(event) = t.rx.try_recv() {
+        match event {
+            ShellEvent::ResourceUsage { block_id: id, usage } if id == block_id => {
+                assert!(usage.max_rss > 0);
+                order.push("usage");
+            }
+            ShellEvent::CommandFinished { block_id: id, exit_code, .. } if id == block_id => {
+                assert_eq!(exit_code, 3);
+                order.push("finished");
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(order, ["usage", "finished"]);
+
+    let Some(Value::Record(fields)) = t.run("time sh -c 'exit 2'") else {
+        panic!("expected a record from time");
+    };
+    let names: Vec<_> = fields.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["real_ms", "user_ms", "sys_ms", "max_rss", "blocks_in", "blocks_out", "exit"]);
+    assert_eq!(fields[6].1, Value::Int(2));
+    assert_eq!(t.kernel.state().last_exit_code, 2);
+}
+
+#[test]
+fn test_debug_steps_through_script() {
+    use nexus_api::BlockId;
+    use nexus_kernel::debug::{self, DebugAction};
+
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("greet.sh"), "echo one\nGREETING=hi\necho $GREETING two\n").unwrap();
+
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    kernel.state_mut().set_cwd(dir.path().to_path_buf()).unwrap();
+    let block_id = BlockId(9100);
+    let run = std::thread::spawn(move || kernel.execute_with_block_id("debug greet.sh", Some(block_id)).unwrap());
+
+    let mut paused = Vec::new();
+    while paused.len() < 3 {
+        if let Ok(ShellEvent::DebugPaused { step, total, command, .. }) = rx.blocking_recv() {
+            paused.push((step, total, command));
+            debug::send(block_id, if step < 3 { DebugAction::Step } else { DebugAction::Continue });
+        }
+    }
+    assert_eq!(run.join().unwrap(), 0);
+    assert_eq!(paused[0], (1, 3, "echo one".to_string()));
+    assert_eq!(paused[2], (3, 3, "echo hi two".to_string()));
+
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    kernel.state_mut().set_cwd(dir.path().to_path_buf()).unwrap();
+    let run = std::thread::spawn(move || kernel.execute_with_block_id("debug greet.sh", Some(block_id)).unwrap());
+    while !matches!(rx.blocking_recv(), Ok(ShellEvent::DebugPaused { .. })) {}
+    debug::send(block_id, DebugAction::Abort);
+    assert_eq!(run.join().unwrap(), 130);
+}
+
+#[test]
+fn test_background_job_runs_and_is_waited_for() {
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    let started = std::time::Instant::now();
+    assert_eq!(kernel.execute("sleep 0.3 &").unwrap(), 0);
+    assert!(started.elapsed() < std::time::Duration::from_millis(300));
+    assert_eq!(kernel.state().jobs.len(), 1);
+    assert!(kernel.state().last_bg_pid.is_some());
+
+    assert_eq!(kernel.execute("wait").unwrap(), 0);
+    assert!(kernel.state().jobs.is_empty());
+
+    let mut states = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let ShellEvent::JobStateChanged { job_id, command, state } = event {
+            assert_eq!((job_id, command.as_str()), (1, "sleep 0.3"));
+            states.push(state);
+        }
+    }
+    assert_eq!(states, vec![nexus_api::JobState::Running, nexus_api::JobState::Done(0)]);
+}
+
+#[test]
+fn test_background_refuses_what_cannot_be_a_job() {
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    let cwd = kernel.state().cwd.clone();
+    let started = std::time::Instant::now();
+    for line in ["cd / &", "sleep 0.3 | cat &", "(sleep 0.3) &", "true && sleep 0.3 &"] {
+        assert_eq!(kernel.execute(line).unwrap(), 1, "{}", line);
+    }
+    // Nothing ran, in the foreground or as a job.
+    assert!(started.elapsed() < std::time::Duration::from_millis(300));
+    assert_eq!(kernel.state().cwd, cwd);
+    assert!(kernel.state().jobs.is_empty());
+
+    let errors = std::iter::from_fn(|| rx.try_recv().ok())
+        .filter(|event| matches!(event, ShellEvent::CommandError { error, .. } if error.message.contains("background")))
+        .count();
+    assert_eq!(errors, 4);
+}
+
+#[test]
+fn test_disown_removes_job() {
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    kernel.execute("sleep 5 &").unwrap();
+    let pid = kernel.state().jobs[0].pgid;
+    kernel.execute("disown %1").unwrap();
+    assert!(kernel.state().jobs.is_empty());
+    let _ = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGKILL);
+
+    let disowned = std::iter::from_fn(|| rx.try_recv().ok())
+        .any(|event| matches!(event, ShellEvent::JobStateChanged { state: nexus_api::JobState::Disowned, .. }));
+    assert!(disowned);
+}
+
+// =============================================================================
+// Here-documents and here-strings
+// =============================================================================
+
+#[test]
+fn test_heredoc_feeds_native_command() {
+    let mut t = PipelineTest::new();
+    t.run("NAME=world");
+    t.expect_string("cat <<EOF\nhello $NAME\nEOF", "hello world\n");
+    t.expect_string("cat <<'EOF'\nhello $NAME\nEOF", "hello $NAME\n");
+}
+
+#[test]
+fn test_heredoc_into_native_pipeline() {
+    let mut t = PipelineTest::new();
+    t.expect_int("cat <<EOF | wc -l\na\nb\nc\nEOF", 3);
+}
+
+#[test]
+fn test_herestring_feeds_native_command() {
+    let mut t = PipelineTest::new();
+    t.expect_int("wc -w <<< \"one two three\"", 3);
+}
+
+#[test]
+fn test_heredoc_feeds_external_command() {
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    assert_eq!(kernel.execute("/bin/cat <<EOF\nfrom a heredoc\nEOF").unwrap(), 0);
+
+    let mut stdout = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let ShellEvent::StdoutChunk { data, .. } = event {
+            stdout.extend(data);
+        }
+    }
+    assert!(String::from_utf8_lossy(&stdout).contains("from a heredoc"));
+}
+
+#[test]
+fn test_substitution_inside_word() {
+    let mut t = PipelineTest::new();
+    t.expect_string("echo \"today is $(echo friday)\"", "today is friday");
+    t.expect_string("echo pre-$(echo mid)-post", "pre-mid-post");
+    t.expect_string("echo $(echo $(echo nested))", "nested");
+}
+
+#[test]
+fn test_substitution_sees_shell_variables() {
+    // Not exported, so only an in-process substitution can see it
+    let mut t = PipelineTest::new();
+    t.run("greeting=hi");
+    t.expect_string("echo \"$(echo $greeting) there\"", "hi there");
+}
+
+#[test]
+fn test_substitution_splits_into_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "alpha\n").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "beta\n").unwrap();
+    std::fs::write(dir.path().join("files.txt"), "a.txt\nb.txt\n").unwrap();
+
+    let mut t = PipelineTest::new();
+    t.run(&format!("cd {}", dir.path().display()));
+    assert_eq!(t.expect_list("ls $(cat files.txt)").len(), 2);
+    t.expect_string("echo \"$(cat files.txt)\" | lines | join ','", "a.txt,b.txt");
+}
+
+#[test]
+fn test_process_substitution() {
+    let mut t = PipelineTest::new();
+    // Native producers are written into the pipe as text
+    t.expect_int("cat <(printf 'alpha\\nbeta\\ngamma\\n') | wc -l", 3);
+    t.expect_int("wc -l < <(echo one; echo two)", 2);
+    t.expect_string("diff <(echo same) <(echo same) && echo equal", "equal");
+
+    // An external consumer keeps the descriptors across exec
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    assert_eq!(kernel.execute("/bin/cat <(/bin/echo from-sh) <(echo native)").unwrap(), 0);
+    let mut stdout = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let ShellEvent::StdoutChunk { data, .. } = event {
+            stdout.extend(data);
+        }
+    }
+    let stdout = String::from_utf8_lossy(&stdout);
+    assert!(stdout.contains("from-sh") && stdout.contains("native"), "got {:?}", stdout);
+
+    // >(cmd) reads what the command writes to its path
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("out.txt");
+    kernel.execute(&format!("/bin/echo written > >(cat > {})", out.display())).unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while std::fs::read_to_string(&out).unwrap_or_default().is_empty() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "written\n");
+}
+
+#[test]
+fn test_substitution_assignment_is_silent() {
+    let mut t = PipelineTest::new();
+    assert!(t.run("x=$(echo captured)").is_none());
+    t.expect_string("echo $x", "captured");
+}
+
+#[test]
+fn test_arithmetic_expansion_is_int() {
+    let mut t = PipelineTest::new();
+    t.run("n=4");
+    t.expect_int("echo $((n * 10 + 2))", 42);
+    t.expect_int("echo $((n * 10 + 2)) | sum", 42);
+    t.expect_string("echo total: $((n + 1))", "total: 5");
+}
+
+#[test]
+fn test_arithmetic_command_and_c_style_for() {
+    let mut t = PipelineTest::new();
+    t.run("i=1; ((i++)); ((i += 10))");
+    t.expect_string("echo $i", "12");
+    t.run("sum=0; for ((k = 1; k <= 4; k++)); do ((sum += k)); done");
+    t.expect_string("echo $sum", "10");
+    t.run("for ((;;)); do ((n++ == 3)) && break; done");
+    t.expect_string("echo $n", "4");
+}
+
+#[test]
+fn test_output_json() {
+    let mut t = PipelineTest::new();
+    t.expect_string("seq 1 3 --output json", "[1,2,3]");
+    // Commands that print text of their own take `--output` as an argument
+    t.expect_string("echo hello --output=json", "hello --output=json");
+    // The flag converts its own stage; later stages see JSON text
+    t.expect_int("seq 1 3 --output json | wc -c", 7);
+    // NEXUS_OUTPUT applies to what leaves the pipeline
+    t.run("NEXUS_OUTPUT=json");
+    t.expect_string("seq 1 3", "[1,2,3]");
+    t.expect_string("seq 1 3 | head -n 2", "[1,2]");
+    t.expect_string("seq 1 2 --output value | wc -l", "2");
+    // $_ keeps the structured value
+    t.expect_string("seq 1 2", "[1,2]");
+    assert_eq!(t.kernel.state().get_last_output(), Some(&Value::List(vec![Value::Int(1), Value::Int(2)])));
+}
+
+#[test]
+fn test_color_policy_for_piped_external_stage() {
+    use nexus_kernel::config::{ColorMode, Origin, Setting};
+
+    fn stdout_of(kernel: &mut Kernel, rx: &mut tokio::sync::broadcast::Receiver<ShellEvent>, cmd: &str) -> String {
+        kernel.execute(cmd).unwrap();
+        // Output is read on threads that may outlive the command.
+        let mut stdout = Vec::new();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !stdout.ends_with(b"\n") && std::time::Instant::now() < deadline {
+            match rx.try_recv() {
+                Ok(ShellEvent::StdoutChunk { data, .. }) => stdout.extend(data),
+                Ok(_) => {}
+                Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+            }
+        }
+        String::from_utf8_lossy(&stdout).into_owned()
+    }
+
+    // The last stage writes to a pipe the block reads, so it is asked for color
+    let (mut kernel, mut rx) = Kernel::new().unwrap();
+    let out = stdout_of(&mut kernel, &mut rx, "echo x | sh -c 'echo force=$CLICOLOR_FORCE'");
+    assert_eq!(out.trim(), "force=1");
+
+    kernel.state_mut().config.color_commands.insert(
+        "sh".to_string(),
+        Setting { value: ColorMode::Never, origin: Origin::User(Default::default()) },
+    );
+    let out = stdout_of(&mut kernel, &mut rx, "echo x | sh -c 'echo no=$NO_COLOR'");
+    assert_eq!(out.trim(), "no=1");
+}
+
+#[test]
+fn test_shopt_glob_options() {
+    let root = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(root.path().join("src/eval")).unwrap();
+    for file in ["a.rs", "b.md", ".hidden", "src/lib.rs", "src/eval/glob.rs"] {
+        std::fs::write(root.path().join(file), "").unwrap();
+    }
+
+    let mut t = PipelineTest::new();
+    t.run(&format!("cd {}", root.path().display()));
+
+    // Dotfiles only match when dotglob is set, like bash.
+    t.expect_string("echo *", "a.rs b.md src");
+    t.run("shopt -s dotglob");
+    t.expect_string("echo *", ".hidden a.rs b.md src");
+    t.run("shopt -u dotglob");
+
+    // Without globstar, ** is just *.
+    t.expect_string("echo **/*.rs", "src/lib.rs");
+    t.run("shopt -s globstar");
+    t.expect_string("echo **/*.rs", "a.rs src/eval/glob.rs src/lib.rs");
+
+    // An unmatched pattern stays as typed unless nullglob is set.
+    t.expect_string("echo *.txt end", "*.txt end");
+    t.run("shopt -s nullglob");
+    t.expect_string("echo *.txt end", "end");
+
+    t.run("shopt -s extglob");
+    t.expect_string("echo !(*.rs|src)", "b.md");
+    t.expect_string("echo @(a|b).*", "a.rs b.md");
+    t.expect_string("case main.rs in *.@(rs|md)) echo yes;; *) echo no;; esac", "yes");
+
+    let options = &t.kernel.state().options;
+    assert!(options.extglob && options.globstar && options.nullglob && !options.dotglob);
+}
+
+#[test]
+fn test_aliases_at_each_command_position() {
+    let mut t = PipelineTest::new();
+    t.run("alias three='seq 3' say='echo ' greeting='hello' loop1=loop2 loop2=loop1");
+
+    t.expect_int("true; three | count", 3);
+    t.expect_int("false || three | count", 3);
+    t.expect_int("if true; then three | count; fi", 3);
+    t.expect_int("N=1 three | count", 3);
+
+    // Only command words are aliases, unless the alias before ends in a blank.
+    t.expect_string("echo three", "three");
+    t.expect_string("echo 'three'", "three");
+    t.expect_string("say greeting", "hello");
+    t.expect_string("cat <<EOF\nthree\nEOF", "three\n");
+
+    // Aliases expanding to each other stop once each has been used.
+    let status = t.kernel.execute("loop1").unwrap();
+    assert_ne!(status, 0);
+}
+
+// ============================================================================
+// Traps
+// ============================================================================
+
+#[test]
+fn test_err_and_exit_traps() {
+    let mut t = PipelineTest::new();
+    t.run("trap 'E=$?' ERR");
+    t.run("false");
+    t.expect_string("echo $E", "1");
+
+    // Tests in conditions aren't errors.
+    t.run("E=none; if false; then :; fi; false || true; false && true");
+    t.expect_string("echo $E", "none");
+
+    // A subshell runs its EXIT trap as it ends; `$?` survives the trap.
+    t.expect_string("(trap 'echo bye' EXIT; echo hi)", "bye");
+    t.run("trap - ERR; (trap 'true' EXIT; false)");
+    t.expect_string("echo $?", "1");
+
+    t.run("trap 'echo x' USR2; trap '' HUP");
+    let (_, rows) = t.expect_table("trap");
+    assert_eq!(rows.len(), 2);
+    t.expect_string("trap -p USR2", "trap -- 'echo x' SIGUSR2");
+}
+
+#[test]
+fn test_signal_and_interrupt_traps() {
+    let mut t = PipelineTest::new();
+
+    // A caught signal runs its trap before the next command.
+    t.run("trap 'GOT=usr1' USR1");
+    nix::sys::signal::raise(nix::sys::signal::Signal::SIGUSR1).unwrap();
+    t.expect_string("echo $GOT", "usr1");
+
+    // Ctrl-C with INT untrapped skips the rest of the block.
+    let block = nexus_api::BlockIdAllocator::global().reserve();
+    assert!(nexus_kernel::traps::interrupt(block));
+    let status = t.kernel.execute_with_block_id("A=1; A=2", Some(block)).unwrap();
+    assert_eq!(status, nexus_kernel::INTERRUPTED_EXIT_CODE);
+    t.expect_string("echo ${A:-unset}", "unset");
+
+    // Trapped, the trap runs and the block goes on.
+    t.run("trap 'T=int' INT");
+    let block = nexus_api::BlockIdAllocator::global().reserve();
+    assert!(nexus_kernel::traps::interrupt(block));
+    t.kernel.execute_with_block_id("A=2", Some(block)).unwrap();
+    t.expect_string("echo $T $A", "int 2");
+
+    // Ignored, nothing happens.
+    t.run("trap '' INT");
+    assert!(!nexus_kernel::traps::interrupt(nexus_api::BlockIdAllocator::global().reserve()));
+    t.run("trap - INT USR1");
+}
+
+// ============================================================================
+// Project environments
+// ============================================================================
+
+#[test]
+fn test_activate_and_deactivate() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().canonicalize().unwrap().join("api");
+    std::fs::create_dir_all(project.join(".venv")).unwrap();
+    std::fs::write(project.join(".venv/pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+    let venv = project.join(".venv").display().to_string();
+
+    let mut t = PipelineTest::new();
+    t.run(&format!("cd {}", project.display()));
+    t.run("activate venv");
+    t.expect_string("echo $VIRTUAL_ENV", &venv);
+    assert!(t.kernel.state().get_env("PATH").unwrap().starts_with(&format!("{}/bin:", venv)));
+    t.run("deactivate");
+    t.expect_string("echo ${VIRTUAL_ENV:-none}", "none");
+    assert_eq!(t.kernel.execute("deactivate").unwrap(), 1);
+
+    // Leaving the project deactivates it.
+    t.run("activate");
+    t.run("cd ..");
+    t.expect_string("echo ${VIRTUAL_ENV:-none}", "none");
+    assert_eq!(t.kernel.execute("activate").unwrap(), 1);
+}
+
+#[test]
+fn test_errexit_nounset_and_pipefail() {
+    let mut t = PipelineTest::new();
+
+    // The last stage decides, unless pipefail is on.
+    t.run("sh -c 'exit 3' | cat");
+    t.expect_string("echo $?", "0");
+    t.run("set -o pipefail");
+    t.run("sh -c 'exit 3' | cat");
+    t.expect_string("echo $?", "3");
+    let (columns, rows) = t.expect_table("set -o");
+    assert_eq!(columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["option", "enabled"]);
+    assert!(rows.contains(&vec![Value::String("pipefail".into()), Value::Bool(true)]));
+    t.run("set +o pipefail");
+
+    // errexit ends the block at the failing command, but not a tested one.
+    t.run("set -e");
+    assert_eq!(t.kernel.execute("A=1; false; A=2").unwrap(), 1);
+    t.expect_string("echo $A", "1");
+    t.run("false || A=3; if false; then :; fi");
+    t.expect_string("echo $A", "3");
+    t.run("set +e");
+
+    // nounset makes a plain reference to an unset variable an error.
+    t.run("set -u");
+    assert_eq!(t.kernel.execute("echo $NEXUS_UNSET_X; B=ran").unwrap(), 1);
+    t.expect_string("echo ${B:-none} ${NEXUS_UNSET_X:-default}", "none default");
+    t.run("set +u");
+    t.expect_string("echo x$NEXUS_UNSET_X", "x");
+}
+
+#[test]
+fn test_typed_pipe_with_select_and_sort_by() {
+    let mut t = PipelineTest::new();
+    let json = r#"[{"name":"b","age":30},{"name":"a","age":30},{"name":"c","age":20}]"#;
+
+    let (columns, rows) = t.expect_table(&format!("echo '{}' |> from-json |> sort-by -r age name |> select name", json));
+    assert_eq!(columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["name"]);
+    assert_eq!(rows, [["b"], ["a"], ["c"]].map(|r| vec![Value::String(r[0].into())]));
+
+    // `where` filters what `|>` hands on, and `|` still mixes with it.
+    t.expect_int(&format!("echo '{}' |> from-json |> where age == 20 | count", json), 1);
+
+    // Both sides of a `|>` have to be native commands.
+    assert_eq!(t.kernel.execute("echo hi |> tr a-z A-Z").unwrap(), 1);
+    assert_eq!(t.kernel.execute("sh -c 'echo 1' |> count").unwrap(), 1);
+    assert_eq!(t.kernel.execute("echo '[1]' | from-json |> sort-by").unwrap(), 1);
+}
@@ -377,6 +377,7 @@ mod tests {
             cwd: PathBuf::from("/home/alice"),
             os: "linux".into(),
             arch: "x86_64".into(),
+            home: None,
         };
         let resp = Response::HelloOk {
             agent_version: "0.2.0".into(),
@@ -423,6 +424,7 @@ mod tests {
             block_id: BlockId(1),
             command: "echo hello".into(),
             cwd: PathBuf::from("/tmp"),
+            container: None,
         };
         let resp = Response::Event { seq: 1, event };
 
@@ -536,6 +538,16 @@ mod tests {
         }
     }
 
+    /// An `EnvInfo` from an agent that predates `home` still decodes.
+    #[test]
+    fn env_info_without_home() {
+        let old = ("id", "alice", "devbox", PathBuf::from("/srv"), "linux", "x86_64");
+        let bytes = encode_payload(&old).unwrap();
+        let env: EnvInfo = decode_payload(&bytes).unwrap();
+        assert_eq!(env.user, "alice");
+        assert_eq!(env.home, None);
+    }
+
     /// Test chunk_data splits correctly.
     #[test]
     fn chunk_data_splits() {
@@ -674,6 +686,7 @@ mod tests {
             cwd: PathBuf::from("/home/test"),
             os: "linux".into(),
             arch: "x86_64".into(),
+            home: None,
         };
 
         let responses: Vec<Response> = vec![
@@ -13,7 +13,7 @@ pub use types::{AgentCaps, ClientCaps};
 
 /// Protocol version. Increment on breaking changes.
 /// Used to version-key deployed agent binaries.
-pub const PROTOCOL_VERSION: u32 = 10;
+pub const PROTOCOL_VERSION: u32 = 11;
 
 /// Maximum payload size per frame (16 KB) to prevent head-of-line blocking.
 pub const MAX_FRAME_PAYLOAD: usize = 16 * 1024;
@@ -275,6 +275,9 @@ pub struct EnvInfo {
     pub cwd: PathBuf,
     pub os: String,
     pub arch: String,
+    /// The remote user's home directory. Older agents don't send it.
+    #[serde(default)]
+    pub home: Option<PathBuf>,
 }
 
 /// Transport method for nesting (connecting to a deeper level).
@@ -332,6 +335,7 @@ pub enum CompletionKind {
     Variable,
     GitBranch,
     Flag,
+    Task,
 }
 
 /// A shell history entry sent over the wire.
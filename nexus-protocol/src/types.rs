@@ -1,5 +1,6 @@
 //! Capability negotiation types for client↔agent handshake.
 
+use nexus_api::ApiCaps;
 use serde::{Deserialize, Serialize};
 
 /// Capabilities advertised by the client during Hello.
@@ -13,6 +14,9 @@ pub struct ClientCaps {
     pub nesting: bool,
     /// Client supports file transfer (FileRead/FileWrite).
     pub file_transfer: bool,
+    /// Events and values the client can decode. The agent downgrades
+    /// everything else (see `nexus_api::downgrade_event`).
+    pub api: ApiCaps,
 }
 
 /// Capabilities advertised by the agent during HelloOk.
@@ -28,4 +32,6 @@ pub struct AgentCaps {
     pub file_transfer: bool,
     /// Agent supports PTY allocation.
     pub pty: bool,
+    /// Events and values the agent's kernel produces.
+    pub api: ApiCaps,
 }
@@ -1,6 +1,6 @@
 //! Terminal cell representation.
 
-use alacritty_terminal::term::cell::Flags as AlacrittyFlags;
+use alacritty_terminal::term::cell::{Cell as AlacrittyCell, Flags as AlacrittyFlags};
 use alacritty_terminal::vte::ansi::Color as AnsiColor;
 use serde::{Deserialize, Serialize};
 
@@ -19,9 +19,18 @@ pub struct Cell {
     /// These form the grapheme cluster together with `c`.
     /// None for the vast majority of cells (pure ASCII).
     pub zerowidth: Option<Box<[char]>>,
+    /// OSC 8 hyperlink this cell is part of.
+    #[serde(default)]
+    pub hyperlink: Option<Box<Hyperlink>>,
 }
 
 impl Cell {
+    /// The OSC 8 hyperlink this cell is part of, if any.
+    #[inline]
+    pub fn hyperlink(&self) -> Option<&Hyperlink> {
+        self.hyperlink.as_deref()
+    }
+
     /// Append this cell's full grapheme cluster (main char + zero-width chars)
     /// to the given string. Zero allocations.
     #[inline]
@@ -35,6 +44,36 @@ impl Cell {
     }
 }
 
+impl From<&AlacrittyCell> for Cell {
+    fn from(cell: &AlacrittyCell) -> Self {
+        Self {
+            c: cell.c,
+            fg: Color::from(cell.fg),
+            bg: Color::from(cell.bg),
+            flags: CellFlags::from(cell.flags),
+            zerowidth: cell.zerowidth().filter(|zw| !zw.is_empty()).map(|zw| zw.to_vec().into_boxed_slice()),
+            hyperlink: cell.hyperlink().map(|link| Box::new(Hyperlink::from(link))),
+        }
+    }
+}
+
+/// An OSC 8 hyperlink (`ESC ] 8 ; id=ID ; URI ST`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hyperlink {
+    /// The `id=` parameter. Cells with the same id and URI belong to one
+    /// link even when they are not adjacent (e.g. wrapped across rows).
+    pub id: Option<String>,
+    pub uri: String,
+}
+
+impl From<alacritty_terminal::term::cell::Hyperlink> for Hyperlink {
+    fn from(link: alacritty_terminal::term::cell::Hyperlink) -> Self {
+        // Links without an id get a generated one ending in "_alacritty".
+        let id = Some(link.id()).filter(|id| !id.ends_with("_alacritty")).map(str::to_string);
+        Self { id, uri: link.uri().to_string() }
+    }
+}
+
 /// Underline style.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UnderlineStyle {
@@ -2,7 +2,8 @@
 
 use std::sync::atomic::{AtomicU16, Ordering};
 
-use crate::cell::Cell;
+use crate::cell::{Cell, CellFlags, Color, Hyperlink, UnderlineStyle};
+use crate::image::GridImage;
 use serde::{Deserialize, Serialize};
 
 /// Terminal cursor shape.
@@ -15,6 +16,17 @@ pub enum CursorShape {
     Hidden,
 }
 
+/// A run of adjacent cells in one row that share a hyperlink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HyperlinkSpan<'a> {
+    pub row: u16,
+    /// First column of the run.
+    pub start_col: u16,
+    /// Column after the last cell of the run.
+    pub end_col: u16,
+    pub link: &'a Hyperlink,
+}
+
 /// A terminal grid containing rows of cells.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TerminalGrid {
@@ -36,6 +48,10 @@ pub struct TerminalGrid {
     /// Uses AtomicU16 for thread-safe interior mutability (u16::MAX = uncached).
     #[serde(skip, default = "default_content_cache")]
     content_rows_cache: AtomicU16,
+    /// Inline images over the grid. Not serialized: they stay with the
+    /// parser that decoded them.
+    #[serde(skip)]
+    images: Vec<GridImage>,
 }
 
 /// Sentinel value meaning "cache not computed yet".
@@ -56,6 +72,7 @@ impl Clone for TerminalGrid {
             cursor_visible: self.cursor_visible,
             cursor_shape: self.cursor_shape,
             content_rows_cache: AtomicU16::new(self.content_rows_cache.load(Ordering::Relaxed)),
+            images: self.images.clone(),
         }
     }
 }
@@ -73,6 +90,7 @@ impl TerminalGrid {
             cursor_visible: true,
             cursor_shape: CursorShape::Block,
             content_rows_cache: AtomicU16::new(CONTENT_CACHE_NONE),
+            images: Vec::new(),
         }
     }
 
@@ -178,6 +196,11 @@ impl TerminalGrid {
             }
         }
 
+        // Include the rows images cover
+        for image in &self.images {
+            last_content_row = last_content_row.max(image.row.saturating_add(image.image.cell_span.1).min(self.rows));
+        }
+
         // Include cursor row if visible
         if self.cursor_visible {
             let cursor_row = self.cursor_row + 1;
@@ -270,11 +293,109 @@ impl TerminalGrid {
         self.cells.chunks(self.cols as usize)
     }
 
+    /// Iterate over the hyperlinked runs of cells, top to bottom.
+    pub fn hyperlinks(&self) -> impl Iterator<Item = HyperlinkSpan<'_>> {
+        self.rows_iter().enumerate().flat_map(|(row, cells)| {
+            let mut spans: Vec<HyperlinkSpan<'_>> = Vec::new();
+            for (col, cell) in cells.iter().enumerate() {
+                let Some(link) = cell.hyperlink() else { continue };
+                match spans.last_mut() {
+                    Some(span) if span.end_col as usize == col && span.link == link => span.end_col += 1,
+                    _ => spans.push(HyperlinkSpan {
+                        row: row as u16,
+                        start_col: col as u16,
+                        end_col: col as u16 + 1,
+                        link,
+                    }),
+                }
+            }
+            spans
+        })
+    }
+
+    /// Inline images over the grid, top to bottom.
+    pub fn images(&self) -> &[GridImage] {
+        &self.images
+    }
+
+    /// Replace the grid's images.
+    pub fn set_images(&mut self, images: Vec<GridImage>) {
+        self.images = images;
+        self.content_rows_cache.store(CONTENT_CACHE_NONE, Ordering::Relaxed);
+    }
+
     /// Get all cells as a slice.
     pub fn cells(&self) -> &[Cell] {
         &self.cells
     }
 
+    /// Cells (column, row) whose width in the grid disagrees with
+    /// [`crate::width`]: a character the terminal laid out as one column
+    /// that the width tables make two, or the reverse. Places where text
+    /// measured elsewhere (selection, tables) will not line up.
+    pub fn width_mismatches(&self) -> Vec<(u16, u16)> {
+        let mut mismatches = Vec::new();
+        for (row, cells) in self.rows_iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                if cell.flags.wide_char_spacer || cell.c == '\0' || cell.c == ' ' {
+                    continue;
+                }
+                if cell.flags.wide_char != (crate::width::char_width(cell.c) == 2) {
+                    mismatches.push((col as u16, row as u16));
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// The grid's text as escape sequences that, fed to a fresh
+    /// [`crate::TerminalParser`], draw it again with its colors,
+    /// attributes and hyperlinks. Trailing blank cells and rows are left
+    /// out, so the result reflows to any width.
+    pub fn to_ansi(&self) -> Vec<u8> {
+        let blank = |cell: &Cell| (cell.c == ' ' || cell.c == '\0') && cell.bg == Color::Default && !cell.flags.inverse;
+        let mut out = String::new();
+        let mut style: Option<(Color, Color, CellFlags)> = None;
+        let mut link: Option<&Hyperlink> = None;
+        let rows: Vec<&[Cell]> = self.rows_iter().collect();
+        let last_row = rows.iter().rposition(|row| !row.iter().all(blank)).map_or(0, |i| i + 1);
+        for (i, row) in rows[..last_row].iter().enumerate() {
+            if i > 0 {
+                out.push_str("\r\n");
+            }
+            let end = row.iter().rposition(|cell| !blank(cell)).map_or(0, |i| i + 1);
+            for cell in &row[..end] {
+                if cell.flags.wide_char_spacer {
+                    continue;
+                }
+                let cell_style = (cell.fg, cell.bg, cell.flags);
+                if style != Some(cell_style) {
+                    push_sgr(&mut out, cell);
+                    style = Some(cell_style);
+                }
+                if link != cell.hyperlink() {
+                    link = cell.hyperlink();
+                    match link {
+                        Some(l) => out.push_str(&format!("\x1b]8;{};{}\x1b\\", l.id.as_ref().map_or(String::new(), |id| format!("id={}", id)), l.uri)),
+                        None => out.push_str("\x1b]8;;\x1b\\"),
+                    }
+                }
+                if cell.c == '\0' {
+                    out.push(' ');
+                } else {
+                    cell.push_grapheme(&mut out);
+                }
+            }
+        }
+        if link.is_some() {
+            out.push_str("\x1b]8;;\x1b\\");
+        }
+        if style.is_some() {
+            out.push_str("\x1b[0m");
+        }
+        out.into_bytes()
+    }
+
     /// Extract visible text content (for debugging/search).
     pub fn to_string(&self) -> String {
         let mut result = String::new();
@@ -305,3 +426,41 @@ impl Default for TerminalGrid {
         Self::new(crate::DEFAULT_COLS, crate::DEFAULT_ROWS)
     }
 }
+
+/// Select graphic rendition: reset, then `cell`'s colors and attributes.
+fn push_sgr(out: &mut String, cell: &Cell) {
+    let mut params = vec!["0".to_string()];
+    let flags = cell.flags;
+    for (on, code) in [
+        (flags.bold, "1"),
+        (flags.dim, "2"),
+        (flags.italic, "3"),
+        (flags.inverse, "7"),
+        (flags.hidden, "8"),
+        (flags.strikethrough, "9"),
+    ] {
+        if on {
+            params.push(code.to_string());
+        }
+    }
+    match flags.underline {
+        UnderlineStyle::None => {}
+        UnderlineStyle::Single => params.push("4".into()),
+        UnderlineStyle::Double => params.push("4:2".into()),
+        UnderlineStyle::Curly => params.push("4:3".into()),
+        UnderlineStyle::Dotted => params.push("4:4".into()),
+        UnderlineStyle::Dashed => params.push("4:5".into()),
+    }
+    for (color, base, bright) in [(cell.fg, 30, 90), (cell.bg, 40, 100)] {
+        match color {
+            Color::Default => {}
+            Color::Named(n) if n < 8 => params.push((base + n as u16).to_string()),
+            Color::Named(n) if n < 16 => params.push((bright + n as u16 - 8).to_string()),
+            Color::Named(n) | Color::Indexed(n) => params.push(format!("{};5;{}", base + 8, n)),
+            Color::Rgb(r, g, b) => params.push(format!("{};2;{};{};{}", base + 8, r, g, b)),
+        }
+    }
+    out.push_str("\x1b[");
+    out.push_str(&params.join(";"));
+    out.push('m');
+}
@@ -0,0 +1,599 @@
+//! Inline images - iTerm2 `OSC 1337;File=` and Sixel (`DCS … q`) payloads.
+//!
+//! alacritty drops both, so the parser pulls them out of the byte stream
+//! before it reaches the terminal, decodes them here, and leaves blank rows
+//! in the grid for the image to cover.
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+
+use base64::Engine;
+
+/// Largest image sequence kept; anything longer is dropped unread.
+const MAX_PAYLOAD: usize = 32 * 1024 * 1024;
+
+const ITERM2_START: &[u8] = b"\x1b]1337;File=";
+const DCS_START: &[u8] = b"\x1bP";
+
+/// How an image's `data` is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    /// Some other file type iTerm2 would hand to the system to decode.
+    Other,
+    /// Decoded pixels, four bytes each, row-major (what Sixel becomes).
+    Rgba,
+}
+
+/// An image a program drew inline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineImage {
+    /// The image file as sent, or RGBA pixels for [`ImageFormat::Rgba`].
+    pub data: Arc<[u8]>,
+    pub format: ImageFormat,
+    /// Size in pixels; zero when the file's header couldn't be read.
+    pub width: u32,
+    pub height: u32,
+    /// Columns and rows of the grid the image covers.
+    pub cell_span: (u16, u16),
+}
+
+/// An image in a [`TerminalGrid`](crate::TerminalGrid), by the cell at its
+/// top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridImage {
+    pub col: u16,
+    pub row: u16,
+    pub image: Arc<InlineImage>,
+}
+
+/// A complete image sequence, not yet decoded.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Payload {
+    /// `OSC 1337;File=` arguments and the base64 body.
+    Iterm2 { args: Vec<u8>, body: Vec<u8> },
+    /// Sixel data after the `q`.
+    Sixel(Vec<u8>),
+}
+
+/// Part of a fed chunk: bytes for the terminal, or an image.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Text(Range<usize>),
+    Image(Payload),
+}
+
+/// Pulls image sequences out of terminal output, across feeds.
+#[derive(Debug, Default)]
+pub(crate) struct ImageScanner {
+    /// An open image sequence from its `ESC`, or the start of one cut off at
+    /// the end of the last chunk.
+    pending: Vec<u8>,
+    open: bool,
+    /// The open sequence outgrew `MAX_PAYLOAD`; it is skipped to its end.
+    overflowed: bool,
+}
+
+impl ImageScanner {
+    /// Split `bytes` into runs for the terminal and finished image
+    /// sequences. Text ranges index the returned buffer, which is `bytes`
+    /// unless a cut-off sequence start was held back from the last call.
+    pub fn split<'b>(&mut self, bytes: &'b [u8]) -> (Cow<'b, [u8]>, Vec<Piece>) {
+        let input: Cow<'b, [u8]> = if self.pending.is_empty() || self.open {
+            Cow::Borrowed(bytes)
+        } else {
+            let mut held = std::mem::take(&mut self.pending);
+            held.extend_from_slice(bytes);
+            Cow::Owned(held)
+        };
+        let mut pieces = Vec::new();
+        let mut pos = 0;
+
+        if self.open {
+            match self.close(&input) {
+                Some((end, payload)) => {
+                    pieces.extend(payload.map(Piece::Image));
+                    pos = end;
+                }
+                None => return (input, pieces),
+            }
+        }
+
+        let mut text_start = pos;
+        while let Some(offset) = input[pos..].iter().position(|&b| b == 0x1b) {
+            let esc = pos + offset;
+            match sequence_start(&input[esc..]) {
+                Start::No => pos = esc + 1,
+                Start::Partial => {
+                    self.pending.extend_from_slice(&input[esc..]);
+                    push_text(&mut pieces, text_start..esc);
+                    return (input, pieces);
+                }
+                Start::Yes(len) => {
+                    push_text(&mut pieces, text_start..esc);
+                    self.open = true;
+                    self.pending.extend_from_slice(&input[esc..esc + len]);
+                    match self.close(&input[esc + len..]) {
+                        Some((end, payload)) => {
+                            pieces.extend(payload.map(Piece::Image));
+                            pos = esc + len + end;
+                            text_start = pos;
+                        }
+                        None => return (input, pieces),
+                    }
+                }
+            }
+        }
+        push_text(&mut pieces, text_start..input.len());
+        (input, pieces)
+    }
+
+    /// Look for the end of the open sequence in `bytes`. Returns how much
+    /// of `bytes` it took, and the payload (None if it was too long), or
+    /// None with everything buffered if it doesn't end here.
+    fn close(&mut self, bytes: &[u8]) -> Option<(usize, Option<Payload>)> {
+        let osc = self.pending.starts_with(ITERM2_START);
+        let end = if self.pending.last() == Some(&0x1b) && bytes.first() == Some(&b'\\') {
+            // ST split across chunks.
+            self.pending.pop();
+            Some((0, 1))
+        } else {
+            bytes.iter().enumerate().find_map(|(i, &b)| match b {
+                0x07 if osc => Some((i, 1)),
+                0x9c => Some((i, 1)),
+                0x1b if bytes.get(i + 1) == Some(&b'\\') => Some((i, 2)),
+                _ => None,
+            })
+        };
+        let Some((at, terminator)) = end else {
+            if self.pending.len() + bytes.len() > MAX_PAYLOAD {
+                // Too big to keep: remember only what it is, and its last byte.
+                self.overflowed = true;
+                self.pending.truncate(ITERM2_START.len().min(self.pending.len()));
+                self.pending.extend(bytes.last());
+            } else {
+                self.pending.extend_from_slice(bytes);
+            }
+            return None;
+        };
+
+        self.open = false;
+        let mut sequence = std::mem::take(&mut self.pending);
+        let payload = if std::mem::take(&mut self.overflowed) || sequence.len() + at > MAX_PAYLOAD {
+            None
+        } else {
+            sequence.extend_from_slice(&bytes[..at]);
+            Some(payload(sequence, osc))
+        };
+        Some((at + terminator, payload))
+    }
+}
+
+fn push_text(pieces: &mut Vec<Piece>, range: Range<usize>) {
+    if !range.is_empty() {
+        pieces.push(Piece::Text(range));
+    }
+}
+
+enum Start {
+    No,
+    /// Could be one; the chunk ends first.
+    Partial,
+    /// One is, this many bytes long.
+    Yes(usize),
+}
+
+/// Whether `bytes` (from an `ESC`) starts an image sequence.
+fn sequence_start(bytes: &[u8]) -> Start {
+    if bytes.len() < ITERM2_START.len() && ITERM2_START.starts_with(bytes) {
+        return Start::Partial;
+    }
+    if bytes.starts_with(ITERM2_START) {
+        return Start::Yes(ITERM2_START.len());
+    }
+    if !bytes.starts_with(DCS_START) {
+        return if bytes.len() == 1 { Start::Partial } else { Start::No };
+    }
+    // Sixel: DCS, numeric parameters, then `q`.
+    for (i, &b) in bytes.iter().enumerate().skip(DCS_START.len()) {
+        match b {
+            b'0'..=b'9' | b';' => {}
+            b'q' => return Start::Yes(i + 1),
+            _ => return Start::No,
+        }
+    }
+    Start::Partial
+}
+
+/// Split a finished sequence into its payload.
+fn payload(sequence: Vec<u8>, osc: bool) -> Payload {
+    if osc {
+        let rest = &sequence[ITERM2_START.len()..];
+        let colon = rest.iter().position(|&b| b == b':').unwrap_or(rest.len());
+        let body = rest.get(colon + 1..).unwrap_or_default().to_vec();
+        Payload::Iterm2 { args: rest[..colon].to_vec(), body }
+    } else {
+        let q = sequence.iter().position(|&b| b == b'q').unwrap_or(0);
+        Payload::Sixel(sequence[q + 1..].to_vec())
+    }
+}
+
+// =========================================================================
+// Decoding
+// =========================================================================
+
+/// Grid geometry an image is sized against.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CellGeometry {
+    pub cols: u16,
+    pub rows: u16,
+    /// One cell, in pixels.
+    pub cell_width: u32,
+    pub cell_height: u32,
+}
+
+/// Decode a payload and work out the cells it covers. None for iTerm2
+/// downloads (`inline=0`) and payloads that don't decode.
+pub(crate) fn decode(payload: Payload, geometry: CellGeometry) -> Option<InlineImage> {
+    match payload {
+        Payload::Iterm2 { args, body } => decode_iterm2(&args, &body, geometry),
+        Payload::Sixel(data) => {
+            let (width, height, pixels) = decode_sixel(&data)?;
+            let cell_span = fit((width, height), (None, None), true, geometry);
+            Some(InlineImage { data: pixels.into(), format: ImageFormat::Rgba, width, height, cell_span })
+        }
+    }
+}
+
+fn decode_iterm2(args: &[u8], body: &[u8], geometry: CellGeometry) -> Option<InlineImage> {
+    let args = String::from_utf8_lossy(args);
+    let arg = |name: &str| {
+        args.split(';').find_map(|pair| pair.split_once('=').filter(|(key, _)| *key == name).map(|(_, value)| value))
+    };
+    if arg("inline") != Some("1") {
+        return None;
+    }
+    let body: Vec<u8> = body.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    let data = base64::engine::general_purpose::STANDARD.decode(body).ok()?;
+    let (format, width, height) = sniff(&data);
+
+    let width_px = arg("width").and_then(|w| requested_px(w, geometry.cols, geometry.cell_width));
+    let height_px = arg("height").and_then(|h| requested_px(h, geometry.rows, geometry.cell_height));
+    let preserve = arg("preserveAspectRatio") != Some("0");
+    let cell_span = fit((width, height), (width_px, height_px), preserve, geometry);
+    Some(InlineImage { data: data.into(), format, width, height, cell_span })
+}
+
+/// A `width=`/`height=` argument in pixels: `N` cells, `Npx`, `N%` of the
+/// screen, or `auto` (None).
+fn requested_px(value: &str, cells: u16, cell_px: u32) -> Option<u32> {
+    if let Some(px) = value.strip_suffix("px") {
+        px.parse().ok()
+    } else if let Some(percent) = value.strip_suffix('%') {
+        percent.parse::<u32>().ok().map(|p| cells as u32 * cell_px * p / 100)
+    } else {
+        value.parse::<u32>().ok().map(|n| n * cell_px)
+    }
+}
+
+/// Cells covered by an image of `size` pixels drawn at the `requested`
+/// size. Unrequested images wider than the screen are scaled down to it.
+fn fit(size: (u32, u32), requested: (Option<u32>, Option<u32>), preserve: bool, geometry: CellGeometry) -> (u16, u16) {
+    let (w, h) = (size.0 as f64, size.1 as f64);
+    let known = w > 0.0 && h > 0.0;
+    let (width, height) = match requested {
+        (Some(rw), Some(rh)) if preserve && known => {
+            let scale = (rw as f64 / w).min(rh as f64 / h);
+            (w * scale, h * scale)
+        }
+        (Some(rw), Some(rh)) => (rw as f64, rh as f64),
+        (Some(rw), None) if known => (rw as f64, if preserve { h * rw as f64 / w } else { h }),
+        (None, Some(rh)) if known => (if preserve { w * rh as f64 / h } else { w }, rh as f64),
+        (Some(rw), None) => (rw as f64, geometry.cell_height as f64),
+        (None, Some(rh)) => (geometry.cell_width as f64, rh as f64),
+        (None, None) => {
+            let screen = (geometry.cols as u32 * geometry.cell_width) as f64;
+            if w > screen { (screen, h * screen / w) } else { (w, h) }
+        }
+    };
+    let cells = |px: f64, cell: u32, max: u16| ((px / cell.max(1) as f64).ceil() as u16).clamp(1, max.max(1));
+    (cells(width, geometry.cell_width, geometry.cols), cells(height, geometry.cell_height, u16::MAX))
+}
+
+/// An image file's format and pixel size, from its header.
+fn sniff(data: &[u8]) -> (ImageFormat, u32, u32) {
+    let be32 = |at: usize| data.get(at..at + 4).map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let le16 = |at: usize| data.get(at..at + 2).map_or(0, |b| u16::from_le_bytes([b[0], b[1]]) as u32);
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        (ImageFormat::Png, be32(16), be32(20))
+    } else if data.starts_with(b"GIF8") {
+        (ImageFormat::Gif, le16(6), le16(8))
+    } else if data.starts_with(&[0xff, 0xd8]) {
+        let (width, height) = jpeg_size(data).unwrap_or((0, 0));
+        (ImageFormat::Jpeg, width, height)
+    } else {
+        (ImageFormat::Other, 0, 0)
+    }
+}
+
+/// Walk a JPEG's segments to its frame header.
+fn jpeg_size(data: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *data.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(at + 1)?;
+        let len = u16::from_be_bytes([*data.get(at + 2)?, *data.get(at + 3)?]) as usize;
+        // SOF0-SOF15, except DHT (C4), JPG (C8) and DAC (CC).
+        if (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker) {
+            let height = u16::from_be_bytes([*data.get(at + 5)?, *data.get(at + 6)?]);
+            let width = u16::from_be_bytes([*data.get(at + 7)?, *data.get(at + 8)?]);
+            return Some((width as u32, height as u32));
+        }
+        at += 2 + len;
+    }
+}
+
+/// The VT340's default Sixel palette.
+const SIXEL_PALETTE: [[u8; 3]; 16] = [
+    [0, 0, 0], [51, 51, 204], [204, 36, 36], [51, 204, 51],
+    [204, 51, 204], [51, 204, 204], [204, 204, 51], [135, 135, 135],
+    [66, 66, 66], [84, 84, 153], [153, 66, 66], [84, 153, 84],
+    [153, 84, 153], [84, 153, 153], [153, 153, 84], [204, 204, 204],
+];
+
+/// Decode Sixel data to its size and RGBA pixels. Pixels no sixel set
+/// are transparent.
+fn decode_sixel(data: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    let mut palette: Vec<[u8; 3]> = SIXEL_PALETTE.to_vec();
+    palette.resize(256, [0, 0, 0]);
+    let mut canvas = Canvas::default();
+    let (mut x, mut band, mut color) = (0usize, 0usize, 0usize);
+
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        i += 1;
+        match b {
+            // Raster attributes: "Pan;Pad;Ph;Pv - the last two size the image.
+            b'"' => {
+                let params = numbers(data, &mut i);
+                if let (Some(&w), Some(&h)) = (params.get(2), params.get(3)) {
+                    canvas.size_to(w as usize, h as usize);
+                }
+            }
+            // #Pc selects a color; #Pc;Pu;Px;Py;Pz also defines it.
+            b'#' => {
+                let params = numbers(data, &mut i);
+                color = params.first().map_or(0, |&c| c as usize % palette.len());
+                if let [_, space, a, b, c, ..] = params[..] {
+                    palette[color] = match space {
+                        1 => hls_to_rgb(a, b, c),
+                        _ => [a, b, c].map(|v| (v.min(100) * 255 / 100) as u8),
+                    };
+                }
+            }
+            // !Pn repeats the next sixel.
+            b'!' => {
+                let count = numbers(data, &mut i).first().copied().unwrap_or(1).max(1) as usize;
+                if let Some(&sixel @ 0x3f..=0x7e) = data.get(i) {
+                    i += 1;
+                    // Columns past the edge would be dropped anyway.
+                    for column in x..x.saturating_add(count).min(Canvas::MAX_SIDE) {
+                        canvas.paint(column, band, sixel - 0x3f, palette[color]);
+                    }
+                    x = x.saturating_add(count);
+                }
+            }
+            b'$' => x = 0,
+            b'-' => {
+                x = 0;
+                band = band.saturating_add(6);
+            }
+            0x3f..=0x7e => {
+                canvas.paint(x, band, b - 0x3f, palette[color]);
+                x = x.saturating_add(1);
+            }
+            _ => {}
+        }
+    }
+    canvas.finish()
+}
+
+/// `;`-separated numbers at `data[*i..]`, advancing past them.
+fn numbers(data: &[u8], i: &mut usize) -> Vec<u32> {
+    let mut out = vec![0u32];
+    while let Some(&b) = data.get(*i) {
+        match b {
+            b'0'..=b'9' => {
+                let last = out.last_mut().unwrap();
+                *last = last.saturating_mul(10).saturating_add((b - b'0') as u32);
+            }
+            b';' => out.push(0),
+            _ => break,
+        }
+        *i += 1;
+    }
+    out
+}
+
+/// Sixel HLS (hue 0 is blue, all 0-100 but hue) to RGB.
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> [u8; 3] {
+    let (l, s) = (lightness.min(100) as f64 / 100.0, saturation.min(100) as f64 / 100.0);
+    let h = ((hue + 240) % 360) as f64 / 360.0;
+    if s == 0.0 {
+        let v = (l * 255.0).round() as u8;
+        return [v, v, v];
+    }
+    let q = if l < 0.5 { l * (1.0 + s) } else { l + s - l * s };
+    let p = 2.0 * l - q;
+    let channel = |t: f64| {
+        let t = t.rem_euclid(1.0);
+        let v = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (v * 255.0).round() as u8
+    };
+    [channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0)]
+}
+
+/// RGBA pixels that grow to fit what is painted.
+#[derive(Default)]
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    /// The image's size so far: the raster attributes' or what was painted.
+    extent: (usize, usize),
+}
+
+impl Canvas {
+    /// Largest canvas side; sixels beyond it are dropped.
+    const MAX_SIDE: usize = 8192;
+    /// Most pixels a canvas holds, a 4K screen's worth; sixels that would
+    /// need more are dropped, so a few bytes can't ask for gigabytes.
+    const MAX_PIXELS: usize = 4096 * 2304;
+
+    /// Make room for `width` × `height`. False if that's past the limits.
+    fn grow(&mut self, width: usize, height: usize) -> bool {
+        if width <= self.width && height <= self.height {
+            return true;
+        }
+        let (new_width, new_height) = (width.max(self.width), height.max(self.height));
+        if new_width > Self::MAX_SIDE || new_height > Self::MAX_SIDE || new_width * new_height > Self::MAX_PIXELS {
+            return false;
+        }
+        let mut pixels = vec![0u8; new_width * new_height * 4];
+        for row in 0..self.height {
+            let old = &self.pixels[row * self.width * 4..(row + 1) * self.width * 4];
+            pixels[row * new_width * 4..row * new_width * 4 + old.len()].copy_from_slice(old);
+        }
+        (self.width, self.height, self.pixels) = (new_width, new_height, pixels);
+        true
+    }
+
+    /// The size the raster attributes give. Nothing is allocated until
+    /// sixels are painted; a size past the limits is ignored.
+    fn size_to(&mut self, width: usize, height: usize) {
+        if width <= Self::MAX_SIDE && height <= Self::MAX_SIDE && width * height <= Self::MAX_PIXELS {
+            self.extent = (width, height);
+        }
+    }
+
+    /// Size and pixels, cropped or padded to the extent. None if nothing
+    /// was drawn.
+    fn finish(self) -> Option<(u32, u32, Vec<u8>)> {
+        let (width, height) = self.extent;
+        if width == 0 || height == 0 || self.pixels.is_empty() {
+            return None;
+        }
+        let mut pixels = vec![0u8; width * height * 4];
+        let copied = width.min(self.width) * 4;
+        for row in 0..height.min(self.height) {
+            let start = row * self.width * 4;
+            pixels[row * width * 4..row * width * 4 + copied].copy_from_slice(&self.pixels[start..start + copied]);
+        }
+        Some((width as u32, height as u32, pixels))
+    }
+
+    /// Set the pixels of one sixel: bit n of `bits` is row `band + n`.
+    fn paint(&mut self, x: usize, band: usize, bits: u8, [r, g, b]: [u8; 3]) {
+        if bits == 0 || x >= Self::MAX_SIDE || band >= Self::MAX_SIDE {
+            return;
+        }
+        let top = 8 - bits.leading_zeros() as usize;
+        // Grow in steps so wide images don't re-layout every column.
+        if x >= self.width || band + top > self.height {
+            let height = (band + top).max(self.height);
+            let width = if x >= self.width {
+                (x + 1).max((self.width * 2).min(Self::MAX_SIDE).min(Self::MAX_PIXELS / height))
+            } else {
+                self.width
+            };
+            if !self.grow(width, height) {
+                return;
+            }
+        }
+        self.extent = (self.extent.0.max(x + 1), self.extent.1.max(band + top));
+        for n in 0..6 {
+            if bits & (1 << n) != 0 && band + n < self.height {
+                let at = ((band + n) * self.width + x) * 4;
+                self.pixels[at..at + 4].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GEOMETRY: CellGeometry = CellGeometry { cols: 80, rows: 24, cell_width: 8, cell_height: 16 };
+
+    #[test]
+    fn test_scanner_splits_sequences_across_feeds() {
+        let mut scanner = ImageScanner::default();
+        let (input, pieces) = scanner.split(b"ab\x1b]13");
+        assert_eq!(pieces, [Piece::Text(0..2)]);
+        assert_eq!(&input[..2], b"ab");
+
+        let (_, pieces) = scanner.split(b"37;File=inline=1:QUJD\x1b");
+        assert!(pieces.is_empty());
+        let (input, pieces) = scanner.split(b"\\cd\x1b[1mx\x1bP0;1q#1!3~-\x1b\\");
+        assert_eq!(pieces.len(), 3);
+        assert_eq!(pieces[0], Piece::Image(Payload::Iterm2 { args: b"inline=1".to_vec(), body: b"QUJD".to_vec() }));
+        let Piece::Text(text) = &pieces[1] else { panic!() };
+        assert_eq!(&input[text.clone()], b"cd\x1b[1mx");
+        assert_eq!(pieces[2], Piece::Image(Payload::Sixel(b"#1!3~-".to_vec())));
+    }
+
+    #[test]
+    fn test_decode_sixel_and_sizes() {
+        // Three columns of color 1, six rows tall, then one red pixel below.
+        let (width, height, pixels) = decode_sixel(b"#1!3~-#2;2;100;0;0@").unwrap();
+        assert_eq!((width, height), (3, 7));
+        assert_eq!(&pixels[..4], &[51, 51, 204, 255]);
+        assert_eq!(&pixels[6 * 3 * 4..6 * 3 * 4 + 4], &[255, 0, 0, 255]);
+        assert_eq!(pixels[6 * 3 * 4 + 7], 0);
+
+        assert_eq!(fit((800, 320), (None, None), true, GEOMETRY), (80, 16));
+        assert_eq!(fit((16, 16), (Some(8 * 10), None), true, GEOMETRY), (10, 5));
+        assert_eq!(requested_px("50%", 80, 8), Some(320));
+        assert_eq!(requested_px("12px", 80, 8), Some(12));
+    }
+
+    #[test]
+    fn test_decode_sixel_bounds_memory_and_work() {
+        // Raster attributes alone don't allocate, and one past the limits
+        // is ignored.
+        let mut canvas = Canvas::default();
+        canvas.size_to(8192, 8192);
+        assert_eq!((canvas.extent, canvas.pixels.len()), ((0, 0), 0));
+        assert!(decode_sixel(b"\"1;1;8192;8192").is_none());
+        let (width, height, pixels) = decode_sixel(b"\"1;1;8192;8192#1~").unwrap();
+        assert_eq!((width, height, pixels.len()), (1, 6, 24));
+
+        // A huge repeat stops at the edge, without looping over the rest.
+        assert!(decode_sixel(b"#1!4294967295?~").is_none());
+        let (width, height, _) = decode_sixel(b"#1!4294967295~").unwrap();
+        assert_eq!((width as usize, height), (Canvas::MAX_SIDE, 6));
+
+        // Painting far down and right can't grow past the pixel limit.
+        let mut far = b"#1".to_vec();
+        far.extend(std::iter::repeat_n(b'-', 8000 / 6));
+        far.extend(b"!8000~");
+        let (width, height, pixels) = decode_sixel(&far).unwrap();
+        assert!(width as usize * height as usize <= Canvas::MAX_PIXELS);
+        assert_eq!(pixels.len(), width as usize * height as usize * 4);
+    }
+}
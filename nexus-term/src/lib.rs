@@ -6,11 +6,14 @@
 mod grid;
 mod parser;
 mod cell;
+mod image;
 mod shadow;
+pub mod width;
 
-pub use grid::{CursorShape, TerminalGrid};
+pub use grid::{CursorShape, HyperlinkSpan, TerminalGrid};
 pub use parser::{FeedResult, TerminalParser};
-pub use cell::{Cell, CellFlags, Color, UnderlineStyle};
+pub use cell::{Cell, CellFlags, Color, Hyperlink, UnderlineStyle};
+pub use image::{GridImage, ImageFormat, InlineImage};
 pub use shadow::ShadowParser;
 
 /// Default terminal dimensions.
@@ -57,6 +60,26 @@ mod tests {
         assert!(text.contains("A"), "Content should be preserved after resize back");
     }
 
+    #[test]
+    fn test_grid_to_ansi_redraws_grid() {
+        let mut parser = TerminalParser::new(40, 5);
+        parser.feed(b"plain \x1b[1;31merror\x1b[0m \x1b[38;2;1;2;3mrgb\x1b[0m\r\n");
+        parser.feed(b"\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\ \xe4\xb8\xad\r\n");
+        let grid = parser.grid_with_scrollback();
+
+        let mut replayed = TerminalParser::new(40, 5);
+        replayed.feed(&grid.to_ansi());
+        let again = replayed.grid_with_scrollback();
+        assert_eq!(again.to_string().trim_end(), grid.to_string().trim_end());
+        let error = again.get(6, 0).unwrap();
+        assert_eq!(error.fg, Color::Named(1));
+        assert!(error.flags.bold);
+        assert_eq!(again.get(12, 0).unwrap().fg, Color::Rgb(1, 2, 3));
+        assert_eq!(again.get(0, 1).unwrap().hyperlink().map(|l| l.uri.as_str()), Some("https://example.com"));
+        assert!(again.get(5, 1).unwrap().flags.wide_char);
+        assert!(!again.get(6, 0).unwrap().flags.italic);
+    }
+
     #[test]
     fn test_resize_width_only() {
         let mut parser = TerminalParser::new(120, 24);
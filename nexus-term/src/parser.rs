@@ -10,8 +10,9 @@ use alacritty_terminal::index::{Column, Line};
 use alacritty_terminal::term::{Config, Term, test::TermSize};
 use alacritty_terminal::vte::ansi::Processor;
 
-use crate::cell::{Cell, CellFlags, Color};
+use crate::cell::Cell;
 use crate::grid::TerminalGrid;
+use crate::image::{self, CellGeometry, GridImage, ImageScanner, InlineImage, Piece};
 
 /// Signals returned from `feed_tracking_writes` about frame boundaries
 /// and cursor transitions detected in the byte stream.
@@ -50,6 +51,13 @@ pub struct TerminalParser {
     dectcem_window: Vec<u8>,
     /// Reusable buffer for viewport diff snapshots.
     diff_buffer: Vec<char>,
+    /// Pulls inline image sequences out of the output before alacritty.
+    image_scanner: ImageScanner,
+    /// Images drawn so far: column, line counted from the top of
+    /// scrollback, and the image.
+    images: Vec<(u16, usize, Arc<InlineImage>)>,
+    /// One cell in pixels, for sizing images given in pixels.
+    cell_size: (u32, u32),
 }
 
 impl std::fmt::Debug for TerminalParser {
@@ -82,6 +90,9 @@ impl EventListener for EventProxy {
 /// Default scrollback history (10k lines).
 const SCROLLBACK_LINES: usize = 10_000;
 
+/// Cell size assumed for images until the renderer sets the real one.
+const DEFAULT_CELL_SIZE: (u32, u32) = (8, 16);
+
 impl TerminalParser {
     /// Create a new parser with the given dimensions.
     pub fn new(cols: u16, rows: u16) -> Self {
@@ -104,12 +115,53 @@ impl TerminalParser {
             title_slot,
             dectcem_window: Vec::new(),
             diff_buffer: Vec::new(),
+            image_scanner: ImageScanner::default(),
+            images: Vec::new(),
+            cell_size: DEFAULT_CELL_SIZE,
+        }
+    }
+
+    /// Set the pixel size of a cell, which images given in pixels are
+    /// measured against.
+    pub fn set_cell_size(&mut self, width: u32, height: u32) {
+        self.cell_size = (width.max(1), height.max(1));
+    }
+
+    /// Run bytes through the terminal, taking out inline images.
+    fn advance(&mut self, bytes: &[u8]) {
+        let (input, pieces) = self.image_scanner.split(bytes);
+        for piece in pieces {
+            match piece {
+                Piece::Text(range) => self.processor.advance(&mut self.term, &input[range]),
+                Piece::Image(payload) => {
+                    let geometry = CellGeometry {
+                        cols: self.term.columns() as u16,
+                        rows: self.term.screen_lines() as u16,
+                        cell_width: self.cell_size.0,
+                        cell_height: self.cell_size.1,
+                    };
+                    if let Some(image) = image::decode(payload, geometry) {
+                        self.place_image(image);
+                    }
+                }
+            }
         }
     }
 
+    /// Anchor an image at the cursor and move the cursor to the start of
+    /// the line below it, scrolling as a run of newlines would.
+    fn place_image(&mut self, image: InlineImage) {
+        let grid = self.term.grid();
+        let cursor = grid.cursor.point;
+        let line = (cursor.line.0 + grid.history_size() as i32).max(0) as usize;
+        let rows = image.cell_span.1 as usize;
+        self.images.push((cursor.column.0 as u16, line, Arc::new(image)));
+        self.processor.advance(&mut self.term, "\r\n".repeat(rows).as_bytes());
+    }
+
     /// Feed bytes into the parser. Invalidates cached grids.
     pub fn feed(&mut self, bytes: &[u8]) {
-        self.processor.advance(&mut self.term, bytes);
+        self.advance(bytes);
         // Invalidate caches - new content means grids need regeneration
         *self.cached_viewport.borrow_mut() = None;
         *self.cached_scrollback.borrow_mut() = None;
@@ -146,7 +198,7 @@ impl TerminalParser {
             if let Some(offset) = find_subsequence(&bytes[start..], SHOW_CURSOR_SEQ) {
                 let end = start + offset + SHOW_CURSOR_SEQ.len();
                 // Process bytes up through the show-cursor sequence
-                self.processor.advance(&mut self.term, &bytes[start..end]);
+                self.advance(&bytes[start..end]);
                 // Capture cursor position at the moment it became visible
                 let cursor = &self.term.grid().cursor;
                 last_visible_pos = Some((
@@ -156,7 +208,7 @@ impl TerminalParser {
                 start = end;
             } else {
                 // No more show-cursor sequences — process the rest
-                self.processor.advance(&mut self.term, &bytes[start..]);
+                self.advance(&bytes[start..]);
                 break;
             }
         }
@@ -276,7 +328,7 @@ impl TerminalParser {
                 let feed_start = start.max(leftover_len) - leftover_len;
                 let feed_end = end.saturating_sub(leftover_len).min(bytes.len());
                 if feed_end > feed_start {
-                    self.processor.advance(&mut self.term, &bytes[feed_start..feed_end]);
+                    self.advance(&bytes[feed_start..feed_end]);
                 }
                 let cursor = &self.term.grid().cursor;
                 let pos = (cursor.point.column.0 as u16, cursor.point.line.0 as u16);
@@ -290,7 +342,7 @@ impl TerminalParser {
                 // No more matches — feed remaining original bytes and break.
                 let feed_start = start.max(leftover_len) - leftover_len;
                 if feed_start < bytes.len() {
-                    self.processor.advance(&mut self.term, &bytes[feed_start..]);
+                    self.advance(&bytes[feed_start..]);
                 }
                 break;
             }
@@ -368,19 +420,7 @@ impl TerminalParser {
             let col = indexed_cell.point.column.0 as u16;
             let row = indexed_cell.point.line.0 as u16;
 
-            let zerowidth = indexed_cell.zerowidth()
-                .filter(|zw| !zw.is_empty())
-                .map(|zw| zw.to_vec().into_boxed_slice());
-
-            let cell = Cell {
-                c: indexed_cell.c,
-                fg: Color::from(indexed_cell.fg),
-                bg: Color::from(indexed_cell.bg),
-                flags: CellFlags::from(indexed_cell.flags),
-                zerowidth,
-            };
-
-            grid.set(col, row, cell);
+            grid.set(col, row, Cell::from(indexed_cell.cell));
         }
 
         // Set cursor position and shape.
@@ -395,6 +435,13 @@ impl TerminalParser {
         };
         grid.set_cursor_shape(shape);
 
+        // Images whose top row is on screen.
+        let history = self.term.grid().history_size() as isize - term_content.display_offset as isize;
+        grid.set_images(self.grid_images(|line| {
+            let row = line as isize - history;
+            (0..rows as isize).contains(&row).then_some(row as u16)
+        }));
+
         grid
     }
 
@@ -442,18 +489,7 @@ impl TerminalParser {
             let term_line = Line(start_line + line_idx as i32);
             let row = &grid[term_line];
             for col_idx in 0..cols {
-                let cell = &row[Column(col_idx)];
-                let zerowidth = cell.zerowidth()
-                    .filter(|zw| !zw.is_empty())
-                    .map(|zw| zw.to_vec().into_boxed_slice());
-                let our_cell = Cell {
-                    c: cell.c,
-                    fg: Color::from(cell.fg),
-                    bg: Color::from(cell.bg),
-                    flags: CellFlags::from(cell.flags),
-                    zerowidth,
-                };
-                result.set(col_idx as u16, line_idx as u16, our_cell);
+                result.set(col_idx as u16, line_idx as u16, Cell::from(&row[Column(col_idx)]));
             }
         }
 
@@ -472,10 +508,19 @@ impl TerminalParser {
             alacritty_terminal::vte::ansi::CursorShape::Hidden => crate::grid::CursorShape::Hidden,
         };
         result.set_cursor_shape(shape);
+        result.set_images(self.grid_images(|line| (line < total_to_render).then_some(line as u16)));
 
         result
     }
 
+    /// The images whose line `row_of` maps to a grid row.
+    fn grid_images(&self, row_of: impl Fn(usize) -> Option<u16>) -> Vec<GridImage> {
+        self.images
+            .iter()
+            .filter_map(|(col, line, image)| Some(GridImage { col: *col, row: row_of(*line)?, image: Arc::clone(image) }))
+            .collect()
+    }
+
     /// Get the number of lines in scrollback history.
     pub fn scrollback_lines(&self) -> usize {
         self.term.grid().history_size()
@@ -536,10 +581,19 @@ impl TerminalParser {
             return 1;
         }
 
+        // Images leave blank rows behind; count the rows they cover.
+        let image_rows = self
+            .images
+            .iter()
+            .map(|(_, line, image)| line + image.cell_span.1 as usize)
+            .max()
+            .unwrap_or(0)
+            .min(total_lines);
+
         // Scan from bottom to top to find last row with content
         let start_line = -(history_lines as i32);
 
-        for line_idx in (0..total_lines).rev() {
+        for line_idx in (image_rows..total_lines).rev() {
             let term_line = Line(start_line + line_idx as i32);
             let row = &grid[term_line];
 
@@ -552,7 +606,7 @@ impl TerminalParser {
             }
         }
 
-        1 // At least 1 row
+        image_rows.max(1) // At least 1 row
     }
 
     /// Replace the cached viewport with an externally-provided grid snapshot.
@@ -692,4 +746,71 @@ mod tests {
         let (col, _row) = result.last_write_pos.unwrap();
         assert_eq!(col, 4); // 'o' at column 4
     }
+
+    #[test]
+    fn osc8_hyperlinks_reach_cells_and_grid() {
+        let mut parser = TerminalParser::new(80, 24);
+        parser.feed(b"see \x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\ and \x1b]8;id=pr;https://example.com/pr/1\x07#1\x1b]8;;\x07\r\n");
+
+        for grid in [parser.grid(), parser.grid_with_scrollback()] {
+            assert_eq!(grid.get(4, 0).and_then(|c| c.hyperlink()).map(|l| l.uri.as_str()), Some("https://example.com"));
+            assert!(grid.get(3, 0).unwrap().hyperlink().is_none());
+
+            let spans: Vec<_> = grid.hyperlinks().collect();
+            assert_eq!(spans.len(), 2);
+            assert_eq!((spans[0].row, spans[0].start_col, spans[0].end_col), (0, 4, 8));
+            assert_eq!(spans[0].link.id, None);
+            assert_eq!((spans[1].start_col, spans[1].end_col), (13, 15));
+            assert_eq!(spans[1].link.id.as_deref(), Some("pr"));
+        }
+    }
+
+    #[test]
+    fn inline_images_are_placed_on_the_grid() {
+        use base64::Engine;
+
+        // A PNG header is enough to size it: 32x48 pixels, 4x3 cells.
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 32, 0, 0, 0, 48]);
+        let body = base64::engine::general_purpose::STANDARD.encode(&png);
+
+        let mut parser = TerminalParser::new(80, 24);
+        parser.feed(format!("a\r\n\x1b]1337;File=name=eA==;inline=1:{}\x07b\r\n", body).as_bytes());
+        parser.feed(b"\x1bPq#1!16~\x1b\\c");
+
+        let grid = parser.grid_with_scrollback();
+        let images = grid.images();
+        assert_eq!(images.len(), 2);
+        assert_eq!((images[0].col, images[0].row), (0, 1));
+        assert_eq!(images[0].image.format, crate::ImageFormat::Png);
+        assert_eq!(images[0].image.cell_span, (4, 3));
+        assert_eq!(&images[0].image.data[..], &png[..]);
+        assert_eq!(images[1].row, 5);
+        assert_eq!(images[1].image.format, crate::ImageFormat::Rgba);
+        assert_eq!((images[1].image.width, images[1].image.height, images[1].image.cell_span), (16, 6, (2, 1)));
+
+        // The image rows are left blank; text resumes below them.
+        let text = grid.to_string();
+        let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+        assert_eq!(lines[..7], ["a", "", "", "", "b", "", "c"]);
+        assert_eq!(parser.grid().images().len(), 2);
+
+        // iTerm2 downloads aren't drawn.
+        parser.feed(format!("\x1b]1337;File=inline=0:{}\x07", body).as_bytes());
+        assert_eq!(parser.grid().images().len(), 2);
+    }
+
+    #[test]
+    fn width_mismatches_against_width_tables() {
+        let _guard = crate::width::TEST_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        let mut parser = TerminalParser::new(80, 24);
+        parser.feed("漢a\u{1F600}e\u{301}±".as_bytes());
+        assert!(parser.grid().width_mismatches().is_empty());
+
+        // The terminal keeps `±` narrow; CJK-width tables disagree.
+        crate::width::set_ambiguous_wide(true);
+        let mismatches = parser.grid().width_mismatches();
+        crate::width::set_ambiguous_wide(false);
+        assert_eq!(mismatches, [(6, 0)]);
+    }
 }
@@ -10,7 +10,7 @@ use alacritty_terminal::grid::Dimensions;
 use alacritty_terminal::term::{Config, Term, TermMode, test::TermSize};
 use alacritty_terminal::vte::ansi::Processor;
 
-use crate::cell::{Cell, CellFlags, Color};
+use crate::cell::Cell;
 use crate::grid::{CursorShape, TerminalGrid};
 
 /// A Send-safe terminal parser for agent-side shadow tracking.
@@ -99,20 +99,7 @@ impl ShadowParser {
             let col = indexed_cell.point.column.0 as u16;
             let row = indexed_cell.point.line.0 as u16;
 
-            let zerowidth = indexed_cell
-                .zerowidth()
-                .filter(|zw| !zw.is_empty())
-                .map(|zw| zw.to_vec().into_boxed_slice());
-
-            let cell = Cell {
-                c: indexed_cell.c,
-                fg: Color::from(indexed_cell.fg),
-                bg: Color::from(indexed_cell.bg),
-                flags: CellFlags::from(indexed_cell.flags),
-                zerowidth,
-            };
-
-            grid.set(col, row, cell);
+            grid.set(col, row, Cell::from(indexed_cell.cell));
         }
 
         let cursor = term_content.cursor;
@@ -189,18 +176,7 @@ impl ShadowParser {
             let term_line = Line(-((history_lines - line_idx) as i32));
             let row = &grid[term_line];
             for col_idx in 0..cols {
-                let cell = &row[Column(col_idx)];
-                let zerowidth = cell
-                    .zerowidth()
-                    .filter(|zw| !zw.is_empty())
-                    .map(|zw| zw.to_vec().into_boxed_slice());
-                cells.push(Cell {
-                    c: cell.c,
-                    fg: Color::from(cell.fg),
-                    bg: Color::from(cell.bg),
-                    flags: CellFlags::from(cell.flags),
-                    zerowidth,
-                });
+                cells.push(Cell::from(&row[Column(col_idx)]));
             }
         }
 
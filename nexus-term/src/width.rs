@@ -0,0 +1,140 @@
+//! Display width of text in terminal columns.
+//!
+//! One table-driven answer (Unicode's East Asian Width and emoji data, via
+//! `unicode-width`) for everything that lays text out in columns: table
+//! cells, truncated completion labels, and checks on the terminal grid.
+//!
+//! Characters of *ambiguous* East Asian width (box drawing, `±`, `…`, …)
+//! are one column in most Western fonts and two in CJK ones. Which applies
+//! is a process-wide switch, [`set_ambiguous_wide`], set from the
+//! `[font] ambiguous_wide` config.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+static AMBIGUOUS_WIDE: AtomicBool = AtomicBool::new(false);
+
+/// Count ambiguous-width characters as two columns (`true`) or one.
+pub fn set_ambiguous_wide(wide: bool) {
+    AMBIGUOUS_WIDE.store(wide, Ordering::Relaxed);
+}
+
+/// Whether ambiguous-width characters count as two columns.
+pub fn ambiguous_wide() -> bool {
+    AMBIGUOUS_WIDE.load(Ordering::Relaxed)
+}
+
+/// Columns `c` takes on its own. Controls, combining marks and other
+/// zero-width characters take none.
+pub fn char_width(c: char) -> usize {
+    let width = if ambiguous_wide() { c.width_cjk() } else { c.width() };
+    width.unwrap_or(0)
+}
+
+/// Columns `s` takes, treating emoji ZWJ sequences, variation selectors
+/// and combining marks as parts of the character they attach to.
+pub fn str_width(s: &str) -> usize {
+    if ambiguous_wide() { s.width_cjk() } else { s.width() }
+}
+
+/// `s` cut to fit in `max` columns, ending in `…` when cut. A wide
+/// character that would straddle the edge is dropped whole, and combining
+/// marks stay with their base.
+pub fn truncate(s: &str, max: usize) -> Cow<'_, str> {
+    if str_width(s) <= max {
+        return Cow::Borrowed(s);
+    }
+    let budget = max.saturating_sub(1);
+    let mut end = 0;
+    for (start, cluster) in clusters(s) {
+        if str_width(&s[..start + cluster.text.len()]) > budget {
+            break;
+        }
+        end = start + cluster.text.len();
+    }
+    Cow::Owned(format!("{}\u{2026}", &s[..end]))
+}
+
+/// One user-perceived character and the columns it takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster<'a> {
+    pub text: &'a str,
+    pub width: usize,
+}
+
+/// `s` split into characters with what follows them attached (combining
+/// marks, variation selectors, ZWJ and what it joins), with byte offsets.
+/// For auditing how text will be laid out.
+pub fn clusters(s: &str) -> Vec<(usize, Cluster<'_>)> {
+    let mut out: Vec<(usize, Cluster<'_>)> = Vec::new();
+    let mut joined = false;
+    for (i, c) in s.char_indices() {
+        let attaches = joined || (char_width(c) == 0 && !c.is_control());
+        joined = c == '\u{200D}';
+        match out.last_mut() {
+            Some((start, cluster)) if attaches => {
+                cluster.text = &s[*start..i + c.len_utf8()];
+                cluster.width = str_width(cluster.text);
+            }
+            _ => out.push((i, Cluster { text: &s[i..i + c.len_utf8()], width: char_width(c) })),
+        }
+    }
+    out
+}
+
+/// The ambiguous switch is process-wide; tests that depend on it take this.
+#[cfg(test)]
+pub(crate) static TEST_SWITCH: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widths() {
+        let _guard = TEST_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(str_width("hello"), 5);
+        assert_eq!(str_width("漢字"), 4);
+        assert_eq!(str_width("e\u{301}"), 1);
+        assert_eq!(str_width("\u{1F600}"), 2);
+        assert_eq!(str_width("\u{2764}\u{FE0F}"), 2);
+        assert_eq!(str_width("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"), 2);
+        assert_eq!(char_width('\u{301}'), 0);
+        assert_eq!(char_width('\t'), 0);
+    }
+
+    #[test]
+    fn test_ambiguous_switch() {
+        let _guard = TEST_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(str_width("±…"), 2);
+        set_ambiguous_wide(true);
+        assert_eq!(str_width("±…"), 4);
+        assert_eq!(str_width("abc"), 3);
+        set_ambiguous_wide(false);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let _guard = TEST_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("hello world", 6), "hello\u{2026}");
+        // A wide character never straddles the edge
+        assert_eq!(truncate("漢字漢字", 4), "漢\u{2026}");
+        assert_eq!(truncate("漢字漢字", 5), "漢字\u{2026}");
+        // Combining marks stay with their base
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 3), "e\u{301}e\u{301}e\u{301}");
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}x", 3), "e\u{301}e\u{301}\u{2026}");
+        assert_eq!(truncate("abc", 0), "\u{2026}");
+    }
+
+    #[test]
+    fn test_clusters() {
+        let _guard = TEST_SWITCH.lock().unwrap_or_else(|e| e.into_inner());
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let text = format!("a\u{301}{}b", family);
+        let clusters: Vec<_> = clusters(&text).into_iter().map(|(_, c)| (c.text.to_string(), c.width)).collect();
+        assert_eq!(clusters, [("a\u{301}".to_string(), 1), (family.to_string(), 2), ("b".to_string(), 1)]);
+    }
+}
@@ -370,6 +370,12 @@ impl TestClient {
                 })) if bid == block_id => {
                     output.extend_from_slice(&data);
                 }
+                Ok(Ok(ShellEvent::CommandError {
+                    block_id: bid,
+                    error,
+                })) if bid == block_id => {
+                    output.extend_from_slice(format!("{error}\n").as_bytes());
+                }
                 Ok(Ok(ShellEvent::CommandOutput {
                     block_id: bid,
                     value,
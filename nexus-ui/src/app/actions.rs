@@ -1,15 +1,34 @@
 //! State actions — focus, cursor, clipboard, terminal sizing, block navigation.
 
+use std::collections::VecDeque;
 use std::time::Instant;
 
 use strata::ImageStore;
 
 use crate::data::Focus;
+use crate::data::macros::{self, MacroStep};
+use crate::features::macros::PausedReplay;
+use crate::features::settings::Edit;
+use super::message::{CrashMsg, InsightsMsg, MacroMsg, NexusMessage, OnboardingMsg, UpdateMsg};
 use super::NexusState;
 
 const ZOOM_STEP: f32 = 0.1;
 const ZOOM_MIN: f32 = 0.5;
 const ZOOM_MAX: f32 = 3.0;
+/// Text size at zoom 1.0; `font.size` in the config picks the zoom level.
+const BASE_FONT_SIZE: f32 = 14.0;
+
+/// Frame rate cap in low-power mode; output renders coalesce to this rate.
+pub(super) const LOW_POWER_FPS: u32 = 20;
+/// How often to re-read battery / Low Power Mode state.
+const POWER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// How often to look for a network route.
+const NETWORK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// The zoom level that renders text at the configured `font.size`.
+pub(super) fn font_zoom(config: &nexus_kernel::config::Config) -> Option<f32> {
+    config.font_size().map(|size| (size / BASE_FONT_SIZE).clamp(ZOOM_MIN, ZOOM_MAX))
+}
 
 impl NexusState {
     pub(super) fn zoom_in(&mut self) {
@@ -20,9 +39,189 @@ impl NexusState {
         self.zoom_level = (self.zoom_level - ZOOM_STEP).max(ZOOM_MIN);
     }
 
+    // --- Settings ---
+
+    /// Write a change from the settings view to the user config and apply
+    /// it. On failure the file is left as it was and the view shows why.
+    pub(super) fn save_setting(&mut self, edit: Edit) {
+        let Some(path) = nexus_kernel::config::user_config_path() else {
+            self.settings.error = Some("HOME is not set".to_string());
+            return;
+        };
+        let font_changed = matches!(&edit, Edit::Set(key, _) | Edit::Unset(key) if key == "font.size");
+        match edit.write(&path) {
+            Ok(()) => self.settings.error = None,
+            Err(e) => {
+                self.settings.error = Some(format!("{:#}", e));
+                return;
+            }
+        }
+        self.context.reload_config();
+        self.kernel.blocking_lock().state_mut().reload_config();
+        if font_changed {
+            self.zoom_level = font_zoom(&self.context.config).unwrap_or(1.0);
+        }
+    }
+
+    // --- Onboarding ---
+
+    /// Carry out a step of first-run onboarding.
+    pub(super) fn onboard(&mut self, msg: OnboardingMsg) {
+        match msg {
+            OnboardingMsg::ImportSettings => {
+                if self.onboarding.import_settings(&self.context.config) {
+                    self.context.reload_config();
+                    self.kernel.blocking_lock().state_mut().reload_config();
+                }
+            }
+            OnboardingMsg::ImportHistory => {
+                let mut kernel = self.kernel.blocking_lock();
+                let result = kernel.import_history(&self.onboarding.history);
+                let recent = kernel.get_recent_history(1000).into_iter().map(|e| e.command).collect();
+                drop(kernel);
+                self.onboarding.history_imported(result);
+                self.input.set_shell_history(recent);
+            }
+            OnboardingMsg::Finish => self.onboarding.finish(),
+        }
+    }
+
+    // --- Insights ---
+
+    /// Open, close or clear the insights view. The counts are read when it
+    /// opens, not kept live.
+    pub(super) fn handle_insights(&mut self, msg: InsightsMsg) {
+        match msg {
+            InsightsMsg::Open => {
+                self.settings.update(super::message::SettingsMsg::Close);
+                let insights = self.kernel.blocking_lock().insights();
+                self.insights.show(insights);
+            }
+            InsightsMsg::Close => self.insights.close(),
+            InsightsMsg::Clear => {
+                let kernel = self.kernel.blocking_lock();
+                let result = kernel.purge_insights();
+                let insights = kernel.insights();
+                drop(kernel);
+                self.insights.cleared(result, insights);
+            }
+        }
+    }
+
+    // --- Macros ---
+
+    /// Record, name and replay keyboard macros.
+    pub(super) fn handle_macro(
+        &mut self,
+        msg: MacroMsg,
+        ctx: &mut strata::component::Ctx,
+    ) -> strata::Command<NexusMessage> {
+        match msg {
+            MacroMsg::ToggleRecording if self.macros.recording.is_none() => {
+                self.macros.start_recording();
+                self.set_focus(Focus::Input);
+            }
+            MacroMsg::ToggleRecording => {
+                // The next line entered in the input names it.
+                if self.macros.finish_recording() {
+                    self.clear_input();
+                }
+            }
+            MacroMsg::Record(event, msg) => {
+                self.macros.record(&event);
+                return self.dispatch_update(*msg, ctx);
+            }
+            MacroMsg::InsertPause => self.macros.insert_pause(),
+            MacroMsg::Name(name) => {
+                self.macros.save_as(&name);
+                self.clear_input();
+            }
+            MacroMsg::Run(name) => {
+                if let Some((name, steps)) = self.macros.start(name) {
+                    return self.replay_macro(name, steps, ctx);
+                }
+            }
+            MacroMsg::Continue => {
+                if let Some(paused) = self.macros.paused.take() {
+                    return self.replay_macro(paused.name, paused.rest, ctx);
+                }
+            }
+            MacroMsg::Stop => self.macros.stop(),
+        }
+        strata::Command::none()
+    }
+
+    /// Press a macro's keys up to the next confirmation marker, where the
+    /// rest waits in `macros.paused`.
+    fn replay_macro(
+        &mut self,
+        name: String,
+        mut steps: VecDeque<MacroStep>,
+        ctx: &mut strata::component::Ctx,
+    ) -> strata::Command<NexusMessage> {
+        let mut commands = Vec::new();
+        while let Some(step) = steps.pop_front() {
+            if step == MacroStep::Confirm {
+                self.macros.paused = Some(PausedReplay { name, rest: steps });
+                break;
+            }
+            for event in macros::key_events(&step) {
+                // A submitted command may have taken focus; the keys were
+                // recorded in the input.
+                if self.focus != Focus::Input {
+                    self.set_focus(Focus::Input);
+                }
+                if let Some(msg) = super::routing::on_key(self, event) {
+                    commands.push(self.dispatch_update(msg, ctx));
+                }
+            }
+        }
+        strata::Command::batch(commands)
+    }
+
+    pub(super) fn clear_input(&mut self) {
+        self.input.text_input.text.clear();
+        self.input.text_input.cursor = 0;
+        self.input.text_input.selection = None;
+    }
+
+    /// Update checker. Downloading runs off the UI thread; installing
+    /// opens the staged file, which mounts a disk image in Finder.
+    pub(super) fn handle_update(&mut self, msg: UpdateMsg) -> strata::Command<NexusMessage> {
+        if msg == UpdateMsg::Install {
+            if let Some(path) = self.update.staged()
+                && let Err(e) = std::process::Command::new("open").arg(path).spawn()
+            {
+                tracing::warn!("update: failed to open {}: {}", path.display(), e);
+            }
+            return strata::Command::none();
+        }
+        match self.update.update(msg) {
+            Some(release) => strata::Command::perform(async move {
+                NexusMessage::Update(UpdateMsg::Downloaded(crate::features::update::stage(release).await))
+            }),
+            None => strata::Command::none(),
+        }
+    }
+
+    /// Crash prompt. Sending runs off the UI thread.
+    pub(super) fn handle_crash(&mut self, msg: CrashMsg) -> strata::Command<NexusMessage> {
+        if msg == CrashMsg::Copy {
+            Self::set_clipboard_text(&self.crash.report);
+            return strata::Command::none();
+        }
+        match self.crash.update(msg) {
+            Some((paths, url)) => strata::Command::perform(async move {
+                NexusMessage::Crash(CrashMsg::Submitted(crate::features::crash::submit(paths, url).await))
+            }),
+            None => strata::Command::none(),
+        }
+    }
+
+    /// Reserve a block id. Ids come from the process-wide allocator shared
+    /// with the kernel, so they are unique across windows and restarts.
     pub(super) fn next_id(&mut self) -> nexus_api::BlockId {
-        let id = self.next_block_id.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        nexus_api::BlockId(id)
+        nexus_api::BlockIdAllocator::global().reserve()
     }
 
     // --- Focus ---
@@ -119,6 +318,7 @@ impl NexusState {
     pub(super) fn clear_screen(&mut self) {
         self.shell.clear();
         self.agent.clear();
+        self.context.contributions.clear_annotations();
         self.scroll.reset();
         self.transient.dismiss_all(&mut self.input);
         self.set_focus(crate::data::Focus::Input);
@@ -140,7 +340,10 @@ impl NexusState {
                 (None, None) => unreachable!(),
             };
             if take_shell {
-                ids.push(self.shell.blocks.blocks[si].id);
+                let block = &self.shell.blocks.blocks[si];
+                if self.shell.worktree_filter.is_none() || block.worktree == self.shell.worktree_filter {
+                    ids.push(block.id);
+                }
                 si += 1;
             } else {
                 ids.push(self.agent.blocks[ai].id);
@@ -191,6 +394,31 @@ impl NexusState {
         self.shell.blocks.push(block);
     }
 
+    /// Re-insert blocks that were still running when a previous Nexus
+    /// process died, with whatever output its crash journal captured.
+    pub(super) fn restore_interrupted_blocks(&mut self) {
+        use crate::data::Block;
+        use nexus_api::{BlockState, DomainValue};
+
+        let recovered = nexus_kernel::journal::BlockJournal::recover_default(self.kernel.blocking_lock().store());
+        for recovered in recovered {
+            // The dead process's id may already be taken here; save the
+            // block under the id it gets in this window.
+            let id = self.next_id();
+            self.kernel.blocking_lock().save_recovered(id, &recovered);
+
+            let mut block = Block::new(id, recovered.command);
+            block.parser = self.shell.pty.new_parser();
+            block.parser.feed(&recovered.output);
+            // Viewers (top, less) can't resume; their raw output is enough.
+            block.structured_output = recovered
+                .value
+                .filter(|v| !matches!(v.as_domain(), Some(DomainValue::Interactive(_))));
+            block.state = BlockState::Interrupted;
+            self.shell.blocks.push(block);
+        }
+    }
+
     // --- Cursor ---
 
     pub(super) fn cursor_visible(&self) -> bool {
@@ -199,12 +427,65 @@ impl NexusState {
         if matches!(self.focus, crate::data::Focus::Block(_)) {
             return false;
         }
+        // Solid cursor in low-power mode: blinking costs a frame every 500ms.
+        if self.low_power {
+            return true;
+        }
         let blink_elapsed = Instant::now()
             .duration_since(self.last_edit_time)
             .as_millis();
         (blink_elapsed / 500) % 2 == 0
     }
 
+    // --- Power ---
+
+    /// Re-read the platform power state (rate-limited) and refresh
+    /// `low_power`. Returns true when the effective mode changed.
+    pub(super) fn poll_power(&mut self) -> bool {
+        use nexus_kernel::power;
+
+        if self.power_polled_at.is_none_or(|t| t.elapsed() >= POWER_POLL_INTERVAL) {
+            self.power_polled_at = Some(Instant::now());
+            let status = strata::platform::power_status();
+            power::set_system_low_power(status.on_battery || status.low_power_mode);
+        }
+        let low_power = power::is_low_power();
+        let changed = low_power != self.low_power;
+        if changed {
+            tracing::info!("low-power mode {}", if low_power { "on" } else { "off" });
+        }
+        self.low_power = low_power;
+        changed
+    }
+
+    /// Status-bar power pill: shown while throttling or when overridden.
+    pub(super) fn power_indicator(&self) -> Option<crate::ui::widgets::PowerIndicator> {
+        let mode = nexus_kernel::power::power_override();
+        (self.low_power || mode != nexus_kernel::power::PowerOverride::Auto)
+            .then_some(crate::ui::widgets::PowerIndicator { active: self.low_power, mode })
+    }
+
+    // --- Network ---
+
+    /// Probe for a network route (rate-limited) and refresh `online`.
+    /// Returns true when it changed.
+    pub(super) fn poll_network(&mut self) -> bool {
+        use nexus_kernel::network;
+
+        let online = if self.network_polled_at.is_none_or(|t| t.elapsed() >= NETWORK_POLL_INTERVAL) {
+            self.network_polled_at = Some(Instant::now());
+            network::probe()
+        } else {
+            network::is_online()
+        };
+        let changed = online != self.online;
+        if changed {
+            tracing::info!("network {}", if online { "back online" } else { "offline" });
+        }
+        self.online = online;
+        changed
+    }
+
     pub(super) fn has_blocks(&self) -> bool {
         !self.shell.blocks.is_empty() || !self.agent.blocks.is_empty()
     }
@@ -353,7 +634,8 @@ impl NexusState {
     pub(super) fn compute_terminal_size(vw: f32, vh: f32) -> (u16, u16) {
         let char_width = 8.4;
         let line_height = 18.0;
-        let h_padding = 4.0 + 6.0 * 2.0;
+        // Block padding, and the notes gutter left of the output.
+        let h_padding = 4.0 + 6.0 * 2.0 + crate::ui::widgets::NOTE_GUTTER_COLS as f32 * char_width;
         let v_padding = 44.0;
         let cols = ((vw - h_padding) / char_width) as u16;
         let rows = ((vh - v_padding) / line_height) as u16;
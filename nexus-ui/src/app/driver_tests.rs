@@ -0,0 +1,199 @@
+//! End-to-end UI tests through the headless driver.
+//!
+//! Unlike the state machine tests in `tests.rs`, these run the full app loop:
+//! scripted key and mouse events go through `on_key`/`on_mouse` and hit
+//! testing against the real laid-out frame, and kernel output arrives via
+//! the shell subscription, as it does in the window.
+//!
+//! The app keeps its store, journal, history and config under `$HOME`, so
+//! every test re-runs itself in a child process with `HOME` set to a fresh
+//! temp dir (see `isolated!`): nothing touches the real `~/.nexus`, and
+//! tests can't restore each other's sessions.
+
+use std::time::Duration;
+
+use nexus_api::{BlockId, BlockState, ShellEvent};
+use strata::component::ComponentApp;
+use strata::event_context::NamedKey;
+use strata::headless::HeadlessDriver;
+use strata::primitives::Point;
+
+use super::NexusState;
+
+type Driver = HeadlessDriver<ComponentApp<NexusState>>;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Set in the child process a test re-runs itself in.
+const CHILD_ENV: &str = "NEXUS_DRIVER_TEST_CHILD";
+
+/// Run the rest of the test only in an isolated child process; in the
+/// parent, wait for the child and fail with its output if it failed.
+macro_rules! isolated {
+    ($name:ident) => {
+        if !run_isolated(stringify!($name)) {
+            return;
+        }
+    };
+}
+
+/// `true` when already isolated. Otherwise runs test `name` in a child of
+/// this test binary with a scratch `HOME` and returns `false`.
+fn run_isolated(name: &str) -> bool {
+    if std::env::var_os(CHILD_ENV).is_some() {
+        return true;
+    }
+    let home = tempfile::tempdir().expect("create scratch HOME");
+    // Test names are module paths without the crate name.
+    let module = module_path!().split_once("::").map_or(module_path!(), |(_, rest)| rest);
+    let test = format!("{}::{}", module, name);
+    let output = std::process::Command::new(std::env::current_exe().expect("test binary path"))
+        .args([test.as_str(), "--exact", "--nocapture", "--test-threads=1"])
+        .env(CHILD_ENV, "1")
+        .env("HOME", home.path())
+        .env_remove("HISTFILE")
+        .output()
+        .expect("run isolated test");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{} failed:\n{}\n{}", test, stdout, stderr);
+    // `--exact` with a wrong name runs nothing and still succeeds.
+    assert!(stdout.contains("1 passed"), "{} did not run:\n{}", test, stdout);
+    false
+}
+
+fn driver() -> Driver {
+    HeadlessDriver::new(1000.0, 700.0)
+}
+
+/// Inject a kernel event as if a command produced it.
+fn emit(driver: &mut Driver, event: ShellEvent) {
+    driver.state().kernel_tx.send(event).expect("shell subscription is listening");
+}
+
+fn finished(state: &NexusState, command: &str) -> bool {
+    state.shell.blocks.blocks.iter().any(|b| b.command == command && !b.is_running())
+}
+
+#[test]
+fn typed_command_runs_and_selection_copies_output() {
+    isolated!(typed_command_runs_and_selection_copies_output);
+    let mut d = driver();
+    d.type_text("echo hello | tr a-z A-Z");
+    d.press(NamedKey::Enter);
+    assert!(d.run_until(TIMEOUT, |s| finished(s, "echo hello | tr a-z A-Z")), "command never finished");
+
+    let block = d.state().shell.blocks.blocks.last().unwrap();
+    assert_eq!(block.state, BlockState::Success);
+
+    let start = d.find_text("HELLO").expect("output is drawn");
+    d.drag(start, Point::new(start.x + 60.0, start.y));
+
+    let state = d.state();
+    let copied = state.selection.extract_selected_text(&state.shell.blocks.blocks, &state.agent.blocks);
+    assert!(copied.is_some_and(|t| t.contains("HELLO")), "selection did not cover the output");
+}
+
+#[test]
+fn injected_events_render_a_block() {
+    isolated!(injected_events_render_a_block);
+    let mut d = driver();
+    let block_id = BlockId(9_000);
+    emit(&mut d, ShellEvent::CommandStarted { block_id, command: "fake".into(), cwd: "/".into(), container: None });
+    emit(&mut d, ShellEvent::StdoutChunk { block_id, data: b"scripted output\r\n".to_vec(), last_echo_epoch: 0 });
+    emit(&mut d, ShellEvent::CommandFinished { block_id, exit_code: 3, duration_ms: 1 });
+
+    assert!(d.run_until(TIMEOUT, |s| finished(s, "fake")));
+    let block = d.state().shell.blocks.get(block_id).unwrap();
+    assert_eq!(block.state, BlockState::Failed(3));
+    assert!(d.find_text("scripted output").is_some(), "output not in frame:\n{}", d.visible_text());
+}
+
+#[test]
+fn repeated_output_is_folded() {
+    isolated!(repeated_output_is_folded);
+    let mut d = driver();
+    for (block_id, output) in [(9_001, "up 3 days"), (9_002, "up 3 days"), (9_003, "up 4 days")] {
+        let block_id = BlockId(block_id);
+        emit(&mut d, ShellEvent::CommandStarted { block_id, command: "uptime".into(), cwd: "/".into(), container: None });
+        let data = format!("{}\r\n", output).into_bytes();
+        emit(&mut d, ShellEvent::StdoutChunk { block_id, data, last_echo_epoch: 0 });
+        emit(&mut d, ShellEvent::CommandFinished { block_id, exit_code: 0, duration_ms: 1 });
+    }
+    assert!(d.run_until(TIMEOUT, |s| s.shell.blocks.get(BlockId(9_003)).is_some_and(|b| !b.is_running())));
+
+    let blocks = &d.state().shell.blocks;
+    assert_eq!(blocks.get(BlockId(9_001)).unwrap().repeat_of, None);
+    assert_eq!(blocks.get(BlockId(9_002)).unwrap().repeat_of, Some(BlockId(9_001)));
+    assert_eq!(blocks.get(BlockId(9_003)).unwrap().repeat_of, None);
+    assert!(d.find_text("Same output as previous run").is_some(), "no fold marker:\n{}", d.visible_text());
+}
+
+#[test]
+fn restored_blocks_start_collapsed() {
+    use nexus_kernel::persistence::SessionBlock;
+    isolated!(restored_blocks_start_collapsed);
+
+    let mut d = driver();
+    let saved = |id, command: &str, exit_code| SessionBlock {
+        id,
+        block_id: BlockId(id as u64),
+        command: command.into(),
+        exit_code: Some(exit_code),
+        duration_ms: Some(5),
+        timestamp: chrono::Utc::now(),
+        has_output: false,
+        has_snapshot: false,
+        notes: Vec::new(),
+    };
+    d.state_mut().shell.rehydrate(vec![saved(1, "cargo build", 0), saved(2, "cargo test", 101)]);
+    d.render();
+
+    let blocks = &d.state().shell.blocks.blocks;
+    assert_eq!(blocks.len(), 2);
+    assert!(blocks.iter().all(|b| b.collapsed && b.restored.is_some_and(|r| !r.loaded)));
+    assert_eq!(blocks[1].state, BlockState::Failed(101));
+
+    let toggle = d.find_text("From the previous session").expect("restored marker is drawn");
+    d.click(toggle);
+    let block = &d.state().shell.blocks.blocks[0];
+    assert!(!block.collapsed);
+    assert!(block.restored.is_some_and(|r| r.loaded));
+}
+
+#[test]
+fn unknown_command_fails_without_spawning() {
+    use nexus_kernel::executables::CommandStatus;
+    isolated!(unknown_command_fails_without_spawning);
+
+    let mut d = driver();
+    let command = "nexus-no-such-command --flag";
+    // The PATH scan runs in the background; wait for it so the check is made.
+    let scanned = |s: &NexusState| s.kernel.try_lock().is_ok_and(|k| k.command_status(command) != CommandStatus::Unchecked);
+    assert!(d.run_until(TIMEOUT, scanned), "PATH was never scanned");
+
+    d.type_text(command);
+    d.press(NamedKey::Enter);
+    assert!(d.run_until(TIMEOUT, |s| finished(s, command)));
+
+    let block = d.state().shell.blocks.blocks.last().unwrap();
+    assert_eq!(block.state, BlockState::Failed(127));
+    // A shell reporting 127 would leave only terminal output.
+    let error = block.error.as_ref().expect("the block reports the missing command");
+    assert_eq!(error.command, "nexus-no-such-command");
+    assert!(d.find_text("command not found").is_some(), "no error chip:\n{}", d.visible_text());
+}
+
+#[test]
+fn clicking_input_keeps_typed_text() {
+    isolated!(clicking_input_keeps_typed_text);
+    let mut d = driver();
+    d.type_text("ls");
+    assert_eq!(d.state().input.text_input.text, "ls");
+
+    let pos = d.find_text("ls").expect("input text is drawn");
+    d.click(pos);
+    d.type_text(" -la");
+    assert!(d.state().input.text_input.text.contains("-la"));
+    assert!(!d.exit_requested());
+}
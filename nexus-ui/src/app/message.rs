@@ -12,7 +12,10 @@ use strata::{ScrollAction, TextInputMouseAction};
 
 use crate::data::ProcSort;
 
+use crate::data::keymap::Action;
 use crate::features::agent::events::AgentEvent;
+use crate::features::settings::SettingValue;
+use crate::features::shell::bundle::Bundle;
 use crate::ui::context_menu::{ContextMenuItem, ContextTarget};
 
 // =========================================================================
@@ -27,6 +30,12 @@ pub enum NexusMessage {
     Agent(AgentMsg),
     Selection(SelectionMsg),
     Viewer(ViewerMsg),
+    Settings(SettingsMsg),
+    Onboarding(OnboardingMsg),
+    Update(UpdateMsg),
+    Crash(CrashMsg),
+    Insights(InsightsMsg),
+    Macro(MacroMsg),
 
     // Cross-cutting (root handles directly)
     FocusBlock(BlockId),
@@ -41,10 +50,19 @@ pub enum NexusMessage {
     BlurAll,
     Tick,
     ScrollToJob(u32),
+    /// Cycle the low-power override from the status bar (auto → on → off).
+    CyclePowerMode,
     /// Unnest remote connection to the specified depth (0 = disconnect entirely).
     UnnestToLevel(usize),
     /// Disconnect confirmation timeout expired (3s elapsed without second click).
     DisconnectConfirmExpired,
+    /// A context provider replied to a lifecycle event.
+    Provider(crate::data::provider_host::ProviderUpdate),
+    /// Hide a provider warning (by id) until the directory changes.
+    DismissWarning(String),
+    /// Activate the project environment shown at this index in the
+    /// prompt, or deactivate it when active.
+    ToggleEnvironment(usize),
     /// Remote connection state changed (from reconnect task).
     RemoteStateChanged(crate::features::shell::remote::ConnectionState),
     /// Reconnection succeeded — swap transport.
@@ -93,6 +111,95 @@ pub enum NexusMessage {
     ToggleDebugLayout,
 }
 
+/// Settings view messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingsMsg {
+    Open,
+    /// Open or close (the settings shortcut).
+    Toggle,
+    Close,
+    /// Write a dotted key to the user config.
+    Set(String, SettingValue),
+    /// Remove a dotted key from the user config.
+    Unset(String),
+    /// Wait for a new chord for this action.
+    Capture(Action),
+    /// The key pressed with Cmd while capturing.
+    Captured(String),
+    CancelCapture,
+}
+
+/// First-run onboarding messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingMsg {
+    /// Write the previewed aliases, env and PATH to the user config.
+    ImportSettings,
+    /// Copy the previous shell's history into Nexus's.
+    ImportHistory,
+    /// Close and don't offer again.
+    Finish,
+}
+
+/// Update checker messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateMsg {
+    /// The startup check finished; `None` when already up to date.
+    Checked(Option<nexus_kernel::update::Release>),
+    ShowNotes,
+    CloseNotes,
+    /// Download and stage the release (macOS).
+    Download,
+    Downloaded(Result<std::path::PathBuf, String>),
+    /// Open the staged download.
+    Install,
+    /// Hide the update until next launch.
+    Dismiss,
+}
+
+/// Insights view messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsightsMsg {
+    Open,
+    Close,
+    /// Forget the recorded usage.
+    Clear,
+}
+
+/// Keyboard macro messages.
+#[derive(Debug, Clone)]
+pub enum MacroMsg {
+    /// Start recording, or stop and ask for a name.
+    ToggleRecording,
+    /// A key the input bar handled while recording, and what it routed to.
+    Record(KeyEvent, Box<NexusMessage>),
+    /// Add a pause-for-confirmation marker to the recording.
+    InsertPause,
+    /// Save the recording under this name.
+    Name(String),
+    /// Replay a saved macro; `None` is the one last saved or run.
+    Run(Option<String>),
+    /// Go on past a confirmation marker.
+    Continue,
+    /// Drop the recording, the name prompt or the paused replay.
+    Stop,
+}
+
+/// Crash prompt messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrashMsg {
+    /// Show or hide the full report.
+    Review,
+    /// Send the reports to `[crash] upload_url`.
+    Submit,
+    Submitted(Result<(), String>),
+    /// Copy the newest report to the clipboard.
+    Copy,
+    /// Don't send, and don't ask again.
+    Dismiss,
+    /// Close; ask again next start.
+    Later,
+}
+
 /// File drop messages (from OS → Nexus).
 #[derive(Debug, Clone)]
 pub enum FileDropMsg {
@@ -106,6 +213,8 @@ pub enum FileDropMsg {
     FileLoaded(PathBuf, Vec<u8>),
     /// Async file read failed.
     FileLoadFailed(PathBuf, String),
+    /// A dropped `.nexusblock` bundle was read.
+    BundleLoaded(PathBuf, Result<Box<Bundle>, String>),
 }
 
 /// Where a file drop is targeting.
@@ -182,6 +291,17 @@ pub enum InputMsg {
         /// Monotonic generation — discard if != current history_generation.
         generation: u64,
     },
+
+    // Agent ghost completion
+    /// The agent's continuation of `prefix` (empty if it had none).
+    GhostResult {
+        prefix: String,
+        suffix: String,
+        /// Monotonic generation — discard if != current ghost generation.
+        generation: u64,
+    },
+    /// Tab: append the shown ghost suggestion.
+    AcceptGhost,
 }
 
 // =========================================================================
@@ -199,8 +319,53 @@ pub enum ShellMsg {
     PtyInput(BlockId, KeyEvent),
     /// Root resolves the target block and passes its ID.
     SendInterrupt(BlockId),
+    /// Key typed into the secure sudo password prompt.
+    SudoKey(KeyEvent),
+    /// Send the entered sudo password to the prompting PTY.
+    SudoSubmit,
+    /// Dismiss the sudo prompt and return focus to the terminal.
+    SudoCancel,
     KernelEvent(nexus_api::ShellEvent),
+    /// The kernel subscription fell behind and this many events were lost.
+    KernelLagged(u64),
     KillBlock(BlockId),
+    /// Switch a block between its terminal grid and the timeline of
+    /// output chunks.
+    ToggleTimeline(BlockId),
+    /// Expand or collapse a block's resource usage footer.
+    ToggleUsage(BlockId),
+    /// Show or fold the output of a block that repeats its last run.
+    ToggleRepeat(BlockId),
+    /// Expand or collapse a block restored from the previous session,
+    /// loading its output from the store the first time.
+    ToggleRestored(BlockId),
+    /// Start writing a note on an output line (from 0) of a block, in the
+    /// input bar.
+    EditNote(BlockId, usize),
+    /// Open the next problem a rerun added in the editor.
+    NextProblem(BlockId),
+    /// Switch a test run between its result tree and terminal output.
+    ToggleTestOutput(BlockId),
+    /// Expand or collapse a suite, or a failed test's output.
+    ToggleTestNode(BlockId, usize, Option<usize>),
+    /// Run the command that re-runs a test run's failures.
+    RerunFailedTests(BlockId),
+    /// Start a `repl` block's interpreter over with a fresh namespace.
+    RestartRepl(BlockId),
+    /// Add a block to the bulk selection, or take it out.
+    ToggleBlockSelected(BlockId),
+    ClearBlockSelection,
+    /// Apply an action to every selected block.
+    Bulk(crate::features::shell::bulk::BulkAction),
+    /// Take a block out of the session; true also deletes its stored copy.
+    HideBlock(BlockId, bool),
+    /// Bring back the block hidden last.
+    UndoHide,
+    /// Collapse or expand the group whose first block this is.
+    ToggleGroup(BlockId),
+    Ungroup(BlockId),
+    /// Step, continue or abort a paused `debug <script>`.
+    Debug(BlockId, nexus_kernel::debug::DebugAction),
     SortTable(BlockId, usize),
     /// Apply or clear a column filter on a table block.
     FilterTable(BlockId, usize, Option<crate::data::ColumnFilter>),
@@ -259,6 +424,9 @@ pub enum AgentMsg {
     Event(AgentEvent),
     ToggleThinking(BlockId),
     ToggleTool(BlockId, usize),
+    TogglePlan(BlockId),
+    /// Copy a block's plan to the clipboard as a Markdown task list.
+    CopyPlan(BlockId),
     /// Expand all collapsed tools in the most recent agent block (Ctrl+O).
     ExpandAllTools,
     PermissionGrant(BlockId, String),
@@ -271,6 +439,35 @@ pub enum AgentMsg {
     /// Mouse event for the free-form question text input.
     QuestionInputMouse(strata::text_input_state::TextInputMouseAction),
     Interrupt,
+    /// Open context file `i` in its default app.
+    OpenContextFile(usize),
+    /// Show or hide the diff of the agent's changes to context file `i`.
+    ToggleContextDiff(usize),
+    /// Remove context file `i`; the next query tells the agent.
+    RemoveContextFile(usize),
+    /// Load the chosen JSONL transcript; None if the picker was cancelled.
+    ImportTranscript(Option<PathBuf>),
+    /// A citation in a response was clicked.
+    FollowCitation(crate::features::agent::citations::Citation),
+    /// Open the project instructions (NEXUS.md) in the editor, creating
+    /// one when there are none.
+    EditInstructions,
+    /// Show or hide the background task list.
+    ToggleTasks,
+    /// Show or hide a background task's answer.
+    ToggleTask(u64),
+    CancelTask(u64),
+    /// Drop finished, failed and cancelled tasks from the list.
+    ClearTasks,
+    /// A background task's run ended.
+    TaskFinished(u64, Result<crate::features::agent::tasks::TaskOutcome, String>),
+    /// Copy the command that installs the Claude CLI, from a block's setup
+    /// panel.
+    CopySetupCommand(BlockId),
+    /// Check the Claude CLI again from a block's setup panel.
+    CheckSetup(BlockId),
+    /// Send a block's query again, once the CLI is set up.
+    RerunQuery(BlockId),
 }
 
 // =========================================================================
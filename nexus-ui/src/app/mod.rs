@@ -12,13 +12,21 @@ mod update;
 mod view;
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod driver_tests;
 
-use message::{NexusMessage, InputMsg};
+use message::{NexusMessage, InputMsg, UpdateMsg};
 use crate::features::input::InputWidget;
 use crate::ui::scroll::ScrollModel;
 use crate::features::selection::SelectionWidget;
 use crate::features::shell::ShellWidget;
 use crate::features::agent::AgentWidget;
+use crate::features::onboarding::OnboardingWidget;
+use crate::features::update::UpdateWidget;
+use crate::features::crash::CrashPromptWidget;
+use crate::features::insights::InsightsWidget;
+use crate::features::macros::MacroWidget;
+use crate::features::settings::SettingsWidget;
 use crate::ui::transient::TransientUi;
 
 use std::cell::Cell;
@@ -32,6 +40,9 @@ use nexus_kernel::Kernel;
 
 use crate::data::Focus;
 use crate::data::context::NexusContext;
+use crate::data::provider_host::ProviderHost;
+use crate::data::providers::ProviderRegistry;
+use crate::infra::systems::provider_subscription;
 use strata::component::{Component, ComponentApp, Ctx, IdSpace, RootComponent};
 use strata::event_context::{CaptureState, FileDropEvent, KeyEvent, MouseEvent};
 use strata::layout_snapshot::HitResult;
@@ -64,23 +75,24 @@ pub struct Attachment {
 /// native shell history file.
 #[derive(Clone)]
 pub struct NexusShared {
-    /// Global block ID counter — ensures unique IDs across all windows.
-    pub next_block_id: Arc<AtomicU64>,
     /// Hues assigned to existing windows, for max-distance tint selection.
     pub window_hues: Arc<std::sync::Mutex<Vec<f32>>>,
     /// Global window ID counter.
     pub next_window_id: Arc<AtomicU64>,
     /// Session registry — external tools query this via the UDS server.
     pub session_registry: crate::infra::scripting::SessionRegistry,
+    /// Where the next window starts instead of $HOME, e.g. a worktree
+    /// picked from the palette.
+    pub next_window_cwd: Arc<std::sync::Mutex<Option<std::path::PathBuf>>>,
 }
 
 impl Default for NexusShared {
     fn default() -> Self {
         Self {
-            next_block_id: Arc::new(AtomicU64::new(1)),
             window_hues: Arc::new(std::sync::Mutex::new(Vec::new())),
             next_window_id: Arc::new(AtomicU64::new(1)),
             session_registry: crate::infra::scripting::SessionRegistry::new(),
+            next_window_cwd: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 }
@@ -95,6 +107,12 @@ pub struct NexusState {
     pub(crate) shell: ShellWidget,
     pub(crate) agent: AgentWidget,
     pub(crate) selection: SelectionWidget,
+    pub(crate) settings: SettingsWidget,
+    pub(crate) onboarding: OnboardingWidget,
+    pub(crate) update: UpdateWidget,
+    pub(crate) crash: CrashPromptWidget,
+    pub(crate) insights: InsightsWidget,
+    pub(crate) macros: MacroWidget,
 
     // --- Subsystems ---
     pub(crate) scroll: ScrollModel,
@@ -102,7 +120,6 @@ pub struct NexusState {
 
     // --- Shared context ---
     pub cwd: String,
-    pub next_block_id: Arc<AtomicU64>,
     pub focus: Focus,
     pub kernel: Arc<Mutex<Kernel>>,
     pub kernel_tx: broadcast::Sender<nexus_api::ShellEvent>,
@@ -126,13 +143,16 @@ pub struct NexusState {
     /// CancellationToken for the in-flight reconnection task (at most one).
     pub(crate) reconnect_cancel: Option<tokio_util::sync::CancellationToken>,
     /// Timestamp of the last tick — used to detect sleep/wake (large gap = system slept).
-    pub(crate) last_tick_at: Instant,
+    /// A `Stopwatch` rather than `Instant`, which doesn't advance during sleep.
+    pub(crate) last_tick_at: nexus_api::Stopwatch,
     /// Current reconnect attempt number, shared with the reconnect task.
     pub(crate) reconnect_attempt: std::sync::Arc<std::sync::atomic::AtomicUsize>,
     /// Last seen attempt number — repaint only when this changes.
     pub(crate) last_reconnect_attempt: usize,
     /// Brief "Session restored" flash — set to `Instant::now()` on successful resume.
     pub(crate) session_restored_at: Option<Instant>,
+    /// What happened in the last session, shown on the welcome screen.
+    pub(crate) previous_session: Option<nexus_kernel::titles::SessionSummary>,
 
     // --- Layout ---
     pub zoom_level: f32,
@@ -151,6 +171,20 @@ pub struct NexusState {
     fps_smooth: Cell<f32>,
     /// Cached cursor blink state — on_tick only re-renders when it transitions.
     last_cursor_blink: bool,
+    /// Effective low-power mode (battery / OS Low Power Mode / user override).
+    /// Pauses the FPS counter and cursor blink and caps the frame rate.
+    pub(crate) low_power: bool,
+    /// Last platform power poll — battery state is read every few seconds.
+    power_polled_at: Option<Instant>,
+    /// Whether the last connectivity probe found a network route.
+    pub(crate) online: bool,
+    network_polled_at: Option<Instant>,
+    /// Scheduled runs still going: the run to record each block's outcome under.
+    pub(crate) scheduled_runs: std::collections::HashMap<nexus_api::BlockId, i64>,
+    /// Last look for due schedules; None until the first, at startup.
+    schedules_polled_at: Option<Instant>,
+    /// Last check of the kernel's background jobs for stops and exits.
+    jobs_polled_at: Option<Instant>,
     pub context: NexusContext,
 
     /// Per-window background tint color (subtle hue to distinguish windows).
@@ -164,6 +198,8 @@ pub struct NexusState {
     pub window_id: u64,
     /// Session registry ref (for cleanup on drop).
     session_registry: crate::infra::scripting::SessionRegistry,
+    /// Shared start directory for the next window (set by the palette).
+    next_window_cwd: Arc<std::sync::Mutex<Option<std::path::PathBuf>>>,
 
     /// Debug mode for layout visualization (toggle with Cmd+Shift+D).
     #[cfg(debug_assertions)]
@@ -355,13 +391,35 @@ impl Component for NexusState {
             );
         }
 
-        // FPS counter (top-right corner)
-        snapshot.primitives_mut().add_text(
-            format!("{:.0} FPS", fps),
-            strata::primitives::Point::new(vw - 70.0, 4.0),
-            crate::ui::theme::TEXT_MUTED,
-            14.0,
-        );
+        // The note on the gutter marker under the pointer
+        if let Some(hover) = self.shell.note_hover.get().filter(|hover| hover.on_gutter)
+            && let Some(text) = self.shell.block_by_id(hover.block).and_then(|b| b.note(hover.line))
+        {
+            let lines = crate::utils::text::wrap_words(text, 60);
+            let (line_h, char_w, pad) = (18.0, 7.8, 8.0);
+            let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0) as f32 * char_w + pad * 2.0;
+            let height = lines.len() as f32 * line_h + pad * 2.0;
+            let x = (hover.position.x + 14.0).min(vw - width - 4.0).max(4.0);
+            let y = if hover.position.y + 12.0 + height > vh { hover.position.y - height - 4.0 } else { hover.position.y + 12.0 };
+            let p = snapshot.overlay_primitives_mut();
+            p.add_rounded_rect(Rect::new(x, y, width, height), 6.0, crate::ui::theme::TOOL_ARTIFACT_BG);
+            p.add_border(Rect::new(x, y, width, height), 6.0, 1.0, crate::ui::theme::WARNING);
+            for (i, line) in lines.into_iter().enumerate() {
+                let at = strata::primitives::Point::new(x + pad, y + pad + i as f32 * line_h);
+                p.add_text(line, at, crate::ui::theme::TEXT_PRIMARY, 13.0);
+            }
+        }
+
+        // FPS counter (top-right corner) — paused in low-power mode, where
+        // the capped frame rate would make it meaningless anyway.
+        if !self.low_power {
+            snapshot.primitives_mut().add_text(
+                format!("{:.0} FPS", fps),
+                strata::primitives::Point::new(vw - 70.0, 4.0),
+                crate::ui::theme::TEXT_MUTED,
+                14.0,
+            );
+        }
 
         // Debug layout overlay (Cmd+Shift+D to toggle)
         #[cfg(debug_assertions)]
@@ -445,10 +503,13 @@ impl Component for NexusState {
     }
 
     fn subscription(&self) -> Subscription<NexusMessage> {
-        let subs = vec![
+        let mut subs = vec![
             self.shell.subscription(),
             self.agent.subscription(),
         ];
+        if let Some(rx) = self.context.providers.updates() {
+            subs.push(provider_subscription(rx).map(NexusMessage::Provider));
+        }
 
         Subscription::batch(subs)
     }
@@ -460,6 +521,17 @@ impl Component for NexusState {
         self.on_output_arrived();
         let spring_animating = self.scroll.tick_overscroll();
 
+        // Flush a debounced PTY resize once the window size settles, and
+        // bring alternate-screen grids along with it.
+        self.shell.pty.sync_pty_sizes();
+        let pty_resized = self.shell.sync_alt_screen_sizes();
+        let throughput_changed = self.shell.sample_throughput();
+        let blocks_scanned = self.scan_finished();
+        let hidden_expired = self.expire_hidden_block();
+
+        let power_changed = self.poll_power();
+        let network_changed = self.poll_network();
+
         // Cursor blink: only re-render on the 500ms transition, not every tick.
         let cursor_now = self.cursor_visible();
         let cursor_changed = cursor_now != self.last_cursor_blink;
@@ -468,10 +540,9 @@ impl Component for NexusState {
         // Detect sleep/wake: if the tick gap exceeds 5s, the system probably
         // slept. Cancel any in-flight reconnect so check_reconnect() starts
         // a fresh attempt immediately (rather than waiting out old retry delays).
-        let now = Instant::now();
-        if now.duration_since(self.last_tick_at).as_secs() > 5 {
-            tracing::info!("detected system wake ({}s gap)",
-                now.duration_since(self.last_tick_at).as_secs());
+        let tick_gap = self.last_tick_at.elapsed();
+        if tick_gap.as_secs() > 5 {
+            tracing::info!("detected system wake ({}s gap)", tick_gap.as_secs());
             if let Some(cancel) = self.reconnect_cancel.take() {
                 tracing::info!("restarting in-flight reconnect after wake");
                 cancel.cancel();
@@ -487,9 +558,13 @@ impl Component for NexusState {
                 remote.last_pong_at.store(0, std::sync::atomic::Ordering::Relaxed);
             }
         }
-        self.last_tick_at = now;
+        self.last_tick_at = nexus_api::Stopwatch::start();
 
-        let cmd = self.check_reconnect();
+        let (tasks_changed, tasks_cmd) = self.poll_agent_tasks();
+        let (schedules_ran, schedules_cmd) = self.poll_schedules();
+        self.poll_jobs();
+        let cmd = Command::batch(vec![self.check_reconnect(), self.poll_ghost_completion(), tasks_cmd, schedules_cmd]);
+        let sent_queued = self.send_queued_agent_query();
 
         // Clear "Session restored" flash after 3 seconds
         let restoring = if let Some(t) = self.session_restored_at {
@@ -510,7 +585,7 @@ impl Component for NexusState {
         let current_attempt = self.reconnect_attempt.load(std::sync::atomic::Ordering::Relaxed);
         let reconnect_changed = current_attempt != self.last_reconnect_attempt;
         self.last_reconnect_attempt = current_attempt;
-        let dirty = output_dirty || spring_animating || auto_scrolling || cursor_changed || connecting || restoring || reconnect_changed;
+        let dirty = output_dirty || pty_resized || spring_animating || auto_scrolling || cursor_changed || connecting || restoring || reconnect_changed || power_changed || network_changed || sent_queued || tasks_changed || schedules_ran || throughput_changed || blocks_scanned || hidden_expired;
         (dirty, cmd)
     }
 
@@ -522,6 +597,10 @@ impl Component for NexusState {
         self.zoom_level
     }
 
+    fn frame_rate_cap(&self) -> Option<u32> {
+        self.low_power.then_some(actions::LOW_POWER_FPS)
+    }
+
     fn on_native_menu_result(&mut self, index: usize) -> Option<NexusMessage> {
         let (items, target) = self.pending_menu_items.take()?;
         let item = items.into_iter().nth(index)?;
@@ -654,6 +733,19 @@ impl RootComponent for NexusState {
         // History is naturally shared (all kernels read the same shell history file).
         let (mut kernel, kernel_rx) = Kernel::new().expect("Failed to create kernel");
         let kernel_tx = kernel.event_sender().clone();
+        let kernel_dropped = kernel.dropped_events();
+        let kernel_replay = kernel.replay_log();
+
+        // Picks up renderers from plugins enabled since the last window opened.
+        let plugin_renderers = kernel
+            .commands()
+            .plugins()
+            .list()
+            .into_iter()
+            .filter(|p| p.status == nexus_kernel::plugins::PluginStatus::Loaded)
+            .filter_map(|p| p.manifest)
+            .flat_map(|m| m.renderers);
+        crate::ui::widgets::install_renderers(crate::ui::widgets::RendererRegistry::load(plugin_renderers));
 
         let command_history: Vec<String> = kernel
             .get_recent_history(1000)
@@ -661,14 +753,19 @@ impl RootComponent for NexusState {
             .map(|e| e.command)
             .collect();
 
-        // Each window starts in $HOME. The process-level CWD is not meaningful
-        // in multi-window mode — each window tracks its own CWD independently.
-        let home = std::env::var("HOME")
-            .map(std::path::PathBuf::from)
-            .unwrap_or_else(|_| std::env::current_dir().unwrap_or_default());
+        // Each window starts in $HOME, or where the window that opened it
+        // asked for. The process-level CWD is not meaningful in multi-window
+        // mode — each window tracks its own CWD independently.
+        let requested = shared.next_window_cwd.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let home = requested.filter(|dir| dir.is_dir()).unwrap_or_else(|| {
+            std::env::var("HOME")
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|_| std::env::current_dir().unwrap_or_default())
+        });
         let cwd = home.display().to_string();
 
-        let context = NexusContext::new(home.clone());
+        let mut context = NexusContext::new(home.clone());
+        context.start_providers(ProviderHost::spawn(&ProviderRegistry::new()));
 
         // Assign a unique window ID and start the scripting server (first window only).
         let window_id = shared.next_window_id.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
@@ -682,8 +779,28 @@ impl RootComponent for NexusState {
             (hue, crate::ui::theme::tinted_bg(hue))
         };
 
+        // First launch: offer to bring the user's shell setup along.
+        let onboarding = if window_id == 1 {
+            OnboardingWidget::first_run(&context.config, kernel.history_path())
+        } else {
+            OnboardingWidget::closed()
+        };
+        // After a crash: offer the report.
+        let crash = if window_id == 1 {
+            CrashPromptWidget::on_start(&context.config)
+        } else {
+            CrashPromptWidget::closed()
+        };
+
         // Sync the kernel's internal CWD to match this window's starting dir.
         kernel.state_mut().set_cwd(home).ok();
+        context.environments = nexus_kernel::dev_env::status(kernel.state());
+        let previous_session = kernel.previous_session_summary();
+        let restored_blocks = if window_id == 1 && context.config.restores_session() {
+            kernel.previous_session_blocks()
+        } else {
+            Vec::new()
+        };
 
         let kernel = Arc::new(Mutex::new(kernel));
 
@@ -692,17 +809,22 @@ impl RootComponent for NexusState {
         // set_focus() before the state is constructed.
         input_widget.text_input.focused = true;
 
-        let state = NexusState {
+        let mut state = NexusState {
             input: input_widget,
-            shell: ShellWidget::new(Arc::new(Mutex::new(kernel_rx))),
+            shell: ShellWidget::new(Arc::new(Mutex::new(kernel_rx)), kernel_dropped, kernel_replay),
             agent: AgentWidget::new(),
             selection: SelectionWidget::new(),
+            settings: SettingsWidget::new(),
+            onboarding,
+            update: UpdateWidget::new(),
+            crash,
+            insights: InsightsWidget::new(),
+            macros: MacroWidget::new(),
 
             scroll: ScrollModel::new(),
             transient: TransientUi::new(),
 
             cwd,
-            next_block_id: shared.next_block_id.clone(),
             focus: Focus::Input,
             kernel,
             kernel_tx,
@@ -715,12 +837,13 @@ impl RootComponent for NexusState {
             disconnect_confirm: None,
             resize_debounce_cancel: None,
             reconnect_cancel: None,
-            last_tick_at: Instant::now(),
+            last_tick_at: nexus_api::Stopwatch::start(),
             reconnect_attempt: std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0)),
             last_reconnect_attempt: 0,
             session_restored_at: None,
+            previous_session,
 
-            zoom_level: 0.85,
+            zoom_level: actions::font_zoom(&context.config).unwrap_or(0.85),
 
             last_edit_time: Instant::now(),
             exit_requested: false,
@@ -730,17 +853,39 @@ impl RootComponent for NexusState {
             last_frame: Cell::new(Instant::now()),
             fps_smooth: Cell::new(0.0),
             last_cursor_blink: true,
+            low_power: nexus_kernel::power::is_low_power(),
+            power_polled_at: None,
+            // Probed up front so the startup update check knows.
+            online: nexus_kernel::network::probe(),
+            network_polled_at: Some(Instant::now()),
+            scheduled_runs: std::collections::HashMap::new(),
+            schedules_polled_at: None,
+            jobs_polled_at: None,
             window_tint,
             window_hue,
             window_hues: shared.window_hues.clone(),
             window_id,
             session_registry: shared.session_registry.clone(),
+            next_window_cwd: shared.next_window_cwd.clone(),
             context,
             #[cfg(debug_assertions)]
             debug_layout: false,
         };
 
-        (state, Command::none())
+        state.shell.rehydrate(restored_blocks);
+        state.restore_interrupted_blocks();
+        state.shell.blocks.journal = nexus_kernel::journal::BlockJournal::open_default(state.kernel.blocking_lock().store());
+
+        // Opted-in update check, once per launch.
+        let command = if window_id == 1 && state.context.config.checks_for_updates() {
+            let feed = state.context.config.update_feed().to_string();
+            Command::perform(async move {
+                NexusMessage::Update(UpdateMsg::Checked(crate::features::update::check(feed).await))
+            })
+        } else {
+            Command::none()
+        };
+        (state, command)
     }
 
     fn create_window(shared: &NexusShared, images: &mut ImageStore) -> Option<(Self, Command<NexusMessage>)> {
@@ -781,6 +926,11 @@ impl RootComponent for NexusState {
 // =========================================================================
 
 pub fn run() -> Result<(), strata::shell::Error> {
+    // NEXUS_LOW_POWER=on|off|auto presets the status-bar override.
+    if let Some(mode) = std::env::var("NEXUS_LOW_POWER").ok().and_then(|v| nexus_kernel::power::PowerOverride::parse(&v)) {
+        nexus_kernel::power::set_override(mode);
+    }
+    nexus_kernel::diagnostics::register_check(|| Some(renderer_check()));
     strata::shell::run_with_config::<ComponentApp<NexusState>>(AppConfig {
         title: String::from("Nexus (Strata)"),
         window_size: (1200.0, 800.0),
@@ -788,3 +938,16 @@ pub fn run() -> Result<(), strata::shell::Error> {
         background_color: crate::ui::theme::BG_APP,
     })
 }
+
+/// `doctor` check: the GPU the renderer came up on.
+fn renderer_check() -> nexus_kernel::diagnostics::CheckResult {
+    use nexus_kernel::diagnostics::CheckResult;
+    match strata::gpu::renderer_status() {
+        Some(Ok(info)) => {
+            let low_power = if info.low_power { " (low-power GPU)" } else { "" };
+            CheckResult::pass("renderer", format!("Metal on {}{}", info.device, low_power))
+        }
+        Some(Err(e)) => CheckResult::fail("renderer", e, "Nexus needs a Metal-capable GPU"),
+        None => CheckResult::warn("renderer", "not initialized", "open a window, then run doctor again"),
+    }
+}
@@ -7,11 +7,15 @@ use strata::layout_snapshot::HitResult;
 use strata::{MouseResponse, ScrollAction, route_mouse};
 
 use crate::data::Focus;
+use crate::data::keymap::{self, Action};
 use crate::ui::widgets::JobBar;
 
 use crate::features::selection::drag::PendingIntent;
+use crate::features::shell::notebook::NotebookFormat;
+use crate::ui::context_menu::{ContextMenuItem, ContextTarget};
 use super::message::{
-    AgentMsg, DragMsg, InputMsg, NexusMessage, SelectionMsg, ShellMsg, ViewerMsg,
+    AgentMsg, ContextMenuMsg, DragMsg, InputMsg, MacroMsg, NexusMessage, SelectionMsg, SettingsMsg, ShellMsg,
+    ViewerMsg,
 };
 use crate::utils::ids as source_ids;
 use super::NexusState;
@@ -20,6 +24,16 @@ use super::NexusState;
 // Keyboard routing
 // =========================================================================
 
+/// Route keyboard events, keeping the keys the input bar handles while a
+/// macro is being recorded.
+pub(super) fn on_key(state: &NexusState, event: KeyEvent) -> Option<NexusMessage> {
+    let msg = route_key(state, event.clone())?;
+    if state.macros.recording.is_some() && state.focus == Focus::Input && matches!(msg, NexusMessage::Input(_)) {
+        return Some(NexusMessage::Macro(MacroMsg::Record(event, Box::new(msg))));
+    }
+    Some(msg)
+}
+
 /// Route keyboard events to the appropriate widget or message.
 ///
 /// When a PTY block is focused (and no viewer is active), the terminal gets
@@ -27,7 +41,7 @@ use super::NexusState;
 /// shortcuts (`Cmd`+key on macOS) are carved out before the PTY sees them.
 /// This ensures Escape, Ctrl+C, Ctrl+R, Ctrl+Z, etc. all reach the terminal
 /// exactly as they would in iTerm2, Alacritty, or Kitty.
-pub(super) fn on_key(state: &NexusState, event: KeyEvent) -> Option<NexusMessage> {
+fn route_key(state: &NexusState, event: KeyEvent) -> Option<NexusMessage> {
     if matches!(&event, KeyEvent::Released { .. }) {
         return None;
     }
@@ -46,6 +60,69 @@ pub(super) fn on_key(state: &NexusState, event: KeyEvent) -> Option<NexusMessage
         return Some(NexusMessage::Drag(DragMsg::Cancel));
     }
 
+    // Phase 0b: Secure sudo prompt owns the keyboard until answered, so a
+    // password can never be typed into the input bar or a PTY by accident.
+    if state.shell.sudo.is_active() && !modifiers.meta {
+        return state.shell.sudo.on_key(&event).map(NexusMessage::Shell);
+    }
+
+    // Phase 0c: Crash prompt — Escape puts it off until next start.
+    if state.crash.open {
+        if let Some(msg) = state.crash.on_key(&event) {
+            return Some(NexusMessage::Crash(msg));
+        }
+    }
+
+    // Phase 0d: First-run onboarding — Escape skips it.
+    if state.onboarding.open {
+        if let Some(msg) = state.onboarding.on_key(&event) {
+            return Some(NexusMessage::Onboarding(msg));
+        }
+    }
+
+    // Phase 0e: Settings view — Escape closes it, and while rebinding the
+    // next Cmd chord is the new binding rather than a shortcut.
+    if state.settings.open {
+        if let Some(msg) = state.settings.on_key(&event) {
+            return Some(NexusMessage::Settings(msg));
+        }
+    }
+
+    // Phase 0f: Insights view — Escape closes it.
+    if state.insights.open {
+        if let Some(msg) = state.insights.on_key(&event) {
+            return Some(NexusMessage::Insights(msg));
+        }
+    }
+
+    // Phase 0g: Release notes — Escape closes them.
+    if state.update.notes_open {
+        if let Some(msg) = state.update.on_key(&event) {
+            return Some(NexusMessage::Update(msg));
+        }
+    }
+
+    // Phase 0h: Macro replay paused at a confirmation marker, or a
+    // recording waiting for its name — Enter in the input answers, Escape
+    // drops it. A terminal block keeps its Enter.
+    if state.focus == Focus::Input {
+        if let Some(msg) = state.macros.on_key(&event, &state.input.text_input.text) {
+            return Some(NexusMessage::Macro(msg));
+        }
+    }
+
+    // Phase 0i: Release chord — takes the keyboard back from a terminal
+    // block. Checked before Cmd shortcuts so it can be any chord; a
+    // full-window app keeps its keys until it exits.
+    if let Focus::Block(id) = state.focus {
+        if state.block_has_active_pty(id)
+            && state.shell.fullscreen_block().is_none()
+            && keymap::release_chords(&state.context.config).iter().any(|c| c.matches(key, modifiers))
+        {
+            return Some(NexusMessage::BlurAll);
+        }
+    }
+
     // Phase 1: Cmd-key chrome shortcuts (window management, copy/paste).
     // These are intercepted regardless of focus — they control the GUI, not
     // the terminal.
@@ -164,23 +241,37 @@ pub(super) fn on_key(state: &NexusState, event: KeyEvent) -> Option<NexusMessage
 
 /// Cmd+key shortcuts — GUI chrome that is always intercepted, even when a PTY
 /// is focused.  These are the macOS standard window/edit shortcuts.
-fn route_cmd_shortcut(_state: &NexusState, key: &Key) -> Option<NexusMessage> {
+fn route_cmd_shortcut(state: &NexusState, key: &Key) -> Option<NexusMessage> {
+    // A full-window app grabs the keyboard: only window, zoom and clipboard
+    // shortcuts still reach the GUI.
+    let grabbed = state.shell.fullscreen_block().is_some();
     if let Key::Character(c) = key {
+        // Rebindable actions first, so a binding can take over any key.
+        if let Some(action) = keymap::lookup(&state.context.config, c) {
+            let input_action = matches!(
+                action,
+                Action::ClearScreen | Action::ToggleMode | Action::Settings | Action::RecordMacro | Action::ReplayMacro
+            );
+            if grabbed && input_action {
+                return None;
+            }
+            return Some(action_message(action));
+        }
         match c.as_str() {
-            "n" => return Some(NexusMessage::NewWindow),
             "q" => return Some(NexusMessage::QuitApp),
-            "w" => return Some(NexusMessage::CloseWindow),
-            "k" => return Some(NexusMessage::ClearScreen),
             "c" => return Some(NexusMessage::Copy),
             "v" => return Some(NexusMessage::Paste),
-            "." => return Some(NexusMessage::Input(InputMsg::ToggleMode)),
-            "=" | "+" => return Some(NexusMessage::ZoomIn),
-            "-" => return Some(NexusMessage::ZoomOut),
-            "0" => return Some(NexusMessage::ZoomReset),
+            // Shifted "=" on US layouts, while zoom in keeps its default key.
+            "+" if keymap::key_for(&state.context.config, Action::ZoomIn) == "=" => {
+                return Some(NexusMessage::ZoomIn);
+            }
             _ => {}
         }
     }
     // Cmd+Arrow for first/last block (macOS top/bottom convention)
+    if grabbed {
+        return None;
+    }
     match key {
         Key::Named(NamedKey::ArrowUp) => return Some(NexusMessage::FocusFirstBlock),
         Key::Named(NamedKey::ArrowDown) => return Some(NexusMessage::FocusLastBlock),
@@ -189,6 +280,21 @@ fn route_cmd_shortcut(_state: &NexusState, key: &Key) -> Option<NexusMessage> {
     None
 }
 
+fn action_message(action: Action) -> NexusMessage {
+    match action {
+        Action::NewWindow => NexusMessage::NewWindow,
+        Action::CloseWindow => NexusMessage::CloseWindow,
+        Action::ClearScreen => NexusMessage::ClearScreen,
+        Action::ToggleMode => NexusMessage::Input(InputMsg::ToggleMode),
+        Action::ZoomIn => NexusMessage::ZoomIn,
+        Action::ZoomOut => NexusMessage::ZoomOut,
+        Action::ZoomReset => NexusMessage::ZoomReset,
+        Action::Settings => NexusMessage::Settings(SettingsMsg::Toggle),
+        Action::RecordMacro => NexusMessage::Macro(MacroMsg::ToggleRecording),
+        Action::ReplayMacro => NexusMessage::Macro(MacroMsg::Run(None)),
+    }
+}
+
 /// Global shortcuts that only apply when a PTY block is NOT focused.
 /// (Cmd+key shortcuts are handled earlier by `route_cmd_shortcut`.)
 fn route_global_shortcut(
@@ -242,6 +348,9 @@ fn route_escape(state: &NexusState) -> Option<NexusMessage> {
     if let Some(id) = state.shell.active_viewer_block() {
         return Some(NexusMessage::Viewer(ViewerMsg::Exit(id)));
     }
+    if !state.shell.bulk.selected.is_empty() {
+        return Some(NexusMessage::Shell(ShellMsg::ClearBlockSelection));
+    }
     if state.selection.selection.is_some() {
         return Some(NexusMessage::Selection(SelectionMsg::Clear));
     }
@@ -259,8 +368,8 @@ fn route_escape(state: &NexusState) -> Option<NexusMessage> {
 /// Provides arrow-key navigation between blocks, Escape to return to input,
 /// and type-through for character keys.
 fn route_block_navigation(
-    _state: &NexusState,
-    _id: nexus_api::BlockId,
+    state: &NexusState,
+    id: nexus_api::BlockId,
     key: &Key,
     modifiers: &strata::event_context::Modifiers,
     event: KeyEvent,
@@ -279,6 +388,12 @@ fn route_block_navigation(
         Key::Named(NamedKey::ArrowUp) => Some(NexusMessage::FocusPrevBlock),
         Key::Named(NamedKey::ArrowDown) => Some(NexusMessage::FocusNextBlock),
         Key::Named(NamedKey::Enter) => Some(NexusMessage::BlurAll),
+        // Delete hides the block; Shift+Delete also drops its stored copy.
+        Key::Named(NamedKey::Backspace | NamedKey::Delete)
+            if !modifiers.ctrl && !modifiers.meta && !modifiers.alt && state.shell.block_by_id(id).is_some() =>
+        {
+            Some(NexusMessage::Shell(ShellMsg::HideBlock(id, modifiers.shift)))
+        }
         // Character key or Space without modifiers → type-through to input
         Key::Character(_) | Key::Named(NamedKey::Space)
             if !modifiers.ctrl && !modifiers.meta && !modifiers.alt =>
@@ -343,8 +458,8 @@ pub(super) fn on_mouse(
     }
 
     // 4. Hover tracking
-    if let MouseEvent::CursorMoved { .. } = &event {
-        route_hover(state, &hit);
+    if let MouseEvent::CursorMoved { position, .. } = &event {
+        route_hover(state, &hit, *position);
     }
 
     // 5. Left-click chain
@@ -367,6 +482,16 @@ fn route_left_click(
     position: strata::primitives::Point,
     modifiers: strata::Modifiers,
 ) -> MouseResponse<NexusMessage> {
+    // Shift-click on a block header picks the block for bulk actions.
+    if modifiers.shift {
+        if let Some(r) = route_block_select(state, &hit) {
+            return r;
+        }
+    }
+    // The notes gutter: write a note on that line.
+    if let Some((block, line, true)) = state.shell.note_line(&hit) {
+        return MouseResponse::message(NexusMessage::Shell(ShellMsg::EditNote(block, line)));
+    }
     // Selection drag (click inside existing selection) — but NOT on multi-clicks,
     // which should pass through to route_text_selection_start for word/line snap.
     if !state.drag.click_tracker.would_be_multi_click(position) {
@@ -418,6 +543,36 @@ fn route_widget_click(
     }
 
     // Try each child in order
+    if state.crash.open {
+        if let Some(msg) = state.crash.on_click(id) {
+            return Some(MouseResponse::message(NexusMessage::Crash(msg)));
+        }
+    }
+    if state.onboarding.open {
+        if let Some(msg) = state.onboarding.on_click(id) {
+            return Some(MouseResponse::message(NexusMessage::Onboarding(msg)));
+        }
+    }
+    if state.settings.open {
+        if let Some(msg) = state.settings.on_click(id, &state.context.config) {
+            return Some(MouseResponse::message(NexusMessage::Settings(msg)));
+        }
+    }
+    if state.insights.open {
+        if let Some(msg) = state.insights.on_click(id) {
+            return Some(MouseResponse::message(NexusMessage::Insights(msg)));
+        }
+    }
+    if state.update.release.is_some() {
+        if let Some(msg) = state.update.on_click(id) {
+            return Some(MouseResponse::message(NexusMessage::Update(msg)));
+        }
+    }
+    if state.macros.is_active() {
+        if let Some(msg) = state.macros.on_click(id) {
+            return Some(MouseResponse::message(NexusMessage::Macro(msg)));
+        }
+    }
     if let Some(msg) = state.input.on_click(id) {
         return Some(MouseResponse::message(NexusMessage::Input(msg)));
     }
@@ -444,6 +599,25 @@ fn route_widget_click(
         }
     }
 
+    // Provider warning dismiss buttons
+    for (i, (warning, _)) in state.context.warnings().enumerate() {
+        if id == source_ids::warning_dismiss(i) {
+            return Some(MouseResponse::message(NexusMessage::DismissWarning(warning.to_string())));
+        }
+    }
+
+    // Project environment chips in the prompt — activate or deactivate
+    for i in 0..state.context.environments.len() {
+        if id == source_ids::environment_toggle(i) {
+            return Some(MouseResponse::message(NexusMessage::ToggleEnvironment(i)));
+        }
+    }
+
+    // Low-power pill — cycle the override
+    if id == source_ids::power_mode() {
+        return Some(MouseResponse::message(NexusMessage::CyclePowerMode));
+    }
+
     // Breadcrumb segments — unnest to clicked level
     if state.remote.is_some() {
         let stack_len = state.remote.as_ref().map_or(0, |r| r.backend_stack.len());
@@ -482,6 +656,18 @@ fn route_image_drag(
 }
 
 /// Handle click on a shell block area that didn't match any specific handler — focus the block.
+fn route_block_select(
+    state: &NexusState,
+    hit: &Option<HitResult>,
+) -> Option<MouseResponse<NexusMessage>> {
+    let Some(HitResult::Content(addr)) = hit else {
+        return None;
+    };
+    let block_id = state.shell.block_for_source(addr.source_id)?;
+    (addr.source_id == source_ids::shell_header(block_id))
+        .then(|| MouseResponse::message(NexusMessage::Shell(ShellMsg::ToggleBlockSelected(block_id))))
+}
+
 fn route_block_focus(
     state: &NexusState,
     hit: &Option<HitResult>,
@@ -507,14 +693,14 @@ fn route_right_click(
     let (x, y) = (position.x, position.y);
 
     // Input area right-click
-    if let Some(msg) = state.input.context_menu(x, y) {
-        return MouseResponse::message(NexusMessage::ContextMenu(msg));
+    if let Some(msg) = state.input.context_menu(x, y, &state.context, !state.agent.blocks.is_empty()) {
+        return MouseResponse::message(NexusMessage::ContextMenu(with_macro_items(state, msg)));
     }
 
     // Content area right-click — delegate to children
     if let Some(HitResult::Content(addr)) = hit {
         if let Some(msg) = state.shell.context_menu_for_source(addr.source_id, Some(addr.item_index), x, y) {
-            return MouseResponse::message(NexusMessage::ContextMenu(msg));
+            return MouseResponse::message(NexusMessage::ContextMenu(with_selection_export(state, msg)));
         }
         if let Some(msg) = state.agent.context_menu_for_source(addr.source_id, x, y) {
             return MouseResponse::message(NexusMessage::ContextMenu(msg));
@@ -532,7 +718,7 @@ fn route_right_click(
             .or_else(|| state.shell.block_for_anchor(*wid));
         if let Some(block_id) = block_id {
             if let Some(msg) = state.shell.context_menu_for_source(source_ids::native(block_id), None, x, y) {
-                return MouseResponse::message(NexusMessage::ContextMenu(msg));
+                return MouseResponse::message(NexusMessage::ContextMenu(with_selection_export(state, msg)));
             }
         }
     }
@@ -550,8 +736,52 @@ fn route_right_click(
     MouseResponse::none()
 }
 
-fn route_hover(state: &NexusState, hit: &Option<HitResult>) {
+/// Add export actions to a block's menu when the selection covers the block.
+fn with_selection_export(state: &NexusState, msg: ContextMenuMsg) -> ContextMenuMsg {
+    let ContextMenuMsg::Show(x, y, mut items, target) = msg else {
+        return msg;
+    };
+    let block_id = match &target {
+        ContextTarget::Block(id) | ContextTarget::TableCell { block_id: id, .. } => *id,
+        _ => return ContextMenuMsg::Show(x, y, items, target),
+    };
+    let blocks = state.selection.selected_blocks(&state.shell.blocks.blocks, &state.agent.blocks);
+    if blocks.contains(&block_id) {
+        items.push(ContextMenuItem::Separator);
+        for format in [NotebookFormat::Ipynb, NotebookFormat::Script] {
+            items.push(ContextMenuItem::ExportBlocks { format, blocks: blocks.clone() });
+        }
+    }
+    ContextMenuMsg::Show(x, y, items, target)
+}
+
+/// Add macro recording and the saved macros to the input's menu, before
+/// settings.
+fn with_macro_items(state: &NexusState, msg: ContextMenuMsg) -> ContextMenuMsg {
+    let ContextMenuMsg::Show(x, y, mut items, target) = msg else {
+        return msg;
+    };
+    let mut macro_items = vec![ContextMenuItem::Separator];
+    if state.macros.recording.is_some() {
+        macro_items.push(ContextMenuItem::InsertMacroPause);
+        macro_items.push(ContextMenuItem::StopMacroRecording);
+    } else {
+        macro_items.push(ContextMenuItem::RecordMacro);
+        macro_items.extend(state.macros.macros.iter().map(|m| ContextMenuItem::RunMacro {
+            label: format!("Run Macro: {}", m.name),
+            name: m.name.clone(),
+        }));
+    }
+    macro_items.push(ContextMenuItem::Separator);
+    let at = items.iter().position(|item| *item == ContextMenuItem::Settings).unwrap_or(items.len());
+    items.splice(at..at, macro_items);
+    ContextMenuMsg::Show(x, y, items, target)
+}
+
+fn route_hover(state: &NexusState, hit: &Option<HitResult>, position: strata::primitives::Point) {
     // Input-owned hover tracking (completion, history search)
     state.input.on_hover(hit);
+    // The output line under the pointer, for the notes gutter
+    state.shell.on_hover(hit, position);
 }
 
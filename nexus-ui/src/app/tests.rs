@@ -101,7 +101,7 @@ mod input_widget_tests {
 
     fn create_test_input() -> InputWidget {
         let history = vec!["ls".to_string(), "pwd".to_string(), "echo hello".to_string()];
-        let (kernel, _rx) = Kernel::new().expect("kernel creation");
+        let (kernel, _rx) = Kernel::ephemeral().expect("kernel creation");
         let kernel = Arc::new(Mutex::new(kernel));
         InputWidget::new(history, kernel)
     }
@@ -250,6 +250,57 @@ mod input_widget_tests {
         assert_eq!(input.text_input.text, "echo hello");
     }
 
+    #[test]
+    fn expansion_preview_only_for_history_references() {
+        let mut input = create_test_input();
+        input.paste_text("echo hello");
+        assert!(input.expansion_preview.is_none());
+
+        // Literal `!` (before whitespace / `=`) never triggers a preview.
+        input.paste_text(" != x");
+        assert!(input.expansion_preview.is_none());
+
+        input.toggle_mode();
+        input.paste_text(" !!");
+        assert!(input.expansion_preview.is_none(), "agent mode never expands");
+    }
+
+    #[test]
+    fn output_preview_follows_references() {
+        let (mut kernel, _rx) = Kernel::ephemeral().expect("kernel creation");
+        kernel.state_mut().store_output(
+            nexus_api::BlockId(1),
+            "seq 2".to_string(),
+            nexus_api::Value::List(vec![nexus_api::Value::Int(1), nexus_api::Value::Int(2)]),
+        );
+        let mut input = InputWidget::new(Vec::new(), Arc::new(Mutex::new(kernel)));
+
+        input.paste_text("echo $HOME");
+        assert!(input.output_preview.is_none());
+
+        input.paste_text(" $_1");
+        let preview = input.output_preview.as_ref().expect("preview for $_1");
+        assert_eq!((preview.shape.as_str(), preview.rows.len()), ("list of 2", 2));
+
+        input.paste_text(" $_9");
+        assert!(input.output_preview.is_none(), "nothing stored at $_9");
+    }
+
+    #[test]
+    fn lints_follow_text() {
+        let mut input = create_test_input();
+        input.paste_text("rm -rf \"$OUT");
+        assert!(input.lints.is_empty(), "unfinished lines aren't linted");
+
+        input.paste_text("/\"*");
+        let rules: Vec<_> = input.lints.iter().map(|lint| lint.rule.name()).collect();
+        assert_eq!(rules, ["rm-variable-path"]);
+
+        input.toggle_mode();
+        input.paste_text(" ");
+        assert!(input.lints.is_empty(), "agent mode is never linted");
+    }
+
     #[test]
     fn captures_keys_when_overlays_active() {
         let input = create_test_input();
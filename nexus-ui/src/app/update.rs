@@ -4,18 +4,40 @@ use std::time::Instant;
 
 use strata::Command;
 
-use crate::data::Focus;
+use crate::data::{Focus, InputMode, TestTree};
 
 use crate::ui::context_menu::{ContextMenuItem, ContextTarget};
 use crate::features::selection::drag::{ActiveKind, DragStatus, PendingIntent};
 use crate::features::selection::drop as file_drop;
 use crate::features::selection::snap;
 use crate::features::input::SubmitRequest;
-use super::message::{AnchorAction, ContextMenuMsg, DragMsg, DropZone, FileDropMsg, NexusMessage, ShellMsg, ViewerMsg};
+use crate::features::input::slash::{self, SlashAction};
+use crate::features::agent::claude::AgentLimits;
+use crate::features::agent::citations::Citation;
+use crate::features::agent::events::AgentEvent;
+use crate::features::agent::remote_files::{self, RemoteFileOp, RemoteTarget};
+use crate::features::agent::tasks;
+use crate::features::agent::transcript::{self, TranscriptFormat};
+use crate::features::shell::bulk::{self, BulkAction};
+use crate::features::shell::bundle::{self, Bundle};
+use crate::features::shell::notebook;
+use nexus_kernel::dev_env;
+use nexus_kernel::insights::UsageEvent;
+use nexus_kernel::instructions::Instructions;
+use nexus_kernel::opener;
+use nexus_kernel::test_report::{Runner, TestReport};
+use nexus_kernel::titles::{self, Outcome};
+use super::message::{AnchorAction, ContextMenuMsg, DragMsg, DropZone, FileDropMsg, MacroMsg, NexusMessage, ShellMsg, ViewerMsg};
 use crate::features::selection;
 use super::update_context::{UpdateContext, sync_focus_flags};
-use super::NexusState;
-use crate::features::shell::shell_context::build_shell_context;
+use super::{actions, NexusState};
+use crate::features::shell::shell_context::{build_shell_context, describe_block};
+
+/// How often due schedules are looked for. Schedules have minute
+/// granularity; runs started up to a poll late still count as on time.
+const SCHEDULE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(20);
+/// How often running background jobs are checked for stops and exits.
+const JOB_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
 // =========================================================================
 // Borrow-splitting helpers
@@ -76,12 +98,15 @@ impl NexusState {
             NexusMessage::Input(m) => {
                 if matches!(m, super::message::InputMsg::Mouse(_)) {
                     self.set_focus(Focus::Input);
-                } else {
+                } else if !matches!(m, super::message::InputMsg::GhostResult { .. }) {
                     self.scroll.snap_to_bottom();
                 }
 
                 // Remote tab completion: intercept and send async request
-                if matches!(m, super::message::InputMsg::TabComplete) && self.remote.is_some() {
+                if matches!(m, super::message::InputMsg::TabComplete)
+                    && self.remote.is_some()
+                    && slash::complete(&self.input.text_input.text, self.input.text_input.cursor).is_none()
+                {
                     let remote = self.remote.as_mut().unwrap();
                     let input = self.input.text_input.text.clone();
                     let cursor = self.input.text_input.cursor;
@@ -136,7 +161,7 @@ impl NexusState {
                     }
                 }
 
-                let submit = self.input.update(m);
+                let submit = self.input.update(m, &self.context.contributions);
                 if let Some(req) = submit {
                     self.handle_submit(req)
                 } else {
@@ -149,6 +174,29 @@ impl NexusState {
                     self.exec_anchor_action(action);
                     return Command::none();
                 }
+                if let ShellMsg::NextProblem(id) = m {
+                    return self.open_next_problem(id);
+                }
+                if let ShellMsg::ToggleRestored(id) = m {
+                    self.shell.toggle_restored(id, &self.kernel);
+                    return Command::none();
+                }
+                if let ShellMsg::EditNote(id, line) = m {
+                    let text = self.shell.block_by_id(id).and_then(|b| b.note(line)).unwrap_or_default();
+                    self.input.prefill_shell(&format!("/note {}:{} {}", id.0, line + 1, text));
+                    self.set_focus(Focus::Input);
+                    return Command::none();
+                }
+                if let ShellMsg::Bulk(action @ (BulkAction::Copy | BulkAction::Export(_) | BulkAction::SendToAgent)) = m {
+                    self.apply_bulk_action(action);
+                    return Command::none();
+                }
+                if let ShellMsg::RerunFailedTests(id) = m {
+                    let Some(text) = self.shell.block_by_id(id).and_then(|b| b.tests.as_ref()?.rerun.clone()) else {
+                        return Command::none();
+                    };
+                    return self.handle_submit(SubmitRequest { text, is_agent: false, attachments: Vec::new(), record_history: true });
+                }
                 // Cancel in-flight remote connections on kill or interrupt
                 if let ShellMsg::KillBlock(id) | ShellMsg::SendInterrupt(id) = &m {
                     if let Some(cancel) = self.connecting_tasks.remove(id) {
@@ -281,6 +329,38 @@ impl NexusState {
                     cmds
                 }
             }
+            NexusMessage::Agent(super::message::AgentMsg::CopyPlan(block_id)) => {
+                if let Some(block) = self.agent.block_index.get(&block_id).and_then(|&idx| self.agent.blocks.get(idx)) {
+                    Self::set_clipboard_text(&block.plan_markdown());
+                }
+                Command::none()
+            }
+            NexusMessage::Agent(super::message::AgentMsg::CopySetupCommand(block_id)) => {
+                let block = self.agent.block_index.get(&block_id).and_then(|&idx| self.agent.blocks.get(idx));
+                if let Some(fix) = block.and_then(|b| b.setup.as_ref()).and_then(|setup| setup.status.fix()) {
+                    Self::set_clipboard_text(fix);
+                }
+                Command::none()
+            }
+            NexusMessage::Agent(super::message::AgentMsg::RerunQuery(block_id)) => {
+                let query = self.agent.block_index.get(&block_id).and_then(|&idx| self.agent.blocks.get(idx)).map(|b| b.query.clone());
+                if let Some(query) = query
+                    && !self.agent.is_active()
+                {
+                    self.spawn_agent_query(query, Vec::new());
+                }
+                Command::none()
+            }
+            NexusMessage::Agent(super::message::AgentMsg::FollowCitation(citation)) => {
+                self.follow_citation(citation)
+            }
+            NexusMessage::Agent(super::message::AgentMsg::EditInstructions) => {
+                self.edit_instructions()
+            }
+            NexusMessage::Agent(super::message::AgentMsg::Event(AgentEvent::RemoteFile { op, reply })) => {
+                self.serve_remote_file(op, reply);
+                Command::none()
+            }
             NexusMessage::Agent(m) => {
                 if matches!(m, super::message::AgentMsg::QuestionInputMouse(_)) {
                     self.set_focus(Focus::AgentInput);
@@ -303,13 +383,33 @@ impl NexusState {
                 Command::none()
             }
             NexusMessage::Viewer(m) => { self.dispatch_viewer_msg(m); Command::none() }
+            NexusMessage::Settings(m) => {
+                if let Some(edit) = self.settings.update(m) {
+                    self.save_setting(edit);
+                }
+                Command::none()
+            }
+            NexusMessage::Onboarding(m) => { self.onboard(m); Command::none() }
+            NexusMessage::Update(m) => self.handle_update(m),
+            NexusMessage::Crash(m) => self.handle_crash(m),
+            NexusMessage::Insights(m) => { self.handle_insights(m); Command::none() }
+            NexusMessage::Macro(m) => self.handle_macro(m, ctx),
             NexusMessage::FocusBlock(id) => {
                 self.set_focus(Focus::Block(id));
                 Command::none()
             }
             NexusMessage::ContextMenu(m) => self.dispatch_context_menu(m),
+            NexusMessage::Provider(update) => { self.context.contributions.apply(update); Command::none() }
+            NexusMessage::DismissWarning(id) => { self.context.contributions.dismiss_warning(&id); Command::none() }
+            NexusMessage::ToggleEnvironment(i) => { self.toggle_environment(i); Command::none() }
             NexusMessage::Scroll(action) => { self.scroll.apply_user_scroll(action); Command::none() }
             NexusMessage::ScrollToJob(_) => { self.scroll.snap_to_bottom(); Command::none() }
+            NexusMessage::CyclePowerMode => {
+                use nexus_kernel::power;
+                power::set_override(power::power_override().next());
+                self.poll_power();
+                Command::none()
+            }
             NexusMessage::UnnestToLevel(level) => {
                 if level == 0 {
                     // Disconnect — requires confirmation (double-click within 3s)
@@ -479,7 +579,9 @@ impl NexusState {
             NexusMessage::NewWindow | NexusMessage::QuitApp => Command::none(),
             NexusMessage::BlurAll => {
                 self.transient.dismiss_all(&mut self.input);
-                self.set_focus(Focus::Input);
+                // A full-window app keeps the keyboard until it exits.
+                let focus = self.shell.fullscreen_block().map_or(Focus::Input, |b| Focus::Block(b.id));
+                self.set_focus(focus);
                 Command::none()
             }
             NexusMessage::Tick => {
@@ -545,7 +647,7 @@ impl NexusState {
                 self.set_focus(Focus::Input);
                 self.scroll.snap_to_bottom();
                 if let Some(msg) = self.input.on_key(&event) {
-                    let submit = self.input.update(msg);
+                    let submit = self.input.update(msg, &self.context.contributions);
                     if let Some(req) = submit {
                         self.handle_submit(req)
                     } else {
@@ -557,7 +659,7 @@ impl NexusState {
             }
             NexusMessage::ZoomIn => { self.zoom_in(); Command::none() }
             NexusMessage::ZoomOut => { self.zoom_out(); Command::none() }
-            NexusMessage::ZoomReset => { self.zoom_level = 1.0; Command::none() }
+            NexusMessage::ZoomReset => { self.zoom_level = actions::font_zoom(&self.context.config).unwrap_or(1.0); Command::none() }
             #[cfg(debug_assertions)]
             NexusMessage::ToggleDebugLayout => {
                 self.debug_layout = !self.debug_layout;
@@ -572,14 +674,456 @@ impl NexusState {
 // =========================================================================
 
 impl NexusState {
+    /// Start an agent turn for `text`, with shell context on a new session.
+    fn spawn_agent_query(&mut self, text: String, attachments: Vec<nexus_api::Value>) {
+        self.kernel.blocking_lock().record_usage(&UsageEvent::AgentQuery);
+        let block_id = self.next_id();
+        let contextualized_query = if self.agent.session_id.is_some() {
+            format!("[CWD: {}]\n{}", self.cwd, text)
+        } else {
+            let shell_context = build_shell_context(
+                &self.cwd,
+                &self.shell.blocks.blocks,
+                self.input.shell_history(),
+            );
+            format!("{}{}", shell_context, text)
+        };
+        // Project instructions go with a new session's first query, and
+        // again whenever they change; re-read in case they were edited.
+        self.context.instructions = Instructions::find(std::path::Path::new(&self.cwd));
+        let instructions = self.context.instructions.clone();
+        let contextualized_query = match instructions.as_ref().and_then(Instructions::prompt_section) {
+            Some(section) if self.agent.session_id.is_none() || self.agent.instructions_sent != instructions => {
+                self.agent.instructions_sent = instructions;
+                format!("{}{}", section, contextualized_query)
+            }
+            _ => contextualized_query,
+        };
+        let contextualized_query = match self.agent.seed.take() {
+            Some(seed) => format!("{}{}", seed, contextualized_query),
+            None => contextualized_query,
+        };
+        let contextualized_query = match self.agent.context_files.take_removal_notice() {
+            Some(notice) => format!("{}{}", notice, contextualized_query),
+            None => contextualized_query,
+        };
+        let limits = AgentLimits::from_config(&self.context.config);
+        let remote = self.remote.as_ref().map(|remote| RemoteTarget {
+            host: format!("{}@{}", remote.env.user, remote.env.hostname),
+            cwd: self.cwd.clone(),
+        });
+        self.agent.spawn(block_id, text, contextualized_query, attachments, &self.cwd, limits, remote);
+        self.scroll.snap_to_bottom();
+    }
+
+    /// Run an agent file tool on the remote host and send its result to
+    /// `reply` once the transfer is done.
+    fn serve_remote_file(&mut self, op: RemoteFileOp, reply: tokio::sync::mpsc::UnboundedSender<Result<String, String>>) {
+        use crate::features::shell::remote::ConnectionState;
+
+        let Some(remote) = self.remote.as_mut().filter(|remote| remote.state == ConnectionState::Connected) else {
+            let _ = reply.send(Err("not connected to the remote host".to_string()));
+            return;
+        };
+        let path = remote_files::resolve(op.path(), &self.cwd, &remote.env);
+        // Transfers that never finish (the connection dropped) fail here
+        // rather than leaving the agent waiting.
+        const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+        match op {
+            RemoteFileOp::Read { offset, limit, .. } => {
+                let rx = remote.file_read(&path, Some(remote_files::MAX_READ_BYTES + 1));
+                tokio::spawn(async move {
+                    let result = match tokio::time::timeout(TIMEOUT, rx).await {
+                        Ok(Ok(data)) => remote_files::format_read(&data, offset, limit).map_err(|e| format!("{}: {}", path, e)),
+                        _ => Err(format!("{}: could not read the file", path)),
+                    };
+                    let _ = reply.send(result);
+                });
+            }
+            RemoteFileOp::Write { content, .. } => {
+                let rx = remote.file_write(&path, content.as_bytes());
+                tokio::spawn(async move {
+                    let result = match tokio::time::timeout(TIMEOUT, rx).await {
+                        Ok(Ok(written)) => Ok(format!("wrote {} bytes to {}", written, path)),
+                        _ => Err(format!("{}: could not write the file", path)),
+                    };
+                    let _ = reply.send(result);
+                });
+            }
+            RemoteFileOp::List { .. } => {
+                let Some(rx) = remote.list_dir(std::path::PathBuf::from(&path), nexus_api::BlockId(0)) else {
+                    let _ = reply.send(Err("not connected to the remote host".to_string()));
+                    return;
+                };
+                tokio::spawn(async move {
+                    let result = match tokio::time::timeout(TIMEOUT, rx).await {
+                        Ok(Ok(entries)) => Ok(remote_files::format_listing(&entries)),
+                        _ => Err(format!("{}: could not list the directory", path)),
+                    };
+                    let _ = reply.send(result);
+                });
+            }
+        }
+    }
+
+    /// Send the oldest query held while offline, once the network is back
+    /// and the agent is free. Returns true when one was sent.
+    pub(super) fn send_queued_agent_query(&mut self) -> bool {
+        if !self.online || self.agent.is_active() {
+            return false;
+        }
+        match self.agent.queued.pop_front() {
+            Some((text, attachments)) => {
+                self.spawn_agent_query(text, attachments);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move background tasks along: release those whose block has
+    /// finished and start what the `[agent] max_background` limit allows.
+    /// Returns whether any task changed, and the runs started.
+    pub(super) fn poll_agent_tasks(&mut self) -> (bool, Command<NexusMessage>) {
+        let shell = &self.shell;
+        let released = self.agent.tasks.release(|id| {
+            shell.block_by_id(id).map(|block| !matches!(block.state, nexus_api::BlockState::Running))
+        });
+        if !self.online {
+            return (released, Command::none());
+        }
+        let started = self.agent.tasks.start(self.context.config.agent_max_background());
+        if started.is_empty() {
+            return (released, Command::none());
+        }
+        let shell_context = build_shell_context(&self.cwd, &self.shell.blocks.blocks, self.input.shell_history());
+        let runs = started.into_iter().map(|(id, prompt, after, cancel)| {
+            let waited = after
+                .and_then(|block| self.shell.block_by_id(block))
+                .map(|block| format!("<finished_command>\n{}</finished_command>\n\n", describe_block(block)))
+                .unwrap_or_default();
+            let prompt = format!("{}{}{}", shell_context, waited, prompt);
+            let cwd = self.cwd.clone();
+            Command::perform(async move {
+                let result = tasks::run(prompt, cwd, cancel).await;
+                NexusMessage::Agent(super::message::AgentMsg::TaskFinished(id, result))
+            })
+        });
+        (true, Command::batch(runs.collect::<Vec<_>>()))
+    }
+
+    /// Record how scheduled runs ended, and every [`SCHEDULE_POLL_INTERVAL`]
+    /// start the scheduled commands that have come due, each as a block of
+    /// its own. Local windows only; the kernel claims each run, so another
+    /// window polling at the same time skips it.
+    pub(super) fn poll_schedules(&mut self) -> (bool, Command<NexusMessage>) {
+        let finished: Vec<(nexus_api::BlockId, i64)> = self
+            .scheduled_runs
+            .iter()
+            .filter(|(id, _)| {
+                self.shell.block_by_id(**id).is_none_or(|block| !matches!(block.state, nexus_api::BlockState::Running))
+            })
+            .map(|(id, run)| (*id, *run))
+            .collect();
+        for (block_id, run_id) in finished {
+            self.scheduled_runs.remove(&block_id);
+            let (exit_code, duration_ms) = match self.shell.block_by_id(block_id) {
+                Some(block) => match block.state {
+                    nexus_api::BlockState::Success => (Some(0), block.duration_ms),
+                    nexus_api::BlockState::Failed(code) => (Some(code), block.duration_ms),
+                    _ => (None, None),
+                },
+                // Cleared before it finished.
+                None => (None, None),
+            };
+            self.kernel.blocking_lock().finish_schedule_run(run_id, exit_code, duration_ms);
+        }
+
+        if self.remote.is_some() || self.schedules_polled_at.is_some_and(|t| t.elapsed() < SCHEDULE_POLL_INTERVAL) {
+            return (false, Command::none());
+        }
+        self.schedules_polled_at = Some(Instant::now());
+        let due = self.kernel.blocking_lock().take_due_schedules(self.context.config.schedule_catch_up());
+        if due.is_empty() {
+            return (false, Command::none());
+        }
+        // A scheduled run shouldn't take the keyboard from what's being typed.
+        let focus = self.focus;
+        let mut cmds = Vec::new();
+        for run in due {
+            let block_id = self.next_id();
+            self.scheduled_runs.insert(block_id, run.run_id);
+            let line = format!("in {} {}", file_drop::shell_quote(std::path::Path::new(&run.cwd)), run.command);
+            cmds.push(self.run_shell(line, block_id));
+        }
+        self.set_focus(focus);
+        sync_focus_flags(&self.focus, &mut self.input, &mut self.agent);
+        (true, Command::batch(cmds))
+    }
+
+    /// Every [`JOB_POLL_INTERVAL`] while the job bar shows jobs, have the
+    /// kernel reap them; changes arrive as `JobStateChanged` events. Skipped
+    /// while a command holds the kernel, which reaps on `jobs` and `wait`.
+    pub(super) fn poll_jobs(&mut self) {
+        if self.remote.is_some()
+            || self.shell.jobs.is_empty()
+            || self.jobs_polled_at.is_some_and(|t| t.elapsed() < JOB_POLL_INTERVAL)
+        {
+            return;
+        }
+        self.jobs_polled_at = Some(Instant::now());
+        if let Ok(mut kernel) = self.kernel.try_lock() {
+            kernel.reap_jobs();
+        }
+    }
+
+    /// Ask the agent for a ghost completion once typing pauses, when
+    /// `[agent] ghost_completions` is on and the line isn't cached.
+    pub(super) fn poll_ghost_completion(&mut self) -> Command<NexusMessage> {
+        let text = &self.input.text_input.text;
+        if !self.context.config.agent_ghost_completions()
+            || !self.online
+            || self.input.mode != crate::data::InputMode::Shell
+            || self.input.text_input.cursor != text.chars().count()
+        {
+            return Command::none();
+        }
+        let Some(generation) = self.input.ghost.due(text) else {
+            return Command::none();
+        };
+        let prefix = text.clone();
+        let shell_context = build_shell_context(&self.cwd, &self.shell.blocks.blocks, self.input.shell_history());
+        let cwd = self.cwd.clone();
+        Command::perform(async move {
+            let suffix = crate::features::input::ghost::suggest(prefix.clone(), shell_context, cwd).await;
+            NexusMessage::Input(super::message::InputMsg::GhostResult { prefix, suffix, generation })
+        })
+    }
+
+    /// Go to what a response cited: scroll to a block, or open a file in
+    /// `$VISUAL` / `$EDITOR` at the line (with `open` when neither is set).
+    fn follow_citation(&mut self, citation: Citation) -> Command<NexusMessage> {
+        match citation {
+            Citation::Block(id) => {
+                if self.shell.block_by_id(id).is_some() || self.agent.block_index.contains_key(&id) {
+                    self.scroll.scroll_to_block(id);
+                    self.set_focus(Focus::Block(id));
+                }
+                Command::none()
+            }
+            Citation::File { path, line } => {
+                let path = std::path::Path::new(&self.cwd).join(path);
+                self.open_in_editor(&path, line)
+            }
+        }
+    }
+
+    /// Open the project instructions in the editor; with none above the
+    /// cwd, start a NEXUS.md in it.
+    fn edit_instructions(&mut self) -> Command<NexusMessage> {
+        let path = match &self.context.instructions {
+            Some(instructions) => instructions.path.clone(),
+            None => {
+                let path = std::path::Path::new(&self.cwd).join("NEXUS.md");
+                if let Err(e) = std::fs::write(&path, nexus_kernel::instructions::TEMPLATE) {
+                    tracing::warn!("instructions: couldn't create {}: {}", path.display(), e);
+                    return Command::none();
+                }
+                self.context.instructions = Instructions::find(std::path::Path::new(&self.cwd));
+                path
+            }
+        };
+        self.open_in_editor(&path, None)
+    }
+
+    /// Copy, export or hand to the agent the shift-click selected blocks,
+    /// then drop the selection.
+    fn apply_bulk_action(&mut self, action: BulkAction) {
+        let order: Vec<_> = self.shell.blocks.blocks.iter().map(|b| b.id).collect();
+        let selected = self.shell.bulk.in_order(order);
+        let blocks: Vec<_> = selected.iter().filter_map(|id| self.shell.blocks.get(*id)).collect();
+        match action {
+            BulkAction::Copy => Self::set_clipboard_text(&bulk::to_text(&blocks)),
+            BulkAction::Export(format) => {
+                let path = notebook::export_path(format);
+                match std::fs::write(&path, notebook::export(&blocks, format)) {
+                    Ok(()) => {
+                        Self::reveal(&path);
+                    }
+                    Err(e) => tracing::warn!("export: failed to write {}: {}", path.display(), e),
+                }
+            }
+            BulkAction::SendToAgent => {
+                let seed = bulk::agent_seed(&blocks);
+                self.agent.seed = Some(self.agent.seed.take().unwrap_or_default() + &seed);
+                if self.input.mode == InputMode::Shell {
+                    self.input.toggle_mode();
+                }
+                self.set_focus(Focus::Input);
+            }
+            BulkAction::Group | BulkAction::Remove => return,
+        }
+        self.shell.bulk.clear();
+    }
+
+    /// Open the next of the problems a rerun added, cycling through them.
+    fn open_next_problem(&mut self, id: nexus_api::BlockId) -> Command<NexusMessage> {
+        let Some(block) = self.shell.block_by_id_mut(id) else {
+            return Command::none();
+        };
+        let Some(problem) = block.problems.as_ref().and_then(|delta| {
+            delta.new.get(block.problem_cursor % delta.new.len().max(1)).cloned()
+        }) else {
+            return Command::none();
+        };
+        block.problem_cursor += 1;
+        let path = std::path::Path::new(&self.cwd).join(&problem.file);
+        self.open_in_editor(&path, Some(problem.line))
+    }
+
+    /// Close the undo window of a hidden block whose time is up, deleting
+    /// stored copies of blocks hidden with "Delete from History".
+    pub(super) fn expire_hidden_block(&mut self) -> bool {
+        let expired = self.shell.expire_hidden();
+        if !self.shell.forgotten.is_empty() {
+            let kernel = self.kernel.blocking_lock();
+            for id in std::mem::take(&mut self.shell.forgotten) {
+                kernel.forget_block(id);
+            }
+        }
+        expired
+    }
+
+    /// Scan the output of blocks that finished since the last tick: title
+    /// them, save what their process used, show how compiler problems
+    /// changed since the command last ran here, and test runs as a tree of
+    /// results.
+    pub(super) fn scan_finished(&mut self) -> bool {
+        if self.shell.finished.is_empty() {
+            return false;
+        }
+        let mut changed = false;
+        for id in std::mem::take(&mut self.shell.finished) {
+            let Some(block) = self.shell.block_by_id(id) else {
+                continue;
+            };
+            let grid = block.parser.grid_with_scrollback();
+            let output = grid.to_string();
+            let command = block.command.clone();
+            let usage = block.resource_usage;
+            let duration_ms = block.duration_ms;
+            // Viewers (less, top, man) are live sessions, not output to keep.
+            let value = block
+                .structured_output
+                .clone()
+                .filter(|v| !matches!(v.as_domain(), Some(nexus_api::DomainValue::Interactive(_))));
+            let exit_code = match block.state {
+                nexus_api::BlockState::Failed(code) => code,
+                _ => 0,
+            };
+            let title = titles::block_title(&command, &Outcome::parse(&command, &output, exit_code));
+            let problems = nexus_kernel::problems::parse(&output);
+            let delta = {
+                let kernel = self.kernel.blocking_lock();
+                kernel.record_block(id, &command, exit_code, duration_ms);
+                match &value {
+                    Some(value) => kernel.save_block_output(id, value),
+                    None if !output.is_empty() => kernel.save_block_snapshot(id, &grid),
+                    None => {}
+                }
+                kernel.record_block_title(id, &title, exit_code);
+                if let Some(usage) = &usage {
+                    kernel.record_block_usage(id, usage);
+                }
+                kernel.record_problems(&command, &self.cwd, &problems)
+            };
+            let tests = Runner::detect(&command)
+                .and_then(|runner| TestReport::parse(runner, &output))
+                .map(|report| TestTree::new(report, &command));
+            let delta = delta.filter(|delta| !delta.is_empty());
+            if let Some(block) = self.shell.block_by_id_mut(id) {
+                block.title = Some(title);
+                if delta.is_some() || tests.is_some() {
+                    block.problems = delta;
+                    block.tests = tests;
+                }
+                block.version += 1;
+                changed = true;
+            }
+        }
+        // `activate`, `deactivate` and `cd` change the prompt's environments.
+        if self.remote.is_none() {
+            let environments = dev_env::status(self.kernel.blocking_lock().state());
+            changed |= environments != self.context.environments;
+            self.context.environments = environments;
+        }
+        changed
+    }
+
+    /// Activate the project environment shown at `index` in the prompt, or
+    /// deactivate it when active.
+    fn toggle_environment(&mut self, index: usize) {
+        let Some((env, active)) = self.context.environments.get(index).cloned() else {
+            return;
+        };
+        let mut kernel = self.kernel.blocking_lock();
+        if active {
+            dev_env::deactivate(kernel.state_mut(), env.kind);
+        } else {
+            dev_env::activate(kernel.state_mut(), env);
+        }
+        self.context.environments = dev_env::status(kernel.state());
+    }
+
+    /// Open `path` in `$VISUAL` / `$EDITOR` at `line`, or with its opener when
+    /// neither is set.
+    fn open_in_editor(&mut self, path: &std::path::Path, line: Option<u32>) -> Command<NexusMessage> {
+        let editor = {
+            let kernel = self.kernel.blocking_lock();
+            ["VISUAL", "EDITOR"]
+                .iter()
+                .find_map(|name| kernel.state().get_var_value(name))
+                .map(|value| value.to_text())
+                .filter(|editor| !editor.trim().is_empty())
+        };
+        let Some(editor) = editor else {
+            self.open_path(path);
+            return Command::none();
+        };
+        let line = line.map(|line| format!(" +{}", line)).unwrap_or_default();
+        self.handle_submit(SubmitRequest {
+            text: format!("{}{} {}", editor, line, file_drop::shell_quote(path)),
+            is_agent: false,
+            attachments: Vec::new(),
+            record_history: true,
+        })
+    }
+
     fn handle_submit(&mut self, req: SubmitRequest) -> Command<NexusMessage> {
-        let SubmitRequest { text, is_agent, attachments } = req;
+        let SubmitRequest { text, is_agent, attachments, record_history } = req;
+        // Output goes where the settings, insights and onboarding views are drawn.
+        self.settings.update(super::message::SettingsMsg::Close);
+        self.insights.close();
+        if self.onboarding.open {
+            self.onboarding.finish();
+        }
+
+        // Slash commands are app actions, wherever the session runs.
+        if !is_agent && let Some(Ok(action)) = slash::parse(&text) {
+            self.input.reset_history_nav();
+            return self.run_slash_command(action);
+        }
 
         // Short-circuit built-in "clear" before any side effects.
         if !is_agent && text.trim() == "clear" {
             return Command::message(NexusMessage::ClearScreen);
         }
 
+        // `instructions edit` needs the editor, which only the UI can run.
+        if !is_agent && text.trim() == "instructions edit" && self.remote.is_none() {
+            return self.edit_instructions();
+        }
+
         // Handle "exit" when in remote mode — pop the backend stack.
         if !is_agent && text.trim() == "exit" && self.remote.is_some() {
             let remote = self.remote.as_mut().unwrap();
@@ -603,89 +1147,108 @@ impl NexusState {
             return Command::none();
         }
 
+        // While a `repl` block runs, the input types its cells.
+        if !is_agent && self.remote.is_none() {
+            if let Some((block_id, _)) = self.shell.attached_repl() {
+                nexus_kernel::repl::send(block_id, nexus_kernel::repl::ReplInput::Cell(text));
+                self.input.reset_history_nav();
+                self.scroll.snap_to_bottom();
+                return Command::none();
+            }
+        }
+
         // Append to native shell history (before execution, for crash safety).
         // Records both kernel and PTY commands.
-        if !is_agent {
+        if !is_agent && record_history {
             self.kernel.blocking_lock().append_history(&text);
         }
 
         self.input.reset_history_nav();
 
         if is_agent {
-            let block_id = self.next_id();
-            let contextualized_query = if self.agent.session_id.is_some() {
-                format!("[CWD: {}]\n{}", self.cwd, text)
+            if let Some((after, prompt)) = tasks::parse(&text) {
+                // Runs beside the conversation, started from on_tick.
+                self.agent.tasks.push(prompt, after);
+            } else if !self.online && self.context.config.queues_agent_offline() {
+                // Sent from on_tick once the network is back.
+                self.agent.queued.push_back((text, attachments));
             } else {
-                let shell_context = build_shell_context(
-                    &self.cwd,
-                    &self.shell.blocks.blocks,
-                    self.input.shell_history(),
-                );
-                format!("{}{}", shell_context, text)
-            };
-            self.agent.spawn(block_id, text, contextualized_query, attachments, &self.cwd);
-            self.scroll.snap_to_bottom();
+                self.spawn_agent_query(text, attachments);
+            }
         } else {
             let block_id = self.next_id();
-            let kernel = self.kernel.clone();
-            let kernel_tx = self.kernel_tx.clone();
-            let cwd = self.cwd.clone();
-            // Manual borrow splitting: shell_ctx() borrows scroll/focus/cwd/context,
-            // but we also need &mut remote which is a separate field.
-            let uctx = UpdateContext::new(
-                &mut self.scroll,
-                &mut self.focus,
-                &mut self.cwd,
-                &mut self.context,
-            );
-            let shell = &mut self.shell;
-            let remote = &mut self.remote;
-            let mut uctx = uctx;
-            let remote_transport = shell.execute(
-                text,
-                block_id,
-                &cwd,
-                &kernel,
-                &kernel_tx,
-                remote.as_mut(),
-                &mut uctx,
-            );
-            let cmds = uctx.into_commands();
-            sync_focus_flags(&self.focus, &mut self.input, &mut self.agent);
+            return self.run_shell(text, block_id);
+        }
 
-            if let Some(ssh_command) = remote_transport {
-                // Already connected — nest via the existing connection
-                if let Some(ref mut remote) = self.remote {
-                    if let Some(transport) = parse_remote_command(&ssh_command) {
-                        remote.nest(transport, block_id);
-                    }
-                    return cmds;
-                }
+        Command::none()
+    }
 
-                // Not connected — first connection, spawn async task
-                let kernel_tx = self.kernel_tx.clone();
-                let cancel = tokio_util::sync::CancellationToken::new();
-                self.connecting_tasks.insert(block_id, cancel.clone());
-                let connect_cmd = Command::perform(async move {
-                    match Self::connect_remote(ssh_command, kernel_tx, block_id, cancel).await {
-                        Ok((remote, env)) => NexusMessage::Shell(ShellMsg::RemoteConnected {
-                            block_id,
-                            remote: std::sync::Arc::new(std::sync::Mutex::new(Some(remote))),
-                            env: Box::new(env),
-                        }),
-                        Err(e) => NexusMessage::Shell(ShellMsg::RemoteConnectFailed {
-                            block_id,
-                            error: e.to_string(),
-                        }),
-                    }
-                });
-                return Command::batch(vec![cmds, connect_cmd]);
+    /// Run a shell command line as block `block_id`: through the remote
+    /// backend when connected, else in the kernel or a PTY.
+    fn run_shell(&mut self, text: String, block_id: nexus_api::BlockId) -> Command<NexusMessage> {
+        let kernel = self.kernel.clone();
+        let cwd = self.cwd.clone();
+        // Manual borrow splitting: shell_ctx() borrows scroll/focus/cwd/context,
+        // but we also need &mut remote which is a separate field.
+        let uctx = UpdateContext::new(
+            &mut self.scroll,
+            &mut self.focus,
+            &mut self.cwd,
+            &mut self.context,
+        );
+        let shell = &mut self.shell;
+        let remote = &mut self.remote;
+        let mut uctx = uctx;
+        let remote_transport = shell.execute(
+            text,
+            block_id,
+            &cwd,
+            &kernel,
+            remote.as_mut(),
+            &mut uctx,
+        );
+        let cmds = uctx.into_commands();
+        sync_focus_flags(&self.focus, &mut self.input, &mut self.agent);
+
+        // Tag the block with its worktree, so the list can be narrowed to
+        // one branch's commands.
+        if self.remote.is_none() {
+            let worktree = self.context.contributions.current_worktree().map(str::to_string);
+            if let Some(block) = self.shell.blocks.get_mut(block_id) {
+                block.worktree = worktree;
             }
+        }
 
-            return cmds;
+        if let Some(ssh_command) = remote_transport {
+            // Already connected — nest via the existing connection
+            if let Some(ref mut remote) = self.remote {
+                if let Some(transport) = parse_remote_command(&ssh_command) {
+                    remote.nest(transport, block_id);
+                }
+                return cmds;
+            }
+
+            // Not connected — first connection, spawn async task
+            let kernel_tx = self.kernel_tx.clone();
+            let cancel = tokio_util::sync::CancellationToken::new();
+            self.connecting_tasks.insert(block_id, cancel.clone());
+            let connect_cmd = Command::perform(async move {
+                match Self::connect_remote(ssh_command, kernel_tx, block_id, cancel).await {
+                    Ok((remote, env)) => NexusMessage::Shell(ShellMsg::RemoteConnected {
+                        block_id,
+                        remote: std::sync::Arc::new(std::sync::Mutex::new(Some(remote))),
+                        env: Box::new(env),
+                    }),
+                    Err(e) => NexusMessage::Shell(ShellMsg::RemoteConnectFailed {
+                        block_id,
+                        error: e.to_string(),
+                    }),
+                }
+            });
+            return Command::batch(vec![cmds, connect_cmd]);
         }
 
-        Command::none()
+        cmds
     }
 
     /// Handle a NexusSSH OSC from a PTY process: kill the PTY and initiate
@@ -1127,6 +1690,14 @@ impl NexusState {
             if source_id == source_ids::agent_thinking(block.id) {
                 return Some(text_snap(block.thinking.clone()));
             }
+            if source_id == source_ids::agent_plan(block.id) {
+                let lines = block
+                    .plan
+                    .iter()
+                    .flat_map(|step| [step.status.checkbox().to_string(), step.content.clone()])
+                    .collect();
+                return Some(snap::SnapContent::Text { lines });
+            }
             if source_id == source_ids::agent_query(block.id) {
                 return Some(snap::SnapContent::Text {
                     lines: vec!["?".to_string(), block.query.clone()],
@@ -1136,6 +1707,13 @@ impl NexusState {
                 if source_id == source_ids::agent_tool(block.id, i) {
                     return Some(text_snap(tool.extract_text()));
                 }
+                if let Some(terminal) = &tool.terminal
+                    && source_id == source_ids::agent_tool_term(block.id, i)
+                {
+                    let grid = terminal.grid_with_scrollback();
+                    let chars = grid.cells().iter().map(|c| c.c).collect();
+                    return Some(snap::SnapContent::Grid { chars, cols: grid.cols() as usize });
+                }
             }
             if let Some(ref perm) = block.pending_permission {
                 if source_id == source_ids::agent_perm_text(block.id) {
@@ -1189,6 +1767,20 @@ impl NexusState {
                     self.insert_text_at_cursor(&text);
                     return Command::none();
                 }
+                // A shared block opens as a block wherever it is dropped.
+                if bundle::is_bundle(&path) {
+                    return Command::perform(async move {
+                        let loaded = match tokio::fs::metadata(&path).await {
+                            Ok(meta) if meta.len() > bundle::MAX_BYTES => Err("File exceeds 32 MB limit".to_string()),
+                            Ok(_) => match tokio::fs::read_to_string(&path).await {
+                                Ok(text) => Bundle::parse(&text).map(Box::new),
+                                Err(e) => Err(e.to_string()),
+                            },
+                            Err(e) => Err(e.to_string()),
+                        };
+                        NexusMessage::FileDrop(FileDropMsg::BundleLoaded(path, loaded))
+                    });
+                }
                 match zone {
                     DropZone::InputBar | DropZone::Empty => {
                         let quoted = file_drop::shell_quote(&path);
@@ -1248,6 +1840,16 @@ impl NexusState {
                 tracing::warn!("File drop failed for {}: {}", path.display(), reason);
                 Command::none()
             }
+            FileDropMsg::BundleLoaded(_path, Ok(bundle)) => {
+                let (shell, mut uctx) = self.shell_ctx();
+                shell.open_bundle(*bundle, &mut uctx);
+                sync_focus_flags(&self.focus, &mut self.input, &mut self.agent);
+                Command::none()
+            }
+            FileDropMsg::BundleLoaded(path, Err(reason)) => {
+                tracing::warn!("Could not open shared block {}: {}", path.display(), reason);
+                Command::none()
+            }
         }
     }
 
@@ -1270,23 +1872,12 @@ impl NexusState {
                     tracing::warn!("Quick Look failed: {}", e);
                 }
             }
-            AnchorAction::RevealPath(path) => {
-                // Reveal in Finder (macOS) — `open -R <path>`
-                let _ = std::process::Command::new("open")
-                    .arg("-R")
-                    .arg(path)
-                    .spawn();
-            }
-            AnchorAction::Open(path) => {
-                // Open with default application (macOS) — `open <path>`
-                let _ = std::process::Command::new("open")
-                    .arg(path)
-                    .spawn();
-            }
+            AnchorAction::RevealPath(path) => Self::reveal(path),
+            AnchorAction::Open(path) => self.open_path(path),
             AnchorAction::OpenUrl(url) => {
-                let _ = std::process::Command::new("open")
-                    .arg(url)
-                    .spawn();
+                if let Err(e) = opener::open(url, None) {
+                    tracing::warn!("open: failed to open {}: {}", url, e);
+                }
             }
             AnchorAction::CopyToClipboard(text) => {
                 Self::set_clipboard_text(text);
@@ -1294,6 +1885,21 @@ impl NexusState {
         }
     }
 
+    /// Open a clicked path with its `[open]` application or the default.
+    fn open_path(&self, path: &std::path::Path) {
+        let app = self.context.config.opener_for(path);
+        if let Err(e) = opener::open(&path.to_string_lossy(), app) {
+            tracing::warn!("open: failed to open {}: {}", path.display(), e);
+        }
+    }
+
+    /// Show a path selected in the file manager.
+    fn reveal(path: &std::path::Path) {
+        if let Err(e) = opener::reveal(path) {
+            tracing::warn!("open: failed to reveal {}: {}", path.display(), e);
+        }
+    }
+
     fn copy_selection_or_input(&mut self) {
         // Try content selection first
         if let Some(text) =
@@ -1329,7 +1935,7 @@ impl NexusState {
                     strata::platform::NativeMenuItem {
                         label: item.label().to_string(),
                         shortcut: String::new(),
-                        separator: false,
+                        separator: *item == ContextMenuItem::Separator,
                     }
                 }).collect();
 
@@ -1352,6 +1958,50 @@ impl NexusState {
         }
     }
 
+    fn run_slash_command(&mut self, action: SlashAction) -> Command<NexusMessage> {
+        match action {
+            SlashAction::Clear => Command::message(NexusMessage::ClearScreen),
+            SlashAction::Theme(color) => {
+                let key = "theme.accent".to_string();
+                let msg = match color {
+                    Some(hex) => super::message::SettingsMsg::Set(key, crate::features::settings::SettingValue::Text(hex)),
+                    None => super::message::SettingsMsg::Unset(key),
+                };
+                Command::message(NexusMessage::Settings(msg))
+            }
+            SlashAction::ExportSession(format) => {
+                let blocks = self.shell.blocks.blocks.iter().map(|block| block.id).collect();
+                self.exec_context_menu_item(ContextMenuItem::ExportBlocks { format, blocks }, None)
+            }
+            SlashAction::NewConversation => {
+                self.agent.new_conversation();
+                Command::none()
+            }
+            SlashAction::ExportConversation(format) => {
+                self.exec_context_menu_item(ContextMenuItem::ExportConversation { format, thinking: false }, None)
+            }
+            SlashAction::Note { block, line, text } => {
+                self.set_note(block, line, &text);
+                Command::none()
+            }
+        }
+    }
+
+    /// Put a note on a finished block's output line and store it with the
+    /// block: in this session's copy, or the earlier session's for a
+    /// restored block.
+    fn set_note(&mut self, id: nexus_api::BlockId, line: usize, text: &str) {
+        let Some(block) = self.shell.block_by_id_mut(id).filter(|b| !b.is_running()) else {
+            return;
+        };
+        block.set_note(line, text);
+        let kernel = self.kernel.blocking_lock();
+        match &block.restored {
+            Some(restored) => kernel.save_restored_notes(restored.row_id, &block.notes),
+            None => kernel.record_block_notes(id, &block.notes),
+        }
+    }
+
     fn exec_context_menu_item(&mut self, item: ContextMenuItem, target: Option<ContextTarget>) -> Command<NexusMessage> {
         match item {
             ContextMenuItem::Copy => {
@@ -1407,11 +2057,7 @@ impl NexusState {
                         .select_all(&self.shell.blocks.blocks, &self.agent.blocks);
                 }
             },
-            ContextMenuItem::Clear => {
-                self.input.text_input.text.clear();
-                self.input.text_input.cursor = 0;
-                self.input.text_input.selection = None;
-            }
+            ContextMenuItem::Clear => self.clear_input(),
             ContextMenuItem::CopyCommand => {
                 if let Some(block) = self.target_shell_block(&target) {
                     Self::set_clipboard_text(&block.command);
@@ -1443,28 +2089,113 @@ impl NexusState {
                         text: cmd,
                         is_agent: false,
                         attachments: Vec::new(),
+                        record_history: true,
                     });
                 }
             }
+            ContextMenuItem::ShareBlock => {
+                if let Some(block) = self.target_shell_block(&target) {
+                    let bundle = Bundle::from_block(block, self.context.accent());
+                    let path = bundle::export_path(block);
+                    let written = std::fs::write(&path, bundle.to_json())
+                        .and_then(|()| std::fs::write(path.with_extension("html"), bundle.to_html()));
+                    match written {
+                        Ok(()) => {
+                            Self::reveal(&path);
+                        }
+                        Err(e) => tracing::warn!("share: failed to write {}: {}", path.display(), e),
+                    }
+                }
+            }
+            ContextMenuItem::HideBlock { forget } => {
+                if let Some(id) = self.target_shell_block_id(&target) {
+                    let (shell, mut uctx) = self.shell_ctx();
+                    shell.hide_block(id, forget, &mut uctx);
+                    sync_focus_flags(&self.focus, &mut self.input, &mut self.agent);
+                }
+            }
+            ContextMenuItem::PipeInto => {
+                let Some(id) = self.target_shell_block_id(&target) else {
+                    return Command::none();
+                };
+                // `_N` counts back from the latest output, so resolve it now.
+                let Some(index) = self.kernel.blocking_lock().state().output_index(id) else {
+                    tracing::warn!("Pipe into: output of block {:?} is no longer stored", id);
+                    return Command::none();
+                };
+                self.input.prefill_shell(&format!("_{} | ", index));
+                self.set_focus(Focus::Input);
+                self.scroll.snap_to_bottom();
+            }
+            ContextMenuItem::OpenWorktree { path, .. } => {
+                *self.next_window_cwd.lock().unwrap_or_else(|e| e.into_inner()) = Some(path);
+                return Command::message(NexusMessage::NewWindow);
+            }
+            ContextMenuItem::FilterWorktree(worktree) => {
+                self.shell.worktree_filter = worktree;
+                self.scroll.snap_to_bottom();
+            }
+            ContextMenuItem::RunAction { label, command } => {
+                self.kernel.blocking_lock().record_usage(&UsageEvent::PaletteAction(label));
+                return self.handle_submit(SubmitRequest {
+                    text: command,
+                    is_agent: false,
+                    attachments: Vec::new(),
+                    record_history: true,
+                });
+            }
+            // Replay needs the update context, so go round the loop again.
+            ContextMenuItem::RecordMacro | ContextMenuItem::StopMacroRecording => {
+                return Command::message(NexusMessage::Macro(MacroMsg::ToggleRecording));
+            }
+            ContextMenuItem::InsertMacroPause => self.macros.insert_pause(),
+            ContextMenuItem::RunMacro { name, .. } => {
+                return Command::message(NexusMessage::Macro(MacroMsg::Run(Some(name))));
+            }
+            ContextMenuItem::Settings => {
+                self.settings.update(super::message::SettingsMsg::Open);
+            }
+            ContextMenuItem::Insights => self.handle_insights(super::message::InsightsMsg::Open),
+            ContextMenuItem::Separator => {}
+            ContextMenuItem::ExportConversation { format, thinking } => {
+                let turns = self.agent.transcript(thinking);
+                let text = match format {
+                    TranscriptFormat::Markdown => transcript::to_markdown(&turns),
+                    TranscriptFormat::Jsonl => transcript::to_jsonl(&turns),
+                };
+                let path = transcript::export_path(format);
+                match std::fs::write(&path, text) {
+                    Ok(()) => {
+                        Self::reveal(&path);
+                    }
+                    Err(e) => tracing::warn!("export: failed to write {}: {}", path.display(), e),
+                }
+            }
+            ContextMenuItem::ExportBlocks { format, blocks } => {
+                let blocks: Vec<_> = blocks.iter().filter_map(|id| self.shell.blocks.get(*id)).collect();
+                let path = notebook::export_path(format);
+                match std::fs::write(&path, notebook::export(&blocks, format)) {
+                    Ok(()) => {
+                        Self::reveal(&path);
+                    }
+                    Err(e) => tracing::warn!("export: failed to write {}: {}", path.display(), e),
+                }
+            }
+            ContextMenuItem::ImportTranscript => {
+                return Command::perform(async {
+                    NexusMessage::Agent(super::message::AgentMsg::ImportTranscript(transcript::choose_transcript().await))
+                });
+            }
             ContextMenuItem::QuickLook(path) => {
                 if let Err(e) = strata::platform::preview_file(&path) {
                     tracing::warn!("Quick Look failed: {}", e);
                 }
             }
-            ContextMenuItem::Open(path) => {
-                let _ = std::process::Command::new("open")
-                    .arg(&path)
-                    .spawn();
-            }
+            ContextMenuItem::Open(path) => self.open_path(&path),
             ContextMenuItem::CopyPath(path) => {
                 Self::set_clipboard_text(&path.display().to_string());
             }
-            ContextMenuItem::RevealInFinder(path) => {
-                let _ = std::process::Command::new("open")
-                    .arg("-R")
-                    .arg(&path)
-                    .spawn();
-            }
+            ContextMenuItem::RevealInFinder(path) => Self::reveal(&path),
             ContextMenuItem::CopyCellValue(text) => {
                 Self::set_clipboard_text(&text);
             }
@@ -1738,6 +2469,7 @@ fn convert_completion_kind(kind: nexus_protocol::messages::CompletionKind) -> ne
         nexus_protocol::messages::CompletionKind::Variable => nexus_kernel::CompletionKind::Variable,
         nexus_protocol::messages::CompletionKind::GitBranch => nexus_kernel::CompletionKind::GitBranch,
         nexus_protocol::messages::CompletionKind::Flag => nexus_kernel::CompletionKind::Flag,
+        nexus_protocol::messages::CompletionKind::Task => nexus_kernel::CompletionKind::Task,
     }
 }
 
@@ -69,8 +69,8 @@ impl<'a> UpdateContext<'a> {
     }
 
     /// Notify context that a command finished (for error parsing).
-    pub fn on_command_finished(&mut self, command: String, output: String, exit_code: i32) {
-        self.context.on_command_finished(command, output, exit_code);
+    pub fn on_command_finished(&mut self, block_id: nexus_api::BlockId, command: String, output: String, exit_code: i32) {
+        self.context.on_command_finished(block_id, command, output, exit_code);
     }
 
     /// Enqueue an async command (e.g. LoadTreeChildren).
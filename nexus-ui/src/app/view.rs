@@ -3,20 +3,89 @@
 use strata::{Column, LayoutSnapshot, ScrollColumn};
 
 use super::NexusState;
-use crate::ui::widgets::WelcomeScreen;
+use crate::data::keymap;
+use crate::ui::widgets::{AgentTaskPanel, BlockFocusHint, BlockGroupHeader, BlockSelectionBar, ContextFileChips, CrashPromptPanel, HiddenBlockToast, InsightsPanel, MacroBar, OfflineBanner, OnboardingPanel, ProviderWarnings, ReleaseNotesPanel, SettingsPanel, WelcomeScreen};
 
 impl NexusState {
     pub(super) fn layout_blocks<'a>(&'a self, mut scroll: ScrollColumn<'a>) -> ScrollColumn<'a> {
-        if !self.has_blocks() {
-            scroll = scroll.push(WelcomeScreen { cwd: &self.cwd });
+        if self.crash.open {
+            scroll = scroll.push(CrashPromptPanel {
+                crashes: &self.crash.crashes,
+                report: &self.crash.report,
+                reviewing: self.crash.reviewing,
+                can_submit: self.crash.upload_url.is_some(),
+                submitting: self.crash.submitting,
+                error: self.crash.error.as_deref(),
+            });
+        } else if self.onboarding.open {
+            let onboarding = &self.onboarding;
+            scroll = scroll.push(OnboardingPanel {
+                shell: onboarding.shell_name(),
+                files: onboarding.found.files.iter().map(|p| p.display().to_string()).collect(),
+                diff: onboarding.diff.as_ref(),
+                skipped: &onboarding.found.skipped,
+                history: onboarding.history.len(),
+                history_shared: onboarding.history_shared,
+                settings_imported: onboarding.settings_imported,
+                history_imported: onboarding.history_imported,
+                error: onboarding.error.as_deref(),
+            });
+        } else if self.settings.open {
+            scroll = scroll.push(SettingsPanel {
+                rows: self.settings.rows(&self.context.config),
+                file: nexus_kernel::config::user_config_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default(),
+                error: self.settings.error.as_deref(),
+            });
+        } else if self.insights.open {
+            scroll = scroll.push(InsightsPanel {
+                insights: &self.insights.insights,
+                cleared: self.insights.cleared,
+                error: self.insights.error.as_deref(),
+            });
+        } else if let Some(release) = self.update.release.as_ref().filter(|_| self.update.notes_open) {
+            scroll = scroll.push(ReleaseNotesPanel {
+                release,
+                current: nexus_kernel::update::CURRENT_VERSION,
+                download: &self.update.download,
+                can_stage: self.update.can_stage(),
+            });
+        } else if let Some(block) = self.shell.fullscreen_block() {
+            // A full-window app (vim, htop, ...) gets the whole scroll area
+            // to itself until it exits.
+            let annotations = self.context.contributions.annotations(block.id);
+            scroll = self.shell.push_block(scroll, block, &self.focus, false, annotations, self.context.accent());
+        } else if !self.has_blocks() {
+            scroll = scroll.push(WelcomeScreen { cwd: &self.cwd, previous: self.previous_session.as_ref() });
         } else {
             // Use shared ordered block list (same order as navigation helpers)
             for id in self.all_block_ids_ordered() {
                 if let Some(block) = self.shell.block_by_id(id) {
+                    // A group's header goes above its first block; collapsed,
+                    // it stands in for all of them.
+                    if let Some(group) = self.shell.bulk.group_of(id) {
+                        if group.first() == id {
+                            scroll = scroll.push(BlockGroupHeader {
+                                first: id,
+                                titles: group
+                                    .blocks
+                                    .iter()
+                                    .filter_map(|&b| self.shell.block_by_id(b))
+                                    .map(|b| b.title.as_deref().unwrap_or(&b.command))
+                                    .collect(),
+                                collapsed: group.collapsed,
+                            });
+                        }
+                        if group.collapsed {
+                            continue;
+                        }
+                    }
                     let dimmed = self.remote.as_ref().map_or(false, |r| {
                         r.state != crate::features::shell::remote::ConnectionState::Connected
                     });
-                    scroll = self.shell.push_block(scroll, block, &self.focus, dimmed);
+                    let annotations = self.context.contributions.annotations(block.id);
+                    scroll = self.shell.push_block(scroll, block, &self.focus, dimmed, annotations, self.context.accent());
                 } else if let Some(&idx) = self.agent.block_index.get(&id) {
                     if let Some(block) = self.agent.blocks.get(idx) {
                         scroll = self.agent.push_block(scroll, block);
@@ -51,15 +120,92 @@ impl NexusState {
             });
         }
 
-        // Job bar (shell-owned data, placed in overlay area)
-        if let Some(job_bar) = self.shell.view_job_bar() {
+        // Secure sudo password prompt (shell-owned, sits just above input)
+        if let Some(sudo_prompt) = self.shell.view_sudo_prompt() {
+            col = col.push(sudo_prompt);
+        }
+
+        // A full-window app owns the keyboard; no input bar to type into.
+        if self.shell.fullscreen_block().is_some() {
+            return col;
+        }
+
+        // Job bar (shell-owned data + offline, low-power and update pills, placed in overlay area)
+        if let Some(job_bar) = self.shell.view_job_bar(!self.online, self.power_indicator(), self.update.indicator()) {
             col = col.push(job_bar);
         }
 
+        // Agent mode without a network: say so, and what happens to queries.
+        if !self.online && (self.input.mode == crate::data::InputMode::Agent || !self.agent.queued.is_empty()) {
+            col = col.push(OfflineBanner {
+                queueing: self.context.config.queues_agent_offline(),
+                queued: self.agent.queued.len(),
+            });
+        }
+
+        // Background agent tasks, in either mode: they don't need the input.
+        if !self.agent.tasks.tasks.is_empty() {
+            col = col.push(AgentTaskPanel { queue: &self.agent.tasks });
+        }
+
+        // A block just hidden, while it can still be brought back.
+        if let Some(hidden) = &self.shell.hidden {
+            col = col.push(HiddenBlockToast { command: &hidden.block.command, forgotten: hidden.forget });
+        }
+
+        // A macro being recorded or named, or a replay waiting at a pause.
+        if self.macros.is_active() {
+            col = col.push(MacroBar {
+                recording: self.macros.recording.as_ref().map(Vec::len),
+                naming: self.macros.naming.is_some(),
+                paused: self.macros.paused.as_ref().map(|p| p.name.as_str()),
+                error: self.macros.error.as_deref(),
+                accent: self.context.accent(),
+            });
+        }
+
+        // Shift-click selected blocks and what can be done with them.
+        if !self.shell.bulk.selected.is_empty() {
+            col = col.push(BlockSelectionBar { count: self.shell.bulk.selected.len(), accent: self.context.accent() });
+        }
+
+        // Project instructions and the files the agent has read or
+        // changed, while talking to it.
+        let instructions = self.context.instructions.as_ref();
+        if self.input.mode == crate::data::InputMode::Agent
+            && (instructions.is_some() || !self.agent.context_files.files.is_empty())
+        {
+            col = col.push(ContextFileChips {
+                files: &self.agent.context_files.files,
+                diff: self.agent.context_diff.as_ref().map(|(i, diff)| (*i, diff.as_str())),
+                instructions,
+            });
+        }
+
+        // Keys go to a terminal block: say which, and how to get them back.
+        if let crate::data::Focus::Block(id) = self.focus {
+            if let Some(block) = self.shell.block_by_id(id).filter(|_| self.block_has_active_pty(id)) {
+                let program = block.command.split_whitespace().next().unwrap_or(&block.command);
+                col = col.push(BlockFocusHint {
+                    program: program.rsplit('/').next().unwrap_or(program),
+                    release: keymap::release_chords(&self.context.config).iter().map(keymap::Chord::label).collect(),
+                    accent: self.context.accent(),
+                });
+            }
+        }
+
+        // What providers found wrong with the directory just entered.
+        let warnings: Vec<&str> = self.context.warnings().map(|(_, text)| text).collect();
+        if !warnings.is_empty() {
+            col = col.push(ProviderWarnings { warnings });
+        }
+
         // Input-owned sections: completion popup, history search, attachments, input bar
         col = self.input.layout_overlays(col);
         col = self.input.layout_attachments(col);
-        col = self.input.layout_input_bar(col, &self.cwd, self.shell.last_exit_code, cursor_visible);
+        // Only local repls take input; see handle_submit.
+        let repl = self.shell.attached_repl().filter(|_| self.remote.is_none()).map(|(_, language)| language);
+        col = self.input.layout_input_bar(col, &self.cwd, &self.context, self.shell.last_exit_code, repl, self.remote.is_some(), cursor_visible);
         col
     }
 
@@ -3,13 +3,21 @@
 //! An AgentBlock contains:
 //! - User's query
 //! - Agent's thinking/reasoning
-//! - Tool invocations with parameters and results
+//! - Tool invocations with parameters and results; tools that run shell
+//!   commands stream their output into a terminal of their own
+//! - The agent's plan, as a checklist that updates with each TodoWrite
 //! - Final response text
 //! - Any images or media
+//! - When the Claude CLI couldn't run the query, how to set it up
 
-use nexus_api::BlockId;
+use nexus_api::{BlockId, Stopwatch};
+use nexus_term::TerminalParser;
+use crate::features::agent::claude::capability::CliStatus;
 use std::collections::HashMap;
-use std::time::Instant;
+
+/// Width of a tool's terminal, matching a new shell block's.
+const TOOL_TERMINAL_COLS: u16 = 120;
+const TOOL_TERMINAL_ROWS: u16 = 24;
 
 /// Status of a tool invocation.
 #[derive(Debug, Clone, PartialEq)]
@@ -25,7 +33,7 @@ pub enum ToolStatus {
 }
 
 /// A single tool invocation within an agent turn.
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct ToolInvocation {
     /// Unique ID for this tool call.
     pub id: String,
@@ -41,10 +49,14 @@ pub struct ToolInvocation {
     pub message: Option<String>,
     /// Whether the tool UI is collapsed.
     pub collapsed: bool,
+    /// Output parsed as a terminal would, for tools that run shell
+    /// commands, so colors and cursor movement render as in a shell block.
+    pub terminal: Option<TerminalParser>,
 }
 
 impl ToolInvocation {
     pub fn new(id: String, name: String) -> Self {
+        let terminal = runs_shell(&name).then(|| TerminalParser::new(TOOL_TERMINAL_COLS, TOOL_TERMINAL_ROWS));
         Self {
             id,
             name,
@@ -53,9 +65,31 @@ impl ToolInvocation {
             status: ToolStatus::Pending,
             message: None,
             collapsed: false,
+            terminal,
+        }
+    }
+
+    /// Append a chunk of output, feeding the terminal if there is one.
+    pub fn append_output(&mut self, chunk: &str) {
+        self.output.get_or_insert_with(String::new).push_str(chunk);
+        if let Some(terminal) = &mut self.terminal {
+            feed_text(terminal, chunk);
         }
     }
 
+    /// Replace the output with the final result. The terminal is only
+    /// re-fed when the result differs from what streamed in.
+    pub fn set_output(&mut self, output: String) {
+        if self.output.as_deref() == Some(output.as_str()) {
+            return;
+        }
+        if let Some(terminal) = &mut self.terminal {
+            *terminal = TerminalParser::new(TOOL_TERMINAL_COLS, TOOL_TERMINAL_ROWS);
+            feed_text(terminal, &output);
+        }
+        self.output = Some(output);
+    }
+
     /// Gather all visible text for copy/selection extraction.
     pub fn extract_text(&self) -> String {
         let mut text = String::with_capacity(256);
@@ -78,12 +112,12 @@ impl ToolInvocation {
             text.push(')');
         }
 
-        // Output (if expanded)
+        // Output (if expanded). A terminal's output is its own source.
         if !self.collapsed {
-            if let Some(output) = &self.output {
+            if let Some(output) = self.output.as_ref().filter(|_| self.terminal.is_none()) {
                 text.push('\n');
                 text.push_str(output);
-            } else if let Some(err) = &self.message {
+            } else if let Some(err) = self.message.as_ref().filter(|_| self.output.is_none()) {
                 text.push('\n');
                 text.push_str(err);
             }
@@ -101,6 +135,26 @@ impl ToolInvocation {
     }
 }
 
+/// Whether a tool runs shell commands, and so gets a terminal.
+fn runs_shell(tool_name: &str) -> bool {
+    matches!(tool_name, "Bash" | "BashOutput")
+}
+
+/// Feed tool output to a terminal. The text didn't come through a PTY,
+/// so bare newlines get the carriage return a PTY would add.
+fn feed_text(terminal: &mut TerminalParser, text: &str) {
+    let mut bytes = Vec::with_capacity(text.len() + text.len() / 16);
+    let mut prev = 0u8;
+    for &b in text.as_bytes() {
+        if b == b'\n' && prev != b'\r' {
+            bytes.push(b'\r');
+        }
+        bytes.push(b);
+        prev = b;
+    }
+    terminal.feed(&bytes);
+}
+
 /// Lifecycle state of an agent conversation turn.
 ///
 /// Typical progression: `Pending` → `Thinking` → `Streaming` → (`Executing` ↔
@@ -153,8 +207,74 @@ pub struct PendingUserQuestion {
     pub questions: Vec<crate::features::agent::events::UserQuestion>,
 }
 
+/// A transient API failure the turn is being retried after.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentRetry {
+    /// Retries so far, counting this one.
+    pub attempt: u32,
+    pub max_attempts: u32,
+    /// The error that caused it.
+    pub reason: String,
+}
+
+/// Why the Claude CLI couldn't run a block's query, shown in place of
+/// the response with install instructions and a "Check again" button.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentSetup {
+    /// The latest check's result.
+    pub status: CliStatus,
+    /// Whether "Check again" is running.
+    pub checking: bool,
+}
+
+/// Progress of a plan step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanStepStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+impl PlanStepStatus {
+    /// The checkbox shown beside a step.
+    pub fn checkbox(self) -> &'static str {
+        match self {
+            Self::Pending => "\u{2610}",    // ☐
+            Self::InProgress => "\u{25D0}", // ◐
+            Self::Completed => "\u{2611}",  // ☑
+        }
+    }
+}
+
+/// One step of the agent's plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep {
+    pub content: String,
+    pub status: PlanStepStatus,
+}
+
+/// Parse the `todos` parameter of a TodoWrite call. None until the JSON
+/// has streamed in completely.
+fn parse_plan(todos: &str) -> Option<Vec<PlanStep>> {
+    let value: serde_json::Value = serde_json::from_str(todos).ok()?;
+    let steps = value
+        .as_array()?
+        .iter()
+        .filter_map(|todo| {
+            let content = todo.get("content")?.as_str()?.to_string();
+            let status = match todo.get("status").and_then(|s| s.as_str()) {
+                Some("in_progress") => PlanStepStatus::InProgress,
+                Some("completed") => PlanStepStatus::Completed,
+                _ => PlanStepStatus::Pending,
+            };
+            Some(PlanStep { content, status })
+        })
+        .collect();
+    Some(steps)
+}
+
 /// An agent conversation turn (query + response).
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct AgentBlock {
     /// Block ID for the UI.
     pub id: BlockId,
@@ -173,7 +293,7 @@ pub struct AgentBlock {
     /// Current state.
     pub state: AgentBlockState,
     /// When the query started.
-    pub started_at: Instant,
+    pub started_at: Stopwatch,
     /// Duration in milliseconds (when completed).
     pub duration_ms: Option<u64>,
     /// Pending permission request.
@@ -188,6 +308,14 @@ pub struct AgentBlock {
     pub input_tokens: Option<u64>,
     /// Output token count (from CLI result).
     pub output_tokens: Option<u64>,
+    /// The latest retry after a transient API failure, if there was one.
+    pub retry: Option<AgentRetry>,
+    /// The agent's plan, from its latest TodoWrite call.
+    pub plan: Vec<PlanStep>,
+    /// Whether the plan panel is collapsed.
+    pub plan_collapsed: bool,
+    /// Set when the CLI wasn't set up to run the query.
+    pub setup: Option<AgentSetup>,
     /// Version counter for lazy invalidation.
     pub version: u64,
 }
@@ -213,7 +341,7 @@ impl AgentBlock {
             active_tool_id: None,
             images: Vec::new(),
             state: AgentBlockState::Pending,
-            started_at: Instant::now(),
+            started_at: Stopwatch::start(),
             duration_ms: None,
             pending_permission: None,
             pending_question: None,
@@ -221,6 +349,10 @@ impl AgentBlock {
             cost_usd: None,
             input_tokens: None,
             output_tokens: None,
+            retry: None,
+            plan: Vec::new(),
+            plan_collapsed: false,
+            setup: None,
             version: 0,
         }
     }
@@ -257,6 +389,11 @@ impl AgentBlock {
                 .entry(name)
                 .and_modify(|v| v.push_str(&value))
                 .or_insert(value);
+            if tool.name == "TodoWrite"
+                && let Some(plan) = tool.parameters.get("todos").and_then(|todos| parse_plan(todos))
+            {
+                self.plan = plan;
+            }
             self.version += 1;
         }
     }
@@ -279,7 +416,7 @@ impl AgentBlock {
             tool.status = status;
             tool.message = message;
             if let Some(out) = output {
-                tool.output = Some(out);
+                tool.set_output(out);
             }
             self.version += 1;
         }
@@ -292,8 +429,7 @@ impl AgentBlock {
     /// Append output to a tool.
     pub fn append_tool_output(&mut self, tool_id: &str, chunk: &str) {
         if let Some(tool) = self.tools.iter_mut().find(|t| t.id == tool_id) {
-            let output = tool.output.get_or_insert_with(String::new);
-            output.push_str(chunk);
+            tool.append_output(chunk);
             self.version += 1;
         }
     }
@@ -318,10 +454,28 @@ impl AgentBlock {
         self.version += 1;
     }
 
+    /// Record a retry after a transient failure; the turn keeps running.
+    pub fn retrying(&mut self, retry: AgentRetry) {
+        self.retry = Some(retry);
+        self.state = AgentBlockState::Pending;
+        self.version += 1;
+    }
+
+    /// What the footer says about retries: the one in progress while
+    /// running, how many there were once done.
+    pub fn retry_status(&self) -> Option<String> {
+        let retry = self.retry.as_ref()?;
+        Some(if self.is_running() {
+            format!("Retrying ({}/{}): {}", retry.attempt, retry.max_attempts, retry.reason)
+        } else {
+            format!("retried {}\u{00D7}", retry.attempt)
+        })
+    }
+
     /// Mark the block as completed.
     pub fn complete(&mut self) {
         self.state = AgentBlockState::Completed;
-        self.duration_ms = Some(self.started_at.elapsed().as_millis() as u64);
+        self.duration_ms = Some(self.started_at.elapsed_ms());
         self.active_tool_id = None;
         self.version += 1;
     }
@@ -329,11 +483,33 @@ impl AgentBlock {
     /// Mark the block as failed.
     pub fn fail(&mut self, error: String) {
         self.state = AgentBlockState::Failed(error);
-        self.duration_ms = Some(self.started_at.elapsed().as_millis() as u64);
+        self.duration_ms = Some(self.started_at.elapsed_ms());
         self.active_tool_id = None;
         self.version += 1;
     }
 
+    /// Fail the block because the CLI couldn't run its query.
+    pub fn needs_setup(&mut self, status: CliStatus) {
+        self.setup = Some(AgentSetup { status, checking: false });
+        self.fail("Claude CLI setup needed".to_string());
+    }
+
+    /// Start a "Check again".
+    pub fn checking_setup(&mut self) {
+        if let Some(setup) = &mut self.setup {
+            setup.checking = true;
+            self.version += 1;
+        }
+    }
+
+    /// Show the result of a "Check again".
+    pub fn setup_checked(&mut self, status: CliStatus) {
+        if let Some(setup) = &mut self.setup {
+            *setup = AgentSetup { status, checking: false };
+            self.version += 1;
+        }
+    }
+
     /// Check if the block is still processing.
     pub fn is_running(&self) -> bool {
         matches!(
@@ -352,6 +528,28 @@ impl AgentBlock {
         self.version += 1;
     }
 
+    /// Toggle plan collapsed state.
+    pub fn toggle_plan(&mut self) {
+        self.plan_collapsed = !self.plan_collapsed;
+        self.version += 1;
+    }
+
+    /// How many plan steps are done.
+    pub fn plan_completed(&self) -> usize {
+        self.plan.iter().filter(|step| step.status == PlanStepStatus::Completed).count()
+    }
+
+    /// The plan as a Markdown task list. Steps in progress are unchecked.
+    pub fn plan_markdown(&self) -> String {
+        self.plan
+            .iter()
+            .map(|step| {
+                let mark = if step.status == PlanStepStatus::Completed { "x" } else { " " };
+                format!("- [{}] {}\n", mark, step.content)
+            })
+            .collect()
+    }
+
     /// Gather footer text (status, duration, cost, tokens) for copy/selection.
     pub fn footer_text(&self) -> String {
         let mut parts: Vec<String> = Vec::new();
@@ -368,6 +566,10 @@ impl AgentBlock {
         };
         parts.push(status.to_string());
 
+        if let Some(retry) = self.retry_status() {
+            parts.push(retry);
+        }
+
         if let Some(ms) = self.duration_ms {
             if ms < 1000 {
                 parts.push(format!("{}ms", ms));
@@ -439,8 +641,59 @@ mod tests {
         assert!(!tool.collapsed);
     }
 
+    #[test]
+    fn test_shell_tool_output_feeds_terminal() {
+        let mut tool = ToolInvocation::new("tool-1".to_string(), "Bash".to_string());
+        tool.append_output("one\n\x1b[31mtwo\x1b[0m\n");
+        // The final result repeats what streamed in; it isn't fed twice.
+        tool.set_output("one\n\x1b[31mtwo\x1b[0m\n".to_string());
+        let text = tool.terminal.as_ref().unwrap().grid_with_scrollback().to_string();
+        let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+        assert_eq!(lines[..3], ["one", "two", ""]);
+        assert_eq!(tool.extract_text(), "Bash");
+
+        assert!(ToolInvocation::new("tool-2".to_string(), "Read".to_string()).terminal.is_none());
+    }
+
     // ========== AgentBlock tests ==========
 
+    #[test]
+    fn test_agent_block_retry_status() {
+        let mut block = AgentBlock::new(BlockId(1), "test".to_string());
+        assert!(block.retry_status().is_none());
+
+        block.retrying(AgentRetry { attempt: 2, max_attempts: 3, reason: "Overloaded".to_string() });
+        assert!(block.is_running());
+        assert_eq!(block.retry_status().as_deref(), Some("Retrying (2/3): Overloaded"));
+
+        block.complete();
+        assert_eq!(block.retry_status().as_deref(), Some("retried 2\u{00D7}"));
+        assert!(block.footer_text().starts_with("Completed | retried 2\u{00D7}"));
+    }
+
+    #[test]
+    fn test_agent_block_plan_from_todo_write() {
+        let mut block = AgentBlock::new(BlockId(1), "test".to_string());
+        block.start_tool("t1".to_string(), "TodoWrite".to_string());
+        // Parameters stream in chunks; the plan appears once the JSON is whole.
+        block.add_tool_parameter("t1", "todos".to_string(), r#"[{"content":"Read the code","status":"completed"},"#.to_string());
+        assert!(block.plan.is_empty());
+        block.add_tool_parameter(
+            "t1",
+            "todos".to_string(),
+            r#"{"content":"Fix the bug","status":"in_progress"},{"content":"Run tests","status":"pending"}]"#.to_string(),
+        );
+        assert_eq!(block.plan.len(), 3);
+        assert_eq!(block.plan[1].status, PlanStepStatus::InProgress);
+        assert_eq!(block.plan_completed(), 1);
+        assert_eq!(block.plan_markdown(), "- [x] Read the code\n- [ ] Fix the bug\n- [ ] Run tests\n");
+
+        // A later call replaces the plan.
+        block.start_tool("t2".to_string(), "TodoWrite".to_string());
+        block.add_tool_parameter("t2", "todos".to_string(), r#"[{"content":"Run tests","status":"completed"}]"#.to_string());
+        assert_eq!(block.plan, [PlanStep { content: "Run tests".to_string(), status: PlanStepStatus::Completed }]);
+    }
+
     #[test]
     fn test_agent_block_new() {
         let block = AgentBlock::new(BlockId(1), "What is Rust?".to_string());
@@ -754,6 +1007,7 @@ mod tests {
             status: ToolStatus::Success,
             message: None,
             collapsed: false,
+            terminal: None,
         }
     }
 
@@ -817,13 +1071,17 @@ mod tests {
             active_tool_id: None,
             images: vec![],
             state,
-            started_at: std::time::Instant::now(),
+            started_at: Stopwatch::start(),
             pending_permission: None,
             pending_question: None,
             duration_ms: None,
             cost_usd: None,
             input_tokens: None,
             output_tokens: None,
+            retry: None,
+            plan: Vec::new(),
+            plan_collapsed: false,
+            setup: None,
             version: 0,
         }
     }
@@ -886,4 +1144,21 @@ mod tests {
         let result = block.footer_text();
         assert!(result.contains("Connection error"));
     }
+
+    #[test]
+    fn test_setup_flow() {
+        let mut block = AgentBlock::new(BlockId(1), "hi".to_string());
+        block.needs_setup(CliStatus::Missing);
+        assert!(!block.is_running());
+        assert_eq!(block.setup.as_ref().map(|s| &s.status), Some(&CliStatus::Missing));
+
+        block.checking_setup();
+        assert!(block.setup.as_ref().is_some_and(|s| s.checking));
+
+        let ready = CliStatus::Ready { path: "/usr/local/bin/claude".into(), version: crate::features::agent::claude::capability::CliVersion(1, 0, 40) };
+        let version = block.version;
+        block.setup_checked(ready.clone());
+        assert_eq!(block.setup, Some(AgentSetup { status: ready, checking: false }));
+        assert!(block.version > version);
+    }
 }
@@ -4,6 +4,8 @@
 #[derive(Debug, Clone)]
 pub enum PtyEvent {
     Output(Vec<u8>),
+    /// What the process used; sent just before `Exited`.
+    Usage(nexus_api::ResourceUsage),
     Exited(i32),
 }
 
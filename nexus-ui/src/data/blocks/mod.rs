@@ -5,7 +5,7 @@ mod view;
 mod enums;
 mod events;
 
-pub use model::{Block, ConnectProgress, UnifiedBlock, UnifiedBlockRef};
+pub use model::{Block, ConnectProgress, DebugPause, OutputChunk, OutputStream, ReplCell, ReplCellOutput, ReplSession, Restored, Shared, TestTree, Throughput, UnifiedBlock, UnifiedBlockRef};
 pub use view::{ViewState, FileTreeState, ColumnFilter, TableFilter, TableSort};
 pub use enums::{Focus, InputMode, ProcSort};
 pub use events::PtyEvent;
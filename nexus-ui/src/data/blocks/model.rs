@@ -1,11 +1,14 @@
 //! Core block types: Block, UnifiedBlock, UnifiedBlockRef.
 
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::sync::atomic::AtomicU16;
-use std::time::Instant;
-
-use nexus_api::{BlockId, BlockState, OutputFormat, Value};
+use nexus_api::{BlockId, BlockState, OutputFormat, ReplUpdate, Stopwatch, Value};
+use nexus_kernel::persistence::BlockNote;
+use nexus_kernel::problems::ProblemDelta;
+use nexus_kernel::process::io::IoCounters;
+use nexus_kernel::test_report::{TestReport, TestStatus};
 use nexus_term::TerminalParser;
+use strata::primitives::Color;
 
 use crate::features::shell::prediction::PredictionEngine;
 
@@ -53,6 +56,223 @@ pub struct ConnectProgress {
     pub progress: Option<f32>,
 }
 
+/// Where a `debug <script>` run is paused.
+#[derive(Debug, Clone)]
+pub struct DebugPause {
+    /// 1-based step about to run, of `total`.
+    pub step: u32,
+    pub total: u32,
+    /// The step's source, variables expanded.
+    pub command: String,
+}
+
+/// Where a block shown from the previous session keeps its output, see
+/// [`crate::features::shell::ShellWidget::rehydrate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Restored {
+    /// The block's row in the store.
+    pub row_id: i64,
+    pub has_output: bool,
+    pub has_snapshot: bool,
+    /// Whether the output has been loaded into the block yet.
+    pub loaded: bool,
+}
+
+/// Set on blocks opened from a shared `.nexusblock` bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shared {
+    /// The sharer's focus ring color.
+    pub accent: Option<Color>,
+    /// When the block was shared, RFC 3339.
+    pub shared_at: String,
+}
+
+/// Output chunks kept per block for the timeline view; older ones are dropped.
+const TIMELINE_CHUNKS: usize = 2000;
+/// Bytes of each chunk kept for the timeline view.
+const TIMELINE_CHUNK_BYTES: usize = 256;
+
+/// The stream an output chunk arrived on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+    /// A terminal, which merges stdout and stderr.
+    Terminal,
+}
+
+/// An output chunk as it arrived, for the timeline view.
+#[derive(Debug, Clone)]
+pub struct OutputChunk {
+    /// Milliseconds after the block started.
+    pub at_ms: u64,
+    pub stream: OutputStream,
+    /// Size of the whole chunk.
+    pub len: usize,
+    /// Its first [`TIMELINE_CHUNK_BYTES`] bytes.
+    pub head: Vec<u8>,
+}
+
+/// How fast a running command is producing output and doing IO, in bytes
+/// per second, for its header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Throughput {
+    pub output: u64,
+    /// The process's reads and writes, where the platform reports them.
+    pub io: Option<IoCounters>,
+}
+
+/// Byte counts at the last throughput sample.
+#[derive(Debug, Clone, Copy)]
+pub struct ThroughputSample {
+    /// Milliseconds after the block started.
+    at_ms: u64,
+    output: u64,
+    io: Option<IoCounters>,
+}
+
+/// A test run's results, shown as a tree of suites in place of the
+/// terminal grid.
+#[derive(Debug, Clone)]
+pub struct TestTree {
+    pub report: TestReport,
+    /// Expanded suites, by index; those with failures start out expanded.
+    pub open_suites: HashSet<usize>,
+    /// Failed tests whose output is shown, by suite and test index.
+    pub open_failures: HashSet<(usize, usize)>,
+    /// The command that re-runs just the failed tests.
+    pub rerun: Option<String>,
+    /// Show the terminal output instead of the tree.
+    pub show_output: bool,
+}
+
+impl TestTree {
+    pub fn new(report: TestReport, command: &str) -> Self {
+        let open_suites = report
+            .suites
+            .iter()
+            .enumerate()
+            .filter(|(_, suite)| suite.counts().failed > 0)
+            .map(|(i, _)| i)
+            .collect();
+        let rerun = report.rerun_failed(command);
+        Self { report, open_suites, open_failures: HashSet::new(), rerun, show_output: false }
+    }
+
+    /// Expand or collapse a suite, or with `test`, a failed test's output.
+    pub fn toggle(&mut self, suite: usize, test: Option<usize>) {
+        match test {
+            Some(test) => {
+                let failed = self.report.suites.get(suite).and_then(|s| s.tests.get(test)).is_some_and(|t| t.status == TestStatus::Failed);
+                if failed && !self.open_failures.remove(&(suite, test)) {
+                    self.open_failures.insert((suite, test));
+                }
+            }
+            None => {
+                if !self.open_suites.remove(&suite) {
+                    self.open_suites.insert(suite);
+                }
+            }
+        }
+    }
+}
+
+/// What a REPL cell produced, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplCellOutput {
+    Text { text: String, stderr: bool },
+    /// The value of the cell's last expression.
+    Value(Value),
+}
+
+/// A cell sent to a `repl` block's interpreter.
+#[derive(Debug, Clone)]
+pub struct ReplCell {
+    /// Numbered from 1 in each session; 0 collects what the interpreter
+    /// printed before the first cell.
+    pub number: u32,
+    /// Interpreter sessions before this cell's: restarts so far.
+    pub session: u32,
+    pub code: String,
+    pub outputs: Vec<ReplCellOutput>,
+    pub running: bool,
+    /// The traceback, if the cell raised.
+    pub error: Option<String>,
+}
+
+/// A `repl` block's interpreter and the cells sent to it, across restarts.
+#[derive(Debug, Clone, Default)]
+pub struct ReplSession {
+    pub language: String,
+    /// Restarts so far.
+    pub session: u32,
+    pub cells: Vec<ReplCell>,
+}
+
+impl ReplSession {
+    pub fn apply(&mut self, update: ReplUpdate) {
+        match update {
+            ReplUpdate::Ready { language } => {
+                if !self.language.is_empty() {
+                    self.session += 1;
+                }
+                self.language = language;
+                self.end();
+            }
+            ReplUpdate::CellStarted { cell, code } => {
+                self.cell_mut(cell).code = code;
+                self.cell_mut(cell).running = true;
+            }
+            ReplUpdate::Output { cell, text, stderr } => {
+                let outputs = &mut self.cell_mut(cell).outputs;
+                match outputs.last_mut() {
+                    Some(ReplCellOutput::Text { text: last, stderr: last_stderr }) if *last_stderr == stderr => {
+                        last.push_str(&text)
+                    }
+                    _ => outputs.push(ReplCellOutput::Text { text, stderr }),
+                }
+            }
+            ReplUpdate::Value { cell, value } => self.cell_mut(cell).outputs.push(ReplCellOutput::Value(value)),
+            ReplUpdate::CellFinished { cell, error } => {
+                let cell = self.cell_mut(cell);
+                cell.running = false;
+                cell.error = error;
+            }
+        }
+    }
+
+    /// Stop showing cells as running: the interpreter is gone.
+    pub fn end(&mut self) {
+        for cell in &mut self.cells {
+            cell.running = false;
+        }
+    }
+
+    /// Whether a cell is still running.
+    pub fn is_busy(&self) -> bool {
+        self.cells.iter().any(|cell| cell.running)
+    }
+
+    fn cell_mut(&mut self, number: u32) -> &mut ReplCell {
+        let session = self.session;
+        let index = match self.cells.iter().rposition(|c| c.number == number && c.session == session) {
+            Some(index) => index,
+            None => {
+                self.cells.push(ReplCell {
+                    number,
+                    session,
+                    code: String::new(),
+                    outputs: Vec::new(),
+                    running: false,
+                    error: None,
+                });
+                self.cells.len() - 1
+            }
+        };
+        &mut self.cells[index]
+    }
+}
+
 /// A shell command block: user-typed command + its output.
 ///
 /// Output can take three mutually-exclusive forms, checked in priority order:
@@ -71,7 +291,7 @@ pub struct Block {
     #[allow(dead_code)]
     pub format: OutputFormat,
     pub collapsed: bool,
-    pub started_at: Instant,
+    pub started_at: Stopwatch,
     pub duration_ms: Option<u64>,
     /// Version counter for lazy invalidation.
     pub version: u64,
@@ -89,6 +309,8 @@ pub struct Block {
     pub has_permission_denied: bool,
     /// Whether output contained "command not found".
     pub has_command_not_found: bool,
+    /// Why a native command failed, rendered as an error chip.
+    pub error: Option<nexus_api::CommandError>,
     /// Append-only event log (ping replies, etc.). Capped at 1000 entries.
     pub event_log: VecDeque<Value>,
     /// Latest coalesced state (progress bar, live table, etc.).
@@ -101,8 +323,57 @@ pub struct Block {
     pub file_tree: Option<FileTreeState>,
     /// OSC title set by the child process (via escape sequences).
     pub osc_title: Option<String>,
+    /// Whether the block takes over the whole window while it runs
+    /// (`[fullscreen] commands`: vim, htop, ...).
+    pub fullscreen: bool,
     /// Remote connection progress overlay (spinner + stage text + progress bar).
     pub connect_progress: Option<ConnectProgress>,
+    /// Set while `debug <script>` waits to step, continue or abort.
+    pub debug: Option<DebugPause>,
+    /// Output chunks with arrival time and stream, fed alongside `parser`.
+    pub chunks: VecDeque<OutputChunk>,
+    /// Chunks dropped from the front of `chunks`.
+    pub chunks_dropped: usize,
+    /// Show `chunks` as a timeline instead of the terminal grid.
+    pub timeline: bool,
+    /// Total output bytes received.
+    pub output_bytes: u64,
+    /// Live output and IO rates, once two samples have been taken.
+    pub throughput: Option<Throughput>,
+    pub throughput_sample: Option<ThroughputSample>,
+    /// How the compiler problems in the output differ from the last run
+    /// of the same command here.
+    pub problems: Option<ProblemDelta>,
+    /// Index into `problems.new` of the one to open next.
+    pub problem_cursor: usize,
+    /// Parsed results, when the command was a test run.
+    pub tests: Option<TestTree>,
+    /// The command and its outcome in a few words, once it finished
+    /// (`cargo build — 2 errors`).
+    pub title: Option<String>,
+    /// Cells and their output, when the block runs `repl`.
+    pub repl: Option<ReplSession>,
+    /// CPU time, memory and IO of the block's process, once it exited.
+    pub resource_usage: Option<nexus_api::ResourceUsage>,
+    /// Show every figure of `resource_usage`, not just the summary.
+    pub usage_expanded: bool,
+    /// The last run of the same command, when this run's output is
+    /// identical to it. Such a block is shown folded.
+    pub repeat_of: Option<BlockId>,
+    /// Show a repeated block's output anyway.
+    pub repeat_expanded: bool,
+    /// Set on blocks shown from the previous session, which start
+    /// collapsed and load their output when first expanded.
+    pub restored: Option<Restored>,
+    /// Set on blocks opened from a bundle someone shared.
+    pub shared: Option<Shared>,
+    /// The git worktree the command ran in (its branch, or its directory's
+    /// name when detached), when the repository has more than one.
+    pub worktree: Option<String>,
+    /// The container the command runs in, for `nexus exec -c`.
+    pub container: Option<String>,
+    /// Notes the user left on lines of the output, ordered by line.
+    pub notes: Vec<BlockNote>,
     /// High-water mark for content_rows, used to debounce shrink flicker
     /// on running blocks that do clear+reprint cycles (e.g. Claude Code).
     pub peak_content_rows: AtomicU16,
@@ -134,7 +405,7 @@ impl Block {
             state: BlockState::Running,
             format: OutputFormat::PlainText,
             collapsed: false,
-            started_at: Instant::now(),
+            started_at: Stopwatch::start(),
             duration_ms: None,
             version: 0,
             structured_output: None,
@@ -143,13 +414,36 @@ impl Block {
             filtered_row_indices: None,
             has_permission_denied: false,
             has_command_not_found: false,
+            error: None,
             event_log: VecDeque::new(),
             live_value: None,
             event_seq: 0,
             view_state: None,
             file_tree: None,
             osc_title: None,
+            fullscreen: false,
             connect_progress: None,
+            debug: None,
+            chunks: VecDeque::new(),
+            chunks_dropped: 0,
+            timeline: false,
+            output_bytes: 0,
+            throughput: None,
+            throughput_sample: None,
+            problems: None,
+            problem_cursor: 0,
+            tests: None,
+            title: None,
+            repl: None,
+            resource_usage: None,
+            usage_expanded: false,
+            repeat_of: None,
+            repeat_expanded: false,
+            restored: None,
+            shared: None,
+            worktree: None,
+            container: None,
+            notes: Vec::new(),
             peak_content_rows: AtomicU16::new(0),
             terminal_modes: None,
             prediction: PredictionEngine::new(),
@@ -164,6 +458,82 @@ impl Block {
         matches!(self.state, BlockState::Running)
     }
 
+    /// Discard everything the kernel has sent for this block, ahead of
+    /// replaying its events after a subscription gap. View state (sort,
+    /// filters, collapse) is kept.
+    pub fn reset_output(&mut self, parser: TerminalParser) {
+        self.parser = parser;
+        self.state = BlockState::Running;
+        self.duration_ms = None;
+        self.structured_output = None;
+        self.error = None;
+        self.filtered_row_indices = None;
+        self.event_log.clear();
+        self.chunks.clear();
+        self.chunks_dropped = 0;
+        self.output_bytes = 0;
+        self.throughput = None;
+        self.throughput_sample = None;
+        self.problems = None;
+        self.problem_cursor = 0;
+        self.tests = None;
+        self.title = None;
+        self.repl = None;
+        self.resource_usage = None;
+        self.repeat_of = None;
+        self.live_value = None;
+        self.event_seq = 0;
+        self.connect_progress = None;
+        self.terminal_modes = None;
+        self.prediction.reset();
+        self.last_visible_cursor = None;
+        self.last_write_cursor = None;
+        self.sync_output_active = false;
+        self.sync_frame_started = None;
+        self.version += 1;
+    }
+
+    /// Note an output chunk for the timeline view. The data itself goes to
+    /// `parser` as before.
+    pub fn record_chunk(&mut self, stream: OutputStream, data: &[u8]) {
+        if self.chunks.len() == TIMELINE_CHUNKS {
+            self.chunks.pop_front();
+            self.chunks_dropped += 1;
+        }
+        self.chunks.push_back(OutputChunk {
+            at_ms: self.started_at.elapsed_ms(),
+            stream,
+            len: data.len(),
+            head: data[..data.len().min(TIMELINE_CHUNK_BYTES)].to_vec(),
+        });
+        self.output_bytes += data.len() as u64;
+    }
+
+    /// Sample the output byte count and the process's IO counters `io` at
+    /// `at_ms`, and set `throughput` to the rates since the last sample.
+    /// Returns whether the figure changed.
+    pub fn sample_throughput(&mut self, at_ms: u64, io: Option<IoCounters>) -> bool {
+        let sample = ThroughputSample { at_ms, output: self.output_bytes, io };
+        let Some(last) = self.throughput_sample.replace(sample) else {
+            return false;
+        };
+        let elapsed_ms = at_ms.saturating_sub(last.at_ms).max(1);
+        let rate = |now: u64, then: u64| now.saturating_sub(then) * 1000 / elapsed_ms;
+        let throughput = Throughput {
+            output: rate(sample.output, last.output),
+            io: io.zip(last.io).map(|(now, then)| IoCounters {
+                read: rate(now.read, then.read),
+                written: rate(now.written, then.written),
+            }),
+        };
+        if self.throughput == Some(throughput) {
+            return false;
+        }
+        self.throughput = Some(throughput);
+        self.version += 1;
+        true
+    }
+
     /// Get or create file tree expansion state.
     pub fn ensure_file_tree(&mut self) -> &mut FileTreeState {
         self.file_tree.get_or_insert_with(FileTreeState::default)
@@ -345,6 +715,48 @@ impl Block {
             .unwrap_or(0)
     }
 
+    /// Digest of the finished output, for telling whether two runs printed
+    /// the same thing: the native value when there is one, otherwise the
+    /// terminal text. None for empty, streamed or interactive output.
+    pub fn output_digest(&self) -> Option<u64> {
+        use std::hash::{Hash, Hasher};
+        if self.live_value.is_some() || !self.event_log.is_empty() || self.repl.is_some() || self.view_state.is_some() {
+            return None;
+        }
+        let text = match &self.structured_output {
+            Some(value) => serde_json::to_string(value).ok()?,
+            // Rows are padded to the grid width, which depends on when the
+            // block was sized; compare the text only.
+            None => self.parser.grid_with_scrollback().to_string().lines().map(str::trim_end).collect::<Vec<_>>().join("\n"),
+        };
+        if text.trim().is_empty() {
+            return None;
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        text.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    /// The note on output row `line`, if there is one.
+    pub fn note(&self, line: usize) -> Option<&str> {
+        self.notes.iter().find(|note| note.line == line).map(|note| note.text.as_str())
+    }
+
+    /// Put a note on output row `line`, replacing the one there; empty
+    /// text removes it.
+    pub fn set_note(&mut self, line: usize, text: &str) {
+        let text = text.trim();
+        match self.notes.binary_search_by_key(&line, |note| note.line) {
+            Ok(i) if text.is_empty() => {
+                self.notes.remove(i);
+            }
+            Ok(i) => self.notes[i].text = text.to_string(),
+            Err(_) if text.is_empty() => return,
+            Err(i) => self.notes.insert(i, BlockNote { line, text: text.to_string() }),
+        }
+        self.version += 1;
+    }
+
     // =========================================================================
     // Clipboard helpers — encapsulate block data extraction for copy operations
     // =========================================================================
@@ -440,6 +852,118 @@ mod tests {
         assert!(block.structured_output.is_none());
     }
 
+    #[test]
+    fn test_output_digest() {
+        let mut a = Block::new(BlockId(1), "ls".to_string());
+        let mut b = Block::new(BlockId(2), "ls".to_string());
+        assert_eq!(a.output_digest(), None);
+
+        a.structured_output = Some(Value::List(vec![Value::String("a.txt".into())]));
+        b.structured_output = Some(Value::List(vec![Value::String("a.txt".into())]));
+        assert!(a.output_digest().is_some());
+        assert_eq!(a.output_digest(), b.output_digest());
+
+        b.structured_output = Some(Value::List(vec![Value::String("b.txt".into())]));
+        assert_ne!(a.output_digest(), b.output_digest());
+
+        a.live_value = Some(Value::Int(1));
+        assert_eq!(a.output_digest(), None);
+    }
+
+    #[test]
+    fn test_set_note() {
+        let mut block = Block::new(BlockId(1), "make".to_string());
+        block.set_note(7, "the real failure");
+        block.set_note(2, "  warning, ignore  ");
+        assert_eq!(block.notes.iter().map(|n| n.line).collect::<Vec<_>>(), [2, 7]);
+        assert_eq!(block.note(2), Some("warning, ignore"));
+
+        block.set_note(7, "fixed in abc123");
+        assert_eq!(block.note(7), Some("fixed in abc123"));
+        let version = block.version;
+        block.set_note(2, "");
+        block.set_note(3, " ");
+        assert_eq!((block.note(2), block.notes.len(), block.version), (None, 1, version + 1));
+    }
+
+    #[test]
+    fn test_record_chunk_keeps_head_and_caps_count() {
+        let mut block = Block::new(BlockId(1), "make".to_string());
+        block.record_chunk(OutputStream::Stderr, &[b'x'; 1000]);
+        assert_eq!((block.chunks[0].len, block.chunks[0].head.len()), (1000, TIMELINE_CHUNK_BYTES));
+
+        for _ in 0..TIMELINE_CHUNKS {
+            block.record_chunk(OutputStream::Stdout, b"ok\n");
+        }
+        assert_eq!((block.chunks.len(), block.chunks_dropped), (TIMELINE_CHUNKS, 1));
+        assert_eq!(block.chunks[0].stream, OutputStream::Stdout);
+    }
+
+    #[test]
+    fn test_sample_throughput() {
+        let mut block = Block::new(BlockId(1), "cp -r a b".to_string());
+        let io = |read, written| Some(IoCounters { read, written });
+        assert!(!block.sample_throughput(1000, io(0, 0)));
+        assert_eq!(block.throughput, None);
+
+        block.record_chunk(OutputStream::Terminal, &[b'.'; 500]);
+        assert!(block.sample_throughput(1500, io(4096, 1024)));
+        assert_eq!(block.throughput, Some(Throughput { output: 1000, io: io(8192, 2048) }));
+
+        // Unchanged rates leave the block alone; lost counters drop the IO part.
+        let version = block.version;
+        block.record_chunk(OutputStream::Terminal, &[b'.'; 1000]);
+        assert!(!block.sample_throughput(2500, io(12288, 3072)));
+        assert_eq!(block.version, version);
+        assert!(block.sample_throughput(3500, None));
+        assert_eq!(block.throughput, Some(Throughput { output: 0, io: None }));
+    }
+
+    #[test]
+    fn test_test_tree_opens_failures() {
+        use nexus_kernel::test_report::Runner;
+
+        let output = "test a::ok ... ok\ntest b::broken ... FAILED\n\n---- b::broken stdout ----\npanicked\n\nfailures:\n";
+        let report = TestReport::parse(Runner::Cargo, output).unwrap();
+        let mut tree = TestTree::new(report, "cargo test");
+        assert_eq!(tree.open_suites, HashSet::from([1]));
+        assert_eq!(tree.rerun.as_deref(), Some("cargo test -- --exact b::broken"));
+
+        tree.toggle(1, Some(0));
+        assert!(tree.open_failures.contains(&(1, 0)));
+        tree.toggle(0, Some(0));
+        assert!(!tree.open_failures.contains(&(0, 0)));
+        tree.toggle(1, None);
+        assert!(tree.open_suites.is_empty());
+    }
+
+    #[test]
+    fn test_repl_session_groups_output_by_cell() {
+        let mut repl = ReplSession::default();
+        repl.apply(ReplUpdate::Ready { language: "python".into() });
+        repl.apply(ReplUpdate::CellStarted { cell: 1, code: "print('a'); 2".into() });
+        repl.apply(ReplUpdate::Output { cell: 1, text: "a".into(), stderr: false });
+        repl.apply(ReplUpdate::Output { cell: 1, text: "\n".into(), stderr: false });
+        repl.apply(ReplUpdate::Value { cell: 1, value: Value::Int(2) });
+        assert!(repl.is_busy());
+        repl.apply(ReplUpdate::CellFinished { cell: 1, error: None });
+        assert!(!repl.is_busy());
+        assert_eq!(repl.cells[0].outputs, [
+            ReplCellOutput::Text { text: "a\n".into(), stderr: false },
+            ReplCellOutput::Value(Value::Int(2)),
+        ]);
+
+        // After a restart, numbering starts over in a new session.
+        repl.apply(ReplUpdate::CellStarted { cell: 2, code: "while True: pass".into() });
+        repl.apply(ReplUpdate::Ready { language: "python".into() });
+        assert!(!repl.is_busy());
+        repl.apply(ReplUpdate::CellStarted { cell: 1, code: "x".into() });
+        repl.apply(ReplUpdate::CellFinished { cell: 1, error: Some("NameError".into()) });
+        assert_eq!(repl.cells.len(), 3);
+        assert_eq!((repl.cells[2].session, repl.cells[2].error.as_deref()), (1, Some("NameError")));
+        assert!(repl.cells[0].error.is_none());
+    }
+
     #[test]
     fn test_block_is_running() {
         let mut block = Block::new(BlockId(1), "cmd".to_string());
@@ -3,7 +3,8 @@
 //! This module provides rich context for:
 //! - Smart completions (git branches, npm scripts, etc.)
 //! - Error parsing and actionable suggestions
-//! - Project-specific agent instructions (NEXUS.md)
+//! - Project-specific agent instructions (NEXUS.md, see
+//!   `nexus_kernel::instructions`)
 //!
 //! The Context System complements (not duplicates) Claude Code CLI's context.
 //! CLI handles: conversation history, system prompt, context compaction.
@@ -17,9 +18,22 @@
 //! - `PythonProvider`: pip/module errors
 //! - `RustProvider`: cargo compilation errors
 //!
-//! Providers also generate context snippets for AI prompts.
-
-use super::providers::{ParsedError, ProviderRegistry, Suggestion};
+//! Providers also generate context snippets for AI prompts, and receive
+//! `CwdChanged` / `CommandFinished` events through the `ProviderHost`, whose
+//! replies accumulate in `contributions`.
+//!
+//! `config` is the user configuration merged with any project
+//! `.nexus/config.toml` above the cwd; it supplies theme accents,
+//! workflows, and which provider warnings are shown.
+
+use super::provider_host::{Contributions, ProviderHost};
+use super::providers::{ParsedError, ProviderEvent, ProviderRegistry, Suggestion};
+use crate::ui::theme;
+use nexus_api::BlockId;
+use nexus_kernel::config::{Config, Setting};
+use nexus_kernel::dev_env::DevEnv;
+use nexus_kernel::instructions::Instructions;
+use strata::primitives::Color;
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -41,8 +55,17 @@ pub struct NexusContext {
     pub last_interaction: Option<InteractionContext>,
     /// Environment variables (cached).
     pub env_vars: HashMap<String, String>,
-    /// Project-specific instructions from NEXUS.md.
-    pub nexus_md: Option<String>,
+    /// The nearest NEXUS.md (or CLAUDE.md) at or above `cwd`.
+    pub instructions: Option<Instructions>,
+    /// Provider workers; inert until `start_providers`.
+    pub providers: ProviderHost,
+    /// What providers have contributed so far.
+    pub contributions: Contributions,
+    /// User and project configuration for `cwd`.
+    pub config: Config,
+    /// Project environments for `cwd` and those active, from the kernel
+    /// after each command (see `nexus_kernel::dev_env`).
+    pub environments: Vec<(DevEnv, bool)>,
 }
 
 /// Git repository context.
@@ -112,11 +135,79 @@ impl NexusContext {
     pub fn refresh_sync(&mut self) {
         self.git = scan_git_sync(&self.cwd);
         self.project = scan_project_sync(&self.cwd);
-        self.nexus_md = read_nexus_md_sync(&self.cwd);
+        self.instructions = Instructions::find(&self.cwd);
+        self.reload_config();
+    }
+
+    /// Re-read the user and project configuration for `cwd`.
+    pub fn reload_config(&mut self) {
+        self.config = Config::load(&self.cwd);
+        nexus_term::width::set_ambiguous_wide(self.config.ambiguous_wide());
+        theme::set_symbols(self.config.symbols());
+        for (path, error) in &self.config.errors {
+            tracing::warn!("config: ignoring {}: {}", path.display(), error);
+        }
+        for setting in [&self.config.accent, &self.config.path_color].into_iter().flatten() {
+            if theme::parse_hex(&setting.value).is_none() {
+                tracing::warn!("config: invalid color '{}' in {}", setting.value, setting.origin);
+            }
+        }
+    }
+
+    /// Focus ring color, from `theme.accent` when configured.
+    pub fn accent(&self) -> Color {
+        configured_color(&self.config.accent).unwrap_or(theme::FOCUS_RING)
+    }
+
+    /// Prompt path color, from `theme.path` when configured.
+    pub fn path_color(&self) -> Color {
+        configured_color(&self.config.path_color).unwrap_or(theme::TEXT_PATH)
+    }
+
+    /// Configured workflows as `(label, command line)`.
+    pub fn workflows(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.config.workflows.iter().map(|(name, setting)| {
+            let workflow = &setting.value;
+            let label = if workflow.description.is_empty() {
+                format!("Run {}", name)
+            } else {
+                format!("Run {}: {}", name, workflow.description)
+            };
+            (label, workflow.command_line())
+        })
+    }
+
+    /// Provider warnings not dismissed or turned off in `[warnings]`, as
+    /// `(id, text)`.
+    pub fn warnings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.contributions.warnings().filter(|(id, _)| self.config.shows_warning(id))
+    }
+
+    /// Start provider workers and announce the current directory to them.
+    pub fn start_providers(&mut self, host: ProviderHost) {
+        self.providers = host;
+        self.announce_cwd();
+    }
+
+    fn announce_cwd(&mut self) {
+        let generation = self.providers.dispatch(ProviderEvent::CwdChanged {
+            cwd: self.cwd.clone(),
+            project: self.project.clone(),
+        });
+        self.contributions.reset(generation);
     }
 
     /// Update after a command finishes.
-    pub fn on_command_finished(&mut self, command: String, output: String, exit_code: i32) {
+    pub fn on_command_finished(&mut self, block_id: BlockId, command: String, output: String, exit_code: i32) {
+        self.providers.dispatch(ProviderEvent::CommandFinished {
+            block_id,
+            command: command.clone(),
+            output: output.clone(),
+            exit_code,
+            cwd: self.cwd.clone(),
+            project: self.project.clone(),
+        });
+
         let parsed_error = if exit_code != 0 {
             let registry = ProviderRegistry::new();
             registry.analyze(&command, &output, self.project.as_ref())
@@ -166,8 +257,8 @@ impl NexusContext {
         }
 
         // NEXUS.md instructions
-        if let Some(ref instructions) = self.nexus_md {
-            parts.push(format!("Project instructions:\n{}", instructions));
+        if let Some(ref instructions) = self.instructions {
+            parts.push(format!("Project instructions:\n{}", instructions.text));
         }
 
         parts.join("\n")
@@ -178,6 +269,7 @@ impl NexusContext {
         if self.cwd != cwd {
             self.cwd = cwd;
             self.refresh_sync();
+            self.announce_cwd();
         }
     }
 
@@ -190,6 +282,10 @@ impl NexusContext {
     }
 }
 
+fn configured_color(setting: &Option<Setting<String>>) -> Option<Color> {
+    theme::parse_hex(&setting.as_ref()?.value)
+}
+
 // =============================================================================
 // Sync Scanners (simple, blocking - for MVP)
 // =============================================================================
@@ -312,27 +408,3 @@ fn parse_npm_scripts(path: &PathBuf) -> Option<Vec<String>> {
     Some(scripts.keys().cloned().collect())
 }
 
-/// Read NEXUS.md from project root.
-fn read_nexus_md_sync(cwd: &PathBuf) -> Option<String> {
-    // Walk up to find NEXUS.md
-    let mut current = cwd.clone();
-
-    loop {
-        let nexus_md = current.join("NEXUS.md");
-        if nexus_md.exists() {
-            return std::fs::read_to_string(&nexus_md).ok();
-        }
-
-        // Also check .nexus/NEXUS.md
-        let dot_nexus_md = current.join(".nexus").join("NEXUS.md");
-        if dot_nexus_md.exists() {
-            return std::fs::read_to_string(&dot_nexus_md).ok();
-        }
-
-        if !current.pop() {
-            break;
-        }
-    }
-
-    None
-}
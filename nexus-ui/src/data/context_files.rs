@@ -0,0 +1,138 @@
+//! Files in the agent's context: the ones its tools have read or changed
+//! this conversation.
+//!
+//! A file is recorded the first time a file tool names it, along with its
+//! contents at that moment, so the diff shows only what the agent changed.
+//! Removing a file can't take it out of the model's context; instead the
+//! next query tells the agent not to rely on what it read from it.
+
+use std::path::{Path, PathBuf};
+
+use similar::TextDiff;
+
+/// A file the agent has read or modified.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextFile {
+    pub path: PathBuf,
+    /// Whether the agent has written to it, not just read it.
+    pub modified: bool,
+    /// Contents when the agent first touched it; None if it didn't exist.
+    original: Option<String>,
+}
+
+impl ContextFile {
+    /// The path as shown on its chip: the file name.
+    pub fn label(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    /// Unified diff of the agent's changes: the contents it first saw
+    /// against the file as it is now.
+    pub fn diff(&self) -> String {
+        let current = std::fs::read_to_string(&self.path).unwrap_or_default();
+        let original = self.original.as_deref().unwrap_or("");
+        let name = self.path.display().to_string();
+        TextDiff::from_lines(original, &current)
+            .unified_diff()
+            .header(&name, &name)
+            .to_string()
+    }
+}
+
+/// The files of one agent conversation.
+#[derive(Debug, Default)]
+pub struct ContextFiles {
+    pub files: Vec<ContextFile>,
+    /// Files removed since the last query, to mention in the next one.
+    removed: Vec<PathBuf>,
+}
+
+impl ContextFiles {
+    /// Record a file a tool named. `tool` is the tool's name; tools that
+    /// don't take a file are ignored. Returns true if anything changed.
+    pub fn touch(&mut self, tool: &str, path: &str, cwd: &Path) -> bool {
+        let modifies = match tool {
+            "Read" => false,
+            "Edit" | "MultiEdit" | "Write" | "NotebookEdit" => true,
+            _ => return false,
+        };
+        let path = cwd.join(path);
+        self.removed.retain(|removed| *removed != path);
+        match self.files.iter_mut().find(|file| file.path == path) {
+            Some(file) if modifies && !file.modified => file.modified = true,
+            Some(_) => return false,
+            None => {
+                let original = std::fs::read_to_string(&path).ok();
+                self.files.push(ContextFile { path, modified: modifies, original });
+            }
+        }
+        true
+    }
+
+    /// Drop a file from the chips; the next query says so.
+    pub fn remove(&mut self, index: usize) {
+        if index < self.files.len() {
+            let file = self.files.remove(index);
+            self.removed.push(file.path);
+        }
+    }
+
+    /// A note for the start of the next query about removed files, once.
+    pub fn take_removal_notice(&mut self) -> Option<String> {
+        if self.removed.is_empty() {
+            return None;
+        }
+        let paths: Vec<String> = self.removed.drain(..).map(|path| path.display().to_string()).collect();
+        Some(format!(
+            "[The user removed these files from context: {}. Don't rely on what you read from them earlier; read them again if you need them.]\n",
+            paths.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nexus-context-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_touch_records_original_and_diff() {
+        let dir = scratch_dir("diff");
+        std::fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+        let mut files = ContextFiles::default();
+
+        assert!(files.touch("Read", "main.rs", &dir));
+        assert!(!files.touch("Read", "main.rs", &dir));
+        assert!(!files.touch("Bash", "main.rs", &dir));
+        assert!(files.touch("Edit", "main.rs", &dir));
+        assert_eq!(files.files.len(), 1);
+        assert!(files.files[0].modified);
+        assert_eq!(files.files[0].label(), "main.rs");
+
+        std::fs::write(dir.join("main.rs"), "fn main() { run() }\n").unwrap();
+        let diff = files.files[0].diff();
+        assert!(diff.contains("-fn main() {}\n+fn main() { run() }\n"), "{}", diff);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_removal_notice_once() {
+        let dir = scratch_dir("remove");
+        let mut files = ContextFiles::default();
+        files.touch("Write", "new.txt", &dir);
+        files.remove(0);
+        assert!(files.files.is_empty());
+        let notice = files.take_removal_notice().unwrap();
+        assert!(notice.contains("new.txt"));
+        assert!(files.take_removal_notice().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
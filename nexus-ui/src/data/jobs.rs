@@ -47,33 +47,19 @@ impl JobManager {
     }
 
     /// Process a kernel `JobStateChanged` event: create, update, or remove a job.
-    pub fn handle_event(&mut self, job_id: u32, state: nexus_api::JobState) {
-        match state {
-            nexus_api::JobState::Running => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == job_id) {
-                    job.state = VisualJobState::Running;
-                } else {
-                    self.jobs.push(VisualJob::new(
-                        job_id,
-                        format!("Job {}", job_id),
-                        VisualJobState::Running,
-                    ));
-                }
-            }
-            nexus_api::JobState::Stopped => {
-                if let Some(job) = self.jobs.iter_mut().find(|j| j.id == job_id) {
-                    job.state = VisualJobState::Stopped;
-                } else {
-                    self.jobs.push(VisualJob::new(
-                        job_id,
-                        format!("Job {}", job_id),
-                        VisualJobState::Stopped,
-                    ));
-                }
-            }
-            nexus_api::JobState::Done(_) => {
+    pub fn handle_event(&mut self, job_id: u32, command: String, state: nexus_api::JobState) {
+        let state = match state {
+            nexus_api::JobState::Running => VisualJobState::Running,
+            nexus_api::JobState::Stopped => VisualJobState::Stopped,
+            nexus_api::JobState::Done(_) | nexus_api::JobState::Disowned => {
                 self.jobs.retain(|j| j.id != job_id);
+                return;
             }
+        };
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == job_id) {
+            job.state = state;
+        } else {
+            self.jobs.push(VisualJob::new(job_id, command, state));
         }
     }
 
@@ -140,6 +126,22 @@ mod tests {
         assert_eq!(job.icon(), "⏸");
     }
 
+    // ========== JobManager tests ==========
+
+    #[test]
+    fn test_job_manager_tracks_kernel_events() {
+        let mut jobs = JobManager::new();
+        jobs.handle_event(1, "sleep 100".to_string(), nexus_api::JobState::Running);
+        jobs.handle_event(2, "vim notes".to_string(), nexus_api::JobState::Running);
+        jobs.handle_event(2, "vim notes".to_string(), nexus_api::JobState::Stopped);
+        assert_eq!(jobs.as_slice()[0].command, "sleep 100");
+        assert_eq!(jobs.as_slice()[1].state, VisualJobState::Stopped);
+
+        jobs.handle_event(1, "sleep 100".to_string(), nexus_api::JobState::Done(0));
+        jobs.handle_event(2, "vim notes".to_string(), nexus_api::JobState::Disowned);
+        assert!(jobs.is_empty());
+    }
+
     #[test]
     fn test_visual_job_state_eq() {
         assert_eq!(VisualJobState::Running, VisualJobState::Running);
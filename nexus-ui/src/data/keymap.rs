@@ -0,0 +1,248 @@
+//! Rebindable Cmd-key shortcuts.
+//!
+//! Each action has a default key; `[keybindings]` in the config maps an
+//! action name to a chord such as `"cmd+l"`. Only Cmd chords are bindable —
+//! everything else belongs to the focused terminal. Copy, paste and quit
+//! keep their platform keys.
+//!
+//! The one exception is `release_focus`: the chords that take the keyboard
+//! back from a focused terminal block, which have to be chords a terminal
+//! app is unlikely to want (`"ctrl+shift+q"` by default, several may be
+//! listed separated by commas).
+
+use nexus_kernel::config::Config;
+use strata::event_context::{Key, Modifiers, NamedKey};
+
+const CHORD_PREFIX: &str = "cmd+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    NewWindow,
+    CloseWindow,
+    ClearScreen,
+    ToggleMode,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Settings,
+    RecordMacro,
+    ReplayMacro,
+}
+
+impl Action {
+    pub const ALL: [Self; 10] = [
+        Self::NewWindow,
+        Self::CloseWindow,
+        Self::ClearScreen,
+        Self::ToggleMode,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::ZoomReset,
+        Self::Settings,
+        Self::RecordMacro,
+        Self::ReplayMacro,
+    ];
+
+    /// Key in the `[keybindings]` section.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NewWindow => "new_window",
+            Self::CloseWindow => "close_window",
+            Self::ClearScreen => "clear_screen",
+            Self::ToggleMode => "toggle_mode",
+            Self::ZoomIn => "zoom_in",
+            Self::ZoomOut => "zoom_out",
+            Self::ZoomReset => "zoom_reset",
+            Self::Settings => "settings",
+            Self::RecordMacro => "record_macro",
+            Self::ReplayMacro => "replay_macro",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::NewWindow => "New window",
+            Self::CloseWindow => "Close window",
+            Self::ClearScreen => "Clear screen",
+            Self::ToggleMode => "Toggle shell/agent mode",
+            Self::ZoomIn => "Zoom in",
+            Self::ZoomOut => "Zoom out",
+            Self::ZoomReset => "Reset zoom",
+            Self::Settings => "Settings",
+            Self::RecordMacro => "Record or stop a macro",
+            Self::ReplayMacro => "Replay last macro",
+        }
+    }
+
+    fn default_key(self) -> &'static str {
+        match self {
+            Self::NewWindow => "n",
+            Self::CloseWindow => "w",
+            Self::ClearScreen => "k",
+            Self::ToggleMode => ".",
+            Self::ZoomIn => "=",
+            Self::ZoomOut => "-",
+            Self::ZoomReset => "0",
+            Self::Settings => ",",
+            Self::RecordMacro => "r",
+            Self::ReplayMacro => "p",
+        }
+    }
+}
+
+/// The key of a `cmd+<key>` chord, lowercased.
+pub fn parse_chord(chord: &str) -> Option<String> {
+    let key = chord.trim().to_lowercase().strip_prefix(CHORD_PREFIX)?.to_string();
+    (key.chars().count() == 1).then_some(key)
+}
+
+pub fn chord(key: &str) -> String {
+    format!("{}{}", CHORD_PREFIX, key)
+}
+
+/// The key bound to `action`, falling back to its default when the
+/// configured chord is missing or malformed.
+pub fn key_for(config: &Config, action: Action) -> String {
+    config
+        .keybinding(action.name())
+        .and_then(parse_chord)
+        .unwrap_or_else(|| action.default_key().to_string())
+}
+
+/// The action bound to Cmd+`key`.
+pub fn lookup(config: &Config, key: &str) -> Option<Action> {
+    let key = key.to_lowercase();
+    Action::ALL.into_iter().find(|a| key_for(config, *a) == key)
+}
+
+/// Key in the `[keybindings]` section for the focus-release chords.
+pub const RELEASE_FOCUS: &str = "release_focus";
+
+const DEFAULT_RELEASE_FOCUS: &str = "ctrl+shift+q";
+
+/// A key with any modifiers, e.g. `ctrl+shift+q` or `cmd+escape`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub cmd: bool,
+    /// A lowercase character, or `escape`, `tab`, `enter` or `backspace`.
+    pub key: String,
+}
+
+impl Chord {
+    pub fn parse(chord: &str) -> Option<Self> {
+        let chord = chord.trim().to_lowercase();
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|k| {
+            k.chars().count() == 1 || matches!(*k, "escape" | "tab" | "enter" | "backspace")
+        })?;
+        let mut parsed = Self { ctrl: false, shift: false, alt: false, cmd: false, key: key.to_string() };
+        for modifier in parts {
+            match modifier {
+                "ctrl" => parsed.ctrl = true,
+                "shift" => parsed.shift = true,
+                "alt" | "opt" => parsed.alt = true,
+                "cmd" => parsed.cmd = true,
+                _ => return None,
+            }
+        }
+        Some(parsed)
+    }
+
+    pub fn matches(&self, key: &Key, modifiers: &Modifiers) -> bool {
+        let pressed = match key {
+            Key::Character(c) => c.to_lowercase(),
+            Key::Named(NamedKey::Escape) => "escape".to_string(),
+            Key::Named(NamedKey::Tab) => "tab".to_string(),
+            Key::Named(NamedKey::Enter) => "enter".to_string(),
+            Key::Named(NamedKey::Backspace) => "backspace".to_string(),
+            _ => return false,
+        };
+        pressed == self.key
+            && modifiers.ctrl == self.ctrl
+            && modifiers.shift == self.shift
+            && modifiers.alt == self.alt
+            && modifiers.meta == self.cmd
+    }
+
+    /// How the chord is shown to the user, e.g. `Ctrl+Shift+Q`.
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.alt, "Opt+"), (self.shift, "Shift+"), (self.cmd, "Cmd+")] {
+            if held {
+                label.push_str(name);
+            }
+        }
+        let mut chars = self.key.chars();
+        if let Some(first) = chars.next() {
+            label.extend(first.to_uppercase());
+            label.push_str(chars.as_str());
+        }
+        label
+    }
+}
+
+/// The chords that release a focused terminal block, falling back to the
+/// default when none of the configured ones parse.
+pub fn release_chords(config: &Config) -> Vec<Chord> {
+    let parse = |list: &str| list.split(',').filter_map(Chord::parse).collect::<Vec<_>>();
+    let chords = config.keybinding(RELEASE_FOCUS).map(parse).unwrap_or_default();
+    if chords.is_empty() { parse(DEFAULT_RELEASE_FOCUS) } else { chords }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexus_kernel::config::{Origin, Setting};
+
+    fn bind(config: &mut Config, action: Action, chord: &str) {
+        let setting = Setting { value: chord.to_string(), origin: Origin::User("/test".into()) };
+        config.keybindings.insert(action.name().to_string(), setting);
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        assert_eq!(parse_chord("cmd+L").as_deref(), Some("l"));
+        assert_eq!(parse_chord("ctrl+l"), None);
+        assert_eq!(parse_chord("cmd+ll"), None);
+    }
+
+    #[test]
+    fn test_rebinding_moves_the_action() {
+        let mut config = Config::default();
+        assert_eq!(lookup(&config, "k"), Some(Action::ClearScreen));
+
+        bind(&mut config, Action::ClearScreen, "cmd+l");
+        assert_eq!(lookup(&config, "L"), Some(Action::ClearScreen));
+        assert_eq!(lookup(&config, "k"), None);
+    }
+
+    #[test]
+    fn test_release_chords() {
+        let mut config = Config::default();
+        let chords = release_chords(&config);
+        assert_eq!(chords.len(), 1);
+        assert_eq!(chords[0].label(), "Ctrl+Shift+Q");
+        let ctrl_shift = Modifiers { shift: true, ctrl: true, alt: false, meta: false };
+        assert!(chords[0].matches(&Key::Character("Q".into()), &ctrl_shift));
+        assert!(!chords[0].matches(&Key::Character("q".into()), &Modifiers::NONE));
+
+        let mut setting = Setting { value: "cmd+escape, ctrl+]".to_string(), origin: Origin::User("/test".into()) };
+        config.keybindings.insert(RELEASE_FOCUS.to_string(), setting.clone());
+        let labels: Vec<_> = release_chords(&config).iter().map(Chord::label).collect();
+        assert_eq!(labels, ["Cmd+Escape", "Ctrl+]"]);
+
+        setting.value = "hyper+q".to_string();
+        config.keybindings.insert(RELEASE_FOCUS.to_string(), setting);
+        assert_eq!(release_chords(&config)[0].label(), "Ctrl+Shift+Q");
+    }
+
+    #[test]
+    fn test_malformed_binding_keeps_default() {
+        let mut config = Config::default();
+        bind(&mut config, Action::Settings, "comma");
+        assert_eq!(key_for(&config, Action::Settings), ",");
+    }
+}
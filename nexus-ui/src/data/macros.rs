@@ -0,0 +1,218 @@
+//! Keyboard macros — input-bar keystrokes recorded under a name and
+//! replayed as if typed again.
+//!
+//! Saved to `~/.nexus/macros.json`. A macro is a list of steps: text typed,
+//! any other key as a chord (`enter`, `ctrl+a`, `up`), and `confirm`
+//! markers, where replay stops until the user says to go on. A marker
+//! belongs before a step that deletes or overwrites something.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use strata::event_context::{Key, KeyEvent, Modifiers, NamedKey};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroStep {
+    /// Characters typed, in order.
+    Text(String),
+    /// Any other key, e.g. `enter` or `ctrl+a`.
+    Key(String),
+    /// Stop replaying until the user confirms.
+    Confirm,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<MacroStep>,
+}
+
+/// Named keys a macro can hold, and how they're written.
+const NAMED_KEYS: [(NamedKey, &str); 14] = [
+    (NamedKey::ArrowUp, "up"),
+    (NamedKey::ArrowDown, "down"),
+    (NamedKey::ArrowLeft, "left"),
+    (NamedKey::ArrowRight, "right"),
+    (NamedKey::Home, "home"),
+    (NamedKey::End, "end"),
+    (NamedKey::PageUp, "pageup"),
+    (NamedKey::PageDown, "pagedown"),
+    (NamedKey::Backspace, "backspace"),
+    (NamedKey::Delete, "delete"),
+    (NamedKey::Enter, "enter"),
+    (NamedKey::Tab, "tab"),
+    (NamedKey::Escape, "escape"),
+    (NamedKey::Space, "space"),
+];
+
+/// Append a key press to `steps`, merging typed characters into the text
+/// step before. Keys a macro can't hold (function keys, bare modifiers)
+/// are dropped.
+pub fn record(steps: &mut Vec<MacroStep>, event: &KeyEvent) {
+    let KeyEvent::Pressed { key, modifiers, text } = event else {
+        return;
+    };
+    let typed = text
+        .as_deref()
+        .filter(|t| !t.is_empty() && !t.chars().any(char::is_control))
+        .filter(|_| !modifiers.ctrl && !modifiers.alt && !modifiers.meta);
+    if let Some(typed) = typed {
+        match steps.last_mut() {
+            Some(MacroStep::Text(text)) => text.push_str(typed),
+            _ => steps.push(MacroStep::Text(typed.to_string())),
+        }
+    } else if let Some(chord) = chord(key, modifiers) {
+        steps.push(MacroStep::Key(chord));
+    }
+}
+
+/// The key presses that replay a text or key step; none for `confirm` or
+/// a malformed chord.
+pub fn key_events(step: &MacroStep) -> Vec<KeyEvent> {
+    match step {
+        MacroStep::Text(text) => text
+            .chars()
+            .map(|c| KeyEvent::Pressed {
+                key: Key::Character(c.to_string()),
+                modifiers: Modifiers::NONE,
+                text: Some(c.to_string()),
+            })
+            .collect(),
+        MacroStep::Key(chord) => parse_chord(chord).into_iter().collect(),
+        MacroStep::Confirm => Vec::new(),
+    }
+}
+
+fn chord(key: &Key, modifiers: &Modifiers) -> Option<String> {
+    let name = match key {
+        Key::Character(c) => c.to_lowercase(),
+        Key::Named(named) => NAMED_KEYS.iter().find(|(k, _)| k == named)?.1.to_string(),
+    };
+    let mut chord = String::new();
+    for (held, prefix) in [(modifiers.ctrl, "ctrl+"), (modifiers.alt, "alt+"), (modifiers.shift, "shift+"), (modifiers.meta, "cmd+")] {
+        if held {
+            chord.push_str(prefix);
+        }
+    }
+    chord.push_str(&name);
+    Some(chord)
+}
+
+fn parse_chord(chord: &str) -> Option<KeyEvent> {
+    let mut parts: Vec<&str> = chord.split('+').collect();
+    // A trailing empty part is the `+` key itself.
+    let name = match parts.pop()? {
+        "" => "+",
+        name => name,
+    };
+    let mut modifiers = Modifiers::NONE;
+    for modifier in parts.into_iter().filter(|m| !m.is_empty()) {
+        match modifier {
+            "ctrl" => modifiers.ctrl = true,
+            "alt" => modifiers.alt = true,
+            "shift" => modifiers.shift = true,
+            "cmd" => modifiers.meta = true,
+            _ => return None,
+        }
+    }
+    let key = match NAMED_KEYS.iter().find(|(_, n)| *n == name) {
+        Some((named, _)) => Key::Named(*named),
+        None if name.chars().count() == 1 => Key::Character(name.to_string()),
+        None => return None,
+    };
+    Some(KeyEvent::Pressed { key, modifiers, text: None })
+}
+
+fn macros_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".nexus").join("macros.json"))
+}
+
+/// Saved macros; none when the file is missing or unreadable.
+pub fn load() -> Vec<Macro> {
+    let Some(path) = macros_path() else {
+        return Vec::new();
+    };
+    match std::fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            tracing::warn!("macros: invalid {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            tracing::warn!("macros: failed to read {}: {}", path.display(), e);
+            Vec::new()
+        }
+    }
+}
+
+pub fn save(macros: &[Macro]) -> anyhow::Result<()> {
+    let path = macros_path().ok_or_else(|| anyhow::anyhow!("HOME is not set"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, serde_json::to_vec_pretty(macros)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: Key, modifiers: Modifiers, text: Option<&str>) -> KeyEvent {
+        KeyEvent::Pressed { key, modifiers, text: text.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_typed_characters_merge() {
+        let mut steps = Vec::new();
+        for c in ["l", "s"] {
+            record(&mut steps, &press(Key::Character(c.into()), Modifiers::NONE, Some(c)));
+        }
+        record(&mut steps, &press(Key::Named(NamedKey::Space), Modifiers::NONE, Some(" ")));
+        record(&mut steps, &press(Key::Character("L".into()), Modifiers { shift: true, ..Modifiers::NONE }, Some("L")));
+        record(&mut steps, &press(Key::Named(NamedKey::Enter), Modifiers::NONE, Some("\r")));
+        record(&mut steps, &press(Key::Character("a".into()), Modifiers { ctrl: true, ..Modifiers::NONE }, Some("\u{1}")));
+        record(&mut steps, &press(Key::Named(NamedKey::F1), Modifiers::NONE, None));
+
+        assert_eq!(steps, vec![
+            MacroStep::Text("ls L".into()),
+            MacroStep::Key("enter".into()),
+            MacroStep::Key("ctrl+a".into()),
+        ]);
+    }
+
+    #[test]
+    fn test_replayed_keys_record_the_same_steps() {
+        let steps = vec![
+            MacroStep::Text("rm -rf build".into()),
+            MacroStep::Key("alt+left".into()),
+            MacroStep::Key("ctrl++".into()),
+            MacroStep::Confirm,
+            MacroStep::Key("enter".into()),
+        ];
+        let mut recorded = Vec::new();
+        for event in steps.iter().flat_map(key_events) {
+            record(&mut recorded, &event);
+        }
+        let expected: Vec<_> = steps.into_iter().filter(|s| *s != MacroStep::Confirm).collect();
+        assert_eq!(recorded, expected);
+    }
+
+    #[test]
+    fn test_malformed_chord_replays_nothing() {
+        assert!(key_events(&MacroStep::Key("hyper+q".into())).is_empty());
+        assert!(key_events(&MacroStep::Key("f13".into())).is_empty());
+    }
+
+    #[test]
+    fn test_steps_round_trip_through_json() {
+        let saved = vec![Macro {
+            name: "clean".into(),
+            steps: vec![MacroStep::Text("make clean".into()), MacroStep::Confirm, MacroStep::Key("enter".into())],
+        }];
+        let json = serde_json::to_string(&saved).unwrap();
+        assert_eq!(json, r#"[{"name":"clean","steps":[{"text":"make clean"},"confirm",{"key":"enter"}]}]"#);
+        assert_eq!(serde_json::from_str::<Vec<Macro>>(&json).unwrap(), saved);
+    }
+}
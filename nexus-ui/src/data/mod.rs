@@ -4,9 +4,13 @@
 
 pub mod blocks;
 pub mod agent_block;
+pub mod context_files;
 pub mod jobs;
 pub mod providers;
+pub mod provider_host;
 pub mod context;
+pub mod keymap;
+pub mod macros;
 
-pub use blocks::{Block, ColumnFilter, ConnectProgress, FileTreeState, Focus, InputMode, ProcSort, PtyEvent, TableFilter, TableSort, UnifiedBlock, UnifiedBlockRef, ViewState};
+pub use blocks::{Block, ColumnFilter, ConnectProgress, DebugPause, FileTreeState, OutputChunk, OutputStream, ReplCell, ReplCellOutput, ReplSession, Restored, Shared, TestTree, Throughput, Focus, InputMode, ProcSort, PtyEvent, TableFilter, TableSort, UnifiedBlock, UnifiedBlockRef, ViewState};
 pub use jobs::{VisualJob, VisualJobState};
@@ -0,0 +1,425 @@
+//! Provider host — runs context providers off the UI thread.
+//!
+//! Each provider gets its own worker thread with a small bounded inbox.
+//! `ProviderHost::dispatch` never blocks: when a provider is still busy and
+//! its inbox is full, the event is dropped for that provider only. Replies
+//! come back as `ProviderUpdate`s on the host's update channel (polled by the
+//! app's subscription) and are folded into `Contributions` on the UI thread.
+//! A provider that panics is disabled for the rest of the session.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nexus_api::BlockId;
+use tokio::sync::{mpsc, Mutex};
+
+use super::providers::{ContextProvider, Contribution, ProviderEvent, ProviderRegistry, Tone};
+
+/// Events queued per provider before further ones are dropped.
+const INBOX_CAPACITY: usize = 4;
+
+/// Provider calls slower than this are logged.
+const SLOW_CALL: Duration = Duration::from_millis(500);
+
+/// A provider's reply to one event.
+#[derive(Debug, Clone)]
+pub struct ProviderUpdate {
+    pub provider: &'static str,
+    /// Working-directory generation the event was dispatched in.
+    pub generation: u64,
+    /// The block a `CommandFinished` reply is about.
+    pub block_id: Option<BlockId>,
+    pub contributions: Vec<Contribution>,
+}
+
+pub type UpdateReceiver = Arc<Mutex<mpsc::UnboundedReceiver<ProviderUpdate>>>;
+
+/// Handle to the provider workers. The default host has no workers and
+/// ignores events (tests, remote sessions before startup).
+#[derive(Clone, Default)]
+pub struct ProviderHost {
+    inner: Option<Arc<HostInner>>,
+}
+
+struct HostInner {
+    workers: Vec<Worker>,
+    updates: UpdateReceiver,
+    generation: AtomicU64,
+}
+
+struct Worker {
+    name: &'static str,
+    inbox: SyncSender<(u64, Arc<ProviderEvent>)>,
+    dropped: AtomicU64,
+}
+
+impl std::fmt::Debug for ProviderHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<&str> = self.inner.iter().flat_map(|i| i.workers.iter().map(|w| w.name)).collect();
+        f.debug_struct("ProviderHost").field("providers", &names).finish()
+    }
+}
+
+impl ProviderHost {
+    /// Start one worker per provider in `registry`.
+    pub fn spawn(registry: &ProviderRegistry) -> Self {
+        Self::with_providers(registry.providers().to_vec())
+    }
+
+    fn with_providers(providers: Vec<Arc<dyn ContextProvider>>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let workers = providers.into_iter().filter_map(|provider| spawn_worker(provider, tx.clone())).collect();
+        Self {
+            inner: Some(Arc::new(HostInner {
+                workers,
+                updates: Arc::new(Mutex::new(rx)),
+                generation: AtomicU64::new(0),
+            })),
+        }
+    }
+
+    /// Receiver for provider replies, if the host is running.
+    pub fn updates(&self) -> Option<UpdateReceiver> {
+        self.inner.as_ref().map(|i| i.updates.clone())
+    }
+
+    /// Hand `event` to every provider without waiting for them. Returns the
+    /// working-directory generation it was dispatched in; `CwdChanged`
+    /// starts a new one.
+    pub fn dispatch(&self, event: ProviderEvent) -> u64 {
+        let Some(inner) = &self.inner else {
+            return 0;
+        };
+        let generation = match event {
+            ProviderEvent::CwdChanged { .. } => inner.generation.fetch_add(1, Ordering::Relaxed) + 1,
+            ProviderEvent::CommandFinished { .. } => inner.generation.load(Ordering::Relaxed),
+        };
+        let event = Arc::new(event);
+        for worker in &inner.workers {
+            match worker.inbox.try_send((generation, event.clone())) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    let dropped = worker.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    tracing::warn!("provider {}: busy, dropped event ({} so far)", worker.name, dropped);
+                }
+                // The worker stopped after a panic.
+                Err(TrySendError::Disconnected(_)) => {}
+            }
+        }
+        generation
+    }
+}
+
+fn spawn_worker(
+    provider: Arc<dyn ContextProvider>,
+    updates: mpsc::UnboundedSender<ProviderUpdate>,
+) -> Option<Worker> {
+    let name = provider.name();
+    let (inbox, events) = std::sync::mpsc::sync_channel::<(u64, Arc<ProviderEvent>)>(INBOX_CAPACITY);
+    let spawned = std::thread::Builder::new()
+        .name(format!("provider-{}", name))
+        .spawn(move || {
+            for (generation, event) in events {
+                let started = Instant::now();
+                let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    if provider.applies_to(event.project()) {
+                        provider.on_event(&event)
+                    } else {
+                        Vec::new()
+                    }
+                }));
+                let elapsed = started.elapsed();
+                if elapsed > SLOW_CALL {
+                    tracing::warn!("provider {}: event took {:?}", name, elapsed);
+                }
+                let Ok(contributions) = result else {
+                    tracing::error!("provider {}: panicked, disabled for this session", name);
+                    return;
+                };
+                if contributions.is_empty() {
+                    continue;
+                }
+                let update = ProviderUpdate { provider: name, generation, block_id: event.block_id(), contributions };
+                if updates.send(update).is_err() {
+                    return;
+                }
+            }
+        });
+    match spawned {
+        Ok(_) => Some(Worker { name, inbox, dropped: AtomicU64::new(0) }),
+        Err(e) => {
+            tracing::warn!("provider {}: failed to start worker: {}", name, e);
+            None
+        }
+    }
+}
+
+// =============================================================================
+// Contributions
+// =============================================================================
+
+/// A provider's label on a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub provider: &'static str,
+    pub label: String,
+    pub tone: Tone,
+}
+
+/// Everything providers have contributed, as seen by the UI.
+#[derive(Debug, Clone, Default)]
+pub struct Contributions {
+    generation: u64,
+    /// Segments, completions, palette actions and warnings for the current
+    /// working directory, by provider (sorted, so rendering order is stable).
+    session: BTreeMap<&'static str, Vec<Contribution>>,
+    annotations: HashMap<BlockId, Vec<Annotation>>,
+    /// Ids of the warnings dismissed since the directory changed.
+    dismissed: HashSet<String>,
+}
+
+impl Contributions {
+    /// Forget what was contributed for the previous working directory.
+    pub fn reset(&mut self, generation: u64) {
+        self.generation = generation;
+        self.session.clear();
+        self.dismissed.clear();
+    }
+
+    /// Fold in a provider's reply.
+    pub fn apply(&mut self, update: ProviderUpdate) {
+        let (annotations, rest): (Vec<_>, Vec<_>) = update
+            .contributions
+            .into_iter()
+            .partition(|c| matches!(c, Contribution::BlockAnnotation { .. }));
+
+        if let Some(block_id) = update.block_id {
+            let labels = self.annotations.entry(block_id).or_default();
+            labels.retain(|a| a.provider != update.provider);
+            labels.extend(annotations.into_iter().filter_map(|c| match c {
+                Contribution::BlockAnnotation { label, tone } => {
+                    Some(Annotation { provider: update.provider, label, tone })
+                }
+                _ => None,
+            }));
+        }
+
+        // Replies from before the last directory change describe another
+        // directory.
+        if update.generation < self.generation || (update.block_id.is_some() && rest.is_empty()) {
+            return;
+        }
+        self.session.insert(update.provider, rest);
+    }
+
+    /// Drop all block annotations (the blocks were cleared).
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+    }
+
+    pub fn prompt_segments(&self) -> impl Iterator<Item = (&str, Tone)> {
+        self.session.values().flatten().filter_map(|c| match c {
+            Contribution::PromptSegment { text, tone } => Some((text.as_str(), *tone)),
+            _ => None,
+        })
+    }
+
+    /// Candidates for the word after `prefix`, the input up to that word.
+    pub fn completions<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        let prefix = prefix.trim();
+        self.session.values().flatten().flat_map(move |c| match c {
+            Contribution::Completions { command, candidates } if command.trim() == prefix => {
+                candidates.as_slice()
+            }
+            _ => &[][..],
+        })
+        .map(String::as_str)
+    }
+
+    pub fn annotations(&self, block_id: BlockId) -> &[Annotation] {
+        self.annotations.get(&block_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// `(label, command)` pairs for the input's context menu.
+    pub fn palette_actions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.session.values().flatten().filter_map(|c| match c {
+            Contribution::PaletteAction { label, command } => Some((label.as_str(), command.as_str())),
+            _ => None,
+        })
+    }
+
+    /// `(path, name, current)` of the repository's worktrees, when it has
+    /// more than one.
+    pub fn worktrees(&self) -> impl Iterator<Item = (&Path, &str, bool)> {
+        self.session.values().flatten().filter_map(|c| match c {
+            Contribution::Worktree { path, name, current } => Some((path.as_path(), name.as_str(), *current)),
+            _ => None,
+        })
+    }
+
+    /// The name of the worktree the directory is in, when it has siblings.
+    pub fn current_worktree(&self) -> Option<&str> {
+        self.worktrees().find(|(_, _, current)| *current).map(|(_, name, _)| name)
+    }
+
+    /// `(id, text)` of the warnings not dismissed.
+    pub fn warnings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.session.values().flatten().filter_map(|c| match c {
+            Contribution::Warning { id, text } if !self.dismissed.contains(id) => Some((id.as_str(), text.as_str())),
+            _ => None,
+        })
+    }
+
+    /// Hide the warning `id` until the directory changes.
+    pub fn dismiss_warning(&mut self, id: &str) {
+        self.dismissed.insert(id.to_string());
+    }
+
+    /// `(label, command)` pairs for the current project's tasks.
+    pub fn project_tasks(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.session.values().flatten().filter_map(|c| match c {
+            Contribution::ProjectTask { label, command } => Some((label.as_str(), command.as_str())),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::context::ProjectContext;
+    use crate::data::providers::ParsedError;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicBool;
+
+    fn segment(text: &str) -> Contribution {
+        Contribution::PromptSegment { text: text.into(), tone: Tone::Info }
+    }
+
+    fn update(generation: u64, block_id: Option<BlockId>, contributions: Vec<Contribution>) -> ProviderUpdate {
+        ProviderUpdate { provider: "test", generation, block_id, contributions }
+    }
+
+    fn cwd_event() -> ProviderEvent {
+        ProviderEvent::CwdChanged { cwd: PathBuf::from("/tmp"), project: None }
+    }
+
+    #[test]
+    fn test_stale_replies_are_ignored() {
+        let mut store = Contributions::default();
+        store.reset(2);
+        store.apply(update(1, None, vec![segment("old")]));
+        assert_eq!(store.prompt_segments().count(), 0);
+
+        store.apply(update(2, None, vec![segment("main")]));
+        assert_eq!(store.prompt_segments().collect::<Vec<_>>(), vec![("main", Tone::Info)]);
+    }
+
+    #[test]
+    fn test_annotation_only_reply_keeps_session() {
+        let mut store = Contributions::default();
+        store.apply(update(0, None, vec![segment("main")]));
+        store.apply(update(
+            0,
+            Some(BlockId(7)),
+            vec![Contribution::BlockAnnotation { label: "3 tests failed".into(), tone: Tone::Error }],
+        ));
+
+        assert_eq!(store.prompt_segments().count(), 1);
+        assert_eq!(store.annotations(BlockId(7))[0].label, "3 tests failed");
+        assert!(store.annotations(BlockId(8)).is_empty());
+    }
+
+    #[test]
+    fn test_completions_match_command_prefix() {
+        let mut store = Contributions::default();
+        store.apply(update(
+            0,
+            None,
+            vec![Contribution::Completions { command: "npm run".into(), candidates: vec!["build".into()] }],
+        ));
+        assert_eq!(store.completions("npm run ").collect::<Vec<_>>(), vec!["build"]);
+        assert_eq!(store.completions("npm ").count(), 0);
+    }
+
+    #[test]
+    fn test_dismissed_warnings_return_in_a_new_directory() {
+        let warning = || Contribution::Warning { id: "env-file".into(), text: "No .env file".into() };
+        let mut store = Contributions::default();
+        store.apply(update(0, None, vec![warning()]));
+        store.dismiss_warning("env-file");
+        assert_eq!(store.warnings().count(), 0);
+
+        store.reset(1);
+        store.apply(update(1, None, vec![warning()]));
+        assert_eq!(store.warnings().collect::<Vec<_>>(), vec![("env-file", "No .env file")]);
+    }
+
+    struct Sleepy(Arc<AtomicBool>);
+
+    impl ContextProvider for Sleepy {
+        fn name(&self) -> &'static str {
+            "sleepy"
+        }
+        fn applies_to(&self, _: Option<&ProjectContext>) -> bool {
+            true
+        }
+        fn parse_error(&self, _: &str, _: &str, _: Option<&ProjectContext>) -> Option<ParsedError> {
+            None
+        }
+        fn on_event(&self, _: &ProviderEvent) -> Vec<Contribution> {
+            while !self.0.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            vec![segment("done")]
+        }
+    }
+
+    struct Panicky;
+
+    impl ContextProvider for Panicky {
+        fn name(&self) -> &'static str {
+            "panicky"
+        }
+        fn applies_to(&self, _: Option<&ProjectContext>) -> bool {
+            true
+        }
+        fn parse_error(&self, _: &str, _: &str, _: Option<&ProjectContext>) -> Option<ParsedError> {
+            None
+        }
+        fn on_event(&self, _: &ProviderEvent) -> Vec<Contribution> {
+            panic!("provider bug");
+        }
+    }
+
+    #[test]
+    fn test_slow_provider_does_not_block_dispatch() {
+        let release = Arc::new(AtomicBool::new(false));
+        let host = ProviderHost::with_providers(vec![Arc::new(Sleepy(release.clone()))]);
+
+        let started = Instant::now();
+        for _ in 0..(INBOX_CAPACITY + 4) {
+            host.dispatch(cwd_event());
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let dropped = host.inner.as_ref().unwrap().workers[0].dropped.load(Ordering::Relaxed);
+        assert!(dropped >= 3, "expected dropped events, got {}", dropped);
+
+        release.store(true, Ordering::Relaxed);
+        let update = host.updates().unwrap().blocking_lock().blocking_recv().unwrap();
+        assert_eq!(update.provider, "sleepy");
+    }
+
+    #[test]
+    fn test_panicking_provider_is_isolated() {
+        let host = ProviderHost::with_providers(vec![Arc::new(Panicky), Arc::new(Sleepy(Arc::new(AtomicBool::new(true))))]);
+        host.dispatch(cwd_event());
+        let update = host.updates().unwrap().blocking_lock().blocking_recv().unwrap();
+        assert_eq!(update.provider, "sleepy");
+    }
+}
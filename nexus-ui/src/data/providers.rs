@@ -1,10 +1,26 @@
 //! Context providers — modular error parsing and context enrichment.
 //!
 //! Each provider handles a specific domain (Node, Rust, Python, System)
-//! via the `ContextProvider` trait.
+//! via the `ContextProvider` trait. Providers can also react to lifecycle
+//! events (see `ContextProvider::on_event`) and contribute prompt segments,
+//! completions, block annotations, palette actions, project tasks,
+//! warnings and sibling worktrees; those calls run off the UI thread in `provider_host`.
+//!
+//! Third-party providers are added with [`register_provider`] before a
+//! window opens.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use nexus_api::BlockId;
+use nexus_kernel::project_tasks::{self, Task};
 
 use super::context::{ProjectContext, ProjectKind};
 
+/// Providers registered by embedders and plugins, after the built-ins.
+static EXTERNAL: LazyLock<RwLock<Vec<Arc<dyn ContextProvider>>>> = LazyLock::new(Default::default);
+
 // =============================================================================
 // Core Types
 // =============================================================================
@@ -48,6 +64,76 @@ pub struct Suggestion {
     pub command: String,
 }
 
+/// A lifecycle event delivered to every provider.
+#[derive(Debug, Clone)]
+pub enum ProviderEvent {
+    /// The working directory changed (also sent once at startup).
+    CwdChanged {
+        cwd: PathBuf,
+        project: Option<ProjectContext>,
+    },
+    /// A shell command finished.
+    CommandFinished {
+        block_id: BlockId,
+        command: String,
+        /// Tail of the output, as used for error parsing.
+        output: String,
+        exit_code: i32,
+        cwd: PathBuf,
+        project: Option<ProjectContext>,
+    },
+}
+
+impl ProviderEvent {
+    pub fn project(&self) -> Option<&ProjectContext> {
+        match self {
+            Self::CwdChanged { project, .. } | Self::CommandFinished { project, .. } => project.as_ref(),
+        }
+    }
+
+    /// The block a `CommandFinished` event is about.
+    pub fn block_id(&self) -> Option<BlockId> {
+        match self {
+            Self::CwdChanged { .. } => None,
+            Self::CommandFinished { block_id, .. } => Some(*block_id),
+        }
+    }
+}
+
+/// How a contributed label should be colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tone {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// Something a provider adds to the UI in reply to an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Contribution {
+    /// Short text shown in the prompt after the working directory.
+    PromptSegment { text: String, tone: Tone },
+    /// Candidates for the word after `command` (e.g. `"npm run"`).
+    Completions { command: String, candidates: Vec<String> },
+    /// Label on the finished command's block. Ignored outside
+    /// `CommandFinished` replies.
+    BlockAnnotation { label: String, tone: Tone },
+    /// A command offered in the input's context menu.
+    PaletteAction { label: String, command: String },
+    /// A task of the current project (make target, npm script, ...),
+    /// listed in its own section of the input's context menu.
+    ProjectTask { label: String, command: String },
+    /// A problem with the directory just entered (wrong Node version,
+    /// missing `.env`), shown above the input until dismissed. `id` (e.g.
+    /// `"node-version"`) names it in a project's `[warnings]` config.
+    Warning { id: String, text: String },
+    /// A worktree of the repository the directory is in, listed when it
+    /// has more than one. `current` marks the one the directory is in.
+    Worktree { path: PathBuf, name: String, current: bool },
+}
+
 // =============================================================================
 // Provider Trait
 // =============================================================================
@@ -77,6 +163,23 @@ pub trait ContextProvider: Send + Sync {
         let _ = project;
         None
     }
+
+    /// React to a lifecycle event. Only called when `applies_to` accepts
+    /// the event's project.
+    ///
+    /// Runs on this provider's own worker thread, so it may block (read
+    /// files, run git). A reply replaces the provider's previous prompt
+    /// segments, completions, palette actions and warnings; a
+    /// `CommandFinished` reply without any of those leaves them as they were.
+    fn on_event(&self, event: &ProviderEvent) -> Vec<Contribution> {
+        let _ = event;
+        Vec::new()
+    }
+}
+
+/// Add a provider to every registry created from now on.
+pub fn register_provider(provider: Arc<dyn ContextProvider>) {
+    EXTERNAL.write().unwrap().push(provider);
 }
 
 // =============================================================================
@@ -85,7 +188,7 @@ pub trait ContextProvider: Send + Sync {
 
 /// Registry of all context providers.
 pub struct ProviderRegistry {
-    providers: Vec<Box<dyn ContextProvider>>,
+    providers: Vec<Arc<dyn ContextProvider>>,
 }
 
 impl Default for ProviderRegistry {
@@ -95,16 +198,25 @@ impl Default for ProviderRegistry {
 }
 
 impl ProviderRegistry {
-    /// Create a new registry with all built-in providers.
+    /// Create a new registry with all built-in providers, followed by
+    /// registered third-party ones.
     pub fn new() -> Self {
-        Self {
-            providers: vec![
-                Box::new(SystemProvider),
-                Box::new(NodeProvider),
-                Box::new(PythonProvider),
-                Box::new(RustProvider),
-            ],
-        }
+        let mut providers: Vec<Arc<dyn ContextProvider>> = vec![
+            Arc::new(SystemProvider),
+            Arc::new(NodeProvider),
+            Arc::new(PythonProvider),
+            Arc::new(RustProvider),
+            Arc::new(TasksProvider),
+            Arc::new(WorkspaceProvider),
+            Arc::new(WorktreeProvider),
+        ];
+        providers.extend(EXTERNAL.read().unwrap().iter().cloned());
+        Self { providers }
+    }
+
+    /// All providers, in priority order.
+    pub fn providers(&self) -> &[Arc<dyn ContextProvider>] {
+        &self.providers
     }
 
     /// Analyze command output using all applicable providers.
@@ -271,6 +383,51 @@ impl ContextProvider for NodeProvider {
         }
         None
     }
+
+    fn on_event(&self, event: &ProviderEvent) -> Vec<Contribution> {
+        let ProviderEvent::CwdChanged { project: Some(p), .. } = event else {
+            return Vec::new();
+        };
+        let mut contributions = Vec::new();
+        if !p.scripts.is_empty() {
+            contributions.push(Contribution::Completions {
+                command: "npm run".into(),
+                candidates: p.scripts.clone(),
+            });
+        }
+        contributions.extend(node_version_warning(&p.root));
+        contributions
+    }
+}
+
+/// A warning when the project's `.nvmrc` (or `.node-version`) asks for a
+/// different Node than the one on `PATH`.
+fn node_version_warning(root: &Path) -> Option<Contribution> {
+    let (file, wanted) = [".nvmrc", ".node-version"].into_iter().find_map(|name| {
+        let text = std::fs::read_to_string(root.join(name)).ok()?;
+        Some((name, text.trim().to_string()))
+    })?;
+    let output = Command::new("node").arg("--version").output().ok().filter(|o| o.status.success())?;
+    let active = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if node_version_matches(&wanted, &active) {
+        return None;
+    }
+    Some(Contribution::Warning {
+        id: "node-version".into(),
+        text: format!("Node {} is active but {} asks for {}", active, file, wanted),
+    })
+}
+
+/// Whether `active` (`v20.11.0`) is the version an `.nvmrc` asks for,
+/// written in full or in part (`20`, `v20.11`). Aliases such as `lts/*`
+/// can't be checked here and always match.
+fn node_version_matches(wanted: &str, active: &str) -> bool {
+    let wanted = wanted.trim_start_matches('v');
+    if !wanted.starts_with(|c: char| c.is_ascii_digit()) {
+        return true;
+    }
+    let active: Vec<&str> = active.trim_start_matches('v').split('.').collect();
+    wanted.split('.').enumerate().all(|(i, part)| active.get(i) == Some(&part))
 }
 
 // =============================================================================
@@ -378,6 +535,230 @@ impl ContextProvider for RustProvider {
     }
 }
 
+// =============================================================================
+// Project Tasks Provider
+// =============================================================================
+
+/// Longest task description shown in a menu label.
+const TASK_DESCRIPTION_CHARS: usize = 40;
+
+/// Lists the Makefile targets, just recipes, npm scripts and cargo
+/// binaries of the working directory's project.
+pub struct TasksProvider;
+
+impl ContextProvider for TasksProvider {
+    fn name(&self) -> &'static str {
+        "tasks"
+    }
+
+    fn applies_to(&self, _project: Option<&ProjectContext>) -> bool {
+        // A Makefile or justfile is a project of its own.
+        true
+    }
+
+    fn parse_error(
+        &self,
+        _command: &str,
+        _output: &str,
+        _project: Option<&ProjectContext>,
+    ) -> Option<ParsedError> {
+        None
+    }
+
+    fn on_event(&self, event: &ProviderEvent) -> Vec<Contribution> {
+        let ProviderEvent::CwdChanged { cwd, .. } = event else {
+            return Vec::new();
+        };
+        project_tasks::discover(cwd).iter().map(task_contribution).collect()
+    }
+}
+
+/// `make build — Compile everything`, with long descriptions cut short.
+fn task_contribution(task: &Task) -> Contribution {
+    let command = task.command_line();
+    let label = match task.description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(description) if description.chars().count() > TASK_DESCRIPTION_CHARS => {
+            let short: String = description.chars().take(TASK_DESCRIPTION_CHARS - 1).collect();
+            format!("{} \u{2014} {}\u{2026}", command, short.trim_end())
+        }
+        Some(description) => format!("{} \u{2014} {}", command, description),
+        None => command.clone(),
+    };
+    Contribution::ProjectTask { label, command }
+}
+
+// =============================================================================
+// Workspace Provider (.env, submodules)
+// =============================================================================
+
+/// Files whose presence means a project expects a `.env` next to them.
+const ENV_TEMPLATES: [&str; 3] = [".env.example", ".env.sample", ".env.template"];
+
+/// Warns about setup a checkout is missing, whatever the project type: a
+/// `.env` its template calls for, and submodules not at their recorded
+/// commits.
+pub struct WorkspaceProvider;
+
+impl ContextProvider for WorkspaceProvider {
+    fn name(&self) -> &'static str {
+        "workspace"
+    }
+
+    fn applies_to(&self, _project: Option<&ProjectContext>) -> bool {
+        true
+    }
+
+    fn parse_error(
+        &self,
+        _command: &str,
+        _output: &str,
+        _project: Option<&ProjectContext>,
+    ) -> Option<ParsedError> {
+        None
+    }
+
+    fn on_event(&self, event: &ProviderEvent) -> Vec<Contribution> {
+        let ProviderEvent::CwdChanged { cwd, project } = event else {
+            return Vec::new();
+        };
+        let root = project.as_ref().map_or(cwd.as_path(), |p| p.root.as_path());
+        env_file_warning(root).into_iter().chain(submodules_warning(cwd)).collect()
+    }
+}
+
+fn env_file_warning(root: &Path) -> Option<Contribution> {
+    if root.join(".env").exists() {
+        return None;
+    }
+    let template = ENV_TEMPLATES.into_iter().find(|name| root.join(name).is_file())?;
+    Some(Contribution::Warning {
+        id: "env-file".into(),
+        text: format!("No .env file; {} shows what it needs", template),
+    })
+}
+
+fn submodules_warning(cwd: &Path) -> Option<Contribution> {
+    let root = cwd.ancestors().find(|dir| dir.join(".git").exists())?;
+    if !root.join(".gitmodules").is_file() {
+        return None;
+    }
+    let output = Command::new("git").args(["submodule", "status"]).current_dir(root).output().ok()?;
+    let status = String::from_utf8_lossy(&output.stdout);
+    let stale = stale_submodules(&status);
+    let text = match stale[..] {
+        [] => return None,
+        [path] => format!("Submodule {} is not at its recorded commit (git submodule update --init)", path),
+        _ => format!(
+            "{} submodules are not at their recorded commits: {} (git submodule update --init)",
+            stale.len(),
+            stale.join(", ")
+        ),
+    };
+    Some(Contribution::Warning { id: "submodules".into(), text })
+}
+
+/// Paths `git submodule status` marks as not initialized (`-`), checked
+/// out at another commit (`+`) or conflicted (`U`).
+fn stale_submodules(status: &str) -> Vec<&str> {
+    status
+        .lines()
+        .filter(|line| line.starts_with(['-', '+', 'U']))
+        .filter_map(|line| line[1..].split_whitespace().nth(1))
+        .collect()
+}
+
+// =============================================================================
+// Worktree Provider (git worktrees)
+// =============================================================================
+
+/// A checkout listed by `git worktree list`.
+#[derive(Debug, Clone, PartialEq)]
+struct Worktree {
+    path: PathBuf,
+    /// `main` for `refs/heads/main`; None when detached.
+    branch: Option<String>,
+}
+
+impl Worktree {
+    /// Its branch, or its directory's name when detached.
+    fn name(&self) -> String {
+        self.branch.clone().unwrap_or_else(|| {
+            self.path.file_name().map_or_else(|| self.path.display().to_string(), |n| n.to_string_lossy().into_owned())
+        })
+    }
+}
+
+/// Lists the worktrees of the repository the directory is in, so the
+/// palette can open a window in a sibling and blocks can be told apart by
+/// the branch they ran on. Repositories with one worktree list nothing.
+pub struct WorktreeProvider;
+
+impl ContextProvider for WorktreeProvider {
+    fn name(&self) -> &'static str {
+        "worktree"
+    }
+
+    fn applies_to(&self, _project: Option<&ProjectContext>) -> bool {
+        true
+    }
+
+    fn parse_error(
+        &self,
+        _command: &str,
+        _output: &str,
+        _project: Option<&ProjectContext>,
+    ) -> Option<ParsedError> {
+        None
+    }
+
+    fn on_event(&self, event: &ProviderEvent) -> Vec<Contribution> {
+        let ProviderEvent::CwdChanged { cwd, .. } = event else {
+            return Vec::new();
+        };
+        if !cwd.ancestors().any(|dir| dir.join(".git").exists()) {
+            return Vec::new();
+        }
+        let Ok(output) = Command::new("git").args(["worktree", "list", "--porcelain"]).current_dir(cwd).output() else {
+            return Vec::new();
+        };
+        let worktrees = parse_worktrees(&String::from_utf8_lossy(&output.stdout));
+        if worktrees.len() < 2 {
+            return Vec::new();
+        }
+        let cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.clone());
+        let current = worktrees
+            .iter()
+            .filter(|w| cwd.starts_with(&w.path))
+            .max_by_key(|w| w.path.components().count())
+            .map(|w| w.path.clone());
+        worktrees
+            .into_iter()
+            .map(|w| Contribution::Worktree { name: w.name(), current: current.as_ref() == Some(&w.path), path: w.path })
+            .collect()
+    }
+}
+
+/// The checked-out worktrees in `git worktree list --porcelain` output;
+/// bare repositories and ones whose directory is gone are left out.
+fn parse_worktrees(porcelain: &str) -> Vec<Worktree> {
+    porcelain
+        .split("\n\n")
+        .filter_map(|entry| {
+            let mut lines = entry.lines();
+            let path = lines.next()?.strip_prefix("worktree ")?;
+            let mut branch = None;
+            for line in lines {
+                match line.split_once(' ').unwrap_or((line, "")) {
+                    ("bare", _) | ("prunable", _) => return None,
+                    ("branch", name) => branch = Some(name.strip_prefix("refs/heads/").unwrap_or(name).to_string()),
+                    _ => {}
+                }
+            }
+            Some(Worktree { path: PathBuf::from(path), branch })
+        })
+        .collect()
+}
+
 // =============================================================================
 // Extraction Helpers
 // =============================================================================
@@ -611,6 +992,70 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_node_contributes_script_completions() {
+        let event = ProviderEvent::CwdChanged {
+            cwd: PathBuf::from("/tmp"),
+            project: Some(node_project()),
+        };
+        assert_eq!(
+            NodeProvider.on_event(&event),
+            vec![Contribution::Completions {
+                command: "npm run".into(),
+                candidates: vec!["start".into(), "test".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_node_version_matches() {
+        assert!(node_version_matches("20", "v20.11.0"));
+        assert!(node_version_matches("v20.11", "v20.11.0"));
+        assert!(node_version_matches("lts/*", "v18.0.0"));
+        assert!(!node_version_matches("18", "v20.11.0"));
+        assert!(!node_version_matches("20.10", "v20.11.0"));
+    }
+
+    #[test]
+    fn test_stale_submodules() {
+        let status = " 1a2b3c vendor/ok (v1.0)\n+4d5e6f vendor/moved (v1.1)\n-7a8b9c vendor/missing\n";
+        assert_eq!(stale_submodules(status), vec!["vendor/moved", "vendor/missing"]);
+    }
+
+    #[test]
+    fn test_parse_worktrees() {
+        let porcelain = "worktree /src/app\nHEAD 1a2b3c\nbranch refs/heads/main\n\n\
+            worktree /src/app-fix\nHEAD 4d5e6f\nbranch refs/heads/fix/login\n\n\
+            worktree /src/app-bisect\nHEAD 7a8b9c\ndetached\n\n\
+            worktree /src/app-gone\nHEAD 7a8b9c\ndetached\nprunable gitdir file points to non-existent location\n\n";
+        let worktrees = parse_worktrees(porcelain);
+        let names: Vec<String> = worktrees.iter().map(Worktree::name).collect();
+        assert_eq!(names, vec!["main", "fix/login", "app-bisect"]);
+        assert_eq!(worktrees[1].path, PathBuf::from("/src/app-fix"));
+        assert!(parse_worktrees("worktree /src/app.git\nbare\n").is_empty());
+    }
+
+    #[test]
+    fn test_task_contribution_labels() {
+        let task = |description: Option<&str>| Task {
+            tool: project_tasks::TaskTool::Make,
+            name: "build".into(),
+            description: description.map(str::to_string),
+        };
+        assert_eq!(
+            task_contribution(&task(Some("Compile everything"))),
+            Contribution::ProjectTask { label: "make build \u{2014} Compile everything".into(), command: "make build".into() }
+        );
+        assert_eq!(
+            task_contribution(&task(None)),
+            Contribution::ProjectTask { label: "make build".into(), command: "make build".into() }
+        );
+        let Contribution::ProjectTask { label, .. } = task_contribution(&task(Some(&"x".repeat(100)))) else {
+            unreachable!()
+        };
+        assert!(label.ends_with('\u{2026}') && label.chars().count() < 60);
+    }
+
     #[test]
     fn test_context_prompt() {
         let registry = ProviderRegistry::new();
@@ -0,0 +1,135 @@
+//! Citations in agent responses.
+//!
+//! The shell context asks the agent to cite what it was given as
+//! `[[block:ID]]` for a command's output and `[[file:PATH:LINE]]` for a
+//! file (the line is optional). Responses render each citation as a small
+//! button: a block citation scrolls to the block, a file citation opens the
+//! file in the editor at the line.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use nexus_api::BlockId;
+
+/// How the agent is told to cite, for the shell context.
+pub const CONVENTION: &str =
+    "cite a command's output as [[block:ID]] and a file as [[file:PATH:LINE]] (LINE optional); they become links";
+
+/// Something a response refers to.
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub enum Citation {
+    Block(BlockId),
+    File { path: String, line: Option<u32> },
+}
+
+impl Citation {
+    /// Parse the inside of `[[...]]`.
+    fn parse(inner: &str) -> Option<Self> {
+        if let Some(id) = inner.strip_prefix("block:") {
+            return id.trim().parse().ok().map(|id| Self::Block(BlockId(id)));
+        }
+        let target = inner.strip_prefix("file:")?.trim();
+        let (path, line) = match target.rsplit_once(':') {
+            Some((path, line)) if !line.is_empty() && line.bytes().all(|b| b.is_ascii_digit()) => {
+                (path, line.parse().ok())
+            }
+            _ => (target, None),
+        };
+        (!path.is_empty()).then(|| Self::File { path: path.to_string(), line })
+    }
+
+    /// Button text: the block number, or the file name and line.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Block(id) => format!("#{}", id.0),
+            Self::File { path, line } => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                match line {
+                    Some(line) => format!("{}:{}", name, line),
+                    None => name.to_string(),
+                }
+            }
+        }
+    }
+
+    /// Identifies the citation within a response, for its button's id.
+    /// Citing the same thing twice gives the same key.
+    pub fn key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A piece of response text.
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Cite(Citation),
+}
+
+/// Split `text` into plain text and citations. Brackets that don't hold a
+/// citation stay text.
+pub fn split(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    let mut plain_start = 0;
+    let mut offset = 0;
+    while let Some(open) = rest.find("[[") {
+        let after = &rest[open + 2..];
+        let Some(close) = after.find("]]") else { break };
+        let consumed = open + 2 + close + 2;
+        if let Some(citation) = Citation::parse(&after[..close]) {
+            if plain_start < offset + open {
+                segments.push(Segment::Text(&text[plain_start..offset + open]));
+            }
+            segments.push(Segment::Cite(citation));
+            plain_start = offset + consumed;
+            offset += consumed;
+            rest = &rest[consumed..];
+        } else {
+            offset += open + 2;
+            rest = after;
+        }
+    }
+    if plain_start < text.len() {
+        segments.push(Segment::Text(&text[plain_start..]));
+    }
+    segments
+}
+
+/// Every citation in `text`, in order.
+pub fn find_all(text: &str) -> impl Iterator<Item = Citation> + '_ {
+    split(text).into_iter().filter_map(|segment| match segment {
+        Segment::Cite(citation) => Some(citation),
+        Segment::Text(_) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_citations() {
+        let segments = split("See [[block:42]] and [[file:src/main.rs:17]], not [[other]] or [x].");
+        assert_eq!(
+            segments,
+            [
+                Segment::Text("See "),
+                Segment::Cite(Citation::Block(BlockId(42))),
+                Segment::Text(" and "),
+                Segment::Cite(Citation::File { path: "src/main.rs".to_string(), line: Some(17) }),
+                Segment::Text(", not [[other]] or [x]."),
+            ]
+        );
+        assert_eq!(split("[[file:C:notes.txt]]"), [Segment::Cite(Citation::File { path: "C:notes.txt".to_string(), line: None })]);
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(Citation::Block(BlockId(7)).label(), "#7");
+        let file = Citation::File { path: "src/app/mod.rs".to_string(), line: Some(3) };
+        assert_eq!(file.label(), "mod.rs:3");
+        assert_eq!(file.key(), file.clone().key());
+    }
+}
@@ -0,0 +1,232 @@
+//! Whether the Claude CLI can run Nexus's queries, checked before one is
+//! spawned.
+//!
+//! A missing, outdated or broken CLI shows as a setup panel in the agent
+//! block (how to install or upgrade, and a button to check again) rather
+//! than as the raw error from spawning it. A working CLI is remembered so
+//! later queries don't pay for `claude --version`; [`recheck`] forgets it.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How to install the CLI, or upgrade it.
+pub const INSTALL_COMMAND: &str = "npm install -g @anthropic-ai/claude-code";
+
+/// The oldest CLI with everything [`super::ClaudeCli::spawn`] passes it:
+/// `--permission-prompt-tool`, `--append-system-prompt` and the
+/// `stream-json` output format.
+pub const MIN_VERSION: CliVersion = CliVersion(1, 0, 0);
+
+/// How long `claude --version` may take.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The last check, when it found a working CLI.
+static READY: Mutex<Option<CliStatus>> = Mutex::new(None);
+
+/// A CLI version, `major.minor.patch`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CliVersion(pub u32, pub u32, pub u32);
+
+impl CliVersion {
+    /// The version in `claude --version` output, e.g. `1.0.33 (Claude Code)`.
+    pub fn parse(output: &str) -> Option<Self> {
+        let word = output
+            .split_whitespace()
+            .map(|w| w.trim_start_matches('v'))
+            .find(|w| w.starts_with(|c: char| c.is_ascii_digit()))?;
+        let mut parts = word.split(['.', '-', '+']).map(|p| p.parse::<u32>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next()?.ok()?;
+        let patch = parts.next().and_then(Result::ok).unwrap_or(0);
+        Some(Self(major, minor, patch))
+    }
+}
+
+impl std::fmt::Display for CliVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// What a check found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliStatus {
+    Ready { path: PathBuf, version: CliVersion },
+    /// No `claude` on `PATH`.
+    Missing,
+    /// Older than [`MIN_VERSION`].
+    Outdated { path: PathBuf, version: CliVersion },
+    /// `claude --version` failed, timed out or printed no version.
+    Broken { path: PathBuf, error: String },
+}
+
+impl CliStatus {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready { .. })
+    }
+
+    /// The setup panel's heading.
+    pub fn title(&self) -> String {
+        match self {
+            Self::Ready { version, .. } => format!("Claude CLI {} is ready", version),
+            Self::Missing => "Claude CLI not found".to_string(),
+            Self::Outdated { version, .. } => format!("Claude CLI {} is too old", version),
+            Self::Broken { .. } => "Claude CLI is not working".to_string(),
+        }
+    }
+
+    /// What was found, and what to do about it.
+    pub fn detail(&self) -> String {
+        match self {
+            Self::Ready { path, .. } => format!("Found at {}. Run the query again to send it.", path.display()),
+            Self::Missing => "The agent runs queries through the `claude` command, which isn't on PATH. \
+                Install it, or add its directory to PATH, then check again."
+                .to_string(),
+            Self::Outdated { path, .. } => {
+                format!("{} is older than {}, the oldest version Nexus works with. Upgrade it, then check again.", path.display(), MIN_VERSION)
+            }
+            Self::Broken { path, error } => {
+                format!("`{} --version` failed: {}. Reinstall it, then check again.", path.display(), error)
+            }
+        }
+    }
+
+    /// The command that fixes it, if one would.
+    pub fn fix(&self) -> Option<&'static str> {
+        match self {
+            Self::Ready { .. } => None,
+            Self::Missing | Self::Outdated { .. } | Self::Broken { .. } => Some(INSTALL_COMMAND),
+        }
+    }
+}
+
+/// The CLI's status, reusing the last check when it found a working one.
+pub fn check() -> CliStatus {
+    if let Some(status) = READY.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+        return status;
+    }
+    recheck()
+}
+
+/// Check the CLI afresh, e.g. after it was installed.
+pub fn recheck() -> CliStatus {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let status = match find_in_path(&path, "claude") {
+        Some(claude) => status_of(&claude),
+        None => CliStatus::Missing,
+    };
+    *READY.lock().unwrap_or_else(|e| e.into_inner()) = status.is_ready().then(|| status.clone());
+    status
+}
+
+fn status_of(claude: &Path) -> CliStatus {
+    let broken = |error: String| CliStatus::Broken { path: claude.to_path_buf(), error };
+    let output = match version_output(claude) {
+        Ok(output) => output,
+        Err(e) => return broken(e),
+    };
+    match CliVersion::parse(&output) {
+        Some(version) if version < MIN_VERSION => CliStatus::Outdated { path: claude.to_path_buf(), version },
+        Some(version) => CliStatus::Ready { path: claude.to_path_buf(), version },
+        None => broken(format!("unexpected output {:?}", output.trim())),
+    }
+}
+
+/// `claude --version`'s stdout, killing it after [`VERSION_TIMEOUT`].
+fn version_output(claude: &Path) -> Result<String, String> {
+    let mut child = Command::new(claude)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() < VERSION_TIMEOUT => std::thread::sleep(Duration::from_millis(20)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {}s", VERSION_TIMEOUT.as_secs()));
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    let (mut stdout, mut stderr) = (String::new(), String::new());
+    if let Some(mut pipe) = child.stdout.take() {
+        let _ = pipe.read_to_string(&mut stdout);
+    }
+    if let Some(mut pipe) = child.stderr.take() {
+        let _ = pipe.read_to_string(&mut stderr);
+    }
+    if status.success() {
+        Ok(stdout)
+    } else {
+        Err(format!("{} {}", status, stderr.trim()).trim_end().to_string())
+    }
+}
+
+/// The first executable `name` in the directories of `path`.
+fn find_in_path(path: &std::ffi::OsStr, name: &str) -> Option<PathBuf> {
+    std::env::split_paths(path).map(|dir| dir.join(name)).find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(CliVersion::parse("1.0.33 (Claude Code)"), Some(CliVersion(1, 0, 33)));
+        assert_eq!(CliVersion::parse("claude v2.1.0-beta.3"), Some(CliVersion(2, 1, 0)));
+        assert_eq!(CliVersion::parse("0.2"), Some(CliVersion(0, 2, 0)));
+        assert_eq!(CliVersion::parse("Claude Code"), None);
+        assert!(CliVersion(0, 2, 125) < MIN_VERSION);
+    }
+
+    #[test]
+    fn test_status_of_fake_cli() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("nexus-capability-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let claude = dir.join("claude");
+        let install = |script: &str| {
+            std::fs::write(&claude, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            std::fs::set_permissions(&claude, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+
+        install("echo '1.0.40 (Claude Code)'");
+        assert_eq!(find_in_path(dir.as_os_str(), "claude"), Some(claude.clone()));
+        assert_eq!(status_of(&claude), CliStatus::Ready { path: claude.clone(), version: CliVersion(1, 0, 40) });
+
+        install("echo '0.2.9 (Claude Code)'");
+        assert!(matches!(status_of(&claude), CliStatus::Outdated { .. }));
+
+        install("echo 'node: not found' >&2; exit 127");
+        match status_of(&claude) {
+            CliStatus::Broken { error, .. } => assert!(error.contains("node: not found"), "{}", error),
+            other => panic!("expected Broken, got {:?}", other),
+        }
+
+        assert_eq!(find_in_path(dir.join("missing").as_os_str(), "claude"), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -6,23 +6,48 @@
 //!
 //! Instead of reimplementing the agent loop, we spawn the CLI with `--output-format stream-json`
 //! and parse its NDJSON output stream.
+//!
+//! A turn that ends in a transient API error (overload, rate limit, a 5xx)
+//! or whose stream drops before the result is retried a few times with
+//! growing pauses, resuming the session when it had started, and reported
+//! in the block's footer meanwhile. Other errors fail the block at once.
+//!
+//! Before a query is spawned, [`capability`] checks that `claude` is
+//! installed and new enough; when it isn't, the block shows how to set it
+//! up instead of failing with the spawn error.
 
+pub mod capability;
 pub mod types;
 pub mod session;
 
 pub use types::*;
 pub use session::{parse_user_questions, patch_session_jsonl, session_file_path};
 
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use tokio::sync::mpsc;
 
 use super::events::AgentEvent;
+use super::remote_files::{self, RemoteTarget};
 use crate::data::agent_block::ToolStatus;
+use nexus_kernel::config::{NetworkPolicy, SandboxPolicy};
+use nexus_kernel::egress::Egress;
+
+/// Retries of one turn before its block fails...
+const MAX_RETRIES: u32 = 3;
+/// ...pausing this long before the first, and four times longer before
+/// each after it.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+/// The prompt a retry resumes the session with, when one had started.
+const RESUME_PROMPT: &str = "The previous response was cut off by a connection error. Continue where you left off.";
+/// Domains the CLI itself needs under a `[sandbox] network` policy: the
+/// API and sign-in.
+const SERVICE_DOMAINS: &[&str] = &["anthropic.com", "claude.ai"];
 
 // =============================================================================
 // Helpers
@@ -159,10 +184,51 @@ fn send_tool_result(
     }
 }
 
+/// The pause before retry number `attempt` (1-based).
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY * 4u32.pow(attempt.saturating_sub(1))
+}
+
+/// Sleep for `delay` unless `cancel_flag` is set first. Returns false if
+/// it was.
+fn sleep_unless_cancelled(delay: Duration, cancel_flag: &AtomicBool) -> bool {
+    let step = Duration::from_millis(100);
+    let mut slept = Duration::ZERO;
+    while slept < delay {
+        if cancel_flag.load(Ordering::Relaxed) {
+            return false;
+        }
+        std::thread::sleep(step);
+        slept += step;
+    }
+    !cancel_flag.load(Ordering::Relaxed)
+}
+
 // =============================================================================
 // Claude CLI Wrapper
 // =============================================================================
 
+/// How a CLI run ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEnd {
+    /// A result arrived; the UI has been sent `Finished`.
+    Finished,
+    /// Cancelled; the UI has been sent `Interrupted`.
+    Cancelled,
+    /// A transient API error, or the stream ended without a result. Worth
+    /// retrying.
+    Transient(String),
+    /// The CLI failed for good, e.g. it isn't logged in.
+    Failed(String),
+}
+
+/// What [`ClaudeCli::process_stream`] returns.
+#[derive(Debug)]
+pub struct StreamOutcome {
+    pub session_id: Option<String>,
+    pub end: StreamEnd,
+}
+
 /// Wrapper around the Claude Code CLI process.
 pub struct ClaudeCli {
     child: Child,
@@ -173,7 +239,10 @@ pub struct ClaudeCli {
 impl ClaudeCli {
     /// Spawn a new Claude Code CLI process with the given prompt and options.
     pub fn spawn(prompt: &str, options: CliOptions) -> std::io::Result<Self> {
-        let mut cmd = Command::new("claude");
+        let mut cmd = match &options.egress {
+            Some(egress) => egress.command("claude")?,
+            None => Command::new("claude"),
+        };
 
         // Basic flags for non-interactive streaming
         cmd.args(["-p", prompt]);
@@ -278,15 +347,17 @@ impl ClaudeCli {
     /// Process the CLI output stream and send AgentEvents.
     ///
     /// This is the main loop that reads NDJSON from the CLI and converts
-    /// it to AgentEvents for the UI.
+    /// it to AgentEvents for the UI. Errors are left to the caller, which
+    /// decides whether to retry.
     pub fn process_stream(
         mut self,
         event_tx: mpsc::UnboundedSender<AgentEvent>,
-    ) -> std::io::Result<Option<String>> {
+    ) -> std::io::Result<StreamOutcome> {
         let stdout = self.child.stdout.take().expect("stdout not captured");
         let reader = BufReader::new(stdout);
 
         let mut session_id: Option<String> = None;
+        let mut end: Option<StreamEnd> = None;
 
         // request_id is always 0 — CLI mode doesn't have request tracking
         let _ = event_tx.send(AgentEvent::Started { request_id: 0 });
@@ -295,17 +366,13 @@ impl ClaudeCli {
             // Check for cancellation
             if self.cancel_flag.load(Ordering::Relaxed) {
                 self.cancel();
-                let _ = event_tx.send(AgentEvent::Interrupted {
-                    request_id: 0,
-                    messages: vec![], // CLI manages its own history
-                });
                 break;
             }
 
             let line = match line {
                 Ok(l) => l,
                 Err(e) => {
-                    let _ = event_tx.send(AgentEvent::Error(format!("Read error: {}", e)));
+                    end = Some(StreamEnd::Transient(format!("Read error: {}", e)));
                     break;
                 }
             };
@@ -353,7 +420,14 @@ impl ClaudeCli {
                 }
 
                 CliMessage::Result(result) => {
-                    session_id = Some(handle_result_message(&event_tx, result));
+                    let error = result.result.as_deref().filter(|_| result.is_error).unwrap_or_default();
+                    if is_transient_error(error) {
+                        session_id = Some(result.session_id);
+                        end = Some(StreamEnd::Transient(error.to_string()));
+                    } else {
+                        session_id = Some(handle_result_message(&event_tx, result));
+                        end = Some(StreamEnd::Finished);
+                    }
                 }
             }
         }
@@ -364,12 +438,31 @@ impl ClaudeCli {
                 request_id: 0,
                 messages: vec![],
             });
+            end = Some(StreamEnd::Cancelled);
         }
 
         // Wait for process to exit
-        let _ = self.child.wait();
+        let status = self.child.wait();
+
+        // The stream ended without a result: say why, from stderr if the
+        // CLI left a reason there.
+        let end = end.unwrap_or_else(|| {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = self.child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            let stderr = stderr.trim();
+            if stderr.is_empty() {
+                let status = status.map(|s| s.to_string()).unwrap_or_else(|e| e.to_string());
+                StreamEnd::Transient(format!("stream ended before the result ({})", status))
+            } else if is_transient_error(stderr) {
+                StreamEnd::Transient(stderr.to_string())
+            } else {
+                StreamEnd::Failed(stderr.to_string())
+            }
+        });
 
-        Ok(session_id)
+        Ok(StreamOutcome { session_id, end })
     }
 }
 
@@ -387,25 +480,68 @@ impl Drop for ClaudeCli {
 
 /// Write a temporary MCP config file that points the Claude CLI at our
 /// permission proxy subcommand, which communicates with the UI over TCP.
-fn write_mcp_config(port: u16) -> PathBuf {
+/// With `remote` the proxy also offers the remote file tools.
+fn write_mcp_config(port: u16, remote: bool) -> PathBuf {
     let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("nexus"));
+    let mut args = vec!["mcp-proxy".to_string(), "--port".to_string(), port.to_string()];
+    if remote {
+        args.push("--remote".to_string());
+    }
     let config = serde_json::json!({
         "mcpServers": {
             "nexus_perm": {
                 "type": "stdio",
                 "command": exe.to_str().unwrap_or("nexus"),
-                "args": ["mcp-proxy", "--port", port.to_string()]
+                "args": args
             }
         }
     });
-    let path = std::env::temp_dir().join(format!("nexus-mcp-{}.json", std::process::id()));
+    let suffix = if remote { "-remote" } else { "" };
+    let path = std::env::temp_dir().join(format!("nexus-mcp-{}{}.json", std::process::id(), suffix));
     std::fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
     path
 }
 
+/// Disallow plan-mode tools (no interactive terminal), and under the
+/// read-only sandbox every tool that runs commands or changes files.
+/// AskUserQuestion goes through MCP permission prompt — the permission
+/// server shows the question dialog and returns answers via updatedInput.
+///
+/// On a remote host the local file tools are off too, for the remote ones
+/// in [`remote_files`].
+///
+/// Web search runs on the API's side, out of the sandbox's reach, so it's
+/// off under any network policy that restricts hosts.
+fn disallowed_tools(sandbox: SandboxPolicy, network: &NetworkPolicy, remote: bool) -> Vec<String> {
+    let mut tools = vec!["EnterPlanMode", "ExitPlanMode"];
+    if sandbox == SandboxPolicy::ReadOnly {
+        tools.extend(["Bash", "Edit", "MultiEdit", "Write", "NotebookEdit"]);
+    }
+    if network.restricts() {
+        tools.push("WebSearch");
+    }
+    if remote {
+        for tool in ["Read", "Edit", "MultiEdit", "Write", "NotebookEdit", "Glob", "Grep", "LS"] {
+            if !tools.contains(&tool) {
+                tools.push(tool);
+            }
+        }
+    }
+    let mut tools: Vec<String> = tools.into_iter().map(String::from).collect();
+    if remote && sandbox == SandboxPolicy::ReadOnly {
+        tools.push(remote_files::qualified(remote_files::WRITE_FILE));
+    }
+    tools
+}
+
 /// Spawn a Claude Code CLI query and stream events to the UI.
 ///
 /// This replaces the old `spawn_agent_task` function that used nexus-agent directly.
+///
+/// With `remote`, `working_dir` is on the remote host: the CLI runs in the
+/// local home directory and works on files through the remote file tools,
+/// which need the permission server.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_claude_cli_task(
     event_tx: mpsc::UnboundedSender<AgentEvent>,
     cancel_flag: Arc<AtomicBool>,
@@ -414,18 +550,28 @@ pub async fn spawn_claude_cli_task(
     session_id: Option<String>,
     attachments: Vec<nexus_api::Value>,
     permission_port: Option<u16>,
+    limits: AgentLimits,
+    remote: Option<RemoteTarget>,
 ) -> anyhow::Result<Option<String>> {
     use tokio::task::spawn_blocking;
 
+    let remote = remote.filter(|_| permission_port.is_some());
+    let working_dir = match remote {
+        Some(_) => std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(std::env::temp_dir),
+        None => working_dir,
+    };
+
     // When a permission port is provided, set up MCP config and use default
     // permission mode so dangerous tools go through our permission proxy.
     let (mcp_config, permission_prompt_tool, permission_mode) = if let Some(port) = permission_port
     {
-        let config_path = write_mcp_config(port);
+        let config_path = write_mcp_config(port, remote.is_some());
+        // Accept-edits still routes commands through the permission tool.
+        let mode = (limits.sandbox == SandboxPolicy::AcceptEdits).then(|| "acceptEdits".to_string());
         (
             Some(config_path),
             Some("mcp__nexus_perm__permission_prompt".to_string()),
-            None, // default mode — CLI will call permission tool for dangerous ops
+            mode, // None: default mode — CLI will call permission tool for dangerous ops
         )
     } else {
         (
@@ -435,32 +581,43 @@ pub async fn spawn_claude_cli_task(
         )
     };
 
+    // Under a network policy the CLI, and every command it runs, can only
+    // reach the permission server and a proxy that holds it to the policy.
+    let egress = if limits.network.restricts() {
+        match Egress::start(limits.network.clone(), SERVICE_DOMAINS, permission_port.as_slice()) {
+            Ok(egress) => Some(Arc::new(egress)),
+            Err(e) => {
+                let _ = event_tx.send(AgentEvent::Error(format!("Can't hold the agent to [sandbox] network: {}", e)));
+                return Ok(None);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Safe read-only tools are always allowed without prompting.
+    // Dangerous tools (Bash, Edit, Write) go through the MCP permission prompt.
+    let mut allowed_tools: Vec<String> =
+        ["Read", "Glob", "Grep", "Task", "TodoWrite", "WebFetch"].into_iter().map(String::from).collect();
+    if !limits.network.restricts() {
+        allowed_tools.push("WebSearch".to_string());
+    }
+    if remote.is_some() {
+        allowed_tools.extend([remote_files::READ_FILE, remote_files::LIST_DIRECTORY].map(remote_files::qualified));
+    }
+
     // Build options
     let options = CliOptions {
-        // Safe read-only tools are always allowed without prompting.
-        // Dangerous tools (Bash, Edit, Write) go through the MCP permission prompt.
-        allowed_tools: vec![
-            "Read".to_string(),
-            "Glob".to_string(),
-            "Grep".to_string(),
-            "Task".to_string(),
-            "TodoWrite".to_string(),
-            "WebSearch".to_string(),
-            "WebFetch".to_string(),
-        ],
-        // Disallow plan-mode tools (no interactive terminal).
-        // AskUserQuestion goes through MCP permission prompt — the permission
-        // server shows the question dialog and returns answers via updatedInput.
-        disallowed_tools: vec![
-            "EnterPlanMode".to_string(),
-            "ExitPlanMode".to_string(),
-        ],
-        max_turns: Some(100),
+        allowed_tools,
+        disallowed_tools: disallowed_tools(limits.sandbox, &limits.network, remote.is_some()),
+        max_turns: Some(limits.max_turns.unwrap_or(100)),
         resume: session_id,
         working_dir: Some(working_dir),
         mcp_config,
         permission_prompt_tool,
         permission_mode,
+        append_system_prompt: remote.as_ref().map(RemoteTarget::system_prompt),
+        egress,
         ..Default::default()
     };
 
@@ -479,40 +636,100 @@ pub async fn spawn_claude_cli_task(
     };
 
     // Spawn CLI in blocking task (it does synchronous I/O)
-    let result = spawn_blocking(move || {
-        match ClaudeCli::spawn(&prompt, options) {
-            Ok(cli) => {
-                // Get child PID for direct SIGINT
-                let child_pid = cli.child_pid();
-
-                // Share cancel flag
-                let cli_cancel = cli.cancel_flag();
-
-                // Set up cancellation forwarding with direct SIGINT
-                let cancel_flag_clone = cancel_flag.clone();
-                std::thread::spawn(move || {
-                    while !cancel_flag_clone.load(Ordering::Relaxed) {
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-                    }
-                    // Set the flag
-                    cli_cancel.store(true, Ordering::SeqCst);
-                    // Also send SIGINT directly to unblock any waiting I/O
-                    #[cfg(unix)]
-                    {
-                        use nix::sys::signal::{kill, Signal};
-                        use nix::unistd::Pid;
-                        let _ = kill(Pid::from_raw(child_pid as i32), Signal::SIGINT);
+    let result = spawn_blocking(move || -> std::io::Result<Option<String>> {
+        let status = capability::check();
+        if !status.is_ready() {
+            let _ = event_tx.send(AgentEvent::SetupRequired(status));
+            return Ok(None);
+        }
+
+        let mut options = options;
+        let mut prompt = prompt;
+        let mut attempt = 0;
+        loop {
+            let cli = match ClaudeCli::spawn(&prompt, options.clone()) {
+                Ok(cli) => cli,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    // Uninstalled since the last check.
+                    let _ = event_tx.send(AgentEvent::SetupRequired(capability::recheck()));
+                    return Ok(None);
+                }
+                Err(e) => {
+                    let _ = event_tx.send(AgentEvent::Error(format!(
+                        "Failed to spawn Claude CLI: {}. Is 'claude' installed?",
+                        e
+                    )));
+                    return Ok(None);
+                }
+            };
+
+            // Get child PID for direct SIGINT
+            let child_pid = cli.child_pid();
+
+            // Share cancel flag
+            let cli_cancel = cli.cancel_flag();
+
+            // Set up cancellation forwarding with direct SIGINT, until this
+            // run is over.
+            let cancel_flag_clone = cancel_flag.clone();
+            let run_over = Arc::new(AtomicBool::new(false));
+            let run_over_clone = run_over.clone();
+            std::thread::spawn(move || {
+                while !cancel_flag_clone.load(Ordering::Relaxed) {
+                    if run_over_clone.load(Ordering::Relaxed) {
+                        return;
                     }
-                });
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                // Set the flag
+                cli_cancel.store(true, Ordering::SeqCst);
+                // Also send SIGINT directly to unblock any waiting I/O
+                #[cfg(unix)]
+                {
+                    use nix::sys::signal::{kill, Signal};
+                    use nix::unistd::Pid;
+                    let _ = kill(Pid::from_raw(child_pid as i32), Signal::SIGINT);
+                }
+            });
 
-                cli.process_stream(event_tx)
-            }
-            Err(e) => {
-                let _ = event_tx.send(AgentEvent::Error(format!(
-                    "Failed to spawn Claude CLI: {}. Is 'claude' installed?",
-                    e
-                )));
-                Ok(None)
+            let outcome = cli.process_stream(event_tx.clone());
+            run_over.store(true, Ordering::Relaxed);
+            let outcome = outcome?;
+
+            match outcome.end {
+                StreamEnd::Transient(reason) if attempt < MAX_RETRIES => {
+                    attempt += 1;
+                    tracing::warn!("Agent turn failed ({}), retry {}/{}", reason, attempt, MAX_RETRIES);
+                    let _ = event_tx.send(AgentEvent::Retrying {
+                        attempt,
+                        max_attempts: MAX_RETRIES,
+                        reason,
+                    });
+                    if !sleep_unless_cancelled(retry_delay(attempt), &cancel_flag) {
+                        let _ = event_tx.send(AgentEvent::Interrupted {
+                            request_id: 0,
+                            messages: vec![],
+                        });
+                        return Ok(outcome.session_id);
+                    }
+                    // Pick the conversation up where it broke off.
+                    if let Some(session_id) = outcome.session_id {
+                        options.resume = Some(session_id);
+                        prompt = RESUME_PROMPT.to_string();
+                    }
+                }
+                StreamEnd::Transient(reason) => {
+                    let _ = event_tx.send(AgentEvent::Error(format!(
+                        "{} (gave up after {} retries)",
+                        reason, MAX_RETRIES
+                    )));
+                    return Ok(outcome.session_id);
+                }
+                StreamEnd::Failed(reason) => {
+                    let _ = event_tx.send(AgentEvent::Error(reason));
+                    return Ok(outcome.session_id);
+                }
+                StreamEnd::Finished | StreamEnd::Cancelled => return Ok(outcome.session_id),
             }
         }
     })
@@ -1,7 +1,10 @@
 //! Serde types for the Claude Code CLI JSON stream protocol.
 
 use std::path::PathBuf;
+use std::sync::Arc;
 
+use nexus_kernel::config::{NetworkPolicy, SandboxPolicy};
+use nexus_kernel::egress::Egress;
 use serde::Deserialize;
 
 // =============================================================================
@@ -93,6 +96,9 @@ pub struct UserMessageInner {
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ResultMessage {
+    /// Whether the turn ended in an error; `result` then says which.
+    #[serde(default)]
+    pub is_error: bool,
     /// The final text result.
     pub result: Option<String>,
     /// Structured output if requested.
@@ -160,6 +166,41 @@ pub struct CliOptions {
     pub permission_prompt_tool: Option<String>,
     /// Working directory.
     pub working_dir: Option<PathBuf>,
+    /// The proxy the CLI is confined to under a `[sandbox] network` policy.
+    pub egress: Option<Arc<Egress>>,
+}
+
+/// Limits from the `[agent]` and `[sandbox]` sections of the user config.
+#[derive(Debug, Clone, Default)]
+pub struct AgentLimits {
+    /// Turn cap; the CLI default of 100 when unset.
+    pub max_turns: Option<u32>,
+    pub sandbox: SandboxPolicy,
+    /// The hosts the CLI and the commands it runs may reach.
+    pub network: NetworkPolicy,
+}
+
+impl AgentLimits {
+    pub fn from_config(config: &nexus_kernel::config::Config) -> Self {
+        Self {
+            max_turns: config.agent_max_turns(),
+            sandbox: config.sandbox_policy(),
+            network: config.network_policy(),
+        }
+    }
+}
+
+/// Whether a CLI error is worth retrying: overload, rate limits, server
+/// errors and dropped connections, as opposed to bad credentials or
+/// requests.
+pub fn is_transient_error(message: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "overloaded", "rate limit", "rate_limit", "429", "500", "502", "503", "504", "529",
+        "timed out", "timeout", "econnreset", "socket hang up", "connection reset", "connection error",
+        "network",
+    ];
+    let message = message.to_ascii_lowercase();
+    MARKERS.iter().any(|marker| message.contains(marker))
 }
 
 // =============================================================================
@@ -199,6 +240,29 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_is_transient_error() {
+        assert!(is_transient_error("API Error: 529 {\"type\":\"overloaded_error\"}"));
+        assert!(is_transient_error("API Error: Request rate limit exceeded"));
+        assert!(is_transient_error("Connection error: socket hang up"));
+        assert!(!is_transient_error("Invalid API key · Please run /login"));
+        assert!(!is_transient_error("prompt is too long"));
+    }
+
+    #[test]
+    fn test_result_message_is_error() {
+        let json = json!({"type": "result", "is_error": true, "result": "API Error: 500", "session_id": "s1"});
+        let CliMessage::Result(result) = serde_json::from_value(json).unwrap() else {
+            panic!("expected result");
+        };
+        assert!(result.is_error);
+        let json = json!({"type": "result", "session_id": "s1"});
+        let CliMessage::Result(result) = serde_json::from_value(json).unwrap() else {
+            panic!("expected result");
+        };
+        assert!(!result.is_error);
+    }
+
     // -------------------------------------------------------------------------
     // deserialize_tool_content tests
     // -------------------------------------------------------------------------
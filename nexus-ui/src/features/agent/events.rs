@@ -1,6 +1,9 @@
 //! Agent events — data payloads flowing from the Claude CLI to the UI.
 
+use super::claude::capability::CliStatus;
+use super::remote_files::RemoteFileOp;
 use crate::data::agent_block::ToolStatus;
+use nexus_api::BlockId;
 
 /// Events sent from Claude CLI to UI.
 #[derive(Debug, Clone)]
@@ -55,14 +58,32 @@ pub enum AgentEvent {
         input_tokens: Option<u64>,
         output_tokens: Option<u64>,
     },
+    /// A transient API failure or dropped stream; the turn is retried
+    /// after a pause, resuming the session when it had started.
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        reason: String,
+    },
     /// Agent encountered an error.
     Error(String),
+    /// The Claude CLI is missing, outdated or broken, so the query wasn't
+    /// sent; the block shows how to set it up.
+    SetupRequired(CliStatus),
+    /// The result of a block's "Check again".
+    CliChecked { block_id: BlockId, status: CliStatus },
     /// Agent tried to ask the user a question (via AskUserQuestion tool).
     /// Emitted after the CLI finishes, extracted from permission_denials in the result.
     UserQuestionRequested {
         tool_use_id: String,
         questions: Vec<UserQuestion>,
     },
+    /// A file tool call to serve on the remote host; the tool's text
+    /// result, or its error, goes back on `reply`.
+    RemoteFile {
+        op: RemoteFileOp,
+        reply: tokio::sync::mpsc::UnboundedSender<Result<String, String>>,
+    },
 }
 
 /// A question from Claude's AskUserQuestion tool.
@@ -5,6 +5,9 @@
 //! `permission_prompt` tool. We forward the request over TCP to the Nexus UI,
 //! which shows a permission dialog and returns Allow/Deny.
 //!
+//! With `--remote` it also offers the file tools in [`super::remote_files`],
+//! which the UI serves on the remote host the shell is connected to.
+//!
 //! Protocol:
 //!   CLI ←JSON-RPC 2.0 stdio→ this process ←JSON line TCP→ Nexus UI
 
@@ -12,8 +15,8 @@ use std::io::{self, BufRead, BufReader, Write};
 use std::net::TcpStream;
 
 /// Run the MCP permission proxy. Blocks forever (CLI manages our lifetime).
-pub fn run(port: u16) -> ! {
-    eprintln!("[mcp-proxy] started, port={port}");
+pub fn run(port: u16, remote: bool) -> ! {
+    eprintln!("[mcp-proxy] started, port={port} remote={remote}");
     let stdin = io::stdin();
     let reader = BufReader::new(stdin.lock());
     let stdout = io::stdout();
@@ -51,7 +54,19 @@ pub fn run(port: u16) -> ! {
 
             "tools/list" => {
                 eprintln!("[mcp-proxy] -> tools/list");
-                let resp = build_tools_list_response(&id);
+                let resp = build_tools_list_response(&id, remote);
+                write_response(&mut out, &resp);
+            }
+
+            "tools/call" if remote && is_file_tool(&msg) => {
+                let name = msg.pointer("/params/name").and_then(|v| v.as_str()).unwrap_or_default();
+                let args = msg.pointer("/params/arguments").cloned().unwrap_or_default();
+                eprintln!("[mcp-proxy] -> {name}");
+                let result = match ask_ui_file_tool(port, name, &args) {
+                    Ok(result) => result,
+                    Err(e) => Err(format!("lost the connection to Nexus: {e}")),
+                };
+                let resp = build_file_tool_response(&id, result);
                 write_response(&mut out, &resp);
             }
 
@@ -122,6 +137,20 @@ fn ask_ui(port: u16, args: &serde_json::Value) -> io::Result<UiResponse> {
     Ok(UiResponse { allow, updated_input })
 }
 
+/// Have the UI run a file tool on the remote host; its text result or error.
+fn ask_ui_file_tool(port: u16, name: &str, args: &serde_json::Value) -> io::Result<Result<String, String>> {
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{port}"))?;
+    let request = serde_json::json!({ "file_tool": name, "arguments": args });
+    writeln!(stream, "{request}")?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut response = String::new();
+    reader.read_line(&mut response)?;
+    let resp: serde_json::Value = serde_json::from_str(response.trim()).unwrap_or_default();
+    Ok(parse_file_tool_response(&resp))
+}
+
 /// Write a JSON-RPC response as a single line to stdout.
 fn write_response(out: &mut impl Write, resp: &serde_json::Value) {
     let s = serde_json::to_string(resp).unwrap();
@@ -149,29 +178,63 @@ fn build_initialize_response(id: &serde_json::Value) -> serde_json::Value {
     })
 }
 
-/// Build the JSON-RPC response for `tools/list`.
-fn build_tools_list_response(id: &serde_json::Value) -> serde_json::Value {
+/// Build the JSON-RPC response for `tools/list`; the remote file tools
+/// follow the permission tool when `remote`.
+fn build_tools_list_response(id: &serde_json::Value, remote: bool) -> serde_json::Value {
+    let mut tools = vec![serde_json::json!({
+        "name": "permission_prompt",
+        "description": "Prompt the user for permission to execute a tool",
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "tool_name": { "type": "string" },
+                "input": { "type": "object" },
+                "tool_use_id": { "type": "string" }
+            },
+            "required": ["tool_name", "input"]
+        }
+    })];
+    if remote {
+        tools.extend(super::remote_files::tool_definitions());
+    }
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "result": { "tools": tools }
+    })
+}
+
+/// Whether a `tools/call` is for one of the remote file tools.
+fn is_file_tool(msg: &serde_json::Value) -> bool {
+    use super::remote_files::{LIST_DIRECTORY, READ_FILE, WRITE_FILE};
+    matches!(msg.pointer("/params/name").and_then(|v| v.as_str()), Some(READ_FILE | WRITE_FILE | LIST_DIRECTORY))
+}
+
+/// Build the JSON-RPC response for a file tool's result.
+fn build_file_tool_response(id: &serde_json::Value, result: Result<String, String>) -> serde_json::Value {
+    let (text, is_error) = match result {
+        Ok(text) => (text, false),
+        Err(message) => (message, true),
+    };
     serde_json::json!({
         "jsonrpc": "2.0",
         "id": id,
         "result": {
-            "tools": [{
-                "name": "permission_prompt",
-                "description": "Prompt the user for permission to execute a tool",
-                "inputSchema": {
-                    "type": "object",
-                    "properties": {
-                        "tool_name": { "type": "string" },
-                        "input": { "type": "object" },
-                        "tool_use_id": { "type": "string" }
-                    },
-                    "required": ["tool_name", "input"]
-                }
-            }]
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error
         }
     })
 }
 
+/// Parse the UI's answer to a file tool call: `{"ok": text}` or
+/// `{"error": message}`.
+fn parse_file_tool_response(json: &serde_json::Value) -> Result<String, String> {
+    match json.get("ok").and_then(|v| v.as_str()) {
+        Some(text) => Ok(text.to_string()),
+        None => Err(json.get("error").and_then(|v| v.as_str()).unwrap_or("no answer from Nexus").to_string()),
+    }
+}
+
 /// Build a JSON-RPC error response for an unknown method.
 fn build_error_response(id: &serde_json::Value, method: &str) -> serde_json::Value {
     serde_json::json!({
@@ -261,7 +324,7 @@ mod tests {
     #[test]
     fn test_tools_list_response_structure() {
         let id = json!(2);
-        let resp = build_tools_list_response(&id);
+        let resp = build_tools_list_response(&id, false);
 
         assert_eq!(resp["jsonrpc"], "2.0");
         assert_eq!(resp["id"], 2);
@@ -271,10 +334,18 @@ mod tests {
         assert_eq!(tools[0]["name"], "permission_prompt");
     }
 
+    #[test]
+    fn test_tools_list_remote_adds_file_tools() {
+        let resp = build_tools_list_response(&json!(1), true);
+
+        let names: Vec<_> = resp["result"]["tools"].as_array().unwrap().iter().map(|t| t["name"].clone()).collect();
+        assert_eq!(names, vec![json!("permission_prompt"), json!("read_file"), json!("write_file"), json!("list_directory")]);
+    }
+
     #[test]
     fn test_tools_list_has_input_schema() {
         let id = json!(1);
-        let resp = build_tools_list_response(&id);
+        let resp = build_tools_list_response(&id, false);
 
         let schema = &resp["result"]["tools"][0]["inputSchema"];
         assert_eq!(schema["type"], "object");
@@ -370,6 +441,24 @@ mod tests {
         assert!(text.contains("nope"));
     }
 
+    // -------------------------------------------------------------------------
+    // file tool tests
+    // -------------------------------------------------------------------------
+
+    #[test]
+    fn test_file_tool_call_and_response() {
+        let call = json!({"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"read_file","arguments":{"path":"a"}}});
+        assert!(is_file_tool(&call));
+        assert!(!is_file_tool(&json!({"params":{"name":"permission_prompt"}})));
+
+        assert_eq!(parse_file_tool_response(&json!({"ok": "text"})), Ok("text".to_string()));
+        assert_eq!(parse_file_tool_response(&json!({"error": "nope"})), Err("nope".to_string()));
+
+        let resp = build_file_tool_response(&json!(3), Err("nope".to_string()));
+        assert_eq!(resp["result"]["isError"], true);
+        assert_eq!(resp["result"]["content"][0]["text"], "nope");
+    }
+
     // -------------------------------------------------------------------------
     // parse_request tests
     // -------------------------------------------------------------------------
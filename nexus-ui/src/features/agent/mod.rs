@@ -1,25 +1,37 @@
 //! Agent widget — owns agent blocks, streaming channels, and permission handling.
 
+pub mod citations;
 pub mod events;
 pub mod claude;
 pub mod mcp;
+pub mod remote_files;
+pub mod tasks;
+pub mod transcript;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use tokio::sync::{mpsc, Mutex};
 
 use nexus_api::{BlockId, Value};
+use nexus_kernel::diagnostics::CheckResult;
+use nexus_kernel::instructions::Instructions;
 use strata::{Padding, Subscription, TextInputState};
 use strata::content_address::SourceId;
 use strata::event_context::KeyEvent;
 
 use self::events::{AgentEvent, UserQuestion};
-use crate::data::agent_block::{AgentBlock, AgentBlockState, PermissionRequest};
+use self::tasks::TaskQueue;
+use self::transcript::{TranscriptFormat, TranscriptTurn};
+use crate::data::agent_block::{AgentBlock, AgentBlockState, AgentRetry, PermissionRequest};
+use crate::data::context_files::ContextFiles;
 use crate::ui::widgets::AgentBlockWidget;
 use crate::infra::systems::{agent_subscription, spawn_agent_task};
+use self::claude::AgentLimits;
+use self::remote_files::RemoteTarget;
 use crate::infra::systems::permission_server::PermissionDecision;
 
 use crate::ui::context_menu::{ContextMenuItem, ContextTarget};
@@ -46,6 +58,22 @@ fn build_question_answer(questions: &[UserQuestion], answered_idx: usize, select
 use crate::app::update_context::UpdateContext;
 use crate::data::Focus;
 
+/// `doctor` check: the permission server still accepts connections.
+fn check_permission_server(port: Option<u16>) -> CheckResult {
+    let Some(port) = port else {
+        return CheckResult::pass("permission server", "not started (starts with the first agent query)");
+    };
+    let addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+    match std::net::TcpStream::connect_timeout(&addr, Duration::from_secs(1)) {
+        Ok(_) => CheckResult::pass("permission server", format!("listening on {}", addr)),
+        Err(e) => CheckResult::fail(
+            "permission server",
+            format!("{} not reachable: {}", addr, e),
+            "restart Nexus; agent tool calls can't be approved until then",
+        ),
+    }
+}
+
 /// Manages all agent-related state: agent blocks, streaming, permissions.
 pub(crate) struct AgentWidget {
     pub blocks: Vec<AgentBlock>,
@@ -55,7 +83,8 @@ pub(crate) struct AgentWidget {
     /// Channel to send permission responses back to the TCP permission server.
     pub permission_response_tx: Option<mpsc::UnboundedSender<PermissionDecision>>,
     /// TCP port the permission server is listening on (for CLI spawns).
-    pub permission_port: Option<u16>,
+    /// Shared with its `doctor` check.
+    pub permission_port: Arc<OnceLock<u16>>,
     pub cancel_flag: Arc<AtomicBool>,
     pub dirty: bool,
     pub session_id: Option<String>,
@@ -63,6 +92,21 @@ pub(crate) struct AgentWidget {
     pub cwd: String,
     /// Text input state for free-form answers to AskUserQuestion.
     pub question_input: TextInputState,
+    /// Queries typed while offline, with their attachments, oldest first
+    /// (`[agent] queue_offline`).
+    pub queued: VecDeque<(String, Vec<Value>)>,
+    /// Files the agent has read or modified this conversation.
+    pub context_files: ContextFiles,
+    /// The context file whose diff is shown, with the diff.
+    pub context_diff: Option<(usize, String)>,
+    /// An imported conversation, to put ahead of the new session's first
+    /// query.
+    pub seed: Option<String>,
+    /// The project instructions last sent in this session, so they're
+    /// sent again only when they change.
+    pub instructions_sent: Option<Instructions>,
+    /// Background tasks (`& prompt`), started from the root's tick.
+    pub tasks: TaskQueue,
 
     // --- Subscription channel (owned by this widget) ---
     event_rx: Arc<Mutex<mpsc::UnboundedReceiver<AgentEvent>>>,
@@ -71,13 +115,23 @@ pub(crate) struct AgentWidget {
 impl AgentWidget {
     pub fn new() -> Self {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let event_rx = Arc::new(Mutex::new(event_rx));
+        let weak_rx = Arc::downgrade(&event_rx);
+        nexus_kernel::diagnostics::register_channel("agent.events", None, move || {
+            weak_rx.upgrade().map(|rx| rx.try_lock().map(|rx| rx.len()).unwrap_or(0))
+        });
+        let permission_port = Arc::new(OnceLock::new());
+        let weak_port = Arc::downgrade(&permission_port);
+        nexus_kernel::diagnostics::register_check(move || {
+            weak_port.upgrade().map(|port| check_permission_server(port.get().copied()))
+        });
         Self {
             blocks: Vec::new(),
             block_index: HashMap::new(),
             active: None,
             event_tx,
             permission_response_tx: None,
-            permission_port: None,
+            permission_port,
             cancel_flag: Arc::new(AtomicBool::new(false)),
             dirty: false,
             session_id: None,
@@ -88,7 +142,13 @@ impl AgentWidget {
                 qi.set_padding(Padding::new(8.0, 12.0, 8.0, 12.0));
                 qi
             },
-            event_rx: Arc::new(Mutex::new(event_rx)),
+            queued: VecDeque::new(),
+            context_files: ContextFiles::default(),
+            context_diff: None,
+            seed: None,
+            instructions_sent: None,
+            tasks: TaskQueue::default(),
+            event_rx,
         }
     }
 
@@ -119,13 +179,63 @@ impl AgentWidget {
 
     /// Handle a widget click within agent-owned UI. Returns None if not our widget.
     pub fn on_click(&self, id: SourceId) -> Option<AgentMsg> {
+        if id == source_ids::project_instructions() {
+            return Some(AgentMsg::EditInstructions);
+        }
+        if id == source_ids::agent_tasks_toggle() {
+            return Some(AgentMsg::ToggleTasks);
+        }
+        if id == source_ids::agent_tasks_clear() {
+            return Some(AgentMsg::ClearTasks);
+        }
+        for task in &self.tasks.tasks {
+            if id == source_ids::agent_task_toggle(task.id) {
+                return Some(AgentMsg::ToggleTask(task.id));
+            }
+            if id == source_ids::agent_task_cancel(task.id) {
+                return Some(AgentMsg::CancelTask(task.id));
+            }
+        }
+        for i in 0..self.context_files.files.len() {
+            if id == source_ids::context_file_open(i) {
+                return Some(AgentMsg::OpenContextFile(i));
+            }
+            if id == source_ids::context_file_diff(i) {
+                return Some(AgentMsg::ToggleContextDiff(i));
+            }
+            if id == source_ids::context_file_remove(i) {
+                return Some(AgentMsg::RemoveContextFile(i));
+            }
+        }
         for block in &self.blocks {
+            if let Some(citation) = citations::find_all(&block.response)
+                .find(|citation| id == source_ids::agent_citation(block.id, citation.key()))
+            {
+                return Some(AgentMsg::FollowCitation(citation));
+            }
             if id == source_ids::agent_thinking_toggle(block.id) {
                 return Some(AgentMsg::ToggleThinking(block.id));
             }
+            if id == source_ids::agent_plan_toggle(block.id) {
+                return Some(AgentMsg::TogglePlan(block.id));
+            }
+            if id == source_ids::agent_plan_copy(block.id) {
+                return Some(AgentMsg::CopyPlan(block.id));
+            }
             if id == source_ids::agent_stop(block.id) {
                 return Some(AgentMsg::Interrupt);
             }
+            if block.setup.is_some() {
+                if id == source_ids::agent_setup_copy(block.id) {
+                    return Some(AgentMsg::CopySetupCommand(block.id));
+                }
+                if id == source_ids::agent_setup_check(block.id) {
+                    return Some(AgentMsg::CheckSetup(block.id));
+                }
+                if id == source_ids::agent_setup_rerun(block.id) {
+                    return Some(AgentMsg::RerunQuery(block.id));
+                }
+            }
             for (i, _tool) in block.tools.iter().enumerate() {
                 if id == source_ids::agent_tool_toggle(block.id, i) {
                     return Some(AgentMsg::ToggleTool(block.id, i));
@@ -173,21 +283,27 @@ impl AgentWidget {
         y: f32,
     ) -> Option<ContextMenuMsg> {
         let block_id = self.block_for_source(source_id)?;
-        Some(ContextMenuMsg::Show(
-            x, y,
-            vec![ContextMenuItem::Copy, ContextMenuItem::SelectAll],
-            ContextTarget::AgentBlock(block_id),
-        ))
+        let mut items = vec![ContextMenuItem::Copy, ContextMenuItem::SelectAll];
+        items.extend(Self::conversation_items());
+        Some(ContextMenuMsg::Show(x, y, items, ContextTarget::AgentBlock(block_id)))
     }
 
     /// Build a fallback context menu (last block) for right-click on empty area.
     pub fn fallback_context_menu(&self, x: f32, y: f32) -> Option<ContextMenuMsg> {
         let block = self.blocks.last()?;
-        Some(ContextMenuMsg::Show(
-            x, y,
-            vec![ContextMenuItem::Copy, ContextMenuItem::SelectAll],
-            ContextTarget::AgentBlock(block.id),
-        ))
+        let mut items = vec![ContextMenuItem::Copy, ContextMenuItem::SelectAll];
+        items.extend(Self::conversation_items());
+        Some(ContextMenuMsg::Show(x, y, items, ContextTarget::AgentBlock(block.id)))
+    }
+
+    /// Export and import actions for the whole conversation.
+    pub fn conversation_items() -> [ContextMenuItem; 4] {
+        [
+            ContextMenuItem::ExportConversation { format: TranscriptFormat::Markdown, thinking: false },
+            ContextMenuItem::ExportConversation { format: TranscriptFormat::Markdown, thinking: true },
+            ContextMenuItem::ExportConversation { format: TranscriptFormat::Jsonl, thinking: true },
+            ContextMenuItem::ImportTranscript,
+        ]
     }
 
     /// Check if a hit address belongs to an agent block. Returns the block_id if so.
@@ -195,7 +311,9 @@ impl AgentWidget {
         for block in &self.blocks {
             if source_id == source_ids::agent_query(block.id)
                 || source_id == source_ids::agent_thinking(block.id)
+                || source_id == source_ids::agent_plan(block.id)
                 || source_id == source_ids::agent_response(block.id)
+                || (0..block.tools.len()).any(|i| source_id == source_ids::agent_tool_term(block.id, i))
             {
                 return Some(block.id);
             }
@@ -211,7 +329,7 @@ impl AgentWidget {
 
     /// Start the TCP permission server (once, reused across spawns).
     fn ensure_permission_server(&mut self) {
-        if self.permission_port.is_some() {
+        if self.permission_port.get().is_some() {
             return; // already running
         }
 
@@ -236,11 +354,12 @@ impl AgentWidget {
         });
 
         self.permission_response_tx = Some(response_tx);
-        self.permission_port = Some(port);
+        let _ = self.permission_port.set(port);
         tracing::info!("Permission server listening on port {}", port);
     }
 
     /// Spawn an agent task.
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         &mut self,
         block_id: BlockId,
@@ -248,6 +367,8 @@ impl AgentWidget {
         contextualized_query: String,
         attachments: Vec<Value>,
         cwd: &str,
+        limits: AgentLimits,
+        remote: Option<RemoteTarget>,
     ) {
         let agent_block = AgentBlock::new(block_id, query);
         let idx = self.blocks.len();
@@ -267,7 +388,7 @@ impl AgentWidget {
         let cancel_flag = self.cancel_flag.clone();
         let cwd = PathBuf::from(cwd);
         let session_id = self.session_id.clone();
-        let permission_port = self.permission_port;
+        let permission_port = self.permission_port.get().copied();
 
         tokio::spawn(async move {
             match spawn_agent_task(
@@ -278,6 +399,8 @@ impl AgentWidget {
                 attachments,
                 session_id,
                 permission_port,
+                limits,
+                remote,
             )
             .await
             {
@@ -304,11 +427,18 @@ impl AgentWidget {
     pub fn update(&mut self, msg: AgentMsg, uctx: &mut UpdateContext) {
         match msg {
             AgentMsg::Event(evt) => {
+                let _span = tracing::debug_span!("agent.event").entered();
                 self.dirty = true;
                 self.handle_event(evt, uctx);
             }
             AgentMsg::ToggleThinking(id) => { self.toggle_thinking(id); }
             AgentMsg::ToggleTool(id, idx) => { self.toggle_tool(id, idx); }
+            AgentMsg::TogglePlan(id) => { self.toggle_plan(id); }
+            // Handled by the root, which owns the clipboard.
+            AgentMsg::CopyPlan(_) | AgentMsg::CopySetupCommand(_) => {}
+            AgentMsg::CheckSetup(id) => { self.check_setup(id); }
+            // Handled by the root, which builds the query's context.
+            AgentMsg::RerunQuery(_) => {}
             AgentMsg::ExpandAllTools => { self.expand_all_tools(); }
             AgentMsg::PermissionGrant(block_id, perm_id) => { self.permission_grant(block_id, perm_id); }
             AgentMsg::PermissionGrantSession(block_id, perm_id) => { self.permission_grant_session(block_id, perm_id); }
@@ -324,6 +454,34 @@ impl AgentWidget {
                 self.question_input.apply_mouse(action);
             }
             AgentMsg::Interrupt => { self.interrupt(); }
+            AgentMsg::OpenContextFile(i) => { self.open_context_file(i); }
+            AgentMsg::ToggleContextDiff(i) => { self.toggle_context_diff(i); }
+            AgentMsg::ImportTranscript(Some(path)) => {
+                self.import_transcript(&path);
+                uctx.hint_bottom();
+            }
+            AgentMsg::ImportTranscript(None) => {}
+            // Handled by the root, which owns the blocks and the editor.
+            AgentMsg::FollowCitation(_) | AgentMsg::EditInstructions => {}
+            AgentMsg::RemoveContextFile(i) => {
+                self.context_files.remove(i);
+                self.context_diff = None;
+            }
+            AgentMsg::ToggleTasks => { self.tasks.open = !self.tasks.open; }
+            AgentMsg::ToggleTask(id) => {
+                if let Some(task) = self.tasks.get_mut(id) {
+                    task.expanded = !task.expanded;
+                }
+            }
+            AgentMsg::CancelTask(id) => { self.tasks.cancel(id); }
+            AgentMsg::ClearTasks => { self.tasks.clear_done(); }
+            AgentMsg::TaskFinished(id, result) => {
+                if self.tasks.finish(id, result) {
+                    if let Some(task) = self.tasks.get_mut(id) {
+                        tasks::notify(&task.prompt, &task.status, &task.output);
+                    }
+                }
+            }
         }
     }
 
@@ -377,8 +535,23 @@ impl AgentWidget {
                 }
             }
             AgentEvent::ToolParameter { tool_id, name, value } => {
+                let names_file = name == "file_path" || name == "notebook_path";
+                let mut touched = None;
                 if let Some(block) = self.active_block_mut() {
-                    block.add_tool_parameter(&tool_id, name, value);
+                    block.add_tool_parameter(&tool_id, name.clone(), value);
+                    if names_file
+                        && let Some(tool) = block.tools.iter().find(|t| t.id == tool_id)
+                        && let Some(path) = tool.parameters.get(&name)
+                    {
+                        touched = Some((tool.name.clone(), path.clone()));
+                    }
+                }
+                // The file's tool names it before running, so its contents
+                // are still the ones the agent started from.
+                if let Some((tool, path)) = touched
+                    && self.context_files.touch(&tool, &path, std::path::Path::new(&self.cwd))
+                {
+                    self.context_diff = None;
                 }
             }
             AgentEvent::ToolOutput { tool_id, chunk } => {
@@ -424,12 +597,30 @@ impl AgentWidget {
                 }
                 self.active = None;
             }
+            AgentEvent::Retrying { attempt, max_attempts, reason } => {
+                if let Some(block) = self.active_block_mut() {
+                    block.retrying(AgentRetry { attempt, max_attempts, reason });
+                }
+            }
             AgentEvent::Error(err) => {
                 if let Some(block) = self.active_block_mut() {
                     block.fail(err);
                 }
                 self.active = None;
             }
+            AgentEvent::SetupRequired(status) => {
+                if let Some(block) = self.active_block_mut() {
+                    block.needs_setup(status);
+                }
+                self.active = None;
+            }
+            AgentEvent::CliChecked { block_id, status } => {
+                if let Some(block) = self.block_index.get(&block_id).and_then(|&idx| self.blocks.get_mut(idx)) {
+                    block.setup_checked(status);
+                }
+            }
+            // Handled by the root, which owns the remote connection.
+            AgentEvent::RemoteFile { .. } => {}
         }
 
         uctx.hint_bottom();
@@ -444,6 +635,30 @@ impl AgentWidget {
         }
     }
 
+    /// Check the Claude CLI again for a block's setup panel, off the UI
+    /// thread; the result arrives as `CliChecked`.
+    fn check_setup(&mut self, id: BlockId) {
+        let Some(block) = self.block_index.get(&id).and_then(|&idx| self.blocks.get_mut(idx)) else { return };
+        if block.setup.as_ref().is_none_or(|setup| setup.checking) {
+            return;
+        }
+        block.checking_setup();
+        let event_tx = self.event_tx.clone();
+        tokio::task::spawn_blocking(move || {
+            let status = claude::capability::recheck();
+            let _ = event_tx.send(AgentEvent::CliChecked { block_id: id, status });
+        });
+    }
+
+    /// Toggle plan panel visibility for a block.
+    pub fn toggle_plan(&mut self, id: BlockId) {
+        if let Some(&idx) = self.block_index.get(&id) {
+            if let Some(block) = self.blocks.get_mut(idx) {
+                block.toggle_plan();
+            }
+        }
+    }
+
     /// Toggle tool invocation visibility for a block.
     pub fn toggle_tool(&mut self, id: BlockId, tool_index: usize) {
         if let Some(&idx) = self.block_index.get(&id) {
@@ -514,6 +729,61 @@ impl AgentWidget {
         }
     }
 
+    /// Open a context file with the app macOS associates with it.
+    fn open_context_file(&self, index: usize) {
+        if let Some(file) = self.context_files.files.get(index)
+            && let Err(e) = std::process::Command::new("open").arg(&file.path).spawn()
+        {
+            tracing::warn!("context: failed to open {}: {}", file.path.display(), e);
+        }
+    }
+
+    /// Show the diff of a context file, or hide it if it's showing.
+    fn toggle_context_diff(&mut self, index: usize) {
+        if self.context_diff.as_ref().is_some_and(|(shown, _)| *shown == index) {
+            self.context_diff = None;
+        } else if let Some(file) = self.context_files.files.get(index) {
+            self.context_diff = Some((index, file.diff()));
+        }
+    }
+
+    /// The conversation so far as transcript turns.
+    pub fn transcript(&self, thinking: bool) -> Vec<TranscriptTurn> {
+        self.blocks.iter().map(|block| TranscriptTurn::from_block(block, thinking)).collect()
+    }
+
+    /// Show a JSONL transcript's turns as blocks and start a new session
+    /// that is told about them. A transcript that can't be read shows as a
+    /// failed block.
+    fn import_transcript(&mut self, path: &std::path::Path) {
+        let turns = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| transcript::parse_jsonl(&text));
+        let allocator = nexus_api::BlockIdAllocator::global();
+        match turns {
+            Ok(turns) => {
+                self.seed = Some(transcript::seed_context(&turns));
+                self.session_id = None;
+                self.context_files = ContextFiles::default();
+                self.context_diff = None;
+                for turn in turns {
+                    self.push_finished_block(turn.into_block(allocator.reserve()));
+                }
+            }
+            Err(e) => {
+                let mut block = AgentBlock::new(allocator.reserve(), format!("import {}", path.display()));
+                block.fail(format!("Import failed: {}", e));
+                self.push_finished_block(block);
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn push_finished_block(&mut self, block: AgentBlock) {
+        self.block_index.insert(block.id, self.blocks.len());
+        self.blocks.push(block);
+    }
+
     /// Clear all agent blocks and cancel active agent.
     pub fn clear(&mut self) {
         if self.active.is_some() {
@@ -524,6 +794,20 @@ impl AgentWidget {
         self.block_index.clear();
     }
 
+    /// Start a new conversation (`/agent new`): the next query opens a
+    /// fresh session. Earlier blocks stay on screen.
+    pub fn new_conversation(&mut self) {
+        if self.active.is_some() {
+            self.cancel_flag.store(true, Ordering::SeqCst);
+            self.active = None;
+        }
+        self.session_id = None;
+        self.seed = None;
+        self.instructions_sent = None;
+        self.context_files = ContextFiles::default();
+        self.context_diff = None;
+    }
+
     /// Answer a pending user question via MCP permission response.
     pub fn answer_question(&mut self, block_id: BlockId, _tool_use_id: String, answer_json: String) {
         // Clear pending question from the block and reset the free-form input.
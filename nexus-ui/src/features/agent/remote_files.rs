@@ -0,0 +1,257 @@
+//! Agent file tools for remote sessions.
+//!
+//! The Claude CLI runs on this machine, so while the shell is connected
+//! over SSH its own Read/Write/Glob would see the wrong filesystem. Instead
+//! the MCP proxy offers `read_file`, `write_file` and `list_directory`,
+//! which the permission server hands to the UI as [`AgentEvent::RemoteFile`]
+//! and the UI serves through the remote agent's `FileRead`, `FileWrite` and
+//! `ListDir` requests.
+//!
+//! Paths are resolved on the remote side: relative ones against the shell's
+//! remote cwd, `~` against the remote user's home. Reads, writes and
+//! listings are capped so one tool call can't pull a huge file through the
+//! connection or into the conversation.
+//!
+//! [`AgentEvent::RemoteFile`]: super::events::AgentEvent::RemoteFile
+
+use nexus_api::{FileEntry, FileType};
+use nexus_protocol::messages::EnvInfo;
+
+/// Bytes read from a file in one call; more is cut off with a note.
+pub const MAX_READ_BYTES: u64 = 256 * 1024;
+/// Bytes one `write_file` call may write.
+pub const MAX_WRITE_BYTES: usize = 1024 * 1024;
+/// Entries one `list_directory` call shows.
+pub const MAX_LIST_ENTRIES: usize = 1000;
+/// Lines `read_file` shows when no limit is given.
+const DEFAULT_READ_LINES: usize = 2000;
+
+pub const READ_FILE: &str = "read_file";
+pub const WRITE_FILE: &str = "write_file";
+pub const LIST_DIRECTORY: &str = "list_directory";
+
+/// A tool's name as the CLI knows it, under the `nexus_perm` MCP server.
+pub fn qualified(tool: &str) -> String {
+    format!("mcp__nexus_perm__{}", tool)
+}
+
+/// Where the shell is connected, for the agent's system prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteTarget {
+    /// `user@host`.
+    pub host: String,
+    pub cwd: String,
+}
+
+impl RemoteTarget {
+    /// What the agent is told about the remote session and its tools.
+    pub fn system_prompt(&self) -> String {
+        format!(
+            "The user's shell is connected to the remote host {host}; their working directory is {cwd} on that host. \
+             Files there are not on this machine, so the built-in file tools are disabled. Use {read}, {write} and \
+             {list} instead: they act on {host}, and relative paths resolve against {cwd}. Bash still runs on this \
+             machine, not on {host}.",
+            host = self.host,
+            cwd = self.cwd,
+            read = qualified(READ_FILE),
+            write = qualified(WRITE_FILE),
+            list = qualified(LIST_DIRECTORY),
+        )
+    }
+}
+
+/// A file tool call from the agent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteFileOp {
+    /// `limit` lines from line `offset` (1-based) on.
+    Read { path: String, offset: usize, limit: usize },
+    Write { path: String, content: String },
+    List { path: String },
+}
+
+impl RemoteFileOp {
+    /// The operation a call of `tool` with `args` asks for.
+    pub fn from_call(tool: &str, args: &serde_json::Value) -> Result<Self, String> {
+        let path = args.get("path").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let count = |key: &str| args.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+        match tool {
+            READ_FILE if !path.is_empty() => Ok(RemoteFileOp::Read {
+                path,
+                offset: count("offset").unwrap_or(1).max(1),
+                limit: count("limit").unwrap_or(DEFAULT_READ_LINES),
+            }),
+            WRITE_FILE if !path.is_empty() => match args.get("content").and_then(|v| v.as_str()) {
+                Some(content) if content.len() > MAX_WRITE_BYTES => {
+                    Err(format!("{}: {} bytes is over the {} byte limit for one write", path, content.len(), MAX_WRITE_BYTES))
+                }
+                Some(content) => Ok(RemoteFileOp::Write { path, content: content.to_string() }),
+                None => Err("write_file: missing content".to_string()),
+            },
+            LIST_DIRECTORY => Ok(RemoteFileOp::List { path: if path.is_empty() { ".".to_string() } else { path } }),
+            READ_FILE | WRITE_FILE => Err(format!("{}: missing path", tool)),
+            _ => Err(format!("unknown tool: {}", tool)),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            RemoteFileOp::Read { path, .. } | RemoteFileOp::Write { path, .. } | RemoteFileOp::List { path } => path,
+        }
+    }
+}
+
+/// `path` as an absolute path on the remote host.
+///
+/// `~` is the home directory the agent reports; for older agents that
+/// don't report one, it is guessed from the user name and OS.
+pub fn resolve(path: &str, cwd: &str, env: &EnvInfo) -> String {
+    let home = || match (&env.home, env.user.as_str(), env.os.as_str()) {
+        (Some(home), _, _) => home.display().to_string(),
+        (None, "root", _) => "/root".to_string(),
+        (None, user, "macos") => format!("/Users/{}", user),
+        (None, user, _) => format!("/home/{}", user),
+    };
+    if path == "~" {
+        home()
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        format!("{}/{}", home().trim_end_matches('/'), rest)
+    } else if path.starts_with('/') {
+        path.to_string()
+    } else {
+        let relative = path.strip_prefix("./").unwrap_or(path);
+        match relative {
+            "" | "." => cwd.to_string(),
+            _ => format!("{}/{}", cwd.trim_end_matches('/'), relative),
+        }
+    }
+}
+
+/// The tool result for a read: numbered lines, like the built-in Read.
+/// `data` is at most [`MAX_READ_BYTES`] plus one byte, which says the file
+/// goes on.
+pub fn format_read(data: &[u8], offset: usize, limit: usize) -> Result<String, String> {
+    let truncated = data.len() as u64 > MAX_READ_BYTES;
+    let data = &data[..data.len().min(MAX_READ_BYTES as usize)];
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        // A cut can land inside a character.
+        Err(e) if truncated && e.error_len().is_none() => std::str::from_utf8(&data[..e.valid_up_to()]).unwrap_or_default(),
+        Err(_) => return Err("binary file, not shown".to_string()),
+    };
+    let mut out = String::new();
+    for (i, line) in text.lines().enumerate().skip(offset - 1).take(limit) {
+        out.push_str(&format!("{:>6}\t{}\n", i + 1, line));
+    }
+    if truncated {
+        out.push_str(&format!("[only the first {} KB of the file was read]\n", MAX_READ_BYTES / 1024));
+    }
+    if out.is_empty() {
+        out.push_str("[empty]\n");
+    }
+    Ok(out)
+}
+
+/// The tool result for a listing: one entry per line, directories with a
+/// trailing `/` and files with their size.
+pub fn format_listing(entries: &[FileEntry]) -> String {
+    let mut out = String::new();
+    for entry in entries.iter().take(MAX_LIST_ENTRIES) {
+        match entry.file_type {
+            FileType::Directory => out.push_str(&format!("{}/\n", entry.name)),
+            _ => out.push_str(&format!("{}\t{}\n", entry.name, entry.size)),
+        }
+    }
+    if entries.len() > MAX_LIST_ENTRIES {
+        out.push_str(&format!("[{} more entries not shown]\n", entries.len() - MAX_LIST_ENTRIES));
+    }
+    if out.is_empty() {
+        out.push_str("[empty directory]\n");
+    }
+    out
+}
+
+/// The MCP tool definitions, for `tools/list`.
+pub fn tool_definitions() -> Vec<serde_json::Value> {
+    let path = serde_json::json!({ "type": "string", "description": "Absolute, ~/ or relative to the remote working directory" });
+    vec![
+        serde_json::json!({
+            "name": READ_FILE,
+            "description": "Read a text file on the remote host, with line numbers",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": path,
+                    "offset": { "type": "integer", "description": "First line to show, from 1" },
+                    "limit": { "type": "integer", "description": "How many lines to show" }
+                },
+                "required": ["path"]
+            }
+        }),
+        serde_json::json!({
+            "name": WRITE_FILE,
+            "description": "Create or overwrite a file on the remote host",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "path": path, "content": { "type": "string" } },
+                "required": ["path", "content"]
+            }
+        }),
+        serde_json::json!({
+            "name": LIST_DIRECTORY,
+            "description": "List a directory on the remote host",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "path": path }
+            }
+        }),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_call() {
+        assert_eq!(
+            RemoteFileOp::from_call(READ_FILE, &json!({"path": "src/main.rs", "offset": 10})),
+            Ok(RemoteFileOp::Read { path: "src/main.rs".into(), offset: 10, limit: DEFAULT_READ_LINES })
+        );
+        assert_eq!(RemoteFileOp::from_call(LIST_DIRECTORY, &json!({})), Ok(RemoteFileOp::List { path: ".".into() }));
+        assert!(RemoteFileOp::from_call(WRITE_FILE, &json!({"path": "a"})).is_err());
+        let big = "x".repeat(MAX_WRITE_BYTES + 1);
+        assert!(RemoteFileOp::from_call(WRITE_FILE, &json!({"path": "a", "content": big})).unwrap_err().contains("limit"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut env = EnvInfo {
+            instance_id: String::new(),
+            user: "deploy".into(),
+            hostname: "box".into(),
+            cwd: "/srv/app".into(),
+            os: "linux".into(),
+            arch: "x86_64".into(),
+            home: Some("/var/lib/deploy".into()),
+        };
+        assert_eq!(resolve("src/lib.rs", "/srv/app/", &env), "/srv/app/src/lib.rs");
+        assert_eq!(resolve("./x", "/srv/app", &env), "/srv/app/x");
+        assert_eq!(resolve(".", "/srv/app", &env), "/srv/app");
+        assert_eq!(resolve("~/.bashrc", "/srv/app", &env), "/var/lib/deploy/.bashrc");
+        assert_eq!(resolve("~", "/srv/app", &env), "/var/lib/deploy");
+        assert_eq!(resolve("/etc/hosts", "/srv/app", &env), "/etc/hosts");
+
+        // Older agents don't report a home directory.
+        env.home = None;
+        assert_eq!(resolve("~/.bashrc", "/srv/app", &env), "/home/deploy/.bashrc");
+    }
+
+    #[test]
+    fn test_format_read() {
+        assert_eq!(format_read(b"a\nb\nc\n", 2, 1).unwrap(), "     2\tb\n");
+        assert!(format_read(&[0xff, 0xfe], 1, 10).is_err());
+        let long = vec![b'x'; MAX_READ_BYTES as usize + 1];
+        assert!(format_read(&long, 1, 10).unwrap().ends_with("was read]\n"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Background agent tasks: queries that run beside the conversation
+//! without holding the input.
+//!
+//! An agent query starting with `&` becomes a task; `&#3 summarize the
+//! failures` waits for block 3 to finish first and is given its output.
+//! Tasks run as one-off read-only CLI calls, at most `[agent]
+//! max_background` at a time, and post a notification when they finish.
+//! The queue panel above the input shows each task and its result.
+
+use std::time::Duration;
+
+use nexus_api::BlockId;
+use tokio_util::sync::CancellationToken;
+
+use super::claude::capability;
+use super::claude::ResultMessage;
+
+/// Turns a task may take; it only reads, so it needs few.
+const MAX_TURNS: &str = "20";
+
+/// Tools a task may use. Nothing that writes or runs commands: no one is
+/// watching to approve them.
+const ALLOWED_TOOLS: &str = "Read,Glob,Grep,WebSearch,WebFetch";
+const DISALLOWED_TOOLS: &str = "Bash,Edit,MultiEdit,Write,NotebookEdit,EnterPlanMode,ExitPlanMode";
+
+/// How long a task may run before it's given up on.
+const TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Parse a background query: `& prompt`, or `&#N prompt` to wait for
+/// block N. None if `text` isn't one.
+pub fn parse(text: &str) -> Option<(Option<BlockId>, String)> {
+    let rest = text.trim_start().strip_prefix('&')?.trim_start();
+    let (after, prompt) = match rest.strip_prefix('#') {
+        Some(tail) => {
+            let digits = tail.find(|c: char| !c.is_ascii_digit()).unwrap_or(tail.len());
+            let id = tail[..digits].parse().ok()?;
+            (Some(BlockId(id)), &tail[digits..])
+        }
+        None => (None, rest),
+    };
+    let prompt = prompt.trim();
+    (!prompt.is_empty()).then(|| (after, prompt.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskStatus {
+    /// Waiting for a block to finish.
+    Waiting(BlockId),
+    /// Ready, waiting for a free slot.
+    Pending,
+    Running,
+    Finished,
+    Failed(String),
+    Cancelled,
+}
+
+impl TaskStatus {
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Finished | Self::Failed(_) | Self::Cancelled)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Waiting(id) => format!("waiting for #{}", id.0),
+            Self::Pending => "queued".to_string(),
+            Self::Running => "running".to_string(),
+            Self::Finished => "done".to_string(),
+            Self::Failed(_) => "failed".to_string(),
+            Self::Cancelled => "cancelled".to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AgentTask {
+    pub id: u64,
+    pub prompt: String,
+    pub after: Option<BlockId>,
+    pub status: TaskStatus,
+    /// The agent's answer, once finished.
+    pub output: String,
+    pub cost_usd: Option<f64>,
+    /// Whether the panel shows the output.
+    pub expanded: bool,
+    cancel: CancellationToken,
+}
+
+/// What a finished run came back with.
+#[derive(Debug, Clone)]
+pub struct TaskOutcome {
+    pub output: String,
+    pub cost_usd: Option<f64>,
+}
+
+/// All background tasks of a window, oldest first.
+#[derive(Debug, Default)]
+pub struct TaskQueue {
+    pub tasks: Vec<AgentTask>,
+    next_id: u64,
+    /// Whether the panel lists the tasks or only counts them.
+    pub open: bool,
+}
+
+impl TaskQueue {
+    pub fn push(&mut self, prompt: String, after: Option<BlockId>) -> u64 {
+        self.next_id += 1;
+        self.tasks.push(AgentTask {
+            id: self.next_id,
+            prompt,
+            after,
+            status: after.map_or(TaskStatus::Pending, TaskStatus::Waiting),
+            output: String::new(),
+            cost_usd: None,
+            expanded: false,
+            cancel: CancellationToken::new(),
+        });
+        self.open = true;
+        self.next_id
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut AgentTask> {
+        self.tasks.iter_mut().find(|task| task.id == id)
+    }
+
+    pub fn running(&self) -> usize {
+        self.tasks.iter().filter(|task| task.status == TaskStatus::Running).count()
+    }
+
+    /// Release tasks whose block has finished. `block_done` says whether a
+    /// block has finished, or None if there's no such block.
+    pub fn release(&mut self, block_done: impl Fn(BlockId) -> Option<bool>) -> bool {
+        let mut changed = false;
+        for task in &mut self.tasks {
+            if let TaskStatus::Waiting(id) = task.status {
+                match block_done(id) {
+                    Some(true) => task.status = TaskStatus::Pending,
+                    Some(false) => continue,
+                    None => task.status = TaskStatus::Failed(format!("there's no block #{}", id.0)),
+                }
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Mark pending tasks running while fewer than `limit` are, and return
+    /// them with the token that cancels each.
+    pub fn start(&mut self, limit: usize) -> Vec<(u64, String, Option<BlockId>, CancellationToken)> {
+        let free = limit.saturating_sub(self.running());
+        self.tasks
+            .iter_mut()
+            .filter(|task| task.status == TaskStatus::Pending)
+            .take(free)
+            .map(|task| {
+                task.status = TaskStatus::Running;
+                (task.id, task.prompt.clone(), task.after, task.cancel.clone())
+            })
+            .collect()
+    }
+
+    /// Record how a run ended. False if the task was cancelled meanwhile.
+    pub fn finish(&mut self, id: u64, result: Result<TaskOutcome, String>) -> bool {
+        let Some(task) = self.get_mut(id).filter(|task| task.status == TaskStatus::Running) else {
+            return false;
+        };
+        match result {
+            Ok(outcome) => {
+                task.status = TaskStatus::Finished;
+                task.output = outcome.output;
+                task.cost_usd = outcome.cost_usd;
+            }
+            Err(error) => task.status = TaskStatus::Failed(error),
+        }
+        true
+    }
+
+    pub fn cancel(&mut self, id: u64) {
+        if let Some(task) = self.get_mut(id).filter(|task| !task.status.is_done()) {
+            task.cancel.cancel();
+            task.status = TaskStatus::Cancelled;
+        }
+    }
+
+    pub fn clear_done(&mut self) {
+        self.tasks.retain(|task| !task.status.is_done());
+    }
+}
+
+/// Run one task to completion, or until `cancel` fires.
+pub async fn run(prompt: String, cwd: String, cancel: CancellationToken) -> Result<TaskOutcome, String> {
+    let status = tokio::task::spawn_blocking(capability::check).await.map_err(|e| e.to_string())?;
+    if let Some(fix) = status.fix() {
+        return Err(format!("{}; run `{}`", status.title(), fix));
+    }
+    let call = tokio::process::Command::new("claude")
+        .args(["-p", &prompt])
+        .args(["--output-format", "json"])
+        .args(["--max-turns", MAX_TURNS])
+        .args(["--allowedTools", ALLOWED_TOOLS])
+        .args(["--disallowedTools", DISALLOWED_TOOLS])
+        .current_dir(&cwd)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::select! {
+        output = tokio::time::timeout(TIMEOUT, call) => match output {
+            Ok(output) => output.map_err(|e| format!("couldn't start the agent: {}", e))?,
+            Err(_) => return Err("timed out".to_string()),
+        },
+        _ = cancel.cancelled() => return Err("cancelled".to_string()),
+    };
+    let result: ResultMessage = serde_json::from_slice(&output.stdout).map_err(|_| {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        stderr.lines().last().unwrap_or("the agent gave no result").to_string()
+    })?;
+    let text = result.result.unwrap_or_default();
+    if result.is_error {
+        return Err(text);
+    }
+    Ok(TaskOutcome { output: text, cost_usd: result.cost_usd })
+}
+
+/// Tell the user a task finished, with the start of its answer.
+pub fn notify(prompt: &str, status: &TaskStatus, output: &str) {
+    let title = match status {
+        TaskStatus::Finished => "Agent task done",
+        _ => "Agent task failed",
+    };
+    let body: String = match status {
+        TaskStatus::Failed(error) => error.clone(),
+        _ => output.split_whitespace().collect::<Vec<_>>().join(" "),
+    };
+    let body: String = body.chars().take(180).collect();
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!(
+        "display notification \"{}\" with title \"{}\" subtitle \"{}\"",
+        quote(&body),
+        title,
+        quote(&prompt.chars().take(80).collect::<String>())
+    );
+    let _ = std::process::Command::new("osascript").args(["-e", &script]).spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_background_query() {
+        assert_eq!(parse("& summarize the log"), Some((None, "summarize the log".to_string())));
+        assert_eq!(parse("&#3 why did it fail?"), Some((Some(BlockId(3)), "why did it fail?".to_string())));
+        assert_eq!(parse("& #12  check"), Some((Some(BlockId(12)), "check".to_string())));
+        assert_eq!(parse("&#3"), None);
+        assert_eq!(parse("&#x go"), None);
+        assert_eq!(parse("summarize & go"), None);
+    }
+
+    #[test]
+    fn test_queue_waits_and_limits() {
+        let mut queue = TaskQueue::default();
+        let waiting = queue.push("after".to_string(), Some(BlockId(3)));
+        let a = queue.push("a".to_string(), None);
+        let b = queue.push("b".to_string(), None);
+
+        let started: Vec<u64> = queue.start(1).into_iter().map(|(id, ..)| id).collect();
+        assert_eq!(started, [a]);
+        assert!(queue.start(1).is_empty());
+
+        assert!(!queue.release(|_| Some(false)));
+        assert!(queue.release(|_| Some(true)));
+        assert!(queue.finish(a, Ok(TaskOutcome { output: "done".to_string(), cost_usd: None })));
+        let started: Vec<u64> = queue.start(2).into_iter().map(|(id, ..)| id).collect();
+        assert_eq!(started, [waiting, b]);
+
+        queue.cancel(b);
+        assert!(!queue.finish(b, Err("cancelled".to_string())));
+        queue.clear_done();
+        assert_eq!(queue.tasks.len(), 1);
+        assert_eq!(queue.tasks[0].id, waiting);
+    }
+
+    #[test]
+    fn test_release_fails_unknown_block() {
+        let mut queue = TaskQueue::default();
+        queue.push("after".to_string(), Some(BlockId(9)));
+        assert!(queue.release(|_| None));
+        assert_eq!(queue.tasks[0].status, TaskStatus::Failed("there's no block #9".to_string()));
+    }
+}
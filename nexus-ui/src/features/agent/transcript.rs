@@ -0,0 +1,255 @@
+//! Agent conversations as files: export to Markdown for reading or JSONL
+//! for keeping, and import a JSONL transcript to carry an old conversation
+//! into a new session.
+//!
+//! JSONL has one [`TranscriptTurn`] per line. An imported transcript comes
+//! back as completed blocks, and a summary of it goes ahead of the next
+//! query so the agent's new session starts from it.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use nexus_api::BlockId;
+use serde::{Deserialize, Serialize};
+
+use crate::data::agent_block::{AgentBlock, AgentBlockState, ToolInvocation, ToolStatus};
+
+/// Export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Markdown,
+    Jsonl,
+}
+
+impl TranscriptFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// One query and everything the agent did to answer it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptTurn {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<TranscriptTool>,
+    #[serde(default)]
+    pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptTool {
+    pub name: String,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub failed: bool,
+}
+
+impl TranscriptTurn {
+    pub fn from_block(block: &AgentBlock, thinking: bool) -> Self {
+        Self {
+            query: block.query.clone(),
+            thinking: (thinking && !block.thinking.is_empty()).then(|| block.thinking.clone()),
+            tools: block
+                .tools
+                .iter()
+                .map(|tool| TranscriptTool {
+                    name: tool.name.clone(),
+                    parameters: tool.parameters.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                    output: tool.output.clone(),
+                    failed: tool.status == ToolStatus::Error,
+                })
+                .collect(),
+            response: block.response.clone(),
+            cost_usd: block.cost_usd,
+            input_tokens: block.input_tokens,
+            output_tokens: block.output_tokens,
+            duration_ms: block.duration_ms,
+        }
+    }
+
+    /// A completed block showing this turn.
+    pub fn into_block(self, id: BlockId) -> AgentBlock {
+        let mut block = AgentBlock::new(id, self.query);
+        block.thinking = self.thinking.unwrap_or_default();
+        block.thinking_collapsed = true;
+        for (i, tool) in self.tools.into_iter().enumerate() {
+            let mut invocation = ToolInvocation::new(format!("imported-{}", i), tool.name);
+            invocation.parameters = tool.parameters.into_iter().collect();
+            if let Some(output) = tool.output {
+                invocation.set_output(output);
+            }
+            invocation.status = if tool.failed { ToolStatus::Error } else { ToolStatus::Success };
+            invocation.collapsed = true;
+            block.tools.push(invocation);
+        }
+        block.response = self.response;
+        block.cost_usd = self.cost_usd;
+        block.input_tokens = self.input_tokens;
+        block.output_tokens = self.output_tokens;
+        block.duration_ms = self.duration_ms;
+        block.state = AgentBlockState::Completed;
+        block
+    }
+}
+
+/// The conversation as Markdown: a section per turn.
+pub fn to_markdown(turns: &[TranscriptTurn]) -> String {
+    let mut out = String::from("# Agent conversation\n");
+    for turn in turns {
+        out.push_str(&format!("\n## > {}\n", turn.query));
+        if let Some(thinking) = &turn.thinking {
+            out.push_str("\n<details><summary>Thinking</summary>\n\n");
+            out.push_str(thinking.trim_end());
+            out.push_str("\n\n</details>\n");
+        }
+        for tool in &turn.tools {
+            let params: Vec<String> = tool.parameters.iter().map(|(k, v)| format!("{}: {}", k, v)).collect();
+            out.push_str(&format!("\n**{}**{}\n", tool.name, if tool.failed { " (failed)" } else { "" }));
+            if !params.is_empty() || tool.output.is_some() {
+                out.push_str("\n```\n");
+                for param in &params {
+                    out.push_str(param);
+                    out.push('\n');
+                }
+                if let Some(output) = &tool.output {
+                    if !params.is_empty() {
+                        out.push_str("---\n");
+                    }
+                    out.push_str(output.trim_end());
+                    out.push('\n');
+                }
+                out.push_str("```\n");
+            }
+        }
+        if !turn.response.is_empty() {
+            out.push('\n');
+            out.push_str(turn.response.trim_end());
+            out.push('\n');
+        }
+        let mut stats = Vec::new();
+        if let Some(cost) = turn.cost_usd {
+            stats.push(format!("${:.4}", cost));
+        }
+        let tokens = turn.input_tokens.unwrap_or(0) + turn.output_tokens.unwrap_or(0);
+        if tokens > 0 {
+            stats.push(crate::data::agent_block::format_tokens(tokens));
+        }
+        if !stats.is_empty() {
+            out.push_str(&format!("\n_{}_\n", stats.join(" \u{00B7} ")));
+        }
+    }
+    out
+}
+
+/// The conversation as JSONL, one turn per line.
+pub fn to_jsonl(turns: &[TranscriptTurn]) -> String {
+    turns
+        .iter()
+        .filter_map(|turn| serde_json::to_string(turn).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Parse a JSONL transcript. Blank lines are skipped; anything else that
+/// isn't a turn is an error naming its line.
+pub fn parse_jsonl(text: &str) -> Result<Vec<TranscriptTurn>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+/// What a new session is told of an imported conversation: each query
+/// and response, with the tools used in between.
+pub fn seed_context(turns: &[TranscriptTurn]) -> String {
+    let mut out = String::from("[Earlier conversation, imported for context:]\n");
+    for turn in turns {
+        out.push_str(&format!("User: {}\n", turn.query));
+        if !turn.tools.is_empty() {
+            let names: Vec<&str> = turn.tools.iter().map(|tool| tool.name.as_str()).collect();
+            out.push_str(&format!("(tools used: {})\n", names.join(", ")));
+        }
+        out.push_str(&format!("Assistant: {}\n", turn.response.trim_end()));
+    }
+    out.push_str("[End of earlier conversation.]\n");
+    out
+}
+
+/// Where an export is written.
+pub fn export_path(format: TranscriptFormat) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    crate::utils::text::export_dir().join(format!("nexus-conversation-{}.{}", stamp, format.extension()))
+}
+
+/// Ask for a transcript with the standard open panel. None if cancelled.
+pub async fn choose_transcript() -> Option<PathBuf> {
+    let output = tokio::process::Command::new("osascript")
+        .args(["-e", "POSIX path of (choose file with prompt \"Import agent transcript (JSONL)\")"])
+        .output()
+        .await
+        .ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_block() -> AgentBlock {
+        let mut block = AgentBlock::new(BlockId(1), "list files".to_string());
+        block.append_thinking("use ls");
+        block.start_tool("t1".to_string(), "Bash".to_string());
+        block.add_tool_parameter("t1", "command".to_string(), "ls".to_string());
+        block.update_tool_status("t1", ToolStatus::Success, None, Some("a.txt\n".to_string()));
+        block.append_response("There is one file.");
+        block.cost_usd = Some(0.0123);
+        block.complete();
+        block
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let turn = TranscriptTurn::from_block(&sample_block(), true);
+        let jsonl = to_jsonl(std::slice::from_ref(&turn));
+        assert_eq!(jsonl.lines().count(), 1);
+        let parsed = parse_jsonl(&format!("{}\n\n", jsonl)).unwrap();
+        assert_eq!(parsed, [turn.clone()]);
+
+        let block = parsed.into_iter().next().unwrap().into_block(BlockId(2));
+        assert_eq!(block.state, AgentBlockState::Completed);
+        assert_eq!(block.tools[0].output.as_deref(), Some("a.txt\n"));
+        assert_eq!(block.response, "There is one file.");
+
+        assert_eq!(parse_jsonl("{\"query\": 1}").unwrap_err().split(':').next(), Some("line 1"));
+    }
+
+    #[test]
+    fn test_markdown_thinking_is_optional() {
+        let block = sample_block();
+        let without = to_markdown(&[TranscriptTurn::from_block(&block, false)]);
+        assert!(without.contains("## > list files"));
+        assert!(without.contains("**Bash**\n\n```\ncommand: ls\n---\na.txt\n```"));
+        assert!(without.contains("$0.0123"));
+        assert!(!without.contains("use ls"));
+        assert!(to_markdown(&[TranscriptTurn::from_block(&block, true)]).contains("use ls"));
+    }
+}
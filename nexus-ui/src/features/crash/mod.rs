@@ -0,0 +1,166 @@
+//! Crash prompt — offered on the start after Nexus crashed.
+//!
+//! Shows what the panic hook recorded (see `nexus_kernel::crash`) and lets
+//! the user read the report, copy it, send it to `[crash] upload_url`, or
+//! dismiss it. Escape closes the prompt without deciding, so it comes back
+//! next start; `diagnostics crash list` has the reports either way.
+
+use nexus_kernel::config::Config;
+use nexus_kernel::crash::{self, CrashStatus, CrashSummary};
+use strata::content_address::SourceId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::CrashMsg;
+use crate::utils::ids;
+
+pub(crate) struct CrashPromptWidget {
+    pub open: bool,
+    /// Reports not yet dismissed or sent, newest first.
+    pub crashes: Vec<CrashSummary>,
+    /// Full text of the newest report.
+    pub report: String,
+    pub reviewing: bool,
+    pub upload_url: Option<String>,
+    pub submitting: bool,
+    /// Why the last step failed.
+    pub error: Option<String>,
+}
+
+impl CrashPromptWidget {
+    pub fn closed() -> Self {
+        Self {
+            open: false,
+            crashes: Vec::new(),
+            report: String::new(),
+            reviewing: false,
+            upload_url: None,
+            submitting: false,
+            error: None,
+        }
+    }
+
+    /// The prompt for this launch: open when there are new crash reports.
+    pub fn on_start(config: &Config) -> Self {
+        let crashes = crash::crashes_dir().map(|dir| crash::pending(&dir)).unwrap_or_default();
+        let Some(newest) = crashes.first() else {
+            return Self::closed();
+        };
+        let report = std::fs::read_to_string(&newest.path).unwrap_or_default();
+        Self {
+            open: true,
+            crashes,
+            report,
+            upload_url: config.crash_upload_url().map(str::to_string),
+            ..Self::closed()
+        }
+    }
+
+    /// Apply a message. Returns the reports to upload, and where, when the
+    /// user chose to send them.
+    pub fn update(&mut self, msg: CrashMsg) -> Option<(Vec<std::path::PathBuf>, String)> {
+        match msg {
+            CrashMsg::Review => self.reviewing = !self.reviewing,
+            CrashMsg::Submit => {
+                let url = self.upload_url.clone()?;
+                if !self.submitting {
+                    self.submitting = true;
+                    self.error = None;
+                    return Some((self.crashes.iter().map(|c| c.path.clone()).collect(), url));
+                }
+            }
+            CrashMsg::Submitted(Ok(())) => {
+                self.submitting = false;
+                self.open = false;
+            }
+            CrashMsg::Submitted(Err(e)) => {
+                self.submitting = false;
+                self.error = Some(e);
+            }
+            // Copying happens in the root.
+            CrashMsg::Copy => {}
+            CrashMsg::Dismiss => {
+                for crash in &self.crashes {
+                    if let Err(e) = crash::set_status(&crash.path, CrashStatus::Dismissed) {
+                        tracing::warn!("crash: failed to dismiss {}: {}", crash.path.display(), e);
+                    }
+                }
+                self.open = false;
+            }
+            CrashMsg::Later => self.open = false,
+        }
+        None
+    }
+
+    pub fn on_key(&self, event: &KeyEvent) -> Option<CrashMsg> {
+        match event {
+            KeyEvent::Pressed { key: Key::Named(NamedKey::Escape), .. } => Some(CrashMsg::Later),
+            _ => None,
+        }
+    }
+
+    pub fn on_click(&self, id: SourceId) -> Option<CrashMsg> {
+        if id == ids::crash_review() {
+            Some(CrashMsg::Review)
+        } else if id == ids::crash_submit() {
+            Some(CrashMsg::Submit)
+        } else if id == ids::crash_copy() {
+            Some(CrashMsg::Copy)
+        } else if id == ids::crash_dismiss() {
+            Some(CrashMsg::Dismiss)
+        } else {
+            None
+        }
+    }
+}
+
+/// Upload each report in turn, stopping at the first failure.
+pub(crate) async fn submit(paths: Vec<std::path::PathBuf>, url: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        paths.iter().try_for_each(|path| crash::submit(path, &url)).map_err(|e| format!("{:#}", e))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompt() -> CrashPromptWidget {
+        let crash = CrashSummary {
+            path: std::path::PathBuf::from("/nonexistent/crash-1.txt"),
+            at_ms: 1,
+            message: "boom".to_string(),
+            location: None,
+            status: CrashStatus::New,
+        };
+        CrashPromptWidget { open: true, crashes: vec![crash], ..CrashPromptWidget::closed() }
+    }
+
+    #[test]
+    fn test_submit_needs_upload_url() {
+        let mut widget = prompt();
+        assert_eq!(widget.update(CrashMsg::Submit), None);
+
+        widget.upload_url = Some("https://example.com/crash".to_string());
+        let (paths, url) = widget.update(CrashMsg::Submit).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(url, "https://example.com/crash");
+        // A second click while sending does nothing.
+        assert_eq!(widget.update(CrashMsg::Submit), None);
+
+        widget.update(CrashMsg::Submitted(Err("offline".to_string())));
+        assert!(widget.open);
+        assert_eq!(widget.error.as_deref(), Some("offline"));
+    }
+
+    #[test]
+    fn test_escape_closes_without_deciding() {
+        let mut widget = prompt();
+        let escape = KeyEvent::Pressed { key: Key::Named(NamedKey::Escape), modifiers: Default::default(), text: None };
+        assert_eq!(widget.on_key(&escape), Some(CrashMsg::Later));
+        widget.update(CrashMsg::Later);
+        assert!(!widget.open);
+        assert_eq!(widget.crashes[0].status, CrashStatus::New);
+    }
+}
@@ -3,11 +3,14 @@
 use std::cell::Cell;
 use std::sync::Arc;
 
-use nexus_kernel::{Completion, Kernel, longest_common_prefix};
+use nexus_kernel::filesystem::DirectoryPreview;
+use nexus_kernel::{Completion, CompletionKind, Kernel, longest_common_prefix};
 use tokio::sync::Mutex;
 
 use strata::{ScrollAction, ScrollState};
 
+use crate::data::provider_host::Contributions;
+
 /// Typed output from CompletionWidget → parent.
 pub(crate) enum CompletionOutput {
     /// Nothing happened.
@@ -40,6 +43,30 @@ fn snap_to_char_boundary(s: &str, n: usize) -> usize {
     i
 }
 
+/// Append provider candidates for the word at `anchor..cursor` that the
+/// kernel didn't already offer.
+fn add_provider_completions(
+    completions: &mut Vec<Completion>,
+    providers: &Contributions,
+    input_text: &str,
+    anchor: usize,
+    cursor: usize,
+) {
+    let anchor = snap_to_char_boundary(input_text, anchor);
+    let cursor = snap_to_char_boundary(input_text, cursor).max(anchor);
+    let word = &input_text[anchor..cursor];
+    for candidate in providers.completions(&input_text[..anchor]) {
+        if candidate.starts_with(word) && !completions.iter().any(|c| c.text == candidate) {
+            completions.push(Completion {
+                text: candidate.to_string(),
+                display: candidate.to_string(),
+                kind: CompletionKind::Function,
+                score: 0,
+            });
+        }
+    }
+}
+
 /// Completion popup state and logic.
 pub(crate) struct CompletionWidget {
     pub completions: Vec<Completion>,
@@ -51,6 +78,11 @@ pub(crate) struct CompletionWidget {
     /// Second Tab will open the popup from these.
     pub(crate) pending_completions: Vec<Completion>,
     pending_anchor: usize,
+    /// The completions came from the local kernel, so their paths can be
+    /// previewed here (not over a remote connection).
+    pub(crate) local: bool,
+    /// Contents of the directory the selected completion names.
+    pub preview: Option<DirectoryPreview>,
 }
 
 impl CompletionWidget {
@@ -63,6 +95,8 @@ impl CompletionWidget {
             hovered: Cell::new(None),
             pending_completions: Vec::new(),
             pending_anchor: 0,
+            local: true,
+            preview: None,
         }
     }
 
@@ -74,11 +108,15 @@ impl CompletionWidget {
     /// - First Tab: insert longest common prefix (no popup).
     /// - Second Tab (or first Tab if LCP adds nothing): open popup.
     /// - Single match: apply immediately.
+    ///
+    /// Candidates contributed by context providers are offered after the
+    /// kernel's own.
     pub fn tab_complete(
         &mut self,
         input_text: &str,
         input_cursor: usize,
         kernel: &Arc<Mutex<Kernel>>,
+        providers: &Contributions,
     ) -> CompletionOutput {
         // Double-tab: if we have pending completions from a previous LCP insertion, show popup now
         if !self.pending_completions.is_empty() {
@@ -107,7 +145,15 @@ impl CompletionWidget {
             return CompletionOutput::None;
         }
 
-        let (completions, anchor) = kernel.blocking_lock().complete(input_text, input_cursor);
+        let (completions, anchor) = match super::slash::complete(input_text, input_cursor) {
+            Some(found) => found,
+            None => {
+                let (mut completions, anchor) = kernel.blocking_lock().complete(input_text, input_cursor);
+                add_provider_completions(&mut completions, providers, input_text, anchor, input_cursor);
+                (completions, anchor)
+            }
+        };
+        self.local = true;
         if completions.len() == 1 {
             // Single completion: apply immediately with trailing space (like Bash)
             let comp = &completions[0];
@@ -219,6 +265,7 @@ impl CompletionWidget {
         input_text: &str,
         input_cursor: usize,
     ) -> CompletionOutput {
+        self.local = false;
         if completions.len() == 1 {
             let comp = &completions[0];
             let completed = with_trailing_space(&comp.text);
@@ -257,6 +304,11 @@ impl CompletionWidget {
         }
     }
 
+    /// The highlighted completion, if the popup is showing.
+    pub fn selected(&self) -> Option<&Completion> {
+        self.completions.get(self.index?)
+    }
+
     /// Handle scroll action on the completion popup.
     pub fn apply_scroll(&mut self, action: ScrollAction) {
         self.scroll.apply(action);
@@ -296,6 +348,28 @@ mod tests {
         assert!(widget.hovered.get().is_none());
     }
 
+    #[test]
+    fn test_provider_completions_are_appended() {
+        use crate::data::provider_host::ProviderUpdate;
+        use crate::data::providers::Contribution;
+
+        let mut providers = Contributions::default();
+        providers.apply(ProviderUpdate {
+            provider: "node",
+            generation: 0,
+            block_id: None,
+            contributions: vec![Contribution::Completions {
+                command: "npm run".into(),
+                candidates: vec!["build".into(), "bench".into(), "test".into()],
+            }],
+        });
+        let mut completions = vec![make_completion("build")];
+        add_provider_completions(&mut completions, &providers, "npm run b", 8, 9);
+
+        let texts: Vec<&str> = completions.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["build", "bench"]);
+    }
+
     #[test]
     fn test_completion_widget_is_active_when_empty() {
         let widget = CompletionWidget::new();
@@ -352,6 +426,14 @@ mod tests {
         assert_eq!(widget.index, Some(1));
     }
 
+    #[test]
+    fn test_remote_results_are_not_previewed_locally() {
+        let mut widget = CompletionWidget::new();
+        widget.apply_remote_result(vec![make_completion("src/"), make_completion("scripts/")], 3, "cd s", 4);
+        assert_eq!(widget.selected().map(|c| c.text.as_str()), Some("src/"));
+        assert!(!widget.local);
+    }
+
     #[test]
     fn test_completion_widget_accept_no_selection() {
         let mut widget = CompletionWidget::new();
@@ -0,0 +1,184 @@
+//! Agent ghost completions: once typing pauses, a small model suggests the
+//! rest of the shell command, shown dimmed after the cursor and accepted
+//! with Tab.
+//!
+//! Opt-in through `[agent] ghost_completions`, since every suggestion is a
+//! model call. Suggestions are cached by the text they complete, and
+//! typing along a suggestion keeps showing the rest of it, so a pause only
+//! costs a call when the line has gone somewhere new.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long typing must pause before a suggestion is asked for.
+pub const IDLE: Duration = Duration::from_millis(600);
+
+/// Lines shorter than this aren't worth a call.
+const MIN_PREFIX: usize = 2;
+
+/// Cached suggestions kept before the cache starts over.
+const MAX_CACHED: usize = 256;
+
+/// The model used: small and cheap, since it runs on every pause.
+const MODEL: &str = "haiku";
+
+/// How long a suggestion may take before it's given up on.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+pub struct GhostCompletion {
+    /// Suggested continuation by the text it continues. An empty
+    /// continuation records that the model had nothing to add.
+    cache: HashMap<String, String>,
+    /// When the text last changed, while a request for it is still due.
+    edited_at: Option<Instant>,
+    /// Monotonic generation — a result is dropped unless it matches.
+    pub(crate) generation: u64,
+}
+
+impl GhostCompletion {
+    /// The text changed; a request is due after [`IDLE`].
+    pub fn edited(&mut self) {
+        self.edited_at = Some(Instant::now());
+    }
+
+    /// What to show after `text`: a cached continuation of it, or the rest
+    /// of one for a shorter line that `text` has typed along.
+    pub fn suggestion(&self, text: &str) -> Option<&str> {
+        if let Some(suffix) = self.cache.get(text) {
+            return (!suffix.is_empty()).then_some(suffix.as_str());
+        }
+        self.cache.iter().find_map(|(prefix, suffix)| {
+            let typed = text.strip_prefix(prefix.as_str())?;
+            let rest = suffix.strip_prefix(typed)?;
+            (!rest.is_empty()).then_some(rest)
+        })
+    }
+
+    /// Whether a request for `text` should go out now. Returns its
+    /// generation if so; a line that's cached, too short, or still being
+    /// typed waits.
+    pub fn due(&mut self, text: &str) -> Option<u64> {
+        let edited_at = self.edited_at?;
+        if edited_at.elapsed() < IDLE {
+            return None;
+        }
+        self.edited_at = None;
+        if text.trim().chars().count() < MIN_PREFIX || text.contains('\n') || self.cache.contains_key(text) {
+            return None;
+        }
+        if self.suggestion(text).is_some() {
+            return None;
+        }
+        self.generation += 1;
+        Some(self.generation)
+    }
+
+    /// Record the answer for `prefix`, unless a newer request replaced it.
+    pub fn store(&mut self, prefix: String, suffix: String, generation: u64) -> bool {
+        if generation != self.generation {
+            return false;
+        }
+        if self.cache.len() >= MAX_CACHED {
+            self.cache.clear();
+        }
+        self.cache.insert(prefix, suffix);
+        true
+    }
+}
+
+/// The prompt for continuing `prefix`, given the shell context.
+fn prompt(prefix: &str, shell_context: &str) -> String {
+    format!(
+        "{}\nComplete this partially typed shell command. Reply with the whole command on one line \
+         and nothing else: no explanation, no code fence. If there's no likely completion, reply \
+         with the command unchanged.\n\n{}",
+        shell_context, prefix
+    )
+}
+
+/// The continuation in a model reply: what comes after `prefix` on the
+/// reply's first command line. Replies that don't extend `prefix` give
+/// nothing.
+pub fn continuation(prefix: &str, reply: &str) -> String {
+    let line = reply
+        .lines()
+        .map(str::trim_end)
+        .find(|line| !line.trim().is_empty() && !line.trim_start().starts_with("```"))
+        .unwrap_or("");
+    let line = line.trim_start().strip_prefix("$ ").unwrap_or(line.trim_start());
+    let line = line.strip_prefix('`').and_then(|l| l.strip_suffix('`')).unwrap_or(line);
+    line.strip_prefix(prefix).map(str::to_string).unwrap_or_default()
+}
+
+/// Ask the model to continue `prefix`. An empty string when it has
+/// nothing to add or the call fails.
+pub async fn suggest(prefix: String, shell_context: String, cwd: String) -> String {
+    let call = tokio::process::Command::new("claude")
+        .args(["-p", &prompt(&prefix, &shell_context)])
+        .args(["--model", MODEL])
+        .args(["--output-format", "text"])
+        .args(["--max-turns", "1"])
+        .current_dir(&cwd)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(TIMEOUT, call).await {
+        Ok(Ok(output)) if output.status.success() => continuation(&prefix, &String::from_utf8_lossy(&output.stdout)),
+        Ok(Ok(output)) => {
+            tracing::debug!("ghost completion failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+            String::new()
+        }
+        Ok(Err(e)) => {
+            tracing::debug!("ghost completion failed: {}", e);
+            String::new()
+        }
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestion_follows_typing() {
+        let mut ghost = GhostCompletion { generation: 1, ..Default::default() };
+        assert!(ghost.store("git ch".to_string(), "eckout main".to_string(), 1));
+        assert!(!ghost.store("git".to_string(), " status".to_string(), 0));
+
+        assert_eq!(ghost.suggestion("git ch"), Some("eckout main"));
+        assert_eq!(ghost.suggestion("git check"), Some("out main"));
+        assert_eq!(ghost.suggestion("git checkout main"), None);
+        assert_eq!(ghost.suggestion("git co"), None);
+
+        ghost.store("ls".to_string(), String::new(), 1);
+        assert_eq!(ghost.suggestion("ls"), None);
+    }
+
+    #[test]
+    fn test_due_waits_for_pause_and_skips_cached() {
+        let mut ghost = GhostCompletion::default();
+        assert_eq!(ghost.due("git ch"), None);
+        ghost.edited();
+        assert_eq!(ghost.due("git ch"), None);
+
+        ghost.edited_at = Some(Instant::now() - IDLE);
+        assert_eq!(ghost.due("git ch"), Some(1));
+        assert_eq!(ghost.due("git ch"), None);
+
+        ghost.store("git ch".to_string(), "eckout main".to_string(), 1);
+        ghost.edited_at = Some(Instant::now() - IDLE);
+        assert_eq!(ghost.due("git chec"), None);
+        ghost.edited_at = Some(Instant::now() - IDLE);
+        assert_eq!(ghost.due("g"), None);
+    }
+
+    #[test]
+    fn test_continuation_strips_reply_decoration() {
+        assert_eq!(continuation("git ch", "git checkout main\n"), "eckout main");
+        assert_eq!(continuation("git ch", "```bash\n$ git checkout main\n```"), "eckout main");
+        assert_eq!(continuation("git ch", "`git checkout -b feature`"), "eckout -b feature");
+        assert_eq!(continuation("git ch", "Try git checkout"), "");
+    }
+}
@@ -1,12 +1,18 @@
 //! Input widget — owns text input state, mode, history, attachments, and child widgets.
 
 pub(crate) mod completion;
+pub(crate) mod ghost;
 pub(crate) mod history;
+pub(crate) mod slash;
 
 use std::sync::Arc;
 
 use nexus_api::Value;
 use nexus_kernel::Kernel;
+use nexus_kernel::insights::UsageEvent;
+use nexus_kernel::executables::CommandStatus;
+use nexus_kernel::lint::Lint;
+use nexus_kernel::outputs::OutputPreview;
 use tokio::sync::Mutex;
 
 use strata::content_address::SourceId;
@@ -17,22 +23,35 @@ use strata::{
     Padding, Row, TextInputAction, TextInputMouseAction, TextInputState,
 };
 
-use crate::ui::widgets::{CompletionPopup, HistorySearchBar, NexusInputBar};
+use crate::ui::widgets::{
+    CompletionPopup, HistoryExpansionPreview, HistorySearchBar, LintExplanation, NexusInputBar, OutputReferencePreview,
+    SlashCommandHelp,
+};
 
 use crate::data::InputMode;
+use crate::data::provider_host::Contributions;
+use crate::data::context::NexusContext;
 use self::completion::{CompletionWidget, CompletionOutput};
 use crate::ui::context_menu::{ContextMenuItem, ContextTarget};
 use crate::app::message::ContextMenuMsg;
+use self::ghost::GhostCompletion;
 use self::history::{HistorySearchWidget, HistorySearchOutput};
 use crate::app::message::InputMsg;
 use crate::app::Attachment;
 
+/// Project tasks listed in the input's context menu; the rest are one
+/// completion away.
+const MENU_PROJECT_TASKS: usize = 20;
+
 /// Submit request returned to orchestrator when user presses Enter.
 /// The orchestrator decides whether to route to shell or agent.
 pub(crate) struct SubmitRequest {
     pub text: String,
     pub is_agent: bool,
     pub attachments: Vec<Value>,
+    /// Whether the command goes to the shell history file (see `[history]`
+    /// in the user config).
+    pub record_history: bool,
 }
 
 /// Manages all input-related state: text, mode, history, attachments, and child widgets.
@@ -55,6 +74,20 @@ pub(crate) struct InputWidget {
     pub(crate) completion_generation: u64,
     /// Monotonic generation counter for remote history search — prevents stale responses.
     pub(crate) history_generation: u64,
+    /// Preview of `!!` / `!$` / `^old^new` expansion for the current text.
+    /// `Err` holds the expansion error (e.g. "!foo: event not found").
+    pub(crate) expansion_preview: Option<Result<String, String>>,
+    /// What the `$_` / `$_N` reference before the cursor holds.
+    pub(crate) output_preview: Option<OutputPreview>,
+    /// Lint findings for the current text, underlined in the input bar.
+    pub(crate) lints: Vec<Lint>,
+    /// Byte range of a command name that names nothing runnable.
+    pub(crate) unknown_command: Option<std::ops::Range<usize>>,
+    /// Agent suggestions for the rest of the command (`[agent] ghost_completions`).
+    pub(crate) ghost: GhostCompletion,
+    /// Why the slash command just submitted couldn't run, until the text
+    /// changes.
+    pub(crate) slash_error: Option<String>,
 }
 
 impl InputWidget {
@@ -76,11 +109,31 @@ impl InputWidget {
             kernel,
             completion_generation: 0,
             history_generation: 0,
+            expansion_preview: None,
+            output_preview: None,
+            lints: Vec::new(),
+            unknown_command: None,
+            ghost: GhostCompletion::default(),
+            slash_error: None,
         }
     }
 
     /// Handle a message. Returns `Some(SubmitRequest)` if the user submitted text.
-    pub fn update(&mut self, msg: InputMsg) -> Option<SubmitRequest> {
+    /// `providers` supplies extra tab completions.
+    pub fn update(&mut self, msg: InputMsg, providers: &Contributions) -> Option<SubmitRequest> {
+        let before = self.text_input.text.clone();
+        let submit = self.dispatch(msg, providers);
+        if self.text_input.text != before {
+            self.ghost.edited();
+            self.slash_error = None;
+        }
+        self.refresh_expansion_preview();
+        self.refresh_output_preview();
+        self.refresh_lints();
+        submit
+    }
+
+    fn dispatch(&mut self, msg: InputMsg, providers: &Contributions) -> Option<SubmitRequest> {
         match msg {
             InputMsg::Key(event) => self.handle_key(&event),
             InputMsg::Mouse(action) => { self.handle_mouse(action); None }
@@ -91,7 +144,7 @@ impl InputWidget {
             InputMsg::InsertNewline => { self.insert_newline(); None }
             InputMsg::RemoveAttachment(idx) => { self.remove_attachment(idx); None }
 
-            InputMsg::TabComplete => { self.tab_complete(); None }
+            InputMsg::TabComplete => { self.tab_complete(providers); None }
             InputMsg::CompletionNav(delta) => { self.completion_nav(delta); None }
             InputMsg::CompletionAccept => { self.completion_accept(); None }
             InputMsg::CompletionDismiss => { self.completion_dismiss(); None }
@@ -120,6 +173,7 @@ impl InputWidget {
                 }
                 let output = self.completion.apply_remote_result(completions, anchor, &self.text_input.text, self.text_input.cursor);
                 self.apply_completion_output(output);
+                self.completion.preview = None;
                 None
             }
             InputMsg::RemoteHistoryResult { results, generation } => {
@@ -131,6 +185,12 @@ impl InputWidget {
                 self.history_search.index = 0;
                 None
             }
+
+            InputMsg::GhostResult { prefix, suffix, generation } => {
+                self.ghost.store(prefix, suffix, generation);
+                None
+            }
+            InputMsg::AcceptGhost => { self.accept_ghost(); None }
         }
     }
 
@@ -218,6 +278,22 @@ impl InputWidget {
     /// Insert text (paste).
     pub fn paste_text(&mut self, text: &str) {
         self.text_input.insert_str(text);
+        self.ghost.edited();
+        self.refresh_expansion_preview();
+        self.refresh_output_preview();
+        self.refresh_lints();
+    }
+
+    /// Replace the text with a shell command to finish, cursor at the end.
+    pub fn prefill_shell(&mut self, text: &str) {
+        self.mode = InputMode::Shell;
+        self.text_input.text = text.to_string();
+        self.text_input.cursor = self.text_input.text.len();
+        self.text_input.selection = None;
+        self.reset_history_nav();
+        self.refresh_expansion_preview();
+        self.refresh_output_preview();
+        self.refresh_lints();
     }
 
     /// Add a clipboard image attachment.
@@ -235,15 +311,29 @@ impl InputWidget {
     // ---- Completion delegation ----
 
     /// Trigger tab completion.
-    pub fn tab_complete(&mut self) {
+    pub fn tab_complete(&mut self, providers: &Contributions) {
         let kernel = self.kernel.clone();
-        let output = self.completion.tab_complete(&self.text_input.text, self.text_input.cursor, &kernel);
+        let output = self.completion.tab_complete(&self.text_input.text, self.text_input.cursor, &kernel, providers);
         self.apply_completion_output(output);
+        self.refresh_completion_preview();
     }
 
     /// Navigate completions by delta.
     pub fn completion_nav(&mut self, delta: isize) {
         self.completion.navigate(delta);
+        self.refresh_completion_preview();
+    }
+
+    /// Preview the directory the highlighted completion names, for the
+    /// popup's documentation pane.
+    fn refresh_completion_preview(&mut self) {
+        let completion = self.completion.selected().filter(|_| self.completion.local);
+        let preview = completion.and_then(|completion| {
+            let kernel = self.kernel.try_lock().ok()?;
+            let line = self.text_input.text.get(..self.completion.anchor)?;
+            kernel.preview_completion(line, completion)
+        });
+        self.completion.preview = preview;
     }
 
     /// Accept the current completion.
@@ -254,7 +344,11 @@ impl InputWidget {
 
     /// Dismiss the completion popup.
     pub fn completion_dismiss(&mut self) {
-        self.completion.dismiss();
+        let shown = self.completion.is_active();
+        let output = self.completion.dismiss();
+        if shown {
+            self.apply_completion_output(output);
+        }
     }
 
     /// Select a completion by index (click).
@@ -263,6 +357,27 @@ impl InputWidget {
         self.apply_completion_output(output);
     }
 
+    // ---- Ghost completion ----
+
+    /// The agent's suggested rest of the command, when there is one to
+    /// show: shell mode, one line, cursor at the end.
+    pub fn ghost_suggestion(&self) -> Option<&str> {
+        let text = &self.text_input.text;
+        if self.mode != InputMode::Shell || self.text_input.cursor != text.chars().count() {
+            return None;
+        }
+        self.ghost.suggestion(text)
+    }
+
+    /// Append the shown suggestion.
+    fn accept_ghost(&mut self) {
+        if let Some(suffix) = self.ghost_suggestion().map(str::to_string) {
+            self.text_input.text.push_str(&suffix);
+            self.text_input.cursor = self.text_input.text.chars().count();
+            self.text_input.selection = None;
+        }
+    }
+
     // ---- History search delegation ----
 
     /// Toggle history search on/off.
@@ -303,6 +418,12 @@ impl InputWidget {
         &self.shell_history
     }
 
+    /// Replace shell history, e.g. after importing another shell's.
+    pub fn set_shell_history(&mut self, history: Vec<String>) {
+        self.shell_history = history;
+        self.shell_history_index = None;
+    }
+
     /// Reset history navigation state (called after submit).
     pub fn reset_history_nav(&mut self) {
         self.shell_history_index = None;
@@ -318,6 +439,22 @@ impl InputWidget {
             return None;
         }
 
+        // Slash commands run in the UI and stay out of history.
+        match slash::parse(&text) {
+            Some(Ok(_)) => {
+                return Some(SubmitRequest { text, is_agent: false, attachments: Vec::new(), record_history: false });
+            }
+            Some(Err(e)) => {
+                self.text_input.cursor = text.len();
+                self.text_input.text = text;
+                self.slash_error = Some(e);
+                return None;
+            }
+            None => {}
+        }
+        let leading_space = submitted_text.starts_with(' ');
+        let record_history = self.kernel.blocking_lock().state().config.records_history(leading_space);
+
         let is_agent = self.mode == InputMode::Agent || text.starts_with("? ");
         let query = if text.starts_with("? ") {
             text[2..].to_string()
@@ -325,7 +462,26 @@ impl InputWidget {
             text.clone()
         };
 
-        self.push_history(&text);
+        // Expand history references before recording so `!!` never lands
+        // in history verbatim. On failure, keep the line for editing.
+        let (text, query) = if is_agent {
+            (text, query)
+        } else {
+            match self.kernel.blocking_lock().expand_history(&text) {
+                Ok(Some(expanded)) => (expanded.clone(), expanded),
+                Ok(None) => (text, query),
+                Err(e) => {
+                    self.text_input.cursor = text.len();
+                    self.text_input.text = text;
+                    self.expansion_preview = Some(Err(e.to_string()));
+                    return None;
+                }
+            }
+        };
+
+        if record_history {
+            self.push_history(&text);
+        }
 
         let attachments: Vec<Value> = if is_agent {
             self.attachments.drain(..).map(|a| {
@@ -344,17 +500,75 @@ impl InputWidget {
             text: query,
             is_agent,
             attachments,
+            record_history,
         })
     }
 
+    /// Recompute the history expansion preview for the current text.
+    ///
+    /// Uses `try_lock` so a long-running kernel command never stalls typing;
+    /// the previous preview is kept until the kernel is free again.
+    fn refresh_expansion_preview(&mut self) {
+        let text = self.text_input.text.trim();
+        if self.mode == InputMode::Agent || !(text.contains('!') || text.starts_with('^')) {
+            self.expansion_preview = None;
+            return;
+        }
+        if let Ok(kernel) = self.kernel.try_lock() {
+            self.expansion_preview = kernel
+                .expand_history(text)
+                .map_err(|e| e.to_string())
+                .transpose();
+        }
+    }
+
+    /// Recompute the preview of the output `$_` / `$_N` refers to, like
+    /// [`Self::refresh_expansion_preview`].
+    fn refresh_output_preview(&mut self) {
+        if self.mode == InputMode::Agent || !self.text_input.text.contains('_') {
+            self.output_preview = None;
+            return;
+        }
+        if let Ok(kernel) = self.kernel.try_lock() {
+            self.output_preview = kernel.preview_output(&self.text_input.text, self.text_input.cursor);
+        }
+    }
+
+    /// Re-lint the current text, like [`Self::refresh_expansion_preview`].
+    fn refresh_lints(&mut self) {
+        let text = &self.text_input.text;
+        if self.mode == InputMode::Agent || text.trim().is_empty() || !slash::matching(text).is_empty() {
+            self.lints.clear();
+            self.unknown_command = None;
+            return;
+        }
+        if let Ok(mut kernel) = self.kernel.try_lock() {
+            self.lints = kernel.lint(&self.text_input.text);
+            self.unknown_command = unknown_command(&kernel, &self.text_input.text);
+        }
+    }
+
+    /// The finding under the cursor, if any.
+    fn lint_at_cursor(&self) -> Option<&Lint> {
+        let text = &self.text_input.text;
+        let chars = |byte: usize| text.get(..byte).map_or(0, |before| before.chars().count());
+        let cursor = self.text_input.cursor;
+        self.lints.iter().find(|lint| chars(lint.range.start) <= cursor && cursor <= chars(lint.range.end))
+    }
+
     fn apply_completion_output(&mut self, output: CompletionOutput) {
-        match output {
+        let event = match output {
             CompletionOutput::Applied { text, cursor } |
             CompletionOutput::Accepted { text, cursor } => {
                 self.text_input.text = text;
                 self.text_input.cursor = cursor;
+                UsageEvent::CompletionAccepted
             }
-            CompletionOutput::None | CompletionOutput::Dismissed => {}
+            CompletionOutput::Dismissed => UsageEvent::CompletionDismissed,
+            CompletionOutput::None => return,
+        };
+        if let Ok(kernel) = self.kernel.try_lock() {
+            kernel.record_usage(&event);
         }
     }
 
@@ -403,19 +617,45 @@ impl InputWidget {
     }
 }
 
+/// Byte range of the command name in `text` when nothing by that name
+/// exists, once the user has typed past it. Never waits on a `PATH` scan.
+fn unknown_command(kernel: &Kernel, text: &str) -> Option<std::ops::Range<usize>> {
+    let range = nexus_kernel::executables::command_word_range(text).filter(|range| range.end < text.len())?;
+    matches!(kernel.command_status(text), CommandStatus::Unknown { .. }).then_some(range)
+}
+
 // =========================================================================
 // View contributions
 // =========================================================================
 
 impl InputWidget {
-    /// Build the overlays section (completion popup, history search bar).
+    /// Build the overlays section (completion popup, history search bar,
+    /// history expansion preview, `$_` output preview, lint explanation).
     pub fn layout_overlays<'a>(&'a self, mut col: Column<'a>) -> Column<'a> {
+        let slash_commands = slash::matching(&self.text_input.text);
+        if !self.completion.is_active() && (!slash_commands.is_empty() || self.slash_error.is_some()) {
+            col = col.push(SlashCommandHelp { commands: slash_commands, error: self.slash_error.as_deref() });
+        }
+
+        if let Some(lint) = self.lint_at_cursor() {
+            col = col.push(LintExplanation { lint });
+        }
+
+        if let Some(preview) = &self.expansion_preview {
+            col = col.push(HistoryExpansionPreview { preview });
+        }
+
+        if let Some(preview) = &self.output_preview {
+            col = col.push(OutputReferencePreview { preview });
+        }
+
         if self.completion.is_active() {
             col = col.push(CompletionPopup {
                 completions: &self.completion.completions,
                 selected_index: self.completion.index,
                 hovered_index: self.completion.hovered.get(),
                 scroll: &self.completion.scroll,
+                preview: self.completion.preview.as_ref(),
             });
         }
 
@@ -472,7 +712,10 @@ impl InputWidget {
         &'a self,
         mut col: Column<'a>,
         cwd: &'a str,
+        context: &'a NexusContext,
         last_exit_code: Option<i32>,
+        repl: Option<&'a str>,
+        remote: bool,
         cursor_visible: bool,
     ) -> Column<'a> {
         let line_count = {
@@ -488,9 +731,18 @@ impl InputWidget {
             input: &self.text_input,
             mode: self.mode,
             cwd,
+            path_color: context.path_color(),
+            segments: context.contributions.prompt_segments().collect(),
+            // The kernel's environments are local ones.
+            environments: if remote { &[] } else { &context.environments },
             last_exit_code,
             cursor_visible,
             line_count,
+            lints: &self.lints,
+            // The local PATH says nothing about a remote host's.
+            unknown_command: self.unknown_command.clone().filter(|_| !remote),
+            ghost: self.ghost_suggestion(),
+            repl,
         });
         col
     }
@@ -614,6 +866,9 @@ impl InputWidget {
             return Some(InputMsg::InsertNewline);
         }
         if matches!(key, Key::Named(NamedKey::Tab)) {
+            if !modifiers.shift && self.ghost_suggestion().is_some() {
+                return Some(InputMsg::AcceptGhost);
+            }
             return Some(InputMsg::TabComplete);
         }
         if matches!(key, Key::Named(NamedKey::ArrowUp)) {
@@ -681,19 +936,43 @@ impl InputWidget {
         x >= b.x && x <= b.x + b.width && y >= b.y && y <= b.y + b.height
     }
 
-    /// Build a context menu for a right-click on the input area.
-    pub fn context_menu(&self, x: f32, y: f32) -> Option<ContextMenuMsg> {
+    /// Build a context menu for a right-click on the input area: edit
+    /// actions, configured workflows and provider palette actions, sibling
+    /// worktrees to open, the current project's tasks, agent conversation
+    /// export and import, then settings and insights.
+    pub fn context_menu(&self, x: f32, y: f32, context: &NexusContext, conversation: bool) -> Option<ContextMenuMsg> {
         if !self.hit_test(x, y) {
             return None;
         }
-        Some(ContextMenuMsg::Show(
-            x, y,
-            vec![
-                ContextMenuItem::Paste,
-                ContextMenuItem::SelectAll,
-                ContextMenuItem::Clear,
-            ],
-            ContextTarget::Input,
-        ))
+        let mut items = vec![
+            ContextMenuItem::Paste,
+            ContextMenuItem::SelectAll,
+            ContextMenuItem::Clear,
+        ];
+        items.extend(context.workflows().map(|(label, command)| ContextMenuItem::RunAction { label, command }));
+        items.extend(context.contributions.palette_actions().map(|(label, command)| ContextMenuItem::RunAction {
+            label: label.to_string(),
+            command: command.to_string(),
+        }));
+        items.extend(context.contributions.worktrees().filter(|(_, _, current)| !current).map(|(path, name, _)| {
+            ContextMenuItem::OpenWorktree { label: format!("Open Worktree {} in New Window", name), path: path.to_path_buf() }
+        }));
+        let mut tasks = context.contributions.project_tasks().take(MENU_PROJECT_TASKS).peekable();
+        if tasks.peek().is_some() {
+            items.push(ContextMenuItem::Separator);
+            items.extend(tasks.map(|(label, command)| ContextMenuItem::RunAction {
+                label: label.to_string(),
+                command: command.to_string(),
+            }));
+            items.push(ContextMenuItem::Separator);
+        }
+        if conversation {
+            items.extend(crate::features::agent::AgentWidget::conversation_items());
+        } else {
+            items.push(ContextMenuItem::ImportTranscript);
+        }
+        items.push(ContextMenuItem::Settings);
+        items.push(ContextMenuItem::Insights);
+        Some(ContextMenuMsg::Show(x, y, items, ContextTarget::Input))
     }
 }
@@ -0,0 +1,187 @@
+//! Slash commands — app actions typed in the input bar.
+//!
+//! A line whose first word is `/` and one of [`COMMANDS`] is run by the UI
+//! rather than the shell or the agent: `/clear`, `/theme teal`, `/session
+//! export`, `/agent new`. It never reaches the kernel or shell history.
+//! Anything else starting with `/` (`/bin/ls -la`) is still a command.
+//!
+//! Typing `/` lists the commands that match with their help, and Tab
+//! completes their names and arguments.
+
+use nexus_api::BlockId;
+use nexus_kernel::{Completion, CompletionKind};
+
+use crate::features::agent::transcript::TranscriptFormat;
+use crate::features::settings::SWATCHES;
+use crate::features::shell::notebook::NotebookFormat;
+
+/// A slash command, for completion and help.
+pub struct SlashCommand {
+    pub name: &'static str,
+    /// Arguments it takes, each one complete; completion offers them.
+    pub args: &'static [&'static str],
+    pub help: &'static str,
+}
+
+impl SlashCommand {
+    /// How to call it, e.g. `/agent new|export|export jsonl`.
+    pub fn usage(&self) -> String {
+        match self.args {
+            [] => format!("/{}", self.name),
+            args => format!("/{} {}", self.name, args.join("|")),
+        }
+    }
+}
+
+pub(crate) const COMMANDS: &[SlashCommand] = &[
+    SlashCommand { name: "clear", args: &[], help: "Clear the screen and the conversation" },
+    SlashCommand {
+        name: "theme",
+        args: &["blue", "teal", "amber", "rose", "violet", "default"],
+        help: "Set the focus accent to a preset or #rrggbb color",
+    },
+    SlashCommand {
+        name: "session",
+        args: &["export", "export script"],
+        help: "Export this session's blocks as a notebook or shell script",
+    },
+    SlashCommand {
+        name: "agent",
+        args: &["new", "export", "export jsonl"],
+        help: "Start a new conversation, or export this one",
+    },
+    SlashCommand {
+        name: "note",
+        args: &[],
+        help: "Note a line of a block's output (<block>:<line> text); no text removes it",
+    },
+];
+
+/// What a slash command does.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SlashAction {
+    Clear,
+    /// Set `theme.accent`, or unset it for the default.
+    Theme(Option<String>),
+    ExportSession(NotebookFormat),
+    NewConversation,
+    ExportConversation(TranscriptFormat),
+    /// Put a note on output row `line` (from 0) of a block; empty text
+    /// removes it.
+    Note { block: BlockId, line: usize, text: String },
+}
+
+/// The slash command `line` runs: None when it isn't one, an error naming
+/// the usage when its arguments are wrong.
+pub(crate) fn parse(line: &str) -> Option<Result<SlashAction, String>> {
+    let rest = line.trim().strip_prefix('/')?;
+    let mut words = rest.split_whitespace();
+    let name = words.next()?;
+    let command = COMMANDS.iter().find(|command| command.name == name)?;
+    if name == "note" {
+        return Some(parse_note(rest.trim_start()[name.len()..].trim_start()));
+    }
+    let args: Vec<&str> = words.collect();
+    let action = match (name, args.as_slice()) {
+        ("clear", []) => SlashAction::Clear,
+        ("theme", ["default"]) => SlashAction::Theme(None),
+        ("theme", [color]) if crate::ui::theme::parse_hex(color).is_some() => SlashAction::Theme(Some(color.to_string())),
+        ("theme", [color]) => match SWATCHES.iter().find(|(label, _)| label.eq_ignore_ascii_case(color)) {
+            Some((_, hex)) => SlashAction::Theme(Some(hex.to_string())),
+            None => return Some(Err(format!("unknown color '{}'; usage: {}", color, command.usage()))),
+        },
+        ("session", ["export"] | ["export", "notebook"]) => SlashAction::ExportSession(NotebookFormat::Ipynb),
+        ("session", ["export", "script"]) => SlashAction::ExportSession(NotebookFormat::Script),
+        ("agent", ["new"]) => SlashAction::NewConversation,
+        ("agent", ["export"] | ["export", "markdown"]) => SlashAction::ExportConversation(TranscriptFormat::Markdown),
+        ("agent", ["export", "jsonl"]) => SlashAction::ExportConversation(TranscriptFormat::Jsonl),
+        _ => return Some(Err(format!("usage: {}", command.usage()))),
+    };
+    Some(Ok(action))
+}
+
+/// `/note`'s arguments: `<block>:<line>`, the line counted from 1, then
+/// the text as typed.
+fn parse_note(args: &str) -> Result<SlashAction, String> {
+    let (target, text) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+    let parsed = target.split_once(':').and_then(|(block, line)| Some((block.parse::<u64>().ok()?, line.parse::<usize>().ok()?)));
+    match parsed {
+        Some((block, line)) if line > 0 => {
+            Ok(SlashAction::Note { block: BlockId(block), line: line - 1, text: text.trim().to_string() })
+        }
+        _ => Err("usage: /note <block>:<line> [text]".to_string()),
+    }
+}
+
+/// The commands to show help for while `line` is typed: those whose name
+/// starts with the first word, or the one it names once arguments follow.
+pub(crate) fn matching(line: &str) -> Vec<&'static SlashCommand> {
+    let Some(rest) = line.strip_prefix('/').filter(|rest| !rest.contains('\n')) else {
+        return Vec::new();
+    };
+    match rest.split_once(char::is_whitespace) {
+        Some((name, _)) => COMMANDS.iter().filter(|command| command.name == name).collect(),
+        None => COMMANDS.iter().filter(|command| command.name.starts_with(rest)).collect(),
+    }
+}
+
+/// Completions for the slash command before `cursor`, and where the word
+/// they replace starts. None when `line` isn't a slash command, so paths
+/// complete as usual.
+pub(crate) fn complete(line: &str, cursor: usize) -> Option<(Vec<Completion>, usize)> {
+    let before = line.get(..cursor)?;
+    let rest = before.strip_prefix('/')?;
+    let candidate = |text: String| Completion { display: text.clone(), text, kind: CompletionKind::Builtin, score: 0 };
+    match rest.split_once(' ') {
+        None => {
+            let names: Vec<Completion> = matching(before).iter().map(|command| candidate(format!("/{}", command.name))).collect();
+            (!names.is_empty()).then_some((names, 0))
+        }
+        Some((name, typed)) => {
+            let command = COMMANDS.iter().find(|command| command.name == name)?;
+            let typed = typed.trim_start();
+            let args = command.args.iter().filter(|arg| arg.starts_with(typed)).map(|arg| candidate(arg.to_string()));
+            Some((args.collect(), cursor - typed.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("/clear"), Some(Ok(SlashAction::Clear)));
+        assert_eq!(parse(" /theme Teal "), Some(Ok(SlashAction::Theme(Some("#5fd7af".to_string())))));
+        assert_eq!(parse("/theme #123456"), Some(Ok(SlashAction::Theme(Some("#123456".to_string())))));
+        assert_eq!(parse("/theme default"), Some(Ok(SlashAction::Theme(None))));
+        assert_eq!(parse("/session export script"), Some(Ok(SlashAction::ExportSession(NotebookFormat::Script))));
+        assert_eq!(parse("/agent new"), Some(Ok(SlashAction::NewConversation)));
+        assert_eq!(
+            parse("/note 12:3  retries  here "),
+            Some(Ok(SlashAction::Note { block: BlockId(12), line: 2, text: "retries  here".to_string() }))
+        );
+        assert_eq!(parse("/note 12:3"), Some(Ok(SlashAction::Note { block: BlockId(12), line: 2, text: String::new() })));
+        assert!(matches!(parse("/note 12:0 x"), Some(Err(e)) if e.starts_with("usage: /note")));
+        assert!(matches!(parse("/theme dark"), Some(Err(e)) if e.contains("unknown color 'dark'")));
+        assert!(matches!(parse("/agent"), Some(Err(e)) if e == "usage: /agent new|export|export jsonl"));
+
+        // Paths and everything else are commands.
+        assert_eq!(parse("/bin/ls -la"), None);
+        assert_eq!(parse("/clearly"), None);
+        assert_eq!(parse("echo /clear"), None);
+    }
+
+    #[test]
+    fn test_complete() {
+        let texts = |found: Option<(Vec<Completion>, usize)>| {
+            found.map(|(completions, anchor)| (completions.into_iter().map(|c| c.text).collect::<Vec<_>>(), anchor))
+        };
+        assert_eq!(texts(complete("/th", 3)), Some((vec!["/theme".to_string()], 0)));
+        assert_eq!(texts(complete("/agent ex", 9)), Some((vec!["export".to_string(), "export jsonl".to_string()], 7)));
+        assert_eq!(texts(complete("/usr/bi", 7)), None);
+        assert_eq!(matching("/").len(), COMMANDS.len());
+        assert!(matching("/usr").is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+//! Insights view — what this install's features get used for.
+//!
+//! Opened from the input context menu. Shows the counts kept by
+//! `nexus_kernel::insights` (all local, nothing is sent anywhere), tips for
+//! features that haven't been tried, and a button that forgets the counts.
+
+use nexus_kernel::insights::Insights;
+use strata::content_address::SourceId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::InsightsMsg;
+use crate::utils::ids;
+
+pub(crate) struct InsightsWidget {
+    pub open: bool,
+    /// The counts as of opening (or clearing).
+    pub insights: Insights,
+    /// Set after the counts were cleared, until the view closes.
+    pub cleared: bool,
+    pub error: Option<String>,
+}
+
+impl InsightsWidget {
+    pub fn new() -> Self {
+        Self { open: false, insights: Insights::default(), cleared: false, error: None }
+    }
+
+    /// Open with freshly read counts.
+    pub fn show(&mut self, insights: anyhow::Result<Insights>) {
+        self.open = true;
+        self.cleared = false;
+        self.load(insights);
+    }
+
+    /// The counts were cleared; `insights` is what remains.
+    pub fn cleared(&mut self, result: anyhow::Result<usize>, insights: anyhow::Result<Insights>) {
+        match result {
+            Ok(_) => {
+                self.cleared = true;
+                self.load(insights);
+            }
+            Err(e) => self.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    fn load(&mut self, insights: anyhow::Result<Insights>) {
+        match insights {
+            Ok(insights) => {
+                self.insights = insights;
+                self.error = None;
+            }
+            Err(e) => {
+                self.insights = Insights::default();
+                self.error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn on_key(&self, event: &KeyEvent) -> Option<InsightsMsg> {
+        match event {
+            KeyEvent::Pressed { key: Key::Named(NamedKey::Escape), .. } => Some(InsightsMsg::Close),
+            _ => None,
+        }
+    }
+
+    pub fn on_click(&self, id: SourceId) -> Option<InsightsMsg> {
+        if id == ids::insights_close() {
+            Some(InsightsMsg::Close)
+        } else if id == ids::insights_clear() {
+            Some(InsightsMsg::Clear)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Keyboard macros — recording, naming and replay state.
+//!
+//! While recording, keys the input bar handles are appended to the
+//! recording (see `routing::on_key`). Stopping asks for a name: the next
+//! line entered in the input bar. Replay feeds the keys back through the
+//! same routing, and stops at each `confirm` marker until Enter is pressed
+//! again, so a destructive step never runs unseen.
+
+use std::collections::VecDeque;
+
+use strata::content_address::SourceId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::MacroMsg;
+use crate::data::macros::{self, Macro, MacroStep};
+use crate::utils::ids;
+
+/// A replay stopped at a `confirm` marker.
+pub(crate) struct PausedReplay {
+    pub name: String,
+    /// The steps after the marker.
+    pub rest: VecDeque<MacroStep>,
+}
+
+pub(crate) struct MacroWidget {
+    pub macros: Vec<Macro>,
+    /// Steps so far, while recording.
+    pub recording: Option<Vec<MacroStep>>,
+    /// A finished recording waiting for its name.
+    pub naming: Option<Vec<MacroStep>>,
+    pub paused: Option<PausedReplay>,
+    /// What the replay shortcut runs: the macro last saved or run.
+    pub last: Option<String>,
+    pub error: Option<String>,
+}
+
+impl MacroWidget {
+    pub fn new() -> Self {
+        let macros = macros::load();
+        let last = macros.last().map(|m| m.name.clone());
+        Self { macros, recording: None, naming: None, paused: None, last, error: None }
+    }
+
+    /// Whether the bar above the input has anything to show.
+    pub fn is_active(&self) -> bool {
+        self.recording.is_some() || self.naming.is_some() || self.paused.is_some() || self.error.is_some()
+    }
+
+    /// Start a recording, dropping any paused replay or unnamed recording.
+    pub fn start_recording(&mut self) {
+        self.stop();
+        self.recording = Some(Vec::new());
+    }
+
+    /// Stop recording. Returns whether anything was recorded, and so needs
+    /// a name.
+    pub fn finish_recording(&mut self) -> bool {
+        let steps = self.recording.take().unwrap_or_default();
+        // A trailing pause has nothing to guard.
+        let steps: Vec<_> = match steps.split_last() {
+            Some((MacroStep::Confirm, rest)) => rest.to_vec(),
+            _ => steps,
+        };
+        if steps.is_empty() {
+            return false;
+        }
+        self.naming = Some(steps);
+        true
+    }
+
+    pub fn record(&mut self, event: &KeyEvent) {
+        if let Some(steps) = &mut self.recording {
+            macros::record(steps, event);
+        }
+    }
+
+    /// Add a confirmation marker, unless the recording already ends in one.
+    pub fn insert_pause(&mut self) {
+        if let Some(steps) = &mut self.recording
+            && !matches!(steps.last(), None | Some(MacroStep::Confirm))
+        {
+            steps.push(MacroStep::Confirm);
+        }
+    }
+
+    /// Save the recording being named, replacing a macro of the same name.
+    pub fn save_as(&mut self, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        let Some(steps) = self.naming.take() else {
+            return;
+        };
+        self.macros.retain(|m| m.name != name);
+        self.macros.push(Macro { name: name.to_string(), steps });
+        self.last = Some(name.to_string());
+        self.error = macros::save(&self.macros).err().map(|e| format!("Failed to save macros: {:#}", e));
+    }
+
+    /// The steps of the macro called `name`, or of the last one saved or
+    /// run. Nothing while recording or naming.
+    pub fn start(&mut self, name: Option<String>) -> Option<(String, VecDeque<MacroStep>)> {
+        if self.recording.is_some() || self.naming.is_some() {
+            return None;
+        }
+        let name = name.or_else(|| self.last.clone())?;
+        let Some(found) = self.macros.iter().find(|m| m.name == name) else {
+            self.error = Some(format!("No macro named {}", name));
+            return None;
+        };
+        self.paused = None;
+        self.error = None;
+        self.last = Some(name.clone());
+        Some((name, found.steps.iter().cloned().collect()))
+    }
+
+    /// Drop the recording, the unnamed recording or the paused replay.
+    pub fn stop(&mut self) {
+        self.recording = None;
+        self.naming = None;
+        self.paused = None;
+        self.error = None;
+    }
+
+    /// Enter and Escape answer a paused replay or the name prompt; `input`
+    /// is the input bar's text, the name.
+    pub fn on_key(&self, event: &KeyEvent, input: &str) -> Option<MacroMsg> {
+        let KeyEvent::Pressed { key: Key::Named(key), modifiers, .. } = event else {
+            return None;
+        };
+        if modifiers.meta || (self.paused.is_none() && self.naming.is_none()) {
+            return None;
+        }
+        match key {
+            NamedKey::Escape => Some(MacroMsg::Stop),
+            NamedKey::Enter if self.paused.is_some() => Some(MacroMsg::Continue),
+            NamedKey::Enter if !input.trim().is_empty() => Some(MacroMsg::Name(input.to_string())),
+            _ => None,
+        }
+    }
+
+    pub fn on_click(&self, id: SourceId) -> Option<MacroMsg> {
+        if id == ids::macro_pause() {
+            Some(MacroMsg::InsertPause)
+        } else if id == ids::macro_stop() {
+            Some(MacroMsg::ToggleRecording)
+        } else if id == ids::macro_continue() {
+            Some(MacroMsg::Continue)
+        } else if id == ids::macro_discard() {
+            Some(MacroMsg::Stop)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strata::event_context::Modifiers;
+
+    fn widget() -> MacroWidget {
+        MacroWidget { macros: Vec::new(), recording: None, naming: None, paused: None, last: None, error: None }
+    }
+
+    fn type_text(widget: &mut MacroWidget, text: &str) {
+        for event in macros::key_events(&MacroStep::Text(text.into())) {
+            widget.record(&event);
+        }
+    }
+
+    #[test]
+    fn test_pauses_only_between_steps() {
+        let mut w = widget();
+        w.start_recording();
+        w.insert_pause();
+        type_text(&mut w, "ls");
+        w.insert_pause();
+        w.insert_pause();
+        assert_eq!(w.recording, Some(vec![MacroStep::Text("ls".into()), MacroStep::Confirm]));
+
+        assert!(w.finish_recording());
+        assert_eq!(w.naming, Some(vec![MacroStep::Text("ls".into())]));
+    }
+
+    #[test]
+    fn test_empty_recording_is_not_named() {
+        let mut w = widget();
+        w.start_recording();
+        assert!(!w.finish_recording());
+        assert!(w.naming.is_none() && w.recording.is_none());
+    }
+
+    #[test]
+    fn test_replay_refused_while_recording() {
+        let mut w = widget();
+        w.macros.push(Macro { name: "m".into(), steps: vec![MacroStep::Key("enter".into())] });
+        w.last = Some("m".into());
+        w.start_recording();
+        assert!(w.start(None).is_none());
+        w.stop();
+        assert_eq!(w.start(None).map(|(name, steps)| (name, steps.len())), Some(("m".into(), 1)));
+        assert!(w.start(Some("missing".into())).is_none());
+        assert_eq!(w.error.as_deref(), Some("No macro named missing"));
+    }
+
+    #[test]
+    fn test_enter_answers_pause_and_name_prompt() {
+        let enter = KeyEvent::Pressed { key: Key::Named(NamedKey::Enter), modifiers: Modifiers::NONE, text: None };
+        let mut w = widget();
+        assert!(w.on_key(&enter, "deploy").is_none());
+
+        w.naming = Some(vec![MacroStep::Text("x".into())]);
+        assert!(w.on_key(&enter, "  ").is_none());
+        assert!(matches!(w.on_key(&enter, "deploy"), Some(MacroMsg::Name(name)) if name == "deploy"));
+
+        w.naming = None;
+        w.paused = Some(PausedReplay { name: "deploy".into(), rest: VecDeque::new() });
+        assert!(matches!(w.on_key(&enter, ""), Some(MacroMsg::Continue)));
+    }
+}
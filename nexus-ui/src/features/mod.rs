@@ -4,3 +4,9 @@ pub mod shell;
 pub mod agent;
 pub mod input;
 pub mod selection;
+pub mod settings;
+pub mod onboarding;
+pub mod update;
+pub mod crash;
+pub mod insights;
+pub mod macros;
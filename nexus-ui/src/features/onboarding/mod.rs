@@ -0,0 +1,192 @@
+//! First-run onboarding — bring the user's shell setup along.
+//!
+//! Shown instead of the welcome screen the first time Nexus starts. It
+//! detects zsh, bash or fish, previews what importing their aliases,
+//! exported variables and PATH additions would change in
+//! `~/.nexus/config.toml`, and offers to copy their history when Nexus
+//! doesn't already share the history file. Finishing or skipping leaves
+//! `~/.nexus/onboarded` behind so it isn't offered again; `config import`
+//! does the settings part any time later.
+
+use std::path::{Path, PathBuf};
+
+use nexus_api::DiffFileInfo;
+use nexus_kernel::config::{self, Config};
+use nexus_kernel::shell_history::ShellKind;
+use nexus_kernel::shell_import::{self, ShellImport};
+use strata::content_address::SourceId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::OnboardingMsg;
+use crate::utils::ids;
+
+pub(crate) struct OnboardingWidget {
+    pub open: bool,
+    pub shell: ShellKind,
+    pub found: ShellImport,
+    /// The change importing makes to the user config; `None` when it
+    /// makes none.
+    pub diff: Option<DiffFileInfo>,
+    /// Commands to offer from the shell's history, oldest first.
+    pub history: Vec<String>,
+    /// Whether Nexus already records to the shell's history file.
+    pub history_shared: bool,
+    pub settings_imported: Option<usize>,
+    pub history_imported: Option<usize>,
+    /// Why the last step failed.
+    pub error: Option<String>,
+}
+
+impl OnboardingWidget {
+    pub fn closed() -> Self {
+        Self {
+            open: false,
+            shell: ShellKind::Unknown,
+            found: ShellImport::default(),
+            diff: None,
+            history: Vec::new(),
+            history_shared: false,
+            settings_imported: None,
+            history_imported: None,
+            error: None,
+        }
+    }
+
+    /// Onboarding for this launch: closed unless it is the first, and
+    /// skipped outright when there is nothing to bring over.
+    /// `recording_to` is the history file the kernel records to.
+    pub fn first_run(config: &Config, recording_to: Option<&Path>) -> Self {
+        let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+            return Self::closed();
+        };
+        if marker_path(&home).exists() {
+            return Self::closed();
+        }
+        let widget = Self::scan(&home, shell_import::detect_shell(&home), config, recording_to);
+        if widget.diff.is_none() && widget.history.is_empty() {
+            mark_done(&home);
+            return Self::closed();
+        }
+        widget
+    }
+
+    fn scan(home: &Path, shell: ShellKind, config: &Config, recording_to: Option<&Path>) -> Self {
+        let found = ShellImport::scan(shell, home);
+        let (diff, error) = match config::user_config_path().map(|path| found.diff(config, &path)) {
+            Some(Ok(diff)) => (diff, None),
+            Some(Err(e)) => (None, Some(format!("{:#}", e))),
+            None => (None, None),
+        };
+        let history = shell_import::history_to_import(shell, home, recording_to);
+        let history_shared = history.is_empty() && shell_import::history_file(shell, home).is_some();
+        Self { open: true, shell, found, diff, history, history_shared, error, ..Self::closed() }
+    }
+
+    /// Write the previewed settings to the user config. Returns whether
+    /// anything was written, so the caller knows to reload.
+    pub fn import_settings(&mut self, config: &Config) -> bool {
+        let Some(path) = config::user_config_path() else {
+            self.error = Some("HOME is not set".to_string());
+            return false;
+        };
+        match self.found.apply(config, &path) {
+            Ok(count) => {
+                self.settings_imported = Some(count);
+                self.diff = None;
+                self.error = None;
+                count > 0
+            }
+            Err(e) => {
+                self.error = Some(format!("{:#}", e));
+                false
+            }
+        }
+    }
+
+    pub fn history_imported(&mut self, result: anyhow::Result<usize>) {
+        match result {
+            Ok(count) => {
+                self.history_imported = Some(count);
+                self.history.clear();
+                self.error = None;
+            }
+            Err(e) => self.error = Some(format!("{:#}", e)),
+        }
+    }
+
+    /// Close, and don't offer onboarding again.
+    pub fn finish(&mut self) {
+        self.open = false;
+        if let Some(home) = std::env::var_os("HOME") {
+            mark_done(Path::new(&home));
+        }
+    }
+
+    pub fn on_key(&self, event: &KeyEvent) -> Option<OnboardingMsg> {
+        match event {
+            KeyEvent::Pressed { key: Key::Named(NamedKey::Escape), .. } => Some(OnboardingMsg::Finish),
+            _ => None,
+        }
+    }
+
+    pub fn on_click(&self, id: SourceId) -> Option<OnboardingMsg> {
+        if id == ids::onboarding_import_settings() {
+            Some(OnboardingMsg::ImportSettings)
+        } else if id == ids::onboarding_import_history() {
+            Some(OnboardingMsg::ImportHistory)
+        } else if id == ids::onboarding_done() {
+            Some(OnboardingMsg::Finish)
+        } else {
+            None
+        }
+    }
+
+    pub fn shell_name(&self) -> &'static str {
+        match self.shell {
+            ShellKind::Zsh => "zsh",
+            ShellKind::Bash => "bash",
+            ShellKind::Fish => "fish",
+            ShellKind::Unknown => "shell",
+        }
+    }
+}
+
+/// `~/.nexus/onboarded`, present once onboarding has been finished or skipped.
+fn marker_path(home: &Path) -> PathBuf {
+    home.join(".nexus").join("onboarded")
+}
+
+fn mark_done(home: &Path) {
+    let path = marker_path(home);
+    let written = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(&path, ""));
+    if let Err(e) = written {
+        tracing::warn!("onboarding: failed to write {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_import_result() {
+        let mut widget = OnboardingWidget::closed();
+        widget.history = vec!["make".to_string()];
+        widget.history_imported(Err(anyhow::anyhow!("disk full")));
+        assert_eq!(widget.history.len(), 1);
+        assert_eq!(widget.error.as_deref(), Some("disk full"));
+
+        widget.history_imported(Ok(1));
+        assert!(widget.history.is_empty());
+        assert_eq!(widget.history_imported, Some(1));
+        assert_eq!(widget.error, None);
+    }
+
+    #[test]
+    fn test_clicks_map_to_steps() {
+        let widget = OnboardingWidget::closed();
+        assert_eq!(widget.on_click(ids::onboarding_import_history()), Some(OnboardingMsg::ImportHistory));
+        assert_eq!(widget.on_click(ids::onboarding_done()), Some(OnboardingMsg::Finish));
+        assert_eq!(widget.on_click(ids::settings_close()), None);
+    }
+}
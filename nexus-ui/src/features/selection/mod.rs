@@ -152,6 +152,24 @@ impl SelectionWidget {
         }
     }
 
+    /// Shell blocks the current selection touches, in display order.
+    pub fn selected_blocks(&self, blocks: &[Block], agent_blocks: &[AgentBlock]) -> Vec<BlockId> {
+        let Some(sel) = self.selection.as_ref().filter(|sel| !sel.is_collapsed()) else {
+            return Vec::new();
+        };
+        let ordering = build_source_ordering(blocks, agent_blocks);
+        let sources = sel.sources(&ordering);
+        blocks
+            .iter()
+            .filter(|block| {
+                [source_ids::shell_header(block.id), source_ids::shell_term(block.id), source_ids::native(block.id), source_ids::table(block.id)]
+                    .iter()
+                    .any(|id| sources.contains(id))
+            })
+            .map(|block| block.id)
+            .collect()
+    }
+
     /// Check if a click hit falls inside the current non-collapsed selection.
     ///
     /// Returns `(source_id, content_address)` for starting a selection drag,
@@ -259,6 +277,48 @@ impl SelectionWidget {
     }
 }
 
+/// A shell block's output as text: native values formatted, else the
+/// terminal grid with trailing blank lines dropped.
+pub(crate) fn block_output_text(block: &Block) -> Option<String> {
+    // If the block has native output, convert it to text
+    if let Some(ref value) = block.structured_output {
+        // Format tables as markdown
+        if let nexus_api::Value::Table { columns, rows } = value {
+            return Some(format_table_as_markdown(columns, rows));
+        }
+        return Some(value.to_text());
+    }
+
+    // Otherwise extract from terminal grid (match view's grid selection)
+    let grid = if block.parser.is_alternate_screen() {
+        block.parser.grid()
+    } else {
+        block.parser.grid_with_scrollback()
+    };
+
+    let mut lines = Vec::new();
+    for row in grid.rows_iter() {
+        let mut text = String::with_capacity(row.len());
+        for cell in row {
+            if cell.flags.wide_char_spacer { continue; }
+            cell.push_grapheme(&mut text);
+        }
+        let trimmed = text.trim_end();
+        if !trimmed.is_empty() || !lines.is_empty() {
+            lines.push(trimmed.to_string());
+        }
+    }
+    // Trim trailing empty lines
+    while lines.last().map_or(false, |l| l.is_empty()) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
 /// Extract all text from a specific block (for context menu Copy).
 pub(crate) fn extract_block_text(
     bm: &BlockManager,
@@ -268,47 +328,7 @@ pub(crate) fn extract_block_text(
     target: &ContextTarget,
 ) -> Option<String> {
     match target {
-        ContextTarget::Block(block_id) => {
-            let block = bm.get(*block_id)?;
-
-            // If the block has native output, convert it to text
-            if let Some(ref value) = block.structured_output {
-                // Format tables as markdown
-                if let nexus_api::Value::Table { columns, rows } = value {
-                    return Some(format_table_as_markdown(columns, rows));
-                }
-                return Some(value.to_text());
-            }
-
-            // Otherwise extract from terminal grid (match view's grid selection)
-            let grid = if block.parser.is_alternate_screen() {
-                block.parser.grid()
-            } else {
-                block.parser.grid_with_scrollback()
-            };
-
-            let mut lines = Vec::new();
-            for row in grid.rows_iter() {
-                let mut text = String::with_capacity(row.len());
-                for cell in row {
-                    if cell.flags.wide_char_spacer { continue; }
-                    cell.push_grapheme(&mut text);
-                }
-                let trimmed = text.trim_end();
-                if !trimmed.is_empty() || !lines.is_empty() {
-                    lines.push(trimmed.to_string());
-                }
-            }
-            // Trim trailing empty lines
-            while lines.last().map_or(false, |l| l.is_empty()) {
-                lines.pop();
-            }
-            if lines.is_empty() {
-                None
-            } else {
-                Some(lines.join("\n"))
-            }
-        }
+        ContextTarget::Block(block_id) => block_output_text(bm.get(*block_id)?),
         ContextTarget::AgentBlock(block_id) => {
             let idx = agent_block_index.get(block_id)?;
             let block = agent_blocks.get(*idx)?;
@@ -368,8 +388,14 @@ pub(crate) fn build_source_ordering(blocks: &[Block], agent_blocks: &[AgentBlock
                 if !block.thinking.is_empty() && !block.thinking_collapsed {
                     ordering.register(source_ids::agent_thinking(block.id));
                 }
-                for (i, _tool) in block.tools.iter().enumerate() {
+                if !block.plan.is_empty() && !block.plan_collapsed {
+                    ordering.register(source_ids::agent_plan(block.id));
+                }
+                for (i, tool) in block.tools.iter().enumerate() {
                     ordering.register(source_ids::agent_tool(block.id, i));
+                    if tool.terminal.is_some() && !tool.collapsed {
+                        ordering.register(source_ids::agent_tool_term(block.id, i));
+                    }
                 }
                 if block.pending_permission.is_some() {
                     ordering.register(source_ids::agent_perm_text(block.id));
@@ -448,21 +474,7 @@ fn extract_shell_source(
         } else {
             block.parser.grid_with_scrollback()
         };
-        let cols = grid.cols() as usize;
-        if cols == 0 {
-            return Some(String::new());
-        }
-
-        let start_offset = if is_start { start.content_offset } else { 0 };
-        let total_cells = grid.content_rows() as usize * cols;
-        let end_offset = if is_end { end.content_offset } else { total_cells };
-
-        if start_offset >= end_offset {
-            return Some(String::new());
-        }
-
-        let rows: Vec<Vec<nexus_term::Cell>> = grid.rows_iter().map(|r| r.to_vec()).collect();
-        return Some(extract_grid_range(&rows, cols, start_offset, end_offset));
+        return Some(extract_grid_source(&grid, is_start, is_end, start, end, extract_grid_range));
     }
 
     if source_id == source_ids::native(block.id) {
@@ -522,10 +534,21 @@ fn extract_agent_source(
         return Some(extract(preview));
     }
 
+    if source_id == source_ids::agent_plan(block.id) {
+        let items = plan_items(block);
+        return Some(extract_multi_item_range(&items, is_start, is_end, start, end));
+    }
+
     for (i, tool) in block.tools.iter().enumerate() {
         if source_id == source_ids::agent_tool(block.id, i) {
             return Some(extract(tool.extract_text()));
         }
+        if let Some(terminal) = &tool.terminal
+            && source_id == source_ids::agent_tool_term(block.id, i)
+        {
+            let grid = terminal.grid_with_scrollback();
+            return Some(extract_grid_source(&grid, is_start, is_end, start, end, extract_grid_range));
+        }
     }
 
     if let Some(ref perm) = block.pending_permission {
@@ -563,6 +586,43 @@ fn extract_agent_source(
     None
 }
 
+/// The plan panel's text elements in order: each step's checkbox, then its
+/// content.
+fn plan_items(block: &AgentBlock) -> Vec<&str> {
+    block
+        .plan
+        .iter()
+        .flat_map(|step| [step.status.checkbox(), step.content.as_str()])
+        .collect()
+}
+
+/// Text of a terminal grid between two selection addresses, cut linearly
+/// or as a rectangle by `extract`.
+fn extract_grid_source(
+    grid: &nexus_term::TerminalGrid,
+    is_start: bool,
+    is_end: bool,
+    start: &ContentAddress,
+    end: &ContentAddress,
+    extract: fn(&[Vec<nexus_term::Cell>], usize, usize, usize) -> String,
+) -> String {
+    let cols = grid.cols() as usize;
+    if cols == 0 {
+        return String::new();
+    }
+
+    let start_offset = if is_start { start.content_offset } else { 0 };
+    let total_cells = grid.content_rows() as usize * cols;
+    let end_offset = if is_end { end.content_offset } else { total_cells };
+
+    if start_offset >= end_offset {
+        return String::new();
+    }
+
+    let rows: Vec<Vec<nexus_term::Cell>> = grid.rows_iter().map(|r| r.to_vec()).collect();
+    extract(&rows, cols, start_offset, end_offset)
+}
+
 /// Extract a range of characters from a terminal grid.
 fn extract_grid_range(rows: &[Vec<nexus_term::Cell>], cols: usize, start: usize, end: usize) -> String {
     let start_row = start / cols;
@@ -675,21 +735,7 @@ fn extract_shell_source_rect(
         } else {
             block.parser.grid_with_scrollback()
         };
-        let cols = grid.cols() as usize;
-        if cols == 0 {
-            return Some(String::new());
-        }
-
-        let start_offset = if is_start { start.content_offset } else { 0 };
-        let total_cells = grid.content_rows() as usize * cols;
-        let end_offset = if is_end { end.content_offset } else { total_cells };
-
-        if start_offset >= end_offset {
-            return Some(String::new());
-        }
-
-        let rows: Vec<Vec<nexus_term::Cell>> = grid.rows_iter().map(|r| r.to_vec()).collect();
-        return Some(extract_grid_range_rect(&rows, cols, start_offset, end_offset));
+        return Some(extract_grid_source(&grid, is_start, is_end, start, end, extract_grid_range_rect));
     }
 
     if source_id == source_ids::native(block.id) {
@@ -747,10 +793,21 @@ fn extract_agent_source_rect(
         return Some(extract(preview));
     }
 
+    if source_id == source_ids::agent_plan(block.id) {
+        let items = plan_items(block);
+        return Some(extract_multi_item_range_rect(&items, is_start, is_end, start, end));
+    }
+
     for (i, tool) in block.tools.iter().enumerate() {
         if source_id == source_ids::agent_tool(block.id, i) {
             return Some(extract(tool.extract_text()));
         }
+        if let Some(terminal) = &tool.terminal
+            && source_id == source_ids::agent_tool_term(block.id, i)
+        {
+            let grid = terminal.grid_with_scrollback();
+            return Some(extract_grid_source(&grid, is_start, is_end, start, end, extract_grid_range_rect));
+        }
     }
 
     if let Some(ref perm) = block.pending_permission {
@@ -870,7 +927,7 @@ fn extract_multi_item_range_rect(
 }
 
 /// Format a table as a markdown table.
-fn format_table_as_markdown(columns: &[nexus_api::TableColumn], rows: &[Vec<nexus_api::Value>]) -> String {
+pub(crate) fn format_table_as_markdown(columns: &[nexus_api::TableColumn], rows: &[Vec<nexus_api::Value>]) -> String {
     let mut lines: Vec<String> = Vec::new();
 
     // Header row
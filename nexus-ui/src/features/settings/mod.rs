@@ -0,0 +1,409 @@
+//! Settings view — edits `~/.nexus/config.toml` in place.
+//!
+//! Opened with Cmd+, or from the input context menu. Every change is written
+//! straight to the user config (comments and layout survive) and the config
+//! is reloaded, so colors, font size, keybindings and history privacy apply
+//! at once and agent limits from the next query. A value a project
+//! `.nexus/config.toml` sets still wins there; its row says so.
+
+use std::path::{Path, PathBuf};
+
+use nexus_kernel::config::{self, ColorMode, Config, Origin, PagerMode, SandboxPolicy, Setting};
+use strata::content_address::SourceId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::SettingsMsg;
+use crate::data::keymap::{self, Action};
+use crate::utils::ids;
+
+/// Preset colors offered for `theme.accent` and `theme.path`.
+pub(crate) const SWATCHES: [(&str, &str); 5] = [
+    ("Blue", "#4db3ff"),
+    ("Teal", "#5fd7af"),
+    ("Amber", "#ffaf5f"),
+    ("Rose", "#ff87af"),
+    ("Violet", "#af87ff"),
+];
+
+/// `font.size` when unset, for stepping from.
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+const FONT_SIZE_RANGE: (f64, f64) = (8.0, 32.0);
+/// `agent.max_turns` when unset (the CLI wrapper's default).
+const DEFAULT_MAX_TURNS: i64 = 100;
+const MAX_TURNS_STEP: i64 = 10;
+
+/// A value to write to the config file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl SettingValue {
+    fn write(self, path: &Path, key: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Bool(v) => config::set_setting(path, key, v),
+            Self::Int(v) => config::set_setting(path, key, v),
+            Self::Float(v) => config::set_setting(path, key, v),
+            Self::Text(v) => config::set_setting(path, key, v),
+        }
+    }
+}
+
+/// A change for the root to write and reload.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Edit {
+    Set(String, SettingValue),
+    Unset(String),
+}
+
+impl Edit {
+    /// Apply to the config file at `path`.
+    pub fn write(self, path: &Path) -> anyhow::Result<()> {
+        match self {
+            Self::Set(key, value) => value.write(path, &key),
+            Self::Unset(key) => config::unset_setting(path, &key),
+        }
+    }
+}
+
+/// One clickable option in a row.
+#[derive(Debug, Clone)]
+pub(crate) struct Choice {
+    pub label: String,
+    pub selected: bool,
+    /// Hex color shown on the button, for color presets.
+    pub swatch: Option<&'static str>,
+    pub msg: SettingsMsg,
+}
+
+/// One setting, as the view shows it.
+#[derive(Debug, Clone)]
+pub(crate) struct SettingRow {
+    pub section: &'static str,
+    pub label: String,
+    pub value: String,
+    /// Project file whose value wins over what this view writes.
+    pub overridden_by: Option<PathBuf>,
+    pub choices: Vec<Choice>,
+}
+
+pub(crate) struct SettingsWidget {
+    pub open: bool,
+    /// Action waiting for its new Cmd chord.
+    pub capturing: Option<Action>,
+    /// Why the last change could not be saved.
+    pub error: Option<String>,
+}
+
+impl SettingsWidget {
+    pub fn new() -> Self {
+        Self { open: false, capturing: None, error: None }
+    }
+
+    /// Handle a message. Returns the change to save, if any.
+    pub fn update(&mut self, msg: SettingsMsg) -> Option<Edit> {
+        match msg {
+            SettingsMsg::Open => {
+                self.open = true;
+                self.error = None;
+            }
+            SettingsMsg::Toggle => {
+                self.open = !self.open;
+                self.capturing = None;
+                self.error = None;
+            }
+            SettingsMsg::Close => {
+                self.open = false;
+                self.capturing = None;
+            }
+            SettingsMsg::Set(key, value) => return Some(Edit::Set(key, value)),
+            SettingsMsg::Unset(key) => return Some(Edit::Unset(key)),
+            SettingsMsg::Capture(action) => self.capturing = Some(action),
+            SettingsMsg::CancelCapture => self.capturing = None,
+            SettingsMsg::Captured(key) => {
+                let action = self.capturing.take()?;
+                return Some(Edit::Set(binding_key(action), SettingValue::Text(keymap::chord(&key))));
+            }
+        }
+        None
+    }
+
+    /// Keys while the view is open: Escape closes it, and while rebinding
+    /// the next Cmd chord becomes the binding.
+    pub fn on_key(&self, event: &KeyEvent) -> Option<SettingsMsg> {
+        let KeyEvent::Pressed { key, modifiers, .. } = event else {
+            return None;
+        };
+        match (self.capturing, key) {
+            (_, Key::Named(NamedKey::Escape)) if self.capturing.is_some() => Some(SettingsMsg::CancelCapture),
+            (_, Key::Named(NamedKey::Escape)) => Some(SettingsMsg::Close),
+            (Some(_), Key::Character(c)) if modifiers.meta && c.chars().count() == 1 => {
+                Some(SettingsMsg::Captured(c.to_lowercase()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn on_click(&self, id: SourceId, config: &Config) -> Option<SettingsMsg> {
+        if id == ids::settings_close() {
+            return Some(SettingsMsg::Close);
+        }
+        self.rows(config).into_iter().enumerate().find_map(|(r, row)| {
+            row.choices.into_iter().enumerate().find(|(c, _)| id == ids::settings_choice(r, *c)).map(|(_, c)| c.msg)
+        })
+    }
+
+    /// The rows to show for `config`, grouped by section.
+    pub fn rows(&self, config: &Config) -> Vec<SettingRow> {
+        let mut rows = vec![
+            color_row("Theme", "Focus accent", "theme.accent", &config.accent),
+            color_row("Theme", "Prompt path", "theme.path", &config.path_color),
+            toggle_row("Theme", "Pair status colors with symbols", "theme.symbols", &config.theme_symbols, false),
+            font_row(config),
+            toggle_row("Font", "Ambiguous-width characters are wide", "font.ambiguous_wide", &config.font_ambiguous_wide, false),
+        ];
+        rows.extend(Action::ALL.into_iter().map(|action| self.binding_row(config, action)));
+        rows.push(toggle_row(
+            "History & privacy",
+            "Save commands to shell history",
+            "history.record",
+            &config.history_record,
+            true,
+        ));
+        rows.push(toggle_row(
+            "History & privacy",
+            "Skip commands typed with a leading space",
+            "history.ignore_space",
+            &config.history_ignore_space,
+            false,
+        ));
+        rows.push(max_turns_row(config));
+        rows.push(sandbox_row(config));
+        rows.push(pager_row(config));
+        rows.push(color_mode_row(config));
+        rows.push(toggle_row("Updates", "Check for new releases at startup", "updates.check", &config.updates_check, false));
+        rows
+    }
+
+    fn binding_row(&self, config: &Config, action: Action) -> SettingRow {
+        let key = binding_key(action);
+        let setting = config.keybindings.get(action.name());
+        let value = if self.capturing == Some(action) {
+            "press a Cmd chord\u{2026}".to_string()
+        } else {
+            keymap::chord(&keymap::key_for(config, action))
+        };
+        SettingRow {
+            section: "Keybindings",
+            label: action.label().to_string(),
+            value,
+            overridden_by: project_path(setting),
+            choices: vec![
+                choice("Change", self.capturing == Some(action), SettingsMsg::Capture(action)),
+                choice("Default", setting.is_none(), SettingsMsg::Unset(key)),
+            ],
+        }
+    }
+}
+
+fn binding_key(action: Action) -> String {
+    format!("keybindings.{}", action.name())
+}
+
+fn choice(label: &str, selected: bool, msg: SettingsMsg) -> Choice {
+    Choice { label: label.to_string(), selected, swatch: None, msg }
+}
+
+fn project_path<T>(setting: Option<&Setting<T>>) -> Option<PathBuf> {
+    match &setting?.origin {
+        Origin::Project(path) => Some(path.clone()),
+        Origin::User(_) => None,
+    }
+}
+
+fn color_row(section: &'static str, label: &str, key: &str, setting: &Option<Setting<String>>) -> SettingRow {
+    let current = setting.as_ref().map(|s| s.value.to_lowercase());
+    let mut choices = vec![choice("Default", current.is_none(), SettingsMsg::Unset(key.to_string()))];
+    choices.extend(SWATCHES.iter().map(|(name, hex)| Choice {
+        label: name.to_string(),
+        selected: current.as_deref() == Some(*hex),
+        swatch: Some(*hex),
+        msg: SettingsMsg::Set(key.to_string(), SettingValue::Text(hex.to_string())),
+    }));
+    SettingRow {
+        section,
+        label: label.to_string(),
+        value: current.unwrap_or_else(|| "default".to_string()),
+        overridden_by: project_path(setting.as_ref()),
+        choices,
+    }
+}
+
+fn font_row(config: &Config) -> SettingRow {
+    let size = config.font_size().map(f64::from);
+    let current = size.unwrap_or(DEFAULT_FONT_SIZE);
+    let step = |delta: f64| {
+        let size = (current + delta).clamp(FONT_SIZE_RANGE.0, FONT_SIZE_RANGE.1);
+        SettingsMsg::Set("font.size".to_string(), SettingValue::Float(size))
+    };
+    SettingRow {
+        section: "Font",
+        label: "Size".to_string(),
+        value: size.map_or_else(|| "default".to_string(), |s| format!("{}pt", s)),
+        overridden_by: project_path(config.font_size.as_ref()),
+        choices: vec![
+            choice("\u{2212}", false, step(-1.0)),
+            choice("+", false, step(1.0)),
+            choice("Default", size.is_none(), SettingsMsg::Unset("font.size".to_string())),
+        ],
+    }
+}
+
+fn toggle_row(section: &'static str, label: &str, key: &str, setting: &Option<Setting<bool>>, default: bool) -> SettingRow {
+    let on = setting.as_ref().map_or(default, |s| s.value);
+    let set = |value| SettingsMsg::Set(key.to_string(), SettingValue::Bool(value));
+    SettingRow {
+        section,
+        label: label.to_string(),
+        value: if on { "on" } else { "off" }.to_string(),
+        overridden_by: None,
+        choices: vec![choice("On", on, set(true)), choice("Off", !on, set(false))],
+    }
+}
+
+fn max_turns_row(config: &Config) -> SettingRow {
+    let turns = config.agent_max_turns().map(i64::from);
+    let current = turns.unwrap_or(DEFAULT_MAX_TURNS);
+    let step = |delta: i64| {
+        let turns = (current + delta).max(1);
+        SettingsMsg::Set("agent.max_turns".to_string(), SettingValue::Int(turns))
+    };
+    SettingRow {
+        section: "Agent",
+        label: "Max turns per query".to_string(),
+        value: turns.map_or_else(|| format!("{} (default)", DEFAULT_MAX_TURNS), |t| t.to_string()),
+        overridden_by: None,
+        choices: vec![
+            choice(&format!("\u{2212}{}", MAX_TURNS_STEP), false, step(-MAX_TURNS_STEP)),
+            choice(&format!("+{}", MAX_TURNS_STEP), false, step(MAX_TURNS_STEP)),
+            choice("Default", turns.is_none(), SettingsMsg::Unset("agent.max_turns".to_string())),
+        ],
+    }
+}
+
+fn sandbox_row(config: &Config) -> SettingRow {
+    let current = config.sandbox_policy();
+    let choices = SandboxPolicy::ALL
+        .into_iter()
+        .map(|policy| {
+            let label = match policy {
+                SandboxPolicy::Ask => "Ask first",
+                SandboxPolicy::AcceptEdits => "Edit files",
+                SandboxPolicy::ReadOnly => "Read only",
+            };
+            let msg = SettingsMsg::Set("sandbox.agent".to_string(), SettingValue::Text(policy.as_str().to_string()));
+            choice(label, policy == current, msg)
+        })
+        .collect();
+    SettingRow {
+        section: "Sandbox",
+        label: "Without asking, the agent may".to_string(),
+        value: current.as_str().to_string(),
+        overridden_by: None,
+        choices,
+    }
+}
+
+fn pager_row(config: &Config) -> SettingRow {
+    let current = config.pager_mode.as_ref().map(|s| s.value).unwrap_or_default();
+    let choices = PagerMode::ALL
+        .into_iter()
+        .map(|mode| {
+            let label = match mode {
+                PagerMode::Disable => "Print output",
+                PagerMode::Capture => "Open in viewer",
+                PagerMode::Keep => "Leave alone",
+            };
+            let msg = SettingsMsg::Set("pager.mode".to_string(), SettingValue::Text(mode.as_str().to_string()));
+            choice(label, mode == current, msg)
+        })
+        .collect();
+    SettingRow {
+        section: "Pager",
+        label: "When a command would start a pager".to_string(),
+        value: current.as_str().to_string(),
+        overridden_by: project_path(config.pager_mode.as_ref()),
+        choices,
+    }
+}
+
+fn color_mode_row(config: &Config) -> SettingRow {
+    let current = config.color_mode.as_ref().map(|s| s.value).unwrap_or_default();
+    let choices = ColorMode::ALL
+        .into_iter()
+        .map(|mode| {
+            let label = match mode {
+                ColorMode::Auto => "In blocks",
+                ColorMode::Always => "Always",
+                ColorMode::Never => "Never",
+            };
+            let msg = SettingsMsg::Set("color.mode".to_string(), SettingValue::Text(mode.as_str().to_string()));
+            choice(label, mode == current, msg)
+        })
+        .collect();
+    SettingRow {
+        section: "Color",
+        label: "Ask commands for color".to_string(),
+        value: current.as_str().to_string(),
+        overridden_by: project_path(config.color_mode.as_ref()),
+        choices,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'r>(rows: &'r [SettingRow], label: &str) -> &'r SettingRow {
+        rows.iter().find(|r| r.label == label).unwrap()
+    }
+
+    #[test]
+    fn test_rows_reflect_config_and_origin() {
+        let mut config = Config::default();
+        config.accent = Some(Setting {
+            value: "#4DB3FF".to_string(),
+            origin: Origin::Project(PathBuf::from("/repo/.nexus/config.toml")),
+        });
+        let rows = SettingsWidget::new().rows(&config);
+
+        let accent = find(&rows, "Focus accent");
+        assert!(accent.choices.iter().any(|c| c.selected && c.label == "Blue"));
+        assert_eq!(accent.overridden_by, Some(PathBuf::from("/repo/.nexus/config.toml")));
+        let sandbox = find(&rows, "Without asking, the agent may");
+        assert!(sandbox.choices[0].selected);
+    }
+
+    #[test]
+    fn test_capture_sets_binding() {
+        let mut widget = SettingsWidget::new();
+        assert_eq!(widget.update(SettingsMsg::Capture(Action::ClearScreen)), None);
+        let edit = widget.update(SettingsMsg::Captured("l".to_string()));
+        assert_eq!(
+            edit,
+            Some(Edit::Set("keybindings.clear_screen".to_string(), SettingValue::Text("cmd+l".to_string())))
+        );
+        assert_eq!(widget.capturing, None);
+        assert_eq!(widget.update(SettingsMsg::Captured("m".to_string())), None);
+    }
+
+    #[test]
+    fn test_font_steps_from_default() {
+        let rows = SettingsWidget::new().rows(&Config::default());
+        let plus = &find(&rows, "Size").choices[1];
+        assert!(matches!(&plus.msg, SettingsMsg::Set(k, SettingValue::Float(s)) if k == "font.size" && *s == 13.0));
+    }
+}
@@ -1,12 +1,28 @@
-//! Block storage — owns the block list, block-ID-to-index map, and image handles.
+//! Block storage — owns the block list, block-ID-to-index map, image handles,
+//! and the crash journal of running blocks.
 
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use nexus_api::BlockId;
+use nexus_kernel::journal::BlockJournal;
 use strata::ImageHandle;
 
 use crate::data::Block;
 
+/// How long a hidden block can be brought back.
+pub(crate) const UNDO_WINDOW: Duration = Duration::from_secs(8);
+
+/// A block the user hid, held until its undo toast runs out.
+pub(crate) struct HiddenBlock {
+    pub block: Block,
+    /// Where it sat in the block list.
+    pub index: usize,
+    /// Also delete the Store's copy once it can no longer be brought back.
+    pub forget: bool,
+    pub hidden_at: Instant,
+}
+
 /// Manages the block list, block-ID index, and decoded image handles.
 ///
 /// All mutations to the block list should go through `BlockManager` methods
@@ -20,6 +36,9 @@ pub(crate) struct BlockManager {
     pub image_handles: HashMap<BlockId, (ImageHandle, u32, u32)>,
     /// Decoded image handles for table cells: (block_id, data_row_index, col_index) → (handle, w, h).
     pub table_cell_images: HashMap<(BlockId, usize, usize), (ImageHandle, u32, u32)>,
+    /// Crash journal: mirrors running blocks' output to disk. Disabled until
+    /// the window enables it, so tests never touch `~/.nexus`.
+    pub journal: BlockJournal,
 }
 
 impl BlockManager {
@@ -29,6 +48,7 @@ impl BlockManager {
             block_index: HashMap::new(),
             image_handles: HashMap::new(),
             table_cell_images: HashMap::new(),
+            journal: BlockJournal::disabled(),
         }
     }
 
@@ -51,8 +71,11 @@ impl BlockManager {
         self.block_index.contains_key(&id)
     }
 
-    /// Append a block, updating the index.
+    /// Append a block, updating the index. Running blocks are journaled.
     pub fn push(&mut self, block: Block) {
+        if block.is_running() {
+            self.journal.started(block.id, &block.command);
+        }
         let idx = self.blocks.len();
         self.block_index.insert(block.id, idx);
         self.blocks.push(block);
@@ -90,6 +113,33 @@ impl BlockManager {
         self.blocks.is_empty()
     }
 
+    /// Remove the given blocks and their images, rebuilding the index.
+    pub fn remove(&mut self, ids: &[BlockId]) {
+        self.blocks.retain(|block| !ids.contains(&block.id));
+        self.reindex();
+        self.image_handles.retain(|id, _| !ids.contains(id));
+        self.table_cell_images.retain(|(id, _, _), _| !ids.contains(id));
+    }
+
+    /// Take a block out of the list with its position, keeping its images
+    /// for `restore`.
+    pub fn take(&mut self, id: BlockId) -> Option<(usize, Block)> {
+        let idx = *self.block_index.get(&id)?;
+        let block = self.blocks.remove(idx);
+        self.reindex();
+        Some((idx, block))
+    }
+
+    /// Put a block from `take` back where it was.
+    pub fn restore(&mut self, idx: usize, block: Block) {
+        self.blocks.insert(idx.min(self.blocks.len()), block);
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.block_index = self.blocks.iter().enumerate().map(|(idx, block)| (block.id, idx)).collect();
+    }
+
     /// Clear all blocks, the index, and image handles.
     pub fn clear(&mut self) {
         self.blocks.clear();
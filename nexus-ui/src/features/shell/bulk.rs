@@ -0,0 +1,157 @@
+//! Multi-block selection and the groups made from it.
+//!
+//! Blocks are picked by shift-clicking their headers. The picked set is
+//! what the selection bar's bulk actions (copy, export, send to agent,
+//! group, remove) apply to; Esc drops it.
+
+use nexus_api::BlockId;
+
+use super::notebook::NotebookFormat;
+use crate::data::Block;
+use crate::features::selection::block_output_text;
+
+/// Something to do with every selected block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkAction {
+    /// Commands and output to the clipboard.
+    Copy,
+    Export(NotebookFormat),
+    /// Commands and output ahead of the next agent query.
+    SendToAgent,
+    /// Fold the blocks under one collapsible header.
+    Group,
+    /// Take finished blocks out of the session.
+    Remove,
+}
+
+/// Blocks folded under one header, in display order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlockGroup {
+    pub blocks: Vec<BlockId>,
+    pub collapsed: bool,
+}
+
+impl BlockGroup {
+    /// The block the group's header is drawn above; also its identity.
+    pub fn first(&self) -> BlockId {
+        self.blocks[0]
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BulkSelection {
+    /// Picked blocks, in the order they were picked.
+    pub selected: Vec<BlockId>,
+    pub groups: Vec<BlockGroup>,
+}
+
+impl BulkSelection {
+    pub fn toggle(&mut self, id: BlockId) {
+        match self.selected.iter().position(|&s| s == id) {
+            Some(i) => {
+                self.selected.remove(i);
+            }
+            None => self.selected.push(id),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.selected.clear();
+    }
+
+    pub fn is_selected(&self, id: BlockId) -> bool {
+        self.selected.contains(&id)
+    }
+
+    /// The selected blocks in `order` (the display order).
+    pub fn in_order(&self, order: impl IntoIterator<Item = BlockId>) -> Vec<BlockId> {
+        order.into_iter().filter(|id| self.is_selected(*id)).collect()
+    }
+
+    /// Fold `blocks` (in display order) into a new collapsed group, taking
+    /// them out of any group they were in. Fewer than two is not a group.
+    pub fn group(&mut self, blocks: Vec<BlockId>) {
+        if blocks.len() < 2 {
+            return;
+        }
+        self.leave_groups(&blocks);
+        self.groups.push(BlockGroup { blocks, collapsed: true });
+    }
+
+    /// The group a block belongs to.
+    pub fn group_of(&self, id: BlockId) -> Option<&BlockGroup> {
+        self.groups.iter().find(|group| group.blocks.contains(&id))
+    }
+
+    pub fn toggle_group(&mut self, first: BlockId) {
+        if let Some(group) = self.groups.iter_mut().find(|group| group.first() == first) {
+            group.collapsed = !group.collapsed;
+        }
+    }
+
+    pub fn ungroup(&mut self, first: BlockId) {
+        self.groups.retain(|group| group.first() != first);
+    }
+
+    /// Drop blocks that are gone from the selection and groups; a group
+    /// left with one block goes too.
+    pub fn forget(&mut self, ids: &[BlockId]) {
+        self.selected.retain(|id| !ids.contains(id));
+        self.leave_groups(ids);
+    }
+
+    fn leave_groups(&mut self, ids: &[BlockId]) {
+        for group in &mut self.groups {
+            group.blocks.retain(|id| !ids.contains(id));
+        }
+        self.groups.retain(|group| group.blocks.len() > 1);
+    }
+}
+
+/// `blocks` as a transcript: each command after a `$`, then its output.
+pub fn to_text(blocks: &[&Block]) -> String {
+    let mut parts = Vec::new();
+    for block in blocks {
+        let mut text = format!("$ {}", block.command);
+        if let Some(output) = block_output_text(block) {
+            text.push('\n');
+            text.push_str(output.trim_end());
+        }
+        parts.push(text);
+    }
+    parts.join("\n\n")
+}
+
+/// `blocks` framed as context for the next agent query.
+pub fn agent_seed(blocks: &[&Block]) -> String {
+    format!("[Selected blocks, for context:]\n{}\n[End of selected blocks.]\n", to_text(blocks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_and_groups() {
+        let mut bulk = BulkSelection::default();
+        for id in [3, 1, 2] {
+            bulk.toggle(BlockId(id));
+        }
+        bulk.toggle(BlockId(2));
+        assert_eq!(bulk.in_order((1..=4).map(BlockId)), [BlockId(1), BlockId(3)]);
+
+        bulk.group(bulk.in_order((1..=4).map(BlockId)));
+        assert!(bulk.group_of(BlockId(3)).is_some_and(|g| g.collapsed && g.first() == BlockId(1)));
+        bulk.toggle_group(BlockId(1));
+        assert!(!bulk.groups[0].collapsed);
+
+        // Regrouping a member moves it; a group of one dissolves.
+        bulk.group(vec![BlockId(3), BlockId(4)]);
+        assert_eq!(bulk.groups.len(), 1);
+        assert_eq!(bulk.groups[0].blocks, [BlockId(3), BlockId(4)]);
+
+        bulk.forget(&[BlockId(4), BlockId(1)]);
+        assert!(bulk.groups.is_empty());
+        assert_eq!(bulk.selected, [BlockId(3)]);
+    }
+}
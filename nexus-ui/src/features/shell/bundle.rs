@@ -0,0 +1,312 @@
+//! A block as a file another Nexus can open.
+//!
+//! "Share Block…" writes a `.nexusblock` bundle, JSON holding the command,
+//! its outcome, notes, the sharer's accent color and the output: the
+//! structured value of a native command, or the terminal as ANSI, which is
+//! replayed into a parser on open like a restored block's snapshot. An HTML
+//! preview is written next to it for people without Nexus. Dropping a
+//! bundle on the window opens it as a block, marked as shared.
+
+use std::path::{Path, PathBuf};
+
+use nexus_api::{BlockId, BlockState, DomainValue, Value};
+use nexus_kernel::persistence::BlockNote;
+use nexus_term::{Cell, Color as TermColor, TerminalGrid, TerminalParser};
+use serde::{Deserialize, Serialize};
+use strata::primitives::Color;
+
+use crate::data::{Block, Shared};
+use crate::ui::theme;
+
+/// File extension of a bundle.
+pub const EXTENSION: &str = "nexusblock";
+/// Bundle format version; newer bundles are refused rather than misread.
+const VERSION: u32 = 1;
+/// Largest bundle opened, in bytes.
+pub const MAX_BYTES: u64 = 32 * 1024 * 1024;
+
+/// A shared block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    pub version: u32,
+    pub command: String,
+    pub state: BlockState,
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub worktree: Option<String>,
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(default)]
+    pub notes: Vec<BlockNote>,
+    /// When the block was shared, RFC 3339.
+    pub shared_at: String,
+    #[serde(default)]
+    pub theme: BundleTheme,
+    pub output: BundleOutput,
+}
+
+/// The sharer's colors, as `#rrggbb`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BundleTheme {
+    pub accent: Option<String>,
+}
+
+/// What the block showed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BundleOutput {
+    Empty,
+    Value { value: Value },
+    /// The terminal grid and scrollback as ANSI, at the width it ran at.
+    Terminal { cols: u16, ansi: String },
+}
+
+impl Bundle {
+    /// `block` as a bundle, with `accent` as its theme.
+    pub fn from_block(block: &Block, accent: Color) -> Self {
+        // Viewers (less, top, man) are live sessions, not output to keep.
+        let value = block
+            .structured_output
+            .clone()
+            .filter(|v| !matches!(v.as_domain(), Some(DomainValue::Interactive(_))));
+        let output = match value {
+            Some(value) => BundleOutput::Value { value },
+            None => {
+                let grid = block.parser.grid_with_scrollback();
+                if grid.to_string().trim().is_empty() {
+                    BundleOutput::Empty
+                } else {
+                    BundleOutput::Terminal { cols: grid.cols(), ansi: String::from_utf8_lossy(&grid.to_ansi()).into_owned() }
+                }
+            }
+        };
+        Self {
+            version: VERSION,
+            command: block.command.clone(),
+            state: block.state,
+            duration_ms: block.duration_ms,
+            title: block.title.clone(),
+            worktree: block.worktree.clone(),
+            container: block.container.clone(),
+            notes: block.notes.clone(),
+            shared_at: chrono::Local::now().to_rfc3339(),
+            theme: BundleTheme { accent: Some(hex(accent)) },
+            output,
+        }
+    }
+
+    /// Read a bundle from its JSON.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let bundle: Self = serde_json::from_str(text).map_err(|e| format!("not a Nexus block: {}", e))?;
+        if bundle.version > VERSION {
+            return Err(format!("made by a newer Nexus (format {})", bundle.version));
+        }
+        Ok(bundle)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// The bundle as block `id`, its terminal output replayed into `parser`.
+    pub fn into_block(self, id: BlockId, mut parser: TerminalParser) -> Block {
+        let mut block = Block::new(id, self.command);
+        match self.output {
+            BundleOutput::Value { value } => block.structured_output = Some(value),
+            BundleOutput::Terminal { ansi, .. } => parser.feed(ansi.as_bytes()),
+            BundleOutput::Empty => {}
+        }
+        block.parser = parser;
+        block.state = match self.state {
+            // A block shared mid-run stopped there.
+            BlockState::Running => BlockState::Interrupted,
+            state => state,
+        };
+        block.duration_ms = self.duration_ms;
+        block.title = self.title;
+        block.worktree = self.worktree;
+        block.container = self.container;
+        block.notes = self.notes;
+        block.shared = Some(Shared {
+            accent: self.theme.accent.as_deref().and_then(theme::parse_hex),
+            shared_at: self.shared_at,
+        });
+        block
+    }
+
+    /// A standalone HTML page showing the block.
+    pub fn to_html(&self) -> String {
+        let accent = self.theme.accent.as_deref().unwrap_or("#4db3ff");
+        let status = match self.state {
+            BlockState::Success => "\u{2713}",
+            BlockState::Running => "\u{25CF}",
+            BlockState::Failed(_) => "\u{2717}",
+            _ => "\u{26A0}",
+        };
+        let output = match &self.output {
+            BundleOutput::Empty => String::new(),
+            BundleOutput::Value { value } => escape(&value.to_text()),
+            BundleOutput::Terminal { cols, ansi } => {
+                let mut parser = TerminalParser::new(*cols, 24);
+                parser.feed(ansi.as_bytes());
+                grid_html(&parser.grid_with_scrollback())
+            }
+        };
+        let mut notes = String::new();
+        for note in &self.notes {
+            notes.push_str(&format!("<li>line {}: {}</li>", note.line + 1, escape(&note.text)));
+        }
+        if !notes.is_empty() {
+            notes = format!("<ul class=\"notes\">{}</ul>", notes);
+        }
+        format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title><style>\
+             body{{background:#000;color:#d9d9d9;font:13px/1.35 Menlo,monospace;margin:24px}}\
+             .block{{background:#17171a;border:1px solid {accent};border-radius:4px;padding:6px 10px}}\
+             .header{{color:#8099b3;margin-bottom:6px}}pre{{margin:0;white-space:pre-wrap}}\
+             .notes{{color:#e6b34d;margin:8px 0 0}}.meta{{color:#666;margin-top:8px}}\
+             </style></head><body><div class=\"block\"><div class=\"header\">{status} $ {command}</div>\
+             <pre>{output}</pre>{notes}</div><div class=\"meta\">Shared from Nexus {shared_at}</div></body></html>\n",
+            title = escape(self.title.as_deref().unwrap_or(&self.command)),
+            accent = escape(accent),
+            command = escape(&self.command),
+            shared_at = escape(&self.shared_at),
+        )
+    }
+}
+
+/// Where a bundle of `block` is written; its preview goes next to it with
+/// an `.html` extension.
+pub fn export_path(block: &Block) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let program = nexus_kernel::titles::command_name(&block.command).replace(' ', "-");
+    let program: String = program.chars().filter(|c| c.is_alphanumeric() || *c == '-').collect();
+    let name = if program.is_empty() { "block".to_string() } else { program };
+    crate::utils::text::export_dir().join(format!("{}-{}.{}", name, stamp, EXTENSION))
+}
+
+/// Whether `path` names a bundle.
+pub fn is_bundle(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == EXTENSION)
+}
+
+/// `#rrggbb` for `color`.
+fn hex(color: Color) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(color.r), byte(color.g), byte(color.b))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The grid's rows as HTML, each run of like-styled cells in a span.
+fn grid_html(grid: &TerminalGrid) -> String {
+    let mut out = String::new();
+    for row in grid.rows_iter().take(grid.content_rows() as usize) {
+        let mut run = String::new();
+        let mut style = String::new();
+        for cell in row.iter().filter(|cell| !cell.flags.wide_char_spacer) {
+            let cell_style = css(cell);
+            if cell_style != style {
+                push_run(&mut out, &style, &run);
+                run.clear();
+                style = cell_style;
+            }
+            if cell.c == '\0' { run.push(' ') } else { cell.push_grapheme(&mut run) }
+        }
+        push_run(&mut out, &style, run.trim_end());
+        out.push('\n');
+    }
+    out.truncate(out.trim_end().len());
+    out
+}
+
+fn push_run(out: &mut String, style: &str, text: &str) {
+    match (style, text) {
+        (_, "") => {}
+        ("", text) => out.push_str(&escape(text)),
+        (style, text) => out.push_str(&format!("<span style=\"{}\">{}</span>", style, escape(text))),
+    }
+}
+
+/// Inline CSS for how `cell` is drawn; empty for plain text.
+fn css(cell: &Cell) -> String {
+    let (fg, bg) = if cell.flags.inverse { (cell.bg, cell.fg) } else { (cell.fg, cell.bg) };
+    let rgb = |color: TermColor, foreground: bool| {
+        let [r, g, b, _] = color.to_rgba(foreground);
+        hex(Color { r, g, b, a: 1.0 })
+    };
+    let mut style = String::new();
+    if fg != TermColor::Default || cell.flags.inverse {
+        style.push_str(&format!("color:{};", rgb(fg, !cell.flags.inverse)));
+    }
+    if bg != TermColor::Default || cell.flags.inverse {
+        style.push_str(&format!("background:{};", rgb(bg, cell.flags.inverse)));
+    }
+    if cell.flags.bold {
+        style.push_str("font-weight:bold;");
+    }
+    if cell.flags.italic {
+        style.push_str("font-style:italic;");
+    }
+    if cell.flags.dim {
+        style.push_str("opacity:0.6;");
+    }
+    if cell.flags.underline != nexus_term::UnderlineStyle::None {
+        style.push_str("text-decoration:underline;");
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terminal_block() -> Block {
+        let mut block = Block::new(BlockId(1), "cargo build".to_string());
+        block.parser.feed(b"\x1b[31merror\x1b[0m: <oops> & more\r\nok\r\n");
+        block.state = BlockState::Failed(101);
+        block.duration_ms = Some(1200);
+        block.set_note(0, "the type error");
+        block
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bundle = Bundle::from_block(&terminal_block(), theme::FOCUS_RING);
+        assert!(matches!(bundle.output, BundleOutput::Terminal { cols: 120, .. }));
+        let parsed = Bundle::parse(&bundle.to_json()).unwrap();
+        assert_eq!(parsed, bundle);
+
+        let block = parsed.into_block(BlockId(7), TerminalParser::new(120, 24));
+        assert_eq!((block.id, block.state, block.duration_ms), (BlockId(7), BlockState::Failed(101), Some(1200)));
+        assert_eq!(block.note(0), Some("the type error"));
+        let text = block.parser.grid_with_scrollback().to_string();
+        assert_eq!(text.lines().map(str::trim_end).take(2).collect::<Vec<_>>(), ["error: <oops> & more", "ok"]);
+        assert_eq!(block.shared.unwrap().accent, theme::parse_hex(&hex(theme::FOCUS_RING)));
+
+        let mut value = Block::new(BlockId(2), "ls".to_string());
+        value.structured_output = Some(Value::String("a.txt".into()));
+        assert_eq!(Bundle::from_block(&value, theme::FOCUS_RING).output, BundleOutput::Value { value: Value::String("a.txt".into()) });
+    }
+
+    #[test]
+    fn test_parse_refuses() {
+        assert!(Bundle::parse("{}").is_err());
+        let mut bundle = Bundle::from_block(&terminal_block(), theme::FOCUS_RING);
+        bundle.version = VERSION + 1;
+        assert!(Bundle::parse(&bundle.to_json()).unwrap_err().contains("newer"));
+    }
+
+    #[test]
+    fn test_html() {
+        let html = Bundle::from_block(&terminal_block(), theme::FOCUS_RING).to_html();
+        assert!(html.contains("\u{2717} $ cargo build"));
+        assert!(html.contains("<span style=\"color:#cd3131;\">error</span>: &lt;oops&gt; &amp; more\nok</pre>"));
+        assert!(html.contains("<li>line 1: the type error</li>"));
+        assert!(is_bundle(Path::new("/tmp/cargo-build.nexusblock")));
+    }
+}
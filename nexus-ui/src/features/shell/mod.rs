@@ -1,32 +1,48 @@
 //! Shell widget — owns terminal blocks, PTY handles, jobs, and image handles.
 
 pub(crate) mod block_manager;
+pub(crate) mod bulk;
+pub(crate) mod bundle;
+pub(crate) mod notebook;
 pub(crate) mod prediction;
 pub(crate) mod pty_backend;
 pub(crate) mod remote;
 pub(crate) mod shell_context;
+pub(crate) mod sudo;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use tokio::sync::{broadcast, Mutex};
 
-use nexus_api::{BlockId, BlockState, DomainValue, ShellEvent, Value};
+use nexus_api::{BlockId, BlockState, DomainValue, ReplUpdate, ShellEvent, Value};
+use nexus_kernel::replay::ReplayLog;
+use nexus_kernel::executables::CommandStatus;
 use nexus_kernel::{CommandClassification, Kernel};
 
-use crate::data::{Block, ConnectProgress, PtyEvent};
+use crate::data::{Block, ConnectProgress, DebugPause, OutputStream, PtyEvent, Restored};
+use self::bulk::{BulkAction, BulkSelection};
+use self::bundle::Bundle;
+use self::notebook::NotebookFormat;
 use crate::infra::systems::{kernel_subscription, pty_subscription};
+use strata::shell::subscription::BroadcastItem;
 use strata::{ImageStore, Subscription};
 use strata::content_address::SourceId;
+use strata::layout_snapshot::HitResult;
+use strata::primitives::{Color, Point};
 
 use crate::data::Focus;
-use crate::ui::widgets::{JobBar, ShellBlockWidget, ShellBlockMessage, TableLayoutCache};
+use crate::data::provider_host::Annotation;
+use crate::features::update::UpdateIndicator;
+use crate::ui::widgets::{has_note_gutter, JobBar, PowerIndicator, ShellBlockWidget, ShellBlockMessage, SudoPromptBar, TableLayoutCache, NOTE_GUTTER_COLS};
 
-use self::block_manager::BlockManager;
+use self::block_manager::{BlockManager, HiddenBlock, UNDO_WINDOW};
 use crate::data::jobs::JobManager;
 use self::pty_backend::PtyBackend;
+use self::sudo::SudoAuth;
 
 use crate::ui::context_menu::{ContextMenuItem, ContextTarget};
 use crate::app::message::{AnchorAction, ContextMenuMsg, NexusMessage, ShellMsg};
@@ -95,15 +111,52 @@ pub(crate) struct ShellWidget {
 
     // --- Subscription channel (kernel events) ---
     kernel_rx: Arc<Mutex<broadcast::Receiver<ShellEvent>>>,
+    /// Kernel events skipped because the subscription lagged.
+    kernel_dropped: Arc<AtomicU64>,
+    /// The kernel's event history, used to rebuild blocks after a lag.
+    replay: ReplayLog,
 
     /// Pending SSH connection request from a NexusSSH OSC escape sequence.
     /// Set by PTY output handlers, consumed by the orchestrator.
     pub(crate) pending_osc_ssh: Option<(BlockId, String, Option<u16>, Option<String>, Vec<String>)>,
+
+    /// Blocks that finished since the orchestrator last scanned their
+    /// output for compiler problems and titled them.
+    pub(crate) finished: Vec<BlockId>,
+
+    /// sudo password prompt detection and the secure input overlay.
+    pub(crate) sudo: SudoAuth,
+
+    /// Blocks picked for bulk actions, and block groups.
+    pub(crate) bulk: BulkSelection,
+
+    /// The last block hidden, while it can still be brought back.
+    pub(crate) hidden: Option<HiddenBlock>,
+    /// Hidden blocks whose stored copy the orchestrator should delete.
+    pub(crate) forgotten: Vec<BlockId>,
+
+    /// Show only the blocks run in this git worktree.
+    pub(crate) worktree_filter: Option<String>,
+
+    /// The output line under the pointer, for the notes gutter.
+    pub(crate) note_hover: Cell<Option<NoteHover>>,
+}
+
+/// An output line of a finished block under the pointer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct NoteHover {
+    pub block: BlockId,
+    pub line: usize,
+    /// Over the gutter itself, where a note's text pops up.
+    pub on_gutter: bool,
+    pub position: Point,
 }
 
 impl ShellWidget {
     pub fn new(
         kernel_rx: Arc<Mutex<broadcast::Receiver<ShellEvent>>>,
+        kernel_dropped: Arc<AtomicU64>,
+        replay: ReplayLog,
     ) -> Self {
         Self {
             blocks: BlockManager::new(),
@@ -114,8 +167,17 @@ impl ShellWidget {
             click_registry: RefCell::new(HashMap::new()),
             table_layout_cache: RefCell::new(HashMap::new()),
             kernel_rx,
+            kernel_dropped,
+            replay,
             pending_osc_ssh: None,
+            finished: Vec::new(),
             rtt_ms: 0,
+            sudo: SudoAuth::new(),
+            bulk: BulkSelection::default(),
+            hidden: None,
+            forgotten: Vec::new(),
+            worktree_filter: None,
+            note_hover: Cell::new(None),
         }
     }
 
@@ -140,6 +202,8 @@ impl ShellWidget {
         block: &'a Block,
         focus: &Focus,
         connection_dimmed: bool,
+        annotations: &'a [Annotation],
+        accent: Color,
     ) -> strata::ScrollColumn<'a> {
         let is_focused = matches!(focus, Focus::Block(id) if *id == block.id);
         scroll.push(ShellBlockWidget {
@@ -147,19 +211,59 @@ impl ShellWidget {
             kill_id: source_ids::kill(block.id),
             image_info: self.blocks.image_info(block.id),
             is_focused,
+            is_selected: self.bulk.is_selected(block.id),
+            // A shared block keeps its sharer's color.
+            accent: block.shared.as_ref().and_then(|shared| shared.accent).unwrap_or(accent),
             click_registry: &self.click_registry,
             table_layout_cache: &self.table_layout_cache,
             table_cell_images: &self.blocks.table_cell_images,
             connection_dimmed,
+            annotations,
+            note_hover: self.note_hover.get().filter(|hover| hover.block == block.id).map(|hover| hover.line),
         })
     }
 
-    /// Build the job bar widget, if any jobs exist.
-    pub fn view_job_bar(&self) -> Option<JobBar<'_>> {
-        if self.jobs.is_empty() {
+    /// The finished block and output line `hit` is on, and whether it is
+    /// on the notes gutter rather than the output itself.
+    pub fn note_line(&self, hit: &Option<HitResult>) -> Option<(BlockId, usize, bool)> {
+        let Some(HitResult::Content(addr)) = hit else {
+            return None;
+        };
+        self.blocks.blocks.iter().filter(|block| !block.is_running() && has_note_gutter(block)).find_map(|block| {
+            if addr.source_id == source_ids::note_gutter(block.id) {
+                Some((block.id, addr.content_offset / NOTE_GUTTER_COLS as usize, true))
+            } else if addr.source_id == source_ids::shell_term(block.id) && block.structured_output.is_none() {
+                let cols = block.parser.size().0.max(1) as usize;
+                Some((block.id, addr.content_offset / cols, false))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Track the output line under the pointer for the notes gutter.
+    pub fn on_hover(&self, hit: &Option<HitResult>, position: Point) {
+        let hover = self.note_line(hit).map(|(block, line, on_gutter)| NoteHover { block, line, on_gutter, position });
+        self.note_hover.set(hover);
+    }
+
+    /// Build the secure password bar, if a prompt is pending.
+    pub fn view_sudo_prompt(&self) -> Option<SudoPromptBar<'_>> {
+        self.sudo.pending().map(|p| SudoPromptBar {
+            prompt: &p.prompt,
+            noun: p.kind.noun(),
+            masked_len: p.secret.len(),
+            submit_id: source_ids::sudo_submit(p.block_id),
+            cancel_id: source_ids::sudo_cancel(p.block_id),
+        })
+    }
+
+    /// Build the job bar widget, if any jobs exist or there is a status pill to show.
+    pub fn view_job_bar(&self, offline: bool, power: Option<PowerIndicator>, update: Option<UpdateIndicator>) -> Option<JobBar<'_>> {
+        if self.jobs.is_empty() && !offline && power.is_none() && update.is_none() {
             None
         } else {
-            Some(JobBar { jobs: self.jobs.as_slice() })
+            Some(JobBar { jobs: self.jobs.as_slice(), offline, power, update })
         }
     }
 
@@ -167,6 +271,41 @@ impl ShellWidget {
 
     /// Handle a widget click within shell-owned UI. Returns None if not our widget.
     pub fn on_click(&self, id: SourceId) -> Option<ShellMsg> {
+        if let Some(prompt) = self.sudo.pending() {
+            if id == source_ids::sudo_submit(prompt.block_id) {
+                return Some(ShellMsg::SudoSubmit);
+            }
+            if id == source_ids::sudo_cancel(prompt.block_id) {
+                return Some(ShellMsg::SudoCancel);
+            }
+        }
+        // Block selection bar and group headers
+        for (bar, action) in [
+            (source_ids::bulk_copy(), BulkAction::Copy),
+            (source_ids::bulk_notebook(), BulkAction::Export(NotebookFormat::Ipynb)),
+            (source_ids::bulk_script(), BulkAction::Export(NotebookFormat::Script)),
+            (source_ids::bulk_send_to_agent(), BulkAction::SendToAgent),
+            (source_ids::bulk_group(), BulkAction::Group),
+            (source_ids::bulk_remove(), BulkAction::Remove),
+        ] {
+            if id == bar {
+                return Some(ShellMsg::Bulk(action));
+            }
+        }
+        if id == source_ids::bulk_clear() {
+            return Some(ShellMsg::ClearBlockSelection);
+        }
+        if id == source_ids::hide_undo() {
+            return Some(ShellMsg::UndoHide);
+        }
+        for group in &self.bulk.groups {
+            if id == source_ids::group_toggle(group.first()) {
+                return Some(ShellMsg::ToggleGroup(group.first()));
+            }
+            if id == source_ids::group_ungroup(group.first()) {
+                return Some(ShellMsg::Ungroup(group.first()));
+            }
+        }
         // Delegate to ShellBlockWidget for block-specific clicks
         for block in &self.blocks.blocks {
             if let Some(msg) = ShellBlockWidget::on_click(block, id) {
@@ -199,6 +338,16 @@ impl ShellWidget {
     fn translate_block_message(block_id: BlockId, msg: ShellBlockMessage) -> ShellMsg {
         match msg {
             ShellBlockMessage::Kill => ShellMsg::KillBlock(block_id),
+            ShellBlockMessage::Debug(action) => ShellMsg::Debug(block_id, action),
+            ShellBlockMessage::ToggleTimeline => ShellMsg::ToggleTimeline(block_id),
+            ShellBlockMessage::ToggleUsage => ShellMsg::ToggleUsage(block_id),
+            ShellBlockMessage::ToggleRepeat => ShellMsg::ToggleRepeat(block_id),
+            ShellBlockMessage::ToggleRestored => ShellMsg::ToggleRestored(block_id),
+            ShellBlockMessage::NextProblem => ShellMsg::NextProblem(block_id),
+            ShellBlockMessage::ToggleTestOutput => ShellMsg::ToggleTestOutput(block_id),
+            ShellBlockMessage::ToggleTestNode(suite, test) => ShellMsg::ToggleTestNode(block_id, suite, test),
+            ShellBlockMessage::RerunFailedTests => ShellMsg::RerunFailedTests(block_id),
+            ShellBlockMessage::RestartRepl => ShellMsg::RestartRepl(block_id),
             ShellBlockMessage::TreeToggle(path) => ShellMsg::ToggleTreeExpand(block_id, path),
             // These are handled via other paths (ViewerMsg, registry, etc.)
             ShellBlockMessage::ExitViewer
@@ -219,6 +368,18 @@ impl ShellWidget {
         self.blocks.get_mut(id)
     }
 
+    /// The running block that has taken over the window, if any.
+    pub fn fullscreen_block(&self) -> Option<&Block> {
+        self.blocks.blocks.iter().rev().find(|b| b.fullscreen && b.is_running())
+    }
+
+    /// The running `repl` block the input feeds, with its language.
+    pub fn attached_repl(&self) -> Option<(BlockId, &str)> {
+        self.blocks.blocks.iter().rev()
+            .filter(|b| b.is_running())
+            .find_map(|b| b.repl.as_ref().map(|repl| (b.id, repl.language.as_str())))
+    }
+
     /// Find the most recent block with an active viewer (e.g. top, less, tree).
     /// Used as a fallback when focus is Input but a viewer is still running.
     pub fn active_viewer_block(&self) -> Option<BlockId> {
@@ -275,6 +436,25 @@ impl ShellWidget {
         if !block.is_running() && !block.command.is_empty() {
             items.push(ContextMenuItem::Rerun);
         }
+        // Offer PipeInto for finished blocks with a stored value
+        if !block.is_running() && block.structured_output.is_some() {
+            items.push(ContextMenuItem::PipeInto);
+        }
+        // Offer ShareBlock for finished blocks
+        if !block.is_running() {
+            items.push(ContextMenuItem::ShareBlock);
+        }
+        // Narrow the list to one worktree's blocks, or widen it again
+        if self.worktree_filter.is_some() {
+            items.push(ContextMenuItem::FilterWorktree(None));
+        } else if let Some(worktree) = &block.worktree {
+            items.push(ContextMenuItem::FilterWorktree(Some(worktree.clone())));
+        }
+        if !block.is_running() {
+            items.push(ContextMenuItem::Separator);
+            items.push(ContextMenuItem::HideBlock { forget: false });
+            items.push(ContextMenuItem::HideBlock { forget: true });
+        }
 
         Some(ContextMenuMsg::Show(x, y, items, ContextTarget::Block(block_id)))
     }
@@ -524,6 +704,7 @@ impl ShellWidget {
                 || source_id == source_ids::image_output(id)
                 || source_id == source_ids::kill(id)
                 || source_id == source_ids::viewer_exit(id)
+                || source_id == source_ids::note_gutter(id)
             {
                 return Some(id);
             }
@@ -558,7 +739,10 @@ impl ShellWidget {
 
         let kernel_rx = self.kernel_rx.clone();
         subs.push(
-            kernel_subscription(kernel_rx).map(|evt| NexusMessage::Shell(ShellMsg::KernelEvent(evt))),
+            kernel_subscription(kernel_rx).map(|item| match item {
+                BroadcastItem::Message(evt) => NexusMessage::Shell(ShellMsg::KernelEvent(evt)),
+                BroadcastItem::Lagged(n) => NexusMessage::Shell(ShellMsg::KernelLagged(n)),
+            }),
         );
 
         Subscription::batch(subs)
@@ -571,12 +755,35 @@ impl ShellWidget {
             ShellMsg::PtyOutput(id, data) => self.handle_pty_output(id, data, uctx),
             ShellMsg::PtyExited(id, exit_code) => self.handle_pty_exited(id, exit_code, uctx),
             ShellMsg::KernelEvent(evt) => self.handle_kernel_event(evt, images, uctx),
-            ShellMsg::SendInterrupt(id) => { self.pty.send_interrupt(id); }
+            ShellMsg::KernelLagged(n) => self.resync_kernel(n, images, uctx),
+            ShellMsg::SendInterrupt(id) => {
+                if self.pty.has_handle(id) {
+                    self.pty.send_interrupt(id);
+                } else {
+                    // A kernel pipeline: SIGINT to its process, and the
+                    // INT trap or the end of the block.
+                    nexus_kernel::traps::interrupt(id);
+                }
+            }
+            ShellMsg::SudoKey(event) => self.sudo.handle_key(&event),
+            ShellMsg::SudoSubmit => {
+                if let Some((block_id, secret)) = self.sudo.submit() {
+                    self.pty.write_secret(block_id, secret.as_bytes());
+                }
+            }
+            ShellMsg::SudoCancel => {
+                // Hand the prompt back to the terminal so the user can
+                // answer there or press Ctrl+C.
+                if let Some(block_id) = self.sudo.cancel() {
+                    uctx.set_focus(Focus::Block(block_id));
+                }
+            }
             ShellMsg::KillBlock(id) => {
                 self.pty.kill(id);
                 // Also cancel kernel-native commands (e.g. top) which have
                 // no PTY handle — they use a cancel flag instead.
                 nexus_kernel::commands::cancel_block(id);
+                nexus_kernel::debug::send(id, nexus_kernel::debug::DebugAction::Abort);
                 if let Some(block) = self.blocks.get_mut(id).filter(|b| b.view_state.is_some()) {
                     block.view_state = None;
                     block.version += 1;
@@ -589,12 +796,89 @@ impl ShellWidget {
                     uctx.snap_to_bottom();
                 }
             }
+            ShellMsg::ToggleTimeline(block_id) => {
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.timeline = !block.timeline;
+                    block.version += 1;
+                }
+            }
+            ShellMsg::ToggleUsage(block_id) => {
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.usage_expanded = !block.usage_expanded;
+                    block.version += 1;
+                }
+            }
+            ShellMsg::ToggleRepeat(block_id) => {
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.repeat_expanded = !block.repeat_expanded;
+                    block.version += 1;
+                }
+            }
+            ShellMsg::Debug(block_id, action) => {
+                nexus_kernel::debug::send(block_id, action);
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.debug = None;
+                    block.version += 1;
+                }
+            }
             ShellMsg::SortTable(block_id, col_idx) => { self.sort_table(block_id, col_idx); }
             ShellMsg::FilterTable(block_id, col, filter) => { self.filter_table(block_id, col, filter); }
             ShellMsg::ClearAllFilters(block_id) => { self.clear_all_filters(block_id); }
             ShellMsg::OpenAnchor(_, _) => {
                 // Handled at the root level in state_update.rs
             }
+            ShellMsg::ToggleRestored(_) | ShellMsg::EditNote(..) => {
+                // Needs the kernel or the input; handled at the root level.
+            }
+            ShellMsg::ToggleTestOutput(block_id) => {
+                if let Some(block) = self.blocks.get_mut(block_id)
+                    && let Some(tests) = &mut block.tests
+                {
+                    tests.show_output = !tests.show_output;
+                    block.version += 1;
+                }
+            }
+            ShellMsg::ToggleTestNode(block_id, suite, test) => {
+                if let Some(block) = self.blocks.get_mut(block_id)
+                    && let Some(tests) = &mut block.tests
+                {
+                    tests.toggle(suite, test);
+                    block.version += 1;
+                }
+            }
+            ShellMsg::RestartRepl(block_id) => {
+                nexus_kernel::repl::send(block_id, nexus_kernel::repl::ReplInput::Restart);
+            }
+            ShellMsg::ToggleBlockSelected(block_id) => self.bulk.toggle(block_id),
+            ShellMsg::ClearBlockSelection => self.bulk.clear(),
+            ShellMsg::ToggleGroup(first) => self.bulk.toggle_group(first),
+            ShellMsg::Ungroup(first) => self.bulk.ungroup(first),
+            ShellMsg::Bulk(BulkAction::Group) => {
+                let selected = self.bulk.in_order(self.blocks.blocks.iter().map(|b| b.id));
+                self.bulk.group(selected);
+                self.bulk.clear();
+            }
+            ShellMsg::Bulk(BulkAction::Remove) => {
+                // A running block keeps its PTY or kernel command; leave it.
+                let removable: Vec<BlockId> = self
+                    .bulk
+                    .selected
+                    .iter()
+                    .copied()
+                    .filter(|&id| self.blocks.get(id).is_some_and(|b| !b.is_running()))
+                    .collect();
+                self.blocks.remove(&removable);
+                self.bulk.forget(&removable);
+                self.bulk.clear();
+            }
+            ShellMsg::HideBlock(block_id, forget) => self.hide_block(block_id, forget, uctx),
+            ShellMsg::UndoHide => self.undo_hide(uctx),
+            ShellMsg::Bulk(_) => {
+                // Copy, export and send-to-agent are handled at the root level in update.rs
+            }
+            ShellMsg::NextProblem(_) | ShellMsg::RerunFailedTests(_) => {
+                // Handled at the root level in update.rs (needs the editor / input)
+            }
             ShellMsg::ToggleTreeExpand(_, _) => {
                 // Handled at the root level in update.rs (needs remote backend access)
             }
@@ -618,7 +902,6 @@ impl ShellWidget {
         block_id: BlockId,
         cwd: &str,
         kernel: &Arc<Mutex<Kernel>>,
-        kernel_tx: &broadcast::Sender<ShellEvent>,
         remote: Option<&mut crate::features::shell::remote::RemoteBackend>,
         uctx: &mut UpdateContext,
     ) -> Option<String> {
@@ -659,15 +942,26 @@ impl ShellWidget {
             }
         }
 
-        let classification = kernel.blocking_lock().classify_command(&trimmed);
+        let (classification, status) = {
+            let kernel = kernel.blocking_lock();
+            let classification = kernel.classify_command(&trimmed);
+            let status = match classification {
+                CommandClassification::Pty => kernel.command_status(&trimmed),
+                _ => CommandStatus::Unchecked,
+            };
+            (classification, status)
+        };
 
         match classification {
             CommandClassification::Kernel => {
-                self.execute_kernel_command(trimmed, block_id, cwd, kernel, kernel_tx, uctx);
+                self.execute_kernel_command(trimmed, block_id, cwd, kernel, uctx);
                 None
             }
             CommandClassification::Pty => {
-                self.execute_pty_command(trimmed, block_id, cwd, uctx);
+                match status {
+                    CommandStatus::Unknown { suggestion } => self.fail_unknown_command(trimmed, block_id, suggestion, uctx),
+                    _ => self.execute_pty_command(trimmed, block_id, cwd, uctx),
+                }
                 None
             }
             CommandClassification::RemoteTransport => {
@@ -741,18 +1035,26 @@ impl ShellWidget {
     /// for the same block into a single `feed()` call, preserving ordering
     /// relative to Exited events.
     pub fn handle_pty_batch(&mut self, batch: Vec<(BlockId, PtyEvent)>, uctx: &mut UpdateContext) {
+        let _span = tracing::debug_span!("pty.batch", events = batch.len()).entered();
         // Coalesce: merge consecutive Output(data) for the same block.
         // When we hit an Exited or a different block, flush the accumulator.
         let mut acc_id: Option<BlockId> = None;
         let mut acc_data: Vec<u8> = Vec::new();
         let mut had_exit = false;
+        let mut entered_alt: Option<BlockId> = None;
 
         let flush = |acc_id: &mut Option<BlockId>,
                      acc_data: &mut Vec<u8>,
                      bm: &mut BlockManager,
-                     pending_osc: &mut Option<(BlockId, String, Option<u16>, Option<String>, Vec<String>)>| {
+                     pending_osc: &mut Option<(BlockId, String, Option<u16>, Option<String>, Vec<String>)>,
+                     sudo: &mut SudoAuth,
+                     entered_alt: &mut Option<BlockId>| {
             if let Some(id) = acc_id.take() {
                 if !acc_data.is_empty() {
+                    if sudo.journals(id) {
+                        bm.journal.output(id, acc_data);
+                    }
+                    sudo.scan(id, acc_data);
                     // Check for NexusSSH OSC before feeding to parser
                     if pending_osc.is_none() {
                         if let Some((dest, port, key, ssh_opts)) = Self::scan_nexus_ssh_osc(acc_data) {
@@ -760,7 +1062,12 @@ impl ShellWidget {
                         }
                     }
                     if let Some(block) = bm.get_mut(id) {
+                        block.record_chunk(OutputStream::Terminal, acc_data);
+                        let was_alt = block.parser.is_alternate_screen();
                         block.parser.feed(acc_data);
+                        if !was_alt && block.parser.is_alternate_screen() {
+                            *entered_alt = Some(id);
+                        }
                         if let Some(title) = block.parser.take_title() {
                             block.osc_title = Some(title);
                         }
@@ -784,11 +1091,18 @@ impl ShellWidget {
                             &mut acc_data,
                             &mut self.blocks,
                             &mut self.pending_osc_ssh,
+                            &mut self.sudo,
+                            &mut entered_alt,
                         );
                         acc_id = Some(id);
                         acc_data = data;
                     }
                 }
+                PtyEvent::Usage(usage) => {
+                    if let Some(block) = self.blocks.get_mut(id) {
+                        block.resource_usage = Some(usage);
+                    }
+                }
                 PtyEvent::Exited(code) => {
                     // Flush any pending output for this or previous block first.
                     flush(
@@ -796,6 +1110,8 @@ impl ShellWidget {
                         &mut acc_data,
                         &mut self.blocks,
                         &mut self.pending_osc_ssh,
+                        &mut self.sudo,
+                        &mut entered_alt,
                     );
                     self.handle_pty_exited(id, code, uctx);
                     had_exit = true;
@@ -804,7 +1120,7 @@ impl ShellWidget {
         }
 
         // Flush remaining accumulated output.
-        flush(&mut acc_id, &mut acc_data, &mut self.blocks, &mut self.pending_osc_ssh);
+        flush(&mut acc_id, &mut acc_data, &mut self.blocks, &mut self.pending_osc_ssh, &mut self.sudo, &mut entered_alt);
 
         // Don't set terminal_dirty here — the batch message itself triggers
         // a render (every App message bumps frame).
@@ -814,49 +1130,123 @@ impl ShellWidget {
         // Follow output only if already tailing (Bottom target).
         // Don't force-scroll — the user may be reading history, or an
         // interactive app (vim, htop) manages its own viewport.
-        if !had_exit {
+        if let Some(id) = entered_alt {
+            self.show_alt_screen(id, uctx);
+        } else if !had_exit {
             uctx.hint_bottom();
         }
     }
 
+    /// A focused block just entered the alternate screen: bring its whole
+    /// viewport-sized grid into view.
+    fn show_alt_screen(&self, id: BlockId, uctx: &mut UpdateContext) {
+        if *uctx.focus == Focus::Block(id) {
+            uctx.scroll_to_block_bottom(id);
+        }
+    }
+
     /// Handle a single PTY output event (unbatched fallback).
     pub fn handle_pty_output(&mut self, id: BlockId, data: Vec<u8>, uctx: &mut UpdateContext) {
+        if self.sudo.journals(id) {
+            self.blocks.journal.output(id, &data);
+        }
+        self.sudo.scan(id, &data);
         // Check for NexusSSH OSC before feeding to parser
         if self.pending_osc_ssh.is_none() {
             if let Some((dest, port, key, ssh_opts)) = Self::scan_nexus_ssh_osc(&data) {
                 self.pending_osc_ssh = Some((id, dest, port, key, ssh_opts));
             }
         }
+        let mut entered_alt = false;
         if let Some(block) = self.blocks.get_mut(id) {
+            let was_alt = block.parser.is_alternate_screen();
             block.parser.feed(&data);
+            entered_alt = !was_alt && block.parser.is_alternate_screen();
             if let Some(title) = block.parser.take_title() {
                 block.osc_title = Some(title);
             }
             block.version += 1;
         }
         self.terminal_dirty = true;
-        uctx.hint_bottom();
+        if entered_alt {
+            self.show_alt_screen(id, uctx);
+        } else {
+            uctx.hint_bottom();
+        }
     }
 
     /// Handle PTY exit. Conditionally returns focus to input if the exited block was focused.
     pub fn handle_pty_exited(&mut self, id: BlockId, exit_code: i32, uctx: &mut UpdateContext) {
+        let mut fullscreen = false;
         if let Some(block) = self.blocks.get_mut(id) {
+            fullscreen = block.fullscreen;
+            if !fullscreen && !block.parser.is_alternate_screen() {
+                self.finished.push(id);
+            }
             block.state = if exit_code == 0 {
                 BlockState::Success
             } else {
                 BlockState::Failed(exit_code)
             };
-            block.duration_ms = Some(block.started_at.elapsed().as_millis() as u64);
+            block.duration_ms = Some(block.started_at.elapsed_ms());
             block.version += 1;
         }
+        self.blocks.journal.finished(id);
         self.pty.remove_handle(id);
+        self.sudo.block_exited(id);
         self.last_exit_code = Some(exit_code);
-        if *uctx.focus == Focus::Block(id) {
+        if fullscreen {
+            uctx.set_focus(Focus::Input);
+            uctx.scroll.restore_position();
+        } else if *uctx.focus == Focus::Block(id) {
             uctx.set_focus(Focus::Input);
             uctx.snap_to_bottom();
         }
     }
 
+    /// Recover from a gap in the kernel subscription: rebuild every block
+    /// that may have lost events from the kernel's replay log.
+    ///
+    /// Blocks the UI already saw finish were complete before the gap and are
+    /// left alone. The subscription stops after reporting the lag, so no
+    /// event past the gap has been handled yet; `resync` drains the receiver
+    /// and everything it yields afterwards is newer than the replay.
+    fn resync_kernel(&mut self, lost: u64, images: &mut ImageStore, uctx: &mut UpdateContext) {
+        self.kernel_dropped.fetch_add(lost, Ordering::Relaxed);
+        let Ok(mut rx) = self.kernel_rx.try_lock() else {
+            tracing::warn!(lost, "kernel subscription lagged; receiver busy, cannot resync");
+            return;
+        };
+        let resync = self.replay.resync(&mut rx);
+        drop(rx);
+        tracing::warn!(lost, discarded = resync.discarded, "kernel subscription lagged; resyncing");
+
+        // Replayed output was journaled (or predates the journal) already.
+        let journal = std::mem::replace(&mut self.blocks.journal, nexus_kernel::journal::BlockJournal::disabled());
+        for replay in resync.blocks {
+            match self.blocks.get_mut(replay.block_id) {
+                Some(block) if !block.is_running() => continue,
+                Some(block) => block.reset_output(self.pty.new_parser()),
+                None => {}
+            }
+            let mut events = replay.events;
+            for evt in events.by_ref() {
+                self.handle_kernel_event(evt, images, uctx);
+            }
+            if replay.truncated || events.lost() {
+                tracing::warn!(block = replay.block_id.0, "replayed output was truncated");
+            }
+        }
+        for (job_id, command, state) in resync.jobs {
+            self.jobs.handle_event(job_id, command, state);
+        }
+        if let Some(cwd) = resync.cwd {
+            uctx.set_cwd(cwd);
+        }
+        self.blocks.journal = journal;
+        self.terminal_dirty = true;
+    }
+
     /// Handle a kernel event.
     pub fn handle_kernel_event(
         &mut self,
@@ -865,15 +1255,18 @@ impl ShellWidget {
         uctx: &mut UpdateContext,
     ) {
         match evt {
-            ShellEvent::CommandStarted { block_id, command, .. } => {
+            ShellEvent::CommandStarted { block_id, command, container, .. } => {
                 if !self.blocks.contains(block_id) {
                     let mut block = Block::new(block_id, command);
                     block.parser = self.pty.new_parser();
+                    block.container = container;
                     self.blocks.push(block);
                 }
             }
             ShellEvent::StdoutChunk { block_id, data, last_echo_epoch } => {
+                self.blocks.journal.output(block_id, &data);
                 if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.record_chunk(OutputStream::Stdout, &data);
                     // Snapshot predicted positions BEFORE feed for false-positive detection
                     let should_reconcile = last_echo_epoch > 0 && block.prediction.pending_count() > 0;
                     if should_reconcile {
@@ -963,15 +1356,60 @@ impl ShellWidget {
                 self.terminal_dirty = true;
             }
             ShellEvent::StderrChunk { block_id, data } => {
+                self.blocks.journal.output(block_id, &data);
                 if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.record_chunk(OutputStream::Stderr, &data);
                     block.parser.feed(&data);
                     block.version += 1;
                 }
                 self.terminal_dirty = true;
             }
+            ShellEvent::Trace { block_id, depth, line } => {
+                // `set -x` lines are dim, to stand apart from the output.
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    let text = format!("\x1b[2m{} {}\x1b[22m\r\n", "+".repeat(depth as usize), line);
+                    block.parser.feed(text.as_bytes());
+                    block.version += 1;
+                }
+                self.terminal_dirty = true;
+            }
+            ShellEvent::DebugPaused { block_id, step, total, command } => {
+                // Steps finish the block as they run; it is live again
+                // while the script waits.
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.state = BlockState::Running;
+                    block.debug = Some(DebugPause { step, total, command });
+                    block.version += 1;
+                }
+            }
+            ShellEvent::Repl { block_id, update } => {
+                match &update {
+                    ReplUpdate::CellStarted { code, .. } => self.blocks.journal.output(block_id, format!("{}\r\n", code).as_bytes()),
+                    ReplUpdate::Output { text, .. } => self.blocks.journal.output(block_id, text.as_bytes()),
+                    _ => {}
+                }
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.repl.get_or_insert_default().apply(update);
+                    block.version += 1;
+                }
+                uctx.snap_to_bottom();
+            }
             ShellEvent::CommandOutput { block_id, value } => {
+                self.blocks.journal.value(block_id, &value);
                 self.handle_command_output(block_id, value, images, uctx);
             }
+            ShellEvent::CommandError { block_id, error } => {
+                self.blocks.journal.output(block_id, format!("{}\r\n", error).as_bytes());
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.error = Some(error);
+                    block.version += 1;
+                }
+            }
+            ShellEvent::ResourceUsage { block_id, usage } => {
+                if let Some(block) = self.blocks.get_mut(block_id) {
+                    block.resource_usage = Some(usage);
+                }
+            }
             ShellEvent::CommandFinished {
                 block_id,
                 exit_code,
@@ -981,9 +1419,10 @@ impl ShellWidget {
             }
             ShellEvent::JobStateChanged {
                 job_id,
+                command,
                 state: job_state,
             } => {
-                self.jobs.handle_event(job_id, job_state);
+                self.jobs.handle_event(job_id, command, job_state);
             }
             ShellEvent::StreamingUpdate {
                 block_id,
@@ -1050,6 +1489,17 @@ impl ShellWidget {
                     block.terminal_modes = Some(modes);
                 }
             }
+            ShellEvent::KernelPanic { block_id, message, location, backtrace } => {
+                // The block already shows the message on stderr and fails
+                // via CommandFinished; keep the full report for bug filing.
+                tracing::error!(
+                    "kernel panic in block {:?}: {} at {}\n{}",
+                    block_id,
+                    message,
+                    location.as_deref().unwrap_or("<unknown>"),
+                    backtrace
+                );
+            }
             ShellEvent::ScrollbackHistory {
                 block_id,
                 cells,
@@ -1174,11 +1624,16 @@ impl ShellWidget {
         duration_ms: u64,
         uctx: &mut UpdateContext,
     ) {
+        self.blocks.journal.finished(block_id);
         let mut cmd = String::new();
         let mut output = String::new();
         let mut has_viewer = false;
         if let Some(block) = self.blocks.get_mut(block_id) {
             block.connect_progress = None;
+            block.debug = None;
+            if let Some(repl) = &mut block.repl {
+                repl.end();
+            }
             block.state = if exit_code == 0 {
                 BlockState::Success
             } else {
@@ -1186,6 +1641,7 @@ impl ShellWidget {
             };
             block.duration_ms = Some(duration_ms);
             block.version += 1;
+            self.finished.push(block_id);
             cmd = block.command.clone();
             let raw = block.parser.grid_with_scrollback().to_string();
             output = if raw.len() > 10_000 {
@@ -1195,14 +1651,39 @@ impl ShellWidget {
             };
             has_viewer = block.view_state.is_some();
         }
+        self.mark_repeat(block_id);
         self.last_exit_code = Some(exit_code);
-        uctx.on_command_finished(cmd, output, exit_code);
+        uctx.on_command_finished(block_id, cmd, output, exit_code);
         if !has_viewer {
             uctx.set_focus(Focus::Input);
         }
         uctx.snap_to_bottom();
     }
 
+    /// Fold a finished block whose output is the same as the last run of
+    /// the same command (`watch`-style reruns), pointing it at that run.
+    fn mark_repeat(&mut self, block_id: BlockId) {
+        let Some(block) = self.blocks.get(block_id) else {
+            return;
+        };
+        let Some(digest) = block.output_digest() else {
+            return;
+        };
+        let previous = self
+            .blocks
+            .blocks
+            .iter()
+            .rev()
+            .filter(|b| b.id != block_id && !b.is_running() && b.command == block.command)
+            .find(|b| b.id.0 < block_id.0)
+            .filter(|b| b.output_digest() == Some(digest))
+            .map(|b| b.id);
+        if let (Some(previous), Some(block)) = (previous, self.blocks.get_mut(block_id)) {
+            block.repeat_of = Some(previous);
+            block.version += 1;
+        }
+    }
+
     fn handle_streaming_update(
         &mut self,
         block_id: BlockId,
@@ -1355,6 +1836,64 @@ impl ShellWidget {
         }
     }
 
+    /// Show the blocks the previous session saved above the prompt,
+    /// collapsed. Their outputs stay in the store until a block is first
+    /// expanded, see [`ShellWidget::toggle_restored`].
+    pub fn rehydrate(&mut self, blocks: Vec<nexus_kernel::persistence::SessionBlock>) {
+        for saved in blocks {
+            let mut block = Block::new(nexus_api::BlockIdAllocator::global().reserve(), saved.command);
+            block.parser = self.pty.new_parser();
+            block.state = match saved.exit_code {
+                Some(0) | None => BlockState::Success,
+                Some(code) => BlockState::Failed(code),
+            };
+            block.duration_ms = saved.duration_ms;
+            block.collapsed = true;
+            block.notes = saved.notes;
+            block.restored = Some(Restored {
+                row_id: saved.id,
+                has_output: saved.has_output,
+                has_snapshot: saved.has_snapshot,
+                loaded: false,
+            });
+            self.blocks.push(block);
+        }
+    }
+
+    /// Show a block someone shared below the others, and focus it.
+    pub fn open_bundle(&mut self, bundle: Bundle, uctx: &mut UpdateContext) {
+        let id = nexus_api::BlockIdAllocator::global().reserve();
+        self.blocks.push(bundle.into_block(id, self.pty.new_parser()));
+        uctx.set_focus(Focus::Block(id));
+        uctx.snap_to_bottom();
+    }
+
+    /// Expand or collapse a block restored from the previous session. The
+    /// first expansion reads its structured output, or else its terminal
+    /// snapshot, from the store; a snapshot is replayed into the block's
+    /// parser so it reflows like live output.
+    pub fn toggle_restored(&mut self, block_id: BlockId, kernel: &Mutex<Kernel>) {
+        let Some(block) = self.blocks.get_mut(block_id) else {
+            return;
+        };
+        let Some(restored) = block.restored.as_mut() else {
+            return;
+        };
+        if !restored.loaded {
+            restored.loaded = true;
+            let kernel = kernel.blocking_lock();
+            if restored.has_output {
+                block.structured_output = kernel.load_block_output(restored.row_id);
+            } else if restored.has_snapshot
+                && let Some(grid) = kernel.load_block_snapshot(restored.row_id)
+            {
+                block.parser.feed(&grid.to_ansi());
+            }
+        }
+        block.collapsed = !block.collapsed;
+        block.version += 1;
+    }
+
     /// Store loaded children for a tree node.
     pub fn set_tree_children(&mut self, block_id: BlockId, path: PathBuf, entries: Vec<nexus_api::FileEntry>) {
         if let Some(block) = self.blocks.get_mut(block_id) {
@@ -1363,6 +1902,56 @@ impl ShellWidget {
         }
     }
 
+    /// Take a finished block out of the session, moving focus off it. It
+    /// can be brought back until the undo window closes; `forget` also
+    /// deletes the Store's copy after that.
+    pub fn hide_block(&mut self, id: BlockId, forget: bool, uctx: &mut UpdateContext) {
+        if self.blocks.get(id).is_none_or(|block| block.is_running()) {
+            return;
+        }
+        self.commit_hidden();
+        if *uctx.focus == Focus::Block(id) {
+            let next = self.blocks.blocks.iter().skip_while(|b| b.id != id).nth(1).map(|b| b.id);
+            let prev = self.blocks.blocks.iter().take_while(|b| b.id != id).last().map(|b| b.id);
+            uctx.set_focus(next.or(prev).map_or(Focus::Input, Focus::Block));
+        }
+        let Some((index, block)) = self.blocks.take(id) else {
+            return;
+        };
+        self.bulk.forget(&[id]);
+        self.hidden = Some(HiddenBlock { block, index, forget, hidden_at: std::time::Instant::now() });
+    }
+
+    /// Bring back the last hidden block and focus it.
+    pub fn undo_hide(&mut self, uctx: &mut UpdateContext) {
+        if let Some(hidden) = self.hidden.take() {
+            let id = hidden.block.id;
+            self.blocks.restore(hidden.index, hidden.block);
+            uctx.set_focus(Focus::Block(id));
+        }
+    }
+
+    /// Close the undo window of a hidden block whose time is up. True if
+    /// one was dropped.
+    pub fn expire_hidden(&mut self) -> bool {
+        if self.hidden.as_ref().is_some_and(|hidden| hidden.hidden_at.elapsed() >= UNDO_WINDOW) {
+            self.commit_hidden();
+            return true;
+        }
+        false
+    }
+
+    /// Drop the hidden block for good, queueing its stored copy for
+    /// deletion if asked.
+    fn commit_hidden(&mut self) {
+        if let Some(hidden) = self.hidden.take() {
+            self.blocks.remove(&[hidden.block.id]);
+            if hidden.forget {
+                self.forgotten.push(hidden.block.id);
+            }
+        }
+    }
+
     /// Clear all blocks, kill PTYs, cancel kernel commands, clear jobs.
     pub fn clear(&mut self) {
         // Cancel any running kernel commands (e.g. `top`) so they release
@@ -1371,8 +1960,10 @@ impl ShellWidget {
             nexus_kernel::commands::cancel_block(block.id);
         }
         self.pty.kill_all();
+        self.commit_hidden();
         self.blocks.clear();
         self.jobs.clear();
+        self.bulk = BulkSelection::default();
     }
 
     /// Propagate terminal size changes to block parsers (delegates to PtyBackend).
@@ -1380,6 +1971,16 @@ impl ShellWidget {
         self.pty.sync_terminal_size(&mut self.blocks.blocks);
     }
 
+    /// Bring alternate-screen grids to the size last sent to the PTYs.
+    pub fn sync_alt_screen_sizes(&mut self) -> bool {
+        self.pty.sync_alt_screen_sizes(&mut self.blocks.blocks)
+    }
+
+    /// Refresh the throughput figures of running PTY blocks.
+    pub fn sample_throughput(&mut self) -> bool {
+        self.pty.sample_throughput(&mut self.blocks.blocks)
+    }
+
     // ---- Internal ----
 
     fn execute_kernel_command(
@@ -1388,7 +1989,6 @@ impl ShellWidget {
         block_id: BlockId,
         cwd: &str,
         kernel: &Arc<Mutex<Kernel>>,
-        kernel_tx: &broadcast::Sender<ShellEvent>,
         uctx: &mut UpdateContext,
     ) {
         let mut block = Block::new(block_id, cmd.clone());
@@ -1396,44 +1996,48 @@ impl ShellWidget {
         self.blocks.push(block);
 
         let kernel = kernel.clone();
-        let kernel_tx = kernel_tx.clone();
         let cwd = cwd.to_string();
 
+        // Panics are contained by the kernel: the block fails with the panic
+        // message and a KernelPanic crash report follows. Commands that leave
+        // the session alone run on a lease, so they don't wait for each other
+        // or hold the kernel while they work.
         std::thread::spawn(move || {
-            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                let rt = tokio::runtime::Runtime::new().unwrap();
-                rt.block_on(async {
-                    let mut kernel = kernel.lock().await;
+            let lease = kernel.blocking_lock().lease(&cmd);
+            match lease {
+                Some(mut lease) => {
+                    let _ = lease.state_mut().set_cwd(std::path::PathBuf::from(&cwd));
+                    let _ = lease.execute_supervised(&cmd, block_id);
+                    kernel.blocking_lock().release(lease);
+                }
+                None => {
+                    let mut kernel = kernel.blocking_lock();
                     let _ = kernel
                         .state_mut()
                         .set_cwd(std::path::PathBuf::from(&cwd));
-                    let _ = kernel.execute_with_block_id(&cmd, Some(block_id));
-                });
-            }));
-
-            if let Err(panic_info) = result {
-                let error_msg = if let Some(s) = panic_info.downcast_ref::<&str>() {
-                    format!("Command panicked: {}", s)
-                } else if let Some(s) = panic_info.downcast_ref::<String>() {
-                    format!("Command panicked: {}", s)
-                } else {
-                    "Command panicked (unknown error)".to_string()
-                };
-                let _ = kernel_tx.send(ShellEvent::StderrChunk {
-                    block_id,
-                    data: format!("{}\n", error_msg).into_bytes(),
-                });
-                let _ = kernel_tx.send(ShellEvent::CommandFinished {
-                    block_id,
-                    exit_code: 1,
-                    duration_ms: 0,
-                });
+                    let _ = kernel.execute_supervised(&cmd, block_id);
+                }
             }
         });
 
         uctx.snap_to_bottom();
     }
 
+    /// A line whose command doesn't exist fails at once, with the closest
+    /// name as a suggestion, instead of spawning a shell to report it.
+    fn fail_unknown_command(&mut self, cmd: String, block_id: BlockId, suggestion: Option<String>, uctx: &mut UpdateContext) {
+        let name = nexus_kernel::executables::command_word(&cmd).unwrap_or_default().to_string();
+        let mut error = nexus_api::CommandError::new(name, nexus_api::CommandErrorKind::NotFound, "command not found");
+        if let Some(suggestion) = suggestion {
+            error = error.with_suggestion(format!("did you mean '{}'?", suggestion));
+        }
+        let mut block = Block::new(block_id, cmd);
+        block.parser = self.pty.new_parser();
+        block.error = Some(error);
+        self.blocks.push(block);
+        self.handle_command_finished(block_id, 127, 0, uctx);
+    }
+
     fn execute_pty_command(
         &mut self,
         cmd: String,
@@ -1443,10 +2047,33 @@ impl ShellWidget {
     ) {
         let mut block = Block::new(block_id, cmd.clone());
         block.parser = self.pty.new_parser();
+        // `in <dir> NAME=v cmd` keeps the full line in the header but spawns
+        // `cmd` with the directory and environment applied.
+        let (spawn_line, program) = match nexus_kernel::overrides::ExecOverride::split(&cmd) {
+            Some((over, rest)) => (over.shell_line(rest), rest.to_string()),
+            None => (cmd.clone(), cmd.clone()),
+        };
+        // `nexus exec -c <container> cmd` spawns `cmd` through the container
+        // engine's `exec -it`.
+        let (spawn_line, program) = match nexus_kernel::containers::ContainerExec::split(&cmd) {
+            Some((exec, rest)) => {
+                let runtime = exec.runtime(&std::env::var("PATH").unwrap_or_default());
+                block.container = Some(exec.container.clone());
+                (exec.shell_line(runtime, rest), rest.to_string())
+            }
+            None => (spawn_line, program),
+        };
+        block.fullscreen = uctx.context.config.runs_fullscreen(&program);
+        let fullscreen = block.fullscreen;
         self.blocks.push(block);
 
-        match self.pty.spawn(&cmd, block_id, cwd) {
+        match self.pty.spawn(&spawn_line, block_id, cwd) {
             Ok(()) => {
+                // Full-window apps hide the history; come back to where the
+                // user was reading when they exit.
+                if fullscreen {
+                    uctx.scroll.save_position();
+                }
                 uctx.set_focus(Focus::Block(block_id));
                 uctx.snap_to_bottom();
             }
@@ -1599,7 +2226,7 @@ mod tests {
 
         let (_kernel_tx, kernel_rx) = tokio::sync::broadcast::channel(16);
         let kernel_rx = std::sync::Arc::new(tokio::sync::Mutex::new(kernel_rx));
-        let mut shell = ShellWidget::new(kernel_rx);
+        let mut shell = ShellWidget::new(kernel_rx, Default::default(), Default::default());
 
         let block_id = BlockId(1);
         let mut block = crate::data::Block::new(block_id, "test".to_string());
@@ -1617,7 +2244,8 @@ mod tests {
             project: None,
             last_interaction: None,
             env_vars: std::collections::HashMap::new(),
-            nexus_md: None,
+            instructions: None,
+            ..Default::default()
         };
         let mut uctx = crate::app::update_context::UpdateContext::new(
             &mut scroll,
@@ -0,0 +1,279 @@
+//! A run of shell blocks as a file: a Jupyter notebook, or a shell script
+//! with the captured output as comments.
+//!
+//! Commands become `!cmd` cells (a plain `sh` line in scripts); a `repl`
+//! block contributes its cells, and each interpreter session of one becomes
+//! a heredoc in the script. Notebooks are Python unless every repl in the
+//! range is Node, in which case commands are kept as Markdown. Notes left
+//! on output lines follow their command, as Markdown or as comments.
+
+use std::path::PathBuf;
+
+use nexus_api::{BlockState, Value};
+use serde_json::{Value as Json, json};
+
+use crate::data::{Block, ReplCell, ReplCellOutput};
+use crate::features::selection::{block_output_text, format_table_as_markdown};
+
+/// Export formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotebookFormat {
+    Ipynb,
+    Script,
+}
+
+impl NotebookFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Ipynb => "ipynb",
+            Self::Script => "sh",
+        }
+    }
+}
+
+/// `blocks` in the format asked for.
+pub fn export(blocks: &[&Block], format: NotebookFormat) -> String {
+    match format {
+        NotebookFormat::Ipynb => to_ipynb(blocks),
+        NotebookFormat::Script => to_script(blocks),
+    }
+}
+
+/// Where an export is written.
+pub fn export_path(format: NotebookFormat) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    crate::utils::text::export_dir().join(format!("nexus-blocks-{}.{}", stamp, format.extension()))
+}
+
+/// A Jupyter (nbformat 4) notebook of `blocks`.
+pub fn to_ipynb(blocks: &[&Block]) -> String {
+    let mut languages = blocks.iter().filter_map(|block| block.repl.as_ref()).map(|repl| repl.language.as_str());
+    let node = languages.next().is_some_and(|first| first == "node" && languages.all(|l| l == "node"));
+
+    let mut cells = Vec::new();
+    for block in blocks {
+        match &block.repl {
+            Some(repl) => {
+                // Node cells in a Python notebook run through a cell magic.
+                let magic = (repl.language == "node" && !node).then_some("%%script node\n");
+                for cell in repl.cells.iter().filter(|cell| cell.number > 0) {
+                    cells.push(repl_cell(cell, magic));
+                }
+            }
+            None if node => {
+                let mut text = format!("```sh\n$ {}\n", block.command);
+                if let Some(output) = block_output_text(block) {
+                    text.push_str(&output);
+                    text.push('\n');
+                }
+                text.push_str("```");
+                if !block.notes.is_empty() {
+                    text.push_str(&format!("\n\n{}", notes_markdown(block)));
+                }
+                cells.push(json!({ "cell_type": "markdown", "metadata": {}, "source": lines(&text) }));
+            }
+            None => {
+                let source = if block.command.contains('\n') {
+                    format!("%%sh\n{}", block.command)
+                } else {
+                    format!("!{}", block.command)
+                };
+                let outputs: Vec<Json> = block_output_text(block)
+                    .map(|text| json!({ "output_type": "stream", "name": "stdout", "text": lines(&format!("{}\n", text)) }))
+                    .into_iter()
+                    .collect();
+                cells.push(code_cell(&source, None, outputs));
+                if !block.notes.is_empty() {
+                    cells.push(json!({ "cell_type": "markdown", "metadata": {}, "source": lines(&notes_markdown(block)) }));
+                }
+            }
+        }
+    }
+
+    let (kernel, language) = if node {
+        (json!({ "name": "javascript", "display_name": "JavaScript (Node.js)", "language": "javascript" }), "javascript")
+    } else {
+        (json!({ "name": "python3", "display_name": "Python 3", "language": "python" }), "python")
+    };
+    let notebook = json!({
+        "cells": cells,
+        "metadata": { "kernelspec": kernel, "language_info": { "name": language } },
+        "nbformat": 4,
+        "nbformat_minor": 4,
+    });
+    serde_json::to_string_pretty(&notebook).unwrap_or_default()
+}
+
+fn repl_cell(cell: &ReplCell, magic: Option<&str>) -> Json {
+    let count = Some(cell.number);
+    let mut outputs = Vec::new();
+    for output in &cell.outputs {
+        outputs.push(match output {
+            ReplCellOutput::Text { text, stderr } => {
+                let name = if *stderr { "stderr" } else { "stdout" };
+                json!({ "output_type": "stream", "name": name, "text": lines(text) })
+            }
+            ReplCellOutput::Value(value) => {
+                let mut data = json!({ "text/plain": lines(&value.to_text()) });
+                if let Value::Table { columns, rows } = value {
+                    data["text/markdown"] = json!(lines(&format_table_as_markdown(columns, rows)));
+                }
+                json!({ "output_type": "execute_result", "execution_count": count, "data": data, "metadata": {} })
+            }
+        });
+    }
+    if let Some(error) = &cell.error {
+        // The last traceback line is "Name: message" in both languages.
+        let last = error.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("");
+        let (name, value) = last.split_once(": ").unwrap_or((last, ""));
+        outputs.push(json!({
+            "output_type": "error",
+            "ename": name.trim(),
+            "evalue": value,
+            "traceback": error.lines().collect::<Vec<_>>(),
+        }));
+    }
+    let source = format!("{}{}", magic.unwrap_or(""), cell.code);
+    code_cell(&source, count, outputs)
+}
+
+fn code_cell(source: &str, count: Option<u32>, outputs: Vec<Json>) -> Json {
+    json!({
+        "cell_type": "code",
+        "execution_count": count,
+        "metadata": {},
+        "source": lines(source),
+        "outputs": outputs,
+    })
+}
+
+/// A block's notes as a Markdown list.
+fn notes_markdown(block: &Block) -> String {
+    let items: Vec<String> = block.notes.iter().map(|note| format!("- line {}: {}", note.line + 1, note.text)).collect();
+    format!("**Notes**\n\n{}", items.join("\n"))
+}
+
+/// Notebook multiline strings: one entry per line, newlines kept.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// A shell script that re-runs `blocks`, with what they printed as comments.
+pub fn to_script(blocks: &[&Block]) -> String {
+    let mut out = String::from("#!/bin/sh\n");
+    for block in blocks {
+        out.push('\n');
+        let Some(repl) = &block.repl else {
+            out.push_str(&block.command);
+            out.push('\n');
+            if let Some(output) = block_output_text(block) {
+                push_comment(&mut out, "#", &output);
+            }
+            if let BlockState::Failed(code) = block.state {
+                out.push_str(&format!("# exit {}\n", code));
+            }
+            for note in &block.notes {
+                out.push_str(&format!("# note, line {}: {}\n", note.line + 1, note.text));
+            }
+            continue;
+        };
+
+        let (interpreter, comment, tag) = match repl.language.as_str() {
+            "node" => ("node", "//", "JS"),
+            _ => ("python3", "#", "PY"),
+        };
+        let cells: Vec<&ReplCell> = repl.cells.iter().filter(|cell| cell.number > 0).collect();
+        for session in cells.chunk_by(|a, b| a.session == b.session) {
+            out.push_str(&format!("{} <<'{}'\n", interpreter, tag));
+            for cell in session {
+                out.push_str(cell.code.trim_end());
+                out.push('\n');
+                for output in &cell.outputs {
+                    match output {
+                        ReplCellOutput::Text { text, .. } => push_comment(&mut out, comment, text),
+                        ReplCellOutput::Value(value) => push_comment(&mut out, comment, &value.to_text()),
+                    }
+                }
+                if let Some(error) = &cell.error {
+                    push_comment(&mut out, comment, error);
+                }
+            }
+            out.push_str(tag);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn push_comment(out: &mut String, comment: &str, text: &str) {
+    for line in text.trim_end_matches('\n').lines() {
+        if line.is_empty() {
+            out.push_str(comment);
+        } else {
+            out.push_str(&format!("{} {}", comment, line));
+        }
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nexus_api::{BlockId, ReplUpdate};
+
+    fn repl_block(language: &str) -> Block {
+        let mut block = Block::new(BlockId(2), format!("repl {}", language));
+        let repl = block.repl.get_or_insert_default();
+        repl.apply(ReplUpdate::Ready { language: language.into() });
+        repl.apply(ReplUpdate::CellStarted { cell: 1, code: "x = 2\nx * 3".into() });
+        repl.apply(ReplUpdate::Value { cell: 1, value: Value::Int(6) });
+        repl.apply(ReplUpdate::CellFinished { cell: 1, error: None });
+        repl.apply(ReplUpdate::Ready { language: language.into() });
+        repl.apply(ReplUpdate::CellStarted { cell: 1, code: "x".into() });
+        repl.apply(ReplUpdate::CellFinished { cell: 1, error: Some("Traceback\nNameError: name 'x' is not defined".into()) });
+        block
+    }
+
+    fn command_block() -> Block {
+        let mut block = Block::new(BlockId(1), "ls".to_string());
+        block.structured_output = Some(Value::String("a.txt\nb.txt".into()));
+        block.state = BlockState::Failed(1);
+        block.set_note(1, "the missing file");
+        block
+    }
+
+    #[test]
+    fn test_ipynb_cells() {
+        let (command, repl) = (command_block(), repl_block("python"));
+        let notebook: Json = serde_json::from_str(&to_ipynb(&[&command, &repl])).unwrap();
+        assert_eq!(notebook["metadata"]["kernelspec"]["name"], "python3");
+        let cells = notebook["cells"].as_array().unwrap();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[0]["source"], json!(["!ls"]));
+        assert_eq!(cells[0]["outputs"][0]["text"], json!(["a.txt\n", "b.txt\n"]));
+        assert_eq!(cells[1]["source"], json!(["**Notes**\n", "\n", "- line 2: the missing file"]));
+        assert_eq!(cells[2]["source"], json!(["x = 2\n", "x * 3"]));
+        assert_eq!(cells[2]["outputs"][0]["data"]["text/plain"], json!(["6"]));
+        assert_eq!(cells[3]["outputs"][0]["ename"], "NameError");
+
+        // Node cells run through a magic in a Python notebook; alone they
+        // make a JavaScript one.
+        let node = repl_block("node");
+        let mixed: Json = serde_json::from_str(&to_ipynb(&[&repl, &node])).unwrap();
+        assert_eq!(mixed["cells"][2]["source"][0], "%%script node\n");
+        let only_node: Json = serde_json::from_str(&to_ipynb(&[&command, &node])).unwrap();
+        assert_eq!(only_node["metadata"]["kernelspec"]["name"], "javascript");
+        assert_eq!(only_node["cells"][0]["cell_type"], "markdown");
+    }
+
+    #[test]
+    fn test_script_comments_output() {
+        let script = to_script(&[&command_block(), &repl_block("python")]);
+        assert_eq!(
+            script,
+            "#!/bin/sh\n\nls\n# a.txt\n# b.txt\n# exit 1\n# note, line 2: the missing file\n\n\
+             python3 <<'PY'\nx = 2\nx * 3\n# 6\nPY\n\
+             python3 <<'PY'\nx\n# Traceback\n# NameError: name 'x' is not defined\nPY\n"
+        );
+    }
+}
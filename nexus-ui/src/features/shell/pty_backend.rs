@@ -310,23 +310,35 @@ pub(crate) struct PtyBackend {
     last_parser_size: Cell<(u16, u16)>,
     /// Last size sent to PTY handles (avoids redundant SIGWINCH).
     last_pty_size: Cell<(u16, u16)>,
+    /// Size not yet sent to PTY handles: `(target, first_seen, last_changed)`.
+    pending_pty_size: Cell<Option<((u16, u16), Instant, Instant)>>,
     /// Pending column downsize: `(target_size, first_seen)`. The timer
     /// restarts whenever the target changes, so the reflow only commits
     /// once the size has been stable for the debounce window.
     pending_downsize: Cell<Option<((u16, u16), Instant)>>,
+    /// When running blocks' throughput was last sampled.
+    throughput_sampled_at: Option<Instant>,
 }
 
 impl PtyBackend {
     pub fn new() -> Self {
         let (tx, rx) = mpsc::unbounded_channel();
+        let rx = Arc::new(Mutex::new(rx));
+        // Unbounded, so nothing is ever dropped; only the depth is reported.
+        let weak_rx = Arc::downgrade(&rx);
+        nexus_kernel::diagnostics::register_channel("pty.output", None, move || {
+            weak_rx.upgrade().map(|rx| rx.try_lock().map(|rx| rx.len()).unwrap_or(0))
+        });
         Self {
             handles: Vec::new(),
             tx,
-            rx: Arc::new(Mutex::new(rx)),
+            rx,
             terminal_size: Cell::new((120, 24)),
             last_parser_size: Cell::new((120, 24)),
             last_pty_size: Cell::new((120, 24)),
+            pending_pty_size: Cell::new(None),
             pending_downsize: Cell::new(None),
+            throughput_sampled_at: None,
         }
     }
 
@@ -379,6 +391,17 @@ impl PtyBackend {
         }
     }
 
+    /// Write a password followed by Enter to a PTY.
+    ///
+    /// Bypasses key encoding and bracketed paste: sudo reads the line with
+    /// echo disabled, and nothing here is logged.
+    pub fn write_secret(&self, block_id: BlockId, secret: &[u8]) {
+        if let Some(handle) = self.handles.iter().find(|h| h.block_id == block_id) {
+            let _ = handle.write(secret);
+            let _ = handle.write(b"\r");
+        }
+    }
+
     /// Paste text into a PTY, respecting Bracketed Paste mode.
     ///
     /// If the terminal has enabled bracketed paste (`\x1b[?2004h`), the text
@@ -426,8 +449,12 @@ impl PtyBackend {
     ///   - **Column downsize**: delay the column reflow until the target
     ///     size has been stable for ~32ms.
     ///
-    /// PTY handles are resized via `sync_pty_sizes()` in `view()`.
+    /// Blocks in the alternate screen are left to `sync_alt_screen_sizes`.
+    ///
+    /// PTY handles are resized via `sync_pty_sizes()`.
     pub fn sync_terminal_size(&self, blocks: &mut [Block]) {
+        self.sync_alt_screen_sizes(blocks);
+
         let current_size = self.terminal_size.get();
         let (target_cols, target_rows) = current_size;
         let (parser_cols, parser_rows) = self.last_parser_size.get();
@@ -441,7 +468,7 @@ impl PtyBackend {
         if target_cols >= parser_cols {
             self.last_parser_size.set(current_size);
             self.pending_downsize.set(None);
-            for block in blocks.iter_mut() {
+            for block in blocks.iter_mut().filter(|b| !b.parser.is_alternate_screen()) {
                 block.parser.resize(target_cols, target_rows);
             }
             return;
@@ -450,7 +477,7 @@ impl PtyBackend {
         // Column downsize: apply row changes immediately, delay column reflow.
         if target_rows != parser_rows {
             self.last_parser_size.set((parser_cols, target_rows));
-            for block in blocks.iter_mut() {
+            for block in blocks.iter_mut().filter(|b| !b.parser.is_alternate_screen()) {
                 block.parser.resize(parser_cols, target_rows);
             }
         }
@@ -462,7 +489,7 @@ impl PtyBackend {
             {
                 self.last_parser_size.set(current_size);
                 self.pending_downsize.set(None);
-                for block in blocks.iter_mut() {
+                for block in blocks.iter_mut().filter(|b| !b.parser.is_alternate_screen()) {
                     block.parser.resize(target_cols, target_rows);
                 }
             }
@@ -475,18 +502,82 @@ impl PtyBackend {
         }
     }
 
+    /// Size alternate-screen parsers (vim, htop) to what the PTYs were last
+    /// told, not the window: such apps redraw after SIGWINCH rather than
+    /// reflow, so their grid must change in step with the signal. Returns
+    /// whether any parser was resized.
+    pub fn sync_alt_screen_sizes(&self, blocks: &mut [Block]) -> bool {
+        let (cols, rows) = self.last_pty_size.get();
+        let mut resized = false;
+        for block in blocks.iter_mut().filter(|b| b.parser.is_alternate_screen()) {
+            if block.parser.size() != (cols, rows) {
+                block.parser.resize(cols, rows);
+                block.version += 1;
+                resized = true;
+            }
+        }
+        resized
+    }
+
+    /// Sample the output rate and IO counters of running PTY blocks, about
+    /// once a second, for the throughput figure in their headers. Blocks
+    /// show nothing until they've run a couple of seconds. Returns whether
+    /// any figure changed.
+    pub fn sample_throughput(&mut self, blocks: &mut [Block]) -> bool {
+        const INTERVAL: Duration = Duration::from_secs(1);
+        const AFTER_MS: u64 = 2000;
+
+        if self.throughput_sampled_at.is_some_and(|at| at.elapsed() < INTERVAL) {
+            return false;
+        }
+        self.throughput_sampled_at = Some(Instant::now());
+        let mut changed = false;
+        for handle in &self.handles {
+            let Some(block) = blocks.iter_mut().find(|b| b.id == handle.block_id) else {
+                continue;
+            };
+            let at_ms = block.started_at.elapsed_ms();
+            if !block.is_running() || at_ms < AFTER_MS {
+                continue;
+            }
+            let io = handle.foreground_pid().and_then(nexus_kernel::process::io::io_counters);
+            changed |= block.sample_throughput(at_ms, io);
+        }
+        changed
+    }
+
     /// Send PTY resize (SIGWINCH) to all handles when size changes.
     ///
-    /// Only sends when the size actually changes (avoids redundant signals
-    /// every frame).
+    /// Debounced: a size is sent once it has held for `SETTLE`, and at
+    /// least every `MAX_WAIT` while a window drag or zoom keeps changing
+    /// it, so full-screen apps follow along without a signal per frame.
+    /// Called from `view()` and every tick, which flushes the last size of a
+    /// burst.
     pub fn sync_pty_sizes(&self) {
+        const SETTLE: Duration = Duration::from_millis(50);
+        const MAX_WAIT: Duration = Duration::from_millis(150);
+
         let current_size = self.terminal_size.get();
-        if current_size != self.last_pty_size.get() {
-            self.last_pty_size.set(current_size);
-            let (cols, rows) = current_size;
-            for handle in &self.handles {
-                let _ = handle.resize(cols, rows);
-            }
+        if current_size == self.last_pty_size.get() {
+            self.pending_pty_size.set(None);
+            return;
+        }
+        let now = Instant::now();
+        let (first_seen, changed) = match self.pending_pty_size.get() {
+            Some((size, first_seen, changed)) if size == current_size => (first_seen, changed),
+            Some((_, first_seen, _)) => (first_seen, now),
+            None => (now, now),
+        };
+        if changed.elapsed() < SETTLE && first_seen.elapsed() < MAX_WAIT {
+            self.pending_pty_size.set(Some((current_size, first_seen, changed)));
+            return;
+        }
+
+        self.pending_pty_size.set(None);
+        self.last_pty_size.set(current_size);
+        let (cols, rows) = current_size;
+        for handle in &self.handles {
+            let _ = handle.resize(cols, rows);
         }
     }
 
@@ -415,13 +415,12 @@ impl RemoteBackend {
         self.send(Request::PtyClose { block_id });
     }
 
-    /// Read a file from the remote agent.
+    /// Read a file from the remote agent, at most `len` bytes of it.
     ///
-    /// Returns a oneshot receiver that resolves with the full file contents.
+    /// Returns a oneshot receiver that resolves with the file contents.
     /// Accumulates chunked `FileData` responses internally. Capped at 50 MB
     /// to prevent OOM — larger files will error on the receiver side.
-    #[allow(dead_code)]
-    pub fn file_read(&mut self, path: &str) -> oneshot::Receiver<Vec<u8>> {
+    pub fn file_read(&mut self, path: &str, len: Option<u64>) -> oneshot::Receiver<Vec<u8>> {
         let (tx, rx) = oneshot::channel();
         let id = self.next_id();
         self.pending.insert(
@@ -435,7 +434,7 @@ impl RemoteBackend {
             id,
             path: path.to_string(),
             offset: 0,
-            len: None,
+            len,
         });
         rx
     }
@@ -463,7 +462,6 @@ impl RemoteBackend {
     // poll the spawned task to completion before the transport drains the
     // channel. A bounded channel or explicit semaphore would be needed to
     // cap in-flight data.
-    #[allow(dead_code)]
     pub fn file_write(&mut self, path: &str, data: &[u8]) -> oneshot::Receiver<u64> {
         let (tx, rx) = oneshot::channel();
 
@@ -9,6 +9,7 @@
 //! - Current working directory
 //! - Last command output (serialized for LLM)
 //! - Recent command history
+//! - How to cite blocks and files (see `features::agent::citations`)
 
 use crate::data::Block;
 use nexus_api::BlockState;
@@ -37,31 +38,12 @@ pub fn build_shell_context(
 
     // Current working directory (most important for agent orientation)
     ctx.push_str(&format!("cwd: {}\n", cwd));
+    ctx.push_str(&format!("citations: {}\n", crate::features::agent::citations::CONVENTION));
 
     // Last command and output (critical for understanding what just happened)
     if let Some(last_block) = find_last_completed_block(blocks) {
         ctx.push_str("\nlast_command:\n");
-        ctx.push_str(&format!("  $ {}\n", last_block.command));
-        ctx.push_str(&format!("  exit_code: {}\n", exit_code_from_state(&last_block.state)));
-
-        // Include output (native structured or terminal text)
-        if let Some(ref value) = last_block.structured_output {
-            let output = value.to_text();
-            if !output.is_empty() {
-                ctx.push_str("  output:\n");
-                ctx.push_str(&indent_text(&truncate_output(&output), "    "));
-                ctx.push('\n');
-            }
-        } else {
-            // Fall back to terminal text - extract from grid
-            let grid = last_block.parser.grid();
-            let term_text = extract_text_from_grid(&grid);
-            if !term_text.is_empty() {
-                ctx.push_str("  output:\n");
-                ctx.push_str(&indent_text(&truncate_output(&term_text), "    "));
-                ctx.push('\n');
-            }
-        }
+        ctx.push_str(&describe_block(last_block));
     }
 
     // Recent history (for pattern understanding)
@@ -78,6 +60,45 @@ pub fn build_shell_context(
     ctx
 }
 
+/// A command's block, exit code, error and output, as the lines under
+/// `last_command:`.
+pub fn describe_block(block: &Block) -> String {
+    let mut out = format!("  block: {}\n", block.id.0);
+    out.push_str(&format!("  $ {}\n", block.command));
+    out.push_str(&format!("  exit_code: {}\n", exit_code_from_state(&block.state)));
+    if let Some(error) = &block.error {
+        out.push_str(&format_error(error));
+    }
+
+    // Include output (native structured or terminal text)
+    let output = match &block.structured_output {
+        Some(value) => value.to_text(),
+        // Fall back to terminal text - extract from grid
+        None => extract_text_from_grid(&block.parser.grid()),
+    };
+    if !output.is_empty() {
+        out.push_str("  output:\n");
+        out.push_str(&indent_text(&truncate_output(&output), "    "));
+        out.push('\n');
+    }
+    out
+}
+
+/// Machine-readable failure of a native command.
+fn format_error(error: &nexus_api::CommandError) -> String {
+    let mut out = format!("  error:\n    kind: {:?}\n    message: {}\n", error.kind, error.message);
+    if let Some(path) = &error.path {
+        out.push_str(&format!("    path: {}\n", path.display()));
+    }
+    if let Some(errno) = error.errno {
+        out.push_str(&format!("    errno: {}\n", errno));
+    }
+    if let Some(suggestion) = &error.suggestion {
+        out.push_str(&format!("    suggestion: {}\n", suggestion));
+    }
+    out
+}
+
 /// Build a minimal context for quick queries (less token usage).
 #[allow(dead_code)]
 pub fn build_minimal_context(cwd: &str) -> String {
@@ -95,6 +116,7 @@ fn exit_code_from_state(state: &BlockState) -> i32 {
         BlockState::Running => -1,
         BlockState::Success => 0,
         BlockState::Failed(code) => *code,
+        BlockState::Interrupted => -1,
     }
 }
 
@@ -173,12 +195,28 @@ mod tests {
         let ctx = build_shell_context("/home/user", &blocks, &history);
 
         assert!(ctx.contains("cwd: /home/user"));
+        assert!(ctx.contains("[[block:ID]]"));
+        assert!(ctx.contains("block: 1\n"));
         assert!(ctx.contains("$ ls -la"));
         assert!(ctx.contains("exit_code: 0"));
         assert!(ctx.contains("file1.txt"));
         assert!(ctx.contains("cd /tmp"));
     }
 
+    #[test]
+    fn test_build_shell_context_includes_command_error() {
+        let mut block = make_test_block(1, "cat nope", BlockState::Failed(1), None);
+        let io = std::io::Error::from_raw_os_error(2);
+        block.error = Some(nexus_api::CommandError::io("cat", "nope", &io).with_suggestion("did you mean 'note'?"));
+
+        let ctx = build_shell_context("/tmp", &[block], &[]);
+
+        assert!(ctx.contains("kind: NotFound"));
+        assert!(ctx.contains("path: nope"));
+        assert!(ctx.contains("errno: 2"));
+        assert!(ctx.contains("suggestion: did you mean 'note'?"));
+    }
+
     #[test]
     fn test_build_shell_context_empty() {
         let ctx = build_shell_context("/tmp", &[], &[]);
@@ -0,0 +1,363 @@
+//! Password prompts — secure input instead of typing into the PTY.
+//!
+//! PTY output is scanned for password prompts: sudo's, ssh's passphrase and
+//! password prompts, gpg's when pinentry falls back to the terminal, and
+//! git's for HTTPS remotes. When one appears, the shell shows a masked
+//! input bar above the command line; the password is written straight to
+//! the PTY master and never touches the input buffer, shell history, macro
+//! recordings, or tracing output. Output from the block is kept out of the
+//! event journal until the answer is in, in case the program echoes it.
+//!
+//! Prompts from several blocks queue up, one bar at a time, each with its
+//! own input, so a second prompt never takes over one being answered.
+//!
+//! Passwords are never kept after they're written: a cached one would be
+//! typed into whatever next printed a matching prompt. How long sudo itself
+//! remembers an answer is left to its policy: its timestamp is per terminal
+//! by default and every block has its own, so sudo asks again in each block
+//! unless sudoers says otherwise (`Defaults timestamp_type=global`).
+
+use std::collections::VecDeque;
+
+use nexus_api::BlockId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::ShellMsg;
+
+/// Password bytes that are wiped on drop and never printed.
+pub(crate) struct Secret(Vec<u8>);
+
+impl Secret {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().filter(|b| (**b & 0xC0) != 0x80).count()
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    fn pop(&mut self) {
+        // Remove one full UTF-8 character.
+        while let Some(b) = self.0.pop() {
+            if (b & 0xC0) != 0x80 {
+                break;
+            }
+        }
+    }
+
+    fn wipe(&mut self) {
+        for b in self.0.iter_mut() {
+            // Volatile write so the zeroing isn't optimized away.
+            unsafe { std::ptr::write_volatile(b, 0) };
+        }
+        self.0.clear();
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.wipe();
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+/// The program asking for a secret.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PromptKind {
+    Sudo,
+    Ssh,
+    Gpg,
+    Git,
+    /// A bare `Password:`, which macOS sudo, su, ssh's keyboard-interactive
+    /// auth and any script may print alike.
+    Unknown,
+}
+
+impl PromptKind {
+    /// What the user is asked for, for the input's placeholder.
+    pub fn noun(self) -> &'static str {
+        match self {
+            PromptKind::Gpg => "passphrase",
+            _ => "password",
+        }
+    }
+}
+
+/// A password prompt waiting for the user.
+#[derive(Debug)]
+pub(crate) struct SudoPrompt {
+    pub block_id: BlockId,
+    /// The prompt text as printed (e.g. "[sudo] password for alice:").
+    pub prompt: String,
+    pub kind: PromptKind,
+    pub secret: Secret,
+}
+
+/// Tracks the prompts waiting for an answer.
+pub(crate) struct SudoAuth {
+    /// At most one per block; the first is the one shown.
+    queue: VecDeque<SudoPrompt>,
+    /// Block just answered, whose next output isn't journaled.
+    echo_guard: Option<BlockId>,
+}
+
+impl SudoAuth {
+    pub fn new() -> Self {
+        Self { queue: VecDeque::new(), echo_guard: None }
+    }
+
+    /// The prompt being shown.
+    pub fn pending(&self) -> Option<&SudoPrompt> {
+        self.queue.front()
+    }
+
+    /// Whether the secure prompt is visible (and should capture keys).
+    pub fn is_active(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    /// Scan a chunk of PTY output from `block_id` for a password prompt,
+    /// queueing it unless the block already has one waiting. Returns
+    /// whether a prompt was queued.
+    pub fn scan(&mut self, block_id: BlockId, data: &[u8]) -> bool {
+        if self.queue.iter().any(|p| p.block_id == block_id) {
+            return false;
+        }
+        let Some((kind, prompt)) = detect_prompt(data) else {
+            return false;
+        };
+        self.queue.push_back(SudoPrompt { block_id, prompt, kind, secret: Secret::new() });
+        true
+    }
+
+    /// Take the entered password for writing to the PTY, and show the next
+    /// prompt.
+    pub fn submit(&mut self) -> Option<(BlockId, Secret)> {
+        let prompt = self.queue.pop_front()?;
+        self.echo_guard = Some(prompt.block_id);
+        Some((prompt.block_id, prompt.secret))
+    }
+
+    /// Whether output from `block_id` may go to the event journal. Output
+    /// while its prompt waits, and the first chunk after it is answered,
+    /// is left out.
+    pub fn journals(&mut self, block_id: BlockId) -> bool {
+        if self.queue.iter().any(|p| p.block_id == block_id) {
+            return false;
+        }
+        self.echo_guard.take_if(|id| *id == block_id).is_none()
+    }
+
+    /// Dismiss the shown prompt without answering. Returns the block it
+    /// belonged to.
+    pub fn cancel(&mut self) -> Option<BlockId> {
+        self.queue.pop_front().map(|p| p.block_id)
+    }
+
+    /// Drop the block's prompt if it went away.
+    pub fn block_exited(&mut self, block_id: BlockId) {
+        self.queue.retain(|p| p.block_id != block_id);
+        if self.echo_guard == Some(block_id) {
+            self.echo_guard = None;
+        }
+    }
+
+    /// Apply a key to the shown prompt.
+    pub fn handle_key(&mut self, event: &KeyEvent) {
+        let (Some(prompt), KeyEvent::Pressed { key, modifiers, text }) = (self.queue.front_mut(), event) else {
+            return;
+        };
+        match key {
+            Key::Named(NamedKey::Backspace) => prompt.secret.pop(),
+            Key::Character(c) if modifiers.ctrl && c == "u" => prompt.secret.wipe(),
+            Key::Character(c) if !modifiers.ctrl && !modifiers.meta => {
+                prompt.secret.push_str(text.as_deref().unwrap_or(c));
+            }
+            Key::Named(NamedKey::Space) => prompt.secret.push_str(" "),
+            _ => {}
+        }
+    }
+
+    /// Route a key while the prompt is active.
+    pub fn on_key(&self, event: &KeyEvent) -> Option<ShellMsg> {
+        let KeyEvent::Pressed { key, modifiers, .. } = event else {
+            return None;
+        };
+        match key {
+            Key::Named(NamedKey::Enter) => Some(ShellMsg::SudoSubmit),
+            Key::Named(NamedKey::Escape) => Some(ShellMsg::SudoCancel),
+            Key::Character(c) if modifiers.ctrl && c == "c" => Some(ShellMsg::SudoCancel),
+            _ => Some(ShellMsg::SudoKey(event.clone())),
+        }
+    }
+}
+
+/// Detect a password prompt at the end of a PTY output chunk.
+///
+/// Matches sudo's `[sudo] password for user: `; ssh's `Enter passphrase for key '…':`, `user@host's
+/// password:` and `(user@host) Password:`; gpg's `Passphrase:` and
+/// `Enter passphrase:` from pinentry-tty or loopback; and git's
+/// `Password for 'https://…':`. A bare `Password:` (macOS sudo, su) can't be
+/// told apart and is [`PromptKind::Unknown`]. Only the last line is considered so that a
+/// prompt string appearing earlier in ordinary output is ignored.
+pub(crate) fn detect_prompt(data: &[u8]) -> Option<(PromptKind, String)> {
+    let text = String::from_utf8_lossy(data);
+    let last_line = text.rsplit(['\n', '\r']).find(|l| !l.trim().is_empty())?;
+    let line = strip_ansi(last_line);
+    let line = line.trim();
+    if !line.ends_with(':') {
+        return None;
+    }
+    let kind = if line.starts_with("[sudo] password for ") {
+        PromptKind::Sudo
+    } else if line == "Password:" {
+        PromptKind::Unknown
+    } else if line.starts_with("Enter passphrase for key ")
+        || line.ends_with("'s password:")
+        || (line.starts_with('(') && line.ends_with(") Password:"))
+    {
+        PromptKind::Ssh
+    } else if line == "Passphrase:" || line == "Enter passphrase:" {
+        PromptKind::Gpg
+    } else if line.starts_with("Password for '") && line.ends_with("':") {
+        PromptKind::Git
+    } else {
+        return None;
+    };
+    Some((kind, line.to_string()))
+}
+
+/// Remove CSI escape sequences (sudo may emit color or bell).
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            if chars.peek() == Some(&'[') {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if !c.is_control() {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_linux_and_macos_prompts() {
+        let prompt = |data: &[u8]| detect_prompt(data).map(|(_, prompt)| prompt);
+        assert_eq!(
+            prompt(b"[sudo] password for alice: ").as_deref(),
+            Some("[sudo] password for alice:")
+        );
+        assert_eq!(prompt(b"\r\nPassword:").as_deref(), Some("Password:"));
+        assert_eq!(prompt(b"Sorry, try again.\n[sudo] password for bob: ").as_deref(),
+            Some("[sudo] password for bob:"));
+    }
+
+    #[test]
+    fn detects_ssh_gpg_and_git_prompts() {
+        let kind = |data: &[u8]| detect_prompt(data).map(|(kind, _)| kind);
+        assert_eq!(kind(b"Enter passphrase for key '/home/a/.ssh/id_ed25519': "), Some(PromptKind::Ssh));
+        assert_eq!(kind(b"alice@build-01's password: "), Some(PromptKind::Ssh));
+        assert_eq!(kind(b"(alice@build-01) Password: "), Some(PromptKind::Ssh));
+        assert_eq!(kind(b"\x1b[1mPassphrase:\x1b[0m "), Some(PromptKind::Gpg));
+        assert_eq!(kind(b"Password for 'https://alice@github.com': "), Some(PromptKind::Git));
+        assert_eq!(kind(b"Username for 'https://github.com': "), None);
+    }
+
+    #[test]
+    fn bare_password_prompt_is_not_sudo() {
+        let kind = |data: &[u8]| detect_prompt(data).map(|(kind, _)| kind);
+        // su, and ssh keyboard-interactive auth.
+        assert_eq!(kind(b"$ su -\r\nPassword: "), Some(PromptKind::Unknown));
+        assert_eq!(kind(b"Authenticated with partial success.\r\nPassword:"), Some(PromptKind::Unknown));
+    }
+
+    #[test]
+    fn ignores_prompt_text_in_ordinary_output() {
+        assert!(detect_prompt(b"Password: reset\nDone\n").is_none());
+        assert!(detect_prompt(b"enter your Password: ").is_none());
+        assert!(detect_prompt(b"").is_none());
+    }
+
+    #[test]
+    fn every_prompt_asks() {
+        let mut auth = SudoAuth::new();
+        assert!(auth.scan(BlockId(1), b"[sudo] password for alice: "));
+        auth.queue[0].secret.push_str("hunter2");
+        let (id, secret) = auth.submit().unwrap();
+        assert_eq!(id, BlockId(1));
+        assert_eq!(secret.len(), 7);
+
+        // Nothing is kept to answer the next one with.
+        assert!(auth.scan(BlockId(2), b"[sudo] password for alice: "));
+        assert_eq!(auth.pending().unwrap().secret.len(), 0);
+    }
+
+    #[test]
+    fn second_prompt_waits_its_turn() {
+        let mut auth = SudoAuth::new();
+        auth.scan(BlockId(1), b"[sudo] password for alice: ");
+        auth.queue[0].secret.push_str("hun");
+        // Another block's prompt queues; the same block's again is ignored.
+        assert!(auth.scan(BlockId(2), b"alice@build-01's password: "));
+        assert!(!auth.scan(BlockId(1), b"[sudo] password for alice: "));
+        assert_eq!(auth.pending().unwrap().block_id, BlockId(1));
+        assert_eq!(auth.pending().unwrap().secret.len(), 3);
+        assert!(!auth.journals(BlockId(2)));
+
+        let (id, secret) = auth.submit().unwrap();
+        assert_eq!((id, secret.len()), (BlockId(1), 3));
+        assert_eq!(auth.pending().unwrap().kind, PromptKind::Ssh);
+
+        auth.scan(BlockId(3), b"Passphrase: ");
+        auth.block_exited(BlockId(2));
+        assert_eq!(auth.pending().unwrap().block_id, BlockId(3));
+        assert_eq!(auth.cancel(), Some(BlockId(3)));
+        assert!(!auth.is_active());
+    }
+
+    #[test]
+    fn answer_is_kept_out_of_journal() {
+        let mut auth = SudoAuth::new();
+        auth.scan(BlockId(1), b"Enter passphrase: ");
+        assert!(!auth.journals(BlockId(1)));
+        assert!(auth.journals(BlockId(2)));
+        auth.submit();
+        // The chunk after the answer may echo it; later ones are fine.
+        assert!(!auth.journals(BlockId(1)));
+        assert!(auth.journals(BlockId(1)));
+    }
+
+    #[test]
+    fn secret_debug_is_redacted() {
+        let mut s = Secret::new();
+        s.push_str("pw");
+        assert_eq!(format!("{s:?}"), "Secret(<redacted>)");
+    }
+}
@@ -0,0 +1,196 @@
+//! Update checker — "new version available" in the status bar, release
+//! notes on click, and (on macOS) downloading the update for the user to
+//! install.
+//!
+//! Opt-in: nothing is fetched unless `[updates] check = true`. The first
+//! window checks once at startup, or once the network returns if it starts
+//! offline; see `nexus_kernel::update` for the feed.
+
+use std::path::PathBuf;
+
+use nexus_kernel::network;
+use nexus_kernel::update::{self, Release};
+use strata::content_address::SourceId;
+use strata::event_context::{Key, KeyEvent, NamedKey};
+
+use crate::app::message::UpdateMsg;
+use crate::utils::ids;
+
+/// Progress of downloading the release.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Download {
+    NotStarted,
+    InProgress,
+    /// Downloaded and verified, waiting to be installed.
+    Staged(PathBuf),
+    Failed(String),
+}
+
+pub(crate) struct UpdateWidget {
+    /// A release newer than this build, once a check found one.
+    pub release: Option<Release>,
+    pub download: Download,
+    /// Whether the release notes are showing.
+    pub notes_open: bool,
+    /// Hidden from the status bar for the rest of this run.
+    pub dismissed: bool,
+}
+
+/// What the status-bar pill says.
+pub(crate) struct UpdateIndicator {
+    pub label: String,
+    /// Ready to install, rather than merely available.
+    pub ready: bool,
+}
+
+impl UpdateWidget {
+    pub fn new() -> Self {
+        Self { release: None, download: Download::NotStarted, notes_open: false, dismissed: false }
+    }
+
+    /// Whether downloads can be staged on this platform.
+    pub fn can_stage(&self) -> bool {
+        cfg!(target_os = "macos") && self.release.as_ref().is_some_and(|r| r.macos_asset().is_some())
+    }
+
+    pub fn indicator(&self) -> Option<UpdateIndicator> {
+        let release = self.release.as_ref().filter(|_| !self.dismissed)?;
+        Some(match &self.download {
+            Download::Staged(_) => UpdateIndicator { label: format!("\u{2B06} Nexus {} ready to install", release.version), ready: true },
+            Download::InProgress => UpdateIndicator { label: format!("\u{2B06} Downloading Nexus {}\u{2026}", release.version), ready: false },
+            Download::NotStarted | Download::Failed(_) => {
+                UpdateIndicator { label: format!("\u{2B06} Nexus {} available", release.version), ready: false }
+            }
+        })
+    }
+
+    /// Apply an update message. Returns the release to download when the
+    /// user asked for it.
+    pub fn update(&mut self, msg: UpdateMsg) -> Option<Release> {
+        match msg {
+            UpdateMsg::Checked(release) => {
+                if let Some(release) = &release {
+                    tracing::info!("update: Nexus {} is available (running {})", release.version, update::CURRENT_VERSION);
+                }
+                self.release = release;
+            }
+            UpdateMsg::ShowNotes => self.notes_open = true,
+            UpdateMsg::CloseNotes => self.notes_open = false,
+            UpdateMsg::Download => {
+                if self.can_stage() && !matches!(self.download, Download::InProgress | Download::Staged(_)) {
+                    self.download = Download::InProgress;
+                    return self.release.clone();
+                }
+            }
+            UpdateMsg::Downloaded(result) => {
+                self.download = match result {
+                    Ok(path) => Download::Staged(path),
+                    Err(e) => Download::Failed(e),
+                };
+            }
+            // Opening the installer happens in the root; nothing changes here.
+            UpdateMsg::Install => {}
+            UpdateMsg::Dismiss => {
+                self.dismissed = true;
+                self.notes_open = false;
+            }
+        }
+        None
+    }
+
+    /// The staged file to open, if there is one.
+    pub fn staged(&self) -> Option<&PathBuf> {
+        match &self.download {
+            Download::Staged(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn on_key(&self, event: &KeyEvent) -> Option<UpdateMsg> {
+        match event {
+            KeyEvent::Pressed { key: Key::Named(NamedKey::Escape), .. } => Some(UpdateMsg::CloseNotes),
+            _ => None,
+        }
+    }
+
+    pub fn on_click(&self, id: SourceId) -> Option<UpdateMsg> {
+        if id == ids::update_pill() {
+            Some(UpdateMsg::ShowNotes)
+        } else if id == ids::update_close() {
+            Some(UpdateMsg::CloseNotes)
+        } else if id == ids::update_download() {
+            Some(UpdateMsg::Download)
+        } else if id == ids::update_install() {
+            Some(UpdateMsg::Install)
+        } else if id == ids::update_dismiss() {
+            Some(UpdateMsg::Dismiss)
+        } else {
+            None
+        }
+    }
+}
+
+/// Look for a newer release at `feed`. Failures are logged, not shown:
+/// nobody asked for this check in the moment. While offline the check
+/// waits, backing off, until the network returns.
+pub(crate) async fn check(feed: String) -> Option<Release> {
+    let mut backoff = network::Backoff::new();
+    while !network::is_online() {
+        let delay = backoff.next_delay();
+        tracing::info!("update: offline, checking again in {}s", delay.as_secs());
+        tokio::time::sleep(delay).await;
+    }
+    match tokio::task::spawn_blocking(move || update::check(&feed)).await {
+        Ok(Ok(release)) => release,
+        Ok(Err(e)) => {
+            tracing::warn!("{:#}", e);
+            None
+        }
+        Err(e) => {
+            tracing::warn!("update: check task failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Download and verify `release` into `~/.nexus/updates`.
+pub(crate) async fn stage(release: Release) -> Result<PathBuf, String> {
+    let dir = update::updates_dir().ok_or_else(|| "HOME is not set".to_string())?;
+    tokio::task::spawn_blocking(move || update::stage(&release, &dir).map_err(|e| format!("{:#}", e)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release() -> Release {
+        Release { version: "9.9.9".to_string(), notes: "- Faster".to_string(), page: None, assets: Vec::new() }
+    }
+
+    #[test]
+    fn test_indicator_follows_download() {
+        let mut widget = UpdateWidget::new();
+        assert!(widget.indicator().is_none());
+
+        widget.update(UpdateMsg::Checked(Some(release())));
+        assert_eq!(widget.indicator().unwrap().label, "\u{2B06} Nexus 9.9.9 available");
+
+        widget.update(UpdateMsg::Downloaded(Ok(PathBuf::from("/tmp/Nexus.dmg"))));
+        let indicator = widget.indicator().unwrap();
+        assert!(indicator.ready);
+        assert_eq!(widget.staged(), Some(&PathBuf::from("/tmp/Nexus.dmg")));
+
+        widget.update(UpdateMsg::Dismiss);
+        assert!(widget.indicator().is_none());
+    }
+
+    #[test]
+    fn test_download_needs_a_macos_asset() {
+        let mut widget = UpdateWidget::new();
+        widget.update(UpdateMsg::Checked(Some(release())));
+        assert_eq!(widget.update(UpdateMsg::Download), None);
+        assert_eq!(widget.download, Download::NotStarted);
+    }
+}
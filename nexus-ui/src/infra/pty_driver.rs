@@ -22,6 +22,8 @@ pub struct PtyHandle {
     child: Arc<Mutex<Option<Box<dyn Child + Send + Sync>>>>,
     /// Slave PTY device path (e.g. `/dev/ttys001`), if available.
     pub tty_path: Option<String>,
+    /// PID of the `sh -c` child.
+    pid: Option<u32>,
 }
 
 impl PtyHandle {
@@ -66,9 +68,8 @@ impl PtyHandle {
         let child = pair.slave.spawn_command(cmd)?;
 
         // Capture the slave TTY device path (e.g. /dev/ttys001) from the child PID.
-        let tty_path = child
-            .process_id()
-            .and_then(crate::infra::scripting::tty_for_pid);
+        let pid = child.process_id();
+        let tty_path = pid.and_then(crate::infra::scripting::tty_for_pid);
 
         let child: Arc<Mutex<Option<Box<dyn Child + Send + Sync>>>> =
             Arc::new(Mutex::new(Some(child)));
@@ -83,6 +84,8 @@ impl PtyHandle {
         let tx_clone = tx.clone();
         let child_clone = child.clone();
         thread::spawn(move || {
+            // Spans the child's lifetime; per-read spans would mostly time the wait.
+            let _span = tracing::debug_span!("pty.reader", block = ?block_id).entered();
             let mut buf = [0u8; 4096];
             loop {
                 match reader.read(&mut buf) {
@@ -100,8 +103,14 @@ impl PtyHandle {
                 }
             }
 
-            // Wait for child and send exit status
+            // Wait for child and send exit status. Reaping it directly also
+            // gets its resource usage.
             if let Some(mut child) = child_clone.lock().unwrap().take() {
+                if let Some((code, usage)) = pid.and_then(nexus_kernel::process::usage::wait_exit) {
+                    let _ = tx_clone.send((block_id, PtyEvent::Usage(usage)));
+                    let _ = tx_clone.send((block_id, PtyEvent::Exited(code)));
+                    return;
+                }
                 match child.wait() {
                     Ok(status) => {
                         let code = status.exit_code() as i32;
@@ -121,9 +130,20 @@ impl PtyHandle {
             master,
             child,
             tty_path,
+            pid,
         })
     }
 
+    /// PID of the process in the foreground of the PTY: the job the shell
+    /// is running, falling back to the shell itself.
+    pub fn foreground_pid(&self) -> Option<u32> {
+        #[cfg(unix)]
+        if let Some(leader) = self.master.lock().unwrap().process_group_leader() {
+            return Some(leader as u32);
+        }
+        self.pid
+    }
+
     /// Write input to the PTY.
     pub fn write(&self, data: &[u8]) -> std::io::Result<()> {
         let mut writer = self.writer.lock().unwrap();
@@ -10,7 +10,8 @@ use std::sync::Arc;
 use tokio::sync::{mpsc, Mutex};
 
 use crate::features::agent::events::AgentEvent;
-use crate::features::agent::claude::spawn_claude_cli_task;
+use crate::features::agent::claude::{spawn_claude_cli_task, AgentLimits};
+use crate::features::agent::remote_files::RemoteTarget;
 
 /// Spawn an agent task to process a query using Claude Code CLI.
 ///
@@ -23,6 +24,10 @@ use crate::features::agent::claude::spawn_claude_cli_task;
 /// - MCP integration
 ///
 /// `session_id` is used to resume a prior conversation (the CLI maintains its own history).
+/// `limits` come from the user config and are read afresh for every query.
+/// `remote` is set while the shell is connected to a remote host, whose
+/// files the agent then works on.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_agent_task(
     event_tx: mpsc::UnboundedSender<AgentEvent>,
     cancel_flag: Arc<AtomicBool>,
@@ -31,8 +36,11 @@ pub async fn spawn_agent_task(
     attachments: Vec<nexus_api::Value>,
     session_id: Option<String>,
     permission_port: Option<u16>,
+    limits: AgentLimits,
+    remote: Option<RemoteTarget>,
 ) -> anyhow::Result<Option<String>> {
-    spawn_claude_cli_task(event_tx, cancel_flag, query, working_dir, session_id, attachments, permission_port).await
+    spawn_claude_cli_task(event_tx, cancel_flag, query, working_dir, session_id, attachments, permission_port, limits, remote)
+        .await
 }
 
 /// Async subscription that awaits agent events.
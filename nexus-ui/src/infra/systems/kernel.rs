@@ -5,11 +5,14 @@ use std::sync::Arc;
 use tokio::sync::{broadcast, Mutex};
 
 use nexus_api::ShellEvent;
+use strata::shell::subscription::BroadcastItem;
 
 /// Async subscription that awaits kernel events.
-/// Returns raw ShellEvent for caller to map to messages.
+/// Returns raw ShellEvents for the caller to map to messages. When the
+/// receiver lags, yields `Lagged` and stops for the rest of the tick so the
+/// caller can resync from the kernel's replay log before reading on.
 pub fn kernel_subscription(
     rx: Arc<Mutex<broadcast::Receiver<ShellEvent>>>,
-) -> strata::Subscription<ShellEvent> {
-    strata::shell::subscription::from_broadcast(rx)
+) -> strata::Subscription<BroadcastItem<ShellEvent>> {
+    strata::shell::subscription::from_broadcast_reporting_lag(rx)
 }
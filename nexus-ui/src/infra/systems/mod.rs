@@ -4,12 +4,15 @@
 //! - PTY (pseudo-terminal) processes
 //! - Kernel (native command execution)
 //! - Agent (AI assistant)
+//! - Context provider workers
 
 pub mod agent;
 pub mod kernel;
 pub(crate) mod permission_server;
+pub mod providers;
 pub mod pty;
 
 pub use agent::{agent_subscription, spawn_agent_task};
 pub use kernel::kernel_subscription;
+pub use providers::provider_subscription;
 pub use pty::pty_subscription;
@@ -6,6 +6,10 @@
 //!
 //! Special handling for AskUserQuestion: instead of a generic permission dialog,
 //! we show the question dialog and return the user's answer via `updatedInput`.
+//!
+//! Remote file tool calls (`{"file_tool": .., "arguments": ..}`) aren't
+//! permission requests: they go to the UI as `AgentEvent::RemoteFile` and the
+//! result is sent back as `{"ok": text}` or `{"error": message}`.
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpListener;
@@ -13,6 +17,7 @@ use tokio::sync::mpsc;
 
 use crate::features::agent::events::AgentEvent;
 use crate::features::agent::claude::parse_user_questions;
+use crate::features::agent::remote_files::{self, RemoteFileOp};
 
 /// Response from the UI to a permission/question request.
 #[derive(Debug, Clone)]
@@ -46,8 +51,9 @@ pub async fn run(
         let mut reader = BufReader::new(reader);
         let mut line = String::new();
 
+        // `doctor` probes the port by connecting and hanging up.
         if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
-            tracing::warn!("[perm-server] empty read, skipping");
+            tracing::debug!("[perm-server] empty read, skipping");
             continue;
         }
 
@@ -61,6 +67,32 @@ pub async fn run(
             }
         };
 
+        // Served on their own task, so a slow transfer doesn't hold up
+        // permission prompts.
+        if let Some(tool) = request.get("file_tool").and_then(|v| v.as_str()) {
+            let op = RemoteFileOp::from_call(tool, request.get("arguments").unwrap_or(&serde_json::Value::Null));
+            let event_tx = event_tx.clone();
+            tokio::spawn(async move {
+                let result = match op {
+                    Ok(op) => {
+                        let (reply, mut rx) = mpsc::unbounded_channel();
+                        let _ = event_tx.send(AgentEvent::RemoteFile { op, reply });
+                        rx.recv().await.unwrap_or_else(|| Err("the request was dropped".to_string()))
+                    }
+                    Err(e) => Err(e),
+                };
+                let resp = match result {
+                    Ok(text) => serde_json::json!({ "ok": text }),
+                    Err(message) => serde_json::json!({ "error": message }),
+                };
+                let _ = writer
+                    .write_all(format!("{}\n", serde_json::to_string(&resp).unwrap()).as_bytes())
+                    .await;
+                let _ = writer.flush().await;
+            });
+            continue;
+        }
+
         let tool_name = request
             .get("tool_name")
             .and_then(|v| v.as_str())
@@ -171,6 +203,13 @@ fn format_description(tool_name: &str, input: &serde_json::Value) -> String {
                 .unwrap_or("?");
             format!("Edit notebook {path}")
         }
+        name if name == remote_files::qualified(remote_files::WRITE_FILE) => {
+            let path = input
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or("?");
+            format!("Write {path} on the remote host")
+        }
         _ => format!("{tool_name}"),
     }
 }
@@ -190,6 +229,11 @@ fn summarize_action(tool_name: &str, input: &serde_json::Value) -> String {
             .and_then(|v| v.as_str())
             .unwrap_or("modify file")
             .to_string(),
+        name if name == remote_files::qualified(remote_files::WRITE_FILE) => input
+            .get("path")
+            .and_then(|v| v.as_str())
+            .unwrap_or("modify remote file")
+            .to_string(),
         _ => tool_name.to_string(),
     }
 }
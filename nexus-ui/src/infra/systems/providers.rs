@@ -0,0 +1,8 @@
+//! Provider subscription for replies from context provider workers.
+
+use crate::data::provider_host::{ProviderUpdate, UpdateReceiver};
+
+/// Async subscription that yields provider replies as they arrive.
+pub fn provider_subscription(rx: UpdateReceiver) -> strata::Subscription<ProviderUpdate> {
+    strata::shell::subscription::from_receiver(rx)
+}
@@ -5,25 +5,32 @@
 //! Flags:
 //!   --demo  Launch the Strata demo/playground UI
 
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 fn main() -> strata::shell::Result {
     let args: Vec<String> = std::env::args().collect();
 
-    // Hidden subcommand: `nexus mcp-proxy --port <PORT>`
-    // Spawned by the Claude CLI as an MCP stdio server for permission prompts.
+    // Hidden subcommand: `nexus mcp-proxy --port <PORT> [--remote]`
+    // Spawned by the Claude CLI as an MCP stdio server for permission prompts,
+    // and with `--remote` for file tools on the remote host.
     if args.iter().any(|a| a == "mcp-proxy") {
         let port = args
             .windows(2)
             .find(|w| w[0] == "--port")
             .and_then(|w| w[1].parse::<u16>().ok())
             .expect("usage: nexus mcp-proxy --port <PORT>");
-        nexus_ui::features::agent::mcp::run(port);
+        nexus_ui::features::agent::mcp::run(port, args.iter().any(|a| a == "--remote"));
     }
 
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
+    // RUST_LOG filters only the log output; the diagnostics layer keeps
+    // warnings, recent log lines and span timings regardless, for the
+    // `diagnostics` command and crash reports.
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(nexus_kernel::diagnostics::layer())
         .init();
+    nexus_kernel::crash::install();
 
     if args.iter().any(|a| a == "--demo") {
         tracing::info!("Starting Strata demo");
@@ -6,6 +6,9 @@ use std::path::PathBuf;
 
 use nexus_api::BlockId;
 
+use crate::features::agent::transcript::TranscriptFormat;
+use crate::features::shell::notebook::NotebookFormat;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ContextMenuItem {
     Copy,
@@ -17,6 +20,12 @@ pub enum ContextMenuItem {
     CopyAsJson,
     CopyAsTsv,
     Rerun,
+    /// Start a pipeline from this block's output (`_N | `).
+    PipeInto,
+    /// Write the block to a `.nexusblock` bundle and an HTML preview.
+    ShareBlock,
+    /// Take the block out of the session; `forget` also deletes its stored copy.
+    HideBlock { forget: bool },
     // File-specific actions
     QuickLook(PathBuf),
     Open(PathBuf),
@@ -33,6 +42,33 @@ pub enum ContextMenuItem {
     ClearColumnFilter(BlockId, usize),
     /// Clear all filters on this table.
     ClearAllFilters(BlockId),
+    // Provider actions
+    /// Run a command a context provider offered.
+    RunAction { label: String, command: String },
+    /// Open a new window in a sibling git worktree.
+    OpenWorktree { label: String, path: PathBuf },
+    /// Show only the blocks run in this worktree; None shows them all.
+    FilterWorktree(Option<String>),
+    /// A line between groups of items; does nothing.
+    Separator,
+    // Keyboard macros
+    RecordMacro,
+    StopMacroRecording,
+    /// Make replay wait for confirmation at this point of the recording.
+    InsertMacroPause,
+    RunMacro { label: String, name: String },
+    /// Open the settings view.
+    Settings,
+    /// Open the usage insights view.
+    Insights,
+    // Agent conversation actions
+    /// Write the agent conversation to a file.
+    ExportConversation { format: TranscriptFormat, thinking: bool },
+    /// Load a JSONL transcript to start a new session from.
+    ImportTranscript,
+    // Selection actions
+    /// Write the selected blocks to a notebook or script.
+    ExportBlocks { format: NotebookFormat, blocks: Vec<BlockId> },
 }
 
 impl ContextMenuItem {
@@ -47,6 +83,10 @@ impl ContextMenuItem {
             Self::CopyAsJson => "Copy as JSON",
             Self::CopyAsTsv => "Copy as TSV",
             Self::Rerun => "Rerun",
+            Self::PipeInto => "Pipe Into\u{2026}",
+            Self::ShareBlock => "Share Block\u{2026}",
+            Self::HideBlock { forget: false } => "Hide Block",
+            Self::HideBlock { forget: true } => "Delete Block from History",
             Self::QuickLook(_) => "Quick Look",
             Self::Open(_) => "Open",
             Self::CopyPath(_) => "Copy Path",
@@ -56,6 +96,25 @@ impl ContextMenuItem {
             Self::ExcludeValue { .. } => "Exclude This Value",
             Self::ClearColumnFilter(_, _) => "Clear Column Filter",
             Self::ClearAllFilters(_) => "Clear All Filters",
+            Self::RunAction { label, .. } => label.as_str(),
+            Self::OpenWorktree { label, .. } => label.as_str(),
+            Self::FilterWorktree(Some(_)) => "Show Only This Worktree",
+            Self::FilterWorktree(None) => "Show All Worktrees",
+            Self::Separator => "",
+            Self::RecordMacro => "Record Macro",
+            Self::StopMacroRecording => "Stop Recording Macro\u{2026}",
+            Self::InsertMacroPause => "Insert Confirmation Pause",
+            Self::RunMacro { label, .. } => label.as_str(),
+            Self::Settings => "Settings\u{2026}",
+            Self::Insights => "Usage Insights\u{2026}",
+            Self::ExportConversation { format: TranscriptFormat::Markdown, thinking: false } => "Export Conversation as Markdown",
+            Self::ExportConversation { format: TranscriptFormat::Markdown, thinking: true } => {
+                "Export Conversation as Markdown with Thinking"
+            }
+            Self::ExportConversation { format: TranscriptFormat::Jsonl, .. } => "Export Conversation as JSONL",
+            Self::ImportTranscript => "Import Transcript\u{2026}",
+            Self::ExportBlocks { format: NotebookFormat::Ipynb, .. } => "Export Selection as Notebook",
+            Self::ExportBlocks { format: NotebookFormat::Script, .. } => "Export Selection as Shell Script",
         }
     }
 }
@@ -118,6 +177,18 @@ mod tests {
         assert_eq!(ContextMenuItem::Rerun.label(), "Rerun");
     }
 
+    #[test]
+    fn test_context_menu_item_label_pipe_into() {
+        assert_eq!(ContextMenuItem::PipeInto.label(), "Pipe Into\u{2026}");
+        assert_eq!(ContextMenuItem::ShareBlock.label(), "Share Block\u{2026}");
+    }
+
+    #[test]
+    fn test_context_menu_item_label_hide_block() {
+        assert_eq!(ContextMenuItem::HideBlock { forget: false }.label(), "Hide Block");
+        assert_eq!(ContextMenuItem::HideBlock { forget: true }.label(), "Delete Block from History");
+    }
+
     #[test]
     fn test_context_menu_item_label_quick_look() {
         let item = ContextMenuItem::QuickLook(PathBuf::from("/test"));
@@ -141,4 +212,40 @@ mod tests {
         let item = ContextMenuItem::RevealInFinder(PathBuf::from("/test"));
         assert_eq!(item.label(), "Reveal in Finder");
     }
+
+    #[test]
+    fn test_context_menu_item_label_run_action() {
+        let item = ContextMenuItem::RunAction { label: "Run tests".into(), command: "npm test".into() };
+        assert_eq!(item.label(), "Run tests");
+    }
+
+    #[test]
+    fn test_context_menu_item_label_settings() {
+        assert_eq!(ContextMenuItem::Settings.label(), "Settings\u{2026}");
+    }
+
+    #[test]
+    fn test_context_menu_item_label_insights() {
+        assert_eq!(ContextMenuItem::Insights.label(), "Usage Insights\u{2026}");
+    }
+
+    #[test]
+    fn test_context_menu_item_label_export_conversation() {
+        let item = ContextMenuItem::ExportConversation { format: TranscriptFormat::Markdown, thinking: true };
+        assert_eq!(item.label(), "Export Conversation as Markdown with Thinking");
+        let item = ContextMenuItem::ExportConversation { format: TranscriptFormat::Jsonl, thinking: true };
+        assert_eq!(item.label(), "Export Conversation as JSONL");
+    }
+
+    #[test]
+    fn test_context_menu_item_label_export_blocks() {
+        let item = ContextMenuItem::ExportBlocks { format: NotebookFormat::Ipynb, blocks: vec![BlockId(1)] };
+        assert_eq!(item.label(), "Export Selection as Notebook");
+    }
+
+    #[test]
+    fn test_context_menu_item_label_worktree() {
+        assert_eq!(ContextMenuItem::FilterWorktree(Some("main".into())).label(), "Show Only This Worktree");
+        assert_eq!(ContextMenuItem::FilterWorktree(None).label(), "Show All Worktrees");
+    }
 }
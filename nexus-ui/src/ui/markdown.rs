@@ -1,12 +1,17 @@
 //! Markdown rendering for agent mode responses.
 //!
-//! Uses pulldown-cmark for proper CommonMark + GFM parsing.
+//! Uses pulldown-cmark for proper CommonMark + GFM parsing. Agent responses
+//! also turn citations (`[[block:ID]]`, `[[file:PATH:LINE]]`) into buttons.
 
+use nexus_api::BlockId;
 use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, HeadingLevel};
-use strata::layout::{Column, FlowContainer, Length, Padding, Row, TextElement};
+use strata::layout::{ButtonElement, Column, FlowContainer, Length, Padding, Row, TextElement};
 use strata::content_address::SourceId;
+use strata::primitives::Color;
 
+use crate::features::agent::citations::{self, Segment};
 use crate::ui::theme;
+use crate::utils::ids;
 
 /// Split text into words for flow layout.
 /// Each word includes any trailing whitespace so spacing is preserved.
@@ -36,12 +41,23 @@ fn split_into_words(text: &str) -> Vec<String> {
 
 /// Render markdown text to a strata Column layout.
 pub fn render(text: &str, source_id: SourceId) -> Column<'static> {
+    render_with(text, source_id, None)
+}
+
+/// Render an agent response, with its citations as buttons owned by
+/// `block_id`.
+pub fn render_cited(text: &str, source_id: SourceId, block_id: BlockId) -> Column<'static> {
+    render_with(text, source_id, Some(block_id))
+}
+
+fn render_with(text: &str, source_id: SourceId, citations: Option<BlockId>) -> Column<'static> {
     let options = Options::ENABLE_TABLES
         | Options::ENABLE_STRIKETHROUGH
         | Options::ENABLE_TASKLISTS;
 
     let parser = Parser::new_ext(text, options);
     let mut renderer = MarkdownRenderer::new(source_id);
+    renderer.citations = citations;
     renderer.render(parser);
     renderer.finish()
 }
@@ -75,6 +91,8 @@ struct MarkdownRenderer {
     inline_content: Vec<InlineSpan>,
     /// Whether we're currently in a list item
     in_list_item: bool,
+    /// Block whose citations become buttons (None: citations stay text)
+    citations: Option<BlockId>,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -117,6 +135,7 @@ impl MarkdownRenderer {
             heading_level: 0,
             inline_content: Vec::new(),
             in_list_item: false,
+            citations: None,
         }
     }
 
@@ -274,6 +293,16 @@ impl MarkdownRenderer {
             is_link: self.style_stack.contains(&Style::Link),
         };
 
+        // Brackets can arrive as text events of their own; rejoin them so
+        // a citation is seen whole.
+        if let Some(last) = self.inline_content.last_mut()
+            && !last.is_code
+            && (last.bold, last.italic, last.strikethrough, last.is_link)
+                == (span.bold, span.italic, span.strikethrough, span.is_link)
+        {
+            last.text.push_str(&span.text);
+            return;
+        }
         self.inline_content.push(span);
     }
 
@@ -424,36 +453,62 @@ impl MarkdownRenderer {
             .width(Length::Fill);
 
         for span in content {
-            // Split text at word boundaries for proper wrapping
-            // Keep whitespace attached to the preceding word for natural spacing
-            let words = split_into_words(&span.text);
+            let segments = match self.citations {
+                Some(_) if !span.is_code => citations::split(&span.text),
+                _ => vec![Segment::Text(&span.text)],
+            };
+            for segment in segments {
+                let text = match segment {
+                    Segment::Text(text) => text,
+                    Segment::Cite(citation) => {
+                        if let Some(block_id) = self.citations {
+                            flow = flow.push(
+                                ButtonElement::new(ids::agent_citation(block_id, citation.key()), &citation.label())
+                                    .background(Color::rgba(1.0, 1.0, 1.0, 0.06))
+                                    .text_color(theme::TEXT_PATH)
+                                    .corner_radius(3.0),
+                            );
+                        }
+                        continue;
+                    }
+                };
+                flow = self.push_words(flow, &span, text);
+            }
+        }
+        flow
+    }
 
-            for word in words {
-                if word.is_empty() {
-                    continue;
-                }
+    /// Push `text`, styled as `span`, one word at a time.
+    fn push_words(&self, mut flow: FlowContainer<'static>, span: &InlineSpan, text: &str) -> FlowContainer<'static> {
+        // Split text at word boundaries for proper wrapping
+        // Keep whitespace attached to the preceding word for natural spacing
+        let words = split_into_words(text);
 
-                let mut elem = TextElement::new(&word).source(self.source_id);
+        for word in words {
+            if word.is_empty() {
+                continue;
+            }
 
-                if span.is_code {
-                    elem = elem.color(theme::TOOL_ACTION);
-                } else if span.is_link {
-                    elem = elem.color(theme::TEXT_PATH);
-                } else if span.strikethrough {
-                    elem = elem.color(theme::TEXT_MUTED);
-                } else {
-                    elem = elem.color(theme::TEXT_PRIMARY);
-                }
+            let mut elem = TextElement::new(&word).source(self.source_id);
 
-                if span.bold {
-                    elem = elem.bold();
-                }
-                if span.italic {
-                    elem = elem.italic();
-                }
+            if span.is_code {
+                elem = elem.color(theme::TOOL_ACTION);
+            } else if span.is_link {
+                elem = elem.color(theme::TEXT_PATH);
+            } else if span.strikethrough {
+                elem = elem.color(theme::TEXT_MUTED);
+            } else {
+                elem = elem.color(theme::TEXT_PRIMARY);
+            }
 
-                flow = flow.push(elem);
+            if span.bold {
+                elem = elem.bold();
             }
+            if span.italic {
+                elem = elem.italic();
+            }
+
+            flow = flow.push(elem);
         }
         flow
     }
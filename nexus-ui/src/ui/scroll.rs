@@ -30,6 +30,8 @@ pub(crate) struct ScrollModel {
     /// Deferred offset computed in view() for Block(id) targets.
     /// Applied at the start of the next update() frame.
     pub(crate) pending_offset: Cell<Option<f32>>,
+    /// Position saved while a full-window block covers the history.
+    saved: Option<(f32, ScrollTarget)>,
 }
 
 impl ScrollModel {
@@ -38,6 +40,7 @@ impl ScrollModel {
             state: ScrollState::new(),
             target: ScrollTarget::Bottom,
             pending_offset: Cell::new(None),
+            saved: None,
         }
     }
 
@@ -72,6 +75,24 @@ impl ScrollModel {
         self.state.reset_overscroll();
     }
 
+    /// Remember the current position, to go back to with `restore_position`.
+    pub fn save_position(&mut self) {
+        self.saved = Some((self.state.offset, self.target));
+    }
+
+    /// Go back to the position from `save_position`. A viewport that was
+    /// tailing keeps tailing; without a saved position, snap to bottom.
+    pub fn restore_position(&mut self) {
+        match self.saved.take() {
+            Some((offset, ScrollTarget::None)) => {
+                self.state.offset = offset;
+                self.target = ScrollTarget::None;
+            }
+            _ => self.snap_to_bottom(),
+        }
+        self.state.reset_overscroll();
+    }
+
     /// Apply a user scroll action (wheel, scrollbar drag, etc.).
     /// If locked to Bottom, sync offset to the real max first (since view()
     /// uses f32::MAX) and break the lock so the delta applies correctly.
@@ -187,6 +208,27 @@ mod tests {
         assert_eq!(model.target, ScrollTarget::Block(block_id));
     }
 
+    #[test]
+    fn test_restore_position() {
+        let mut model = ScrollModel::new();
+        model.state.offset = 320.0;
+        model.target = ScrollTarget::None;
+        model.save_position();
+        model.snap_to_bottom();
+        model.state.offset = 900.0;
+        model.restore_position();
+        assert_eq!((model.state.offset, model.target), (320.0, ScrollTarget::None));
+
+        // Tailing before, tailing after; a second restore has nothing saved.
+        model.snap_to_bottom();
+        model.save_position();
+        model.restore_position();
+        assert_eq!(model.target, ScrollTarget::Bottom);
+        model.target = ScrollTarget::None;
+        model.restore_position();
+        assert_eq!(model.target, ScrollTarget::Bottom);
+    }
+
     #[test]
     fn test_reset() {
         let mut model = ScrollModel::new();
@@ -1,7 +1,25 @@
 //! Color palette for the Nexus UI theme.
+//!
+//! Status colors can be paired with symbols for users who can't tell them
+//! apart. Whether they are is a process-wide switch, [`set_symbols`], set
+//! from the `[theme] symbols` config.
+
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use strata::primitives::Color;
 
+static SYMBOLS: AtomicBool = AtomicBool::new(false);
+
+/// Pair status colors with symbols (`true`) or rely on color alone.
+pub fn set_symbols(on: bool) {
+    SYMBOLS.store(on, Ordering::Relaxed);
+}
+
+/// Whether status colors are paired with symbols.
+pub fn symbols() -> bool {
+    SYMBOLS.load(Ordering::Relaxed)
+}
+
 // Backgrounds (darker charcoal matching Claude Code)
 pub const BG_APP: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
 pub const BG_BLOCK: Color = Color { r: 0.09, g: 0.09, b: 0.1, a: 0.6 };
@@ -19,7 +37,12 @@ pub const TEXT_PRIMARY: Color = Color { r: 0.85, g: 0.85, b: 0.85, a: 1.0 };
 pub const TEXT_SECONDARY: Color = Color { r: 0.55, g: 0.55, b: 0.55, a: 1.0 };
 pub const TEXT_MUTED: Color = Color { r: 0.4, g: 0.4, b: 0.42, a: 1.0 };
 pub const TEXT_PATH: Color = Color { r: 0.4, g: 0.6, b: 0.9, a: 1.0 };
+/// Focus ring around the selected block.
+pub const FOCUS_RING: Color = Color { r: 0.3, g: 0.7, b: 1.0, a: 1.0 };
 pub const TEXT_PURPLE: Color = Color { r: 0.6, g: 0.5, b: 0.85, a: 1.0 };
+/// Agent-suggested rest of a command: a dimmed agent purple, so it isn't
+/// taken for muted text.
+pub const GHOST_AGENT: Color = Color { r: 0.42, g: 0.38, b: 0.58, a: 1.0 };
 
 // Tool colors (cyan accent matching Claude Code)
 pub const TOOL_PENDING: Color = Color { r: 0.7, g: 0.65, b: 0.3, a: 1.0 };
@@ -104,3 +127,75 @@ pub fn tinted_bg(hue: f32) -> Color {
         a: 1.0,
     }
 }
+
+/// Text color for a provider-contributed label.
+pub fn tone(tone: crate::data::providers::Tone) -> Color {
+    use crate::data::providers::Tone;
+    match tone {
+        Tone::Info => TEXT_SECONDARY,
+        Tone::Success => SUCCESS,
+        Tone::Warning => WARNING,
+        Tone::Error => ERROR,
+    }
+}
+
+/// Underline and label color for a lint finding.
+pub fn lint_severity(severity: nexus_kernel::config::LintSeverity) -> Color {
+    use nexus_kernel::config::LintSeverity;
+    match severity {
+        LintSeverity::Off | LintSeverity::Info => TOOL_ACTION,
+        LintSeverity::Warning => WARNING,
+        LintSeverity::Error => ERROR,
+    }
+}
+
+/// Parse a `#rrggbb` color from configuration.
+pub fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// The symbol and color for a terminal row whose colored text is mostly
+/// ANSI red (`✗`), green (`✓`) or yellow (`!`), for the symbol gutter.
+pub fn row_signal(row: &[nexus_term::Cell]) -> Option<(&'static str, Color)> {
+    let mut counts = [0usize; 3];
+    for cell in row.iter().filter(|cell| !cell.c.is_whitespace() && cell.c != '\0') {
+        let (nexus_term::Color::Named(n) | nexus_term::Color::Indexed(n)) = cell.fg else {
+            continue;
+        };
+        match n {
+            1 | 9 => counts[0] += 1,
+            2 | 10 => counts[1] += 1,
+            3 | 11 => counts[2] += 1,
+            _ => {}
+        }
+    }
+    let [red, green, yellow] = counts;
+    if red > 0 && red >= green.max(yellow) {
+        Some(("\u{2717}", ERROR))
+    } else if yellow > 0 && yellow >= green {
+        Some(("!", WARNING))
+    } else if green > 0 {
+        Some(("\u{2713}", SUCCESS))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_signal() {
+        let mut parser = nexus_term::TerminalParser::new(40, 4);
+        parser.feed(b"\x1b[32mok\x1b[0m \x1b[31mFAILED\x1b[0m\r\n\x1b[33mwarning\x1b[0m: x\r\nplain\r\n\x1b[92mpassed\x1b[0m");
+        let grid = parser.grid();
+        let signals: Vec<_> = grid.rows_iter().map(|row| row_signal(row).map(|(symbol, _)| symbol)).collect();
+        assert_eq!(signals, [Some("\u{2717}"), Some("!"), None, Some("\u{2713}")]);
+    }
+}
@@ -3,10 +3,12 @@
 //! Contains:
 //! - Query display with badge styling
 //! - Collapsible thinking section
+//! - Collapsible plan checklist, copyable as Markdown
 //! - Tool invocations (delegated to ToolWidget)
 //! - Permission and question dialogs
+//! - Setup panel when the Claude CLI is missing or outdated
 //! - Response with markdown rendering
-//! - Status footer with duration/cost/tokens
+//! - Status footer with retries/duration/cost/tokens
 
 use nexus_api::BlockId;
 use strata::content_address::SourceId;
@@ -16,7 +18,7 @@ use strata::layout::{
 };
 use strata::primitives::Color;
 
-use crate::data::agent_block::{AgentBlock, AgentBlockState, PermissionRequest, PendingUserQuestion};
+use crate::data::agent_block::{AgentBlock, AgentBlockState, AgentSetup, PermissionRequest, PendingUserQuestion, PlanStepStatus};
 use crate::ui::theme;
 use crate::utils::ids;
 use crate::ui::widgets::{ToolWidget, ToolMessage};
@@ -38,6 +40,10 @@ use crate::ui::widgets::{ToolWidget, ToolMessage};
 pub enum AgentBlockMessage {
     /// Toggle the thinking section collapse state.
     ToggleThinking,
+    /// Toggle the plan panel collapse state.
+    TogglePlan,
+    /// Copy the plan as a Markdown task list.
+    CopyPlan,
     /// Stop the running agent.
     Stop,
     /// Permission response: deny.
@@ -52,6 +58,12 @@ pub enum AgentBlockMessage {
     QuestionOption { question_idx: usize, option_idx: usize },
     /// Question free-form submit.
     QuestionSubmit,
+    /// Copy the command that installs the CLI.
+    CopySetupCommand,
+    /// Check the CLI again.
+    CheckSetup,
+    /// Send the query again, once the CLI is ready.
+    Rerun,
 }
 
 // =========================================================================
@@ -117,11 +129,16 @@ impl<'a> AgentBlockWidget<'a> {
             }
         }
 
+        // Plan panel
+        if !block.plan.is_empty() {
+            content = content.push(build_plan_panel(block));
+        }
+
         // Tool invocations
         for (i, tool) in block.tools.iter().enumerate() {
             let toggle_id = ids::agent_tool_toggle(block_id, i);
             let tool_source = ids::agent_tool(block_id, i);
-            content = content.push(ToolWidget::view(tool, toggle_id, tool_source));
+            content = content.push(ToolWidget::view(tool, toggle_id, tool_source, ids::agent_tool_term(block_id, i)));
         }
 
         // Permission dialog
@@ -142,6 +159,11 @@ impl<'a> AgentBlockWidget<'a> {
             content = content.push(build_question_dialog(question, block_id, question_input, q_source));
         }
 
+        // CLI setup panel
+        if let Some(ref setup) = block.setup {
+            content = content.push(build_setup_panel(setup, block_id));
+        }
+
         // Response text (Claude Code style: bullet prefix)
         if !block.response.is_empty() {
             let response_source = ids::agent_response(block_id);
@@ -150,7 +172,7 @@ impl<'a> AgentBlockWidget<'a> {
                     .spacing(6.0)
                     .cross_align(CrossAxisAlignment::Start)
                     .push(TextElement::new("\u{25CF}").color(theme::TEXT_MUTED)) // ●
-                    .push(crate::ui::markdown::render(&block.response, response_source)),
+                    .push(crate::ui::markdown::render_cited(&block.response, response_source, block_id)),
             );
         }
 
@@ -171,6 +193,14 @@ impl<'a> AgentBlockWidget<'a> {
             return Some(AgentBlockMessage::ToggleThinking);
         }
 
+        // Plan panel
+        if click_id == ids::agent_plan_toggle(block_id) {
+            return Some(AgentBlockMessage::TogglePlan);
+        }
+        if click_id == ids::agent_plan_copy(block_id) {
+            return Some(AgentBlockMessage::CopyPlan);
+        }
+
         // Stop button
         if click_id == ids::agent_stop(block_id) {
             return Some(AgentBlockMessage::Stop);
@@ -187,6 +217,17 @@ impl<'a> AgentBlockWidget<'a> {
             return Some(AgentBlockMessage::PermissionAlways);
         }
 
+        // Setup panel buttons
+        if click_id == ids::agent_setup_copy(block_id) {
+            return Some(AgentBlockMessage::CopySetupCommand);
+        }
+        if click_id == ids::agent_setup_check(block_id) {
+            return Some(AgentBlockMessage::CheckSetup);
+        }
+        if click_id == ids::agent_setup_rerun(block_id) {
+            return Some(AgentBlockMessage::Rerun);
+        }
+
         // Question submit
         if click_id == ids::agent_question_submit(block_id) {
             return Some(AgentBlockMessage::QuestionSubmit);
@@ -281,6 +322,58 @@ fn build_permission_dialog(
     dialog
 }
 
+/// Build the panel shown when the Claude CLI couldn't run the query:
+/// what's wrong, the command that fixes it, and a button to check again.
+/// Once a check finds the CLI ready, it offers to send the query again.
+fn build_setup_panel(setup: &AgentSetup, block_id: BlockId) -> Column<'static> {
+    let source_id = ids::agent_setup(block_id);
+    let ready = setup.status.is_ready();
+    let (icon, color) = if ready { ("\u{2713}", theme::SUCCESS) } else { ("\u{26A0}", theme::WARNING) };
+
+    let mut panel = Column::new()
+        .padding(8.0)
+        .spacing(4.0)
+        .background(Color::rgba(1.0, 1.0, 1.0, 0.04))
+        .corner_radius(8.0)
+        .border(color, 1.0)
+        .width(Length::Fill)
+        .push(TextElement::new(format!("{} {}", icon, setup.status.title())).color(color).source(source_id))
+        .push(TextElement::new(setup.status.detail()).color(theme::TEXT_SECONDARY).source(source_id));
+
+    if let Some(fix) = setup.status.fix() {
+        panel = panel.push(
+            Column::new()
+                .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                .background(theme::CODE_BG)
+                .corner_radius(4.0)
+                .push(TextElement::new(format!("$ {}", fix)).color(theme::CODE_TEXT).source(source_id)),
+        );
+    }
+
+    let mut buttons = Row::new().spacing(8.0);
+    if ready {
+        buttons = buttons.push(
+            ButtonElement::new(ids::agent_setup_rerun(block_id), "Run Again")
+                .background(theme::BTN_ALLOW)
+                .corner_radius(4.0),
+        );
+    } else {
+        buttons = buttons.push(
+            ButtonElement::new(ids::agent_setup_copy(block_id), "Copy Command")
+                .background(Color::rgb(0.12, 0.25, 0.45))
+                .corner_radius(4.0),
+        );
+    }
+    let check_label = if setup.checking { "Checking..." } else { "Check Again" };
+    buttons = buttons.push(
+        ButtonElement::new(ids::agent_setup_check(block_id), check_label)
+            .background(Color::rgba(1.0, 1.0, 1.0, 0.08))
+            .corner_radius(4.0),
+    );
+
+    panel.push(buttons)
+}
+
 /// Build a question dialog for AskUserQuestion (via MCP permission).
 fn build_question_dialog(
     question: &PendingUserQuestion,
@@ -346,7 +439,61 @@ fn build_question_dialog(
     dialog
 }
 
-/// Build the status footer with stop button, status text, duration, cost, tokens.
+/// Build the status footer with stop button, status text, retries, duration, cost, tokens.
+/// Build the plan panel: a header with progress and a copy button, then
+/// one checkbox row per step unless collapsed.
+fn build_plan_panel(block: &AgentBlock) -> Column<'static> {
+    let plan_source = ids::agent_plan(block.id);
+    let collapse_icon = if block.plan_collapsed { "\u{25B6}" } else { "\u{25BC}" };
+    let header = Row::new()
+        .spacing(8.0)
+        .cross_align(CrossAxisAlignment::Center)
+        .push(
+            ButtonElement::new(
+                ids::agent_plan_toggle(block.id),
+                &format!("{} Plan {}/{}", collapse_icon, block.plan_completed(), block.plan.len()),
+            )
+            .background(Color::TRANSPARENT)
+            .text_color(theme::TEXT_SECONDARY)
+            .corner_radius(2.0),
+        )
+        .spacer(1.0)
+        .push(
+            ButtonElement::new(ids::agent_plan_copy(block.id), "Copy as Markdown")
+                .background(Color::rgba(1.0, 1.0, 1.0, 0.06))
+                .text_color(theme::TEXT_MUTED)
+                .corner_radius(4.0),
+        );
+
+    let mut panel = Column::new()
+        .padding(6.0)
+        .spacing(2.0)
+        .background(theme::TOOL_ARTIFACT_BG)
+        .corner_radius(4.0)
+        .border(theme::TOOL_BORDER, 1.0)
+        .width(Length::Fill)
+        .push(header);
+
+    if !block.plan_collapsed {
+        for step in &block.plan {
+            let (box_color, text_color) = match step.status {
+                PlanStepStatus::Pending => (theme::TEXT_MUTED, theme::TEXT_SECONDARY),
+                PlanStepStatus::InProgress => (theme::RUNNING, theme::TEXT_PRIMARY),
+                PlanStepStatus::Completed => (theme::SUCCESS, theme::TEXT_MUTED),
+            };
+            panel = panel.push(
+                Row::new()
+                    .fixed_spacer(8.0)
+                    .spacing(6.0)
+                    .push(TextElement::new(step.status.checkbox()).color(box_color).source(plan_source))
+                    .push(TextElement::new(&step.content).color(text_color).source(plan_source)),
+            );
+        }
+    }
+
+    panel
+}
+
 fn build_footer(block: &AgentBlock, stop_id: SourceId) -> Row<'static> {
     let (status_text, status_color) = match &block.state {
         AgentBlockState::Pending => ("Waiting...", theme::TEXT_MUTED),
@@ -376,6 +523,11 @@ fn build_footer(block: &AgentBlock, stop_id: SourceId) -> Row<'static> {
 
     footer = footer.push(TextElement::new(status_text).color(status_color).source(footer_source));
 
+    if let Some(retry) = block.retry_status() {
+        let color = if is_running { theme::WARNING } else { theme::TEXT_MUTED };
+        footer = footer.push(TextElement::new(&retry).color(color).source(footer_source));
+    }
+
     if let Some(ms) = block.duration_ms {
         let duration = if ms < 1000 {
             format!("{}ms", ms)
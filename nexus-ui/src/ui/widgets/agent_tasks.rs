@@ -0,0 +1,124 @@
+//! Background agent task panel — above the input while there are tasks:
+//! a header counting them, and when open, a row per task with its status
+//! and a cancel button, and the answer of finished ones on demand.
+
+use strata::layout::{ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget};
+use strata::primitives::Color;
+
+use crate::features::agent::tasks::{TaskQueue, TaskStatus};
+use crate::ui::{markdown, theme};
+use crate::utils::ids;
+
+/// Prompt characters shown on a task's row.
+const PROMPT_CHARS: usize = 80;
+
+pub struct AgentTaskPanel<'a> {
+    pub queue: &'a TaskQueue,
+}
+
+impl<'a> Widget<'a> for AgentTaskPanel<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let count = |done: fn(&TaskStatus) -> bool| self.queue.tasks.iter().filter(|task| done(&task.status)).count();
+        let running = count(|status| *status == TaskStatus::Running);
+        let waiting = count(|status| matches!(status, TaskStatus::Waiting(_) | TaskStatus::Pending));
+        let done = count(TaskStatus::is_done);
+        let mut summary = Vec::new();
+        for (n, what) in [(running, "running"), (waiting, "waiting"), (done, "done")] {
+            if n > 0 {
+                summary.push(format!("{} {}", n, what));
+            }
+        }
+
+        let arrow = if self.queue.open { "\u{25BC}" } else { "\u{25B6}" };
+        let mut header = Row::new()
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .width(Length::Fill)
+            .push(
+                ButtonElement::new(ids::agent_tasks_toggle(), &format!("{} Background tasks", arrow))
+                    .background(Color::TRANSPARENT)
+                    .text_color(theme::TEXT_SECONDARY)
+                    .corner_radius(2.0),
+            )
+            .push(TextElement::new(summary.join(" \u{00B7} ")).color(theme::TEXT_MUTED))
+            .spacer(1.0);
+        if done > 0 {
+            header = header.push(
+                ButtonElement::new(ids::agent_tasks_clear(), "Clear finished")
+                    .background(Color::TRANSPARENT)
+                    .text_color(theme::TEXT_MUTED)
+                    .corner_radius(2.0),
+            );
+        }
+
+        let mut col = Column::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(4.0)
+            .background(theme::CARD_BG)
+            .border(theme::CARD_BORDER, 1.0)
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(header);
+        if !self.queue.open {
+            return col.into();
+        }
+
+        for task in &self.queue.tasks {
+            let (glyph, color) = match task.status {
+                TaskStatus::Waiting(_) | TaskStatus::Pending => ("\u{25CB}", theme::TEXT_MUTED),
+                TaskStatus::Running => ("\u{25D0}", theme::RUNNING),
+                TaskStatus::Finished => ("\u{2713}", theme::SUCCESS),
+                TaskStatus::Failed(_) => ("\u{2717}", theme::ERROR),
+                TaskStatus::Cancelled => ("\u{2013}", theme::TEXT_MUTED),
+            };
+            let mut prompt: String = task.prompt.chars().take(PROMPT_CHARS).collect();
+            if task.prompt.chars().count() > PROMPT_CHARS {
+                prompt.push('\u{2026}');
+            }
+            let mut row = Row::new()
+                .spacing(6.0)
+                .cross_align(CrossAxisAlignment::Center)
+                .width(Length::Fill)
+                .push(TextElement::new(glyph).color(color))
+                .push(TextElement::new(prompt).color(theme::TEXT_PRIMARY))
+                .push(TextElement::new(task.status.label()).color(theme::TEXT_MUTED));
+            if let Some(cost) = task.cost_usd {
+                row = row.push(TextElement::new(format!("${:.4}", cost)).color(theme::TEXT_MUTED));
+            }
+            row = row.spacer(1.0);
+            if task.status == TaskStatus::Finished {
+                let label = if task.expanded { "Hide" } else { "Show" };
+                row = row.push(
+                    ButtonElement::new(ids::agent_task_toggle(task.id), label)
+                        .background(Color::TRANSPARENT)
+                        .text_color(theme::TEXT_SECONDARY)
+                        .corner_radius(2.0),
+                );
+            }
+            if !task.status.is_done() {
+                row = row.push(
+                    ButtonElement::new(ids::agent_task_cancel(task.id), "\u{2715}")
+                        .background(Color::TRANSPARENT)
+                        .text_color(theme::TEXT_MUTED)
+                        .corner_radius(2.0),
+                );
+            }
+            col = col.push(row);
+
+            if let TaskStatus::Failed(error) = &task.status {
+                col = col.push(TextElement::new(format!("  {}", error)).color(theme::ERROR));
+            }
+            if task.expanded && task.status == TaskStatus::Finished {
+                col = col.push(
+                    Column::new()
+                        .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                        .background(theme::TOOL_ARTIFACT_BG)
+                        .corner_radius(4.0)
+                        .width(Length::Fill)
+                        .push(markdown::render(&task.output, ids::agent_task_output(task.id))),
+                );
+            }
+        }
+        col.into()
+    }
+}
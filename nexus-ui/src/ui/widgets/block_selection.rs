@@ -0,0 +1,89 @@
+//! Block selection bar — above the input while blocks are shift-click
+//! selected: how many, and the actions that apply to all of them — and the
+//! header drawn above a group of blocks made from a selection.
+
+use nexus_api::BlockId;
+use strata::content_address::SourceId;
+use strata::layout::{ButtonElement, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget};
+use strata::primitives::Color;
+
+use crate::ui::theme;
+use crate::utils::ids;
+
+pub struct BlockSelectionBar {
+    pub count: usize,
+    pub accent: Color,
+}
+
+impl<'a> Widget<'a> for BlockSelectionBar {
+    fn build(self) -> LayoutChild<'a> {
+        let plural = if self.count == 1 { "" } else { "s" };
+        let button = |id: SourceId, label: &str, color: Color| {
+            ButtonElement::new(id, label).background(Color::TRANSPARENT).text_color(color).corner_radius(2.0)
+        };
+
+        let mut row = Row::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .background(theme::CARD_BG)
+            .border(self.accent, 1.0)
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(TextElement::new(format!("{} block{} selected", self.count, plural)).color(theme::TEXT_PRIMARY))
+            .spacer(1.0)
+            .push(button(ids::bulk_copy(), "Copy", theme::TEXT_SECONDARY))
+            .push(button(ids::bulk_notebook(), "Notebook", theme::TEXT_SECONDARY))
+            .push(button(ids::bulk_script(), "Script", theme::TEXT_SECONDARY))
+            .push(button(ids::bulk_send_to_agent(), "Send to Agent", theme::TEXT_SECONDARY));
+        if self.count > 1 {
+            row = row.push(button(ids::bulk_group(), "Group", theme::TEXT_SECONDARY));
+        }
+        row.push(button(ids::bulk_remove(), "Remove", theme::ERROR))
+            .push(button(ids::bulk_clear(), "\u{2715}", theme::TEXT_MUTED))
+            .into()
+    }
+}
+
+/// Blocks named in a group header before the rest are elided.
+const GROUP_TITLES: usize = 3;
+
+/// Header above a block group: expands or collapses it, or dissolves it.
+pub struct BlockGroupHeader<'a> {
+    pub first: BlockId,
+    /// Each block's title, or its command until it has one.
+    pub titles: Vec<&'a str>,
+    pub collapsed: bool,
+}
+
+impl<'a> Widget<'a> for BlockGroupHeader<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let arrow = if self.collapsed { "\u{25B6}" } else { "\u{25BC}" };
+        let mut names: Vec<&str> = self.titles.iter().take(GROUP_TITLES).map(|c| c.lines().next().unwrap_or(c)).collect();
+        if self.titles.len() > GROUP_TITLES {
+            names.push("\u{2026}");
+        }
+        let label = format!("{} {} blocks: {}", arrow, self.titles.len(), names.join(" \u{00B7} "));
+        Row::new()
+            .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .background(theme::BG_BLOCK)
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(
+                ButtonElement::new(ids::group_toggle(self.first), &label)
+                    .background(Color::TRANSPARENT)
+                    .text_color(theme::TEXT_SECONDARY)
+                    .corner_radius(2.0),
+            )
+            .spacer(1.0)
+            .push(
+                ButtonElement::new(ids::group_ungroup(self.first), "Ungroup")
+                    .background(Color::TRANSPARENT)
+                    .text_color(theme::TEXT_MUTED)
+                    .corner_radius(2.0),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,121 @@
+//! Context file chips — the files the agent has read or modified, above
+//! the input in agent mode, each with open / diff / remove buttons. A
+//! leading chip names the project instructions in effect, if any, and
+//! opens them for editing.
+
+use strata::layout::{ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget};
+use strata::primitives::Color;
+
+use crate::data::context_files::ContextFile;
+use nexus_kernel::instructions::Instructions;
+use crate::ui::theme;
+use crate::utils::ids;
+
+/// Diff lines shown before the rest is cut off.
+const MAX_DIFF_LINES: usize = 80;
+
+pub struct ContextFileChips<'a> {
+    pub files: &'a [ContextFile],
+    /// The file whose diff is open, and the diff.
+    pub diff: Option<(usize, &'a str)>,
+    /// Project instructions the agent is given.
+    pub instructions: Option<&'a Instructions>,
+}
+
+impl<'a> Widget<'a> for ContextFileChips<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let mut chips = Row::new().spacing(6.0).cross_align(CrossAxisAlignment::Center);
+        if let Some(instructions) = self.instructions {
+            let name = instructions.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            chips = chips.push(
+                Row::new()
+                    .padding_custom(Padding::new(2.0, 4.0, 2.0, 6.0))
+                    .spacing(4.0)
+                    .cross_align(CrossAxisAlignment::Center)
+                    .background(theme::CARD_BG)
+                    .border(theme::CARD_BORDER, 1.0)
+                    .corner_radius(4.0)
+                    .push(TextElement::new("\u{2713} instructions").color(theme::SUCCESS))
+                    .push(
+                        ButtonElement::new(ids::project_instructions(), &name)
+                            .background(Color::TRANSPARENT)
+                            .text_color(theme::TOOL_PATH)
+                            .corner_radius(2.0),
+                    ),
+            );
+        }
+        for (i, file) in self.files.iter().enumerate() {
+            let (marker, marker_color) = if file.modified { ("M", theme::WARNING) } else { ("R", theme::TEXT_MUTED) };
+            let mut chip = Row::new()
+                .padding_custom(Padding::new(2.0, 4.0, 2.0, 6.0))
+                .spacing(4.0)
+                .cross_align(CrossAxisAlignment::Center)
+                .background(theme::CARD_BG)
+                .border(theme::CARD_BORDER, 1.0)
+                .corner_radius(4.0)
+                .push(TextElement::new(marker).color(marker_color))
+                .push(
+                    ButtonElement::new(ids::context_file_open(i), &file.label())
+                        .background(Color::TRANSPARENT)
+                        .text_color(theme::TOOL_PATH)
+                        .corner_radius(2.0),
+                );
+            if file.modified {
+                let open = self.diff.is_some_and(|(shown, _)| shown == i);
+                chip = chip.push(
+                    ButtonElement::new(ids::context_file_diff(i), "diff")
+                        .background(if open { theme::BORDER_INPUT } else { Color::TRANSPARENT })
+                        .text_color(theme::TEXT_SECONDARY)
+                        .corner_radius(2.0),
+                );
+            }
+            chip = chip.push(
+                ButtonElement::new(ids::context_file_remove(i), "\u{2715}")
+                    .background(Color::TRANSPARENT)
+                    .text_color(theme::TEXT_MUTED)
+                    .corner_radius(2.0),
+            );
+            chips = chips.push(chip);
+        }
+
+        let mut col = Column::new()
+            .padding_custom(Padding::new(2.0, 4.0, 2.0, 4.0))
+            .spacing(4.0)
+            .width(Length::Fill)
+            .push(chips);
+
+        if let Some((_, diff)) = self.diff {
+            let mut diff_col = Column::new()
+                .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                .background(theme::TOOL_ARTIFACT_BG)
+                .corner_radius(4.0)
+                .width(Length::Fill);
+            if diff.is_empty() {
+                diff_col = diff_col.push(TextElement::new("no changes since the agent first read it").color(theme::TEXT_MUTED));
+            }
+            for line in diff.lines().take(MAX_DIFF_LINES) {
+                let (color, bg) = if line.starts_with("+++") || line.starts_with("---") || line.starts_with("@@") {
+                    (theme::TEXT_MUTED, None)
+                } else if line.starts_with('+') {
+                    (theme::DIFF_ADD, Some(theme::DIFF_BG_ADD))
+                } else if line.starts_with('-') {
+                    (theme::DIFF_REMOVE, Some(theme::DIFF_BG_REMOVE))
+                } else {
+                    (theme::TEXT_MUTED, None)
+                };
+                let mut row = Row::new().width(Length::Fill);
+                if let Some(bg) = bg {
+                    row = row.background(bg);
+                }
+                diff_col = diff_col.push(row.push(TextElement::new(line).color(color)));
+            }
+            let hidden = diff.lines().count().saturating_sub(MAX_DIFF_LINES);
+            if hidden > 0 {
+                diff_col = diff_col.push(TextElement::new(format!("\u{2026} {} more lines", hidden)).color(theme::TEXT_MUTED));
+            }
+            col = col.push(diff_col);
+        }
+
+        col.into()
+    }
+}
@@ -0,0 +1,85 @@
+//! Crash prompt widget — offers the report from a previous crash.
+
+use nexus_kernel::crash::CrashSummary;
+use strata::content_address::SourceId;
+use strata::layout::{
+    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget,
+};
+use strata::primitives::Color;
+
+use crate::ui::theme;
+use crate::utils::ids;
+
+pub struct CrashPromptPanel<'a> {
+    pub crashes: &'a [CrashSummary],
+    /// Full text of the newest report.
+    pub report: &'a str,
+    pub reviewing: bool,
+    /// Whether `[crash] upload_url` is set.
+    pub can_submit: bool,
+    pub submitting: bool,
+    pub error: Option<&'a str>,
+}
+
+impl<'a> Widget<'a> for CrashPromptPanel<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let title = if self.crashes.len() > 1 {
+            format!("Nexus crashed {} times since you last looked", self.crashes.len())
+        } else {
+            "Nexus quit unexpectedly".to_string()
+        };
+        let mut header = Row::new()
+            .spacing(8.0)
+            .width(Length::Fill)
+            .cross_align(CrossAxisAlignment::Center)
+            .push(TextElement::new(title).color(theme::WELCOME_TITLE).size(16.0))
+            .spacer(1.0)
+            .push(button(ids::crash_review(), if self.reviewing { "Hide report" } else { "Review report" }, theme::CARD_BG))
+            .push(button(ids::crash_copy(), "Copy", theme::CARD_BG));
+        if self.submitting {
+            header = header.push(TextElement::new("Sending\u{2026}").color(theme::TEXT_SECONDARY));
+        } else if self.can_submit {
+            header = header.push(button(ids::crash_submit(), "Send", theme::BTN_ALLOW));
+        }
+        header = header.push(button(ids::crash_dismiss(), "Don't send", theme::CARD_BG));
+
+        let mut panel = Column::new().padding(12.0).spacing(12.0).width(Length::Fill).push(header);
+        if let Some(newest) = self.crashes.first() {
+            let mut line = newest.message.clone();
+            if let Some(location) = &newest.location {
+                line = format!("{} ({})", line, location);
+            }
+            panel = panel.push(TextElement::new(line).color(theme::TEXT_PRIMARY));
+        }
+        let note = if self.can_submit {
+            "The report has a backtrace, recent log lines and basic system details, with your home directory and user name removed. It is only sent if you choose Send."
+        } else {
+            "The report has a backtrace, recent log lines and basic system details, with your home directory and user name removed. Copy it into an issue to share it; set [crash] upload_url to send reports directly."
+        };
+        panel = panel.push(TextElement::new(note).color(theme::TEXT_SECONDARY));
+        if let Some(error) = self.error {
+            panel = panel.push(TextElement::new(format!("Sending failed: {}", error)).color(theme::ERROR));
+        }
+
+        if self.reviewing {
+            let mut report = Column::new()
+                .padding(8.0)
+                .background(theme::CARD_BG)
+                .corner_radius(4.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .width(Length::Fill);
+            for line in self.report.lines() {
+                report = report.push(TextElement::new(line.to_string()).color(theme::TEXT_SECONDARY));
+            }
+            panel = panel.push(report);
+        }
+        panel.into()
+    }
+}
+
+fn button(id: SourceId, label: &str, background: Color) -> ButtonElement {
+    ButtonElement::new(id, label)
+        .background(background)
+        .corner_radius(4.0)
+        .padding(Padding::new(2.0, 8.0, 2.0, 8.0))
+}
@@ -0,0 +1,47 @@
+//! Undo toast — above the input for a few seconds after a block is hidden,
+//! naming it, with a button to bring it back.
+
+use strata::layout::{ButtonElement, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget};
+use strata::primitives::Color;
+
+use crate::ui::theme;
+use crate::utils::ids;
+
+/// Command characters shown in the toast.
+const COMMAND_CHARS: usize = 60;
+
+pub struct HiddenBlockToast<'a> {
+    pub command: &'a str,
+    /// Its stored copy goes too once the toast closes.
+    pub forgotten: bool,
+}
+
+impl<'a> Widget<'a> for HiddenBlockToast<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let first_line = self.command.lines().next().unwrap_or("");
+        let mut command: String = first_line.chars().take(COMMAND_CHARS).collect();
+        if first_line.chars().count() > COMMAND_CHARS || self.command.contains('\n') {
+            command.push('\u{2026}');
+        }
+        let what = if self.forgotten { "Deleted" } else { "Hid" };
+
+        Row::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .background(theme::CARD_BG)
+            .border(theme::CARD_BORDER, 1.0)
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(TextElement::new(format!("{} block", what)).color(theme::TEXT_SECONDARY))
+            .push(TextElement::new(command).color(theme::TEXT_PRIMARY))
+            .spacer(1.0)
+            .push(
+                ButtonElement::new(ids::hide_undo(), "Undo")
+                    .background(Color::TRANSPARENT)
+                    .text_color(theme::TEXT_SECONDARY)
+                    .corner_radius(2.0),
+            )
+            .into()
+    }
+}
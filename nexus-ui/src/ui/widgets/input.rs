@@ -1,11 +1,23 @@
 //! Input widgets — mode toggle, prompt, completions, and history search.
 //!
 //! Contains:
-//! - NexusInputBar: Mode toggle + path + prompt + text input
+//! - NexusInputBar: Mode toggle + path + provider segments + prompt + text input
 //! - CompletionPopup: Tab completion results overlay
 //! - HistorySearchBar: Ctrl+R reverse-i-search overlay
-
+//! - HistoryExpansionPreview: what `!!` / `!$` / `^old^new` will run
+//! - OutputReferencePreview: what `$_` / `$_N` holds
+//! - LintExplanation: the lint finding under the cursor, and why it matters
+//! - SlashCommandHelp: the slash commands matching the line, or why one failed
+//! - BlockFocusHint: which terminal block keys go to, and how to take them back
+//! - OfflineBanner: agent mode without a network, and what happens to queries
+//! - ProviderWarnings: problems providers found in the directory just entered
+
+use nexus_kernel::dev_env::DevEnv;
+use nexus_kernel::filesystem::DirectoryPreview;
+use nexus_kernel::lint::Lint;
+use nexus_kernel::outputs::OutputPreview;
 use nexus_kernel::{Completion, CompletionKind};
+use nexus_term::width;
 
 use crate::utils::text::display_path;
 use strata::content_address::SourceId;
@@ -17,6 +29,8 @@ use strata::primitives::Color;
 use strata::scroll_state::ScrollState;
 
 use crate::data::InputMode;
+use crate::data::providers::Tone;
+use crate::features::input::slash::SlashCommand;
 use crate::ui::theme;
 use crate::utils::ids;
 
@@ -28,9 +42,23 @@ pub struct NexusInputBar<'a> {
     pub input: &'a strata::TextInputState,
     pub mode: InputMode,
     pub cwd: &'a str,
+    pub path_color: Color,
+    /// Prompt segments contributed by context providers (e.g. git branch).
+    pub segments: Vec<(&'a str, Tone)>,
+    /// Project environments for the directory, and whether each is active;
+    /// clicking one toggles it.
+    pub environments: &'a [(DevEnv, bool)],
     pub last_exit_code: Option<i32>,
     pub cursor_visible: bool,
     pub line_count: usize,
+    /// Lint findings, underlined in the text.
+    pub lints: &'a [Lint],
+    /// Byte range of a command name that names nothing runnable.
+    pub unknown_command: Option<std::ops::Range<usize>>,
+    /// Agent-suggested rest of the command, drawn after the cursor.
+    pub ghost: Option<&'a str>,
+    /// Language of the running `repl` block the input feeds, if any.
+    pub repl: Option<&'a str>,
 }
 
 impl<'a> Widget<'a> for NexusInputBar<'a> {
@@ -42,6 +70,11 @@ impl<'a> Widget<'a> for NexusInputBar<'a> {
             InputMode::Shell => ("SH", Color::rgb(0.5, 0.9, 0.5), Color::rgb(0.2, 0.3, 0.2), "$"),
             InputMode::Agent => ("AI", Color::rgb(0.7, 0.7, 1.0), Color::rgb(0.25, 0.25, 0.4), "?"),
         };
+        let (mode_label, prompt_char) = match (self.mode, self.repl) {
+            (InputMode::Shell, Some("node")) => ("JS", ">"),
+            (InputMode::Shell, Some(_)) => ("PY", ">>>"),
+            _ => (mode_label, prompt_char),
+        };
 
         let mode_btn = ButtonElement::new(mode_toggle_id, mode_label)
             .background(mode_bg)
@@ -56,13 +89,26 @@ impl<'a> Widget<'a> for NexusInputBar<'a> {
             Some(_) => Color::rgb(0.863, 0.196, 0.196),        // bright red
         };
 
-        let input_row = Row::new()
+        let mut input_row = Row::new()
             .padding_custom(Padding::new(4.0, 6.0, 4.0, 6.0))
             .spacing(6.0)
             .width(Length::Fill)
             .cross_align(CrossAxisAlignment::Center)
             .push(mode_btn)
-            .push(TextElement::new(display_cwd).color(theme::TEXT_PATH))
+            .push(TextElement::new(display_cwd).color(self.path_color));
+        for (text, tone) in self.segments {
+            input_row = input_row.push(TextElement::new(text).color(theme::tone(tone)));
+        }
+        for (i, (env, active)) in self.environments.iter().enumerate() {
+            let (mark, color) = if *active { ("\u{25CF}", theme::SUCCESS) } else { ("\u{25CB}", theme::TEXT_MUTED) };
+            input_row = input_row.push(
+                ButtonElement::new(ids::environment_toggle(i), format!("{} {}", mark, env.label()))
+                    .background(theme::CARD_BG)
+                    .text_color(color)
+                    .corner_radius(4.0),
+            );
+        }
+        let input_row = input_row
             .push(TextElement::new(prompt_char).color(prompt_color))
             .push({
                 let mut elem = TextInputElement::from_state(self.input)
@@ -74,6 +120,17 @@ impl<'a> Widget<'a> for NexusInputBar<'a> {
                     .padding(Padding::new(0.0, 4.0, 0.0, 4.0))
                     .width(Length::Fill)
                     .cursor_visible(self.cursor_visible);
+                let text = &self.input.text;
+                let chars = |byte: usize| text.get(..byte).map_or(0, |before| before.chars().count());
+                for lint in self.lints {
+                    elem = elem.underline(chars(lint.range.start), chars(lint.range.end), theme::lint_severity(lint.severity));
+                }
+                if let Some(range) = self.unknown_command {
+                    elem = elem.underline(chars(range.start), chars(range.end), theme::ERROR);
+                }
+                if let Some(ghost) = self.ghost {
+                    elem = elem.ghost(ghost, theme::GHOST_AGENT);
+                }
                 if self.line_count > 1 {
                     let line_height = 18.0_f32;
                     let input_height = self.line_count as f32 * line_height + 4.0;
@@ -100,11 +157,16 @@ impl<'a> Widget<'a> for NexusInputBar<'a> {
 // Completion Popup — shows tab completion results
 // =========================================================================
 
+/// Columns of a completion label that fit beside its icon in the popup.
+const COMPLETION_LABEL_COLS: usize = 30;
+
 pub struct CompletionPopup<'a> {
     pub completions: &'a [Completion],
     pub selected_index: Option<usize>,
     pub hovered_index: Option<usize>,
     pub scroll: &'a ScrollState,
+    /// Contents of the highlighted directory, shown beside the list.
+    pub preview: Option<&'a DirectoryPreview>,
 }
 
 impl CompletionPopup<'_> {
@@ -140,6 +202,7 @@ impl<'a> Widget<'a> for CompletionPopup<'a> {
                 CompletionKind::Builtin => Color::rgb(1.0, 0.8, 0.4),
                 CompletionKind::Function => Color::rgb(0.8, 0.6, 1.0),
                 CompletionKind::Variable => Color::rgb(1.0, 0.6, 0.6),
+                CompletionKind::Task => Color::rgb(0.4, 0.8, 0.9),
                 _ => Color::rgb(0.7, 0.7, 0.7),
             };
 
@@ -162,18 +225,59 @@ impl<'a> Widget<'a> for CompletionPopup<'a> {
                     .corner_radius(3.0)
                     .cross_align(CrossAxisAlignment::Center)
                     .push(TextElement::new(format!("{} ", icon)).color(icon_color))
-                    .push(TextElement::new(&comp.display).color(text_color)),
+                    .push(TextElement::new(width::truncate(&comp.display, COMPLETION_LABEL_COLS)).color(text_color)),
             );
         }
 
+        let mut popup = Row::new().spacing(4.0).push(scroll);
+        if let Some(preview) = self.preview {
+            popup = popup.push(directory_preview(preview));
+        }
         Column::new()
             .padding_custom(Padding::new(0.0, 4.0, 2.0, 4.0))
             .width(Length::Fill)
-            .push(scroll)
+            .push(popup)
             .into()
     }
 }
 
+/// Documentation pane for a directory completion: counts, then the first
+/// few entries.
+fn directory_preview<'a>(preview: &DirectoryPreview) -> Column<'a> {
+    let plural = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+    let mut summary = format!("{}, {}", plural(preview.dirs, "folder", "folders"), plural(preview.files, "file", "files"));
+    if preview.hidden > 0 {
+        summary.push_str(&format!(", {} hidden", preview.hidden));
+    }
+    if preview.truncated {
+        summary.push_str(" (at least)");
+    }
+
+    let mut pane = Column::new()
+        .padding(8.0)
+        .spacing(2.0)
+        .width(Length::Fixed(220.0))
+        .background(Color::rgb(0.12, 0.12, 0.15))
+        .corner_radius(4.0)
+        .border(Color::rgb(0.3, 0.3, 0.35), 1.0)
+        .push(TextElement::new(summary).color(theme::TEXT_SECONDARY));
+    if preview.entries.is_empty() {
+        return pane.push(TextElement::new("Empty").color(theme::TEXT_MUTED));
+    }
+    for entry in &preview.entries {
+        let (icon, color) = if entry.is_dir {
+            (CompletionKind::Directory.icon(), Color::rgb(0.4, 0.7, 1.0))
+        } else {
+            (CompletionKind::File.icon(), Color::rgb(0.8, 0.8, 0.8))
+        };
+        pane = pane.push(TextElement::new(format!("{} {}", icon, entry.name)).color(color));
+    }
+    if preview.more() > 0 {
+        pane = pane.push(TextElement::new(format!("\u{2026} and {} more", preview.more())).color(theme::TEXT_MUTED));
+    }
+    pane
+}
+
 // =========================================================================
 // History Search Bar — Ctrl+R reverse-i-search
 // =========================================================================
@@ -286,3 +390,252 @@ impl<'a> Widget<'a> for HistorySearchBar<'a> {
             .into()
     }
 }
+
+// =========================================================================
+// History Expansion Preview — shows the line `!!` / `!$` will actually run
+// =========================================================================
+
+pub struct HistoryExpansionPreview<'a> {
+    /// Expanded command line, or the expansion error.
+    pub preview: &'a Result<String, String>,
+}
+
+impl<'a> Widget<'a> for HistoryExpansionPreview<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let (arrow_color, text) = match self.preview {
+            Ok(expanded) => (theme::TEXT_MUTED, TextElement::new(expanded).color(Color::rgb(0.8, 0.8, 0.8))),
+            Err(error) => (theme::ERROR, TextElement::new(error).color(theme::ERROR)),
+        };
+
+        Column::new()
+            .padding_custom(Padding::new(0.0, 4.0, 2.0, 4.0))
+            .width(Length::Fill)
+            .push(
+                Row::new()
+                    .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                    .spacing(6.0)
+                    .background(Color::rgb(0.12, 0.12, 0.15))
+                    .corner_radius(4.0)
+                    .width(Length::Fill)
+                    .cross_align(CrossAxisAlignment::Center)
+                    .push(TextElement::new("\u{2192}").color(arrow_color))
+                    .push(text),
+            )
+            .into()
+    }
+}
+
+// =========================================================================
+// Output Reference Preview — what `$_` / `$_N` holds
+// =========================================================================
+
+pub struct OutputReferencePreview<'a> {
+    pub preview: &'a OutputPreview,
+}
+
+impl<'a> Widget<'a> for OutputReferencePreview<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let preview = self.preview;
+        let mut header = format!("${} \u{b7} {}", preview.reference, preview.shape);
+        if let Some(command) = &preview.command {
+            header.push_str(&format!(" \u{2190} {}", command));
+        }
+
+        let mut pane = Column::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(2.0)
+            .background(Color::rgb(0.12, 0.12, 0.15))
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(TextElement::new(header).color(theme::TEXT_SECONDARY));
+        if !preview.columns.is_empty() {
+            pane = pane.push(TextElement::new(preview.columns.join("  ")).color(theme::TEXT_MUTED));
+        }
+        for row in &preview.rows {
+            pane = pane.push(TextElement::new(row).color(Color::rgb(0.8, 0.8, 0.8)));
+        }
+        if preview.more > 0 {
+            pane = pane.push(TextElement::new(format!("\u{2026} and {} more", preview.more)).color(theme::TEXT_MUTED));
+        }
+
+        Column::new()
+            .padding_custom(Padding::new(0.0, 4.0, 2.0, 4.0))
+            .width(Length::Fill)
+            .push(pane)
+            .into()
+    }
+}
+
+// =========================================================================
+// Lint Explanation — the finding under the cursor
+// =========================================================================
+
+pub struct LintExplanation<'a> {
+    pub lint: &'a Lint,
+}
+
+impl<'a> Widget<'a> for LintExplanation<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let lint = self.lint;
+        let color = theme::lint_severity(lint.severity);
+
+        Column::new()
+            .padding_custom(Padding::new(0.0, 4.0, 2.0, 4.0))
+            .width(Length::Fill)
+            .push(
+                Column::new()
+                    .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                    .spacing(2.0)
+                    .background(Color::rgb(0.12, 0.12, 0.15))
+                    .border(color, 1.0)
+                    .corner_radius(4.0)
+                    .width(Length::Fill)
+                    .push(
+                        Row::new()
+                            .spacing(6.0)
+                            .push(TextElement::new(lint.severity.as_str()).color(color))
+                            .push(TextElement::new(lint.rule.name()).color(theme::TEXT_MUTED))
+                            .push(TextElement::new(&lint.message).color(Color::rgb(0.8, 0.8, 0.8))),
+                    )
+                    .push(TextElement::new(lint.rule.explanation()).color(theme::TEXT_SECONDARY)),
+            )
+            .into()
+    }
+}
+
+// =========================================================================
+// Slash Command Help — app actions typed as `/name`
+// =========================================================================
+
+/// Shown while the line starts with `/`: each matching command's usage and
+/// what it does, and the error when one was submitted with bad arguments.
+pub struct SlashCommandHelp<'a> {
+    pub commands: Vec<&'static SlashCommand>,
+    pub error: Option<&'a str>,
+}
+
+impl<'a> Widget<'a> for SlashCommandHelp<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let mut pane = Column::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(2.0)
+            .background(Color::rgb(0.12, 0.12, 0.15))
+            .corner_radius(4.0)
+            .width(Length::Fill);
+        for command in self.commands {
+            pane = pane.push(
+                Row::new()
+                    .spacing(8.0)
+                    .push(TextElement::new(command.usage()).color(theme::TEXT_PRIMARY))
+                    .push(TextElement::new(command.help).color(theme::TEXT_SECONDARY)),
+            );
+        }
+        if let Some(error) = self.error {
+            pane = pane.push(TextElement::new(error).color(theme::ERROR));
+        }
+
+        Column::new()
+            .padding_custom(Padding::new(0.0, 4.0, 2.0, 4.0))
+            .width(Length::Fill)
+            .push(pane)
+            .into()
+    }
+}
+
+// =========================================================================
+// Block Focus Hint — where keystrokes go while a terminal block is focused
+// =========================================================================
+
+/// Shown above the input bar while a running terminal block has the
+/// keyboard: "keys go to: htop — press Ctrl+Shift+Q to release".
+pub struct BlockFocusHint<'a> {
+    /// Program the keys go to.
+    pub program: &'a str,
+    /// Chords that release focus, as labels.
+    pub release: Vec<String>,
+    /// Focus ring color, shared with the block's border.
+    pub accent: Color,
+}
+
+impl<'a> Widget<'a> for BlockFocusHint<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let release = self.release.join(" or ");
+        Row::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .border(self.accent, 1.0)
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(TextElement::new("keys go to:").color(theme::TEXT_MUTED))
+            .push(TextElement::new(self.program).color(self.accent))
+            .push(TextElement::new(format!("\u{2014} press {} to release", release)).color(theme::TEXT_MUTED))
+            .into()
+    }
+}
+
+// =========================================================================
+// Offline Banner — agent mode without a network
+// =========================================================================
+
+pub struct OfflineBanner {
+    /// Whether queries wait for the network (`[agent] queue_offline`).
+    pub queueing: bool,
+    /// Queries waiting to be sent.
+    pub queued: usize,
+}
+
+impl<'a> Widget<'a> for OfflineBanner {
+    fn build(self) -> LayoutChild<'a> {
+        let detail = match (self.queueing, self.queued) {
+            (true, 0) => "queries wait here and are sent when the network returns".to_string(),
+            (true, 1) => "1 query will be sent when the network returns".to_string(),
+            (true, n) => format!("{} queries will be sent when the network returns", n),
+            (false, _) => "the agent can't be reached until the network returns".to_string(),
+        };
+        Row::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .border(theme::WARNING, 1.0)
+            .corner_radius(4.0)
+            .width(Length::Fill)
+            .push(TextElement::new("offline").color(theme::WARNING))
+            .push(TextElement::new(format!("\u{2014} {}", detail)).color(theme::TEXT_MUTED))
+            .into()
+    }
+}
+
+/// Provider warnings about the working directory, one row each with a
+/// button to dismiss it.
+pub struct ProviderWarnings<'a> {
+    /// Warning texts, in the order `ids::warning_dismiss` numbers them.
+    pub warnings: Vec<&'a str>,
+}
+
+impl<'a> Widget<'a> for ProviderWarnings<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let mut col = Column::new().spacing(2.0).width(Length::Fill);
+        for (i, text) in self.warnings.into_iter().enumerate() {
+            col = col.push(
+                Row::new()
+                    .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                    .spacing(6.0)
+                    .cross_align(CrossAxisAlignment::Center)
+                    .border(theme::WARNING, 1.0)
+                    .corner_radius(4.0)
+                    .width(Length::Fill)
+                    .push(TextElement::new("\u{26A0}").color(theme::WARNING))
+                    .push(TextElement::new(text).color(theme::TEXT_SECONDARY))
+                    .spacer(1.0)
+                    .push(
+                        ButtonElement::new(ids::warning_dismiss(i), "\u{2715}")
+                            .background(Color::TRANSPARENT)
+                            .text_color(theme::TEXT_MUTED)
+                            .corner_radius(2.0),
+                    ),
+            );
+        }
+        col.into()
+    }
+}
@@ -0,0 +1,98 @@
+//! Insights view widget — usage counts, tips, and a button to clear them.
+
+use nexus_kernel::insights::Insights;
+use strata::layout::{
+    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget,
+};
+
+use crate::ui::theme;
+use crate::utils::ids;
+
+/// Palette actions listed before the rest are summed up.
+const MAX_ACTIONS: usize = 10;
+
+pub struct InsightsPanel<'a> {
+    pub insights: &'a Insights,
+    /// The counts were just cleared.
+    pub cleared: bool,
+    pub error: Option<&'a str>,
+}
+
+impl<'a> Widget<'a> for InsightsPanel<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let header = Row::new()
+            .spacing(8.0)
+            .width(Length::Fill)
+            .cross_align(CrossAxisAlignment::Center)
+            .push(TextElement::new("Usage Insights").color(theme::WELCOME_TITLE).size(16.0))
+            .push(TextElement::new("kept on this Mac, never sent").color(theme::TEXT_MUTED))
+            .spacer(1.0)
+            .push(
+                ButtonElement::new(ids::insights_clear(), "Clear usage data")
+                    .background(theme::CARD_BG)
+                    .corner_radius(4.0)
+                    .padding(Padding::new(2.0, 8.0, 2.0, 8.0)),
+            )
+            .push(ButtonElement::new(ids::insights_close(), "Close").background(theme::CARD_BG).corner_radius(4.0));
+
+        let mut panel = Column::new().padding(12.0).spacing(12.0).width(Length::Fill).push(header);
+        if let Some(error) = self.error {
+            panel = panel.push(TextElement::new(format!("Usage data unavailable: {}", error)).color(theme::ERROR));
+        } else if self.cleared {
+            panel = panel.push(TextElement::new("\u{2713} Usage data cleared").color(theme::SUCCESS));
+        }
+
+        let insights = self.insights;
+        let completions = match insights.acceptance_rate() {
+            Some(rate) => format!(
+                "{} taken, {} dismissed ({}% taken)",
+                insights.completions_accepted, insights.completions_dismissed, rate
+            ),
+            None => "none yet".to_string(),
+        };
+        let summary = card("Features")
+            .push(stat("Completions", completions))
+            .push(stat("Agent queries", insights.agent_queries.to_string()));
+        panel = panel.push(summary);
+
+        if !insights.palette_actions.is_empty() {
+            let mut actions = card("Most-used actions");
+            for (label, count) in insights.palette_actions.iter().take(MAX_ACTIONS) {
+                actions = actions.push(stat(label, count.to_string()));
+            }
+            let rest = insights.palette_actions.len().saturating_sub(MAX_ACTIONS);
+            if rest > 0 {
+                actions = actions.push(TextElement::new(format!("and {} more", rest)).color(theme::TEXT_MUTED));
+            }
+            panel = panel.push(actions);
+        }
+
+        if !insights.tips.is_empty() {
+            let mut tips = card("Haven't tried yet");
+            for tip in &insights.tips {
+                tips = tips.push(TextElement::new(format!("\u{2022} {}", tip)).color(theme::TEXT_SECONDARY));
+            }
+            panel = panel.push(tips);
+        }
+        panel.into()
+    }
+}
+
+fn card<'a>(title: &str) -> Column<'a> {
+    Column::new()
+        .padding(8.0)
+        .spacing(6.0)
+        .background(theme::CARD_BG)
+        .corner_radius(4.0)
+        .border(theme::CARD_BORDER, 1.0)
+        .width(Length::Fill)
+        .push(TextElement::new(title.to_string()).color(theme::WELCOME_HEADING))
+}
+
+fn stat<'a>(label: &str, value: String) -> Row<'a> {
+    Row::new()
+        .spacing(8.0)
+        .width(Length::Fill)
+        .push(Row::new().width(Length::Fixed(280.0)).push(TextElement::new(label.to_string()).color(theme::TEXT_PRIMARY)))
+        .push(TextElement::new(value).color(theme::TEXT_SECONDARY))
+}
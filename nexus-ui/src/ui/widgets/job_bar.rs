@@ -1,10 +1,13 @@
-//! Job bar widget — shows background job pills.
+//! Job bar widget — shows background job pills and the offline,
+//! low-power and update indicators.
 
+use nexus_kernel::power::PowerOverride;
 use strata::content_address::SourceId;
 use strata::layout::{LayoutChild, Length, Padding, Row, TextElement, Widget};
 use strata::primitives::Color;
 
 use crate::data::{VisualJob, VisualJobState};
+use crate::features::update::UpdateIndicator;
 use crate::utils::ids;
 
 // =========================================================================
@@ -13,6 +16,18 @@ use crate::utils::ids;
 
 pub struct JobBar<'a> {
     pub jobs: &'a [VisualJob],
+    /// No network route: agent and update features are paused.
+    pub offline: bool,
+    pub power: Option<PowerIndicator>,
+    /// New-version pill. Clicking it shows the release notes.
+    pub update: Option<UpdateIndicator>,
+}
+
+/// Low-power pill. Clicking it cycles the override (auto → on → off).
+pub struct PowerIndicator {
+    /// Whether throttling is currently in effect.
+    pub active: bool,
+    pub mode: PowerOverride,
 }
 
 impl JobBar<'_> {
@@ -27,6 +42,51 @@ impl<'a> Widget<'a> for JobBar<'a> {
     fn build(self) -> LayoutChild<'a> {
         let mut row = Row::new().spacing(8.0);
 
+        if self.offline {
+            row = row.push(
+                Row::new()
+                    .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                    .background(Color::rgba(0.4, 0.15, 0.15, 0.6))
+                    .corner_radius(12.0)
+                    .border(Color::rgba(0.5, 0.5, 0.5, 0.3), 1.0)
+                    .push(TextElement::new("\u{2298} Offline").color(Color::rgb(0.95, 0.5, 0.45))),
+            );
+        }
+
+        if let Some(power) = self.power {
+            let (label, color, bg) = match (power.active, power.mode) {
+                (true, PowerOverride::Auto) => ("\u{25D0} Low power \u{00B7} auto", Color::rgb(0.9, 0.7, 0.2), Color::rgba(0.4, 0.35, 0.1, 0.6)),
+                (true, _) => ("\u{25D0} Low power", Color::rgb(0.9, 0.7, 0.2), Color::rgba(0.4, 0.35, 0.1, 0.6)),
+                (false, _) => ("\u{25CF} Full power", Color::rgb(0.6, 0.6, 0.6), Color::rgba(0.25, 0.25, 0.25, 0.6)),
+            };
+            row = row.push(
+                Row::new()
+                    .id(ids::power_mode())
+                    .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                    .background(bg)
+                    .corner_radius(12.0)
+                    .border(Color::rgba(0.5, 0.5, 0.5, 0.3), 1.0)
+                    .push(TextElement::new(label).color(color)),
+            );
+        }
+
+        if let Some(update) = self.update {
+            let (color, bg) = if update.ready {
+                (Color::rgb(0.3, 0.8, 0.3), Color::rgba(0.2, 0.4, 0.2, 0.6))
+            } else {
+                (Color::rgb(0.4, 0.7, 1.0), Color::rgba(0.15, 0.25, 0.4, 0.6))
+            };
+            row = row.push(
+                Row::new()
+                    .id(ids::update_pill())
+                    .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                    .background(bg)
+                    .corner_radius(12.0)
+                    .border(Color::rgba(0.5, 0.5, 0.5, 0.3), 1.0)
+                    .push(TextElement::new(update.label).color(color)),
+            );
+        }
+
         for job in self.jobs {
             let (icon, color, bg) = match job.state {
                 VisualJobState::Running => ("\u{25CF}", Color::rgb(0.3, 0.8, 0.3), Color::rgba(0.2, 0.4, 0.2, 0.6)),
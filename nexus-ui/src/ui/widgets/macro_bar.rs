@@ -0,0 +1,64 @@
+//! Macro bar — above the input while a macro is recorded, named or paused
+//! at a confirmation marker, with what Enter and Escape will do.
+
+use strata::content_address::SourceId;
+use strata::layout::{ButtonElement, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget};
+use strata::primitives::Color;
+
+use crate::ui::theme;
+use crate::utils::ids;
+
+pub struct MacroBar<'a> {
+    /// Steps recorded so far, while recording.
+    pub recording: Option<usize>,
+    /// A finished recording waits for its name.
+    pub naming: bool,
+    /// The macro whose replay is paused.
+    pub paused: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub accent: Color,
+}
+
+impl<'a> Widget<'a> for MacroBar<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let button = |id: SourceId, label: &str, color: Color| {
+            ButtonElement::new(id, label).background(Color::TRANSPARENT).text_color(color).corner_radius(2.0)
+        };
+        let row = Row::new()
+            .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+            .spacing(6.0)
+            .cross_align(CrossAxisAlignment::Center)
+            .background(theme::CARD_BG)
+            .border(self.accent, 1.0)
+            .corner_radius(4.0)
+            .width(Length::Fill);
+
+        if let Some(steps) = self.recording {
+            let plural = if steps == 1 { "" } else { "s" };
+            row.push(TextElement::new("\u{25CF} Recording macro").color(theme::ERROR))
+                .push(TextElement::new(format!("{} step{}", steps, plural)).color(theme::TEXT_SECONDARY))
+                .spacer(1.0)
+                .push(button(ids::macro_pause(), "Insert Pause", theme::TEXT_SECONDARY))
+                .push(button(ids::macro_stop(), "Stop", theme::TEXT_PRIMARY))
+                .into()
+        } else if self.naming {
+            row.push(TextElement::new("Name the macro and press Enter").color(theme::TEXT_PRIMARY))
+                .spacer(1.0)
+                .push(button(ids::macro_discard(), "Discard", theme::TEXT_MUTED))
+                .into()
+        } else if let Some(name) = self.paused {
+            row.push(TextElement::new(format!("Macro {} paused", name)).color(theme::TEXT_PRIMARY))
+                .push(TextElement::new("Enter runs the next step").color(theme::TEXT_SECONDARY))
+                .spacer(1.0)
+                .push(button(ids::macro_continue(), "Continue", theme::TEXT_PRIMARY))
+                .push(button(ids::macro_discard(), "Stop", theme::ERROR))
+                .into()
+        } else {
+            let error = self.error.unwrap_or_default();
+            row.push(TextElement::new(error).color(theme::ERROR))
+                .spacer(1.0)
+                .push(button(ids::macro_discard(), "\u{2715}", theme::TEXT_MUTED))
+                .into()
+        }
+    }
+}
@@ -8,15 +8,39 @@ mod shell_block;
 mod tool;
 mod value_renderer;
 mod agent_block;
+mod agent_tasks;
+mod block_selection;
+mod context_files;
+mod crash_prompt;
+mod hidden_block;
 mod input;
+mod insights;
 mod job_bar;
+mod macro_bar;
+mod onboarding;
+mod release_notes;
+mod settings;
+mod sudo_prompt;
 mod welcome;
 
 pub use shell_block::{ShellBlockWidget, ShellBlockMessage};
+pub(crate) use shell_block::{has_note_gutter, NOTE_GUTTER_COLS};
 pub use tool::{ToolWidget, ToolMessage};
 pub use agent_block::{AgentBlockWidget, AgentBlockMessage};
+pub use agent_tasks::AgentTaskPanel;
+pub use block_selection::{BlockGroupHeader, BlockSelectionBar};
+pub use context_files::ContextFileChips;
 pub(crate) use value_renderer::{render_native_value, term_color_to_strata, TableLayoutCache};
-pub use input::{NexusInputBar, CompletionPopup, HistorySearchBar};
-pub use job_bar::JobBar;
+pub(crate) use value_renderer::custom::{RendererRegistry, install as install_renderers};
+pub use input::{BlockFocusHint, NexusInputBar, CompletionPopup, HistoryExpansionPreview, HistorySearchBar, LintExplanation, OfflineBanner, OutputReferencePreview, ProviderWarnings, SlashCommandHelp};
+pub use job_bar::{JobBar, PowerIndicator};
+pub use macro_bar::MacroBar;
+pub use sudo_prompt::SudoPromptBar;
+pub use crash_prompt::CrashPromptPanel;
+pub use hidden_block::HiddenBlockToast;
+pub use insights::InsightsPanel;
+pub use onboarding::OnboardingPanel;
+pub use release_notes::ReleaseNotesPanel;
+pub use settings::SettingsPanel;
 pub use welcome::WelcomeScreen;
 pub(crate) use breadcrumb::BreadcrumbBar;
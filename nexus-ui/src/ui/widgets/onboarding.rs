@@ -0,0 +1,157 @@
+//! First-run onboarding widget — import settings and history from the
+//! previous shell.
+
+use nexus_api::{DiffFileInfo, DiffLineKind};
+use strata::content_address::SourceId;
+use strata::layout::{
+    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget,
+};
+use strata::primitives::Color;
+
+use crate::ui::theme;
+use crate::utils::ids;
+use crate::utils::text::display_path;
+
+/// Skipped rc lines listed before summarizing the rest.
+const MAX_SKIPPED_SHOWN: usize = 8;
+
+pub struct OnboardingPanel<'a> {
+    pub shell: &'static str,
+    /// rc files that were read.
+    pub files: Vec<String>,
+    pub diff: Option<&'a DiffFileInfo>,
+    pub skipped: &'a [String],
+    /// Commands on offer from the shell's history.
+    pub history: usize,
+    pub history_shared: bool,
+    pub settings_imported: Option<usize>,
+    pub history_imported: Option<usize>,
+    pub error: Option<&'a str>,
+}
+
+impl<'a> Widget<'a> for OnboardingPanel<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let done = self.settings_imported.is_some() || self.history_imported.is_some();
+        let header = Row::new()
+            .spacing(8.0)
+            .width(Length::Fill)
+            .cross_align(CrossAxisAlignment::Center)
+            .push(TextElement::new("Welcome to Nexus").color(theme::WELCOME_TITLE).size(16.0))
+            .spacer(1.0)
+            .push(button(ids::onboarding_done(), if done { "Done" } else { "Skip" }, theme::CARD_BG));
+
+        let found = if self.files.is_empty() {
+            format!("No {} startup files found.", self.shell)
+        } else {
+            let files: Vec<String> = self.files.iter().map(|f| display_path(f)).collect();
+            format!("Found your {} setup in {}.", self.shell, files.join(", "))
+        };
+
+        let mut panel = Column::new()
+            .padding(12.0)
+            .spacing(12.0)
+            .width(Length::Fill)
+            .push(header)
+            .push(TextElement::new(found).color(theme::TEXT_SECONDARY));
+        if let Some(error) = self.error {
+            panel = panel.push(TextElement::new(format!("Import failed: {}", error)).color(theme::ERROR));
+        }
+
+        let mut settings = card("Aliases, environment and PATH");
+        match (self.settings_imported, self.diff) {
+            (Some(count), _) => {
+                let note = format!("\u{2713} Imported {} settings", count);
+                settings = settings.push(TextElement::new(note).color(theme::SUCCESS));
+            }
+            (None, Some(diff)) => {
+                let file = display_path(&diff.file_path);
+                let intro = Row::new()
+                    .spacing(8.0)
+                    .width(Length::Fill)
+                    .cross_align(CrossAxisAlignment::Center)
+                    .push(TextElement::new(format!("Review the changes to {}", file)).color(theme::TEXT_PRIMARY))
+                    .spacer(1.0)
+                    .push(button(ids::onboarding_import_settings(), "Import", theme::BTN_ALLOW));
+                settings = settings.push(intro).push(diff_lines(diff));
+            }
+            (None, None) => {
+                settings = settings.push(TextElement::new("Nothing new to import.").color(theme::TEXT_MUTED));
+            }
+        }
+        if !self.skipped.is_empty() && self.settings_imported.is_none() {
+            settings = settings.push(
+                TextElement::new("Left out \u{2014} these need your shell to evaluate them:").color(theme::WARNING),
+            );
+            for line in self.skipped.iter().take(MAX_SKIPPED_SHOWN) {
+                settings = settings.push(TextElement::new(format!("  {}", line)).color(theme::TEXT_MUTED));
+            }
+            if self.skipped.len() > MAX_SKIPPED_SHOWN {
+                let more = format!("  \u{2026} and {} more", self.skipped.len() - MAX_SKIPPED_SHOWN);
+                settings = settings.push(TextElement::new(more).color(theme::TEXT_MUTED));
+            }
+        }
+        panel = panel.push(settings);
+
+        let history = match (self.history_imported, self.history) {
+            (Some(count), _) => Some(
+                card("History").push(
+                    TextElement::new(format!("\u{2713} Imported {} commands", count)).color(theme::SUCCESS),
+                ),
+            ),
+            (None, 0) if self.history_shared => Some(card("History").push(
+                TextElement::new(format!("Nexus already shares your {} history.", self.shell))
+                    .color(theme::TEXT_SECONDARY),
+            )),
+            (None, 0) => None,
+            (None, count) => Some(
+                card("History").push(
+                    Row::new()
+                        .spacing(8.0)
+                        .width(Length::Fill)
+                        .cross_align(CrossAxisAlignment::Center)
+                        .push(
+                            TextElement::new(format!("Bring over {} commands from your {} history", count, self.shell))
+                                .color(theme::TEXT_PRIMARY),
+                        )
+                        .spacer(1.0)
+                        .push(button(ids::onboarding_import_history(), "Import history", theme::BTN_ALLOW)),
+                ),
+            ),
+        };
+        if let Some(history) = history {
+            panel = panel.push(history);
+        }
+        panel.into()
+    }
+}
+
+fn card<'a>(title: &str) -> Column<'a> {
+    Column::new()
+        .padding(8.0)
+        .spacing(6.0)
+        .background(theme::CARD_BG)
+        .corner_radius(4.0)
+        .border(theme::CARD_BORDER, 1.0)
+        .width(Length::Fill)
+        .push(TextElement::new(title.to_string()).color(theme::WELCOME_HEADING))
+}
+
+fn button(id: SourceId, label: &str, background: Color) -> ButtonElement {
+    ButtonElement::new(id, label)
+        .background(background)
+        .corner_radius(4.0)
+        .padding(Padding::new(2.0, 8.0, 2.0, 8.0))
+}
+
+fn diff_lines<'a>(diff: &DiffFileInfo) -> Column<'a> {
+    let mut col = Column::new().padding(6.0).spacing(0.0).background(theme::CODE_BG).width(Length::Fill);
+    for line in diff.hunks.iter().flat_map(|h| &h.lines) {
+        let (prefix, color) = match line.kind {
+            DiffLineKind::Context => (" ", theme::TEXT_MUTED),
+            DiffLineKind::Addition => ("+", theme::DIFF_ADD),
+            DiffLineKind::Deletion => ("-", theme::DIFF_REMOVE),
+        };
+        col = col.push(TextElement::new(format!("{}{}", prefix, line.content)).color(color));
+    }
+    col
+}
@@ -0,0 +1,79 @@
+//! Release notes widget — what's new in an available update, with the
+//! download and install steps.
+
+use nexus_kernel::update::Release;
+use strata::content_address::SourceId;
+use strata::layout::{
+    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget,
+};
+use strata::primitives::Color;
+
+use crate::features::update::Download;
+use crate::ui::{markdown, theme};
+use crate::utils::ids;
+
+pub struct ReleaseNotesPanel<'a> {
+    pub release: &'a Release,
+    /// The running version.
+    pub current: &'static str,
+    pub download: &'a Download,
+    /// Whether a download for this platform is offered.
+    pub can_stage: bool,
+}
+
+impl<'a> Widget<'a> for ReleaseNotesPanel<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let title = format!("Nexus {}", self.release.version);
+        let mut header = Row::new()
+            .spacing(8.0)
+            .width(Length::Fill)
+            .cross_align(CrossAxisAlignment::Center)
+            .push(TextElement::new(title).color(theme::WELCOME_TITLE).size(16.0))
+            .push(TextElement::new(format!("you have {}", self.current)).color(theme::TEXT_MUTED))
+            .spacer(1.0);
+        header = match (self.download, self.can_stage) {
+            (Download::Staged(_), _) => header.push(button(ids::update_install(), "Install\u{2026}", theme::BTN_ALLOW)),
+            (Download::InProgress, _) => header.push(TextElement::new("Downloading\u{2026}").color(theme::TEXT_SECONDARY)),
+            (_, true) => header.push(button(ids::update_download(), "Download", theme::BTN_ALLOW)),
+            (_, false) => header,
+        };
+        header = header
+            .push(button(ids::update_dismiss(), "Not now", theme::CARD_BG))
+            .push(button(ids::update_close(), "Close", theme::CARD_BG));
+
+        let mut panel = Column::new().padding(12.0).spacing(12.0).width(Length::Fill).push(header);
+        match self.download {
+            Download::Staged(path) => {
+                let note = format!("\u{2713} Downloaded to {}. Install opens it; quit Nexus to finish.", path.display());
+                panel = panel.push(TextElement::new(note).color(theme::SUCCESS));
+            }
+            Download::Failed(error) => {
+                panel = panel.push(TextElement::new(format!("Download failed: {}", error)).color(theme::ERROR));
+            }
+            Download::NotStarted | Download::InProgress => {}
+        }
+        if !self.can_stage && let Some(page) = &self.release.page {
+            panel = panel.push(TextElement::new(format!("Download it from {}", page)).color(theme::TEXT_SECONDARY));
+        }
+
+        let notes = if self.release.notes.trim().is_empty() { "No release notes." } else { &self.release.notes };
+        panel
+            .push(
+                Column::new()
+                    .padding(8.0)
+                    .background(theme::CARD_BG)
+                    .corner_radius(4.0)
+                    .border(theme::CARD_BORDER, 1.0)
+                    .width(Length::Fill)
+                    .push(markdown::render(notes, ids::update_notes())),
+            )
+            .into()
+    }
+}
+
+fn button(id: SourceId, label: &str, background: Color) -> ButtonElement {
+    ButtonElement::new(id, label)
+        .background(background)
+        .corner_radius(4.0)
+        .padding(Padding::new(2.0, 8.0, 2.0, 8.0))
+}
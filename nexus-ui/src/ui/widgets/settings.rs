@@ -0,0 +1,100 @@
+//! Settings view widget — one card per section, one row per setting.
+
+use strata::content_address::SourceId;
+use strata::layout::{
+    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget,
+};
+use strata::primitives::Color;
+
+use crate::features::settings::SettingRow;
+use crate::ui::theme;
+use crate::utils::ids;
+use crate::utils::text::display_path;
+
+pub struct SettingsPanel<'a> {
+    pub rows: Vec<SettingRow>,
+    /// The file changes are written to.
+    pub file: String,
+    /// Why the last change could not be saved.
+    pub error: Option<&'a str>,
+}
+
+impl<'a> Widget<'a> for SettingsPanel<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let header = Row::new()
+            .spacing(8.0)
+            .width(Length::Fill)
+            .cross_align(CrossAxisAlignment::Center)
+            .push(TextElement::new("Settings").color(theme::WELCOME_TITLE).size(16.0))
+            .push(TextElement::new(display_path(&self.file)).color(theme::TEXT_MUTED))
+            .spacer(1.0)
+            .push(ButtonElement::new(ids::settings_close(), "Close").background(theme::CARD_BG).corner_radius(4.0));
+
+        let mut panel = Column::new().padding(12.0).spacing(12.0).width(Length::Fill).push(header);
+        if let Some(error) = self.error {
+            panel = panel.push(TextElement::new(format!("Not saved: {}", error)).color(theme::ERROR));
+        }
+
+        let mut card: Option<Column<'a>> = None;
+        let mut section = "";
+        for (r, row) in self.rows.into_iter().enumerate() {
+            if row.section != section {
+                if let Some(done) = card.take() {
+                    panel = panel.push(done);
+                }
+                section = row.section;
+                card = Some(section_card(section));
+            }
+            card = card.map(|c| c.push(build_row(r, row)));
+        }
+        if let Some(done) = card {
+            panel = panel.push(done);
+        }
+        panel.into()
+    }
+}
+
+fn section_card<'a>(title: &str) -> Column<'a> {
+    Column::new()
+        .padding(8.0)
+        .spacing(6.0)
+        .background(theme::CARD_BG)
+        .corner_radius(4.0)
+        .border(theme::CARD_BORDER, 1.0)
+        .width(Length::Fill)
+        .push(TextElement::new(title.to_string()).color(theme::WELCOME_HEADING))
+}
+
+fn build_row<'a>(r: usize, row: SettingRow) -> Column<'a> {
+    let mut line = Row::new()
+        .spacing(8.0)
+        .width(Length::Fill)
+        .cross_align(CrossAxisAlignment::Center)
+        .push(Row::new().width(Length::Fixed(280.0)).push(TextElement::new(row.label).color(theme::TEXT_PRIMARY)))
+        .push(TextElement::new(row.value).color(theme::TEXT_SECONDARY))
+        .spacer(1.0);
+    for (c, choice) in row.choices.into_iter().enumerate() {
+        let swatch = choice.swatch.and_then(theme::parse_hex);
+        let background = match (swatch, choice.selected) {
+            (Some(color), _) => color,
+            (None, true) => theme::BTN_ALLOW,
+            (None, false) => theme::CARD_BG,
+        };
+        let label = if swatch.is_some() && choice.selected { format!("\u{2713} {}", choice.label) } else { choice.label };
+        let mut button = ButtonElement::new(ids::settings_choice(r, c), label)
+            .background(background)
+            .corner_radius(4.0)
+            .padding(Padding::new(2.0, 8.0, 2.0, 8.0));
+        if swatch.is_some() {
+            button = button.text_color(Color::BLACK);
+        }
+        line = line.push(button);
+    }
+
+    let mut col = Column::new().spacing(2.0).width(Length::Fill).push(line);
+    if let Some(path) = row.overridden_by {
+        let note = format!("overridden here by {}", path.display());
+        col = col.push(TextElement::new(note).color(theme::WARNING));
+    }
+    col
+}
@@ -6,29 +6,43 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 
 use nexus_api::BlockState;
+use nexus_kernel::test_report::TestStatus;
+use nexus_kernel::debug::DebugAction;
 
-use crate::data::{Block, ConnectProgress};
+use crate::data::{Block, ConnectProgress, DebugPause, OutputStream, ReplCellOutput, ReplSession, TestTree, Throughput};
+use crate::data::provider_host::Annotation;
 use crate::features::shell::ClickAction;
+use crate::features::shell::prediction::PredictionEngine;
 use crate::utils::ids;
 use crate::ui::theme;
 use super::{render_native_value, term_color_to_strata, TableLayoutCache};
 use strata::content_address::SourceId;
 use strata::gpu::ImageHandle;
 use strata::layout::{
-    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Row,
+    ButtonElement, Column, CrossAxisAlignment, LayoutChild, Length, Padding, Row,
     TerminalElement, TextElement, Widget,
 };
-use strata::layout_snapshot::{RunStyle, TextRun, UnderlineStyle};
+use strata::layout_snapshot::{CursorIcon, RunStyle, TextRun, UnderlineStyle};
 use strata::primitives::Color;
 
 /// Message type for shell block interactions.
 #[derive(Debug, Clone)]
 pub enum ShellBlockMessage {
     Kill,
+    Debug(DebugAction),
     ExitViewer,
     ToggleCollapse,
     AnchorClick(SourceId),
     TreeToggle(std::path::PathBuf),
+    ToggleTimeline,
+    ToggleUsage,
+    ToggleRepeat,
+    ToggleRestored,
+    NextProblem,
+    ToggleTestOutput,
+    ToggleTestNode(usize, Option<usize>),
+    RerunFailedTests,
+    RestartRepl,
 }
 
 /// Shell block widget — renders a command block with terminal output.
@@ -37,6 +51,10 @@ pub struct ShellBlockWidget<'a> {
     pub kill_id: SourceId,
     pub image_info: Option<(ImageHandle, u32, u32)>,
     pub is_focused: bool,
+    /// Shift-click selected for a bulk action.
+    pub is_selected: bool,
+    /// Focus ring color.
+    pub accent: Color,
     /// Unified click registry — populated during rendering so click/drag
     /// handling can do O(1) lookups without re-iterating the Value tree.
     pub(crate) click_registry: &'a RefCell<HashMap<SourceId, ClickAction>>,
@@ -46,6 +64,19 @@ pub struct ShellBlockWidget<'a> {
     pub(crate) table_cell_images: &'a HashMap<(nexus_api::BlockId, usize, usize), (ImageHandle, u32, u32)>,
     /// Whether the remote connection is down (dims authoritative grid cells).
     pub connection_dimmed: bool,
+    /// Labels context providers attached to this block.
+    pub annotations: &'a [Annotation],
+    /// Output row under the pointer, which shows where a note would go.
+    pub note_hover: Option<usize>,
+}
+
+/// Width of the notes gutter left of terminal output, in cells.
+pub(crate) const NOTE_GUTTER_COLS: u16 = 2;
+
+/// Whether the block's terminal output has a notes gutter. Full-screen
+/// programs redraw their rows in place, so rows there aren't lines.
+pub(crate) fn has_note_gutter(block: &Block) -> bool {
+    !block.parser.is_alternate_screen()
 }
 
 impl<'a> Widget<'a> for ShellBlockWidget<'a> {
@@ -81,15 +112,27 @@ impl<'a> Widget<'a> for ShellBlockWidget<'a> {
             .width(Length::Fill);
 
         if self.is_focused {
-            content = content.border(Color::rgb(0.3, 0.7, 1.0), 2.0);
+            content = content.border(self.accent, 2.0);
+        } else if self.is_selected {
+            content = content.border(self.accent, 1.0);
         }
 
         content = content.push(build_header(block, self.kill_id, header_source));
 
+        // Output that repeats the last run is folded until asked for, as
+        // is that of blocks restored from the previous session.
+        let folded = (block.repeat_of.is_some() && !block.repeat_expanded) || block.collapsed;
+        if block.repeat_of.is_some() {
+            content = content.push(build_repeat_toggle(block.id, block.repeat_expanded));
+        }
+        if block.restored.is_some() {
+            content = content.push(build_restored_toggle(block.id, !block.collapsed));
+        }
+
         if let Some(ref cp) = block.connect_progress {
             // Render connection progress overlay instead of terminal output
             content = build_connect_progress(content, cp);
-        } else {
+        } else if !folded {
             // Render output: live_value replaces structured_output when present (e.g. top),
             // otherwise show structured_output (e.g. ls, git status).
             if let Some(ref latest) = block.live_value {
@@ -100,11 +143,25 @@ impl<'a> Widget<'a> for ShellBlockWidget<'a> {
 
             content = build_event_log(content, block, self.image_info, self.click_registry, self.table_layout_cache, self.table_cell_images);
 
-            if block.structured_output.is_none() && block.live_value.is_none() && block.event_log.is_empty() && content_rows > 0 {
-                content = build_terminal_content(content, block, &grid, cols, content_rows, self.connection_dimmed);
+            if let Some(repl) = &block.repl {
+                content = build_repl_cells(content, block, repl, self.image_info, self.click_registry, self.table_layout_cache, self.table_cell_images);
+            } else if let Some(tests) = block.tests.as_ref().filter(|tests| !tests.show_output) {
+                content = build_test_tree(content, block.id, tests);
+            } else if block.timeline && has_timeline(block) {
+                content = build_timeline(content, block);
+            } else if block.structured_output.is_none() && block.live_value.is_none() && block.event_log.is_empty() && content_rows > 0 {
+                content = build_terminal_content(content, block, &grid, cols, content_rows, self.connection_dimmed, self.note_hover);
             }
         }
 
+        if let Some(pause) = &block.debug {
+            content = content.push(build_debug_bar(block.id, pause, header_source));
+        }
+
+        if let Some(error) = &block.error {
+            content = content.push(build_error_chip(error, header_source));
+        }
+
         // Exit code indicator for failed commands
         match block.state {
             BlockState::Failed(code) => {
@@ -113,9 +170,24 @@ impl<'a> Widget<'a> for ShellBlockWidget<'a> {
                         .source(header_source),
                 );
             }
+            BlockState::Interrupted => {
+                content = content.push(
+                    TextElement::new("interrupted — output recovered after Nexus quit unexpectedly")
+                        .color(theme::WARNING)
+                        .source(header_source),
+                );
+            }
             _ => {}
         }
 
+        if let Some(usage) = block.resource_usage.as_ref().filter(|_| !block.is_running()) {
+            content = content.push(build_usage_footer(block.id, usage, block.usage_expanded));
+        }
+
+        if !self.annotations.is_empty() {
+            content = content.push(build_annotations(self.annotations, header_source));
+        }
+
         content.into()
     }
 }
@@ -130,19 +202,118 @@ fn build_header<'a>(block: &Block, kill_id: SourceId, header_source: SourceId) -
         BlockState::Running => ("\u{25CF}", theme::RUNNING),
         BlockState::Success => ("\u{2713}", theme::SUCCESS),
         BlockState::Failed(_) => ("\u{2717}", theme::ERROR),
+        BlockState::Interrupted => ("\u{26A0}", theme::WARNING),
     };
 
+    // An `in <dir> NAME=v` prefix shows as a pill after the command it applies to.
+    let (command, over) = match nexus_kernel::overrides::ExecOverride::split(&block.command) {
+        Some((over, rest)) => (rest, Some(over)),
+        None => (block.command.as_str(), None),
+    };
+    // So does the container a `nexus exec -c` line runs in.
+    let command = nexus_kernel::containers::ContainerExec::split(command).map_or(command, |(_, rest)| rest);
+
     let mut header = Row::new()
         .spacing(8.0)
         .cross_align(CrossAxisAlignment::Center)
         .push(
-            TextElement::new(format!("{} $ {}", status_icon, block.command))
+            TextElement::new(format!("{} $ {}", status_icon, command))
                 .color(status_color)
                 .source(header_source),
-        )
-        .spacer(1.0);
+        );
+    if let Some(over) = over {
+        header = header.push(
+            Row::new()
+                .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                .background(theme::CARD_BG)
+                .corner_radius(12.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .push(TextElement::new(over.label()).color(theme::TEXT_MUTED)),
+        );
+    }
+    if let Some(container) = &block.container {
+        header = header.push(
+            Row::new()
+                .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                .background(theme::CARD_BG)
+                .corner_radius(12.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .push(TextElement::new(format!("\u{2b22} {}", container)).color(theme::TEXT_MUTED)),
+        );
+    }
+    if let Some(shared) = &block.shared {
+        let when = chrono::DateTime::parse_from_rfc3339(&shared.shared_at)
+            .map(|at| format!(" {}", at.format("%b %-d, %H:%M")))
+            .unwrap_or_default();
+        header = header.push(
+            Row::new()
+                .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                .background(theme::CARD_BG)
+                .corner_radius(12.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .push(TextElement::new(format!("\u{21e7} shared{}", when)).color(theme::TEXT_MUTED)),
+        );
+    }
+    if let Some(worktree) = &block.worktree {
+        header = header.push(
+            Row::new()
+                .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                .background(theme::CARD_BG)
+                .corner_radius(12.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .push(TextElement::new(format!("\u{2387} {}", worktree)).color(theme::TEXT_MUTED)),
+        );
+    }
+    header = header.spacer(1.0);
+
+    // "2 new warnings, 5 fixed" since the last run; click to visit the new ones.
+    if let Some(summary) = block.problems.as_ref().and_then(|delta| delta.summary()) {
+        let new = block.problems.as_ref().map_or(0, |delta| delta.new.len());
+        if new > 0 {
+            header = header.push(
+                ButtonElement::new(ids::problems_next(block.id), format!("{} \u{2192}", summary))
+                    .background(theme::CARD_BG)
+                    .text_color(theme::WARNING)
+                    .corner_radius(4.0),
+            );
+        } else {
+            header = header.push(TextElement::new(summary).color(theme::SUCCESS));
+        }
+    }
+
+    if let Some(tests) = &block.tests {
+        let label = if tests.show_output { "Tests" } else { "Output" };
+        header = header.push(
+            ButtonElement::new(ids::tests_toggle(block.id), label)
+                .background(theme::CARD_BG)
+                .text_color(theme::TEXT_SECONDARY)
+                .corner_radius(4.0),
+        );
+    }
+
+    if block.is_running() && block.repl.is_some() {
+        header = header.push(
+            ButtonElement::new(ids::repl_restart(block.id), "Restart")
+                .background(theme::CARD_BG)
+                .text_color(theme::TEXT_SECONDARY)
+                .corner_radius(4.0),
+        );
+    }
+
+    if has_timeline(block) {
+        let label = if block.timeline { "Grid" } else { "Timeline" };
+        header = header.push(
+            ButtonElement::new(ids::timeline_toggle(block.id), label)
+                .background(theme::CARD_BG)
+                .text_color(theme::TEXT_SECONDARY)
+                .corner_radius(4.0),
+        );
+    }
 
     if block.is_running() {
+        if let Some(throughput) = block.throughput.as_ref().and_then(throughput_label) {
+            header = header.push(TextElement::new(throughput).color(theme::TEXT_MUTED));
+        }
         header = header.push(
             ButtonElement::new(kill_id, "Kill")
                 .background(theme::BTN_KILL)
@@ -167,10 +338,181 @@ fn build_header<'a>(block: &Block, kill_id: SourceId, header_source: SourceId) -
     header
 }
 
+/// "out 2.1K/s · read 1.2M/s · write 4.0M/s", leaving out idle parts;
+/// None when everything is idle.
+fn throughput_label(throughput: &Throughput) -> Option<String> {
+    let io = throughput.io.unwrap_or_default();
+    let parts: Vec<String> = [("out", throughput.output), ("read", io.read), ("write", io.written)]
+        .into_iter()
+        .filter(|&(_, rate)| rate > 0)
+        .map(|(name, rate)| format!("{} {}/s", name, nexus_api::format_size(rate)))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" \u{00B7} "))
+}
+
+/// What the process used: one line, or every figure once expanded.
+fn build_usage_footer<'a>(block_id: nexus_api::BlockId, usage: &nexus_api::ResourceUsage, expanded: bool) -> Row<'a> {
+    let (arrow, label) = if expanded {
+        let details: Vec<String> = usage.details().into_iter().map(|(name, value)| format!("{} {}", name, value)).collect();
+        ("\u{25BE}", details.join(" \u{00B7} "))
+    } else {
+        ("\u{25B8}", usage.summary())
+    };
+    Row::new().push(
+        ButtonElement::new(ids::usage_toggle(block_id), format!("{} {}", arrow, label))
+            .background(Color::TRANSPARENT)
+            .text_color(theme::TEXT_MUTED)
+            .corner_radius(4.0),
+    )
+}
+
+/// Marks a block whose output repeats the last run of its command, and
+/// folds or shows that output.
+fn build_repeat_toggle<'a>(block_id: nexus_api::BlockId, expanded: bool) -> Row<'a> {
+    let arrow = if expanded { "\u{25BE}" } else { "\u{25B8}" };
+    Row::new().push(
+        ButtonElement::new(ids::repeat_toggle(block_id), format!("{} Same output as previous run", arrow))
+            .background(Color::TRANSPARENT)
+            .text_color(theme::TEXT_MUTED)
+            .corner_radius(4.0),
+    )
+}
+
+fn build_restored_toggle<'a>(block_id: nexus_api::BlockId, expanded: bool) -> Row<'a> {
+    let arrow = if expanded { "\u{25BE}" } else { "\u{25B8}" };
+    Row::new().push(
+        ButtonElement::new(ids::restored_toggle(block_id), format!("{} From the previous session", arrow))
+            .background(Color::TRANSPARENT)
+            .text_color(theme::TEXT_MUTED)
+            .corner_radius(4.0),
+    )
+}
+
+/// Structured command failure: a kind pill, the message, and any suggestion.
+fn build_error_chip<'a>(error: &nexus_api::CommandError, source: SourceId) -> Row<'a> {
+    let mut message = match &error.path {
+        Some(path) => format!("{}: {}", path.display(), error.message),
+        None => error.message.clone(),
+    };
+    if let Some(suggestion) = &error.suggestion {
+        message.push_str(" \u{2014} ");
+        message.push_str(suggestion);
+    }
+
+    Row::new()
+        .spacing(8.0)
+        .cross_align(CrossAxisAlignment::Center)
+        .push(
+            Row::new()
+                .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                .background(Color::rgba(0.5, 0.15, 0.15, 0.6))
+                .corner_radius(12.0)
+                .border(Color::rgba(0.8, 0.3, 0.3, 0.4), 1.0)
+                .push(TextElement::new(format!("{} \u{00B7} {}", error.command, error.kind.label())).color(theme::ERROR)),
+        )
+        .push(TextElement::new(message).color(theme::TEXT_SECONDARY).source(source))
+}
+
+/// `debug <script>` paused: the step about to run and the controls.
+fn build_debug_bar<'a>(block_id: nexus_api::BlockId, pause: &DebugPause, source: SourceId) -> Row<'a> {
+    Row::new()
+        .spacing(8.0)
+        .cross_align(CrossAxisAlignment::Center)
+        .padding_custom(Padding::new(4.0, 6.0, 4.0, 6.0))
+        .background(theme::CARD_BG)
+        .corner_radius(4.0)
+        .border(theme::CARD_BORDER, 1.0)
+        .push(TextElement::new(format!("\u{23F8} {}/{}", pause.step, pause.total)).color(theme::WARNING))
+        .push(TextElement::new(pause.command.clone()).color(theme::TEXT_SECONDARY).source(source))
+        .spacer(1.0)
+        .push(ButtonElement::new(ids::debug_step(block_id), "Step").background(theme::BTN_ALWAYS).corner_radius(4.0))
+        .push(ButtonElement::new(ids::debug_continue(block_id), "Continue").background(theme::BTN_ALLOW).corner_radius(4.0))
+        .push(ButtonElement::new(ids::debug_abort(block_id), "Abort").background(theme::BTN_KILL).corner_radius(4.0))
+}
+
+/// A `repl` block's cells: the code sent, then what it printed and returned.
+fn build_repl_cells<'a>(
+    mut content: Column<'a>,
+    block: &Block,
+    repl: &ReplSession,
+    image_info: Option<(ImageHandle, u32, u32)>,
+    click_registry: &RefCell<HashMap<SourceId, ClickAction>>,
+    table_layout_cache: &TableLayoutCache,
+    table_cell_images: &HashMap<(nexus_api::BlockId, usize, usize), (ImageHandle, u32, u32)>,
+) -> Column<'a> {
+    let source_id = ids::shell_term(block.id);
+    let lines = |mut column: Column<'a>, text: &str, color: Color| {
+        for line in text.trim_end_matches('\n').lines() {
+            column = column.push(TextElement::new(line.to_string()).color(color).source(source_id));
+        }
+        column
+    };
+
+    let mut session = 0;
+    for cell in &repl.cells {
+        if cell.session != session {
+            session = cell.session;
+            content = content.push(TextElement::new("\u{21BB} restarted").color(theme::TEXT_MUTED).source(source_id));
+        }
+        if cell.number > 0 {
+            let mut code = cell.code.lines();
+            let first = code.next().unwrap_or("");
+            let mut row = Row::new()
+                .spacing(6.0)
+                .push(TextElement::new(format!("[{}]", cell.number)).color(theme::TEXT_PURPLE).source(source_id))
+                .push(TextElement::new(first.to_string()).color(theme::TEXT_PRIMARY).source(source_id));
+            if cell.running {
+                row = row.push(TextElement::new("\u{25CF}").color(theme::RUNNING).source(source_id));
+            }
+            content = content.push(row);
+            for line in code {
+                content = content.push(
+                    Row::new()
+                        .padding_custom(Padding::new(0.0, 0.0, 0.0, 24.0))
+                        .push(TextElement::new(line.to_string()).color(theme::TEXT_PRIMARY).source(source_id)),
+                );
+            }
+        }
+        for output in &cell.outputs {
+            content = match output {
+                ReplCellOutput::Text { text, stderr } => {
+                    lines(content, text, if *stderr { theme::WARNING } else { theme::TEXT_SECONDARY })
+                }
+                ReplCellOutput::Value(value) => {
+                    render_native_value(content, value, block, image_info, click_registry, table_layout_cache, table_cell_images)
+                }
+            };
+        }
+        if let Some(error) = &cell.error {
+            content = lines(content, error, theme::ERROR);
+        }
+    }
+    content
+}
+
+/// Provider annotations as a row of pills.
+fn build_annotations<'a>(annotations: &[Annotation], source: SourceId) -> Row<'a> {
+    annotations.iter().fold(Row::new().spacing(6.0), |row, annotation| {
+        row.push(
+            Row::new()
+                .padding_custom(Padding::new(2.0, 6.0, 2.0, 6.0))
+                .background(theme::CARD_BG)
+                .corner_radius(12.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .push(TextElement::new(annotation.label.clone()).color(theme::tone(annotation.tone)).source(source)),
+        )
+    })
+}
+
 /// Debounce shrink for running non-alt-screen blocks to mask clear+reprint flicker.
+/// A running alt-screen app gets its whole viewport-sized grid, drawn or not,
+/// so the block keeps one height while the app repaints.
 fn debounced_content_rows(block: &Block, grid: &nexus_term::TerminalGrid) -> u16 {
     let content_rows = grid.content_rows();
-    if block.is_running() && !block.parser.is_alternate_screen() {
+    if block.is_running() && block.parser.is_alternate_screen() {
+        block.peak_content_rows.store(0, std::sync::atomic::Ordering::Relaxed);
+        grid.rows()
+    } else if block.is_running() {
         let peak = block.peak_content_rows.load(std::sync::atomic::Ordering::Relaxed);
         if content_rows >= peak {
             block.peak_content_rows.store(content_rows, std::sync::atomic::Ordering::Relaxed);
@@ -230,6 +572,168 @@ fn build_event_log<'a>(
     content
 }
 
+/// Lines of a failed test's output shown before the rest is elided.
+const FAILURE_LINES: usize = 40;
+
+/// A test run's results: the totals and a re-run button, then each suite
+/// with its counts, expanding to its tests and their failure output.
+fn build_test_tree<'a>(mut content: Column<'a>, block_id: nexus_api::BlockId, tests: &TestTree) -> Column<'a> {
+    let source_id = ids::shell_term(block_id);
+    let counts = tests.report.counts();
+    let mut summary = Row::new()
+        .spacing(12.0)
+        .cross_align(CrossAxisAlignment::Center)
+        .push(TextElement::new(format!("\u{2713} {} passed", counts.passed)).color(theme::SUCCESS).source(source_id));
+    if counts.failed > 0 {
+        summary = summary.push(TextElement::new(format!("\u{2717} {} failed", counts.failed)).color(theme::ERROR).source(source_id));
+    }
+    if counts.skipped > 0 {
+        summary = summary.push(TextElement::new(format!("\u{25CB} {} skipped", counts.skipped)).color(theme::TEXT_MUTED).source(source_id));
+    }
+    if tests.rerun.is_some() {
+        summary = summary.spacer(1.0).push(
+            ButtonElement::new(ids::tests_rerun(block_id), "Re-run failed")
+                .background(theme::CARD_BG)
+                .text_color(theme::TEXT_SECONDARY)
+                .corner_radius(4.0),
+        );
+    }
+    content = content.push(summary);
+
+    for (i, suite) in tests.report.suites.iter().enumerate() {
+        let open = tests.open_suites.contains(&i);
+        let suite_counts = suite.counts();
+        let color = if suite_counts.failed > 0 { theme::ERROR } else { theme::SUCCESS };
+        let node_id = ids::test_node(block_id, i, None);
+        content = content.push(
+            Row::new()
+                .spacing(6.0)
+                .push(
+                    TextElement::new(if open { "\u{25BC}" } else { "\u{25B6}" })
+                        .color(theme::TEXT_MUTED)
+                        .widget_id(node_id)
+                        .cursor_hint(CursorIcon::Pointer),
+                )
+                .push(TextElement::new(suite.name.clone()).color(theme::TEXT_PRIMARY).widget_id(node_id).cursor_hint(CursorIcon::Pointer))
+                .push(
+                    TextElement::new(format!("{}/{}", suite_counts.passed, suite_counts.passed + suite_counts.failed))
+                        .color(color)
+                        .source(source_id),
+                ),
+        );
+        if !open {
+            continue;
+        }
+        for (j, test) in suite.tests.iter().enumerate() {
+            let (icon, color) = match test.status {
+                TestStatus::Passed => ("\u{2713}", theme::SUCCESS),
+                TestStatus::Failed => ("\u{2717}", theme::ERROR),
+                TestStatus::Skipped => ("\u{25CB}", theme::TEXT_MUTED),
+            };
+            let mut name = TextElement::new(test.name.clone()).color(theme::TEXT_SECONDARY);
+            name = match test.failure {
+                Some(_) => name.widget_id(ids::test_node(block_id, i, Some(j))).cursor_hint(CursorIcon::Pointer),
+                None => name.source(source_id),
+            };
+            let mut row = Row::new()
+                .spacing(6.0)
+                .padding_custom(Padding::new(0.0, 0.0, 0.0, 16.0))
+                .push(TextElement::new(icon).color(color).source(source_id))
+                .push(name);
+            if let Some(ms) = test.duration_ms {
+                row = row.push(TextElement::new(format!("{}ms", ms)).color(theme::TEXT_MUTED).source(source_id));
+            }
+            content = content.push(row);
+
+            if let Some(failure) = &test.failure
+                && tests.open_failures.contains(&(i, j))
+            {
+                let lines: Vec<&str> = failure.lines().collect();
+                let mut output = Column::new().padding_custom(Padding::new(0.0, 0.0, 0.0, 32.0));
+                for line in lines.iter().take(FAILURE_LINES) {
+                    output = output.push(TextElement::new(line.to_string()).color(theme::TEXT_SECONDARY).source(source_id));
+                }
+                if lines.len() > FAILURE_LINES {
+                    output = output.push(
+                        TextElement::new(format!("\u{2026} {} more lines", lines.len() - FAILURE_LINES))
+                            .color(theme::TEXT_MUTED)
+                            .source(source_id),
+                    );
+                }
+                content = content.push(output);
+            }
+        }
+    }
+    content
+}
+
+/// Chunks the timeline view shows, most recent last.
+const TIMELINE_ROWS: usize = 500;
+
+/// Whether the block's output is terminal text with chunks to show as a
+/// timeline.
+fn has_timeline(block: &Block) -> bool {
+    !block.chunks.is_empty()
+        && block.structured_output.is_none()
+        && block.live_value.is_none()
+        && block.event_log.is_empty()
+        && !block.parser.is_alternate_screen()
+}
+
+/// Output chunks in arrival order, one per row: time since the block
+/// started, gap since the previous chunk, stream, size, and the bytes with
+/// control characters made visible.
+fn build_timeline<'a>(mut content: Column<'a>, block: &'a Block) -> Column<'a> {
+    let source_id = ids::shell_term(block.id);
+    let shown = block.chunks.len().min(TIMELINE_ROWS);
+    let skipped = block.chunks_dropped + block.chunks.len() - shown;
+    if skipped > 0 {
+        content = content.push(
+            TextElement::new(format!("\u{2026} {} earlier chunks", skipped))
+                .color(theme::TEXT_MUTED)
+                .source(source_id),
+        );
+    }
+
+    let mut previous: Option<u64> = None;
+    for chunk in block.chunks.iter().skip(block.chunks.len() - shown) {
+        let gap = previous.map_or(String::new(), |at| format!("+{}", chunk.at_ms.saturating_sub(at)));
+        previous = Some(chunk.at_ms);
+        let (label, color) = match chunk.stream {
+            OutputStream::Stdout => ("out", theme::TEXT_PRIMARY),
+            OutputStream::Stderr => ("err", theme::ERROR),
+            OutputStream::Terminal => ("pty", theme::TEXT_SECONDARY),
+        };
+        let mut text = visible_bytes(&chunk.head);
+        if chunk.len > chunk.head.len() {
+            text.push('\u{2026}');
+        }
+        content = content.push(
+            Row::new()
+                .spacing(8.0)
+                .push(TextElement::new(format!("{:>8}ms {:>6}", chunk.at_ms, gap)).color(theme::TEXT_MUTED).source(source_id))
+                .push(TextElement::new(label).color(color).source(source_id))
+                .push(TextElement::new(format!("{:>6}B", chunk.len)).color(theme::TEXT_MUTED).source(source_id))
+                .push(TextElement::new(text).color(color).source(source_id)),
+        );
+    }
+    content
+}
+
+/// `data` as one line of text, with newlines, escapes and other control
+/// characters written out (`\n`, `\e`, `\u{7}`).
+fn visible_bytes(data: &[u8]) -> String {
+    let mut text = String::new();
+    for c in String::from_utf8_lossy(data).chars() {
+        match c {
+            '\x1b' => text.push_str("\\e"),
+            c if c.is_control() => text.extend(c.escape_default()),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
 /// Flush a pending text run into the runs vector.
 fn flush_run(
     runs: &mut Vec<TextRun>,
@@ -261,6 +765,7 @@ fn build_terminal_content<'a>(
     cols: u16,
     content_rows: u16,
     connection_dimmed: bool,
+    note_hover: Option<usize>,
 ) -> Column<'a> {
     let source_id = ids::shell_term(block.id);
     let prediction = &block.prediction;
@@ -297,10 +802,57 @@ fn build_terminal_content<'a>(
     } else {
         None
     };
-    let mut term = TerminalElement::new(source_id, cols, content_rows)
+    let term = TerminalElement::new(source_id, cols, content_rows)
         .cell_size(8.4, 18.0)
         .cursor(cursor_info);
+    let term = push_grid_rows(term, grid, Some(prediction), connection_dimmed);
 
+    if !has_note_gutter(block) {
+        return content.terminal(term);
+    }
+    content.push(Row::new().terminal(build_note_gutter(block, grid, content_rows, note_hover)).terminal(term))
+}
+
+/// The notes gutter: a marker on each row with a note, and a `+` on the
+/// hovered row of a finished block, where a click adds one. With
+/// `[theme] symbols`, rows of red, green or yellow text get a symbol too.
+fn build_note_gutter(block: &Block, grid: &nexus_term::TerminalGrid, content_rows: u16, hover: Option<usize>) -> TerminalElement {
+    let mut gutter = TerminalElement::new(ids::note_gutter(block.id), NOTE_GUTTER_COLS, content_rows).cell_size(8.4, 18.0);
+    let mut rows = grid.rows_iter();
+    let symbols = theme::symbols();
+    for row in 0..content_rows as usize {
+        let cells = rows.next();
+        let mark = match block.note(row) {
+            Some(_) => Some(("\u{25c6}", theme::WARNING)),
+            None if hover == Some(row) && !block.is_running() => Some(("+", theme::TEXT_MUTED)),
+            None if symbols => cells.and_then(theme::row_signal),
+            None => None,
+        };
+        let runs = mark
+            .map(|(text, color)| {
+                vec![TextRun {
+                    text: text.to_string(),
+                    fg: color.pack(),
+                    bg: 0,
+                    col_offset: 0,
+                    cell_len: 1,
+                    style: RunStyle::default(),
+                }]
+            })
+            .unwrap_or_default();
+        gutter = gutter.row(runs);
+    }
+    gutter
+}
+
+/// Add `grid`'s rows to `term` as styled runs, with any pending
+/// `prediction` overlaid. Shared with agent tools' terminals.
+pub(crate) fn push_grid_rows(
+    mut term: TerminalElement,
+    grid: &nexus_term::TerminalGrid,
+    prediction: Option<&PredictionEngine>,
+    connection_dimmed: bool,
+) -> TerminalElement {
     let default_fg_packed = Color::rgb(0.9, 0.9, 0.9).pack();
     let default_bg_packed: u32 = 0;
 
@@ -339,7 +891,7 @@ fn build_terminal_content<'a>(
             let cell_width: u16 = if cell.flags.wide_char { 2 } else { 1 };
 
             // Check for a prediction overlay at this position
-            let predicted = prediction.get(col, row_idx);
+            let predicted = prediction.and_then(|p| p.get(col, row_idx));
 
             let (fg_packed, bg_packed, style, ch_override) = if let Some(pred) = predicted {
                 // Predicted cell: use default fg (dimmed) + dashed underline
@@ -423,7 +975,7 @@ fn build_terminal_content<'a>(
         row_idx += 1;
     }
 
-    content.terminal(term)
+    term
 }
 
 /// Braille spinner characters — 10 frames at ~12.5 FPS via system time.
@@ -508,6 +1060,50 @@ impl ShellBlockWidget<'_> {
         if block.is_running() && id == ids::kill(block.id) {
             return Some(ShellBlockMessage::Kill);
         }
+        if id == ids::timeline_toggle(block.id) {
+            return Some(ShellBlockMessage::ToggleTimeline);
+        }
+        if block.resource_usage.is_some() && id == ids::usage_toggle(block.id) {
+            return Some(ShellBlockMessage::ToggleUsage);
+        }
+        if block.repeat_of.is_some() && id == ids::repeat_toggle(block.id) {
+            return Some(ShellBlockMessage::ToggleRepeat);
+        }
+        if block.restored.is_some() && id == ids::restored_toggle(block.id) {
+            return Some(ShellBlockMessage::ToggleRestored);
+        }
+        if id == ids::problems_next(block.id) {
+            return Some(ShellBlockMessage::NextProblem);
+        }
+        if block.repl.is_some() && id == ids::repl_restart(block.id) {
+            return Some(ShellBlockMessage::RestartRepl);
+        }
+        if let Some(tests) = &block.tests {
+            if id == ids::tests_toggle(block.id) {
+                return Some(ShellBlockMessage::ToggleTestOutput);
+            }
+            if id == ids::tests_rerun(block.id) {
+                return Some(ShellBlockMessage::RerunFailedTests);
+            }
+            for (i, suite) in tests.report.suites.iter().enumerate() {
+                if id == ids::test_node(block.id, i, None) {
+                    return Some(ShellBlockMessage::ToggleTestNode(i, None));
+                }
+                if let Some(j) = (0..suite.tests.len()).find(|&j| id == ids::test_node(block.id, i, Some(j))) {
+                    return Some(ShellBlockMessage::ToggleTestNode(i, Some(j)));
+                }
+            }
+        }
+        if block.debug.is_some() {
+            let actions = [
+                (ids::debug_step(block.id), DebugAction::Step),
+                (ids::debug_continue(block.id), DebugAction::Continue),
+                (ids::debug_abort(block.id), DebugAction::Abort),
+            ];
+            if let Some((_, action)) = actions.into_iter().find(|(button, _)| *button == id) {
+                return Some(ShellBlockMessage::Debug(action));
+            }
+        }
         None
     }
 }
@@ -0,0 +1,63 @@
+//! Password prompt widget — masked entry for a PTY's password prompt.
+
+use strata::content_address::SourceId;
+use strata::layout::{
+    ButtonElement, CrossAxisAlignment, LayoutChild, Length, Padding, Row, TextElement, Widget,
+};
+use strata::primitives::Color;
+
+use crate::ui::theme;
+
+// =========================================================================
+// Password Prompt Bar — never renders the password, only a dot per character
+// =========================================================================
+
+pub struct SudoPromptBar<'a> {
+    /// Prompt text as printed by the program.
+    pub prompt: &'a str,
+    /// What is asked for: "password" or "passphrase".
+    pub noun: &'a str,
+    /// Number of characters typed so far.
+    pub masked_len: usize,
+    pub submit_id: SourceId,
+    pub cancel_id: SourceId,
+}
+
+impl<'a> Widget<'a> for SudoPromptBar<'a> {
+    fn build(self) -> LayoutChild<'a> {
+        let masked = if self.masked_len == 0 {
+            TextElement::new(format!("Type {}, Enter to send, Esc to cancel", self.noun)).color(theme::TEXT_MUTED)
+        } else {
+            TextElement::new("\u{2022}".repeat(self.masked_len)).color(theme::TEXT_PRIMARY)
+        };
+
+        Row::new()
+            .padding_custom(Padding::new(2.0, 4.0, 2.0, 4.0))
+            .width(Length::Fill)
+            .push(
+                Row::new()
+                    .padding_custom(Padding::new(6.0, 10.0, 6.0, 10.0))
+                    .spacing(8.0)
+                    .background(Color::rgb(0.12, 0.1, 0.08))
+                    .corner_radius(6.0)
+                    .border(theme::WARNING, 1.0)
+                    .width(Length::Fill)
+                    .cross_align(CrossAxisAlignment::Center)
+                    .push(TextElement::new("\u{1F512}").color(theme::WARNING))
+                    .push(TextElement::new(self.prompt).color(theme::WARNING))
+                    .push(masked)
+                    .spacer(1.0)
+                    .push(
+                        ButtonElement::new(self.cancel_id, "Cancel")
+                            .background(theme::BTN_DENY)
+                            .corner_radius(4.0),
+                    )
+                    .push(
+                        ButtonElement::new(self.submit_id, "Send")
+                            .background(theme::BTN_ALLOW)
+                            .corner_radius(4.0),
+                    ),
+            )
+            .into()
+    }
+}
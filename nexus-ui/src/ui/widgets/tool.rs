@@ -5,11 +5,14 @@
 
 use similar::{ChangeTag, TextDiff};
 
+use nexus_term::TerminalParser;
+
 use crate::data::agent_block::{ToolInvocation, ToolStatus};
 use crate::ui::theme;
+use super::shell_block::push_grid_rows;
 use crate::utils::text::truncate_str;
 use strata::content_address::SourceId;
-use strata::layout::{Column, CrossAxisAlignment, Length, Padding, Row, TextElement};
+use strata::layout::{Column, CrossAxisAlignment, Length, Padding, Row, TerminalElement, TextElement};
 
 // =========================================================================
 // Message types
@@ -35,13 +38,22 @@ impl ToolWidget {
     /// * `tool` - The tool invocation to render
     /// * `toggle_id` - SourceId for the toggle button (collapse/expand)
     /// * `source_id` - Base SourceId for content
-    pub fn view(tool: &ToolInvocation, toggle_id: SourceId, source_id: SourceId) -> Column<'static> {
+    /// * `term_id` - SourceId for the terminal of a tool that runs shell commands
+    pub fn view(tool: &ToolInvocation, toggle_id: SourceId, source_id: SourceId, term_id: SourceId) -> Column<'static> {
         let (status_icon, status_color) = match tool.status {
             ToolStatus::Pending => ("\u{25CF}", theme::TOOL_PENDING),   // ●
             ToolStatus::Running => ("\u{25CF}", theme::RUNNING),        // ●
             ToolStatus::Success => ("\u{25CF}", theme::SUCCESS),        // ● green
             ToolStatus::Error   => ("\u{25CF}", theme::ERROR),          // ●
         };
+        // With `[theme] symbols` the status shows in the shape, not just the color.
+        let status_icon = match tool.status {
+            _ if !theme::symbols() => status_icon,
+            ToolStatus::Pending => "\u{25CB}",                          // ○
+            ToolStatus::Running => "\u{25D0}",                          // ◐
+            ToolStatus::Success => "\u{2713}",                          // ✓
+            ToolStatus::Error => "\u{2717}",                            // ✗
+        };
 
         let header_label = tool_header_label(tool);
 
@@ -64,7 +76,7 @@ impl ToolWidget {
         if tool.collapsed {
             col = col.push(build_collapsed_preview(tool, source_id));
         } else {
-            col = col.push(build_tool_body(tool, source_id));
+            col = col.push(build_tool_body(tool, source_id, term_id));
         }
 
         col
@@ -241,11 +253,13 @@ fn build_collapsed_preview(tool: &ToolInvocation, source_id: SourceId) -> Column
 // =========================================================================
 
 /// Dispatch to tool-specific body rendering.
-fn build_tool_body(tool: &ToolInvocation, source_id: SourceId) -> Column<'static> {
+fn build_tool_body(tool: &ToolInvocation, source_id: SourceId, term_id: SourceId) -> Column<'static> {
+    if let Some(terminal) = &tool.terminal {
+        return build_terminal_body(tool, terminal, source_id, term_id);
+    }
     match tool.name.as_str() {
         "Edit" => build_edit_body(tool, source_id),
         "Read" => build_read_body(tool, source_id),
-        "Bash" => build_bash_body(tool, source_id),
         "Grep" | "Glob" => build_search_body(tool, source_id),
         "Write" => build_write_body(tool, source_id),
         "Task" => build_task_body(tool, source_id),
@@ -329,8 +343,14 @@ fn build_read_body(tool: &ToolInvocation, source_id: SourceId) -> Column<'static
     col
 }
 
-/// Bash tool: output in a code block with optional timeout display.
-fn build_bash_body(tool: &ToolInvocation, source_id: SourceId) -> Column<'static> {
+/// Shell-running tools: the output in a mini terminal, rendered and
+/// selected like a shell block's.
+fn build_terminal_body(
+    tool: &ToolInvocation,
+    terminal: &TerminalParser,
+    source_id: SourceId,
+    term_id: SourceId,
+) -> Column<'static> {
     let mut col = Column::new().spacing(1.0);
 
     if let Some(timeout) = tool.parameters.get("timeout") {
@@ -341,37 +361,18 @@ fn build_bash_body(tool: &ToolInvocation, source_id: SourceId) -> Column<'static
         );
     }
 
-    if let Some(ref output) = tool.output {
-        let lines: Vec<&str> = output.lines().collect();
-        let max_lines = 30;
-
-        // First line gets tree prefix, rest are just indented (matching Claude Code)
-        for (i, line) in lines.iter().take(max_lines).enumerate() {
-            if i == 0 {
-                col = col.push(
-                    Row::new()
-                        .fixed_spacer(16.0)
-                        .spacing(4.0)
-                        .push(TextElement::new("└").color(theme::TEXT_MUTED))
-                        .push(TextElement::new(*line).color(theme::TOOL_OUTPUT).source(source_id)),
-                );
-            } else {
-                col = col.push(
-                    Row::new()
-                        .fixed_spacer(28.0)
-                        .push(TextElement::new(*line).color(theme::TOOL_OUTPUT).source(source_id)),
-                );
-            }
-        }
-
-        // Show remaining lines indicator
-        if lines.len() > max_lines {
-            col = col.push(
-                Row::new()
-                    .fixed_spacer(16.0)
-                    .push(TextElement::new(format!("… ({} more lines)", lines.len() - max_lines)).color(theme::TEXT_MUTED).source(source_id)),
-            );
-        }
+    let grid = terminal.grid_with_scrollback();
+    let rows = grid.content_rows();
+    if rows > 0 {
+        let term = TerminalElement::new(term_id, grid.cols(), rows).cell_size(8.4, 18.0);
+        col = col.push(
+            Column::new()
+                .padding_custom(Padding::new(4.0, 8.0, 4.0, 8.0))
+                .background(theme::TOOL_ARTIFACT_BG)
+                .corner_radius(4.0)
+                .width(Length::Fill)
+                .terminal(push_grid_rows(term, &grid, None, false)),
+        );
     }
     col
 }
@@ -543,6 +544,7 @@ mod tests {
             status: ToolStatus::Success,
             message: None,
             collapsed: false,
+            terminal: None,
         }
     }
 
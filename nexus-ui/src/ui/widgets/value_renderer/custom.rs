@@ -0,0 +1,270 @@
+//! Custom renderers for particular value shapes or commands.
+//!
+//! Renderers come from `~/.nexus/renderers.json` (a list of
+//! `RendererSpec`s) and from the `renderers` section of enabled plugins'
+//! manifests. Code can also register a [`CustomRenderer`] directly. When a
+//! value is rendered, the highest-priority renderer whose matcher accepts
+//! it wins; user configuration beats plugins at equal priority. Values no
+//! renderer accepts get the default rendering.
+
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use nexus_api::Value;
+use nexus_kernel::plugins::manifest::{RenderStyle, RendererSpec, ValueShape};
+use strata::content_address::SourceId;
+use strata::layout::{Column, Row, TextElement};
+
+use crate::ui::theme;
+
+static REGISTRY: LazyLock<RwLock<RendererRegistry>> = LazyLock::new(Default::default);
+
+/// Renders a value the default renderer would otherwise handle.
+pub(crate) trait CustomRenderer: Send + Sync {
+    fn render<'a>(&self, parent: Column<'a>, value: &Value, source: SourceId) -> Column<'a>;
+}
+
+/// Which values a renderer applies to.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Matcher {
+    pub command: Option<String>,
+    pub shape: Option<ValueShape>,
+    pub kind: Option<String>,
+    pub fields: Vec<String>,
+}
+
+impl Matcher {
+    fn matches(&self, command: &str, value: &Value) -> bool {
+        if self.command.as_deref().is_some_and(|c| c != command) {
+            return false;
+        }
+        if let Some(shape) = self.shape
+            && shape_of(value) != Some(shape)
+        {
+            return false;
+        }
+        if let Some(kind) = &self.kind
+            && !matches!(value, Value::Structured { kind: Some(k), .. } if k == kind)
+        {
+            return false;
+        }
+        self.fields.iter().all(|f| field(value, f).is_some())
+    }
+}
+
+struct Entry {
+    priority: i32,
+    matcher: Matcher,
+    renderer: Arc<dyn CustomRenderer>,
+}
+
+/// Registered renderers, highest priority first.
+#[derive(Default)]
+pub(crate) struct RendererRegistry {
+    entries: Vec<Entry>,
+}
+
+impl RendererRegistry {
+    /// Add a renderer. Among equal priorities, earlier registrations win.
+    pub fn register(&mut self, priority: i32, matcher: Matcher, renderer: Arc<dyn CustomRenderer>) {
+        let at = self.entries.partition_point(|e| e.priority >= priority);
+        self.entries.insert(at, Entry { priority, matcher, renderer });
+    }
+
+    /// Add a renderer described by a spec.
+    pub fn register_spec(&mut self, spec: RendererSpec) {
+        let matcher = Matcher {
+            command: spec.command.clone(),
+            shape: spec.shape,
+            kind: spec.kind.clone(),
+            fields: spec.fields.clone(),
+        };
+        self.register(spec.priority, matcher, Arc::new(SpecRenderer(spec)));
+    }
+
+    /// The renderer for `value`, output of `command`, if any.
+    pub fn find(&self, command: &str, value: &Value) -> Option<Arc<dyn CustomRenderer>> {
+        self.entries.iter().find(|e| e.matcher.matches(command, value)).map(|e| e.renderer.clone())
+    }
+
+    /// Renderers from the user's configuration, then from `plugins`.
+    pub fn load(plugins: impl IntoIterator<Item = RendererSpec>) -> Self {
+        let mut registry = Self::default();
+        if let Some(path) = config_path() {
+            match std::fs::read(&path) {
+                Ok(bytes) => match serde_json::from_slice::<Vec<RendererSpec>>(&bytes) {
+                    Ok(specs) => specs.into_iter().for_each(|s| registry.register_spec(s)),
+                    Err(e) => tracing::warn!("renderers: invalid {}: {}", path.display(), e),
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("renderers: failed to read {}: {}", path.display(), e),
+            }
+        }
+        plugins.into_iter().for_each(|s| registry.register_spec(s));
+        registry
+    }
+}
+
+/// Replace the process-wide registry.
+pub(crate) fn install(registry: RendererRegistry) {
+    *REGISTRY.write().unwrap() = registry;
+}
+
+/// The registered renderer for `value`, if any.
+pub(crate) fn find(command: &str, value: &Value) -> Option<Arc<dyn CustomRenderer>> {
+    REGISTRY.read().unwrap().find(command, value)
+}
+
+fn config_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".nexus").join("renderers.json"))
+}
+
+fn shape_of(value: &Value) -> Option<ValueShape> {
+    Some(match value {
+        Value::Record(_) => ValueShape::Record,
+        Value::Structured { .. } => ValueShape::Structured,
+        Value::List(_) => ValueShape::List,
+        Value::Table { .. } => ValueShape::Table,
+        Value::String(_) => ValueShape::String,
+        Value::Int(_) | Value::Float(_) => ValueShape::Number,
+        _ => return None,
+    })
+}
+
+fn field<'v>(value: &'v Value, name: &str) -> Option<&'v Value> {
+    match value {
+        Value::Record(fields) => fields.iter().find(|(k, _)| k == name).map(|(_, v)| v),
+        Value::Structured { data, .. } => data.get(name),
+        _ => None,
+    }
+}
+
+fn fields(value: &Value) -> Vec<(&str, &Value)> {
+    match value {
+        Value::Record(fields) => fields.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+        Value::Structured { data, .. } => data.iter().map(|(k, v)| (k.as_str(), v)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Fill `{field}` placeholders; unknown fields render empty.
+fn fill_template(template: &str, value: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}') else {
+            rest = &rest[open..];
+            break;
+        };
+        let name = &rest[open + 1..open + close];
+        if name == "value" {
+            out.push_str(&value.to_text());
+        } else if let Some(v) = field(value, name) {
+            out.push_str(&v.to_text());
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+struct SpecRenderer(RendererSpec);
+
+impl CustomRenderer for SpecRenderer {
+    fn render<'a>(&self, parent: Column<'a>, value: &Value, source: SourceId) -> Column<'a> {
+        let spec = &self.0;
+        match spec.style {
+            RenderStyle::Template => {
+                let text = fill_template(spec.template.as_deref().unwrap_or("{value}"), value);
+                text.lines().fold(parent, |parent, line| {
+                    parent.push(TextElement::new(line).color(theme::TEXT_PRIMARY).source(source))
+                })
+            }
+            RenderStyle::Card => {
+                let mut card = Column::new()
+                    .padding(10.0)
+                    .spacing(4.0)
+                    .background(theme::CARD_BG)
+                    .border(theme::CARD_BORDER, 1.0)
+                    .corner_radius(6.0);
+                if let Some(title) = spec.title.as_deref().and_then(|f| field(value, f)) {
+                    card = card.push(TextElement::new(title.to_text()).color(theme::WELCOME_HEADING).source(source));
+                }
+                if let Some(subtitle) = spec.subtitle.as_deref().and_then(|f| field(value, f)) {
+                    card = card.push(TextElement::new(subtitle.to_text()).color(theme::TEXT_SECONDARY).source(source));
+                }
+                let body: Vec<(&str, &Value)> = if spec.show.is_empty() {
+                    let shown = [spec.title.as_deref(), spec.subtitle.as_deref()];
+                    fields(value).into_iter().filter(|(k, _)| !shown.contains(&Some(*k))).collect()
+                } else {
+                    spec.show.iter().filter_map(|k| field(value, k).map(|v| (k.as_str(), v))).collect()
+                };
+                for (key, val) in body {
+                    card = card.push(
+                        Row::new()
+                            .spacing(8.0)
+                            .push(TextElement::new(format!("{}:", key)).color(theme::TEXT_MUTED).source(source))
+                            .push(TextElement::new(val.to_text()).color(theme::TEXT_PRIMARY).source(source)),
+                    );
+                }
+                parent.push(card)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather() -> Value {
+        Value::Record(vec![
+            ("city".into(), Value::String("Oslo".into())),
+            ("temp".into(), Value::Int(4)),
+        ])
+    }
+
+    fn spec(json: &str) -> RendererSpec {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_matcher_checks_command_shape_and_fields() {
+        let mut registry = RendererRegistry::default();
+        registry.register_spec(spec(r#"{"command": "weather", "shape": "record", "fields": ["temp"], "style": "card"}"#));
+
+        assert!(registry.find("weather", &weather()).is_some());
+        assert!(registry.find("curl", &weather()).is_none());
+        assert!(registry.find("weather", &Value::String("x".into())).is_none());
+        assert!(registry.find("weather", &Value::Record(vec![])).is_none());
+    }
+
+    #[test]
+    fn test_higher_priority_wins_then_registration_order() {
+        struct Fixed(&'static str);
+        impl CustomRenderer for Fixed {
+            fn render<'a>(&self, parent: Column<'a>, _: &Value, _: SourceId) -> Column<'a> {
+                parent.push(TextElement::new(self.0))
+            }
+        }
+
+        let mut registry = RendererRegistry::default();
+        registry.register(0, Matcher::default(), Arc::new(Fixed("first")));
+        registry.register(0, Matcher::default(), Arc::new(Fixed("second")));
+        registry.register(5, Matcher { shape: Some(ValueShape::Record), ..Default::default() }, Arc::new(Fixed("high")));
+
+        let found = registry.find("any", &weather()).unwrap();
+        assert!(Arc::ptr_eq(&found, &registry.entries[0].renderer));
+        assert_eq!(registry.entries[0].priority, 5);
+        let plain = registry.find("any", &Value::Int(1)).unwrap();
+        assert!(Arc::ptr_eq(&plain, &registry.entries[1].renderer));
+    }
+
+    #[test]
+    fn test_fill_template() {
+        assert_eq!(fill_template("{city}: {temp}\u{00B0} {missing}", &weather()), "Oslo: 4\u{00B0} ");
+        assert_eq!(fill_template("n={value}", &Value::Int(3)), "n=3");
+        assert_eq!(fill_template("open {brace", &weather()), "open {brace");
+    }
+}
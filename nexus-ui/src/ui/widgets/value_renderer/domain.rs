@@ -199,6 +199,8 @@ fn render_file_op<'a>(
         nexus_api::FileOpKind::Remove => "Remove",
         nexus_api::FileOpKind::Chmod => "Chmod",
         nexus_api::FileOpKind::Chown => "Chown",
+        nexus_api::FileOpKind::Upload => "Upload",
+        nexus_api::FileOpKind::Download => "Download",
     };
     parent = parent.push(
         TextElement::new(format!("{} {} {:?}", icon, op_label, info.phase))
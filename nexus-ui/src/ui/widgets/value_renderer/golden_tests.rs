@@ -0,0 +1,174 @@
+//! Golden snapshots of the value renderer.
+//!
+//! Each case renders a representative `Value` offscreen at a fixed width and
+//! compares the serialized primitives against `tests/golden/values/<name>.txt`.
+//! A renderer change that moves, recolors or drops anything shows up as a
+//! diff. Accept intended changes with `STRATA_UPDATE_GOLDENS=1 cargo test`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use nexus_api::{
+    BlockId, DiffFileInfo, DiffHunk, DiffLine, DiffLineKind, DisplayFormat, DomainValue, FileOpError,
+    FileOpInfo, FileOpKind, FileOpPhase, GitChangeType, HttpResponseInfo, HttpTiming, TableColumn, Value,
+};
+use strata::golden::{assert_golden, serialize};
+use strata::layout::{Column, LayoutConstraints, LayoutContext};
+use strata::layout_snapshot::LayoutSnapshot;
+use strata::primitives::Point;
+
+use super::{TableLayoutCache, render_native_value};
+use crate::data::Block;
+
+const WIDTH: f32 = 800.0;
+const HEIGHT: f32 = 2000.0;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/values").join(format!("{name}.txt"))
+}
+
+fn check(name: &str, value: Value) {
+    let block = Block::new(BlockId(1), name.to_string());
+    let click_registry = RefCell::new(HashMap::new());
+    let table_layout_cache = TableLayoutCache::default();
+    let table_cell_images = HashMap::new();
+
+    let column = render_native_value(
+        Column::new(),
+        &value,
+        &block,
+        None,
+        &click_registry,
+        &table_layout_cache,
+        &table_cell_images,
+    );
+
+    let mut snapshot = LayoutSnapshot::new();
+    {
+        let mut ctx = LayoutContext::new(&mut snapshot);
+        column.layout_with_constraints(&mut ctx, LayoutConstraints::loose(WIDTH, HEIGHT), Point::ORIGIN);
+    }
+    assert_golden(golden_path(name), &serialize(&snapshot));
+}
+
+fn domain(value: DomainValue) -> Value {
+    Value::Domain(Box::new(value))
+}
+
+#[test]
+fn golden_table() {
+    check(
+        "table",
+        Value::Table {
+            columns: vec![
+                TableColumn::new("name"),
+                TableColumn::with_format("size", DisplayFormat::HumanBytes),
+                TableColumn::with_format("cpu", DisplayFormat::Percentage),
+                TableColumn::new("ok"),
+            ],
+            rows: vec![
+                vec![Value::String("alpha".into()), Value::Int(1024), Value::Float(12.5), Value::Bool(true)],
+                vec![Value::String("beta".into()), Value::Int(3 * 1024 * 1024), Value::Float(0.0), Value::Bool(false)],
+                vec![Value::String("gamma".into()), Value::Int(0), Value::Float(99.9), Value::Bool(true)],
+            ],
+        },
+    );
+}
+
+#[test]
+fn golden_record() {
+    check(
+        "record",
+        Value::Record(vec![
+            ("name".into(), Value::String("nexus".into())),
+            ("version".into(), Value::Int(2)),
+            ("encrypted".into(), Value::Bool(false)),
+        ]),
+    );
+}
+
+#[test]
+fn golden_list() {
+    check("list", Value::List(vec![Value::String("one".into()), Value::Int(2), Value::String("three".into())]));
+}
+
+#[test]
+fn golden_error() {
+    check("error", Value::Error { code: 1, message: "cat: missing.txt: No such file or directory".into() });
+}
+
+#[test]
+fn golden_diff() {
+    let line = |kind, content: &str, old, new| DiffLine { kind, content: content.into(), old_lineno: old, new_lineno: new };
+    check(
+        "diff",
+        domain(DomainValue::DiffFile(DiffFileInfo {
+            file_path: "src/main.rs".into(),
+            old_path: None,
+            change_type: GitChangeType::Modified,
+            hunks: vec![DiffHunk {
+                header: "@@ -1,3 +1,3 @@".into(),
+                old_start: 1,
+                old_count: 3,
+                new_start: 1,
+                new_count: 3,
+                lines: vec![
+                    line(DiffLineKind::Context, "fn main() {", Some(1), Some(1)),
+                    line(DiffLineKind::Deletion, "    println!(\"hello\");", Some(2), None),
+                    line(DiffLineKind::Addition, "    println!(\"hello, world\");", None, Some(2)),
+                    line(DiffLineKind::Context, "}", Some(3), Some(3)),
+                ],
+            }],
+            additions: 1,
+            deletions: 1,
+        })),
+    );
+}
+
+#[test]
+fn golden_file_op() {
+    check(
+        "file_op",
+        domain(DomainValue::FileOp(FileOpInfo {
+            op_type: FileOpKind::Copy,
+            phase: FileOpPhase::Failed,
+            sources: vec!["a.txt".into(), "b.txt".into()],
+            dest: Some("backup/".into()),
+            total_bytes: Some(4096),
+            bytes_processed: 2048,
+            files_total: Some(2),
+            files_processed: 1,
+            current_file: None,
+            // Zero keeps elapsed/ETA text out of the snapshot.
+            start_time_ms: 0,
+            errors: vec![FileOpError { path: "b.txt".into(), message: "Permission denied".into() }],
+        })),
+    );
+}
+
+#[test]
+fn golden_http_response() {
+    check(
+        "http_response",
+        domain(DomainValue::HttpResponse(HttpResponseInfo {
+            url: "https://example.com/api".into(),
+            method: "GET".into(),
+            status_code: 200,
+            status_text: "OK".into(),
+            headers: vec![("content-type".into(), "application/json".into())],
+            body_preview: Some("{\"ok\": true}".into()),
+            body_len: 12,
+            body_truncated: false,
+            content_type: Some("application/json".into()),
+            timing: HttpTiming {
+                total_ms: 120.0,
+                dns_ms: Some(10.0),
+                connect_ms: Some(20.0),
+                tls_ms: Some(30.0),
+                ttfb_ms: Some(50.0),
+                transfer_ms: Some(10.0),
+            },
+        })),
+    );
+}
@@ -6,11 +6,18 @@
 //! - File trees with expand/collapse
 //! - Diffs with syntax highlighting
 //! - Images, HTTP responses, DNS records, etc.
+//! - Renderers registered by plugins and user configuration (`custom`)
+//!
+//! Rendered structure is pinned by golden snapshots (`golden_tests`).
 
 mod color;
+pub(crate) mod custom;
 mod domain;
 mod table;
 
+#[cfg(test)]
+mod golden_tests;
+
 pub(crate) use color::term_color_to_strata;
 
 use std::cell::RefCell;
@@ -61,6 +68,10 @@ pub(crate) fn render_native_value<'a>(
     table_cell_images: &HashMap<(nexus_api::BlockId, usize, usize), (ImageHandle, u32, u32)>,
 ) -> Column<'a> {
     let block_id = block.id;
+    let command = block.command.split_whitespace().next().unwrap_or("");
+    if let Some(renderer) = custom::find(command, value) {
+        return renderer.render(parent, value, ids::native(block_id));
+    }
     match value {
         Value::Unit => parent,
 
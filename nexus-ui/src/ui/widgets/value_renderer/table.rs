@@ -81,7 +81,7 @@ pub(super) fn render_table<'a>(
                 cell.to_text()
             };
             let line_len = text.lines()
-                .map(|l| unicode_width::UnicodeWidthStr::width(l))
+                .map(nexus_term::width::str_width)
                 .max().unwrap_or(0);
             if line_len > max_col_lens[col_idx] {
                 max_col_lens[col_idx] = line_len;
@@ -90,7 +90,7 @@ pub(super) fn render_table<'a>(
     }
 
     let col_widths: Vec<f32> = columns.iter().enumerate().map(|(i, col)| {
-        let header_width = unicode_width::UnicodeWidthStr::width(col.name.as_str());
+        let header_width = nexus_term::width::str_width(&col.name);
         let max_len = header_width.max(max_col_lens[i]).max(4);
         (max_len as f32 * TABLE_CHAR_W + TABLE_CELL_PADDING).min(TABLE_MAX_COL_W)
     }).collect();
@@ -1,5 +1,7 @@
-//! Welcome screen widget — shown when no blocks exist.
+//! Welcome screen widget — shown when no blocks exist, with a summary of
+//! the last session when there was one.
 
+use nexus_kernel::titles::SessionSummary;
 use strata::layout::{Column, LayoutChild, Length, Row, TextElement, Widget};
 
 use crate::ui::theme;
@@ -11,13 +13,14 @@ use crate::utils::text::display_path;
 
 pub struct WelcomeScreen<'a> {
     pub cwd: &'a str,
+    pub previous: Option<&'a SessionSummary>,
 }
 
 impl<'a> Widget<'a> for WelcomeScreen<'a> {
     fn build(self) -> LayoutChild<'a> {
         let display_cwd = display_path(self.cwd);
 
-        let left = Column::new()
+        let mut left = Column::new()
             .spacing(4.0)
             .width(Length::Fill)
             .push(TextElement::new("NEXUS").color(theme::WELCOME_TITLE).size(48.0))
@@ -31,6 +34,27 @@ impl<'a> Widget<'a> for WelcomeScreen<'a> {
             .fixed_spacer(4.0)
             .push(TextElement::new(format!("  {}", display_cwd)).color(theme::TEXT_PATH));
 
+        // Last session card: what ran, and what went wrong
+        if let Some(previous) = self.previous {
+            let mut last = Column::new()
+                .padding(8.0)
+                .spacing(2.0)
+                .background(theme::CARD_BG)
+                .corner_radius(4.0)
+                .border(theme::CARD_BORDER, 1.0)
+                .width(Length::Fill)
+                .push(TextElement::new("Last Session").color(theme::WELCOME_HEADING))
+                .push(
+                    TextElement::new(format!("{} in {}", previous.headline(), display_path(&previous.cwd)))
+                        .color(theme::TEXT_MUTED),
+                )
+                .fixed_spacer(8.0);
+            for title in &previous.highlights {
+                last = last.push(TextElement::new(format!("\u{2022} {}", title)).color(theme::TEXT_SECONDARY));
+            }
+            left = left.fixed_spacer(12.0).push(last);
+        }
+
         // Tips card
         let tips = Column::new()
             .padding(8.0)
@@ -37,6 +37,32 @@ const AGENT_FOOTER: u64 = 21;
 const VIEWER_EXIT: u64 = 22;
 const TREE_EXPAND: u64 = 23;
 const BLOCK_CONTAINER: u64 = 24;
+const SUDO_SUBMIT: u64 = 25;
+const SUDO_CANCEL: u64 = 26;
+const DEBUG_STEP: u64 = 27;
+const DEBUG_CONTINUE: u64 = 28;
+const DEBUG_ABORT: u64 = 29;
+const TIMELINE_TOGGLE: u64 = 30;
+const AGENT_TOOL_TERM: u64 = 31;
+const PLAN_TOGGLE: u64 = 32;
+const PLAN_COPY: u64 = 33;
+const AGENT_PLAN: u64 = 34;
+const AGENT_CITATION: u64 = 35;
+const PROBLEMS_NEXT: u64 = 36;
+const TESTS_TOGGLE: u64 = 37;
+const TESTS_RERUN: u64 = 38;
+const TEST_NODE: u64 = 39;
+const REPL_RESTART: u64 = 40;
+const GROUP_TOGGLE: u64 = 41;
+const GROUP_UNGROUP: u64 = 42;
+const USAGE_TOGGLE: u64 = 43;
+const REPEAT_TOGGLE: u64 = 44;
+const RESTORED_TOGGLE: u64 = 45;
+const AGENT_SETUP: u64 = 46;
+const SETUP_COPY: u64 = 47;
+const SETUP_CHECK: u64 = 48;
+const SETUP_RERUN: u64 = 49;
+const NOTE_GUTTER: u64 = 50;
 
 // --- Shell block IDs ---
 
@@ -51,6 +77,24 @@ pub fn table(id: BlockId) -> SourceId { block_space(id).id(TABLE) }
 pub fn kill(id: BlockId) -> SourceId { block_space(id).id(KILL) }
 pub fn image_output(id: BlockId) -> SourceId { block_space(id).id(IMAGE_OUTPUT) }
 pub fn viewer_exit(id: BlockId) -> SourceId { block_space(id).id(VIEWER_EXIT) }
+pub fn sudo_submit(id: BlockId) -> SourceId { block_space(id).id(SUDO_SUBMIT) }
+pub fn sudo_cancel(id: BlockId) -> SourceId { block_space(id).id(SUDO_CANCEL) }
+pub fn debug_step(id: BlockId) -> SourceId { block_space(id).id(DEBUG_STEP) }
+pub fn debug_continue(id: BlockId) -> SourceId { block_space(id).id(DEBUG_CONTINUE) }
+pub fn debug_abort(id: BlockId) -> SourceId { block_space(id).id(DEBUG_ABORT) }
+pub fn timeline_toggle(id: BlockId) -> SourceId { block_space(id).id(TIMELINE_TOGGLE) }
+pub fn problems_next(id: BlockId) -> SourceId { block_space(id).id(PROBLEMS_NEXT) }
+pub fn tests_toggle(id: BlockId) -> SourceId { block_space(id).id(TESTS_TOGGLE) }
+pub fn tests_rerun(id: BlockId) -> SourceId { block_space(id).id(TESTS_RERUN) }
+pub fn repl_restart(id: BlockId) -> SourceId { block_space(id).id(REPL_RESTART) }
+pub fn usage_toggle(id: BlockId) -> SourceId { block_space(id).id(USAGE_TOGGLE) }
+pub fn repeat_toggle(id: BlockId) -> SourceId { block_space(id).id(REPEAT_TOGGLE) }
+pub fn restored_toggle(id: BlockId) -> SourceId { block_space(id).id(RESTORED_TOGGLE) }
+/// The column left of a finished block's output where its notes show.
+pub fn note_gutter(id: BlockId) -> SourceId { block_space(id).id(NOTE_GUTTER) }
+/// A block group's header, keyed by the group's first block.
+pub fn group_toggle(id: BlockId) -> SourceId { block_space(id).id(GROUP_TOGGLE) }
+pub fn group_ungroup(id: BlockId) -> SourceId { block_space(id).id(GROUP_UNGROUP) }
 
 // --- Agent block IDs ---
 
@@ -62,6 +106,13 @@ pub fn agent_stop(id: BlockId) -> SourceId { block_space(id).id(STOP) }
 pub fn agent_perm_deny(id: BlockId) -> SourceId { block_space(id).id(PERM_DENY) }
 pub fn agent_perm_allow(id: BlockId) -> SourceId { block_space(id).id(PERM_ALLOW) }
 pub fn agent_perm_always(id: BlockId) -> SourceId { block_space(id).id(PERM_ALWAYS) }
+pub fn agent_plan(id: BlockId) -> SourceId { block_space(id).id(AGENT_PLAN) }
+pub fn agent_plan_toggle(id: BlockId) -> SourceId { block_space(id).id(PLAN_TOGGLE) }
+pub fn agent_plan_copy(id: BlockId) -> SourceId { block_space(id).id(PLAN_COPY) }
+pub fn agent_setup(id: BlockId) -> SourceId { block_space(id).id(AGENT_SETUP) }
+pub fn agent_setup_copy(id: BlockId) -> SourceId { block_space(id).id(SETUP_COPY) }
+pub fn agent_setup_check(id: BlockId) -> SourceId { block_space(id).id(SETUP_CHECK) }
+pub fn agent_setup_rerun(id: BlockId) -> SourceId { block_space(id).id(SETUP_RERUN) }
 
 // --- Indexed IDs (block + index dimension) ---
 
@@ -96,6 +147,25 @@ pub fn agent_tool(id: BlockId, i: usize) -> SourceId {
     block_space(id).child(AGENT_TOOL).id(i as u64)
 }
 
+/// The terminal a shell-running tool's output streams into.
+pub fn agent_tool_term(id: BlockId, i: usize) -> SourceId {
+    block_space(id).child(AGENT_TOOL_TERM).id(i as u64)
+}
+
+/// A suite row of a test run's tree, or with `test`, a test row in it.
+pub fn test_node(id: BlockId, suite: usize, test: Option<usize>) -> SourceId {
+    let space = block_space(id).child(TEST_NODE).child(suite as u64);
+    match test {
+        Some(test) => space.id(test as u64 + 1),
+        None => space.id(0),
+    }
+}
+
+/// A citation button in a response, by [`Citation::key`](crate::features::agent::citations::Citation::key).
+pub fn agent_citation(id: BlockId, key: u64) -> SourceId {
+    block_space(id).child(AGENT_CITATION).id(key)
+}
+
 pub fn agent_perm_text(id: BlockId) -> SourceId { block_space(id).id(AGENT_PERM_TEXT) }
 pub fn agent_question_text(id: BlockId) -> SourceId { block_space(id).id(AGENT_QUESTION_TEXT) }
 pub fn agent_footer(id: BlockId) -> SourceId { block_space(id).id(AGENT_FOOTER) }
@@ -111,6 +181,54 @@ pub fn completion_item(i: usize) -> SourceId { GLOBAL.child(4).id(i as u64) }
 pub fn history_result(i: usize) -> SourceId { GLOBAL.child(5).id(i as u64) }
 pub fn job_pill(job_id: u32) -> SourceId { GLOBAL.child(6).id(job_id as u64) }
 pub fn breadcrumb_segment(depth: usize) -> SourceId { GLOBAL.child(7).id(depth as u64) }
+pub fn power_mode() -> SourceId { GLOBAL.id(8) }
+pub fn settings_close() -> SourceId { GLOBAL.id(9) }
+/// Option `choice` of row `row` in the settings view.
+pub fn settings_choice(row: usize, choice: usize) -> SourceId {
+    GLOBAL.child(10).child(row as u64).id(choice as u64)
+}
+pub fn onboarding_import_settings() -> SourceId { GLOBAL.id(11) }
+pub fn onboarding_import_history() -> SourceId { GLOBAL.id(12) }
+pub fn onboarding_done() -> SourceId { GLOBAL.id(13) }
+pub fn update_pill() -> SourceId { GLOBAL.id(14) }
+pub fn update_close() -> SourceId { GLOBAL.id(15) }
+pub fn update_download() -> SourceId { GLOBAL.id(16) }
+pub fn update_install() -> SourceId { GLOBAL.id(17) }
+pub fn update_dismiss() -> SourceId { GLOBAL.id(18) }
+/// Markdown content of the release notes.
+pub fn update_notes() -> SourceId { GLOBAL.id(19) }
+pub fn crash_review() -> SourceId { GLOBAL.id(20) }
+pub fn crash_submit() -> SourceId { GLOBAL.id(21) }
+pub fn crash_copy() -> SourceId { GLOBAL.id(22) }
+pub fn crash_dismiss() -> SourceId { GLOBAL.id(23) }
+pub fn insights_close() -> SourceId { GLOBAL.id(24) }
+pub fn insights_clear() -> SourceId { GLOBAL.id(25) }
+/// Actions on the chip of context file `i`.
+pub fn context_file_open(i: usize) -> SourceId { GLOBAL.child(26).id(i as u64) }
+pub fn context_file_diff(i: usize) -> SourceId { GLOBAL.child(27).id(i as u64) }
+pub fn context_file_remove(i: usize) -> SourceId { GLOBAL.child(28).id(i as u64) }
+pub fn project_instructions() -> SourceId { GLOBAL.id(29) }
+pub fn agent_tasks_toggle() -> SourceId { GLOBAL.id(30) }
+pub fn agent_tasks_clear() -> SourceId { GLOBAL.id(31) }
+pub fn agent_task_toggle(id: u64) -> SourceId { GLOBAL.child(32).id(id) }
+pub fn agent_task_cancel(id: u64) -> SourceId { GLOBAL.child(33).id(id) }
+pub fn agent_task_output(id: u64) -> SourceId { GLOBAL.child(34).id(id) }
+pub fn bulk_copy() -> SourceId { GLOBAL.id(35) }
+pub fn bulk_notebook() -> SourceId { GLOBAL.id(36) }
+pub fn bulk_script() -> SourceId { GLOBAL.id(37) }
+pub fn bulk_send_to_agent() -> SourceId { GLOBAL.id(38) }
+pub fn bulk_group() -> SourceId { GLOBAL.id(39) }
+pub fn bulk_remove() -> SourceId { GLOBAL.id(40) }
+pub fn bulk_clear() -> SourceId { GLOBAL.id(41) }
+pub fn hide_undo() -> SourceId { GLOBAL.id(42) }
+pub fn macro_pause() -> SourceId { GLOBAL.id(43) }
+pub fn macro_stop() -> SourceId { GLOBAL.id(44) }
+pub fn macro_continue() -> SourceId { GLOBAL.id(45) }
+pub fn macro_discard() -> SourceId { GLOBAL.id(46) }
+/// Dismiss button of provider warning `i` above the input.
+pub fn warning_dismiss(i: usize) -> SourceId { GLOBAL.child(47).id(i as u64) }
+/// Project environment `i` in the prompt.
+pub fn environment_toggle(i: usize) -> SourceId { GLOBAL.child(48).id(i as u64) }
 
 #[cfg(test)]
 mod tests {
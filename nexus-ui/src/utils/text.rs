@@ -8,6 +8,13 @@ pub fn home_dir() -> &'static str {
     HOME.get_or_init(|| std::env::var("HOME").unwrap_or_default())
 }
 
+/// Where exports are written: Downloads, or home without one.
+pub fn export_dir() -> std::path::PathBuf {
+    let home = std::path::PathBuf::from(home_dir());
+    let downloads = home.join("Downloads");
+    if downloads.is_dir() { downloads } else { home }
+}
+
 /// Replace the home directory prefix with `~` for display.
 pub fn display_path(path: &str) -> String {
     let home = home_dir();
@@ -35,6 +42,26 @@ pub fn truncate_str(s: &str, max_chars: usize) -> String {
     }
 }
 
+/// Break `s` into lines of at most `width` characters at spaces; a word
+/// longer than that gets a line of its own.
+pub fn wrap_words(s: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    for word in s.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,4 +103,11 @@ mod tests {
     fn zero_max() {
         assert_eq!(truncate_str("hello", 0), "\u{2026}");
     }
+
+    #[test]
+    fn wraps_at_spaces() {
+        assert_eq!(wrap_words("fails  only on the second run", 12), ["fails only", "on the", "second run"]);
+        assert_eq!(wrap_words("see target/debug/build.log", 8), ["see", "target/debug/build.log"]);
+        assert!(wrap_words("  ", 8).is_empty());
+    }
 }
@@ -472,6 +472,13 @@ pub trait StrataApp: Sized + 'static {
     fn on_tick(_state: &mut Self::State) -> (bool, Command<Self::Message>) {
         (false, Command::none())
     }
+
+    /// Upper bound on the tick and render rate, e.g. in a low-power mode.
+    /// `None` runs at the display refresh rate. Key events still render
+    /// immediately; only `on_tick` and output-driven frames are throttled.
+    fn frame_rate_cap(_state: &Self::State) -> Option<u32> {
+        None
+    }
 }
 
 /// Request to start an OS-level outbound drag.
@@ -172,6 +172,10 @@ where
         state.on_tick()
     }
 
+    fn frame_rate_cap(state: &C) -> Option<u32> {
+        state.frame_rate_cap()
+    }
+
     fn window_tag(state: &C) -> isize {
         state.window_tag()
     }
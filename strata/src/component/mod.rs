@@ -124,4 +124,9 @@ pub trait Component {
     fn on_tick(&mut self) -> (bool, Command<Self::Message>) {
         (false, Command::none())
     }
+
+    /// Upper bound on the tick and render rate (`None` = display refresh rate).
+    fn frame_rate_cap(&self) -> Option<u32> {
+        None
+    }
 }
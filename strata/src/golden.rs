@@ -0,0 +1,262 @@
+//! Golden-file snapshots of laid-out frames.
+//!
+//! [`serialize`] turns a [`LayoutSnapshot`] into a stable, line-oriented text
+//! form: every primitive the GPU would draw (in draw order per kind, with
+//! coordinates rounded to 0.1px and colors as hex) plus the registered
+//! widget hit-boxes. Layout needs no GPU or font system (text is measured
+//! with fixed monospace metrics), so frames can be laid out offscreen in
+//! tests and compared with [`assert_golden`].
+//!
+//! Goldens are committed next to the tests that use them and refreshed with
+//! `STRATA_UPDATE_GOLDENS=1 cargo test`. A missing golden fails like a
+//! mismatch, so a test can never pass by writing its own expectation.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::layout::primitives::{LineStyle, PrimitiveBatch};
+use crate::layout_snapshot::LayoutSnapshot;
+use crate::primitives::{Color, Gradient, Point, Rect};
+
+/// Env var that rewrites goldens instead of comparing against them.
+pub const UPDATE_ENV: &str = "STRATA_UPDATE_GOLDENS";
+
+/// Mismatched lines shown before the diff is cut off.
+const MAX_DIFF_LINES: usize = 40;
+
+/// Serialize everything a frame draws into a stable text form.
+pub fn serialize(snapshot: &LayoutSnapshot) -> String {
+    let mut out = String::new();
+    write_batch(&mut out, snapshot.primitives());
+
+    let overlay = snapshot.overlay_primitives();
+    if !overlay.is_empty() {
+        out.push_str("-- overlay\n");
+        write_batch(&mut out, overlay);
+    }
+
+    // Widgets live in hash maps; sort by position so output is stable.
+    let mut widgets: Vec<_> = snapshot.registered_widgets().collect();
+    if !widgets.is_empty() {
+        widgets.sort_by(|a, b| {
+            (a.0.y, a.0.x, a.0.width, a.0.height)
+                .partial_cmp(&(b.0.y, b.0.x, b.0.width, b.0.height))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        out.push_str("-- widgets\n");
+        for (rect, cursor, overlay) in widgets {
+            let _ = write!(out, "widget {} cursor={:?}", rect_str(rect), cursor);
+            if overlay {
+                out.push_str(" overlay");
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_batch(out: &mut String, batch: &PrimitiveBatch) {
+    for s in &batch.shadows {
+        let _ = write!(out, "shadow {} r={} blur={} {}", rect_str(s.rect), num(s.corner_radius), num(s.blur_radius), hex(s.color));
+        end(out, s.clip_rect);
+    }
+    for r in &batch.solid_rects {
+        let _ = write!(out, "rect {} {}", rect_str(r.rect), hex(r.color));
+        end(out, r.clip_rect);
+    }
+    for r in &batch.rounded_rects {
+        let _ = write!(out, "rounded {} r={} {}", rect_str(r.rect), num(r.corner_radius), hex(r.color));
+        end(out, r.clip_rect);
+    }
+    for g in &batch.gradient_rects {
+        let _ = write!(out, "gradient {} r={} {}", rect_str(g.rect), num(g.corner_radius), gradient_str(&g.gradient));
+        end(out, g.clip_rect);
+    }
+    for c in &batch.circles {
+        let _ = write!(out, "circle {} r={} {}", point_str(c.center), num(c.radius), hex(c.color));
+        end(out, c.clip_rect);
+    }
+    for b in &batch.borders {
+        let _ = write!(out, "border {} r={} w={} {}", rect_str(b.rect), num(b.corner_radius), num(b.border_width), hex(b.color));
+        end(out, b.clip_rect);
+    }
+    for l in &batch.lines {
+        let _ = write!(out, "line {}->{} w={} {}{}", point_str(l.p1), point_str(l.p2), num(l.thickness), hex(l.color), style_str(l.style));
+        end(out, l.clip_rect);
+    }
+    for p in &batch.polylines {
+        let points: Vec<String> = p.points.iter().map(|&pt| point_str(pt)).collect();
+        let _ = write!(out, "polyline [{}] w={} {}{}", points.join(" "), num(p.thickness), hex(p.color), style_str(p.style));
+        end(out, p.clip_rect);
+    }
+    for i in &batch.images {
+        let _ = write!(out, "image {} r={} handle={} tint={}", rect_str(i.rect), num(i.corner_radius), i.handle.0, hex(i.tint));
+        end(out, i.clip_rect);
+    }
+    for t in &batch.text_runs {
+        let _ = write!(out, "text {} {}px {} {:?}", point_str(t.position), num(t.font_size), hex(t.color), t.text);
+        if t.bold {
+            out.push_str(" bold");
+        }
+        if t.italic {
+            out.push_str(" italic");
+        }
+        end(out, t.clip_rect);
+    }
+}
+
+fn end(out: &mut String, clip: Option<Rect>) {
+    if let Some(clip) = clip {
+        let _ = write!(out, " clip={}", rect_str(clip));
+    }
+    out.push('\n');
+}
+
+/// Round to 0.1px; `-0.0` prints as `0`.
+fn num(v: f32) -> String {
+    let rounded = (v * 10.0).round() / 10.0;
+    if rounded == 0.0 { "0".to_string() } else { format!("{}", rounded) }
+}
+
+fn point_str(p: Point) -> String {
+    format!("({},{})", num(p.x), num(p.y))
+}
+
+fn rect_str(r: Rect) -> String {
+    format!("({},{} {}x{})", num(r.x), num(r.y), num(r.width), num(r.height))
+}
+
+fn hex(c: Color) -> String {
+    let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}{:02x}", byte(c.r), byte(c.g), byte(c.b), byte(c.a))
+}
+
+fn style_str(style: LineStyle) -> &'static str {
+    match style {
+        LineStyle::Solid => "",
+        LineStyle::Dashed => " dashed",
+        LineStyle::Dotted => " dotted",
+    }
+}
+
+fn gradient_str(gradient: &Gradient) -> String {
+    let (kind, stops) = match gradient {
+        Gradient::Linear { stops, .. } => ("linear", stops),
+        Gradient::Radial { stops, .. } => ("radial", stops),
+        Gradient::Conic { stops, .. } => ("conic", stops),
+    };
+    let stops: Vec<String> = stops.iter().map(|s| format!("{}@{}", hex(s.color), num(s.offset))).collect();
+    format!("{}[{}]", kind, stops.join(" "))
+}
+
+/// Compare `actual` against the golden file at `path`.
+///
+/// Panics with a line diff on mismatch, and when the golden is missing.
+/// With [`UPDATE_ENV`] set the file is (re)written instead.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &str) {
+    let path = path.as_ref();
+    let update = std::env::var_os(UPDATE_ENV).is_some();
+
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => Some(expected),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => panic!("failed to read golden {}: {}", path.display(), e),
+    };
+
+    match expected {
+        Some(expected) if expected == actual => {}
+        Some(_) | None if update => write_golden(path, actual),
+        None => panic!("missing golden {} (run with {}=1 to create it)", path.display(), UPDATE_ENV),
+        Some(expected) => panic!(
+            "golden mismatch for {} (run with {}=1 to accept):\n{}",
+            path.display(),
+            UPDATE_ENV,
+            diff(&expected, actual)
+        ),
+    }
+}
+
+fn write_golden(path: &Path, contents: &str) {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).expect("create golden directory");
+    }
+    std::fs::write(path, contents).unwrap_or_else(|e| panic!("failed to write golden {}: {}", path.display(), e));
+}
+
+/// Line-by-line diff, enough to see what moved.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut out = String::new();
+    let mut shown = 0;
+    for i in 0..expected.len().max(actual.len()) {
+        let (e, a) = (expected.get(i), actual.get(i));
+        if e == a {
+            continue;
+        }
+        if shown == MAX_DIFF_LINES {
+            out.push_str("...\n");
+            break;
+        }
+        if let Some(e) = e {
+            let _ = writeln!(out, "{:>4} - {}", i + 1, e);
+        }
+        if let Some(a) = a {
+            let _ = writeln!(out, "{:>4} + {}", i + 1, a);
+        }
+        shown += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::constraints::LayoutConstraints;
+    use crate::layout::context::LayoutContext;
+    use crate::layout::{Column, TextElement};
+
+    fn frame() -> LayoutSnapshot {
+        let col = Column::new()
+            .spacing(4.0)
+            .push(TextElement::new("Hello").color(Color::WHITE))
+            .push(TextElement::new("World").color(Color::WHITE));
+        let mut snapshot = LayoutSnapshot::new();
+        let mut ctx = LayoutContext::new(&mut snapshot);
+        col.layout_with_constraints(&mut ctx, LayoutConstraints::loose(400.0, 300.0), Point::ORIGIN);
+        snapshot
+    }
+
+    #[test]
+    fn test_serialize_is_deterministic() {
+        let a = serialize(&frame());
+        assert_eq!(a, serialize(&frame()));
+        assert!(a.contains("\"Hello\""));
+        assert!(a.contains("#ffffffff"));
+    }
+
+    #[test]
+    fn test_num_rounding() {
+        assert_eq!(num(-0.01), "0");
+        assert_eq!(num(12.04), "12");
+        assert_eq!(num(12.06), "12.1");
+    }
+
+    #[test]
+    fn test_missing_golden_fails() {
+        let path = std::env::temp_dir().join(format!("strata-golden-missing-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let result = std::panic::catch_unwind(|| assert_golden(&path, "frame\n"));
+        if std::env::var_os(UPDATE_ENV).is_none() {
+            assert!(result.is_err());
+            assert!(!path.exists());
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_diff_marks_changed_lines() {
+        let d = diff("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(d, "   2 - b\n   2 + B\n   4 + d\n");
+    }
+}
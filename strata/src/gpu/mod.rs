@@ -8,3 +8,26 @@ mod pipeline;
 
 pub use glyph_atlas::{GlyphAtlas, SizeMetrics, metrics_for_size};
 pub use pipeline::{GpuInstance, ImageHandle, ImageStore, LineStyle, PendingImage, StrataPipeline, SELECTION_COLOR, GRID_SELECTION_BG, GRID_SELECTION_FG, is_box_drawing, is_block_element, is_custom_drawn};
+
+use std::sync::Mutex;
+
+/// The GPU the renderer is drawing with.
+#[derive(Debug, Clone)]
+pub struct RendererInfo {
+    pub device: String,
+    /// An integrated GPU on a machine that also has a discrete one.
+    pub low_power: bool,
+}
+
+/// Outcome of the most recent renderer initialization.
+static RENDERER_STATUS: Mutex<Option<Result<RendererInfo, String>>> = Mutex::new(None);
+
+pub(crate) fn set_renderer_status(status: Result<RendererInfo, String>) {
+    *RENDERER_STATUS.lock().unwrap_or_else(|e| e.into_inner()) = Some(status);
+}
+
+/// The device the renderer initialized on, or why it failed. `None`
+/// until the first window has been created.
+pub fn renderer_status() -> Option<Result<RendererInfo, String>> {
+    RENDERER_STATUS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
@@ -0,0 +1,406 @@
+//! Headless Driver
+//!
+//! Runs a `StrataApp` without a window or GPU so end-to-end behavior can be
+//! scripted in tests: feed key and mouse events, let async commands and
+//! subscriptions deliver their messages, then assert on app state and on
+//! the [`LayoutSnapshot`] the view produced.
+//!
+//! The driver mirrors the platform backend's event loop: keys go through
+//! `on_key`, mouse events are hit-tested against the last frame exactly as
+//! the backend does (see [`LayoutSnapshot::pointer_hit`]), pointer capture
+//! is tracked, and every event is followed by a fresh `view()`. Positions
+//! are in layout (logical) coordinates; zoom is not applied.
+//!
+//! ```ignore
+//! let mut driver = HeadlessDriver::<ComponentApp<MyApp>>::new(800.0, 600.0);
+//! driver.type_text("echo hi");
+//! driver.press(NamedKey::Enter);
+//! assert!(driver.run_until(Duration::from_secs(5), |app| app.done()));
+//! let pos = driver.find_text("hi").unwrap();
+//! driver.click(pos);
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::app::{CaptureRequest, Command, StrataApp};
+use crate::event_context::{CaptureState, Key, KeyEvent, Modifiers, MouseButton, MouseEvent, NamedKey};
+use crate::gpu::ImageStore;
+use crate::layout_snapshot::{ItemLayout, LayoutSnapshot};
+use crate::primitives::{Point, Rect};
+
+/// How long `run_until` sleeps between polls while waiting on background work.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Drives a `StrataApp` through scripted events without a window.
+pub struct HeadlessDriver<A: StrataApp> {
+    state: A::State,
+    images: ImageStore,
+    snapshot: LayoutSnapshot,
+    size: (f32, f32),
+    capture: CaptureState,
+    runtime: tokio::runtime::Runtime,
+    command_tx: mpsc::Sender<A::Message>,
+    command_rx: mpsc::Receiver<A::Message>,
+    /// Async commands spawned but not yet resolved.
+    in_flight: Arc<AtomicUsize>,
+    exit_requested: bool,
+    new_window_requests: usize,
+}
+
+impl<A: StrataApp> HeadlessDriver<A> {
+    /// Initialize the app with default shared state and lay out the first frame.
+    pub fn new(width: f32, height: f32) -> Self {
+        Self::with_shared(&A::SharedState::default(), width, height)
+    }
+
+    /// Initialize the app with the given shared state.
+    pub fn with_shared(shared: &A::SharedState, width: f32, height: f32) -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .expect("failed to build headless runtime");
+        let mut images = ImageStore::new();
+        // Apps may create tokio resources during init.
+        let (state, cmd) = {
+            let _guard = runtime.enter();
+            A::init(shared, &mut images)
+        };
+        let (command_tx, command_rx) = mpsc::channel();
+
+        let mut driver = Self {
+            state,
+            images,
+            snapshot: LayoutSnapshot::new(),
+            size: (width, height),
+            capture: CaptureState::None,
+            runtime,
+            command_tx,
+            command_rx,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            exit_requested: false,
+            new_window_requests: 0,
+        };
+        driver.spawn(cmd);
+        driver.render();
+        driver
+    }
+
+    /// The app state.
+    pub fn state(&self) -> &A::State {
+        &self.state
+    }
+
+    /// Mutable app state, for test setup. Call [`render`](Self::render) after.
+    pub fn state_mut(&mut self) -> &mut A::State {
+        &mut self.state
+    }
+
+    /// The most recent frame.
+    pub fn snapshot(&self) -> &LayoutSnapshot {
+        &self.snapshot
+    }
+
+    /// Whether the app asked to quit.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    /// Number of new-window requests (the driver does not open windows).
+    pub fn new_window_requests(&self) -> usize {
+        self.new_window_requests
+    }
+
+    /// Current pointer capture.
+    pub fn capture(&self) -> &CaptureState {
+        &self.capture
+    }
+
+    /// Resize the viewport and re-render.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.size = (width, height);
+        self.render();
+    }
+
+    /// Lay out a fresh frame.
+    pub fn render(&mut self) {
+        let _guard = self.runtime.enter();
+        let mut snapshot = LayoutSnapshot::new();
+        snapshot.set_viewport(Rect::new(0.0, 0.0, self.size.0, self.size.1));
+        snapshot.set_zoom_level(A::zoom_level(&self.state));
+        A::view(&self.state, &mut snapshot);
+        self.snapshot = snapshot;
+    }
+
+    /// Deliver a message directly to `update()`, then re-render.
+    pub fn send(&mut self, msg: A::Message) {
+        self.process(msg);
+        self.render();
+    }
+
+    fn process(&mut self, msg: A::Message) {
+        if A::is_exit_request(&msg) {
+            self.exit_requested = true;
+            return;
+        }
+        if A::is_new_window_request(&msg) {
+            self.new_window_requests += 1;
+            return;
+        }
+        let cmd = {
+            let _guard = self.runtime.enter();
+            A::update(&mut self.state, msg, &mut self.images)
+        };
+        self.spawn(cmd);
+    }
+
+    fn spawn(&mut self, mut cmd: Command<A::Message>) {
+        for fut in cmd.take_futures() {
+            let tx = self.command_tx.clone();
+            let in_flight = self.in_flight.clone();
+            in_flight.fetch_add(1, Ordering::SeqCst);
+            self.runtime.spawn(async move {
+                let msg = fut.await;
+                let _ = tx.send(msg);
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Keyboard
+    // ---------------------------------------------------------------------
+
+    /// Dispatch a key event, as the backend does on key down/up.
+    pub fn key(&mut self, event: KeyEvent) {
+        self.drain_commands();
+        let msg = {
+            let _guard = self.runtime.enter();
+            A::on_key(&self.state, event)
+        };
+        if let Some(msg) = msg {
+            self.process(msg);
+        }
+        self.render();
+    }
+
+    /// Press and release a named key.
+    pub fn press(&mut self, key: NamedKey) {
+        self.press_with(Key::Named(key), Modifiers::NONE);
+    }
+
+    /// Press and release a key with modifiers (e.g. Cmd+C).
+    pub fn press_with(&mut self, key: Key, modifiers: Modifiers) {
+        let text = match &key {
+            Key::Character(c) if !modifiers.meta && !modifiers.ctrl => Some(c.clone()),
+            _ => None,
+        };
+        self.key(KeyEvent::Pressed { key: key.clone(), modifiers, text });
+        self.key(KeyEvent::Released { key, modifiers });
+    }
+
+    /// Type text one character at a time.
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            let modifiers = Modifiers { shift: c.is_uppercase(), ..Modifiers::NONE };
+            self.press_with(Key::Character(c.to_string()), modifiers);
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Mouse
+    // ---------------------------------------------------------------------
+
+    /// Dispatch a mouse event against the current frame.
+    pub fn mouse(&mut self, event: MouseEvent) {
+        let position = match &event {
+            MouseEvent::CursorMoved { position }
+            | MouseEvent::ButtonPressed { position, .. }
+            | MouseEvent::ButtonReleased { position, .. }
+            | MouseEvent::WheelScrolled { position, .. } => Some(*position),
+            _ => None,
+        };
+        let is_press = matches!(event, MouseEvent::ButtonPressed { .. });
+        let hit = position.and_then(|p| self.snapshot.pointer_hit(p, is_press, self.capture.is_captured()));
+
+        let response = {
+            let _guard = self.runtime.enter();
+            A::on_mouse(&self.state, event, hit, &self.capture)
+        };
+        match response.capture {
+            CaptureRequest::Capture(source) => self.capture = CaptureState::Captured(source),
+            CaptureRequest::Release => self.capture = CaptureState::None,
+            CaptureRequest::None => {}
+        }
+        if let Some(msg) = response.message {
+            self.process(msg);
+        }
+        self.render();
+    }
+
+    /// Left-click at a position.
+    pub fn click(&mut self, position: Point) {
+        self.mouse(MouseEvent::CursorMoved { position });
+        self.mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, position, modifiers: Modifiers::NONE });
+        self.mouse(MouseEvent::ButtonReleased { button: MouseButton::Left, position, modifiers: Modifiers::NONE });
+    }
+
+    /// Left-drag from one position to another in a few steps.
+    pub fn drag(&mut self, from: Point, to: Point) {
+        const STEPS: usize = 4;
+        self.mouse(MouseEvent::CursorMoved { position: from });
+        self.mouse(MouseEvent::ButtonPressed { button: MouseButton::Left, position: from, modifiers: Modifiers::NONE });
+        for i in 1..=STEPS {
+            let t = i as f32 / STEPS as f32;
+            let position = Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t);
+            self.mouse(MouseEvent::CursorMoved { position });
+        }
+        self.mouse(MouseEvent::ButtonReleased { button: MouseButton::Left, position: to, modifiers: Modifiers::NONE });
+    }
+
+    // ---------------------------------------------------------------------
+    // Background work
+    // ---------------------------------------------------------------------
+
+    fn drain_commands(&mut self) -> usize {
+        let mut count = 0;
+        while let Ok(msg) = self.command_rx.try_recv() {
+            self.process(msg);
+            count += 1;
+        }
+        count
+    }
+
+    /// One timer tick: deliver finished commands and subscription events,
+    /// run `on_tick`, and re-render. Returns the number of messages delivered.
+    pub fn tick(&mut self) -> usize {
+        let mut count = self.drain_commands();
+
+        let mut messages = Vec::new();
+        {
+            let _guard = self.runtime.enter();
+            let mut sub = A::subscription(&self.state);
+            for stream in &mut sub.streams {
+                while let Some(msg) = stream.try_recv() {
+                    messages.push(msg);
+                }
+            }
+        }
+        count += messages.len();
+        for msg in messages {
+            self.process(msg);
+        }
+
+        let (_dirty, cmd) = {
+            let _guard = self.runtime.enter();
+            A::on_tick(&mut self.state)
+        };
+        self.spawn(cmd);
+        self.render();
+        count
+    }
+
+    /// Tick until `done` holds or `timeout` passes. Returns whether it held.
+    pub fn run_until(&mut self, timeout: Duration, mut done: impl FnMut(&A::State) -> bool) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.tick();
+            if done(&self.state) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Tick until no commands are in flight and a tick delivers nothing,
+    /// or `timeout` passes. Returns whether the app went idle.
+    pub fn settle(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let delivered = self.tick();
+            if delivered == 0 && self.in_flight.load(Ordering::SeqCst) == 0 {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    // ---------------------------------------------------------------------
+    // Queries
+    // ---------------------------------------------------------------------
+
+    /// Position of the first drawn text containing `needle`, pointing at
+    /// the middle of the start of the match (monospace metrics). Searches
+    /// text runs, then the rows of terminal grids.
+    pub fn find_text(&self, needle: &str) -> Option<Point> {
+        self.text_lines().into_iter().find_map(|line| {
+            let byte = line.text.find(needle)?;
+            let chars_before = line.text[..byte].chars().count() as f32;
+            Some(Point::new(line.origin.x + chars_before * line.char_width, line.origin.y + line.height / 2.0))
+        })
+    }
+
+    /// All text drawn in the current frame: one text run per line, then
+    /// the non-blank rows of terminal grids.
+    pub fn visible_text(&self) -> String {
+        self.text_lines()
+            .into_iter()
+            .map(|line| line.text.trim_end().to_string())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn text_lines(&self) -> Vec<TextLine> {
+        use crate::layout::{BASE_FONT_SIZE, CHAR_WIDTH};
+        let runs = self.snapshot.primitives().text_runs.iter().map(|run| TextLine {
+            text: run.text.clone(),
+            origin: run.position,
+            char_width: CHAR_WIDTH * run.font_size / BASE_FONT_SIZE,
+            height: run.font_size,
+        });
+        let grids = self.snapshot.sources_in_order().flat_map(|(_, source)| &source.items).filter_map(|item| match item {
+            ItemLayout::Grid(grid) => Some(grid),
+            ItemLayout::Text(_) => None,
+        });
+        let rows = grids.flat_map(|grid| {
+            grid.rows_content.iter().enumerate().map(move |(i, row)| {
+                // Runs start at their column; fill the gaps so columns line up.
+                let mut text = String::new();
+                let mut col = 0;
+                for run in &row.runs {
+                    while col < run.col_offset as usize {
+                        text.push(' ');
+                        col += 1;
+                    }
+                    text.push_str(&run.text);
+                    col += run.cell_len as usize;
+                }
+                TextLine {
+                    text,
+                    origin: Point::new(grid.bounds.x, grid.bounds.y + i as f32 * grid.cell_height),
+                    char_width: grid.cell_width,
+                    height: grid.cell_height,
+                }
+            })
+        });
+        runs.chain(rows).collect()
+    }
+}
+
+/// A line of drawn text and where its first character sits.
+struct TextLine {
+    text: String,
+    origin: Point,
+    char_width: f32,
+    height: f32,
+}
@@ -25,6 +25,9 @@ use super::length::{Length, Padding, CHAR_WIDTH, LINE_HEIGHT, BASE_FONT_SIZE};
 // Helper Functions
 // =========================================================================
 
+/// Thickness of the lines drawn under [`TextInputElement::underline`] ranges.
+const UNDERLINE_HEIGHT: f32 = 2.0;
+
 /// Get the X offset in cell-width units for a given column index in a string.
 /// Accounts for CJK (2-wide), combining marks (0-wide), etc.
 fn unicode_col_x(text: &str, col: usize) -> f32 {
@@ -68,6 +71,12 @@ pub struct TextInputElement<'a> {
     pub height: Length,
     pub scroll_offset: f32,
     pub cursor_visible: bool,
+    /// Char ranges drawn with a colored line under them, e.g. lint findings.
+    pub underlines: Vec<(usize, usize, Color)>,
+    /// Suggested continuation drawn after the text while the cursor is at
+    /// its end (single-line only). Not part of the text until accepted.
+    pub ghost: String,
+    pub ghost_color: Color,
     pub(crate) cache_key: u64,
     /// Phantom data to hold the lifetime.
     _marker: PhantomData<&'a ()>,
@@ -97,6 +106,9 @@ impl<'a> TextInputElement<'a> {
             height: Length::Shrink,
             scroll_offset: 0.0,
             cursor_visible: true,
+            underlines: Vec::new(),
+            ghost: String::new(),
+            ghost_color: Color::rgba(0.4, 0.4, 0.45, 1.0),
             cache_key,
             _marker: PhantomData,
         }
@@ -131,6 +143,8 @@ impl<'a> TextInputElement<'a> {
     pub fn height(mut self, height: Length) -> Self { self.height = height; self }
     pub fn scroll_offset(mut self, offset: f32) -> Self { self.scroll_offset = offset; self }
     pub fn cursor_visible(mut self, visible: bool) -> Self { self.cursor_visible = visible; self }
+    pub fn underline(mut self, start: usize, end: usize, color: Color) -> Self { self.underlines.push((start, end, color)); self }
+    pub fn ghost(mut self, text: impl Into<String>, color: Color) -> Self { self.ghost = text.into(); self.ghost_color = color; self }
 
     pub(crate) fn estimate_size(&self) -> Size {
         let text_w = unicode_display_width(&self.text).max(20.0) * CHAR_WIDTH;
@@ -193,6 +207,16 @@ pub(crate) fn render_text_input(
         );
     }
 
+    // Underlines
+    for &(start, end, color) in &input.underlines {
+        let line_x = text_x + unicode_col_x(&input.text, start) * CHAR_WIDTH;
+        let line_w = (unicode_col_x(&input.text, end) - unicode_col_x(&input.text, start)) * CHAR_WIDTH;
+        snapshot.primitives_mut().add_solid_rect(
+            Rect::new(line_x, text_y + LINE_HEIGHT - UNDERLINE_HEIGHT, line_w, UNDERLINE_HEIGHT),
+            color,
+        );
+    }
+
     // Text or placeholder
     if input.text.is_empty() && !input.focused {
         snapshot.primitives_mut().add_text_cached(
@@ -203,6 +227,15 @@ pub(crate) fn render_text_input(
             hash_text(&input.placeholder),
         );
     } else {
+        if input.focused && !input.ghost.is_empty() && input.cursor == input.text.chars().count() {
+            snapshot.primitives_mut().add_text_cached(
+                input.ghost.clone(),
+                Point::new(text_x + unicode_display_width(&input.text) * CHAR_WIDTH, text_y),
+                input.ghost_color,
+                BASE_FONT_SIZE,
+                hash_text(&input.ghost),
+            );
+        }
         snapshot.primitives_mut().add_text_cached(
             input.text,
             Point::new(text_x, text_y),
@@ -306,6 +339,23 @@ pub(crate) fn render_text_input_multiline(
         }
     }
 
+    // Underlines (per visual line, like the selection)
+    for &(start, end, color) in &input.underlines {
+        let (s_vis_line, s_vis_col) = offset_to_visual(&visual_lines, start);
+        let (e_vis_line, e_vis_col) = offset_to_visual(&visual_lines, end);
+        for vis_idx in s_vis_line.max(first_visible)..=e_vis_line.min(last_visible.saturating_sub(1)) {
+            let vl = &visual_lines[vis_idx];
+            let ll = logical_lines.get(vl.logical_line).copied().unwrap_or("");
+            let vis_text = &ll[vl.start_byte..vl.end_byte];
+            let col_start = if vis_idx == s_vis_line { s_vis_col } else { 0 };
+            let col_end = if vis_idx == e_vis_line { e_vis_col } else { vl.char_count };
+            let line_x = text_x + unicode_col_x(vis_text, col_start) * CHAR_WIDTH;
+            let line_w = (unicode_col_x(vis_text, col_end) - unicode_col_x(vis_text, col_start)) * CHAR_WIDTH;
+            let line_y = text_y + (vis_idx + 1) as f32 * LINE_HEIGHT - UNDERLINE_HEIGHT - input.scroll_offset;
+            snapshot.primitives_mut().add_solid_rect(Rect::new(line_x, line_y, line_w, UNDERLINE_HEIGHT), color);
+        }
+    }
+
     // Render visible visual lines
     if input.text.is_empty() && !input.focused {
         snapshot.primitives_mut().add_text_cached(
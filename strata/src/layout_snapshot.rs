@@ -982,6 +982,16 @@ impl LayoutSnapshot {
         self.overlay_widget_bounds.get(id).or_else(|| self.widget_bounds.get(id)).copied()
     }
 
+    /// All registered widgets with their bounds and cursor hint, overlay
+    /// widgets flagged. Iteration order is unspecified.
+    pub(crate) fn registered_widgets(&self) -> impl Iterator<Item = (Rect, CursorIcon, bool)> + '_ {
+        let hint = |id: &SourceId| self.cursor_hints.get(id).copied().unwrap_or_default();
+        self.widget_bounds
+            .iter()
+            .map(move |(id, rect)| (*rect, hint(id), false))
+            .chain(self.overlay_widget_bounds.iter().map(move |(id, rect)| (*rect, hint(id), true)))
+    }
+
     /// Set a cursor hint for a widget. Called during layout by framework containers.
     pub fn set_cursor_hint(&mut self, id: SourceId, cursor: CursorIcon) {
         self.cursor_hints.insert(id, cursor);
@@ -1194,6 +1204,48 @@ impl LayoutSnapshot {
         self.hit_test_xy(point.x, point.y)
     }
 
+    /// Resolve what a pointer event at `point` targets, the way the platform
+    /// backend dispatches it.
+    ///
+    /// HACK: Area-based heuristic to decide whether a widget "claims" a click
+    /// or lets it pass through to nearest_content for browser-style gap selection.
+    ///
+    /// The right solution is a proper event bubbling/capturing system (like the
+    /// DOM): events dispatch to the most specific target, which can handle them
+    /// or let them propagate up to parent containers. Each widget would declare
+    /// whether it's interactive (claims clicks) or a passive layout container
+    /// (lets clicks pass through to content beneath it).
+    ///
+    /// What we do instead: use the widget's screen area as a proxy. Small widgets
+    /// (< 40k sq px — buttons, inputs, pills) are assumed interactive. Large
+    /// widgets (scroll areas, panels) are assumed to be passive containers. This
+    /// breaks if:
+    ///   - A large widget IS interactive (e.g., a big custom canvas)
+    ///   - A small widget is NOT interactive (unlikely but possible)
+    ///   - Widget sizes change dynamically across the threshold
+    ///
+    /// The 40k threshold mirrors INTERACTIVE_MAX_AREA in hit_test_xy(). If that
+    /// changes, this must change too.
+    pub fn pointer_hit(&self, point: Point, button_pressed: bool, captured: bool) -> Option<HitResult> {
+        let raw_hit = self.hit_test(point);
+        let claims_click = match &raw_hit {
+            Some(HitResult::Content(_)) => true,
+            Some(HitResult::Widget(id)) => {
+                const INTERACTIVE_MAX_AREA: f32 = 40_000.0; // keep in sync with hit_test_xy
+                self.widget_bounds(id)
+                    .map(|r| r.width * r.height <= INTERACTIVE_MAX_AREA)
+                    .unwrap_or(false)
+            }
+            None => false,
+        };
+        let needs_fallback = captured || (button_pressed && !claims_click);
+        if needs_fallback {
+            self.nearest_content(point.x, point.y).or(raw_hit)
+        } else {
+            raw_hit
+        }
+    }
+
     /// Hit test with separate x, y coordinates.
     pub fn hit_test_xy(&self, x: f32, y: f32) -> Option<HitResult> {
         // 0. Highest priority: Overlay widgets (context menus, popovers).
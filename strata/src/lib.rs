@@ -36,6 +36,12 @@ pub mod content_address;
 pub mod layout_snapshot;
 pub mod event_context;
 
+// Golden-file snapshots of laid-out frames (offscreen tests)
+pub mod golden;
+
+// Headless driver for scripted end-to-end tests
+pub mod headless;
+
 // Layout system (flexbox-inspired containers)
 pub mod layout;
 
@@ -1000,6 +1000,50 @@ pub fn set_cursor(icon: crate::layout_snapshot::CursorIcon) {
     }
 }
 
+// =============================================================================
+// Power State
+// =============================================================================
+
+/// Read battery and Low Power Mode state.
+///
+/// Cheap enough to poll every few seconds; callers should not call it per frame.
+pub fn power_status() -> super::PowerStatus {
+    #[link(name = "IOKit", kind = "framework")]
+    unsafe extern "C" {
+        fn IOPSCopyPowerSourcesInfo() -> *const std::ffi::c_void;
+        fn IOPSGetProvidingPowerSourceType(snapshot: *const std::ffi::c_void) -> *const std::ffi::c_void;
+    }
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        fn CFRelease(cf: *const std::ffi::c_void);
+    }
+
+    let on_battery = unsafe {
+        let info = IOPSCopyPowerSourcesInfo();
+        if info.is_null() {
+            false
+        } else {
+            // Borrowed CFStringRef (toll-free bridged to NSString), valid while `info` lives.
+            let kind = IOPSGetProvidingPowerSourceType(info);
+            let battery = !kind.is_null() && (*(kind as *const NSString)).to_string() == "Battery Power";
+            CFRelease(info);
+            battery
+        }
+    };
+
+    // `isLowPowerModeEnabled` exists on macOS 12+.
+    let low_power_mode = unsafe {
+        let info: *mut AnyObject = msg_send![AnyClass::get("NSProcessInfo").unwrap(), processInfo];
+        let supported: Bool = msg_send![info, respondsToSelector: sel!(isLowPowerModeEnabled)];
+        supported.as_bool() && {
+            let enabled: Bool = msg_send![info, isLowPowerModeEnabled];
+            enabled.as_bool()
+        }
+    };
+
+    super::PowerStatus { on_battery, low_power_mode }
+}
+
 // =============================================================================
 // Native Context Menu
 // =============================================================================
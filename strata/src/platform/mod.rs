@@ -1,13 +1,13 @@
 //! Platform-specific functionality.
 //!
 //! Provides native OS integration such as initiating outbound drag
-//! operations, Quick Look previews, and cursor management.
+//! operations, Quick Look previews, cursor management, and power state.
 
 #[cfg(target_os = "macos")]
 pub mod macos;
 
 #[cfg(target_os = "macos")]
-pub use macos::{start_drag, preview_file, preview_file_with_rect, close_quicklook, preview_file_with_local_rect, install_reopen_handler, take_reopen_receiver, setup_menu_bar, show_definition, install_force_click_handler, take_force_click_receiver, setup_force_click_monitor, set_cursor, clipboard_image_file_path, show_context_menu, NativeMenuItem};
+pub use macos::{start_drag, preview_file, preview_file_with_rect, close_quicklook, preview_file_with_local_rect, install_reopen_handler, take_reopen_receiver, setup_menu_bar, show_definition, install_force_click_handler, take_force_click_receiver, setup_force_click_monitor, set_cursor, clipboard_image_file_path, show_context_menu, NativeMenuItem, power_status};
 
 #[cfg(not(target_os = "macos"))]
 pub fn start_drag(_source: &crate::app::DragSource) -> Result<(), String> {
@@ -51,6 +51,20 @@ pub fn setup_force_click_monitor() {}
 #[cfg(not(target_os = "macos"))]
 pub fn set_cursor(_icon: crate::layout_snapshot::CursorIcon) {}
 
+/// Battery and OS power-saving state, from `power_status()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowerStatus {
+    /// Running on battery rather than AC power.
+    pub on_battery: bool,
+    /// The OS-level Low Power Mode is enabled.
+    pub low_power_mode: bool,
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn power_status() -> PowerStatus {
+    PowerStatus::default()
+}
+
 #[cfg(not(target_os = "macos"))]
 pub struct NativeMenuItem {
     pub label: String,
@@ -394,6 +394,16 @@ fn open_new_window_with_state<A: StrataApp>(
         let _: () = msg_send![&*window, setDelegate: &*view_state.view];
     }
 
+    // Observe system and display wake so the surface is rebuilt afterwards.
+    // Removed in handle_window_close.
+    unsafe {
+        let center = workspace_notification_center();
+        for name in [ns_string!("NSWorkspaceDidWakeNotification"), ns_string!("NSWorkspaceScreensDidWakeNotification")] {
+            let _: () = msg_send![center, addObserver: &*view_state.view,
+                selector: sel!(workspaceDidWake:), name: name, object: std::ptr::null::<AnyObject>()];
+        }
+    }
+
     // Initialize Metal.
     let gpu = init_metal(view_state.metal_layer_ptr, win_w, win_h, dpi_scale)?;
 
@@ -773,6 +783,13 @@ fn register_strata_view_class() -> &'static AnyClass {
             // NSWindowDelegate method — view acts as its window's delegate.
             add_method_raw(cls_ptr, sel!(windowWillClose:),
                 Some(std::mem::transmute::<extern "C" fn(&AnyObject, Sel, *mut AnyObject), unsafe extern "C" fn()>(window_will_close)), c"v@:@");
+            add_method_raw(cls_ptr, sel!(windowDidChangeBackingProperties:),
+                Some(std::mem::transmute::<extern "C" fn(&AnyObject, Sel, *mut AnyObject), unsafe extern "C" fn()>(surface_invalidated)), c"v@:@");
+            add_method_raw(cls_ptr, sel!(windowDidChangeScreen:),
+                Some(std::mem::transmute::<extern "C" fn(&AnyObject, Sel, *mut AnyObject), unsafe extern "C" fn()>(surface_invalidated)), c"v@:@");
+            // NSWorkspace wake notifications (observer registered per window).
+            add_method_raw(cls_ptr, sel!(workspaceDidWake:),
+                Some(std::mem::transmute::<extern "C" fn(&AnyObject, Sel, *mut AnyObject), unsafe extern "C" fn()>(surface_invalidated)), c"v@:@");
 
             // NSDraggingDestination protocol — file drop support.
             // Return value is NSDragOperation (unsigned long = Q on 64-bit).
@@ -808,6 +825,7 @@ static mut RESIZE_HANDLER: Option<fn(&AnyObject, f32, f32)> = None;
 static mut RESIZE_START_HANDLER: Option<fn(&AnyObject)> = None;
 static mut RESIZE_END_HANDLER: Option<fn(&AnyObject)> = None;
 static mut WINDOW_CLOSE_HANDLER: Option<fn(&AnyObject)> = None;
+static mut SURFACE_RESET_HANDLER: Option<fn(&AnyObject)> = None;
 static mut FILE_DROP_HANDLER: Option<fn(&AnyObject, FileDropEvent, Point)> = None;
 
 fn install_event_handlers<A: StrataApp>() {
@@ -819,6 +837,7 @@ fn install_event_handlers<A: StrataApp>() {
         RESIZE_END_HANDLER = Some(handle_resize_end::<A>);
         RESIZE_IDLE_HANDLER = Some(handle_resize_idle::<A>);
         WINDOW_CLOSE_HANDLER = Some(handle_window_close::<A>);
+        SURFACE_RESET_HANDLER = Some(handle_surface_reset::<A>);
         FILE_DROP_HANDLER = Some(handle_file_drop_event::<A>);
     }
 }
@@ -1098,6 +1117,15 @@ extern "C" fn window_will_close(this: &AnyObject, _sel: Sel, _notification: *mut
     dispatch_window_close(this);
 }
 
+/// Display change (scale factor, screen) or system wake.
+extern "C" fn surface_invalidated(this: &AnyObject, _sel: Sel, _notification: *mut AnyObject) {
+    unsafe {
+        if let Some(handler) = SURFACE_RESET_HANDLER {
+            handler(this);
+        }
+    }
+}
+
 // ============================================================================
 // NSDraggingDestination — File Drop
 // ============================================================================
@@ -1187,6 +1215,13 @@ extern "C" fn perform_drag_operation(this: &AnyObject, _sel: Sel, sender: *mut A
     }
 }
 
+/// `[[NSWorkspace sharedWorkspace] notificationCenter]` — wake notifications
+/// are only posted there, not on the default center.
+unsafe fn workspace_notification_center() -> *mut AnyObject {
+    let workspace: *mut AnyObject = msg_send![AnyClass::get("NSWorkspace").unwrap(), sharedWorkspace];
+    msg_send![workspace, notificationCenter]
+}
+
 /// Get DPI scale from the view's backing scale factor.
 unsafe fn get_dpi_scale(view: &AnyObject) -> f32 {
     let window: *mut AnyObject = msg_send![view, window];
@@ -1245,7 +1280,17 @@ fn flush_pending_resize<A: StrataApp>(state_cell: &RefCell<WindowState<A>>) {
             }
         }
 
-        state_cell.borrow_mut().transactional_present = false;
+        let mut state = state_cell.borrow_mut();
+        state.transactional_present = false;
+        // A window that can't take the zoomed size (full screen, screen
+        // edge) keeps its size, so the logical content area changes instead
+        // and the app lays out — and sizes its terminals — for what fits.
+        let (win_w, win_h) = state.window_size;
+        if (win_w - w).abs() > 2.0 || (win_h - h).abs() > 2.0 {
+            let zoom = state.current_zoom;
+            state.base_size = (win_w / zoom, win_h / zoom);
+            state.needs_render = true;
+        }
     }
 }
 
@@ -1271,47 +1316,12 @@ fn handle_mouse_event<A: StrataApp>(view: &AnyObject, strata_event: MouseEvent)
         let zoom = state.current_zoom;
         let adjusted_cursor = state.cursor_position.map(|p| Point::new(p.x / zoom, p.y / zoom));
 
-        // HACK: Area-based heuristic to decide whether a widget "claims" a click
-        // or lets it pass through to nearest_content for browser-style gap selection.
-        //
-        // The right solution is a proper event bubbling/capturing system (like the
-        // DOM): events dispatch to the most specific target, which can handle them
-        // or let them propagate up to parent containers. Each widget would declare
-        // whether it's interactive (claims clicks) or a passive layout container
-        // (lets clicks pass through to content beneath it).
-        //
-        // What we do instead: use the widget's screen area as a proxy. Small widgets
-        // (< 40k sq px — buttons, inputs, pills) are assumed interactive. Large
-        // widgets (scroll areas, panels) are assumed to be passive containers. This
-        // breaks if:
-        //   - A large widget IS interactive (e.g., a big custom canvas)
-        //   - A small widget is NOT interactive (unlikely but possible)
-        //   - Widget sizes change dynamically across the threshold
-        //
-        // The 40k threshold mirrors INTERACTIVE_MAX_AREA in hit_test_xy(). If that
-        // changes, this must change too.
+        let is_button_pressed = matches!(strata_event, MouseEvent::ButtonPressed { .. });
+        let captured = state.capture.is_captured();
         let hit = state.cached_snapshot.as_ref().and_then(|snapshot| {
-            let raw_hit = adjusted_cursor.and_then(|pos| snapshot.hit_test(pos));
-            let claims_click = match &raw_hit {
-                Some(HitResult::Content(_)) => true,
-                Some(HitResult::Widget(id)) => {
-                    const INTERACTIVE_MAX_AREA: f32 = 40_000.0; // keep in sync with hit_test_xy
-                    snapshot.widget_bounds(id)
-                        .map(|r| r.width * r.height <= INTERACTIVE_MAX_AREA)
-                        .unwrap_or(false)
-                }
-                None => false,
-            };
-            let needs_fallback = state.capture.is_captured()
-                || (matches!(&strata_event, MouseEvent::ButtonPressed { .. }) && !claims_click);
-            if needs_fallback {
-                adjusted_cursor.and_then(|pos| snapshot.nearest_content(pos.x, pos.y)).or(raw_hit)
-            } else {
-                raw_hit
-            }
+            adjusted_cursor.and_then(|pos| snapshot.pointer_hit(pos, is_button_pressed, captured))
         });
 
-        let is_button_pressed = matches!(strata_event, MouseEvent::ButtonPressed { .. });
         let is_cursor_moved = matches!(strata_event, MouseEvent::CursorMoved { .. });
         let is_scroll = matches!(strata_event, MouseEvent::WheelScrolled { .. });
         if !hit.is_some() && !state.capture.is_captured() && !is_cursor_moved && !is_button_pressed && !is_scroll {
@@ -1461,6 +1471,48 @@ fn handle_resize<A: StrataApp>(view: &AnyObject, new_w: f32, new_h: f32) {
     }
 }
 
+/// Rebuild the drawable surface after system wake or a display change.
+///
+/// After sleep the CAMetalLayer can hand back stale or nil drawables, and a
+/// move to another display may change the backing scale and refresh rate.
+/// Re-read both, resize the drawable pool, and render a fresh frame (glyph
+/// atlases are re-rasterized by `render_frame` when the scale differs).
+/// PTYs and app state are untouched.
+fn handle_surface_reset<A: StrataApp>(view: &AnyObject) {
+    let Some(state_cell) = (unsafe { get_state::<A>(view) }) else { return };
+    // Notifications can arrive while a modal loop holds the borrow; the
+    // next display change or wake will retry.
+    let Ok(mut state) = state_cell.try_borrow_mut() else { return };
+
+    let dpi_scale = unsafe { get_dpi_scale(view) };
+    unsafe {
+        for name in ["_metal_layer", "_overlay_layer"] {
+            let ivar = view.class().instance_variable(name).unwrap();
+            let layer = *ivar.load::<*mut c_void>(view) as *mut AnyObject;
+            if !layer.is_null() {
+                let _: () = msg_send![layer, setContentsScale: dpi_scale as CGFloat];
+            }
+        }
+    }
+
+    state.dpi_scale = dpi_scale;
+    state.render.gpu.surface_width = (state.window_size.0 * dpi_scale) as u32;
+    state.render.gpu.surface_height = (state.window_size.1 * dpi_scale) as u32;
+    if !state.window.is_null() {
+        let screen: *mut AnyObject = unsafe { msg_send![state.window, screen] };
+        if !screen.is_null() {
+            let max_fps: isize = unsafe { msg_send![screen, maximumFramesPerSecond] };
+            if max_fps > 0 {
+                state.tick_interval_ms = (1000 / max_fps) as u64;
+            }
+        }
+    }
+
+    state.surface_dirty = true;
+    state.needs_render = true;
+    render_if_needed::<A>(&mut state);
+}
+
 fn handle_resize_start<A: StrataApp>(view: &AnyObject) {
     let Some(_state_cell) = (unsafe { get_state::<A>(view) }) else { return };
 
@@ -1542,6 +1594,7 @@ fn process_message<A: StrataApp>(state: &mut WindowState<A>, msg: A::Message) {
 }
 
 fn build_scene<A: StrataApp>(state: &WindowState<A>) -> Scene {
+    let _span = tracing::debug_span!("frame.view").entered();
     let zoom = A::zoom_level(&state.app);
     let mut snapshot = LayoutSnapshot::new();
     let tb = state.titlebar_height / zoom;
@@ -1569,6 +1622,7 @@ fn build_scene<A: StrataApp>(state: &WindowState<A>) -> Scene {
 fn render_if_needed<A: StrataApp>(state: &mut WindowState<A>) {
     if !state.needs_render { return; }
     state.needs_render = false;
+    let _span = tracing::debug_span!("frame.render").entered();
 
     if state.surface_dirty {
         state.surface_dirty = false;
@@ -1698,8 +1752,14 @@ fn init_metal(
     win_h: f32,
     dpi_scale: f32,
 ) -> Result<GpuState, Error> {
-    let device = metal::Device::system_default()
-        .ok_or_else(|| Error::Gpu("No Metal device found".into()))?;
+    let Some(device) = metal::Device::system_default() else {
+        crate::gpu::set_renderer_status(Err("No Metal device found".into()));
+        return Err(Error::Gpu("No Metal device found".into()));
+    };
+    crate::gpu::set_renderer_status(Ok(crate::gpu::RendererInfo {
+        device: device.name().to_string(),
+        low_power: device.is_low_power(),
+    }));
     let queue = device.new_command_queue();
 
     let pixel_format = metal::MTLPixelFormat::BGRA8Unorm_sRGB;
@@ -2565,6 +2625,9 @@ fn handle_window_close<A: StrataApp>(view: &AnyObject) {
         let ivar_ptr = view_ptr.offset(ivar.offset()) as *mut *mut c_void;
         *ivar_ptr = std::ptr::null_mut();
 
+        // Stop wake notifications (the center doesn't retain the observer).
+        let _: () = msg_send![workspace_notification_center(), removeObserver: view];
+
         // Extract window pointer and invalidate timers before dropping state.
         let state_cell = &*(state_ptr as *const RefCell<WindowState<A>>);
         let window_ptr = {
@@ -2680,8 +2743,14 @@ fn install_main_thread_timer<A: StrataApp>(state_ptr: *mut RefCell<WindowState<A
 
             // Call on_tick at the display's refresh rate for periodic effects
             // (spring animation, auto-scroll, output polling). Only flag a
-            // render when on_tick reports state actually changed.
-            let at_tick = state.last_tick_time.elapsed().as_millis() >= state.tick_interval_ms as u128;
+            // render when on_tick reports state actually changed. A frame
+            // rate cap (low-power mode) stretches the interval, which also
+            // coalesces output into fewer renders.
+            let tick_interval_ms = match A::frame_rate_cap(&state.app) {
+                Some(fps) if fps > 0 => state.tick_interval_ms.max(1000 / fps as u64),
+                _ => state.tick_interval_ms,
+            };
+            let at_tick = state.last_tick_time.elapsed().as_millis() >= tick_interval_ms as u128;
             if at_tick {
                 state.last_tick_time = Instant::now();
                 let (dirty, cmd) = A::on_tick(&mut state.app);
@@ -128,3 +128,51 @@ pub fn from_broadcast<T: Clone + Send + 'static>(
         streams: vec![Box::new(BroadcastStream { rx })],
     }
 }
+
+/// Item of a [`from_broadcast_reporting_lag`] subscription.
+#[derive(Debug, Clone)]
+pub enum BroadcastItem<T> {
+    Message(T),
+    /// The receiver fell behind and this many messages were lost.
+    Lagged(u64),
+}
+
+/// Broadcast stream that reports lag instead of skipping over it.
+struct LagReportingStream<T> {
+    rx: Arc<Mutex<broadcast::Receiver<T>>>,
+    /// Set after yielding `Lagged`: the rest of this poll yields nothing.
+    paused: bool,
+}
+
+impl<T: Clone + Send + 'static> SubscriptionStream for LagReportingStream<T> {
+    type Item = BroadcastItem<T>;
+
+    fn try_recv(&mut self) -> Option<BroadcastItem<T>> {
+        if self.paused {
+            return None;
+        }
+        let mut guard = self.rx.try_lock().ok()?;
+        match guard.try_recv() {
+            Ok(event) => Some(BroadcastItem::Message(event)),
+            Err(broadcast::error::TryRecvError::Lagged(n)) => {
+                self.paused = true;
+                Some(BroadcastItem::Lagged(n))
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Create a subscription from a broadcast receiver that surfaces lag.
+///
+/// When messages are lost, yields [`BroadcastItem::Lagged`] and nothing
+/// more until the next poll (subscriptions are rebuilt every tick). The
+/// handler can therefore resynchronize from the receiver — e.g. drain it
+/// against a replay log — before any newer message is delivered.
+pub fn from_broadcast_reporting_lag<T: Clone + Send + 'static>(
+    rx: Arc<Mutex<broadcast::Receiver<T>>>,
+) -> crate::Subscription<BroadcastItem<T>> {
+    crate::Subscription {
+        streams: vec![Box::new(LagReportingStream { rx, paused: false })],
+    }
+}